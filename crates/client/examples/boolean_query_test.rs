@@ -1,3 +1,4 @@
+use common::net::connect;
 use common::rpc::{
     manager_service_client::ManagerServiceClient, AddRequest, DeleteRequest, QueryRequest,
 };
@@ -18,9 +19,13 @@ impl Client {
         fid: String,
         keywords: Vec<String>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut client = ManagerServiceClient::connect(self.manager_addr.clone()).await?;
+        let mut client = ManagerServiceClient::new(connect(&self.manager_addr).await?);
 
-        let request = AddRequest { fid, keywords };
+        let request = AddRequest {
+            fid,
+            keywords,
+            ..Default::default()
+        };
 
         let response = client.add(request).await?;
         let resp = response.into_inner();
@@ -39,12 +44,13 @@ impl Client {
         &self,
         keyword: String,
     ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let mut client = ManagerServiceClient::connect(self.manager_addr.clone()).await?;
+        let mut client = ManagerServiceClient::new(connect(&self.manager_addr).await?);
 
         let request = QueryRequest {
             query_type: Some(common::rpc::query_request::QueryType::Keyword(
                 keyword.clone(),
             )),
+            ..Default::default()
         };
 
         let response = client.query(request).await?;
@@ -71,12 +77,13 @@ impl Client {
         &self,
         boolean_func: String,
     ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let mut client = ManagerServiceClient::connect(self.manager_addr.clone()).await?;
+        let mut client = ManagerServiceClient::new(connect(&self.manager_addr).await?);
 
         let request = QueryRequest {
             query_type: Some(common::rpc::query_request::QueryType::BooleanFunction(
                 boolean_func.clone(),
             )),
+            ..Default::default()
         };
 
         let response = client.query(request).await?;
@@ -104,9 +111,13 @@ impl Client {
         fid: String,
         keywords: Vec<String>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut client = ManagerServiceClient::connect(self.manager_addr.clone()).await?;
+        let mut client = ManagerServiceClient::new(connect(&self.manager_addr).await?);
 
-        let request = DeleteRequest { fid, keywords };
+        let request = DeleteRequest {
+            fid,
+            keywords,
+            ..Default::default()
+        };
 
         let response = client.delete(request).await?;
         let resp = response.into_inner();
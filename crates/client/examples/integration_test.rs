@@ -1,3 +1,4 @@
+use common::net::connect;
 use common::rpc::{
     manager_service_client::ManagerServiceClient, AddRequest, DeleteRequest, QueryRequest,
     UpdateRequest,
@@ -14,7 +15,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("\n📝 测试 1: 添加文件到系统");
     println!("{}", "-".repeat(60));
 
-    let mut client = ManagerServiceClient::connect(manager_addr.clone()).await?;
+    let mut client = ManagerServiceClient::new(connect(&manager_addr).await?);
 
     // 添加文件1: Rust 项目
     println!("添加 file1: Rust 分布式存储项目");
@@ -25,6 +26,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "distributed".to_string(),
             "storage".to_string(),
         ],
+        ..Default::default()
     };
     let response = client.add(request).await?;
     println!("  结果: {}", response.into_inner().message);
@@ -38,6 +40,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "ai".to_string(),
             "machine-learning".to_string(),
         ],
+        ..Default::default()
     };
     let response = client.add(request).await?;
     println!("  结果: {}", response.into_inner().message);
@@ -51,6 +54,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "blockchain".to_string(),
             "crypto".to_string(),
         ],
+        ..Default::default()
     };
     let response = client.add(request).await?;
     println!("  结果: {}", response.into_inner().message);
@@ -64,6 +68,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "microservice".to_string(),
             "distributed".to_string(),
         ],
+        ..Default::default()
     };
     let response = client.add(request).await?;
     println!("  结果: {}", response.into_inner().message);
@@ -77,6 +82,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         query_type: Some(common::rpc::query_request::QueryType::Keyword(
             "rust".to_string(),
         )),
+        ..Default::default()
     };
     let response = client.query(request).await?;
     let resp = response.into_inner();
@@ -98,6 +104,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         query_type: Some(common::rpc::query_request::QueryType::Keyword(
             "distributed".to_string(),
         )),
+        ..Default::default()
     };
     let response = client.query(request).await?;
     let resp = response.into_inner();
@@ -123,6 +130,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         query_type: Some(common::rpc::query_request::QueryType::BooleanFunction(
             "rust AND distributed".to_string(),
         )),
+        ..Default::default()
     };
     let response = client.query(request).await?;
     let resp = response.into_inner();
@@ -144,6 +152,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         query_type: Some(common::rpc::query_request::QueryType::BooleanFunction(
             "rust OR python".to_string(),
         )),
+        ..Default::default()
     };
     let response = client.query(request).await?;
     let resp = response.into_inner();
@@ -169,6 +178,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         fid: "file1".to_string(),
         old_keywords: vec!["storage".to_string()],
         new_keywords: vec!["database".to_string()],
+        ..Default::default()
     };
     let response = client.update(request).await?;
     println!("  结果: {}", response.into_inner().message);
@@ -179,6 +189,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         query_type: Some(common::rpc::query_request::QueryType::Keyword(
             "database".to_string(),
         )),
+        ..Default::default()
     };
     let response = client.query(request).await?;
     let resp = response.into_inner();
@@ -196,6 +207,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "microservice".to_string(),
             "distributed".to_string(),
         ],
+        ..Default::default()
     };
     let response = client.delete(request).await?;
     println!("  结果: {}", response.into_inner().message);
@@ -206,6 +218,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         query_type: Some(common::rpc::query_request::QueryType::Keyword(
             "go".to_string(),
         )),
+        ..Default::default()
     };
     let response = client.query(request).await?;
     let resp = response.into_inner();
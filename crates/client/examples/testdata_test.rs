@@ -1,9 +1,10 @@
+use client::dataset::parse_records;
+use client::DatasetFormat;
+use common::net::connect;
 use common::rpc::{
-    manager_service_client::ManagerServiceClient, AddRequest, DeleteRequest, QueryRequest,
-    UpdateRequest,
+    manager_service_client::ManagerServiceClient, BulkAddRecord, BulkAddResponse, DeleteRequest,
+    QueryRequest, UpdateRequest,
 };
-use std::fs::File;
-use std::io::{BufRead, BufReader};
 use std::time::Instant;
 
 // Client structure
@@ -16,21 +17,27 @@ impl Client {
         Client { manager_addr }
     }
 
-    // Add file with keywords
-    pub async fn add_file(
+    // Import all records through one BulkAdd stream
+    pub async fn bulk_add(
         &self,
-        fid: String,
-        keywords: Vec<String>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut client = ManagerServiceClient::connect(self.manager_addr.clone()).await?;
-        let request = AddRequest { fid, keywords };
-        let response = client.add(request).await?;
+        records: &[(String, Vec<String>)],
+    ) -> Result<BulkAddResponse, Box<dyn std::error::Error>> {
+        let mut client = ManagerServiceClient::new(connect(&self.manager_addr).await?);
+        let records: Vec<BulkAddRecord> = records
+            .iter()
+            .map(|(fid, keywords)| BulkAddRecord {
+                fid: fid.clone(),
+                keywords: keywords.clone(),
+                ..Default::default()
+            })
+            .collect();
+        let response = client.bulk_add(tokio_stream::iter(records)).await?;
         let resp = response.into_inner();
 
         if !resp.success {
-            return Err(format!("Add failed: {}", resp.message).into());
+            return Err(format!("Bulk add failed: {}", resp.message).into());
         }
-        Ok(())
+        Ok(resp)
     }
 
     // Query by keyword
@@ -38,9 +45,10 @@ impl Client {
         &self,
         keyword: String,
     ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let mut client = ManagerServiceClient::connect(self.manager_addr.clone()).await?;
+        let mut client = ManagerServiceClient::new(connect(&self.manager_addr).await?);
         let request = QueryRequest {
             query_type: Some(common::rpc::query_request::QueryType::Keyword(keyword)),
+            ..Default::default()
         };
         let response = client.query(request).await?;
         let resp = response.into_inner();
@@ -56,11 +64,12 @@ impl Client {
         &self,
         boolean_func: String,
     ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let mut client = ManagerServiceClient::connect(self.manager_addr.clone()).await?;
+        let mut client = ManagerServiceClient::new(connect(&self.manager_addr).await?);
         let request = QueryRequest {
             query_type: Some(common::rpc::query_request::QueryType::BooleanFunction(
                 boolean_func,
             )),
+            ..Default::default()
         };
         let response = client.query(request).await?;
         let resp = response.into_inner();
@@ -77,8 +86,12 @@ impl Client {
         fid: String,
         keywords: Vec<String>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut client = ManagerServiceClient::connect(self.manager_addr.clone()).await?;
-        let request = DeleteRequest { fid, keywords };
+        let mut client = ManagerServiceClient::new(connect(&self.manager_addr).await?);
+        let request = DeleteRequest {
+            fid,
+            keywords,
+            ..Default::default()
+        };
         let response = client.delete(request).await?;
         let resp = response.into_inner();
 
@@ -95,11 +108,12 @@ impl Client {
         old_keywords: Vec<String>,
         new_keywords: Vec<String>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut client = ManagerServiceClient::connect(self.manager_addr.clone()).await?;
+        let mut client = ManagerServiceClient::new(connect(&self.manager_addr).await?);
         let request = UpdateRequest {
             fid,
             old_keywords,
             new_keywords,
+            ..Default::default()
         };
         let response = client.update(request).await?;
         let resp = response.into_inner();
@@ -111,6 +125,11 @@ impl Client {
     }
 }
 
+// First bytes of a root hash, hex-encoded
+fn short_hex(hash: &[u8]) -> String {
+    hash.iter().take(6).map(|b| format!("{:02x}", b)).collect()
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let manager_addr = "http://[::1]:50051".to_string();
@@ -120,19 +139,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("===========================================================\n");
 
     // Read testdata file
-    let file = File::open("data/testdata")?;
-    let reader = BufReader::new(file);
-    let mut data_entries = Vec::new();
-
-    for line in reader.lines() {
-        let line = line?;
-        let parts: Vec<String> = line.split(',').map(|s| s.trim().to_string()).collect();
-        if parts.len() >= 2 {
-            let fid = parts[0].clone();
-            let keywords = parts[1..].to_vec();
-            data_entries.push((fid, keywords));
-        }
-    }
+    let input = std::fs::read_to_string("data/testdata")?;
+    let data_entries = parse_records(&input, DatasetFormat::Csv)?;
 
     println!("加载了 {} 条数据记录\n", data_entries.len());
 
@@ -140,23 +148,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("测试 1: 批量添加数据");
     println!("-----------------------------------------------------------");
     let start = Instant::now();
-    let mut success_count = 0;
-    let mut fail_count = 0;
-
-    for (i, (fid, keywords)) in data_entries.iter().enumerate() {
-        match client.add_file(fid.clone(), keywords.clone()).await {
-            Ok(_) => {
-                success_count += 1;
-                if (i + 1) % 20 == 0 {
-                    println!("  进度: {}/{} 条记录已添加", i + 1, data_entries.len());
-                }
-            }
-            Err(e) => {
-                fail_count += 1;
-                println!("  添加 {} 失败: {}", fid, e);
+    let (success_count, fail_count) = match client.bulk_add(&data_entries).await {
+        Ok(resp) => {
+            for transition in &resp.transitions {
+                println!(
+                    "  {}: 根哈希 {} → {} (epoch {})",
+                    transition.storager,
+                    short_hex(&transition.old_root_hash),
+                    short_hex(&transition.new_root_hash),
+                    transition.epoch
+                );
             }
+            (resp.records as usize, 0)
         }
-    }
+        Err(e) => {
+            println!("  批量添加失败: {}", e);
+            (0, data_entries.len())
+        }
+    };
 
     let duration = start.elapsed();
     println!("\n  添加完成: {} 成功, {} 失败", success_count, fail_count);
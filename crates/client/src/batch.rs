@@ -0,0 +1,101 @@
+//! 批量写入
+//!
+//! [`Client::batch`] 返回的 [`Batch`] 在本地累积 add / delete / update 操作，
+//! [`Batch::submit`] 把它们放进一次 `Batch` RPC 发送，Manager 按顺序执行并逐个返回结果：
+//!
+//! ```no_run
+//! # async fn ingest(client: &client::Client) -> Result<(), client::ClientError> {
+//! let mut batch = client.batch();
+//! batch
+//!     .add("file1".into(), vec!["rust".into(), "storage".into()])
+//!     .update("file2".into(), vec!["draft".into()], vec!["final".into()])
+//!     .delete("file3".into(), vec![]);
+//! let response = batch.submit().await?;
+//! for result in response.results.iter().filter(|result| !result.success) {
+//!     eprintln!("{}", result.message);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! 操作使用 Client 的确认模式、租户、命名空间和盲索引设置，与单独调用 `add`、`delete`、
+//! `update_file` 相同。批量请求不是事务，单个操作失败不影响其他操作。
+
+use crate::client::Client;
+use crate::error::ClientError;
+use common::rpc::{batch_operation::Operation, BatchOperation, BatchRequest, BatchResponse};
+use std::time::Duration;
+
+/// 尚未提交的批量写入
+pub struct Batch<'a> {
+    client: &'a Client,
+    operations: Vec<BatchOperation>,
+}
+
+impl<'a> Batch<'a> {
+    pub(crate) fn new(client: &'a Client) -> Self {
+        Batch {
+            client,
+            operations: Vec::new(),
+        }
+    }
+
+    /// 加入 (fid, keywords)
+    pub fn add(&mut self, fid: String, keywords: Vec<String>) -> &mut Self {
+        let request = self.client.add_request(fid, keywords, None);
+        self.push(Operation::Add(request))
+    }
+
+    /// 加入 (fid, keywords)，`ttl` 之后由 storager 删除
+    pub fn add_with_ttl(&mut self, fid: String, keywords: Vec<String>, ttl: Duration) -> &mut Self {
+        let request = self.client.add_request(fid, keywords, Some(ttl));
+        self.push(Operation::Add(request))
+    }
+
+    /// 移除 (fid, keywords)；keywords 为空时 Manager 从它记录的所有 keyword 中移除 fid
+    pub fn delete(&mut self, fid: String, keywords: Vec<String>) -> &mut Self {
+        let request = self.client.delete_request(fid, keywords);
+        self.push(Operation::Delete(request))
+    }
+
+    /// 把 (fid, old_keywords) 改为 (fid, new_keywords)
+    pub fn update(
+        &mut self,
+        fid: String,
+        old_keywords: Vec<String>,
+        new_keywords: Vec<String>,
+    ) -> &mut Self {
+        let request = self.client.update_request(fid, old_keywords, new_keywords);
+        self.push(Operation::Update(request))
+    }
+
+    fn push(&mut self, operation: Operation) -> &mut Self {
+        self.operations.push(BatchOperation {
+            operation: Some(operation),
+        });
+        self
+    }
+
+    /// 已累积的操作数
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// 提交所有操作，`results` 按加入的顺序对应每个操作
+    ///
+    /// 只有整个请求失败（无法连接、权限不足、操作数超过 Manager 的上限）时返回错误，
+    /// 单个操作的失败记录在对应结果的 `success`、`message` 和 `code` 中
+    pub async fn submit(self) -> Result<BatchResponse, ClientError> {
+        if self.operations.is_empty() {
+            return Ok(BatchResponse::default());
+        }
+        let request = BatchRequest {
+            operations: self.operations,
+        };
+        self.client.send_batch(request).await
+    }
+}
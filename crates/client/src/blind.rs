@@ -0,0 +1,136 @@
+//! 盲索引 (blind index) 模式
+//!
+//! 面向隐私敏感的租户：客户端在发送前使用租户密钥对 keyword 做 HMAC-SHA256，
+//! Manager 和 Storager 只能看到盲化后的 token。布尔查询直接在 token 上运行，
+//! 返回的 fid 不受影响，客户端再用本地映射把 token 还原为明文 keyword。
+//!
+//! # 归一化
+//!
+//! 盲化前 keyword 会先经过归一化（去除首尾空白并转为小写），
+//! 保证同一 keyword 的不同写法得到相同 token。
+//!
+//! # 限制
+//!
+//! 盲化后 keyword 的字面结构完全丢失，以下功能在此模式下不可用：
+//! - 前缀查询 / 范围查询（token 之间没有顺序和前缀关系）
+//! - 服务端的 keyword 统计、调试日志中的可读 keyword
+//! - 与未启用盲索引的租户共享 keyword 空间
+
+use common::BooleanExpr;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 盲化 token 的前缀，便于在日志中识别
+const TOKEN_PREFIX: &str = "b_";
+
+/// 盲索引
+///
+/// 持有租户密钥以及本地的 token -> keyword 映射
+#[derive(Clone)]
+pub struct BlindIndex {
+    key: Vec<u8>,
+    tokens: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl BlindIndex {
+    /// 使用租户密钥创建盲索引
+    pub fn new(tenant_key: &[u8]) -> Self {
+        BlindIndex {
+            key: tenant_key.to_vec(),
+            tokens: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// keyword 归一化
+    pub fn normalize(keyword: &str) -> String {
+        keyword.trim().to_lowercase()
+    }
+
+    /// 将 keyword 盲化为 token
+    pub fn blind_keyword(&self, keyword: &str) -> String {
+        let normalized = Self::normalize(keyword);
+
+        let mut mac =
+            HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(normalized.as_bytes());
+        let digest = mac.finalize().into_bytes();
+
+        let token = format!("{}{}", TOKEN_PREFIX, hex::encode(&digest[..16]));
+        self.tokens
+            .write()
+            .unwrap()
+            .insert(token.clone(), normalized);
+        token
+    }
+
+    /// 批量盲化 keyword
+    pub fn blind_keywords(&self, keywords: Vec<String>) -> Vec<String> {
+        keywords.iter().map(|k| self.blind_keyword(k)).collect()
+    }
+
+    /// 盲化布尔表达式中的所有 keyword，运算符和结构保持不变
+    pub fn blind_expr(&self, expr: &BooleanExpr) -> BooleanExpr {
+        match expr {
+            BooleanExpr::Keyword(kw) => BooleanExpr::Keyword(self.blind_keyword(kw)),
+            BooleanExpr::And(left, right) => BooleanExpr::And(
+                Box::new(self.blind_expr(left)),
+                Box::new(self.blind_expr(right)),
+            ),
+            BooleanExpr::Or(left, right) => BooleanExpr::Or(
+                Box::new(self.blind_expr(left)),
+                Box::new(self.blind_expr(right)),
+            ),
+            BooleanExpr::Not(inner) => BooleanExpr::Not(Box::new(self.blind_expr(inner))),
+        }
+    }
+
+    /// 盲化布尔函数字符串
+    pub fn blind_boolean_function(&self, func: &str) -> Result<String, String> {
+        let expr = common::parse_boolean_expr(func)?;
+        Ok(self.blind_expr(&expr).to_string())
+    }
+
+    /// 将 token 还原为明文 keyword（仅限本客户端盲化过的 token）
+    pub fn unblind(&self, token: &str) -> Option<String> {
+        self.tokens.read().unwrap().get(token).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blind_is_deterministic_and_normalized() {
+        let index = BlindIndex::new(b"tenant-key");
+        let a = index.blind_keyword("Rust");
+        let b = index.blind_keyword("  rust ");
+        assert_eq!(a, b);
+        assert!(a.starts_with(TOKEN_PREFIX));
+        assert_eq!(index.unblind(&a), Some("rust".to_string()));
+    }
+
+    #[test]
+    fn test_different_tenants_get_different_tokens() {
+        let a = BlindIndex::new(b"tenant-a").blind_keyword("rust");
+        let b = BlindIndex::new(b"tenant-b").blind_keyword("rust");
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_blind_boolean_function_keeps_structure() {
+        let index = BlindIndex::new(b"tenant-key");
+        let blinded = index
+            .blind_boolean_function("(rust OR python) AND NOT java")
+            .unwrap();
+        let expr = common::parse_boolean_expr(&blinded).unwrap();
+
+        assert!(matches!(expr, BooleanExpr::And(_, _)));
+        assert!(expr.get_keywords().contains(&index.blind_keyword("rust")));
+        assert!(!blinded.contains("python"));
+    }
+}
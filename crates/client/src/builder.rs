@@ -0,0 +1,173 @@
+//! [`Client`] 的构造器
+//!
+//! ```no_run
+//! # fn build() -> Result<client::Client, client::ClientError> {
+//! use std::time::Duration;
+//!
+//! let client = client::Client::builder("http://[::1]:50051")
+//!     .with_namespace("logs")
+//!     .with_timeout(Some(Duration::from_secs(10)))
+//!     .with_max_attempts(5)
+//!     .build()?;
+//! # Ok(client)
+//! # }
+//! ```
+//!
+//! `build` 不连接 Manager：第一次请求时建立连接，之后所有请求共用这条连接，
+//! 断开后在下一次请求时自动重连。
+
+use crate::blind::BlindIndex;
+use crate::client::Client;
+use crate::error::ClientError;
+use common::retry::RetryPolicy;
+use common::rpc::AckMode;
+use common::transport::TransportConfig;
+use std::time::Duration;
+
+/// 默认的单次请求超时时间（Manager 需要等待 storager 生成和验证证明）
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Client 的配置
+pub struct ClientBuilder {
+    manager_addr: String,
+    blind_index: Option<BlindIndex>,
+    ack_mode: AckMode,
+    tenant: String,
+    namespace: String,
+    allow_background: bool,
+    transport: TransportConfig,
+    token: Option<String>,
+    retry: RetryPolicy,
+}
+
+impl ClientBuilder {
+    /// 连接 `manager_addr`（支持 `http://`、`unix:` 和 `memory:` 地址）的默认配置
+    pub fn new(manager_addr: impl Into<String>) -> Self {
+        ClientBuilder {
+            manager_addr: manager_addr.into(),
+            blind_index: None,
+            ack_mode: AckMode::Sync,
+            tenant: String::new(),
+            namespace: String::new(),
+            allow_background: false,
+            transport: TransportConfig::default(),
+            token: None,
+            retry: RetryPolicy {
+                timeout: Some(DEFAULT_TIMEOUT),
+                ..RetryPolicy::default()
+            },
+        }
+    }
+
+    /// 设置变更请求的确认模式
+    ///
+    /// 异步模式下 Manager 在 storager 应用变更后立即返回，证明在后台验证；
+    /// 被标记为关键租户的请求仍会被 Manager 强制为同步确认
+    pub fn with_ack_mode(mut self, ack_mode: AckMode) -> Self {
+        self.ack_mode = ack_mode;
+        self
+    }
+
+    /// 允许超出代价预算的查询排入 Manager 的后台队列（默认直接拒绝）
+    ///
+    /// 未开启时，超出预算的查询返回 [`ClientError::Rejected`]
+    pub fn with_background_queries(mut self, allow: bool) -> Self {
+        self.allow_background = allow;
+        self
+    }
+
+    /// 设置租户标识
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = tenant.into();
+        self
+    }
+
+    /// Read and write keywords in `namespace` instead of the default namespace
+    ///
+    /// Each namespace has its own ADS and root hashes on every storager, so the same
+    /// keyword in two namespaces holds unrelated fids
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    /// 启用盲索引模式，使用租户密钥盲化所有 keyword
+    pub fn with_blind_index(mut self, tenant_key: &[u8]) -> Self {
+        self.blind_index = Some(BlindIndex::new(tenant_key));
+        self
+    }
+
+    /// 设置消息大小上限、压缩算法和 keepalive（默认见 [`TransportConfig::default`]）
+    pub fn with_transport(mut self, transport: TransportConfig) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// 在每个请求中携带 API token
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// 单次尝试的超时时间，`None` 表示不限制（默认见 [`DEFAULT_TIMEOUT`]）
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.retry.timeout = timeout;
+        self
+    }
+
+    /// 只读请求最多尝试的次数（包括第一次），1 表示不重试
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.retry.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// 替换整个重试策略
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 创建 Client；地址无法解析或 TLS 配置无效时返回错误
+    pub fn build(self) -> Result<Client, ClientError> {
+        let channel = common::net::connect_lazy_with(&self.manager_addr, &self.transport)?;
+        Ok(Client {
+            manager_addr: self.manager_addr,
+            channel,
+            blind_index: self.blind_index,
+            ack_mode: self.ack_mode,
+            tenant: self.tenant,
+            namespace: self.namespace,
+            allow_background: self.allow_background,
+            transport: self.transport,
+            token: self.token,
+            retry: self.retry,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    #[tokio::test]
+    async fn test_build_connects_lazily() {
+        assert!(matches!(
+            Client::new("http://bad host"),
+            Err(ClientError::Connect(_))
+        ));
+
+        // 没有服务在监听：build 成功，请求在重试后以 UNAVAILABLE 失败
+        let client = Client::builder("http://127.0.0.1:1")
+            .with_retry_policy(RetryPolicy {
+                max_attempts: 2,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(1),
+                timeout: Some(Duration::from_secs(5)),
+            })
+            .build()
+            .unwrap();
+        let error = client.query("rust").await.unwrap_err();
+        assert_eq!(error.code(), Some(Code::Unavailable));
+    }
+}
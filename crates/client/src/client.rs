@@ -1,141 +1,583 @@
+use crate::batch::Batch;
+use crate::blind::BlindIndex;
+use crate::builder::ClientBuilder;
+use crate::dataset::{records_from_index, Record};
+use crate::error::ClientError;
+use crate::query::QueryResult;
+use common::auth::attach_token;
+use common::retry::RetryPolicy;
 use common::rpc::{
-    manager_service_client::ManagerServiceClient, AddRequest, DeleteRequest, QueryRequest,
-    UpdateRequest,
+    admin_service_client::AdminServiceClient, manager_service_client::ManagerServiceClient,
+    query_request::QueryType, AckMode, AddRequest, AddResponse, ApproxCountRequest, BatchRequest,
+    BatchResponse, BulkAddRecord, BulkAddResponse, ClusterStatusRequest, ClusterStatusResponse,
+    DeleteRequest, DeleteResponse, DeregisterStoragerRequest, DeregisterStoragerResponse,
+    PrefixQueryRequest, QueryRequest, QueryResponse, RangeQueryRequest, RegisterStoragerRequest,
+    RegisterStoragerResponse, RootHashUpdate, SubscribeRootHashesRequest, UpdateRequest,
+    UpdateResponse, WatchKeywordEvent, WatchKeywordRequest,
 };
+use common::telemetry::TracedChannel;
+use common::transport::TransportConfig;
+use common::{parse_boolean_expr, BooleanExpr};
+use std::future::Future;
+use std::time::Duration;
+use tonic::transport::Channel;
+use tonic::{Request, Response, Status, Streaming};
 
 /// Client 结构，封装与 Manager 的交互
+///
+/// 由 [`ClientBuilder`] 创建。所有请求共用同一条到 Manager 的连接，clone 出的 Client 也共用它
+#[derive(Clone)]
 pub struct Client {
-    manager_addr: String,
+    pub(crate) manager_addr: String,
+    /// 到 Manager 的 channel（第一次请求时建立连接，断开后自动重连）
+    pub(crate) channel: Channel,
+    /// 可选的盲索引（启用后 keyword 在发送前被 HMAC 盲化）
+    pub(crate) blind_index: Option<BlindIndex>,
+    /// 变更请求的确认模式（默认同步）
+    pub(crate) ack_mode: AckMode,
+    /// 租户标识（Manager 可按租户强制同步确认）
+    pub(crate) tenant: String,
+    /// 读写的命名空间，为空时使用默认命名空间
+    pub(crate) namespace: String,
+    /// 查询超出代价预算时是否允许排入后台队列
+    pub(crate) allow_background: bool,
+    /// 消息大小上限、压缩算法和 keepalive
+    pub(crate) transport: TransportConfig,
+    /// Manager 启用访问控制时携带的 API token
+    pub(crate) token: Option<String>,
+    /// 超时和重试策略
+    pub(crate) retry: RetryPolicy,
 }
 
 impl Client {
-    /// 创建新的 Client
-    pub fn new(manager_addr: String) -> Self {
-        Client { manager_addr }
+    /// 使用默认配置创建 Client（需要修改配置时使用 [`Client::builder`]）
+    pub fn new(manager_addr: impl Into<String>) -> Result<Self, ClientError> {
+        ClientBuilder::new(manager_addr).build()
     }
 
-    /// Put file: add (fid, keywords) to the system
-    pub async fn put_file(
-        &self,
-        fid: String,
-        keywords: Vec<String>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut client = ManagerServiceClient::connect(self.manager_addr.clone()).await?;
+    /// 配置并创建 Client
+    pub fn builder(manager_addr: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(manager_addr)
+    }
 
-        let request = AddRequest { fid, keywords };
+    /// Manager 地址
+    pub fn manager_addr(&self) -> &str {
+        &self.manager_addr
+    }
 
-        let response = client.add(request).await?;
-        let resp = response.into_inner();
+    /// 获取盲索引（未启用时为 None）
+    pub fn blind_index(&self) -> Option<&BlindIndex> {
+        self.blind_index.as_ref()
+    }
 
-        if resp.success {
-            println!("Put file succeeded: {}", resp.message);
-        } else {
-            println!("Put file failed: {}", resp.message);
+    /// 共用连接上的 Manager 客户端存根
+    fn manager_client(&self) -> ManagerServiceClient<TracedChannel> {
+        self.transport.manager_client(self.channel.clone())
+    }
+
+    /// 共用连接上的管理服务客户端存根（与 `ManagerService` 共用同一个地址）
+    fn admin_client(&self) -> AdminServiceClient<TracedChannel> {
+        self.transport.admin_client(self.channel.clone())
+    }
+
+    /// 构造发往 Manager 的请求，配置了 token 时附加在 metadata 中
+    fn request<T>(&self, message: T) -> Result<Request<T>, ClientError> {
+        let mut request = Request::new(message);
+        if let Some(token) = &self.token {
+            attach_token(&mut request, token).map_err(ClientError::InvalidToken)?;
         }
+        Ok(request)
+    }
 
-        Ok(())
+    /// 按超时和重试策略调用 Manager，`attempt` 每次尝试构造一个新请求
+    ///
+    /// 只读请求（`read_only`）在暂时性错误后重试，写请求只尝试一次
+    async fn call<T, F, Fut>(&self, read_only: bool, mut attempt: F) -> Result<T, ClientError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ClientError>>,
+    {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let outcome = self
+                .retry
+                .within_deadline(attempt())
+                .await
+                .unwrap_or_else(|| {
+                    Err(ClientError::Timeout(self.retry.timeout.unwrap_or_default()))
+                });
+            match outcome {
+                Err(error)
+                    if read_only && error.is_transient() && self.retry.can_retry(attempts) =>
+                {
+                    tokio::time::sleep(self.retry.backoff(attempts)).await;
+                }
+                outcome => return outcome,
+            }
+        }
     }
 
-    /// Query by keyword
-    pub async fn query_by_keyword(
+    /// 通过 `send` 发送 ManagerService 请求，返回响应内容
+    async fn call_manager<M, T, F, Fut>(
         &self,
-        keyword: String,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut client = ManagerServiceClient::connect(self.manager_addr.clone()).await?;
-
-        let request = QueryRequest {
-            query_type: Some(common::rpc::query_request::QueryType::Keyword(keyword)),
-        };
+        read_only: bool,
+        message: M,
+        send: F,
+    ) -> Result<T, ClientError>
+    where
+        M: Clone,
+        F: Fn(ManagerServiceClient<TracedChannel>, Request<M>) -> Fut,
+        Fut: Future<Output = Result<Response<T>, Status>>,
+    {
+        self.call(read_only, || {
+            let sent = self
+                .request(message.clone())
+                .map(|request| send(self.manager_client(), request));
+            async move { Ok(sent?.await?.into_inner()) }
+        })
+        .await
+    }
 
-        let response = client.query(request).await?;
-        let resp = response.into_inner();
+    /// 发送前处理 keyword 列表
+    fn prepare_keywords(&self, keywords: Vec<String>) -> Vec<String> {
+        match &self.blind_index {
+            Some(index) => index.blind_keywords(keywords),
+            None => keywords,
+        }
+    }
 
-        if resp.verified {
-            println!("Query succeeded, found {} files:", resp.fids.len());
-            for fid in resp.fids {
-                println!("  - {}", fid);
-            }
-        } else {
-            println!("Query verification failed!");
+    /// 发送前处理单个 keyword
+    fn prepare_keyword(&self, keyword: String) -> String {
+        match &self.blind_index {
+            Some(index) => index.blind_keyword(&keyword),
+            None => keyword,
         }
+    }
+
+    /// Add (fid, keywords) and return the Manager's response
+    pub async fn add(
+        &self,
+        fid: String,
+        keywords: Vec<String>,
+    ) -> Result<AddResponse, ClientError> {
+        let request = self.add_request(fid, keywords, None);
+        self.call_manager(false, request, |mut client, request| async move {
+            client.add(request).await
+        })
+        .await
+    }
 
-        Ok(())
+    /// Add (fid, keywords) that the storagers delete again after `ttl`
+    ///
+    /// The TTL is rounded up to whole seconds. Adding the same pair again without a TTL
+    /// keeps it until it is deleted
+    pub async fn add_with_ttl(
+        &self,
+        fid: String,
+        keywords: Vec<String>,
+        ttl: Duration,
+    ) -> Result<AddResponse, ClientError> {
+        let request = self.add_request(fid, keywords, Some(ttl));
+        self.call_manager(false, request, |mut client, request| async move {
+            client.add(request).await
+        })
+        .await
     }
 
-    /// Query by boolean function
-    pub async fn query_by_func(
+    /// 构造 Add 请求（keyword 按盲索引设置处理）
+    pub(crate) fn add_request(
         &self,
-        boolean_func: String,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut client = ManagerServiceClient::connect(self.manager_addr.clone()).await?;
+        fid: String,
+        keywords: Vec<String>,
+        ttl: Option<Duration>,
+    ) -> AddRequest {
+        AddRequest {
+            fid,
+            keywords: self.prepare_keywords(keywords),
+            ack_mode: self.ack_mode as i32,
+            tenant: self.tenant.clone(),
+            namespace: self.namespace.clone(),
+            ttl_seconds: ttl.map(ttl_seconds).unwrap_or(0),
+        }
+    }
+
+    /// 批量导入 (fid, keywords) 记录
+    ///
+    /// 所有记录通过一个流发送，Manager 分批写入各 storager，逐条验证证明，
+    /// 导入结束时为每个 storager 发布一次根哈希。适合初始数据集的导入；
+    /// 导入时间随数据集增长，不受单次请求的超时限制
+    pub async fn bulk_add(
+        &self,
+        records: Vec<(String, Vec<String>)>,
+    ) -> Result<BulkAddResponse, ClientError> {
+        let records: Vec<BulkAddRecord> = records
+            .into_iter()
+            .map(|(fid, keywords)| BulkAddRecord {
+                fid,
+                keywords: self.prepare_keywords(keywords),
+                namespace: self.namespace.clone(),
+            })
+            .collect();
+        let resp = self
+            .manager_client()
+            .bulk_add(self.request(tokio_stream::iter(records))?)
+            .await?
+            .into_inner();
+
+        Ok(resp)
+    }
 
+    /// 查询 keyword 或布尔表达式，返回结果、证明和验证结论
+    ///
+    /// 只包含单个 keyword 的表达式按 keyword 查询发送，其余按布尔函数发送；
+    /// 表达式在本地解析，语法错误返回 [`ClientError::InvalidExpression`]
+    pub async fn query(&self, expression: &str) -> Result<QueryResult, ClientError> {
+        match parse_boolean_expr(expression).map_err(ClientError::InvalidExpression)? {
+            BooleanExpr::Keyword(keyword) => self.query_by_keyword(keyword).await,
+            _ => self.query_by_func(expression.to_string()).await,
+        }
+    }
+
+    /// 发送单页查询，布尔查询附带每个 keyword 的结果
+    async fn send_query(&self, query_type: QueryType) -> Result<QueryResponse, ClientError> {
         let request = QueryRequest {
-            query_type: Some(common::rpc::query_request::QueryType::BooleanFunction(
-                boolean_func,
-            )),
+            query_type: Some(query_type),
+            allow_background: self.allow_background,
+            namespace: self.namespace.clone(),
+            keyword_breakdown: true,
+            ..Default::default()
         };
+        self.call_manager(true, request, |mut client, request| async move {
+            client.query(request).await
+        })
+        .await
+    }
 
-        let response = client.query(request).await?;
-        let resp = response.into_inner();
+    /// 盲索引模式下在 token 上构造布尔函数
+    fn prepare_boolean_function(&self, boolean_func: String) -> Result<String, ClientError> {
+        match &self.blind_index {
+            Some(index) => index
+                .blind_boolean_function(&boolean_func)
+                .map_err(ClientError::InvalidExpression),
+            None => Ok(boolean_func),
+        }
+    }
 
-        if resp.verified {
-            println!("Query succeeded, found {} files:", resp.fids.len());
-            for fid in resp.fids {
-                println!("  - {}", fid);
+    /// 查询 keyword，返回结果、证明和验证结论
+    ///
+    /// 盲索引模式下证明和结果中的 keyword 是盲化后的 token
+    pub async fn query_by_keyword(&self, keyword: String) -> Result<QueryResult, ClientError> {
+        let keyword = self.prepare_keyword(keyword);
+        let resp = self.send_query(QueryType::Keyword(keyword.clone())).await?;
+        Ok(QueryResult::from_keyword_response(&keyword, resp))
+    }
+
+    /// 分页查询 keyword，逐页读取直到最后一页
+    ///
+    /// 证明随最后一页返回，最后一页验证通过、且各页 fid 的总数与结果大小一致时
+    /// `verified` 为 true；翻页期间 keyword 被修改时 Manager 返回 `ABORTED`，调用方可以重新查询
+    ///
+    /// # Arguments
+    /// * `keyword` - 查询的 keyword
+    /// * `page_size` - 每页 fid 数量
+    pub async fn query_by_keyword_paged(
+        &self,
+        keyword: String,
+        page_size: u32,
+    ) -> Result<QueryResult, ClientError> {
+        let keyword = self.prepare_keyword(keyword);
+
+        let mut fids = Vec::new();
+        let mut page_token = String::new();
+        loop {
+            let request = QueryRequest {
+                query_type: Some(QueryType::Keyword(keyword.clone())),
+                allow_background: self.allow_background,
+                page_size,
+                page_token,
+                namespace: self.namespace.clone(),
+                ..Default::default()
+            };
+            let mut resp = self
+                .call_manager(true, request, |mut client, request| async move {
+                    client.query(request).await
+                })
+                .await?;
+            fids.append(&mut resp.fids);
+
+            if resp.next_page_token.is_empty() {
+                resp.verified &= fids.len() as u64 == resp.total_count;
+                resp.fids = fids;
+                return Ok(QueryResult::from_keyword_response(&keyword, resp));
             }
-        } else {
-            println!("Query verification failed!");
+            page_token = resp.next_page_token;
         }
+    }
 
-        Ok(())
+    /// 查询布尔函数，返回结果、证明、验证结论和每个 keyword 的结果
+    pub async fn query_by_func(&self, boolean_func: String) -> Result<QueryResult, ClientError> {
+        let boolean_func = self.prepare_boolean_function(boolean_func)?;
+        let resp = self
+            .send_query(QueryType::BooleanFunction(boolean_func))
+            .await?;
+        Ok(QueryResult::from_response(resp))
     }
 
-    /// Delete file: remove (fid, keywords) from the system
-    pub async fn delete_file(
+    /// Remove (fid, keywords) and return the Manager's response
+    ///
+    /// With no keywords the Manager removes the fid from every keyword it has recorded for it
+    pub async fn delete(
         &self,
         fid: String,
         keywords: Vec<String>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut client = ManagerServiceClient::connect(self.manager_addr.clone()).await?;
-
-        let request = DeleteRequest { fid, keywords };
-
-        let response = client.delete(request).await?;
-        let resp = response.into_inner();
+    ) -> Result<DeleteResponse, ClientError> {
+        let request = self.delete_request(fid, keywords);
+        self.call_manager(false, request, |mut client, request| async move {
+            client.delete(request).await
+        })
+        .await
+    }
 
-        if resp.success {
-            println!("Delete file succeeded: {}", resp.message);
-        } else {
-            println!("Delete file failed: {}", resp.message);
+    /// 构造 Delete 请求（keyword 按盲索引设置处理）
+    pub(crate) fn delete_request(&self, fid: String, keywords: Vec<String>) -> DeleteRequest {
+        DeleteRequest {
+            fid,
+            keywords: self.prepare_keywords(keywords),
+            ack_mode: self.ack_mode as i32,
+            tenant: self.tenant.clone(),
+            strict: false,
+            namespace: self.namespace.clone(),
         }
-
-        Ok(())
     }
 
-    /// Update file: change (fid, old_keywords) to (fid, new_keywords)
-    pub async fn update_file(
+    /// Change (fid, old_keywords) to (fid, new_keywords) and return the Manager's response
+    pub async fn update(
         &self,
         fid: String,
         old_keywords: Vec<String>,
         new_keywords: Vec<String>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut client = ManagerServiceClient::connect(self.manager_addr.clone()).await?;
+    ) -> Result<UpdateResponse, ClientError> {
+        let request = self.update_request(fid, old_keywords, new_keywords);
+        self.call_manager(false, request, |mut client, request| async move {
+            client.update(request).await
+        })
+        .await
+    }
 
-        let request = UpdateRequest {
+    /// 构造 Update 请求（keyword 按盲索引设置处理）
+    pub(crate) fn update_request(
+        &self,
+        fid: String,
+        old_keywords: Vec<String>,
+        new_keywords: Vec<String>,
+    ) -> UpdateRequest {
+        UpdateRequest {
             fid,
-            old_keywords,
-            new_keywords,
+            old_keywords: self.prepare_keywords(old_keywords),
+            new_keywords: self.prepare_keywords(new_keywords),
+            ack_mode: self.ack_mode as i32,
+            tenant: self.tenant.clone(),
+            namespace: self.namespace.clone(),
+        }
+    }
+
+    /// 开始一个批量写入：累积 add / delete / update 操作，通过一次 `Batch` RPC 提交
+    ///
+    /// 适合逐个写入大量文件的应用；每个操作的结果单独返回，见 [`Batch::submit`]
+    pub fn batch(&self) -> Batch<'_> {
+        Batch::new(self)
+    }
+
+    /// 发送批量写入
+    pub(crate) async fn send_batch(
+        &self,
+        request: BatchRequest,
+    ) -> Result<BatchResponse, ClientError> {
+        self.call_manager(false, request, |mut client, request| async move {
+            client.batch(request).await
+        })
+        .await
+    }
+
+    /// 近似计数：keyword 下不同文件数量的估计值，只返回验证通过的结果
+    pub async fn approx_count(&self, keyword: String) -> Result<u64, ClientError> {
+        let request = ApproxCountRequest {
+            keyword: self.prepare_keyword(keyword),
+            namespace: self.namespace.clone(),
+        };
+        let resp = self
+            .call_manager(true, request, |mut client, request| async move {
+                client.approx_count(request).await
+            })
+            .await?;
+        if !resp.verified {
+            return Err(ClientError::Unverified("Approx count"));
+        }
+
+        Ok(resp.estimate)
+    }
+
+    /// 范围查询：列出 `[start_key, end_key)` 内的 keyword 及其 fid
+    ///
+    /// `end_key` 为空表示没有上界；前缀搜索（如 `category:*`）可以用
+    /// [`common::prefix_range_end`] 计算上界。盲索引模式下 keyword 的顺序被打乱，不支持范围查询
+    pub async fn range_query(
+        &self,
+        start_key: String,
+        end_key: String,
+    ) -> Result<Vec<(String, Vec<String>)>, ClientError> {
+        if self.blind_index.is_some() {
+            return Err(ClientError::BlindIndex("Range queries"));
+        }
+
+        let request = RangeQueryRequest {
+            start_key,
+            end_key,
+            namespace: self.namespace.clone(),
         };
+        let resp = self
+            .call_manager(true, request, |mut client, request| async move {
+                client.range_query(request).await
+            })
+            .await?;
+        if !resp.verified {
+            return Err(ClientError::Unverified("Range query"));
+        }
+
+        Ok(resp
+            .entries
+            .into_iter()
+            .map(|entry| (entry.keyword, entry.fids))
+            .collect())
+    }
 
-        let response = client.update(request).await?;
-        let resp = response.into_inner();
+    /// 导出完整的 keyword 索引，按 fid 还原为记录（见 [`crate::dataset`]）
+    ///
+    /// 数据来自覆盖整个 keyword 空间的范围查询，只有范围证明验证通过时才返回
+    pub async fn export(&self) -> Result<Vec<Record>, ClientError> {
+        let entries = self.range_query(String::new(), String::new()).await?;
+        Ok(records_from_index(entries))
+    }
 
-        if resp.success {
-            println!("Update file succeeded: {}", resp.message);
-        } else {
-            println!("Update file failed: {}", resp.message);
+    /// 前缀查询：列出以 `prefix` 开头的 keyword 及其 fid 数量，用于 keyword 自动补全
+    ///
+    /// 结果按 keyword 排序，并经过每个 storager 的前缀子树证明验证
+    pub async fn query_by_prefix(&self, prefix: String) -> Result<Vec<(String, u64)>, ClientError> {
+        if self.blind_index.is_some() {
+            return Err(ClientError::BlindIndex("Prefix queries"));
+        }
+
+        let request = PrefixQueryRequest {
+            prefix,
+            namespace: self.namespace.clone(),
+        };
+        let resp = self
+            .call_manager(true, request, |mut client, request| async move {
+                client.query_by_prefix(request).await
+            })
+            .await?;
+        if !resp.verified {
+            return Err(ClientError::Unverified("Prefix query"));
         }
 
-        Ok(())
+        Ok(resp
+            .keywords
+            .into_iter()
+            .map(|entry| (entry.keyword, entry.fid_count))
+            .collect())
+    }
+
+    /// 在运行中的集群里加入 storager，返回迁移到它的哈希区间和复制的数据量
+    ///
+    /// # 参数
+    ///
+    /// * `name` - 环上的节点名称，为空时由 Manager 自动命名
+    /// * `address` - storager 通告地址
+    /// * `virtual_nodes` - 虚拟节点数量，0 表示使用 Manager 的默认值
+    pub async fn register_storager(
+        &self,
+        name: String,
+        address: String,
+        virtual_nodes: u32,
+    ) -> Result<RegisterStoragerResponse, ClientError> {
+        let request = RegisterStoragerRequest {
+            name,
+            address,
+            virtual_nodes,
+        };
+        self.call_manager(false, request, |mut client, request| async move {
+            client.register_storager(request).await
+        })
+        .await
+    }
+
+    /// 从运行中的集群移除 storager，返回迁出的哈希区间和复制的数据量
+    pub async fn deregister_storager(
+        &self,
+        name: String,
+    ) -> Result<DeregisterStoragerResponse, ClientError> {
+        let request = DeregisterStoragerRequest { name };
+        self.call_manager(false, request, |mut client, request| async move {
+            client.deregister_storager(request).await
+        })
+        .await
+    }
+
+    /// 集群状态：storager 健康状况、哈希环布局和各命名空间的根哈希（需要读权限）
+    pub async fn cluster_status(&self) -> Result<ClusterStatusResponse, ClientError> {
+        self.call(true, || {
+            let request = self.request(ClusterStatusRequest {});
+            let mut client = self.admin_client();
+            async move { Ok(client.cluster_status(request?).await?.into_inner()) }
+        })
+        .await
     }
+
+    /// 订阅各 storager 的根哈希
+    ///
+    /// 流先返回每个 storager 当前的根哈希，之后每次变更验证通过都会推送新的根哈希。
+    /// 同一 storager 的更新可能重复或跳过中间版本，客户端应只保留 `version` 最大的一条
+    pub async fn subscribe_root_hashes(&self) -> Result<Streaming<RootHashUpdate>, ClientError> {
+        let stream = self
+            .manager_client()
+            .subscribe_root_hashes(self.request(SubscribeRootHashesRequest {})?)
+            .await?
+            .into_inner();
+
+        Ok(stream)
+    }
+
+    /// 订阅 keyword 或布尔表达式的结果变化
+    ///
+    /// 流的第一条通知（`sequence` 为 0）在 `added` 中给出当前已验证的结果，之后每次写请求
+    /// 涉及表达式中的 keyword 且结果发生变化时推送新增和移除的 fid。订阅者处理不及时时
+    /// 多次变化合并为一条通知；盲索引模式下 keyword 在发送前盲化
+    pub async fn watch_keyword(
+        &self,
+        expression: &str,
+    ) -> Result<Streaming<WatchKeywordEvent>, ClientError> {
+        let expression =
+            match parse_boolean_expr(expression).map_err(ClientError::InvalidExpression)? {
+                BooleanExpr::Keyword(keyword) => self.prepare_keyword(keyword),
+                _ => self.prepare_boolean_function(expression.to_string())?,
+            };
+        let request = WatchKeywordRequest {
+            expression,
+            namespace: self.namespace.clone(),
+        };
+        let stream = self
+            .manager_client()
+            .watch_keyword(self.request(request)?)
+            .await?
+            .into_inner();
+
+        Ok(stream)
+    }
+}
+
+/// TTL 向上取整到秒，至少 1 秒（0 表示不过期）
+fn ttl_seconds(ttl: Duration) -> u64 {
+    let seconds = ttl.as_secs() + u64::from(ttl.subsec_nanos() > 0);
+    seconds.max(1)
 }
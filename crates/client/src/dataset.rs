@@ -0,0 +1,231 @@
+//! 数据集导入导出格式
+//!
+//! 数据集由 (fid, keywords) 记录组成，支持两种格式：
+//!
+//! - `csv`：每行 `fid,keyword,keyword,...`（`data/testdata` 的格式），忽略空行和 `#` 开头的注释
+//! - `jsonl`：每行一个 `{"fid": "...", "keywords": ["...", ...]}` 对象
+//!
+//! 导入时记录交给 [`Client::bulk_add`](crate::Client::bulk_add)；导出时把
+//! keyword 索引（keyword → fids）还原为按 fid 排列的记录，两种格式都能原样再导入。
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// 一条记录：fid 及其所属的 keyword
+pub type Record = (String, Vec<String>);
+
+/// 数据集格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DatasetFormat {
+    #[default]
+    Csv,
+    Jsonl,
+}
+
+impl FromStr for DatasetFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "csv" => Ok(DatasetFormat::Csv),
+            "jsonl" => Ok(DatasetFormat::Jsonl),
+            other => Err(format!(
+                "unknown dataset format '{}' (expected csv or jsonl)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DatasetFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DatasetFormat::Csv => "csv",
+            DatasetFormat::Jsonl => "jsonl",
+        })
+    }
+}
+
+/// JSONL 中的一行
+#[derive(Serialize, Deserialize)]
+struct JsonRecord {
+    fid: String,
+    keywords: Vec<String>,
+}
+
+/// 解析数据集，错误信息包含出错的行号
+pub fn parse_records(input: &str, format: DatasetFormat) -> Result<Vec<Record>, String> {
+    let mut records = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (format == DatasetFormat::Csv && line.starts_with('#')) {
+            continue;
+        }
+        let (fid, keywords): (String, Vec<String>) = match format {
+            DatasetFormat::Csv => {
+                let mut fields = line.split(',').map(str::trim);
+                let fid = fields.next().unwrap_or_default().to_string();
+                let keywords = fields.map(str::to_string).collect();
+                (fid, keywords)
+            }
+            DatasetFormat::Jsonl => {
+                let record: JsonRecord =
+                    serde_json::from_str(line).map_err(|e| format!("line {}: {}", index + 1, e))?;
+                (record.fid, record.keywords)
+            }
+        };
+        let keywords: Vec<String> = keywords
+            .into_iter()
+            .filter(|keyword| !keyword.is_empty())
+            .collect();
+        if fid.is_empty() || keywords.is_empty() {
+            return Err(format!(
+                "line {}: a record needs a fid and at least one keyword",
+                index + 1
+            ));
+        }
+        records.push((fid, keywords));
+    }
+    Ok(records)
+}
+
+/// 把 keyword 索引（keyword 及其 fid）还原为记录，按 fid 排序，每条记录的 keyword 也排好序
+pub fn records_from_index(entries: Vec<(String, Vec<String>)>) -> Vec<Record> {
+    let mut by_fid: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (keyword, fids) in entries {
+        for fid in fids {
+            by_fid.entry(fid).or_default().push(keyword.clone());
+        }
+    }
+    by_fid
+        .into_iter()
+        .map(|(fid, mut keywords)| {
+            keywords.sort();
+            keywords.dedup();
+            (fid, keywords)
+        })
+        .collect()
+}
+
+/// 写出数据集
+///
+/// CSV 没有转义，fid 或 keyword 含有逗号或换行时返回 [`io::ErrorKind::InvalidData`]，
+/// 这类数据应导出为 JSONL
+pub fn write_records<W: Write>(
+    records: &[Record],
+    format: DatasetFormat,
+    out: &mut W,
+) -> io::Result<()> {
+    for (fid, keywords) in records {
+        match format {
+            DatasetFormat::Csv => {
+                let unrepresentable = std::iter::once(fid)
+                    .chain(keywords)
+                    .find(|field| field.contains([',', '\n', '\r']));
+                if let Some(field) = unrepresentable {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("'{}' cannot be written as CSV, use jsonl", field),
+                    ));
+                }
+                writeln!(out, "{},{}", fid, keywords.join(","))?;
+            }
+            DatasetFormat::Jsonl => {
+                let record = JsonRecord {
+                    fid: fid.clone(),
+                    keywords: keywords.clone(),
+                };
+                serde_json::to_writer(&mut *out, &record)?;
+                writeln!(out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(fid: &str, keywords: &[&str]) -> Record {
+        (
+            fid.to_string(),
+            keywords.iter().map(|k| k.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let records = parse_records(
+            "# fid,keywords\n001, animal ,bird\n\n002,cat,\n",
+            DatasetFormat::Csv,
+        )
+        .unwrap();
+        assert_eq!(
+            records,
+            vec![record("001", &["animal", "bird"]), record("002", &["cat"])]
+        );
+
+        let error = parse_records("001,a\n002\n", DatasetFormat::Csv).unwrap_err();
+        assert!(error.starts_with("line 2"), "{}", error);
+        assert!(parse_records(",a", DatasetFormat::Csv).is_err());
+    }
+
+    #[test]
+    fn test_parse_jsonl() {
+        let input = "{\"fid\":\"001\",\"keywords\":[\"a\",\"b\"]}\n\n{\"fid\":\"002\",\"keywords\":[\"c\"]}";
+        let records = parse_records(input, DatasetFormat::Jsonl).unwrap();
+        assert_eq!(
+            records,
+            vec![record("001", &["a", "b"]), record("002", &["c"])]
+        );
+
+        let error = parse_records("{\"fid\":\"001\"}", DatasetFormat::Jsonl).unwrap_err();
+        assert!(error.starts_with("line 1"), "{}", error);
+        let error =
+            parse_records("{\"fid\":\"001\",\"keywords\":[]}", DatasetFormat::Jsonl).unwrap_err();
+        assert!(error.contains("at least one keyword"), "{}", error);
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let index = vec![
+            ("bird".to_string(), vec!["001".to_string()]),
+            (
+                "animal".to_string(),
+                vec!["002".to_string(), "001".to_string()],
+            ),
+        ];
+        let records = records_from_index(index);
+        assert_eq!(
+            records,
+            vec![
+                record("001", &["animal", "bird"]),
+                record("002", &["animal"])
+            ]
+        );
+
+        for format in [DatasetFormat::Csv, DatasetFormat::Jsonl] {
+            let mut out = Vec::new();
+            write_records(&records, format, &mut out).unwrap();
+            let parsed = parse_records(std::str::from_utf8(&out).unwrap(), format).unwrap();
+            assert_eq!(parsed, records, "{}", format);
+        }
+
+        let mut out = Vec::new();
+        let error =
+            write_records(&[record("a,b", &["k"])], DatasetFormat::Csv, &mut out).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        write_records(&[record("a,b", &["k"])], DatasetFormat::Jsonl, &mut out).unwrap();
+    }
+
+    #[test]
+    fn test_format_names() {
+        assert_eq!("jsonl".parse(), Ok(DatasetFormat::Jsonl));
+        assert_eq!(DatasetFormat::Csv.to_string(), "csv");
+        assert!("xml".parse::<DatasetFormat>().is_err());
+    }
+}
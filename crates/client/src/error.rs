@@ -0,0 +1,120 @@
+//! Client 的错误类型
+//!
+//! Manager 返回的状态被还原为 [`ClientError`]，调用方用 [`ClientError::kind`]
+//! 判断失败的类别（例如只在路由失败时重试，验证失败时告警），不需要解析错误信息。
+
+use common::{ErrorKind, QueryRejected};
+use std::time::Duration;
+use thiserror::Error;
+use tonic::{Code, Status};
+
+/// Client 请求失败的原因
+#[derive(Debug, Error)]
+pub enum ClientError {
+    /// 无法连接 Manager
+    #[error("Failed to connect to manager: {0}")]
+    Connect(#[from] tonic::transport::Error),
+
+    /// 查询超出 Manager 的代价预算
+    #[error(transparent)]
+    Rejected(#[from] QueryRejected),
+
+    /// Manager 返回了错误状态
+    #[error("{}", .0.message())]
+    Rpc(Box<Status>),
+
+    /// 响应没有通过验证
+    #[error("{0} verification failed")]
+    Unverified(&'static str),
+
+    /// 当前配置不支持该操作
+    #[error("{0} are not supported with a blind index")]
+    BlindIndex(&'static str),
+
+    /// 布尔表达式无法解析
+    #[error("Failed to parse boolean expression: {0}")]
+    InvalidExpression(String),
+
+    /// API token 无法放入请求 metadata
+    #[error("{0}")]
+    InvalidToken(String),
+
+    /// Manager 没有在超时时间内响应
+    #[error("Request to manager timed out after {0:?}")]
+    Timeout(Duration),
+}
+
+impl ClientError {
+    /// 错误类别；Manager 没有标记类别的状态返回 None
+    pub fn kind(&self) -> Option<ErrorKind> {
+        match self {
+            ClientError::Connect(_) | ClientError::Timeout(_) => Some(ErrorKind::Routing),
+            ClientError::Rejected(_) => None,
+            ClientError::Rpc(status) => ErrorKind::from_status(status),
+            ClientError::Unverified(_) => Some(ErrorKind::Verification),
+            ClientError::BlindIndex(_) | ClientError::InvalidExpression(_) => {
+                Some(ErrorKind::InvalidRequest)
+            }
+            ClientError::InvalidToken(_) => Some(ErrorKind::Auth),
+        }
+    }
+
+    /// 是否是重试可能成功的暂时性错误（超时、Manager 暂时不可用）
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ClientError::Timeout(_) => true,
+            ClientError::Rpc(status) => status.code() == Code::Unavailable,
+            _ => false,
+        }
+    }
+
+    /// Manager 返回的状态码（错误不是来自 Manager 时返回 None）
+    pub fn code(&self) -> Option<Code> {
+        match self {
+            ClientError::Rejected(_) => Some(Code::ResourceExhausted),
+            ClientError::Rpc(status) => Some(status.code()),
+            _ => None,
+        }
+    }
+}
+
+impl From<Status> for ClientError {
+    /// 准入拒绝还原为 [`QueryRejected`]，其余状态原样保留
+    fn from(status: Status) -> Self {
+        match QueryRejected::from_status(&status) {
+            Some(rejected) => ClientError::Rejected(rejected),
+            None => ClientError::Rpc(Box::new(status)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_from_manager_status() {
+        let status = ErrorKind::Verification.status(Code::DataLoss, "bad proof");
+        let error = ClientError::from(status);
+        assert_eq!(error.kind(), Some(ErrorKind::Verification));
+        assert_eq!(error.code(), Some(Code::DataLoss));
+        assert_eq!(error.to_string(), "bad proof");
+
+        let rejected = QueryRejected {
+            estimated_cost: 10,
+            budget: 5,
+        };
+        let error = ClientError::from(Status::from(rejected.clone()));
+        assert!(matches!(error, ClientError::Rejected(r) if r == rejected));
+
+        assert_eq!(ClientError::from(Status::internal("x")).kind(), None);
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        assert!(ClientError::from(Status::unavailable("restarting")).is_transient());
+        assert!(ClientError::Timeout(Duration::from_secs(1)).is_transient());
+        assert!(!ClientError::from(Status::invalid_argument("bad namespace")).is_transient());
+        assert!(!ClientError::Unverified("Query").is_transient());
+    }
+}
@@ -1,3 +1,16 @@
+pub mod batch;
+pub mod blind;
+pub mod builder;
 pub mod client;
+pub mod dataset;
+pub mod error;
+pub mod query;
 
+pub use batch::Batch;
+pub use blind::BlindIndex;
+pub use builder::ClientBuilder;
 pub use client::Client;
+pub use dataset::DatasetFormat;
+pub use error::ClientError;
+pub use query::{KeywordResult, QueryResult};
+pub use common::retry::RetryPolicy;
@@ -1,46 +1,547 @@
-use client::Client;
+//! 命令行客户端
+//!
+//! ```text
+//! client add file1 --keywords rust,distributed,storage
+//! client query "rust AND (storage OR database)"
+//! client delete file1
+//! client import data/testdata
+//! client export --format jsonl --output backup.jsonl
+//! client --json status
+//! ```
+//!
+//! Manager 地址依次取自 `--manager-addr`、`DSS_MANAGER_ADDR`、`--config` 指向的
+//! `SystemConfig` JSON，都没有时为 `http://[::1]:50051`。
+//!
+//! `--json` 时结果（包括错误）以单个 JSON 对象输出到 stdout，便于脚本处理。
+//! 退出码区分失败的类别，见 [`exit_code`]。
+
+use clap::{Parser, Subcommand};
+use client::dataset::{parse_records, write_records};
+use client::{Client, ClientError, DatasetFormat};
+use common::{ErrorKind, SystemConfig};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// 未指定地址和配置文件时连接的 Manager
+const DEFAULT_MANAGER_ADDR: &str = "http://[::1]:50051";
+
+/// 成功
+const EXIT_OK: u8 = 0;
+/// Manager 执行失败，或错误没有标记类别
+const EXIT_FAILED: u8 = 1;
+/// 参数或输入无效（与 clap 的用法错误相同）
+const EXIT_INVALID: u8 = 2;
+/// 无法连接 Manager，或找不到负责 keyword 的节点
+const EXIT_ROUTING: u8 = 3;
+/// 证明验证失败
+const EXIT_UNVERIFIED: u8 = 4;
+/// 凭据无效或权限不足
+const EXIT_AUTH: u8 = 5;
+
+#[derive(Debug, Parser)]
+#[command(name = "client", about = "Verifiable keyword storage client")]
+struct Cli {
+    /// Manager address (`http://host:port` or `unix:/path`) [default: manager_addr of --config, else http://[::1]:50051]
+    #[arg(long, global = true, env = "DSS_MANAGER_ADDR")]
+    manager_addr: Option<String>,
+
+    /// SystemConfig JSON whose manager_addr is used when --manager-addr is not given
+    #[arg(long, global = true, env = "DSS_CONFIG", value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Print results as JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// API token sent with every request
+    #[arg(long, global = true, env = "DSS_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+
+    /// Namespace to read and write (default namespace when omitted)
+    #[arg(long, global = true, env = "DSS_NAMESPACE")]
+    namespace: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Add a fid under one or more keywords
+    Add {
+        fid: String,
+        /// Comma-separated keywords
+        #[arg(long, value_delimiter = ',', required = true)]
+        keywords: Vec<String>,
+    },
+    /// Query a keyword or a boolean expression such as "a AND (b OR NOT c)"
+    Query { expression: String },
+    /// Delete a fid; without --keywords it is removed from every keyword it was added under
+    Delete {
+        fid: String,
+        /// Comma-separated keywords
+        #[arg(long, value_delimiter = ',')]
+        keywords: Vec<String>,
+    },
+    /// Bulk import a dataset of (fid, keywords) records through one BulkAdd stream
+    Import {
+        path: PathBuf,
+        /// `csv` (`fid,keyword,keyword,...` per line) or `jsonl` (`{"fid", "keywords"}` per line)
+        #[arg(long, default_value_t = DatasetFormat::Csv)]
+        format: DatasetFormat,
+    },
+    /// Export the verified keyword index as (fid, keywords) records
+    Export {
+        #[arg(long, default_value_t = DatasetFormat::Csv)]
+        format: DatasetFormat,
+        /// Write to a file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Show storager health, the hash ring layout and published root hashes
+    Status,
+}
+
+/// 一次命令的结果
+struct Report {
+    code: u8,
+    json: Value,
+    text: String,
+    /// 摘要写到 stderr（stdout 已经用于输出数据）
+    stderr: bool,
+}
+
+impl Report {
+    /// Manager 返回的 `success` 标志决定退出码
+    fn outcome(success: bool, json: Value, text: String) -> Self {
+        Report {
+            code: if success { EXIT_OK } else { EXIT_FAILED },
+            json,
+            text,
+            stderr: false,
+        }
+    }
+}
+
+/// 命令失败的原因
+#[derive(Debug)]
+enum Failure {
+    Client(ClientError),
+    Input(String),
+}
+
+impl Failure {
+    fn code(&self) -> u8 {
+        match self {
+            Failure::Client(error) => exit_code(error),
+            Failure::Input(_) => EXIT_INVALID,
+        }
+    }
+
+    fn kind(&self) -> Option<ErrorKind> {
+        match self {
+            Failure::Client(error) => error.kind(),
+            Failure::Input(_) => Some(ErrorKind::InvalidRequest),
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Failure::Client(error) => error.to_string(),
+            Failure::Input(message) => message.clone(),
+        }
+    }
+}
+
+impl From<ClientError> for Failure {
+    fn from(error: ClientError) -> Self {
+        Failure::Client(error)
+    }
+}
+
+/// 按错误类别选择退出码
+fn exit_code(error: &ClientError) -> u8 {
+    match error.kind() {
+        Some(ErrorKind::InvalidRequest) => EXIT_INVALID,
+        Some(ErrorKind::Routing) => EXIT_ROUTING,
+        Some(ErrorKind::Verification) => EXIT_UNVERIFIED,
+        Some(ErrorKind::Auth) => EXIT_AUTH,
+        Some(ErrorKind::Storage) | Some(ErrorKind::Membership) | None => EXIT_FAILED,
+    }
+}
+
+async fn run(client: &Client, command: Command) -> Result<Report, Failure> {
+    match command {
+        Command::Add { fid, keywords } => {
+            let resp = client.add(fid.clone(), keywords.clone()).await?;
+            Ok(Report::outcome(
+                resp.success,
+                json!({
+                    "fid": fid,
+                    "keywords": keywords,
+                    "success": resp.success,
+                    "message": resp.message,
+                    "pending_ops": resp.pending_ops,
+                }),
+                format!("add {}: {}", fid, resp.message),
+            ))
+        }
+        Command::Query { expression } => {
+            let resp = client.query(&expression).await?;
+            let keywords: Vec<Value> = resp
+                .per_keyword_breakdown
+                .iter()
+                .map(|keyword| {
+                    json!({
+                        "keyword": keyword.keyword,
+                        "fids": keyword.fids.len(),
+                        "storager": keyword.storager,
+                        "filtered": keyword.filtered,
+                    })
+                })
+                .collect();
+            let mut text = format!("{} fid(s)", resp.fids.len());
+            for fid in &resp.fids {
+                text.push_str(&format!("\n  {}", fid));
+            }
+            if !resp.verified {
+                text.push_str("\nverification failed");
+            }
+            Ok(Report {
+                code: if resp.verified {
+                    EXIT_OK
+                } else {
+                    EXIT_UNVERIFIED
+                },
+                json: json!({
+                    "query": expression,
+                    "fids": resp.fids,
+                    "verified": resp.verified,
+                    "root_hash": hex::encode(&resp.root_hash),
+                    "keywords": keywords,
+                }),
+                text,
+                stderr: false,
+            })
+        }
+        Command::Delete { fid, keywords } => {
+            let resp = client.delete(fid.clone(), keywords).await?;
+            Ok(Report::outcome(
+                resp.success,
+                json!({
+                    "fid": fid,
+                    "success": resp.success,
+                    "message": resp.message,
+                    "pending_ops": resp.pending_ops,
+                }),
+                format!("delete {}: {}", fid, resp.message),
+            ))
+        }
+        Command::Import { path, format } => {
+            let input = std::fs::read_to_string(&path)
+                .map_err(|e| Failure::Input(format!("{}: {}", path.display(), e)))?;
+            let records = parse_records(&input, format)
+                .map_err(|e| Failure::Input(format!("{}: {}", path.display(), e)))?;
+            let resp = client.bulk_add(records).await?;
+            let transitions: Vec<Value> = resp
+                .transitions
+                .iter()
+                .map(|transition| {
+                    json!({
+                        "storager": transition.storager,
+                        "old_root_hash": hex::encode(&transition.old_root_hash),
+                        "new_root_hash": hex::encode(&transition.new_root_hash),
+                        "epoch": transition.epoch,
+                    })
+                })
+                .collect();
+            Ok(Report::outcome(
+                resp.success,
+                json!({
+                    "records": resp.records,
+                    "success": resp.success,
+                    "message": resp.message,
+                    "transitions": transitions,
+                }),
+                format!("imported {} record(s): {}", resp.records, resp.message),
+            ))
+        }
+        Command::Export { format, output } => {
+            let records = client.export().await?;
+            let mut buffer = Vec::new();
+            write_records(&records, format, &mut buffer)
+                .map_err(|e| Failure::Input(e.to_string()))?;
+            let destination = match &output {
+                Some(path) => {
+                    std::fs::write(path, &buffer)
+                        .map_err(|e| Failure::Input(format!("{}: {}", path.display(), e)))?;
+                    path.display().to_string()
+                }
+                None => {
+                    std::io::stdout()
+                        .write_all(&buffer)
+                        .map_err(|e| Failure::Input(e.to_string()))?;
+                    "stdout".to_string()
+                }
+            };
+            let summary = json!({
+                "records": records.len(),
+                "format": format.to_string(),
+                "output": destination,
+            });
+            Ok(Report {
+                code: EXIT_OK,
+                text: format!("exported {} record(s) to {}", records.len(), destination),
+                json: summary,
+                // 记录已经写到 stdout 时摘要改写到 stderr，避免混入导出的数据
+                stderr: output.is_none(),
+            })
+        }
+        Command::Status => {
+            let status = client.cluster_status().await?;
+            let mut text = format!(
+                "ads mode {}, ring hasher {}, replication factor {}",
+                status.ads_mode, status.ring_hasher, status.replication_factor
+            );
+            for storager in &status.storagers {
+                let health = match (storager.reachable, storager.routed) {
+                    (true, true) => "healthy".to_string(),
+                    (true, false) => "reachable, not routed".to_string(),
+                    (false, _) => format!("unreachable: {}", storager.message),
+                };
+                text.push_str(&format!(
+                    "\n  {} {} vnodes={} share={:.1}% {}",
+                    storager.name,
+                    storager.address,
+                    storager.virtual_nodes,
+                    storager.hash_space_fraction * 100.0,
+                    health
+                ));
+            }
+            for root in &status.roots {
+                text.push_str(&format!(
+                    "\n  root {}/{} v{} {}",
+                    root.storager,
+                    root.namespace,
+                    root.version,
+                    hex::encode(&root.root_hash)
+                ));
+            }
+            let storagers: Vec<Value> = status
+                .storagers
+                .iter()
+                .map(|storager| {
+                    json!({
+                        "name": storager.name,
+                        "address": storager.address,
+                        "virtual_nodes": storager.virtual_nodes,
+                        "hash_space_fraction": storager.hash_space_fraction,
+                        "routed": storager.routed,
+                        "reachable": storager.reachable,
+                        "crypto_ready": storager.crypto_ready,
+                        "message": storager.message,
+                    })
+                })
+                .collect();
+            let roots: Vec<Value> = status
+                .roots
+                .iter()
+                .map(|root| {
+                    json!({
+                        "storager": root.storager,
+                        "namespace": root.namespace,
+                        "version": root.version,
+                        "root_hash": hex::encode(&root.root_hash),
+                    })
+                })
+                .collect();
+            // 有 storager 不可达时以路由失败退出，便于监控脚本判断
+            let reachable = status.storagers.iter().all(|storager| storager.reachable);
+            Ok(Report {
+                code: if reachable { EXIT_OK } else { EXIT_ROUTING },
+                json: json!({
+                    "ads_mode": status.ads_mode,
+                    "ring_hasher": status.ring_hasher,
+                    "replication_factor": status.replication_factor,
+                    "storagers": storagers,
+                    "roots": roots,
+                }),
+                text,
+                stderr: false,
+            })
+        }
+    }
+}
+
+impl Cli {
+    /// `--manager-addr`（或 `DSS_MANAGER_ADDR`）优先，其次是配置文件中的地址
+    fn manager_addr(&self) -> Result<String, Failure> {
+        if let Some(addr) = &self.manager_addr {
+            return Ok(addr.clone());
+        }
+        match &self.config {
+            Some(path) => SystemConfig::load(path)
+                .map(|config| config.manager_addr)
+                .map_err(Failure::Input),
+            None => Ok(DEFAULT_MANAGER_ADDR.to_string()),
+        }
+    }
+
+    async fn execute(self) -> Result<Report, Failure> {
+        let mut builder = Client::builder(self.manager_addr()?);
+        if let Some(token) = self.token {
+            builder = builder.with_token(token);
+        }
+        if let Some(namespace) = self.namespace {
+            builder = builder.with_namespace(namespace);
+        }
+        run(&builder.build()?, self.command).await
+    }
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let manager_addr = "http://[::1]:50051".to_string();
-    let client = Client::new(manager_addr);
-
-    // Example usage
-    println!("=== Testing Put File ===");
-    client
-        .put_file(
-            "file1".to_string(),
-            vec![
-                "rust".to_string(),
-                "distributed".to_string(),
-                "storage".to_string(),
-            ],
-        )
-        .await?;
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let json_output = cli.json;
 
-    println!("\n=== Testing Query ===");
-    client.query_by_keyword("rust".to_string()).await?;
+    let code = match cli.execute().await {
+        Ok(report) => {
+            let summary = if json_output {
+                report.json.to_string()
+            } else {
+                report.text
+            };
+            if report.stderr {
+                eprintln!("{}", summary);
+            } else {
+                println!("{}", summary);
+            }
+            report.code
+        }
+        Err(failure) => {
+            if json_output {
+                println!(
+                    "{}",
+                    json!({
+                        "error": failure.message(),
+                        "kind": failure.kind().map(ErrorKind::as_str),
+                    })
+                );
+            } else {
+                eprintln!("error: {}", failure.message());
+            }
+            failure.code()
+        }
+    };
+    ExitCode::from(code)
+}
 
-    println!("\n=== Testing Update ===");
-    client
-        .update_file(
-            "file1".to_string(),
-            vec!["storage".to_string()],
-            vec!["database".to_string()],
-        )
-        .await?;
-
-    println!("\n=== Testing Delete ===");
-    client
-        .delete_file(
-            "file1".to_string(),
-            vec![
-                "rust".to_string(),
-                "distributed".to_string(),
-                "database".to_string(),
-            ],
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use tonic::{Code, Status};
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_add_and_global_flags() {
+        let cli = Cli::try_parse_from([
+            "client",
+            "add",
+            "f1",
+            "--keywords",
+            "a,b,c",
+            "--json",
+            "--manager-addr",
+            "unix:/tmp/manager.sock",
+        ])
+        .unwrap();
+        assert!(cli.json);
+        assert_eq!(cli.manager_addr().unwrap(), "unix:/tmp/manager.sock");
+        match cli.command {
+            Command::Add { fid, keywords } => {
+                assert_eq!(fid, "f1");
+                assert_eq!(keywords, vec!["a", "b", "c"]);
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+
+        // add 必须给出 keyword；delete 可以省略
+        assert!(Cli::try_parse_from(["client", "add", "f1"]).is_err());
+        let cli = Cli::try_parse_from(["client", "delete", "f1"]).unwrap();
+        assert!(matches!(cli.command, Command::Delete { keywords, .. } if keywords.is_empty()));
+    }
+
+    #[test]
+    fn test_manager_addr_from_config() {
+        let path = std::env::temp_dir().join("client_manager_addr_test.json");
+        std::fs::write(
+            &path,
+            r#"{"num_clients": 1, "num_storagers": 0, "ads_mode": "Mpt",
+                "manager_addr": "http://10.0.0.2:50051", "storager_addrs": [], "client_addrs": []}"#,
         )
-        .await?;
+        .unwrap();
+        let config = path.to_str().unwrap();
+
+        let cli = Cli::try_parse_from(["client", "status", "--config", config]).unwrap();
+        assert_eq!(cli.manager_addr().unwrap(), "http://10.0.0.2:50051");
+        let cli = Cli::try_parse_from([
+            "client",
+            "--manager-addr",
+            "unix:/tmp/manager.sock",
+            "status",
+            "--config",
+            config,
+        ])
+        .unwrap();
+        assert_eq!(cli.manager_addr().unwrap(), "unix:/tmp/manager.sock");
+
+        std::fs::remove_file(&path).unwrap();
+        let cli = Cli::try_parse_from(["client", "status", "--config", config]).unwrap();
+        assert!(matches!(cli.manager_addr(), Err(Failure::Input(_))));
+    }
+
+    #[test]
+    fn test_parse_dataset_commands() {
+        let cli =
+            Cli::try_parse_from(["client", "import", "data.jsonl", "--format", "jsonl"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Import {
+                format: DatasetFormat::Jsonl,
+                ..
+            }
+        ));
+        let cli = Cli::try_parse_from(["client", "export"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Export {
+                format: DatasetFormat::Csv,
+                output: None
+            }
+        ));
+        assert!(Cli::try_parse_from(["client", "export", "--format", "xml"]).is_err());
+    }
 
-    Ok(())
+    #[test]
+    fn test_exit_code_by_kind() {
+        let status = ErrorKind::Verification.status(Code::DataLoss, "bad proof");
+        assert_eq!(exit_code(&ClientError::from(status)), EXIT_UNVERIFIED);
+        let status = ErrorKind::Auth.status(Code::PermissionDenied, "denied");
+        assert_eq!(exit_code(&ClientError::from(status)), EXIT_AUTH);
+        assert_eq!(
+            exit_code(&ClientError::InvalidExpression("(".to_string())),
+            EXIT_INVALID
+        );
+        assert_eq!(
+            exit_code(&ClientError::from(Status::internal("x"))),
+            EXIT_FAILED
+        );
+    }
 }
@@ -0,0 +1,145 @@
+//! 查询结果
+//!
+//! Client 的查询方法返回 [`QueryResult`]：除了 fid，还保留 Manager 返回的证明、
+//! 验证时使用的根哈希和验证结论，以及布尔查询中每个 keyword 各自的结果和证明，
+//! 应用可以把它们（serde 序列化后）与结果一起保存，之后自行审计。
+//!
+//! 验证没有通过的结果也会返回，`verified` 为 false，由调用方决定如何处理。
+
+use common::rpc::{KeywordBreakdown, QueryResponse};
+use common::{Proof, RootHash};
+use serde::{Deserialize, Serialize};
+
+/// 一次查询的结果和证据
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub fids: Vec<String>,
+    /// 覆盖整个结果的证明（布尔查询为组合证明）；Manager 没有返回证明时为 None
+    pub proof: Option<Proof>,
+    /// 验证使用的根哈希（布尔查询为其中一个 storager 的根哈希）
+    pub root_hash: RootHash,
+    /// Manager 是否验证通过了完整的结果
+    pub verified: bool,
+    /// 每个 keyword 的结果，按 keyword 排序
+    ///
+    /// 单关键词查询只有一项；由单个 storager 证明整个表达式的布尔查询为空
+    pub per_keyword_breakdown: Vec<KeywordResult>,
+}
+
+/// 查询中一个 keyword 的结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeywordResult {
+    pub keyword: String,
+    pub fids: Vec<String>,
+    /// keyword 的查询证明（被过滤器排除的 keyword 没有证明）
+    pub proof: Option<Proof>,
+    /// 证明对应的根哈希
+    pub root_hash: RootHash,
+    /// 读取该 keyword 的 storager（单关键词查询时为空）
+    pub storager: String,
+    /// 被 storager 的 keyword 过滤器排除，按空结果参与求值，没有不存在证明
+    pub filtered: bool,
+}
+
+impl QueryResult {
+    /// 布尔查询的响应
+    pub(crate) fn from_response(response: QueryResponse) -> Self {
+        QueryResult {
+            fids: response.fids,
+            proof: response.proof.and_then(|proof| Proof::try_from(proof).ok()),
+            root_hash: response.root_hash,
+            verified: response.verified,
+            per_keyword_breakdown: response
+                .keyword_breakdown
+                .into_iter()
+                .map(KeywordResult::from)
+                .collect(),
+        }
+    }
+
+    /// 单关键词查询的响应，结果本身就是该 keyword 的结果
+    pub(crate) fn from_keyword_response(keyword: &str, response: QueryResponse) -> Self {
+        let mut result = Self::from_response(response);
+        result.per_keyword_breakdown = vec![KeywordResult {
+            keyword: keyword.to_string(),
+            fids: result.fids.clone(),
+            proof: result.proof.clone(),
+            root_hash: result.root_hash.clone(),
+            storager: String::new(),
+            filtered: false,
+        }];
+        result
+    }
+}
+
+impl From<KeywordBreakdown> for KeywordResult {
+    fn from(breakdown: KeywordBreakdown) -> Self {
+        KeywordResult {
+            keyword: breakdown.keyword,
+            fids: breakdown.fids,
+            proof: breakdown
+                .proof
+                .and_then(|proof| Proof::try_from(proof).ok()),
+            root_hash: breakdown.root_hash,
+            storager: breakdown.storager,
+            filtered: breakdown.filtered,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_response_keeps_evidence() {
+        let response = QueryResponse {
+            fids: vec!["f1".to_string()],
+            proof: Some(Proof::Mpt(vec![1, 2]).into()),
+            root_hash: vec![7; 32],
+            verified: true,
+            keyword_breakdown: vec![
+                KeywordBreakdown {
+                    keyword: "go".to_string(),
+                    storager: "s2".to_string(),
+                    filtered: true,
+                    ..Default::default()
+                },
+                KeywordBreakdown {
+                    keyword: "rust".to_string(),
+                    fids: vec!["f1".to_string()],
+                    proof: Some(Proof::Mpt(vec![1, 2]).into()),
+                    root_hash: vec![7; 32],
+                    storager: "s1".to_string(),
+                    filtered: false,
+                },
+            ],
+            ..Default::default()
+        };
+        let result = QueryResult::from_response(response);
+        assert_eq!(result.proof, Some(Proof::Mpt(vec![1, 2])));
+        assert_eq!(result.per_keyword_breakdown.len(), 2);
+        assert!(result.per_keyword_breakdown[0].filtered);
+        assert_eq!(result.per_keyword_breakdown[0].proof, None);
+        assert_eq!(result.per_keyword_breakdown[1].storager, "s1");
+
+        // 可以序列化后保存
+        let json = serde_json::to_string(&result).unwrap();
+        assert_eq!(serde_json::from_str::<QueryResult>(&json).unwrap(), result);
+    }
+
+    #[test]
+    fn test_keyword_response_is_its_own_breakdown() {
+        let response = QueryResponse {
+            fids: vec!["f1".to_string()],
+            root_hash: vec![7; 32],
+            verified: false,
+            ..Default::default()
+        };
+        let result = QueryResult::from_keyword_response("rust", response);
+        assert!(!result.verified);
+        assert_eq!(result.per_keyword_breakdown.len(), 1);
+        assert_eq!(result.per_keyword_breakdown[0].keyword, "rust");
+        assert_eq!(result.per_keyword_breakdown[0].fids, result.fids);
+    }
+}
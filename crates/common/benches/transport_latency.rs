@@ -0,0 +1,300 @@
+//! 回环 TCP 与 Unix domain socket 的 RPC 往返延迟对比
+//!
+//! 启动一个只返回固定结果的 StoragerService，分别通过 `127.0.0.1` 和
+//! `unix:` 地址发送相同数量的 Query 请求，输出平均延迟和 p50 / p99。
+//!
+//! ```bash
+//! cargo bench -p common --bench transport_latency
+//! # 自定义请求数量
+//! TRANSPORT_BENCH_REQUESTS=20000 cargo bench -p common --bench transport_latency
+//! ```
+
+use common::net::{connect, serve_all, ListenConfig};
+use common::rpc::storager_service_client::StoragerServiceClient;
+use common::rpc::storager_service_server::{StoragerService, StoragerServiceServer};
+use common::rpc::{
+    BulkAddRecord, FlushRequest, FlushResponse, GetProofRequest, GetProofResponse,
+    KeywordFilterRequest, KeywordFilterResponse, ListExpiredRequest, ListExpiredResponse,
+    ListKeywordsRequest, ListKeywordsResponse, ListNamespacesRequest, ListNamespacesResponse,
+    ListRootHistoryRequest, ListRootHistoryResponse, MigrateInResponse, MigrateOutRequest,
+    MigrateOutResponse, MigrationEntry, PrefixQueryRequest, ProveDifferenceRequest,
+    ProveDifferenceResponse, PruneRequest, PruneResponse, QueryAtRootRequest, QueryAtRootResponse,
+    RangeQueryRequest, StoragerAddRequest, StoragerAddResponse, StoragerApproxCountRequest,
+    StoragerApproxCountResponse, StoragerBatchAddRequest, StoragerBatchAddResponse,
+    StoragerBooleanQueryRequest, StoragerBooleanQueryResponse, StoragerBulkAddResponse,
+    StoragerDeleteRequest, StoragerDeleteResponse, StoragerHealthRequest, StoragerHealthResponse,
+    StoragerPrefixQueryResponse, StoragerQueryChunk, StoragerQueryRequest, StoragerQueryResponse,
+    StoragerRangeQueryResponse, StoragerStatsRequest, StoragerStatsResponse,
+};
+use std::time::{Duration, Instant};
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+/// 不做任何存储工作的 storager，只用于测量传输开销
+#[derive(Clone)]
+struct EchoStorager;
+
+#[tonic::async_trait]
+impl StoragerService for EchoStorager {
+    async fn add(
+        &self,
+        _request: Request<StoragerAddRequest>,
+    ) -> Result<Response<StoragerAddResponse>, Status> {
+        Ok(Response::new(StoragerAddResponse::default()))
+    }
+
+    async fn batch_add(
+        &self,
+        _request: Request<StoragerBatchAddRequest>,
+    ) -> Result<Response<StoragerBatchAddResponse>, Status> {
+        Ok(Response::new(StoragerBatchAddResponse::default()))
+    }
+
+    async fn query(
+        &self,
+        request: Request<StoragerQueryRequest>,
+    ) -> Result<Response<StoragerQueryResponse>, Status> {
+        Ok(Response::new(StoragerQueryResponse {
+            fids: vec![request.into_inner().keyword],
+            proof: Some(common::Proof::Mpt(vec![0u8; 256]).into()),
+            epoch: 0,
+            total_count: 1,
+            next_page_token: String::new(),
+        }))
+    }
+
+    async fn prove_difference(
+        &self,
+        _request: Request<ProveDifferenceRequest>,
+    ) -> Result<Response<ProveDifferenceResponse>, Status> {
+        Ok(Response::new(ProveDifferenceResponse::default()))
+    }
+
+    async fn boolean_query(
+        &self,
+        _request: Request<StoragerBooleanQueryRequest>,
+    ) -> Result<Response<StoragerBooleanQueryResponse>, Status> {
+        Ok(Response::new(StoragerBooleanQueryResponse::default()))
+    }
+
+    async fn delete(
+        &self,
+        _request: Request<StoragerDeleteRequest>,
+    ) -> Result<Response<StoragerDeleteResponse>, Status> {
+        Ok(Response::new(StoragerDeleteResponse::default()))
+    }
+
+    async fn approx_count(
+        &self,
+        _request: Request<StoragerApproxCountRequest>,
+    ) -> Result<Response<StoragerApproxCountResponse>, Status> {
+        Ok(Response::new(StoragerApproxCountResponse::default()))
+    }
+
+    async fn health(
+        &self,
+        _request: Request<StoragerHealthRequest>,
+    ) -> Result<Response<StoragerHealthResponse>, Status> {
+        Ok(Response::new(StoragerHealthResponse::default()))
+    }
+
+    async fn flush(
+        &self,
+        _request: Request<FlushRequest>,
+    ) -> Result<Response<FlushResponse>, Status> {
+        Ok(Response::new(FlushResponse::default()))
+    }
+
+    async fn stats(
+        &self,
+        _request: Request<StoragerStatsRequest>,
+    ) -> Result<Response<StoragerStatsResponse>, Status> {
+        Ok(Response::new(StoragerStatsResponse::default()))
+    }
+
+    async fn keyword_filter(
+        &self,
+        _request: Request<KeywordFilterRequest>,
+    ) -> Result<Response<KeywordFilterResponse>, Status> {
+        Ok(Response::new(KeywordFilterResponse::default()))
+    }
+
+    async fn list_expired(
+        &self,
+        _request: Request<ListExpiredRequest>,
+    ) -> Result<Response<ListExpiredResponse>, Status> {
+        Ok(Response::new(ListExpiredResponse::default()))
+    }
+
+    async fn list_keywords(
+        &self,
+        _request: Request<ListKeywordsRequest>,
+    ) -> Result<Response<ListKeywordsResponse>, Status> {
+        Ok(Response::new(ListKeywordsResponse::default()))
+    }
+
+    async fn list_namespaces(
+        &self,
+        _request: Request<ListNamespacesRequest>,
+    ) -> Result<Response<ListNamespacesResponse>, Status> {
+        Ok(Response::new(ListNamespacesResponse::default()))
+    }
+
+    async fn migrate_out(
+        &self,
+        _request: Request<MigrateOutRequest>,
+    ) -> Result<Response<MigrateOutResponse>, Status> {
+        Ok(Response::new(MigrateOutResponse::default()))
+    }
+
+    async fn migrate_in(
+        &self,
+        _request: Request<tonic::Streaming<MigrationEntry>>,
+    ) -> Result<Response<MigrateInResponse>, Status> {
+        Ok(Response::new(MigrateInResponse::default()))
+    }
+
+    async fn bulk_add(
+        &self,
+        _request: Request<tonic::Streaming<BulkAddRecord>>,
+    ) -> Result<Response<StoragerBulkAddResponse>, Status> {
+        Ok(Response::new(StoragerBulkAddResponse::default()))
+    }
+
+    async fn range_query(
+        &self,
+        _request: Request<RangeQueryRequest>,
+    ) -> Result<Response<StoragerRangeQueryResponse>, Status> {
+        Ok(Response::new(StoragerRangeQueryResponse::default()))
+    }
+
+    async fn query_by_prefix(
+        &self,
+        _request: Request<PrefixQueryRequest>,
+    ) -> Result<Response<StoragerPrefixQueryResponse>, Status> {
+        Ok(Response::new(StoragerPrefixQueryResponse::default()))
+    }
+
+    async fn prune(
+        &self,
+        _request: Request<PruneRequest>,
+    ) -> Result<Response<PruneResponse>, Status> {
+        Ok(Response::new(PruneResponse::default()))
+    }
+
+    async fn query_at_root(
+        &self,
+        _request: Request<QueryAtRootRequest>,
+    ) -> Result<Response<QueryAtRootResponse>, Status> {
+        Ok(Response::new(QueryAtRootResponse::default()))
+    }
+
+    async fn list_root_history(
+        &self,
+        _request: Request<ListRootHistoryRequest>,
+    ) -> Result<Response<ListRootHistoryResponse>, Status> {
+        Ok(Response::new(ListRootHistoryResponse::default()))
+    }
+    async fn get_proof(
+        &self,
+        _request: Request<GetProofRequest>,
+    ) -> Result<Response<GetProofResponse>, Status> {
+        Ok(Response::new(GetProofResponse::default()))
+    }
+
+    type QueryStreamStream = tokio_stream::Empty<Result<StoragerQueryChunk, Status>>;
+
+    async fn query_stream(
+        &self,
+        _request: Request<StoragerQueryRequest>,
+    ) -> Result<Response<Self::QueryStreamStream>, Status> {
+        Ok(Response::new(tokio_stream::empty()))
+    }
+}
+
+async fn measure(addr: &str, requests: usize) -> Vec<Duration> {
+    let mut client = StoragerServiceClient::new(connect(addr).await.unwrap());
+
+    // 预热：建立 HTTP/2 连接
+    for _ in 0..100 {
+        client
+            .query(StoragerQueryRequest {
+                keyword: "warmup".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+    }
+
+    let mut samples = Vec::with_capacity(requests);
+    for i in 0..requests {
+        let start = Instant::now();
+        client
+            .query(StoragerQueryRequest {
+                keyword: format!("keyword{}", i),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        samples.push(start.elapsed());
+    }
+    samples.sort();
+    samples
+}
+
+fn report(name: &str, samples: &[Duration]) -> Duration {
+    let mean = samples.iter().sum::<Duration>() / samples.len() as u32;
+    let percentile = |p: f64| samples[((samples.len() - 1) as f64 * p) as usize];
+    println!(
+        "{:>6} {:>12.1} {:>12.1} {:>12.1}",
+        name,
+        mean.as_secs_f64() * 1e6,
+        percentile(0.5).as_secs_f64() * 1e6,
+        percentile(0.99).as_secs_f64() * 1e6
+    );
+    mean
+}
+
+#[tokio::main]
+async fn main() {
+    let requests = std::env::var("TRANSPORT_BENCH_REQUESTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(10_000);
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let socket = std::env::temp_dir().join(format!("transport_bench_{}.sock", std::process::id()));
+    let spec = format!("127.0.0.1:{},unix:{}", port, socket.display());
+    let listen = ListenConfig::parse(&spec, port).unwrap();
+
+    let service = StoragerServiceServer::new(EchoStorager);
+    tokio::spawn(async move {
+        serve_all(&listen, || Server::builder().add_service(service.clone()))
+            .await
+            .unwrap();
+    });
+    // 等待监听套接字就绪
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    println!("StoragerService Query round trip, {} requests", requests);
+    println!(
+        "{:>6} {:>12} {:>12} {:>12}",
+        "", "mean (us)", "p50 (us)", "p99 (us)"
+    );
+
+    let tcp = report(
+        "tcp",
+        &measure(&format!("http://127.0.0.1:{}", port), requests).await,
+    );
+    let uds = report(
+        "uds",
+        &measure(&format!("unix:{}", socket.display()), requests).await,
+    );
+    println!("UDS speedup: {:.2}x", tcp.as_secs_f64() / uds.as_secs_f64());
+
+    std::fs::remove_file(&socket).ok();
+}
@@ -0,0 +1,62 @@
+//! 查询准入控制的错误类型
+//!
+//! Manager 拒绝超出代价预算的查询时返回 `RESOURCE_EXHAUSTED` 状态，
+//! 并在 metadata 中携带估计代价和预算，客户端可以据此还原出 [`QueryRejected`]。
+
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Status};
+
+/// 拒绝响应中携带估计代价的 metadata 键
+pub const COST_METADATA_KEY: &str = "x-query-cost";
+/// 拒绝响应中携带代价预算的 metadata 键
+pub const BUDGET_METADATA_KEY: &str = "x-query-budget";
+
+/// 查询因估计代价超出预算而被拒绝
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryRejected {
+    /// 估计代价
+    pub estimated_cost: u64,
+    /// 代价预算
+    pub budget: u64,
+}
+
+impl QueryRejected {
+    /// 从 gRPC 状态中还原拒绝原因（不是准入拒绝时返回 None）
+    pub fn from_status(status: &Status) -> Option<Self> {
+        if status.code() != Code::ResourceExhausted {
+            return None;
+        }
+        let read =
+            |key: &str| -> Option<u64> { status.metadata().get(key)?.to_str().ok()?.parse().ok() };
+        Some(QueryRejected {
+            estimated_cost: read(COST_METADATA_KEY)?,
+            budget: read(BUDGET_METADATA_KEY)?,
+        })
+    }
+}
+
+impl std::fmt::Display for QueryRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Query cost {} exceeds budget {}; use ApproxCount for a count-only answer, \
+             narrow the query (pagination), or opt in to background execution",
+            self.estimated_cost, self.budget
+        )
+    }
+}
+
+impl std::error::Error for QueryRejected {}
+
+impl From<QueryRejected> for Status {
+    fn from(rejected: QueryRejected) -> Status {
+        let mut status = Status::resource_exhausted(rejected.to_string());
+        let metadata = status.metadata_mut();
+        metadata.insert(
+            COST_METADATA_KEY,
+            MetadataValue::from(rejected.estimated_cost),
+        );
+        metadata.insert(BUDGET_METADATA_KEY, MetadataValue::from(rejected.budget));
+        status
+    }
+}
@@ -0,0 +1,81 @@
+//! ADS 写操作的错误
+//!
+//! storager 的 ADS 实现用 [`AdsError`] 报告无法完成的写操作，
+//! RPC 处理函数把它转换为对应状态码的 [`Status`] 返回给 Manager。
+
+use crate::ErrorKind;
+use tonic::{Code, Status};
+
+/// ADS 写操作失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdsError {
+    /// 元素已经在数据结构中，但不在 keyword 的 fid 列表里（通常是元素冲突）
+    DuplicateElement { keyword: String, fid: String },
+    /// keyword 没有任何 fid
+    MissingKeyword(String),
+    /// fid 不在 keyword 下
+    MissingFid { keyword: String, fid: String },
+    /// 批量操作没有给出 keyword
+    EmptyBatch,
+    /// 持有 ADS 锁的线程 panic 过，状态可能不完整
+    LockPoisoned,
+    /// 底层数据结构（MPT、累加器、节点数据库）执行失败
+    Backend(String),
+}
+
+impl std::fmt::Display for AdsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdsError::DuplicateElement { keyword, fid } => write!(
+                f,
+                "fid '{}' collides with an element already under keyword '{}'",
+                fid, keyword
+            ),
+            AdsError::MissingKeyword(keyword) => write!(f, "keyword '{}' has no fids", keyword),
+            AdsError::MissingFid { keyword, fid } => {
+                write!(f, "fid '{}' is not under keyword '{}'", fid, keyword)
+            }
+            AdsError::EmptyBatch => write!(f, "batch operation requires at least one keyword"),
+            AdsError::LockPoisoned => write!(f, "ADS lock poisoned by a panicked writer"),
+            AdsError::Backend(message) => write!(f, "ADS backend error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for AdsError {}
+
+impl From<AdsError> for Status {
+    fn from(error: AdsError) -> Status {
+        let (kind, code) = match error {
+            AdsError::DuplicateElement { .. } => (ErrorKind::Storage, Code::AlreadyExists),
+            AdsError::MissingKeyword(_) | AdsError::MissingFid { .. } => {
+                (ErrorKind::Storage, Code::NotFound)
+            }
+            AdsError::EmptyBatch => (ErrorKind::InvalidRequest, Code::InvalidArgument),
+            AdsError::LockPoisoned | AdsError::Backend(_) => (ErrorKind::Storage, Code::Internal),
+        };
+        kind.status(code, error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_codes() {
+        let missing = AdsError::MissingFid {
+            keyword: "rust".to_string(),
+            fid: "f1".to_string(),
+        };
+        let status = Status::from(missing.clone());
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(status.message(), missing.to_string());
+        assert_eq!(ErrorKind::from_status(&status), Some(ErrorKind::Storage));
+        assert_eq!(
+            Status::from(AdsError::EmptyBatch).code(),
+            Code::InvalidArgument
+        );
+        assert_eq!(Status::from(AdsError::LockPoisoned).code(), Code::Internal);
+    }
+}
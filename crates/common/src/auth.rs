@@ -0,0 +1,59 @@
+//! API token 的传递方式
+//!
+//! 客户端在 `authorization` metadata 中以 `Bearer <token>` 的形式携带 token，
+//! Manager 只保存 token 的 SHA-256 摘要（见 [`token_digest`]），访问控制文件泄露时不会暴露 token 本身。
+
+use sha2::{Digest, Sha256};
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::Request;
+
+/// 携带 token 的 metadata 键
+pub const AUTHORIZATION_METADATA_KEY: &str = "authorization";
+
+const BEARER_PREFIX: &str = "Bearer ";
+
+/// token 的十六进制 SHA-256 摘要
+pub fn token_digest(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// 在请求中附加 token；token 含有 metadata 不允许的字符时返回错误
+pub fn attach_token<T>(request: &mut Request<T>, token: &str) -> Result<(), String> {
+    let value = MetadataValue::try_from(format!("{}{}", BEARER_PREFIX, token))
+        .map_err(|_| "API token contains characters not allowed in metadata".to_string())?;
+    request
+        .metadata_mut()
+        .insert(AUTHORIZATION_METADATA_KEY, value);
+    Ok(())
+}
+
+/// 读取请求携带的 token，没有或格式不对时返回 None
+pub fn bearer_token(metadata: &MetadataMap) -> Option<&str> {
+    metadata
+        .get(AUTHORIZATION_METADATA_KEY)?
+        .to_str()
+        .ok()?
+        .strip_prefix(BEARER_PREFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_round_trip() {
+        let mut request = Request::new(());
+        assert_eq!(bearer_token(request.metadata()), None);
+        attach_token(&mut request, "secret").unwrap();
+        assert_eq!(bearer_token(request.metadata()), Some("secret"));
+        assert!(attach_token(&mut request, "bad\ntoken").is_err());
+
+        assert_eq!(
+            token_digest("secret"),
+            "2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b"
+        );
+    }
+}
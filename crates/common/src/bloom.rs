@@ -0,0 +1,196 @@
+//! keyword 集合的 Bloom filter
+//!
+//! storager 为每个命名空间的 keyword 集合构建过滤器并发给 Manager（`KeywordFilter` RPC），
+//! Manager 在分发布尔查询的子查询之前用它排除肯定不存在的 keyword。过滤器只会误报
+//! （不存在的 keyword 被判为可能存在），不会漏报。
+//!
+//! 位数组长度和哈希函数个数由元素数量和目标误报率决定；第 i 个哈希函数取
+//! `h1 + i * h2`（double hashing），h1、h2 来自 keyword 的 SHA256。
+//!
+//! storager 在同一把 ADS 读锁下读取 keyword、根哈希和版本号来构建 [`KeywordFilterSnapshot`]，
+//! 因此过滤器包含该根哈希下的所有 keyword；Manager 只在它与自己发布的根哈希相同时使用。
+//! [`KeywordFilterCache`] 在写入改变版本号或根哈希之前复用同一份过滤器。
+
+use crate::RootHash;
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, Mutex};
+
+/// 默认的目标误报率
+pub const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// 哈希函数个数的上限
+const MAX_HASHES: u32 = 16;
+
+/// Bloom filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    /// 为 `items` 个元素创建误报率约为 `false_positive_rate` 的空过滤器
+    pub fn new(items: usize, false_positive_rate: f64) -> Self {
+        let items = items.max(1) as f64;
+        let rate = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-items * rate.ln() / (ln2 * ln2)).ceil().max(8.0) as usize;
+        let hashes = ((bits as f64 / items) * ln2).round() as u32;
+        BloomFilter {
+            bits: vec![0; bits.div_ceil(8)],
+            hashes: hashes.clamp(1, MAX_HASHES),
+        }
+    }
+
+    /// 由 keyword 集合构建过滤器
+    pub fn from_keywords<'a>(
+        keywords: impl ExactSizeIterator<Item = &'a String>,
+        false_positive_rate: f64,
+    ) -> Self {
+        let mut filter = Self::new(keywords.len(), false_positive_rate);
+        for keyword in keywords {
+            filter.insert(keyword);
+        }
+        filter
+    }
+
+    /// 从 [`bits`](Self::bits) 和 [`hashes`](Self::hashes) 恢复过滤器
+    pub fn from_parts(bits: Vec<u8>, hashes: u32) -> Option<Self> {
+        if bits.is_empty() || hashes == 0 || hashes > MAX_HASHES {
+            return None;
+        }
+        Some(BloomFilter { bits, hashes })
+    }
+
+    /// 插入一个元素
+    pub fn insert(&mut self, item: &str) {
+        for index in self.indexes(item) {
+            self.bits[index / 8] |= 1 << (index % 8);
+        }
+    }
+
+    /// 元素可能在集合中时返回 true；返回 false 时元素肯定不在集合中
+    pub fn contains(&self, item: &str) -> bool {
+        self.indexes(item)
+            .all(|index| self.bits[index / 8] & (1 << (index % 8)) != 0)
+    }
+
+    /// 位数组
+    pub fn bits(&self) -> &[u8] {
+        &self.bits
+    }
+
+    /// 哈希函数个数
+    pub fn hashes(&self) -> u32 {
+        self.hashes
+    }
+
+    fn indexes(&self, item: &str) -> impl Iterator<Item = usize> {
+        let digest = Sha256::digest(item.as_bytes());
+        let h1 = u64::from_le_bytes(digest[..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(digest[8..16].try_into().unwrap());
+        let len = (self.bits.len() * 8) as u64;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+}
+
+/// 某个根哈希下的 keyword 过滤器
+#[derive(Debug)]
+pub struct KeywordFilterSnapshot {
+    pub filter: BloomFilter,
+    pub root_hash: RootHash,
+    pub epoch: u64,
+    /// 插入过滤器的 keyword 数量
+    pub keywords: usize,
+}
+
+impl KeywordFilterSnapshot {
+    /// 由 `root_hash` 下的全部 keyword 构建
+    pub fn build(keywords: &[String], root_hash: RootHash, epoch: u64) -> Self {
+        KeywordFilterSnapshot {
+            filter: BloomFilter::from_keywords(keywords.iter(), DEFAULT_FALSE_POSITIVE_RATE),
+            root_hash,
+            epoch,
+            keywords: keywords.len(),
+        }
+    }
+}
+
+/// 最近一次构建的过滤器
+#[derive(Default)]
+pub struct KeywordFilterCache {
+    latest: Mutex<Option<Arc<KeywordFilterSnapshot>>>,
+}
+
+impl KeywordFilterCache {
+    /// 版本号和根哈希都没有变化时返回缓存的过滤器，否则用 `build` 重新构建
+    pub fn get_or_build<E>(
+        &self,
+        epoch: u64,
+        root_hash: &RootHash,
+        build: impl FnOnce() -> Result<KeywordFilterSnapshot, E>,
+    ) -> Result<Arc<KeywordFilterSnapshot>, E> {
+        let mut latest = self.latest.lock().unwrap();
+        if let Some(snapshot) = latest.as_ref() {
+            if snapshot.epoch == epoch && snapshot.root_hash == *root_hash {
+                return Ok(snapshot.clone());
+            }
+        }
+        let snapshot = Arc::new(build()?);
+        *latest = Some(snapshot.clone());
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_false_negatives() {
+        let keywords: Vec<String> = (0..1000).map(|i| format!("kw{}", i)).collect();
+        let filter = BloomFilter::from_keywords(keywords.iter(), DEFAULT_FALSE_POSITIVE_RATE);
+        assert!(keywords.iter().all(|keyword| filter.contains(keyword)));
+
+        // 误报率接近目标值
+        let false_positives = (0..10_000)
+            .filter(|i| filter.contains(&format!("missing{}", i)))
+            .count();
+        assert!(false_positives < 300, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn test_parts_round_trip() {
+        let mut filter = BloomFilter::new(0, DEFAULT_FALSE_POSITIVE_RATE);
+        assert!(!filter.contains("rust"));
+        filter.insert("rust");
+
+        let restored = BloomFilter::from_parts(filter.bits().to_vec(), filter.hashes()).unwrap();
+        assert_eq!(restored, filter);
+        assert!(restored.contains("rust"));
+        assert!(BloomFilter::from_parts(Vec::new(), 3).is_none());
+        assert!(BloomFilter::from_parts(vec![0; 8], 0).is_none());
+    }
+
+    #[test]
+    fn test_cache_rebuilds_after_writes() {
+        let cache = KeywordFilterCache::default();
+        let keywords = vec!["rust".to_string()];
+        let root = vec![1u8; 32];
+        let first = cache
+            .get_or_build(1, &root, || {
+                Ok::<_, ()>(KeywordFilterSnapshot::build(&keywords, root.clone(), 1))
+            })
+            .unwrap();
+        assert!(first.filter.contains("rust"));
+        assert_eq!(first.keywords, 1);
+        let again = cache
+            .get_or_build(1, &root, || Err(()))
+            .expect("unchanged state reuses the filter");
+        assert!(Arc::ptr_eq(&first, &again));
+
+        // 版本号或根哈希变化后重新构建
+        assert!(cache.get_or_build(2, &root, || Err(())).is_err());
+        assert!(cache.get_or_build(1, &vec![2u8; 32], || Err(())).is_err());
+    }
+}
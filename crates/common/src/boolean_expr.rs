@@ -80,25 +80,49 @@ impl BooleanExpr {
     ) -> HashSet<String> {
         match self {
             BooleanExpr::Keyword(kw) => keyword_results.get(kw).cloned().unwrap_or_default(),
-            BooleanExpr::And(left, right) => {
-                let left_result = left.evaluate(keyword_results);
-                let right_result = right.evaluate(keyword_results);
-                left_result.intersection(&right_result).cloned().collect()
-            }
+            BooleanExpr::And(left, right) => match (&**left, &**right) {
+                // A AND NOT B: 差集
+                (included, BooleanExpr::Not(excluded)) | (BooleanExpr::Not(excluded), included) => {
+                    let included = included.evaluate(keyword_results);
+                    let excluded = excluded.evaluate(keyword_results);
+                    included.difference(&excluded).cloned().collect()
+                }
+                _ => {
+                    let left_result = left.evaluate(keyword_results);
+                    let right_result = right.evaluate(keyword_results);
+                    left_result.intersection(&right_result).cloned().collect()
+                }
+            },
             BooleanExpr::Or(left, right) => {
                 let left_result = left.evaluate(keyword_results);
                 let right_result = right.evaluate(keyword_results);
                 left_result.union(&right_result).cloned().collect()
             }
-            BooleanExpr::Not(expr) => {
-                // For NOT operation, return empty set
-                // (NOT operation is tricky without a universal set)
-                let _result = expr.evaluate(keyword_results);
+            BooleanExpr::Not(_) => {
+                // 没有全集时无法求补集，单独的 NOT 返回空集；
+                // 与 AND 组合时按差集求值（见上）
                 HashSet::new()
             }
         }
     }
 
+    /// 表达式为 `A AND NOT B`（或 `NOT B AND A`）且两侧都是单个关键词时，返回 (A, B)
+    ///
+    /// 这类查询可以由持有 A 的 storager 生成可验证的差集证明
+    pub fn as_keyword_difference(&self) -> Option<(&str, &str)> {
+        let BooleanExpr::And(left, right) = self else {
+            return None;
+        };
+        match (&**left, &**right) {
+            (BooleanExpr::Keyword(included), BooleanExpr::Not(excluded))
+            | (BooleanExpr::Not(excluded), BooleanExpr::Keyword(included)) => match &**excluded {
+                BooleanExpr::Keyword(excluded) => Some((included, excluded)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     /// 转换为字符串表示
     pub fn to_string(&self) -> String {
         match self {
@@ -407,6 +431,37 @@ mod tests {
         assert!(result.contains("file2"));
     }
 
+    #[test]
+    fn test_evaluate_and_not() {
+        let mut results = std::collections::HashMap::new();
+        results.insert(
+            "rust".to_string(),
+            HashSet::from(["file1".to_string(), "file2".to_string()]),
+        );
+        results.insert("python".to_string(), HashSet::from(["file2".to_string()]));
+
+        for query in ["rust AND NOT python", "NOT python AND rust"] {
+            let expr = parse_boolean_expr(query).unwrap();
+            assert_eq!(expr.as_keyword_difference(), Some(("rust", "python")));
+            assert_eq!(
+                expr.evaluate(&results),
+                HashSet::from(["file1".to_string()])
+            );
+        }
+
+        // 单独的 NOT 没有全集，结果为空
+        let expr = parse_boolean_expr("NOT python").unwrap();
+        assert!(expr.evaluate(&results).is_empty());
+        assert_eq!(expr.as_keyword_difference(), None);
+
+        let expr = parse_boolean_expr("rust AND NOT (python OR go)").unwrap();
+        assert_eq!(expr.as_keyword_difference(), None);
+        assert_eq!(
+            expr.evaluate(&results),
+            HashSet::from(["file1".to_string()])
+        );
+    }
+
     #[test]
     fn test_evaluate_or() {
         let expr = parse_boolean_expr("rust OR python").unwrap();
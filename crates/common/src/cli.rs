@@ -0,0 +1,184 @@
+//! manager 和 storager 共用的命令行参数
+//!
+//! 每个参数都可以通过 `DSS_` 开头的环境变量设置，命令行优先于环境变量。
+//! 三个二进制（manager、storager、client）都接受 `--config <file>`，读取
+//! [`SystemConfig`](crate::SystemConfig) JSON 中的地址和 ADS 模式，
+//! 优先级低于命令行和环境变量。
+
+use crate::net::validate_address;
+use crate::telemetry::LogConfig;
+use crate::tls::TlsConfig;
+use crate::transport::{Compression, TransportConfig, DEFAULT_MAX_MESSAGE_SIZE};
+use crate::AdsMode;
+use clap::Args;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// 按注册表解析 ADS 模式（内置模式以及已注册的第三方模式）
+pub fn parse_ads_mode(name: &str) -> Result<AdsMode, String> {
+    AdsMode::from_name(name).ok_or_else(|| format!("unknown ADS mode '{}'", name))
+}
+
+/// 解析 storager 地址：`url` 或 `name=url`
+pub fn parse_storager_addr(entry: &str) -> Result<String, String> {
+    let entry = entry.trim();
+    let addr = entry.split_once('=').map_or(entry, |(_, addr)| addr.trim());
+    validate_address(addr)?;
+    Ok(entry.to_string())
+}
+
+fn parse_compression(name: &str) -> Result<String, String> {
+    Compression::parse(name).map(|_| name.to_string())
+}
+
+/// gRPC 传输参数：消息大小、压缩、keepalive 和 TLS
+#[derive(Debug, Clone, Args)]
+pub struct TransportArgs {
+    /// Largest gRPC message sent or accepted, in MiB
+    #[arg(long, env = "DSS_MAX_MESSAGE_MIB", value_name = "MIB",
+          default_value_t = DEFAULT_MAX_MESSAGE_SIZE >> 20)]
+    pub max_message_mib: usize,
+
+    /// Compress outgoing messages: none|gzip|zstd
+    #[arg(long, env = "DSS_COMPRESSION", value_name = "ALG", default_value = "none",
+          value_parser = parse_compression)]
+    pub compression: String,
+
+    /// HTTP/2 keepalive ping interval in seconds, 0 disables
+    #[arg(long, env = "DSS_KEEPALIVE", value_name = "SECS", default_value_t = 0)]
+    pub keepalive: u64,
+
+    /// PEM certificate for serving and for connecting to other nodes
+    #[arg(long, env = "DSS_TLS_CERT", value_name = "PATH")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key for --tls-cert
+    #[arg(long, env = "DSS_TLS_KEY", value_name = "PATH")]
+    pub tls_key: Option<PathBuf>,
+
+    /// PEM CA used to verify servers and client certificates
+    #[arg(long, env = "DSS_TLS_CA", value_name = "PATH")]
+    pub tls_ca: Option<PathBuf>,
+
+    /// Expected name in server certificates (default: address host)
+    #[arg(long, env = "DSS_TLS_DOMAIN", value_name = "NAME")]
+    pub tls_domain: Option<String>,
+
+    /// Require clients to present a certificate signed by --tls-ca
+    #[arg(long, env = "DSS_TLS_CLIENT_AUTH")]
+    pub tls_client_auth: bool,
+}
+
+impl TransportArgs {
+    /// 构造传输配置，给出证书、私钥或 CA 中任意一项时开启 TLS
+    pub fn config(&self) -> Result<TransportConfig, String> {
+        let mut transport = TransportConfig::default()
+            .with_max_message_size(self.max_message_mib << 20)
+            .with_compression(Compression::parse(&self.compression)?)
+            .with_keepalive((self.keepalive > 0).then(|| Duration::from_secs(self.keepalive)));
+        if self.tls_cert.is_some() || self.tls_key.is_some() || self.tls_ca.is_some() {
+            let mut tls = TlsConfig::load(
+                self.tls_cert.as_deref(),
+                self.tls_key.as_deref(),
+                self.tls_ca.as_deref(),
+            )?
+            .require_client_cert(self.tls_client_auth);
+            if let Some(domain) = &self.tls_domain {
+                tls = tls.with_domain(domain.clone());
+            }
+            transport = transport.with_tls(Some(tls));
+        }
+        Ok(transport)
+    }
+}
+
+/// 日志参数
+#[derive(Debug, Clone, Args)]
+pub struct LogArgs {
+    /// Log filter, e.g. debug or info,manager=debug (default: RUST_LOG or info)
+    #[arg(long, env = "DSS_LOG_LEVEL", value_name = "FILTER")]
+    pub log_level: Option<String>,
+
+    /// Write logs as one JSON object per line
+    #[arg(long, env = "DSS_LOG_JSON")]
+    pub log_json: bool,
+}
+
+impl LogArgs {
+    pub fn config(&self) -> LogConfig {
+        LogConfig {
+            level: self.log_level.clone(),
+            json: self.log_json,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Debug, Parser)]
+    struct TestCli {
+        #[arg(long, value_parser = parse_ads_mode)]
+        ads_mode: Option<AdsMode>,
+        #[arg(long, value_delimiter = ',', value_parser = parse_storager_addr)]
+        storagers: Vec<String>,
+        #[command(flatten)]
+        transport: TransportArgs,
+        #[command(flatten)]
+        log: LogArgs,
+    }
+
+    #[test]
+    fn test_defaults() {
+        let cli = TestCli::try_parse_from(["test"]).unwrap();
+        let transport = cli.transport.config().unwrap();
+        assert_eq!(transport.max_message_size, DEFAULT_MAX_MESSAGE_SIZE);
+        assert_eq!(transport.compression, None);
+        assert_eq!(transport.keepalive_interval, None);
+        assert!(transport.tls.is_none());
+        assert!(!cli.log.config().json);
+    }
+
+    #[test]
+    fn test_parse_values() {
+        let cli = TestCli::try_parse_from([
+            "test",
+            "--ads-mode=mpt",
+            "--storagers",
+            "s1=http://10.0.0.5:50052, unix:/run/dss/s2.sock",
+            "--max-message-mib",
+            "256",
+            "--compression=zstd",
+            "--keepalive=30",
+            "--log-json",
+        ])
+        .unwrap();
+        assert_eq!(cli.ads_mode, Some(AdsMode::Mpt));
+        assert_eq!(
+            cli.storagers,
+            vec!["s1=http://10.0.0.5:50052", "unix:/run/dss/s2.sock"]
+        );
+        let transport = cli.transport.config().unwrap();
+        assert_eq!(transport.max_message_size, 256 << 20);
+        assert_eq!(transport.compression, Some(Compression::Zstd));
+        assert_eq!(transport.keepalive_interval, Some(Duration::from_secs(30)));
+        assert!(cli.log.config().json);
+    }
+
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        for args in [
+            &["test", "--ads-mode", "btree"][..],
+            &["test", "--storagers", "s1=unix:relative.sock"],
+            &["test", "--compression", "brotli"],
+            &["test", "--max-message-mib", "lots"],
+        ] {
+            assert!(TestCli::try_parse_from(args).is_err(), "{:?}", args);
+        }
+
+        let cli = TestCli::try_parse_from(["test", "--tls-cert", "/nonexistent.pem"]).unwrap();
+        assert!(cli.transport.config().is_err());
+    }
+}
@@ -0,0 +1,275 @@
+//! 可替换的时间源
+//!
+//! Manager / Storager 不直接读取系统时间，而是通过构造时注入的 [`Clock`]，
+//! 测试和确定性模拟可以使用 [`MockClock`] 或 [`SimulatedClock`] 控制时间。
+//!
+//! 持久化记录同时保存墙上时间和 [`LogicalClock`] 分配的逻辑序号：
+//! 墙上时间可能回拨或在节点间不一致，排序和比较应以逻辑序号为准。
+//!
+//! # 示例
+//!
+//! ```
+//! use common::clock::{Clock, LogicalClock, MockClock};
+//! use std::time::Duration;
+//!
+//! let clock = MockClock::new(Duration::from_secs(1_700_000_000));
+//! let logical = LogicalClock::new();
+//!
+//! let first = logical.stamp(&clock);
+//! clock.advance(Duration::from_millis(250));
+//! let second = logical.stamp(&clock);
+//!
+//! assert_eq!(second.wall_millis - first.wall_millis, 250);
+//! assert!(second > first);
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 时间源
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    /// 墙上时间（UNIX 纪元以来的时长）
+    fn wall_time(&self) -> Duration;
+
+    /// 单调时间（自某个固定起点以来的时长，只用于计算时间间隔）
+    fn monotonic(&self) -> Duration;
+
+    /// 墙上时间的毫秒数
+    fn unix_millis(&self) -> u64 {
+        self.wall_time().as_millis() as u64
+    }
+}
+
+/// 可以在组件之间共享的时间源
+pub type SharedClock = Arc<dyn Clock>;
+
+/// 默认时间源（系统时间）
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock::new())
+}
+
+/// 系统时间
+#[derive(Debug, Clone)]
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn wall_time(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// 手动控制的时间源
+///
+/// 时间只在调用 [`set`](Self::set) / [`advance`](Self::advance) 时变化，
+/// clone 出的实例共享同一时间
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    /// (墙上时间, 单调时间)
+    now: Arc<Mutex<(Duration, Duration)>>,
+}
+
+impl MockClock {
+    /// 以给定墙上时间创建，单调时间从 0 开始
+    pub fn new(wall_time: Duration) -> Self {
+        MockClock {
+            now: Arc::new(Mutex::new((wall_time, Duration::ZERO))),
+        }
+    }
+
+    /// 同时推进墙上时间和单调时间
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.now.lock().unwrap();
+        now.0 += by;
+        now.1 += by;
+    }
+
+    /// 设置墙上时间（可以回拨，用于模拟时钟跳变；单调时间不受影响）
+    pub fn set(&self, wall_time: Duration) {
+        self.now.lock().unwrap().0 = wall_time;
+    }
+}
+
+impl Clock for MockClock {
+    fn wall_time(&self) -> Duration {
+        self.now.lock().unwrap().0
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.now.lock().unwrap().1
+    }
+}
+
+/// 确定性模拟时间源
+///
+/// 每次读取时间都会推进固定步长，
+/// 相同的操作序列总是观察到相同的时间，不依赖真实耗时
+#[derive(Debug)]
+pub struct SimulatedClock {
+    start: Duration,
+    tick: Duration,
+    reads: AtomicU64,
+}
+
+impl SimulatedClock {
+    /// # 参数
+    ///
+    /// * `start` - 初始墙上时间
+    /// * `tick` - 每次读取推进的时长
+    pub fn new(start: Duration, tick: Duration) -> Self {
+        SimulatedClock {
+            start,
+            tick,
+            reads: AtomicU64::new(0),
+        }
+    }
+
+    fn next(&self) -> Duration {
+        let reads = self.reads.fetch_add(1, Ordering::SeqCst);
+        self.tick
+            .saturating_mul(u32::try_from(reads).unwrap_or(u32::MAX))
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn wall_time(&self) -> Duration {
+        self.start + self.next()
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.next()
+    }
+}
+
+/// 持久化记录使用的时间戳
+///
+/// 按 (逻辑序号, 墙上时间) 排序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Timestamp {
+    pub sequence: u64,
+    pub wall_millis: u64,
+}
+
+impl PartialOrd for Timestamp {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timestamp {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.sequence, self.wall_millis).cmp(&(other.sequence, other.wall_millis))
+    }
+}
+
+/// 逻辑时钟（Lamport 计数器）
+#[derive(Debug, Default)]
+pub struct LogicalClock {
+    counter: AtomicU64,
+}
+
+impl LogicalClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从已持久化的最大序号恢复
+    pub fn starting_at(sequence: u64) -> Self {
+        LogicalClock {
+            counter: AtomicU64::new(sequence),
+        }
+    }
+
+    /// 分配下一个序号（从 1 开始）
+    pub fn tick(&self) -> u64 {
+        self.counter.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// 观察到其他节点的序号后推进，保证之后分配的序号更大
+    pub fn observe(&self, sequence: u64) {
+        self.counter.fetch_max(sequence, Ordering::SeqCst);
+    }
+
+    /// 最近分配的序号
+    pub fn current(&self) -> u64 {
+        self.counter.load(Ordering::SeqCst)
+    }
+
+    /// 分配序号并读取墙上时间
+    pub fn stamp(&self, clock: &dyn Clock) -> Timestamp {
+        Timestamp {
+            sequence: self.tick(),
+            wall_millis: clock.unix_millis(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_shared_and_rewindable() {
+        let clock = MockClock::new(Duration::from_secs(100));
+        let shared: SharedClock = Arc::new(clock.clone());
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(shared.wall_time(), Duration::from_secs(105));
+        assert_eq!(shared.monotonic(), Duration::from_secs(5));
+
+        // 墙上时间回拨不影响单调时间
+        clock.set(Duration::from_secs(50));
+        assert_eq!(shared.unix_millis(), 50_000);
+        assert_eq!(shared.monotonic(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_simulated_clock_is_deterministic() {
+        let run = || {
+            let clock = SimulatedClock::new(Duration::from_secs(10), Duration::from_millis(3));
+            (0..4).map(|_| clock.unix_millis()).collect::<Vec<_>>()
+        };
+        assert_eq!(run(), vec![10_000, 10_003, 10_006, 10_009]);
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn test_logical_clock_orders_despite_wall_skew() {
+        let clock = MockClock::new(Duration::from_secs(100));
+        let logical = LogicalClock::new();
+
+        let first = logical.stamp(&clock);
+        clock.set(Duration::from_secs(90));
+        let second = logical.stamp(&clock);
+        assert!(second > first);
+        assert!(second.wall_millis < first.wall_millis);
+
+        logical.observe(41);
+        assert_eq!(logical.tick(), 42);
+        logical.observe(10);
+        assert_eq!(logical.current(), 42);
+        assert_eq!(LogicalClock::starting_at(7).tick(), 8);
+    }
+}
@@ -0,0 +1,97 @@
+//! 跨组件传递的错误类别
+//!
+//! 状态码只说明请求能否重试，无法区分失败发生在哪一层。各组件的错误类型在转换为
+//! [`Status`] 时把类别写入 metadata，客户端用 [`ErrorKind::from_status`] 读取，
+//! 据此区分路由失败、证明验证失败和存储失败。
+
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Status};
+
+/// 携带错误类别的 metadata 键
+pub const ERROR_KIND_METADATA_KEY: &str = "x-error-kind";
+
+/// 错误类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// 请求本身无效（参数、布尔表达式、分页游标）
+    InvalidRequest,
+    /// 找不到或连接不上负责 keyword 的节点
+    Routing,
+    /// storager 返回的证明格式错误或验证失败
+    Verification,
+    /// storager 执行操作失败
+    Storage,
+    /// 集群成员变更被拒绝
+    Membership,
+    /// 没有提供有效的凭据，或凭据没有相应的权限
+    Auth,
+}
+
+impl ErrorKind {
+    /// metadata 中使用的名称
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::InvalidRequest => "invalid-request",
+            ErrorKind::Routing => "routing",
+            ErrorKind::Verification => "verification",
+            ErrorKind::Storage => "storage",
+            ErrorKind::Membership => "membership",
+            ErrorKind::Auth => "auth",
+        }
+    }
+
+    /// 解析 [`as_str`](Self::as_str) 的输出，未知名称返回 None
+    pub fn parse(name: &str) -> Option<Self> {
+        [
+            ErrorKind::InvalidRequest,
+            ErrorKind::Routing,
+            ErrorKind::Verification,
+            ErrorKind::Storage,
+            ErrorKind::Membership,
+            ErrorKind::Auth,
+        ]
+        .into_iter()
+        .find(|kind| kind.as_str() == name)
+    }
+
+    /// 构造带有该类别的状态
+    pub fn status(self, code: Code, message: impl Into<String>) -> Status {
+        let mut status = Status::new(code, message);
+        status.metadata_mut().insert(
+            ERROR_KIND_METADATA_KEY,
+            MetadataValue::from_static(self.as_str()),
+        );
+        status
+    }
+
+    /// 从状态中读取类别（对端没有标记类别时返回 None）
+    pub fn from_status(status: &Status) -> Option<Self> {
+        let value = status.metadata().get(ERROR_KIND_METADATA_KEY)?;
+        Self::parse(value.to_str().ok()?)
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_survives_status() {
+        let status = ErrorKind::Verification.status(Code::DataLoss, "bad proof");
+        assert_eq!(status.code(), Code::DataLoss);
+        assert_eq!(status.message(), "bad proof");
+        assert_eq!(
+            ErrorKind::from_status(&status),
+            Some(ErrorKind::Verification)
+        );
+        assert_eq!(ErrorKind::from_status(&Status::internal("untagged")), None);
+        assert_eq!(ErrorKind::parse("routing"), Some(ErrorKind::Routing));
+        assert_eq!(ErrorKind::parse("other"), None);
+    }
+}
@@ -0,0 +1,234 @@
+//! Fid 驻留的紧凑 id 和映射表证明
+//!
+//! 启用驻留的 storager 按驻留顺序给 fid 编号，在 ADS 中保存 `~` 加序号的 base36 形式
+//! （见 [`compact_id`]），只有比紧凑 id 长的 fid 才驻留，短 fid 原样保存。
+//!
+//! 映射表本身不写入 ADS：第 i 个叶子为 `leaf_hash(FID_TABLE_KEYWORD, fid_i)` 的二叉
+//! Merkle 树的根（映射表摘要）作为保留 keyword [`FID_TABLE_KEYWORD`] 下唯一的条目写入 ADS，
+//! 随每次新增驻留更新。查询结果中有紧凑 id 时，storager 返回 [`InternedProof`]：原来的
+//! 查询证明、映射表摘要的证明，以及每个紧凑 id 对应的映射表条目及其到摘要的路径。
+//! 客户端不能读写保留 keyword。
+
+use crate::Proof;
+
+/// fid 驻留映射表摘要使用的保留 keyword
+pub const FID_TABLE_KEYWORD: &str = "__fid_table__";
+
+/// 紧凑 id 的前缀，用于和原始 fid 区分
+const COMPACT_PREFIX: char = '~';
+
+/// 紧凑 id 使用的 base36 数字
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// 序号为 `index` 的紧凑 id
+pub fn compact_id(index: u64) -> String {
+    let mut digits = Vec::new();
+    let mut rest = index;
+    loop {
+        digits.push(DIGITS[(rest % 36) as usize]);
+        rest /= 36;
+        if rest == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    format!("{}{}", COMPACT_PREFIX, String::from_utf8(digits).unwrap())
+}
+
+/// 紧凑 id 的序号；不是 [`compact_id`] 生成的形式（包括有前导零的）时返回 None
+pub fn parse_compact_id(stored: &str) -> Option<u64> {
+    let digits = stored.strip_prefix(COMPACT_PREFIX)?;
+    if digits.is_empty() || (digits.len() > 1 && digits.starts_with('0')) {
+        return None;
+    }
+    digits.bytes().try_fold(0u64, |index, digit| {
+        let value = DIGITS.iter().position(|&d| d == digit)? as u64;
+        index.checked_mul(36)?.checked_add(value)
+    })
+}
+
+/// 是否用紧凑 id `id` 代替 `fid`：fid 比 id 长，或者以紧凑 id 的前缀开头（原样保存会被误认成 id）
+pub fn should_intern(fid: &str, id: &str) -> bool {
+    fid.len() > id.len() || fid.starts_with(COMPACT_PREFIX)
+}
+
+/// 映射表摘要在 ADS 中保存的形式（十六进制）
+pub fn digest_entry(digest: &[u8; 32]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 解析 [`digest_entry`] 的输出
+pub fn parse_digest_entry(entry: &str) -> Option<[u8; 32]> {
+    if entry.len() != 64 || !entry.is_ascii() {
+        return None;
+    }
+    let mut digest = [0u8; 32];
+    for (byte, pair) in digest.iter_mut().zip(entry.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(digest)
+}
+
+/// 是否是保留 keyword
+pub fn is_reserved_keyword(keyword: &str) -> bool {
+    keyword.starts_with(FID_TABLE_KEYWORD)
+}
+
+/// 拒绝客户端写入保留 keyword
+pub fn check_keyword(keyword: &str) -> Result<(), String> {
+    if is_reserved_keyword(keyword) {
+        Err(format!(
+            "Keyword '{}' uses the reserved prefix '{}'",
+            keyword, FID_TABLE_KEYWORD
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// 映射表中的一个条目及其到映射表摘要的路径
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableEntry {
+    /// 紧凑 id 的序号，即叶子位置
+    pub index: u64,
+    pub fid: String,
+    /// 自底向上的兄弟节点哈希
+    pub siblings: Vec<[u8; 32]>,
+}
+
+/// 结果中含紧凑 id 的查询证明（[`Proof::Interned`]）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InternedProof {
+    /// ADS 对保存形式的查询证明
+    pub proof: Proof,
+    /// [`FID_TABLE_KEYWORD`] 的查询证明，与 `proof` 对应同一个根
+    pub table: Proof,
+    /// 结果中每个紧凑 id 的映射表条目（同一个 id 只出现一次）
+    pub entries: Vec<TableEntry>,
+}
+
+impl InternedProof {
+    /// 编码
+    ///
+    /// 格式: len(u32) | proof | len(u32) | table | count(u32) | 条目 * count，
+    /// 两个证明按 protobuf 编码，每个条目为 [index(u64) | len(u32) | fid | depth(u8) | siblings(32 * depth)]，
+    /// 整数均为小端序
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for proof in [&self.proof, &self.table] {
+            let bytes = proof.to_bytes();
+            out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            out.extend_from_slice(&bytes);
+        }
+        out.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for entry in &self.entries {
+            out.extend_from_slice(&entry.index.to_le_bytes());
+            out.extend_from_slice(&(entry.fid.len() as u32).to_le_bytes());
+            out.extend_from_slice(entry.fid.as_bytes());
+            out.push(entry.siblings.len() as u8);
+            for sibling in &entry.siblings {
+                out.extend_from_slice(sibling);
+            }
+        }
+        out
+    }
+
+    /// 解码，格式错误时返回 None
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut rest = bytes;
+        let mut take = |n: usize| -> Option<&[u8]> {
+            if rest.len() < n {
+                return None;
+            }
+            let (head, tail) = rest.split_at(n);
+            rest = tail;
+            Some(head)
+        };
+
+        let len = u32::from_le_bytes(take(4)?.try_into().ok()?) as usize;
+        let proof = Proof::from_bytes(take(len)?).ok()?;
+        let len = u32::from_le_bytes(take(4)?.try_into().ok()?) as usize;
+        let table = Proof::from_bytes(take(len)?).ok()?;
+        let count = u32::from_le_bytes(take(4)?.try_into().ok()?);
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let index = u64::from_le_bytes(take(8)?.try_into().ok()?);
+            let len = u32::from_le_bytes(take(4)?.try_into().ok()?) as usize;
+            let fid = String::from_utf8(take(len)?.to_vec()).ok()?;
+            let depth = take(1)?[0] as usize;
+            let mut siblings = Vec::with_capacity(depth);
+            for _ in 0..depth {
+                siblings.push(take(32)?.try_into().ok()?);
+            }
+            entries.push(TableEntry {
+                index,
+                fid,
+                siblings,
+            });
+        }
+
+        if !rest.is_empty() {
+            return None;
+        }
+        Some(InternedProof {
+            proof,
+            table,
+            entries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_ids() {
+        for index in [0, 1, 35, 36, 1295, 1296, u64::MAX] {
+            let id = compact_id(index);
+            assert_eq!(parse_compact_id(&id), Some(index));
+        }
+        assert_eq!(compact_id(0), "~0");
+        assert_eq!(compact_id(36), "~10");
+        // 只接受规范形式
+        for stored in ["file1", "~", "~00", "~01", "~A", "~-1", "~3w5e11264sgsg0"] {
+            assert_eq!(parse_compact_id(stored), None, "{}", stored);
+        }
+
+        assert!(!should_intern("f1", "~0"));
+        assert!(should_intern("file1", "~0"));
+        assert!(should_intern("~x", "~0"));
+    }
+
+    #[test]
+    fn test_digest_entry_round_trip() {
+        let digest = [0xa5; 32];
+        let entry = digest_entry(&digest);
+        assert_eq!(entry.len(), 64);
+        assert_eq!(parse_digest_entry(&entry), Some(digest));
+        assert_eq!(parse_digest_entry("a5"), None);
+    }
+
+    #[test]
+    fn test_interned_proof_round_trip() {
+        let proof = InternedProof {
+            proof: Proof::Mpt(vec![1, 2, 3]),
+            table: Proof::Mpt(vec![4]),
+            entries: vec![TableEntry {
+                index: 3,
+                fid: "file-0003".to_string(),
+                siblings: vec![[7; 32], [8; 32]],
+            }],
+        };
+        let bytes = proof.to_bytes();
+        assert_eq!(InternedProof::from_bytes(&bytes), Some(proof));
+        assert_eq!(InternedProof::from_bytes(&bytes[..bytes.len() - 1]), None);
+    }
+
+    #[test]
+    fn test_reserved_keywords() {
+        assert!(check_keyword("rust").is_ok());
+        assert!(check_keyword(FID_TABLE_KEYWORD).is_err());
+        assert!(check_keyword("__fid_table__/x").is_err());
+    }
+}
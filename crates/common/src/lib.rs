@@ -1,7 +1,31 @@
+pub mod admission;
+pub mod auth;
+pub mod ads_error;
+pub mod bloom;
 pub mod boolean_expr;
+pub mod cli;
+pub mod clock;
+pub mod error_kind;
+pub mod fid_intern;
+pub mod namespace;
+pub mod net;
+pub mod page;
+pub mod query_stream;
+pub mod registry;
+pub mod retry;
 pub mod rpc;
+pub mod sketch;
+pub mod telemetry;
+pub mod tls;
+pub mod transport;
 pub mod types;
 
 // Re-export commonly used types
+pub use admission::QueryRejected;
+pub use ads_error::AdsError;
+pub use bloom::BloomFilter;
 pub use boolean_expr::{parse_boolean_expr, BooleanExpr};
-pub use types::{AdsMode, Fid, Keyword, Proof, RootHash, SystemConfig};
+pub use error_kind::ErrorKind;
+pub use namespace::{validate_namespace, DEFAULT_NAMESPACE};
+pub use page::{paginate, Page, PageError};
+pub use types::{prefix_range_end, AdsMode, Fid, Keyword, Proof, RootHash, SystemConfig};
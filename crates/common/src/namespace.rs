@@ -0,0 +1,52 @@
+//! 命名空间（多租户）
+//!
+//! 多个应用共用一个集群时各自使用一个命名空间。每个命名空间在 storager 上有独立的 ADS，
+//! 同名 keyword 互不干扰，Manager 也为每个 (storager, 命名空间) 分别验证和发布根哈希，
+//! 每个租户都能得到只覆盖自己数据的根摘要。
+//!
+//! 空名称是默认命名空间，不设置命名空间的请求都落在这里。
+
+/// 默认命名空间
+pub const DEFAULT_NAMESPACE: &str = "";
+
+/// 命名空间名称的最大长度
+pub const MAX_NAMESPACE_LEN: usize = 64;
+
+/// 检查命名空间名称：只允许 ASCII 字母、数字、`-` 和 `_`，默认命名空间总是合法的
+///
+/// 持久化的 storager 用名称作为数据目录下的子目录名，因此不允许路径分隔符和 `.`
+pub fn validate_namespace(name: &str) -> Result<(), String> {
+    if name.len() > MAX_NAMESPACE_LEN {
+        return Err(format!(
+            "Namespace '{}' is longer than {} characters",
+            name, MAX_NAMESPACE_LEN
+        ));
+    }
+    match name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+    {
+        Some(c) => Err(format!(
+            "Namespace '{}' contains invalid character '{}'",
+            name, c
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_namespace() {
+        assert!(validate_namespace(DEFAULT_NAMESPACE).is_ok());
+        assert!(validate_namespace("tenant-a_1").is_ok());
+        assert!(validate_namespace(&"a".repeat(MAX_NAMESPACE_LEN)).is_ok());
+
+        for name in ["..", "a/b", "a b", "ümlaut"] {
+            assert!(validate_namespace(name).is_err(), "{}", name);
+        }
+        assert!(validate_namespace(&"a".repeat(MAX_NAMESPACE_LEN + 1)).is_err());
+    }
+}
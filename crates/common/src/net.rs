@@ -0,0 +1,681 @@
+//! 监听地址配置
+//!
+//! Manager 和 Storager 可以同时监听多个地址（例如 IPv4 + IPv6），
+//! 并且对外通告的地址可以与监听地址不同（NAT / 容器场景下监听 `0.0.0.0`，
+//! 通告宿主机地址）。
+//!
+//! 同一主机上的 Manager 和 Storager 可以通过 Unix domain socket 通信，
+//! 地址写作 `unix:/path/to/socket`，监听和连接两端都支持该格式。
+//! 同一进程内的节点还可以使用 `memory:name` 地址（见 [`memory_incoming`]），
+//! 连接不经过操作系统，确定性模拟用它让所有 I/O 都由 Tokio 运行时调度。
+//!
+//! # 示例
+//!
+//! ```
+//! use common::net::ListenConfig;
+//!
+//! let listen = ListenConfig::parse("0.0.0.0:50052,[::]:50052", 50052).unwrap();
+//! assert_eq!(listen.bind_addrs.len(), 2);
+//! // 未显式指定通告地址时，通配地址被替换为回环地址
+//! assert_eq!(listen.advertise_url(), "http://127.0.0.1:50052");
+//!
+//! let listen = listen.with_advertise("http://storager-1.example:50052");
+//! assert_eq!(listen.advertise_url(), "http://storager-1.example:50052");
+//!
+//! let local = ListenConfig::parse("unix:/tmp/storager.sock", 50052).unwrap();
+//! assert_eq!(local.advertise_url(), "unix:/tmp/storager.sock");
+//! ```
+//!
+//! 监听套接字先绑定为 [`Listeners`] 再启动服务，
+//! 这样套接字可以在进程之间传递（Storager 的原地升级见 `storager::handover`）。
+//!
+//! 收到 SIGINT / SIGTERM（[`shutdown_signal`]）后，[`serve_with_drain`] 停止接受新连接，
+//! 在限定时间内等待进行中的请求完成，之后调用方再把状态落盘并退出。
+
+use crate::transport::TransportConfig;
+use socket2::{Domain, Socket, Type};
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Mutex, OnceLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::DuplexStream;
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
+use tonic::transport::server::{Router, TcpIncoming};
+use tonic::transport::{Channel, Endpoint};
+
+/// Unix domain socket 地址前缀
+pub const UNIX_SCHEME: &str = "unix:";
+
+/// 进程内连接的地址前缀
+pub const MEMORY_SCHEME: &str = "memory:";
+
+/// 进程内连接每个方向的缓冲区大小
+const MEMORY_BUFFER: usize = 64 * 1024;
+
+/// 收到关闭信号后等待进行中请求的默认时长（秒）
+pub const DEFAULT_DRAIN_SECS: u64 = 30;
+
+/// 解析 `unix:/path` 形式的地址，返回 socket 路径
+pub fn unix_path(addr: &str) -> Option<&Path> {
+    addr.strip_prefix(UNIX_SCHEME).map(Path::new)
+}
+
+/// 校验一个对外地址（`http(s)://host:port`、`unix:/absolute/path` 或 `memory:name`）
+pub fn validate_address(addr: &str) -> Result<(), String> {
+    if let Some(name) = addr.strip_prefix(MEMORY_SCHEME) {
+        if name.is_empty() {
+            return Err(format!("In-process address needs a name: {}", addr));
+        }
+        return Ok(());
+    }
+    if let Some(path) = unix_path(addr) {
+        if !path.is_absolute() {
+            return Err(format!("Unix socket path must be absolute: {}", addr));
+        }
+        if !cfg!(unix) {
+            return Err(format!(
+                "Unix sockets are not supported on this platform: {}",
+                addr
+            ));
+        }
+        return Ok(());
+    }
+
+    if !(addr.starts_with("http://") || addr.starts_with("https://")) {
+        return Err(format!(
+            "Address must start with http://, https:// or {}: {}",
+            UNIX_SCHEME, addr
+        ));
+    }
+    Endpoint::from_shared(addr.to_string())
+        .map(|_| ())
+        .map_err(|e| format!("Invalid address {}: {}", addr, e))
+}
+
+/// 连接到一个对外地址，支持 TCP 和 Unix domain socket
+pub async fn connect(addr: &str) -> Result<Channel, tonic::transport::Error> {
+    connect_with(addr, &TransportConfig::default()).await
+}
+
+/// 按给定的传输参数连接到一个对外地址
+pub async fn connect_with(
+    addr: &str,
+    transport: &TransportConfig,
+) -> Result<Channel, tonic::transport::Error> {
+    if let Some(name) = addr.strip_prefix(MEMORY_SCHEME) {
+        let name = name.to_string();
+        return transport
+            .endpoint(Endpoint::from_static("http://memory"))?
+            .connect_with_connector(tower::service_fn(move |_: tonic::transport::Uri| {
+                std::future::ready(connect_memory(&name))
+            }))
+            .await;
+    }
+
+    #[cfg(unix)]
+    if let Some(path) = unix_path(addr) {
+        let path = path.to_path_buf();
+        // UDS 连接时 URI 只用于 HTTP/2 的 :authority，不参与寻址
+        return transport
+            .endpoint(Endpoint::from_static("http://localhost"))?
+            .connect_with_connector(tower::service_fn(move |_: tonic::transport::Uri| {
+                tokio::net::UnixStream::connect(path.clone())
+            }))
+            .await;
+    }
+
+    transport
+        .endpoint(Endpoint::from_shared(addr.to_string())?)?
+        .connect()
+        .await
+}
+
+/// 创建到一个对外地址的 channel，第一次请求时才建立连接
+///
+/// 连接断开后 channel 在下一次请求时自动重连，可以 clone 后在多个请求之间共用；
+/// 地址无法解析或 TLS 配置无效时立即返回错误，连接失败在请求中以 `UNAVAILABLE` 返回
+pub fn connect_lazy_with(
+    addr: &str,
+    transport: &TransportConfig,
+) -> Result<Channel, tonic::transport::Error> {
+    if let Some(name) = addr.strip_prefix(MEMORY_SCHEME) {
+        let name = name.to_string();
+        return Ok(transport
+            .endpoint(Endpoint::from_static("http://memory"))?
+            .connect_with_connector_lazy(tower::service_fn(move |_: tonic::transport::Uri| {
+                std::future::ready(connect_memory(&name))
+            })));
+    }
+
+    #[cfg(unix)]
+    if let Some(path) = unix_path(addr) {
+        let path = path.to_path_buf();
+        return Ok(transport
+            .endpoint(Endpoint::from_static("http://localhost"))?
+            .connect_with_connector_lazy(tower::service_fn(move |_: tonic::transport::Uri| {
+                tokio::net::UnixStream::connect(path.clone())
+            })));
+    }
+
+    Ok(transport
+        .endpoint(Endpoint::from_shared(addr.to_string())?)?
+        .connect_lazy())
+}
+
+/// 监听配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListenConfig {
+    /// TCP 监听地址
+    pub bind_addrs: Vec<SocketAddr>,
+    /// Unix domain socket 监听路径
+    pub unix_paths: Vec<PathBuf>,
+    /// 对外通告的地址（None 时由第一个监听地址推导）
+    pub advertise_addr: Option<String>,
+}
+
+impl ListenConfig {
+    /// 只监听 IPv6 回环地址（与之前的默认行为一致）
+    pub fn localhost(port: u16) -> Self {
+        ListenConfig {
+            bind_addrs: vec![SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), port)],
+            unix_paths: Vec::new(),
+            advertise_addr: None,
+        }
+    }
+
+    /// 同时监听所有 IPv4 和 IPv6 地址
+    pub fn dual_stack(port: u16) -> Self {
+        ListenConfig {
+            bind_addrs: vec![
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port),
+                SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port),
+            ],
+            unix_paths: Vec::new(),
+            advertise_addr: None,
+        }
+    }
+
+    /// 解析逗号分隔的监听地址列表
+    ///
+    /// 每一项可以是 `ip:port`、`[ipv6]:port`、省略端口的 IP（使用 `default_port`），
+    /// 或者 `unix:/path` 形式的 Unix domain socket
+    pub fn parse(spec: &str, default_port: u16) -> Result<Self, String> {
+        let mut bind_addrs = Vec::new();
+        let mut unix_paths = Vec::new();
+        for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            if let Some(path) = unix_path(item) {
+                validate_address(item)?;
+                if !unix_paths.iter().any(|p: &PathBuf| p == path) {
+                    unix_paths.push(path.to_path_buf());
+                }
+                continue;
+            }
+
+            let addr = match item.parse::<SocketAddr>() {
+                Ok(addr) => addr,
+                Err(_) => {
+                    let ip = item
+                        .trim_start_matches('[')
+                        .trim_end_matches(']')
+                        .parse::<IpAddr>()
+                        .map_err(|_| format!("Invalid listen address: {}", item))?;
+                    SocketAddr::new(ip, default_port)
+                }
+            };
+            if !bind_addrs.contains(&addr) {
+                bind_addrs.push(addr);
+            }
+        }
+
+        if bind_addrs.is_empty() && unix_paths.is_empty() {
+            return Err("No listen address provided".to_string());
+        }
+        Ok(ListenConfig {
+            bind_addrs,
+            unix_paths,
+            advertise_addr: None,
+        })
+    }
+
+    /// 设置对外通告的地址
+    pub fn with_advertise(mut self, advertise: impl Into<String>) -> Self {
+        self.advertise_addr = Some(advertise.into());
+        self
+    }
+
+    /// 对外通告的 URL
+    ///
+    /// 未设置通告地址时使用第一个 TCP 监听地址（通配地址替换为同协议族的回环地址），
+    /// 只监听 Unix domain socket 时使用第一个 socket 路径
+    pub fn advertise_url(&self) -> String {
+        if let Some(addr) = &self.advertise_addr {
+            return addr.clone();
+        }
+
+        let mut addr = match self.bind_addrs.first() {
+            Some(addr) => *addr,
+            None => {
+                return format!("{}{}", UNIX_SCHEME, self.unix_paths[0].display());
+            }
+        };
+        if addr.ip().is_unspecified() {
+            addr.set_ip(match addr {
+                SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            });
+        }
+        format!("http://{}", addr)
+    }
+}
+
+/// 绑定一个 TCP 监听地址
+///
+/// IPv6 套接字设置 `IPV6_V6ONLY`，因此可以与同端口的 IPv4 套接字共存
+pub fn bind_tcp(addr: SocketAddr) -> std::io::Result<std::net::TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    Ok(socket.into())
+}
+
+/// 绑定一个监听地址，返回可交给 tonic `serve_with_incoming` 的连接流
+pub fn tcp_incoming(
+    addr: SocketAddr,
+) -> Result<TcpIncoming, Box<dyn std::error::Error + Send + Sync>> {
+    tcp_incoming_from(bind_tcp(addr)?)
+}
+
+fn tcp_incoming_from(
+    listener: std::net::TcpListener,
+) -> Result<TcpIncoming, Box<dyn std::error::Error + Send + Sync>> {
+    listener.set_nonblocking(true)?;
+    let listener = tokio::net::TcpListener::from_std(listener)?;
+    TcpIncoming::from_listener(listener, true, None)
+}
+
+/// 绑定一个 Unix domain socket
+///
+/// 路径上残留的 socket 文件（例如进程异常退出后留下的）会被先删除
+#[cfg(unix)]
+pub fn bind_unix(path: &Path) -> std::io::Result<std::os::unix::net::UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if meta.file_type().is_socket() {
+            std::fs::remove_file(path)?;
+        }
+    }
+    std::os::unix::net::UnixListener::bind(path)
+}
+
+/// 绑定一个 Unix domain socket，返回可交给 tonic 的连接流
+#[cfg(unix)]
+pub fn unix_incoming(path: &Path) -> std::io::Result<tokio_stream::wrappers::UnixListenerStream> {
+    unix_incoming_from(bind_unix(path)?)
+}
+
+#[cfg(unix)]
+fn unix_incoming_from(
+    listener: std::os::unix::net::UnixListener,
+) -> std::io::Result<tokio_stream::wrappers::UnixListenerStream> {
+    listener.set_nonblocking(true)?;
+    let listener = tokio::net::UnixListener::from_std(listener)?;
+    Ok(tokio_stream::wrappers::UnixListenerStream::new(listener))
+}
+
+/// 进程内监听的名称和接收新连接的一端
+type MemoryListeners = HashMap<String, mpsc::UnboundedSender<DuplexStream>>;
+
+fn memory_listeners() -> &'static Mutex<MemoryListeners> {
+    static LISTENERS: OnceLock<Mutex<MemoryListeners>> = OnceLock::new();
+    LISTENERS.get_or_init(Mutex::default)
+}
+
+/// 在进程内注册监听名称 `name`，返回可交给 tonic `serve_with_incoming` 的连接流
+///
+/// 连接到 `memory:name` 的客户端通过内存管道与服务端通信；返回的流被丢弃后名称被注销，
+/// 之后的连接被拒绝（已经建立的连接不受影响）。名称已被占用时返回错误
+pub fn memory_incoming(name: &str) -> Result<MemoryIncoming, String> {
+    let mut listeners = memory_listeners().lock().unwrap();
+    if listeners.get(name).is_some_and(|tx| !tx.is_closed()) {
+        return Err(format!("{}{} is already in use", MEMORY_SCHEME, name));
+    }
+    let (tx, rx) = mpsc::unbounded_channel();
+    listeners.insert(name.to_string(), tx);
+    Ok(MemoryIncoming {
+        name: name.to_string(),
+        rx,
+    })
+}
+
+/// 建立到进程内监听 `name` 的连接
+fn connect_memory(name: &str) -> std::io::Result<DuplexStream> {
+    let (client, server) = tokio::io::duplex(MEMORY_BUFFER);
+    match memory_listeners().lock().unwrap().get(name) {
+        Some(tx) if tx.send(server).is_ok() => Ok(client),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            format!("nothing is listening on {}{}", MEMORY_SCHEME, name),
+        )),
+    }
+}
+
+/// 进程内监听收到的连接
+#[derive(Debug)]
+pub struct MemoryIncoming {
+    name: String,
+    rx: mpsc::UnboundedReceiver<DuplexStream>,
+}
+
+impl MemoryIncoming {
+    /// 客户端使用的地址（`memory:name`）
+    pub fn url(&self) -> String {
+        format!("{}{}", MEMORY_SCHEME, self.name)
+    }
+}
+
+impl Stream for MemoryIncoming {
+    type Item = std::io::Result<DuplexStream>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx).map(|stream| stream.map(Ok))
+    }
+}
+
+impl Drop for MemoryIncoming {
+    fn drop(&mut self) {
+        self.rx.close();
+        let mut listeners = memory_listeners().lock().unwrap();
+        if listeners.get(&self.name).is_some_and(|tx| tx.is_closed()) {
+            listeners.remove(&self.name);
+        }
+    }
+}
+
+/// 已绑定的监听套接字
+#[derive(Debug, Default)]
+pub struct Listeners {
+    pub tcp: Vec<std::net::TcpListener>,
+    #[cfg(unix)]
+    pub unix: Vec<std::os::unix::net::UnixListener>,
+}
+
+impl Listeners {
+    /// 绑定监听配置中的所有地址
+    pub fn bind(listen: &ListenConfig) -> std::io::Result<Self> {
+        let mut listeners = Listeners::default();
+        for addr in &listen.bind_addrs {
+            listeners.tcp.push(bind_tcp(*addr)?);
+        }
+        #[cfg(unix)]
+        for path in &listen.unix_paths {
+            listeners.unix.push(bind_unix(path)?);
+        }
+        Ok(listeners)
+    }
+
+    /// 复制所有套接字（`dup`），副本与原套接字共享同一个内核监听队列
+    pub fn try_clone(&self) -> std::io::Result<Self> {
+        Ok(Listeners {
+            tcp: self
+                .tcp
+                .iter()
+                .map(|l| l.try_clone())
+                .collect::<Result<_, _>>()?,
+            #[cfg(unix)]
+            unix: self
+                .unix
+                .iter()
+                .map(|l| l.try_clone())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// 套接字数量
+    pub fn len(&self) -> usize {
+        #[cfg(unix)]
+        return self.tcp.len() + self.unix.len();
+        #[cfg(not(unix))]
+        return self.tcp.len();
+    }
+
+    /// 是否没有任何套接字
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 在所有监听地址上启动 gRPC 服务，任一地址上的服务出错时返回
+///
+/// 每个监听地址使用 `make_router` 构造一份独立的 Router（服务本身可以共享）
+pub async fn serve_all<F>(
+    listen: &ListenConfig,
+    make_router: F,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnMut() -> Router,
+{
+    let listeners = Listeners::bind(listen)?;
+    serve_listeners(listeners, make_router, std::future::pending()).await
+}
+
+/// 在已绑定的套接字上启动 gRPC 服务
+///
+/// `shutdown` 完成后所有服务停止接受新连接，等待进行中的请求完成后返回
+/// （HTTP/2 连接会收到 GOAWAY，客户端随后重新连接）
+pub async fn serve_listeners<F, S>(
+    listeners: Listeners,
+    make_router: F,
+    shutdown: S,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnMut() -> Router,
+    S: Future<Output = ()>,
+{
+    serve(listeners, make_router, shutdown, None).await
+}
+
+/// 与 [`serve_listeners`] 相同，但 `shutdown` 完成后最多等待 `drain` 时长
+///
+/// 超时后不再等待尚未结束的请求（例如长期订阅的流），直接关闭所有连接并返回
+pub async fn serve_with_drain<F, S>(
+    listeners: Listeners,
+    make_router: F,
+    shutdown: S,
+    drain: Duration,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnMut() -> Router,
+    S: Future<Output = ()>,
+{
+    serve(listeners, make_router, shutdown, Some(drain)).await
+}
+
+async fn serve<F, S>(
+    listeners: Listeners,
+    mut make_router: F,
+    shutdown: S,
+    drain: Option<Duration>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnMut() -> Router,
+    S: Future<Output = ()>,
+{
+    let (stop_tx, stop_rx) = tokio::sync::watch::channel(false);
+    let signal = |mut rx: tokio::sync::watch::Receiver<bool>| async move {
+        let _ = rx.wait_for(|stop| *stop).await;
+    };
+
+    let mut servers = tokio::task::JoinSet::new();
+    for listener in listeners.tcp {
+        let incoming = tcp_incoming_from(listener).map_err(|e| e as Box<dyn std::error::Error>)?;
+        servers
+            .spawn(make_router().serve_with_incoming_shutdown(incoming, signal(stop_rx.clone())));
+    }
+
+    #[cfg(unix)]
+    for listener in listeners.unix {
+        let incoming = unix_incoming_from(listener)?;
+        servers
+            .spawn(make_router().serve_with_incoming_shutdown(incoming, signal(stop_rx.clone())));
+    }
+
+    tokio::pin!(shutdown);
+    let deadline = tokio::time::sleep(Duration::MAX);
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            _ = &mut shutdown, if !*stop_tx.borrow() => {
+                let _ = stop_tx.send(true);
+                if let Some(drain) = drain {
+                    deadline.as_mut().reset(tokio::time::Instant::now() + drain);
+                }
+            }
+            _ = &mut deadline, if *stop_tx.borrow() && drain.is_some() => {
+                tracing::warn!(
+                    "{} server(s) still draining after {:?}, closing remaining connections",
+                    servers.len(),
+                    drain.unwrap_or_default()
+                );
+                servers.abort_all();
+                return Ok(());
+            }
+            result = servers.join_next() => match result {
+                Some(result) => result??,
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+/// 等待 SIGINT（Ctrl-C）或 SIGTERM，返回收到的信号名称
+pub async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+                _ = terminate.recv() => "SIGTERM",
+            },
+            Err(e) => {
+                tracing::warn!("Failed to install SIGTERM handler: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "Ctrl-C"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_listen_addrs() {
+        let listen = ListenConfig::parse("127.0.0.1, ::1, [::1]:7000, 127.0.0.1", 50052).unwrap();
+        assert_eq!(
+            listen.bind_addrs,
+            vec![
+                "127.0.0.1:50052".parse().unwrap(),
+                "[::1]:50052".parse().unwrap(),
+                "[::1]:7000".parse().unwrap(),
+            ]
+        );
+        assert!(ListenConfig::parse("not-an-ip", 1).is_err());
+        assert!(ListenConfig::parse(" , ", 1).is_err());
+    }
+
+    #[test]
+    fn test_parse_unix_listen_addrs() {
+        let listen = ListenConfig::parse("unix:/tmp/a.sock, 127.0.0.1", 50052).unwrap();
+        assert_eq!(listen.unix_paths, vec![PathBuf::from("/tmp/a.sock")]);
+        assert_eq!(listen.advertise_url(), "http://127.0.0.1:50052");
+        assert!(ListenConfig::parse("unix:relative.sock", 1).is_err());
+    }
+
+    #[test]
+    fn test_validate_address() {
+        assert!(validate_address("http://[::1]:50052").is_ok());
+        assert!(validate_address("unix:/run/storager.sock").is_ok());
+        assert!(validate_address("[::1]:50052").is_err());
+        assert!(validate_address("unix:storager.sock").is_err());
+        assert!(validate_address("memory:storager-0").is_ok());
+        assert!(validate_address("memory:").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_memory_connections() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio_stream::StreamExt;
+
+        let name = format!("net-test-{}", std::process::id());
+        let mut incoming = memory_incoming(&name).unwrap();
+        assert_eq!(incoming.url(), format!("memory:{}", name));
+        assert!(memory_incoming(&name).is_err());
+
+        let mut client = connect_memory(&name).unwrap();
+        let mut server = incoming.next().await.unwrap().unwrap();
+        client.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        // 注销后拒绝新连接，名称可以重新注册
+        drop(incoming);
+        let error = connect_memory(&name).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::ConnectionRefused);
+        assert!(connect(&format!("memory:{}", name)).await.is_err());
+        drop(memory_incoming(&name).unwrap());
+    }
+
+    #[test]
+    fn test_advertise_url() {
+        assert_eq!(
+            ListenConfig::localhost(50051).advertise_url(),
+            "http://[::1]:50051"
+        );
+        assert_eq!(
+            ListenConfig::dual_stack(50051).advertise_url(),
+            "http://127.0.0.1:50051"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tcp_incoming_binds() {
+        assert!(tcp_incoming("127.0.0.1:0".parse().unwrap()).is_ok());
+
+        // IPv6 可能在沙箱环境中不可用，此时跳过
+        if std::net::TcpListener::bind("[::1]:0").is_ok() {
+            assert!(tcp_incoming("[::1]:0".parse().unwrap()).is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cloned_listeners_share_queue() {
+        let listen = ListenConfig::parse("127.0.0.1:0", 0).unwrap();
+        let listeners = Listeners::bind(&listen).unwrap();
+        let clone = listeners.try_clone().unwrap();
+        assert_eq!(clone.len(), 1);
+
+        // 关闭原套接字后副本仍然可以接受连接
+        let addr = listeners.tcp[0].local_addr().unwrap();
+        drop(listeners);
+        let _client = std::net::TcpStream::connect(addr).unwrap();
+        assert!(clone.tcp[0].accept().is_ok());
+    }
+}
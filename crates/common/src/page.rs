@@ -0,0 +1,154 @@
+//! 查询结果分页
+//!
+//! 页游标的格式为 `<结果集摘要>:<偏移量>`。摘要取自签发游标时的完整结果集，
+//! 之后结果集发生变化（keyword 被写入或删除）时旧游标失效，客户端需要从第一页重新开始，
+//! 这样拼接起来的各页不会遗漏或重复 fid。
+//!
+//! ADS 的证明针对完整的结果集，无法拆分到单页，因此证明只随最后一页返回。
+
+use sha2::{Digest, Sha256};
+use tonic::Status;
+
+/// 一页查询结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    pub fids: Vec<String>,
+    /// 下一页的游标，最后一页为空
+    pub next_page_token: String,
+    /// 完整结果集的大小
+    pub total_count: u64,
+}
+
+impl Page {
+    /// 是否是最后一页（证明随最后一页返回）
+    pub fn is_last(&self) -> bool {
+        self.next_page_token.is_empty()
+    }
+}
+
+/// 页游标无效
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageError {
+    /// 游标格式错误
+    Malformed(String),
+    /// 签发游标之后结果集发生了变化
+    Stale,
+}
+
+impl std::fmt::Display for PageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PageError::Malformed(token) => write!(f, "Malformed page token '{}'", token),
+            PageError::Stale => write!(
+                f,
+                "Result set changed since the page token was issued; restart from the first page"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PageError {}
+
+impl From<PageError> for Status {
+    fn from(error: PageError) -> Status {
+        match error {
+            PageError::Malformed(_) => Status::invalid_argument(error.to_string()),
+            PageError::Stale => Status::aborted(error.to_string()),
+        }
+    }
+}
+
+/// 结果集摘要（前 8 字节，十六进制）
+fn result_digest(fids: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    for fid in fids {
+        hasher.update(fid.as_bytes());
+        hasher.update([0u8]);
+    }
+    hasher.finalize()[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 从完整结果集中取出游标指向的一页
+///
+/// # Arguments
+/// * `fids` - 完整结果集（多次调用之间顺序必须一致）
+/// * `page_size` - 每页 fid 数量，0 表示返回游标之后的全部结果
+/// * `page_token` - 上一页返回的游标，空表示第一页
+pub fn paginate(fids: Vec<String>, page_size: u32, page_token: &str) -> Result<Page, PageError> {
+    let total_count = fids.len() as u64;
+    let digest = result_digest(&fids);
+
+    let offset = if page_token.is_empty() {
+        0
+    } else {
+        let malformed = || PageError::Malformed(page_token.to_string());
+        let (token_digest, offset) = page_token.split_once(':').ok_or_else(malformed)?;
+        let offset: usize = offset.parse().map_err(|_| malformed())?;
+        if token_digest != digest || offset > fids.len() {
+            return Err(PageError::Stale);
+        }
+        offset
+    };
+
+    let end = match page_size {
+        0 => fids.len(),
+        size => fids.len().min(offset + size as usize),
+    };
+    let next_page_token = if end < fids.len() {
+        format!("{}:{}", digest, end)
+    } else {
+        String::new()
+    };
+
+    Ok(Page {
+        fids: fids.into_iter().skip(offset).take(end - offset).collect(),
+        next_page_token,
+        total_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fids(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("f{}", i)).collect()
+    }
+
+    #[test]
+    fn test_pages_cover_result_once() {
+        let mut collected = Vec::new();
+        let mut token = String::new();
+        loop {
+            let page = paginate(fids(7), 3, &token).unwrap();
+            assert_eq!(page.total_count, 7);
+            collected.extend(page.fids.clone());
+            if page.is_last() {
+                break;
+            }
+            token = page.next_page_token;
+        }
+        assert_eq!(collected, fids(7));
+
+        // 不分页时一次返回全部结果
+        let page = paginate(fids(7), 0, "").unwrap();
+        assert_eq!(page.fids.len(), 7);
+        assert!(page.is_last());
+    }
+
+    #[test]
+    fn test_rejects_stale_and_malformed_tokens() {
+        let page = paginate(fids(5), 2, "").unwrap();
+        assert_eq!(
+            paginate(fids(6), 2, &page.next_page_token),
+            Err(PageError::Stale)
+        );
+        assert!(matches!(
+            paginate(fids(5), 2, "garbage"),
+            Err(PageError::Malformed(_))
+        ));
+    }
+}
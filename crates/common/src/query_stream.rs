@@ -0,0 +1,358 @@
+//! 流式查询结果
+//!
+//! fid 很多的 keyword，结果和证明可能超过 gRPC 的单条消息上限。`QueryStream` 把一次查询的
+//! 完整结果拆成若干大小有界的消息：
+//!
+//! 1. 头部，声明 fid 数、编码后证明的长度和结果所在的 epoch；
+//! 2. 按倒排列表顺序分批的 fid；
+//! 3. 按顺序切分的完整证明字节；
+//! 4. 尾部，标记流的结束。
+//!
+//! 证明可以按 fid 拆开的 ADS（Merkle 树）不传输完整证明：每批 fid 附带只覆盖这批 fid 的证明，
+//! 接收方收到一批就可以对照头部 epoch 的根哈希验证这一批，发送方也不必一次生成全部证明。
+//! MPT、稀疏 Merkle 树和累加器的证明绑定整个 fid 列表，无法逐批验证，仍然在 fid 之后
+//! 分段传输完整的证明，接收方在流结束后验证。
+//!
+//! 接收方用 [`QueryAssembler`] 边接收边检查：超出头部声明的数量立即报错，不会无限制地缓存；
+//! 消息的真实性由证明保证，这里只检查结构。
+
+use crate::rpc::storager_query_chunk::Chunk;
+use crate::rpc::{
+    Proof, QueryStreamFids, QueryStreamHeader, QueryStreamTrailer, StoragerQueryChunk,
+    StoragerQueryResponse,
+};
+use prost::Message;
+
+/// 默认每条消息携带的 fid 数
+pub const DEFAULT_FIDS_PER_CHUNK: usize = 1024;
+/// 默认每条消息携带的证明字节数
+pub const DEFAULT_PROOF_PART_SIZE: usize = 256 * 1024;
+
+/// 流式结果不完整或与声明不符
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamError {
+    /// 第一条消息不是头部，或出现了第二个头部
+    UnexpectedHeader,
+    /// 头部之前或尾部之后收到了数据
+    OutOfOrder,
+    /// fid 数超过头部声明
+    TooManyFids { declared: u64 },
+    /// 证明字节数超过头部声明
+    ProofTooLarge { declared: u64 },
+    /// 头部没有声明完整证明，但一批 fid 没有附带证明
+    MissingBatchProof,
+    /// 头部声明了完整证明，但一批 fid 附带了自己的证明
+    UnexpectedBatchProof,
+    /// 流在尾部之前结束，或数量少于声明
+    Truncated,
+    /// 拼接后的证明无法解码
+    MalformedProof(String),
+}
+
+impl std::fmt::Display for StreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreamError::UnexpectedHeader => write!(f, "query stream header out of place"),
+            StreamError::OutOfOrder => write!(f, "query stream data outside header and trailer"),
+            StreamError::TooManyFids { declared } => {
+                write!(
+                    f,
+                    "query stream sent more than the {} declared fid(s)",
+                    declared
+                )
+            }
+            StreamError::ProofTooLarge { declared } => write!(
+                f,
+                "query stream proof exceeds the {} declared byte(s)",
+                declared
+            ),
+            StreamError::MissingBatchProof => write!(f, "query stream batch without a proof"),
+            StreamError::UnexpectedBatchProof => {
+                write!(f, "query stream batch proof alongside a complete proof")
+            }
+            StreamError::Truncated => write!(f, "query stream ended early"),
+            StreamError::MalformedProof(e) => write!(f, "query stream proof: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+/// 头部消息；`proof_size` 为 0 表示每批 fid 附带自己的证明
+pub fn header_chunk(total_count: u64, proof_size: u64, epoch: u64) -> StoragerQueryChunk {
+    StoragerQueryChunk {
+        chunk: Some(Chunk::Header(QueryStreamHeader {
+            total_count,
+            proof_size,
+            epoch,
+        })),
+    }
+}
+
+/// 一批 fid，`proof` 只覆盖这批 fid
+pub fn batch_chunk(fids: Vec<String>, proof: Option<Proof>) -> StoragerQueryChunk {
+    StoragerQueryChunk {
+        chunk: Some(Chunk::Fids(QueryStreamFids { fids, proof })),
+    }
+}
+
+/// 尾部消息
+pub fn trailer_chunk() -> StoragerQueryChunk {
+    StoragerQueryChunk {
+        chunk: Some(Chunk::Trailer(QueryStreamTrailer {})),
+    }
+}
+
+/// 把带有完整证明的查询响应拆成流式消息（fid 批次不附带证明）
+///
+/// 响应中的分页字段被忽略；`fids_per_chunk` 和 `proof_part_size` 为 0 时按 1 处理
+pub fn split_query(
+    response: StoragerQueryResponse,
+    fids_per_chunk: usize,
+    proof_part_size: usize,
+) -> Vec<StoragerQueryChunk> {
+    let proof = response.proof.unwrap_or_default().encode_to_vec();
+    let mut chunks = vec![header_chunk(
+        response.fids.len() as u64,
+        proof.len() as u64,
+        response.epoch,
+    )];
+    for batch in response.fids.chunks(fids_per_chunk.max(1)) {
+        chunks.push(batch_chunk(batch.to_vec(), None));
+    }
+    for part in proof.chunks(proof_part_size.max(1)) {
+        chunks.push(StoragerQueryChunk {
+            chunk: Some(Chunk::ProofPart(part.to_vec())),
+        });
+    }
+    chunks.push(trailer_chunk());
+    chunks
+}
+
+/// 逐条接收流式消息，还原完整的查询响应
+///
+/// 附带证明的批次由 [`push`](Self::push) 交给调用方立即验证
+#[derive(Default)]
+pub struct QueryAssembler {
+    header: Option<QueryStreamHeader>,
+    fids: Vec<String>,
+    /// 最近一批 fid 在 `fids` 中的起点
+    batch_start: usize,
+    batches: usize,
+    proof: Vec<u8>,
+    finished: bool,
+}
+
+impl QueryAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 接收一条消息；消息是附带证明的一批 fid 时返回这批的证明，
+    /// 这批 fid 见 [`last_batch`](Self::last_batch)
+    pub fn push(&mut self, chunk: StoragerQueryChunk) -> Result<Option<Proof>, StreamError> {
+        if self.finished {
+            return Err(StreamError::OutOfOrder);
+        }
+        match chunk.chunk.ok_or(StreamError::OutOfOrder)? {
+            Chunk::Header(header) => {
+                if self.header.is_some() {
+                    return Err(StreamError::UnexpectedHeader);
+                }
+                self.fids.reserve(header.total_count.min(1 << 20) as usize);
+                self.header = Some(header);
+            }
+            Chunk::Fids(batch) => {
+                let header = self.header()?;
+                let declared = header.total_count;
+                let batch_proofs = header.proof_size == 0;
+                // 证明部分开始之后不能再有 fid
+                if !self.proof.is_empty() {
+                    return Err(StreamError::OutOfOrder);
+                }
+                match (batch_proofs, batch.proof.is_some()) {
+                    (true, false) => return Err(StreamError::MissingBatchProof),
+                    (false, true) => return Err(StreamError::UnexpectedBatchProof),
+                    _ => {}
+                }
+                if (self.fids.len() + batch.fids.len()) as u64 > declared {
+                    return Err(StreamError::TooManyFids { declared });
+                }
+                self.batch_start = self.fids.len();
+                self.batches += 1;
+                self.fids.extend(batch.fids);
+                return Ok(batch.proof);
+            }
+            Chunk::ProofPart(part) => {
+                let declared = self.header()?.proof_size;
+                if (self.proof.len() + part.len()) as u64 > declared {
+                    return Err(StreamError::ProofTooLarge { declared });
+                }
+                self.proof.extend_from_slice(&part);
+            }
+            Chunk::Trailer(_) => {
+                let header = self.header()?;
+                // 逐批证明时，没有 fid 的 keyword 也要有一批携带证明
+                if self.fids.len() as u64 != header.total_count
+                    || self.proof.len() as u64 != header.proof_size
+                    || (header.proof_size == 0 && self.batches == 0)
+                {
+                    return Err(StreamError::Truncated);
+                }
+                self.finished = true;
+            }
+        }
+        Ok(None)
+    }
+
+    /// 头部中的 epoch；还没有收到头部时为 None
+    pub fn epoch(&self) -> Option<u64> {
+        self.header.as_ref().map(|header| header.epoch)
+    }
+
+    /// 最近收到的一批 fid
+    pub fn last_batch(&self) -> &[String] {
+        &self.fids[self.batch_start..]
+    }
+
+    /// 头部；还没有收到头部时返回错误
+    fn header(&self) -> Result<&QueryStreamHeader, StreamError> {
+        self.header.as_ref().ok_or(StreamError::UnexpectedHeader)
+    }
+
+    /// 流结束，返回还原出的响应
+    ///
+    /// 逐批证明的流没有完整证明，响应中的 `proof` 为 None
+    pub fn finish(self) -> Result<StoragerQueryResponse, StreamError> {
+        if !self.finished {
+            return Err(StreamError::Truncated);
+        }
+        let header = self.header.ok_or(StreamError::Truncated)?;
+        let proof = if header.proof_size == 0 {
+            None
+        } else {
+            let proof = Proof::decode(self.proof.as_slice())
+                .map_err(|e| StreamError::MalformedProof(e.to_string()))?;
+            Some(proof)
+        };
+        Ok(StoragerQueryResponse {
+            total_count: self.fids.len() as u64,
+            fids: self.fids,
+            proof,
+            epoch: header.epoch,
+            next_page_token: String::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::proof::Kind;
+
+    fn response(count: usize) -> StoragerQueryResponse {
+        StoragerQueryResponse {
+            fids: (0..count).map(|i| format!("f{}", i)).collect(),
+            proof: Some(Proof {
+                kind: Some(Kind::Mpt(vec![7; 1000])),
+            }),
+            epoch: 3,
+            total_count: count as u64,
+            ..Default::default()
+        }
+    }
+
+    fn assemble(chunks: Vec<StoragerQueryChunk>) -> Result<StoragerQueryResponse, StreamError> {
+        let mut assembler = QueryAssembler::new();
+        for chunk in chunks {
+            assembler.push(chunk)?;
+        }
+        assembler.finish()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let chunks = split_query(response(25), 10, 300);
+        // 头部、3 批 fid、4 段证明、尾部
+        assert_eq!(chunks.len(), 1 + 3 + 4 + 1);
+        assert_eq!(assemble(chunks).unwrap(), response(25));
+
+        assert_eq!(
+            assemble(split_query(response(0), 10, 300)).unwrap(),
+            response(0)
+        );
+    }
+
+    #[test]
+    fn test_tampered_streams_are_rejected() {
+        let chunks = split_query(response(25), 10, 300);
+
+        let mut dropped = chunks.clone();
+        dropped.remove(2);
+        assert_eq!(assemble(dropped), Err(StreamError::Truncated));
+
+        let mut extra = chunks.clone();
+        extra.insert(1, chunks[1].clone());
+        extra.insert(1, chunks[1].clone());
+        assert_eq!(
+            assemble(extra),
+            Err(StreamError::TooManyFids { declared: 25 })
+        );
+
+        let mut swapped = chunks.clone();
+        swapped.swap(3, 4);
+        assert_eq!(assemble(swapped), Err(StreamError::OutOfOrder));
+
+        let mut batch_proof = chunks.clone();
+        batch_proof[1] = batch_chunk(vec!["f0".to_string()], response(0).proof);
+        assert_eq!(
+            assemble(batch_proof),
+            Err(StreamError::UnexpectedBatchProof)
+        );
+
+        let mut unterminated = chunks.clone();
+        unterminated.pop();
+        assert_eq!(assemble(unterminated), Err(StreamError::Truncated));
+
+        assert_eq!(
+            assemble(chunks[1..].to_vec()),
+            Err(StreamError::UnexpectedHeader)
+        );
+    }
+
+    #[test]
+    fn test_batches_with_own_proofs() {
+        let proof = |byte| {
+            Some(Proof {
+                kind: Some(Kind::Merkle(vec![byte])),
+            })
+        };
+        let fids = |range: std::ops::Range<usize>| range.map(|i| format!("f{}", i)).collect();
+
+        let mut assembler = QueryAssembler::new();
+        assembler.push(header_chunk(3, 0, 5)).unwrap();
+        assert_eq!(assembler.epoch(), Some(5));
+        // 每批的证明在收到时交给调用方
+        let batch = assembler.push(batch_chunk(fids(0..2), proof(1))).unwrap();
+        assert_eq!(batch, proof(1));
+        assert_eq!(assembler.last_batch(), ["f0", "f1"]);
+        let batch = assembler.push(batch_chunk(fids(2..3), proof(2))).unwrap();
+        assert_eq!(batch, proof(2));
+        assert_eq!(assembler.last_batch(), ["f2"]);
+        assembler.push(trailer_chunk()).unwrap();
+        let response = assembler.finish().unwrap();
+        assert_eq!(response.fids, fids(0..3));
+        assert_eq!(response.proof, None);
+
+        // 缺少证明的批次、没有任何批次的空结果
+        let mut assembler = QueryAssembler::new();
+        assembler.push(header_chunk(1, 0, 5)).unwrap();
+        assert_eq!(
+            assembler.push(batch_chunk(fids(0..1), None)),
+            Err(StreamError::MissingBatchProof)
+        );
+        let mut assembler = QueryAssembler::new();
+        assembler.push(header_chunk(0, 0, 5)).unwrap();
+        assert_eq!(assembler.push(trailer_chunk()), Err(StreamError::Truncated));
+    }
+}
@@ -0,0 +1,239 @@
+//! ADS 模式注册表
+//!
+//! 内置的 ADS 模式（累加器、MPT）在 [`AdsMode`] 中有固定的变体；
+//! 第三方 ADS 可以在启动时以字符串名称注册到这里，并声明自己的能力元数据，
+//! 注册后即可通过 [`AdsMode::Custom`] 在 Storager 和 Manager 中使用，无需修改 common。
+//!
+//! # 示例
+//!
+//! ```
+//! use common::registry::{register_ads_mode, AdsCapabilities, AdsDescriptor};
+//! use common::AdsMode;
+//!
+//! let mode = register_ads_mode(AdsDescriptor {
+//!     name: "my-ads",
+//!     aliases: &["mine"],
+//!     capabilities: AdsCapabilities {
+//!         supports_non_membership: false,
+//!         supports_range: true,
+//!         proof_version: 1,
+//!     },
+//! })
+//! .unwrap();
+//!
+//! assert_eq!(AdsMode::from_name("MINE"), Some(mode));
+//! assert!(mode.capabilities().unwrap().supports_range);
+//! ```
+
+use crate::types::AdsMode;
+use std::sync::{OnceLock, RwLock};
+
+/// ADS 能力元数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdsCapabilities {
+    /// 是否支持非成员资格证明
+    pub supports_non_membership: bool,
+    /// 是否支持范围查询
+    pub supports_range: bool,
+    /// 证明格式版本
+    pub proof_version: u32,
+}
+
+/// ADS 模式描述
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdsDescriptor {
+    /// 规范名称（大小写不敏感）
+    pub name: &'static str,
+    /// 别名
+    pub aliases: &'static [&'static str],
+    /// 能力元数据
+    pub capabilities: AdsCapabilities,
+}
+
+impl AdsDescriptor {
+    fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || self.aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+    }
+
+    /// 描述对应的 AdsMode
+    pub fn mode(&self) -> AdsMode {
+        match self.name {
+            ACCUMULATOR_NAME => AdsMode::CryptoAccumulator,
+            MPT_NAME => AdsMode::Mpt,
+            MERKLE_NAME => AdsMode::MerkleTree,
+            SMT_NAME => AdsMode::SparseMerkleTree,
+            name => AdsMode::Custom(name),
+        }
+    }
+}
+
+const ACCUMULATOR_NAME: &str = "accumulator";
+const MPT_NAME: &str = "mpt";
+const MERKLE_NAME: &str = "merkle";
+const SMT_NAME: &str = "smt";
+
+fn builtin_descriptors() -> Vec<AdsDescriptor> {
+    vec![
+        AdsDescriptor {
+            name: ACCUMULATOR_NAME,
+            aliases: &["crypto", "cryptoaccumulator"],
+            capabilities: AdsCapabilities {
+                supports_non_membership: true,
+                supports_range: false,
+                proof_version: 1,
+            },
+        },
+        AdsDescriptor {
+            name: MPT_NAME,
+            aliases: &[],
+            capabilities: AdsCapabilities {
+                supports_non_membership: true,
+                supports_range: true,
+                proof_version: 2,
+            },
+        },
+        AdsDescriptor {
+            name: MERKLE_NAME,
+            aliases: &["merkletree", "merkle-tree"],
+            capabilities: AdsCapabilities {
+                supports_non_membership: true,
+                supports_range: false,
+                proof_version: 2,
+            },
+        },
+        AdsDescriptor {
+            name: SMT_NAME,
+            aliases: &["sparse-merkle", "sparsemerkletree"],
+            capabilities: AdsCapabilities {
+                supports_non_membership: true,
+                supports_range: false,
+                proof_version: 1,
+            },
+        },
+    ]
+}
+
+fn registry() -> &'static RwLock<Vec<AdsDescriptor>> {
+    static REGISTRY: OnceLock<RwLock<Vec<AdsDescriptor>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(builtin_descriptors()))
+}
+
+/// 注册一个新的 ADS 模式
+///
+/// 名称或别名与已注册模式冲突时返回错误
+pub fn register_ads_mode(descriptor: AdsDescriptor) -> Result<AdsMode, String> {
+    let mut entries = registry().write().unwrap();
+
+    let names = std::iter::once(descriptor.name).chain(descriptor.aliases.iter().copied());
+    for name in names {
+        if entries.iter().any(|d| d.matches(name)) {
+            return Err(format!("ADS mode name '{}' is already registered", name));
+        }
+    }
+
+    entries.push(descriptor);
+    Ok(descriptor.mode())
+}
+
+/// 按名称或别名查找 ADS 模式描述
+pub fn resolve_ads_mode(name: &str) -> Option<AdsDescriptor> {
+    registry()
+        .read()
+        .unwrap()
+        .iter()
+        .find(|d| d.matches(name.trim()))
+        .copied()
+}
+
+/// 列出所有已注册的 ADS 模式
+pub fn registered_ads_modes() -> Vec<AdsDescriptor> {
+    registry().read().unwrap().clone()
+}
+
+impl AdsMode {
+    /// 规范名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            AdsMode::CryptoAccumulator => ACCUMULATOR_NAME,
+            AdsMode::Mpt => MPT_NAME,
+            AdsMode::MerkleTree => MERKLE_NAME,
+            AdsMode::SparseMerkleTree => SMT_NAME,
+            AdsMode::Custom(name) => name,
+        }
+    }
+
+    /// 按名称或别名解析 ADS 模式（仅限已注册的模式）
+    pub fn from_name(name: &str) -> Option<AdsMode> {
+        resolve_ads_mode(name).map(|d| d.mode())
+    }
+
+    /// 查询该模式的能力元数据
+    pub fn capabilities(&self) -> Option<AdsCapabilities> {
+        resolve_ads_mode(self.name()).map(|d| d.capabilities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_modes_resolve() {
+        assert_eq!(
+            AdsMode::from_name("accumulator"),
+            Some(AdsMode::CryptoAccumulator)
+        );
+        assert_eq!(
+            AdsMode::from_name("Crypto"),
+            Some(AdsMode::CryptoAccumulator)
+        );
+        assert_eq!(AdsMode::from_name("mpt"), Some(AdsMode::Mpt));
+        assert_eq!(AdsMode::from_name("merkle"), Some(AdsMode::MerkleTree));
+        assert_eq!(AdsMode::from_name("Merkle-Tree"), Some(AdsMode::MerkleTree));
+        assert_eq!(
+            AdsMode::from_name("Sparse-Merkle"),
+            Some(AdsMode::SparseMerkleTree)
+        );
+        assert_eq!(AdsMode::from_name("unknown"), None);
+        assert!(
+            AdsMode::CryptoAccumulator
+                .capabilities()
+                .unwrap()
+                .supports_non_membership
+        );
+    }
+
+    #[test]
+    fn test_duplicate_registration_rejected() {
+        let descriptor = AdsDescriptor {
+            name: "test-dup-ads",
+            aliases: &["mpt"],
+            capabilities: AdsCapabilities {
+                supports_non_membership: false,
+                supports_range: false,
+                proof_version: 1,
+            },
+        };
+        assert!(register_ads_mode(descriptor).is_err());
+        assert_eq!(AdsMode::from_name("test-dup-ads"), None);
+    }
+
+    #[test]
+    fn test_custom_mode_roundtrip() {
+        let mode = register_ads_mode(AdsDescriptor {
+            name: "test-custom-ads",
+            aliases: &[],
+            capabilities: AdsCapabilities {
+                supports_non_membership: false,
+                supports_range: true,
+                proof_version: 2,
+            },
+        })
+        .unwrap();
+
+        assert_eq!(mode, AdsMode::Custom("test-custom-ads"));
+        assert_eq!(mode.name(), "test-custom-ads");
+        assert_eq!(mode.capabilities().unwrap().proof_version, 2);
+    }
+}
@@ -0,0 +1,108 @@
+//! 远程调用的超时和重试策略
+//!
+//! Manager 调用 storager、Client 调用 Manager 共用同一套策略：每次尝试受 `timeout` 限制
+//! （包括按需建立连接的时间），暂时性错误按指数退避重试，其余错误立即返回。
+//! 哪些错误是暂时性的由调用方的错误类型决定（`ManagerError::is_transient`、
+//! `ClientError::is_transient`）。
+
+use std::future::Future;
+use std::time::Duration;
+
+/// 默认的最大尝试次数（包括第一次）
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+/// 默认的第一次重试前的等待时间
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(50);
+/// 默认的等待时间上限
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(1);
+/// 默认的单次尝试超时时间（累加器写入需要生成证明，留出足够的余量）
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 重试策略
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// 每次调用最多尝试的次数（包括第一次），至少为 1
+    pub max_attempts: u32,
+    /// 第一次重试前的等待时间，之后每次翻倍
+    pub initial_backoff: Duration,
+    /// 等待时间上限
+    pub max_backoff: Duration,
+    /// 单次尝试的超时时间，`None` 表示不限制
+    pub timeout: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            timeout: Some(DEFAULT_TIMEOUT),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 只尝试一次，不设超时
+    pub fn no_retry() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            timeout: None,
+            ..Self::default()
+        }
+    }
+
+    /// 第 `attempt` 次尝试失败后的等待时间（`attempt` 从 1 开始）
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let doublings = attempt.saturating_sub(1).min(31);
+        self.initial_backoff
+            .saturating_mul(1 << doublings)
+            .min(self.max_backoff)
+    }
+
+    /// 第 `attempt` 次尝试以暂时性错误失败后是否还可以重试
+    pub fn can_retry(&self, attempt: u32) -> bool {
+        attempt < self.max_attempts
+    }
+
+    /// 在超时时间内等待 `future`，超时返回 None
+    pub async fn within_deadline<F: Future>(&self, future: F) -> Option<F::Output> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, future).await.ok(),
+            None => Some(future.await),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            timeout: None,
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(4), Duration::from_millis(500));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_millis(500));
+        assert!(policy.can_retry(9));
+        assert!(!policy.can_retry(10));
+        assert!(!RetryPolicy::no_retry().can_retry(1));
+    }
+
+    #[tokio::test]
+    async fn test_deadline() {
+        let policy = RetryPolicy {
+            timeout: Some(Duration::from_millis(20)),
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.within_deadline(async { 1 }).await, Some(1));
+        let slow = tokio::time::sleep(Duration::from_secs(5));
+        assert_eq!(policy.within_deadline(slow).await, None);
+    }
+}
@@ -0,0 +1,131 @@
+//! 可认证的基数估计草图 (HyperLogLog)
+//!
+//! 每个 keyword 在 storager 端维护一个 HyperLogLog 草图，草图的叶子哈希与 Merkle Tree ADS
+//! 的叶子规则相同（`esa_rust::merkle_tree::leaf_hash_bytes(keyword, sketch)`）。
+//!
+//! 与最初的设计不同，草图没有提交到 ADS 根之下，ApproxCount 也不返回包含证明：
+//! 把草图写进 ADS 会让每次添加多出一次 ADS 变更和证明，而 storager 自己给出的草图树的根
+//! 又不能说明任何事情。
+//! 草图改由 Manager 跟踪的摘要认证：每次添加的响应带有添加后草图的叶子哈希，Manager
+//! 在变更证明验证通过后记录它，查询时要求草图的叶子哈希等于记录的值，分析方因此不必
+//! 物化完整结果就能得到经过验证的近似基数。
+//!
+//! 注意：HyperLogLog 不支持删除，估计值反映的是曾经加入过的不同 fid 数量。
+
+use sha2::{Digest, Sha256};
+
+/// 精度参数：寄存器数量为 2^PRECISION
+pub const PRECISION: u32 = 10;
+
+/// 寄存器数量
+pub const NUM_REGISTERS: usize = 1 << PRECISION;
+
+/// HyperLogLog 草图
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// 创建空草图
+    pub fn new() -> Self {
+        HyperLogLog {
+            registers: vec![0; NUM_REGISTERS],
+        }
+    }
+
+    /// 由一组元素构建草图
+    pub fn from_items<'a>(items: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut sketch = Self::new();
+        items.into_iter().for_each(|item| sketch.insert(item));
+        sketch
+    }
+
+    /// 插入一个元素
+    pub fn insert(&mut self, item: &str) {
+        let digest = Sha256::digest(item.as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        let hash = u64::from_le_bytes(bytes);
+
+        let index = (hash >> (64 - PRECISION)) as usize;
+        let rest = hash << PRECISION;
+        // rank = 剩余位中第一个 1 的位置（从 1 开始）
+        let rank = (rest.leading_zeros().min(64 - PRECISION) + 1) as u8;
+
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    /// 估计不同元素的数量
+    pub fn estimate(&self) -> f64 {
+        let m = NUM_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let raw = alpha * m * m / sum;
+
+        // 小基数修正（线性计数）
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        }
+    }
+
+    /// 合并另一个草图（取寄存器最大值）
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (a, b) in self.registers.iter_mut().zip(other.registers.iter()) {
+            *a = (*a).max(*b);
+        }
+    }
+
+    /// 序列化为字节
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.registers.clone()
+    }
+
+    /// 从字节反序列化
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != NUM_REGISTERS {
+            return None;
+        }
+        Some(HyperLogLog {
+            registers: bytes.to_vec(),
+        })
+    }
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_is_close() {
+        let mut hll = HyperLogLog::new();
+        for i in 0..5000 {
+            hll.insert(&format!("file{}", i));
+            // 重复插入不影响估计
+            hll.insert(&format!("file{}", i));
+        }
+        let estimate = hll.estimate();
+        assert!((estimate - 5000.0).abs() / 5000.0 < 0.1, "{}", estimate);
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let mut hll = HyperLogLog::new();
+        hll.insert("file1");
+        let restored = HyperLogLog::from_bytes(&hll.to_bytes()).unwrap();
+        assert_eq!(hll, restored);
+        assert!(HyperLogLog::from_bytes(&[0u8; 3]).is_none());
+    }
+}
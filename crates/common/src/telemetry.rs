@@ -0,0 +1,420 @@
+//! 分布式追踪和日志
+//!
+//! 一次客户端请求在 Manager 上扇出到多个 storager，各进程的 span 通过同一个 trace id 关联。
+//! 追踪上下文以 W3C Trace Context 的 `traceparent` 格式放在 gRPC metadata 中：
+//!
+//! - 服务端用 [`Traced`] 包装 gRPC 服务，为每个 RPC 创建 `rpc` span，
+//!   请求带有 `traceparent` 时沿用其中的 trace id，否则开始新的 trace；
+//!   处理期间的上下文保存在 task-local 中（见 [`TraceContext::current`]）
+//! - 客户端存根经过 [`TraceInterceptor`]，把当前上下文写入发出的请求，
+//!   下游的 span 因此成为当前 RPC 的子 span
+//!
+//! 启用 `otlp` feature 并设置 `OTEL_EXPORTER_OTLP_ENDPOINT` 后，
+//! [`init_tracing`] 同时通过 OTLP 导出 span，span id 与 `traceparent` 中传递的一致。
+//! 日志级别和格式（文本或 JSON）由 [`LogConfig`] 控制，级别可以在运行时用
+//! [`set_log_level`] 调整。
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tonic::codegen::http;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::server::NamedService;
+use tonic::service::interceptor::InterceptedService;
+use tonic::service::Interceptor;
+use tonic::transport::Channel;
+use tonic::{Request, Status};
+use tower::Service;
+use tracing::Instrument;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// 携带追踪上下文的 metadata 键
+pub const TRACEPARENT_METADATA_KEY: &str = "traceparent";
+
+/// [`init_tracing`] 安装的过滤层，[`set_log_level`] 通过它替换过滤指令
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+/// 一个 span 在 trace 中的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: u128,
+    pub span_id: u64,
+}
+
+impl TraceContext {
+    /// 开始新的 trace
+    pub fn new_root() -> Self {
+        TraceContext {
+            trace_id: (u128::from(random_id()) << 64) | u128::from(random_id()),
+            span_id: random_id(),
+        }
+    }
+
+    /// 同一 trace 中的新 span
+    pub fn child(&self) -> Self {
+        TraceContext {
+            trace_id: self.trace_id,
+            span_id: random_id(),
+        }
+    }
+
+    /// 当前 RPC 的上下文，不在 [`Traced`] 服务的处理过程中时为 None
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(|context| *context).ok()
+    }
+
+    /// 以 `self` 作为当前上下文执行 `future`
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+
+    pub fn trace_id_hex(&self) -> String {
+        format!("{:032x}", self.trace_id)
+    }
+
+    pub fn span_id_hex(&self) -> String {
+        format!("{:016x}", self.span_id)
+    }
+
+    /// `traceparent` 格式：`00-<trace id>-<span id>-01`
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-01", self.trace_id_hex(), self.span_id_hex())
+    }
+
+    /// 解析 `traceparent`，格式不对或 id 全为零时返回 None
+    pub fn parse(traceparent: &str) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let (version, trace_id, span_id, flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        let hex = |s: &str, len: usize| s.len() == len && s.bytes().all(|b| b.is_ascii_hexdigit());
+        if !hex(version, 2) || version == "ff" || !hex(flags, 2) {
+            return None;
+        }
+        // 版本 00 恰好四段，更高的版本允许在后面追加字段
+        if version == "00" && parts.next().is_some() {
+            return None;
+        }
+        if !hex(trace_id, 32) || !hex(span_id, 16) {
+            return None;
+        }
+        let context = TraceContext {
+            trace_id: u128::from_str_radix(trace_id, 16).ok()?,
+            span_id: u64::from_str_radix(span_id, 16).ok()?,
+        };
+        (context.trace_id != 0 && context.span_id != 0).then_some(context)
+    }
+
+    /// 读取请求 metadata 中的上下文
+    pub fn from_metadata(metadata: &MetadataMap) -> Option<Self> {
+        Self::parse(metadata.get(TRACEPARENT_METADATA_KEY)?.to_str().ok()?)
+    }
+
+    /// 把上下文写入请求 metadata
+    pub fn inject(&self, metadata: &mut MetadataMap) {
+        if let Ok(value) = MetadataValue::try_from(self.traceparent()) {
+            metadata.insert(TRACEPARENT_METADATA_KEY, value);
+        }
+    }
+}
+
+/// 非零的随机 id
+fn random_id() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    loop {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        let id = hasher.finish();
+        if id != 0 {
+            return id;
+        }
+    }
+}
+
+/// 把当前 RPC 的追踪上下文写入发出的请求
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceInterceptor;
+
+impl Interceptor for TraceInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(context) = TraceContext::current() {
+            context.inject(request.metadata_mut());
+        }
+        Ok(request)
+    }
+}
+
+/// 传递追踪上下文的客户端连接
+pub type TracedChannel = InterceptedService<Channel, TraceInterceptor>;
+
+/// 为每个 RPC 创建 span 并设置当前追踪上下文的 gRPC 服务包装
+///
+/// 响应的 metadata 中带回本次 RPC 的 `traceparent`，调用方可以据此查找整条 trace
+#[derive(Debug, Clone)]
+pub struct Traced<S> {
+    inner: S,
+}
+
+impl<S> Traced<S> {
+    pub fn new(inner: S) -> Self {
+        Traced { inner }
+    }
+}
+
+impl<S: NamedService> NamedService for Traced<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S, B, R> Service<http::Request<B>> for Traced<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<R>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let parent = request
+            .headers()
+            .get(TRACEPARENT_METADATA_KEY)
+            .and_then(|value| value.to_str().ok())
+            .and_then(TraceContext::parse);
+        let context = parent.map_or_else(TraceContext::new_root, |parent| parent.child());
+        let span = tracing::info_span!(
+            "rpc",
+            method = request.uri().path(),
+            trace_id = tracing::field::Empty,
+            span_id = tracing::field::Empty,
+            parent_span_id = parent.map(|parent| parent.span_id_hex()),
+        );
+        #[cfg(feature = "otlp")]
+        let context = otlp::attach(&span, parent, context);
+        span.record("trace_id", context.trace_id_hex());
+        span.record("span_id", context.span_id_hex());
+
+        let future = self.inner.call(request);
+        let handled = async move {
+            let started = Instant::now();
+            let mut response = future.await?;
+            tracing::debug!(
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                "rpc finished"
+            );
+            if let Ok(value) = http::HeaderValue::from_str(&context.traceparent()) {
+                response
+                    .headers_mut()
+                    .insert(TRACEPARENT_METADATA_KEY, value);
+            }
+            Ok(response)
+        };
+        Box::pin(context.scope(handled).instrument(span))
+    }
+}
+
+/// 日志输出设置
+#[derive(Debug, Clone, Default)]
+pub struct LogConfig {
+    /// 过滤指令，如 `debug` 或 `info,manager=debug`；未指定时使用 `RUST_LOG`，都没有时为 `info`
+    pub level: Option<String>,
+    /// 每个事件输出一行 JSON，便于日志系统采集
+    pub json: bool,
+}
+
+impl LogConfig {
+    fn filter(&self) -> Result<EnvFilter, String> {
+        match &self.level {
+            Some(level) => EnvFilter::try_new(level)
+                .map_err(|e| format!("Invalid log level '{}': {}", level, e)),
+            None => {
+                Ok(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+            }
+        }
+    }
+}
+
+/// 安装全局 tracing subscriber
+///
+/// 事件按 `log` 的设置过滤后输出到标准错误，依赖库通过 `log` 发出的记录也一并输出。
+/// 启用 `otlp` feature 且设置了 `OTEL_EXPORTER_OTLP_ENDPOINT` 时，span 同时以
+/// `service_name` 的名义通过 OTLP 导出（必须在 Tokio 运行时中调用）
+pub fn init_tracing(service_name: &str, log: &LogConfig) -> Result<(), String> {
+    use tracing_subscriber::fmt;
+
+    let (filter, handle) = reload::Layer::new(log.filter()?);
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(
+            log.json
+                .then(|| fmt::layer().json().with_writer(std::io::stderr)),
+        )
+        .with((!log.json).then(|| fmt::layer().with_writer(std::io::stderr)));
+    #[cfg(feature = "otlp")]
+    let registry = registry.with(otlp::layer(service_name)?);
+    #[cfg(not(feature = "otlp"))]
+    let _ = service_name;
+    registry.try_init().map_err(|e| e.to_string())?;
+    let _ = FILTER.set(handle);
+    Ok(())
+}
+
+/// 运行时替换日志过滤指令（格式同 [`LogConfig::level`]）
+///
+/// 指令无效或尚未调用 [`init_tracing`] 时返回错误，原有过滤不变
+pub fn set_log_level(level: &str) -> Result<(), String> {
+    let filter = LogConfig {
+        level: Some(level.to_string()),
+        json: false,
+    }
+    .filter()?;
+    FILTER
+        .get()
+        .ok_or_else(|| "tracing is not initialized".to_string())?
+        .reload(filter)
+        .map_err(|e| format!("Failed to reload log filter: {}", e))
+}
+
+#[cfg(feature = "otlp")]
+mod otlp {
+    use super::TraceContext;
+    use opentelemetry::trace::{
+        SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState,
+    };
+    use opentelemetry::KeyValue;
+    use opentelemetry_sdk::trace::Tracer;
+    use opentelemetry_sdk::Resource;
+    use tracing::Subscriber;
+    use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+    use tracing_subscriber::registry::LookupSpan;
+
+    /// 导出端点的环境变量
+    const ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+    /// 设置了导出端点时构造 OTLP 导出层
+    pub(super) fn layer<S>(
+        service_name: &str,
+    ) -> Result<Option<OpenTelemetryLayer<S, Tracer>>, String>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        if std::env::var_os(ENDPOINT_ENV).is_none() {
+            return Ok(None);
+        }
+        let resource = Resource::new(vec![KeyValue::new(
+            "service.name",
+            service_name.to_string(),
+        )]);
+        let tracer = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(opentelemetry_otlp::new_exporter().tonic())
+            .with_trace_config(opentelemetry_sdk::trace::config().with_resource(resource))
+            .install_batch(opentelemetry_sdk::runtime::Tokio)
+            .map_err(|e| format!("Failed to install OTLP exporter: {}", e))?;
+        Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+    }
+
+    /// 把 `span` 接到远端的父 span 上，返回导出时使用的上下文
+    ///
+    /// 没有安装导出层时 span 没有 OpenTelemetry 上下文，沿用 `context`
+    pub(super) fn attach(
+        span: &tracing::Span,
+        parent: Option<TraceContext>,
+        context: TraceContext,
+    ) -> TraceContext {
+        if let Some(parent) = parent {
+            let remote = SpanContext::new(
+                TraceId::from_bytes(parent.trace_id.to_be_bytes()),
+                SpanId::from_bytes(parent.span_id.to_be_bytes()),
+                TraceFlags::SAMPLED,
+                true,
+                TraceState::default(),
+            );
+            span.set_parent(opentelemetry::Context::new().with_remote_span_context(remote));
+        }
+        let exported = span.context();
+        let exported = exported.span().span_context().clone();
+        if !exported.is_valid() {
+            return context;
+        }
+        TraceContext {
+            trace_id: u128::from_be_bytes(exported.trace_id().to_bytes()),
+            span_id: u64::from_be_bytes(exported.span_id().to_bytes()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent_round_trip() {
+        let context = TraceContext::new_root();
+        assert_eq!(TraceContext::parse(&context.traceparent()), Some(context));
+
+        let child = context.child();
+        assert_eq!(child.trace_id, context.trace_id);
+        assert_ne!(child.span_id, context.span_id);
+
+        let parsed =
+            TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        assert_eq!(parsed.trace_id, 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(parsed.span_id, 0x00f067aa0ba902b7);
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            assert_eq!(TraceContext::parse(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_log_level_is_validated() {
+        let config = |level: &str| LogConfig {
+            level: Some(level.to_string()),
+            json: false,
+        };
+        assert!(config("debug").filter().is_ok());
+        assert!(config("info,manager=trace").filter().is_ok());
+        assert!(config("manager=loud").filter().is_err());
+
+        // 无效指令在检查是否已初始化之前就被拒绝
+        let err = set_log_level("manager=loud").unwrap_err();
+        assert!(err.contains("Invalid log level"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_interceptor_injects_current_context() {
+        let mut interceptor = TraceInterceptor;
+        let request = interceptor.call(Request::new(())).unwrap();
+        assert_eq!(TraceContext::from_metadata(request.metadata()), None);
+
+        let context = TraceContext::new_root();
+        let request = context
+            .scope(async { interceptor.call(Request::new(())).unwrap() })
+            .await;
+        assert_eq!(
+            TraceContext::from_metadata(request.metadata()),
+            Some(context)
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! TLS 和双向 TLS 配置
+//!
+//! 服务端需要本端证书和私钥；要求客户端证书时还需要签发客户端证书的 CA，
+//! 这样只有持有集群证书的 Manager 和 storager 才能调用 StoragerService。
+//! 客户端用 CA 验证服务端证书，配置了本端证书时在握手中出示（双向 TLS）。
+//!
+//! Manager 和 storager 用同一份证书既提供服务又连接其他节点，
+//! 证书如果带有扩展密钥用法，需要同时包含 serverAuth 和 clientAuth。
+//!
+//! TLS 只用于 `https://` 地址；`unix:` 地址仍是明文，由套接字文件的权限保护。
+
+use std::path::Path;
+use tonic::transport::{Certificate, ClientTlsConfig, Identity, ServerTlsConfig};
+
+/// TLS 证书和验证策略
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    /// 本端证书和私钥
+    identity: Option<Identity>,
+    /// 用于验证对端证书的 CA
+    ca: Option<Certificate>,
+    /// 作为服务端时是否要求客户端出示由 `ca` 签发的证书
+    require_client_cert: bool,
+    /// 验证服务端证书时使用的域名，默认取连接地址的主机名
+    domain: Option<String>,
+}
+
+impl TlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从 PEM 文件读取证书、私钥和 CA，未给出的路径保持为空
+    pub fn load(
+        cert: Option<&Path>,
+        key: Option<&Path>,
+        ca: Option<&Path>,
+    ) -> Result<Self, String> {
+        let read = |path: &Path| {
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+        };
+        let mut config = TlsConfig::new();
+        match (cert, key) {
+            (Some(cert), Some(key)) => config = config.with_identity(read(cert)?, read(key)?),
+            (None, None) => {}
+            _ => return Err("TLS certificate and key must be given together".to_string()),
+        }
+        if let Some(ca) = ca {
+            config = config.with_ca(read(ca)?);
+        }
+        Ok(config)
+    }
+
+    /// 设置本端证书链和私钥（PEM）
+    pub fn with_identity(mut self, cert_pem: impl AsRef<[u8]>, key_pem: impl AsRef<[u8]>) -> Self {
+        self.identity = Some(Identity::from_pem(cert_pem, key_pem));
+        self
+    }
+
+    /// 设置用于验证对端证书的 CA（PEM）
+    pub fn with_ca(mut self, ca_pem: impl AsRef<[u8]>) -> Self {
+        self.ca = Some(Certificate::from_pem(ca_pem));
+        self
+    }
+
+    /// 作为服务端时要求客户端证书（双向 TLS）
+    pub fn require_client_cert(mut self, require: bool) -> Self {
+        self.require_client_cert = require;
+        self
+    }
+
+    /// 设置验证服务端证书时使用的域名
+    pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// 服务端配置；缺少本端证书，或要求客户端证书但没有 CA 时返回错误
+    pub fn server_config(&self) -> Result<ServerTlsConfig, String> {
+        let identity = self
+            .identity
+            .clone()
+            .ok_or("TLS server requires a certificate and key")?;
+        let config = ServerTlsConfig::new().identity(identity);
+        if !self.require_client_cert {
+            return Ok(config);
+        }
+        let ca = self
+            .ca
+            .clone()
+            .ok_or("Requiring client certificates needs a CA to verify them")?;
+        Ok(config.client_ca_root(ca))
+    }
+
+    /// 客户端配置
+    pub fn client_config(&self) -> ClientTlsConfig {
+        let mut config = ClientTlsConfig::new();
+        if let Some(ca) = &self.ca {
+            config = config.ca_certificate(ca.clone());
+        }
+        if let Some(identity) = &self.identity {
+            config = config.identity(identity.clone());
+        }
+        if let Some(domain) = &self.domain {
+            config = config.domain_name(domain.clone());
+        }
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_config_requirements() {
+        assert!(TlsConfig::new().server_config().is_err());
+
+        let config = TlsConfig::new().with_identity("cert", "key");
+        assert!(config.server_config().is_ok());
+        assert!(config
+            .clone()
+            .require_client_cert(true)
+            .server_config()
+            .is_err());
+        assert!(config
+            .with_ca("ca")
+            .require_client_cert(true)
+            .server_config()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_load_requires_cert_and_key_together() {
+        let cert = std::env::temp_dir().join(format!("common-tls-{}.pem", std::process::id()));
+        std::fs::write(&cert, "cert").unwrap();
+        assert!(TlsConfig::load(Some(&cert), None, None).is_err());
+        assert!(TlsConfig::load(Some(&cert), Some(&cert), Some(&cert)).is_ok());
+        assert!(TlsConfig::load(None, None, Some(Path::new("/nonexistent/ca.pem"))).is_err());
+        std::fs::remove_file(&cert).unwrap();
+    }
+}
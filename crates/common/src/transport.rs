@@ -0,0 +1,474 @@
+//! gRPC 传输参数
+//!
+//! tonic 默认最多接收 4 MiB 的消息，较大的 MPT 证明和累加器公开参数会超过这个限制。
+//! [`TransportConfig`] 统一设置消息大小上限、压缩算法和 HTTP/2 keepalive，
+//! Manager、Storager 和 Client 创建连接、客户端存根和服务时都通过它应用这些参数。
+//!
+//! 压缩只决定本端发送时使用的算法；接收方向始终接受 gzip 和 zstd，
+//! 因此两端可以配置不同的压缩算法。配置了 [`TlsConfig`] 时，服务和 `https://` 连接启用 TLS。
+
+use crate::rpc::admin_service_client::AdminServiceClient;
+use crate::rpc::admin_service_server::{AdminService, AdminServiceServer};
+use crate::rpc::manager_service_client::ManagerServiceClient;
+use crate::rpc::manager_service_server::{ManagerService, ManagerServiceServer};
+use crate::rpc::storager_service_client::StoragerServiceClient;
+use crate::rpc::storager_service_server::{StoragerService, StoragerServiceServer};
+use crate::telemetry::{TraceInterceptor, TracedChannel};
+use crate::tls::TlsConfig;
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::codec::CompressionEncoding;
+use tonic::transport::{Channel, Endpoint, Server};
+
+/// 默认的单条消息大小上限（发送和接收）
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+/// 默认的 keepalive 应答超时时间
+pub const DEFAULT_KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// 消息压缩算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// 解析 `none`、`gzip` 或 `zstd`，`none` 返回 None
+    pub fn parse(name: &str) -> Result<Option<Self>, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "none" | "" => Ok(None),
+            "gzip" => Ok(Some(Compression::Gzip)),
+            "zstd" => Ok(Some(Compression::Zstd)),
+            other => Err(format!(
+                "Unknown compression '{}' (expected none, gzip or zstd)",
+                other
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    fn encoding(self) -> CompressionEncoding {
+        match self {
+            Compression::Gzip => CompressionEncoding::Gzip,
+            Compression::Zstd => CompressionEncoding::Zstd,
+        }
+    }
+}
+
+/// gRPC 传输参数
+#[derive(Debug, Clone)]
+pub struct TransportConfig {
+    /// 单条消息大小上限（字节），发送和接收都适用
+    pub max_message_size: usize,
+    /// 发送时使用的压缩算法，None 表示不压缩
+    pub compression: Option<Compression>,
+    /// HTTP/2 keepalive ping 间隔，None 表示不发送
+    pub keepalive_interval: Option<Duration>,
+    /// keepalive ping 的应答超时时间，超时后关闭连接
+    pub keepalive_timeout: Duration,
+    /// TLS 配置，None 表示明文
+    pub tls: Option<TlsConfig>,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        TransportConfig {
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            compression: None,
+            keepalive_interval: None,
+            keepalive_timeout: DEFAULT_KEEPALIVE_TIMEOUT,
+            tls: None,
+        }
+    }
+}
+
+impl TransportConfig {
+    /// 设置单条消息大小上限
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// 设置发送时使用的压缩算法
+    pub fn with_compression(mut self, compression: Option<Compression>) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// 设置 keepalive ping 间隔
+    pub fn with_keepalive(mut self, interval: Option<Duration>) -> Self {
+        self.keepalive_interval = interval;
+        self
+    }
+
+    /// 设置 TLS
+    pub fn with_tls(mut self, tls: Option<TlsConfig>) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    /// 为连接端点应用 keepalive 和 TLS 设置
+    pub fn endpoint(&self, endpoint: Endpoint) -> Result<Endpoint, tonic::transport::Error> {
+        let endpoint = match &self.tls {
+            Some(tls) => endpoint.tls_config(tls.client_config())?,
+            None => endpoint,
+        };
+        Ok(match self.keepalive_interval {
+            Some(interval) => endpoint
+                .http2_keep_alive_interval(interval)
+                .keep_alive_timeout(self.keepalive_timeout)
+                .keep_alive_while_idle(true),
+            None => endpoint,
+        })
+    }
+
+    /// 应用了 keepalive 和 TLS 设置的服务构造器
+    pub fn server(&self) -> Result<Server, String> {
+        let server = Server::builder()
+            .http2_keepalive_interval(self.keepalive_interval)
+            .http2_keepalive_timeout(Some(self.keepalive_timeout));
+        match &self.tls {
+            Some(tls) => server
+                .tls_config(tls.server_config()?)
+                .map_err(|e| format!("Invalid TLS configuration: {}", e)),
+            None => Ok(server),
+        }
+    }
+
+    /// 到 storager 的客户端存根，请求携带当前的追踪上下文
+    pub fn storager_client(&self, channel: Channel) -> StoragerServiceClient<TracedChannel> {
+        let client = StoragerServiceClient::with_interceptor(channel, TraceInterceptor)
+            .max_decoding_message_size(self.max_message_size)
+            .max_encoding_message_size(self.max_message_size)
+            .accept_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Zstd);
+        match self.compression {
+            Some(compression) => client.send_compressed(compression.encoding()),
+            None => client,
+        }
+    }
+
+    /// 到 Manager 的客户端存根，请求携带当前的追踪上下文
+    pub fn manager_client(&self, channel: Channel) -> ManagerServiceClient<TracedChannel> {
+        let client = ManagerServiceClient::with_interceptor(channel, TraceInterceptor)
+            .max_decoding_message_size(self.max_message_size)
+            .max_encoding_message_size(self.max_message_size)
+            .accept_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Zstd);
+        match self.compression {
+            Some(compression) => client.send_compressed(compression.encoding()),
+            None => client,
+        }
+    }
+
+    /// 到 Manager 管理服务的客户端存根，请求携带当前的追踪上下文
+    pub fn admin_client(&self, channel: Channel) -> AdminServiceClient<TracedChannel> {
+        let client = AdminServiceClient::with_interceptor(channel, TraceInterceptor)
+            .max_decoding_message_size(self.max_message_size)
+            .max_encoding_message_size(self.max_message_size)
+            .accept_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Zstd);
+        match self.compression {
+            Some(compression) => client.send_compressed(compression.encoding()),
+            None => client,
+        }
+    }
+
+    /// storager 服务
+    pub fn storager_server<S: StoragerService>(&self, service: Arc<S>) -> StoragerServiceServer<S> {
+        let server = StoragerServiceServer::from_arc(service)
+            .max_decoding_message_size(self.max_message_size)
+            .max_encoding_message_size(self.max_message_size)
+            .accept_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Zstd);
+        match self.compression {
+            Some(compression) => server.send_compressed(compression.encoding()),
+            None => server,
+        }
+    }
+
+    /// Manager 服务
+    pub fn manager_server<S: ManagerService>(&self, service: Arc<S>) -> ManagerServiceServer<S> {
+        let server = ManagerServiceServer::from_arc(service)
+            .max_decoding_message_size(self.max_message_size)
+            .max_encoding_message_size(self.max_message_size)
+            .accept_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Zstd);
+        match self.compression {
+            Some(compression) => server.send_compressed(compression.encoding()),
+            None => server,
+        }
+    }
+
+    /// Manager 的管理服务
+    pub fn admin_server<S: AdminService>(&self, service: Arc<S>) -> AdminServiceServer<S> {
+        let server = AdminServiceServer::from_arc(service)
+            .max_decoding_message_size(self.max_message_size)
+            .max_encoding_message_size(self.max_message_size)
+            .accept_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Zstd);
+        match self.compression {
+            Some(compression) => server.send_compressed(compression.encoding()),
+            None => server,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::{bind_tcp, connect_with, serve_listeners, Listeners};
+    use crate::rpc::proof::Kind;
+    use crate::rpc::storager_service_server::StoragerService;
+    use crate::rpc::*;
+    use tonic::{Request, Response, Status, Streaming};
+
+    #[test]
+    fn test_parse_compression() {
+        assert_eq!(Compression::parse("none"), Ok(None));
+        assert_eq!(Compression::parse("ZSTD"), Ok(Some(Compression::Zstd)));
+        assert_eq!(
+            Compression::parse("gzip").unwrap().map(|c| c.name()),
+            Some("gzip")
+        );
+        assert!(Compression::parse("brotli").is_err());
+    }
+
+    const PROOF_SIZE: usize = 5 * 1024 * 1024;
+
+    /// Query 返回超过 tonic 默认 4 MiB 上限的证明，其余方法不会被调用
+    ///
+    /// 证明是伪随机字节，压缩后仍然超过 4 MiB
+    struct LargeResponse;
+
+    #[tonic::async_trait]
+    impl StoragerService for LargeResponse {
+        async fn add(
+            &self,
+            _: Request<StoragerAddRequest>,
+        ) -> Result<Response<StoragerAddResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn batch_add(
+            &self,
+            _: Request<StoragerBatchAddRequest>,
+        ) -> Result<Response<StoragerBatchAddResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn query(
+            &self,
+            _: Request<StoragerQueryRequest>,
+        ) -> Result<Response<StoragerQueryResponse>, Status> {
+            let mut state = 0x9e37_79b9_7f4a_7c15u64;
+            let proof = (0..PROOF_SIZE)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect();
+            Ok(Response::new(StoragerQueryResponse {
+                proof: Some(Proof {
+                    kind: Some(Kind::Mpt(proof)),
+                }),
+                ..Default::default()
+            }))
+        }
+
+        async fn prove_difference(
+            &self,
+            _: Request<ProveDifferenceRequest>,
+        ) -> Result<Response<ProveDifferenceResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn boolean_query(
+            &self,
+            _: Request<StoragerBooleanQueryRequest>,
+        ) -> Result<Response<StoragerBooleanQueryResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn delete(
+            &self,
+            _: Request<StoragerDeleteRequest>,
+        ) -> Result<Response<StoragerDeleteResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn approx_count(
+            &self,
+            _: Request<StoragerApproxCountRequest>,
+        ) -> Result<Response<StoragerApproxCountResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn health(
+            &self,
+            _: Request<StoragerHealthRequest>,
+        ) -> Result<Response<StoragerHealthResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn flush(&self, _: Request<FlushRequest>) -> Result<Response<FlushResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn stats(
+            &self,
+            _: Request<StoragerStatsRequest>,
+        ) -> Result<Response<StoragerStatsResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn keyword_filter(
+            &self,
+            _: Request<KeywordFilterRequest>,
+        ) -> Result<Response<KeywordFilterResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn list_expired(
+            &self,
+            _: Request<ListExpiredRequest>,
+        ) -> Result<Response<ListExpiredResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn list_keywords(
+            &self,
+            _: Request<ListKeywordsRequest>,
+        ) -> Result<Response<ListKeywordsResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn list_namespaces(
+            &self,
+            _: Request<ListNamespacesRequest>,
+        ) -> Result<Response<ListNamespacesResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn migrate_out(
+            &self,
+            _: Request<MigrateOutRequest>,
+        ) -> Result<Response<MigrateOutResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn migrate_in(
+            &self,
+            _: Request<Streaming<MigrationEntry>>,
+        ) -> Result<Response<MigrateInResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn bulk_add(
+            &self,
+            _: Request<Streaming<BulkAddRecord>>,
+        ) -> Result<Response<StoragerBulkAddResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn range_query(
+            &self,
+            _: Request<RangeQueryRequest>,
+        ) -> Result<Response<StoragerRangeQueryResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn query_by_prefix(
+            &self,
+            _: Request<PrefixQueryRequest>,
+        ) -> Result<Response<StoragerPrefixQueryResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn prune(&self, _: Request<PruneRequest>) -> Result<Response<PruneResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn query_at_root(
+            &self,
+            _: Request<QueryAtRootRequest>,
+        ) -> Result<Response<QueryAtRootResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn list_root_history(
+            &self,
+            _: Request<ListRootHistoryRequest>,
+        ) -> Result<Response<ListRootHistoryResponse>, Status> {
+            unimplemented!()
+        }
+
+        async fn get_proof(
+            &self,
+            _: Request<GetProofRequest>,
+        ) -> Result<Response<GetProofResponse>, Status> {
+            unimplemented!()
+        }
+
+        type QueryStreamStream = tokio_stream::Empty<Result<StoragerQueryChunk, Status>>;
+
+        async fn query_stream(
+            &self,
+            _: Request<StoragerQueryRequest>,
+        ) -> Result<Response<Self::QueryStreamStream>, Status> {
+            unimplemented!()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_large_compressed_messages() {
+        let server_config = TransportConfig::default().with_compression(Some(Compression::Zstd));
+        let service = server_config.storager_server(Arc::new(LargeResponse));
+        let listeners = Listeners {
+            tcp: vec![bind_tcp("127.0.0.1:0".parse().unwrap()).unwrap()],
+            ..Default::default()
+        };
+        let addr = format!("http://{}", listeners.tcp[0].local_addr().unwrap());
+        tokio::spawn(async move {
+            let server = server_config.server().unwrap();
+            let router = move || server.clone().add_service(service.clone());
+            serve_listeners(listeners, router, std::future::pending())
+                .await
+                .unwrap()
+        });
+
+        // 4 MiB 的上限拒绝响应
+        let small = TransportConfig::default().with_max_message_size(4 * 1024 * 1024);
+        let channel = connect_with(&addr, &small).await.unwrap();
+        let status = small
+            .storager_client(channel)
+            .query(StoragerQueryRequest::default())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::OutOfRange);
+
+        // 默认上限足够；客户端用 gzip 发送，两端的压缩算法可以不同
+        let config = TransportConfig::default()
+            .with_compression(Some(Compression::Gzip))
+            .with_keepalive(Some(Duration::from_secs(10)));
+        let channel = connect_with(&addr, &config).await.unwrap();
+        let response = config
+            .storager_client(channel)
+            .query(StoragerQueryRequest::default())
+            .await
+            .unwrap()
+            .into_inner();
+        match response.proof.and_then(|proof| proof.kind) {
+            Some(Kind::Mpt(bytes)) => assert_eq!(bytes.len(), PROOF_SIZE),
+            _ => panic!("expected an MPT proof"),
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use serde::{Deserialize, Serialize};
+use crate::net::ListenConfig;
+use crate::rpc::{self, proof::Kind};
+use prost::Message;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::path::Path;
 
 // Unique file identifier
 pub type Fid = String;
@@ -6,23 +10,194 @@ pub type Fid = String;
 // Keyword for search
 pub type Keyword = String;
 
+// 以 `prefix` 开头的 keyword 构成的范围 [prefix, end) 的上界，空字符串表示没有上界
+// UTF-8 的字节序与码点序一致，把最后一个还能递增的字符加一即可（例如 "category:" -> "category;"）
+pub fn prefix_range_end(prefix: &str) -> String {
+    let mut chars: Vec<char> = prefix.chars().collect();
+    while let Some(last) = chars.pop() {
+        if let Some(next) = (last as u32 + 1..=char::MAX as u32).find_map(char::from_u32) {
+            chars.push(next);
+            return chars.into_iter().collect();
+        }
+    }
+    String::new()
+}
+
 // Hash of a data chunk or a Merkle tree root
 pub type RootHash = Vec<u8>;
 
-// Proof of storage or query
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Proof {
-    pub data: Vec<u8>,
+// Proof of storage or query, tagged with what it proves
+// 各变体的字节格式见 proto 中的 Proof 消息；验证方按变体解码，而不是按 ADS 模式猜测格式
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Proof {
+    AccumulatorAdd(Vec<u8>),
+    AccumulatorDelete(Vec<u8>),
+    AccumulatorMembership(Vec<u8>),
+    AccumulatorIntersection(Vec<u8>),
+    AccumulatorNonMembership(Vec<u8>),
+    Mpt(Vec<u8>),
+    Merkle(Vec<u8>),
+    Smt(Vec<u8>),
+    Custom(Vec<u8>),
+    // 结果中含驻留 fid 的查询证明（见 [`crate::fid_intern::InternedProof`]）
+    Interned(Vec<u8>),
+}
+
+impl Proof {
+    // 证明所属的 ADS 模式；第三方模式返回 None
+    pub fn ads_mode(&self) -> Option<AdsMode> {
+        match self {
+            Proof::AccumulatorAdd(_)
+            | Proof::AccumulatorDelete(_)
+            | Proof::AccumulatorMembership(_)
+            | Proof::AccumulatorIntersection(_)
+            | Proof::AccumulatorNonMembership(_) => Some(AdsMode::CryptoAccumulator),
+            Proof::Mpt(_) => Some(AdsMode::Mpt),
+            Proof::Merkle(_) => Some(AdsMode::MerkleTree),
+            Proof::Smt(_) => Some(AdsMode::SparseMerkleTree),
+            Proof::Custom(_) => None,
+            Proof::Interned(data) => crate::fid_intern::InternedProof::from_bytes(data)
+                .and_then(|interned| interned.proof.ads_mode()),
+        }
+    }
+
+    // 变体携带的证明数据
+    pub fn data(&self) -> &[u8] {
+        match self {
+            Proof::AccumulatorAdd(data)
+            | Proof::AccumulatorDelete(data)
+            | Proof::AccumulatorMembership(data)
+            | Proof::AccumulatorIntersection(data)
+            | Proof::AccumulatorNonMembership(data)
+            | Proof::Mpt(data)
+            | Proof::Merkle(data)
+            | Proof::Smt(data)
+            | Proof::Custom(data)
+            | Proof::Interned(data) => data,
+        }
+    }
+
+    // 按 protobuf 编码，用于持久化（例如审计日志）
+    pub fn to_bytes(&self) -> Vec<u8> {
+        rpc::Proof::from(self.clone()).encode_to_vec()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let proof = rpc::Proof::decode(bytes).map_err(|e| e.to_string())?;
+        Proof::try_from(proof)
+    }
+}
+
+impl From<Proof> for rpc::Proof {
+    fn from(proof: Proof) -> rpc::Proof {
+        let kind = match proof {
+            Proof::AccumulatorAdd(data) => Kind::AccumulatorAdd(data),
+            Proof::AccumulatorDelete(data) => Kind::AccumulatorDelete(data),
+            Proof::AccumulatorMembership(data) => Kind::AccumulatorMembership(data),
+            Proof::AccumulatorIntersection(data) => Kind::AccumulatorIntersection(data),
+            Proof::AccumulatorNonMembership(data) => Kind::AccumulatorNonMembership(data),
+            Proof::Mpt(data) => Kind::Mpt(data),
+            Proof::Merkle(data) => Kind::Merkle(data),
+            Proof::Smt(data) => Kind::Smt(data),
+            Proof::Custom(data) => Kind::Custom(data),
+            Proof::Interned(data) => Kind::Interned(data),
+        };
+        rpc::Proof { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<rpc::Proof> for Proof {
+    type Error = String;
+
+    fn try_from(proof: rpc::Proof) -> Result<Self, Self::Error> {
+        Ok(match proof.kind.ok_or("Proof has no kind")? {
+            Kind::AccumulatorAdd(data) => Proof::AccumulatorAdd(data),
+            Kind::AccumulatorDelete(data) => Proof::AccumulatorDelete(data),
+            Kind::AccumulatorMembership(data) => Proof::AccumulatorMembership(data),
+            Kind::AccumulatorIntersection(data) => Proof::AccumulatorIntersection(data),
+            Kind::AccumulatorNonMembership(data) => Proof::AccumulatorNonMembership(data),
+            Kind::Mpt(data) => Proof::Mpt(data),
+            Kind::Merkle(data) => Proof::Merkle(data),
+            Kind::Smt(data) => Proof::Smt(data),
+            Kind::Custom(data) => Proof::Custom(data),
+            Kind::Interned(data) => Proof::Interned(data),
+        })
+    }
+}
+
+impl TryFrom<Option<rpc::Proof>> for Proof {
+    type Error = String;
+
+    fn try_from(proof: Option<rpc::Proof>) -> Result<Self, Self::Error> {
+        Proof::try_from(proof.ok_or("missing proof")?)
+    }
+}
+
+impl rpc::ProofMetrics {
+    // 累加另一组证明的验证代价: 字节数和运算次数相加，层数取较大值
+    pub fn merge(&mut self, other: &rpc::ProofMetrics) {
+        self.proof_bytes += other.proof_bytes;
+        self.pairing_ops += other.pairing_ops;
+        self.hash_ops += other.hash_ops;
+        self.levels = self.levels.max(other.levels);
+    }
 }
 
 // ADS Mode - type of authenticated data structure
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+// 序列化为字符串: 内置模式沿用 "CryptoAccumulator" / "Mpt"，第三方模式使用注册名称
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AdsMode {
-    CryptoAccumulator, // 密码学累加器 (BLS12-381)
-    Mpt,               // Merkle Patricia Trie
+    CryptoAccumulator,    // 密码学累加器 (BLS12-381)
+    Mpt,                  // Merkle Patricia Trie
+    MerkleTree,           // 二叉 Merkle 树
+    SparseMerkleTree,     // 256 层稀疏 Merkle 树
+    Custom(&'static str), // 通过 registry 注册的第三方 ADS
+}
+
+impl From<AdsMode> for String {
+    fn from(mode: AdsMode) -> String {
+        match mode {
+            AdsMode::CryptoAccumulator => "CryptoAccumulator".to_string(),
+            AdsMode::Mpt => "Mpt".to_string(),
+            AdsMode::MerkleTree => "MerkleTree".to_string(),
+            AdsMode::SparseMerkleTree => "SparseMerkleTree".to_string(),
+            AdsMode::Custom(name) => name.to_string(),
+        }
+    }
+}
+
+impl TryFrom<String> for AdsMode {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "CryptoAccumulator" => Ok(AdsMode::CryptoAccumulator),
+            "Mpt" => Ok(AdsMode::Mpt),
+            "MerkleTree" => Ok(AdsMode::MerkleTree),
+            "SparseMerkleTree" => Ok(AdsMode::SparseMerkleTree),
+            other => {
+                AdsMode::from_name(other).ok_or_else(|| format!("Unknown ADS mode: {}", other))
+            }
+        }
+    }
+}
+
+impl Serialize for AdsMode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&String::from(*self))
+    }
+}
+
+impl<'de> Deserialize<'de> for AdsMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        AdsMode::try_from(value).map_err(serde::de::Error::custom)
+    }
 }
 
 // Configuration for the distributed storage system
+// manager_addr / storager_addrs 是对外通告的地址（storager 可写作 name=url）；
+// 监听地址可以与之不同，未配置时各节点监听自己的通告地址
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemConfig {
     pub num_clients: usize,
@@ -31,4 +206,139 @@ pub struct SystemConfig {
     pub manager_addr: String,
     pub storager_addrs: Vec<String>,
     pub client_addrs: Vec<String>,
+    #[serde(default)]
+    pub manager_bind_addrs: Vec<String>, // Manager 的监听地址
+    #[serde(default)]
+    pub storager_bind_addrs: Vec<Vec<String>>, // 每个 storager 的监听地址，与 storager_addrs 一一对应
+    // 以下为 Manager 的运行参数，未配置时使用命令行或默认值；
+    // 连同 storager_addrs 可以在运行时重新加载（SIGHUP 或 Admin ReloadConfig）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replication_factor: Option<usize>, // 每个 keyword 写入的副本数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fanout_limit: Option<usize>, // 多关键词请求同时发往 storager 的最大并发数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root_history: Option<usize>, // 每个 storager 缓存的历史根哈希数量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_cache: Option<usize>, // 缓存的有结果 keyword 数量，0 表示不缓存
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub negative_cache: Option<usize>, // 缓存的不存在 keyword 数量，0 表示不缓存
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<String>, // 日志过滤指令
+}
+
+impl SystemConfig {
+    /// 从 JSON 文件加载配置
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))
+    }
+
+    /// Manager 的监听配置，通告地址为 `manager_addr`
+    pub fn manager_listen(&self) -> Result<ListenConfig, String> {
+        listen_config(&self.manager_addr, &self.manager_bind_addrs)
+    }
+
+    /// 第 `index` 个 storager 的监听配置，通告地址为 `storager_addrs[index]`（去掉 `name=` 前缀）
+    pub fn storager_listen(&self, index: usize) -> Result<ListenConfig, String> {
+        let entry = self.storager_addrs.get(index).ok_or_else(|| {
+            format!(
+                "storager index {} is out of range ({} storager(s) configured)",
+                index,
+                self.storager_addrs.len()
+            )
+        })?;
+        let advertise = entry
+            .split_once('=')
+            .map_or(entry.as_str(), |(_, addr)| addr.trim());
+        let bind_addrs = self
+            .storager_bind_addrs
+            .get(index)
+            .map_or(&[][..], Vec::as_slice);
+        listen_config(advertise, bind_addrs)
+    }
+}
+
+// 没有配置监听地址时监听通告地址本身；监听地址省略端口时使用通告地址的端口
+fn listen_config(advertise: &str, bind_addrs: &[String]) -> Result<ListenConfig, String> {
+    let authority = advertise
+        .split_once("://")
+        .map_or(advertise, |(_, rest)| rest)
+        .trim_end_matches('/');
+    let port = authority
+        .rsplit_once(':')
+        .and_then(|(_, port)| port.parse().ok())
+        .unwrap_or(0);
+    let listen = if bind_addrs.is_empty() {
+        ListenConfig::parse(authority, port).map_err(|e| {
+            format!(
+                "{} (bind addresses are required when {} is not an IP address)",
+                e, advertise
+            )
+        })?
+    } else {
+        ListenConfig::parse(&bind_addrs.join(","), port)?
+    };
+    Ok(listen.with_advertise(advertise))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn config(storager_addrs: &[&str]) -> SystemConfig {
+        SystemConfig {
+            num_clients: 1,
+            num_storagers: storager_addrs.len(),
+            ads_mode: AdsMode::Mpt,
+            manager_addr: "http://[::1]:50051".to_string(),
+            storager_addrs: storager_addrs.iter().map(|s| s.to_string()).collect(),
+            client_addrs: Vec::new(),
+            manager_bind_addrs: Vec::new(),
+            storager_bind_addrs: Vec::new(),
+            replication_factor: None,
+            fanout_limit: None,
+            root_history: None,
+            query_cache: None,
+            negative_cache: None,
+            log_level: None,
+        }
+    }
+
+    #[test]
+    fn test_listen_on_advertised_address() {
+        let config = config(&["s1=http://127.0.0.1:50052", "unix:/run/dss/s2.sock"]);
+
+        let manager = config.manager_listen().unwrap();
+        assert_eq!(manager.bind_addrs, vec!["[::1]:50051".parse().unwrap()]);
+        assert_eq!(manager.advertise_url(), "http://[::1]:50051");
+
+        let first = config.storager_listen(0).unwrap();
+        assert_eq!(first.bind_addrs, vec!["127.0.0.1:50052".parse().unwrap()]);
+        assert_eq!(first.advertise_url(), "http://127.0.0.1:50052");
+
+        let second = config.storager_listen(1).unwrap();
+        assert_eq!(second.unix_paths, vec![PathBuf::from("/run/dss/s2.sock")]);
+        assert!(config.storager_listen(2).is_err());
+    }
+
+    #[test]
+    fn test_bind_addrs_take_advertised_port() {
+        let mut config = config(&["s1=http://storager-0:50052"]);
+        assert!(config.storager_listen(0).is_err());
+
+        config.storager_bind_addrs = vec![vec!["0.0.0.0".to_string(), "[::]:6000".to_string()]];
+        let listen = config.storager_listen(0).unwrap();
+        assert_eq!(
+            listen.bind_addrs,
+            vec![
+                "0.0.0.0:50052".parse().unwrap(),
+                "[::]:6000".parse().unwrap()
+            ]
+        );
+        assert_eq!(listen.advertise_url(), "http://storager-0:50052");
+    }
 }
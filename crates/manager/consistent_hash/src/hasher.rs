@@ -0,0 +1,244 @@
+//! 哈希环使用的哈希函数
+//!
+//! 标准库的 `DefaultHasher` 不保证在不同 Rust 版本之间输出一致，
+//! 重新编译后节点和键在环上的位置可能全部改变，导致已有数据被重新映射。
+//! 需要长期保持分布稳定的部署应选择 [`RingHasher::XxHash64`]、
+//! [`RingHasher::Fnv1a`] 或 [`RingHasher::Sha256`]，它们的输出只取决于输入字节。
+//!
+//! # 示例
+//!
+//! ```
+//! use consistent_hash::{ConsistentHashRing, RingHasher};
+//!
+//! let mut ring = ConsistentHashRing::new().with_hasher(RingHasher::XxHash64);
+//! ring.add_node("node1", 150);
+//! ring.add_node("node2", 150);
+//!
+//! assert_eq!(ring.hasher(), RingHasher::XxHash64);
+//! assert_eq!(ring.hash_key("user123"), RingHasher::XxHash64.hash_key("user123"));
+//! ```
+
+use crate::HashValue;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::hash::{Hash, Hasher};
+
+/// 哈希函数
+///
+/// 序列化为 [`name`](Self::name) 返回的名称
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum RingHasher {
+    /// 标准库 `DefaultHasher`（SipHash），输出可能随 Rust 版本变化
+    #[default]
+    #[serde(rename = "std")]
+    Std,
+    /// 64 位 FNV-1a，再经过 MurmurHash3 的 fmix64 混淆
+    ///
+    /// FNV-1a 对只有末尾几个字符不同的键（例如 `node1#vnode0`、`node1#vnode1`）
+    /// 高位几乎不变，直接用于排序会让虚拟节点扎堆，因此追加一次混淆
+    #[serde(rename = "fnv")]
+    Fnv1a,
+    /// 64 位 xxHash（seed 为 0）
+    #[serde(rename = "xxhash")]
+    XxHash64,
+    /// SHA-256 的前 8 个字节（大端）
+    #[serde(rename = "sha256")]
+    Sha256,
+}
+
+impl RingHasher {
+    /// 所有可选的哈希函数
+    pub const ALL: [RingHasher; 4] = [
+        RingHasher::Std,
+        RingHasher::Fnv1a,
+        RingHasher::XxHash64,
+        RingHasher::Sha256,
+    ];
+
+    /// 配置中使用的名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            RingHasher::Std => "std",
+            RingHasher::Fnv1a => "fnv",
+            RingHasher::XxHash64 => "xxhash",
+            RingHasher::Sha256 => "sha256",
+        }
+    }
+
+    /// 按名称查找哈希函数
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "std" | "default" | "siphash" => Some(RingHasher::Std),
+            "fnv" | "fnv1a" => Some(RingHasher::Fnv1a),
+            "xxhash" | "xxhash64" | "xxh64" => Some(RingHasher::XxHash64),
+            "sha256" => Some(RingHasher::Sha256),
+            _ => None,
+        }
+    }
+
+    /// 输出是否与 Rust 版本无关
+    pub fn is_stable(&self) -> bool {
+        !matches!(self, RingHasher::Std)
+    }
+
+    /// 计算键在环上的哈希位置
+    pub fn hash_key(&self, key: &str) -> HashValue {
+        match self {
+            RingHasher::Std => {
+                let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                key.hash(&mut hasher);
+                hasher.finish()
+            }
+            RingHasher::Fnv1a => fmix64(fnv1a64(key.as_bytes())),
+            RingHasher::XxHash64 => xxhash64(key.as_bytes()),
+            RingHasher::Sha256 => {
+                let digest = Sha256::digest(key.as_bytes());
+                u64::from_be_bytes(digest[..8].try_into().unwrap())
+            }
+        }
+    }
+}
+
+/// 64 位 FNV-1a
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes
+        .iter()
+        .fold(OFFSET, |hash, &b| (hash ^ b as u64).wrapping_mul(PRIME))
+}
+
+/// MurmurHash3 的 64 位终结混淆
+fn fmix64(mut hash: u64) -> u64 {
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^ (hash >> 33)
+}
+
+const XXH_PRIME_1: u64 = 0x9E37_79B1_85EB_CA87;
+const XXH_PRIME_2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const XXH_PRIME_3: u64 = 0x1656_67B1_9E37_79F9;
+const XXH_PRIME_4: u64 = 0x85EB_CA77_C2B2_AE63;
+const XXH_PRIME_5: u64 = 0x27D4_EB2F_1656_67C5;
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8]) -> u64 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap()) as u64
+}
+
+fn xxh_round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(XXH_PRIME_2))
+        .rotate_left(31)
+        .wrapping_mul(XXH_PRIME_1)
+}
+
+fn xxh_merge_round(acc: u64, val: u64) -> u64 {
+    (acc ^ xxh_round(0, val))
+        .wrapping_mul(XXH_PRIME_1)
+        .wrapping_add(XXH_PRIME_4)
+}
+
+/// 64 位 xxHash（seed 为 0）
+fn xxhash64(bytes: &[u8]) -> u64 {
+    let len = bytes.len() as u64;
+    let mut rest = bytes;
+
+    let mut hash = if rest.len() >= 32 {
+        let mut v = [
+            XXH_PRIME_1.wrapping_add(XXH_PRIME_2),
+            XXH_PRIME_2,
+            0,
+            0u64.wrapping_sub(XXH_PRIME_1),
+        ];
+        while rest.len() >= 32 {
+            for (i, lane) in v.iter_mut().enumerate() {
+                *lane = xxh_round(*lane, read_u64(&rest[i * 8..]));
+            }
+            rest = &rest[32..];
+        }
+        let mut hash = v[0]
+            .rotate_left(1)
+            .wrapping_add(v[1].rotate_left(7))
+            .wrapping_add(v[2].rotate_left(12))
+            .wrapping_add(v[3].rotate_left(18));
+        for lane in v {
+            hash = xxh_merge_round(hash, lane);
+        }
+        hash
+    } else {
+        XXH_PRIME_5
+    };
+    hash = hash.wrapping_add(len);
+
+    while rest.len() >= 8 {
+        hash = (hash ^ xxh_round(0, read_u64(rest)))
+            .rotate_left(27)
+            .wrapping_mul(XXH_PRIME_1)
+            .wrapping_add(XXH_PRIME_4);
+        rest = &rest[8..];
+    }
+    if rest.len() >= 4 {
+        hash = (hash ^ read_u32(rest).wrapping_mul(XXH_PRIME_1))
+            .rotate_left(23)
+            .wrapping_mul(XXH_PRIME_2)
+            .wrapping_add(XXH_PRIME_3);
+        rest = &rest[4..];
+    }
+    for &b in rest {
+        hash = (hash ^ (b as u64).wrapping_mul(XXH_PRIME_5))
+            .rotate_left(11)
+            .wrapping_mul(XXH_PRIME_1);
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(XXH_PRIME_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(XXH_PRIME_3);
+    hash ^ (hash >> 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a_reference_values() {
+        assert_eq!(fnv1a64(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a64(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a64(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_xxhash64_reference_values() {
+        assert_eq!(xxhash64(b""), 0xef46db3751d8e999);
+        assert_eq!(xxhash64(b"a"), 0xd24ec4f1a98c6e5b);
+        assert_eq!(xxhash64(b"abc"), 0x44bc2cf5ad770999);
+        // 覆盖 32 字节分块路径
+        assert_eq!(
+            xxhash64(b"Nobody inspects the spammish repetition"),
+            0xfbcea83c8a378bf1
+        );
+    }
+
+    #[test]
+    fn test_sha256_truncated() {
+        // SHA-256("abc") = ba7816bf8f01cfea...
+        assert_eq!(RingHasher::Sha256.hash_key("abc"), 0xba7816bf8f01cfea);
+    }
+
+    #[test]
+    fn test_names_roundtrip() {
+        for hasher in RingHasher::ALL {
+            assert_eq!(RingHasher::from_name(hasher.name()), Some(hasher));
+        }
+        assert_eq!(RingHasher::from_name("XXH64"), Some(RingHasher::XxHash64));
+        assert_eq!(RingHasher::from_name("md5"), None);
+        assert!(!RingHasher::Std.is_stable());
+    }
+}
@@ -8,6 +8,9 @@
 //! - ✅ 虚拟节点支持，实现负载均衡
 //! - ✅ 动态添加/删除节点
 //! - ✅ 最小化数据迁移
+//! - ✅ 拓扑变更前的迁移预览
+//! - ✅ 可选的稳定哈希函数（xxHash / FNV-1a / SHA-256）
+//! - ✅ 拓扑快照的序列化与恢复（serde）
 //! - ✅ 线程安全
 //! - ✅ 零依赖核心实现
 //!
@@ -48,11 +51,17 @@
 //!   -> 映射到 Node3
 //! ```
 
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap, HashSet};
-use std::hash::{Hash, Hasher};
+
+mod hasher;
+mod rebalance;
+
+pub use hasher::RingHasher;
+pub use rebalance::{MovedRange, RebalancePlan};
 
 /// 哈希函数类型
-type HashValue = u64;
+pub type HashValue = u64;
 
 /// 一致性哈希环
 ///
@@ -70,7 +79,24 @@ type HashValue = u64;
 /// let node = ring.get_node("user123").unwrap();
 /// println!("user123 应该路由到: {}", node);
 /// ```
-#[derive(Debug, Clone)]
+///
+/// # 序列化
+///
+/// 环序列化为拓扑快照（哈希函数以及每个节点的虚拟节点数量），
+/// 反序列化时按快照重新放置虚拟节点，得到与序列化前完全相同的路由：
+///
+/// ```
+/// use consistent_hash::{ConsistentHashRing, RingHasher};
+///
+/// let ring = ConsistentHashRing::with_nodes(&["node1", "node2"], 100)
+///     .with_hasher(RingHasher::XxHash64);
+///
+/// let json = serde_json::to_string(&ring).unwrap();
+/// let restored: ConsistentHashRing = serde_json::from_str(&json).unwrap();
+/// assert_eq!(restored.get_node("user123"), ring.get_node("user123"));
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "RingSnapshot", from = "RingSnapshot")]
 pub struct ConsistentHashRing {
     /// 哈希环: hash_value -> 物理节点名称
     ring: BTreeMap<HashValue, String>,
@@ -80,6 +106,9 @@ pub struct ConsistentHashRing {
 
     /// 虚拟节点到物理节点的映射: virtual_node_key -> physical_node_name
     virtual_to_physical: HashMap<String, String>,
+
+    /// 计算环上位置使用的哈希函数
+    hasher: RingHasher,
 }
 
 impl ConsistentHashRing {
@@ -97,9 +126,43 @@ impl ConsistentHashRing {
             ring: BTreeMap::new(),
             nodes: HashMap::new(),
             virtual_to_physical: HashMap::new(),
+            hasher: RingHasher::default(),
         }
     }
 
+    /// 使用指定的哈希函数
+    ///
+    /// 环中已有节点时会按新的哈希函数重新放置所有虚拟节点
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// use consistent_hash::{ConsistentHashRing, RingHasher};
+    ///
+    /// let ring = ConsistentHashRing::with_nodes(&["node1", "node2"], 100)
+    ///     .with_hasher(RingHasher::Fnv1a);
+    /// assert_eq!(ring.virtual_node_count(), 200);
+    /// ```
+    pub fn with_hasher(mut self, hasher: RingHasher) -> Self {
+        if hasher == self.hasher {
+            return self;
+        }
+
+        let nodes: Vec<(String, usize)> = self.nodes.drain().collect();
+        self.ring.clear();
+        self.virtual_to_physical.clear();
+        self.hasher = hasher;
+        for (name, virtual_nodes) in nodes {
+            self.add_node(&name, virtual_nodes);
+        }
+        self
+    }
+
+    /// 当前使用的哈希函数
+    pub fn hasher(&self) -> RingHasher {
+        self.hasher
+    }
+
     /// 使用默认虚拟节点数创建哈希环并添加节点
     ///
     /// # 参数
@@ -123,13 +186,11 @@ impl ConsistentHashRing {
         ring
     }
 
-    /// 计算字符串的哈希值
+    /// 计算键在环上的哈希位置
     ///
-    /// 使用 Rust 标准库的 DefaultHasher，提供良好的分布性
-    fn hash<T: Hash>(key: &T) -> HashValue {
-        let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        key.hash(&mut hasher);
-        hasher.finish()
+    /// 与 [`get_node`](Self::get_node) 使用的哈希一致
+    pub fn hash_key(&self, key: &str) -> HashValue {
+        self.hasher.hash_key(key)
     }
 
     /// 添加一个节点到哈希环
@@ -162,7 +223,7 @@ impl ConsistentHashRing {
         // 添加虚拟节点到环上
         for i in 0..virtual_nodes {
             let virtual_key = format!("{}#vnode{}", node_name, i);
-            let hash = Self::hash(&virtual_key);
+            let hash = self.hash_key(&virtual_key);
             self.ring.insert(hash, node_name.to_string());
             self.virtual_to_physical
                 .insert(virtual_key, node_name.to_string());
@@ -204,7 +265,7 @@ impl ConsistentHashRing {
         // 移除所有虚拟节点
         for i in 0..virtual_count {
             let virtual_key = format!("{}#vnode{}", node_name, i);
-            let hash = Self::hash(&virtual_key);
+            let hash = self.hash_key(&virtual_key);
             self.ring.remove(&hash);
             self.virtual_to_physical.remove(&virtual_key);
         }
@@ -243,7 +304,7 @@ impl ConsistentHashRing {
             return None;
         }
 
-        let hash = Self::hash(&key);
+        let hash = self.hash_key(key);
 
         // 在环上顺时针查找第一个大于等于 hash 的虚拟节点
         self.ring
@@ -286,7 +347,7 @@ impl ConsistentHashRing {
             return Vec::new();
         }
 
-        let hash = Self::hash(&key);
+        let hash = self.hash_key(key);
         let mut result = Vec::new();
         let mut seen = HashSet::new();
 
@@ -443,6 +504,36 @@ impl Default for ConsistentHashRing {
     }
 }
 
+/// 哈希环的序列化形式
+///
+/// 只保存重建环所需的拓扑信息，虚拟节点的位置在恢复时重新计算
+#[derive(Serialize, Deserialize)]
+struct RingSnapshot {
+    #[serde(default)]
+    hasher: RingHasher,
+    /// 节点名称 -> 虚拟节点数量（有序，保证输出稳定）
+    nodes: BTreeMap<String, usize>,
+}
+
+impl From<ConsistentHashRing> for RingSnapshot {
+    fn from(ring: ConsistentHashRing) -> Self {
+        RingSnapshot {
+            hasher: ring.hasher,
+            nodes: ring.nodes.into_iter().collect(),
+        }
+    }
+}
+
+impl From<RingSnapshot> for ConsistentHashRing {
+    fn from(snapshot: RingSnapshot) -> Self {
+        let mut ring = ConsistentHashRing::new().with_hasher(snapshot.hasher);
+        for (name, virtual_nodes) in &snapshot.nodes {
+            ring.add_node(name, *virtual_nodes);
+        }
+        ring
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -617,4 +708,68 @@ mod tests {
         assert_eq!(ring.get_virtual_node_count("node2"), Some(200));
         assert_eq!(ring.get_virtual_node_count("node3"), None);
     }
+
+    #[test]
+    fn test_with_hasher_rebuilds_ring() {
+        let keys: Vec<String> = (0..200).map(|i| format!("key{}", i)).collect();
+        let built_after = ConsistentHashRing::with_nodes(&["node1", "node2", "node3"], 100)
+            .with_hasher(RingHasher::XxHash64);
+        let mut built_before = ConsistentHashRing::new().with_hasher(RingHasher::XxHash64);
+        for name in ["node1", "node2", "node3"] {
+            built_before.add_node(name, 100);
+        }
+
+        assert_eq!(built_after.hasher(), RingHasher::XxHash64);
+        assert_eq!(built_after.virtual_node_count(), 300);
+        for key in &keys {
+            assert_eq!(built_after.get_node(key), built_before.get_node(key));
+        }
+    }
+
+    #[test]
+    fn test_stable_hashers_balance() {
+        let keys: Vec<String> = (0..3000).map(|i| format!("key{}", i)).collect();
+        for hasher in RingHasher::ALL {
+            let ring = ConsistentHashRing::with_nodes(&["node1", "node2", "node3"], 150)
+                .with_hasher(hasher);
+            let distribution = ring.get_distribution(&keys);
+            assert_eq!(distribution.len(), 3, "{:?}", hasher);
+            for count in distribution.values() {
+                assert!(
+                    (*count as f64 - 1000.0).abs() / 1000.0 < 0.3,
+                    "{:?} too unbalanced: {:?}",
+                    hasher,
+                    distribution
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let mut ring = ConsistentHashRing::with_nodes(&["node1", "node2", "node3"], 100)
+            .with_hasher(RingHasher::Fnv1a);
+        ring.remove_node("node2");
+        ring.add_node("node4", 50);
+
+        let json = serde_json::to_string(&ring).unwrap();
+        assert_eq!(
+            json,
+            r#"{"hasher":"fnv","nodes":{"node1":100,"node3":100,"node4":50}}"#
+        );
+
+        let restored: ConsistentHashRing = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.hasher(), RingHasher::Fnv1a);
+        assert_eq!(restored.virtual_node_count(), 250);
+        assert_eq!(restored.get_virtual_node_count("node4"), Some(50));
+        for i in 0..500 {
+            let key = format!("key{}", i);
+            assert_eq!(restored.get_node(&key), ring.get_node(&key));
+        }
+
+        // 没有 hasher 字段的快照使用默认哈希函数
+        let legacy: ConsistentHashRing = serde_json::from_str(r#"{"nodes":{"a":10}}"#).unwrap();
+        assert_eq!(legacy.hasher(), RingHasher::Std);
+        assert_eq!(legacy.node_count(), 1);
+    }
 }
@@ -0,0 +1,396 @@
+//! 拓扑变更的迁移预览
+//!
+//! 在不修改哈希环的前提下，计算添加/移除节点时哪些哈希区间会换主，
+//! 以便在真正变更拓扑之前评估迁移代价。
+
+use crate::{ConsistentHashRing, HashValue, RingHasher};
+use std::collections::BTreeMap;
+
+/// [`ConsistentHashRing::rebalanced`] 调整虚拟节点数量的轮数
+const REBALANCE_ROUNDS: usize = 8;
+
+/// 调整后单个节点的虚拟节点数量上限
+const MAX_VIRTUAL_NODES: usize = 4096;
+
+/// 一段会迁移的哈希区间 `(start, end]`（环形，`start == end` 表示整个环）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovedRange {
+    /// 区间起点（不包含）
+    pub start: HashValue,
+    /// 区间终点（包含）
+    pub end: HashValue,
+    /// 变更前的归属节点（环原本为空时为 None）
+    pub from: Option<String>,
+    /// 变更后的归属节点（环变为空时为 None）
+    pub to: Option<String>,
+}
+
+impl MovedRange {
+    /// 判断哈希值是否落在该区间内
+    pub fn contains(&self, hash: HashValue) -> bool {
+        if self.start < self.end {
+            hash > self.start && hash <= self.end
+        } else {
+            // 跨越 0 点的区间（或整个环）
+            hash > self.start || hash <= self.end
+        }
+    }
+
+    /// 区间覆盖的哈希空间大小
+    pub fn width(&self) -> u128 {
+        if self.start == self.end {
+            1u128 << 64
+        } else {
+            self.end.wrapping_sub(self.start) as u128
+        }
+    }
+}
+
+/// 迁移计划
+#[derive(Debug, Clone, Default)]
+pub struct RebalancePlan {
+    /// 所有会换主的哈希区间（按终点排序）
+    pub ranges: Vec<MovedRange>,
+    /// 生成计划的环所使用的哈希函数（区间以它的哈希值表示）
+    pub hasher: RingHasher,
+}
+
+impl RebalancePlan {
+    /// 是否没有任何区间需要迁移
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// 迁移区间占整个哈希空间的比例
+    pub fn hash_space_fraction(&self) -> f64 {
+        let total: u128 = self.ranges.iter().map(|r| r.width()).sum();
+        total as f64 / (1u128 << 64) as f64
+    }
+
+    /// 查找键所在的迁移区间（键不迁移时返回 None）
+    pub fn range_for_key(&self, key: &str) -> Option<&MovedRange> {
+        let hash = self.hasher.hash_key(key);
+        self.ranges.iter().find(|r| r.contains(hash))
+    }
+
+    /// 使用采样回调估计需要迁移的键数量
+    ///
+    /// 回调接收每个迁移区间并返回该区间内（估计的）键数量
+    pub fn estimate_moved_keys<F>(&self, mut count_in_range: F) -> usize
+    where
+        F: FnMut(&MovedRange) -> usize,
+    {
+        self.ranges.iter().map(&mut count_in_range).sum()
+    }
+
+    /// 使用一组样本键估计需要迁移的键数量
+    ///
+    /// # 参数
+    ///
+    /// * `sample` - 样本键
+    /// * `total_keys` - 键的总数
+    pub fn estimate_from_sample(&self, sample: &[String], total_keys: usize) -> usize {
+        if sample.is_empty() {
+            return 0;
+        }
+        let moved = sample
+            .iter()
+            .filter(|k| self.range_for_key(k).is_some())
+            .count();
+        (moved as f64 / sample.len() as f64 * total_keys as f64).round() as usize
+    }
+}
+
+impl ConsistentHashRing {
+    /// 预览添加节点会导致的迁移（不修改哈希环）
+    ///
+    /// 节点已存在时返回 None
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// use consistent_hash::ConsistentHashRing;
+    ///
+    /// let mut ring = ConsistentHashRing::new();
+    /// ring.add_node("node1", 150);
+    /// ring.add_node("node2", 150);
+    ///
+    /// let plan = ring.plan_add_node("node3", 150).unwrap();
+    /// assert!(plan.ranges.iter().all(|r| r.to.as_deref() == Some("node3")));
+    /// assert_eq!(ring.node_count(), 2); // 环未被修改
+    /// ```
+    pub fn plan_add_node(&self, node_name: &str, virtual_nodes: usize) -> Option<RebalancePlan> {
+        let mut next = self.clone();
+        if !next.add_node(node_name, virtual_nodes) {
+            return None;
+        }
+        Some(Self::diff(&self.ring, &next.ring, self.hasher))
+    }
+
+    /// 预览移除节点会导致的迁移（不修改哈希环）
+    ///
+    /// 节点不存在时返回 None
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// use consistent_hash::ConsistentHashRing;
+    ///
+    /// let mut ring = ConsistentHashRing::new();
+    /// ring.add_node("node1", 150);
+    /// ring.add_node("node2", 150);
+    ///
+    /// let plan = ring.plan_remove_node("node1").unwrap();
+    /// assert!(plan.ranges.iter().all(|r| r.from.as_deref() == Some("node1")));
+    /// ```
+    pub fn plan_remove_node(&self, node_name: &str) -> Option<RebalancePlan> {
+        let mut next = self.clone();
+        if !next.remove_node(node_name) {
+            return None;
+        }
+        Some(Self::diff(&self.ring, &next.ring, self.hasher))
+    }
+
+    /// 预览从当前环切换到 `next` 会导致的迁移（两个环应使用相同的哈希函数）
+    pub fn plan_transition(&self, next: &ConsistentHashRing) -> RebalancePlan {
+        Self::diff(&self.ring, &next.ring, self.hasher)
+    }
+
+    /// 每个节点占有的哈希空间比例
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// use consistent_hash::ConsistentHashRing;
+    ///
+    /// let ring = ConsistentHashRing::with_nodes(&["node1", "node2"], 100);
+    /// let total: f64 = ring.ownership().values().sum();
+    /// assert!((total - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn ownership(&self) -> BTreeMap<String, f64> {
+        let mut owned: BTreeMap<String, u128> = BTreeMap::new();
+        let Some((&last, _)) = self.ring.iter().next_back() else {
+            return BTreeMap::new();
+        };
+        let mut start = last;
+        for (&end, node) in &self.ring {
+            let width = if start == end {
+                1u128 << 64
+            } else {
+                end.wrapping_sub(start) as u128
+            };
+            *owned.entry(node.clone()).or_default() += width;
+            start = end;
+        }
+        owned
+            .into_iter()
+            .map(|(node, width)| (node, width as f64 / (1u128 << 64) as f64))
+            .collect()
+    }
+
+    /// 占有哈希空间最多的节点相对平均比例的倍数（完全均衡或空环时为 1.0）
+    pub fn imbalance(&self) -> f64 {
+        let ownership = self.ownership();
+        let max = ownership.values().copied().fold(0.0, f64::max);
+        if ownership.is_empty() {
+            1.0
+        } else {
+            max * ownership.len() as f64
+        }
+    }
+
+    /// 调整各节点的虚拟节点数量，使它们占有的哈希空间尽量接近（不修改当前环）
+    ///
+    /// 每一轮按节点占有比例与平均比例之差缩放其虚拟节点数量，返回各轮中最均衡的环；
+    /// 没有更均衡的结果时返回当前环的副本。已有的虚拟节点位置保持不变，
+    /// 因此只有增减的虚拟节点附近的区间会换主
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// use consistent_hash::ConsistentHashRing;
+    ///
+    /// let ring = ConsistentHashRing::with_nodes(&["node1", "node2", "node3"], 10);
+    /// let balanced = ring.rebalanced();
+    /// assert!(balanced.imbalance() <= ring.imbalance());
+    /// ```
+    pub fn rebalanced(&self) -> ConsistentHashRing {
+        let mut best = self.clone();
+        let mut best_imbalance = self.imbalance();
+        let mut current = self.clone();
+        for _ in 0..REBALANCE_ROUNDS {
+            let ownership = current.ownership();
+            let target = 1.0 / ownership.len().max(1) as f64;
+            let mut nodes: Vec<(&String, &usize)> = current.nodes.iter().collect();
+            nodes.sort();
+
+            let mut next = ConsistentHashRing::new().with_hasher(self.hasher);
+            for (node, &count) in nodes {
+                let share = ownership
+                    .get(node)
+                    .copied()
+                    .unwrap_or(0.0)
+                    .max(f64::EPSILON);
+                let scaled = (count as f64 * target / share).round() as usize;
+                next.add_node(node, scaled.clamp(1, (count * 2).min(MAX_VIRTUAL_NODES)));
+            }
+
+            let imbalance = next.imbalance();
+            if imbalance < best_imbalance {
+                best = next.clone();
+                best_imbalance = imbalance;
+            }
+            current = next;
+        }
+        best
+    }
+
+    /// 对比两个环，找出所有换主的区间
+    fn diff(
+        before: &BTreeMap<HashValue, String>,
+        after: &BTreeMap<HashValue, String>,
+        hasher: RingHasher,
+    ) -> RebalancePlan {
+        // 两个环所有虚拟节点位置的并集就是区间边界
+        let mut boundaries: Vec<HashValue> = before.keys().chain(after.keys()).copied().collect();
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let mut ranges: Vec<MovedRange> = Vec::new();
+        for (i, &end) in boundaries.iter().enumerate() {
+            let start = if i == 0 {
+                boundaries[boundaries.len() - 1]
+            } else {
+                boundaries[i - 1]
+            };
+
+            let from = Self::owner(before, end);
+            let to = Self::owner(after, end);
+            if from == to {
+                continue;
+            }
+
+            // 与上一个相邻且归属相同的区间合并
+            if let Some(last) = ranges.last_mut() {
+                if last.end == start && last.from == from && last.to == to {
+                    last.end = end;
+                    continue;
+                }
+            }
+            ranges.push(MovedRange {
+                start,
+                end,
+                from,
+                to,
+            });
+        }
+
+        // 首尾区间在 0 点处相接时合并
+        if ranges.len() > 1 {
+            let first = &ranges[0];
+            let last = &ranges[ranges.len() - 1];
+            if last.end == first.start && last.from == first.from && last.to == first.to {
+                let last = ranges.pop().unwrap();
+                ranges[0].start = last.start;
+            }
+        }
+
+        RebalancePlan { ranges, hasher }
+    }
+
+    /// 哈希位置 `hash` 所在区间的归属节点
+    fn owner(ring: &BTreeMap<HashValue, String>, hash: HashValue) -> Option<String> {
+        ring.range(hash..)
+            .next()
+            .or_else(|| ring.iter().next())
+            .map(|(_, node)| node.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_keys() -> Vec<String> {
+        (0..2000).map(|i| format!("key{}", i)).collect()
+    }
+
+    #[test]
+    fn test_plan_add_matches_actual_moves() {
+        let mut ring = ConsistentHashRing::with_nodes(&["node1", "node2"], 100);
+        let keys = sample_keys();
+        let plan = ring.plan_add_node("node3", 100).unwrap();
+
+        let before: Vec<_> = keys.iter().map(|k| ring.get_node(k)).collect();
+        ring.add_node("node3", 100);
+
+        for (key, old) in keys.iter().zip(before) {
+            let new = ring.get_node(key);
+            match plan.range_for_key(key) {
+                Some(range) => {
+                    assert_eq!(range.from, old);
+                    assert_eq!(range.to, new);
+                }
+                None => assert_eq!(old, new),
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_remove_matches_actual_moves() {
+        let mut ring = ConsistentHashRing::with_nodes(&["node1", "node2", "node3"], 100);
+        let keys = sample_keys();
+        let plan = ring.plan_remove_node("node2").unwrap();
+
+        let before: Vec<_> = keys.iter().map(|k| ring.get_node(k)).collect();
+        ring.remove_node("node2");
+
+        let moved = keys
+            .iter()
+            .zip(before.iter())
+            .filter(|(k, old)| ring.get_node(k) != **old)
+            .count();
+        assert_eq!(plan.estimate_from_sample(&keys, keys.len()), moved);
+    }
+
+    #[test]
+    fn test_plan_on_invalid_nodes() {
+        let ring = ConsistentHashRing::with_nodes(&["node1"], 10);
+        assert!(ring.plan_add_node("node1", 10).is_none());
+        assert!(ring.plan_remove_node("node2").is_none());
+    }
+
+    #[test]
+    fn test_rebalanced_evens_out_ownership() {
+        let ring = ConsistentHashRing::with_nodes(&["node1", "node2", "node3", "node4"], 8);
+        let balanced = ring.rebalanced();
+        assert!(balanced.imbalance() < ring.imbalance());
+        assert_eq!(balanced.node_count(), 4);
+
+        // 迁移计划与实际换主的键一致
+        let plan = ring.plan_transition(&balanced);
+        for key in sample_keys() {
+            let (old, new) = (ring.get_node(&key), balanced.get_node(&key));
+            match plan.range_for_key(&key) {
+                Some(range) => assert_eq!((&range.from, &range.to), (&old, &new)),
+                None => assert_eq!(old, new),
+            }
+        }
+    }
+
+    #[test]
+    fn test_ownership_of_single_node() {
+        let ring = ConsistentHashRing::with_nodes(&["node1"], 1);
+        assert_eq!(ring.ownership().get("node1"), Some(&1.0));
+        assert_eq!(ring.imbalance(), 1.0);
+        assert!(ConsistentHashRing::new().ownership().is_empty());
+    }
+
+    #[test]
+    fn test_plan_from_empty_ring_covers_everything() {
+        let ring = ConsistentHashRing::new();
+        let plan = ring.plan_add_node("node1", 10).unwrap();
+        assert!((plan.hash_space_fraction() - 1.0).abs() < 1e-9);
+        assert_eq!(plan.estimate_moved_keys(|_| 1), plan.ranges.len());
+    }
+}
@@ -0,0 +1,333 @@
+//! 管理接口
+//!
+//! [`AdminService`] 与 [`ManagerService`](common::rpc::manager_service_server::ManagerService)
+//! 由同一个 Manager 提供：运维工具和监控面板通过它查看 storager 健康状态、已发布的根哈希、
+//! 哈希环布局、keyword 的归属、热点 keyword 和查询证明的验证代价，触发再平衡和落盘，
+//! 并重新加载配置（见 [`crate::reload`]），不必从日志中解析这些信息。
+//! 查看类 RPC 需要读权限，会改变集群状态的 RPC 需要管理员权限。
+
+use crate::core::hot_keys::{suggested_replicas, DEFAULT_HOT_SHARE, DEFAULT_HOT_TOP};
+use crate::core::{Access, LoadReport};
+use crate::error::ManagerError;
+use crate::manager::Manager;
+use crate::reload::ReloadConfig;
+use crate::service::moved_ranges;
+use common::rpc::{
+    admin_service_server::AdminService, ClusterStatusRequest, ClusterStatusResponse,
+    FlushAllRequest, FlushAllResponse, FlushRequest, HotKeyword, HotKeywordsRequest,
+    HotKeywordsResponse, KeywordStatsRequest, KeywordStatsResponse, ProofStatsRequest,
+    ProofStatsResponse, RebalanceNowRequest, RebalanceNowResponse, ReloadConfigRequest,
+    ReloadConfigResponse, StoragerFlushResult, StoragerHealthRequest, StoragerStatsRequest,
+    StoragerStatus,
+};
+use tonic::{Request, Response, Status};
+use tracing::{debug, info, warn};
+
+#[tonic::async_trait]
+impl AdminService for Manager {
+    async fn cluster_status(
+        &self,
+        request: Request<ClusterStatusRequest>,
+    ) -> Result<Response<ClusterStatusResponse>, Status> {
+        self.authorize(&request, Access::Read)?;
+        debug!("Manager received ClusterStatus request");
+
+        let layout = self.router.ring_layout();
+        let mut storagers = self.router.get_all_storagers();
+        storagers.sort();
+        // 探测结果只用于本次响应，不改变路由（见 `check_storager_health`）
+        let probes = storagers
+            .into_iter()
+            .map(|(name, address)| async {
+                let (virtual_nodes, hash_space_fraction) =
+                    layout.get(&name).copied().unwrap_or_default();
+                let health = self
+                    .call_storager(&address, "Health", |mut client| async move {
+                        client.health(StoragerHealthRequest {}).await
+                    })
+                    .await;
+                let (reachable, crypto_ready, message) = match health {
+                    Ok(health) => (true, health.crypto_ready, health.message),
+                    Err(e) => (false, false, e.to_string()),
+                };
+                StoragerStatus {
+                    routed: self.router.is_healthy(&name),
+                    name,
+                    address,
+                    virtual_nodes: virtual_nodes as u32,
+                    hash_space_fraction,
+                    reachable,
+                    crypto_ready,
+                    message,
+                }
+            })
+            .collect();
+        let storagers = self.fan_out(probes).await;
+
+        let mut roots = self.current_roots();
+        roots.sort_by(|a, b| (&a.storager, &a.namespace).cmp(&(&b.storager, &b.namespace)));
+
+        let cache = self.query_cache_stats();
+        Ok(Response::new(ClusterStatusResponse {
+            storagers,
+            roots,
+            ads_mode: self.ads_mode().into(),
+            ring_hasher: self.ring_hasher().name().to_string(),
+            replication_factor: self.replication_factor() as u32,
+            query_cache_hits: cache.hits,
+            query_cache_misses: cache.misses,
+            query_cache_entries: cache.entries,
+            negative_cache_hits: cache.negative_hits,
+            negative_cache_entries: cache.negative_entries,
+            keyword_filters: self.keyword_filters.len() as u64,
+            filtered_subqueries: self.keyword_filters.skipped(),
+        }))
+    }
+
+    async fn keyword_stats(
+        &self,
+        request: Request<KeywordStatsRequest>,
+    ) -> Result<Response<KeywordStatsResponse>, Status> {
+        self.authorize(&request, Access::Read)?;
+        let req = request.into_inner();
+        debug!(
+            "Manager received KeywordStats request: keyword={}",
+            req.keyword
+        );
+        if req.keyword.is_empty() {
+            return Err(
+                ManagerError::InvalidRequest("keyword must not be empty".to_string()).into(),
+            );
+        }
+
+        let replicas = self.replicas_for_keyword(&req.keyword);
+        let (owner, owner_address) = replicas.first().cloned().unwrap_or_default();
+
+        Ok(Response::new(KeywordStatsResponse {
+            cardinality: self.admission.cardinality(&req.keyword),
+            owner,
+            owner_address,
+            replicas: replicas.into_iter().map(|(name, _)| name).collect(),
+            ring_position: self.router.ring_position(&req.keyword),
+        }))
+    }
+
+    async fn rebalance_now(
+        &self,
+        request: Request<RebalanceNowRequest>,
+    ) -> Result<Response<RebalanceNowResponse>, Status> {
+        self.authorize(&request, Access::Admin)?;
+        let req = request.into_inner();
+        debug!(
+            "Manager received RebalanceNow request: dry_run={}",
+            req.dry_run
+        );
+
+        let rebalance = Manager::rebalance_now(self, req.dry_run)
+            .await
+            .map_err(ManagerError::Membership)?;
+
+        Ok(Response::new(RebalanceNowResponse {
+            ranges: moved_ranges(&rebalance.plan),
+            hash_space_fraction: rebalance.plan.hash_space_fraction(),
+            migrated_keywords: rebalance.migrated.keywords as u64,
+            migrated_fids: rebalance.migrated.fids as u64,
+            imbalance_before: rebalance.imbalance_before,
+            imbalance_after: rebalance.imbalance_after,
+        }))
+    }
+
+    async fn flush_all(
+        &self,
+        request: Request<FlushAllRequest>,
+    ) -> Result<Response<FlushAllResponse>, Status> {
+        self.authorize(&request, Access::Admin)?;
+        debug!("Manager received FlushAll request");
+
+        self.persist_state()?;
+
+        let mut storagers = self.router.get_all_storagers();
+        storagers.sort();
+        let flushes = storagers
+            .into_iter()
+            .map(|(name, address)| async move {
+                let flushed = self
+                    .call_storager(&address, "Flush", |mut client| async move {
+                        client.flush(FlushRequest {}).await
+                    })
+                    .await;
+                match flushed {
+                    Ok(flushed) => StoragerFlushResult {
+                        name,
+                        success: true,
+                        message: String::new(),
+                        namespaces: flushed.namespaces,
+                    },
+                    Err(e) => {
+                        warn!("Flush on {} failed: {}", name, e);
+                        StoragerFlushResult {
+                            name,
+                            success: false,
+                            message: e.to_string(),
+                            namespaces: 0,
+                        }
+                    }
+                }
+            })
+            .collect();
+        let storagers: Vec<StoragerFlushResult> = self.fan_out(flushes).await;
+        info!(
+            "Flushed {}/{} storager(s)",
+            storagers.iter().filter(|result| result.success).count(),
+            storagers.len()
+        );
+
+        Ok(Response::new(FlushAllResponse { storagers }))
+    }
+
+    async fn reload_config(
+        &self,
+        request: Request<ReloadConfigRequest>,
+    ) -> Result<Response<ReloadConfigResponse>, Status> {
+        self.authorize(&request, Access::Admin)?;
+        let req = request.into_inner();
+        debug!(
+            "Manager received ReloadConfig request: from_file={}",
+            req.from_file
+        );
+
+        let positive = |value: u32| (value > 0).then_some(value as usize);
+        let config = ReloadConfig {
+            storagers: (!req.storagers.is_empty()).then_some(req.storagers),
+            replication_factor: positive(req.replication_factor),
+            fanout_limit: positive(req.fanout_limit),
+            root_history: positive(req.root_history),
+            query_cache: req.cache.as_ref().map(|cache| cache.query_cache as usize),
+            negative_cache: req
+                .cache
+                .as_ref()
+                .map(|cache| cache.negative_cache as usize),
+            log_level: (!req.log_level.is_empty()).then_some(req.log_level),
+        };
+        let report = if req.from_file {
+            self.reload_from_file(config)
+                .await
+                .map_err(ManagerError::InvalidRequest)?
+        } else {
+            self.reload(config).await
+        };
+
+        Ok(Response::new(ReloadConfigResponse {
+            added: report.added,
+            removed: report.removed,
+            errors: report.errors,
+            replication_factor: self.replication_factor() as u32,
+            fanout_limit: self.fanout_limit() as u32,
+            root_history: self.root_history.capacity() as u32,
+            query_cache: self.query_cache.capacity() as u32,
+            negative_cache: self.query_cache.negative_capacity() as u32,
+            backfilled_keywords: report.backfilled.keywords as u64,
+            backfilled_fids: report.backfilled.fids as u64,
+        }))
+    }
+
+    async fn proof_stats(
+        &self,
+        request: Request<ProofStatsRequest>,
+    ) -> Result<Response<ProofStatsResponse>, Status> {
+        self.authorize(&request, Access::Read)?;
+        debug!("Manager received ProofStats request");
+
+        let stats = self.proof_stats();
+        Ok(Response::new(ProofStatsResponse {
+            ads_mode: self.ads_mode().into(),
+            queries: stats.queries,
+            total: Some(stats.total),
+            max: Some(stats.max),
+        }))
+    }
+
+    async fn hot_keywords(
+        &self,
+        request: Request<HotKeywordsRequest>,
+    ) -> Result<Response<HotKeywordsResponse>, Status> {
+        self.authorize(&request, Access::Read)?;
+        let req = request.into_inner();
+        debug!(
+            "Manager received HotKeywords request: top={}, min_share={}",
+            req.top, req.min_share
+        );
+        if !(0.0..=1.0).contains(&req.min_share) {
+            return Err(ManagerError::InvalidRequest(
+                "min_share must be between 0 and 1".to_string(),
+            )
+            .into());
+        }
+        let top = match req.top {
+            0 => DEFAULT_HOT_TOP,
+            top => top as usize,
+        };
+        let min_share = if req.min_share == 0.0 {
+            DEFAULT_HOT_SHARE
+        } else {
+            req.min_share
+        };
+
+        let mut storagers = self.router.get_all_storagers();
+        storagers.sort();
+        let storager_count = storagers.len();
+        let calls = storagers
+            .into_iter()
+            .map(|(name, address)| {
+                let request = StoragerStatsRequest {
+                    top: top as u32,
+                    namespace: req.namespace.clone(),
+                };
+                async move {
+                    let stats = self
+                        .call_storager(&address, "Stats", |mut client| {
+                            let request = request.clone();
+                            async move { client.stats(request).await }
+                        })
+                        .await;
+                    (name, stats)
+                }
+            })
+            .collect();
+
+        let mut report = LoadReport::new();
+        let mut unreachable = Vec::new();
+        for (name, stats) in self.fan_out(calls).await {
+            match stats {
+                Ok(stats) => report.add_storager(&stats),
+                Err(e) => {
+                    warn!("Stats from storager {} failed: {}", name, e);
+                    unreachable.push(name);
+                }
+            }
+        }
+
+        let keywords = report
+            .hot(min_share, top)
+            .into_iter()
+            .map(|hot| HotKeyword {
+                replicas: self
+                    .replicas_for_keyword(&hot.keyword)
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .collect(),
+                suggested_replicas: suggested_replicas(hot.share, storager_count) as u32,
+                queries: hot.load.queries,
+                share: hot.share,
+                fids: hot.load.fids,
+                keyword: hot.keyword,
+            })
+            .collect();
+
+        Ok(Response::new(HotKeywordsResponse {
+            total_queries: report.total_queries(),
+            keywords,
+            unreachable,
+        }))
+    }
+}
@@ -0,0 +1,119 @@
+//! 批量写入
+//!
+//! 客户端通过一次 `Batch` RPC 提交多个 add / delete / update 操作，省去逐个操作的往返。
+//! Manager 按请求中的顺序逐个执行，每个操作与单独的 `Add`、`Delete`、`Update` 经过相同的
+//! 检查、加锁和证明验证，因此同一个 fid 上先后的操作按顺序生效。
+//!
+//! 批量请求不是事务：某个操作失败（被拒绝、storager 不可用、证明验证失败）不影响其他操作，
+//! 响应中逐个返回每个操作的结果。
+
+use crate::core::Principal;
+use crate::error::ManagerError;
+use crate::manager::Manager;
+use common::rpc::{
+    batch_operation::Operation, manager_service_server::ManagerService, BatchOperationResult,
+    BatchRequest, BatchResponse,
+};
+use tonic::metadata::MetadataMap;
+use tonic::{Code, Request, Status};
+use tracing::info;
+
+/// 一次批量请求最多包含的操作数
+pub const MAX_BATCH_OPERATIONS: usize = 1024;
+
+impl Manager {
+    /// 依次执行批量请求中的操作，返回每个操作的结果
+    ///
+    /// 每个操作带着批量请求的元数据和身份执行，与单独发送时的权限检查相同
+    pub(crate) async fn run_batch(
+        &self,
+        request: Request<BatchRequest>,
+    ) -> Result<BatchResponse, Status> {
+        let metadata = request.metadata().clone();
+        let principal = request.extensions().get::<Principal>().cloned();
+        let operations = request.into_inner().operations;
+        if operations.len() > MAX_BATCH_OPERATIONS {
+            return Err(ManagerError::InvalidRequest(format!(
+                "Batch has {} operations, at most {} are allowed",
+                operations.len(),
+                MAX_BATCH_OPERATIONS
+            ))
+            .into());
+        }
+
+        let mut results = Vec::with_capacity(operations.len());
+        for operation in operations {
+            let outcome: Result<BatchOperationResult, Status> = async {
+                let (success, message, pending_ops) = match operation.operation {
+                    Some(Operation::Add(add)) => {
+                        let response = ManagerService::add(
+                            self,
+                            sub_request(add, &metadata, principal.as_ref()),
+                        )
+                        .await?;
+                        let response = response.into_inner();
+                        (response.success, response.message, response.pending_ops)
+                    }
+                    Some(Operation::Delete(delete)) => {
+                        let response = ManagerService::delete(
+                            self,
+                            sub_request(delete, &metadata, principal.as_ref()),
+                        )
+                        .await?;
+                        let response = response.into_inner();
+                        (response.success, response.message, response.pending_ops)
+                    }
+                    Some(Operation::Update(update)) => {
+                        let response = ManagerService::update(
+                            self,
+                            sub_request(update, &metadata, principal.as_ref()),
+                        )
+                        .await?;
+                        let response = response.into_inner();
+                        (response.success, response.message, response.pending_ops)
+                    }
+                    None => {
+                        return Err(ManagerError::InvalidRequest(
+                            "Batch operation is empty".to_string(),
+                        )
+                        .into())
+                    }
+                };
+                Ok(BatchOperationResult {
+                    success,
+                    message,
+                    pending_ops,
+                    code: Code::Ok as i32,
+                })
+            }
+            .await;
+            results.push(outcome.unwrap_or_else(|status| BatchOperationResult {
+                success: false,
+                message: status.message().to_string(),
+                pending_ops: vec![],
+                code: status.code() as i32,
+            }));
+        }
+
+        let succeeded = results.iter().filter(|result| result.success).count();
+        info!(
+            "Batch completed: {}/{} operation(s) succeeded",
+            succeeded,
+            results.len()
+        );
+        Ok(BatchResponse {
+            results,
+            succeeded: succeeded as u32,
+        })
+    }
+}
+
+/// 用批量请求的元数据和身份包装单个操作
+fn sub_request<T>(message: T, metadata: &MetadataMap, principal: Option<&Principal>) -> Request<T> {
+    let mut request = Request::new(message);
+    *request.metadata_mut() = metadata.clone();
+    if let Some(principal) = principal {
+        request.extensions_mut().insert(principal.clone());
+    }
+    request
+}
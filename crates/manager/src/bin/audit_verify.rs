@@ -0,0 +1,89 @@
+//! 离线审计日志校验工具
+//!
+//! 读取 Manager 通过 `--audit-export` 导出的审计文件，检查：
+//! - 哈希链完整（没有记录被修改、删除或重排）
+//! - 每个 storager 的根哈希前后衔接
+//! - 内嵌证明与记录的验证状态一致
+//!
+//! # 使用方法
+//! ```bash
+//! cargo run --bin audit-verify -- audit.bin
+//!
+//! # 同时核对对外公布的链头
+//! cargo run --bin audit-verify -- audit.bin --expect-head sha256:3f5a...
+//! ```
+
+use manager::core::audit_chain::{verify_chain, verify_proofs, AuditExport};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    let mut path = None;
+    let mut expect_head = None;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--expect-head" => {
+                expect_head = args.get(i + 1).cloned();
+                i += 2;
+            }
+            "--help" | "-h" => {
+                println!("USAGE:");
+                println!("    audit-verify <FILE> [--expect-head <HASH>]");
+                return ExitCode::SUCCESS;
+            }
+            other => {
+                path = Some(other.to_string());
+                i += 1;
+            }
+        }
+    }
+
+    let Some(path) = path else {
+        eprintln!("Missing audit export file (see --help)");
+        return ExitCode::FAILURE;
+    };
+
+    match run(&path, expect_head.as_deref()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(path: &str, expect_head: Option<&str>) -> Result<(), String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let export = AuditExport::decode(&bytes)?;
+    println!(
+        "Audit export: {} entries, ADS mode {}",
+        export.entries.len(),
+        export.ads_mode
+    );
+
+    let head = verify_chain(&export)?;
+    println!("✅ Hash chain intact, head {}", head.to_hex());
+
+    if let Some(expected) = expect_head {
+        if !head.to_hex().eq_ignore_ascii_case(expected) {
+            return Err(format!(
+                "Chain head does not match the published head {}",
+                expected
+            ));
+        }
+        println!("✅ Chain head matches the published head");
+    }
+
+    let problems = verify_proofs(&export)?;
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("   {}", problem);
+        }
+        return Err(format!("{} entries failed proof checks", problems.len()));
+    }
+    println!("✅ All embedded proofs are consistent with their status");
+
+    Ok(())
+}
@@ -0,0 +1,301 @@
+//! 初始数据集的批量导入
+//!
+//! 客户端通过 `BulkAdd` 流式发送 (fid, keywords) 记录，Manager 按 keyword 的副本节点
+//! 把记录拆分到各 storager 的缓冲区，缓冲区满时通过一次 storager 端的 `BulkAdd` 流发送。
+//! storager 为每条记录返回一个证明，Manager 逐条验证并写入审计日志，
+//! 但每个 storager 的根哈希只在导入结束时发布一次。
+//!
+//! 一次导入只写入一个命名空间，由第一条记录决定，之后的记录必须使用同一个命名空间。
+//!
+//! 导入中途失败时（storager 不可用、证明验证失败），已经验证过的部分仍然发布，
+//! 保证 Manager 的根与 storager 的实际内容一致；客户端可以重新发送剩余的记录。
+
+use crate::core::{AuditStatus, Caller, MutationKind, RetryPolicy, RootKey};
+use crate::error::ManagerError;
+use crate::manager::Manager;
+use crate::service::{check_namespace, check_reserved, invalid_proof};
+use common::rpc::{AckMode, BulkAddRecord, BulkAddResponse, RootTransition};
+use common::{Proof, RootHash};
+use std::collections::{BTreeMap, HashSet};
+use tonic::{Code, Status, Streaming};
+use tracing::{info, warn};
+
+/// 每个 storager 缓冲的默认记录数，缓冲区满时发送一批
+pub const DEFAULT_BULK_BATCH: usize = 256;
+
+/// 一个 storager 在导入过程中的状态
+struct StoragerLoad {
+    addr: String,
+    /// 导入开始前发布的根哈希
+    old_root: RootHash,
+    /// 尚未发送的记录（只包含该 storager 承载的 keyword）
+    buffer: Vec<BulkAddRecord>,
+    /// 缓冲区中该 storager 作为主副本的 keyword（用于基数统计）
+    primary_keywords: Vec<String>,
+    /// 最后一条验证通过的记录：(根哈希, epoch, 审计 id)
+    verified: Option<(RootHash, u64, u64)>,
+}
+
+impl Manager {
+    /// 读取记录流并分批写入各 storager，返回每个 storager 的根哈希变化
+    pub(crate) async fn bulk_add_records(
+        &self,
+        mut records: Streaming<BulkAddRecord>,
+        caller: &Caller,
+    ) -> Result<BulkAddResponse, Status> {
+        let mut loads: BTreeMap<String, StoragerLoad> = BTreeMap::new();
+        let mut count = 0;
+        let mut namespace = None;
+        // 导入的记录在 storager 接受后写入 fid 反向索引
+        let mut imported: Vec<(String, Vec<String>)> = Vec::new();
+
+        let outcome: Result<(), Status> = async {
+            while let Some(record) = records.message().await? {
+                count += 1;
+                let namespace = match &namespace {
+                    None => {
+                        check_namespace(&record.namespace)?;
+                        namespace.insert(record.namespace)
+                    }
+                    Some(namespace) if *namespace == record.namespace => namespace,
+                    Some(namespace) => {
+                        return Err(ManagerError::InvalidRequest(format!(
+                            "Record for fid {} is in namespace '{}', expected '{}'",
+                            record.fid, record.namespace, namespace
+                        ))
+                        .into());
+                    }
+                };
+                let mut seen = HashSet::new();
+                let keywords: Vec<String> = record
+                    .keywords
+                    .into_iter()
+                    .filter(|keyword| seen.insert(keyword.clone()))
+                    .collect();
+                if keywords.is_empty() {
+                    return Err(ManagerError::InvalidRequest(format!(
+                        "No keywords provided for fid {}",
+                        record.fid
+                    ))
+                    .into());
+                }
+                caller.check_keywords(&keywords)?;
+                check_reserved(&keywords)?;
+
+                let full = self
+                    .route_record(&mut loads, namespace, &record.fid, &keywords)
+                    .ok_or(ManagerError::NoStorager)?;
+                imported.push((record.fid, keywords));
+                for node_name in full {
+                    let load = loads.get_mut(&node_name).expect("routed storager");
+                    self.flush_bulk(RootKey::new(node_name, namespace.as_str()), load)
+                        .await?;
+                }
+            }
+
+            let namespace = namespace.as_deref().unwrap_or_default();
+            let flushes = loads
+                .iter_mut()
+                .map(|(node_name, load)| {
+                    self.flush_bulk(RootKey::new(node_name.clone(), namespace), load)
+                })
+                .collect();
+            for result in self.fan_out(flushes).await {
+                result?;
+            }
+            Ok(())
+        }
+        .await;
+
+        // 失败时也发布已经验证过的部分，Manager 的根与 storager 的内容保持一致
+        let namespace = namespace.unwrap_or_default();
+        let transitions = self.publish_loads(&namespace, loads);
+        let (success, message) = match outcome {
+            Ok(()) => (
+                true,
+                format!(
+                    "Imported {} record(s) into {} storager(s)",
+                    count,
+                    transitions.len()
+                ),
+            ),
+            Err(status) if status.code() == Code::DataLoss => (false, status.message().to_string()),
+            Err(status) => return Err(status),
+        };
+        for (fid, keywords) in &imported {
+            self.fid_index.insert(&namespace, fid, keywords);
+        }
+        if let Err(e) = self.fid_index.persist() {
+            warn!("Failed to persist fid index: {}", e);
+        }
+        info!("{}", message);
+        self.notify_watches(
+            &namespace,
+            imported.iter().flat_map(|(_, keywords)| keywords),
+        )
+        .await;
+
+        Ok(BulkAddResponse {
+            success,
+            message,
+            records: count,
+            transitions,
+        })
+    }
+
+    /// 把一条记录拆分到其 keyword 的副本节点，返回缓冲区已满的节点
+    ///
+    /// 哈希环上没有节点时返回 None
+    fn route_record(
+        &self,
+        loads: &mut BTreeMap<String, StoragerLoad>,
+        namespace: &str,
+        fid: &str,
+        keywords: &[String],
+    ) -> Option<Vec<String>> {
+        let mut split: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for keyword in keywords {
+            let replicas = self.replicas_for_keyword(keyword);
+            if replicas.is_empty() {
+                return None;
+            }
+            for (index, (node_name, storager_addr)) in replicas.into_iter().enumerate() {
+                let load = loads
+                    .entry(node_name.clone())
+                    .or_insert_with(|| StoragerLoad {
+                        old_root: self.current_root(&RootKey::new(node_name.clone(), namespace)),
+                        addr: storager_addr,
+                        buffer: Vec::new(),
+                        primary_keywords: Vec::new(),
+                        verified: None,
+                    });
+                if index == 0 {
+                    load.primary_keywords.push(keyword.clone());
+                }
+                split.entry(node_name).or_default().push(keyword.clone());
+            }
+        }
+
+        let mut full = Vec::new();
+        for (node_name, keywords) in split {
+            let load = loads.get_mut(&node_name).expect("routed storager");
+            load.buffer.push(BulkAddRecord {
+                fid: fid.to_string(),
+                keywords,
+                namespace: namespace.to_string(),
+            });
+            if load.buffer.len() >= self.bulk_batch {
+                full.push(node_name);
+            }
+        }
+        Some(full)
+    }
+
+    /// 把 storager 缓冲的记录作为一批发送，逐条验证返回的证明并记录审计条目
+    ///
+    /// 证明验证失败时返回 `DataLoss`，此前验证通过的记录仍会在导入结束时发布
+    async fn flush_bulk(&self, key: RootKey, load: &mut StoragerLoad) -> Result<(), Status> {
+        if load.buffer.is_empty() {
+            return Ok(());
+        }
+        let records = std::mem::take(&mut load.buffer);
+        let primary_keywords = std::mem::take(&mut load.primary_keywords);
+
+        // 一批记录的证明生成时间随批次大小增长，不设单次超时；
+        // 重复添加不改变 ADS，连接失败后整批重新发送是安全的
+        let policy = RetryPolicy {
+            timeout: None,
+            ..self.retry.clone()
+        };
+        let resp = self
+            .call_storager_with(&policy, &load.addr, "BulkAdd", |mut client| {
+                let records = records.clone();
+                async move { client.bulk_add(tokio_stream::iter(records)).await }
+            })
+            .await?;
+        for keyword in &primary_keywords {
+            self.record_cardinality(MutationKind::Add, keyword);
+        }
+
+        for step in resp.steps {
+            let proof = Proof::try_from(step.proof).map_err(invalid_proof)?;
+            let verified = self.verify_proof(&proof, &step.root_hash)
+                && self.root_store.accumulators().track(
+                    MutationKind::Add,
+                    &key,
+                    &step.keywords,
+                    &step.fid,
+                    &proof,
+                );
+            let status = if verified {
+                AuditStatus::Verified
+            } else {
+                AuditStatus::Rejected
+            };
+            let mut audit_id = 0;
+            for keyword in &step.keywords {
+                audit_id = self.audit_log.record(
+                    MutationKind::Add,
+                    &key.storager,
+                    keyword,
+                    &step.fid,
+                    AckMode::Sync,
+                    step.root_hash.clone(),
+                    proof.clone(),
+                    status,
+                );
+            }
+            if !verified {
+                return Err(ManagerError::VerificationFailed(format!(
+                    "Proof verification failed on {} for fid {}",
+                    key.storager, step.fid
+                ))
+                .into());
+            }
+            Self::record_sketches(
+                self.root_store.sketches(),
+                &key,
+                &step.keywords,
+                step.epoch,
+                step.sketch_digests,
+            );
+            // 导入期间的查询可能落在中间的 epoch 上，先记入历史（当前根在导入结束时才发布）
+            self.root_history
+                .record(&key, step.epoch, step.root_hash.clone());
+            load.verified = Some((step.root_hash, step.epoch, audit_id));
+        }
+        Ok(())
+    }
+
+    /// 发布每个 storager 在 `namespace` 中最后验证通过的根哈希
+    fn publish_loads(
+        &self,
+        namespace: &str,
+        loads: BTreeMap<String, StoragerLoad>,
+    ) -> Vec<RootTransition> {
+        loads
+            .into_iter()
+            .filter_map(|(node_name, load)| {
+                let (root_hash, epoch, audit_id) = load.verified?;
+                Self::publish_root(
+                    &self.root_hashes,
+                    &self.root_versions,
+                    &self.root_history,
+                    &self.root_store,
+                    &self.root_updates,
+                    RootKey::new(node_name.clone(), namespace),
+                    root_hash.clone(),
+                    Some(epoch),
+                    audit_id,
+                );
+                Some(RootTransition {
+                    storager: node_name,
+                    old_root_hash: load.old_root,
+                    new_root_hash: root_hash,
+                    epoch,
+                    namespace: namespace.to_string(),
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,264 @@
+//! 每个 keyword 的累加器值
+//!
+//! 密码学累加器模式下 storager 为每个 keyword 维护一个累加器，查询的成员资格证明只能说明
+//! 返回的 fid 恰好构成证明中携带的累加器。与 MPT 模式跟踪根哈希一样，Manager 从验证通过的
+//! 添加、删除证明中跟踪每个 (storager, 命名空间, keyword) 当前的累加器值，查询证明中的累加器
+//! 必须与之相同，storager 丢掉 fid 后就给不出能通过检查的证明。
+//!
+//! 一条添加/删除记录把累加器从旧值变为新值，只有旧值等于跟踪的值时才前进；并发写入的证明
+//! 可能乱序验证，衔接不上的记录先缓存，等前面的记录到达后依次应用。添加已经存在的 fid 时
+//! 记录的新值就是当前值，跟踪的值不变。storager 应用了变更而 Manager 没有收到证明时，
+//! 之后的记录都衔接不上，该 keyword 的查询报告为未验证，直到关键词迁移重新设置它的值。
+//!
+//! 跟踪的值随根哈希一起持久化（见 [`crate::core::root_store`]），缓存的记录不持久化。
+
+use super::verification::{decode_update_records, encode_accumulator};
+use super::{MutationKind, RootKey};
+use common::{Proof, RootHash};
+use esa_rust::crypto_accumulator::acc::dynamic_accumulator::{
+    element_to_field, DynamicAccumulator,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, OnceLock, RwLock};
+use tracing::warn;
+
+/// 一个 keyword 衔接不上的记录：旧值到新值
+type Transitions = HashMap<RootHash, RootHash>;
+
+/// 没有 fid 的 keyword 的累加器值
+pub fn empty_accumulator() -> &'static RootHash {
+    static EMPTY: OnceLock<RootHash> = OnceLock::new();
+    EMPTY.get_or_init(|| encode_accumulator(&DynamicAccumulator::new().acc_value))
+}
+
+/// 各 storager 命名空间中每个 keyword 当前的累加器值
+#[derive(Default)]
+pub struct KeywordAccumulators {
+    /// 只记录有 fid 的 keyword
+    values: RwLock<HashMap<RootKey, BTreeMap<String, RootHash>>>,
+    pending: Mutex<HashMap<(RootKey, String), Transitions>>,
+}
+
+impl KeywordAccumulators {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// keyword 当前的累加器值，没有记录时为空累加器
+    pub fn value(&self, key: &RootKey, keyword: &str) -> RootHash {
+        self.values
+            .read()
+            .unwrap()
+            .get(key)
+            .and_then(|keywords| keywords.get(keyword))
+            .unwrap_or_else(|| empty_accumulator())
+            .clone()
+    }
+
+    /// 直接设置 keyword 的累加器值（关键词迁移验证了迁移后的内容时使用），丢弃缓存的记录
+    pub fn set(&self, key: &RootKey, keyword: &str, value: RootHash) {
+        let mut values = self.values.write().unwrap();
+        self.pending
+            .lock()
+            .unwrap()
+            .remove(&(key.clone(), keyword.to_string()));
+        Self::store(&mut values, key, keyword, value);
+    }
+
+    /// 按一次已验证的变更推进 `keywords` 的累加器值
+    ///
+    /// 证明必须与变更的类型一致，并且按顺序为每个 keyword 带有一条元素为 `fid` 的记录，
+    /// 否则返回 false，调用方应拒绝这次变更。其他 ADS 模式的证明不做任何事，返回 true
+    pub fn track(
+        &self,
+        kind: MutationKind,
+        key: &RootKey,
+        keywords: &[String],
+        fid: &str,
+        proof: &Proof,
+    ) -> bool {
+        let data = match (kind, proof) {
+            (MutationKind::Add, Proof::AccumulatorAdd(data))
+            | (MutationKind::Delete, Proof::AccumulatorDelete(data)) => data,
+            (_, Proof::AccumulatorAdd(_) | Proof::AccumulatorDelete(_)) => {
+                warn!("Accumulator proof does not match the {:?} mutation", kind);
+                return false;
+            }
+            _ => return true,
+        };
+        let element = element_to_field(fid);
+        let Some(records) = decode_update_records(data).filter(|records| {
+            records.len() == keywords.len() && records.iter().all(|r| r.element == element)
+        }) else {
+            warn!(
+                "Accumulator proof from {} does not carry one record of '{}' per keyword",
+                key.storager, fid
+            );
+            return false;
+        };
+
+        let mut values = self.values.write().unwrap();
+        let mut pending = self.pending.lock().unwrap();
+        for (keyword, record) in keywords.iter().zip(records) {
+            let (old, new) = (
+                encode_accumulator(&record.old),
+                encode_accumulator(&record.new),
+            );
+            let current = values
+                .get(key)
+                .and_then(|keywords| keywords.get(keyword))
+                .unwrap_or_else(|| empty_accumulator());
+            if kind == MutationKind::Add && new == *current {
+                continue;
+            }
+            let slot = (key.clone(), keyword.clone());
+            if old != *current {
+                pending.entry(slot).or_default().insert(old, new);
+                continue;
+            }
+
+            let mut value = new;
+            if let Some(transitions) = pending.get_mut(&slot) {
+                while let Some(next) = transitions.remove(&value) {
+                    value = next;
+                }
+                if transitions.is_empty() {
+                    pending.remove(&slot);
+                }
+            }
+            Self::store(&mut values, key, keyword, value);
+        }
+        true
+    }
+
+    /// 所有跟踪的值
+    pub fn snapshot(&self) -> HashMap<RootKey, BTreeMap<String, RootHash>> {
+        self.values.read().unwrap().clone()
+    }
+
+    /// 用持久化的值替换 storager 命名空间的所有 keyword
+    pub fn restore(&self, key: RootKey, keywords: BTreeMap<String, RootHash>) {
+        let mut values = self.values.write().unwrap();
+        if keywords.is_empty() {
+            values.remove(&key);
+        } else {
+            values.insert(key, keywords);
+        }
+    }
+
+    fn store(
+        values: &mut HashMap<RootKey, BTreeMap<String, RootHash>>,
+        key: &RootKey,
+        keyword: &str,
+        value: RootHash,
+    ) {
+        if value == *empty_accumulator() {
+            if let Some(keywords) = values.get_mut(key) {
+                keywords.remove(keyword);
+                if keywords.is_empty() {
+                    values.remove(key);
+                }
+            }
+        } else {
+            values
+                .entry(key.clone())
+                .or_default()
+                .insert(keyword.to_string(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_serialize::CanonicalSerialize;
+
+    /// 把 `fid` 加入 `acc`（或从中删除），返回这次变更的记录
+    fn record(acc: &mut DynamicAccumulator, fid: &str, add: bool) -> Vec<u8> {
+        let mut data = Vec::new();
+        acc.acc_value.serialize(&mut data).unwrap();
+        if add {
+            acc.add(fid).unwrap();
+        } else {
+            acc.delete(fid).unwrap();
+        }
+        acc.acc_value.serialize(&mut data).unwrap();
+        element_to_field(fid).serialize(&mut data).unwrap();
+        data.push(1);
+        data
+    }
+
+    fn keywords(keywords: &[&str]) -> Vec<String> {
+        keywords.iter().map(|k| k.to_string()).collect()
+    }
+
+    #[test]
+    fn test_follows_out_of_order_records() {
+        let tracked = KeywordAccumulators::new();
+        let key = RootKey::new("s1", "");
+        let rust = keywords(&["rust"]);
+        let mut acc = DynamicAccumulator::new();
+        let first = record(&mut acc, "f1", true);
+        let second = record(&mut acc, "f2", true);
+        let after_second = encode_accumulator(&acc.acc_value);
+
+        // 第二条记录先到，衔接不上时先缓存
+        let add = |fid, data: &Vec<u8>| {
+            tracked.track(
+                MutationKind::Add,
+                &key,
+                &rust,
+                fid,
+                &Proof::AccumulatorAdd(data.clone()),
+            )
+        };
+        assert!(add("f2", &second));
+        assert_eq!(tracked.value(&key, "rust"), *empty_accumulator());
+        assert!(add("f1", &first));
+        assert_eq!(tracked.value(&key, "rust"), after_second);
+        // 重复的添加不改变跟踪的值
+        assert!(add("f2", &second));
+        assert_eq!(tracked.value(&key, "rust"), after_second);
+
+        let delete = |fid, data: Vec<u8>| {
+            tracked.track(
+                MutationKind::Delete,
+                &key,
+                &rust,
+                fid,
+                &Proof::AccumulatorDelete(data),
+            )
+        };
+        assert!(delete("f1", record(&mut acc, "f1", false)));
+        assert!(delete("f2", record(&mut acc, "f2", false)));
+        assert!(tracked.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_rejects_mismatched_records() {
+        let tracked = KeywordAccumulators::new();
+        let key = RootKey::new("s1", "");
+        let mut acc = DynamicAccumulator::new();
+        let data = record(&mut acc, "f1", true);
+        let proof = Proof::AccumulatorAdd(data.clone());
+
+        // 记录的元素不是请求的 fid、数量与 keyword 不符、类型与变更不符
+        let (rust, two) = (keywords(&["rust"]), keywords(&["rust", "go"]));
+        assert!(!tracked.track(MutationKind::Add, &key, &rust, "f2", &proof));
+        assert!(!tracked.track(MutationKind::Add, &key, &two, "f1", &proof));
+        assert!(!tracked.track(MutationKind::Delete, &key, &rust, "f1", &proof));
+        assert!(tracked.snapshot().is_empty());
+
+        // 批量写入的证明每个 keyword 一条记录
+        let mut go = DynamicAccumulator::new();
+        let batch = Proof::AccumulatorAdd([data, record(&mut go, "f1", true)].concat());
+        assert!(tracked.track(MutationKind::Add, &key, &two, "f1", &batch));
+        assert_eq!(
+            tracked.value(&key, "rust"),
+            encode_accumulator(&acc.acc_value)
+        );
+        assert_eq!(tracked.value(&key, "go"), encode_accumulator(&go.acc_value));
+        // 其他模式的证明不跟踪
+        assert!(tracked.track(MutationKind::Add, &key, &two, "f1", &Proof::Mpt(vec![])));
+    }
+}
@@ -0,0 +1,192 @@
+//! 查询准入控制模块
+//!
+//! 根据关键词基数统计估计查询代价，超出预算的查询被拒绝，
+//! 或者在调用方允许时排入后台优先级队列执行。
+//!
+//! 基数统计来自 Manager 观察到的 Add / Delete 变更，是近似值：
+//! Manager 重启后统计从零开始，此时所有查询都会被放行。
+
+use common::BooleanExpr;
+pub use common::QueryRejected;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// 准入控制配置
+#[derive(Debug, Clone)]
+pub struct AdmissionConfig {
+    /// 单个查询的代价预算（None 表示不限制）
+    pub budget: Option<u64>,
+    /// 每个关键词的固定代价（一次 storager 往返和一次证明验证）
+    pub per_keyword_cost: u64,
+    /// 后台优先级队列的并发度
+    pub background_concurrency: usize,
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        AdmissionConfig {
+            budget: None,
+            per_keyword_cost: 100,
+            background_concurrency: 1,
+        }
+    }
+}
+
+/// 准入结果
+pub enum Admission {
+    /// 在预算内，立即执行
+    Admitted,
+    /// 超出预算，持有后台队列许可后执行
+    Background(OwnedSemaphorePermit),
+}
+
+/// 查询准入控制器
+pub struct AdmissionController {
+    config: AdmissionConfig,
+    /// keyword → 近似 fid 数量
+    cardinalities: RwLock<HashMap<String, u64>>,
+    /// 后台优先级队列
+    background: Arc<Semaphore>,
+}
+
+impl AdmissionController {
+    /// 创建准入控制器
+    pub fn new(config: AdmissionConfig) -> Self {
+        let background = Arc::new(Semaphore::new(config.background_concurrency.max(1)));
+        AdmissionController {
+            config,
+            cardinalities: RwLock::new(HashMap::new()),
+            background,
+        }
+    }
+
+    /// 记录一次 keyword 的添加
+    pub fn record_add(&self, keyword: &str) {
+        let mut cards = self.cardinalities.write().unwrap();
+        *cards.entry(keyword.to_string()).or_insert(0) += 1;
+    }
+
+    /// 记录一次 keyword 的删除
+    pub fn record_delete(&self, keyword: &str) {
+        let mut cards = self.cardinalities.write().unwrap();
+        if let Some(count) = cards.get_mut(keyword) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                cards.remove(keyword);
+            }
+        }
+    }
+
+    /// keyword 的近似基数
+    pub fn cardinality(&self, keyword: &str) -> u64 {
+        self.cardinalities
+            .read()
+            .unwrap()
+            .get(keyword)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// 估计布尔查询的代价
+    ///
+    /// 无论 AND / OR / NOT 如何组合，Manager 都要取回每个关键词的完整结果，
+    /// 因此代价为所有不同关键词的基数之和，加上每个关键词的固定代价
+    pub fn estimate_cost(&self, expr: &BooleanExpr) -> u64 {
+        let cards = self.cardinalities.read().unwrap();
+        expr.get_keywords()
+            .iter()
+            .map(|k| cards.get(k).copied().unwrap_or(0) + self.config.per_keyword_cost)
+            .fold(0u64, |acc, c| acc.saturating_add(c))
+    }
+
+    /// 对查询做准入判断
+    ///
+    /// # 参数
+    ///
+    /// * `expr` - 查询表达式（单关键词查询使用 `BooleanExpr::Keyword`）
+    /// * `allow_background` - 调用方是否允许超出预算时排入后台队列
+    pub async fn admit(
+        &self,
+        expr: &BooleanExpr,
+        allow_background: bool,
+    ) -> Result<Admission, QueryRejected> {
+        let budget = match self.config.budget {
+            Some(budget) => budget,
+            None => return Ok(Admission::Admitted),
+        };
+
+        let estimated_cost = self.estimate_cost(expr);
+        if estimated_cost <= budget {
+            return Ok(Admission::Admitted);
+        }
+
+        if !allow_background {
+            return Err(QueryRejected {
+                estimated_cost,
+                budget,
+            });
+        }
+
+        let permit = self
+            .background
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("background semaphore is never closed");
+        Ok(Admission::Background(permit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::parse_boolean_expr;
+    use tonic::Status;
+
+    fn controller(budget: u64) -> AdmissionController {
+        let controller = AdmissionController::new(AdmissionConfig {
+            budget: Some(budget),
+            per_keyword_cost: 1,
+            background_concurrency: 1,
+        });
+        for _ in 0..50 {
+            controller.record_add("hot");
+        }
+        controller.record_add("cold");
+        controller
+    }
+
+    #[test]
+    fn test_estimate_cost_uses_cardinalities() {
+        let c = controller(10);
+        let expr = parse_boolean_expr("hot OR cold OR hot").unwrap();
+        assert_eq!(c.estimate_cost(&expr), 51 + 2);
+
+        c.record_delete("cold");
+        assert_eq!(c.cardinality("cold"), 0);
+        assert_eq!(c.estimate_cost(&expr), 50 + 2);
+    }
+
+    #[tokio::test]
+    async fn test_admission_rejects_or_queues() {
+        let c = controller(10);
+        let cheap = parse_boolean_expr("cold").unwrap();
+        let broad = parse_boolean_expr("hot OR cold").unwrap();
+
+        assert!(matches!(
+            c.admit(&cheap, false).await,
+            Ok(Admission::Admitted)
+        ));
+
+        let rejected = c.admit(&broad, false).await.err().unwrap();
+        assert_eq!(rejected.budget, 10);
+        let status = Status::from(rejected.clone());
+        assert_eq!(QueryRejected::from_status(&status), Some(rejected));
+
+        assert!(matches!(
+            c.admit(&broad, true).await,
+            Ok(Admission::Background(_))
+        ));
+    }
+}
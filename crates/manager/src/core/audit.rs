@@ -0,0 +1,300 @@
+//! 变更审计模块
+//!
+//! 记录每一次发往 storager 的变更及其证明验证状态。
+//! 异步确认模式下，变更在证明验证完成前处于 [`AuditStatus::Pending`] 状态，
+//! 以便运维人员追踪尚未确认的根哈希发布。
+//!
+//! 每条记录同时保存变更前后的根哈希和 storager 返回的证明，
+//! 可以通过 [`crate::core::audit_chain`] 导出为防篡改的哈希链。
+
+use common::clock::{system_clock, LogicalClock, SharedClock};
+use common::rpc::AckMode;
+use common::{Proof, RootHash};
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+/// 变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutationKind {
+    Add,
+    Delete,
+}
+
+/// 证明验证状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditStatus {
+    /// 已确认前验证通过（同步模式）
+    Verified,
+    /// 已确认，等待后台验证（异步模式）
+    Pending,
+    /// 后台验证通过
+    Confirmed,
+    /// 证明验证失败
+    Rejected,
+}
+
+/// 一条审计记录
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    /// 单调递增的记录 id（逻辑序号，排序以它为准）
+    pub id: u64,
+    /// 记录时的墙上时间（UNIX 毫秒，仅供参考，可能因时钟回拨而乱序）
+    pub recorded_at_ms: u64,
+    pub kind: MutationKind,
+    pub storager: String,
+    pub keyword: String,
+    pub fid: String,
+    pub ack_mode: AckMode,
+    /// 变更前该 storager 最近一次记录的根哈希（首次变更时为空）
+    pub prev_root: RootHash,
+    /// 变更后的根哈希
+    pub root_hash: RootHash,
+    /// storager 返回的变更证明（推迟生成的证明取回之前为空）
+    pub proof: Proof,
+    pub status: AuditStatus,
+}
+
+/// 审计日志
+#[derive(Debug)]
+pub struct AuditLog {
+    entries: RwLock<Vec<AuditEntry>>,
+    /// 分配记录 id
+    sequence: LogicalClock,
+    /// storager 名称到最近一次记录的根哈希
+    last_roots: RwLock<HashMap<String, RootHash>>,
+    clock: SharedClock,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::with_clock(system_clock())
+    }
+}
+
+impl AuditLog {
+    /// 创建空的审计日志
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 使用指定时间源创建空的审计日志
+    pub fn with_clock(clock: SharedClock) -> Self {
+        AuditLog {
+            entries: RwLock::new(Vec::new()),
+            sequence: LogicalClock::new(),
+            last_roots: RwLock::new(HashMap::new()),
+            clock,
+        }
+    }
+
+    /// 追加一条记录，返回记录 id
+    ///
+    /// 记录 id 的顺序与追加顺序一致，`prev_root` 取自同一 storager 的上一条记录
+    #[allow(clippy::too_many_arguments)]
+    pub fn record(
+        &self,
+        kind: MutationKind,
+        storager: &str,
+        keyword: &str,
+        fid: &str,
+        ack_mode: AckMode,
+        root_hash: RootHash,
+        proof: Proof,
+        status: AuditStatus,
+    ) -> u64 {
+        let mut entries = self.entries.write().unwrap();
+        let stamp = self.sequence.stamp(self.clock.as_ref());
+        let prev_root = self
+            .last_roots
+            .write()
+            .unwrap()
+            .insert(storager.to_string(), root_hash.clone())
+            .unwrap_or_default();
+        entries.push(AuditEntry {
+            id: stamp.sequence,
+            recorded_at_ms: stamp.wall_millis,
+            kind,
+            storager: storager.to_string(),
+            keyword: keyword.to_string(),
+            fid: fid.to_string(),
+            ack_mode,
+            prev_root,
+            root_hash,
+            proof,
+            status,
+        });
+        stamp.sequence
+    }
+
+    /// 更新记录的验证状态
+    pub fn set_status(&self, id: u64, status: AuditStatus) {
+        let mut entries = self.entries.write().unwrap();
+        if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+            entry.status = status;
+        }
+    }
+
+    /// 填入后台取回的推迟证明
+    pub fn set_proof(&self, id: u64, proof: Proof) {
+        let mut entries = self.entries.write().unwrap();
+        if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+            entry.proof = proof;
+        }
+    }
+
+    /// 按 id 查找记录
+    pub fn get(&self, id: u64) -> Option<AuditEntry> {
+        self.entries
+            .read()
+            .unwrap()
+            .iter()
+            .find(|e| e.id == id)
+            .cloned()
+    }
+
+    /// 按 id 顺序返回所有记录的快照
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.entries.read().unwrap().clone()
+    }
+
+    /// 所有仍在等待验证的记录
+    pub fn pending(&self) -> Vec<AuditEntry> {
+        self.entries
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|e| e.status == AuditStatus::Pending)
+            .cloned()
+            .collect()
+    }
+}
+
+/// 确认模式策略
+///
+/// 客户端可以按请求选择异步确认，但写入关键租户命名空间的请求（以及关闭了异步确认的部署）
+/// 总是被强制为同步确认。策略按请求写入的命名空间而不是请求自报的 `tenant` 字段判断，
+/// 客户端无法通过改写 `tenant` 让关键租户的数据绕过同步确认。
+#[derive(Debug, Clone)]
+pub struct AckPolicy {
+    /// 是否允许异步确认
+    pub allow_async: bool,
+    /// 必须同步确认的租户命名空间
+    pub sync_tenants: HashSet<String>,
+}
+
+impl Default for AckPolicy {
+    fn default() -> Self {
+        AckPolicy {
+            allow_async: true,
+            sync_tenants: HashSet::new(),
+        }
+    }
+}
+
+impl AckPolicy {
+    /// 只允许同步确认的策略
+    pub fn sync_only() -> Self {
+        AckPolicy {
+            allow_async: false,
+            sync_tenants: HashSet::new(),
+        }
+    }
+
+    /// 将租户命名空间标记为必须同步确认
+    pub fn require_sync_for(mut self, namespace: impl Into<String>) -> Self {
+        self.sync_tenants.insert(namespace.into());
+        self
+    }
+
+    /// 计算写入 `namespace` 的请求实际使用的确认模式
+    pub fn effective_mode(&self, requested: AckMode, namespace: &str) -> AckMode {
+        if !self.allow_async || self.sync_tenants.contains(namespace) {
+            AckMode::Sync
+        } else {
+            requested
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_enforces_sync_for_critical_tenants() {
+        let policy = AckPolicy::default().require_sync_for("bank");
+        assert_eq!(policy.effective_mode(AckMode::Async, "bank"), AckMode::Sync);
+        assert_eq!(
+            policy.effective_mode(AckMode::Async, "blog"),
+            AckMode::Async
+        );
+        assert_eq!(
+            AckPolicy::sync_only().effective_mode(AckMode::Async, "blog"),
+            AckMode::Sync
+        );
+    }
+
+    #[test]
+    fn test_audit_log_tracks_pending() {
+        let log = AuditLog::new();
+        let a = log.record(
+            MutationKind::Add,
+            "storager-0",
+            "rust",
+            "f1",
+            AckMode::Async,
+            vec![1],
+            Proof::Mpt(vec![]),
+            AuditStatus::Pending,
+        );
+        let b = log.record(
+            MutationKind::Delete,
+            "storager-0",
+            "go",
+            "f2",
+            AckMode::Sync,
+            vec![2],
+            Proof::Mpt(vec![]),
+            AuditStatus::Verified,
+        );
+        assert!(b > a);
+        assert_eq!(log.get(b).unwrap().prev_root, vec![1]);
+        assert!(log.get(b).unwrap().recorded_at_ms > 0);
+        assert_eq!(log.pending().len(), 1);
+
+        log.set_status(a, AuditStatus::Confirmed);
+        assert!(log.pending().is_empty());
+        assert_eq!(log.get(a).unwrap().status, AuditStatus::Confirmed);
+    }
+
+    #[test]
+    fn test_audit_log_uses_injected_clock() {
+        use common::clock::MockClock;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let clock = MockClock::new(Duration::from_secs(1_000));
+        let log = AuditLog::with_clock(Arc::new(clock.clone()));
+        let record = |log: &AuditLog| {
+            log.record(
+                MutationKind::Add,
+                "storager-0",
+                "rust",
+                "f1",
+                AckMode::Sync,
+                vec![],
+                Proof::Mpt(vec![]),
+                AuditStatus::Verified,
+            )
+        };
+
+        let a = record(&log);
+        // 墙上时间回拨后 id 仍然递增
+        clock.set(Duration::from_secs(500));
+        let b = record(&log);
+
+        assert_eq!(log.get(a).unwrap().recorded_at_ms, 1_000_000);
+        assert_eq!(log.get(b).unwrap().recorded_at_ms, 500_000);
+        assert!(b > a);
+    }
+}
@@ -0,0 +1,580 @@
+//! 审计日志的规范编码与哈希链
+//!
+//! 审计记录按固定的字段顺序编码：整数使用定长大端序，变长字段带 `u32` 长度前缀，
+//! 哈希值带算法标签。同一条记录在任何平台、任何版本的 Manager 上都编码为相同的字节。
+//!
+//! 导出时每条记录与前一条的链哈希串联：
+//!
+//! ```text
+//! chain_0 = SHA-256(header)
+//! chain_i = SHA-256(chain_{i-1} || entry_i)
+//! ```
+//!
+//! 修改、删除或重排任意一条记录都会改变其后所有链哈希，
+//! 因此只需对外公布最后一个链哈希（链头）即可让导出文件自证完整；
+//! 截断文件得到的链仍然自洽，但链头与公布的不一致。
+//!
+//! # 示例
+//!
+//! ```
+//! use common::rpc::AckMode;
+//! use common::{AdsMode, Proof};
+//! use manager::core::audit_chain::{export, verify_chain, AuditExport};
+//! use manager::core::{AuditLog, AuditStatus, MutationKind};
+//!
+//! let log = AuditLog::new();
+//! log.record(
+//!     MutationKind::Add,
+//!     "storager-0",
+//!     "rust",
+//!     "f1",
+//!     AckMode::Sync,
+//!     vec![7; 32],
+//!     Proof::Mpt(vec![7; 32]),
+//!     AuditStatus::Verified,
+//! );
+//!
+//! let bytes = export(&log, AdsMode::Mpt);
+//! let parsed = AuditExport::decode(&bytes).unwrap();
+//! assert_eq!(parsed.entries.len(), 1);
+//! assert!(verify_chain(&parsed).is_ok());
+//! ```
+
+use crate::core::audit::{AuditEntry, AuditLog, AuditStatus, MutationKind};
+use crate::core::verification::ProofVerifier;
+use common::rpc::AckMode;
+use common::{AdsMode, Proof};
+use sha2::{Digest, Sha256};
+
+/// 导出文件的魔数
+pub const EXPORT_MAGIC: &[u8; 8] = b"DSSAUDIT";
+/// 导出格式版本
+pub const EXPORT_VERSION: u16 = 1;
+/// 单条记录的编码版本（版本 1 没有记录时间，版本 2 之前的证明没有类型标签，仍可解析）
+pub const ENTRY_VERSION: u8 = 3;
+
+/// 哈希算法标签
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum HashAlgorithm {
+    Sha256 = 1,
+}
+
+impl HashAlgorithm {
+    fn from_tag(tag: u8) -> Result<Self, String> {
+        match tag {
+            1 => Ok(HashAlgorithm::Sha256),
+            other => Err(format!("Unknown hash algorithm tag: {}", other)),
+        }
+    }
+
+    fn digest(&self, parts: &[&[u8]]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha256 => {
+                let mut hasher = Sha256::new();
+                for part in parts {
+                    hasher.update(part);
+                }
+                hasher.finalize().to_vec()
+            }
+        }
+    }
+}
+
+/// 带算法标签的哈希值
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaggedHash {
+    pub algorithm: HashAlgorithm,
+    pub bytes: Vec<u8>,
+}
+
+impl TaggedHash {
+    /// 十六进制表示，形如 `sha256:ab12...`
+    pub fn to_hex(&self) -> String {
+        let name = match self.algorithm {
+            HashAlgorithm::Sha256 => "sha256",
+        };
+        let hex: String = self.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("{}:{}", name, hex)
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.algorithm as u8);
+        out.push(self.bytes.len() as u8);
+        out.extend_from_slice(&self.bytes);
+    }
+
+    fn decode(reader: &mut Reader) -> Result<Self, String> {
+        let algorithm = HashAlgorithm::from_tag(reader.u8()?)?;
+        let len = reader.u8()? as usize;
+        Ok(TaggedHash {
+            algorithm,
+            bytes: reader.bytes(len)?.to_vec(),
+        })
+    }
+}
+
+fn kind_tag(kind: MutationKind) -> u8 {
+    match kind {
+        MutationKind::Add => 1,
+        MutationKind::Delete => 2,
+    }
+}
+
+fn status_tag(status: AuditStatus) -> u8 {
+    match status {
+        AuditStatus::Verified => 1,
+        AuditStatus::Pending => 2,
+        AuditStatus::Confirmed => 3,
+        AuditStatus::Rejected => 4,
+    }
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+impl AuditEntry {
+    /// 规范编码
+    ///
+    /// 字段顺序：版本、id、记录时间、变更类型、确认模式、验证状态、storager、keyword、fid、
+    /// 变更前根哈希、变更后根哈希、证明
+    pub fn encode_canonical(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.push(ENTRY_VERSION);
+        out.extend_from_slice(&self.id.to_be_bytes());
+        out.extend_from_slice(&self.recorded_at_ms.to_be_bytes());
+        out.push(kind_tag(self.kind));
+        out.push(self.ack_mode as i32 as u8);
+        out.push(status_tag(self.status));
+        put_bytes(&mut out, self.storager.as_bytes());
+        put_bytes(&mut out, self.keyword.as_bytes());
+        put_bytes(&mut out, self.fid.as_bytes());
+        put_bytes(&mut out, &self.prev_root);
+        put_bytes(&mut out, &self.root_hash);
+        put_bytes(&mut out, &self.proof.to_bytes());
+        out
+    }
+
+    /// 解析规范编码
+    ///
+    /// `ads_mode` 是导出时的 ADS 模式名称，用于给版本 3 之前没有类型标签的证明补上类型
+    pub fn decode_canonical(bytes: &[u8], ads_mode: &str) -> Result<Self, String> {
+        let mut reader = Reader::new(bytes);
+        let version = reader.u8()?;
+        if version == 0 || version > ENTRY_VERSION {
+            return Err(format!("Unsupported audit entry version: {}", version));
+        }
+        let id = reader.u64()?;
+        let recorded_at_ms = if version >= 2 { reader.u64()? } else { 0 };
+        let kind = match reader.u8()? {
+            1 => MutationKind::Add,
+            2 => MutationKind::Delete,
+            other => return Err(format!("Unknown mutation kind tag: {}", other)),
+        };
+        let ack_mode = AckMode::try_from(reader.u8()? as i32)
+            .map_err(|_| "Unknown ack mode tag".to_string())?;
+        let status = match reader.u8()? {
+            1 => AuditStatus::Verified,
+            2 => AuditStatus::Pending,
+            3 => AuditStatus::Confirmed,
+            4 => AuditStatus::Rejected,
+            other => return Err(format!("Unknown audit status tag: {}", other)),
+        };
+        let storager = reader.string()?;
+        let keyword = reader.string()?;
+        let fid = reader.string()?;
+        let prev_root = reader.prefixed()?.to_vec();
+        let root_hash = reader.prefixed()?.to_vec();
+        let proof = reader.prefixed()?;
+        let proof = if version >= 3 {
+            Proof::from_bytes(proof)?
+        } else {
+            legacy_proof(ads_mode, kind, proof.to_vec())
+        };
+        let entry = AuditEntry {
+            id,
+            recorded_at_ms,
+            kind,
+            ack_mode,
+            status,
+            storager,
+            keyword,
+            fid,
+            prev_root,
+            root_hash,
+            proof,
+        };
+        reader.finish()?;
+        Ok(entry)
+    }
+}
+
+/// 按导出时的模式和变更类型确定旧版本记录中证明的类型
+fn legacy_proof(ads_mode: &str, kind: MutationKind, data: Vec<u8>) -> Proof {
+    match (AdsMode::from_name(ads_mode), kind) {
+        (Some(AdsMode::CryptoAccumulator), MutationKind::Add) => Proof::AccumulatorAdd(data),
+        (Some(AdsMode::CryptoAccumulator), MutationKind::Delete) => Proof::AccumulatorDelete(data),
+        (Some(AdsMode::Mpt), _) => Proof::Mpt(data),
+        (Some(AdsMode::MerkleTree), _) => Proof::Merkle(data),
+        _ => Proof::Custom(data),
+    }
+}
+
+/// 导出文件中的一条记录
+#[derive(Debug, Clone)]
+pub struct ChainedEntry {
+    pub entry: AuditEntry,
+    /// 规范编码（链哈希按原始字节计算，不依赖重新编码）
+    pub encoded: Vec<u8>,
+    /// 截至本条记录的链哈希
+    pub chain_hash: TaggedHash,
+}
+
+/// 解析后的导出文件
+#[derive(Debug, Clone)]
+pub struct AuditExport {
+    /// 导出时 Manager 使用的 ADS 模式名称
+    pub ads_mode: String,
+    /// 链哈希算法
+    pub algorithm: HashAlgorithm,
+    pub entries: Vec<ChainedEntry>,
+}
+
+/// 文件头（参与链哈希计算；记录数不参与，因此链可以继续追加）
+fn encode_header(ads_mode: &str, algorithm: HashAlgorithm) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(EXPORT_MAGIC);
+    out.extend_from_slice(&EXPORT_VERSION.to_be_bytes());
+    out.push(algorithm as u8);
+    put_bytes(&mut out, ads_mode.as_bytes());
+    out
+}
+
+/// 将审计日志导出为带哈希链的字节流
+pub fn export(log: &AuditLog, ads_mode: AdsMode) -> Vec<u8> {
+    let algorithm = HashAlgorithm::Sha256;
+    let entries = log.entries();
+    let header = encode_header(ads_mode.name(), algorithm);
+
+    let mut chain = algorithm.digest(&[&header]);
+    let mut out = header;
+    out.extend_from_slice(&(entries.len() as u64).to_be_bytes());
+    for entry in &entries {
+        let encoded = entry.encode_canonical();
+        chain = algorithm.digest(&[&chain, &encoded]);
+        put_bytes(&mut out, &encoded);
+        TaggedHash {
+            algorithm,
+            bytes: chain.clone(),
+        }
+        .encode(&mut out);
+    }
+    out
+}
+
+impl AuditExport {
+    /// 解析导出文件（只检查格式，不验证哈希链）
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader::new(bytes);
+        if reader.bytes(EXPORT_MAGIC.len())? != EXPORT_MAGIC {
+            return Err("Not an audit export (bad magic)".to_string());
+        }
+        let version = u16::from_be_bytes(reader.array()?);
+        if version != EXPORT_VERSION {
+            return Err(format!("Unsupported audit export version: {}", version));
+        }
+        let algorithm = HashAlgorithm::from_tag(reader.u8()?)?;
+        let ads_mode = reader.string()?;
+        let count = reader.u64()?;
+
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let encoded = reader.prefixed()?.to_vec();
+            let entry = AuditEntry::decode_canonical(&encoded, &ads_mode)?;
+            let chain_hash = TaggedHash::decode(&mut reader)?;
+            entries.push(ChainedEntry {
+                entry,
+                encoded,
+                chain_hash,
+            });
+        }
+        reader.finish()?;
+
+        Ok(AuditExport {
+            ads_mode,
+            algorithm,
+            entries,
+        })
+    }
+
+    /// 链头（最后一条记录的链哈希；没有记录时为头部哈希）
+    pub fn head(&self) -> TaggedHash {
+        match self.entries.last() {
+            Some(last) => last.chain_hash.clone(),
+            None => TaggedHash {
+                algorithm: self.algorithm,
+                bytes: self.algorithm.digest(&[&self.header()]),
+            },
+        }
+    }
+
+    fn header(&self) -> Vec<u8> {
+        encode_header(&self.ads_mode, self.algorithm)
+    }
+}
+
+/// 重新计算哈希链，并检查 id 单调递增、每个 storager 的根哈希前后衔接
+///
+/// 成功时返回链头
+pub fn verify_chain(export: &AuditExport) -> Result<TaggedHash, String> {
+    let algorithm = export.algorithm;
+    let mut chain = algorithm.digest(&[&export.header()]);
+    let mut last_id = 0u64;
+    let mut last_roots = std::collections::HashMap::new();
+
+    for chained in &export.entries {
+        let entry = &chained.entry;
+        chain = algorithm.digest(&[&chain, &chained.encoded]);
+        if chained.chain_hash.algorithm != algorithm || chained.chain_hash.bytes != chain {
+            return Err(format!("Chain hash mismatch at entry {}", entry.id));
+        }
+        if entry.id <= last_id {
+            return Err(format!(
+                "Entry ids are not strictly increasing ({} after {})",
+                entry.id, last_id
+            ));
+        }
+        last_id = entry.id;
+
+        let prev = last_roots
+            .insert(entry.storager.clone(), entry.root_hash.clone())
+            .unwrap_or_default();
+        if prev != entry.prev_root {
+            return Err(format!(
+                "Root transition gap for {} at entry {}",
+                entry.storager, entry.id
+            ));
+        }
+    }
+
+    Ok(export.head())
+}
+
+/// 用导出时的 ADS 模式重新验证每条记录内嵌的证明
+///
+/// 返回与记录状态不符的条目说明：声称已验证但证明无效，或被拒绝但证明有效。
+/// 第三方 ADS 模式需要先在本进程中注册模式和验证器
+pub fn verify_proofs(export: &AuditExport) -> Result<Vec<String>, String> {
+    let mode = AdsMode::from_name(&export.ads_mode)
+        .ok_or_else(|| format!("Unknown ADS mode in audit export: {}", export.ads_mode))?;
+    let verifier = ProofVerifier::new(mode);
+
+    let mut problems = Vec::new();
+    for chained in &export.entries {
+        let entry = &chained.entry;
+        let valid = verifier.verify(&entry.proof, &entry.root_hash);
+        match (entry.status, valid) {
+            (AuditStatus::Verified | AuditStatus::Confirmed, false) => problems.push(format!(
+                "Entry {} is marked {:?} but its proof does not verify",
+                entry.id, entry.status
+            )),
+            (AuditStatus::Rejected, true) => problems.push(format!(
+                "Entry {} is marked Rejected but its proof verifies",
+                entry.id
+            )),
+            _ => {}
+        }
+    }
+    Ok(problems)
+}
+
+/// 按字节顺序读取规范编码
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| format!("Unexpected end of data at offset {}", self.pos))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_be_bytes(self.array()?))
+    }
+
+    fn prefixed(&mut self) -> Result<&'a [u8], String> {
+        let len = u32::from_be_bytes(self.array()?) as usize;
+        self.bytes(len)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        String::from_utf8(self.prefixed()?.to_vec()).map_err(|e| e.to_string())
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        if self.pos == self.bytes.len() {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} trailing bytes after audit data",
+                self.bytes.len() - self.pos
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use esa_rust::merkle_tree::{leaf_hash, MerkleAdsProof, MerkleInclusion};
+
+    fn sample_log() -> AuditLog {
+        let log = AuditLog::new();
+        for (i, storager) in ["storager-0", "storager-1", "storager-0"]
+            .iter()
+            .enumerate()
+        {
+            let fid = format!("f{}", i);
+            let root = leaf_hash("rust", &fid);
+            let proof = MerkleAdsProof {
+                root,
+                inclusions: vec![MerkleInclusion {
+                    keyword: "rust".to_string(),
+                    fid: fid.clone(),
+                    index: 0,
+                    siblings: vec![],
+                }],
+                boundaries: vec![],
+            };
+            log.record(
+                MutationKind::Add,
+                storager,
+                "rust",
+                &fid,
+                AckMode::Sync,
+                root.to_vec(),
+                Proof::Merkle(proof.to_bytes()),
+                AuditStatus::Verified,
+            );
+        }
+        log
+    }
+
+    #[test]
+    fn test_canonical_encoding_roundtrip() {
+        let entry = sample_log().get(2).unwrap();
+        let encoded = entry.encode_canonical();
+        let decoded = AuditEntry::decode_canonical(&encoded, "mpt").unwrap();
+        assert_eq!(decoded.encode_canonical(), encoded);
+        assert_eq!(decoded.fid, "f1");
+        assert_eq!(decoded.proof, entry.proof);
+        // 字段顺序固定：版本字节后紧跟大端序 id 和记录时间
+        assert_eq!(&encoded[..9], &[ENTRY_VERSION, 0, 0, 0, 0, 0, 0, 0, 2]);
+        assert_eq!(decoded.recorded_at_ms, entry.recorded_at_ms);
+    }
+
+    #[test]
+    fn test_decodes_version_one_entries() {
+        let entry = sample_log().get(2).unwrap();
+        let encoded = entry.encode_canonical();
+
+        // 版本 1 没有记录时间字段
+        let mut v1 = vec![1];
+        v1.extend_from_slice(&encoded[1..9]);
+        v1.extend_from_slice(&encoded[17..]);
+        let decoded = AuditEntry::decode_canonical(&v1, "mpt").unwrap();
+        assert_eq!(decoded.id, 2);
+        assert_eq!(decoded.recorded_at_ms, 0);
+        assert_eq!(decoded.fid, "f1");
+
+        v1[0] = ENTRY_VERSION + 1;
+        assert!(AuditEntry::decode_canonical(&v1, "mpt").is_err());
+    }
+
+    #[test]
+    fn test_decodes_untyped_proofs_by_mode() {
+        let entry = sample_log().get(2).unwrap();
+        let encoded = entry.encode_canonical();
+
+        // 版本 2 的证明是原始字节，没有类型标签
+        let typed_len = 4 + entry.proof.to_bytes().len();
+        let mut v2 = vec![2];
+        v2.extend_from_slice(&encoded[1..encoded.len() - typed_len]);
+        v2.extend_from_slice(&3u32.to_be_bytes());
+        v2.extend_from_slice(&[1, 2, 3]);
+
+        let decoded = AuditEntry::decode_canonical(&v2, "mpt").unwrap();
+        assert_eq!(decoded.proof, Proof::Mpt(vec![1, 2, 3]));
+        let decoded = AuditEntry::decode_canonical(&v2, "accumulator").unwrap();
+        assert_eq!(decoded.proof, Proof::AccumulatorAdd(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn test_export_is_deterministic_and_verifies() {
+        let log = sample_log();
+        let a = export(&log, AdsMode::Mpt);
+        let b = export(&log, AdsMode::Mpt);
+        assert_eq!(a, b);
+
+        let parsed = AuditExport::decode(&a).unwrap();
+        assert_eq!(parsed.ads_mode, "mpt");
+        assert_eq!(verify_chain(&parsed).unwrap(), parsed.head());
+        assert_eq!(parsed.entries[2].entry.prev_root, leaf_hash("rust", "f0"));
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let bytes = export(&sample_log(), AdsMode::Mpt);
+
+        // 修改某条记录中的 fid
+        let pos = bytes.windows(2).position(|w| w == b"f1").unwrap();
+        let mut tampered = bytes.clone();
+        tampered[pos + 1] = b'9';
+        let parsed = AuditExport::decode(&tampered).unwrap();
+        assert!(verify_chain(&parsed).is_err());
+
+        // 截断最后一条记录：剩余的链仍然自洽，但链头与公布的不一致
+        let mut parsed = AuditExport::decode(&bytes).unwrap();
+        let head = parsed.head();
+        parsed.entries.pop();
+        assert!(verify_chain(&parsed).is_ok());
+        assert_ne!(parsed.head(), head);
+    }
+
+    #[test]
+    fn test_verify_proofs_flags_status_mismatch() {
+        let log = sample_log();
+        // 证明都是有效的 Merkle 证明，被标记为 Rejected 的记录应当被指出
+        log.set_status(3, AuditStatus::Rejected);
+        let parsed = AuditExport::decode(&export(&log, AdsMode::MerkleTree)).unwrap();
+        let problems = verify_proofs(&parsed).unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("Entry 3"));
+
+        // 累加器模式下这些证明无效
+        let parsed = AuditExport::decode(&export(&log, AdsMode::CryptoAccumulator)).unwrap();
+        assert_eq!(verify_proofs(&parsed).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,302 @@
+//! 认证和授权
+//!
+//! 启用访问控制后，每个请求必须携带 API token（见 [`common::auth`]）。[`AuthInterceptor`]
+//! 在请求进入 ManagerService 之前校验 token，把对应的 [`Principal`] 放入请求的 extensions；
+//! 各个 handler 再检查访问级别和 keyword 前缀。
+//!
+//! 访问控制文件是 JSON 数组，每一项描述一个客户端：
+//!
+//! ```json
+//! [
+//!   {"name": "ingest", "token_sha256": "<hex>", "access": "write", "keyword_prefixes": ["logs-"]},
+//!   {"name": "ops", "token_sha256": "<hex>", "access": "admin"}
+//! ]
+//! ```
+//!
+//! `keyword_prefixes` 为空表示不限制 keyword。启用盲索引的客户端发送的是盲化后的 keyword，
+//! 前缀限制对它们没有意义，只能授予不限 keyword 的权限。
+
+use crate::error::ManagerError;
+use common::auth::{bearer_token, token_digest};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use tonic::metadata::MetadataMap;
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+/// 访问级别，高级别包含低级别的全部权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    /// 查询和订阅根哈希
+    Read,
+    /// 加入、删除、更新和批量导入
+    Write,
+    /// 注册和移除 storager
+    Admin,
+}
+
+impl Access {
+    pub fn name(self) -> &'static str {
+        match self {
+            Access::Read => "read",
+            Access::Write => "write",
+            Access::Admin => "admin",
+        }
+    }
+}
+
+/// 通过 token 认证的客户端
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Principal {
+    pub name: String,
+    pub access: Access,
+    /// 允许访问的 keyword 前缀，为空时不限制
+    #[serde(default)]
+    pub keyword_prefixes: Vec<String>,
+}
+
+impl Principal {
+    pub fn new(name: impl Into<String>, access: Access) -> Self {
+        Principal {
+            name: name.into(),
+            access,
+            keyword_prefixes: Vec::new(),
+        }
+    }
+
+    /// 只允许访问以这些前缀开头的 keyword
+    pub fn with_keyword_prefixes(mut self, prefixes: Vec<String>) -> Self {
+        self.keyword_prefixes = prefixes;
+        self
+    }
+
+    /// 检查访问级别
+    pub fn authorize(self, access: Access) -> Result<Caller, ManagerError> {
+        if self.access < access {
+            return Err(ManagerError::PermissionDenied(format!(
+                "'{}' does not have {} access",
+                self.name,
+                access.name()
+            )));
+        }
+        Ok(Caller::Principal(self))
+    }
+
+    fn allows(&self, keyword: &str) -> bool {
+        self.keyword_prefixes.is_empty()
+            || self
+                .keyword_prefixes
+                .iter()
+                .any(|prefix| keyword.starts_with(prefix.as_str()))
+    }
+}
+
+/// 发起请求的一方
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Caller {
+    /// 没有启用访问控制，允许所有操作
+    Anyone,
+    Principal(Principal),
+}
+
+impl Caller {
+    /// 检查是否可以访问这些 keyword
+    pub fn check_keywords<I, S>(&self, keywords: I) -> Result<(), ManagerError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let Caller::Principal(principal) = self else {
+            return Ok(());
+        };
+        match keywords
+            .into_iter()
+            .find(|keyword| !principal.allows(keyword.as_ref()))
+        {
+            Some(keyword) => Err(ManagerError::PermissionDenied(format!(
+                "'{}' may not access keyword '{}'",
+                principal.name,
+                keyword.as_ref()
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// 检查是否可以访问 `[start, end)` 中的所有 keyword
+    ///
+    /// 有前缀限制时，两端必须以同一个允许的前缀开头
+    pub fn check_range(&self, start: &str, end: &str) -> Result<(), ManagerError> {
+        let Caller::Principal(principal) = self else {
+            return Ok(());
+        };
+        let inside = principal.keyword_prefixes.is_empty()
+            || principal.keyword_prefixes.iter().any(|prefix| {
+                start.starts_with(prefix.as_str()) && end.starts_with(prefix.as_str())
+            });
+        if inside {
+            Ok(())
+        } else {
+            Err(ManagerError::PermissionDenied(format!(
+                "'{}' may not access keywords in [{}, {})",
+                principal.name, start, end
+            )))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenEntry {
+    token_sha256: String,
+    #[serde(flatten)]
+    principal: Principal,
+}
+
+/// token 摘要到客户端的映射
+#[derive(Debug, Default)]
+pub struct AccessControl {
+    principals: HashMap<String, Principal>,
+}
+
+impl AccessControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 读取访问控制文件
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let entries: Vec<TokenEntry> = serde_json::from_slice(&std::fs::read(path)?)?;
+        Ok(AccessControl {
+            principals: entries
+                .into_iter()
+                .map(|entry| (entry.token_sha256.to_ascii_lowercase(), entry.principal))
+                .collect(),
+        })
+    }
+
+    /// 为 token 授权
+    pub fn with_token(mut self, token: &str, principal: Principal) -> Self {
+        self.principals.insert(token_digest(token), principal);
+        self
+    }
+
+    /// 配置的客户端数量
+    pub fn len(&self) -> usize {
+        self.principals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.principals.is_empty()
+    }
+
+    /// 根据请求携带的 token 找到对应的客户端
+    pub fn authenticate(&self, metadata: &MetadataMap) -> Result<Principal, ManagerError> {
+        let token = bearer_token(metadata)
+            .ok_or_else(|| ManagerError::Unauthenticated("Missing API token".to_string()))?;
+        self.principals
+            .get(&token_digest(token))
+            .cloned()
+            .ok_or_else(|| ManagerError::Unauthenticated("Unknown API token".to_string()))
+    }
+}
+
+/// 在 handler 之前校验 token 的拦截器；未启用访问控制时放行所有请求
+#[derive(Clone)]
+pub struct AuthInterceptor(pub(crate) Option<Arc<AccessControl>>);
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(access) = &self.0 {
+            let principal = access.authenticate(request.metadata())?;
+            request.extensions_mut().insert(principal);
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::auth::attach_token;
+    use tonic::Code;
+
+    fn access_control() -> AccessControl {
+        AccessControl::new()
+            .with_token("reader-token", Principal::new("reader", Access::Read))
+            .with_token(
+                "logs-token",
+                Principal::new("logs", Access::Write).with_keyword_prefixes(vec!["logs-".into()]),
+            )
+    }
+
+    fn request(token: Option<&str>) -> Request<()> {
+        let mut request = Request::new(());
+        if let Some(token) = token {
+            attach_token(&mut request, token).unwrap();
+        }
+        request
+    }
+
+    #[test]
+    fn test_interceptor_rejects_unknown_tokens() {
+        let mut interceptor = AuthInterceptor(Some(Arc::new(access_control())));
+        for token in [None, Some("wrong")] {
+            let status = interceptor.call(request(token)).unwrap_err();
+            assert_eq!(status.code(), Code::Unauthenticated);
+        }
+        let accepted = interceptor.call(request(Some("reader-token"))).unwrap();
+        assert_eq!(
+            accepted
+                .extensions()
+                .get::<Principal>()
+                .map(|p| p.name.as_str()),
+            Some("reader")
+        );
+
+        assert!(AuthInterceptor(None).call(request(None)).is_ok());
+    }
+
+    #[test]
+    fn test_access_levels_and_prefixes() {
+        let access = access_control();
+        let reader = access
+            .authenticate(request(Some("reader-token")).metadata())
+            .unwrap();
+        let error = reader.clone().authorize(Access::Write).unwrap_err();
+        assert_eq!(Status::from(error).code(), Code::PermissionDenied);
+        let reader = reader.authorize(Access::Read).unwrap();
+        assert!(reader.check_keywords(["anything"]).is_ok());
+
+        let logs = access
+            .authenticate(request(Some("logs-token")).metadata())
+            .unwrap();
+        let logs = logs.authorize(Access::Write).unwrap();
+        assert!(logs.check_keywords(["logs-a", "logs-b"]).is_ok());
+        assert!(logs.check_keywords(["logs-a", "metrics-cpu"]).is_err());
+        assert!(logs.check_range("logs-a", "logs-z").is_ok());
+        assert!(logs.check_range("logs-a", "metrics-").is_err());
+
+        assert!(Caller::Anyone.check_range("a", "z").is_ok());
+    }
+
+    #[test]
+    fn test_open_reads_digests() {
+        let path = std::env::temp_dir().join(format!("manager-acl-{}.json", std::process::id()));
+        let json = format!(
+            r#"[{{"name": "ops", "token_sha256": "{}", "access": "admin"}}]"#,
+            token_digest("ops-token").to_uppercase()
+        );
+        std::fs::write(&path, json).unwrap();
+        let access = AccessControl::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(access.len(), 1);
+        let ops = access
+            .authenticate(request(Some("ops-token")).metadata())
+            .unwrap();
+        assert_eq!(ops, Principal::new("ops", Access::Admin));
+    }
+}
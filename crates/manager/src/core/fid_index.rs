@@ -0,0 +1,205 @@
+//! fid → keyword 反向索引
+//!
+//! Manager 记录每个 fid 当前所在的 keyword，Delete 只给出 fid 时由 Manager 找到要删除的
+//! keyword 并分发到对应的 storager，客户端不必再提供完整的 keyword 列表。
+//!
+//! 不同命名空间中的同名 fid 是不同的文件，索引按命名空间分开记录。
+//!
+//! 配置了文件路径时，每次写请求完成后把整个索引写入文件（先写临时文件再重命名），
+//! 重启时从文件恢复。文件是 `{命名空间: {fid: [keyword]}}`，
+//! 支持命名空间之前的 `{fid: [keyword]}` 格式读作默认命名空间。
+
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+/// 一个命名空间中 fid 到 keyword 集合的映射
+type Entries = BTreeMap<String, BTreeSet<String>>;
+
+/// 索引文件的两种格式
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IndexFile {
+    Namespaced(BTreeMap<String, Entries>),
+    Flat(Entries),
+}
+
+/// 每个命名空间中 fid 到 keyword 集合的映射
+#[derive(Default)]
+pub struct FidIndex {
+    namespaces: RwLock<BTreeMap<String, Entries>>,
+    /// 索引文件，`None` 时只保存在内存中
+    path: Option<PathBuf>,
+    /// 串行化文件写入，避免并发的写请求互相覆盖临时文件
+    save_lock: Mutex<()>,
+}
+
+impl FidIndex {
+    /// 只保存在内存中的索引
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 持久化到指定文件的索引；文件已存在时从中恢复
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let namespaces = if !path.exists() {
+            BTreeMap::new()
+        } else {
+            match serde_json::from_slice(&std::fs::read(&path)?)? {
+                IndexFile::Namespaced(namespaces) => namespaces,
+                IndexFile::Flat(entries) => BTreeMap::from([(String::new(), entries)]),
+            }
+        };
+        Ok(FidIndex {
+            namespaces: RwLock::new(namespaces),
+            path: Some(path),
+            save_lock: Mutex::new(()),
+        })
+    }
+
+    /// fid 当前所在的 keyword（按字典序），未记录的 fid 返回空列表
+    pub fn keywords(&self, namespace: &str, fid: &str) -> Vec<String> {
+        self.namespaces
+            .read()
+            .unwrap()
+            .get(namespace)
+            .and_then(|entries| entries.get(fid))
+            .map(|keywords| keywords.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 所有命名空间中记录的 fid 数
+    pub fn len(&self) -> usize {
+        self.namespaces
+            .read()
+            .unwrap()
+            .values()
+            .map(Entries::len)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 记录 fid 加入了 `keywords`
+    pub fn insert<'a>(
+        &self,
+        namespace: &str,
+        fid: &str,
+        keywords: impl IntoIterator<Item = &'a String>,
+    ) {
+        let mut namespaces = self.namespaces.write().unwrap();
+        let entries = namespaces.entry(namespace.to_string()).or_default();
+        let entry = entries.entry(fid.to_string()).or_default();
+        entry.extend(keywords.into_iter().cloned());
+        if entry.is_empty() {
+            entries.remove(fid);
+        }
+        if entries.is_empty() {
+            namespaces.remove(namespace);
+        }
+    }
+
+    /// 记录 fid 离开了 `keywords`，不再属于任何 keyword 时删除 fid
+    pub fn remove<'a>(
+        &self,
+        namespace: &str,
+        fid: &str,
+        keywords: impl IntoIterator<Item = &'a String>,
+    ) {
+        let mut namespaces = self.namespaces.write().unwrap();
+        let Some(entries) = namespaces.get_mut(namespace) else {
+            return;
+        };
+        if let Some(entry) = entries.get_mut(fid) {
+            for keyword in keywords {
+                entry.remove(keyword);
+            }
+            if entry.is_empty() {
+                entries.remove(fid);
+            }
+        }
+        if entries.is_empty() {
+            namespaces.remove(namespace);
+        }
+    }
+
+    /// 把索引写入文件（未配置文件时不做任何事）
+    pub fn persist(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let _saving = self.save_lock.lock().unwrap();
+        let json = serde_json::to_vec(&*self.namespaces.read().unwrap())?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let index = FidIndex::new();
+        index.insert("", "f1", &strings(&["rust", "go"]));
+        index.insert("", "f1", &strings(&["c"]));
+        assert_eq!(index.keywords("", "f1"), strings(&["c", "go", "rust"]));
+
+        index.remove("", "f1", &strings(&["go", "java"]));
+        assert_eq!(index.keywords("", "f1"), strings(&["c", "rust"]));
+        index.remove("", "f1", &strings(&["c", "rust"]));
+        assert!(index.keywords("", "f1").is_empty());
+        assert!(index.is_empty());
+    }
+
+    #[test]
+    fn test_reopen_restores_entries() {
+        let path = std::env::temp_dir().join(format!("manager-fids-{}.json", std::process::id()));
+
+        let index = FidIndex::open(&path).unwrap();
+        index.insert("", "f1", &strings(&["rust"]));
+        index.insert("", "f2", &strings(&["go"]));
+        index.persist().unwrap();
+
+        let reopened = FidIndex::open(&path).unwrap();
+        assert_eq!(reopened.len(), 2);
+        assert_eq!(reopened.keywords("", "f1"), strings(&["rust"]));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_namespaces_are_separate() {
+        let index = FidIndex::new();
+        index.insert("", "f1", &strings(&["rust"]));
+        index.insert("tenant", "f1", &strings(&["go"]));
+        assert_eq!(index.keywords("", "f1"), strings(&["rust"]));
+        assert_eq!(index.keywords("tenant", "f1"), strings(&["go"]));
+        assert_eq!(index.len(), 2);
+
+        index.remove("tenant", "f1", &strings(&["go"]));
+        assert!(index.keywords("tenant", "f1").is_empty());
+        assert_eq!(index.keywords("", "f1"), strings(&["rust"]));
+    }
+
+    #[test]
+    fn test_open_reads_flat_format() {
+        let path = std::env::temp_dir().join(format!("manager-flat-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"f1": ["rust"]}"#).unwrap();
+
+        let index = FidIndex::open(&path).unwrap();
+        assert_eq!(index.keywords("", "f1"), strings(&["rust"]));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+//! 热点 keyword 检测
+//!
+//! 每个 storager 通过 `Stats` RPC 报告查询次数最多的 keyword。Manager 把所有报告汇总为
+//! [`LoadReport`]：同一个 keyword 在各副本上的查询次数相加，占全部查询的比例不低于阈值的
+//! keyword 视为热点，并按 [`suggested_replicas`] 建议副本数，供运维调大复制因子或
+//! 为热点 keyword 单独增加副本。
+
+use common::rpc::StoragerStatsResponse;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// 占全部查询的比例达到该值的 keyword 视为热点
+pub const DEFAULT_HOT_SHARE: f64 = 0.1;
+/// 默认向每个 storager 请求的 keyword 数
+pub const DEFAULT_HOT_TOP: usize = 10;
+
+/// 一个 keyword 在所有 storager 上的负载
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeywordLoad {
+    /// 各副本的查询次数之和
+    pub queries: u64,
+    /// 各副本报告的 fid 数量的最大值
+    pub fids: u64,
+}
+
+/// 一个热点 keyword
+#[derive(Debug, Clone, PartialEq)]
+pub struct HotKeyword {
+    pub keyword: String,
+    pub load: KeywordLoad,
+    /// 占全部查询的比例
+    pub share: f64,
+}
+
+/// 所有 storager 的查询负载汇总
+#[derive(Debug, Clone, Default)]
+pub struct LoadReport {
+    total_queries: u64,
+    keywords: BTreeMap<String, KeywordLoad>,
+}
+
+impl LoadReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 计入一个 storager 的报告（只有报告中的查询最多的 keyword 参与排名）
+    pub fn add_storager(&mut self, stats: &StoragerStatsResponse) {
+        self.total_queries += stats.total_queries;
+        for activity in &stats.hottest {
+            let load = self.keywords.entry(activity.keyword.clone()).or_default();
+            load.queries += activity.queries;
+            load.fids = load.fids.max(activity.fids);
+        }
+    }
+
+    /// 所有 storager 的查询总数
+    pub fn total_queries(&self) -> u64 {
+        self.total_queries
+    }
+
+    /// 查询比例不低于 `min_share` 的 keyword，查询最多的在前，最多 `top` 个
+    pub fn hot(&self, min_share: f64, top: usize) -> Vec<HotKeyword> {
+        if self.total_queries == 0 {
+            return Vec::new();
+        }
+        let mut hot: Vec<HotKeyword> = self
+            .keywords
+            .iter()
+            .map(|(keyword, load)| HotKeyword {
+                keyword: keyword.clone(),
+                load: *load,
+                share: load.queries as f64 / self.total_queries as f64,
+            })
+            .filter(|hot| hot.share >= min_share)
+            .collect();
+        // BTreeMap 已按 keyword 排序，稳定排序保证次数相同时按 keyword 排列
+        hot.sort_by_key(|hot| Reverse(hot.load.queries));
+        hot.truncate(top);
+        hot
+    }
+}
+
+/// 查询比例为 `share` 的 keyword 建议的副本数
+///
+/// 查询平均分给各副本时，每个副本承担的比例不超过平均每个 storager 的比例 `1 / storagers`，
+/// 结果在 `1..=storagers` 之间
+pub fn suggested_replicas(share: f64, storagers: usize) -> usize {
+    let storagers = storagers.max(1);
+    ((share * storagers as f64).ceil() as usize).clamp(1, storagers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::rpc::KeywordActivity;
+
+    fn stats(total_queries: u64, hottest: &[(&str, u64, u64)]) -> StoragerStatsResponse {
+        StoragerStatsResponse {
+            total_queries,
+            hottest: hottest
+                .iter()
+                .map(|&(keyword, fids, queries)| KeywordActivity {
+                    keyword: keyword.to_string(),
+                    fids,
+                    queries,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_hot_keywords_merge_replicas() {
+        let mut report = LoadReport::new();
+        report.add_storager(&stats(60, &[("rust", 10, 40), ("go", 3, 15)]));
+        report.add_storager(&stats(40, &[("rust", 12, 20), ("java", 1, 5)]));
+        assert_eq!(report.total_queries(), 100);
+
+        let hot = report.hot(DEFAULT_HOT_SHARE, DEFAULT_HOT_TOP);
+        assert_eq!(hot.len(), 2);
+        assert_eq!(hot[0].keyword, "rust");
+        assert_eq!(
+            hot[0].load,
+            KeywordLoad {
+                queries: 60,
+                fids: 12
+            }
+        );
+        assert_eq!(hot[0].share, 0.6);
+        assert_eq!(hot[1].keyword, "go");
+        assert_eq!(report.hot(0.0, 1).len(), 1);
+        assert!(LoadReport::new().hot(0.0, 10).is_empty());
+    }
+
+    #[test]
+    fn test_suggested_replicas() {
+        assert_eq!(suggested_replicas(0.6, 4), 3);
+        assert_eq!(suggested_replicas(0.25, 4), 1);
+        assert_eq!(suggested_replicas(1.0, 4), 4);
+        assert_eq!(suggested_replicas(0.0, 4), 1);
+        assert_eq!(suggested_replicas(0.5, 0), 1);
+    }
+}
@@ -0,0 +1,327 @@
+//! 迁移期间的影子读
+//!
+//! 重新平衡时，哈希环先切换到新拓扑，迁移区间内的关键词在数据复制完成前
+//! 只存在于旧节点上。对这些关键词 Manager 同时查询新旧两个节点：
+//! - 新节点已发布的根包含了迁移数据时，优先采用新节点已验证的结果
+//! - 否则合并双方已验证的结果，保证查询结果在迁移期间不出现空洞
+//!
+//! 两边结果不一致时记录差异，供运维排查迁移是否遗漏数据。
+
+use common::{Proof, RootHash};
+use consistent_hash::{MovedRange, RebalancePlan, RingHasher};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+
+/// 最多保留的差异记录数量（超出后丢弃最早的记录）
+const MAX_DISCREPANCIES: usize = 1024;
+
+/// 单个节点对某个关键词的查询结果
+#[derive(Debug, Clone)]
+pub struct KeywordRead {
+    pub node_name: String,
+    pub fids: Vec<String>,
+    pub proof: Proof,
+    /// 验证时使用的根哈希（Manager 为该节点发布的根）
+    pub root_hash: RootHash,
+    pub verified: bool,
+}
+
+/// 影子读最终采用的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadowChoice {
+    /// 采用新节点的结果
+    New,
+    /// 采用旧节点的结果
+    Old,
+    /// 合并双方的结果
+    Union,
+}
+
+/// 影子读发现的新旧节点结果差异
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadDiscrepancy {
+    pub keyword: String,
+    pub old_owner: String,
+    pub new_owner: String,
+    /// 只出现在旧节点结果中的 fid
+    pub only_old: Vec<String>,
+    /// 只出现在新节点结果中的 fid
+    pub only_new: Vec<String>,
+    pub old_verified: bool,
+    pub new_verified: bool,
+    pub choice: ShadowChoice,
+}
+
+/// 迁移区间内关键词的旧节点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShadowSource {
+    pub node_name: String,
+    pub addr: String,
+    /// 关键词复制到新节点时的审计 id（尚未复制时为 None）
+    pub copied_at: Option<u64>,
+}
+
+/// 一次进行中的迁移
+struct ActiveMigration {
+    id: u64,
+    /// 迁移区间使用的哈希函数
+    hasher: RingHasher,
+    /// (迁移区间, 旧节点地址)
+    ranges: Vec<(MovedRange, String)>,
+    /// 已复制到新节点的关键词 -> 复制操作的审计 id
+    copied: HashMap<String, u64>,
+}
+
+/// 迁移跟踪器
+#[derive(Default)]
+pub struct MigrationTracker {
+    migrations: RwLock<Vec<ActiveMigration>>,
+    next_id: AtomicU64,
+    discrepancies: Mutex<VecDeque<ReadDiscrepancy>>,
+}
+
+impl MigrationTracker {
+    /// 创建空的迁移跟踪器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记一次迁移，返回迁移 id
+    ///
+    /// 必须在哈希环切换到新拓扑之前调用，`resolve_addr` 用于查找旧节点的地址
+    /// （移除节点后路由表中不再有它的地址）。没有旧节点或新节点的区间不需要影子读，会被忽略
+    pub fn begin<F>(&self, plan: &RebalancePlan, resolve_addr: F) -> u64
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let ranges = plan
+            .ranges
+            .iter()
+            .filter(|range| range.to.is_some())
+            .filter_map(|range| {
+                let addr = resolve_addr(range.from.as_deref()?)?;
+                Some((range.clone(), addr))
+            })
+            .collect();
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        self.migrations.write().unwrap().push(ActiveMigration {
+            id,
+            hasher: plan.hasher,
+            ranges,
+            copied: HashMap::new(),
+        });
+        id
+    }
+
+    /// 记录关键词已复制到新节点
+    ///
+    /// `audit_id` 是复制写入的审计 id，新节点发布的根版本不小于它时即包含迁移数据
+    pub fn mark_copied(&self, keyword: &str, audit_id: u64) {
+        let mut migrations = self.migrations.write().unwrap();
+        for migration in migrations.iter_mut() {
+            let hash = migration.hasher.hash_key(keyword);
+            if migration.ranges.iter().any(|(r, _)| r.contains(hash)) {
+                let entry = migration.copied.entry(keyword.to_string()).or_insert(0);
+                *entry = (*entry).max(audit_id);
+            }
+        }
+    }
+
+    /// 结束迁移，之后不再对其区间做影子读
+    pub fn finish(&self, id: u64) -> bool {
+        let mut migrations = self.migrations.write().unwrap();
+        let before = migrations.len();
+        migrations.retain(|m| m.id != id);
+        migrations.len() != before
+    }
+
+    /// 是否有进行中的迁移
+    pub fn is_active(&self) -> bool {
+        !self.migrations.read().unwrap().is_empty()
+    }
+
+    /// 查找需要影子读的旧节点
+    ///
+    /// 只有关键词落在迁移区间内且当前路由到的正是该区间的新节点时才返回
+    pub fn shadow_source(&self, keyword: &str, new_owner: &str) -> Option<ShadowSource> {
+        let migrations = self.migrations.read().unwrap();
+        // 较新的迁移优先
+        migrations.iter().rev().find_map(|migration| {
+            let hash = migration.hasher.hash_key(keyword);
+            let (range, addr) = migration
+                .ranges
+                .iter()
+                .find(|(r, _)| r.contains(hash) && r.to.as_deref() == Some(new_owner))?;
+            Some(ShadowSource {
+                node_name: range.from.clone()?,
+                addr: addr.clone(),
+                copied_at: migration.copied.get(keyword).copied(),
+            })
+        })
+    }
+
+    /// 记录差异
+    pub fn record_discrepancy(&self, discrepancy: ReadDiscrepancy) {
+        let mut discrepancies = self.discrepancies.lock().unwrap();
+        if discrepancies.len() == MAX_DISCREPANCIES {
+            discrepancies.pop_front();
+        }
+        discrepancies.push_back(discrepancy);
+    }
+
+    /// 最近记录的差异（按时间顺序）
+    pub fn discrepancies(&self) -> Vec<ReadDiscrepancy> {
+        self.discrepancies.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// 决定影子读采用哪一方的结果
+///
+/// # 参数
+///
+/// * `old` / `new` - 旧节点和新节点的查询结果
+/// * `new_caught_up` - 新节点已发布的根是否已包含该关键词的迁移数据
+///
+/// 双方都验证通过时：新节点已追上则采用新节点，否则合并双方；
+/// 只有一方验证通过时采用该方。双方 fid 集合不同时返回差异记录
+pub fn resolve_shadow_read(
+    keyword: &str,
+    old: &KeywordRead,
+    new: &KeywordRead,
+    new_caught_up: bool,
+) -> (ShadowChoice, Option<ReadDiscrepancy>) {
+    let choice = match (old.verified, new.verified) {
+        (true, true) if new_caught_up => ShadowChoice::New,
+        (true, true) => ShadowChoice::Union,
+        (true, false) => ShadowChoice::Old,
+        // 只有新节点验证通过，或双方都失败（由调用方按未验证处理）
+        _ => ShadowChoice::New,
+    };
+
+    let old_set: HashSet<&String> = old.fids.iter().collect();
+    let new_set: HashSet<&String> = new.fids.iter().collect();
+    let discrepancy = (old_set != new_set).then(|| ReadDiscrepancy {
+        keyword: keyword.to_string(),
+        old_owner: old.node_name.clone(),
+        new_owner: new.node_name.clone(),
+        only_old: old
+            .fids
+            .iter()
+            .filter(|f| !new_set.contains(f))
+            .cloned()
+            .collect(),
+        only_new: new
+            .fids
+            .iter()
+            .filter(|f| !old_set.contains(f))
+            .cloned()
+            .collect(),
+        old_verified: old.verified,
+        new_verified: new.verified,
+        choice,
+    });
+
+    (choice, discrepancy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use consistent_hash::ConsistentHashRing;
+
+    fn read(node: &str, fids: &[&str], verified: bool) -> KeywordRead {
+        KeywordRead {
+            node_name: node.to_string(),
+            fids: fids.iter().map(|s| s.to_string()).collect(),
+            proof: Proof::Mpt(vec![]),
+            root_hash: vec![],
+            verified,
+        }
+    }
+
+    fn add_node_plan() -> RebalancePlan {
+        let ring = ConsistentHashRing::with_nodes(&["storager-0", "storager-1"], 150);
+        ring.plan_add_node("storager-2", 150).unwrap()
+    }
+
+    /// 找一个会迁移到新节点的关键词
+    fn moved_keyword(plan: &RebalancePlan) -> String {
+        (0..)
+            .map(|i| format!("kw{}", i))
+            .find(|k| plan.range_for_key(k).is_some())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_shadow_source_lifecycle() {
+        let tracker = MigrationTracker::new();
+        let plan = add_node_plan();
+        let keyword = moved_keyword(&plan);
+        let range = plan.range_for_key(&keyword).unwrap().clone();
+
+        let id = tracker.begin(&plan, |name| Some(format!("http://{}", name)));
+        assert!(tracker.is_active());
+        assert!(tracker.shadow_source(&keyword, "storager-0").is_none());
+
+        let source = tracker.shadow_source(&keyword, "storager-2").unwrap();
+        assert_eq!(Some(source.node_name.clone()), range.from);
+        assert_eq!(source.addr, format!("http://{}", source.node_name));
+        assert_eq!(source.copied_at, None);
+
+        tracker.mark_copied(&keyword, 7);
+        tracker.mark_copied(&keyword, 3);
+        let source = tracker.shadow_source(&keyword, "storager-2").unwrap();
+        assert_eq!(source.copied_at, Some(7));
+
+        assert!(tracker.finish(id));
+        assert!(!tracker.finish(id));
+        assert!(!tracker.is_active());
+        assert!(tracker.shadow_source(&keyword, "storager-2").is_none());
+    }
+
+    #[test]
+    fn test_resolve_prefers_caught_up_new_owner() {
+        let old = read("storager-0", &["f1", "f2"], true);
+        let new = read("storager-2", &["f1", "f3"], true);
+
+        let (choice, discrepancy) = resolve_shadow_read("rust", &old, &new, false);
+        assert_eq!(choice, ShadowChoice::Union);
+        let discrepancy = discrepancy.unwrap();
+        assert_eq!(discrepancy.only_old, vec!["f2"]);
+        assert_eq!(discrepancy.only_new, vec!["f3"]);
+
+        let (choice, _) = resolve_shadow_read("rust", &old, &new, true);
+        assert_eq!(choice, ShadowChoice::New);
+    }
+
+    #[test]
+    fn test_resolve_falls_back_to_verified_side() {
+        let old = read("storager-0", &["f1"], true);
+        let new = read("storager-2", &["f1"], false);
+        let (choice, discrepancy) = resolve_shadow_read("rust", &old, &new, true);
+        assert_eq!(choice, ShadowChoice::Old);
+        assert!(discrepancy.is_none());
+
+        let old = read("storager-0", &["f1"], false);
+        let new = read("storager-2", &[], true);
+        let (choice, discrepancy) = resolve_shadow_read("rust", &old, &new, false);
+        assert_eq!(choice, ShadowChoice::New);
+        assert!(!discrepancy.unwrap().old_verified);
+    }
+
+    #[test]
+    fn test_discrepancy_log_is_bounded() {
+        let tracker = MigrationTracker::new();
+        let old = read("storager-0", &["f1"], true);
+        let new = read("storager-2", &[], true);
+        for i in 0..MAX_DISCREPANCIES + 5 {
+            let (_, d) = resolve_shadow_read(&format!("kw{}", i), &old, &new, false);
+            tracker.record_discrepancy(d.unwrap());
+        }
+        let discrepancies = tracker.discrepancies();
+        assert_eq!(discrepancies.len(), MAX_DISCREPANCIES);
+        assert_eq!(discrepancies[0].keyword, "kw5");
+    }
+}
@@ -1,9 +1,46 @@
 //! Manager 核心模块
 //!
-//! 包含路由、验证等核心功能
+//! 包含路由、验证、审计、准入控制、迁移影子读、副本读修复、查询结果缓存、布尔子查询预过滤、热点 keyword 检测、证明验证代价统计、根哈希历史和持久化、keyword 累加器和草图摘要跟踪、连接池、Update 协调、fid 反向索引、认证授权等核心功能
 
+pub mod accumulators;
+pub mod admission;
+pub mod auth;
+pub mod audit;
+pub mod audit_chain;
+pub mod fid_index;
+pub mod hot_keys;
+pub mod migration;
+pub mod pool;
+pub mod prefilter;
+pub mod proof_stats;
+pub mod query_cache;
+pub mod read_repair;
+pub mod root_history;
+pub mod root_store;
 pub mod routing;
+pub mod sketches;
+pub mod update;
 pub mod verification;
 
-pub use routing::Router;
-pub use verification::ProofVerifier;
+pub use accumulators::KeywordAccumulators;
+pub use admission::{Admission, AdmissionConfig, AdmissionController, QueryRejected};
+pub use auth::{Access, AccessControl, AuthInterceptor, Caller, Principal};
+pub use common::retry::RetryPolicy;
+pub use audit::{AckPolicy, AuditEntry, AuditLog, AuditStatus, MutationKind};
+pub use fid_index::FidIndex;
+pub use hot_keys::{HotKeyword, KeywordLoad, LoadReport};
+pub use migration::{KeywordRead, MigrationTracker, ReadDiscrepancy, ShadowChoice, ShadowSource};
+pub use pool::ChannelPool;
+pub use prefilter::KeywordFilters;
+pub use proof_stats::{ProofStats, ProofStatsSnapshot};
+pub use query_cache::{QueryCache, QueryCacheStats};
+pub use read_repair::ReplicaRepair;
+pub use root_history::{RootHistory, RootKey, DEFAULT_ROOT_HISTORY};
+pub use root_store::{RootStore, TrackedRoots};
+pub use routing::{Router, RouterSnapshot};
+pub use sketches::KeywordSketches;
+pub use update::{FidGuard, FidLocks, UpdatePlan};
+pub use verification::{
+    query_accumulator, register_verifier, verify_mpt_prefix_proof, verify_mpt_proof,
+    verify_mpt_range_proof, AdsVerifier, MptProofError, ProofVerifier, DELIVERED_EMPTY,
+};
@@ -0,0 +1,137 @@
+//! storager 连接池
+//!
+//! 按地址缓存 gRPC Channel。HTTP/2 连接可以被并发请求多路复用，每个地址只保留一条：
+//! 第一次访问某个地址时才建立连接，之后的请求直接复用，省去每个 keyword 一次的握手。
+//!
+//! 请求因传输错误失败、健康检查无法连接或节点被移除时，连接会被移出连接池，
+//! 下一次访问该地址时重新建立（例如 storager 重启后监听在同一地址上）。
+
+use common::net::connect_with;
+use common::transport::TransportConfig;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use tonic::transport::Channel;
+use tonic::{Code, Status};
+use tracing::debug;
+
+/// 按 storager 地址复用的 gRPC 连接
+#[derive(Default)]
+pub struct ChannelPool {
+    channels: RwLock<HashMap<String, Channel>>,
+    /// 建立连接和创建客户端存根时使用的传输参数
+    transport: TransportConfig,
+}
+
+impl ChannelPool {
+    /// 创建空的连接池
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 创建使用指定传输参数的空连接池
+    pub fn with_transport(transport: TransportConfig) -> Self {
+        ChannelPool {
+            channels: RwLock::default(),
+            transport,
+        }
+    }
+
+    /// 连接池使用的传输参数
+    pub fn transport(&self) -> &TransportConfig {
+        &self.transport
+    }
+
+    /// 获取到 `addr` 的连接，连接池中没有时建立新连接
+    ///
+    /// 连接失败时不会缓存任何内容，下次调用会重新尝试
+    pub async fn get(&self, addr: &str) -> Result<Channel, tonic::transport::Error> {
+        if let Some(channel) = self.channels.read().unwrap().get(addr) {
+            return Ok(channel.clone());
+        }
+
+        let channel = connect_with(addr, &self.transport).await?;
+        // 并发的首次访问可能各自建立了连接，保留最先放入的那一条
+        Ok(self
+            .channels
+            .write()
+            .unwrap()
+            .entry(addr.to_string())
+            .or_insert(channel)
+            .clone())
+    }
+
+    /// 移除到 `addr` 的连接，返回连接池中是否有这个地址
+    pub fn evict(&self, addr: &str) -> bool {
+        self.channels.write().unwrap().remove(addr).is_some()
+    }
+
+    /// 请求失败后调用：传输层错误说明连接可能已经失效，移除后下次重新连接
+    pub fn evict_on_error(&self, addr: &str, status: &Status) {
+        if status.code() == Code::Unavailable && self.evict(addr) {
+            debug!(
+                "Dropped pooled connection to {}: {}",
+                addr,
+                status.message()
+            );
+        }
+    }
+
+    /// 是否缓存了到 `addr` 的连接
+    pub fn contains(&self, addr: &str) -> bool {
+        self.channels.read().unwrap().contains_key(addr)
+    }
+
+    /// 缓存的连接数量
+    pub fn len(&self) -> usize {
+        self.channels.read().unwrap().len()
+    }
+
+    /// 连接池是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Manager;
+    use common::net::{bind_tcp, serve_listeners, Listeners};
+    use common::rpc::manager_service_server::ManagerServiceServer;
+    use common::AdsMode;
+    use tonic::transport::Server;
+
+    #[tokio::test]
+    async fn test_reuses_and_evicts_channels() {
+        let pool = ChannelPool::new();
+        assert!(pool.get("http://127.0.0.1:1").await.is_err());
+        assert!(pool.is_empty());
+
+        let listeners = Listeners {
+            tcp: vec![bind_tcp("127.0.0.1:0".parse().unwrap()).unwrap()],
+            ..Default::default()
+        };
+        let addr = format!("http://{}", listeners.tcp[0].local_addr().unwrap());
+        let service = ManagerServiceServer::new(Manager::new(vec![], AdsMode::MerkleTree));
+        tokio::spawn(async move {
+            let router = move || Server::builder().add_service(service.clone());
+            serve_listeners(listeners, router, std::future::pending())
+                .await
+                .unwrap()
+        });
+
+        pool.get(&addr).await.unwrap();
+        pool.get(&addr).await.unwrap();
+        assert_eq!(pool.len(), 1);
+
+        // 只有传输层错误才会丢弃连接
+        pool.evict_on_error(&addr, &Status::invalid_argument("bad request"));
+        assert!(pool.contains(&addr));
+        pool.evict_on_error(&addr, &Status::unavailable("connection reset"));
+        assert!(!pool.contains(&addr));
+
+        pool.get(&addr).await.unwrap();
+        assert!(pool.evict(&addr));
+        assert!(!pool.evict(&addr));
+    }
+}
@@ -0,0 +1,102 @@
+//! 布尔查询子查询的 keyword 预过滤
+//!
+//! Manager 定期从各 storager 拉取每个命名空间的 keyword Bloom filter（`KeywordFilter` RPC，
+//! 见 [`Manager::refresh_keyword_filters`](crate::Manager::refresh_keyword_filters)），
+//! 过滤器附带构建时的根哈希。布尔查询逐个读取 keyword 之前，如果归属节点的过滤器与 Manager
+//! 当前发布的根哈希一致且不包含该 keyword，就不发出子查询，把它当作空结果参与求值。
+//! 过滤器判为可能存在（包括误报）、过滤器已经过时或者还没有拉取时照常查询。
+//!
+//! 被排除的 keyword 没有不存在证明：它们的不存在依赖 storager 报告的过滤器，
+//! 查询响应在 `filtered_keywords` 中列出这些 keyword。
+
+use super::RootKey;
+use common::{BloomFilter, RootHash};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+struct StoragerFilter {
+    root_hash: RootHash,
+    filter: BloomFilter,
+}
+
+/// 每个 (storager, 命名空间) 最近一次拉取的 keyword 过滤器
+#[derive(Default)]
+pub struct KeywordFilters {
+    filters: RwLock<HashMap<RootKey, StoragerFilter>>,
+    /// 因过滤器排除而省去的子查询
+    skipped: AtomicU64,
+}
+
+impl KeywordFilters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录 storager 在根哈希 `root_hash` 下的过滤器
+    pub fn update(&self, key: RootKey, root_hash: RootHash, filter: BloomFilter) {
+        self.filters
+            .write()
+            .unwrap()
+            .insert(key, StoragerFilter { root_hash, filter });
+    }
+
+    /// `keyword` 肯定不在 `key` 中时返回 true
+    ///
+    /// 只有过滤器的根哈希等于 Manager 当前发布的根哈希 `current_root` 时才使用过滤器
+    pub fn excludes(&self, key: &RootKey, current_root: &RootHash, keyword: &str) -> bool {
+        let filters = self.filters.read().unwrap();
+        let excluded = filters.get(key).is_some_and(|entry| {
+            !current_root.is_empty()
+                && entry.root_hash == *current_root
+                && !entry.filter.contains(keyword)
+        });
+        if excluded {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+        }
+        excluded
+    }
+
+    /// 已拉取的过滤器数量
+    pub fn len(&self) -> usize {
+        self.filters.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 启动以来因过滤器排除而省去的子查询数
+    pub fn skipped(&self) -> u64 {
+        self.skipped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::bloom::DEFAULT_FALSE_POSITIVE_RATE;
+
+    #[test]
+    fn test_excludes_only_with_current_root() {
+        let filters = KeywordFilters::new();
+        let key = RootKey::new("s1", "");
+        let r1 = vec![1u8; 32];
+        let keywords = ["rust".to_string()];
+        // 还没有过滤器时不排除任何 keyword
+        assert!(!filters.excludes(&key, &r1, "go"));
+
+        filters.update(
+            key.clone(),
+            r1.clone(),
+            BloomFilter::from_keywords(keywords.iter(), DEFAULT_FALSE_POSITIVE_RATE),
+        );
+        assert!(filters.excludes(&key, &r1, "go"));
+        assert!(!filters.excludes(&key, &r1, "rust"));
+        // 发布了新的根哈希后过滤器过时
+        assert!(!filters.excludes(&key, &vec![2u8; 32], "go"));
+        assert!(!filters.excludes(&RootKey::new("s1", "tenant"), &r1, "go"));
+        assert_eq!(filters.skipped(), 1);
+        assert_eq!(filters.len(), 1);
+    }
+}
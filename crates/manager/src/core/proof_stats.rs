@@ -0,0 +1,76 @@
+//! 证明验证代价统计
+//!
+//! 每个带证明的查询结果都附带 [`ProofMetrics`]（证明字节数、配对次数、哈希次数、层数）。
+//! Manager 把它们累加起来，通过 `AdminService.ProofStats` 报告，
+//! 比较不同 ADS 模式的验证代价时不需要自己插桩统计。
+
+use common::rpc::ProofMetrics;
+use std::sync::Mutex;
+
+/// 启动以来各查询验证代价的累计值
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProofStatsSnapshot {
+    /// 带证明的查询数
+    pub queries: u64,
+    /// 各项代价的总和
+    pub total: ProofMetrics,
+    /// 单次查询中各项代价的最大值
+    pub max: ProofMetrics,
+}
+
+/// 验证代价的累加器
+#[derive(Default)]
+pub struct ProofStats {
+    snapshot: Mutex<ProofStatsSnapshot>,
+}
+
+impl ProofStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次查询的验证代价
+    pub fn record(&self, metrics: &ProofMetrics) {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        snapshot.queries += 1;
+        snapshot.total.merge(metrics);
+        let max = &mut snapshot.max;
+        max.proof_bytes = max.proof_bytes.max(metrics.proof_bytes);
+        max.pairing_ops = max.pairing_ops.max(metrics.pairing_ops);
+        max.hash_ops = max.hash_ops.max(metrics.hash_ops);
+        max.levels = max.levels.max(metrics.levels);
+    }
+
+    /// 当前的累计值
+    pub fn snapshot(&self) -> ProofStatsSnapshot {
+        self.snapshot.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(proof_bytes: u64, pairing_ops: u64, hash_ops: u64, levels: u64) -> ProofMetrics {
+        ProofMetrics {
+            proof_bytes,
+            pairing_ops,
+            hash_ops,
+            levels,
+        }
+    }
+
+    #[test]
+    fn test_record_sums_and_tracks_maximum() {
+        let stats = ProofStats::new();
+        assert_eq!(stats.snapshot(), ProofStatsSnapshot::default());
+
+        stats.record(&metrics(100, 2, 3, 1));
+        stats.record(&metrics(40, 9, 1, 4));
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.queries, 2);
+        // 层数是路径深度，累计值也取最大值而不是相加
+        assert_eq!(snapshot.total, metrics(140, 11, 4, 4));
+        assert_eq!(snapshot.max, metrics(100, 9, 3, 4));
+    }
+}
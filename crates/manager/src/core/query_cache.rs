@@ -0,0 +1,324 @@
+//! 查询结果缓存
+//!
+//! 以 (命名空间, keyword) 为键缓存验证通过的单关键词读取结果，并记录结果所属的节点和验证时
+//! 使用的根哈希。只有节点仍是 keyword 的归属节点、且 Manager 为它发布的根哈希没有变化时
+//! 才命中；任何写入发布新的根哈希后旧条目自然失效，在下一次读取时被替换。
+//!
+//! 结果为空（已验证不存在）的 keyword 放在单独的有界分区中：布尔查询中反复出现的不存在的
+//! keyword 在本地返回缓存的不存在证明，大量不同的未命中 keyword 也不会挤掉热点 keyword 的结果。
+//!
+//! 结果与 Manager 跟踪的根哈希一致：异步确认的写入在证明验证、根哈希发布之前不可见。
+//! 两个分区都按最近最少使用淘汰，容量为 0 的分区不缓存。容量可以在运行时调整
+//! （见 [`crate::reload`]），缩小时丢弃最久未使用的条目。
+
+use super::KeywordRead;
+use common::RootHash;
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// 容量为 0 时为 None
+type Partition = Mutex<Option<LruCache<(String, String), KeywordRead>>>;
+
+fn partition(capacity: usize) -> Partition {
+    Mutex::new(NonZeroUsize::new(capacity).map(LruCache::new))
+}
+
+/// 调整分区容量，保留最近使用的条目
+fn resize(partition: &Partition, capacity: usize) {
+    let mut entries = partition.lock().unwrap();
+    match (NonZeroUsize::new(capacity), entries.as_mut()) {
+        (Some(cap), Some(cache)) => cache.resize(cap),
+        (cap, _) => *entries = cap.map(LruCache::new),
+    }
+}
+
+fn capacity(partition: &Partition) -> usize {
+    partition
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map_or(0, |entries| entries.cap().get())
+}
+
+/// 缓存的命中统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryCacheStats {
+    /// 命中非空结果的次数
+    pub hits: u64,
+    /// 命中不存在结果的次数
+    pub negative_hits: u64,
+    pub misses: u64,
+    pub entries: u64,
+    pub negative_entries: u64,
+}
+
+/// 单关键词读取结果的 LRU 缓存（默认两个分区都不启用）
+#[derive(Default)]
+pub struct QueryCache {
+    entries: Partition,
+    /// 结果为空的 keyword
+    negative: Partition,
+    hits: AtomicU64,
+    negative_hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl QueryCache {
+    /// 最多缓存 `capacity` 个有结果的 keyword，0 表示不缓存；不缓存不存在的 keyword
+    pub fn new(capacity: usize) -> Self {
+        Self::default().with_positive(capacity)
+    }
+
+    /// 最多缓存 `capacity` 个有结果的 keyword，0 表示不缓存
+    pub fn with_positive(mut self, capacity: usize) -> Self {
+        self.entries = partition(capacity);
+        self
+    }
+
+    /// 最多缓存 `capacity` 个已验证不存在的 keyword，0 表示不缓存
+    pub fn with_negative(mut self, capacity: usize) -> Self {
+        self.negative = partition(capacity);
+        self
+    }
+
+    /// 运行时调整有结果分区的容量，0 表示不再缓存
+    pub fn set_capacity(&self, capacity: usize) {
+        resize(&self.entries, capacity);
+    }
+
+    /// 运行时调整不存在分区的容量，0 表示不再缓存
+    pub fn set_negative_capacity(&self, capacity: usize) {
+        resize(&self.negative, capacity);
+    }
+
+    /// 有结果分区的容量
+    pub fn capacity(&self) -> usize {
+        capacity(&self.entries)
+    }
+
+    /// 不存在分区的容量
+    pub fn negative_capacity(&self) -> usize {
+        capacity(&self.negative)
+    }
+
+    /// 是否启用
+    pub fn is_enabled(&self) -> bool {
+        self.capacity() > 0 || self.negative_capacity() > 0
+    }
+
+    /// 查找 `node_name` 在根哈希 `current_root` 下的结果；条目属于其他节点或其他根哈希时移除它
+    pub fn get(
+        &self,
+        namespace: &str,
+        keyword: &str,
+        node_name: &str,
+        current_root: &RootHash,
+    ) -> Option<KeywordRead> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let key = (namespace.to_string(), keyword.to_string());
+        let lookup = |partition: &Partition| {
+            let mut guard = partition.lock().unwrap();
+            let entries = guard.as_mut()?;
+            let fresh = entries
+                .get(&key)
+                .map(|read| read.node_name == node_name && read.root_hash == *current_root)?;
+            if fresh {
+                entries.peek(&key).cloned()
+            } else {
+                entries.pop(&key);
+                None
+            }
+        };
+        let (read, counter) = match lookup(&self.entries) {
+            Some(read) => (Some(read), &self.hits),
+            None => match lookup(&self.negative) {
+                Some(read) => (Some(read), &self.negative_hits),
+                None => (None, &self.misses),
+            },
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        read
+    }
+
+    /// 缓存一次读取的结果，结果为空时放入不存在分区
+    ///
+    /// 只缓存验证通过、且针对当前发布的根哈希 `current_root` 验证的结果
+    pub fn insert(
+        &self,
+        namespace: &str,
+        keyword: &str,
+        read: &KeywordRead,
+        current_root: &RootHash,
+    ) {
+        let partition = if read.fids.is_empty() {
+            &self.negative
+        } else {
+            &self.entries
+        };
+        if !read.verified || current_root.is_empty() || read.root_hash != *current_root {
+            return;
+        }
+        if let Some(entries) = partition.lock().unwrap().as_mut() {
+            entries.put((namespace.to_string(), keyword.to_string()), read.clone());
+        }
+    }
+
+    /// 清空缓存（不清零命中统计）
+    pub fn clear(&self) {
+        for partition in [&self.entries, &self.negative] {
+            if let Some(entries) = partition.lock().unwrap().as_mut() {
+                entries.clear();
+            }
+        }
+    }
+
+    /// 命中统计和当前的条目数
+    pub fn stats(&self) -> QueryCacheStats {
+        let len = |partition: &Partition| {
+            partition
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map_or(0, |entries| entries.len() as u64)
+        };
+        QueryCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            negative_hits: self.negative_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: len(&self.entries),
+            negative_entries: len(&self.negative),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::Proof;
+
+    fn read(node_name: &str, root_hash: &[u8], verified: bool) -> KeywordRead {
+        KeywordRead {
+            node_name: node_name.to_string(),
+            fids: vec!["f1".to_string()],
+            proof: Proof::Mpt(vec![]),
+            root_hash: root_hash.to_vec(),
+            verified,
+        }
+    }
+
+    #[test]
+    fn test_entries_follow_the_published_root() {
+        let cache = QueryCache::new(8);
+        let r1 = vec![1u8; 32];
+        let r2 = vec![2u8; 32];
+        assert!(cache.get("", "rust", "s1", &r1).is_none());
+
+        cache.insert("", "rust", &read("s1", &r1, true), &r1);
+        assert_eq!(cache.get("", "rust", "s1", &r1).unwrap().fids, ["f1"]);
+        // 命名空间是键的一部分
+        assert!(cache.get("tenant", "rust", "s1", &r1).is_none());
+        // 归属节点变化或发布了新的根哈希后不再命中，旧条目被移除
+        assert!(cache.get("", "rust", "s2", &r1).is_none());
+        cache.insert("", "rust", &read("s1", &r1, true), &r1);
+        assert!(cache.get("", "rust", "s1", &r2).is_none());
+        assert_eq!(
+            cache.stats(),
+            QueryCacheStats {
+                hits: 1,
+                misses: 4,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_only_caches_results_verified_against_current_root() {
+        let cache = QueryCache::new(8);
+        let r1 = vec![1u8; 32];
+        cache.insert("", "rust", &read("s1", &r1, false), &r1);
+        cache.insert("", "go", &read("s1", &r1, true), &vec![2u8; 32]);
+        cache.insert("", "java", &read("s1", &[], true), &vec![]);
+        assert_eq!(cache.stats().entries, 0);
+
+        let disabled = QueryCache::new(0);
+        disabled.insert("", "rust", &read("s1", &r1, true), &r1);
+        assert!(!disabled.is_enabled());
+        assert!(disabled.get("", "rust", "s1", &r1).is_none());
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = QueryCache::new(2);
+        let r1 = vec![1u8; 32];
+        for keyword in ["a", "b"] {
+            cache.insert("", keyword, &read("s1", &r1, true), &r1);
+        }
+        assert!(cache.get("", "a", "s1", &r1).is_some());
+        cache.insert("", "c", &read("s1", &r1, true), &r1);
+        assert!(cache.get("", "b", "s1", &r1).is_none());
+        assert!(cache.get("", "a", "s1", &r1).is_some());
+        assert!(cache.get("", "c", "s1", &r1).is_some());
+    }
+
+    #[test]
+    fn test_resize_at_runtime() {
+        let cache = QueryCache::default();
+        let r1 = vec![1u8; 32];
+        cache.set_capacity(3);
+        assert!(cache.is_enabled());
+        for keyword in ["a", "b", "c"] {
+            cache.insert("", keyword, &read("s1", &r1, true), &r1);
+        }
+        // 缩小时保留最近使用的条目
+        assert!(cache.get("", "a", "s1", &r1).is_some());
+        cache.set_capacity(2);
+        assert_eq!(cache.capacity(), 2);
+        assert!(cache.get("", "b", "s1", &r1).is_none());
+        assert!(cache.get("", "a", "s1", &r1).is_some());
+
+        cache.set_negative_capacity(4);
+        assert_eq!(cache.negative_capacity(), 4);
+        cache.set_capacity(0);
+        assert_eq!(cache.stats().entries, 0);
+        cache.set_negative_capacity(0);
+        assert!(!cache.is_enabled());
+    }
+
+    #[test]
+    fn test_negative_partition() {
+        let r1 = vec![1u8; 32];
+        let mut empty = read("s1", &r1, true);
+        empty.fids.clear();
+
+        // 只启用有结果的分区时不缓存不存在的 keyword
+        let cache = QueryCache::new(8);
+        cache.insert("", "missing", &empty, &r1);
+        assert_eq!(cache.stats().negative_entries, 0);
+
+        let cache = QueryCache::new(1).with_negative(2);
+        cache.insert("", "rust", &read("s1", &r1, true), &r1);
+        for keyword in ["m1", "m2", "m3"] {
+            cache.insert("", keyword, &empty, &r1);
+        }
+        // 不存在的 keyword 不挤掉有结果的条目
+        assert!(cache.get("", "rust", "s1", &r1).is_some());
+        assert!(cache.get("", "m1", "s1", &r1).is_none());
+        assert!(cache.get("", "m3", "s1", &r1).unwrap().fids.is_empty());
+        // 写入发布新的根哈希后失效
+        assert!(cache.get("", "m2", "s1", &vec![2u8; 32]).is_none());
+        assert_eq!(
+            cache.stats(),
+            QueryCacheStats {
+                hits: 1,
+                negative_hits: 1,
+                misses: 2,
+                entries: 1,
+                negative_entries: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! 副本读修复
+//!
+//! 复制因子大于 1 时，单关键词查询读取 keyword 的所有副本。在证明验证通过的结果中，
+//! 至少有法定数量（副本数的多数）副本一致的 fid 集合作为查询结果；
+//! 其他验证通过但结果不同的副本按法定结果补写缺少的 fid、删除多余的 fid。
+//!
+//! 证明没有通过验证的结果不参与比较，对应的副本也不会被修复：
+//! 无法判断它的数据和证明哪一个不可信。
+
+use super::KeywordRead;
+use std::collections::BTreeSet;
+
+/// 副本数量对应的法定数量（多数）
+pub fn quorum_size(replicas: usize) -> usize {
+    replicas / 2 + 1
+}
+
+/// 需要对一个副本执行的修复
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicaRepair {
+    pub node_name: String,
+    /// 需要补写的 fid
+    pub add: Vec<String>,
+    /// 需要删除的 fid
+    pub delete: Vec<String>,
+}
+
+fn fid_set(read: &KeywordRead) -> BTreeSet<&String> {
+    read.fids.iter().collect()
+}
+
+/// 找出法定结果，返回它在 `reads` 中第一次出现的下标
+///
+/// 只统计验证通过的结果；没有任何 fid 集合达到 `quorum` 个副本时返回 None
+pub fn find_quorum(reads: &[KeywordRead], quorum: usize) -> Option<usize> {
+    reads.iter().enumerate().find_map(|(index, read)| {
+        if !read.verified {
+            return None;
+        }
+        let fids = fid_set(read);
+        let agreeing = reads
+            .iter()
+            .filter(|other| other.verified && fid_set(other) == fids)
+            .count();
+        (agreeing >= quorum).then_some(index)
+    })
+}
+
+/// 计算验证通过但与法定结果不一致的副本需要的修复
+pub fn plan_repairs(reads: &[KeywordRead], quorum: &KeywordRead) -> Vec<ReplicaRepair> {
+    let expected = fid_set(quorum);
+    reads
+        .iter()
+        .filter(|read| read.verified)
+        .filter_map(|read| {
+            let actual = fid_set(read);
+            if actual == expected {
+                return None;
+            }
+            Some(ReplicaRepair {
+                node_name: read.node_name.clone(),
+                add: quorum
+                    .fids
+                    .iter()
+                    .filter(|fid| !actual.contains(fid))
+                    .cloned()
+                    .collect(),
+                delete: read
+                    .fids
+                    .iter()
+                    .filter(|fid| !expected.contains(fid))
+                    .cloned()
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::Proof;
+
+    fn read(node: &str, fids: &[&str], verified: bool) -> KeywordRead {
+        KeywordRead {
+            node_name: node.to_string(),
+            fids: fids.iter().map(|s| s.to_string()).collect(),
+            proof: Proof::Mpt(vec![]),
+            root_hash: vec![],
+            verified,
+        }
+    }
+
+    #[test]
+    fn test_repairs_minority_replica() {
+        let reads = vec![
+            read("storager-0", &["f1"], true),
+            read("storager-1", &["f2", "f1"], true),
+            read("storager-2", &["f1", "f2"], true),
+        ];
+        let index = find_quorum(&reads, quorum_size(3)).unwrap();
+        assert_eq!(index, 1);
+
+        let repairs = plan_repairs(&reads, &reads[index]);
+        assert_eq!(
+            repairs,
+            vec![ReplicaRepair {
+                node_name: "storager-0".to_string(),
+                add: vec!["f2".to_string()],
+                delete: vec![],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_unverified_results_do_not_count() {
+        let reads = vec![
+            read("storager-0", &["f1", "f3"], true),
+            read("storager-1", &["f1"], false),
+            read("storager-2", &["f1"], false),
+        ];
+        assert_eq!(find_quorum(&reads, quorum_size(3)), None);
+
+        // 两个副本中只有一个可用时无法形成多数
+        let reads = vec![read("storager-0", &["f1"], true)];
+        assert_eq!(find_quorum(&reads, quorum_size(2)), None);
+
+        let reads = vec![
+            read("storager-0", &["f1", "f3"], true),
+            read("storager-1", &["f1"], true),
+            read("storager-2", &["f1"], true),
+            read("storager-3", &["f9"], false),
+            read("storager-4", &["f1"], true),
+        ];
+        let index = find_quorum(&reads, quorum_size(5)).unwrap();
+        let repairs = plan_repairs(&reads, &reads[index]);
+        assert_eq!(repairs.len(), 1);
+        assert_eq!(repairs[0].delete, vec!["f3"]);
+    }
+}
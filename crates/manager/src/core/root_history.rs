@@ -0,0 +1,192 @@
+//! 带版本的根哈希历史
+//!
+//! storager 的每次写入都会推进它的 ADS 版本号（epoch），响应中的证明和根哈希
+//! 都标注了计算时的版本号。Manager 为每个 storager 保留最近若干个已验证的
+//! (epoch, 根哈希)，查询证明使用与响应版本号对应的根哈希验证：
+//! 查询在并发写入之前完成计算时，不会因为 Manager 已经发布了更新的根而验证失败。
+//!
+//! storager 的每个命名空间都有独立的 ADS 和版本号，历史按 [`RootKey`] 分别保存。
+
+use common::RootHash;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+/// 每个 storager 默认保留的历史根哈希数量
+pub const DEFAULT_ROOT_HISTORY: usize = 64;
+
+/// 根哈希所属的 ADS：一个 storager 上的一个命名空间
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RootKey {
+    pub storager: String,
+    /// 空字符串为默认命名空间
+    pub namespace: String,
+}
+
+impl RootKey {
+    pub fn new(storager: impl Into<String>, namespace: impl Into<String>) -> Self {
+        RootKey {
+            storager: storager.into(),
+            namespace: namespace.into(),
+        }
+    }
+}
+
+/// 每个 (storager, 命名空间) 一个按 epoch 递增排列的环形缓冲区
+pub struct RootHistory {
+    capacity: AtomicUsize,
+    rings: RwLock<HashMap<RootKey, VecDeque<(u64, RootHash)>>>,
+}
+
+impl RootHistory {
+    /// 创建历史记录
+    ///
+    /// # Arguments
+    /// * `capacity` - 每个 storager 保留的根哈希数量（至少为 1）
+    pub fn new(capacity: usize) -> Self {
+        RootHistory {
+            capacity: AtomicUsize::new(capacity.max(1)),
+            rings: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 记录 storager 在 `epoch` 时已验证的根哈希
+    ///
+    /// 并发写入的验证可能乱序完成，因此按 epoch 插入到对应位置；超出容量时丢弃最旧的版本。
+    /// 返回 `epoch` 是否是该 storager 记录过的最新版本（只有最新版本的根哈希应当被发布）
+    pub fn record(&self, key: &RootKey, epoch: u64, root_hash: RootHash) -> bool {
+        let capacity = self.capacity();
+        let mut rings = self.rings.write().unwrap();
+        let ring = rings.entry(key.clone()).or_default();
+
+        match ring.binary_search_by_key(&epoch, |(e, _)| *e) {
+            Ok(index) => {
+                ring[index].1 = root_hash;
+                index + 1 == ring.len()
+            }
+            Err(index) => {
+                let newest = index == ring.len();
+                // 比保留的所有版本都旧，且缓冲区已满：记录后会立即被丢弃
+                if index == 0 && ring.len() >= capacity {
+                    return false;
+                }
+                ring.insert(index, (epoch, root_hash));
+                if ring.len() > capacity {
+                    ring.pop_front();
+                }
+                newest
+            }
+        }
+    }
+
+    /// 每个 storager 保留的根哈希数量
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    /// 运行时调整保留数量（至少为 1），缩小时立即丢弃超出的最旧版本
+    pub fn set_capacity(&self, capacity: usize) {
+        let capacity = capacity.max(1);
+        let mut rings = self.rings.write().unwrap();
+        self.capacity.store(capacity, Ordering::Relaxed);
+        for ring in rings.values_mut() {
+            let excess = ring.len().saturating_sub(capacity);
+            ring.drain(..excess);
+        }
+    }
+
+    /// storager 在 `epoch` 时的根哈希（未记录或已被丢弃时为 None）
+    pub fn root_at(&self, key: &RootKey, epoch: u64) -> Option<RootHash> {
+        let rings = self.rings.read().unwrap();
+        let ring = rings.get(key)?;
+        ring.binary_search_by_key(&epoch, |(e, _)| *e)
+            .ok()
+            .map(|index| ring[index].1.clone())
+    }
+
+    /// storager 保留的 epoch 范围 (最旧, 最新)
+    pub fn epochs(&self, key: &RootKey) -> Option<(u64, u64)> {
+        let rings = self.rings.read().unwrap();
+        let ring = rings.get(key)?;
+        Some((ring.front()?.0, ring.back()?.0))
+    }
+}
+
+impl Default for RootHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_ROOT_HISTORY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(storager: &str) -> RootKey {
+        RootKey::new(storager, "")
+    }
+
+    #[test]
+    fn test_lookup_by_epoch() {
+        let history = RootHistory::new(4);
+        history.record(&key("s0"), 1, vec![1]);
+        history.record(&key("s0"), 2, vec![2]);
+        history.record(&key("s1"), 1, vec![9]);
+
+        assert_eq!(history.root_at(&key("s0"), 1), Some(vec![1]));
+        assert_eq!(history.root_at(&key("s0"), 2), Some(vec![2]));
+        assert_eq!(history.root_at(&key("s1"), 1), Some(vec![9]));
+        assert_eq!(history.root_at(&key("s0"), 3), None);
+        assert_eq!(history.root_at(&key("s2"), 1), None);
+    }
+
+    #[test]
+    fn test_out_of_order_records_and_eviction() {
+        let history = RootHistory::new(3);
+        let newest: Vec<bool> = [2, 4, 1, 3]
+            .into_iter()
+            .map(|epoch| history.record(&key("s0"), epoch, vec![epoch as u8]))
+            .collect();
+        assert_eq!(newest, vec![true, true, false, false]);
+        // 容量为 3，最旧的 epoch 1 被丢弃
+        assert_eq!(history.epochs(&key("s0")), Some((2, 4)));
+        assert_eq!(history.root_at(&key("s0"), 1), None);
+        assert_eq!(history.root_at(&key("s0"), 3), Some(vec![3]));
+
+        // 比保留范围更旧的记录不会挤掉较新的版本
+        assert!(!history.record(&key("s0"), 1, vec![1]));
+        assert_eq!(history.epochs(&key("s0")), Some((2, 4)));
+
+        assert!(history.record(&key("s0"), 5, vec![5]));
+        assert_eq!(history.epochs(&key("s0")), Some((3, 5)));
+    }
+
+    #[test]
+    fn test_namespaces_have_separate_histories() {
+        let history = RootHistory::new(4);
+        history.record(&key("s0"), 1, vec![1]);
+        history.record(&RootKey::new("s0", "tenant"), 1, vec![7]);
+
+        assert_eq!(history.root_at(&key("s0"), 1), Some(vec![1]));
+        assert_eq!(
+            history.root_at(&RootKey::new("s0", "tenant"), 1),
+            Some(vec![7])
+        );
+        assert_eq!(history.root_at(&RootKey::new("s0", "other"), 1), None);
+    }
+
+    #[test]
+    fn test_shrink_capacity_drops_oldest() {
+        let history = RootHistory::new(4);
+        for epoch in 1..=4 {
+            history.record(&key("s0"), epoch, vec![epoch as u8]);
+        }
+        history.set_capacity(2);
+        assert_eq!(history.capacity(), 2);
+        assert_eq!(history.epochs(&key("s0")), Some((3, 4)));
+
+        history.set_capacity(3);
+        assert!(history.record(&key("s0"), 5, vec![5]));
+        assert_eq!(history.epochs(&key("s0")), Some((3, 5)));
+    }
+}
@@ -0,0 +1,158 @@
+//! 已发布根哈希的持久化
+//!
+//! 查询证明只有对照 Manager 自己验证并发布过的根哈希才有意义：没有跟踪的根时，
+//! storager 可以用任意内容构造一份自洽的证明，因此这类查询一律报告为未验证。
+//! Manager 重启后如果丢失了跟踪的根，所有已有数据的查询都会验证失败，直到下一次写入重新发布。
+//!
+//! 配置了文件路径时，每次发布新的根哈希后把全部根哈希及其版本写入文件
+//! （先写临时文件再重命名），重启时从文件恢复。文件是 `[{storager, namespace, root_hash, version}]`，
+//! 密码学累加器模式下每条记录还带有各 keyword 的累加器值（见 [`crate::core::accumulators`]），
+//! 写入过的 keyword 还带有草图摘要（见 [`crate::core::sketches`]）。
+
+use super::sketches::SketchDigest;
+use super::{KeywordAccumulators, KeywordSketches, RootKey};
+use common::RootHash;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 文件中的一条记录
+#[derive(Serialize, Deserialize)]
+struct StoredRoot {
+    storager: String,
+    #[serde(default)]
+    namespace: String,
+    root_hash: RootHash,
+    version: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    accumulators: BTreeMap<String, RootHash>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    sketches: BTreeMap<String, SketchDigest>,
+}
+
+/// 从文件恢复的根哈希和版本
+#[derive(Default)]
+pub struct TrackedRoots {
+    pub roots: HashMap<RootKey, RootHash>,
+    pub versions: HashMap<RootKey, u64>,
+}
+
+/// 根哈希文件，默认只保存在内存中
+#[derive(Default)]
+pub struct RootStore {
+    /// `None` 时不写文件
+    path: Option<PathBuf>,
+    /// 串行化文件写入，避免并发的发布互相覆盖临时文件
+    save_lock: Mutex<()>,
+    /// 每个 keyword 的累加器值，与根哈希一起写入
+    accumulators: KeywordAccumulators,
+    /// 每个 keyword 的草图摘要，与根哈希一起写入
+    sketches: KeywordSketches,
+}
+
+impl RootStore {
+    /// 不写文件
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 持久化到指定文件，返回文件中已有的根哈希（文件不存在时为空）
+    pub fn open(path: impl AsRef<Path>) -> io::Result<(Self, TrackedRoots)> {
+        let path = path.as_ref().to_path_buf();
+        let mut tracked = TrackedRoots::default();
+        let accumulators = KeywordAccumulators::new();
+        let sketches = KeywordSketches::new();
+        if path.exists() {
+            let stored: Vec<StoredRoot> = serde_json::from_slice(&std::fs::read(&path)?)?;
+            for entry in stored {
+                let key = RootKey::new(entry.storager, entry.namespace);
+                accumulators.restore(key.clone(), entry.accumulators);
+                sketches.restore(key.clone(), entry.sketches);
+                tracked.versions.insert(key.clone(), entry.version);
+                tracked.roots.insert(key, entry.root_hash);
+            }
+        }
+        let store = RootStore {
+            path: Some(path),
+            save_lock: Mutex::new(()),
+            accumulators,
+            sketches,
+        };
+        Ok((store, tracked))
+    }
+
+    /// 是否配置了文件
+    pub fn is_persistent(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// 跟踪的 keyword 累加器值
+    pub fn accumulators(&self) -> &KeywordAccumulators {
+        &self.accumulators
+    }
+
+    /// 跟踪的 keyword 草图摘要
+    pub fn sketches(&self) -> &KeywordSketches {
+        &self.sketches
+    }
+
+    /// 把当前跟踪的根哈希、keyword 累加器值和草图摘要写入文件（未配置文件时不做任何事）
+    pub fn save(
+        &self,
+        roots: &HashMap<RootKey, RootHash>,
+        versions: &HashMap<RootKey, u64>,
+    ) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut accumulators = self.accumulators.snapshot();
+        let mut sketches = self.sketches.snapshot();
+        let mut stored: Vec<StoredRoot> = roots
+            .iter()
+            .map(|(key, root_hash)| StoredRoot {
+                storager: key.storager.clone(),
+                namespace: key.namespace.clone(),
+                root_hash: root_hash.clone(),
+                version: versions.get(key).copied().unwrap_or(0),
+                accumulators: accumulators.remove(key).unwrap_or_default(),
+                sketches: sketches.remove(key).unwrap_or_default(),
+            })
+            .collect();
+        stored.sort_by(|a, b| (&a.storager, &a.namespace).cmp(&(&b.storager, &b.namespace)));
+
+        let _guard = self.save_lock.lock().unwrap();
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&stored)?)?;
+        std::fs::rename(&tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reopen_restores_roots() {
+        let path = std::env::temp_dir().join(format!("manager-roots-{}.json", std::process::id()));
+
+        let (store, tracked) = RootStore::open(&path).unwrap();
+        assert!(store.is_persistent());
+        assert!(tracked.roots.is_empty());
+
+        let key = RootKey::new("s1", "tenant");
+        let roots = HashMap::from([(key.clone(), vec![7u8; 32])]);
+        let versions = HashMap::from([(key.clone(), 42)]);
+        store.accumulators().set(&key, "rust", vec![9u8; 48]);
+        store.sketches().record(&key, "rust", 42, vec![3u8; 32]);
+        store.save(&roots, &versions).unwrap();
+
+        let (store, tracked) = RootStore::open(&path).unwrap();
+        assert_eq!(tracked.roots, roots);
+        assert_eq!(tracked.versions, versions);
+        assert_eq!(store.accumulators().value(&key, "rust"), vec![9u8; 48]);
+        assert_eq!(store.sketches().digest(&key, "rust"), Some(vec![3u8; 32]));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -2,10 +2,24 @@
 //!
 //! 负责使用一致性哈希将关键字路由到对应的 storager 节点
 
-use consistent_hash::ConsistentHashRing;
-use std::collections::HashMap;
+use consistent_hash::{ConsistentHashRing, RebalancePlan, RingHasher};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::path::Path;
 use std::sync::{Arc, RwLock};
 
+/// 路由表快照
+///
+/// 保存哈希环拓扑和 storager 地址，Manager 重启后据此恢复运行期间的节点增删
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouterSnapshot {
+    /// 哈希环拓扑
+    pub ring: ConsistentHashRing,
+    /// storager 名称 -> 地址
+    pub storagers: BTreeMap<String, String>,
+}
+
 /// 路由器结构
 ///
 /// 管理一致性哈希环和 storager 地址映射
@@ -13,29 +27,110 @@ pub struct Router {
     /// 一致性哈希环
     hash_ring: Arc<RwLock<ConsistentHashRing>>,
     /// storager 名称到地址的映射
-    storager_addrs: HashMap<String, String>,
+    storager_addrs: RwLock<HashMap<String, String>>,
+    /// 健康检查失败的 storager（路由时跳过）
+    unhealthy: RwLock<HashSet<String>>,
 }
 
 impl Router {
     /// 创建新的路由器
     ///
     /// # Arguments
-    /// * `storager_addrs` - storager 地址列表
+    /// * `storager_addrs` - storager 通告地址列表，每一项为 `url` 或 `name=url`
     /// * `virtual_nodes_per_storager` - 每个 storager 的虚拟节点数量（默认 150）
+    ///
+    /// 指定 `name` 时哈希环使用该名称作为节点标识，storager 的通告地址变化
+    /// （例如容器重新调度）不会影响数据分布；否则按顺序命名为 `storager-{idx}`
     pub fn new(storager_addrs: Vec<String>, virtual_nodes_per_storager: usize) -> Self {
         let mut hash_ring = ConsistentHashRing::new();
         let mut addr_map = HashMap::new();
 
         // 为每个 storager 在哈希环上添加虚拟节点
-        for (idx, addr) in storager_addrs.iter().enumerate() {
-            let node_name = format!("storager-{}", idx);
+        for (idx, entry) in storager_addrs.iter().enumerate() {
+            let (node_name, addr) = Self::parse_storager_entry(idx, entry);
             hash_ring.add_node(&node_name, virtual_nodes_per_storager);
-            addr_map.insert(node_name, addr.clone());
+            addr_map.insert(node_name, addr);
         }
 
         Router {
             hash_ring: Arc::new(RwLock::new(hash_ring)),
-            storager_addrs: addr_map,
+            storager_addrs: RwLock::new(addr_map),
+            unhealthy: RwLock::new(HashSet::new()),
+        }
+    }
+
+    /// 使用指定的哈希函数放置节点和关键词
+    ///
+    /// 切换哈希函数会改变关键词的归属节点，只应在部署初始化时选择
+    pub fn with_hasher(self, hasher: RingHasher) -> Self {
+        {
+            let mut ring = self.hash_ring.write().unwrap();
+            *ring = std::mem::take(&mut *ring).with_hasher(hasher);
+        }
+        self
+    }
+
+    /// 哈希环使用的哈希函数
+    pub fn hasher(&self) -> RingHasher {
+        self.hash_ring.read().unwrap().hasher()
+    }
+
+    /// 当前路由表的快照（健康状态不包含在内，重启后重新检查）
+    pub fn snapshot(&self) -> RouterSnapshot {
+        RouterSnapshot {
+            ring: self.hash_ring.read().unwrap().clone(),
+            storagers: self
+                .storager_addrs
+                .read()
+                .unwrap()
+                .iter()
+                .map(|(name, addr)| (name.clone(), addr.clone()))
+                .collect(),
+        }
+    }
+
+    /// 从快照恢复路由表
+    ///
+    /// 环上的每个节点都必须有对应的地址
+    pub fn from_snapshot(snapshot: RouterSnapshot) -> Result<Self, String> {
+        if let Some(missing) = snapshot
+            .ring
+            .get_all_nodes()
+            .into_iter()
+            .find(|name| !snapshot.storagers.contains_key(name))
+        {
+            return Err(format!("ring node '{}' has no storager address", missing));
+        }
+
+        Ok(Router {
+            hash_ring: Arc::new(RwLock::new(snapshot.ring)),
+            storager_addrs: RwLock::new(snapshot.storagers.into_iter().collect()),
+            unhealthy: RwLock::new(HashSet::new()),
+        })
+    }
+
+    /// 把路由表快照写入文件（先写临时文件再重命名，避免留下写了一半的快照）
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(&self.snapshot())?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)
+    }
+
+    /// 从文件加载路由表快照
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let snapshot: RouterSnapshot = serde_json::from_slice(&std::fs::read(path)?)?;
+        Self::from_snapshot(snapshot).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// 解析 `name=url` 形式的 storager 配置项
+    fn parse_storager_entry(idx: usize, entry: &str) -> (String, String) {
+        match entry.split_once('=') {
+            Some((name, addr)) if !name.trim().is_empty() => {
+                (name.trim().to_string(), addr.trim().to_string())
+            }
+            _ => (format!("storager-{}", idx), entry.trim().to_string()),
         }
     }
 
@@ -43,33 +138,166 @@ impl Router {
     ///
     /// # Returns
     /// 返回 `Some((节点名称, 节点地址))` 或 `None`
+    ///
+    /// 主节点不健康时顺时针选择下一个健康节点；所有节点都不健康时返回 `None`
     pub fn get_storager_for_keyword(&self, keyword: &str) -> Option<(String, String)> {
         let ring = self.hash_ring.read().unwrap();
-        let node_name = ring.get_node(keyword)?;
-        let addr = self.storager_addrs.get(&node_name)?.clone();
+        let storager_addrs = self.storager_addrs.read().unwrap();
+        let unhealthy = self.unhealthy.read().unwrap();
+        let node_name = if unhealthy.is_empty() {
+            ring.get_node(keyword)?
+        } else {
+            ring.get_nodes(keyword, storager_addrs.len())
+                .into_iter()
+                .find(|name| !unhealthy.contains(name))?
+        };
+        let addr = storager_addrs.get(&node_name)?.clone();
         Some((node_name, addr))
     }
 
-    /// 添加新的 storager 节点
-    pub fn add_storager(&mut self, addr: String, virtual_nodes: usize) {
-        let idx = self.storager_addrs.len();
-        let node_name = format!("storager-{}", idx);
+    /// 获取关键字的副本节点：环上顺时针的前 `count` 个健康节点（主节点在前）
+    ///
+    /// 第一个节点与 [`get_storager_for_keyword`](Self::get_storager_for_keyword) 的结果相同
+    pub fn get_replicas_for_keyword(&self, keyword: &str, count: usize) -> Vec<(String, String)> {
+        let ring = self.hash_ring.read().unwrap();
+        let storager_addrs = self.storager_addrs.read().unwrap();
+        let unhealthy = self.unhealthy.read().unwrap();
+        ring.get_nodes(keyword, storager_addrs.len())
+            .into_iter()
+            .filter(|name| !unhealthy.contains(name))
+            .take(count)
+            .filter_map(|name| {
+                let addr = storager_addrs.get(&name)?.clone();
+                Some((name, addr))
+            })
+            .collect()
+    }
+
+    /// 更新 storager 的健康状态
+    ///
+    /// 不健康的节点不再被路由，其负责的关键词暂时落到环上的下一个节点，
+    /// 恢复健康后重新路由回来
+    pub fn set_healthy(&self, node_name: &str, healthy: bool) {
+        let mut unhealthy = self.unhealthy.write().unwrap();
+        if healthy {
+            unhealthy.remove(node_name);
+        } else {
+            unhealthy.insert(node_name.to_string());
+        }
+    }
 
+    /// storager 是否健康（未知节点视为健康）
+    pub fn is_healthy(&self, node_name: &str) -> bool {
+        !self.unhealthy.read().unwrap().contains(node_name)
+    }
+
+    /// 按节点名称查找 storager 地址
+    pub fn get_storager_addr(&self, node_name: &str) -> Option<String> {
+        self.storager_addrs.read().unwrap().get(node_name).cloned()
+    }
+
+    /// 自动命名新节点时使用的名称（`storager-{idx}`，跳过已被占用的编号）
+    pub fn next_storager_name(&self) -> String {
+        let storager_addrs = self.storager_addrs.read().unwrap();
+        (storager_addrs.len()..)
+            .map(|idx| format!("storager-{}", idx))
+            .find(|name| !storager_addrs.contains_key(name))
+            .unwrap()
+    }
+
+    /// 添加新的 storager 节点（自动命名）
+    pub fn add_storager(&self, addr: String, virtual_nodes: usize) {
+        self.add_named_storager(&self.next_storager_name(), addr, virtual_nodes);
+    }
+
+    /// 以指定名称添加 storager 节点（名称已存在时返回 false）
+    pub fn add_named_storager(&self, node_name: &str, addr: String, virtual_nodes: usize) -> bool {
         let mut ring = self.hash_ring.write().unwrap();
-        ring.add_node(&node_name, virtual_nodes);
-        self.storager_addrs.insert(node_name, addr);
+        let mut storager_addrs = self.storager_addrs.write().unwrap();
+        if storager_addrs.contains_key(node_name) || !ring.add_node(node_name, virtual_nodes) {
+            return false;
+        }
+        storager_addrs.insert(node_name.to_string(), addr);
+        true
+    }
+
+    /// 预览添加新 storager 节点的迁移代价（不修改路由）
+    pub fn plan_add_storager(&self, virtual_nodes: usize) -> RebalancePlan {
+        self.plan_add_named_storager(&self.next_storager_name(), virtual_nodes)
+            .unwrap_or_default()
+    }
+
+    /// 预览以指定名称添加 storager 节点的迁移代价（名称已存在时返回 None）
+    pub fn plan_add_named_storager(
+        &self,
+        node_name: &str,
+        virtual_nodes: usize,
+    ) -> Option<RebalancePlan> {
+        if self.storager_addrs.read().unwrap().contains_key(node_name) {
+            return None;
+        }
+        let ring = self.hash_ring.read().unwrap();
+        ring.plan_add_node(node_name, virtual_nodes)
+    }
+
+    /// 预览移除 storager 节点的迁移代价（节点不存在时返回 None）
+    pub fn plan_remove_storager(&self, node_name: &str) -> Option<RebalancePlan> {
+        let ring = self.hash_ring.read().unwrap();
+        ring.plan_remove_node(node_name)
     }
 
     /// 移除 storager 节点
-    pub fn remove_storager(&mut self, node_name: &str) {
+    pub fn remove_storager(&self, node_name: &str) {
         let mut ring = self.hash_ring.write().unwrap();
         ring.remove_node(node_name);
-        self.storager_addrs.remove(node_name);
+        self.storager_addrs.write().unwrap().remove(node_name);
+        self.unhealthy.write().unwrap().remove(node_name);
+    }
+
+    /// 计算更均衡的哈希环及切换到它的迁移计划（不修改路由）
+    ///
+    /// 返回的环只调整各节点的虚拟节点数量，用 [`replace_ring`](Self::replace_ring) 切换
+    pub fn plan_rebalance(&self) -> (ConsistentHashRing, RebalancePlan) {
+        let ring = self.hash_ring.read().unwrap();
+        let balanced = ring.rebalanced();
+        let plan = ring.plan_transition(&balanced);
+        (balanced, plan)
+    }
+
+    /// 切换到新的哈希环（节点集合必须与当前环相同）
+    pub fn replace_ring(&self, ring: ConsistentHashRing) {
+        *self.hash_ring.write().unwrap() = ring;
+    }
+
+    /// 占有哈希空间最多的节点相对平均比例的倍数
+    pub fn imbalance(&self) -> f64 {
+        self.hash_ring.read().unwrap().imbalance()
+    }
+
+    /// 每个 storager 的虚拟节点数量和占有的哈希空间比例
+    pub fn ring_layout(&self) -> BTreeMap<String, (usize, f64)> {
+        let ring = self.hash_ring.read().unwrap();
+        let ownership = ring.ownership();
+        ring.get_all_nodes()
+            .into_iter()
+            .map(|node| {
+                let virtual_nodes = ring.get_virtual_node_count(&node).unwrap_or(0);
+                let fraction = ownership.get(&node).copied().unwrap_or(0.0);
+                (node, (virtual_nodes, fraction))
+            })
+            .collect()
+    }
+
+    /// keyword 在哈希环上的位置
+    pub fn ring_position(&self, keyword: &str) -> u64 {
+        self.hash_ring.read().unwrap().hash_key(keyword)
     }
 
     /// 获取所有 storager 节点
     pub fn get_all_storagers(&self) -> Vec<(String, String)> {
         self.storager_addrs
+            .read()
+            .unwrap()
             .iter()
             .map(|(name, addr)| (name.clone(), addr.clone()))
             .collect()
@@ -77,7 +305,7 @@ impl Router {
 
     /// 获取 storager 数量
     pub fn storager_count(&self) -> usize {
-        self.storager_addrs.len()
+        self.storager_addrs.read().unwrap().len()
     }
 }
 
@@ -109,4 +337,182 @@ mod tests {
 
         assert_eq!(result1, result2);
     }
+
+    #[test]
+    fn test_named_storagers() {
+        let addrs = vec![
+            "alpha=http://10.0.0.5:50052".to_string(),
+            "http://[::1]:50053".to_string(),
+        ];
+        let router = Router::new(addrs, 150);
+        let mut storagers = router.get_all_storagers();
+        storagers.sort();
+
+        assert_eq!(
+            storagers,
+            vec![
+                ("alpha".to_string(), "http://10.0.0.5:50052".to_string()),
+                ("storager-1".to_string(), "http://[::1]:50053".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_add_storager() {
+        let addrs = vec![
+            "http://[::1]:50052".to_string(),
+            "http://[::1]:50053".to_string(),
+        ];
+        let router = Router::new(addrs, 150);
+        let plan = router.plan_add_storager(150);
+
+        assert!(!plan.is_empty());
+        assert!(plan
+            .ranges
+            .iter()
+            .all(|r| r.to.as_deref() == Some("storager-2")));
+        assert_eq!(router.storager_count(), 2);
+    }
+
+    #[test]
+    fn test_runtime_membership_names() {
+        let addrs = vec![
+            "http://[::1]:50052".to_string(),
+            "http://[::1]:50053".to_string(),
+            "http://[::1]:50054".to_string(),
+        ];
+        let router = Router::new(addrs, 150);
+        router.remove_storager("storager-0");
+
+        // 自动命名不会复用仍在使用的编号
+        assert_eq!(router.next_storager_name(), "storager-3");
+        assert!(router.plan_add_named_storager("storager-1", 150).is_none());
+        assert!(!router.add_named_storager("storager-1", "http://[::1]:1".to_string(), 150));
+
+        assert!(router.add_named_storager("beta", "http://[::1]:50055".to_string(), 150));
+        assert_eq!(router.storager_count(), 3);
+        assert_eq!(
+            router.get_storager_addr("beta").as_deref(),
+            Some("http://[::1]:50055")
+        );
+    }
+
+    #[test]
+    fn test_skips_unhealthy_storagers() {
+        let addrs = vec![
+            "http://[::1]:50052".to_string(),
+            "http://[::1]:50053".to_string(),
+        ];
+        let router = Router::new(addrs, 150);
+        let (primary, _) = router.get_storager_for_keyword("test").unwrap();
+
+        router.set_healthy(&primary, false);
+        assert!(!router.is_healthy(&primary));
+        let (fallback, _) = router.get_storager_for_keyword("test").unwrap();
+        assert_ne!(fallback, primary);
+
+        router.set_healthy(&fallback, false);
+        assert!(router.get_storager_for_keyword("test").is_none());
+
+        router.set_healthy(&primary, true);
+        assert_eq!(
+            router
+                .get_storager_for_keyword("test")
+                .map(|(name, _)| name),
+            Some(primary)
+        );
+    }
+
+    #[test]
+    fn test_replicas_start_with_primary() {
+        let addrs: Vec<String> = (0..3).map(|i| format!("http://[::1]:5005{}", i)).collect();
+        let router = Router::new(addrs, 150);
+        let (primary, _) = router.get_storager_for_keyword("test").unwrap();
+
+        let replicas = router.get_replicas_for_keyword("test", 2);
+        assert_eq!(replicas.len(), 2);
+        assert_eq!(replicas[0].0, primary);
+        assert_ne!(replicas[1].0, primary);
+        assert_eq!(router.get_replicas_for_keyword("test", 5).len(), 3);
+
+        router.set_healthy(&primary, false);
+        let replicas = router.get_replicas_for_keyword("test", 2);
+        assert_eq!(replicas.len(), 2);
+        assert!(replicas.iter().all(|(name, _)| *name != primary));
+    }
+
+    #[test]
+    fn test_router_with_hasher() {
+        let addrs = vec![
+            "http://[::1]:50052".to_string(),
+            "http://[::1]:50053".to_string(),
+        ];
+        let router = Router::new(addrs, 150).with_hasher(RingHasher::Sha256);
+        assert_eq!(router.hasher(), RingHasher::Sha256);
+        assert_eq!(router.plan_add_storager(150).hasher, RingHasher::Sha256);
+        assert!(router.get_storager_for_keyword("test").is_some());
+    }
+
+    #[test]
+    fn test_plan_rebalance() {
+        let router = Router::new(
+            vec![
+                "http://localhost:50051".to_string(),
+                "http://localhost:50052".to_string(),
+                "http://localhost:50053".to_string(),
+            ],
+            4,
+        )
+        .with_hasher(RingHasher::XxHash64);
+        let before = router.imbalance();
+        let (ring, plan) = router.plan_rebalance();
+        // 预览不修改路由
+        assert_eq!(router.imbalance(), before);
+
+        router.replace_ring(ring);
+        assert!(router.imbalance() < before);
+        assert!(plan.hash_space_fraction() > 0.0);
+        let layout = router.ring_layout();
+        assert_eq!(layout.len(), 3);
+        let total: f64 = layout.values().map(|(_, fraction)| fraction).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_snapshot_save_and_load() {
+        let addrs = vec![
+            "http://[::1]:50052".to_string(),
+            "http://[::1]:50053".to_string(),
+        ];
+        let router = Router::new(addrs, 150).with_hasher(RingHasher::XxHash64);
+        router.add_storager("http://[::1]:50054".to_string(), 100);
+        router.remove_storager("storager-0");
+
+        let path = std::env::temp_dir().join(format!("router-{}.json", std::process::id()));
+        router.save(&path).unwrap();
+        let restored = Router::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.hasher(), RingHasher::XxHash64);
+        let mut storagers = restored.get_all_storagers();
+        storagers.sort();
+        assert_eq!(
+            storagers,
+            vec![
+                ("storager-1".to_string(), "http://[::1]:50053".to_string()),
+                ("storager-2".to_string(), "http://[::1]:50054".to_string()),
+            ]
+        );
+        for i in 0..200 {
+            let keyword = format!("kw{}", i);
+            assert_eq!(
+                restored.get_storager_for_keyword(&keyword),
+                router.get_storager_for_keyword(&keyword)
+            );
+        }
+
+        let mut snapshot = router.snapshot();
+        snapshot.storagers.remove("storager-2");
+        assert!(Router::from_snapshot(snapshot).is_err());
+    }
 }
@@ -0,0 +1,129 @@
+//! 每个 keyword 的草图摘要
+//!
+//! ApproxCount 返回的 HyperLogLog 草图没有提交到 ADS 根之下（见 [`common::sketch`]），
+//! 不能说明自己的来源。storager 在每次添加的响应中报告添加后 keyword 草图的叶子哈希
+//! （见 [`esa_rust::merkle_tree::leaf_hash_bytes`]），Manager 在变更证明验证通过后按
+//! (storager, 命名空间, keyword) 记录它；查询草图时叶子哈希必须等于记录的值。
+//!
+//! 并发写入的证明可能乱序验证，每个 keyword 只保留 epoch 最新的摘要。关键词迁移时目标节点
+//! 按迁移来的 fid 重建草图，Manager 用验证过的 fid 列表算出同样的摘要。
+//! 摘要随根哈希一起持久化（见 [`crate::core::root_store`]）。
+
+use super::RootKey;
+use common::sketch::HyperLogLog;
+use common::RootHash;
+use esa_rust::merkle_tree::leaf_hash_bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+/// 一个 keyword 记录的草图摘要
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SketchDigest {
+    /// 报告摘要的写入所在的 storager epoch
+    pub epoch: u64,
+    pub digest: RootHash,
+}
+
+/// 由 `fids` 构建的草图的叶子哈希
+pub fn sketch_digest(keyword: &str, fids: &[String]) -> RootHash {
+    let sketch = HyperLogLog::from_items(fids.iter().map(String::as_str));
+    leaf_hash_bytes(keyword, &sketch.to_bytes()).to_vec()
+}
+
+/// 各 storager 命名空间中每个 keyword 的草图摘要
+#[derive(Default)]
+pub struct KeywordSketches {
+    digests: RwLock<HashMap<RootKey, BTreeMap<String, SketchDigest>>>,
+}
+
+impl KeywordSketches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// keyword 记录的摘要，没有记录时为 None
+    pub fn digest(&self, key: &RootKey, keyword: &str) -> Option<RootHash> {
+        self.digests
+            .read()
+            .unwrap()
+            .get(key)
+            .and_then(|keywords| keywords.get(keyword))
+            .map(|entry| entry.digest.clone())
+    }
+
+    /// 记录在 `epoch` 完成的添加报告的摘要，比已记录的 epoch 旧时忽略
+    pub fn record(&self, key: &RootKey, keyword: &str, epoch: u64, digest: RootHash) {
+        let mut digests = self.digests.write().unwrap();
+        let keywords = digests.entry(key.clone()).or_default();
+        match keywords.get(keyword) {
+            Some(entry) if entry.epoch > epoch => {}
+            _ => {
+                keywords.insert(keyword.to_string(), SketchDigest { epoch, digest });
+            }
+        }
+    }
+
+    /// 直接设置 keyword 的摘要（关键词迁移重建了草图时使用），`None` 表示没有草图
+    pub fn set(&self, key: &RootKey, keyword: &str, digest: Option<RootHash>) {
+        let mut digests = self.digests.write().unwrap();
+        let keywords = digests.entry(key.clone()).or_default();
+        let epoch = keywords.get(keyword).map_or(0, |entry| entry.epoch);
+        match digest {
+            Some(digest) => {
+                keywords.insert(keyword.to_string(), SketchDigest { epoch, digest });
+            }
+            None => {
+                keywords.remove(keyword);
+                if keywords.is_empty() {
+                    digests.remove(key);
+                }
+            }
+        }
+    }
+
+    /// 所有记录的摘要
+    pub fn snapshot(&self) -> HashMap<RootKey, BTreeMap<String, SketchDigest>> {
+        self.digests.read().unwrap().clone()
+    }
+
+    /// 用持久化的摘要替换 storager 命名空间的所有 keyword
+    pub fn restore(&self, key: RootKey, keywords: BTreeMap<String, SketchDigest>) {
+        let mut digests = self.digests.write().unwrap();
+        if keywords.is_empty() {
+            digests.remove(&key);
+        } else {
+            digests.insert(key, keywords);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_the_latest_epoch() {
+        let sketches = KeywordSketches::new();
+        let key = RootKey::new("s1", "");
+        assert_eq!(sketches.digest(&key, "rust"), None);
+
+        sketches.record(&key, "rust", 5, vec![5]);
+        // 较早的写入后验证完成，不覆盖较新的摘要
+        sketches.record(&key, "rust", 3, vec![3]);
+        assert_eq!(sketches.digest(&key, "rust"), Some(vec![5]));
+        sketches.record(&key, "rust", 6, vec![6]);
+        assert_eq!(sketches.digest(&key, "rust"), Some(vec![6]));
+
+        // 迁移重建的草图保留 epoch，之后的写入照常前进
+        let fids = vec!["f1".to_string(), "f2".to_string()];
+        sketches.set(&key, "rust", Some(sketch_digest("rust", &fids)));
+        sketches.record(&key, "rust", 4, vec![4]);
+        assert_eq!(
+            sketches.digest(&key, "rust"),
+            Some(sketch_digest("rust", &fids))
+        );
+        sketches.set(&key, "rust", None);
+        assert!(sketches.snapshot().is_empty());
+    }
+}
@@ -0,0 +1,143 @@
+//! Update 协调
+//!
+//! Update 把一个 fid 从旧 keyword 集合移到新 keyword 集合，涉及的 keyword 通常落在不同的
+//! storager 上，无法原子地完成。协调器保证在整个过程中 fid 至少能通过旧集合或新集合之一查到：
+//!
+//! 1. 同一个 fid 的写请求（Add、Delete、Update）由 [`FidLocks`] 串行执行，
+//!    两个并发的 Update 不会交错，互相删掉对方刚加入的 keyword；
+//! 2. 先加入新 keyword，全部成功后再删除只属于旧集合的 keyword（见 [`UpdatePlan`]）。
+//!    加入阶段失败时撤销已经加入的 keyword，fid 回到旧集合；删除阶段失败时 fid 仍在新集合下。
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::sync::OwnedMutexGuard;
+
+/// 按 fid 加锁，没有持有者的锁会被回收
+#[derive(Default)]
+pub struct FidLocks {
+    locks: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+impl FidLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 等待并持有 fid 的锁，返回的守卫被丢弃时释放
+    pub async fn lock(&self, fid: &str) -> FidGuard {
+        let lock = self
+            .locks
+            .lock()
+            .unwrap()
+            .entry(fid.to_string())
+            .or_default()
+            .clone();
+        FidGuard {
+            guard: Some(lock.lock_owned().await),
+            fid: fid.to_string(),
+            locks: self.locks.clone(),
+        }
+    }
+
+    /// 当前有持有者或等待者的 fid 数
+    pub fn len(&self) -> usize {
+        self.locks.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 一个 fid 的锁
+pub struct FidGuard {
+    guard: Option<OwnedMutexGuard<()>>,
+    fid: String,
+    locks: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+impl Drop for FidGuard {
+    fn drop(&mut self) {
+        drop(self.guard.take());
+        let mut locks = self.locks.lock().unwrap();
+        // 表中的引用是最后一个时没有其他等待者，可以回收
+        if locks
+            .get(&self.fid)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            locks.remove(&self.fid);
+        }
+    }
+}
+
+/// Update 的执行顺序：先加入 `adds`，再删除 `deletes`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdatePlan {
+    /// 新集合中的所有 keyword（同时在旧集合中的 keyword 重复加入不改变 ADS）
+    pub adds: Vec<String>,
+    /// 只在旧集合中的 keyword
+    pub deletes: Vec<String>,
+    /// 同时在两个集合中的 keyword，撤销加入阶段时保留
+    kept: BTreeSet<String>,
+}
+
+impl UpdatePlan {
+    pub fn new(old_keywords: Vec<String>, new_keywords: Vec<String>) -> Self {
+        let adds: BTreeSet<String> = new_keywords.into_iter().collect();
+        let (kept, deletes): (BTreeSet<String>, BTreeSet<String>) = old_keywords
+            .into_iter()
+            .partition(|keyword| adds.contains(keyword));
+        UpdatePlan {
+            adds: adds.into_iter().collect(),
+            deletes: deletes.into_iter().collect(),
+            kept,
+        }
+    }
+
+    /// 加入阶段失败时需要撤销的 keyword：`added` 中不在旧集合里的
+    pub fn rollback<'a>(&self, added: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+        added
+            .into_iter()
+            .filter(|keyword| !self.kept.contains(*keyword))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_plan_keeps_shared_keywords() {
+        let plan = UpdatePlan::new(strings(&["a", "b", "a"]), strings(&["c", "b"]));
+        assert_eq!(plan.adds, strings(&["b", "c"]));
+        assert_eq!(plan.deletes, strings(&["a"]));
+
+        assert_eq!(plan.rollback(&plan.adds), strings(&["c"]));
+    }
+
+    #[tokio::test]
+    async fn test_same_fid_is_serialized() {
+        let locks = Arc::new(FidLocks::new());
+        let first = locks.lock("f1").await;
+        let other = locks.lock("f2").await;
+
+        let waiter = tokio::spawn({
+            let locks = locks.clone();
+            async move { locks.lock("f1").await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        drop(first);
+        drop(waiter.await.unwrap());
+        drop(other);
+        assert!(locks.is_empty());
+    }
+}
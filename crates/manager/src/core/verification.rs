@@ -2,9 +2,197 @@
 //!
 //! 负责验证来自 storager 的密码学证明
 
-use ark_bls12_381::G1Affine;
-use ark_serialize::CanonicalDeserialize;
-use common::AdsMode;
+use ark_bls12_381::{Fr, G1Affine, G2Affine};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use common::fid_intern::{parse_compact_id, parse_digest_entry, InternedProof, FID_TABLE_KEYWORD};
+use common::rpc::{boolean_proof::Node, BooleanProof, ProofMetrics};
+use common::{AdsMode, Proof, RootHash};
+use esa_rust::crypto_accumulator::acc::dynamic_accumulator::{
+    element_to_field, poly_from_roots, AddProof, BatchMembershipProof, DeleteProof,
+    DifferenceProof, DynamicAccumulator, IntersectionProof, NonMembershipProof, UnionProof,
+};
+use esa_rust::crypto_accumulator::public_params;
+use esa_rust::merkle_tree::{leaf_hash, verify_merkle_proof, MerkleAdsProof, MerkleProof};
+use esa_rust::mpt::{KVPair, RangeProof, ValueProof};
+use esa_rust::smt::{KeywordProof, DEPTH as SMT_DEPTH};
+use prost::Message;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock, RwLock};
+use tracing::{debug, warn};
+
+/// 第三方 ADS 的证明验证接口
+///
+/// 通过 [`register_verifier`] 注册后，`AdsMode::Custom` 模式的证明交由它验证
+pub trait AdsVerifier: Send + Sync {
+    /// 验证证明
+    fn verify(&self, proof: &[u8], root_hash: &[u8]) -> bool;
+
+    /// 合并多个证明（默认返回第一个非空证明）
+    fn combine_proofs(&self, proofs: &[Vec<u8>]) -> Vec<u8> {
+        proofs
+            .iter()
+            .find(|p| !p.is_empty())
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// 各类累加器证明验证时计算的配对次数，与 `dynamic_accumulator` 中的验证等式对应
+const UPDATE_PAIRINGS: u64 = 2;
+const MEMBERSHIP_PAIRINGS: u64 = 2;
+const NON_MEMBERSHIP_PAIRINGS: u64 = 3;
+const INTERSECTION_PAIRINGS: u64 = 7;
+/// 并集、差集（按累加器值验证）在交集证明之外各多一个配对等式
+const UNION_PAIRINGS: u64 = INTERSECTION_PAIRINGS + 2;
+const DIFFERENCE_ACC_PAIRINGS: u64 = INTERSECTION_PAIRINGS + 2;
+
+/// 分页查询还没有交付任何 fid 时的摘要（见 [`ProofVerifier::fold_delivered`]）
+pub const DELIVERED_EMPTY: [u8; 32] = [0; 32];
+
+fn custom_verifiers() -> &'static RwLock<HashMap<&'static str, Arc<dyn AdsVerifier>>> {
+    static VERIFIERS: OnceLock<RwLock<HashMap<&'static str, Arc<dyn AdsVerifier>>>> =
+        OnceLock::new();
+    VERIFIERS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// 为第三方 ADS 模式注册证明验证器
+///
+/// 内置模式的验证逻辑是固定的，不能被替换
+pub fn register_verifier(mode: AdsMode, verifier: Arc<dyn AdsVerifier>) -> Result<(), String> {
+    match mode {
+        AdsMode::Custom(name) => {
+            custom_verifiers().write().unwrap().insert(name, verifier);
+            Ok(())
+        }
+        builtin => Err(format!(
+            "Cannot override verifier of built-in ADS mode '{}'",
+            builtin.name()
+        )),
+    }
+}
+
+fn custom_verifier(name: &str) -> Option<Arc<dyn AdsVerifier>> {
+    custom_verifiers().read().unwrap().get(name).cloned()
+}
+
+/// MPT 证明验证失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MptProofError {
+    /// 证明无法解码为 [`ValueProof`]
+    Malformed(String),
+    /// 证明路径不连贯，无法推导出根哈希
+    BrokenPath,
+    /// Manager 还没有跟踪这个 storager 命名空间的根哈希，无从比较
+    UntrackedRoot,
+    /// 推导出的根哈希与记录的根哈希不一致
+    RootMismatch {
+        expected: Vec<u8>,
+        computed: Vec<u8>,
+    },
+}
+
+impl std::fmt::Display for MptProofError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MptProofError::Malformed(e) => write!(f, "malformed MPT proof: {}", e),
+            MptProofError::BrokenPath => write!(f, "MPT proof path does not link up to a root"),
+            MptProofError::UntrackedRoot => write!(f, "no root hash is tracked for the storager"),
+            MptProofError::RootMismatch { expected, computed } => write!(
+                f,
+                "MPT proof computes root {:x?} but {:x?} is tracked",
+                computed, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MptProofError {}
+
+/// 验证 MPT 证明
+///
+/// 解码 storager 返回的 [`ValueProof`]，用其中的值和路径重算根哈希并与 `root_hash` 比较；
+/// 还没有记录根哈希（`root_hash` 为空）时拒绝：自洽的证明可以由任意内容构造
+pub fn verify_mpt_proof(proof: &[u8], root_hash: &[u8]) -> Result<(), MptProofError> {
+    if root_hash.is_empty() {
+        return Err(MptProofError::UntrackedRoot);
+    }
+    let proof =
+        ValueProof::from_bytes(proof).map_err(|e| MptProofError::Malformed(e.to_string()))?;
+    let computed = if proof.proves_empty_trie() {
+        [0u8; 32]
+    } else {
+        let root = proof.compute_root();
+        if root == [0u8; 32] {
+            return Err(MptProofError::BrokenPath);
+        }
+        root
+    };
+    if root_hash != computed {
+        return Err(MptProofError::RootMismatch {
+            expected: root_hash.to_vec(),
+            computed: computed.to_vec(),
+        });
+    }
+    Ok(())
+}
+
+/// 验证 MPT 范围证明，返回证明中 `[start_key, end_key)` 内的 (keyword, fids)
+///
+/// 结果直接取自证明，调用方应以它为准而不是 storager 另行返回的列表；
+/// 被剪掉的子树可能包含范围内的 keyword 时视为路径不连贯
+pub fn verify_mpt_range_proof(
+    proof: &[u8],
+    start_key: &str,
+    end_key: &str,
+    root_hash: &[u8],
+) -> Result<Vec<(String, Vec<String>)>, MptProofError> {
+    verify_range_proof_with(proof, root_hash, |proof| {
+        proof.compute_root(start_key, end_key)
+    })
+}
+
+/// 验证 MPT 前缀子树证明，返回以 `prefix` 开头的 (keyword, fids)
+pub fn verify_mpt_prefix_proof(
+    proof: &[u8],
+    prefix: &str,
+    root_hash: &[u8],
+) -> Result<Vec<(String, Vec<String>)>, MptProofError> {
+    verify_range_proof_with(proof, root_hash, |proof| proof.compute_prefix_root(prefix))
+}
+
+fn verify_range_proof_with(
+    proof: &[u8],
+    root_hash: &[u8],
+    compute: impl FnOnce(&RangeProof) -> Option<([u8; 32], Vec<KVPair>)>,
+) -> Result<Vec<(String, Vec<String>)>, MptProofError> {
+    if root_hash.is_empty() {
+        return Err(MptProofError::UntrackedRoot);
+    }
+    let proof =
+        RangeProof::from_bytes(proof).map_err(|e| MptProofError::Malformed(e.to_string()))?;
+    let (computed, pairs) = compute(&proof).ok_or(MptProofError::BrokenPath)?;
+    if root_hash != computed {
+        return Err(MptProofError::RootMismatch {
+            expected: root_hash.to_vec(),
+            computed: computed.to_vec(),
+        });
+    }
+    // 值为空的 keyword 是 MPT 中尚未压缩的墓碑，没有 fid
+    Ok(pairs
+        .iter()
+        .filter(|kv| !kv.get_value().is_empty())
+        .map(|kv| {
+            let fids = kv
+                .get_value()
+                .split(',')
+                .filter(|fid| !fid.is_empty())
+                .map(str::to_string)
+                .collect();
+            (kv.get_key().to_string(), fids)
+        })
+        .collect())
+}
 
 /// 证明验证器
 pub struct ProofVerifier {
@@ -20,79 +208,617 @@ impl ProofVerifier {
     /// 验证证明
     ///
     /// # Arguments
-    /// * `proof` - 证明数据
+    /// * `proof` - 证明数据，按变体解码
     /// * `root_hash` - 根哈希(某些 ADS 模式下需要)
     ///
     /// # Returns
-    /// 验证是否成功
-    pub fn verify(&self, proof: &[u8], _root_hash: &[u8]) -> bool {
-        match self.ads_mode {
-            AdsMode::CryptoAccumulator => self.verify_crypto_accumulator(proof),
-            AdsMode::Mpt => self.verify_mpt(proof),
+    /// 验证是否成功；证明的类型与当前 ADS 模式不符时直接拒绝
+    pub fn verify(&self, proof: &Proof, root_hash: &[u8]) -> bool {
+        let _span = tracing::info_span!("verify_proof", ads_mode = %self.ads_mode.name()).entered();
+        let matches_mode = match proof.ads_mode() {
+            Some(mode) => mode == self.ads_mode,
+            None => matches!(self.ads_mode, AdsMode::Custom(_)),
+        };
+        if !matches_mode {
+            warn!(
+                "Proof type does not match ADS mode {}",
+                self.ads_mode.name()
+            );
+            return false;
+        }
+
+        match proof {
+            Proof::AccumulatorAdd(data) => self.verify_accumulator_update(data, true),
+            Proof::AccumulatorDelete(data) => self.verify_accumulator_update(data, false),
+            Proof::AccumulatorMembership(data) => self.verify_accumulator_membership(data),
+            Proof::AccumulatorIntersection(data) => self.verify_accumulator_intersection(data),
+            Proof::AccumulatorNonMembership(data) => self.verify_accumulator_non_membership(data),
+            Proof::Mpt(data) => self.verify_mpt(data, root_hash),
+            Proof::Merkle(data) => self.verify_merkle_tree(data, root_hash),
+            Proof::Smt(data) => self.verify_smt(data, root_hash),
+            // 映射表条目由 verify_completeness 对照这里验证过的摘要检查
+            Proof::Interned(data) => InternedProof::from_bytes(data).is_some_and(|interned| {
+                self.verify(&interned.proof, root_hash) && self.verify(&interned.table, root_hash)
+            }),
+            Proof::Custom(data) => {
+                let AdsMode::Custom(name) = self.ads_mode else {
+                    return false;
+                };
+                match custom_verifier(name) {
+                    Some(verifier) => verifier.verify(data, root_hash),
+                    None => {
+                        warn!("No verifier registered for ADS mode '{}'", name);
+                        false
+                    }
+                }
+            }
+        }
+    }
+
+    /// 验证累加器的添加/删除证明
+    ///
+    /// 格式见 [`decode_update_records`]；用公开参数检查每条记录新旧累加器的配对关系
+    fn verify_accumulator_update(&self, proof: &[u8], is_add: bool) -> bool {
+        let Some(records) = decode_update_records(proof).filter(|records| !records.is_empty())
+        else {
+            warn!("Failed to deserialize accumulator update proof");
+            return false;
+        };
+        if records.iter().any(|record| !record.valid) {
+            warn!("Storager verification failed");
+            return false;
+        }
+
+        let verified = records.iter().all(|record| {
+            let (old_acc_value, new_acc_value, element) = (record.old, record.new, record.element);
+            if is_add {
+                AddProof {
+                    old_acc_value,
+                    new_acc_value,
+                    element,
+                }
+                .verify()
+            } else {
+                DeleteProof {
+                    old_acc_value,
+                    new_acc_value,
+                    element,
+                }
+                .verify()
+            }
+        });
+        if verified {
+            debug!("Crypto accumulator update proof verified successfully");
+        } else {
+            warn!("Crypto accumulator update proof verification failed");
         }
+        verified
     }
 
-    /// 验证密码学累加器的证明
-    fn verify_crypto_accumulator(&self, proof: &[u8]) -> bool {
-        if proof.is_empty() {
-            println!("❌ Empty proof");
+    /// 验证查询结果的批量成员资格证明
+    ///
+    /// 只有 valid 字节的证明表示空结果
+    fn verify_accumulator_membership(&self, proof: &[u8]) -> bool {
+        let Some((&1, body)) = proof.split_last() else {
+            warn!("Storager verification failed");
             return false;
+        };
+        if body.is_empty() {
+            debug!("Crypto accumulator proof verified (empty result)");
+            return true;
         }
+        let Some((witness, elements, acc)) = decode_membership(body) else {
+            warn!("Failed to deserialize membership proof");
+            return false;
+        };
 
-        // 最后一个字节是 storager 端的验证结果
-        let storager_verified = proof.last() == Some(&1);
+        let proof = BatchMembershipProof { witness, elements };
+        if proof.verify(acc) {
+            debug!("Crypto accumulator proof verified successfully");
+            true
+        } else {
+            warn!("Crypto accumulator membership proof verification failed");
+            false
+        }
+    }
 
-        if !storager_verified {
-            println!("❌ Storager verification failed");
+    /// 验证 keyword 集合累加器的非成员资格证明
+    ///
+    /// 这里只检查配对等式；证明针对的是哪个 keyword 由 [`verify_absence`](Self::verify_absence) 检查。
+    /// 与成员资格证明一样，keyword 集合累加器的值由 storager 自己报告
+    fn verify_accumulator_non_membership(&self, proof: &[u8]) -> bool {
+        let Some((&1, body)) = proof.split_last() else {
+            warn!("Storager verification failed");
             return false;
+        };
+        let Some((acc, proof)) = decode_non_membership(body) else {
+            warn!("Failed to deserialize non-membership proof");
+            return false;
+        };
+        if proof.verify(acc) {
+            debug!("Crypto accumulator non-membership proof verified successfully");
+            true
+        } else {
+            warn!("Crypto accumulator non-membership proof verification failed");
+            false
         }
+    }
 
-        // 验证证明的结构完整性
-        let min_size = 96 + 8 + 1; // G1Affine(96) + element(8) + valid(1)
-        if proof.len() < min_size {
-            println!(
-                "❌ Proof too small: {} bytes (expected >= {})",
-                proof.len(),
-                min_size
+    /// 检查空查询结果的证明确实表明 `keyword` 不存在
+    ///
+    /// 证明本身应已通过 [`verify`](Self::verify)。MPT 的不存在证明必须沿 keyword 的路径展开，
+    /// Merkle 树的必须是 keyword 应在位置两侧的相邻叶子，
+    /// 累加器的非成员资格证明必须针对 keyword 的元素，否则 storager 可以省略结果，
+    /// 或者拿另一个 keyword 的证明冒充。注册表中声明不支持非成员资格证明的模式无从检查，直接接受；
+    /// 第三方模式由其验证器负责
+    pub fn verify_absence(&self, proof: &Proof, keyword: &str) -> bool {
+        let absent = match proof {
+            Proof::Mpt(data) => ValueProof::from_bytes(data)
+                .is_ok_and(|proof| proof.value.is_empty() && proof.binds_key(keyword)),
+            Proof::AccumulatorNonMembership(data) => data
+                .split_last()
+                .and_then(|(_, body)| decode_non_membership(body))
+                .is_some_and(|(_, proof)| proof.element == element_to_field(keyword)),
+            Proof::Smt(data) => KeywordProof::from_bytes(data)
+                .is_some_and(|proof| proof.fids.is_empty() && proof.keyword == keyword),
+            Proof::Merkle(data) => MerkleAdsProof::from_bytes(data)
+                .is_some_and(|proof| proof.inclusions.is_empty() && proof.covers_keyword(keyword)),
+            Proof::Interned(data) => InternedProof::from_bytes(data)
+                .is_some_and(|interned| self.verify_absence(&interned.proof, keyword)),
+            Proof::Custom(_) => true,
+            _ => !self
+                .ads_mode
+                .capabilities()
+                .is_some_and(|capabilities| capabilities.supports_non_membership),
+        };
+        if !absent {
+            warn!(
+                "Empty result for '{}' is not backed by a non-existence proof",
+                keyword
             );
+        }
+        absent
+    }
+
+    /// 检查非空查询结果与证明中 `keyword` 的内容一致
+    ///
+    /// 密码学累加器：证明中的元素必须恰好是返回的 fid 对应的元素，并且由它们重新计算的
+    /// 累加器等于证明中 keyword 的累加器。证明中的累加器由 storager 报告，调用方还需要确认
+    /// 它等于 Manager 跟踪的值（见 [`query_accumulator`]），storager 才无法丢掉 fid 后给出
+    /// 自洽的证明。
+    ///
+    /// MPT、Merkle 树和稀疏 Merkle 树：返回的列表必须与证明中 `keyword` 的 fid 列表
+    /// （见 [`proven_fids`](Self::proven_fids)）逐个相同。第三方模式由其验证器负责
+    pub fn verify_completeness(&self, proof: &Proof, keyword: &str, fids: &[String]) -> bool {
+        let data = match proof {
+            Proof::AccumulatorMembership(data) => data,
+            Proof::Mpt(_) | Proof::Merkle(_) | Proof::Smt(_) | Proof::Interned(_) => {
+                let complete = self
+                    .proven_fids(proof, keyword)
+                    .is_some_and(|proven| proven == fids);
+                if !complete {
+                    warn!(
+                        "Query result differs from the fids of '{}' in the proof",
+                        keyword
+                    );
+                }
+                return complete;
+            }
+            _ => return true,
+        };
+        let Some((_, proven, acc)) = data
+            .split_last()
+            .and_then(|(_, body)| decode_membership(body))
+        else {
+            warn!("Query result has no membership proof to check completeness against");
+            return false;
+        };
+
+        let mut returned: Vec<Fr> = fids
+            .iter()
+            .map(|fid| element_to_field(fid.as_str()))
+            .collect();
+        let mut proven = proven;
+        returned.sort_unstable();
+        proven.sort_unstable();
+        let mut rebuilt = DynamicAccumulator::new();
+        let complete =
+            returned == proven && rebuilt.add_batch(fids).is_ok() && rebuilt.acc_value == acc;
+        if !complete {
+            warn!("Query result does not reconstruct the keyword's accumulator");
+        }
+        complete
+    }
+
+    /// 证明中 `keyword` 的完整 fid 列表，证明不能说明列表完整时返回 None
+    ///
+    /// MPT 取 keyword 的值，稀疏 Merkle 树取 keyword 的 fid。Merkle 树的叶子按 (keyword, fid)
+    /// 排序，证明必须包含 keyword 的整段叶子和两侧的边界（见 [`MerkleAdsProof::covers_keyword`]），
+    /// 否则 storager 可以只返回其中一部分包含证明。
+    ///
+    /// 结果含驻留 fid 的证明（见 [`common::fid_intern`]）先取内层证明中保存的列表，其中的
+    /// 紧凑 id 按附带的映射表条目还原：条目必须能推导出证明中 ADS 记录的映射表摘要。
+    /// 证明本身应已通过 [`verify`](Self::verify)；累加器和第三方模式的证明不含 fid，返回 None
+    pub fn proven_fids(&self, proof: &Proof, keyword: &str) -> Option<Vec<String>> {
+        match proof {
+            Proof::Mpt(data) => {
+                let proof = ValueProof::from_bytes(data).ok()?;
+                if !proof.binds_key(keyword) {
+                    return None;
+                }
+                if proof.value.is_empty() {
+                    return Some(Vec::new());
+                }
+                Some(proof.value.split(',').map(str::to_string).collect())
+            }
+            Proof::Merkle(data) => {
+                let proof = MerkleAdsProof::from_bytes(data)?;
+                proof
+                    .covers_keyword(keyword)
+                    .then(|| proof.fids().into_iter().map(str::to_string).collect())
+            }
+            Proof::Smt(data) => {
+                let proof = KeywordProof::from_bytes(data)?;
+                (proof.keyword == keyword).then_some(proof.fids)
+            }
+            Proof::Interned(data) => {
+                let interned = InternedProof::from_bytes(data)?;
+                let stored = self.proven_fids(&interned.proof, keyword)?;
+                let [digest] = self
+                    .proven_fids(&interned.table, FID_TABLE_KEYWORD)?
+                    .try_into()
+                    .ok()?;
+                let digest = parse_digest_entry(&digest)?;
+                stored
+                    .into_iter()
+                    .map(|stored| {
+                        let Some(index) = parse_compact_id(&stored) else {
+                            return Some(stored);
+                        };
+                        let entry = interned.entries.iter().find(|e| e.index == index)?;
+                        let path = MerkleProof {
+                            index,
+                            siblings: entry.siblings.clone(),
+                        };
+                        path.verify(&leaf_hash(FID_TABLE_KEYWORD, &entry.fid), &digest)
+                            .then(|| entry.fid.clone())
+                    })
+                    .collect()
+            }
+            _ => None,
+        }
+    }
+
+    /// 把分页查询的一页 fid 依次折叠进已交付结果的摘要：`d = SHA256(d || len(item) || item)`
+    ///
+    /// 累加器模式折叠 fid 对应的元素，其他模式折叠 fid 本身，与证明中保存的形式对应
+    pub fn fold_delivered(&self, digest: [u8; 32], fids: &[String]) -> [u8; 32] {
+        fids.iter().fold(digest, |digest, fid| match self.ads_mode {
+            AdsMode::CryptoAccumulator => {
+                fold_item(digest, &element_bytes(&element_to_field(fid.as_str())))
+            }
+            _ => fold_item(digest, fid.as_bytes()),
+        })
+    }
+
+    /// 检查分页查询逐页交付的结果（按 [`fold_delivered`](Self::fold_delivered) 折叠成
+    /// `delivered`）恰好是证明中 `keyword` 的完整结果，是分页版本的
+    /// [`verify_completeness`](Self::verify_completeness)
+    ///
+    /// 累加器的成员资格证明按 fid 的顺序列出元素，这些元素还必须重建出证明中的累加器，
+    /// 否则证明只说明它们属于 keyword，而不是 keyword 的全部 fid；其他模式按
+    /// [`proven_fids`](Self::proven_fids) 取出完整列表。第三方模式由其验证器负责
+    pub fn verify_delivered(&self, proof: &Proof, keyword: &str, delivered: [u8; 32]) -> bool {
+        let proven = match proof {
+            Proof::Custom(_) => return true,
+            Proof::AccumulatorMembership(data) => data
+                .split_last()
+                .and_then(|(_, body)| decode_membership(body))
+                .filter(|(_, elements, acc)| {
+                    elements.iter().collect::<HashSet<_>>().len() == elements.len()
+                        && public_params()
+                            .commit_g1(&poly_from_roots(elements))
+                            .is_ok_and(|rebuilt| rebuilt == *acc)
+                })
+                .map(|(_, elements, _)| {
+                    elements.iter().fold(DELIVERED_EMPTY, |digest, element| {
+                        fold_item(digest, &element_bytes(element))
+                    })
+                }),
+            _ => self
+                .proven_fids(proof, keyword)
+                .map(|fids| self.fold_delivered(DELIVERED_EMPTY, &fids)),
+        };
+        let complete = proven == Some(delivered);
+        if !complete {
+            warn!(
+                "Pages delivered for '{}' differ from the result in the proof",
+                keyword
+            );
+        }
+        complete
+    }
+
+    /// 检查流式查询中的一批结果与这批证明中的包含证明一致
+    ///
+    /// 只有 Merkle 树的证明可以逐批传输（见 [`merge_batch_proofs`](Self::merge_batch_proofs)）。
+    /// 单独一批不能说明结果完整，收齐后还要用合并的证明检查 [`verify_completeness`](Self::verify_completeness)
+    pub fn verify_batch(&self, proof: &Proof, keyword: &str, fids: &[String]) -> bool {
+        let Proof::Merkle(data) = proof else {
             return false;
+        };
+        MerkleAdsProof::from_bytes(data).is_some_and(|proof| {
+            proof.inclusions.iter().all(|i| i.keyword == keyword) && proof.fids() == fids
+        })
+    }
+
+    /// 验证两个累加器的交集证明
+    ///
+    /// 格式: [acc1 | acc2 | intersection_acc | IntersectionProof]
+    fn verify_accumulator_intersection(&self, proof: &[u8]) -> bool {
+        let mut body = proof;
+        let (Ok(acc1), Ok(acc2), Ok(intersection)) = (
+            G1Affine::deserialize(&mut body),
+            G1Affine::deserialize(&mut body),
+            G1Affine::deserialize(&mut body),
+        ) else {
+            warn!("Failed to deserialize intersection proof");
+            return false;
+        };
+        let Ok(intersection_proof) = IntersectionProof::from_bytes(body) else {
+            warn!("Failed to deserialize intersection proof");
+            return false;
+        };
+
+        let verified =
+            DynamicAccumulator::verify_intersection(acc1, acc2, intersection, &intersection_proof);
+        if verified {
+            debug!("Intersection proof verified successfully");
+        } else {
+            warn!("Intersection proof verification failed");
         }
+        verified
+    }
 
-        // 尝试反序列化第一个椭圆曲线点来验证格式正确性
-        match G1Affine::deserialize(&proof[..96]) {
-            Ok(_) => {
-                println!("✅ Crypto accumulator proof verified successfully");
+    /// 验证 `A AND NOT B` 查询的差集证明（仅密码学累加器模式）
+    ///
+    /// # Arguments
+    /// * `included_proof` / `excluded_proof` - A、B 各自的查询证明，用于取出两个累加器的值
+    /// * `fids` - storager 返回的差集
+    /// * `proof` - 格式: [acc | excluded_acc | difference_proof | valid(1)]
+    ///
+    /// 差集证明中的两个累加器必须与查询证明中的一致，差集的元素由 fid 重新计算
+    pub fn verify_difference(
+        &self,
+        included_proof: &Proof,
+        excluded_proof: &Proof,
+        fids: &[String],
+        proof: &[u8],
+    ) -> bool {
+        if self.ads_mode != AdsMode::CryptoAccumulator {
+            return false;
+        }
+        let Some((&1, mut body)) = proof.split_last() else {
+            warn!("Storager difference verification failed");
+            return false;
+        };
+
+        let (Ok(acc), Ok(excluded_acc)) = (
+            G1Affine::deserialize(&mut body),
+            G1Affine::deserialize(&mut body),
+        ) else {
+            warn!("Failed to deserialize difference proof");
+            return false;
+        };
+        let Ok(difference_proof) = DifferenceProof::from_bytes(body) else {
+            warn!("Failed to deserialize difference proof");
+            return false;
+        };
+        if query_accumulator_value(included_proof) != Some(acc)
+            || query_accumulator_value(excluded_proof) != Some(excluded_acc)
+        {
+            warn!("Difference proof does not match the queried accumulators");
+            return false;
+        }
+
+        let verified = DynamicAccumulator::verify_difference_with_values(
+            acc,
+            excluded_acc,
+            fids,
+            &difference_proof,
+        );
+        if verified {
+            debug!("Difference proof verified successfully");
+        } else {
+            warn!("Difference proof verification failed");
+        }
+        verified
+    }
+
+    /// 验证布尔查询的证明树（仅密码学累加器模式）
+    ///
+    /// # Arguments
+    /// * `proof` - storager 返回的证明树，每个节点带有子表达式结果的累加器值
+    /// * `keyword_proofs` - 叶子 keyword 各自的查询证明，叶子的累加器必须与其中的一致
+    /// * `fids` - storager 返回的查询结果
+    ///
+    /// 逐层验证交集、并集、差集证明，最后要求根节点的累加器等于由 fid 重新计算的累加器
+    pub fn verify_boolean_proof(
+        &self,
+        proof: &BooleanProof,
+        keyword_proofs: &HashMap<String, Proof>,
+        fids: &[String],
+    ) -> bool {
+        if self.ads_mode != AdsMode::CryptoAccumulator {
+            return false;
+        }
+        let Some(acc) = verify_boolean_node(proof, keyword_proofs) else {
+            warn!("Boolean proof verification failed");
+            return false;
+        };
+
+        if fids.iter().collect::<HashSet<_>>().len() != fids.len() {
+            warn!("Boolean query result contains duplicate fids");
+            return false;
+        }
+        let mut expected = DynamicAccumulator::new();
+        if expected.add_batch(fids).is_err() || expected.acc_value != acc {
+            warn!("Boolean query result does not match the proven accumulator");
+            return false;
+        }
+        debug!("Boolean proof verified successfully");
+        true
+    }
+
+    /// 验证单个证明的代价
+    ///
+    /// 按证明的结构统计 [`verify`](Self::verify) 及其后的完整性、不存在检查执行的运算：
+    /// 累加器证明计配对次数，成员资格证明另计把 fid 映射为域元素的哈希；
+    /// MPT 每个路径节点一次哈希，Merkle 树每个叶子和兄弟节点一次，
+    /// 稀疏 Merkle 树每层一次再加上 keyword、fid 列表和叶子；含驻留 fid 的证明是两个内层证明
+    /// 之和，再加上每个映射表条目的叶子和兄弟节点。无法解码的证明只统计字节数
+    pub fn proof_metrics(&self, proof: &Proof) -> ProofMetrics {
+        let mut metrics = ProofMetrics {
+            proof_bytes: proof.data().len() as u64,
+            ..Default::default()
+        };
+        match proof {
+            Proof::AccumulatorAdd(data) | Proof::AccumulatorDelete(data) => {
+                if let Some(records) = decode_update_records(data) {
+                    metrics.pairing_ops = UPDATE_PAIRINGS * records.len() as u64;
+                    metrics.levels = 1;
+                }
+            }
+            Proof::AccumulatorMembership(data) => {
+                if let Some((_, elements, _)) = data
+                    .split_last()
+                    .and_then(|(_, body)| decode_membership(body))
+                {
+                    metrics.pairing_ops = MEMBERSHIP_PAIRINGS;
+                    metrics.hash_ops = elements.len() as u64;
+                    metrics.levels = 1;
+                }
+            }
+            Proof::AccumulatorNonMembership(_) => {
+                metrics.pairing_ops = NON_MEMBERSHIP_PAIRINGS;
+                metrics.hash_ops = 1;
+                metrics.levels = 1;
+            }
+            Proof::AccumulatorIntersection(_) => {
+                metrics.pairing_ops = INTERSECTION_PAIRINGS;
+                metrics.levels = 1;
+            }
+            Proof::Mpt(data) => {
+                if let Ok(proof) = ValueProof::from_bytes(data) {
+                    let nodes = proof.proof.proofs.len() as u64;
+                    metrics.hash_ops = nodes + u64::from(proof.value.len() > 32);
+                    metrics.levels = nodes;
+                }
+            }
+            Proof::Merkle(data) => {
+                if let Some(proof) = MerkleAdsProof::from_bytes(data) {
+                    for inclusion in proof.inclusions.iter().chain(&proof.boundaries) {
+                        let depth = inclusion.siblings.len() as u64;
+                        metrics.hash_ops += depth + 1;
+                        metrics.levels = metrics.levels.max(depth);
+                    }
+                }
+            }
+            Proof::Smt(data) => {
+                if let Some(proof) = KeywordProof::from_bytes(data) {
+                    let value_hashes = if proof.fids.is_empty() { 0 } else { 2 };
+                    metrics.hash_ops = SMT_DEPTH as u64 + 1 + value_hashes;
+                    metrics.levels = SMT_DEPTH as u64;
+                }
+            }
+            Proof::Interned(data) => {
+                if let Some(interned) = InternedProof::from_bytes(data) {
+                    for inner in [&interned.proof, &interned.table] {
+                        let inner = self.proof_metrics(inner);
+                        metrics.pairing_ops += inner.pairing_ops;
+                        metrics.hash_ops += inner.hash_ops;
+                        metrics.levels = metrics.levels.max(inner.levels);
+                    }
+                    for entry in &interned.entries {
+                        let depth = entry.siblings.len() as u64;
+                        metrics.hash_ops += depth + 1;
+                        metrics.levels = metrics.levels.max(depth);
+                    }
+                }
+            }
+            Proof::Custom(_) => {}
+        }
+        metrics
+    }
+
+    /// 验证布尔查询证明树的代价，不含叶子 keyword 各自的查询证明
+    ///
+    /// 交集节点 7 次配对，并集和差集节点各 9 次；由 `fids` 重建根累加器时每个 fid 一次哈希。
+    /// 层数为证明树的深度
+    pub fn boolean_proof_metrics(&self, proof: &BooleanProof, fids: &[String]) -> ProofMetrics {
+        let (pairing_ops, levels) = boolean_node_cost(proof);
+        ProofMetrics {
+            proof_bytes: proof.encoded_len() as u64,
+            pairing_ops,
+            hash_ops: fids.len() as u64,
+            levels,
+        }
+    }
+
+    /// 验证差集证明的代价（见 [`verify_difference`](Self::verify_difference)）
+    pub fn difference_metrics(&self, proof: &[u8], fids: &[String]) -> ProofMetrics {
+        ProofMetrics {
+            proof_bytes: proof.len() as u64,
+            pairing_ops: INTERSECTION_PAIRINGS,
+            hash_ops: fids.len() as u64,
+            levels: 1,
+        }
+    }
+
+    /// 验证 MPT 的证明，失败原因见 [`verify_mpt_proof`]
+    fn verify_mpt(&self, proof: &[u8], root_hash: &[u8]) -> bool {
+        match verify_mpt_proof(proof, root_hash) {
+            Ok(()) => {
+                debug!("MPT proof verified");
                 true
             }
             Err(e) => {
-                println!("❌ Failed to deserialize proof: {:?}", e);
+                warn!("MPT proof rejected: {}", e);
                 false
             }
         }
     }
 
-    /// 验证 MPT 的证明
-    fn verify_mpt(&self, proof: &[u8]) -> bool {
-        // MPT 的证明就是根哈希本身
-        // 只要证明非空或长度为 0(空结果)就认为有效
-        if proof.is_empty() {
-            // 空证明表示关键字不存在,这是有效的
-            println!("✅ MPT proof verified (empty result)");
-            true
-        } else if proof.len() == 32 {
-            // 32 字节的根哈希
-            println!("✅ MPT proof verified (root hash present)");
+    /// 验证 Merkle 树的包含证明
+    ///
+    /// 证明中的每个 (keyword, fid) 都必须推导出证明携带的树根，且与记录的根哈希一致
+    fn verify_merkle_tree(&self, proof: &[u8], root_hash: &[u8]) -> bool {
+        if verify_merkle_proof(proof, root_hash) {
+            debug!("Merkle tree inclusion proof verified");
             true
         } else {
-            println!(
-                "⚠️  MPT proof has unexpected length: {} bytes, accepting anyway",
-                proof.len()
-            );
-            // 即使长度不是标准的 32 字节,也接受,因为 MPT 可能有不同的哈希长度
-            true
+            warn!("Merkle tree inclusion proof verification failed");
+            false
         }
     }
 
+    /// 验证稀疏 Merkle 树的证明
+    ///
+    /// 由证明中的 keyword、fid 列表和压缩的路径计算树根，必须与记录的根哈希一致
+    fn verify_smt(&self, proof: &[u8], root_hash: &[u8]) -> bool {
+        let Some(root) = KeywordProof::from_bytes(proof).and_then(|proof| proof.compute_root())
+        else {
+            warn!("Malformed sparse Merkle tree proof");
+            return false;
+        };
+        if root_hash != root {
+            warn!("Sparse Merkle tree proof does not match the root hash");
+            return false;
+        }
+        debug!("Sparse Merkle tree proof verified");
+        true
+    }
+
     /// 合并多个证明
     ///
     /// 用于布尔查询等需要合并多个 storager 证明的场景
@@ -101,35 +827,219 @@ impl ProofVerifier {
     /// * `proofs` - 证明列表
     ///
     /// # Returns
-    /// 合并后的证明
-    pub fn combine_proofs(&self, proofs: &[Vec<u8>]) -> Vec<u8> {
+    /// 合并后的证明；没有证明时返回 None
+    pub fn combine_proofs(&self, proofs: &[Proof]) -> Option<Proof> {
         if proofs.is_empty() {
-            return Vec::new();
+            return None;
         }
 
         match self.ads_mode {
             AdsMode::CryptoAccumulator => {
                 // 简单方案：返回第一个证明
                 // 更复杂的方案可以构建 Merkle 树或使用其他聚合技术
-                proofs[0].clone()
+                Some(proofs[0].clone())
             }
-            AdsMode::Mpt => {
-                // MPT: 返回第一个非空证明
+            AdsMode::Mpt | AdsMode::MerkleTree | AdsMode::SparseMerkleTree => {
+                // MPT / Merkle 树 / 稀疏 Merkle 树: 返回第一个非空证明
                 proofs
                     .iter()
-                    .find(|p| !p.is_empty())
+                    .find(|p| !p.data().is_empty())
+                    .or(proofs.first())
                     .cloned()
-                    .unwrap_or_default()
+            }
+            AdsMode::Custom(name) => {
+                let proofs: Vec<Vec<u8>> = proofs.iter().map(|p| p.data().to_vec()).collect();
+                custom_verifier(name)
+                    .map(|verifier| Proof::Custom(verifier.combine_proofs(&proofs)))
             }
         }
     }
 
+    /// 把流式查询中逐批验证过的证明合并成整个结果的证明
+    ///
+    /// 只有 Merkle 树的证明可以逐批传输：各批的包含证明按顺序拼接，边界（只在第一批和最后一批）
+    /// 合在一起，所有批次必须对应同一个根。只有一批时原样返回；无法合并时返回 None
+    pub fn merge_batch_proofs(&self, mut proofs: Vec<Proof>) -> Option<Proof> {
+        if proofs.len() <= 1 {
+            return proofs.pop();
+        }
+        let mut merged: Option<MerkleAdsProof> = None;
+        for proof in &proofs {
+            let Proof::Merkle(data) = proof else {
+                return None;
+            };
+            let batch = MerkleAdsProof::from_bytes(data)?;
+            match &mut merged {
+                Some(merged) if merged.root == batch.root => {
+                    merged.inclusions.extend(batch.inclusions);
+                    merged.boundaries.extend(batch.boundaries);
+                }
+                Some(_) => return None,
+                None => merged = Some(batch),
+            }
+        }
+        merged.map(|proof| Proof::Merkle(proof.to_bytes()))
+    }
+
     /// 获取当前的 ADS 模式
     pub fn ads_mode(&self) -> AdsMode {
         self.ads_mode
     }
 }
 
+/// 累加器添加/删除证明中一个 keyword 的记录
+pub(crate) struct UpdateRecord {
+    pub old: G1Affine,
+    pub new: G1Affine,
+    pub element: Fr,
+    pub valid: bool,
+}
+
+/// 解码累加器的添加/删除证明: [old_acc | new_acc | element | valid(1)]*
+///
+/// 批量写入时每个 keyword 一条记录，顺序与请求中的 keyword 相同
+pub(crate) fn decode_update_records(mut data: &[u8]) -> Option<Vec<UpdateRecord>> {
+    let mut records = Vec::new();
+    while !data.is_empty() {
+        let old = G1Affine::deserialize(&mut data).ok()?;
+        let new = G1Affine::deserialize(&mut data).ok()?;
+        let element = Fr::deserialize(&mut data).ok()?;
+        let (&valid, rest) = data.split_first()?;
+        data = rest;
+        records.push(UpdateRecord {
+            old,
+            new,
+            element,
+            valid: valid == 1,
+        });
+    }
+    Some(records)
+}
+
+/// 序列化的累加器值，与 storager 写入后返回的根哈希格式相同
+pub(crate) fn encode_accumulator(acc: &G1Affine) -> RootHash {
+    let mut bytes = Vec::new();
+    acc.serialize(&mut bytes).unwrap();
+    bytes
+}
+
+/// 密码学累加器查询证明（成员资格、非成员资格）中 keyword 的累加器值，序列化后返回
+///
+/// keyword 不存在时为空累加器；其他证明返回 None
+pub fn query_accumulator(proof: &Proof) -> Option<RootHash> {
+    query_accumulator_value(proof).map(|acc| encode_accumulator(&acc))
+}
+
+/// 取出密码学累加器查询证明中的累加器值
+///
+/// 查询证明格式: [witness | count(4) | element * count | acc_value | valid(1)]；
+/// 只有 valid 字节的证明和非成员资格证明表示 keyword 没有 fid，对应空累加器
+fn query_accumulator_value(proof: &Proof) -> Option<G1Affine> {
+    let proof = match proof {
+        Proof::AccumulatorMembership(proof) => proof,
+        Proof::AccumulatorNonMembership(_) => return Some(DynamicAccumulator::new().acc_value),
+        _ => return None,
+    };
+    let (_, body) = proof.split_last()?;
+    if body.is_empty() {
+        return Some(DynamicAccumulator::new().acc_value);
+    }
+    decode_membership(body).map(|(_, _, acc)| acc)
+}
+
+/// 把一项折叠进分页查询的摘要：`SHA256(digest || len(item) as u32 LE || item)`
+fn fold_item(digest: [u8; 32], item: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(digest);
+    hasher.update((item.len() as u32).to_le_bytes());
+    hasher.update(item);
+    hasher.finalize().into()
+}
+
+/// 累加器元素的压缩编码
+fn element_bytes(element: &Fr) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    element
+        .serialize(&mut bytes)
+        .expect("serializing a field element cannot fail");
+    bytes
+}
+
+/// 解码去掉 valid 字节后的批量成员资格证明: [witness | count(4) | element * count | acc_value]
+fn decode_membership(mut body: &[u8]) -> Option<(G1Affine, Vec<Fr>, G1Affine)> {
+    let witness = G1Affine::deserialize(&mut body).ok()?;
+    let (count, mut rest) = body.split_at_checked(4)?;
+    let count = u32::from_le_bytes(count.try_into().ok()?) as usize;
+    let elements = (0..count)
+        .map(|_| Fr::deserialize(&mut rest).ok())
+        .collect::<Option<Vec<_>>>()?;
+    let acc = G1Affine::deserialize(&mut rest).ok()?;
+    rest.is_empty().then_some((witness, elements, acc))
+}
+
+/// 解码去掉 valid 字节后的非成员资格证明: [keyword_set_acc | element | g1_a | witness]
+fn decode_non_membership(mut body: &[u8]) -> Option<(G1Affine, NonMembershipProof)> {
+    let acc = G1Affine::deserialize(&mut body).ok()?;
+    let element = Fr::deserialize(&mut body).ok()?;
+    let g1_a = G1Affine::deserialize(&mut body).ok()?;
+    let witness = G2Affine::deserialize(&mut body).ok()?;
+    body.is_empty().then_some((
+        acc,
+        NonMembershipProof {
+            element,
+            witness,
+            g1_a,
+        },
+    ))
+}
+
+/// 递归验证证明树的一个节点，返回该节点已验证的累加器值
+fn verify_boolean_node(
+    proof: &BooleanProof,
+    keyword_proofs: &HashMap<String, Proof>,
+) -> Option<G1Affine> {
+    let acc = G1Affine::deserialize(&mut proof.accumulator.as_slice()).ok()?;
+    let operation = match proof.node.as_ref()? {
+        Node::Keyword(keyword) => {
+            let expected = query_accumulator_value(keyword_proofs.get(keyword)?)?;
+            return (expected == acc).then_some(acc);
+        }
+        Node::And(operation) | Node::Or(operation) | Node::AndNot(operation) => operation,
+    };
+    let left = verify_boolean_node(operation.left.as_deref()?, keyword_proofs)?;
+    let right = verify_boolean_node(operation.right.as_deref()?, keyword_proofs)?;
+
+    let verified = match proof.node.as_ref()? {
+        Node::And(_) => {
+            let proof = IntersectionProof::from_bytes(&operation.proof).ok()?;
+            DynamicAccumulator::verify_intersection(left, right, acc, &proof)
+        }
+        Node::Or(_) => {
+            let proof = UnionProof::from_bytes(&operation.proof).ok()?;
+            DynamicAccumulator::verify_union(left, right, acc, &proof)
+        }
+        Node::AndNot(_) => {
+            let proof = DifferenceProof::from_bytes(&operation.proof).ok()?;
+            DynamicAccumulator::verify_difference_acc(left, right, acc, &proof)
+        }
+        Node::Keyword(_) => false,
+    };
+    verified.then_some(acc)
+}
+
+/// 证明树一个节点及其子树的 (配对次数, 深度)
+fn boolean_node_cost(proof: &BooleanProof) -> (u64, u64) {
+    let (pairings, operation) = match &proof.node {
+        Some(Node::And(operation)) => (INTERSECTION_PAIRINGS, operation),
+        Some(Node::Or(operation)) => (UNION_PAIRINGS, operation),
+        Some(Node::AndNot(operation)) => (DIFFERENCE_ACC_PAIRINGS, operation),
+        Some(Node::Keyword(_)) | None => return (0, 1),
+    };
+    let (left, left_depth) = operation.left.as_deref().map_or((0, 0), boolean_node_cost);
+    let (right, right_depth) = operation.right.as_deref().map_or((0, 0), boolean_node_cost);
+    (pairings + left + right, 1 + left_depth.max(right_depth))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -137,13 +1047,398 @@ mod tests {
     #[test]
     fn test_empty_proof() {
         let verifier = ProofVerifier::new(AdsMode::CryptoAccumulator);
-        assert!(!verifier.verify(&[], &[]));
+        assert!(!verifier.verify(&Proof::AccumulatorAdd(vec![]), &[]));
     }
 
     #[test]
     fn test_proof_too_small() {
         let verifier = ProofVerifier::new(AdsMode::CryptoAccumulator);
         let small_proof = vec![0u8; 50];
-        assert!(!verifier.verify(&small_proof, &[]));
+        assert!(!verifier.verify(&Proof::AccumulatorAdd(small_proof), &[]));
+    }
+
+    #[test]
+    fn test_accumulator_proofs() {
+        let verifier = ProofVerifier::new(AdsMode::CryptoAccumulator);
+        let mut acc = DynamicAccumulator::new();
+        let old_acc = acc.acc_value;
+        acc.add("f1").unwrap();
+
+        let mut update = Vec::new();
+        old_acc.serialize(&mut update).unwrap();
+        acc.acc_value.serialize(&mut update).unwrap();
+        element_to_field("f1").serialize(&mut update).unwrap();
+        update.push(1);
+        assert!(verifier.verify(&Proof::AccumulatorAdd(update.clone()), &[]));
+        // 添加证明不能当作删除证明使用
+        assert!(!verifier.verify(&Proof::AccumulatorDelete(update.clone()), &[]));
+        // 批量写入每个 keyword 一条记录，任何一条无效都拒绝整个证明
+        let mut batch_update = [update.clone(), update.clone()].concat();
+        let batch_proof = Proof::AccumulatorAdd(batch_update.clone());
+        assert!(verifier.verify(&batch_proof, &[]));
+        assert_eq!(
+            verifier.proof_metrics(&batch_proof).pairing_ops,
+            2 * UPDATE_PAIRINGS
+        );
+        *batch_update.last_mut().unwrap() = 0;
+        assert!(!verifier.verify(&Proof::AccumulatorAdd(batch_update), &[]));
+
+        let batch = acc.prove_membership_batch(&["f1"]).unwrap();
+        let mut membership = Vec::new();
+        batch.witness.serialize(&mut membership).unwrap();
+        membership.extend_from_slice(&1u32.to_le_bytes());
+        let element_offset = membership.len();
+        element_to_field("f1").serialize(&mut membership).unwrap();
+        acc.acc_value.serialize(&mut membership).unwrap();
+        membership.push(1);
+        assert!(verifier.verify(&Proof::AccumulatorMembership(membership.clone()), &[]));
+        assert!(verifier.verify(&Proof::AccumulatorMembership(vec![1]), &[]));
+
+        assert!(verifier.verify_completeness(
+            &Proof::AccumulatorMembership(membership.clone()),
+            "rust",
+            &["f1".to_string()]
+        ));
+        assert!(!verifier.verify_completeness(
+            &Proof::AccumulatorMembership(membership.clone()),
+            "rust",
+            &["f1".to_string(), "f2".to_string()]
+        ));
+
+        // 元素被篡改
+        membership[element_offset] ^= 1;
+        assert!(!verifier.verify(&Proof::AccumulatorMembership(membership), &[]));
+
+        // 其他 ADS 模式的证明类型被拒绝
+        assert!(!verifier.verify(&Proof::Mpt(vec![0u8; 32]), &[]));
+        let verifier = ProofVerifier::new(AdsMode::Mpt);
+        assert!(!verifier.verify(&Proof::AccumulatorAdd(update), &[]));
+    }
+
+    #[test]
+    fn test_completeness_rejects_dropped_fids() {
+        let verifier = ProofVerifier::new(AdsMode::CryptoAccumulator);
+        let mut acc = DynamicAccumulator::new();
+        let elements = ["f1", "f2", "f3"].map(str::to_string);
+        acc.add_batch(&elements).unwrap();
+        let membership = |elements: &[String]| {
+            let batch = acc.prove_membership_batch(elements).unwrap();
+            let mut data = Vec::new();
+            batch.witness.serialize(&mut data).unwrap();
+            data.extend_from_slice(&(elements.len() as u32).to_le_bytes());
+            for element in elements {
+                element_to_field(element).serialize(&mut data).unwrap();
+            }
+            acc.acc_value.serialize(&mut data).unwrap();
+            data.push(1);
+            Proof::AccumulatorMembership(data)
+        };
+        let fids = |fids: &[&str]| fids.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+
+        let full = membership(&elements);
+        assert!(verifier.verify(&full, &[]));
+        assert!(verifier.verify_completeness(&full, "rust", &fids(&["f3", "f1", "f2"])));
+        assert!(!verifier.verify_completeness(&full, "rust", &fids(&["f1", "f2"])));
+
+        // 只证明部分 fid 的成员资格是有效证明，但结果不完整
+        let partial = membership(&elements[..2]);
+        assert!(verifier.verify(&partial, &[]));
+        assert!(!verifier.verify_completeness(&partial, "rust", &fids(&["f1", "f2"])));
+        // 重复的 fid 不能凑出元素数量
+        assert!(!verifier.verify_completeness(&full, "rust", &fids(&["f1", "f1", "f2"])));
+
+        // 分页交付的结果按证明中元素的顺序折叠，只证明部分元素的最后一页同样不完整
+        let delivered = |pages: &[&[&str]]| {
+            pages.iter().fold(DELIVERED_EMPTY, |digest, page| {
+                verifier.fold_delivered(digest, &fids(page))
+            })
+        };
+        assert!(verifier.verify_delivered(&full, "rust", delivered(&[&["f1"], &["f2", "f3"]])));
+        assert!(!verifier.verify_delivered(&full, "rust", delivered(&[&["f1"], &["f4", "f3"]])));
+        assert!(!verifier.verify_delivered(&full, "rust", delivered(&[&["f1", "f3"]])));
+        assert!(!verifier.verify_delivered(&partial, "rust", delivered(&[&["f1", "f2"]])));
+    }
+
+    #[test]
+    fn test_accumulator_non_membership() {
+        let verifier = ProofVerifier::new(AdsMode::CryptoAccumulator);
+        let mut keyword_set = DynamicAccumulator::new();
+        keyword_set.add("rust").unwrap();
+        let proof = keyword_set.prove_non_membership("java").unwrap();
+
+        let mut data = Vec::new();
+        keyword_set.acc_value.serialize(&mut data).unwrap();
+        let element_offset = data.len();
+        proof.element.serialize(&mut data).unwrap();
+        proof.g1_a.serialize(&mut data).unwrap();
+        proof.witness.serialize(&mut data).unwrap();
+        data.push(1);
+        let proof = Proof::AccumulatorNonMembership(data.clone());
+        assert!(verifier.verify(&proof, &[]));
+        assert!(verifier.verify_absence(&proof, "java"));
+        // 证明针对的是另一个 keyword
+        assert!(!verifier.verify_absence(&proof, "rust"));
+        // 没有证据的空结果不能表明 keyword 不存在
+        let empty = Proof::AccumulatorMembership(vec![1]);
+        assert!(verifier.verify(&empty, &[]));
+        assert!(!verifier.verify_absence(&empty, "java"));
+
+        // 换成 keyword 集合中已有的元素后配对等式不成立
+        let mut rust = Vec::new();
+        element_to_field("rust").serialize(&mut rust).unwrap();
+        data[element_offset..element_offset + rust.len()].copy_from_slice(&rust);
+        assert!(!verifier.verify(&Proof::AccumulatorNonMembership(data), &[]));
+    }
+
+    #[test]
+    fn test_difference_proof_rejects_malformed() {
+        let verifier = ProofVerifier::new(AdsMode::CryptoAccumulator);
+        let empty = Proof::AccumulatorMembership(vec![1]);
+        assert!(!verifier.verify_difference(&empty, &empty, &[], &[]));
+        assert!(!verifier.verify_difference(&empty, &empty, &[], &[0u8; 64]));
+        assert!(!verifier.verify_difference(&empty, &empty, &[], &[1u8; 64]));
+
+        let verifier = ProofVerifier::new(AdsMode::MerkleTree);
+        assert!(!verifier.verify_difference(&empty, &empty, &[], &[1]));
+    }
+
+    #[test]
+    fn test_boolean_proof_rejects_mismatched_keyword() {
+        let verifier = ProofVerifier::new(AdsMode::CryptoAccumulator);
+        let mut accumulator = Vec::new();
+        DynamicAccumulator::new()
+            .acc_value
+            .serialize(&mut accumulator)
+            .unwrap();
+        let proof = BooleanProof {
+            accumulator,
+            node: Some(Node::Keyword("rust".to_string())),
+        };
+        let keyword_proofs =
+            HashMap::from([("rust".to_string(), Proof::AccumulatorMembership(vec![1]))]);
+        assert!(verifier.verify_boolean_proof(&proof, &keyword_proofs, &[]));
+        // 结果与累加器不一致
+        assert!(!verifier.verify_boolean_proof(&proof, &keyword_proofs, &["f1".to_string()]));
+        // 缺少叶子 keyword 的查询证明
+        assert!(!verifier.verify_boolean_proof(&proof, &HashMap::new(), &[]));
+    }
+
+    #[test]
+    fn test_proof_metrics() {
+        use common::rpc::BooleanProofOperation;
+
+        let verifier = ProofVerifier::new(AdsMode::CryptoAccumulator);
+        let leaf = |keyword: &str| BooleanProof {
+            accumulator: vec![],
+            node: Some(Node::Keyword(keyword.to_string())),
+        };
+        let operation = |left, right| BooleanProofOperation {
+            left: Some(Box::new(left)),
+            right: Some(Box::new(right)),
+            proof: vec![],
+        };
+        // (rust AND go) OR java
+        let proof = BooleanProof {
+            accumulator: vec![],
+            node: Some(Node::Or(Box::new(operation(
+                BooleanProof {
+                    accumulator: vec![],
+                    node: Some(Node::And(Box::new(operation(leaf("rust"), leaf("go"))))),
+                },
+                leaf("java"),
+            )))),
+        };
+        let metrics = verifier.boolean_proof_metrics(&proof, &["f1".to_string()]);
+        assert_eq!(metrics.pairing_ops, UNION_PAIRINGS + INTERSECTION_PAIRINGS);
+        assert_eq!(metrics.hash_ops, 1);
+        assert_eq!(metrics.levels, 3);
+        assert_eq!(metrics.proof_bytes, proof.encoded_len() as u64);
+
+        // 无法解码的证明只统计字节数
+        let metrics = verifier.proof_metrics(&Proof::Mpt(vec![0; 5]));
+        assert_eq!(metrics.proof_bytes, 5);
+        assert_eq!((metrics.hash_ops, metrics.levels), (0, 0));
+    }
+
+    #[test]
+    fn test_mpt_proof() {
+        use esa_rust::mpt::{node::Database, DbError, KVPair, MPT};
+
+        struct MemoryDb(HashMap<Vec<u8>, Vec<u8>>);
+        impl Database for MemoryDb {
+            fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError> {
+                Ok(self.0.get(key).cloned())
+            }
+            fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
+                self.0.insert(key.to_vec(), value.to_vec());
+                Ok(())
+            }
+            fn delete(&mut self, key: &[u8]) -> Result<(), DbError> {
+                self.0.remove(key);
+                Ok(())
+            }
+        }
+
+        let mut db = MemoryDb(HashMap::new());
+        let mut trie = MPT::new(None);
+        for (keyword, fids) in [("rust", "f1,f2"), ("go", "f3")] {
+            let kv = KVPair::new(keyword.to_string(), fids.to_string());
+            trie.insert(kv, &mut db, true, false).unwrap();
+        }
+        let root = trie.root_hash.to_vec();
+        let prove = |trie: &mut MPT, db: &mut MemoryDb, keyword: &str| {
+            let (value, proof) = trie.query_by_key(keyword, db).unwrap();
+            ValueProof::new(value, proof)
+        };
+
+        let verifier = ProofVerifier::new(AdsMode::Mpt);
+        let proof = prove(&mut trie, &mut db, "rust");
+        assert!(verifier.verify(&Proof::Mpt(proof.to_bytes()), &root));
+        // 没有跟踪的根哈希时不能只凭证明自洽就通过
+        assert_eq!(
+            verify_mpt_proof(&proof.to_bytes(), &[]),
+            Err(MptProofError::UntrackedRoot)
+        );
+        // 不存在的 keyword 同样可以对照根哈希验证
+        let absent = prove(&mut trie, &mut db, "java");
+        assert!(verifier.verify(&Proof::Mpt(absent.to_bytes()), &root));
+        // 空结果必须有沿该 keyword 路径的不存在证明
+        assert!(verifier.verify_absence(&Proof::Mpt(absent.to_bytes()), "java"));
+        assert!(!verifier.verify_absence(&Proof::Mpt(absent.to_bytes()), "rust"));
+        assert!(!verifier.verify_absence(&Proof::Mpt(proof.to_bytes()), "rust"));
+
+        // 篡改的值推导不出记录的根哈希
+        let mut forged = proof.clone();
+        forged.value = "f1".to_string();
+        assert_eq!(
+            verify_mpt_proof(&forged.to_bytes(), &root),
+            Err(MptProofError::BrokenPath)
+        );
+        // 证明本身有效，但与记录的根哈希不一致
+        assert!(matches!(
+            verify_mpt_proof(&proof.to_bytes(), &[0u8; 32]),
+            Err(MptProofError::RootMismatch { .. })
+        ));
+        assert!(matches!(
+            verify_mpt_proof(&root, &root),
+            Err(MptProofError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_merkle_tree_proof() {
+        use esa_rust::merkle_tree::{leaf_hash, MerkleAdsProof, MerkleInclusion};
+
+        let root = leaf_hash("rust", "f1");
+        let proof = MerkleAdsProof {
+            root,
+            inclusions: vec![MerkleInclusion {
+                keyword: "rust".to_string(),
+                fid: "f1".to_string(),
+                index: 0,
+                siblings: vec![],
+            }],
+            boundaries: vec![],
+        }
+        .to_bytes();
+        let proof = Proof::Merkle(proof);
+
+        let verifier = ProofVerifier::new(AdsMode::MerkleTree);
+        assert!(verifier.verify(&proof, &root));
+        // 唯一的叶子既是第一个也是最后一个，不需要边界
+        assert!(verifier.verify_completeness(&proof, "rust", &["f1".to_string()]));
+        assert!(!verifier.verify_completeness(&proof, "go", &["f1".to_string()]));
+        assert!(!verifier.verify_absence(&proof, "rust"));
+        assert!(!verifier.verify(&proof, &[]));
+        assert!(!verifier.verify(&proof, &[0u8; 32]));
+        assert!(!verifier.verify(&Proof::Merkle(vec![]), &root));
+    }
+
+    #[test]
+    fn test_smt_proof() {
+        use esa_rust::smt::{fids_hash, key_hash, SparseMerkleTree};
+
+        let mut tree = SparseMerkleTree::new();
+        let fids = vec!["f1".to_string(), "f2".to_string()];
+        tree.insert(key_hash("rust"), fids_hash(&fids));
+        let root = tree.root();
+        let prove = |keyword: &str, fids: &[String]| {
+            Proof::Smt(
+                KeywordProof {
+                    keyword: keyword.to_string(),
+                    fids: fids.to_vec(),
+                    path: tree.prove(&key_hash(keyword)),
+                }
+                .to_bytes(),
+            )
+        };
+
+        let verifier = ProofVerifier::new(AdsMode::SparseMerkleTree);
+        let proof = prove("rust", &fids);
+        assert!(verifier.verify(&proof, &root));
+        assert!(!verifier.verify(&proof, &[]));
+        assert!(!verifier.verify(&proof, &[0u8; 32]));
+        assert!(verifier.verify_completeness(&proof, "rust", &fids));
+        assert!(!verifier.verify_completeness(&proof, "rust", &fids[..1]));
+        assert!(!verifier.verify_completeness(&proof, "go", &fids));
+        let first = verifier.fold_delivered(DELIVERED_EMPTY, &fids[..1]);
+        assert!(verifier.verify_delivered(
+            &proof,
+            "rust",
+            verifier.fold_delivered(first, &fids[1..])
+        ));
+        assert!(!verifier.verify_delivered(&proof, "rust", first));
+        assert!(!verifier.verify_delivered(
+            &proof,
+            "go",
+            verifier.fold_delivered(first, &fids[1..])
+        ));
+        // 少报 fid 的证明推导不出记录的根
+        assert!(!verifier.verify(&prove("rust", &fids[..1]), &root));
+
+        let absent = prove("java", &[]);
+        assert!(verifier.verify(&absent, &root));
+        assert!(verifier.verify_absence(&absent, "java"));
+        assert!(!verifier.verify_absence(&absent, "rust"));
+        assert!(!verifier.verify_absence(&proof, "rust"));
+        // 声称存在的 keyword 不存在
+        assert!(!verifier.verify(&prove("rust", &[]), &root));
+        assert!(!verifier.verify(&Proof::Smt(vec![]), &[]));
+    }
+
+    struct EqualsRootVerifier;
+
+    impl AdsVerifier for EqualsRootVerifier {
+        fn verify(&self, proof: &[u8], root_hash: &[u8]) -> bool {
+            proof == root_hash
+        }
+    }
+
+    #[test]
+    fn test_custom_verifier() {
+        use common::registry::{register_ads_mode, AdsCapabilities, AdsDescriptor};
+
+        let mode = register_ads_mode(AdsDescriptor {
+            name: "test-equals-root",
+            aliases: &[],
+            capabilities: AdsCapabilities {
+                supports_non_membership: false,
+                supports_range: false,
+                proof_version: 1,
+            },
+        })
+        .unwrap();
+        let verifier = ProofVerifier::new(mode);
+
+        // 未注册验证器时拒绝
+        let proof = Proof::Custom(vec![1, 2]);
+        assert!(!verifier.verify(&proof, &[1, 2]));
+
+        register_verifier(mode, Arc::new(EqualsRootVerifier)).unwrap();
+        assert!(verifier.verify(&proof, &[1, 2]));
+        assert!(!verifier.verify(&proof, &[3]));
+        assert!(!verifier.verify(&Proof::Mpt(vec![1, 2]), &[1, 2]));
+
+        assert!(register_verifier(AdsMode::Mpt, Arc::new(EqualsRootVerifier)).is_err());
     }
 }
@@ -0,0 +1,180 @@
+//! Manager 的错误类型
+//!
+//! 每个变体对应一个 [`ErrorKind`]：客户端从返回的状态中读取类别，就能区分
+//! 请求错误、路由失败、证明验证失败和 storager 自身的失败，而不必解析错误信息。
+
+use common::ErrorKind;
+use std::time::Duration;
+use thiserror::Error;
+use tonic::{Code, Status};
+
+/// Manager 处理请求失败的原因
+#[derive(Debug, Error)]
+pub enum ManagerError {
+    /// 请求参数无效
+    #[error("{0}")]
+    InvalidRequest(String),
+
+    /// 查询请求没有指定查询类型
+    #[error("No query type specified")]
+    MissingQueryType,
+
+    /// 布尔表达式无法解析
+    #[error("Failed to parse boolean expression: {0}")]
+    InvalidExpression(String),
+
+    /// 哈希环上没有 storager
+    #[error("No storager available")]
+    NoStorager,
+
+    /// keyword 的所有副本都不可用
+    #[error("No replica available for keyword '{0}'")]
+    NoReplica(String),
+
+    /// 无法建立到 storager 的连接
+    #[error("Failed to connect to storager {addr}: {message}")]
+    Connect { addr: String, message: String },
+
+    /// storager 返回了错误状态，保留它的状态码
+    #[error("Storager {rpc} failed: {message}")]
+    Storager {
+        rpc: &'static str,
+        code: Code,
+        message: String,
+    },
+
+    /// storager 没有在超时时间内响应
+    #[error("Storager {rpc} timed out after {timeout:?}")]
+    Timeout {
+        rpc: &'static str,
+        timeout: Duration,
+    },
+
+    /// storager 返回的证明缺失、类型未知或无法解码
+    #[error("Invalid proof from storager: {0}")]
+    InvalidProof(String),
+
+    /// 证明没有通过验证
+    #[error("{0}")]
+    VerificationFailed(String),
+
+    /// 注册或移除 storager 被拒绝
+    #[error("{0}")]
+    Membership(String),
+
+    /// 请求没有携带有效的 API token
+    #[error("{0}")]
+    Unauthenticated(String),
+
+    /// token 对应的客户端没有执行该操作的权限
+    #[error("{0}")]
+    PermissionDenied(String),
+
+    /// Manager 自身的状态（路由表、fid 索引）写入文件失败
+    #[error("Failed to persist {what}: {message}")]
+    Persist { what: &'static str, message: String },
+}
+
+impl ManagerError {
+    /// 单个 keyword 的查询证明没有通过验证
+    pub fn keyword_unverified(keyword: &str) -> Self {
+        ManagerError::VerificationFailed(format!(
+            "Proof verification failed for keyword: {}",
+            keyword
+        ))
+    }
+
+    /// 是否是重试可能成功的暂时性错误（连接失败、超时、storager 不可用）
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ManagerError::Connect { .. } | ManagerError::Timeout { .. } => true,
+            ManagerError::Storager { code, .. } => {
+                matches!(code, Code::Unavailable | Code::DeadlineExceeded)
+            }
+            _ => false,
+        }
+    }
+
+    /// 错误类别
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ManagerError::InvalidRequest(_)
+            | ManagerError::MissingQueryType
+            | ManagerError::InvalidExpression(_) => ErrorKind::InvalidRequest,
+            ManagerError::NoStorager
+            | ManagerError::NoReplica(_)
+            | ManagerError::Connect { .. } => ErrorKind::Routing,
+            ManagerError::Storager { .. }
+            | ManagerError::Timeout { .. }
+            | ManagerError::Persist { .. } => ErrorKind::Storage,
+            ManagerError::InvalidProof(_) | ManagerError::VerificationFailed(_) => {
+                ErrorKind::Verification
+            }
+            ManagerError::Membership(_) => ErrorKind::Membership,
+            ManagerError::Unauthenticated(_) | ManagerError::PermissionDenied(_) => ErrorKind::Auth,
+        }
+    }
+
+    /// 返回给客户端的状态码
+    ///
+    /// 证明验证失败使用 `DATA_LOSS`，与批量导入和读修复中的验证失败一致
+    pub fn code(&self) -> Code {
+        match self {
+            ManagerError::InvalidRequest(_)
+            | ManagerError::MissingQueryType
+            | ManagerError::InvalidExpression(_) => Code::InvalidArgument,
+            ManagerError::NoStorager
+            | ManagerError::NoReplica(_)
+            | ManagerError::Connect { .. } => Code::Unavailable,
+            ManagerError::Storager { code, .. } => match code {
+                Code::Ok | Code::Unknown => Code::Internal,
+                code => *code,
+            },
+            ManagerError::Timeout { .. } => Code::DeadlineExceeded,
+            ManagerError::InvalidProof(_) | ManagerError::Persist { .. } => Code::Internal,
+            ManagerError::VerificationFailed(_) => Code::DataLoss,
+            ManagerError::Membership(_) => Code::FailedPrecondition,
+            ManagerError::Unauthenticated(_) => Code::Unauthenticated,
+            ManagerError::PermissionDenied(_) => Code::PermissionDenied,
+        }
+    }
+}
+
+impl From<ManagerError> for Status {
+    fn from(error: ManagerError) -> Status {
+        error.kind().status(error.code(), error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_keeps_storager_code() {
+        let status = Status::from(ManagerError::Storager {
+            rpc: "Delete",
+            code: Code::NotFound,
+            message: "fid 'f1' is not under keyword 'rust'".to_string(),
+        });
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(ErrorKind::from_status(&status), Some(ErrorKind::Storage));
+        assert!(status.message().starts_with("Storager Delete failed"));
+
+        let status = Status::from(ManagerError::keyword_unverified("rust"));
+        assert_eq!(status.code(), Code::DataLoss);
+        assert_eq!(
+            ErrorKind::from_status(&status),
+            Some(ErrorKind::Verification)
+        );
+
+        let status = Status::from(ManagerError::NoStorager);
+        assert_eq!(ErrorKind::from_status(&status), Some(ErrorKind::Routing));
+        assert!(!ManagerError::NoStorager.is_transient());
+        assert!(ManagerError::Timeout {
+            rpc: "Add",
+            timeout: Duration::from_secs(1),
+        }
+        .is_transient());
+    }
+}
@@ -0,0 +1,188 @@
+//! 带 TTL 的写入
+//!
+//! Add 请求的 `ttl_seconds` 由 Manager 换算成绝对的过期时间（Unix 毫秒）发给 storager，
+//! 所有副本在同一时刻过期。storager 的清理任务通过普通的 ADS 删除路径删除到期的
+//! (keyword, fid)，生成删除证明和新的根哈希，并按顺序记入清理日志。
+//!
+//! 这些删除不经过 Manager，Manager 定期（`--expiry-interval`）调用
+//! [`Manager::collect_expired`] 通过 `ListExpired` 拉取清理日志，逐条验证删除证明、记入审计日志、
+//! 发布新的根哈希，并更新 fid 反向索引和变更订阅。拉取之前，查询落在清理之后的版本上时
+//! 没有对应的已验证根哈希，结果不会被标记为已验证。
+
+use crate::core::{MutationKind, RootKey};
+use crate::manager::{Manager, MutationProof};
+use common::rpc::{AckMode, ListExpiredRequest, ListExpiredResponse};
+use common::Proof;
+use std::collections::HashMap;
+use tracing::{debug, info, warn};
+
+impl Manager {
+    /// TTL 对应的过期时间（Unix 毫秒），0 表示不过期
+    pub(crate) fn expiry_deadline(&self, ttl_seconds: u64) -> u64 {
+        if ttl_seconds == 0 {
+            return 0;
+        }
+        let now = self.clock.unix_millis();
+        now.saturating_add(ttl_seconds.saturating_mul(1000))
+    }
+
+    /// 从各 storager 拉取过期清理的删除，验证后发布新的根哈希
+    ///
+    /// 只拉取已经发布过根哈希的 (storager, 命名空间)；无法连接的 storager 下次再拉取。
+    /// 返回本次验证通过的删除数量
+    pub async fn collect_expired(&self) -> usize {
+        let addrs: HashMap<String, String> = self.router.get_all_storagers().into_iter().collect();
+        let keys: Vec<RootKey> = self.root_hashes.read().unwrap().keys().cloned().collect();
+        let fetches = keys
+            .into_iter()
+            .filter_map(|key| {
+                let addr = addrs.get(&key.storager)?.clone();
+                Some(async move { self.collect_expired_from(key, &addr).await })
+            })
+            .collect();
+        self.fan_out(fetches).await.into_iter().sum()
+    }
+
+    /// 拉取一个 (storager, 命名空间) 的清理日志直到没有新的删除
+    async fn collect_expired_from(&self, key: RootKey, addr: &str) -> usize {
+        let mut collected = 0;
+        let mut keywords = Vec::new();
+        loop {
+            let after = self.expiry_cursor(&key);
+            let request = ListExpiredRequest {
+                namespace: key.namespace.clone(),
+                after_sequence: after,
+            };
+            let response = match self
+                .call_storager(addr, "ListExpired", |mut client| {
+                    let request = request.clone();
+                    async move { client.list_expired(request).await }
+                })
+                .await
+            {
+                Ok(response) => response,
+                Err(e) => {
+                    debug!("No expired entries from {}: {}", key.storager, e);
+                    break;
+                }
+            };
+            if response.last_sequence < after {
+                // storager 重启后清理日志从头开始
+                info!(
+                    "Expiry log of {} restarted, collecting from the beginning",
+                    key.storager
+                );
+                self.set_expiry_cursor(&key, 0);
+                continue;
+            }
+            if response.entries.is_empty() {
+                break;
+            }
+            collected += self.apply_expired(&key, after, response, &mut keywords);
+        }
+        if !keywords.is_empty() {
+            self.notify_watches(&key.namespace, &keywords).await;
+        }
+        collected
+    }
+
+    /// 验证并应用一批清理删除，返回验证通过的数量
+    fn apply_expired(
+        &self,
+        key: &RootKey,
+        after: u64,
+        response: ListExpiredResponse,
+        keywords: &mut Vec<String>,
+    ) -> usize {
+        if response.truncated {
+            warn!(
+                "Expired deletions on {} after sequence {} were dropped before collection",
+                key.storager, after
+            );
+        }
+        let mut verified = 0;
+        let mut cursor = after;
+        for entry in response.entries {
+            cursor = cursor.max(entry.sequence);
+            let proof = match entry.proof.map(Proof::try_from) {
+                Some(Ok(proof)) => proof,
+                _ => {
+                    warn!(
+                        "Expired deletion of ({}, {}) on {} has no valid proof",
+                        entry.keyword, entry.fid, key.storager
+                    );
+                    continue;
+                }
+            };
+            let (ok, _) = self.settle_mutation(
+                AckMode::Sync,
+                MutationKind::Delete,
+                key.clone(),
+                &entry.keyword,
+                &entry.fid,
+                MutationProof::Ready(proof),
+                entry.root_hash,
+                entry.epoch,
+                Vec::new(),
+            );
+            if !ok {
+                warn!(
+                    "Expired deletion of ({}, {}) on {} failed verification",
+                    entry.keyword, entry.fid, key.storager
+                );
+                continue;
+            }
+            // 每个副本各自清理，基数统计只在主副本上计入
+            let primary = self.get_storager_for_keyword(&entry.keyword);
+            if primary.is_some_and(|(node_name, _)| node_name == key.storager) {
+                self.record_cardinality(MutationKind::Delete, &entry.keyword);
+            }
+            self.index_fid(
+                MutationKind::Delete,
+                &key.namespace,
+                &entry.fid,
+                [&entry.keyword],
+            );
+            keywords.push(entry.keyword);
+            verified += 1;
+        }
+        self.set_expiry_cursor(key, cursor);
+        verified
+    }
+
+    fn expiry_cursor(&self, key: &RootKey) -> u64 {
+        self.expiry_cursors
+            .lock()
+            .unwrap()
+            .get(key)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn set_expiry_cursor(&self, key: &RootKey, sequence: u64) {
+        self.expiry_cursors
+            .lock()
+            .unwrap()
+            .insert(key.clone(), sequence);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::clock::MockClock;
+    use common::AdsMode;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_expiry_deadline() {
+        let clock = MockClock::new(Duration::from_secs(1_000));
+        let manager = Manager::new(vec![], AdsMode::Mpt).with_clock(Arc::new(clock.clone()));
+        assert_eq!(manager.expiry_deadline(0), 0);
+        assert_eq!(manager.expiry_deadline(60), 1_060_000);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(manager.expiry_deadline(60), 1_061_000);
+        assert_eq!(manager.expiry_deadline(u64::MAX), u64::MAX);
+    }
+}
@@ -0,0 +1,364 @@
+//! 拓扑变更时的关键词迁移
+//!
+//! 加入或移除 storager 时，Manager 在切换路由之前完成数据复制：
+//! 1. 向每个源节点列出 keyword（每个命名空间分别列出），按迁移计划找出换主的 keyword，并按新节点分组
+//! 2. 源节点把这些 keyword 的 fid 列表连同证明流式发送给新节点（`MigrateOut` → `MigrateIn`）
+//! 3. Manager 用源节点已发布的根验证源节点的证明，再直接查询新节点，
+//!    用新节点返回的新根验证证明并比对 fid 集合
+//! 4. 全部通过后发布新节点的根，调用方随后切换路由
+//!
+//! 任何一步失败时路由保持不变。迁移期间调用方持有拓扑写锁，写请求会等待迁移结束，
+//! 因此复制的快照不会遗漏写入。
+//!
+//! 源节点上迁出的数据不会被删除（路由已不再指向它们）。新节点按替换语义写入，
+//! 这些 keyword 以后迁回时残留的旧数据会被覆盖，
+//! 草图也按迁移来的 fid 重建。
+//!
+//! 复制因子增大时用同样的流程补齐副本：每个 keyword 由主副本复制到新增的副本节点
+//! （见 `Manager::set_replication_factor`）。
+
+use crate::core::accumulators::empty_accumulator;
+use crate::core::sketches::sketch_digest;
+use crate::core::{query_accumulator, AuditStatus, MutationKind, RootKey};
+use crate::manager::Manager;
+use common::rpc::{
+    AckMode, ListKeywordsRequest, ListNamespacesRequest, MigrateOutRequest, MigrateOutResponse,
+};
+use common::{Proof, DEFAULT_NAMESPACE};
+use consistent_hash::RebalancePlan;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::atomic::Ordering;
+use tracing::info;
+
+/// 一次迁移复制的数据量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MigrationSummary {
+    /// 复制的 keyword 数量（不含空的 keyword）
+    pub keywords: usize,
+    /// 复制的 (keyword, fid) 对数量
+    pub fids: usize,
+}
+
+/// 找出源节点上需要迁移的 keyword，按新节点分组
+///
+/// 落在迁移区间内但区间原主不是 `source` 的 keyword 是之前迁出后残留的数据，不再迁移
+pub(crate) fn assign_moved_keywords(
+    plan: &RebalancePlan,
+    source: &str,
+    keywords: Vec<String>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut assignment: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for keyword in keywords {
+        let Some(range) = plan.range_for_key(&keyword) else {
+            continue;
+        };
+        if let (Some(from), Some(to)) = (&range.from, &range.to) {
+            if from == source {
+                assignment.entry(to.clone()).or_default().push(keyword);
+            }
+        }
+    }
+    assignment
+}
+
+impl Manager {
+    /// 复制并验证迁移计划涉及的所有 keyword（不切换路由）
+    ///
+    /// # 参数
+    ///
+    /// * `plan` - 迁移计划
+    /// * `joining` - 加入的节点 `(名称, 地址)`，它还不在路由表中；其他节点的地址从路由表查找
+    pub(crate) async fn migrate_keywords(
+        &self,
+        plan: &RebalancePlan,
+        joining: Option<(&str, &str)>,
+    ) -> Result<MigrationSummary, String> {
+        let resolve = |name: &str| match joining {
+            Some((joining_name, addr)) if joining_name == name => Some(addr.to_string()),
+            _ => self.router.get_storager_addr(name),
+        };
+
+        let sources: BTreeSet<&str> = plan
+            .ranges
+            .iter()
+            .filter_map(|range| range.from.as_deref())
+            .collect();
+        self.copy_keywords(sources, resolve, |source, keywords| {
+            assign_moved_keywords(plan, source, keywords)
+        })
+        .await
+    }
+
+    /// 把复制因子设为 `factor`
+    ///
+    /// 增大时先把每个 keyword 从主副本复制到新增的副本节点，全部验证通过后才切换复制因子，
+    /// 否则新副本在读修复的法定结果中会以空结果压过主副本。与拓扑变更相同，期间持有拓扑写锁，
+    /// 写请求等待复制结束；失败时复制因子保持不变
+    pub(crate) async fn set_replication_factor(
+        &self,
+        factor: usize,
+    ) -> Result<MigrationSummary, String> {
+        let _topology = self.topology.write().await;
+        let previous = self.replication_factor();
+        // 节点数不超过原复制因子时每个 keyword 已经写入了所有节点
+        let summary = if factor > previous && self.router.storager_count() > previous {
+            let storagers = self.get_storagers();
+            let sources: BTreeSet<&str> = storagers.iter().map(|(name, _)| name.as_str()).collect();
+            let copied = self
+                .copy_keywords(
+                    sources,
+                    |name| self.router.get_storager_addr(name),
+                    |source, keywords| self.assign_new_replicas(source, keywords, previous, factor),
+                )
+                .await?;
+            info!(
+                "Backfilled {} keyword(s) for replication factor {} -> {}",
+                copied.keywords, previous, factor
+            );
+            copied
+        } else {
+            MigrationSummary::default()
+        };
+        self.replication_factor.store(factor, Ordering::Relaxed);
+        Ok(summary)
+    }
+
+    /// 找出以 `source` 为主副本的 keyword 在复制因子增大后新增的副本节点，按节点分组
+    fn assign_new_replicas(
+        &self,
+        source: &str,
+        keywords: Vec<String>,
+        previous: usize,
+        factor: usize,
+    ) -> BTreeMap<String, Vec<String>> {
+        let mut assignment: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for keyword in keywords {
+            let replicas = self.router.get_replicas_for_keyword(&keyword, factor);
+            if replicas.first().map(|(name, _)| name.as_str()) != Some(source) {
+                continue;
+            }
+            for (name, _) in replicas.into_iter().skip(previous) {
+                assignment.entry(name).or_default().push(keyword.clone());
+            }
+        }
+        assignment
+    }
+
+    /// 列出每个源节点各命名空间的 keyword，由 `assign` 按目标节点分组后复制并验证
+    async fn copy_keywords(
+        &self,
+        sources: BTreeSet<&str>,
+        resolve: impl Fn(&str) -> Option<String>,
+        assign: impl Fn(&str, Vec<String>) -> BTreeMap<String, Vec<String>>,
+    ) -> Result<MigrationSummary, String> {
+        let mut summary = MigrationSummary::default();
+        for source in sources {
+            let source_addr =
+                resolve(source).ok_or_else(|| format!("unknown storager '{}'", source))?;
+            let mut client = self
+                .storager_client(&source_addr)
+                .await
+                .map_err(|e| format!("{}: {}", source, e.message()))?;
+            let namespaces = client
+                .list_namespaces(ListNamespacesRequest {})
+                .await
+                .map_err(|e| {
+                    self.channels.evict_on_error(&source_addr, &e);
+                    format!("ListNamespaces on {} failed: {}", source, e.message())
+                })?
+                .into_inner()
+                .namespaces;
+
+            for namespace in std::iter::once(DEFAULT_NAMESPACE.to_string()).chain(namespaces) {
+                let keywords = client
+                    .list_keywords(ListKeywordsRequest {
+                        namespace: namespace.clone(),
+                    })
+                    .await
+                    .map_err(|e| {
+                        self.channels.evict_on_error(&source_addr, &e);
+                        format!("ListKeywords on {} failed: {}", source, e.message())
+                    })?
+                    .into_inner()
+                    .keywords;
+
+                for (target, keywords) in assign(source, keywords) {
+                    let target_addr =
+                        resolve(&target).ok_or_else(|| format!("unknown storager '{}'", target))?;
+                    info!(
+                        "Migrating {} keyword(s) from {} to {}",
+                        keywords.len(),
+                        source,
+                        target
+                    );
+
+                    let response = client
+                        .migrate_out(MigrateOutRequest {
+                            target: target_addr.clone(),
+                            keywords,
+                            namespace: namespace.clone(),
+                        })
+                        .await
+                        .map_err(|e| {
+                            self.channels.evict_on_error(&source_addr, &e);
+                            format!(
+                                "MigrateOut from {} to {} failed: {}",
+                                source,
+                                target,
+                                e.message()
+                            )
+                        })?
+                        .into_inner();
+
+                    let copied = self
+                        .verify_migration(
+                            &RootKey::new(source, namespace.as_str()),
+                            &RootKey::new(target, namespace.as_str()),
+                            &target_addr,
+                            response,
+                        )
+                        .await?;
+                    summary.keywords += copied.keywords;
+                    summary.fids += copied.fids;
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    /// 验证一批迁移，通过后为新节点记录审计条目并发布其新根
+    async fn verify_migration(
+        &self,
+        source: &RootKey,
+        target: &RootKey,
+        target_addr: &str,
+        response: MigrateOutResponse,
+    ) -> Result<MigrationSummary, String> {
+        let source_root = self.current_root(source);
+        // 新节点内容没有变化时不返回根哈希，也就没有新的 epoch 需要记录
+        let target_epoch = (!response.target_root_hash.is_empty()).then_some(response.target_epoch);
+        let target_root = if response.target_root_hash.is_empty() {
+            self.current_root(target)
+        } else {
+            response.target_root_hash
+        };
+
+        let mut summary = MigrationSummary::default();
+        let mut last_id = None;
+        let accumulators = self.root_store.accumulators();
+        let sketches = self.root_store.sketches();
+        // 空的 keyword 只用于清除新节点上的残留数据，没有需要验证的内容
+        let (entries, cleared): (Vec<_>, Vec<_>) = response
+            .entries
+            .into_iter()
+            .partition(|entry| !entry.fids.is_empty());
+        for entry in &cleared {
+            accumulators.set(target, &entry.keyword, empty_accumulator().clone());
+            sketches.set(target, &entry.keyword, None);
+        }
+        for entry in entries {
+            let proof = Proof::try_from(entry.proof.clone())
+                .ok()
+                .filter(|proof| {
+                    self.verify_keyword_proof(
+                        source,
+                        proof,
+                        &source_root,
+                        &entry.keyword,
+                        &entry.fids,
+                    )
+                })
+                .ok_or_else(|| {
+                    format!(
+                        "proof for '{}' from {} does not verify against its published root",
+                        entry.keyword, source.storager
+                    )
+                })?;
+            // 新节点上 keyword 的累加器应与源节点的相同
+            if let Some(acc) = query_accumulator(&proof) {
+                accumulators.set(target, &entry.keyword, acc);
+            }
+            // 新节点按迁移来的 fid 重建 keyword 的草图
+            let digest = sketch_digest(&entry.keyword, &entry.fids);
+            sketches.set(target, &entry.keyword, Some(digest));
+
+            let copy = self
+                .query_storager_at(
+                    target.storager.clone(),
+                    target_addr,
+                    &target.namespace,
+                    &entry.keyword,
+                    target_root.clone(),
+                )
+                .await
+                .map_err(|e| e.message().to_string())?;
+            let copied: HashSet<&String> = copy.fids.iter().collect();
+            if !copy.verified || copied != entry.fids.iter().collect() {
+                return Err(format!(
+                    "{} does not hold a verified copy of '{}'",
+                    target.storager, entry.keyword
+                ));
+            }
+
+            for fid in &entry.fids {
+                last_id = Some(self.audit_log.record(
+                    MutationKind::Add,
+                    &target.storager,
+                    &entry.keyword,
+                    fid,
+                    AckMode::Sync,
+                    target_root.clone(),
+                    copy.proof.clone(),
+                    AuditStatus::Verified,
+                ));
+            }
+            summary.keywords += 1;
+            summary.fids += entry.fids.len();
+        }
+
+        if let Some(id) = last_id {
+            Self::publish_root(
+                &self.root_hashes,
+                &self.root_versions,
+                &self.root_history,
+                &self.root_store,
+                &self.root_updates,
+                target.clone(),
+                target_root,
+                target_epoch,
+                id,
+            );
+        }
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use consistent_hash::ConsistentHashRing;
+
+    #[test]
+    fn test_assign_moved_keywords() {
+        let ring = ConsistentHashRing::with_nodes(&["storager-0", "storager-1"], 150);
+        let plan = ring.plan_add_node("storager-2", 150).unwrap();
+        let keywords: Vec<String> = (0..200).map(|i| format!("kw{}", i)).collect();
+
+        let assignment = assign_moved_keywords(&plan, "storager-0", keywords.clone());
+        assert_eq!(assignment.keys().collect::<Vec<_>>(), vec!["storager-2"]);
+        for keyword in &assignment["storager-2"] {
+            assert_eq!(ring.get_node(keyword).as_deref(), Some("storager-0"));
+        }
+
+        // 两个源节点分到的 keyword 恰好覆盖所有换主的 keyword
+        let moved = keywords
+            .iter()
+            .filter(|k| plan.range_for_key(k).is_some())
+            .count();
+        let from_one = assign_moved_keywords(&plan, "storager-1", keywords).remove("storager-2");
+        assert_eq!(
+            assignment["storager-2"].len() + from_one.map_or(0, |k| k.len()),
+            moved
+        );
+    }
+}
@@ -1,5 +1,22 @@
+pub mod admin;
+pub mod batch;
+pub mod bulk_load;
 pub mod core;
+pub mod error;
+pub mod expiry;
+pub mod key_migration;
 pub mod manager;
+pub mod range_query;
+pub mod reload;
 pub mod service;
+pub mod watch;
 
-pub use manager::Manager;
+pub use batch::MAX_BATCH_OPERATIONS;
+pub use bulk_load::DEFAULT_BULK_BATCH;
+pub use error::ManagerError;
+pub use key_migration::MigrationSummary;
+pub use manager::{
+    Manager, MembershipChange, Rebalance, DEFAULT_FANOUT_LIMIT, DEFAULT_VIRTUAL_NODES,
+};
+pub use reload::{ReloadConfig, ReloadReport};
+pub use watch::WATCH_BUFFER;
@@ -11,115 +11,711 @@
 //! # 使用默认配置（端口 50051，CryptoAccumulator）
 //! cargo run --bin manager
 //!
+//! # 从 SystemConfig JSON 读取 storager 地址、ADS 模式和监听/通告地址（命令行参数优先）
+//! cargo run --bin manager -- --config config.json
+//!
+//! # 每个参数都可以用环境变量设置（名称见 --help），命令行优先于环境变量
+//! DSS_PORT=50061 DSS_ADS_MODE=mpt cargo run --bin manager
+//!
 //! # 指定 ADS 模式
 //! cargo run --bin manager -- --ads-mode accumulator
 //! cargo run --bin manager -- --ads-mode mpt
+//! cargo run --bin manager -- --ads-mode merkle
+//! cargo run --bin manager -- --ads-mode smt
 //!
 //! # 指定端口
 //! cargo run --bin manager -- --port 50051
 //!
 //! # 指定 storager 地址（逗号分隔）
 //! cargo run --bin manager -- --storagers "http://[::1]:50052,http://[::1]:50053"
+//!
+//! # 为 storager 指定稳定的节点名称（name=通告地址）
+//! cargo run --bin manager -- --storagers "s1=http://10.0.0.5:50052,s2=http://10.0.0.6:50052"
+//!
+//! # 同时监听 IPv4 和 IPv6，并通告外部可达的地址
+//! cargo run --bin manager -- --listen "0.0.0.0,::" --advertise http://10.0.0.2:50051
+//!
+//! # 同一主机部署时通过 Unix domain socket 通信
+//! cargo run --bin manager -- --listen unix:/run/dss/manager.sock --storagers unix:/run/dss/storager-0.sock
+//!
+//! # 禁止异步确认，或只对关键租户的命名空间强制同步确认
+//! cargo run --bin manager -- --require-sync
+//! cargo run --bin manager -- --sync-tenants "bank,payments"
+//!
+//! # 限制单个查询的估计代价
+//! cargo run --bin manager -- --query-budget 100000
+//!
+//! # 缓存最多 4096 个 keyword 的验证通过的查询结果，根哈希变化后自动失效（默认不缓存）
+//! cargo run --bin manager -- --query-cache 4096
+//! # 另外缓存最多 1024 个已验证不存在的 keyword（布尔查询中常见）
+//! cargo run --bin manager -- --query-cache 4096 --negative-cache 1024
+//!
+//! # 定期导出防篡改的审计日志（可用 audit-verify 离线校验）
+//! cargo run --bin manager -- --audit-export /var/lib/dss/audit.bin
+//!
+//! # 使用与 Rust 版本无关的稳定哈希函数（xxhash|fnv|sha256，默认 std）
+//! cargo run --bin manager -- --ring-hasher xxhash
+//!
+//! # 持久化哈希环拓扑，重启后恢复运行期间的节点增删（文件存在时忽略 --storagers）
+//! # 运行期间通过 RegisterStorager / DeregisterStorager RPC 增删节点
+//! cargo run --bin manager -- --ring-state /var/lib/dss/ring.json
+//!
+//! # 持久化已发布的根哈希：没有跟踪的根时查询证明一律报告为未验证，
+//! # 不配置时重启后已有数据的查询在下一次写入之前都不能验证
+//! cargo run --bin manager -- --root-state /var/lib/dss/roots.json
+//!
+//! # 持久化 fid → keyword 反向索引，Delete 只给出 fid 时按索引删除
+//! cargo run --bin manager -- --fid-index /var/lib/dss/fids.json
+//!
+//! # 要求客户端携带 API token，按访问控制文件授权（格式见 manager::core::auth）
+//! cargo run --bin manager -- --hash-token <TOKEN>    # 输出写入文件的 token_sha256
+//! cargo run --bin manager -- --access-control /etc/dss/acl.json
+//!
+//! # 限制多关键词请求同时发往 storager 的并发数（默认 16）
+//! cargo run --bin manager -- --fanout-limit 32
+//!
+//! # 调整 storager 健康检查间隔（秒，0 表示关闭）
+//! cargo run --bin manager -- --health-interval 30
+//!
+//! # 每 30 秒拉取各 storager 的 keyword Bloom filter，布尔查询跳过肯定不存在的 keyword
+//! # （这些 keyword 没有不存在证明，响应的 filtered_keywords 列出它们；默认关闭）
+//! cargo run --bin manager -- --keyword-filter-interval 30
+//!
+//! # 每 5 秒拉取 storager 过期清理（Add 请求的 ttl_seconds）的删除证明，验证后发布新的根哈希
+//! cargo run --bin manager -- --expiry-interval 5
+//!
+//! # 验证差集证明时使用与 storager 相同的累加器公开参数
+//! cargo run --bin manager -- --public-params /etc/dss/acc.pp
+//!
+//! # 放宽消息大小上限（MiB）、压缩发往 storager 和客户端的消息、开启 keepalive（秒）
+//! cargo run --bin manager -- --max-message-mib 256 --compression zstd --keepalive 30
+//!
+//! # TLS：同一份证书用于对外服务和连接 storager（storager 要求客户端证书时即为双向 TLS），
+//! # storager 地址使用 https://
+//! cargo run --bin manager -- --tls-cert /etc/dss/manager.pem --tls-key /etc/dss/manager.key \
+//!     --tls-ca /etc/dss/ca.pem --storagers "https://storager-0:50052,https://storager-1:50052"
+//!
+//! # 日志级别（默认读取 RUST_LOG，否则为 info），输出 JSON 格式的日志
+//! cargo run --bin manager -- --log-level "info,manager=debug" --log-json
+//!
+//! # 追踪：以 --features otlp 构建时把 span 导出到 OTLP 收集器
+//! OTEL_EXPORTER_OTLP_ENDPOINT=http://localhost:4317 cargo run --features otlp --bin manager
+//!
+//! # 运行时重新加载 --config 中的 storager 列表、replication_factor、fanout_limit、
+//! # root_history、query_cache、negative_cache 和 log_level（文件中的值替换启动时命令行给出的值）
+//! kill -HUP $(pidof manager)
+//!
+//! # 收到 SIGINT / SIGTERM 后最多等待 60 秒让进行中的请求完成（默认 30 秒）
+//! cargo run --bin manager -- --ring-state /var/lib/dss/ring.json --drain-timeout 60
 //! ```
+//!
+//! 收到 SIGINT 或 SIGTERM 时停止接受新请求，等待进行中的请求（最多 `--drain-timeout` 秒）
+//! 和拓扑变更完成后，保存路由表、fid 反向索引和审计日志导出，然后退出。
+//!
+//! 同一监听地址还提供 `AdminService`：`ClusterStatus`（storager 健康状态、根哈希、哈希环布局）、
+//! `KeywordStats`（keyword 的基数和归属节点）、`RebalanceNow`（均衡各节点占有的哈希空间）
+//! `FlushAll`（保存 Manager 状态并让所有 storager 落盘）、`ReloadConfig`（与 SIGHUP 相同，
+//! 也可以直接给出新值）、`ProofStats`（启动以来查询证明的验证代价）和 `HotKeywords`
+//! （汇总各 storager 的查询频率，列出热点 keyword 和建议的副本数）。开启访问控制时，
+//! `ClusterStatus`、`KeywordStats`、`ProofStats` 和 `HotKeywords` 需要读权限，其余需要管理员权限。
+
+use clap::builder::RangedU64ValueParser;
+use clap::Parser;
+use common::auth::token_digest;
+use common::cli::{parse_ads_mode, parse_storager_addr, LogArgs, TransportArgs};
+use common::net::{
+    serve_with_drain, shutdown_signal, validate_address, ListenConfig, Listeners,
+    DEFAULT_DRAIN_SECS,
+};
+use common::telemetry::{init_tracing, LogConfig, Traced};
+use common::{AdsMode, SystemConfig};
+use consistent_hash::RingHasher;
+use esa_rust::crypto_accumulator::init_public_params;
+use manager::core::audit_chain;
+use manager::core::DEFAULT_ROOT_HISTORY;
+use manager::core::{AccessControl, AckPolicy, AdmissionConfig};
+use manager::{Manager, ReloadConfig, DEFAULT_FANOUT_LIMIT};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::service::interceptor::InterceptedService;
+use tracing::{error, info, warn};
+
+/// 未指定端口和配置文件时的监听端口
+const DEFAULT_PORT: u16 = 50051;
+
+fn parse_ring_hasher(name: &str) -> Result<RingHasher, String> {
+    // 哈希函数决定数据分布，写错时拒绝启动而不是回退到默认值
+    RingHasher::from_name(name).ok_or_else(|| format!("unknown ring hasher '{}'", name))
+}
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "manager",
+    about = "Manager Server - Distributed Storage System",
+    after_help = "Every option can also be set through the environment variable shown next to it."
+)]
+struct Cli {
+    /// SystemConfig JSON providing the storagers, ADS mode and listen/advertise addresses,
+    /// re-read on SIGHUP
+    #[arg(long, env = "DSS_CONFIG", value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Server port [default: 50051]
+    #[arg(short, long, env = "DSS_PORT")]
+    port: Option<u16>,
+
+    /// ADS mode: accumulator|mpt|merkle|smt [default: accumulator]
+    #[arg(short, long, env = "DSS_ADS_MODE", value_name = "MODE", value_parser = parse_ads_mode)]
+    ads_mode: Option<AdsMode>,
+
+    /// Comma-separated storager addresses (url or name=url) [default: http://[::1]:50052,http://[::1]:50053]
+    #[arg(short, long, env = "DSS_STORAGERS", value_name = "ADDRS", value_delimiter = ',',
+          value_parser = parse_storager_addr)]
+    storagers: Vec<String>,
+
+    /// Comma-separated listen addresses, ip:port or unix:/path [default: [::1]:PORT]
+    #[arg(short, long, env = "DSS_LISTEN", value_name = "ADDRS")]
+    listen: Option<String>,
+
+    /// Address advertised to clients
+    #[arg(long, env = "DSS_ADVERTISE", value_name = "URL", value_parser = parse_advertise)]
+    advertise: Option<String>,
+
+    /// Reject async acknowledgment for all requests
+    #[arg(long, env = "DSS_REQUIRE_SYNC")]
+    require_sync: bool,
+
+    /// Comma-separated tenant namespaces whose writes always use sync ack
+    #[arg(
+        long,
+        env = "DSS_SYNC_TENANTS",
+        value_name = "TENANTS",
+        value_delimiter = ','
+    )]
+    sync_tenants: Vec<String>,
+
+    /// Reject queries whose estimated cost exceeds COST
+    #[arg(long, env = "DSS_QUERY_BUDGET", value_name = "COST")]
+    query_budget: Option<u64>,
+
+    /// Cache verified single-keyword results for up to N keywords, 0 disables [default: 0]
+    #[arg(long, env = "DSS_QUERY_CACHE", value_name = "N")]
+    query_cache: Option<usize>,
+
+    /// Cache verified non-existence results for up to N keywords, 0 disables [default: 0]
+    #[arg(long, env = "DSS_NEGATIVE_CACHE", value_name = "N")]
+    negative_cache: Option<usize>,
+
+    /// Periodically export the hash-chained audit log
+    #[arg(long, env = "DSS_AUDIT_EXPORT", value_name = "PATH")]
+    audit_export: Option<String>,
+
+    /// Replicas per keyword, repaired on read [default: 1]
+    #[arg(long, env = "DSS_REPLICATION_FACTOR", value_name = "N",
+          value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    replication_factor: Option<usize>,
+
+    /// Concurrent storager requests per operation [default: 16]
+    #[arg(long, env = "DSS_FANOUT_LIMIT", value_name = "N")]
+    fanout_limit: Option<usize>,
+
+    /// Consistent hash function: std|xxhash|fnv|sha256
+    #[arg(long, env = "DSS_RING_HASHER", value_name = "NAME", default_value = "std",
+          value_parser = parse_ring_hasher)]
+    ring_hasher: RingHasher,
+
+    /// Persist the ring topology and restore it on restart
+    #[arg(long, env = "DSS_RING_STATE", value_name = "PATH")]
+    ring_state: Option<String>,
+
+    /// Persist the published root hashes that query proofs are checked against
+    #[arg(long, env = "DSS_ROOT_STATE", value_name = "PATH")]
+    root_state: Option<String>,
+
+    /// Persist the fid -> keywords index used by fid-only deletes
+    #[arg(long, env = "DSS_FID_INDEX", value_name = "PATH")]
+    fid_index: Option<String>,
+
+    /// Require API tokens and authorize them with this JSON file
+    #[arg(long, env = "DSS_ACCESS_CONTROL", value_name = "PATH")]
+    access_control: Option<String>,
+
+    /// Print the token_sha256 value for an access control entry and exit
+    #[arg(long, value_name = "TOKEN")]
+    hash_token: Option<String>,
+
+    /// Storager health check interval in seconds, 0 disables
+    #[arg(
+        long,
+        env = "DSS_HEALTH_INTERVAL",
+        value_name = "SECS",
+        default_value_t = 10
+    )]
+    health_interval: u64,
+
+    /// Seconds between keyword filter refreshes used to skip boolean sub-queries, 0 disables
+    #[arg(
+        long,
+        env = "DSS_KEYWORD_FILTER_INTERVAL",
+        value_name = "SECS",
+        default_value_t = 0
+    )]
+    keyword_filter_interval: u64,
+
+    /// Seconds between collecting the storagers' TTL expirations, 0 disables
+    #[arg(
+        long,
+        env = "DSS_EXPIRY_INTERVAL",
+        value_name = "SECS",
+        default_value_t = 10
+    )]
+    expiry_interval: u64,
+
+    /// Accumulator public parameters shared with the storagers
+    #[arg(long, env = "DSS_PUBLIC_PARAMS", value_name = "PATH")]
+    public_params: Option<PathBuf>,
+
+    /// Seconds to wait for in-flight requests after SIGINT/SIGTERM before closing connections
+    #[arg(long, env = "DSS_DRAIN_TIMEOUT", value_name = "SECS", default_value_t = DEFAULT_DRAIN_SECS)]
+    drain_timeout: u64,
+
+    #[command(flatten)]
+    transport: TransportArgs,
+
+    #[command(flatten)]
+    log: LogArgs,
+}
+
+fn parse_advertise(addr: &str) -> Result<String, String> {
+    validate_address(addr)?;
+    Ok(addr.to_string())
+}
 
-use common::rpc::manager_service_server::ManagerServiceServer;
-use common::AdsMode;
-use manager::Manager;
-use tonic::transport::Server;
+/// 命令行和环境变量覆盖配置文件，两者都没有时使用默认值
+struct Settings {
+    ads_mode: AdsMode,
+    storager_addrs: Vec<String>,
+    listen: ListenConfig,
+    replication_factor: usize,
+    fanout_limit: usize,
+    root_history: usize,
+    query_cache: usize,
+    negative_cache: usize,
+    log_level: Option<String>,
+}
+
+impl Settings {
+    fn resolve(cli: &Cli) -> Result<Self, String> {
+        let config = cli.config.as_ref().map(SystemConfig::load).transpose()?;
+
+        let ads_mode = cli
+            .ads_mode
+            .or(config.as_ref().map(|c| c.ads_mode))
+            .unwrap_or(AdsMode::CryptoAccumulator);
+        let storager_addrs = if !cli.storagers.is_empty() {
+            cli.storagers.clone()
+        } else if let Some(config) = &config {
+            config
+                .storager_addrs
+                .iter()
+                .map(|entry| parse_storager_addr(entry))
+                .collect::<Result<_, _>>()?
+        } else {
+            vec![
+                "http://[::1]:50052".to_string(),
+                "http://[::1]:50053".to_string(),
+            ]
+        };
+        let port = cli.port.unwrap_or(DEFAULT_PORT);
+        let mut listen = match (&cli.listen, &config) {
+            (Some(spec), _) => ListenConfig::parse(spec, port)?,
+            (None, Some(config)) if cli.port.is_none() => config.manager_listen()?,
+            (None, _) => ListenConfig::localhost(port),
+        };
+        if let Some(advertise) = &cli.advertise {
+            listen = listen.with_advertise(advertise.clone());
+        }
+
+        Ok(Settings {
+            ads_mode,
+            storager_addrs,
+            listen,
+            replication_factor: cli
+                .replication_factor
+                .or(config.as_ref().and_then(|c| c.replication_factor))
+                .unwrap_or(1),
+            fanout_limit: cli
+                .fanout_limit
+                .or(config.as_ref().and_then(|c| c.fanout_limit))
+                .unwrap_or(DEFAULT_FANOUT_LIMIT),
+            root_history: config
+                .as_ref()
+                .and_then(|c| c.root_history)
+                .unwrap_or(DEFAULT_ROOT_HISTORY),
+            query_cache: cli
+                .query_cache
+                .or(config.as_ref().and_then(|c| c.query_cache))
+                .unwrap_or(0),
+            negative_cache: cli
+                .negative_cache
+                .or(config.as_ref().and_then(|c| c.negative_cache))
+                .unwrap_or(0),
+            log_level: cli
+                .log
+                .log_level
+                .clone()
+                .or(config.and_then(|c| c.log_level)),
+        })
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // 解析命令行参数
-    let args: Vec<String> = std::env::args().collect();
-
-    let mut port = 50051u16;
-    let mut ads_mode = AdsMode::CryptoAccumulator;
-    let mut storager_addrs = vec![
-        "http://[::1]:50052".to_string(),
-        "http://[::1]:50053".to_string(),
-    ];
-
-    // 简单的命令行参数解析
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "--port" | "-p" => {
-                if i + 1 < args.len() {
-                    port = args[i + 1].parse().unwrap_or(50051);
-                    i += 2;
-                } else {
-                    i += 1;
+    let cli = Cli::parse();
+    if let Some(token) = &cli.hash_token {
+        println!("{}", token_digest(token));
+        return Ok(());
+    }
+    let Settings {
+        ads_mode,
+        storager_addrs,
+        listen,
+        replication_factor,
+        fanout_limit,
+        root_history,
+        query_cache,
+        negative_cache,
+        log_level,
+    } = Settings::resolve(&cli)?;
+    let log = LogConfig {
+        level: log_level,
+        ..cli.log.config()
+    };
+    init_tracing("manager", &log)?;
+    let transport = cli.transport.config()?;
+    let tls_client_auth = cli.transport.tls_client_auth;
+
+    let mut ack_policy = AckPolicy {
+        allow_async: !cli.require_sync,
+        ..AckPolicy::default()
+    };
+    for tenant in cli.sync_tenants.iter().map(|s| s.trim()) {
+        if !tenant.is_empty() {
+            ack_policy = ack_policy.require_sync_for(tenant);
+        }
+    }
+    let admission = AdmissionConfig {
+        budget: cli.query_budget,
+        ..AdmissionConfig::default()
+    };
+
+    if ads_mode == AdsMode::CryptoAccumulator {
+        init_public_params(cli.public_params.as_deref())
+            .map_err(|e| format!("Failed to load accumulator public parameters: {}", e))?;
+    }
+
+    let mut manager = Manager::new(storager_addrs, ads_mode)
+        .with_ack_policy(ack_policy.clone())
+        .with_admission(admission.clone())
+        .with_ring_hasher(cli.ring_hasher)
+        .with_replication_factor(replication_factor)
+        .with_fanout_limit(fanout_limit)
+        .with_root_history(root_history)
+        .with_query_cache(query_cache)
+        .with_negative_cache(negative_cache)
+        .with_transport(transport.clone());
+    if let Some(path) = &cli.config {
+        manager = manager.with_config_path(path);
+    }
+    if let Some(path) = &cli.ring_state {
+        manager = manager
+            .with_ring_state(path)
+            .map_err(|e| format!("Failed to load ring state from {}: {}", path, e))?;
+    }
+    if let Some(path) = &cli.root_state {
+        manager = manager
+            .with_root_state(path)
+            .map_err(|e| format!("Failed to load root hashes from {}: {}", path, e))?;
+    }
+    if let Some(path) = &cli.fid_index {
+        manager = manager
+            .with_fid_index(path)
+            .map_err(|e| format!("Failed to load fid index from {}: {}", path, e))?;
+    }
+    let mut access_clients = 0;
+    if let Some(path) = &cli.access_control {
+        let access = AccessControl::open(path)
+            .map_err(|e| format!("Failed to load access control from {}: {}", path, e))?;
+        access_clients = access.len();
+        manager = manager.with_access_control(access);
+    }
+    let manager = Arc::new(manager);
+
+    info!("Manager server starting");
+    info!("Listening on: {:?}", listen.bind_addrs);
+    if !listen.unix_paths.is_empty() {
+        info!("Unix sockets: {:?}", listen.unix_paths);
+    }
+    info!("Advertised as: {}", listen.advertise_url());
+    info!("ADS Mode: {:?}", ads_mode);
+    let mut storagers = manager.get_storagers();
+    storagers.sort();
+    info!("Storagers: {:?}", storagers);
+    if let Some(path) = &cli.ring_state {
+        info!("Ring state: {}", path);
+    }
+    if let Some(path) = &cli.root_state {
+        info!("Root state: {}", path);
+    }
+    if let Some(path) = &cli.fid_index {
+        info!("Fid index: {}", path);
+    }
+    if let Some(path) = &cli.access_control {
+        info!("Access control: {} client(s) from {}", access_clients, path);
+    }
+    if let Some(path) = &cli.public_params {
+        info!("Public params: {}", path.display());
+    }
+    let ring_hasher = manager.ring_hasher();
+    info!("Ring hasher: {}", ring_hasher.name());
+    if !ring_hasher.is_stable() {
+        warn!("The std hasher may change across Rust versions; prefer --ring-hasher xxhash");
+    }
+    info!(
+        "Async ack: {} (sync-only tenants: {:?})",
+        ack_policy.allow_async, ack_policy.sync_tenants
+    );
+    info!("Query budget: {:?}", admission.budget);
+    if query_cache > 0 || negative_cache > 0 {
+        info!(
+            "Query cache: {} keyword(s), {} empty keyword(s)",
+            query_cache, negative_cache
+        );
+    }
+    info!("Fan-out limit: {}", fanout_limit);
+    info!(
+        "Transport: max message {} MiB, compression {}, keepalive {:?}",
+        transport.max_message_size >> 20,
+        transport.compression.map_or("none", |c| c.name()),
+        transport.keepalive_interval
+    );
+    if transport.tls.is_some() {
+        info!(
+            "TLS: on (client certificates required: {})",
+            tls_client_auth
+        );
+    }
+    if replication_factor > 1 {
+        info!(
+            "Replication factor: {} (read repair on)",
+            replication_factor
+        );
+    }
+
+    // SIGHUP 重新读取配置文件；成员变更在拓扑写锁下进行，进行中的请求不受影响
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut hangup = signal(SignalKind::hangup())?;
+        let manager = manager.clone();
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                if let Err(e) = manager.reload_from_file(ReloadConfig::default()).await {
+                    warn!("Ignoring SIGHUP: {}", e);
                 }
             }
-            "--ads-mode" | "-a" => {
-                if i + 1 < args.len() {
-                    ads_mode = match args[i + 1].to_lowercase().as_str() {
-                        "mpt" => AdsMode::Mpt,
-                        "accumulator" | "crypto" => AdsMode::CryptoAccumulator,
-                        _ => {
-                            eprintln!("Unknown ADS mode: {}, using default", args[i + 1]);
-                            AdsMode::CryptoAccumulator
-                        }
-                    };
-                    i += 2;
-                } else {
-                    i += 1;
-                }
+        });
+    }
+
+    if let Some(path) = cli.audit_export.clone() {
+        info!("Audit export: {}", path);
+        let manager = manager.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(10));
+            loop {
+                interval.tick().await;
+                export_audit(&manager, &path);
             }
-            "--storagers" | "-s" => {
-                if i + 1 < args.len() {
-                    storager_addrs = args[i + 1]
-                        .split(',')
-                        .map(|s| s.trim().to_string())
-                        .collect();
-                    i += 2;
-                } else {
-                    i += 1;
-                }
+        });
+    }
+
+    // 定期检查 storager 的密码学子系统，停止向初始化失败的节点路由
+    if cli.health_interval > 0 {
+        info!("Health check interval: {}s", cli.health_interval);
+        let period = Duration::from_secs(cli.health_interval);
+        let manager = manager.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                manager.check_storager_health().await;
             }
-            "--help" | "-h" => {
-                print_help();
-                return Ok(());
+        });
+    }
+
+    if cli.keyword_filter_interval > 0 {
+        info!(
+            "Keyword filter refresh interval: {}s",
+            cli.keyword_filter_interval
+        );
+        let period = Duration::from_secs(cli.keyword_filter_interval);
+        let manager = manager.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                manager.refresh_keyword_filters().await;
             }
-            _ => {
-                i += 1;
+        });
+    }
+
+    // storager 清理过期条目后根哈希改变，拉取删除证明并发布新的根哈希
+    if cli.expiry_interval > 0 {
+        info!("Expiry collection interval: {}s", cli.expiry_interval);
+        let period = Duration::from_secs(cli.expiry_interval);
+        let manager = manager.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                manager.collect_expired().await;
             }
+        });
+    }
+
+    // 管理服务与 Manager 服务共用监听地址和认证
+    let interceptor = manager.auth_interceptor();
+    let admin = Traced::new(InterceptedService::new(
+        transport.admin_server(manager.clone()),
+        interceptor.clone(),
+    ));
+    let service = Traced::new(InterceptedService::new(
+        transport.manager_server(manager.clone()),
+        interceptor,
+    ));
+    let server = transport.server()?;
+    let shutdown = async {
+        let signal = shutdown_signal().await;
+        info!("Received {}, draining in-flight requests", signal);
+    };
+    serve_with_drain(
+        Listeners::bind(&listen)?,
+        || {
+            server
+                .clone()
+                .add_service(service.clone())
+                .add_service(admin.clone())
+        },
+        shutdown,
+        Duration::from_secs(cli.drain_timeout),
+    )
+    .await?;
+
+    tokio::select! {
+        saved = manager.shutdown() => saved.map_err(|e| {
+            error!("Failed to save state on shutdown: {}", e);
+            e
+        })?,
+        signal = shutdown_signal() => {
+            warn!("Received {} again, exiting without saving state", signal);
+            return Ok(());
         }
     }
+    if let Some(path) = &cli.audit_export {
+        export_audit(&manager, path);
+    }
+    info!("Manager state saved, exiting");
 
-    let addr = format!("[::1]:{}", port).parse()?;
+    Ok(())
+}
 
-    let manager = Manager::new(storager_addrs.clone(), ads_mode);
+/// 导出审计日志；先写临时文件再重命名，避免校验工具读到写了一半的文件
+fn export_audit(manager: &Manager, path: &str) {
+    let bytes = audit_chain::export(manager.audit_log(), manager.ads_mode());
+    let tmp = format!("{}.tmp", path);
+    if let Err(e) = std::fs::write(&tmp, bytes).and_then(|_| std::fs::rename(&tmp, path)) {
+        error!("Failed to export audit log to {}: {}", path, e);
+    }
+}
 
-    println!("🚀 Manager server starting...");
-    println!("   Listening on: {}", addr);
-    println!("   ADS Mode: {:?}", ads_mode);
-    println!("   Storagers: {:?}", storager_addrs);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
 
-    Server::builder()
-        .add_service(ManagerServiceServer::new(manager))
-        .serve(addr)
-        .await?;
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
 
-    Ok(())
-}
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        for args in [
+            &["manager", "--port", "not-a-port"][..],
+            &["manager", "--ads-mode", "btree"],
+            &["manager", "--ring-hasher", "md5"],
+            &["manager", "--replication-factor", "0"],
+            &["manager", "--unknown-flag"],
+        ] {
+            assert!(Cli::try_parse_from(args).is_err(), "{:?}", args);
+        }
+    }
 
-fn print_help() {
-    println!("Manager Server - Distributed Storage System");
-    println!();
-    println!("USAGE:");
-    println!("    manager [OPTIONS]");
-    println!();
-    println!("OPTIONS:");
-    println!("    -p, --port <PORT>              Set the server port (default: 50051)");
-    println!(
-        "    -a, --ads-mode <MODE>          Set ADS mode: accumulator|mpt (default: accumulator)"
-    );
-    println!("    -s, --storagers <ADDRS>        Comma-separated storager addresses");
-    println!("    -h, --help                     Print this help message");
-    println!();
-    println!("EXAMPLES:");
-    println!("    manager --port 50051");
-    println!("    manager --ads-mode accumulator");
-    println!("    manager --storagers \"http://[::1]:50052,http://[::1]:50053\"");
+    #[test]
+    fn test_flags_override_config() {
+        let path = std::env::temp_dir().join("manager_settings_test.json");
+        std::fs::write(
+            &path,
+            r#"{"num_clients": 1, "num_storagers": 1, "ads_mode": "Mpt",
+                "manager_addr": "http://127.0.0.1:6000",
+                "storager_addrs": ["s1=http://127.0.0.1:6001"], "client_addrs": [],
+                "replication_factor": 2, "root_history": 8, "query_cache": 64,
+                "log_level": "debug"}"#,
+        )
+        .unwrap();
+        let config = path.to_str().unwrap();
+
+        let cli = Cli::try_parse_from(["manager", "--config", config]).unwrap();
+        let settings = Settings::resolve(&cli).unwrap();
+        assert_eq!(settings.ads_mode, AdsMode::Mpt);
+        assert_eq!(settings.storager_addrs, vec!["s1=http://127.0.0.1:6001"]);
+        assert_eq!(settings.listen.advertise_url(), "http://127.0.0.1:6000");
+        assert_eq!(settings.replication_factor, 2);
+        assert_eq!(settings.fanout_limit, DEFAULT_FANOUT_LIMIT);
+        assert_eq!(settings.root_history, 8);
+        assert_eq!((settings.query_cache, settings.negative_cache), (64, 0));
+        assert_eq!(settings.log_level.as_deref(), Some("debug"));
+
+        let cli = Cli::try_parse_from([
+            "manager",
+            "--config",
+            config,
+            "--ads-mode",
+            "smt",
+            "--port",
+            "7000",
+            "--storagers",
+            "http://[::1]:7001",
+            "--replication-factor",
+            "3",
+            "--log-level",
+            "warn",
+        ])
+        .unwrap();
+        let settings = Settings::resolve(&cli).unwrap();
+        assert_eq!(settings.ads_mode, AdsMode::SparseMerkleTree);
+        assert_eq!(settings.replication_factor, 3);
+        assert_eq!(settings.log_level.as_deref(), Some("warn"));
+        assert_eq!(settings.storager_addrs, vec!["http://[::1]:7001"]);
+        assert_eq!(settings.listen, ListenConfig::localhost(7000));
+        std::fs::remove_file(&path).unwrap();
+
+        let cli = Cli::try_parse_from(["manager"]).unwrap();
+        let settings = Settings::resolve(&cli).unwrap();
+        assert_eq!(settings.ads_mode, AdsMode::CryptoAccumulator);
+        assert_eq!(settings.listen, ListenConfig::localhost(DEFAULT_PORT));
+        assert_eq!(settings.replication_factor, 1);
+        assert_eq!(settings.root_history, DEFAULT_ROOT_HISTORY);
+    }
 }
@@ -2,10 +2,100 @@
 //!
 //! 负责协调客户端请求和 storager 节点
 
-use crate::core::{ProofVerifier, Router};
-use common::{AdsMode, RootHash};
+use crate::bulk_load::DEFAULT_BULK_BATCH;
+use crate::core::accumulators::empty_accumulator;
+use crate::core::{
+    query_accumulator, Access, AccessControl, AckPolicy, AdmissionConfig, AdmissionController,
+    AuditLog, AuditStatus, AuthInterceptor, Caller, ChannelPool, FidIndex, FidLocks,
+    KeywordFilters, KeywordSketches, MigrationTracker, MutationKind, Principal, ProofStats,
+    ProofStatsSnapshot, ProofVerifier, QueryCache, QueryCacheStats, ReadDiscrepancy, RetryPolicy,
+    RootHistory, RootKey, RootStore, Router,
+};
+use crate::error::ManagerError;
+use crate::key_migration::MigrationSummary;
+use crate::watch::Watches;
+use common::clock::{system_clock, SharedClock};
+use common::net::validate_address;
+use common::rpc::{
+    storager_service_client::StoragerServiceClient, AckMode, GetProofRequest, KeywordFilterRequest,
+    RootHashUpdate, StoragerHealthRequest,
+};
+use common::telemetry::TracedChannel;
+use common::transport::TransportConfig;
+use common::{AdsMode, BloomFilter, Proof, RootHash};
+use consistent_hash::{RebalancePlan, RingHasher};
+use futures::stream::{self, StreamExt};
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::broadcast;
+use tonic::{Request, Response, Status};
+use tracing::Instrument;
+use tracing::{debug, info, warn};
+
+/// 每个 storager 默认的虚拟节点数量
+pub const DEFAULT_VIRTUAL_NODES: usize = 150;
+
+/// 一个请求同时发往 storager 的默认最大并发数
+pub const DEFAULT_FANOUT_LIMIT: usize = 16;
+
+/// 根哈希订阅的缓冲区大小；落后超过这么多条的订阅者会跳过中间的更新
+const ROOT_UPDATE_BUFFER: usize = 1024;
+
+/// 一次运行时拓扑变更的结果
+#[derive(Debug, Clone)]
+pub struct MembershipChange {
+    /// 加入或移除的节点名称
+    pub name: String,
+    /// 需要迁移的哈希区间
+    pub plan: RebalancePlan,
+    /// 切换路由之前复制到新节点的数据量
+    pub migrated: MigrationSummary,
+}
+
+/// 一次哈希环再平衡的结果
+#[derive(Debug, Clone)]
+pub struct Rebalance {
+    /// 需要迁移的哈希区间
+    pub plan: RebalancePlan,
+    /// 切换路由之前复制的数据量（预览时为空）
+    pub migrated: MigrationSummary,
+    /// 调整前占有最多的节点相对平均比例的倍数
+    pub imbalance_before: f64,
+    /// 调整后占有最多的节点相对平均比例的倍数
+    pub imbalance_after: f64,
+}
+
+/// storager 返回的变更证明
+///
+/// 异步确认的写请求让 storager 推迟生成证明（`defer_proof`），响应中只有证明句柄，
+/// 后台结算时再用 GetProof 从 `storager_addr` 取回
+#[derive(Debug, Clone)]
+pub(crate) enum MutationProof {
+    Ready(Proof),
+    Deferred { storager_addr: String, handle: u64 },
+}
+
+impl MutationProof {
+    /// 从写响应中取出证明，`handle` 非 0 表示证明被推迟
+    pub(crate) fn from_response(
+        proof: Option<common::rpc::Proof>,
+        handle: u64,
+        storager_addr: &str,
+    ) -> Result<Self, ManagerError> {
+        if handle != 0 {
+            return Ok(MutationProof::Deferred {
+                storager_addr: storager_addr.to_string(),
+                handle,
+            });
+        }
+        Proof::try_from(proof)
+            .map(MutationProof::Ready)
+            .map_err(ManagerError::InvalidProof)
+    }
+}
 
 /// Manager 结构
 ///
@@ -18,8 +108,61 @@ pub struct Manager {
     pub(crate) router: Router,
     /// 证明验证器
     pub(crate) verifier: ProofVerifier,
-    /// storager 名称到根哈希的映射
-    pub(crate) root_hashes: Arc<RwLock<HashMap<String, RootHash>>>,
+    /// 到各 storager 的复用连接（异步结算取回推迟的证明时也使用）
+    pub(crate) channels: Arc<ChannelPool>,
+    /// (storager, 命名空间) 到根哈希的映射
+    pub(crate) root_hashes: Arc<RwLock<HashMap<RootKey, RootHash>>>,
+    /// (storager, 命名空间) 到已发布根哈希的审计 id（订阅推送的版本号）
+    pub(crate) root_versions: Arc<RwLock<HashMap<RootKey, u64>>>,
+    /// 每个 storager 最近验证过的 (epoch, 根哈希)，查询证明按响应的 epoch 选择根哈希验证
+    pub(crate) root_history: Arc<RootHistory>,
+    /// 已发布根哈希的文件（见 [`with_root_state`](Self::with_root_state)）
+    pub(crate) root_store: Arc<RootStore>,
+    /// 新发布的根哈希推送给订阅者（见 `SubscribeRootHashes`）
+    pub(crate) root_updates: broadcast::Sender<RootHashUpdate>,
+    /// 确认模式策略
+    pub(crate) ack_policy: AckPolicy,
+    /// 变更审计日志
+    pub(crate) audit_log: Arc<AuditLog>,
+    /// 查询准入控制
+    pub(crate) admission: AdmissionController,
+    /// 进行中的迁移（用于影子读）
+    pub(crate) migrations: MigrationTracker,
+    /// 查询证明的验证代价统计
+    pub(crate) proof_stats: ProofStats,
+    /// 按根哈希失效的单关键词查询结果缓存，默认不启用
+    pub(crate) query_cache: QueryCache,
+    /// 各 storager 的 keyword 过滤器，布尔查询用它跳过肯定不存在的 keyword
+    pub(crate) keyword_filters: KeywordFilters,
+    /// 时间源
+    pub(crate) clock: SharedClock,
+    /// 路由表快照文件（拓扑变更后写入，重启时恢复）
+    pub(crate) ring_state: Option<PathBuf>,
+    /// 重新加载时读取的配置文件（见 [`crate::reload`]）
+    pub(crate) config_path: Option<PathBuf>,
+    /// 拓扑变更和读修复持有写锁，写请求持有读锁：
+    /// 关键词迁移期间写请求等待，迁移计划与实际切换之间哈希环也不会被其他变更修改
+    pub(crate) topology: tokio::sync::RwLock<()>,
+    /// 复制因子（每个 keyword 写入的副本数量），可在运行时重新加载
+    pub(crate) replication_factor: AtomicUsize,
+    /// 多关键词请求同时发往 storager 的最大并发数，可在运行时重新加载
+    pub(crate) fanout_limit: AtomicUsize,
+    /// 批量导入时每个 storager 一批发送的记录数
+    pub(crate) bulk_batch: usize,
+    /// storager 调用的重试策略
+    pub(crate) retry: RetryPolicy,
+    /// 下一个写请求 id
+    pub(crate) request_ids: AtomicU64,
+    /// 同一个 fid 的写请求串行执行（见 [`crate::core::update`]）
+    pub(crate) fid_locks: FidLocks,
+    /// 每个 fid 所在的 keyword，Delete 只给出 fid 时使用
+    pub(crate) fid_index: FidIndex,
+    /// API token 访问控制，None 表示不认证
+    pub(crate) access: Option<Arc<AccessControl>>,
+    /// `WatchKeyword` 的活跃订阅，写请求完成后向受影响的订阅推送结果变化
+    pub(crate) watches: Watches,
+    /// 每个 (storager, 命名空间) 已拉取的清理日志序号（见 [`crate::expiry`]）
+    pub(crate) expiry_cursors: Mutex<HashMap<RootKey, u64>>,
 }
 
 impl Manager {
@@ -29,35 +172,995 @@ impl Manager {
     /// * `storager_addrs` - storager 地址列表
     /// * `ads_mode` - ADS 模式
     pub fn new(storager_addrs: Vec<String>, ads_mode: AdsMode) -> Self {
-        let router = Router::new(storager_addrs, 150); // 每个节点 150 个虚拟节点
+        let router = Router::new(storager_addrs, DEFAULT_VIRTUAL_NODES);
         let verifier = ProofVerifier::new(ads_mode);
         let root_hashes = Arc::new(RwLock::new(HashMap::new()));
+        let clock = system_clock();
+        let request_ids = AtomicU64::new(clock.wall_time().as_nanos() as u64);
 
         Manager {
             router,
             verifier,
+            channels: Arc::new(ChannelPool::new()),
             root_hashes,
+            root_versions: Arc::new(RwLock::new(HashMap::new())),
+            root_history: Arc::new(RootHistory::default()),
+            root_store: Arc::new(RootStore::new()),
+            root_updates: broadcast::channel(ROOT_UPDATE_BUFFER).0,
+            ack_policy: AckPolicy::default(),
+            audit_log: Arc::new(AuditLog::new()),
+            admission: AdmissionController::new(AdmissionConfig::default()),
+            migrations: MigrationTracker::new(),
+            proof_stats: ProofStats::new(),
+            query_cache: QueryCache::default(),
+            keyword_filters: KeywordFilters::new(),
+            clock,
+            ring_state: None,
+            config_path: None,
+            topology: tokio::sync::RwLock::new(()),
+            replication_factor: AtomicUsize::new(1),
+            fanout_limit: AtomicUsize::new(DEFAULT_FANOUT_LIMIT),
+            bulk_batch: DEFAULT_BULK_BATCH,
+            retry: RetryPolicy::default(),
+            request_ids,
+            fid_locks: FidLocks::new(),
+            fid_index: FidIndex::new(),
+            access: None,
+            watches: Watches::new(),
+            expiry_cursors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 设置查询准入控制配置
+    pub fn with_admission(mut self, config: AdmissionConfig) -> Self {
+        self.admission = AdmissionController::new(config);
+        self
+    }
+
+    /// 设置每个 storager 保留的历史根哈希数量
+    ///
+    /// 查询响应的 epoch 落后超过这么多次写入时，退回使用当前发布的根哈希验证
+    pub fn with_root_history(mut self, capacity: usize) -> Self {
+        self.root_history = Arc::new(RootHistory::new(capacity));
+        self
+    }
+
+    /// 缓存最多 `capacity` 个有结果的 keyword 的验证通过的查询结果（0 表示不缓存）
+    ///
+    /// 条目在 Manager 为归属节点发布新的根哈希后失效（见 [`crate::core::query_cache`]）
+    pub fn with_query_cache(mut self, capacity: usize) -> Self {
+        self.query_cache = std::mem::take(&mut self.query_cache).with_positive(capacity);
+        self
+    }
+
+    /// 缓存最多 `capacity` 个已验证不存在的 keyword（0 表示不缓存），与有结果的 keyword 分开计算容量
+    pub fn with_negative_cache(mut self, capacity: usize) -> Self {
+        self.query_cache = std::mem::take(&mut self.query_cache).with_negative(capacity);
+        self
+    }
+
+    /// 设置确认模式策略
+    pub fn with_ack_policy(mut self, policy: AckPolicy) -> Self {
+        self.ack_policy = policy;
+        self
+    }
+
+    /// 设置复制因子：每个 keyword 写入哈希环上顺时针的前 `factor` 个健康节点
+    ///
+    /// 大于 1 时单关键词查询会读取所有副本并修复不一致的副本（见 [`crate::core::read_repair`]）。
+    /// 运行时增删节点只迁移主副本，其余副本由读修复补齐
+    pub fn with_replication_factor(mut self, factor: usize) -> Self {
+        self.replication_factor = AtomicUsize::new(factor.max(1));
+        self
+    }
+
+    /// 设置多关键词请求（添加、删除、布尔查询）同时发往 storager 的最大并发数
+    pub fn with_fanout_limit(mut self, limit: usize) -> Self {
+        self.fanout_limit = AtomicUsize::new(limit.max(1));
+        self
+    }
+
+    /// 设置批量导入时每个 storager 缓冲多少条记录后发送一批
+    pub fn with_bulk_batch(mut self, records: usize) -> Self {
+        self.bulk_batch = records.max(1);
+        self
+    }
+
+    /// 设置 storager 调用的重试次数、退避时间和单次超时（默认见 [`RetryPolicy::default`]）
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = RetryPolicy {
+            max_attempts: policy.max_attempts.max(1),
+            ..policy
+        };
+        self
+    }
+
+    /// 设置到 storager 的消息大小上限、压缩算法和 keepalive（必须在处理任何请求之前调用）
+    pub fn with_transport(mut self, transport: TransportConfig) -> Self {
+        self.channels = Arc::new(ChannelPool::with_transport(transport));
+        self
+    }
+
+    /// 设置一致性哈希环使用的哈希函数（必须在处理任何请求之前调用）
+    pub fn with_ring_hasher(mut self, hasher: RingHasher) -> Self {
+        self.router = self.router.with_hasher(hasher);
+        self
+    }
+
+    /// 持久化路由表到指定文件
+    ///
+    /// 文件已存在时从中恢复哈希环和 storager 地址（覆盖构造时传入的地址和哈希函数），
+    /// 否则立即写入当前路由表。之后每次拓扑变更都应调用 [`persist_ring`](Self::persist_ring)
+    pub fn with_ring_state(mut self, path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if path.exists() {
+            self.router = Router::load(&path)?;
+        } else {
+            self.router.save(&path)?;
+        }
+        self.ring_state = Some(path);
+        Ok(self)
+    }
+
+    /// 把已发布的根哈希持久化到指定文件，文件已存在时从中恢复
+    ///
+    /// 之后每次发布新的根哈希都会写入文件。未配置时重启会丢失跟踪的根哈希，
+    /// 已有数据的查询在下一次写入之前都报告为未验证
+    pub fn with_root_state(mut self, path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let (store, tracked) = RootStore::open(path)?;
+        self.root_hashes = Arc::new(RwLock::new(tracked.roots));
+        self.root_versions = Arc::new(RwLock::new(tracked.versions));
+        self.root_store = Arc::new(store);
+        Ok(self)
+    }
+
+    /// 把当前跟踪的根哈希写入文件（未配置文件时不做任何事）
+    pub fn persist_roots(&self) -> std::io::Result<()> {
+        let versions = self.root_versions.read().unwrap();
+        self.root_store
+            .save(&self.root_hashes.read().unwrap(), &versions)
+    }
+
+    /// 把 fid 反向索引持久化到指定文件，文件已存在时从中恢复
+    pub fn with_fid_index(mut self, path: impl AsRef<Path>) -> std::io::Result<Self> {
+        self.fid_index = FidIndex::open(path)?;
+        Ok(self)
+    }
+
+    /// 在反向索引中记录 fid 加入或离开了 `keywords`，并写入索引文件
+    ///
+    /// 写入文件失败只打印警告：变更已经应用到 storager，不能再报告为失败
+    pub(crate) fn index_fid<'a>(
+        &self,
+        kind: MutationKind,
+        namespace: &str,
+        fid: &str,
+        keywords: impl IntoIterator<Item = &'a String>,
+    ) {
+        match kind {
+            MutationKind::Add => self.fid_index.insert(namespace, fid, keywords),
+            MutationKind::Delete => self.fid_index.remove(namespace, fid, keywords),
+        }
+        if let Err(e) = self.fid_index.persist() {
+            warn!("Failed to persist fid index: {}", e);
+        }
+    }
+
+    /// 要求请求携带 API token，并按 token 对应的权限授权
+    pub fn with_access_control(mut self, access: AccessControl) -> Self {
+        self.access = Some(Arc::new(access));
+        self
+    }
+
+    /// 服务端使用的认证拦截器（未启用访问控制时放行所有请求）
+    pub fn auth_interceptor(&self) -> AuthInterceptor {
+        AuthInterceptor(self.access.clone())
+    }
+
+    /// 认证请求方并检查访问级别
+    ///
+    /// 经过 [`AuthInterceptor`] 的请求直接使用它放入的身份，否则在这里校验 token
+    pub(crate) fn authorize<T>(
+        &self,
+        request: &Request<T>,
+        access: Access,
+    ) -> Result<Caller, ManagerError> {
+        let Some(control) = &self.access else {
+            return Ok(Caller::Anyone);
+        };
+        let principal = match request.extensions().get::<Principal>() {
+            Some(principal) => principal.clone(),
+            None => control.authenticate(request.metadata())?,
+        };
+        principal.authorize(access)
+    }
+
+    /// 把当前路由表写入快照文件（未配置快照文件时不做任何事）
+    pub fn persist_ring(&self) -> std::io::Result<()> {
+        match &self.ring_state {
+            Some(path) => self.router.save(path),
+            None => Ok(()),
         }
     }
 
+    /// 使用指定的时间源（测试和确定性模拟使用）
+    ///
+    /// 会重新创建审计日志，因此必须在处理任何请求之前调用
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.audit_log = Arc::new(AuditLog::with_clock(clock.clone()));
+        self.clock = clock;
+        self
+    }
+
+    /// 时间源
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    /// 变更审计日志（可以 clone 后交给后台导出任务）
+    pub fn audit_log(&self) -> &Arc<AuditLog> {
+        &self.audit_log
+    }
+
+    /// 登记一次迁移，返回迁移 id
+    ///
+    /// 必须在路由切换到新拓扑之前调用。迁移结束前，落在迁移区间内的关键词
+    /// 会同时查询新旧节点（影子读），保证数据复制期间查询结果不出现空洞
+    pub fn begin_migration(&self, plan: &RebalancePlan) -> u64 {
+        self.migrations
+            .begin(plan, |name| self.router.get_storager_addr(name))
+    }
+
+    /// 记录关键词已复制到新节点，`audit_id` 为复制写入的审计 id
+    pub fn mark_migrated(&self, keyword: &str, audit_id: u64) {
+        self.migrations.mark_copied(keyword, audit_id);
+    }
+
+    /// 结束迁移，停止对其区间做影子读
+    pub fn finish_migration(&self, migration_id: u64) -> bool {
+        self.migrations.finish(migration_id)
+    }
+
+    /// 运行时加入 storager
+    ///
+    /// # 参数
+    ///
+    /// * `name` - 环上的节点名称，`None` 时自动命名为 `storager-{idx}`
+    /// * `addr` - storager 通告地址
+    /// * `virtual_nodes` - 虚拟节点数量
+    ///
+    /// 切换路由之前把迁移区间内的 keyword 复制到新节点并验证（见 [`crate::key_migration`]），
+    /// 之后持久化路由表（见 [`with_ring_state`](Self::with_ring_state)）。复制失败时不加入节点
+    pub async fn register_storager(
+        &self,
+        name: Option<&str>,
+        addr: &str,
+        virtual_nodes: usize,
+    ) -> Result<MembershipChange, String> {
+        validate_address(addr)?;
+        if virtual_nodes == 0 {
+            return Err("virtual_nodes must be positive".to_string());
+        }
+
+        let _topology = self.topology.write().await;
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => self.router.next_storager_name(),
+        };
+        let plan = self
+            .router
+            .plan_add_named_storager(&name, virtual_nodes)
+            .ok_or_else(|| format!("storager '{}' is already registered", name))?;
+
+        let migrated = self.migrate_keywords(&plan, Some((&name, addr))).await?;
+        self.router
+            .add_named_storager(&name, addr.to_string(), virtual_nodes);
+        self.persist_topology_change();
+        info!(
+            "Registered storager {} at {} ({:.1}% of the hash space moved, {} keyword(s) copied)",
+            name,
+            addr,
+            plan.hash_space_fraction() * 100.0,
+            migrated.keywords
+        );
+
+        Ok(MembershipChange {
+            name,
+            plan,
+            migrated,
+        })
+    }
+
+    /// 运行时移除 storager（不能移除最后一个节点）
+    ///
+    /// 与 [`register_storager`](Self::register_storager) 相同，切换路由之前把节点上的
+    /// keyword 复制到接管它们的节点，因此要移除的节点必须仍然可以访问
+    pub async fn deregister_storager(&self, name: &str) -> Result<MembershipChange, String> {
+        let _topology = self.topology.write().await;
+        let plan = self
+            .router
+            .plan_remove_storager(name)
+            .ok_or_else(|| format!("unknown storager '{}'", name))?;
+        if self.router.storager_count() == 1 {
+            return Err("cannot deregister the last storager".to_string());
+        }
+
+        let migrated = self.migrate_keywords(&plan, None).await?;
+        if let Some(addr) = self.router.get_storager_addr(name) {
+            self.channels.evict(&addr);
+        }
+        self.router.remove_storager(name);
+        self.persist_topology_change();
+        info!(
+            "Deregistered storager {} ({:.1}% of the hash space moved, {} keyword(s) copied)",
+            name,
+            plan.hash_space_fraction() * 100.0,
+            migrated.keywords
+        );
+
+        Ok(MembershipChange {
+            name: name.to_string(),
+            plan,
+            migrated,
+        })
+    }
+
+    /// 调整各 storager 的虚拟节点数量，使它们占有的哈希空间尽量接近
+    ///
+    /// 与 [`register_storager`](Self::register_storager) 相同，切换路由之前复制换主区间内的
+    /// keyword。`dry_run` 时只返回迁移计划。返回计划、复制的数据量以及调整前后的不均衡度
+    /// （占有最多的节点相对平均比例的倍数）
+    pub async fn rebalance_now(&self, dry_run: bool) -> Result<Rebalance, String> {
+        let _topology = self.topology.write().await;
+        let imbalance_before = self.router.imbalance();
+        let (ring, plan) = self.router.plan_rebalance();
+        let imbalance_after = ring.imbalance();
+        if dry_run || plan.ranges.is_empty() {
+            return Ok(Rebalance {
+                plan,
+                migrated: MigrationSummary::default(),
+                imbalance_before,
+                imbalance_after,
+            });
+        }
+
+        let migrated = self.migrate_keywords(&plan, None).await?;
+        self.router.replace_ring(ring);
+        self.persist_topology_change();
+        info!(
+            "Rebalanced ring (imbalance {:.2} -> {:.2}, {:.1}% of the hash space moved, {} keyword(s) copied)",
+            imbalance_before,
+            imbalance_after,
+            plan.hash_space_fraction() * 100.0,
+            migrated.keywords
+        );
+
+        Ok(Rebalance {
+            plan,
+            migrated,
+            imbalance_before,
+            imbalance_after,
+        })
+    }
+
+    /// 保存路由表快照、已发布的根哈希和 fid 反向索引（未配置的跳过）
+    pub fn persist_state(&self) -> Result<(), ManagerError> {
+        self.persist_ring().map_err(|e| ManagerError::Persist {
+            what: "ring state",
+            message: e.to_string(),
+        })?;
+        self.persist_roots().map_err(|e| ManagerError::Persist {
+            what: "root hashes",
+            message: e.to_string(),
+        })?;
+        self.fid_index.persist().map_err(|e| ManagerError::Persist {
+            what: "fid index",
+            message: e.to_string(),
+        })
+    }
+
+    /// 进程退出之前调用：等待进行中的写请求和拓扑变更（包括 SIGHUP 触发的重新加载）完成，
+    /// 再保存 Manager 状态
+    ///
+    /// 调用时服务应已停止接受请求（见 [`common::net::serve_with_drain`]）
+    pub async fn shutdown(&self) -> Result<(), ManagerError> {
+        let _topology = self.topology.write().await;
+        self.persist_state()
+    }
+
+    /// 拓扑已经切换，持久化失败只记录日志（重启后会回到旧拓扑）
+    fn persist_topology_change(&self) {
+        if let Err(e) = self.persist_ring() {
+            warn!("Failed to persist ring state: {}", e);
+        }
+    }
+
+    /// 影子读发现的新旧节点结果差异
+    pub fn read_discrepancies(&self) -> Vec<ReadDiscrepancy> {
+        self.migrations.discrepancies()
+    }
+
+    /// Manager 为 storager 的命名空间发布的当前根哈希
+    ///
+    /// 尚未发布时为空，对照空的根哈希验证的证明都会被拒绝
+    pub(crate) fn current_root(&self, key: &RootKey) -> RootHash {
+        self.root_hashes
+            .read()
+            .unwrap()
+            .get(key)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// storager 的命名空间在 `epoch` 时已验证的根哈希
+    ///
+    /// 历史中没有这个 epoch 时（尚未验证或已被丢弃）退回当前发布的根哈希
+    pub(crate) fn root_at(&self, key: &RootKey, epoch: u64) -> RootHash {
+        self.root_history
+            .root_at(key, epoch)
+            .unwrap_or_else(|| self.current_root(key))
+    }
+
+    /// 已发布根哈希对应的审计 id
+    pub(crate) fn root_version(&self, key: &RootKey) -> u64 {
+        self.root_versions
+            .read()
+            .unwrap()
+            .get(key)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// 所有 storager 各命名空间当前发布的根哈希及其版本
+    pub fn current_roots(&self) -> Vec<RootHashUpdate> {
+        let versions = self.root_versions.read().unwrap();
+        self.root_hashes
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(key, root_hash)| RootHashUpdate {
+                storager: key.storager.clone(),
+                root_hash: root_hash.clone(),
+                version: versions.get(key).copied().unwrap_or(0),
+                namespace: key.namespace.clone(),
+            })
+            .collect()
+    }
+
+    /// 订阅之后发布的根哈希
+    pub fn subscribe_roots(&self) -> broadcast::Receiver<RootHashUpdate> {
+        self.root_updates.subscribe()
+    }
+
     /// 使用一致性哈希环获取 keyword 对应的 storager
     pub(crate) fn get_storager_for_keyword(&self, keyword: &str) -> Option<(String, String)> {
         self.router.get_storager_for_keyword(keyword)
     }
 
+    /// keyword 的所有副本节点（主副本在前）
+    pub(crate) fn replicas_for_keyword(&self, keyword: &str) -> Vec<(String, String)> {
+        self.router
+            .get_replicas_for_keyword(keyword, self.replication_factor())
+    }
+
+    /// 把一次变更计入 keyword 的基数统计
+    ///
+    /// storager 应用变更后调用，无论证明是否验证通过；每次变更只在主副本上计入一次
+    pub(crate) fn record_cardinality(&self, kind: MutationKind, keyword: &str) {
+        match kind {
+            MutationKind::Add => self.admission.record_add(keyword),
+            MutationKind::Delete => self.admission.record_delete(keyword),
+        }
+    }
+
+    /// 检查所有 storager 的密码学子系统健康状态
+    ///
+    /// 报告不健康的节点会被移出路由，恢复后重新加入；无法连接的节点保持原状态
+    /// （连接失败由请求路径自行报错）。返回本次检查中报告不健康的节点及原因
+    pub async fn check_storager_health(&self) -> Vec<(String, String)> {
+        let mut unhealthy = Vec::new();
+        for (node_name, addr) in self.router.get_all_storagers() {
+            let mut client = match self.storager_client(&addr).await {
+                Ok(client) => client,
+                Err(_) => continue,
+            };
+            let health = match client.health(StoragerHealthRequest {}).await {
+                Ok(response) => response.into_inner(),
+                Err(e) => {
+                    self.channels.evict_on_error(&addr, &e);
+                    continue;
+                }
+            };
+
+            if health.crypto_ready != self.router.is_healthy(&node_name) {
+                info!(
+                    "Storager {} crypto subsystem is now {}{}",
+                    node_name,
+                    if health.crypto_ready {
+                        "ready"
+                    } else {
+                        "unhealthy"
+                    },
+                    if health.message.is_empty() {
+                        String::new()
+                    } else {
+                        format!(": {}", health.message)
+                    }
+                );
+            }
+            self.router.set_healthy(&node_name, health.crypto_ready);
+            if !health.crypto_ready {
+                unhealthy.push((node_name, health.message));
+            }
+        }
+        unhealthy
+    }
+
+    /// 从各 storager 拉取已发布根哈希的命名空间的 keyword 过滤器（见 [`crate::core::prefilter`]）
+    ///
+    /// 不支持过滤器或无法连接的 storager 保留之前的过滤器。返回本次更新的过滤器数量
+    pub async fn refresh_keyword_filters(&self) -> usize {
+        let addrs: HashMap<String, String> = self.router.get_all_storagers().into_iter().collect();
+        let keys: Vec<RootKey> = self.root_hashes.read().unwrap().keys().cloned().collect();
+        let fetches = keys
+            .into_iter()
+            .filter_map(|key| {
+                let addr = addrs.get(&key.storager)?.clone();
+                Some(async move {
+                    let request = KeywordFilterRequest {
+                        namespace: key.namespace.clone(),
+                    };
+                    let result = self
+                        .call_storager(&addr, "KeywordFilter", |mut client| {
+                            let request = request.clone();
+                            async move { client.keyword_filter(request).await }
+                        })
+                        .await;
+                    (key, result)
+                })
+            })
+            .collect();
+
+        let mut updated = 0;
+        for (key, result) in self.fan_out(fetches).await {
+            let response = match result {
+                Ok(response) => response,
+                Err(e) => {
+                    debug!("No keyword filter from {}: {}", key.storager, e);
+                    continue;
+                }
+            };
+            match BloomFilter::from_parts(response.bits, response.hashes) {
+                Some(filter) => {
+                    self.keyword_filters.update(key, response.root_hash, filter);
+                    updated += 1;
+                }
+                None => warn!("Storager {} sent an invalid keyword filter", key.storager),
+            }
+        }
+        updated
+    }
+
+    /// 从连接池获取 storager 客户端（支持 `http://` 和 `unix:` 地址）
+    pub(crate) async fn storager_client(
+        &self,
+        addr: &str,
+    ) -> Result<StoragerServiceClient<TracedChannel>, Status> {
+        self.channels
+            .get(addr)
+            .await
+            .map(|channel| self.channels.transport().storager_client(channel))
+            .map_err(|e| {
+                ManagerError::Connect {
+                    addr: addr.to_string(),
+                    message: e.to_string(),
+                }
+                .into()
+            })
+    }
+
+    /// 调用 storager RPC，按 [`RetryPolicy`] 重试连接失败、超时和 `UNAVAILABLE`
+    ///
+    /// 每次尝试都用新取得的连接调用一次 `call`；传输层错误和超时会丢弃连接池中的连接，
+    /// 下一次尝试重新连接。写请求的每次尝试必须携带同一个 `request_id`。
+    /// 返回的错误保留 storager 的状态码，客户端可以区分 fid 不存在、参数无效和节点故障
+    pub(crate) async fn call_storager<T, F, Fut>(
+        &self,
+        addr: &str,
+        rpc: &'static str,
+        call: F,
+    ) -> Result<T, ManagerError>
+    where
+        F: FnMut(StoragerServiceClient<TracedChannel>) -> Fut,
+        Fut: Future<Output = Result<Response<T>, Status>>,
+    {
+        self.call_storager_with(&self.retry, addr, rpc, call).await
+    }
+
+    /// 与 [`call_storager`](Self::call_storager) 相同，但使用指定的重试策略
+    pub(crate) async fn call_storager_with<T, F, Fut>(
+        &self,
+        policy: &RetryPolicy,
+        addr: &str,
+        rpc: &'static str,
+        call: F,
+    ) -> Result<T, ManagerError>
+    where
+        F: FnMut(StoragerServiceClient<TracedChannel>) -> Fut,
+        Fut: Future<Output = Result<Response<T>, Status>>,
+    {
+        call_with(&self.channels, policy, addr, rpc, call).await
+    }
+
+    /// 生成写请求的 `request_id`，同一次变更的所有重试共用一个
+    ///
+    /// 计数器从启动时间开始，Manager 重启后不会与之前的 id 重复
+    pub(crate) fn next_request_id(&self) -> String {
+        format!("mgr-{}", self.request_ids.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// 并发执行一组 storager 请求（同时进行的请求不超过并发上限），结果按输入顺序返回
+    pub(crate) async fn fan_out<F: Future>(&self, requests: Vec<F>) -> Vec<F::Output> {
+        stream::iter(requests)
+            .buffered(self.fanout_limit())
+            .collect()
+            .await
+    }
+
     /// 验证证明
-    pub(crate) fn verify_proof(&self, proof: &[u8], root_hash: &[u8]) -> bool {
+    pub(crate) fn verify_proof(&self, proof: &Proof, root_hash: &[u8]) -> bool {
         self.verifier.verify(proof, root_hash)
     }
 
-    /// 更新 storager 的根哈希
-    pub(crate) fn update_root_hash(&self, storager_name: String, root_hash: RootHash) {
-        let mut hashes = self.root_hashes.write().unwrap();
-        hashes.insert(storager_name, root_hash);
+    /// 密码学累加器的查询证明中 keyword 的累加器必须等于 Manager 跟踪的值
+    /// （见 [`crate::core::accumulators`]），其他证明不在这里检查
+    ///
+    /// 与根哈希一样，storager 的命名空间尚未发布过根哈希、也没有跟踪 keyword 的值时一律拒绝
+    pub(crate) fn matches_tracked_accumulator(
+        &self,
+        key: &RootKey,
+        keyword: &str,
+        proof: &Proof,
+    ) -> bool {
+        if !matches!(
+            proof,
+            Proof::AccumulatorMembership(_) | Proof::AccumulatorNonMembership(_)
+        ) {
+            return true;
+        }
+        let tracked = self.root_store.accumulators().value(key, keyword);
+        if tracked == *empty_accumulator() && !self.root_hashes.read().unwrap().contains_key(key) {
+            warn!(
+                "No accumulator is tracked for {} in namespace '{}'",
+                key.storager, key.namespace
+            );
+            return false;
+        }
+        let matches = query_accumulator(proof).is_some_and(|proven| proven == tracked);
+        if !matches {
+            warn!(
+                "Accumulator of '{}' from {} differs from the tracked value",
+                keyword, key.storager
+            );
+        }
+        matches
+    }
+
+    /// 验证单关键词查询的证明，并检查证明与返回的 `fids` 一致：
+    /// 结果为空时证明必须表明 keyword 确实不存在，否则结果必须是完整的，
+    /// 并且证明中的累加器是 Manager 为 `key` 跟踪的值
+    pub(crate) fn verify_keyword_proof(
+        &self,
+        key: &RootKey,
+        proof: &Proof,
+        root_hash: &[u8],
+        keyword: &str,
+        fids: &[String],
+    ) -> bool {
+        self.verify_proof(proof, root_hash)
+            && self.matches_tracked_accumulator(key, keyword, proof)
+            && if fids.is_empty() {
+                self.verifier.verify_absence(proof, keyword)
+            } else {
+                self.verifier.verify_completeness(proof, keyword, fids)
+            }
+    }
+
+    /// 计算写入 `namespace` 的请求实际使用的确认模式
+    pub(crate) fn effective_ack_mode(&self, requested: AckMode, namespace: &str) -> AckMode {
+        self.ack_policy.effective_mode(requested, namespace)
+    }
+
+    /// 处理 storager 返回的变更证明
+    ///
+    /// 同步模式下立即验证证明并发布根哈希；异步模式下在后台验证，
+    /// 验证完成前审计记录保持 Pending 状态。推迟生成的证明在后台用 GetProof 取回后验证，
+    /// 取回之前审计记录中是空证明
+    ///
+    /// 返回: (是否可以确认, 审计 id)
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn settle_mutation(
+        &self,
+        ack_mode: AckMode,
+        kind: MutationKind,
+        key: RootKey,
+        keyword: &str,
+        fid: &str,
+        proof: MutationProof,
+        root_hash: RootHash,
+        epoch: u64,
+        sketch_digests: Vec<RootHash>,
+    ) -> (bool, u64) {
+        let (ok, ids) = self.settle_batch(
+            ack_mode,
+            kind,
+            key,
+            &[keyword.to_string()],
+            fid,
+            proof,
+            root_hash,
+            epoch,
+            sketch_digests,
+        );
+        (ok, ids[0])
+    }
+
+    /// 处理 storager 对同一 fid 多个 keyword 的批量变更证明
+    ///
+    /// 证明只验证一次，每个 keyword 各记录一条审计记录（共享同一证明和根哈希），
+    /// 根哈希以最后一条记录的 id 发布，并按 storager 的 `epoch` 记入根哈希历史。
+    /// 密码学累加器模式下验证通过的证明同时推进每个 keyword 跟踪的累加器值；
+    /// 添加时 storager 报告的 `sketch_digests`（每个 keyword 一个）在验证通过后记录
+    ///
+    /// 返回: (是否可以确认, 每个 keyword 的审计 id)
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn settle_batch(
+        &self,
+        ack_mode: AckMode,
+        kind: MutationKind,
+        key: RootKey,
+        keywords: &[String],
+        fid: &str,
+        proof: MutationProof,
+        root_hash: RootHash,
+        epoch: u64,
+        sketch_digests: Vec<RootHash>,
+    ) -> (bool, Vec<u64>) {
+        let recorded = match &proof {
+            MutationProof::Ready(proof) => proof.clone(),
+            MutationProof::Deferred { .. } => Proof::Custom(Vec::new()),
+        };
+        let record = |status: AuditStatus| -> Vec<u64> {
+            keywords
+                .iter()
+                .map(|keyword| {
+                    self.audit_log.record(
+                        kind,
+                        &key.storager,
+                        keyword,
+                        fid,
+                        ack_mode,
+                        root_hash.clone(),
+                        recorded.clone(),
+                        status,
+                    )
+                })
+                .collect()
+        };
+
+        match ack_mode {
+            AckMode::Sync => {
+                // 同步确认的写请求不推迟证明
+                let verified = matches!(&proof, MutationProof::Ready(proof)
+                    if self.verify_proof(proof, &root_hash)
+                        && self
+                            .root_store
+                            .accumulators()
+                            .track(kind, &key, keywords, fid, proof));
+                let status = if verified {
+                    AuditStatus::Verified
+                } else {
+                    AuditStatus::Rejected
+                };
+                let ids = record(status);
+                if verified {
+                    Self::record_sketches(
+                        self.root_store.sketches(),
+                        &key,
+                        keywords,
+                        epoch,
+                        sketch_digests,
+                    );
+                }
+                if let (true, Some(&id)) = (verified, ids.last()) {
+                    Self::publish_root(
+                        &self.root_hashes,
+                        &self.root_versions,
+                        &self.root_history,
+                        &self.root_store,
+                        &self.root_updates,
+                        key,
+                        root_hash,
+                        Some(epoch),
+                        id,
+                    );
+                }
+                (verified, ids)
+            }
+            AckMode::Async => {
+                let ids = record(AuditStatus::Pending);
+
+                let ads_mode = self.ads_mode();
+                let audit_log = self.audit_log.clone();
+                let root_hashes = self.root_hashes.clone();
+                let root_versions = self.root_versions.clone();
+                let root_history = self.root_history.clone();
+                let root_store = self.root_store.clone();
+                let root_updates = self.root_updates.clone();
+                let pending = ids.clone();
+                let (keywords, fid) = (keywords.to_vec(), fid.to_string());
+                let (channels, retry) = (self.channels.clone(), self.retry.clone());
+                let namespace = key.namespace.clone();
+                // 后台验证仍记在发起写入的 RPC 的 trace 下
+                let span = tracing::Span::current();
+                let settle = move |proof: Option<Proof>| {
+                    let _entered = span.enter();
+                    let verified = proof.is_some_and(|proof| {
+                        ProofVerifier::new(ads_mode).verify(&proof, &root_hash)
+                            && root_store
+                                .accumulators()
+                                .track(kind, &key, &keywords, &fid, &proof)
+                    });
+                    if verified {
+                        for &id in &pending {
+                            audit_log.set_status(id, AuditStatus::Confirmed);
+                        }
+                        Self::record_sketches(
+                            root_store.sketches(),
+                            &key,
+                            &keywords,
+                            epoch,
+                            sketch_digests,
+                        );
+                        if let Some(&id) = pending.last() {
+                            Self::publish_root(
+                                &root_hashes,
+                                &root_versions,
+                                &root_history,
+                                &root_store,
+                                &root_updates,
+                                key,
+                                root_hash,
+                                Some(epoch),
+                                id,
+                            );
+                        }
+                    } else {
+                        warn!(
+                            "Async proof verification failed for audit entries {:?}",
+                            pending
+                        );
+                        for &id in &pending {
+                            audit_log.set_status(id, AuditStatus::Rejected);
+                        }
+                    }
+                };
+                match proof {
+                    MutationProof::Ready(proof) => {
+                        tokio::task::spawn_blocking(move || settle(Some(proof)));
+                    }
+                    MutationProof::Deferred {
+                        storager_addr,
+                        handle,
+                    } => {
+                        let (audit_log, pending) = (self.audit_log.clone(), ids.clone());
+                        let fetch = async move {
+                            let proof = match fetch_deferred_proof(
+                                &channels,
+                                &retry,
+                                &storager_addr,
+                                &namespace,
+                                handle,
+                            )
+                            .await
+                            {
+                                Ok(proof) => {
+                                    for &id in &pending {
+                                        audit_log.set_proof(id, proof.clone());
+                                    }
+                                    Some(proof)
+                                }
+                                // 包括句柄在取回之前被 storager 淘汰，审计记录标记为 Rejected
+                                Err(e) => {
+                                    warn!(
+                                        "Failed to fetch deferred proof {} from {}: {}",
+                                        handle, storager_addr, e
+                                    );
+                                    None
+                                }
+                            };
+                            let _ = tokio::task::spawn_blocking(move || settle(proof)).await;
+                        };
+                        tokio::spawn(fetch.instrument(tracing::Span::current()));
+                    }
+                }
+
+                (true, ids)
+            }
+        }
+    }
+
+    /// 记录一次已验证的添加报告的草图摘要，数量与 keyword 不符时不记录
+    pub(crate) fn record_sketches(
+        sketches: &KeywordSketches,
+        key: &RootKey,
+        keywords: &[String],
+        epoch: u64,
+        digests: Vec<RootHash>,
+    ) {
+        if digests.len() != keywords.len() {
+            if !digests.is_empty() {
+                warn!(
+                    "{} reported {} sketch digest(s) for {} keyword(s)",
+                    key.storager,
+                    digests.len(),
+                    keywords.len()
+                );
+            }
+            return;
+        }
+        for (keyword, digest) in keywords.iter().zip(digests) {
+            sketches.record(key, keyword, epoch, digest);
+        }
+    }
+
+    /// 发布 storager 命名空间已验证的根哈希，并推送给订阅者
+    ///
+    /// 带 `epoch` 的根哈希先记入根哈希历史，只有最新 epoch 的根会成为当前根
+    /// （并发写入的验证可能乱序完成）；不带 epoch 时只接受比当前更新的审计 id。
+    /// 新的当前根写入根哈希文件，写入失败只打印警告
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn publish_root(
+        root_hashes: &RwLock<HashMap<RootKey, RootHash>>,
+        root_versions: &RwLock<HashMap<RootKey, u64>>,
+        root_history: &RootHistory,
+        root_store: &RootStore,
+        root_updates: &broadcast::Sender<RootHashUpdate>,
+        key: RootKey,
+        root_hash: RootHash,
+        epoch: Option<u64>,
+        audit_id: u64,
+    ) {
+        // 持有版本锁完成记录和发布，避免较旧的 epoch 在较新的之后发布
+        let mut versions = root_versions.write().unwrap();
+        let latest = versions.entry(key.clone()).or_insert(0);
+        let newest = match epoch {
+            Some(epoch) => root_history.record(&key, epoch, root_hash.clone()),
+            None => audit_id > *latest,
+        };
+        if !newest {
+            return;
+        }
+
+        *latest = (*latest).max(audit_id);
+        let version = *latest;
+        let mut roots = root_hashes.write().unwrap();
+        roots.insert(key.clone(), root_hash.clone());
+        if let Err(e) = root_store.save(&roots, &versions) {
+            warn!("Failed to persist root hashes: {}", e);
+        }
+        drop(roots);
+        // 没有订阅者时发送失败，忽略即可
+        let _ = root_updates.send(RootHashUpdate {
+            storager: key.storager,
+            root_hash,
+            version,
+            namespace: key.namespace,
+        });
     }
 
     /// 合并多个证明
-    pub(crate) fn combine_proofs(&self, proofs: &[Vec<u8>]) -> Vec<u8> {
+    pub(crate) fn combine_proofs(&self, proofs: &[Proof]) -> Option<Proof> {
         self.verifier.combine_proofs(proofs)
     }
 
@@ -66,8 +1169,192 @@ impl Manager {
         self.verifier.ads_mode()
     }
 
+    /// 启动以来查询证明的验证代价（见 [`crate::core::proof_stats`]）
+    pub fn proof_stats(&self) -> ProofStatsSnapshot {
+        self.proof_stats.snapshot()
+    }
+
+    /// 查询结果缓存的命中统计
+    pub fn query_cache_stats(&self) -> QueryCacheStats {
+        self.query_cache.stats()
+    }
+
+    /// 一致性哈希环使用的哈希函数
+    pub fn ring_hasher(&self) -> RingHasher {
+        self.router.hasher()
+    }
+
     /// 获取所有 storager 节点信息
     pub fn get_storagers(&self) -> Vec<(String, String)> {
         self.router.get_all_storagers()
     }
+
+    /// 当前的复制因子
+    pub fn replication_factor(&self) -> usize {
+        self.replication_factor.load(Ordering::Relaxed)
+    }
+
+    /// 当前多关键词请求的最大并发数
+    pub fn fanout_limit(&self) -> usize {
+        self.fanout_limit.load(Ordering::Relaxed)
+    }
+}
+
+/// 通过 `channels` 调用 storager，按 `policy` 重试（见 [`Manager::call_storager`]）
+async fn call_with<T, F, Fut>(
+    channels: &ChannelPool,
+    policy: &RetryPolicy,
+    addr: &str,
+    rpc: &'static str,
+    mut call: F,
+) -> Result<T, ManagerError>
+where
+    F: FnMut(StoragerServiceClient<TracedChannel>) -> Fut,
+    Fut: Future<Output = Result<Response<T>, Status>>,
+{
+    let span = tracing::info_span!("storager_call", rpc, addr);
+    let mut attempt = 1;
+    async move {
+        loop {
+            let error = match channels.get(addr).await {
+                Ok(channel) => {
+                    let response = policy
+                        .within_deadline(call(channels.transport().storager_client(channel)))
+                        .await;
+                    match response {
+                        Some(Ok(response)) => return Ok(response.into_inner()),
+                        Some(Err(status)) => {
+                            channels.evict_on_error(addr, &status);
+                            ManagerError::Storager {
+                                rpc,
+                                code: status.code(),
+                                message: status.message().to_string(),
+                            }
+                        }
+                        None => {
+                            channels.evict(addr);
+                            ManagerError::Timeout {
+                                rpc,
+                                timeout: policy.timeout.unwrap_or_default(),
+                            }
+                        }
+                    }
+                }
+                Err(e) => ManagerError::Connect {
+                    addr: addr.to_string(),
+                    message: e.to_string(),
+                },
+            };
+            if !error.is_transient() || !policy.can_retry(attempt) {
+                return Err(error);
+            }
+
+            let backoff = policy.backoff(attempt);
+            warn!(
+                "{} (attempt {}/{}), retrying in {:?}",
+                error, attempt, policy.max_attempts, backoff
+            );
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+    .instrument(span)
+    .await
+}
+
+/// 用 GetProof 取回 storager 推迟生成的变更证明，等待它生成完成
+async fn fetch_deferred_proof(
+    channels: &ChannelPool,
+    policy: &RetryPolicy,
+    storager_addr: &str,
+    namespace: &str,
+    handle: u64,
+) -> Result<Proof, ManagerError> {
+    let request = GetProofRequest {
+        proof_handle: handle,
+        namespace: namespace.to_string(),
+    };
+    let resp = call_with(channels, policy, storager_addr, "GetProof", |mut client| {
+        let request = request.clone();
+        async move { client.get_proof(request).await }
+    })
+    .await?;
+    Proof::try_from(resp.proof).map_err(ManagerError::InvalidProof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fan_out_is_bounded_and_ordered() {
+        let manager = Manager::new(vec![], AdsMode::MerkleTree).with_fanout_limit(3);
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let requests = (0..10u64)
+            .map(|i| {
+                let (running, peak) = (&running, &peak);
+                async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    // 后发出的请求先完成，结果仍按输入顺序返回
+                    tokio::time::sleep(std::time::Duration::from_millis(10 - i)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    i
+                }
+            })
+            .collect();
+        let results = manager.fan_out(requests).await;
+
+        assert_eq!(results, (0..10).collect::<Vec<_>>());
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_runtime_membership() {
+        let path = std::env::temp_dir().join(format!("manager-ring-{}.json", std::process::id()));
+        let manager = Manager::new(vec![], AdsMode::MerkleTree)
+            .with_ring_state(&path)
+            .unwrap();
+
+        // 空环上加入第一个节点不需要迁移数据
+        let change = manager
+            .register_storager(Some("alpha"), "http://127.0.0.1:1", DEFAULT_VIRTUAL_NODES)
+            .await
+            .unwrap();
+        assert_eq!(change.name, "alpha");
+        assert!(change.plan.ranges.iter().all(|r| r.from.is_none()));
+        assert_eq!(change.migrated, MigrationSummary::default());
+
+        assert!(manager
+            .register_storager(Some("alpha"), "http://127.0.0.1:2", 150)
+            .await
+            .is_err());
+        assert!(manager
+            .register_storager(None, "127.0.0.1:2", 150)
+            .await
+            .is_err());
+
+        // 源节点无法访问时迁移失败，路由保持不变
+        let err = manager
+            .register_storager(Some("beta"), "http://127.0.0.1:2", 150)
+            .await
+            .unwrap_err();
+        assert!(err.contains("alpha"), "{}", err);
+        assert_eq!(manager.get_storagers().len(), 1);
+
+        // 拓扑变更已持久化
+        let restored = Manager::new(vec![], AdsMode::MerkleTree)
+            .with_ring_state(&path)
+            .unwrap();
+        assert_eq!(
+            restored.get_storagers(),
+            vec![("alpha".to_string(), "http://127.0.0.1:1".to_string())]
+        );
+
+        assert!(manager.deregister_storager("beta").await.is_err());
+        assert!(manager.deregister_storager("alpha").await.is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,223 @@
+//! keyword 范围查询与前缀查询
+//!
+//! keyword 按哈希分散在各个 storager 上，任意一段范围都可能落在所有 storager 上，
+//! 因此 Manager 向每个 storager 发送同一个查询，用它在对应 epoch 的根哈希验证范围证明，
+//! 再把各自证明出的 keyword 合并、排序。范围证明保证每个 storager 没有遗漏范围内的 keyword。
+//! 前缀查询（自动补全）是范围的特例，只返回每个 keyword 的 fid 数量。
+
+use crate::core::{verify_mpt_prefix_proof, verify_mpt_range_proof, RootKey};
+use crate::manager::Manager;
+use common::rpc::{
+    KeywordCount, KeywordPostings, PrefixQueryRequest, PrefixQueryResponse, RangeQueryRequest,
+    RangeQueryResponse,
+};
+use std::collections::BTreeMap;
+use tonic::Status;
+use tracing::{debug, warn};
+
+/// 一个 storager 返回的查询结果，`entries` 为 (keyword, 结果)
+struct RangeRead<T> {
+    node_name: String,
+    entries: Vec<(String, T)>,
+    verified: bool,
+}
+
+impl<T: PartialEq> RangeRead<T> {
+    /// 用证明出的结果替换 storager 返回的列表，两者不一致时视为验证失败
+    fn from_proof(node_name: String, reported: Vec<(String, T)>, proven: Vec<(String, T)>) -> Self {
+        let verified = proven == reported;
+        if !verified {
+            warn!("Range result from {} does not match its proof", node_name);
+        }
+        RangeRead {
+            node_name,
+            entries: proven,
+            verified,
+        }
+    }
+}
+
+impl Manager {
+    /// 查询 `[start_key, end_key)` 内的 keyword 及其 fid
+    ///
+    /// 任一 storager 不可用时整个查询失败：缺少它的证明就无法确认结果是完整的
+    pub(crate) async fn query_range(
+        &self,
+        namespace: &str,
+        start_key: &str,
+        end_key: &str,
+    ) -> Result<RangeQueryResponse, Status> {
+        let requests = self
+            .get_storagers()
+            .into_iter()
+            .map(|(node_name, storager_addr)| {
+                self.read_range(node_name, storager_addr, namespace, start_key, end_key)
+            })
+            .collect();
+        let reads = self.fan_out(requests).await;
+        let (merged, verified) = self.merge_reads(reads.into_iter().collect::<Result<_, _>>()?);
+
+        debug!(
+            "Range [{}, {}): {} keyword(s), verified={}",
+            start_key,
+            end_key,
+            merged.len(),
+            verified
+        );
+        Ok(RangeQueryResponse {
+            entries: merged
+                .into_iter()
+                .map(|(keyword, fids)| KeywordPostings { keyword, fids })
+                .collect(),
+            verified,
+        })
+    }
+
+    /// 查询以 `prefix` 开头的 keyword 及其 fid 数量
+    pub(crate) async fn query_prefix(
+        &self,
+        namespace: &str,
+        prefix: &str,
+    ) -> Result<PrefixQueryResponse, Status> {
+        let requests = self
+            .get_storagers()
+            .into_iter()
+            .map(|(node_name, storager_addr)| {
+                self.read_prefix(node_name, storager_addr, namespace, prefix)
+            })
+            .collect();
+        let reads = self.fan_out(requests).await;
+        let (merged, verified) = self.merge_reads(reads.into_iter().collect::<Result<_, _>>()?);
+
+        debug!(
+            "Prefix '{}': {} keyword(s), verified={}",
+            prefix,
+            merged.len(),
+            verified
+        );
+        Ok(PrefixQueryResponse {
+            keywords: merged
+                .into_iter()
+                .map(|(keyword, fid_count)| KeywordCount { keyword, fid_count })
+                .collect(),
+            verified,
+        })
+    }
+
+    /// 合并各 storager 的结果，返回按 keyword 排序的结果和是否全部验证通过
+    ///
+    /// 迁移中途 keyword 可能同时留在旧 storager 上，以路由中排名最靠前的副本为准
+    fn merge_reads<T>(&self, reads: Vec<RangeRead<T>>) -> (BTreeMap<String, T>, bool) {
+        let mut merged: BTreeMap<String, (usize, T)> = BTreeMap::new();
+        let mut verified = true;
+        for read in reads {
+            verified &= read.verified;
+            for (keyword, value) in read.entries {
+                let rank = self
+                    .replicas_for_keyword(&keyword)
+                    .iter()
+                    .position(|(node_name, _)| *node_name == read.node_name);
+                let Some(rank) = rank else {
+                    continue;
+                };
+                match merged.get(&keyword) {
+                    Some((best, _)) if *best <= rank => {}
+                    _ => {
+                        merged.insert(keyword, (rank, value));
+                    }
+                }
+            }
+        }
+        let merged = merged
+            .into_iter()
+            .map(|(keyword, (_, value))| (keyword, value))
+            .collect();
+        (merged, verified)
+    }
+
+    /// 向一个 storager 发送范围查询，并用响应 epoch 对应的根哈希验证范围证明
+    async fn read_range(
+        &self,
+        node_name: String,
+        storager_addr: String,
+        namespace: &str,
+        start_key: &str,
+        end_key: &str,
+    ) -> Result<RangeRead<Vec<String>>, Status> {
+        let request = RangeQueryRequest {
+            start_key: start_key.to_string(),
+            end_key: end_key.to_string(),
+            namespace: namespace.to_string(),
+        };
+        let resp = self
+            .call_storager(&storager_addr, "RangeQuery", |mut client| {
+                let request = request.clone();
+                async move { client.range_query(request).await }
+            })
+            .await?;
+        let reported = resp
+            .entries
+            .into_iter()
+            .map(|entry| (entry.keyword, entry.fids))
+            .collect();
+
+        let root_hash = self.root_at(&RootKey::new(node_name.clone(), namespace), resp.epoch);
+        match verify_mpt_range_proof(&resp.range_proof, start_key, end_key, &root_hash) {
+            Ok(proven) => Ok(RangeRead::from_proof(node_name, reported, proven)),
+            Err(e) => {
+                warn!("Range proof from {} rejected: {}", node_name, e);
+                Ok(RangeRead {
+                    node_name,
+                    entries: reported,
+                    verified: false,
+                })
+            }
+        }
+    }
+
+    /// 向一个 storager 发送前缀查询，并验证前缀子树证明
+    ///
+    /// 证明中的值是完整的 fid 列表，fid 数量由它计算
+    async fn read_prefix(
+        &self,
+        node_name: String,
+        storager_addr: String,
+        namespace: &str,
+        prefix: &str,
+    ) -> Result<RangeRead<u64>, Status> {
+        let request = PrefixQueryRequest {
+            prefix: prefix.to_string(),
+            namespace: namespace.to_string(),
+        };
+        let resp = self
+            .call_storager(&storager_addr, "QueryByPrefix", |mut client| {
+                let request = request.clone();
+                async move { client.query_by_prefix(request).await }
+            })
+            .await?;
+        let reported = resp
+            .keywords
+            .into_iter()
+            .map(|entry| (entry.keyword, entry.fid_count))
+            .collect();
+
+        let root_hash = self.root_at(&RootKey::new(node_name.clone(), namespace), resp.epoch);
+        match verify_mpt_prefix_proof(&resp.subtree_proof, prefix, &root_hash) {
+            Ok(proven) => {
+                let proven = proven
+                    .into_iter()
+                    .map(|(keyword, fids)| (keyword, fids.len() as u64))
+                    .collect();
+                Ok(RangeRead::from_proof(node_name, reported, proven))
+            }
+            Err(e) => {
+                warn!("Prefix proof from {} rejected: {}", node_name, e);
+                Ok(RangeRead {
+                    node_name,
+                    entries: reported,
+                    verified: false,
+                })
+            }
+        }
+    }
+}
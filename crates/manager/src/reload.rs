@@ -0,0 +1,340 @@
+//! 运行时重新加载配置
+//!
+//! 收到 SIGHUP 或 Admin `ReloadConfig` 时，Manager 不重启即可调整 storager 列表、
+//! 复制因子、并发数、根哈希历史容量、查询缓存容量和日志级别：
+//!
+//! - storager 列表与当前成员对比后逐个调用 [`register_storager`](Manager::register_storager) /
+//!   [`deregister_storager`](Manager::deregister_storager)，先加入后移除。每次变更在拓扑写锁下
+//!   先迁移 keyword 再切换路由，进行中的写请求完成后才开始迁移，不会被丢弃
+//! - 复制因子增大时先把已有的 keyword 复制到新增的副本节点，成功后才切换（见
+//!   [`crate::key_migration`]），在 storager 列表调整之后进行
+//! - 其余参数直接替换，之后开始的请求使用新值；缓存容量缩小时丢弃最久未使用的条目
+//!
+//! 某一项无法应用时记录在 [`ReloadReport::errors`] 中，其余项照常生效。
+
+use crate::key_migration::MigrationSummary;
+use crate::manager::{Manager, DEFAULT_VIRTUAL_NODES};
+use common::cli::parse_storager_addr;
+use common::telemetry::set_log_level;
+use common::SystemConfig;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use tracing::{info, warn};
+
+/// 可以重新加载的配置项，None 表示保持不变
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReloadConfig {
+    /// 期望的 storager 成员（`url` 或 `name=url`）
+    pub storagers: Option<Vec<String>>,
+    pub replication_factor: Option<usize>,
+    pub fanout_limit: Option<usize>,
+    pub root_history: Option<usize>,
+    /// 有结果的 keyword 的缓存容量，0 表示不缓存
+    pub query_cache: Option<usize>,
+    /// 已验证不存在的 keyword 的缓存容量，0 表示不缓存
+    pub negative_cache: Option<usize>,
+    pub log_level: Option<String>,
+}
+
+impl ReloadConfig {
+    /// 读取 SystemConfig 中可以重新加载的部分
+    pub fn from_system_config(config: &SystemConfig) -> Result<Self, String> {
+        let storagers = config
+            .storager_addrs
+            .iter()
+            .map(|entry| parse_storager_addr(entry))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ReloadConfig {
+            storagers: (!storagers.is_empty()).then_some(storagers),
+            replication_factor: config.replication_factor,
+            fanout_limit: config.fanout_limit,
+            root_history: config.root_history,
+            query_cache: config.query_cache,
+            negative_cache: config.negative_cache,
+            log_level: config.log_level.clone(),
+        })
+    }
+
+    /// 从 JSON 配置文件加载
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        Self::from_system_config(&SystemConfig::load(path)?)
+    }
+
+    /// 用 `other` 中给出的项覆盖当前值
+    pub fn merge(self, other: ReloadConfig) -> Self {
+        ReloadConfig {
+            storagers: other.storagers.or(self.storagers),
+            replication_factor: other.replication_factor.or(self.replication_factor),
+            fanout_limit: other.fanout_limit.or(self.fanout_limit),
+            root_history: other.root_history.or(self.root_history),
+            query_cache: other.query_cache.or(self.query_cache),
+            negative_cache: other.negative_cache.or(self.negative_cache),
+            log_level: other.log_level.or(self.log_level),
+        }
+    }
+}
+
+/// 一次重新加载的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReloadReport {
+    /// 加入的 storager 名称
+    pub added: Vec<String>,
+    /// 移除的 storager 名称
+    pub removed: Vec<String>,
+    /// 复制因子增大时复制到新副本的数据量
+    pub backfilled: MigrationSummary,
+    /// 未能应用的配置项
+    pub errors: Vec<String>,
+}
+
+impl Manager {
+    /// 设置 SIGHUP 时重新读取的配置文件（见 [`reload_from_file`](Self::reload_from_file)）
+    pub fn with_config_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.config_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// 重新读取配置文件，`overrides` 中给出的项优先
+    pub async fn reload_from_file(&self, overrides: ReloadConfig) -> Result<ReloadReport, String> {
+        let path = self
+            .config_path
+            .as_ref()
+            .ok_or_else(|| "the manager was started without --config".to_string())?;
+        let config = ReloadConfig::load(path)?.merge(overrides);
+        info!("Reloading configuration from {}", path.display());
+        Ok(self.reload(config).await)
+    }
+
+    /// 应用新的配置
+    pub async fn reload(&self, config: ReloadConfig) -> ReloadReport {
+        let mut report = ReloadReport::default();
+
+        if let Some(level) = &config.log_level {
+            match set_log_level(level) {
+                Ok(()) => info!("Log level set to {}", level),
+                Err(e) => report.errors.push(e),
+            }
+        }
+        if let Some(limit) = config.fanout_limit {
+            self.fanout_limit.store(limit.max(1), Ordering::Relaxed);
+        }
+        if let Some(capacity) = config.root_history {
+            self.root_history.set_capacity(capacity);
+        }
+        if let Some(capacity) = config.query_cache {
+            self.query_cache.set_capacity(capacity);
+        }
+        if let Some(capacity) = config.negative_cache {
+            self.query_cache.set_negative_capacity(capacity);
+        }
+        if let Some(storagers) = &config.storagers {
+            self.reconcile_storagers(storagers, &mut report).await;
+        }
+        if let Some(factor) = config.replication_factor {
+            match self.set_replication_factor(factor.max(1)).await {
+                Ok(copied) => report.backfilled = copied,
+                Err(e) => report
+                    .errors
+                    .push(format!("replication factor {}: {}", factor, e)),
+            }
+        }
+
+        info!(
+            "Configuration reloaded: replication factor {}, fan-out limit {}, root history {}, query cache {}/{}, {} storager(s) added, {} removed",
+            self.replication_factor(),
+            self.fanout_limit(),
+            self.root_history.capacity(),
+            self.query_cache.capacity(),
+            self.query_cache.negative_capacity(),
+            report.added.len(),
+            report.removed.len()
+        );
+        for error in &report.errors {
+            warn!("Reload: {}", error);
+        }
+        report
+    }
+
+    /// 让 storager 成员与 `entries` 一致
+    ///
+    /// 带名称的项按名称对应已有节点，不带名称的按地址对应；名称相同但地址不同时
+    /// 不会替换节点（需要先移除再加入）
+    async fn reconcile_storagers(&self, entries: &[String], report: &mut ReloadReport) {
+        if entries.is_empty() {
+            report
+                .errors
+                .push("storager list must not be empty".to_string());
+            return;
+        }
+        let current = self.get_storagers();
+        let mut keep = HashSet::new();
+        let mut missing = Vec::new();
+        for entry in entries {
+            let (name, addr) = match entry.split_once('=') {
+                Some((name, addr)) if !name.trim().is_empty() => (Some(name.trim()), addr.trim()),
+                _ => (None, entry.trim()),
+            };
+            let existing = current
+                .iter()
+                .find(|(n, a)| name.map_or(a == addr, |name| n == name));
+            match existing {
+                Some((n, a)) if a == addr => {
+                    keep.insert(n.clone());
+                }
+                Some((n, a)) => {
+                    keep.insert(n.clone());
+                    report.errors.push(format!(
+                        "storager '{}' is registered at {}, not {}; deregister it first",
+                        n, a, addr
+                    ));
+                }
+                None => missing.push((name, addr)),
+            }
+        }
+
+        for (name, addr) in missing {
+            match self
+                .register_storager(name, addr, DEFAULT_VIRTUAL_NODES)
+                .await
+            {
+                Ok(change) => {
+                    keep.insert(change.name.clone());
+                    report.added.push(change.name);
+                }
+                Err(e) => report.errors.push(format!("register {}: {}", addr, e)),
+            }
+        }
+        for (name, _) in current {
+            if keep.contains(&name) {
+                continue;
+            }
+            match self.deregister_storager(&name).await {
+                Ok(_) => report.removed.push(name),
+                Err(e) => report.errors.push(format!("deregister {}: {}", name, e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::AdsMode;
+
+    #[test]
+    fn test_merge_prefers_overrides() {
+        let file = ReloadConfig {
+            storagers: Some(vec!["http://[::1]:50052".to_string()]),
+            replication_factor: Some(2),
+            log_level: Some("info".to_string()),
+            ..ReloadConfig::default()
+        };
+        let merged = file.merge(ReloadConfig {
+            replication_factor: Some(3),
+            fanout_limit: Some(8),
+            ..ReloadConfig::default()
+        });
+        assert_eq!(
+            merged,
+            ReloadConfig {
+                storagers: Some(vec!["http://[::1]:50052".to_string()]),
+                replication_factor: Some(3),
+                fanout_limit: Some(8),
+                root_history: None,
+                query_cache: None,
+                negative_cache: None,
+                log_level: Some("info".to_string()),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reload_settings() {
+        let manager = Manager::new(
+            vec!["s1=http://127.0.0.1:1".to_string()],
+            AdsMode::MerkleTree,
+        );
+        let report = manager
+            .reload(ReloadConfig {
+                // 与当前成员相同：不迁移
+                storagers: Some(vec!["s1=http://127.0.0.1:1".to_string()]),
+                replication_factor: Some(3),
+                fanout_limit: Some(0),
+                root_history: Some(8),
+                query_cache: Some(16),
+                negative_cache: Some(4),
+                log_level: None,
+            })
+            .await;
+        assert_eq!(report, ReloadReport::default());
+        assert_eq!(manager.replication_factor(), 3);
+        assert_eq!(manager.fanout_limit(), 1);
+        assert_eq!(manager.root_history.capacity(), 8);
+        assert_eq!(manager.query_cache.capacity(), 16);
+        assert_eq!(manager.query_cache.negative_capacity(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_storagers() {
+        let manager = Manager::new(
+            vec![
+                "s1=http://127.0.0.1:1".to_string(),
+                "s2=http://127.0.0.1:2".to_string(),
+            ],
+            AdsMode::MerkleTree,
+        );
+
+        // 地址变化不替换节点；移除 s2 需要从不可达的节点迁移数据，失败时成员不变
+        let report = manager
+            .reload(ReloadConfig {
+                storagers: Some(vec!["s1=http://127.0.0.1:9".to_string()]),
+                ..ReloadConfig::default()
+            })
+            .await;
+        assert!(report.added.is_empty() && report.removed.is_empty());
+        assert_eq!(report.errors.len(), 2, "{:?}", report.errors);
+        assert!(report.errors[0].contains("deregister it first"));
+        assert!(report.errors[1].contains("s2"));
+        assert_eq!(manager.get_storagers().len(), 2);
+
+        let report = manager
+            .reload(ReloadConfig {
+                storagers: Some(Vec::new()),
+                ..ReloadConfig::default()
+            })
+            .await;
+        assert_eq!(report.errors, vec!["storager list must not be empty"]);
+    }
+
+    #[tokio::test]
+    async fn test_reload_from_file() {
+        let manager = Manager::new(vec!["http://127.0.0.1:1".to_string()], AdsMode::MerkleTree);
+        assert!(manager
+            .reload_from_file(ReloadConfig::default())
+            .await
+            .is_err());
+
+        let path = std::env::temp_dir().join(format!("manager-reload-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"num_clients": 1, "num_storagers": 1, "ads_mode": "MerkleTree",
+                "manager_addr": "http://127.0.0.1:6000",
+                "storager_addrs": ["http://127.0.0.1:1"], "client_addrs": [],
+                "replication_factor": 2, "root_history": 16}"#,
+        )
+        .unwrap();
+        let manager = manager.with_config_path(&path);
+        let report = manager
+            .reload_from_file(ReloadConfig {
+                root_history: Some(4),
+                ..ReloadConfig::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(report, ReloadReport::default());
+        assert_eq!(manager.replication_factor(), 2);
+        assert_eq!(manager.root_history.capacity(), 4);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
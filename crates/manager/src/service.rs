@@ -1,18 +1,50 @@
-use crate::manager::Manager;
-use common::parse_boolean_expr;
+use crate::core::migration::resolve_shadow_read;
+use crate::core::read_repair::{find_quorum, plan_repairs, quorum_size};
+use crate::core::{
+    Access, KeywordRead, MutationKind, ReplicaRepair, RootKey, ShadowChoice, UpdatePlan,
+    DELIVERED_EMPTY,
+};
+use crate::error::ManagerError;
+use crate::manager::{Manager, MutationProof, DEFAULT_VIRTUAL_NODES};
+use common::{
+    paginate, parse_boolean_expr, validate_namespace, AdsMode, BooleanExpr, ErrorKind, PageError,
+    Proof, RootHash,
+};
 use common::rpc::{
-    manager_service_server::ManagerService, storager_service_client::StoragerServiceClient,
-    AddRequest, AddResponse, DeleteRequest, DeleteResponse, QueryRequest, QueryResponse,
-    StoragerAddRequest, StoragerDeleteRequest, StoragerQueryRequest, UpdateRequest, UpdateResponse,
+    manager_service_server::ManagerService, AckMode, AddRequest, AddResponse, ApproxCountRequest,
+    ApproxCountResponse, BatchRequest, BatchResponse, BulkAddRecord, BulkAddResponse, DeleteRequest, DeleteResponse, DeregisterStoragerRequest,
+    DeregisterStoragerResponse, KeywordBreakdown, MovedKeyRange, PrefixQueryRequest, PrefixQueryResponse,
+    QueryRequest, QueryResponse, RangeQueryRequest, RangeQueryResponse,
+    RegisterStoragerRequest, RegisterStoragerResponse, StoragerAddRequest, StoragerApproxCountRequest, StoragerBatchAddRequest,
+    ProofMetrics, ProveDifferenceRequest, RootHashUpdate, StoragerBooleanQueryRequest, StoragerDeleteRequest,
+    StoragerQueryRequest, SubscribeRootHashesRequest, UpdateRequest,
+    UpdateResponse, WatchKeywordEvent, WatchKeywordRequest,
 };
+use common::fid_intern::{check_keyword, digest_entry, parse_digest_entry};
+use common::query_stream::QueryAssembler;
+use consistent_hash::RebalancePlan;
+use common::sketch::HyperLogLog;
+use esa_rust::merkle_tree::leaf_hash_bytes;
 use std::collections::{HashMap, HashSet};
-use tonic::{Request, Response, Status};
+use std::pin::Pin;
+use tokio_stream::wrappers::{BroadcastStream, ReceiverStream};
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{debug, info, warn};
 
 #[tonic::async_trait]
 impl ManagerService for Manager {
     async fn add(&self, request: Request<AddRequest>) -> Result<Response<AddResponse>, Status> {
+        let caller = self.authorize(&request, Access::Write)?;
         let req = request.into_inner();
-        println!("Manager received Add request for fid: {}", req.fid);
+        debug!("Manager received Add request for fid: {}", req.fid);
+        check_namespace(&req.namespace)?;
+        caller.check_keywords(&req.keywords)?;
+        check_reserved(&req.keywords)?;
+        let _fid = self.fid_locks.lock(&req.fid).await;
+        // 关键词迁移期间等待，保证迁移复制的快照包含所有已确认的写入
+        let _topology = self.topology.read().await;
+        let ack_mode = self.effective_ack_mode(req.ack_mode(), &req.namespace);
 
         // Deduplicate keywords to avoid adding the same element twice
         let unique_keywords: HashSet<String> = req.keywords.into_iter().collect();
@@ -22,48 +54,37 @@ impl ManagerService for Manager {
             return Ok(Response::new(AddResponse {
                 success: false,
                 message: "No keywords provided".to_string(),
+                pending_ops: vec![],
             }));
         }
         
-        println!("  Processing {} unique keyword(s)", keyword_count);
-
-        // Process each unique keyword
-        for keyword in &unique_keywords {
-            let (node_name, storager_addr) = self
-                .get_storager_for_keyword(keyword)
-                .ok_or_else(|| Status::internal("No storager available"))?;
-
-            // Connect to storager and send Add request
-            let mut client = StoragerServiceClient::connect(storager_addr.clone())
-                .await
-                .map_err(|e| Status::internal(format!("Failed to connect to storager: {}", e)))?;
-
-            let storager_req = StoragerAddRequest {
-                keyword: keyword.clone(),
-                fid: req.fid.clone(),
-            };
-
-            let response = client
-                .add(storager_req)
-                .await
-                .map_err(|e| Status::internal(format!("Storager Add failed: {}", e)))?;
-
-            let resp = response.into_inner();
+        debug!("Processing {} unique keyword(s)", keyword_count);
 
-            // Verify proof and update root hash
-            if self.verify_proof(&resp.proof, &resp.root_hash) {
-                self.update_root_hash(node_name, resp.root_hash);
-            } else {
-                return Ok(Response::new(AddResponse {
-                    success: false,
-                    message: "Proof verification failed".to_string(),
-                }));
-            }
+        // All keywords owned by the same storager go out in one BatchAdd
+        let expires_at_ms = self.expiry_deadline(req.ttl_seconds);
+        let (ok, pending_ops) = self
+            .batch_add_keywords(
+                &req.namespace,
+                &unique_keywords,
+                &req.fid,
+                ack_mode,
+                expires_at_ms,
+            )
+            .await?;
+        self.index_fid(MutationKind::Add, &req.namespace, &req.fid, &unique_keywords);
+        self.notify_watches(&req.namespace, &unique_keywords).await;
+        if !ok {
+            return Ok(Response::new(AddResponse {
+                success: false,
+                message: "Proof verification failed".to_string(),
+                pending_ops,
+            }));
         }
 
         Ok(Response::new(AddResponse {
             success: true,
             message: "Add operation completed successfully".to_string(),
+            pending_ops,
         }))
     }
 
@@ -71,79 +92,125 @@ impl ManagerService for Manager {
         &self,
         request: Request<QueryRequest>,
     ) -> Result<Response<QueryResponse>, Status> {
+        let caller = self.authorize(&request, Access::Read)?;
         let req = request.into_inner();
-        println!("Manager received Query request");
+        debug!("Manager received Query request");
+        check_namespace(&req.namespace)?;
 
-        match req.query_type {
+        // 估计查询代价，超出预算时拒绝或排入后台队列
+        let expr = match &req.query_type {
+            Some(common::rpc::query_request::QueryType::Keyword(keyword)) => {
+                BooleanExpr::Keyword(keyword.clone())
+            }
+            Some(common::rpc::query_request::QueryType::BooleanFunction(func)) => {
+                parse_boolean_expr(func).map_err(ManagerError::InvalidExpression)?
+            }
+            None => return Err(ManagerError::MissingQueryType.into()),
+        };
+        caller.check_keywords(expr.get_keywords())?;
+        let _admission = self.admission.admit(&expr, req.allow_background).await?;
+
+        let paged = req.page_size > 0 || !req.page_token.is_empty();
+        let response = match req.query_type {
+            Some(common::rpc::query_request::QueryType::Keyword(keyword)) if paged => {
+                // 单关键词分页查询，由 storager 分页
+                self.query_keyword_page(&req.namespace, &keyword, req.page_size, req.page_token)
+                    .await
+            }
             Some(common::rpc::query_request::QueryType::Keyword(keyword)) => {
                 // 单关键词查询
-                self.query_single_keyword(&keyword).await
+                self.query_single_keyword(&req.namespace, &keyword).await
             }
             Some(common::rpc::query_request::QueryType::BooleanFunction(func)) => {
-                // 布尔函数查询
-                self.query_boolean_function(&func).await
+                // 布尔函数查询，在 Manager 上计算完整结果后分页
+                let response = self
+                    .query_boolean_function(&req.namespace, &func, req.keyword_breakdown)
+                    .await?
+                    .into_inner();
+                let page = paginate_response(response, req.page_size, &req.page_token)?;
+                Ok(Response::new(page))
             }
-            None => Err(Status::invalid_argument("No query type specified")),
+            None => Err(ManagerError::MissingQueryType.into()),
+        }?;
+        // 分页查询只在最后一页验证证明，代价也只在最后一页记录一次
+        if let Some(metrics) = &response.get_ref().metrics {
+            self.proof_stats.record(metrics);
         }
+        Ok(response)
     }
 
     async fn delete(
         &self,
         request: Request<DeleteRequest>,
     ) -> Result<Response<DeleteResponse>, Status> {
+        let caller = self.authorize(&request, Access::Write)?;
         let req = request.into_inner();
-        println!("Manager received Delete request for fid: {}", req.fid);
+        debug!("Manager received Delete request for fid: {}", req.fid);
+        check_namespace(&req.namespace)?;
+        let _fid = self.fid_locks.lock(&req.fid).await;
+        let _topology = self.topology.read().await;
+        let ack_mode = self.effective_ack_mode(req.ack_mode(), &req.namespace);
 
         // Deduplicate keywords to avoid deleting the same element twice
-        let unique_keywords: HashSet<String> = req.keywords.into_iter().collect();
+        let mut unique_keywords: HashSet<String> = req.keywords.into_iter().collect();
+        // 只给出 fid 时从反向索引中取出它所在的全部 keyword（strict 模式除外）
+        if unique_keywords.is_empty() && !req.strict {
+            unique_keywords.extend(self.fid_index.keywords(&req.namespace, &req.fid));
+        }
+        caller.check_keywords(&unique_keywords)?;
+        check_reserved(&unique_keywords)?;
         let keyword_count = unique_keywords.len();
         
         if keyword_count == 0 {
             return Ok(Response::new(DeleteResponse {
                 success: false,
-                message: "No keywords provided".to_string(),
+                message: if req.strict {
+                    "No keywords provided".to_string()
+                } else {
+                    format!("No keywords provided or recorded for fid {}", req.fid)
+                },
+                pending_ops: vec![],
             }));
         }
         
-        println!("  Processing {} unique keyword(s)", keyword_count);
-
-        // Process each unique keyword
-        for keyword in &unique_keywords {
-            let (node_name, storager_addr) = self
-                .get_storager_for_keyword(keyword)
-                .ok_or_else(|| Status::internal("No storager available"))?;
+        debug!("Processing {} unique keyword(s)", keyword_count);
 
-            // Connect to storager and send Delete request
-            let mut client = StoragerServiceClient::connect(storager_addr.clone())
-                .await
-                .map_err(|e| Status::internal(format!("Failed to connect to storager: {}", e)))?;
-
-            let storager_req = StoragerDeleteRequest {
-                keyword: keyword.clone(),
-                fid: req.fid.clone(),
-            };
-
-            let response = client
-                .delete(storager_req)
-                .await
-                .map_err(|e| Status::internal(format!("Storager Delete failed: {}", e)))?;
-
-            let resp = response.into_inner();
-
-            // Verify proof and update root hash
-            if self.verify_proof(&resp.proof, &resp.root_hash) {
-                self.update_root_hash(node_name, resp.root_hash);
-            } else {
-                return Ok(Response::new(DeleteResponse {
-                    success: false,
-                    message: "Proof verification failed".to_string(),
-                }));
-            }
+        // Delete every keyword concurrently, then aggregate the outcomes
+        let requests = unique_keywords
+            .iter()
+            .map(|keyword| {
+                self.mutate_replicas(
+                    MutationKind::Delete,
+                    &req.namespace,
+                    keyword,
+                    &req.fid,
+                    ack_mode,
+                )
+            })
+            .collect();
+        let results = self.fan_out(requests).await;
+        // 部分 keyword 失败时，已经删除的 keyword 也要从反向索引中移除
+        let deleted = unique_keywords
+            .iter()
+            .zip(&results)
+            .filter(|(_, result)| result.is_ok())
+            .map(|(keyword, _)| keyword);
+        self.index_fid(MutationKind::Delete, &req.namespace, &req.fid, deleted);
+        self.notify_watches(&req.namespace, &unique_keywords).await;
+        let (ok, pending_ops) =
+            merge_outcomes(results.into_iter().collect::<Result<_, _>>()?, ack_mode);
+        if !ok {
+            return Ok(Response::new(DeleteResponse {
+                success: false,
+                message: "Proof verification failed".to_string(),
+                pending_ops,
+            }));
         }
 
         Ok(Response::new(DeleteResponse {
             success: true,
             message: "Delete operation completed successfully".to_string(),
+            pending_ops,
         }))
     }
 
@@ -151,221 +218,1400 @@ impl ManagerService for Manager {
         &self,
         request: Request<UpdateRequest>,
     ) -> Result<Response<UpdateResponse>, Status> {
+        let caller = self.authorize(&request, Access::Write)?;
         let req = request.into_inner();
-        println!("Manager received Update request for fid: {}", req.fid);
+        debug!("Manager received Update request for fid: {}", req.fid);
+        check_namespace(&req.namespace)?;
+        caller.check_keywords(req.old_keywords.iter().chain(&req.new_keywords))?;
+        check_reserved(req.old_keywords.iter().chain(&req.new_keywords))?;
+        let _fid = self.fid_locks.lock(&req.fid).await;
+        let _topology = self.topology.read().await;
+        let ack_mode = self.effective_ack_mode(req.ack_mode(), &req.namespace);
 
-        // Deduplicate old and new keywords
-        let unique_old_keywords: HashSet<String> = req.old_keywords.into_iter().collect();
-        let unique_new_keywords: HashSet<String> = req.new_keywords.into_iter().collect();
-        
-        println!("  Deleting {} unique old keyword(s)", unique_old_keywords.len());
-        println!("  Adding {} unique new keyword(s)", unique_new_keywords.len());
+        // 先加入新 keyword 再删除旧 keyword，过程中 fid 始终能通过其中一个集合查到
+        let plan = UpdatePlan::new(req.old_keywords, req.new_keywords);
+        debug!("Adding {} unique new keyword(s)", plan.adds.len());
+        debug!(
+            "Deleting {} old keyword(s) not in the new set",
+            plan.deletes.len()
+        );
 
-        // Delete old keywords
-        for keyword in &unique_old_keywords {
-            let (node_name, storager_addr) = self
-                .get_storager_for_keyword(keyword)
-                .ok_or_else(|| Status::internal("No storager available"))?;
+        let requests = plan
+            .adds
+            .iter()
+            .map(|keyword| {
+                self.mutate_replicas(MutationKind::Add, &req.namespace, keyword, &req.fid, ack_mode)
+            })
+            .collect();
+        let adds = self.fan_out(requests).await;
+        if !adds.iter().all(|result| matches!(result, Ok((true, _)))) {
+            // 有 keyword 加入失败或证明未通过验证时不再删除旧 keyword，
+            // 撤销已经加入的新 keyword，fid 回到旧集合
+            let added = plan
+                .adds
+                .iter()
+                .zip(&adds)
+                .filter(|(_, result)| result.is_ok())
+                .map(|(keyword, _)| keyword);
+            let (namespace, fid) = (&req.namespace, &req.fid);
+            let requests = plan
+                .rollback(added)
+                .into_iter()
+                .map(|keyword| async move {
+                    let result = self
+                        .mutate_replicas(MutationKind::Delete, namespace, &keyword, fid, ack_mode)
+                        .await;
+                    (keyword, result)
+                })
+                .collect();
+            for (keyword, result) in self.fan_out(requests).await {
+                if let Err(e) = result {
+                    warn!(
+                        "Failed to roll back keyword '{}' for fid {}: {}",
+                        keyword,
+                        req.fid,
+                        e.message()
+                    );
+                }
+            }
+            if let Some(error) = adds.into_iter().find_map(Result::err) {
+                return Err(error);
+            }
+            return Ok(Response::new(UpdateResponse {
+                success: false,
+                message: "Proof verification failed for new keywords, update rolled back"
+                    .to_string(),
+                pending_ops: vec![],
+            }));
+        }
+        let (_, mut pending_ops) =
+            merge_outcomes(adds.into_iter().collect::<Result<_, _>>()?, ack_mode);
+        self.index_fid(MutationKind::Add, &req.namespace, &req.fid, &plan.adds);
 
-            let mut client = StoragerServiceClient::connect(storager_addr.clone())
-                .await
-                .map_err(|e| Status::internal(format!("Failed to connect to storager: {}", e)))?;
+        let requests = plan
+            .deletes
+            .iter()
+            .map(|keyword| {
+                self.mutate_replicas(
+                    MutationKind::Delete,
+                    &req.namespace,
+                    keyword,
+                    &req.fid,
+                    ack_mode,
+                )
+            })
+            .collect();
+        let deletes = self.fan_out(requests).await;
+        let (deleted_ok, deleted) =
+            merge_outcomes(deletes.into_iter().collect::<Result<_, _>>()?, ack_mode);
+        self.index_fid(MutationKind::Delete, &req.namespace, &req.fid, &plan.deletes);
+        pending_ops.extend(deleted);
+        self.notify_watches(&req.namespace, plan.adds.iter().chain(&plan.deletes))
+            .await;
+        if !deleted_ok {
+            return Ok(Response::new(UpdateResponse {
+                success: false,
+                message: "Proof verification failed".to_string(),
+                pending_ops,
+            }));
+        }
 
-            let storager_req = StoragerDeleteRequest {
-                keyword: keyword.clone(),
-                fid: req.fid.clone(),
-            };
+        Ok(Response::new(UpdateResponse {
+            success: true,
+            message: "Update operation completed successfully".to_string(),
+            pending_ops,
+        }))
+    }
 
-            let response = client
-                .delete(storager_req)
-                .await
-                .map_err(|e| Status::internal(format!("Storager Delete failed: {}", e)))?;
+    async fn approx_count(
+        &self,
+        request: Request<ApproxCountRequest>,
+    ) -> Result<Response<ApproxCountResponse>, Status> {
+        let caller = self.authorize(&request, Access::Read)?;
+        let req = request.into_inner();
+        debug!("Manager received ApproxCount request for keyword: {}", req.keyword);
+        check_namespace(&req.namespace)?;
+        caller.check_keywords([&req.keyword])?;
 
-            let resp = response.into_inner();
-            if self.verify_proof(&resp.proof, &resp.root_hash) {
-                self.update_root_hash(node_name, resp.root_hash);
-            }
+        let (node_name, storager_addr) = self
+            .get_storager_for_keyword(&req.keyword)
+            .ok_or(ManagerError::NoStorager)?;
+        let key = RootKey::new(node_name, &req.namespace);
+
+        let request = StoragerApproxCountRequest {
+            keyword: req.keyword.clone(),
+            namespace: req.namespace.clone(),
+        };
+        let resp = self
+            .call_storager(&storager_addr, "ApproxCount", |mut client| {
+                let request = request.clone();
+                async move { client.approx_count(request).await }
+            })
+            .await?;
+
+        // 草图必须是验证过的添加最后报告的那一个；没有草图时，只有 Manager 跟踪着该节点
+        // 且从未记录过这个 keyword 的草图才可信
+        let tracked = self.root_store.sketches().digest(&key, &req.keyword);
+        if !resp.found {
+            let known = self.root_hashes.read().unwrap().contains_key(&key);
+            return Ok(Response::new(ApproxCountResponse {
+                estimate: 0,
+                sketch: vec![],
+                verified: known && tracked.is_none(),
+            }));
         }
 
-        // Add new keywords
-        for keyword in &unique_new_keywords {
-            let (node_name, storager_addr) = self
-                .get_storager_for_keyword(keyword)
-                .ok_or_else(|| Status::internal("No storager available"))?;
+        // 在本地计算估计值
+        let verified = tracked
+            .is_some_and(|digest| digest[..] == leaf_hash_bytes(&req.keyword, &resp.sketch));
+        let estimate = HyperLogLog::from_bytes(&resp.sketch)
+            .map(|hll| hll.estimate().round() as u64)
+            .ok_or_else(|| ManagerError::InvalidProof("malformed sketch".to_string()))?;
 
-            let mut client = StoragerServiceClient::connect(storager_addr.clone())
-                .await
-                .map_err(|e| Status::internal(format!("Failed to connect to storager: {}", e)))?;
+        Ok(Response::new(ApproxCountResponse {
+            estimate,
+            sketch: resp.sketch,
+            verified,
+        }))
+    }
 
-            let storager_req = StoragerAddRequest {
-                keyword: keyword.clone(),
-                fid: req.fid.clone(),
-            };
+    async fn register_storager(
+        &self,
+        request: Request<RegisterStoragerRequest>,
+    ) -> Result<Response<RegisterStoragerResponse>, Status> {
+        self.authorize(&request, Access::Admin)?;
+        let req = request.into_inner();
+        debug!(
+            "Manager received RegisterStorager request: name='{}', address={}",
+            req.name, req.address
+        );
 
-            let response = client
-                .add(storager_req)
-                .await
-                .map_err(|e| Status::internal(format!("Storager Add failed: {}", e)))?;
+        let name = Some(req.name.trim()).filter(|name| !name.is_empty());
+        let virtual_nodes = match req.virtual_nodes {
+            0 => DEFAULT_VIRTUAL_NODES,
+            n => n as usize,
+        };
+        let change = Manager::register_storager(self, name, &req.address, virtual_nodes)
+            .await
+            .map_err(ManagerError::Membership)?;
 
-            let resp = response.into_inner();
-            if self.verify_proof(&resp.proof, &resp.root_hash) {
-                self.update_root_hash(node_name, resp.root_hash);
-            }
-        }
+        Ok(Response::new(RegisterStoragerResponse {
+            ranges: moved_ranges(&change.plan),
+            hash_space_fraction: change.plan.hash_space_fraction(),
+            migrated_keywords: change.migrated.keywords as u64,
+            migrated_fids: change.migrated.fids as u64,
+            name: change.name,
+        }))
+    }
 
-        Ok(Response::new(UpdateResponse {
-            success: true,
-            message: "Update operation completed successfully".to_string(),
+    async fn deregister_storager(
+        &self,
+        request: Request<DeregisterStoragerRequest>,
+    ) -> Result<Response<DeregisterStoragerResponse>, Status> {
+        self.authorize(&request, Access::Admin)?;
+        let req = request.into_inner();
+        debug!("Manager received DeregisterStorager request: name='{}'", req.name);
+
+        let change = Manager::deregister_storager(self, &req.name)
+            .await
+            .map_err(ManagerError::Membership)?;
+
+        Ok(Response::new(DeregisterStoragerResponse {
+            ranges: moved_ranges(&change.plan),
+            hash_space_fraction: change.plan.hash_space_fraction(),
+            migrated_keywords: change.migrated.keywords as u64,
+            migrated_fids: change.migrated.fids as u64,
         }))
     }
+
+    type SubscribeRootHashesStream =
+        Pin<Box<dyn Stream<Item = Result<RootHashUpdate, Status>> + Send>>;
+
+    async fn subscribe_root_hashes(
+        &self,
+        request: Request<SubscribeRootHashesRequest>,
+    ) -> Result<Response<Self::SubscribeRootHashesStream>, Status> {
+        self.authorize(&request, Access::Read)?;
+        debug!("Manager received SubscribeRootHashes request");
+
+        // 先订阅再取快照：两者之间发布的根哈希可能收到两次，但不会漏掉；
+        // 订阅者落后太多时跳过中间的更新，之后的更新仍然携带完整的根哈希
+        let updates = BroadcastStream::new(self.subscribe_roots()).filter_map(Result::ok);
+        let current = tokio_stream::iter(self.current_roots());
+
+        Ok(Response::new(Box::pin(current.chain(updates).map(Ok))))
+    }
+
+    async fn bulk_add(
+        &self,
+        request: Request<Streaming<BulkAddRecord>>,
+    ) -> Result<Response<BulkAddResponse>, Status> {
+        let caller = self.authorize(&request, Access::Write)?;
+        debug!("Manager received BulkAdd stream");
+        // 与单条 Add 一样持有拓扑读锁，导入期间不会发生关键词迁移
+        let _topology = self.topology.read().await;
+
+        let response = self.bulk_add_records(request.into_inner(), &caller).await?;
+        Ok(Response::new(response))
+    }
+
+    async fn batch(
+        &self,
+        request: Request<BatchRequest>,
+    ) -> Result<Response<BatchResponse>, Status> {
+        self.authorize(&request, Access::Write)?;
+        debug!(
+            "Manager received Batch request with {} operation(s)",
+            request.get_ref().operations.len()
+        );
+
+        let response = self.run_batch(request).await?;
+        Ok(Response::new(response))
+    }
+
+    type WatchKeywordStream = Pin<Box<dyn Stream<Item = Result<WatchKeywordEvent, Status>> + Send>>;
+
+    async fn watch_keyword(
+        &self,
+        request: Request<WatchKeywordRequest>,
+    ) -> Result<Response<Self::WatchKeywordStream>, Status> {
+        let caller = self.authorize(&request, Access::Read)?;
+        let req = request.into_inner();
+        debug!(
+            "Manager received WatchKeyword request: '{}'",
+            req.expression
+        );
+        check_namespace(&req.namespace)?;
+        let expr = parse_boolean_expr(&req.expression).map_err(ManagerError::InvalidExpression)?;
+        caller.check_keywords(expr.get_keywords())?;
+
+        let events = self.open_watch(req.namespace, req.expression, expr).await?;
+        Ok(Response::new(Box::pin(ReceiverStream::new(events))))
+    }
+
+    async fn range_query(
+        &self,
+        request: Request<RangeQueryRequest>,
+    ) -> Result<Response<RangeQueryResponse>, Status> {
+        let caller = self.authorize(&request, Access::Read)?;
+        let req = request.into_inner();
+        debug!(
+            "Manager received RangeQuery request: [{}, {})",
+            req.start_key, req.end_key
+        );
+        check_namespace(&req.namespace)?;
+        caller.check_range(&req.start_key, &req.end_key)?;
+        // 迁移期间 keyword 在 storager 之间移动，等迁移完成后再查询
+        let _topology = self.topology.read().await;
+
+        let response = self
+            .query_range(&req.namespace, &req.start_key, &req.end_key)
+            .await?;
+        Ok(Response::new(response))
+    }
+
+    async fn query_by_prefix(
+        &self,
+        request: Request<PrefixQueryRequest>,
+    ) -> Result<Response<PrefixQueryResponse>, Status> {
+        let caller = self.authorize(&request, Access::Read)?;
+        let req = request.into_inner();
+        debug!("Manager received QueryByPrefix request: '{}'", req.prefix);
+        check_namespace(&req.namespace)?;
+        caller.check_keywords([&req.prefix])?;
+        let _topology = self.topology.read().await;
+
+        let response = self.query_prefix(&req.namespace, &req.prefix).await?;
+        Ok(Response::new(response))
+    }
+}
+
+/// 把迁移计划转换为 RPC 返回的区间列表
+pub(crate) fn moved_ranges(plan: &RebalancePlan) -> Vec<MovedKeyRange> {
+    plan
+        .ranges
+        .iter()
+        .map(|range| MovedKeyRange {
+            start: range.start,
+            end: range.end,
+            from: range.from.clone().unwrap_or_default(),
+            to: range.to.clone().unwrap_or_default(),
+        })
+        .collect()
 }
 
 impl Manager {
     /// 单关键词查询
     pub(crate) async fn query_single_keyword(
         &self,
+        namespace: &str,
+        keyword: &str,
+    ) -> Result<Response<QueryResponse>, Status> {
+        debug!("Query type: Single keyword '{}'", keyword);
+
+        let read = if self.replication_factor() > 1 {
+            self.read_with_repair(namespace, keyword).await?
+        } else {
+            self.read_keyword(namespace, keyword).await?
+        };
+
+        let metrics = self.verifier.proof_metrics(&read.proof);
+        Ok(Response::new(QueryResponse {
+            total_count: read.fids.len() as u64,
+            fids: read.fids,
+            proof: Some(read.proof.into()),
+            root_hash: read.root_hash,
+            verified: read.verified,
+            boolean_proof: None,
+            next_page_token: String::new(),
+            metrics: Some(metrics),
+            ..Default::default()
+        }))
+    }
+
+    /// 单关键词分页查询
+    ///
+    /// 主副本所在的 storager 按游标返回一页，证明只随最后一页返回，届时才验证；
+    /// 分页读取不做读修复和影子读
+    async fn query_keyword_page(
+        &self,
+        namespace: &str,
         keyword: &str,
+        page_size: u32,
+        page_token: String,
     ) -> Result<Response<QueryResponse>, Status> {
-        println!("  Query type: Single keyword '{}'", keyword);
+        debug!(
+            "Query type: Single keyword '{}' (page size {})",
+            keyword, page_size
+        );
 
+        let (storager_token, delivered) = split_page_token(&page_token)?;
         let (node_name, storager_addr) = self
             .get_storager_for_keyword(keyword)
-            .ok_or_else(|| Status::internal("No storager available"))?;
-
-        // Connect to storager and send Query request
-        let mut client = StoragerServiceClient::connect(storager_addr.clone())
+            .ok_or(ManagerError::NoStorager)?;
+        let request = StoragerQueryRequest {
+            keyword: keyword.to_string(),
+            page_size,
+            page_token: storager_token.to_string(),
+            namespace: namespace.to_string(),
+        };
+        let resp = self
+            .call_storager(&storager_addr, "Query", |mut client| {
+                let request = request.clone();
+                async move { client.query(request).await }
+            })
             .await
-            .map_err(|e| Status::internal(format!("Failed to connect to storager: {}", e)))?;
+            .map_err(|e| match e {
+                // 游标无效时原样返回，客户端据此从第一页重新开始
+                ManagerError::Storager {
+                    code: code @ (tonic::Code::Aborted | tonic::Code::InvalidArgument),
+                    message,
+                    ..
+                } => ErrorKind::InvalidRequest.status(code, message),
+                e => e.into(),
+            })?;
 
-        let storager_req = StoragerQueryRequest {
-            keyword: keyword.to_string(),
+        let key = RootKey::new(node_name, namespace);
+        let root_hash = self.root_at(&key, resp.epoch);
+        let delivered = self.verifier.fold_delivered(delivered, &resp.fids);
+        let (proof, verified, metrics) = if resp.proof.is_some() {
+            let proof = Proof::try_from(resp.proof).map_err(invalid_proof)?;
+            // 之前各页的 fid 已经折叠进游标，与这一页一起对照证明中的完整结果
+            let verified = if resp.total_count == 0 {
+                delivered == DELIVERED_EMPTY
+                    && self.verify_keyword_proof(&key, &proof, &root_hash, keyword, &[])
+            } else {
+                self.verify_proof(&proof, &root_hash)
+                    && self.matches_tracked_accumulator(&key, keyword, &proof)
+                    && self.verifier.verify_delivered(&proof, keyword, delivered)
+            };
+            let metrics = self.verifier.proof_metrics(&proof);
+            (Some(proof.into()), verified, Some(metrics))
+        } else {
+            (None, false, None)
+        };
+
+        Ok(Response::new(QueryResponse {
+            fids: resp.fids,
+            proof,
+            root_hash,
+            verified,
+            boolean_proof: None,
+            total_count: resp.total_count,
+            next_page_token: join_page_token(&resp.next_page_token, &delivered),
+            metrics,
+            ..Default::default()
+        }))
+    }
+
+    /// 读取 keyword 的所有副本，修复与法定结果不一致的副本（见 [`crate::core::read_repair`]）
+    ///
+    /// 返回法定结果；没有法定结果时不做修复，返回第一个可用副本（通常是主副本）的结果
+    async fn read_with_repair(
+        &self,
+        namespace: &str,
+        keyword: &str,
+    ) -> Result<KeywordRead, Status> {
+        let (mut reads, replicas) = self.read_replicas(namespace, keyword).await;
+        let quorum = quorum_size(replicas.len());
+        let Some(mut index) = find_quorum(&reads, quorum) else {
+            return reads
+                .into_iter()
+                .next()
+                .ok_or_else(|| ManagerError::NoReplica(keyword.to_string()).into());
         };
 
-        let response = client
-            .query(storager_req)
+        if !plan_repairs(&reads, &reads[index]).is_empty() {
+            // 持有写锁后没有进行中的写入，重新读取，避免把写了一半副本的变更当作不一致
+            let _writes = self.topology.write().await;
+            (reads, _) = self.read_replicas(namespace, keyword).await;
+            let Some(current) = find_quorum(&reads, quorum) else {
+                return Ok(reads.swap_remove(0));
+            };
+            index = current;
+
+            for repair in plan_repairs(&reads, &reads[index]) {
+                if let Err(e) = self
+                    .repair_replica(namespace, keyword, &replicas[&repair.node_name], &repair)
+                    .await
+                {
+                    warn!(
+                        "Read repair on {} failed: {}",
+                        repair.node_name,
+                        e.message()
+                    );
+                }
+            }
+        }
+
+        Ok(reads.swap_remove(index))
+    }
+
+    /// 查询 keyword 的所有副本，跳过不可用的副本
+    ///
+    /// 返回: (各副本的结果, 副本名称 -> 地址)
+    async fn read_replicas(
+        &self,
+        namespace: &str,
+        keyword: &str,
+    ) -> (Vec<KeywordRead>, HashMap<String, String>) {
+        let mut reads = Vec::new();
+        let mut replicas = HashMap::new();
+        for (node_name, storager_addr) in self.replicas_for_keyword(keyword) {
+            match self
+                .query_storager(node_name.clone(), &storager_addr, namespace, keyword)
+                .await
+            {
+                Ok(read) => reads.push(read),
+                Err(e) => warn!("Replica {} unavailable: {}", node_name, e.message()),
+            }
+            replicas.insert(node_name, storager_addr);
+        }
+        (reads, replicas)
+    }
+
+    /// 按修复计划补写、删除副本上的 fid（同步验证证明并发布新根）
+    async fn repair_replica(
+        &self,
+        namespace: &str,
+        keyword: &str,
+        storager_addr: &str,
+        repair: &ReplicaRepair,
+    ) -> Result<(), Status> {
+        info!(
+            "Read repair on {} for '{}': {} to add, {} to delete",
+            repair.node_name,
+            keyword,
+            repair.add.len(),
+            repair.delete.len()
+        );
+
+        let mutations = repair
+            .add
+            .iter()
+            .map(|fid| (MutationKind::Add, fid))
+            .chain(repair.delete.iter().map(|fid| (MutationKind::Delete, fid)));
+        for (kind, fid) in mutations {
+            let (proof, root_hash, epoch, sketch_digests) = self
+                .send_mutation(kind, storager_addr, namespace, keyword, fid, AckMode::Sync)
+                .await?;
+            let (ok, _) = self.settle_mutation(
+                AckMode::Sync,
+                kind,
+                RootKey::new(repair.node_name.clone(), namespace),
+                keyword,
+                fid,
+                proof,
+                root_hash,
+                epoch,
+                sketch_digests,
+            );
+            if !ok {
+                return Err(ManagerError::VerificationFailed(format!(
+                    "proof for repaired '{}' failed verification",
+                    fid
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// 按 storager 分组批量添加 keyword，每个 storager 只需要一次 RPC
+    ///
+    /// 每个 keyword 写入它的所有副本，各 storager 的批次并发发送。
+    /// 只承载副本的 storager 不可用时跳过它，错过的写入由读修复补齐。
+    /// `expires_at_ms` 非 0 时各副本在同一时刻删除这些 keyword（见 [`crate::expiry`]）
+    ///
+    /// 返回: (是否全部验证通过, 异步模式下待确认的审计 id)
+    async fn batch_add_keywords(
+        &self,
+        namespace: &str,
+        keywords: &HashSet<String>,
+        fid: &str,
+        ack_mode: AckMode,
+        expires_at_ms: u64,
+    ) -> Result<(bool, Vec<u64>), Status> {
+        let mut batches: HashMap<String, (String, Vec<String>)> = HashMap::new();
+        let mut primaries: HashMap<String, Vec<String>> = HashMap::new();
+        for keyword in keywords {
+            let replicas = self.replicas_for_keyword(keyword);
+            let (primary, _) = replicas.first().ok_or(ManagerError::NoStorager)?;
+            primaries
+                .entry(primary.clone())
+                .or_default()
+                .push(keyword.clone());
+            for (node_name, storager_addr) in replicas {
+                batches
+                    .entry(node_name)
+                    .or_insert_with(|| (storager_addr, Vec::new()))
+                    .1
+                    .push(keyword.clone());
+            }
+        }
+
+        let requests = batches
+            .into_iter()
+            .map(|(node_name, (storager_addr, keywords))| {
+                let primary_keywords = primaries.remove(&node_name).unwrap_or_default();
+                self.send_batch_add(
+                    RootKey::new(node_name, namespace),
+                    storager_addr,
+                    keywords,
+                    primary_keywords,
+                    fid,
+                    ack_mode,
+                    expires_at_ms,
+                )
+            })
+            .collect();
+        let results = self.fan_out(requests).await;
+        Ok(merge_outcomes(
+            results.into_iter().collect::<Result<_, _>>()?,
+            ack_mode,
+        ))
+    }
+
+    /// 把一个批次发给 storager 并处理返回的证明
+    ///
+    /// 只承载副本的 storager（`primary_keywords` 为空）不可用时跳过，返回验证通过
+    #[allow(clippy::too_many_arguments)]
+    async fn send_batch_add(
+        &self,
+        key: RootKey,
+        storager_addr: String,
+        keywords: Vec<String>,
+        primary_keywords: Vec<String>,
+        fid: &str,
+        ack_mode: AckMode,
+        expires_at_ms: u64,
+    ) -> Result<(bool, Vec<u64>), Status> {
+        let storager_req = StoragerBatchAddRequest {
+            fid: fid.to_string(),
+            keywords: keywords.clone(),
+            request_id: self.next_request_id(),
+            namespace: key.namespace.clone(),
+            expires_at_ms,
+            defer_proof: defers_proof(ack_mode),
+        };
+
+        let result = self
+            .call_storager(&storager_addr, "BatchAdd", |mut client| {
+                let request = storager_req.clone();
+                async move { client.batch_add(request).await }
+            })
+            .await;
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(e) if primary_keywords.is_empty() => {
+                warn!("Replica {} missed BatchAdd: {}", key.storager, e);
+                return Ok((true, Vec::new()));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        for keyword in &primary_keywords {
+            self.record_cardinality(MutationKind::Add, keyword);
+        }
+
+        // Verify the combined proof (inline or out-of-band) and update root hash
+        Ok(self.settle_batch(
+            ack_mode,
+            MutationKind::Add,
+            key,
+            &keywords,
+            fid,
+            MutationProof::from_response(resp.proof, resp.proof_handle, &storager_addr)?,
+            resp.root_hash,
+            resp.epoch,
+            resp.sketch_digests,
+        ))
+    }
+
+    /// 把单个 (keyword, fid) 变更写入 keyword 的所有副本（主副本在前）
+    ///
+    /// 主副本不可用时返回错误，其余副本不可用时跳过，错过的写入由读修复补齐
+    ///
+    /// 返回: (是否全部验证通过, 每个副本的审计 id)
+    async fn mutate_replicas(
+        &self,
+        kind: MutationKind,
+        namespace: &str,
+        keyword: &str,
+        fid: &str,
+        ack_mode: AckMode,
+    ) -> Result<(bool, Vec<u64>), Status> {
+        let replicas = self.replicas_for_keyword(keyword);
+        if replicas.is_empty() {
+            return Err(ManagerError::NoStorager.into());
+        }
+
+        let mut all_ok = true;
+        let mut audit_ids = Vec::new();
+        for (index, (node_name, storager_addr)) in replicas.into_iter().enumerate() {
+            let (proof, root_hash, epoch, sketch_digests) =
+                match self
+                    .send_mutation(kind, &storager_addr, namespace, keyword, fid, ack_mode)
+                    .await
+                {
+                    Ok(result) => result,
+                    Err(e) if index > 0 => {
+                        warn!(
+                            "Replica {} missed {:?}: {}",
+                            node_name,
+                            kind,
+                            e.message()
+                        );
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+            if index == 0 {
+                self.record_cardinality(kind, keyword);
+            }
+
+            // Verify proof (inline or out-of-band) and update root hash
+            let (ok, audit_id) = self.settle_mutation(
+                ack_mode,
+                kind,
+                RootKey::new(node_name, namespace),
+                keyword,
+                fid,
+                proof,
+                root_hash,
+                epoch,
+                sketch_digests,
+            );
+            all_ok &= ok;
+            audit_ids.push(audit_id);
+        }
+        Ok((all_ok, audit_ids))
+    }
+
+    /// 向单个 storager 发送 Add 或 Delete，异步确认时让 storager 推迟生成证明
+    ///
+    /// 返回: (proof, root_hash, epoch, 添加后草图的摘要)，删除时摘要为空
+    async fn send_mutation(
+        &self,
+        kind: MutationKind,
+        storager_addr: &str,
+        namespace: &str,
+        keyword: &str,
+        fid: &str,
+        ack_mode: AckMode,
+    ) -> Result<(MutationProof, RootHash, u64, Vec<RootHash>), Status> {
+        let request_id = self.next_request_id();
+        match kind {
+            MutationKind::Add => {
+                let request = StoragerAddRequest {
+                    keyword: keyword.to_string(),
+                    fid: fid.to_string(),
+                    request_id,
+                    namespace: namespace.to_string(),
+                    defer_proof: defers_proof(ack_mode),
+                    ..Default::default()
+                };
+                let resp = self
+                    .call_storager(storager_addr, "Add", |mut client| {
+                        let request = request.clone();
+                        async move { client.add(request).await }
+                    })
+                    .await?;
+                Ok((
+                    MutationProof::from_response(resp.proof, resp.proof_handle, storager_addr)?,
+                    resp.root_hash,
+                    resp.epoch,
+                    vec![resp.sketch_digest],
+                ))
+            }
+            MutationKind::Delete => {
+                let request = StoragerDeleteRequest {
+                    keyword: keyword.to_string(),
+                    fid: fid.to_string(),
+                    request_id,
+                    namespace: namespace.to_string(),
+                    defer_proof: defers_proof(ack_mode),
+                };
+                let resp = self
+                    .call_storager(storager_addr, "Delete", |mut client| {
+                        let request = request.clone();
+                        async move { client.delete(request).await }
+                    })
+                    .await?;
+                Ok((
+                    MutationProof::from_response(resp.proof, resp.proof_handle, storager_addr)?,
+                    resp.root_hash,
+                    resp.epoch,
+                    Vec::new(),
+                ))
+            }
+        }
+    }
+
+    /// 查询单个 storager，并使用与响应 epoch 对应的已验证根哈希验证证明
+    async fn query_storager(
+        &self,
+        node_name: String,
+        storager_addr: &str,
+        namespace: &str,
+        keyword: &str,
+    ) -> Result<KeywordRead, Status> {
+        self.query_storager_with(node_name, storager_addr, namespace, keyword, None)
             .await
-            .map_err(|e| Status::internal(format!("Storager Query failed: {}", e)))?;
+    }
 
-        let resp = response.into_inner();
+    /// 查询单个 storager，并使用指定的根哈希验证证明
+    pub(crate) async fn query_storager_at(
+        &self,
+        node_name: String,
+        storager_addr: &str,
+        namespace: &str,
+        keyword: &str,
+        root_hash: RootHash,
+    ) -> Result<KeywordRead, Status> {
+        self.query_storager_with(node_name, storager_addr, namespace, keyword, Some(root_hash))
+            .await
+    }
 
-        // Get root hash for this storager
-        let root_hash = self
-            .root_hashes
-            .read()
-            .unwrap()
-            .get(&node_name)
-            .cloned()
-            .unwrap_or_default();
+    /// 查询单个 storager；未指定根哈希时按响应的 epoch 从根哈希历史中选择
+    async fn query_storager_with(
+        &self,
+        node_name: String,
+        storager_addr: &str,
+        namespace: &str,
+        keyword: &str,
+        root_hash: Option<RootHash>,
+    ) -> Result<KeywordRead, Status> {
+        let storager_req = StoragerQueryRequest {
+            keyword: keyword.to_string(),
+            namespace: namespace.to_string(),
+            ..Default::default()
+        };
 
-        // Verify proof
-        let verified = self.verify_proof(&resp.proof, &root_hash);
+        // 完整结果分成多条消息传输，避免大结果集的 fid 列表或证明超过单条消息上限；
+        // Merkle 树的每批 fid 自带证明，其他 ADS 的证明分段传输，收齐后统一验证
+        let mut stream = self
+            .call_storager(storager_addr, "QueryStream", |mut client| {
+                let request = storager_req.clone();
+                async move { client.query_stream(request).await }
+            })
+            .await?;
+        let key = RootKey::new(node_name.clone(), namespace);
+        let mut root_hash = root_hash;
+        let mut assembler = QueryAssembler::new();
+        // 逐批携带证明的流（Merkle 树）每收到一批就对照头部 epoch 的根验证，失败后不再验证后续批次
+        let mut batch_proofs = Vec::new();
+        let mut batches_verified = true;
+        while let Some(chunk) = stream
+            .message()
+            .await
+            .map_err(|status| ManagerError::Storager {
+                rpc: "QueryStream",
+                code: status.code(),
+                message: status.message().to_string(),
+            })?
+        {
+            let batch = assembler
+                .push(chunk)
+                .map_err(|e| ManagerError::InvalidProof(e.to_string()))?;
+            let Some(proof) = batch else {
+                continue;
+            };
+            let proof = Proof::try_from(proof).map_err(invalid_proof)?;
+            if batches_verified {
+                let epoch = assembler.epoch().unwrap_or_default();
+                let root = root_hash.get_or_insert_with(|| self.root_at(&key, epoch));
+                let fids = assembler.last_batch();
+                batches_verified = self.verify_proof(&proof, root)
+                    && self.verifier.verify_batch(&proof, keyword, fids);
+            }
+            batch_proofs.push(proof);
+        }
+        let resp = assembler
+            .finish()
+            .map_err(|e| ManagerError::InvalidProof(e.to_string()))?;
+        let root_hash = root_hash.unwrap_or_else(|| self.root_at(&key, resp.epoch));
+        let (proof, verified) = match resp.proof {
+            Some(proof) => {
+                let proof = Proof::try_from(Some(proof)).map_err(invalid_proof)?;
+                let verified =
+                    self.verify_keyword_proof(&key, &proof, &root_hash, keyword, &resp.fids);
+                (proof, verified)
+            }
+            None => {
+                let proof = self
+                    .verifier
+                    .merge_batch_proofs(batch_proofs)
+                    .ok_or_else(|| invalid_proof("QueryStream batch proofs differ".to_string()))?;
+                let verified = batches_verified
+                    && if resp.fids.is_empty() {
+                        self.verifier.verify_absence(&proof, keyword)
+                    } else {
+                        self.verifier.verify_completeness(&proof, keyword, &resp.fids)
+                    };
+                (proof, verified)
+            }
+        };
 
-        Ok(Response::new(QueryResponse {
+        Ok(KeywordRead {
+            node_name,
             fids: resp.fids,
-            proof: resp.proof,
+            proof,
             root_hash,
             verified,
-        }))
+        })
+    }
+
+    /// 查询关键词
+    ///
+    /// 关键词处于迁移中时同时查询新旧节点（影子读），按
+    /// [`resolve_shadow_read`] 的规则选择结果并记录差异
+    pub(crate) async fn read_keyword(
+        &self,
+        namespace: &str,
+        keyword: &str,
+    ) -> Result<KeywordRead, Status> {
+        let (node_name, storager_addr) = self
+            .get_storager_for_keyword(keyword)
+            .ok_or(ManagerError::NoStorager)?;
+
+        let source = self.migrations.shadow_source(keyword, &node_name);
+        // 迁移中的 keyword 需要比较新旧节点的结果，不走缓存
+        let cached_root = source
+            .is_none()
+            .then(|| self.current_root(&RootKey::new(node_name.clone(), namespace)));
+        if let Some(root) = &cached_root {
+            if let Some(read) = self.query_cache.get(namespace, keyword, &node_name, root) {
+                debug!("Query cache hit for '{}' on {}", keyword, node_name);
+                return Ok(read);
+            }
+        }
+        let new = self
+            .query_storager(node_name, &storager_addr, namespace, keyword)
+            .await?;
+        let Some(source) = source else {
+            if let Some(root) = &cached_root {
+                self.query_cache.insert(namespace, keyword, &new, root);
+            }
+            return Ok(new);
+        };
+
+        // 旧节点不可用（例如已经下线）时退回新节点的结果
+        let old = match self
+            .query_storager(source.node_name.clone(), &source.addr, namespace, keyword)
+            .await
+        {
+            Ok(old) => old,
+            Err(e) => {
+                warn!(
+                    "Shadow read from {} failed: {}",
+                    source.node_name,
+                    e.message()
+                );
+                return Ok(new);
+            }
+        };
+
+        let new_key = RootKey::new(new.node_name.clone(), namespace);
+        let caught_up = source
+            .copied_at
+            .is_some_and(|id| self.root_version(&new_key) >= id);
+        let (choice, discrepancy) = resolve_shadow_read(keyword, &old, &new, caught_up);
+        if let Some(discrepancy) = discrepancy {
+            warn!(
+                "Shadow read mismatch for '{}': {} only on {}, {} only on {}",
+                keyword,
+                discrepancy.only_old.len(),
+                discrepancy.old_owner,
+                discrepancy.only_new.len(),
+                discrepancy.new_owner
+            );
+            self.migrations.record_discrepancy(discrepancy);
+        }
+
+        Ok(match choice {
+            ShadowChoice::New => new,
+            ShadowChoice::Old => old,
+            ShadowChoice::Union => {
+                let mut fids = new.fids;
+                for fid in old.fids {
+                    if !fids.contains(&fid) {
+                        fids.push(fid);
+                    }
+                }
+                KeywordRead {
+                    node_name: new.node_name,
+                    fids,
+                    proof: self
+                        .combine_proofs(&[new.proof.clone(), old.proof])
+                        .unwrap_or(new.proof),
+                    root_hash: new.root_hash,
+                    verified: true,
+                }
+            }
+        })
     }
 
     /// 布尔函数查询
+    ///
+    /// `breakdown` 为 true 时在响应中附带每个 keyword 的结果和证明（由单个 storager
+    /// 证明整个表达式时没有逐个 keyword 的读取，不附带）
     pub(crate) async fn query_boolean_function(
         &self,
+        namespace: &str,
         func: &str,
+        breakdown: bool,
     ) -> Result<Response<QueryResponse>, Status> {
-        println!("  Query type: Boolean function '{}'", func);
+        debug!("Query type: Boolean function '{}'", func);
 
         // 1. 解析布尔表达式
-        let expr = parse_boolean_expr(func).map_err(|e| {
-            Status::invalid_argument(format!("Failed to parse boolean expression: {}", e))
-        })?;
+        let expr = parse_boolean_expr(func).map_err(ManagerError::InvalidExpression)?;
+
+        debug!("Parsed expression: {}", expr.to_string());
 
-        println!("  Parsed expression: {}", expr.to_string());
+        // 密码学累加器模式下，keyword 都在同一个 storager 时由它证明整个表达式；
+        // 否则 A AND NOT B 由持有 A 的 storager 给出差集证明
+        if self.ads_mode() == AdsMode::CryptoAccumulator {
+            if let Some(response) = self.query_boolean_proof(namespace, func, &expr).await? {
+                return Ok(response);
+            }
+            if let Some((included, excluded)) = expr.as_keyword_difference() {
+                return self
+                    .query_keyword_difference(namespace, included, excluded, breakdown)
+                    .await;
+            }
+        }
 
         // 2. 获取所有关键词
         let keywords = expr.get_keywords();
-        println!("  Keywords: {:?}", keywords);
+        debug!("Keywords: {:?}", keywords);
 
-        // 3. 并发查询所有关键词
-        let mut keyword_results = HashMap::new();
+        // 3. 并发查询所有关键词；keyword 过滤器排除的关键词不发出子查询，按空结果参与求值
+        let (mut filtered, keywords): (Vec<String>, Vec<String>) = keywords
+            .into_iter()
+            .partition(|k| self.keyword_filtered(namespace, k));
+        filtered.sort();
+        if !filtered.is_empty() {
+            debug!("Skipped by keyword filters: {:?}", filtered);
+        }
+        let requests = keywords
+            .iter()
+            .map(|k| self.read_keyword(namespace, k))
+            .collect();
+        let reads = self.fan_out(requests).await;
+        let mut keyword_results: HashMap<String, HashSet<String>> = filtered
+            .iter()
+            .map(|k| (k.clone(), HashSet::new()))
+            .collect();
         let mut all_proofs = Vec::new();
-
-        for keyword in keywords.iter() {
-            let (node_name, storager_addr) = self
-                .get_storager_for_keyword(keyword)
-                .ok_or_else(|| Status::internal("No storager available"))?;
-
-            // Connect to storager
-            let mut client = StoragerServiceClient::connect(storager_addr.clone())
-                .await
-                .map_err(|e| Status::internal(format!("Failed to connect to storager: {}", e)))?;
-
-            let storager_req = StoragerQueryRequest {
+        let mut metrics = ProofMetrics::default();
+        let mut keyword_breakdown: Vec<KeywordBreakdown> = Vec::new();
+        if breakdown {
+            keyword_breakdown.extend(filtered.iter().map(|keyword| KeywordBreakdown {
                 keyword: keyword.clone(),
-            };
-
-            let response = client
-                .query(storager_req)
-                .await
-                .map_err(|e| Status::internal(format!("Storager Query failed: {}", e)))?;
-
-            let resp = response.into_inner();
+                storager: self
+                    .get_storager_for_keyword(keyword)
+                    .map(|(node_name, _)| node_name)
+                    .unwrap_or_default(),
+                filtered: true,
+                ..Default::default()
+            }));
+        }
 
-            // Get root hash for this storager
-            let root_hash = self
-                .root_hashes
-                .read()
-                .unwrap()
-                .get(&node_name)
-                .cloned()
-                .unwrap_or_default();
+        for (keyword, read) in keywords.iter().zip(reads) {
+            let read = read?;
 
             // Verify individual proof
-            if !self.verify_proof(&resp.proof, &root_hash) {
-                return Err(Status::internal(format!(
-                    "Proof verification failed for keyword: {}",
-                    keyword
-                )));
+            if !read.verified {
+                return Err(ManagerError::keyword_unverified(keyword).into());
+            }
+            if breakdown {
+                keyword_breakdown.push(breakdown_entry(keyword, &read));
             }
-
             // 存储查询结果
-            let fid_set: HashSet<String> = resp.fids.into_iter().collect();
+            let fid_set: HashSet<String> = read.fids.into_iter().collect();
             keyword_results.insert(keyword.clone(), fid_set);
 
             // 收集证明
-            all_proofs.push(resp.proof);
+            metrics.merge(&self.verifier.proof_metrics(&read.proof));
+            all_proofs.push(read.proof);
 
-            println!(
-                "    '{}' -> {} files",
+            debug!(
+                "'{}' -> {} files",
                 keyword,
                 keyword_results.get(keyword).unwrap().len()
             );
         }
 
+        keyword_breakdown.sort_by(|a, b| a.keyword.cmp(&b.keyword));
+
         // 4. 对布尔表达式求值
         let result_set = expr.evaluate(&keyword_results);
         let result_fids: Vec<String> = result_set.into_iter().collect();
 
-        println!("  Final result: {} files", result_fids.len());
+        debug!("Final result: {} files", result_fids.len());
 
         // 5. 生成组合证明
         let combined_proof = self.combine_proofs(&all_proofs);
 
-        // 6. 使用第一个 storager 的 root hash 作为代表
+        // 6. 使用命名空间中第一个 storager 的 root hash 作为代表
         let root_hash = self
             .root_hashes
             .read()
             .unwrap()
-            .values()
-            .next()
-            .cloned()
+            .iter()
+            .find(|(key, _)| key.namespace == namespace)
+            .map(|(_, root_hash)| root_hash.clone())
             .unwrap_or_default();
 
         Ok(Response::new(QueryResponse {
             fids: result_fids,
-            proof: combined_proof,
+            proof: combined_proof.map(Into::into),
             root_hash,
             verified: true, // 已经验证过各个子查询的证明
+            boolean_proof: None,
+            metrics: Some(metrics),
+            filtered_keywords: filtered,
+            keyword_breakdown,
+            ..Default::default()
+        }))
+    }
+
+    /// 归属节点的 keyword 过滤器与当前发布的根哈希一致且排除了 `keyword`
+    /// （迁移中的 keyword 需要影子读，不跳过）
+    fn keyword_filtered(&self, namespace: &str, keyword: &str) -> bool {
+        let Some((node_name, _)) = self.get_storager_for_keyword(keyword) else {
+            return false;
+        };
+        if self.migrations.shadow_source(keyword, &node_name).is_some() {
+            return false;
+        }
+        let key = RootKey::new(node_name, namespace);
+        self.keyword_filters
+            .excludes(&key, &self.current_root(&key), keyword)
+    }
+
+    /// `A AND NOT B` 查询
+    ///
+    /// 先查询 A、B 并验证各自的证明，再把 B 的完整 fid 集合发给返回 A 的 storager，
+    /// 由它证明差集；差集证明必须绑定到 A、B 查询证明中的累加器
+    async fn query_keyword_difference(
+        &self,
+        namespace: &str,
+        included: &str,
+        excluded: &str,
+        breakdown: bool,
+    ) -> Result<Response<QueryResponse>, Status> {
+        let mut reads = Vec::new();
+        let requests = vec![
+            self.read_keyword(namespace, included),
+            self.read_keyword(namespace, excluded),
+        ];
+        let results = self.fan_out(requests).await;
+        for (keyword, read) in [included, excluded].into_iter().zip(results) {
+            let read = read?;
+            if !read.verified {
+                return Err(ManagerError::keyword_unverified(keyword).into());
+            }
+            reads.push(read);
+        }
+        let (included_read, excluded_read) = (&reads[0], &reads[1]);
+
+        let storager_addr = self
+            .get_storagers()
+            .into_iter()
+            .find(|(node_name, _)| *node_name == included_read.node_name)
+            .map(|(_, addr)| addr)
+            .ok_or(ManagerError::NoStorager)?;
+        let request = ProveDifferenceRequest {
+            keyword: included.to_string(),
+            excluded_fids: excluded_read.fids.clone(),
+            namespace: namespace.to_string(),
+        };
+        let resp = self
+            .call_storager(&storager_addr, "ProveDifference", |mut client| {
+                let request = request.clone();
+                async move { client.prove_difference(request).await }
+            })
+            .await?;
+
+        if !self.verifier.verify_difference(
+            &included_read.proof,
+            &excluded_read.proof,
+            &resp.fids,
+            &resp.proof,
+        ) {
+            return Err(ManagerError::VerificationFailed(format!(
+                "Difference proof verification failed for '{} AND NOT {}'",
+                included, excluded
+            ))
+            .into());
+        }
+
+        debug!("Final result: {} files", resp.fids.len());
+
+        let mut metrics = self.verifier.difference_metrics(&resp.proof, &resp.fids);
+        for read in &reads {
+            metrics.merge(&self.verifier.proof_metrics(&read.proof));
+        }
+        let proofs = [included_read.proof.clone(), excluded_read.proof.clone()];
+        let keyword_breakdown = if breakdown {
+            vec![
+                breakdown_entry(included, included_read),
+                breakdown_entry(excluded, excluded_read),
+            ]
+        } else {
+            Vec::new()
+        };
+        Ok(Response::new(QueryResponse {
+            fids: resp.fids,
+            proof: self.combine_proofs(&proofs).map(Into::into),
+            root_hash: included_read.root_hash.clone(),
+            verified: true,
+            boolean_proof: None,
+            metrics: Some(metrics),
+            keyword_breakdown,
+            ..Default::default()
         }))
     }
+
+    /// 由单个 storager 证明整个布尔表达式
+    ///
+    /// 只在所有 keyword 都路由到同一个 storager 且没有迁移中的 keyword 时使用；
+    /// storager 不支持该表达式（例如单独的 NOT）时返回 None，由调用方退回逐个查询
+    async fn query_boolean_proof(
+        &self,
+        namespace: &str,
+        func: &str,
+        expr: &BooleanExpr,
+    ) -> Result<Option<Response<QueryResponse>>, Status> {
+        let keywords = expr.get_keywords();
+        let mut owners = HashSet::new();
+        for keyword in &keywords {
+            let Some((node_name, addr)) = self.get_storager_for_keyword(keyword) else {
+                return Ok(None);
+            };
+            if self.migrations.shadow_source(keyword, &node_name).is_some() {
+                return Ok(None);
+            }
+            owners.insert((node_name, addr));
+        }
+        let mut owners = owners.into_iter();
+        let (Some((node_name, storager_addr)), None) = (owners.next(), owners.next()) else {
+            return Ok(None);
+        };
+
+        let requests = keywords
+            .iter()
+            .map(|k| self.read_keyword(namespace, k))
+            .collect();
+        let reads = self.fan_out(requests).await;
+        let mut keyword_proofs = HashMap::new();
+        let mut root_hash = Vec::new();
+        for (keyword, read) in keywords.iter().zip(reads) {
+            let read = read?;
+            if !read.verified || read.node_name != node_name {
+                return Err(ManagerError::keyword_unverified(keyword).into());
+            }
+            root_hash = read.root_hash;
+            keyword_proofs.insert(keyword.clone(), read.proof);
+        }
+
+        let request = StoragerBooleanQueryRequest {
+            expression: func.to_string(),
+            namespace: namespace.to_string(),
+        };
+        let result = self
+            .call_storager(&storager_addr, "BooleanQuery", |mut client| {
+                let request = request.clone();
+                async move { client.boolean_query(request).await }
+            })
+            .await;
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(ManagerError::Storager {
+                code: tonic::Code::FailedPrecondition,
+                message,
+                ..
+            }) => {
+                debug!("Storager cannot prove expression: {}", message);
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let proof = resp.proof.unwrap_or_default();
+        if !self
+            .verifier
+            .verify_boolean_proof(&proof, &keyword_proofs, &resp.fids)
+        {
+            return Err(ManagerError::VerificationFailed(format!(
+                "Boolean proof verification failed for '{}'",
+                func
+            ))
+            .into());
+        }
+
+        debug!("Final result: {} files", resp.fids.len());
+
+        let mut metrics = self.verifier.boolean_proof_metrics(&proof, &resp.fids);
+        for keyword_proof in keyword_proofs.values() {
+            metrics.merge(&self.verifier.proof_metrics(keyword_proof));
+        }
+        let proofs: Vec<Proof> = keyword_proofs.into_values().collect();
+        Ok(Some(Response::new(QueryResponse {
+            fids: resp.fids,
+            proof: self.combine_proofs(&proofs).map(Into::into),
+            root_hash,
+            verified: true,
+            boolean_proof: Some(proof),
+            metrics: Some(metrics),
+            ..Default::default()
+        })))
+    }
+}
+
+/// 检查请求中的命名空间名称
+pub(crate) fn check_namespace(namespace: &str) -> Result<(), ManagerError> {
+    validate_namespace(namespace).map_err(ManagerError::InvalidRequest)
+}
+
+/// 拒绝写入 fid 驻留使用的保留 keyword
+pub(crate) fn check_reserved<I, S>(keywords: I) -> Result<(), ManagerError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    keywords
+        .into_iter()
+        .try_for_each(|keyword| check_keyword(keyword.as_ref()))
+        .map_err(ManagerError::InvalidRequest)
+}
+
+/// storager 返回的证明缺失或类型未知
+pub(crate) fn invalid_proof(error: String) -> Status {
+    ManagerError::InvalidProof(error).into()
+}
+
+/// 异步确认的写请求让 storager 推迟生成证明，结算时再取回
+fn defers_proof(ack_mode: AckMode) -> bool {
+    ack_mode == AckMode::Async
+}
+
+/// 拆开单关键词分页查询的游标 `<storager 的游标>|<已交付 fid 的摘要>`，空游标表示第一页
+///
+/// 摘要由 [`ProofVerifier::fold_delivered`](crate::core::ProofVerifier::fold_delivered)
+/// 逐页折叠，最后一页对照证明中的完整结果，storager 在中间页替换或漏掉 fid 时结果不能通过验证
+fn split_page_token(token: &str) -> Result<(&str, [u8; 32]), PageError> {
+    if token.is_empty() {
+        return Ok(("", DELIVERED_EMPTY));
+    }
+    token
+        .rsplit_once('|')
+        .and_then(|(storager_token, hex)| Some((storager_token, parse_digest_entry(hex)?)))
+        .ok_or_else(|| PageError::Malformed(token.to_string()))
+}
+
+/// 签发下一页的游标（见 [`split_page_token`]），最后一页返回空游标
+fn join_page_token(storager_token: &str, delivered: &[u8; 32]) -> String {
+    if storager_token.is_empty() {
+        return String::new();
+    }
+    format!("{}|{}", storager_token, digest_entry(delivered))
+}
+
+/// 从 Manager 计算出的完整查询结果中取出一页
+///
+/// 结果按 fid 排序，保证每次重新计算时分页的顺序一致。
+/// 证明和验证代价针对完整结果，只保留在最后一页上；`verified` 表示 Manager 已经验证了完整结果
+fn paginate_response(
+    mut response: QueryResponse,
+    page_size: u32,
+    page_token: &str,
+) -> Result<QueryResponse, PageError> {
+    response.fids.sort();
+    let page = paginate(response.fids, page_size, page_token)?;
+    let last = page.is_last();
+    Ok(QueryResponse {
+        fids: page.fids,
+        proof: response.proof.filter(|_| last),
+        root_hash: response.root_hash,
+        verified: response.verified,
+        boolean_proof: response.boolean_proof.filter(|_| last),
+        total_count: page.total_count,
+        next_page_token: page.next_page_token,
+        metrics: response.metrics.filter(|_| last),
+        filtered_keywords: response.filtered_keywords,
+        keyword_breakdown: if last {
+            response.keyword_breakdown
+        } else {
+            Vec::new()
+        },
+    })
+}
+
+/// 布尔查询中一个 keyword 已验证的读取结果
+fn breakdown_entry(keyword: &str, read: &KeywordRead) -> KeywordBreakdown {
+    KeywordBreakdown {
+        keyword: keyword.to_string(),
+        fids: read.fids.clone(),
+        proof: Some(read.proof.clone().into()),
+        root_hash: read.root_hash.clone(),
+        storager: read.node_name.clone(),
+        filtered: false,
+    }
+}
+
+/// 汇总并发变更的结果
+///
+/// 返回: (是否全部验证通过, 异步模式下待确认的审计 id)
+fn merge_outcomes(outcomes: Vec<(bool, Vec<u64>)>, ack_mode: AckMode) -> (bool, Vec<u64>) {
+    let mut all_ok = true;
+    let mut pending_ops = Vec::new();
+    for (ok, audit_ids) in outcomes {
+        all_ok &= ok;
+        if ack_mode == AckMode::Async {
+            pending_ops.extend(audit_ids);
+        }
+    }
+    (all_ok, pending_ops)
 }
@@ -0,0 +1,259 @@
+//! keyword 变更订阅
+//!
+//! 客户端通过 `WatchKeyword` 订阅一个 keyword 或布尔表达式：流先返回当前已验证的结果，
+//! 之后每次写请求涉及表达式中的 keyword，Manager 都重新执行并验证查询，与上次推送的
+//! 结果比较，只推送新增和移除的 fid。
+//!
+//! 重新查询失败或验证没有通过时不推送，也不更新比较的基准，下一次通知包含累积的差异；
+//! 订阅者的缓冲区已满时同样如此，慢订阅者不会阻塞写请求。
+
+use crate::error::ManagerError;
+use crate::manager::Manager;
+use common::rpc::{QueryResponse, WatchKeywordEvent};
+use common::BooleanExpr;
+use std::collections::{BTreeSet, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tonic::Status;
+use tracing::{debug, warn};
+
+/// 每个订阅者缓冲的通知数
+pub const WATCH_BUFFER: usize = 64;
+
+/// 订阅的通知流
+pub type WatchReceiver = mpsc::Receiver<Result<WatchKeywordEvent, Status>>;
+
+/// 一个订阅
+struct Watch {
+    namespace: String,
+    expression: String,
+    expr: BooleanExpr,
+    keywords: HashSet<String>,
+    /// 上次推送的结果；重新查询期间持有，同一订阅的通知按顺序计算
+    state: tokio::sync::Mutex<WatchState>,
+    sender: mpsc::Sender<Result<WatchKeywordEvent, Status>>,
+}
+
+/// 上次推送给订阅者的结果
+#[derive(Default)]
+struct WatchState {
+    fids: BTreeSet<String>,
+    sequence: u64,
+}
+
+/// 所有活跃的订阅
+#[derive(Default)]
+pub struct Watches {
+    watches: Mutex<Vec<Arc<Watch>>>,
+}
+
+impl Watches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 活跃的订阅数
+    pub fn len(&self) -> usize {
+        self.prune();
+        self.watches.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn insert(&self, watch: Arc<Watch>) {
+        self.watches.lock().unwrap().push(watch);
+    }
+
+    /// 移除客户端已经断开的订阅
+    fn prune(&self) {
+        self.watches
+            .lock()
+            .unwrap()
+            .retain(|watch| !watch.sender.is_closed());
+    }
+
+    /// 命名空间中表达式包含任一 `keywords` 的订阅
+    fn affected(&self, namespace: &str, keywords: &HashSet<&str>) -> Vec<Arc<Watch>> {
+        self.prune();
+        self.watches
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|watch| {
+                watch.namespace == namespace
+                    && watch
+                        .keywords
+                        .iter()
+                        .any(|keyword| keywords.contains(keyword.as_str()))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+impl Manager {
+    /// 注册订阅并推送当前结果，返回通知流
+    ///
+    /// 当前结果无法查询或验证没有通过时返回错误，不注册订阅
+    pub(crate) async fn open_watch(
+        &self,
+        namespace: String,
+        expression: String,
+        expr: BooleanExpr,
+    ) -> Result<WatchReceiver, Status> {
+        let (sender, receiver) = mpsc::channel(WATCH_BUFFER);
+        let watch = Arc::new(Watch {
+            namespace,
+            expression,
+            keywords: expr.get_keywords(),
+            expr,
+            state: tokio::sync::Mutex::new(WatchState::default()),
+            sender,
+        });
+
+        // 先持有状态锁再注册：注册之后的写请求等初始结果推送完再比较，不会漏掉变更
+        let mut state = watch.state.lock().await;
+        self.watches.insert(watch.clone());
+        let response = self.evaluate_watch(&watch).await?;
+        if !response.verified {
+            return Err(ManagerError::VerificationFailed(format!(
+                "Initial result of '{}' failed verification",
+                watch.expression
+            ))
+            .into());
+        }
+        state.fids = response.fids.into_iter().collect();
+        let initial = WatchKeywordEvent {
+            added: state.fids.iter().cloned().collect(),
+            removed: Vec::new(),
+            root_hash: response.root_hash,
+            sequence: 0,
+        };
+        // 新建的通道一定有空间
+        let _ = watch.sender.try_send(Ok(initial));
+        Ok(receiver)
+    }
+
+    /// 写请求修改了命名空间中的 `keywords` 之后，向受影响的订阅推送结果的变化
+    pub(crate) async fn notify_watches<'a>(
+        &self,
+        namespace: &str,
+        keywords: impl IntoIterator<Item = &'a String>,
+    ) {
+        let keywords: HashSet<&str> = keywords.into_iter().map(String::as_str).collect();
+        let watches = self.watches.affected(namespace, &keywords);
+        if watches.is_empty() {
+            return;
+        }
+        debug!("Refreshing {} watch(es)", watches.len());
+        let requests = watches
+            .iter()
+            .map(|watch| self.refresh_watch(watch))
+            .collect();
+        self.fan_out(requests).await;
+    }
+
+    /// 重新查询订阅的表达式，结果有变化时推送差异
+    async fn refresh_watch(&self, watch: &Watch) {
+        let mut state = watch.state.lock().await;
+        let response = match self.evaluate_watch(watch).await {
+            Ok(response) if response.verified => response,
+            Ok(_) => {
+                warn!(
+                    "Watched result of '{}' failed verification",
+                    watch.expression
+                );
+                return;
+            }
+            Err(status) => {
+                warn!(
+                    "Failed to refresh watch on '{}': {}",
+                    watch.expression,
+                    status.message()
+                );
+                return;
+            }
+        };
+
+        let fids: BTreeSet<String> = response.fids.into_iter().collect();
+        let added: Vec<String> = fids.difference(&state.fids).cloned().collect();
+        let removed: Vec<String> = state.fids.difference(&fids).cloned().collect();
+        if added.is_empty() && removed.is_empty() {
+            return;
+        }
+        let event = WatchKeywordEvent {
+            added,
+            removed,
+            root_hash: response.root_hash,
+            sequence: state.sequence + 1,
+        };
+        match watch.sender.try_send(Ok(event)) {
+            Ok(()) => {
+                state.fids = fids;
+                state.sequence += 1;
+            }
+            // 保留旧的基准，下一次通知合并这次的差异
+            Err(TrySendError::Full(_)) => {
+                debug!("Watch on '{}' is lagging", watch.expression)
+            }
+            // 客户端已断开，下一次通知前移除
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+
+    /// 查询订阅的表达式的当前结果
+    async fn evaluate_watch(&self, watch: &Watch) -> Result<QueryResponse, Status> {
+        let response = match &watch.expr {
+            BooleanExpr::Keyword(keyword) => {
+                self.query_single_keyword(&watch.namespace, keyword).await?
+            }
+            _ => {
+                self.query_boolean_function(&watch.namespace, &watch.expression, false)
+                    .await?
+            }
+        };
+        Ok(response.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::parse_boolean_expr;
+
+    fn watch(namespace: &str, expression: &str) -> (Arc<Watch>, WatchReceiver) {
+        let (sender, receiver) = mpsc::channel(1);
+        let expr = parse_boolean_expr(expression).unwrap();
+        let watch = Arc::new(Watch {
+            namespace: namespace.to_string(),
+            expression: expression.to_string(),
+            keywords: expr.get_keywords(),
+            expr,
+            state: tokio::sync::Mutex::new(WatchState::default()),
+            sender,
+        });
+        (watch, receiver)
+    }
+
+    #[test]
+    fn test_affected_watches() {
+        let watches = Watches::new();
+        let (rust, _rust_receiver) = watch("", "rust AND NOT go");
+        let (logs, _logs_receiver) = watch("logs", "rust");
+        let (closed, closed_receiver) = watch("", "rust");
+        watches.insert(rust);
+        watches.insert(logs);
+        watches.insert(closed);
+        drop(closed_receiver);
+
+        // 断开的订阅被移除
+        assert_eq!(watches.len(), 2);
+        let affected = watches.affected("", &HashSet::from(["go"]));
+        assert_eq!(affected.len(), 1);
+        assert_eq!(affected[0].expression, "rust AND NOT go");
+        assert_eq!(watches.affected("logs", &HashSet::from(["rust"])).len(), 1);
+        assert!(watches.affected("", &HashSet::from(["java"])).is_empty());
+    }
+}
@@ -0,0 +1,277 @@
+//! 键值存储后端
+//!
+//! MPT 节点、累加器检查点和 storager 的 WAL 都通过 [`Database`] 读写。
+//! 本 crate 提供内存实现 [`MemoryDatabase`]，启用 `sled` feature 后提供
+//! 纯 Rust 的 [`sled::SledStore`]；RocksDB 实现位于 `esa_rust::mpt::db`。
+//!
+//! 持久化后端把数据分在若干列族中（见 [`Column`]），通过 [`ColumnStore`] 访问。
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use thiserror::Error;
+
+#[cfg(feature = "sled")]
+pub mod sled;
+
+/// 数据库操作错误
+#[derive(Error, Debug)]
+pub enum DbError {
+    #[error("Database error: {0}")]
+    Backend(String),
+
+    #[error("{0} is not supported by this database")]
+    Unsupported(&'static str),
+}
+
+/// 键值对 (key, value)
+pub type Entry = (Vec<u8>, Vec<u8>);
+
+/// 按键的字节序遍历条目的迭代器
+pub type DbIter<'a> = Box<dyn Iterator<Item = Result<Entry, DbError>> + 'a>;
+
+/// 批量写入中的一个操作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchOp {
+    Put(Vec<u8>, Vec<u8>),
+    Delete(Vec<u8>),
+}
+
+/// 一组原子写入的操作，按加入顺序执行
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteBatch {
+    ops: Vec<BatchOp>,
+}
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put(&mut self, key: &[u8], value: &[u8]) {
+        self.ops.push(BatchOp::Put(key.to_vec(), value.to_vec()));
+    }
+
+    pub fn delete(&mut self, key: &[u8]) {
+        self.ops.push(BatchOp::Delete(key.to_vec()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub fn ops(&self) -> &[BatchOp] {
+        &self.ops
+    }
+
+    pub fn into_ops(self) -> Vec<BatchOp> {
+        self.ops
+    }
+}
+
+/// 数据库trait，抽象化数据库操作
+pub trait Database {
+    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError>;
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), DbError>;
+    fn delete(&mut self, key: &[u8]) -> Result<(), DbError>;
+
+    /// 执行一组写操作
+    ///
+    /// 默认实现逐个执行，不保证原子性；持久化后端应覆盖此方法
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<(), DbError> {
+        for op in batch.into_ops() {
+            match op {
+                BatchOp::Put(key, value) => self.put(&key, &value)?,
+                BatchOp::Delete(key) => self.delete(&key)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// 按键的字节序遍历所有条目
+    ///
+    /// 默认不支持，只用于点查询的数据库（如测试用的节点存储）无需实现
+    fn iter(&mut self) -> Result<DbIter<'_>, DbError> {
+        Err(DbError::Unsupported("iteration"))
+    }
+
+    /// 按键的字节序列出所有条目
+    fn entries(&mut self) -> Result<Vec<Entry>, DbError> {
+        self.iter()?.collect()
+    }
+
+    /// 在一次批量写入中删除所有条目
+    fn clear(&mut self) -> Result<(), DbError> {
+        let mut batch = WriteBatch::new();
+        for (key, _) in self.entries()? {
+            batch.delete(&key);
+        }
+        self.write_batch(batch)
+    }
+}
+
+/// 持久化后端使用的列族
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// MPT 节点（以节点哈希为键）
+    Nodes,
+    /// ADS 检查点：根哈希、累加器和 fid 列表
+    Metadata,
+    /// 上一个检查点之后的写操作日志
+    Wal,
+    /// fid 驻留映射表（以序号为键）
+    FidTable,
+}
+
+impl Column {
+    pub const ALL: [Column; 4] = [
+        Column::Nodes,
+        Column::Metadata,
+        Column::Wal,
+        Column::FidTable,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Nodes => "nodes",
+            Column::Metadata => "metadata",
+            Column::Wal => "wal",
+            Column::FidTable => "fid_table",
+        }
+    }
+}
+
+/// 列族中的数据库，可以在线程间移动
+pub type ColumnDb = Box<dyn Database + Send + Sync>;
+
+/// 按列族划分的持久化存储
+pub trait ColumnStore: Send + Sync {
+    /// 访问一个列族；返回的句柄与存储共享同一个底层数据库
+    fn column(&self, column: Column) -> Result<ColumnDb, DbError>;
+}
+
+/// 可在线程间共享的数据库句柄
+///
+/// 克隆共享同一个底层数据库，每次操作只短暂加锁：
+/// 并发的读者各自持有一个句柄，只在从数据库加载节点时互斥
+#[derive(Clone)]
+pub struct SharedDatabase {
+    inner: Arc<Mutex<Box<dyn Database + Send>>>,
+}
+
+impl SharedDatabase {
+    pub fn new(db: Box<dyn Database + Send>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(db)),
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Box<dyn Database + Send>>, DbError> {
+        self.inner
+            .lock()
+            .map_err(|_| DbError::Backend("shared database lock poisoned".to_string()))
+    }
+}
+
+impl Database for SharedDatabase {
+    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError> {
+        self.lock()?.get(key)
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
+        self.lock()?.put(key, value)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<(), DbError> {
+        self.lock()?.delete(key)
+    }
+
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<(), DbError> {
+        self.lock()?.write_batch(batch)
+    }
+
+    /// 迭代器不能跨越锁的生命周期，先在锁内收集所有条目
+    fn iter(&mut self) -> Result<DbIter<'_>, DbError> {
+        Ok(Box::new(self.entries()?.into_iter().map(Ok)))
+    }
+
+    fn entries(&mut self) -> Result<Vec<Entry>, DbError> {
+        self.lock()?.entries()
+    }
+}
+
+/// 内存数据库（用于测试和不需要持久化的场景）
+#[derive(Debug, Clone, Default)]
+pub struct MemoryDatabase {
+    data: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl MemoryDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Database for MemoryDatabase {
+    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError> {
+        Ok(self.data.get(key).cloned())
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
+        self.data.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<(), DbError> {
+        self.data.remove(key);
+        Ok(())
+    }
+
+    fn iter(&mut self) -> Result<DbIter<'_>, DbError> {
+        Ok(Box::new(
+            self.data.iter().map(|(k, v)| Ok((k.clone(), v.clone()))),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_and_iteration() {
+        let mut db = MemoryDatabase::new();
+        db.put(b"b", b"2").unwrap();
+
+        let mut batch = WriteBatch::new();
+        batch.put(b"c", b"3");
+        batch.put(b"a", b"1");
+        batch.delete(b"b");
+        assert_eq!(batch.len(), 3);
+        db.write_batch(batch).unwrap();
+
+        assert_eq!(
+            db.entries().unwrap(),
+            vec![
+                (b"a".to_vec(), b"1".to_vec()),
+                (b"c".to_vec(), b"3".to_vec())
+            ]
+        );
+        db.clear().unwrap();
+        assert!(db.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_shared_handles_see_each_other() {
+        let mut writer = SharedDatabase::new(Box::new(MemoryDatabase::new()));
+        let mut reader = writer.clone();
+        writer.put(b"a", b"1").unwrap();
+
+        let handle = std::thread::spawn(move || reader.get(b"a").unwrap());
+        assert_eq!(handle.join().unwrap(), Some(b"1".to_vec()));
+        assert_eq!(writer.entries().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,113 @@
+//! sled 后端
+//!
+//! 每个 [`Column`] 对应一棵同名的 sled tree。sled 是纯 Rust 实现，
+//! 在没有 C++ 工具链、无法编译 RocksDB 的环境中也能持久化 storager 状态。
+
+use crate::{BatchOp, Column, ColumnDb, ColumnStore, Database, DbError, DbIter, WriteBatch};
+use std::path::Path;
+
+fn db_error(e: ::sled::Error) -> DbError {
+    DbError::Backend(e.to_string())
+}
+
+/// sled 数据库，按列族划分为多棵 tree
+#[derive(Clone)]
+pub struct SledStore {
+    db: ::sled::Db,
+}
+
+impl SledStore {
+    /// 打开（不存在时创建）`path` 下的数据库
+    pub fn open(path: &Path) -> Result<Self, DbError> {
+        let db = ::sled::open(path).map_err(db_error)?;
+        Ok(Self { db })
+    }
+
+    /// 访问一个列族
+    pub fn tree(&self, column: Column) -> Result<SledTree, DbError> {
+        let tree = self.db.open_tree(column.name()).map_err(db_error)?;
+        Ok(SledTree { tree })
+    }
+}
+
+impl ColumnStore for SledStore {
+    fn column(&self, column: Column) -> Result<ColumnDb, DbError> {
+        Ok(Box::new(self.tree(column)?))
+    }
+}
+
+/// sled 中的一个列族
+#[derive(Clone)]
+pub struct SledTree {
+    tree: ::sled::Tree,
+}
+
+impl Database for SledTree {
+    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError> {
+        let value = self.tree.get(key).map_err(db_error)?;
+        Ok(value.map(|v| v.to_vec()))
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
+        self.tree.insert(key, value).map_err(db_error)?;
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<(), DbError> {
+        self.tree.remove(key).map_err(db_error)?;
+        Ok(())
+    }
+
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<(), DbError> {
+        let mut sled_batch = ::sled::Batch::default();
+        for op in batch.into_ops() {
+            match op {
+                BatchOp::Put(key, value) => sled_batch.insert(key, value),
+                BatchOp::Delete(key) => sled_batch.remove(key),
+            }
+        }
+        self.tree.apply_batch(sled_batch).map_err(db_error)
+    }
+
+    fn iter(&mut self) -> Result<DbIter<'_>, DbError> {
+        Ok(Box::new(self.tree.iter().map(|entry| {
+            entry
+                .map(|(key, value)| (key.to_vec(), value.to_vec()))
+                .map_err(db_error)
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trees_are_separate_and_persistent() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let store = SledStore::open(dir.path()).unwrap();
+            let mut wal = store.column(Column::Wal).unwrap();
+            let mut batch = WriteBatch::new();
+            batch.put(&2u64.to_be_bytes(), b"second");
+            batch.put(&1u64.to_be_bytes(), b"first");
+            wal.write_batch(batch).unwrap();
+            store
+                .column(Column::Nodes)
+                .unwrap()
+                .put(b"key", b"node")
+                .unwrap();
+        }
+
+        let store = SledStore::open(dir.path()).unwrap();
+        assert_eq!(
+            store.column(Column::Metadata).unwrap().get(b"key").unwrap(),
+            None
+        );
+        let mut wal = store.column(Column::Wal).unwrap();
+        let records: Vec<Vec<u8>> = wal.entries().unwrap().into_iter().map(|(_, v)| v).collect();
+        assert_eq!(records, vec![b"first".to_vec(), b"second".to_vec()]);
+        wal.clear().unwrap();
+        assert!(wal.entries().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,78 @@
+//! 累加器多项式构建基准：逐个乘入 (X - e) 与乘积树
+//!
+//! 对不同大小的元素集合分别用两种方式构建 P(X) = ∏(X - e)，输出耗时和加速比。
+//! 逐个乘入是 O(n²)，乘积树用 FFT 相乘两半，是 O(n log² n)。
+//! 另外给出用 add_batch 从空累加器一次加入全部元素（乘积树加一次 MSM 承诺）的耗时。
+//!
+//! ```bash
+//! cargo bench -p esa_rust --bench accumulator_poly
+//! # 自定义集合大小（逗号分隔）
+//! ACC_BENCH_SIZES=1000,10000,50000 cargo bench -p esa_rust --bench accumulator_poly
+//! ```
+
+use ark_ff::{One, Zero};
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::UVPolynomial;
+use esa_rust::crypto_accumulator::acc::dynamic_accumulator::{
+    element_to_field, poly_from_roots, DynamicAccumulator,
+};
+use esa_rust::crypto_accumulator::acc::public_params::public_params;
+use esa_rust::crypto_accumulator::acc::Fr;
+use std::time::{Duration, Instant};
+
+fn sequential_product(roots: &[Fr]) -> DensePolynomial<Fr> {
+    let mut coeffs = vec![Fr::one()];
+    for root in roots {
+        let mut next = vec![Fr::zero(); coeffs.len() + 1];
+        for (i, c) in coeffs.iter().enumerate() {
+            next[i + 1] += c;
+            next[i] -= *c * root;
+        }
+        coeffs = next;
+    }
+    DensePolynomial::from_coefficients_vec(coeffs)
+}
+
+fn timed<R>(f: impl FnOnce() -> R) -> (R, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+fn main() {
+    let sizes: Vec<usize> = std::env::var("ACC_BENCH_SIZES")
+        .ok()
+        .map(|v| v.split(',').filter_map(|s| s.trim().parse().ok()).collect())
+        .unwrap_or_else(|| vec![1_000, 5_000, 20_000]);
+    // 公开参数在第一次使用时生成，不计入第一个集合的耗时
+    public_params();
+
+    println!(
+        "{:>8} {:>14} {:>14} {:>9} {:>16}",
+        "elements", "sequential", "product tree", "speedup", "accumulator"
+    );
+    for n in sizes {
+        let roots: Vec<Fr> = (0..n as i64).map(|e| element_to_field(&e)).collect();
+        let (expected, sequential) = timed(|| sequential_product(&roots));
+        let (poly, tree) = timed(|| poly_from_roots(&roots));
+        assert_eq!(poly, expected);
+
+        // 批量添加构建多项式并做一次 MSM；超出公开参数的次数时不输出
+        let elements: Vec<i64> = (0..n as i64).collect();
+        let mut acc = DynamicAccumulator::new();
+        let (added, batch) = timed(|| acc.add_batch(&elements));
+        let accumulator = match added {
+            Ok(()) => format!("{:.2?}", batch),
+            Err(_) => "-".to_string(),
+        };
+
+        println!(
+            "{:>8} {:>14.2?} {:>14.2?} {:>8.1}x {:>16}",
+            n,
+            sequential,
+            tree,
+            sequential.as_secs_f64() / tree.as_secs_f64(),
+            accumulator
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! MPT batch_fix 多核扩展性基准
+//!
+//! 构建 100k 个键的 MPT，分别在 1、2、4...N 个线程的 rayon 线程池中执行 batch_fix，
+//! 输出耗时和相对单线程的加速比。
+//!
+//! ```bash
+//! cargo bench -p esa_rust --bench mpt_batch_fix
+//! # 使用汇编 SHA-256
+//! cargo bench -p esa_rust --bench mpt_batch_fix --features asm
+//! # 自定义键数量
+//! MPT_BENCH_KEYS=20000 cargo bench -p esa_rust --bench mpt_batch_fix
+//! ```
+
+use esa_rust::mpt::node::Database;
+use esa_rust::mpt::{DbError, KVPair, MPT};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+struct MemoryDb {
+    data: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl Database for MemoryDb {
+    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError> {
+        Ok(self.data.get(key).cloned())
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
+        self.data.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<(), DbError> {
+        self.data.remove(key);
+        Ok(())
+    }
+}
+
+/// 构建一棵所有节点都是脏节点的 MPT
+fn build_trie(keys: usize) -> (MPT, MemoryDb) {
+    let mut db = MemoryDb {
+        data: HashMap::new(),
+    };
+    let mut mpt = MPT::new(None);
+    for i in 0..keys {
+        let kv = KVPair::new(format!("key{:08}", i), format!("value{}", i));
+        mpt.insert(kv, &mut db, true, false).unwrap();
+    }
+    (mpt, db)
+}
+
+fn run(threads: usize, keys: usize) -> (Duration, [u8; 32]) {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap();
+    let (mut mpt, mut db) = build_trie(keys);
+
+    let start = Instant::now();
+    pool.install(|| mpt.batch_fix(&mut db)).unwrap();
+    (start.elapsed(), mpt.get_root_hash())
+}
+
+fn main() {
+    let keys = std::env::var("MPT_BENCH_KEYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(100_000);
+    let max_threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+
+    let mut thread_counts = vec![1];
+    while thread_counts.last().unwrap() * 2 <= max_threads {
+        thread_counts.push(thread_counts.last().unwrap() * 2);
+    }
+    if *thread_counts.last().unwrap() != max_threads {
+        thread_counts.push(max_threads);
+    }
+
+    println!("MPT batch_fix, {} keys", keys);
+    println!("{:>8} {:>12} {:>8}", "threads", "time (ms)", "speedup");
+
+    let mut baseline: Option<(Duration, [u8; 32])> = None;
+    for threads in thread_counts {
+        let (elapsed, root) = run(threads, keys);
+        let (base_time, base_root) = *baseline.get_or_insert((elapsed, root));
+        assert_eq!(root, base_root, "root hash must not depend on thread count");
+
+        println!(
+            "{:>8} {:>12.1} {:>7.2}x",
+            threads,
+            elapsed.as_secs_f64() * 1000.0,
+            base_time.as_secs_f64() / elapsed.as_secs_f64()
+        );
+    }
+}
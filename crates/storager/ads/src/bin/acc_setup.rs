@@ -0,0 +1,128 @@
+//! 累加器公开参数的可信设置工具
+//!
+//! 第一个参与者生成参数，之后每个参与者依次贡献自己的随机数；
+//! 只要有一个参与者诚实地丢弃了随机数，最终参数的陷门就无人知晓。
+//! storager 通过 `--public-params` 加载最终的参数文件。
+//!
+//! # 使用方法
+//! ```bash
+//! # 生成参数（支持单个累加器最多 4096 个元素）
+//! cargo run -p esa_rust --bin acc-setup -- generate --max-degree 4096 --output round0.pp
+//!
+//! # 其他参与者依次贡献
+//! cargo run -p esa_rust --bin acc-setup -- contribute --input round0.pp --output round1.pp
+//!
+//! # 检查参数结构
+//! cargo run -p esa_rust --bin acc-setup -- verify round1.pp
+//! ```
+
+use esa_rust::crypto_accumulator::PublicParams;
+use std::path::Path;
+use std::process::ExitCode;
+
+fn print_help() {
+    println!("USAGE:");
+    println!("    acc-setup generate --max-degree <N> --output <FILE>");
+    println!("    acc-setup contribute --input <FILE> --output <FILE>");
+    println!("    acc-setup verify <FILE>");
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    let mut command = None;
+    let mut max_degree = None;
+    let mut input = None;
+    let mut output = None;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--max-degree" => {
+                max_degree = args.get(i + 1).and_then(|n| n.parse::<usize>().ok());
+                i += 2;
+            }
+            "--input" => {
+                input = args.get(i + 1).cloned();
+                i += 2;
+            }
+            "--output" => {
+                output = args.get(i + 1).cloned();
+                i += 2;
+            }
+            "--help" | "-h" => {
+                print_help();
+                return ExitCode::SUCCESS;
+            }
+            other if command.is_none() => {
+                command = Some(other.to_string());
+                i += 1;
+            }
+            other => {
+                input = Some(other.to_string());
+                i += 1;
+            }
+        }
+    }
+
+    let result = match command.as_deref() {
+        Some("generate") => generate(max_degree, output.as_deref()),
+        Some("contribute") => contribute(input.as_deref(), output.as_deref()),
+        Some("verify") => verify(input.as_deref()),
+        _ => {
+            print_help();
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn generate(max_degree: Option<usize>, output: Option<&str>) -> Result<(), String> {
+    let max_degree = max_degree
+        .filter(|&n| n >= 1)
+        .ok_or("--max-degree must be a positive integer")?;
+    let output = output.ok_or("missing --output")?;
+
+    let params = PublicParams::setup(max_degree, &mut rand::thread_rng());
+    params.save(Path::new(output)).map_err(|e| e.to_string())?;
+    println!(
+        "✅ Generated public parameters (max degree {}) to {}",
+        max_degree, output
+    );
+    Ok(())
+}
+
+fn contribute(input: Option<&str>, output: Option<&str>) -> Result<(), String> {
+    let input = input.ok_or("missing --input")?;
+    let output = output.ok_or("missing --output")?;
+
+    let mut rng = rand::thread_rng();
+    let mut params = PublicParams::load(Path::new(input)).map_err(|e| e.to_string())?;
+    params.verify(&mut rng).map_err(|e| e.to_string())?;
+    params.contribute(&mut rng);
+    params.save(Path::new(output)).map_err(|e| e.to_string())?;
+    println!(
+        "✅ Added a contribution to {}, written to {}",
+        input, output
+    );
+    Ok(())
+}
+
+fn verify(input: Option<&str>) -> Result<(), String> {
+    let input = input.ok_or("missing parameter file")?;
+    let params = PublicParams::load(Path::new(input)).map_err(|e| e.to_string())?;
+    params
+        .verify(&mut rand::thread_rng())
+        .map_err(|e| e.to_string())?;
+    println!(
+        "✅ {} holds valid public parameters (max degree {})",
+        input,
+        params.max_degree()
+    );
+    Ok(())
+}
@@ -1,12 +1,14 @@
 //! Implements a dynamic cryptographic accumulator that supports additions and deletions.
 
+use super::public_params::public_params;
 use super::{
     utils::{digest_to_prime_field, xgcd},
-    Curve, Fr, G1Affine, G1Projective, G2Affine, G2Projective,
+    Curve, Fr, G1Affine, G1Projective, G2Affine,
 };
 use super::{Acc1, Accumulator};
 use crate::digest::Digestible;
-use crate::set::MultiSet;
+use crate::mpt::node::Database;
+use crate::set::{MultiSet, SetElement};
 use anyhow::{anyhow, Result};
 use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
 use ark_ff::{Field, One, PrimeField, Zero};
@@ -14,8 +16,10 @@ use ark_poly::{
     univariate::{DenseOrSparsePolynomial, DensePolynomial},
     Polynomial, UVPolynomial,
 };
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ops::Neg;
 
 mod ark_serde {
@@ -56,8 +60,7 @@ impl AddProof {
     /// It checks if e(new_acc, g2) == e(old_acc, g2^(s-element)).
     pub fn verify(&self) -> bool {
         // Calculate g2^(s-element)
-        let s_minus_elem: Fr = *super::PRI_S - self.element;
-        let g2_s_minus_elem = super::G2_POWER.apply(&s_minus_elem);
+        let g2_s_minus_elem = public_params().g2_s_minus(self.element);
 
         let lhs = Curve::pairing(self.new_acc_value, G2Affine::prime_subgroup_generator());
         let rhs = Curve::pairing(self.old_acc_value, g2_s_minus_elem);
@@ -79,8 +82,7 @@ impl DeleteProof {
     /// It checks if e(new_acc, g2^(s-element)) == e(old_acc, g2).
     pub fn verify(&self) -> bool {
         // Calculate g2^(s-element)
-        let s_minus_elem: Fr = *super::PRI_S - self.element;
-        let g2_s_minus_elem = super::G2_POWER.apply(&s_minus_elem);
+        let g2_s_minus_elem = public_params().g2_s_minus(self.element);
 
         let lhs = Curve::pairing(self.new_acc_value, g2_s_minus_elem);
         let rhs = Curve::pairing(self.old_acc_value, G2Affine::prime_subgroup_generator());
@@ -102,14 +104,77 @@ impl MembershipProof {
     /// It checks if e(witness, g2^(s-element)) == e(accumulator, g2).
     pub fn verify(&self, accumulator: G1Affine) -> bool {
         // Calculate g2^(s-element)
-        let s_minus_elem: Fr = *super::PRI_S - self.element;
-        let g2_s_minus_elem = super::G2_POWER.apply(&s_minus_elem);
+        let g2_s_minus_elem = public_params().g2_s_minus(self.element);
 
         let lhs = Curve::pairing(self.witness, g2_s_minus_elem);
         let rhs = Curve::pairing(accumulator, G2Affine::prime_subgroup_generator());
 
         lhs == rhs
     }
+
+    /// Updates the witness after `add` was added to the accumulator, without pairings or
+    /// the trapdoor: P(X)(X-y)/(X-x) = P(X) + (x-y)*P(X)/(X-x), so the new witness is
+    /// old_acc + (x-y)*witness.
+    pub fn update_on_add(&mut self, add: &AddProof) {
+        self.witness = witness_after_add(self.witness, self.element, add).into_affine();
+    }
+
+    /// Updates the witness after `delete` removed another element from the accumulator:
+    /// 1/((X-x)(X-y)) = (1/(X-x) - 1/(X-y)) / (x-y), so the new witness is
+    /// (witness - new_acc) / (x-y).
+    /// Returns an error if the deleted element is the proven one.
+    pub fn update_on_delete(&mut self, delete: &DeleteProof) -> Result<()> {
+        self.witness = witness_after_delete(self.witness, self.element, delete)?.into_affine();
+        Ok(())
+    }
+}
+
+/// See [`MembershipProof::update_on_add`].
+fn witness_after_add(witness: G1Affine, element: Fr, add: &AddProof) -> G1Projective {
+    witness.mul((element - add.element).into_repr()) + add.old_acc_value.into_projective()
+}
+
+/// See [`MembershipProof::update_on_delete`].
+fn witness_after_delete(
+    witness: G1Affine,
+    element: Fr,
+    delete: &DeleteProof,
+) -> Result<G1Projective> {
+    let inverse = (element - delete.element)
+        .inverse()
+        .ok_or_else(|| anyhow!("The proven element itself was deleted"))?;
+    Ok(
+        (witness.into_projective() - delete.new_acc_value.into_projective())
+            .mul(inverse.into_repr()),
+    )
+}
+
+/// A single proof that several elements are all in the accumulator.
+/// The witness is the accumulator of the complement set, g1^(P(s)/Q(s)),
+/// where Q(X) = product(X - e) over the proven elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchMembershipProof {
+    pub witness: G1Affine,
+    /// The proven elements, sorted and without duplicates.
+    pub elements: Vec<Fr>,
+}
+
+impl BatchMembershipProof {
+    /// Verifies the proof against the given accumulator value with one pairing check:
+    /// e(witness, g2^Q(s)) == e(accumulator, g2).
+    pub fn verify(&self, accumulator: G1Affine) -> bool {
+        let Ok(g2_q) = public_params().commit_g2(&poly_from_elements(&self.elements)) else {
+            return false;
+        };
+        Curve::product_of_pairings(&[
+            (self.witness.into(), g2_q.into()),
+            (
+                accumulator.neg().into(),
+                G2Affine::prime_subgroup_generator().into(),
+            ),
+        ])
+        .is_one()
+    }
 }
 
 /// A proof of non-membership for an element in the accumulator.
@@ -123,6 +188,21 @@ pub struct NonMembershipProof {
     pub g1_a: G1Affine,
 }
 
+impl NonMembershipProof {
+    /// Verifies the proof against the given accumulator value:
+    /// e(accumulator, witness) * e(g1_a, g2^(s-element)) == e(g1, g2).
+    pub fn verify(&self, accumulator: G1Affine) -> bool {
+        let g2_s_minus_x = public_params().g2_s_minus(self.element);
+        let lhs1 = Curve::pairing(accumulator, self.witness);
+        let lhs2 = Curve::pairing(self.g1_a, g2_s_minus_x);
+        let rhs = Curve::pairing(
+            G1Affine::prime_subgroup_generator(),
+            G2Affine::prime_subgroup_generator(),
+        );
+        lhs1 * lhs2 == rhs
+    }
+}
+
 /// A proof that a given accumulator represents the intersection of two other accumulators.
 /// This proof uses the Bézout identity: A(X)*P1(X) + B(X)*P2(X) = P_intersect(X)
 /// where P1, P2 are the polynomials of the two original sets, and P_intersect is the intersection polynomial.
@@ -142,6 +222,51 @@ pub struct IntersectionProof {
     pub witness_coprime_b: G1Affine,
 }
 
+/// A proof that a given accumulator represents the intersection of any number of accumulators.
+/// It generalizes [`IntersectionProof`]: every set polynomial is P_i(X) = Q_i(X) * P_intersect(X),
+/// and the quotients share no root, shown by Bézout coefficients with sum(B_i(X) * Q_i(X)) = 1.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultiIntersectionProof {
+    /// g2^Q_i(s) for every input accumulator, in input order
+    #[serde(with = "ark_serde")]
+    pub quotient_witnesses: Vec<G2Affine>,
+    /// g1^B_i(s) for every input accumulator, in input order
+    #[serde(with = "ark_serde")]
+    pub coprime_witnesses: Vec<G1Affine>,
+}
+
+impl IntersectionProof {
+    /// Serializes the four witnesses in declaration order.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.witness_a.serialize(&mut bytes)?;
+        self.witness_b.serialize(&mut bytes)?;
+        self.witness_coprime_a.serialize(&mut bytes)?;
+        self.witness_coprime_b.serialize(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Restores a proof from the output of [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        let proof = Self {
+            witness_a: G2Affine::deserialize(&mut bytes)?,
+            witness_b: G2Affine::deserialize(&mut bytes)?,
+            witness_coprime_a: G1Affine::deserialize(&mut bytes)?,
+            witness_coprime_b: G1Affine::deserialize(&mut bytes)?,
+        };
+        if !bytes.is_empty() {
+            return Err(anyhow!(
+                "{} trailing bytes after intersection proof",
+                bytes.len()
+            ));
+        }
+        Ok(proof)
+    }
+}
+
+/// A proof that a given accumulator represents the union of two other accumulators.
+/// With I = A ∩ B, the union polynomial is P_A(X) * P_B(X) / P_I(X), so the intersection
+/// proof's second quotient witness g2^(P_B(s)/P_I(s)) links the union to A.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UnionProof {
     #[serde(with = "ark_serde")]
@@ -149,6 +274,52 @@ pub struct UnionProof {
     pub intersection_proof: IntersectionProof,
 }
 
+impl UnionProof {
+    /// Serializes the intersection accumulator value followed by the intersection proof.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.intersection_acc_value.serialize(&mut bytes)?;
+        bytes.extend(self.intersection_proof.to_bytes()?);
+        Ok(bytes)
+    }
+
+    /// Restores a proof from the output of [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        Ok(Self {
+            intersection_acc_value: G1Affine::deserialize(&mut bytes)?,
+            intersection_proof: IntersectionProof::from_bytes(bytes)?,
+        })
+    }
+}
+
+/// A proof that a set of elements is the difference `A \ B` of two accumulated sets.
+/// It is an intersection proof for I = A ∩ B: since A is the disjoint union of `A \ B` and I,
+/// its first quotient witness g2^(P_A(s)/P_I(s)) must equal the commitment of the difference.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DifferenceProof {
+    #[serde(with = "ark_serde")]
+    pub intersection_acc_value: G1Affine,
+    pub intersection_proof: IntersectionProof,
+}
+
+impl DifferenceProof {
+    /// Serializes the intersection accumulator value followed by the intersection proof.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.intersection_acc_value.serialize(&mut bytes)?;
+        bytes.extend(self.intersection_proof.to_bytes()?);
+        Ok(bytes)
+    }
+
+    /// Restores a proof from the output of [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        Ok(Self {
+            intersection_acc_value: G1Affine::deserialize(&mut bytes)?,
+            intersection_proof: IntersectionProof::from_bytes(bytes)?,
+        })
+    }
+}
+
 /// Represents the result of a query against the accumulator.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum QueryResult {
@@ -158,16 +329,109 @@ pub enum QueryResult {
     NonMembership(NonMembershipProof),
 }
 
+/// Returns poly * (X - root).
+fn mul_by_root(poly: &DensePolynomial<Fr>, root: Fr) -> DensePolynomial<Fr> {
+    let mut coeffs = vec![Fr::zero(); poly.coeffs.len() + 1];
+    for (i, c) in poly.coeffs.iter().enumerate() {
+        coeffs[i + 1] += c;
+        coeffs[i] -= *c * root;
+    }
+    DensePolynomial::from_coefficients_vec(coeffs)
+}
+
+/// Returns poly / (X - root) by synthetic division. `root` must be a root of `poly`.
+fn div_by_root(poly: &DensePolynomial<Fr>, root: Fr) -> DensePolynomial<Fr> {
+    let mut quotient = vec![Fr::zero(); poly.coeffs.len().saturating_sub(1)];
+    let mut carry = Fr::zero();
+    for i in (1..poly.coeffs.len()).rev() {
+        carry = poly.coeffs[i] + carry * root;
+        quotient[i - 1] = carry;
+    }
+    DensePolynomial::from_coefficients_vec(quotient)
+}
+
+/// Below this many roots, multiplying in (X - e) terms one at a time is faster than
+/// splitting further and multiplying the halves with FFTs.
+const PRODUCT_TREE_LEAF: usize = 64;
+
+/// Returns P(X) = product(X - e) over the given roots.
+///
+/// The product is built as a balanced tree: both halves are expanded in parallel and
+/// multiplied with FFTs, O(n log^2 n) in total instead of O(n^2) for multiplying in
+/// one root at a time.
+pub fn poly_from_roots(roots: &[Fr]) -> DensePolynomial<Fr> {
+    if roots.len() <= PRODUCT_TREE_LEAF {
+        return roots.iter().fold(
+            DensePolynomial::from_coefficients_vec(vec![Fr::one()]),
+            |poly, root| mul_by_root(&poly, *root),
+        );
+    }
+    let (left, right) = roots.split_at(roots.len() / 2);
+    let (left, right) = rayon::join(|| poly_from_roots(left), || poly_from_roots(right));
+    &left * &right
+}
+
+/// Returns P(X) = product(X - e) over the given elements.
+fn poly_from_elements<'a>(elements: impl IntoIterator<Item = &'a Fr>) -> DensePolynomial<Fr> {
+    let roots: Vec<Fr> = elements.into_iter().copied().collect();
+    poly_from_roots(&roots)
+}
+
+/// A value that can be stored in the accumulator, mapped to a field element through its
+/// [`Digestible`] digest. Strings such as fids are accumulated directly, so distinct values
+/// collide only if their digests do.
+///
+/// Implemented for `i64`, strings, byte strings and references to them, so `&[&str]` and
+/// `&[String]` both work as batches. Other integer types are left out on
+/// purpose: with `i64` as the only integer implementation, an untyped literal such as
+/// `acc.add(&100)` still means an `i64` instead of falling back to `i32`, whose digest differs.
+pub trait AccElement: Digestible {}
+
+impl AccElement for i64 {}
+impl AccElement for str {}
+impl AccElement for String {}
+impl AccElement for [u8] {}
+impl<T: AccElement + ?Sized> AccElement for &T {}
+
+/// Maps an element to the field element the accumulator stores for it,
+/// so verifiers can rebuild proofs from serialized elements.
+pub fn element_to_field<T: AccElement + ?Sized>(element: &T) -> Fr {
+    digest_to_prime_field(&element.to_digest())
+}
+
 /// A dynamic cryptographic accumulator based on the Acc1 scheme.
 /// It maintains the accumulator value and the set of elements internally.
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// All values and witnesses are computed from the public parameters (powers of s);
+/// the trapdoor s is never used.
+///
+/// Membership witnesses of selected elements can be cached with
+/// [`cache_witness`](Self::cache_witness). Cached witnesses are kept current on every
+/// add and delete with one scalar multiplication each, so proving membership of a
+/// cached element needs no polynomial division or MSM.
+#[derive(Debug, Clone)]
 pub struct DynamicAccumulator {
     /// The current accumulator value, g1^P(s).
     pub acc_value: G1Affine,
     /// The set of elements (as field elements).
     elements: HashSet<Fr>,
+    /// The accumulator polynomial P(X) = product(X - e).
+    poly: DensePolynomial<Fr>,
+    /// Cached membership witnesses, g1^(P(s)/(s-e)), by element.
+    witnesses: HashMap<Fr, G1Affine>,
+}
+
+/// Cached witnesses are derived data and do not affect equality.
+impl PartialEq for DynamicAccumulator {
+    fn eq(&self, other: &Self) -> bool {
+        self.acc_value == other.acc_value
+            && self.elements == other.elements
+            && self.poly == other.poly
+    }
 }
 
+impl Eq for DynamicAccumulator {}
+
 impl DynamicAccumulator {
     /// Creates a new, empty dynamic accumulator.
     /// The initial value is g1^1, representing an empty set.
@@ -177,42 +441,101 @@ impl DynamicAccumulator {
                 .mul(Fr::one().into_repr())
                 .into_affine(),
             elements: HashSet::new(),
+            poly: DensePolynomial::from_coefficients_vec(vec![Fr::one()]),
+            witnesses: HashMap::new(),
         }
     }
 
+    /// Builds an accumulator for a set of field elements.
+    fn from_elements(elements: HashSet<Fr>) -> Result<Self> {
+        let poly = poly_from_elements(&elements);
+        Ok(Self {
+            acc_value: public_params().commit_g1(&poly)?,
+            elements,
+            poly,
+            witnesses: HashMap::new(),
+        })
+    }
+
     /// Adds a new element to the accumulator and returns a proof of the operation.
     /// If the element already exists, it returns an error.
     /// The accumulator value is updated by scalar multiplying it with (s-element).
-    pub fn add(&mut self, element: &i64) -> Result<AddProof> {
+    pub fn add<T: AccElement + ?Sized>(&mut self, element: &T) -> Result<AddProof> {
         let fr_element = digest_to_prime_field(&element.to_digest());
         if self.elements.contains(&fr_element) {
             return Err(anyhow!("Element already in accumulator"));
         }
         let old_acc = self.acc_value;
 
-        // Update accumulator value: acc' = acc^(s-element)
-        let s_minus_elem: Fr = *super::PRI_S - fr_element;
-        self.acc_value = self
-            .acc_value
-            .into_projective()
-            .mul(s_minus_elem.into_repr())
-            .into_affine();
+        // Update accumulator value: acc' = g1^(P(s)*(s-element))
+        let poly = mul_by_root(&self.poly, fr_element);
+        self.acc_value = public_params().commit_g1(&poly)?;
+        self.poly = poly;
 
         // Update the element set
         self.elements.insert(fr_element);
 
-        Ok(AddProof {
+        let proof = AddProof {
             old_acc_value: old_acc,
             new_acc_value: self.acc_value,
             element: fr_element,
-        })
+        };
+        self.update_witnesses(|witness, element| Ok(witness_after_add(witness, element, &proof)))?;
+        Ok(proof)
+    }
+
+    /// Applies `update` to every cached witness in parallel and converts the results back
+    /// to affine form together, with one field inversion instead of one per witness.
+    fn update_witnesses(
+        &mut self,
+        update: impl Fn(G1Affine, Fr) -> Result<G1Projective> + Sync,
+    ) -> Result<()> {
+        let (elements, witnesses): (Vec<Fr>, Vec<G1Affine>) = self.witnesses.drain().unzip();
+        let mut updated = elements
+            .par_iter()
+            .zip(witnesses.par_iter())
+            .map(|(element, witness)| update(*witness, *element))
+            .collect::<Result<Vec<_>>>()?;
+        G1Projective::batch_normalization(&mut updated);
+        self.witnesses = elements
+            .into_iter()
+            .zip(updated.into_iter().map(|w| w.into_affine()))
+            .collect();
+        Ok(())
     }
 
     /// Adds multiple elements to the accumulator in a batch.
-    pub fn add_batch(&mut self, elements: &[i64]) -> Result<()> {
+    /// The new elements are multiplied into P(X) as one product-tree polynomial and the
+    /// accumulator is committed once, instead of once per element.
+    /// Returns an error without changing the accumulator if any element is already in it
+    /// or appears twice in the batch.
+    pub fn add_batch<T: AccElement>(&mut self, elements: &[T]) -> Result<()> {
+        let mut roots = Vec::with_capacity(elements.len());
+        let mut seen = HashSet::new();
         for element in elements {
-            self.add(element)?;
+            let fr_element = digest_to_prime_field(&element.to_digest());
+            if self.elements.contains(&fr_element) || !seen.insert(fr_element) {
+                return Err(anyhow!("Element already in accumulator"));
+            }
+            roots.push(fr_element);
+        }
+        if roots.is_empty() {
+            return Ok(());
         }
+
+        let poly = &self.poly * &poly_from_roots(&roots);
+        let acc_value = public_params().commit_g1(&poly)?;
+        // Cached witnesses are recomputed once rather than updated per element
+        let witnesses = self
+            .witnesses
+            .keys()
+            .map(|e| Ok((*e, public_params().commit_g1(&div_by_root(&poly, *e))?)))
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        self.acc_value = acc_value;
+        self.poly = poly;
+        self.elements.extend(roots);
+        self.witnesses = witnesses;
         Ok(())
     }
 
@@ -220,10 +543,10 @@ impl DynamicAccumulator {
     /// This is implemented as a delete operation followed by an add operation.
     /// Returns proofs for both operations.
     /// Returns an error if the old element is not in the accumulator.
-    pub fn update(
+    pub fn update<T: AccElement + ?Sized>(
         &mut self,
-        old_element: &i64,
-        new_element: &i64,
+        old_element: &T,
+        new_element: &T,
     ) -> Result<(DeleteProof, AddProof)> {
         let delete_proof = self.delete(old_element)?;
         let add_proof = self.add(new_element)?;
@@ -234,7 +557,7 @@ impl DynamicAccumulator {
     /// If the element exists, its count is decremented. If the count reaches zero, it's removed.
     /// The accumulator value is updated by scalar multiplying it with the inverse of (s-element).
     /// Returns an error if the element is not in the accumulator.
-    pub fn delete(&mut self, element: &i64) -> Result<DeleteProof> {
+    pub fn delete<T: AccElement + ?Sized>(&mut self, element: &T) -> Result<DeleteProof> {
         let fr_element = digest_to_prime_field(&element.to_digest());
         let old_acc = self.acc_value;
 
@@ -242,31 +565,30 @@ impl DynamicAccumulator {
             return Err(anyhow!("Element not in accumulator"));
         }
 
-        // Update accumulator value: acc' = acc^((s-element)^-1)
-        let s_minus_elem: Fr = *super::PRI_S - fr_element;
-        let s_minus_elem_inv = s_minus_elem
-            .inverse()
-            .ok_or_else(|| anyhow!("Failed to compute inverse"))?;
-        self.acc_value = self
-            .acc_value
-            .into_projective()
-            .mul(s_minus_elem_inv.into_repr())
-            .into_affine();
+        // Update accumulator value: acc' = g1^(P(s)/(s-element))
+        let poly = div_by_root(&self.poly, fr_element);
+        self.acc_value = public_params().commit_g1(&poly)?;
+        self.poly = poly;
 
         // Update the element set
         self.elements.remove(&fr_element);
 
-        Ok(DeleteProof {
+        let proof = DeleteProof {
             old_acc_value: old_acc,
             new_acc_value: self.acc_value,
             element: fr_element,
-        })
+        };
+        self.witnesses.remove(&fr_element);
+        // The deleted element is no longer cached, so the update cannot fail
+        self.update_witnesses(|witness, element| witness_after_delete(witness, element, &proof))?;
+        Ok(proof)
     }
 
     /// Generates a membership proof for a given element.
     /// The proof's witness is an accumulator for the set of all other elements.
+    /// A cached witness is returned without recomputation.
     /// Returns an error if the element is not in the accumulator.
-    pub fn prove_membership(&self, element: &i64) -> Result<MembershipProof> {
+    pub fn prove_membership<T: AccElement + ?Sized>(&self, element: &T) -> Result<MembershipProof> {
         let fr_element = digest_to_prime_field(&element.to_digest());
 
         if !self.elements.contains(&fr_element) {
@@ -274,17 +596,15 @@ impl DynamicAccumulator {
                 "Cannot prove membership for an element not in the set"
             ));
         }
+        if let Some(witness) = self.witnesses.get(&fr_element) {
+            return Ok(MembershipProof {
+                witness: *witness,
+                element: fr_element,
+            });
+        }
 
-        // Calculate witness: acc^((s-element)^-1)
-        let s_minus_elem: Fr = *super::PRI_S - fr_element;
-        let s_minus_elem_inv = s_minus_elem
-            .inverse()
-            .ok_or_else(|| anyhow!("Failed to compute inverse"))?;
-        let witness = self
-            .acc_value
-            .into_projective()
-            .mul(s_minus_elem_inv.into_repr())
-            .into_affine();
+        // Calculate witness: g1^(P(s)/(s-element))
+        let witness = public_params().commit_g1(&div_by_root(&self.poly, fr_element))?;
 
         Ok(MembershipProof {
             witness,
@@ -292,14 +612,81 @@ impl DynamicAccumulator {
         })
     }
 
+    /// Computes the membership witness of an element once and keeps it up to date
+    /// across later adds and deletes, until the element is deleted or evicted.
+    /// Returns an error if the element is not in the accumulator.
+    pub fn cache_witness<T: AccElement + ?Sized>(&mut self, element: &T) -> Result<()> {
+        let proof = self.prove_membership(element)?;
+        self.witnesses.insert(proof.element, proof.witness);
+        Ok(())
+    }
+
+    /// Stops maintaining the cached witness of an element.
+    /// Returns true if a witness was cached.
+    pub fn evict_witness<T: AccElement + ?Sized>(&mut self, element: &T) -> bool {
+        self.witnesses
+            .remove(&digest_to_prime_field(&element.to_digest()))
+            .is_some()
+    }
+
+    /// Returns true if the witness of the element is cached.
+    pub fn is_witness_cached<T: AccElement + ?Sized>(&self, element: &T) -> bool {
+        self.witnesses
+            .contains_key(&digest_to_prime_field(&element.to_digest()))
+    }
+
+    /// Returns the number of cached witnesses.
+    pub fn cached_witnesses(&self) -> usize {
+        self.witnesses.len()
+    }
+
     /// Verifies a membership proof against the current accumulator value.
     pub fn verify_membership(&self, proof: &MembershipProof) -> bool {
         proof.verify(self.acc_value)
     }
 
+    /// Generates one membership proof covering all given elements.
+    /// The witness costs a single MSM and verification a single pairing check,
+    /// instead of one of each per element. Duplicates are proven once.
+    /// Returns an error if any element is not in the accumulator.
+    pub fn prove_membership_batch<T: AccElement>(
+        &self,
+        elements: &[T],
+    ) -> Result<BatchMembershipProof> {
+        let mut fr_elements: Vec<Fr> = elements
+            .iter()
+            .map(|element| digest_to_prime_field(&element.to_digest()))
+            .collect();
+        fr_elements.sort();
+        fr_elements.dedup();
+
+        if fr_elements.iter().any(|e| !self.elements.contains(e)) {
+            return Err(anyhow!(
+                "Cannot prove membership for an element not in the set"
+            ));
+        }
+
+        // Calculate witness: g1^(P(s)/Q(s))
+        let quotient = fr_elements
+            .iter()
+            .fold(self.poly.clone(), |poly, e| div_by_root(&poly, *e));
+        Ok(BatchMembershipProof {
+            witness: public_params().commit_g1(&quotient)?,
+            elements: fr_elements,
+        })
+    }
+
+    /// Verifies a batch membership proof against the current accumulator value.
+    pub fn verify_membership_batch(&self, proof: &BatchMembershipProof) -> bool {
+        proof.verify(self.acc_value)
+    }
+
     /// Generates a non-membership proof for a given element.
     /// Returns an error if the element IS in the accumulator.
-    pub fn prove_non_membership(&self, element: &i64) -> Result<NonMembershipProof> {
+    pub fn prove_non_membership<T: AccElement + ?Sized>(
+        &self,
+        element: &T,
+    ) -> Result<NonMembershipProof> {
         let fr_element = digest_to_prime_field(&element.to_digest());
 
         if self.elements.contains(&fr_element) {
@@ -315,13 +702,8 @@ impl DynamicAccumulator {
         // This corresponds to e(g1^P(s), g2^B(s)) * e(g1^A(s), g2^(s-x)) == e(g1, g2)
         // which is B(s)*P(s) + A(s)*(s-x) = 1.
 
-        // 1. Construct the accumulator polynomial P(X) = product(X-e_i).
-        let mut p_poly = DensePolynomial::from_coefficients_vec(vec![Fr::one()]);
-        for elem in &self.elements {
-            // X - e
-            let e_poly = DensePolynomial::from_coefficients_vec(vec![elem.neg(), Fr::one()]);
-            p_poly = &p_poly * &e_poly;
-        }
+        // 1. The accumulator polynomial P(X) = product(X-e_i).
+        let p_poly = self.poly.clone();
 
         // 2. Construct the polynomial for the non-member, Q(X) = X-x.
         let q_poly = DensePolynomial::from_coefficients_vec(vec![fr_element.neg(), Fr::one()]); // X-x
@@ -345,17 +727,13 @@ impl DynamicAccumulator {
                     b_poly.coeffs.iter().map(|c| *c * gcd_inv).collect(),
                 );
 
-                // 4. Evaluate the normalized polynomials at the secret `s`.
-                let a_s = a_poly_norm.evaluate(&*super::PRI_S);
-                let b_s = b_poly_norm.evaluate(&*super::PRI_S);
-
-                // 5. Compute the witness parts: g1^A(s) and g2^B(s)
-                let g1_a = G1Projective::prime_subgroup_generator()
-                    .mul(a_s.into_repr())
-                    .into_affine();
-                let witness_b = G2Projective::prime_subgroup_generator()
-                    .mul(b_s.into_repr())
-                    .into_affine();
+                // 4. Commit to the normalized polynomials with the public parameters:
+                //    g1^A(s) and g2^B(s)
+                let (g1_a, witness_b) = rayon::join(
+                    || public_params().commit_g1(&a_poly_norm),
+                    || public_params().commit_g2(&b_poly_norm),
+                );
+                let (g1_a, witness_b) = (g1_a?, witness_b?);
 
                 return Ok(NonMembershipProof {
                     element: fr_element,
@@ -370,25 +748,8 @@ impl DynamicAccumulator {
 
     /// Verifies a non-membership proof against the current accumulator value.
     pub fn verify_non_membership(&self, proof: &NonMembershipProof) -> bool {
-        // Verification equation: e(Acc, witness) * e(g1_a, g2^(s-x)) == e(g1, g2)
-        // Here, witness = g2^B(s) and g1_a = g1^A(s).
-        // So, e(g1^P(s), g2^B(s)) * e(g1^A(s), g2^(s-x)) == e(g1, g2)
-        // which simplifies to e(g1,g2)^(B(s)*P(s) + A(s)*(s-x)) == e(g1,g2)^1
-        // This holds if B(s)*P(s) + A(s)*(s-x) = 1.
-
-        // 1. Calculate g2^(s-x)
-        let s_minus_x = *super::PRI_S - proof.element;
-        let g2_s_minus_x = super::G2_POWER.apply(&s_minus_x);
-
-        // 2. Calculate the pairings
-        let lhs1 = Curve::pairing(self.acc_value, proof.witness);
-        let lhs2 = Curve::pairing(proof.g1_a, g2_s_minus_x);
-        let rhs = Curve::pairing(
-            G1Affine::prime_subgroup_generator(),
-            G2Affine::prime_subgroup_generator(),
-        );
-
-        lhs1 * lhs2 == rhs
+        // B(s)*P(s) + A(s)*(s-x) = 1 holds only if x is not a root of P(X)
+        proof.verify(self.acc_value)
     }
 
     /// Returns the number of elements in the accumulator.
@@ -401,15 +762,73 @@ impl DynamicAccumulator {
         self.elements.is_empty()
     }
 
+    /// Returns true if the element is in the accumulator.
+    pub fn contains<T: AccElement + ?Sized>(&self, element: &T) -> bool {
+        self.elements
+            .contains(&digest_to_prime_field(&element.to_digest()))
+    }
+
     /// Returns a vector of field elements (Fr) contained in the accumulator.
     /// Note: Original application values cannot be recovered from Fr digests.
     pub fn elements_fr(&self) -> Vec<Fr> {
         self.elements.iter().cloned().collect()
     }
 
+    /// Serializes the accumulator value and its element set.
+    ///
+    /// Elements are written in sorted order, so equal accumulators produce equal bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut elements = self.elements_fr();
+        elements.sort();
+
+        let mut bytes = Vec::new();
+        self.acc_value.serialize(&mut bytes)?;
+        elements.serialize(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Restores an accumulator from the output of [`to_bytes`](Self::to_bytes).
+    ///
+    /// The accumulator value is recomputed from the elements and must match the stored one,
+    /// so data written under different public parameters is rejected.
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        let acc_value = G1Affine::deserialize(&mut bytes)?;
+        let elements: Vec<Fr> = CanonicalDeserialize::deserialize(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(anyhow!("{} trailing bytes after accumulator", bytes.len()));
+        }
+
+        let count = elements.len();
+        let elements: HashSet<Fr> = elements.into_iter().collect();
+        if elements.len() != count {
+            return Err(anyhow!("duplicate elements in serialized accumulator"));
+        }
+
+        let restored = Self::from_elements(elements)?;
+        if restored.acc_value != acc_value {
+            return Err(anyhow!(
+                "accumulator value does not match its elements under the current parameters"
+            ));
+        }
+        Ok(restored)
+    }
+
+    /// Stores the serialized accumulator under `key`.
+    pub fn save_to_db(&self, db: &mut dyn Database, key: &[u8]) -> Result<()> {
+        db.put(key, &self.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// Loads the accumulator stored under `key`, returning `None` if the key is absent.
+    pub fn load_from_db(db: &mut dyn Database, key: &[u8]) -> Result<Option<Self>> {
+        db.get(key)?
+            .map(|bytes| Self::from_bytes(&bytes))
+            .transpose()
+    }
+
     /// Queries the accumulator for a given element and returns a cryptographic proof
     /// of either membership or non-membership.
-    pub fn query(&self, element: &i64) -> QueryResult {
+    pub fn query<T: AccElement + ?Sized>(&self, element: &T) -> QueryResult {
         let fr_element = digest_to_prime_field(&element.to_digest());
         if self.elements.contains(&fr_element) {
             // This unwrap is safe because we've just checked for the element's existence.
@@ -437,38 +856,12 @@ impl DynamicAccumulator {
             .collect();
 
         // 2. Create the intersection accumulator
-        let mut intersection_acc = DynamicAccumulator::new();
-        intersection_acc.elements = intersection_elements;
-
-        // Calculate the intersection accumulator value
-        let mut acc_value = G1Projective::from(G1Affine::prime_subgroup_generator());
-        for elem in &intersection_acc.elements {
-            let s_minus_elem = *super::PRI_S - elem;
-            acc_value = acc_value.mul(s_minus_elem.into_repr());
-        }
-        intersection_acc.acc_value = acc_value.into_affine();
-
-        // 3. Construct polynomials for each set
-        // P1(X) = product(X - e_i) for elements in self
-        let mut p1_poly = DensePolynomial::from_coefficients_vec(vec![Fr::one()]);
-        for elem in &self.elements {
-            let e_poly = DensePolynomial::from_coefficients_vec(vec![elem.neg(), Fr::one()]);
-            p1_poly = &p1_poly * &e_poly;
-        }
-
-        // P2(X) = product(X - e_i) for elements in other
-        let mut p2_poly = DensePolynomial::from_coefficients_vec(vec![Fr::one()]);
-        for elem in &other.elements {
-            let e_poly = DensePolynomial::from_coefficients_vec(vec![elem.neg(), Fr::one()]);
-            p2_poly = &p2_poly * &e_poly;
-        }
+        let intersection_acc = DynamicAccumulator::from_elements(intersection_elements)?;
 
-        // P_intersect(X) = product(X - e_i) for elements in intersection
-        let mut p_intersect_poly = DensePolynomial::from_coefficients_vec(vec![Fr::one()]);
-        for elem in &intersection_acc.elements {
-            let e_poly = DensePolynomial::from_coefficients_vec(vec![elem.neg(), Fr::one()]);
-            p_intersect_poly = &p_intersect_poly * &e_poly;
-        }
+        // 3. Polynomials for each set: P1(X), P2(X) and P_intersect(X)
+        let p1_poly = &self.poly;
+        let p2_poly = &other.poly;
+        let p_intersect_poly = &intersection_acc.poly;
 
         // 4. Use extended GCD to find Bézout coefficients
         // We need to find A(X) and B(X) such that A(X)*P1(X) + B(X)*P2(X) = P_intersect(X)
@@ -479,8 +872,8 @@ impl DynamicAccumulator {
         // Then use the identity: P1(X) = Q1(X) * P_intersect(X) and P2(X) = Q2(X) * P_intersect(X)
 
         let (q1_poly, remainder1): (DensePolynomial<Fr>, DensePolynomial<Fr>) =
-            match DenseOrSparsePolynomial::from(&p1_poly)
-                .divide_with_q_and_r(&DenseOrSparsePolynomial::from(&p_intersect_poly))
+            match DenseOrSparsePolynomial::from(p1_poly)
+                .divide_with_q_and_r(&DenseOrSparsePolynomial::from(p_intersect_poly))
             {
                 Some((q, r)) => (q, r),
                 None => return Err(anyhow!("Failed to divide P1 by P_intersect")),
@@ -492,8 +885,8 @@ impl DynamicAccumulator {
         }
 
         let (q2_poly, remainder2): (DensePolynomial<Fr>, DensePolynomial<Fr>) =
-            match DenseOrSparsePolynomial::from(&p2_poly)
-                .divide_with_q_and_r(&DenseOrSparsePolynomial::from(&p_intersect_poly))
+            match DenseOrSparsePolynomial::from(p2_poly)
+                .divide_with_q_and_r(&DenseOrSparsePolynomial::from(p_intersect_poly))
             {
                 Some((q, r)) => (q, r),
                 None => return Err(anyhow!("Failed to divide P2 by P_intersect")),
@@ -504,21 +897,21 @@ impl DynamicAccumulator {
             ));
         }
 
-        // 5. Evaluate the quotient polynomials at the secret s
-        let q1_s = q1_poly.evaluate(&*super::PRI_S);
-        let q2_s = q2_poly.evaluate(&*super::PRI_S);
-
-        // 6. Compute the witnesses for quotients: g2^Q1(s) and g2^Q2(s)
-        let witness_a = G2Projective::prime_subgroup_generator()
-            .mul(q1_s.into_repr())
-            .into_affine();
-        let witness_b = G2Projective::prime_subgroup_generator()
-            .mul(q2_s.into_repr())
-            .into_affine();
-
-        // 7. Prove that Q1(X) and Q2(X) are coprime using XGCD
+        // 5. Commit to the quotients with the public parameters: g2^Q1(s) and g2^Q2(s),
+        // 6. and prove that Q1(X) and Q2(X) are coprime using XGCD:
         // We find A(X), B(X) such that A(X)Q1(X) + B(X)Q2(X) = 1
-        if let Some((gcd, a_poly, b_poly)) = xgcd(q1_poly, q2_poly) {
+        // The two MSMs do not depend on the XGCD, so they run alongside it
+        let ((witness_a, witness_b), bezout) = rayon::join(
+            || {
+                rayon::join(
+                    || public_params().commit_g2(&q1_poly),
+                    || public_params().commit_g2(&q2_poly),
+                )
+            },
+            || xgcd(&q1_poly, &q2_poly),
+        );
+        let (witness_a, witness_b) = (witness_a?, witness_b?);
+        if let Some((gcd, a_poly, b_poly)) = bezout {
             if !gcd.is_zero() && gcd.degree() == 0 {
                 let gcd_val = gcd.coeffs.first().cloned().unwrap_or_else(Fr::one);
                 let gcd_inv = gcd_val.inverse().ok_or_else(|| {
@@ -532,15 +925,12 @@ impl DynamicAccumulator {
                     b_poly.coeffs.iter().map(|c| *c * gcd_inv).collect(),
                 );
 
-                let a_s = a_poly_norm.evaluate(&*super::PRI_S);
-                let b_s = b_poly_norm.evaluate(&*super::PRI_S);
-
-                let witness_coprime_a = G1Projective::prime_subgroup_generator()
-                    .mul(a_s.into_repr())
-                    .into_affine();
-                let witness_coprime_b = G1Projective::prime_subgroup_generator()
-                    .mul(b_s.into_repr())
-                    .into_affine();
+                let (witness_coprime_a, witness_coprime_b) = rayon::join(
+                    || public_params().commit_g1(&a_poly_norm),
+                    || public_params().commit_g1(&b_poly_norm),
+                );
+                let (witness_coprime_a, witness_coprime_b) =
+                    (witness_coprime_a?, witness_coprime_b?);
 
                 let proof = IntersectionProof {
                     witness_a,
@@ -558,6 +948,123 @@ impl DynamicAccumulator {
         ))
     }
 
+    /// Computes the intersection of any number of accumulators with a single proof,
+    /// instead of chaining pairwise intersection proofs.
+    /// Returns the intersection accumulator and a proof that it represents the intersection.
+    pub fn prove_intersection_multi(
+        accumulators: &[&DynamicAccumulator],
+    ) -> Result<(DynamicAccumulator, MultiIntersectionProof)> {
+        let (first, rest) = accumulators
+            .split_first()
+            .ok_or_else(|| anyhow!("Cannot intersect an empty list of accumulators"))?;
+
+        // 1. Compute the actual intersection of all sets
+        let intersection_elements: HashSet<Fr> = first
+            .elements
+            .iter()
+            .filter(|e| rest.iter().all(|acc| acc.elements.contains(e)))
+            .cloned()
+            .collect();
+        let intersection_acc = DynamicAccumulator::from_elements(intersection_elements)?;
+
+        // 2. Quotients Q_i(X) = P_i(X) / P_intersect(X), dividing out one root at a time
+        let quotients: Vec<DensePolynomial<Fr>> = accumulators
+            .iter()
+            .map(|acc| {
+                intersection_acc
+                    .elements
+                    .iter()
+                    .fold(acc.poly.clone(), |poly, e| div_by_root(&poly, *e))
+            })
+            .collect();
+
+        // 3. Bézout coefficients with sum(B_i(X) * Q_i(X)) = gcd(Q_1, ..., Q_n),
+        //    folding in one quotient at a time with XGCD
+        let mut gcd = quotients[0].clone();
+        let mut coefficients = vec![DensePolynomial::from_coefficients_vec(vec![Fr::one()])];
+        for quotient in &quotients[1..] {
+            let (next_gcd, x, y) = xgcd(&gcd, quotient)
+                .ok_or_else(|| anyhow!("Failed to compute gcd of the quotient polynomials"))?;
+            for coefficient in coefficients.iter_mut() {
+                *coefficient = &*coefficient * &x;
+            }
+            coefficients.push(y);
+            gcd = next_gcd;
+        }
+        if gcd.is_zero() || gcd.degree() != 0 {
+            return Err(anyhow!(
+                "Failed to create intersection proof, quotients might not be coprime"
+            ));
+        }
+        let gcd_inv = gcd.coeffs[0]
+            .inverse()
+            .ok_or_else(|| anyhow!("Failed to compute gcd inverse for coprimality proof"))?;
+
+        // 4. Commit to the quotients and the normalized coefficients
+        let quotient_witnesses = quotients
+            .par_iter()
+            .map(|q| public_params().commit_g2(q))
+            .collect::<Result<Vec<_>, _>>()?;
+        let coprime_witnesses = coefficients
+            .par_iter()
+            .map(|b| {
+                let normalized = DensePolynomial::from_coefficients_vec(
+                    b.coeffs.iter().map(|c| *c * gcd_inv).collect(),
+                );
+                public_params().commit_g1(&normalized)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let proof = MultiIntersectionProof {
+            quotient_witnesses,
+            coprime_witnesses,
+        };
+        Ok((intersection_acc, proof))
+    }
+
+    /// Verifies that the given accumulator represents the intersection of all given accumulators.
+    ///
+    /// Using pairing:
+    /// - e(acc_i, g2) == e(intersection, quotient_witness_i) for every i
+    /// - product(e(coprime_witness_i, quotient_witness_i)) == e(g1, g2)
+    pub fn verify_intersection_multi(
+        acc_values: &[G1Affine],
+        intersection_value: G1Affine,
+        proof: &MultiIntersectionProof,
+    ) -> bool {
+        let count = acc_values.len();
+        if count == 0
+            || proof.quotient_witnesses.len() != count
+            || proof.coprime_witnesses.len() != count
+        {
+            return false;
+        }
+        let g2 = G2Affine::prime_subgroup_generator();
+
+        // Verification equation 1: every P_i(s) = Q_i(s) * P_intersect(s)
+        let divides = acc_values
+            .iter()
+            .zip(&proof.quotient_witnesses)
+            .all(|(acc, witness)| {
+                Curve::product_of_pairings(&[
+                    ((*acc).into(), g2.into()),
+                    (intersection_value.neg().into(), (*witness).into()),
+                ])
+                .is_one()
+            });
+
+        // Verification equation 2: sum(B_i(s) * Q_i(s)) = 1, proving the quotients are coprime
+        let mut pairs: Vec<_> = proof
+            .coprime_witnesses
+            .iter()
+            .zip(&proof.quotient_witnesses)
+            .map(|(coprime, witness)| ((*coprime).into(), (*witness).into()))
+            .collect();
+        pairs.push((G1Affine::prime_subgroup_generator().neg().into(), g2.into()));
+
+        divides && Curve::product_of_pairings(&pairs).is_one()
+    }
+
     /// Computes the intersection and also returns the intersection elements (as Fr values).
     /// Returns (intersection_accumulator, intersection_proof, intersection_elements_fr).
     pub fn prove_intersection_with_elements(
@@ -611,10 +1118,10 @@ impl DynamicAccumulator {
 
     /// One-shot API: compute intersection, return query result on it, the proof, the accumulator, and elements.
     /// Returns (query_result_on_intersection, intersection_proof, intersection_accumulator, intersection_elements_fr).
-    pub fn query_in_intersection_with_elements(
+    pub fn query_in_intersection_with_elements<T: AccElement + ?Sized>(
         &self,
         other: &DynamicAccumulator,
-        element: &i64,
+        element: &T,
     ) -> Result<(QueryResult, IntersectionProof, DynamicAccumulator, Vec<Fr>)> {
         let (intersection_acc, proof) = self.prove_intersection(other)?;
         let q = intersection_acc.query(element);
@@ -625,16 +1132,16 @@ impl DynamicAccumulator {
     /// Prover API: return intersection original values (i64), intersection accumulator and proof.
     /// Note: The prover must supply the clear-text values that correspond to `self` and `other`.
     /// The verifier can recompute the accumulator from returned values and verify against the proof.
-    pub fn prove_intersection_with_values(
+    pub fn prove_intersection_with_values<T: AccElement + SetElement + Ord>(
         &self,
         other: &DynamicAccumulator,
-        self_values: &[i64],
-        other_values: &[i64],
-    ) -> Result<(Vec<i64>, DynamicAccumulator, IntersectionProof)> {
+        self_values: &[T],
+        other_values: &[T],
+    ) -> Result<(Vec<T>, DynamicAccumulator, IntersectionProof)> {
         // Compute intersection values on clear-text
-        let set_a: std::collections::HashSet<i64> = self_values.iter().cloned().collect();
-        let set_b: std::collections::HashSet<i64> = other_values.iter().cloned().collect();
-        let mut intersection_values: Vec<i64> = set_a.intersection(&set_b).cloned().collect();
+        let set_a: std::collections::HashSet<T> = self_values.iter().cloned().collect();
+        let set_b: std::collections::HashSet<T> = other_values.iter().cloned().collect();
+        let mut intersection_values: Vec<T> = set_a.intersection(&set_b).cloned().collect();
         intersection_values.sort_unstable();
 
         // Compute cryptographic intersection accumulator and proof
@@ -661,16 +1168,7 @@ impl DynamicAccumulator {
             self.elements.union(&other.elements).cloned().collect();
 
         // 3. Create the union accumulator from the union elements.
-        let mut union_acc = DynamicAccumulator::new();
-        union_acc.elements = union_elements;
-
-        // Calculate the cryptographic value of the union accumulator.
-        let mut acc_value = G1Projective::from(G1Affine::prime_subgroup_generator());
-        for elem in &union_acc.elements {
-            let s_minus_elem = *super::PRI_S - elem;
-            acc_value = acc_value.mul(s_minus_elem.into_repr());
-        }
-        union_acc.acc_value = acc_value.into_affine();
+        let union_acc = DynamicAccumulator::from_elements(union_elements)?;
 
         // 4. Construct the union proof using the intersection proof data.
         let union_proof = UnionProof {
@@ -681,20 +1179,77 @@ impl DynamicAccumulator {
         Ok((union_acc, union_proof))
     }
 
+    /// Computes the difference of this accumulator minus another accumulator and generates a proof.
+    /// The complement of a set within a universe is `universe.prove_difference(set)`.
+    /// Returns the difference accumulator and a proof that it represents the difference.
+    pub fn prove_difference(
+        &self,
+        other: &DynamicAccumulator,
+    ) -> Result<(DynamicAccumulator, DifferenceProof)> {
+        let (intersection_acc, intersection_proof) = self.prove_intersection(other)?;
+        let difference_acc = DynamicAccumulator::from_elements(
+            self.elements.difference(&other.elements).cloned().collect(),
+        )?;
+        let proof = DifferenceProof {
+            intersection_acc_value: intersection_acc.acc_value,
+            intersection_proof,
+        };
+        Ok((difference_acc, proof))
+    }
+
+    /// Verifies that the given elements are exactly the difference of two accumulated sets.
+    ///
+    /// The verification checks that:
+    /// - the embedded intersection proof holds for acc1, acc2 and the intersection accumulator
+    /// - witness_a, the commitment of P1(X) / P_intersect(X), equals g2^P_diff(s)
+    ///   computed from the claimed difference elements
+    pub fn verify_difference(
+        acc1_value: G1Affine,
+        acc2_value: G1Affine,
+        difference_elements: &[Fr],
+        proof: &DifferenceProof,
+    ) -> bool {
+        let Ok(g2_difference) = public_params().commit_g2(&poly_from_elements(difference_elements))
+        else {
+            return false;
+        };
+        g2_difference == proof.intersection_proof.witness_a
+            && Self::verify_intersection(
+                acc1_value,
+                acc2_value,
+                proof.intersection_acc_value,
+                &proof.intersection_proof,
+            )
+    }
+
+    /// Verifier helper: verify a difference proof using the clear-text difference values.
+    pub fn verify_difference_with_values<T: AccElement>(
+        acc1_value: G1Affine,
+        acc2_value: G1Affine,
+        difference_values: &[T],
+        proof: &DifferenceProof,
+    ) -> bool {
+        let elements: Vec<Fr> = difference_values
+            .iter()
+            .map(|v| digest_to_prime_field(&v.to_digest()))
+            .collect();
+        Self::verify_difference(acc1_value, acc2_value, &elements, proof)
+    }
+
     /// Verifier helper: verify intersection using provided clear-text intersection values.
     /// It recomputes the intersection accumulator from values and checks the proof.
-    pub fn verify_intersection_with_values(
+    pub fn verify_intersection_with_values<T: AccElement + SetElement + Ord>(
         acc1_value: G1Affine,
         acc2_value: G1Affine,
-        intersection_values: &[i64],
+        intersection_values: &[T],
         proof: &IntersectionProof,
     ) -> bool {
         // Build a set (unique) from the provided values
-        let mut unique: std::collections::HashSet<i64> = std::collections::HashSet::new();
+        let mut unique: std::collections::HashSet<T> = std::collections::HashSet::new();
         for v in intersection_values {
-            unique.insert(*v);
+            unique.insert(v.clone());
         }
-        let mut vec_unique: Vec<i64> = unique.into_iter().collect();
+        let mut vec_unique: Vec<T> = unique.into_iter().collect();
         vec_unique.sort_unstable();
 
         // Compute accumulator from values (public, no secret needed)
@@ -712,23 +1267,23 @@ impl DynamicAccumulator {
 
     /// Prover API: computes union and intersection, returns clear-text values, the union accumulator, and a union proof.
     /// The proof internally contains the intersection proof.
-    pub fn prove_union_with_values(
+    pub fn prove_union_with_values<T: AccElement + SetElement + Ord>(
         &self,
         other: &DynamicAccumulator,
-        self_values: &[i64],
-        other_values: &[i64],
-    ) -> Result<(Vec<i64>, Vec<i64>, DynamicAccumulator, UnionProof)> {
+        self_values: &[T],
+        other_values: &[T],
+    ) -> Result<(Vec<T>, Vec<T>, DynamicAccumulator, UnionProof)> {
         // 1. Compute cryptographic intersection accumulator and proof
         let (intersection_acc, intersection_proof) = self.prove_intersection(other)?;
 
         // 2. Compute clear-text intersection and union values
-        let set_a: std::collections::HashSet<i64> = self_values.iter().cloned().collect();
-        let set_b: std::collections::HashSet<i64> = other_values.iter().cloned().collect();
+        let set_a: std::collections::HashSet<T> = self_values.iter().cloned().collect();
+        let set_b: std::collections::HashSet<T> = other_values.iter().cloned().collect();
 
-        let mut intersection_values: Vec<i64> = set_a.intersection(&set_b).cloned().collect();
+        let mut intersection_values: Vec<T> = set_a.intersection(&set_b).cloned().collect();
         intersection_values.sort_unstable();
 
-        let mut union_values: Vec<i64> = set_a.union(&set_b).cloned().collect();
+        let mut union_values: Vec<T> = set_a.union(&set_b).cloned().collect();
         union_values.sort_unstable();
 
         // 3. Create union accumulator from the clear-text union values
@@ -765,24 +1320,29 @@ impl DynamicAccumulator {
             return false;
         }
 
-        // 2. Verify the accumulator relationship: P_A(s) + P_B(s) = P_union(s) + P_intersection(s)
-        // This is checked in the elliptic curve group by point addition:
-        // acc_A + acc_B = acc_union + acc_intersection
-        // In projective coordinates for efficient computation:
-        let lhs = acc1_value.into_projective() + acc2_value.into_projective();
-        let rhs =
-            union_acc_value.into_projective() + proof.intersection_acc_value.into_projective();
-
-        lhs == rhs
+        // 2. Verify the accumulator relationship: P_union(s) = P_A(s) * Q2(s),
+        // where witness_b = g2^Q2(s) and Q2 = P_B / P_intersection:
+        // e(acc_union, g2) == e(acc_A, witness_b)
+        Curve::product_of_pairings(&[
+            (
+                union_acc_value.into(),
+                G2Affine::prime_subgroup_generator().into(),
+            ),
+            (
+                acc1_value.neg().into(),
+                proof.intersection_proof.witness_b.into(),
+            ),
+        ])
+        .is_one()
     }
 
     /// Verifier API: verifies the union proof using provided clear-text union and intersection values.
     /// This function recomputes the accumulators from values and verifies both the intersection and the union relationships.
-    pub fn verify_union_with_values(
+    pub fn verify_union_with_values<T: AccElement>(
         acc1_value: G1Affine,
         acc2_value: G1Affine,
-        union_values: &[i64],
-        intersection_values: &[i64],
+        union_values: &[T],
+        intersection_values: &[T],
         proof: &UnionProof,
     ) -> bool {
         // 1. Recompute intersection accumulator from values and check if it matches the one in the proof.
@@ -797,31 +1357,48 @@ impl DynamicAccumulator {
             return false; // The provided intersection values do not match the proven intersection accumulator.
         }
 
-        // 2. Verify the underlying intersection proof.
-        let is_intersection_valid = Self::verify_intersection(
-            acc1_value,
-            acc2_value,
-            proof.intersection_acc_value,
-            &proof.intersection_proof,
-        );
-
-        if !is_intersection_valid {
-            return false;
-        }
-
-        // 3. Recompute union accumulator from values.
+        // 2. Recompute union accumulator from values.
         let mut recomputed_union_acc = DynamicAccumulator::new();
         if recomputed_union_acc.add_batch(union_values).is_err() {
             return false;
         }
 
-        // 4. Verify that the provided union values are indeed the union of the two original sets
-        // This is implicitly verified by the fact that:
-        // - The intersection proof is valid (step 2)
-        // - The union accumulator can be correctly computed from the provided union values (step 3)
-        // - The verifier can independently check that union_values ∪ intersection_values makes sense
+        // 3. Verify the intersection proof and that the union values form the union of the two sets.
+        Self::verify_union(
+            acc1_value,
+            acc2_value,
+            recomputed_union_acc.acc_value,
+            proof,
+        )
+    }
 
-        true // If we reach here, all checks have passed
+    /// Verifies a difference proof against the difference accumulator value instead of
+    /// clear-text elements, for results that are themselves operands of another proof.
+    /// Checks e(difference, g2) == e(g1, witness_a) in addition to the intersection proof.
+    pub fn verify_difference_acc(
+        acc1_value: G1Affine,
+        acc2_value: G1Affine,
+        difference_value: G1Affine,
+        proof: &DifferenceProof,
+    ) -> bool {
+        let matches_difference = Curve::product_of_pairings(&[
+            (
+                difference_value.into(),
+                G2Affine::prime_subgroup_generator().into(),
+            ),
+            (
+                G1Affine::prime_subgroup_generator().neg().into(),
+                proof.intersection_proof.witness_a.into(),
+            ),
+        ])
+        .is_one();
+        matches_difference
+            && Self::verify_intersection(
+                acc1_value,
+                acc2_value,
+                proof.intersection_acc_value,
+                &proof.intersection_proof,
+            )
     }
 }
 
@@ -929,6 +1506,109 @@ mod tests {
         assert!(dyn_acc.prove_membership(&999i64).is_err());
     }
 
+    #[test]
+    fn test_string_elements() {
+        init_logger();
+        // "Aa" and "BB" collide under a 31-multiplier string hash; as strings they are distinct
+        let mut dyn_acc = DynamicAccumulator::new();
+        dyn_acc.add("Aa").unwrap();
+        dyn_acc.add("BB").unwrap();
+        assert!(dyn_acc.add(&"Aa".to_string()).is_err());
+
+        let proof = dyn_acc.prove_membership("Aa").unwrap();
+        assert_eq!(proof.element, element_to_field("Aa"));
+        assert!(proof.verify(dyn_acc.acc_value));
+        assert!(dyn_acc.prove_membership(&100).is_err());
+        assert_ne!(element_to_field("100"), element_to_field(&100));
+
+        let batch = dyn_acc.prove_membership_batch(&["BB", "Aa"]).unwrap();
+        assert!(batch.verify(dyn_acc.acc_value));
+        dyn_acc.delete("Aa").unwrap();
+        assert!(!dyn_acc.contains("Aa") && dyn_acc.contains("BB"));
+    }
+
+    #[test]
+    fn test_product_tree_matches_sequential_product() {
+        let roots: Vec<Fr> = (0..(PRODUCT_TREE_LEAF as i64 * 5 + 3))
+            .map(|e| element_to_field(&e))
+            .collect();
+        let sequential = roots.iter().fold(
+            DensePolynomial::from_coefficients_vec(vec![Fr::one()]),
+            |poly, root| mul_by_root(&poly, *root),
+        );
+        assert_eq!(poly_from_roots(&roots), sequential);
+        assert_eq!(poly_from_roots(&[]).coeffs, vec![Fr::one()]);
+
+        // Batch adds agree with one-by-one adds and leave the accumulator unchanged on error
+        let elements: Vec<i64> = (0..PRODUCT_TREE_LEAF as i64 - 4).collect();
+        let mut one_by_one = DynamicAccumulator::new();
+        one_by_one.add(&elements[0]).unwrap();
+        one_by_one.cache_witness(&elements[0]).unwrap();
+        let mut batched = one_by_one.clone();
+        for element in &elements[1..] {
+            one_by_one.add(element).unwrap();
+        }
+        batched.add_batch(&elements[1..]).unwrap();
+        assert_eq!(batched, one_by_one);
+        assert_eq!(
+            batched.prove_membership(&elements[0]).unwrap(),
+            one_by_one.prove_membership(&elements[0]).unwrap()
+        );
+        assert!(batched.add_batch(&[1000, 1001, 1000]).is_err());
+        assert!(batched.add_batch(&[1000, elements[3]]).is_err());
+        assert_eq!(batched, one_by_one);
+    }
+
+    #[test]
+    fn test_cached_witnesses_follow_updates() {
+        init_logger();
+        let mut dyn_acc = DynamicAccumulator::new();
+        dyn_acc.add(&100).unwrap();
+        dyn_acc.add(&200).unwrap();
+        dyn_acc.cache_witness(&100).unwrap();
+        dyn_acc.cache_witness(&200).unwrap();
+        assert!(dyn_acc.cache_witness(&999).is_err());
+
+        dyn_acc.add(&300).unwrap();
+        dyn_acc.add(&400).unwrap();
+        dyn_acc.delete(&200).unwrap();
+        dyn_acc.delete(&300).unwrap();
+        assert!(!dyn_acc.is_witness_cached(&200));
+        assert_eq!(dyn_acc.cached_witnesses(), 1);
+
+        // The maintained witness equals one computed from scratch
+        let cached = dyn_acc.prove_membership(&100).unwrap();
+        assert!(dyn_acc.verify_membership(&cached));
+        let expected = Acc1::cal_acc_g1_sk(&MultiSet::from_vec(vec![400i64]));
+        assert_eq!(cached.witness, expected);
+
+        // Cached witnesses do not affect equality or serialization
+        let restored = DynamicAccumulator::from_bytes(&dyn_acc.to_bytes().unwrap()).unwrap();
+        assert_eq!(restored, dyn_acc);
+        assert!(dyn_acc.evict_witness(&100));
+        assert_eq!(dyn_acc.prove_membership(&100).unwrap(), cached);
+    }
+
+    #[test]
+    fn test_client_side_witness_update() {
+        init_logger();
+        let mut dyn_acc = DynamicAccumulator::new();
+        dyn_acc.add(&1).unwrap();
+        dyn_acc.add(&2).unwrap();
+        let mut proof = dyn_acc.prove_membership(&1).unwrap();
+
+        let add = dyn_acc.add(&3).unwrap();
+        proof.update_on_add(&add);
+        assert!(dyn_acc.verify_membership(&proof));
+
+        let delete = dyn_acc.delete(&2).unwrap();
+        proof.update_on_delete(&delete).unwrap();
+        assert!(dyn_acc.verify_membership(&proof));
+
+        let delete = dyn_acc.delete(&1).unwrap();
+        assert!(proof.update_on_delete(&delete).is_err());
+    }
+
     #[test]
     fn test_non_membership_proof() {
         init_logger();
@@ -1098,4 +1778,371 @@ mod tests {
             &proof
         ));
     }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        init_logger();
+        let mut acc = DynamicAccumulator::new();
+        acc.add(&1i64).unwrap();
+        acc.add(&2i64).unwrap();
+        acc.add(&3i64).unwrap();
+        acc.delete(&2i64).unwrap();
+
+        let bytes = acc.to_bytes().unwrap();
+        let restored = DynamicAccumulator::from_bytes(&bytes).unwrap();
+        assert_eq!(restored, acc);
+        assert_eq!(restored.to_bytes().unwrap(), bytes);
+
+        // The restored accumulator keeps producing valid proofs
+        let mut restored = restored;
+        assert!(restored.add(&4i64).unwrap().verify());
+        assert!(restored.delete(&1i64).unwrap().verify());
+
+        let empty = DynamicAccumulator::new();
+        assert_eq!(
+            DynamicAccumulator::from_bytes(&empty.to_bytes().unwrap()).unwrap(),
+            empty
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_mismatched_value() {
+        init_logger();
+        let mut acc = DynamicAccumulator::new();
+        acc.add(&1i64).unwrap();
+        let mut other = DynamicAccumulator::new();
+        other.add(&2i64).unwrap();
+
+        // Accumulator value of one set paired with the elements of another
+        let mut forged = Vec::new();
+        acc.acc_value.serialize(&mut forged).unwrap();
+        other.elements_fr().serialize(&mut forged).unwrap();
+        assert!(DynamicAccumulator::from_bytes(&forged).is_err());
+
+        let bytes = acc.to_bytes().unwrap();
+        assert!(DynamicAccumulator::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(DynamicAccumulator::from_bytes(&trailing).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load_from_db() {
+        init_logger();
+        let mut db = crate::mpt::db::MemoryDatabase::new();
+        assert!(DynamicAccumulator::load_from_db(&mut db, b"acc:rust")
+            .unwrap()
+            .is_none());
+
+        let mut acc = DynamicAccumulator::new();
+        acc.add(&7i64).unwrap();
+        acc.save_to_db(&mut db, b"acc:rust").unwrap();
+        let loaded = DynamicAccumulator::load_from_db(&mut db, b"acc:rust")
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded, acc);
+    }
+
+    #[test]
+    fn test_add_beyond_public_params_fails() {
+        init_logger();
+        let max = public_params().max_degree() as i64;
+        let mut acc = DynamicAccumulator::new();
+        for i in 0..max {
+            acc.add(&i).unwrap();
+        }
+        assert!(acc.prove_membership(&0).unwrap().verify(acc.acc_value));
+
+        // The polynomial would exceed the public parameters; the accumulator is unchanged
+        let before = acc.clone();
+        assert!(acc.add(&max).is_err());
+        assert_eq!(acc, before);
+        assert!(acc.delete(&0).unwrap().verify());
+    }
+
+    #[test]
+    fn test_batch_membership_proof() {
+        init_logger();
+        let mut acc = DynamicAccumulator::new();
+        acc.add_batch(&[10, 20, 30, 40]).unwrap();
+
+        let proof = acc.prove_membership_batch(&[30, 10, 30]).unwrap();
+        assert_eq!(proof.elements.len(), 2);
+        assert!(acc.verify_membership_batch(&proof));
+
+        // The witness equals the accumulator of the complement set
+        let mut complement = DynamicAccumulator::new();
+        complement.add_batch(&[20, 40]).unwrap();
+        assert_eq!(proof.witness, complement.acc_value);
+
+        // Every element together: the witness is g1^1
+        let all = acc.prove_membership_batch(&[10, 20, 30, 40]).unwrap();
+        assert!(acc.verify_membership_batch(&all));
+        assert_eq!(all.witness, G1Affine::prime_subgroup_generator());
+
+        // An empty batch proves nothing beyond the accumulator itself
+        let empty = acc.prove_membership_batch::<i64>(&[]).unwrap();
+        assert_eq!(empty.witness, acc.acc_value);
+        assert!(acc.verify_membership_batch(&empty));
+
+        assert!(acc.prove_membership_batch(&[10, 50]).is_err());
+    }
+
+    #[test]
+    fn test_batch_membership_proof_rejects_tampering() {
+        init_logger();
+        let mut acc = DynamicAccumulator::new();
+        acc.add_batch(&[1, 2, 3]).unwrap();
+        let proof = acc.prove_membership_batch(&[1, 2]).unwrap();
+
+        // Claiming fewer or different elements than were proven fails
+        let mut dropped = proof.clone();
+        dropped.elements.pop();
+        assert!(!acc.verify_membership_batch(&dropped));
+
+        let mut swapped = proof.clone();
+        swapped.elements[0] = digest_to_prime_field(&4i64.to_digest());
+        assert!(!acc.verify_membership_batch(&swapped));
+
+        // The proof does not verify against a different accumulator
+        let mut other = acc.clone();
+        other.add(&4).unwrap();
+        assert!(!other.verify_membership_batch(&proof));
+        assert!(proof.verify(acc.acc_value));
+    }
+
+    #[test]
+    fn test_difference_proof() {
+        init_logger();
+        let mut acc1 = DynamicAccumulator::new();
+        acc1.add_batch(&[1, 2, 3, 4]).unwrap();
+        let mut acc2 = DynamicAccumulator::new();
+        acc2.add_batch(&[3, 4, 5]).unwrap();
+
+        let (difference_acc, proof) = acc1.prove_difference(&acc2).unwrap();
+        assert_eq!(difference_acc.len(), 2);
+        assert!(difference_acc.contains(&1) && difference_acc.contains(&2));
+        assert!(DynamicAccumulator::verify_difference_with_values(
+            acc1.acc_value,
+            acc2.acc_value,
+            &[2, 1],
+            &proof
+        ));
+
+        // Claiming too few, too many or other elements fails
+        for claim in [&[1][..], &[1, 2, 3], &[1, 5]] {
+            assert!(!DynamicAccumulator::verify_difference_with_values(
+                acc1.acc_value,
+                acc2.acc_value,
+                claim,
+                &proof
+            ));
+        }
+        // The proof is bound to the order of the operands
+        assert!(!DynamicAccumulator::verify_difference_with_values(
+            acc2.acc_value,
+            acc1.acc_value,
+            &[1, 2],
+            &proof
+        ));
+
+        let restored = DifferenceProof::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+        assert!(DynamicAccumulator::verify_difference(
+            acc1.acc_value,
+            acc2.acc_value,
+            &difference_acc.elements_fr(),
+            &restored
+        ));
+    }
+
+    #[test]
+    fn test_difference_proof_edge_cases() {
+        init_logger();
+        let mut acc1 = DynamicAccumulator::new();
+        acc1.add_batch(&[1, 2]).unwrap();
+        let mut superset = DynamicAccumulator::new();
+        superset.add_batch(&[1, 2, 3]).unwrap();
+        let empty = DynamicAccumulator::new();
+
+        // A subset minus its superset is empty
+        let (difference_acc, proof) = acc1.prove_difference(&superset).unwrap();
+        assert!(difference_acc.is_empty());
+        assert!(DynamicAccumulator::verify_difference_with_values::<i64>(
+            acc1.acc_value,
+            superset.acc_value,
+            &[],
+            &proof
+        ));
+
+        // Subtracting the empty set leaves everything
+        let (_, proof) = acc1.prove_difference(&empty).unwrap();
+        assert!(DynamicAccumulator::verify_difference_with_values(
+            acc1.acc_value,
+            empty.acc_value,
+            &[1, 2],
+            &proof
+        ));
+
+        // Complement within a universe
+        let (complement, proof) = superset.prove_difference(&acc1).unwrap();
+        assert!(complement.contains(&3) && complement.len() == 1);
+        assert!(DynamicAccumulator::verify_difference_with_values(
+            superset.acc_value,
+            acc1.acc_value,
+            &[3],
+            &proof
+        ));
+    }
+
+    #[test]
+    fn test_multi_intersection_proof() {
+        init_logger();
+        let mut acc1 = DynamicAccumulator::new();
+        acc1.add_batch(&[1, 2, 3, 4]).unwrap();
+        let mut acc2 = DynamicAccumulator::new();
+        acc2.add_batch(&[2, 3, 4, 5]).unwrap();
+        let mut acc3 = DynamicAccumulator::new();
+        acc3.add_batch(&[3, 4, 6]).unwrap();
+        let values = [acc1.acc_value, acc2.acc_value, acc3.acc_value];
+
+        let (intersection_acc, proof) =
+            DynamicAccumulator::prove_intersection_multi(&[&acc1, &acc2, &acc3]).unwrap();
+        assert_eq!(intersection_acc.len(), 2);
+        assert!(intersection_acc.contains(&3) && intersection_acc.contains(&4));
+        assert!(DynamicAccumulator::verify_intersection_multi(
+            &values,
+            intersection_acc.acc_value,
+            &proof
+        ));
+
+        // Same result as chaining pairwise proofs
+        let (pairwise, _) = acc1.prove_intersection(&acc2).unwrap();
+        let (chained, _) = pairwise.prove_intersection(&acc3).unwrap();
+        assert_eq!(chained.acc_value, intersection_acc.acc_value);
+
+        // A smaller claimed intersection, missing accumulators or reordered inputs fail
+        let mut partial = DynamicAccumulator::new();
+        partial.add(&3).unwrap();
+        assert!(!DynamicAccumulator::verify_intersection_multi(
+            &values,
+            partial.acc_value,
+            &proof
+        ));
+        assert!(!DynamicAccumulator::verify_intersection_multi(
+            &values[..2],
+            intersection_acc.acc_value,
+            &proof
+        ));
+        assert!(!DynamicAccumulator::verify_intersection_multi(
+            &[acc2.acc_value, acc1.acc_value, acc3.acc_value],
+            intersection_acc.acc_value,
+            &proof
+        ));
+    }
+
+    #[test]
+    fn test_multi_intersection_proof_edge_cases() {
+        init_logger();
+        let mut acc1 = DynamicAccumulator::new();
+        acc1.add_batch(&[1, 2]).unwrap();
+        let mut acc2 = DynamicAccumulator::new();
+        acc2.add_batch(&[3]).unwrap();
+        let mut acc3 = DynamicAccumulator::new();
+        acc3.add_batch(&[1, 3]).unwrap();
+
+        assert!(DynamicAccumulator::prove_intersection_multi(&[]).is_err());
+
+        // A single accumulator is its own intersection
+        let (intersection_acc, proof) =
+            DynamicAccumulator::prove_intersection_multi(&[&acc1]).unwrap();
+        assert_eq!(intersection_acc.acc_value, acc1.acc_value);
+        assert!(DynamicAccumulator::verify_intersection_multi(
+            &[acc1.acc_value],
+            intersection_acc.acc_value,
+            &proof
+        ));
+
+        // Pairwise overlapping sets with an empty common intersection
+        let (intersection_acc, proof) =
+            DynamicAccumulator::prove_intersection_multi(&[&acc1, &acc2, &acc3]).unwrap();
+        assert!(intersection_acc.is_empty());
+        assert!(DynamicAccumulator::verify_intersection_multi(
+            &[acc1.acc_value, acc2.acc_value, acc3.acc_value],
+            intersection_acc.acc_value,
+            &proof
+        ));
+    }
+
+    #[test]
+    fn test_union_proof() {
+        init_logger();
+        let mut acc1 = DynamicAccumulator::new();
+        acc1.add_batch(&[1, 2, 3]).unwrap();
+        let mut acc2 = DynamicAccumulator::new();
+        acc2.add_batch(&[3, 4]).unwrap();
+
+        let (union_acc, proof) = acc1.prove_union(&acc2).unwrap();
+        assert_eq!(union_acc.len(), 4);
+        assert!(DynamicAccumulator::verify_union(
+            acc1.acc_value,
+            acc2.acc_value,
+            union_acc.acc_value,
+            &proof
+        ));
+        assert!(DynamicAccumulator::verify_union_with_values(
+            acc1.acc_value,
+            acc2.acc_value,
+            &[4, 3, 2, 1],
+            &[3],
+            &proof
+        ));
+
+        // A union missing an element fails
+        let mut partial = DynamicAccumulator::new();
+        partial.add_batch(&[1, 2, 3]).unwrap();
+        assert!(!DynamicAccumulator::verify_union(
+            acc1.acc_value,
+            acc2.acc_value,
+            partial.acc_value,
+            &proof
+        ));
+        assert!(!DynamicAccumulator::verify_union_with_values(
+            acc1.acc_value,
+            acc2.acc_value,
+            &[1, 2, 4],
+            &[3],
+            &proof
+        ));
+
+        let restored = UnionProof::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+        assert!(DynamicAccumulator::verify_union(
+            acc1.acc_value,
+            acc2.acc_value,
+            union_acc.acc_value,
+            &restored
+        ));
+    }
+
+    #[test]
+    fn test_difference_proof_against_accumulator() {
+        init_logger();
+        let mut acc1 = DynamicAccumulator::new();
+        acc1.add_batch(&[1, 2, 3]).unwrap();
+        let mut acc2 = DynamicAccumulator::new();
+        acc2.add_batch(&[3]).unwrap();
+
+        let (difference_acc, proof) = acc1.prove_difference(&acc2).unwrap();
+        assert!(DynamicAccumulator::verify_difference_acc(
+            acc1.acc_value,
+            acc2.acc_value,
+            difference_acc.acc_value,
+            &proof
+        ));
+        assert!(!DynamicAccumulator::verify_difference_acc(
+            acc1.acc_value,
+            acc2.acc_value,
+            acc1.acc_value,
+            &proof
+        ));
+    }
 }
@@ -1,5 +1,7 @@
 pub mod digest_set;
 pub mod dynamic_accumulator;
+pub mod params;
+pub mod public_params;
 pub mod serde_impl;
 pub mod utils;
 
@@ -15,48 +17,20 @@ use ark_ec::{msm::VariableBaseMSM, AffineCurve, PairingEngine, ProjectiveCurve};
 use ark_ff::{Field, One, PrimeField, ToBytes, Zero};
 use ark_poly::{univariate::DensePolynomial, Polynomial};
 use core::any::Any;
-use core::str::FromStr;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use utils::{xgcd, FixedBaseCurvePow, FixedBaseScalarPow};
 
-#[cfg(test)]
-const GS_VEC_LEN: usize = 0;
-#[cfg(not(test))]
-const GS_VEC_LEN: usize = 5000;
-
 lazy_static! {
-    // 250 bits
-    static ref PUB_Q: Fr = Fr::from_str("480721077433357505777975950918924200361380912084288598463024400624539293706").unwrap();
+    // 250 bits，见 params::init_params
+    static ref PUB_Q: Fr = params::params().pub_q;
     // 128 bits
-    static ref PRI_S: Fr = Fr::from_str("259535143263514268207918833918737523409").unwrap();
+    static ref PRI_S: Fr = params::params().pri_s;
     static ref G1_POWER: FixedBaseCurvePow<G1Projective> =
         FixedBaseCurvePow::build(&G1Projective::prime_subgroup_generator());
     static ref G2_POWER: FixedBaseCurvePow<G2Projective> =
         FixedBaseCurvePow::build(&G2Projective::prime_subgroup_generator());
     static ref PRI_S_POWER: FixedBaseScalarPow<Fr> = FixedBaseScalarPow::build(&PRI_S);
-    static ref G1_S_VEC: Vec<G1Affine> = {
-        info!("Initialize G1_S_VEC...");
-        let timer = howlong::ProcessCPUTimer::new();
-        let mut res: Vec<G1Affine> = Vec::with_capacity(GS_VEC_LEN);
-        (0..GS_VEC_LEN)
-            .into_par_iter()
-            .map(|i| get_g1s(Fr::from(i as u64)))
-            .collect_into_vec(&mut res);
-        info!("Done in {}.", timer.elapsed());
-        res
-    };
-    static ref G2_S_VEC: Vec<G2Affine> = {
-        info!("Initialize G2_S_VEC...");
-        let timer = howlong::ProcessCPUTimer::new();
-        let mut res: Vec<G2Affine> = Vec::with_capacity(GS_VEC_LEN);
-        (0..GS_VEC_LEN)
-            .into_par_iter()
-            .map(|i| get_g2s(Fr::from(i as u64)))
-            .collect_into_vec(&mut res);
-        info!("Done in {}.", timer.elapsed());
-        res
-    };
     static ref E_G_G: Fq12 = Curve::pairing(
         G1Affine::prime_subgroup_generator(),
         G2Affine::prime_subgroup_generator()
@@ -117,60 +91,18 @@ pub trait AccumulatorProof: Eq + PartialEq {
 pub struct Acc1;
 
 impl Acc1 {
+    /// 用公开参数计算 `g1^{poly(s)}`；集合超过参数支持的次数时 panic
     fn poly_to_g1(poly: DensePolynomial<Fr>) -> G1Affine {
-        let mut idxes: Vec<usize> = Vec::with_capacity(poly.degree() + 1);
-        for (i, coeff) in poly.coeffs.iter().enumerate() {
-            if coeff.is_zero() {
-                continue;
-            }
-            idxes.push(i);
-        }
-
-        let mut bases: Vec<G1Affine> = Vec::with_capacity(idxes.len());
-        let mut scalars: Vec<<Fr as PrimeField>::BigInt> = Vec::with_capacity(idxes.len());
-        (0..idxes.len())
-            .into_par_iter()
-            .map(|i| {
-                G1_S_VEC.get(i).copied().unwrap_or_else(|| {
-                    trace!("access g1 pub key at {}", i);
-                    get_g1s(Fr::from(i as u64))
-                })
-            })
-            .collect_into_vec(&mut bases);
-        (0..idxes.len())
-            .into_par_iter()
-            .map(|i| poly.coeffs[i].into_repr())
-            .collect_into_vec(&mut scalars);
-
-        VariableBaseMSM::multi_scalar_mul(&bases[..], &scalars[..]).into_affine()
+        public_params::public_params()
+            .commit_g1(&poly)
+            .expect("set exceeds the degree supported by the public parameters")
     }
 
+    /// 用公开参数计算 `g2^{poly(s)}`；集合超过参数支持的次数时 panic
     fn poly_to_g2(poly: DensePolynomial<Fr>) -> G2Affine {
-        let mut idxes: Vec<usize> = Vec::with_capacity(poly.degree() + 1);
-        for (i, coeff) in poly.coeffs.iter().enumerate() {
-            if coeff.is_zero() {
-                continue;
-            }
-            idxes.push(i);
-        }
-
-        let mut bases: Vec<G2Affine> = Vec::with_capacity(idxes.len());
-        let mut scalars: Vec<<Fr as PrimeField>::BigInt> = Vec::with_capacity(idxes.len());
-        (0..idxes.len())
-            .into_par_iter()
-            .map(|i| {
-                G2_S_VEC.get(i).copied().unwrap_or_else(|| {
-                    trace!("access g2 pub key at {}", i);
-                    get_g2s(Fr::from(i as u64))
-                })
-            })
-            .collect_into_vec(&mut bases);
-        (0..idxes.len())
-            .into_par_iter()
-            .map(|i| poly.coeffs[i].into_repr())
-            .collect_into_vec(&mut scalars);
-
-        VariableBaseMSM::multi_scalar_mul(&bases[..], &scalars[..]).into_affine()
+        public_params::public_params()
+            .commit_g2(&poly)
+            .expect("set exceeds the degree supported by the public parameters")
     }
 }
 
@@ -0,0 +1,244 @@
+//! 累加器公共参数的显式初始化
+//!
+//! 曲线参数和预计算表由 `lazy_static` 惰性构建，初始化失败时会在第一次使用时
+//! panic。进程启动时应先调用 [`init_params`]：它加载参数、强制完成预计算并做一次
+//! 自检，失败时返回可读的错误，调用方可以据此拒绝启动或把节点标记为不健康。
+//!
+//! 参数文件是简单的 `key = value` 文本，`#` 开头的行为注释：
+//!
+//! ```text
+//! # 公开模数 q（十进制）
+//! q = 480721077433357505777975950918924200361380912084288598463024400624539293706
+//! # 私有陷门 s（十进制）
+//! s = 259535143263514268207918833918737523409
+//! ```
+//!
+//! 累加器的证明只使用公开参数（见 [`super::public_params`]），
+//! 这里的 s 只用于生成开发用的公开参数和 `Acc1::cal_acc_*_sk` 等显式的私钥路径。
+//!
+//! # 示例
+//!
+//! ```
+//! use esa_rust::crypto_accumulator::params::{init_params, params_ready};
+//!
+//! init_params(None).expect("built-in parameters are valid");
+//! assert!(params_ready());
+//! ```
+
+use super::dynamic_accumulator::DynamicAccumulator;
+use super::{Fq12, Fr};
+use ark_ff::{One, Zero};
+use core::str::FromStr;
+use std::path::Path;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+/// 内置的公开模数 q（250 bits）
+const BUILTIN_PUB_Q: &str =
+    "480721077433357505777975950918924200361380912084288598463024400624539293706";
+/// 内置的私有陷门 s（128 bits）
+const BUILTIN_PRI_S: &str = "259535143263514268207918833918737523409";
+
+static PARAMS: OnceLock<AccumulatorParams> = OnceLock::new();
+static READY: OnceLock<()> = OnceLock::new();
+
+#[derive(Error, Debug)]
+pub enum ParamsError {
+    #[error("Failed to read accumulator parameters from {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid accumulator parameters: {0}")]
+    Invalid(String),
+
+    #[error("Accumulator parameters were already initialized with different values")]
+    AlreadyInitialized,
+
+    #[error("Accumulator self-test failed: {0}")]
+    SelfTest(String),
+
+    #[error("Polynomial of degree {degree} exceeds the public parameters (max degree {max})")]
+    DegreeTooHigh { degree: usize, max: usize },
+}
+
+/// 累加器公共参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccumulatorParams {
+    pub pub_q: Fr,
+    pub pri_s: Fr,
+}
+
+impl AccumulatorParams {
+    /// 内置参数
+    pub fn builtin() -> Self {
+        AccumulatorParams {
+            pub_q: Fr::from_str(BUILTIN_PUB_Q).unwrap(),
+            pri_s: Fr::from_str(BUILTIN_PRI_S).unwrap(),
+        }
+    }
+
+    /// 解析参数文件内容
+    pub fn parse(text: &str) -> Result<Self, ParamsError> {
+        let mut pub_q = None;
+        let mut pri_s = None;
+
+        for (lineno, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| {
+                ParamsError::Invalid(format!("line {}: expected `key = value`", lineno + 1))
+            })?;
+            let value = Fr::from_str(value.trim()).map_err(|_| {
+                ParamsError::Invalid(format!("line {}: not a field element", lineno + 1))
+            })?;
+            match key.trim() {
+                "q" => pub_q = Some(value),
+                "s" => pri_s = Some(value),
+                other => {
+                    return Err(ParamsError::Invalid(format!(
+                        "line {}: unknown key `{}`",
+                        lineno + 1,
+                        other
+                    )))
+                }
+            }
+        }
+
+        let params = AccumulatorParams {
+            pub_q: pub_q.ok_or_else(|| ParamsError::Invalid("missing `q`".to_string()))?,
+            pri_s: pri_s.ok_or_else(|| ParamsError::Invalid("missing `s`".to_string()))?,
+        };
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// 从参数文件加载
+    pub fn load(path: &Path) -> Result<Self, ParamsError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ParamsError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Self::parse(&text)
+    }
+
+    fn validate(&self) -> Result<(), ParamsError> {
+        if self.pub_q.is_zero() || self.pri_s.is_zero() {
+            return Err(ParamsError::Invalid(
+                "parameters must be non-zero".to_string(),
+            ));
+        }
+        if self.pub_q == self.pri_s {
+            return Err(ParamsError::Invalid("`q` and `s` must differ".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// 当前使用的参数（未显式初始化时使用内置参数）
+pub(crate) fn params() -> &'static AccumulatorParams {
+    PARAMS.get_or_init(AccumulatorParams::builtin)
+}
+
+/// 初始化累加器参数
+///
+/// # 参数
+///
+/// * `path` - 参数文件路径，`None` 表示使用内置参数
+///
+/// 加载参数后会强制构建所有预计算表，并用一次添加/成员证明做自检。
+/// 可以重复调用，但参数一旦生效就不能更换
+pub fn init_params(path: Option<&Path>) -> Result<&'static AccumulatorParams, ParamsError> {
+    let loaded = match path {
+        Some(path) => AccumulatorParams::load(path)?,
+        None => AccumulatorParams::builtin(),
+    };
+    let active = PARAMS.get_or_init(|| loaded.clone());
+    if *active != loaded {
+        return Err(ParamsError::AlreadyInitialized);
+    }
+
+    self_test()?;
+    let _ = READY.set(());
+    Ok(active)
+}
+
+/// 参数是否已经初始化并通过自检
+pub fn params_ready() -> bool {
+    READY.get().is_some()
+}
+
+/// 强制构建预计算表并验证一次添加和成员证明，把 panic 转换为错误
+fn self_test() -> Result<(), ParamsError> {
+    let result = std::panic::catch_unwind(|| {
+        if *super::E_G_G == Fq12::one() {
+            return Err("degenerate pairing".to_string());
+        }
+        let _ = super::public_params::public_params();
+
+        let mut acc = DynamicAccumulator::new();
+        let add = acc.add(&42).map_err(|e| e.to_string())?;
+        if !add.verify() {
+            return Err("add proof did not verify".to_string());
+        }
+        let membership = acc.prove_membership(&42).map_err(|e| e.to_string())?;
+        if !acc.verify_membership(&membership) {
+            return Err("membership proof did not verify".to_string());
+        }
+        Ok(())
+    });
+
+    match result {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(ParamsError::SelfTest(e)),
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "panic during initialization".to_string());
+            Err(ParamsError::SelfTest(message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_params_file() {
+        let text = format!("# comment\nq = {}\n\ns={}\n", BUILTIN_PUB_Q, BUILTIN_PRI_S);
+        assert_eq!(
+            AccumulatorParams::parse(&text).unwrap(),
+            AccumulatorParams::builtin()
+        );
+
+        assert!(AccumulatorParams::parse("q = 1").is_err());
+        assert!(AccumulatorParams::parse("q = 1\ns = 1").is_err());
+        assert!(AccumulatorParams::parse("q = 1\ns = x").is_err());
+        assert!(AccumulatorParams::parse("q = 1\nt = 2").is_err());
+        assert!(AccumulatorParams::parse("q 1").is_err());
+    }
+
+    #[test]
+    fn test_init_params() {
+        assert!(matches!(
+            init_params(Some(Path::new("/nonexistent/acc.params"))),
+            Err(ParamsError::Io { .. })
+        ));
+
+        init_params(None).unwrap();
+        assert!(params_ready());
+
+        // 参数生效后不能再更换
+        let dir = std::env::temp_dir().join(format!("acc-params-{}", std::process::id()));
+        std::fs::write(&dir, "q = 7\ns = 11\n").unwrap();
+        let result = init_params(Some(&dir));
+        std::fs::remove_file(&dir).unwrap();
+        assert!(matches!(result, Err(ParamsError::AlreadyInitialized)));
+    }
+}
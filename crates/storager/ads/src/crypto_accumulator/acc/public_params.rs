@@ -0,0 +1,321 @@
+//! 累加器的公开参数（powers of s）
+//!
+//! 累加器值是 `g1^P(s)`，其中 `P(X) = ∏(X - e)`。证明者不需要知道陷门 s，
+//! 只需要公开的 `g1^{s^i}`、`g2^{s^i}`：把多项式的系数和这些点做多标量乘法（MSM）
+//! 即可得到 `g^P(s)`。这组点由一次可信设置生成，之后陷门被丢弃。
+//!
+//! 设置可以由多方依次完成：每个参与者调用 [`PublicParams::contribute`] 把参数中的
+//! s 替换为 `s·t`，只要任意一个参与者丢弃了自己的 t，最终的陷门就没有人知道。
+//! [`PublicParams::verify`] 用配对检查参数结构正确（每一项都是上一项乘以同一个 s），
+//! 但不能证明参与者丢弃了 t。
+//!
+//! 没有调用 [`init_public_params`] 时使用由内置参数中的 s 生成的开发用参数，
+//! 陷门对所有人可见，只适合测试。
+//!
+//! # 示例
+//!
+//! ```
+//! use esa_rust::crypto_accumulator::PublicParams;
+//!
+//! let mut rng = rand::thread_rng();
+//! let mut params = PublicParams::setup(8, &mut rng);
+//! params.contribute(&mut rng);
+//! params.verify(&mut rng).unwrap();
+//!
+//! let restored = PublicParams::from_bytes(&params.to_bytes()).unwrap();
+//! assert_eq!(restored.max_degree(), 8);
+//! ```
+
+use super::params::{params, ParamsError};
+use super::{Curve, G1_POWER, G2_POWER};
+use super::{Fr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::{msm::VariableBaseMSM, AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{One, PrimeField, Zero};
+use ark_poly::univariate::DensePolynomial;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::RngCore;
+use rayon::prelude::*;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// 参数文件的文件头
+const MAGIC: &[u8; 8] = b"ACCPP\0\0\x01";
+
+/// 开发用参数支持的最大多项式次数（即单个累加器最多的元素数）
+#[cfg(test)]
+const DEFAULT_MAX_DEGREE: usize = 64;
+#[cfg(not(test))]
+const DEFAULT_MAX_DEGREE: usize = 5000;
+
+static PUBLIC_PARAMS: OnceLock<PublicParams> = OnceLock::new();
+
+/// 公开参数：`g1^{s^i}` 和 `g2^{s^i}`，`i = 0..=max_degree`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicParams {
+    g1_powers: Vec<G1Affine>,
+    g2_powers: Vec<G2Affine>,
+}
+
+/// 从 RNG 取一个非零的随机域元素
+fn random_scalar(rng: &mut impl RngCore) -> Fr {
+    loop {
+        let mut bytes = [0u8; 64];
+        rng.fill_bytes(&mut bytes);
+        let scalar = Fr::from_le_bytes_mod_order(&bytes);
+        if !scalar.is_zero() {
+            return scalar;
+        }
+    }
+}
+
+/// `base^{t^i}`，`i = 0..powers.len()`，逐项乘到已有的点上
+fn scale_powers<G: AffineCurve<ScalarField = Fr>>(powers: &mut [G], t: Fr) {
+    let scalars: Vec<Fr> = std::iter::successors(Some(Fr::one()), |x| Some(*x * t))
+        .take(powers.len())
+        .collect();
+    let mut scaled: Vec<G::Projective> = powers
+        .par_iter()
+        .zip(scalars.par_iter())
+        .map(|(point, scalar)| point.mul(scalar.into_repr()))
+        .collect();
+    G::Projective::batch_normalization(&mut scaled);
+    for (point, scaled) in powers.iter_mut().zip(scaled) {
+        *point = scaled.into_affine();
+    }
+}
+
+impl PublicParams {
+    /// 由已知的陷门生成参数（陷门不会被保存，调用方负责丢弃）
+    pub fn from_secret(s: Fr, max_degree: usize) -> Self {
+        let exponents: Vec<Fr> = std::iter::successors(Some(Fr::one()), |x| Some(*x * s))
+            .take(max_degree + 1)
+            .collect();
+        let mut g1: Vec<G1Projective> = exponents.par_iter().map(|x| G1_POWER.apply(x)).collect();
+        let mut g2: Vec<G2Projective> = exponents.par_iter().map(|x| G2_POWER.apply(x)).collect();
+        G1Projective::batch_normalization(&mut g1);
+        G2Projective::batch_normalization(&mut g2);
+        PublicParams {
+            g1_powers: g1.into_iter().map(|p| p.into_affine()).collect(),
+            g2_powers: g2.into_iter().map(|p| p.into_affine()).collect(),
+        }
+    }
+
+    /// 用随机陷门生成参数，生成后陷门即被丢弃
+    pub fn setup(max_degree: usize, rng: &mut impl RngCore) -> Self {
+        Self::from_secret(random_scalar(rng), max_degree)
+    }
+
+    /// 追加一次贡献：取随机的 t，把参数中的 s 替换为 `s·t`
+    pub fn contribute(&mut self, rng: &mut impl RngCore) {
+        let t = random_scalar(rng);
+        scale_powers(&mut self.g1_powers, t);
+        scale_powers(&mut self.g2_powers, t);
+    }
+
+    /// 支持的最大多项式次数
+    pub fn max_degree(&self) -> usize {
+        self.g1_powers.len() - 1
+    }
+
+    /// `g2^s`
+    pub fn g2_s(&self) -> G2Affine {
+        self.g2_powers[1]
+    }
+
+    /// `g2^{s-x}`，用于验证证明
+    ///
+    /// 参数从生成元开始（见 [`verify`](Self::verify)），`g2^x` 用生成元的预计算表求得，
+    /// 不必做一次完整的标量乘法
+    pub fn g2_s_minus(&self, x: Fr) -> G2Affine {
+        (self.g2_powers[1].into_projective() - G2_POWER.apply(&x)).into_affine()
+    }
+
+    /// 计算 `g1^{poly(s)}`
+    pub fn commit_g1(&self, poly: &DensePolynomial<Fr>) -> Result<G1Affine, ParamsError> {
+        Self::commit(&self.g1_powers, poly)
+    }
+
+    /// 计算 `g2^{poly(s)}`
+    pub fn commit_g2(&self, poly: &DensePolynomial<Fr>) -> Result<G2Affine, ParamsError> {
+        Self::commit(&self.g2_powers, poly)
+    }
+
+    fn commit<G: AffineCurve<ScalarField = Fr>>(
+        powers: &[G],
+        poly: &DensePolynomial<Fr>,
+    ) -> Result<G, ParamsError> {
+        if poly.coeffs.len() > powers.len() {
+            return Err(ParamsError::DegreeTooHigh {
+                degree: poly.coeffs.len() - 1,
+                max: powers.len() - 1,
+            });
+        }
+        let scalars: Vec<_> = poly.coeffs.iter().map(|c| c.into_repr()).collect();
+        Ok(VariableBaseMSM::multi_scalar_mul(&powers[..scalars.len()], &scalars).into_affine())
+    }
+
+    /// 检查参数结构：两组点从生成元开始，且每一项都是上一项乘以同一个 s
+    ///
+    /// 用随机线性组合把 2n 次配对检查压缩为 4 次配对
+    pub fn verify(&self, rng: &mut impl RngCore) -> Result<(), ParamsError> {
+        let invalid = |reason: &str| Err(ParamsError::Invalid(reason.to_string()));
+        let n = self.g1_powers.len();
+        if n < 2 || self.g2_powers.len() != n {
+            return invalid("public parameters need the same number (>= 2) of G1 and G2 powers");
+        }
+        if self.g1_powers[0] != G1Affine::prime_subgroup_generator()
+            || self.g2_powers[0] != G2Affine::prime_subgroup_generator()
+        {
+            return invalid("public parameters must start at the group generators");
+        }
+        if self.g1_powers[1].is_zero() || self.g2_powers[1].is_zero() {
+            return invalid("public parameters are degenerate");
+        }
+
+        let r: Vec<_> = (0..n - 1).map(|_| random_scalar(rng).into_repr()).collect();
+        let msm_g1 = |points: &[G1Affine]| VariableBaseMSM::multi_scalar_mul(points, &r);
+        let msm_g2 = |points: &[G2Affine]| VariableBaseMSM::multi_scalar_mul(points, &r);
+
+        // Σ r_i·s^{i+1} = s · Σ r_i·s^i
+        let g1_ok = Curve::pairing(msm_g1(&self.g1_powers[1..]), self.g2_powers[0])
+            == Curve::pairing(msm_g1(&self.g1_powers[..n - 1]), self.g2_powers[1]);
+        let g2_ok = Curve::pairing(self.g1_powers[0], msm_g2(&self.g2_powers[1..]))
+            == Curve::pairing(self.g1_powers[1], msm_g2(&self.g2_powers[..n - 1]));
+        if !g1_ok || !g2_ok {
+            return invalid("public parameters are not consecutive powers of one secret");
+        }
+        Ok(())
+    }
+
+    /// 序列化：文件头 | G1 点列表 | G2 点列表
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        self.g1_powers.serialize(&mut bytes).unwrap();
+        self.g2_powers.serialize(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// 解析 [`to_bytes`](Self::to_bytes) 的输出（不做 [`verify`](Self::verify) 检查）
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParamsError> {
+        let invalid = |e: ark_serialize::SerializationError| ParamsError::Invalid(e.to_string());
+        let mut rest = bytes
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or_else(|| ParamsError::Invalid("not a public parameter file".to_string()))?;
+        let g1_powers = Vec::<G1Affine>::deserialize(&mut rest).map_err(invalid)?;
+        let g2_powers = Vec::<G2Affine>::deserialize(&mut rest).map_err(invalid)?;
+        if !rest.is_empty() {
+            return Err(ParamsError::Invalid(format!(
+                "{} trailing bytes in public parameters",
+                rest.len()
+            )));
+        }
+        if g1_powers.len() < 2 || g2_powers.len() != g1_powers.len() {
+            return Err(ParamsError::Invalid(
+                "public parameters need the same number (>= 2) of G1 and G2 powers".to_string(),
+            ));
+        }
+        Ok(PublicParams {
+            g1_powers,
+            g2_powers,
+        })
+    }
+
+    /// 写入参数文件
+    pub fn save(&self, path: &Path) -> Result<(), ParamsError> {
+        std::fs::write(path, self.to_bytes()).map_err(|source| ParamsError::Io {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+
+    /// 读取参数文件
+    pub fn load(path: &Path) -> Result<Self, ParamsError> {
+        let bytes = std::fs::read(path).map_err(|source| ParamsError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// 当前使用的公开参数（未初始化时使用开发用参数）
+pub fn public_params() -> &'static PublicParams {
+    PUBLIC_PARAMS.get_or_init(|| {
+        info!(
+            "Generating development accumulator parameters (max degree {})...",
+            DEFAULT_MAX_DEGREE
+        );
+        PublicParams::from_secret(params().pri_s, DEFAULT_MAX_DEGREE)
+    })
+}
+
+/// 加载并验证公开参数文件
+///
+/// # 参数
+///
+/// * `path` - 可信设置生成的参数文件，`None` 表示使用开发用参数
+///
+/// 必须在第一次使用累加器之前调用；参数一旦生效就不能更换
+pub fn init_public_params(path: Option<&Path>) -> Result<&'static PublicParams, ParamsError> {
+    let Some(path) = path else {
+        return Ok(public_params());
+    };
+    let loaded = PublicParams::load(path)?;
+    loaded.verify(&mut rand::thread_rng())?;
+    let active = PUBLIC_PARAMS.get_or_init(|| loaded.clone());
+    if *active != loaded {
+        return Err(ParamsError::AlreadyInitialized);
+    }
+    Ok(active)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_poly::{Polynomial, UVPolynomial};
+
+    #[test]
+    fn test_commit_matches_evaluation() {
+        let s = Fr::from(7u64);
+        let params = PublicParams::from_secret(s, 4);
+        let poly = DensePolynomial::from_coefficients_vec(vec![
+            Fr::from(3u64),
+            Fr::from(0u64),
+            Fr::from(5u64),
+        ]);
+        let expected = G1Affine::prime_subgroup_generator().mul(poly.evaluate(&s).into_repr());
+        assert_eq!(params.commit_g1(&poly).unwrap(), expected.into_affine());
+        assert_eq!(
+            params.g2_s_minus(Fr::from(2u64)),
+            G2Affine::prime_subgroup_generator()
+                .mul(Fr::from(5u64).into_repr())
+                .into_affine()
+        );
+
+        let too_high = DensePolynomial::from_coefficients_vec(vec![Fr::one(); 6]);
+        assert!(matches!(
+            params.commit_g1(&too_high),
+            Err(ParamsError::DegreeTooHigh { degree: 5, max: 4 })
+        ));
+    }
+
+    #[test]
+    fn test_contribution_keeps_structure() {
+        let mut rng = rand::thread_rng();
+        let mut params = PublicParams::setup(6, &mut rng);
+        let before = params.clone();
+        params.contribute(&mut rng);
+        assert_ne!(params, before);
+        params.verify(&mut rng).unwrap();
+
+        // 替换其中一项后结构检查失败
+        let mut tampered = params.clone();
+        tampered.g1_powers[3] = tampered.g1_powers[2];
+        assert!(tampered.verify(&mut rng).is_err());
+
+        let bytes = params.to_bytes();
+        assert_eq!(PublicParams::from_bytes(&bytes).unwrap(), params);
+        assert!(PublicParams::from_bytes(&bytes[1..]).is_err());
+        assert!(PublicParams::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -6,9 +6,13 @@
 //! - `DynamicAccumulator`: 动态累加器，支持增删元素
 //! - `DigestSet`: 摘要集合，用于存储元素
 //! - 证明生成和验证功能
+//! - `init_params`: 显式初始化公共参数并自检
+//! - `PublicParams`: 可信设置生成的 powers of s，证明只依赖它们而不接触陷门
 
 pub mod acc;
 
 pub use acc::digest_set::DigestSet;
 pub use acc::dynamic_accumulator::DynamicAccumulator;
+pub use acc::params::{init_params, params_ready, AccumulatorParams, ParamsError};
+pub use acc::public_params::{init_public_params, public_params, PublicParams};
 pub use acc::*;
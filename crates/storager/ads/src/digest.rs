@@ -133,6 +133,12 @@ impl Digestible for String {
     }
 }
 
+impl<T: Digestible + ?Sized> Digestible for &T {
+    fn to_digest(&self) -> Digest {
+        (**self).to_digest()
+    }
+}
+
 macro_rules! impl_digestable_for_numeric {
     ($x: ty) => {
         impl Digestible for $x {
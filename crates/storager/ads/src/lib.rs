@@ -4,11 +4,12 @@
 //!
 //! ## 当前实现
 //! - **CryptoAccumulator**: 基于 BLS12-381 的密码学累加器
+//! - **MPT**: Merkle Patricia Trie
+//! - **MerkleTree**: 支持增量更新的二叉 Merkle 树
+//! - **SparseMerkleTree**: 256 层的稀疏 Merkle 树，支持紧凑的非成员资格证明
 //!
 //! ## 未来扩展
 //! 可以添加其他 ADS 实现，例如:
-//! - Merkle Tree
-//! - Patricia Trie
 //! - Vector Commitment
 //! 等等
 
@@ -39,6 +40,12 @@ pub mod crypto_accumulator;
 /// Merkle Patricia Trie implementation
 pub mod mpt;
 
+/// Binary Merkle Tree with inclusion proofs
+pub mod merkle_tree;
+
+/// Sparse Merkle Tree with non-membership proofs
+pub mod smt;
+
 // Re-export commonly used types
 pub use crypto_accumulator::DigestSet;
 pub use crypto_accumulator::DynamicAccumulator;
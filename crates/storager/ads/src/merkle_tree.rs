@@ -0,0 +1,751 @@
+//! Merkle Tree
+//!
+//! 二叉 Merkle 树，支持两种叶子布局：
+//!
+//! - [`MerkleTree::insert`] / [`MerkleTree::remove`]：叶子按插入顺序排列，O(log n) 更新。
+//!   被删除的叶子置为全零的空叶子，其位置会被后续插入复用，因此树不会无限增长。
+//! - [`MerkleTree::insert_at`] / [`MerkleTree::remove_at`]：叶子保持调用方给定的顺序，
+//!   没有空叶子，插入和删除要移动其后的叶子并重算它们的祖先，代价 O(n)。
+//!
+//! 哈希带域分离前缀，防止叶子与内部节点混淆：
+//!
+//! ```text
+//! leaf = SHA256(0x00 || len(keyword) as u32 LE || keyword || fid)
+//! node = SHA256(0x01 || left || right)
+//! ```
+//!
+//! 某一层节点数为奇数时，最后一个节点与空哈希配对。
+//!
+//! Merkle Tree ADS 中每个 storager 维护一棵树，所有 (keyword, fid) 对按 (keyword, fid)
+//! 排序后作为它的叶子，同一个 keyword 的叶子是连续的一段，树根就是该 storager 的根哈希；
+//! storager 返回、Manager 验证的证明格式见 [`MerkleAdsProof`]。
+//!
+//! # 示例
+//!
+//! ```
+//! use esa_rust::merkle_tree::{leaf_hash, MerkleTree};
+//!
+//! let mut tree = MerkleTree::new();
+//! let leaf = leaf_hash("rust", "file1");
+//! let index = tree.insert(leaf);
+//! tree.insert(leaf_hash("rust", "file2"));
+//!
+//! let proof = tree.prove(index).unwrap();
+//! assert!(proof.verify(&leaf, &tree.root()));
+//! ```
+
+use sha2::{Digest, Sha256};
+
+/// 哈希值
+pub type Hash = [u8; 32];
+
+/// 空叶子 / 空树的哈希
+pub const EMPTY_HASH: Hash = [0u8; 32];
+
+/// 计算 (keyword, fid) 叶子哈希
+pub fn leaf_hash(keyword: &str, fid: &str) -> Hash {
+    leaf_hash_bytes(keyword, fid.as_bytes())
+}
+
+/// 计算 keyword 下任意字节值的叶子哈希（如 keyword 的草图），规则与 [`leaf_hash`] 相同
+pub fn leaf_hash_bytes(keyword: &str, value: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0u8]);
+    hasher.update((keyword.len() as u32).to_le_bytes());
+    hasher.update(keyword.as_bytes());
+    hasher.update(value);
+    hasher.finalize().into()
+}
+
+/// 计算内部节点哈希
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// 单个叶子的包含证明
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    /// 叶子位置
+    pub index: u64,
+    /// 自底向上的兄弟节点哈希
+    pub siblings: Vec<Hash>,
+}
+
+impl MerkleProof {
+    /// 由叶子哈希和证明路径计算根
+    pub fn compute_root(&self, leaf: &Hash) -> Hash {
+        path_root(leaf, self.index, &self.siblings)
+    }
+
+    /// 验证叶子是否包含在给定根之下
+    pub fn verify(&self, leaf: &Hash, root: &Hash) -> bool {
+        index_fits(self.index, &self.siblings) && self.compute_root(leaf) == *root
+    }
+
+    /// 编码
+    ///
+    /// 格式: index(u64 小端序) | siblings(32 * depth)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.index.to_le_bytes().to_vec();
+        for sibling in &self.siblings {
+            out.extend_from_slice(sibling);
+        }
+        out
+    }
+
+    /// 解码，格式错误时返回 None
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let index = u64::from_le_bytes(bytes.get(..8)?.try_into().ok()?);
+        let siblings = bytes[8..].chunks_exact(32);
+        if !siblings.remainder().is_empty() {
+            return None;
+        }
+        let siblings = siblings.map(|s| s.try_into().unwrap()).collect();
+        Some(MerkleProof { index, siblings })
+    }
+}
+
+/// 沿自底向上的兄弟节点哈希从叶子计算到根
+fn path_root(leaf: &Hash, mut index: u64, siblings: &[Hash]) -> Hash {
+    let mut current = *leaf;
+    for sibling in siblings {
+        current = if index & 1 == 1 {
+            node_hash(sibling, &current)
+        } else {
+            node_hash(&current, sibling)
+        };
+        index >>= 1;
+    }
+    current
+}
+
+/// 路径长度必须能容纳 index，否则高位会被忽略
+fn index_fits(index: u64, siblings: &[Hash]) -> bool {
+    let depth = u32::try_from(siblings.len()).unwrap_or(u32::MAX);
+    index.checked_shr(depth).unwrap_or(0) == 0
+}
+
+/// Merkle 树
+///
+/// `levels[0]` 是叶子层，最后一层只有一个节点（根）
+#[derive(Debug, Clone, Default)]
+pub struct MerkleTree {
+    levels: Vec<Vec<Hash>>,
+    /// 被删除后可复用的叶子位置
+    free: Vec<usize>,
+}
+
+impl MerkleTree {
+    /// 创建空树
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 由叶子层（包含空叶子）重建树
+    ///
+    /// 得到的根与逐个插入/删除得到原叶子层的树相同，空叶子的位置可被后续插入复用
+    pub fn from_leaves(leaves: Vec<Hash>) -> Self {
+        if leaves.is_empty() {
+            return Self::new();
+        }
+
+        let free = leaves
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, leaf)| **leaf == EMPTY_HASH)
+            .map(|(index, _)| index)
+            .collect();
+        let mut levels = vec![leaves];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| node_hash(&pair[0], pair.get(1).unwrap_or(&EMPTY_HASH)))
+                .collect();
+            levels.push(next);
+        }
+        MerkleTree { levels, free }
+    }
+
+    /// 叶子层（包含空叶子）
+    pub fn leaves(&self) -> &[Hash] {
+        self.levels.first().map_or(&[], |leaves| leaves.as_slice())
+    }
+
+    /// 叶子层长度（包含空叶子）
+    pub fn capacity(&self) -> usize {
+        self.levels.first().map_or(0, |leaves| leaves.len())
+    }
+
+    /// 非空叶子数量
+    pub fn len(&self) -> usize {
+        self.capacity() - self.free.len()
+    }
+
+    /// 树是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 根哈希（空树为全零）
+    pub fn root(&self) -> Hash {
+        if self.is_empty() {
+            return EMPTY_HASH;
+        }
+        self.levels
+            .last()
+            .and_then(|top| top.first())
+            .copied()
+            .unwrap_or(EMPTY_HASH)
+    }
+
+    /// 读取叶子
+    pub fn leaf(&self, index: usize) -> Option<Hash> {
+        self.levels.first()?.get(index).copied()
+    }
+
+    /// 插入叶子，返回其位置
+    pub fn insert(&mut self, leaf: Hash) -> usize {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                if self.levels.is_empty() {
+                    self.levels.push(Vec::new());
+                }
+                self.levels[0].push(EMPTY_HASH);
+                self.levels[0].len() - 1
+            }
+        };
+        self.set_leaf(index, leaf);
+        index
+    }
+
+    /// 删除叶子（置为空叶子），返回原叶子哈希
+    pub fn remove(&mut self, index: usize) -> Option<Hash> {
+        let old = self.leaf(index)?;
+        if old == EMPTY_HASH {
+            return None;
+        }
+        self.set_leaf(index, EMPTY_HASH);
+        self.free.push(index);
+        Some(old)
+    }
+
+    /// 在 `index` 处插入叶子，原来在 `index` 及之后的叶子依次后移
+    ///
+    /// 只用于没有空叶子的树（见模块文档中的第二种布局）
+    pub fn insert_at(&mut self, index: usize, leaf: Hash) {
+        debug_assert!(self.free.is_empty());
+        if self.levels.is_empty() {
+            self.levels.push(Vec::new());
+        }
+        self.levels[0].insert(index, leaf);
+        self.rehash_from(index);
+    }
+
+    /// 删除 `index` 处的叶子，其后的叶子依次前移，返回被删除的叶子哈希
+    pub fn remove_at(&mut self, index: usize) -> Option<Hash> {
+        debug_assert!(self.free.is_empty());
+        if index >= self.capacity() {
+            return None;
+        }
+        let old = self.levels[0].remove(index);
+        self.rehash_from(index);
+        Some(old)
+    }
+
+    /// 生成包含证明
+    pub fn prove(&self, index: usize) -> Option<MerkleProof> {
+        if index >= self.capacity() {
+            return None;
+        }
+
+        let mut siblings = Vec::with_capacity(self.levels.len().saturating_sub(1));
+        let mut idx = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            siblings.push(level.get(idx ^ 1).copied().unwrap_or(EMPTY_HASH));
+            idx >>= 1;
+        }
+        Some(MerkleProof {
+            index: index as u64,
+            siblings,
+        })
+    }
+
+    /// 叶子层从 `index` 起有变化（包括长度）时，重算各层受影响的节点
+    fn rehash_from(&mut self, index: usize) {
+        let mut start = index;
+        let mut depth = 0;
+        while self.levels[depth].len() > 1 {
+            let len = self.levels[depth].len().div_ceil(2);
+            start >>= 1;
+            if depth + 1 == self.levels.len() {
+                self.levels.push(Vec::new());
+            }
+            let (lower, upper) = self.levels.split_at_mut(depth + 1);
+            let (level, next) = (&lower[depth], &mut upper[0]);
+            next.resize(len, EMPTY_HASH);
+            for (i, node) in next.iter_mut().enumerate().skip(start) {
+                *node = node_hash(&level[2 * i], level.get(2 * i + 1).unwrap_or(&EMPTY_HASH));
+            }
+            depth += 1;
+        }
+        self.levels.truncate(depth + 1);
+    }
+
+    /// 更新叶子并重算到根的路径
+    fn set_leaf(&mut self, index: usize, leaf: Hash) {
+        self.levels[0][index] = leaf;
+
+        let mut idx = index;
+        let mut depth = 0;
+        while self.levels[depth].len() > 1 {
+            let level = &self.levels[depth];
+            let left = level[idx & !1];
+            let right = level.get(idx | 1).copied().unwrap_or(EMPTY_HASH);
+            let parent = node_hash(&left, &right);
+
+            idx >>= 1;
+            depth += 1;
+            if depth == self.levels.len() {
+                self.levels.push(Vec::new());
+            }
+            let next = &mut self.levels[depth];
+            if idx == next.len() {
+                next.push(parent);
+            } else {
+                next[idx] = parent;
+            }
+        }
+    }
+}
+
+/// 单个 (keyword, fid) 的包含证明
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleInclusion {
+    pub keyword: String,
+    pub fid: String,
+    /// 叶子位置
+    pub index: u64,
+    /// 自底向上的兄弟节点哈希
+    pub siblings: Vec<Hash>,
+}
+
+impl MerkleInclusion {
+    /// 由叶子和路径计算根，index 超出路径深度时返回 None
+    pub fn compute_root(&self) -> Option<Hash> {
+        index_fits(self.index, &self.siblings).then(|| {
+            path_root(
+                &leaf_hash(&self.keyword, &self.fid),
+                self.index,
+                &self.siblings,
+            )
+        })
+    }
+
+    /// 叶子是否是树中的最后一个：路径上作为左孩子的每一层，右侧的兄弟都不存在（空哈希）
+    pub fn is_last(&self) -> bool {
+        self.siblings.iter().enumerate().all(|(depth, sibling)| {
+            self.index.checked_shr(depth as u32).unwrap_or(0) & 1 == 1 || *sibling == EMPTY_HASH
+        })
+    }
+}
+
+/// Merkle Tree ADS 的操作证明
+///
+/// Add 返回新叶子的包含证明；Query 返回 keyword 下每个 fid 的包含证明，以及这一段叶子
+/// 两侧的相邻叶子（边界），证明结果完整、keyword 不存在时证明它确实不存在（见
+/// [`covers_keyword`](Self::covers_keyword)）；Delete 只携带删除后的根
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MerkleAdsProof {
+    /// 生成证明时的树根
+    pub root: Hash,
+    pub inclusions: Vec<MerkleInclusion>,
+    /// `inclusions` 所在一段叶子两侧的相邻叶子
+    pub boundaries: Vec<MerkleInclusion>,
+}
+
+impl MerkleAdsProof {
+    /// 编码
+    ///
+    /// 格式: root(32) | count(u32) | 包含证明 * count | count(u32) | 边界 * count，
+    /// 每项为 [len(u32) | keyword | len(u32) | fid | index(u64) | depth(u8) | siblings(32 * depth)]，
+    /// 整数均为小端序
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.root);
+        for list in [&self.inclusions, &self.boundaries] {
+            out.extend_from_slice(&(list.len() as u32).to_le_bytes());
+            for inclusion in list {
+                out.extend_from_slice(&(inclusion.keyword.len() as u32).to_le_bytes());
+                out.extend_from_slice(inclusion.keyword.as_bytes());
+                out.extend_from_slice(&(inclusion.fid.len() as u32).to_le_bytes());
+                out.extend_from_slice(inclusion.fid.as_bytes());
+                out.extend_from_slice(&inclusion.index.to_le_bytes());
+                out.push(inclusion.siblings.len() as u8);
+                for sibling in &inclusion.siblings {
+                    out.extend_from_slice(sibling);
+                }
+            }
+        }
+        out
+    }
+
+    /// 解码，格式错误时返回 None
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut rest = bytes;
+        let mut take = |n: usize| -> Option<&[u8]> {
+            if rest.len() < n {
+                return None;
+            }
+            let (head, tail) = rest.split_at(n);
+            rest = tail;
+            Some(head)
+        };
+
+        let root: Hash = take(32)?.try_into().ok()?;
+        let mut lists = [Vec::new(), Vec::new()];
+        for list in &mut lists {
+            let count = u32::from_le_bytes(take(4)?.try_into().ok()?);
+            for _ in 0..count {
+                let len = u32::from_le_bytes(take(4)?.try_into().ok()?) as usize;
+                let keyword = String::from_utf8(take(len)?.to_vec()).ok()?;
+                let len = u32::from_le_bytes(take(4)?.try_into().ok()?) as usize;
+                let fid = String::from_utf8(take(len)?.to_vec()).ok()?;
+                let index = u64::from_le_bytes(take(8)?.try_into().ok()?);
+                let depth = take(1)?[0] as usize;
+                let mut siblings = Vec::with_capacity(depth);
+                for _ in 0..depth {
+                    siblings.push(take(32)?.try_into().ok()?);
+                }
+                list.push(MerkleInclusion {
+                    keyword,
+                    fid,
+                    index,
+                    siblings,
+                });
+            }
+        }
+
+        if !rest.is_empty() {
+            return None;
+        }
+        let [inclusions, boundaries] = lists;
+        Some(MerkleAdsProof {
+            root,
+            inclusions,
+            boundaries,
+        })
+    }
+
+    /// 证明中包含的 fid
+    pub fn fids(&self) -> Vec<&str> {
+        self.inclusions.iter().map(|i| i.fid.as_str()).collect()
+    }
+
+    /// 包含证明是否恰好是 `keyword` 的所有叶子
+    ///
+    /// 叶子按 (keyword, fid) 排序，`keyword` 的叶子是连续的一段：包含证明必须都属于 `keyword`
+    /// 且位置连续，边界中 keyword 较小的叶子紧挨在这一段之前，较大的紧挨在之后；
+    /// 段位于树的开头或结尾时对应一侧不需要边界。没有包含证明时证明 `keyword` 不存在，
+    /// 两个边界必须相邻，空树不需要边界。
+    ///
+    /// 只检查证明的结构，各叶子是否推导出根由 [`verify_merkle_proof`] 检查
+    pub fn covers_keyword(&self, keyword: &str) -> bool {
+        let (lower, upper): (Vec<_>, Vec<_>) = self
+            .boundaries
+            .iter()
+            .partition(|b| b.keyword.as_str() < keyword);
+        if lower.len() > 1
+            || upper.len() > 1
+            || upper.iter().any(|b| b.keyword == keyword)
+            || self.inclusions.iter().any(|i| i.keyword != keyword)
+        {
+            return false;
+        }
+        let (lower, upper) = (lower.first(), upper.first());
+
+        let (Some(first), Some(last)) = (self.inclusions.first(), self.inclusions.last()) else {
+            return match (lower, upper) {
+                (Some(lower), Some(upper)) => lower.index.checked_add(1) == Some(upper.index),
+                (None, Some(upper)) => upper.index == 0,
+                (Some(lower), None) => lower.is_last(),
+                (None, None) => self.root == EMPTY_HASH,
+            };
+        };
+        let contiguous = self
+            .inclusions
+            .iter()
+            .zip(first.index..)
+            .all(|(inclusion, index)| inclusion.index == index);
+        let starts = match lower {
+            Some(lower) => lower.index.checked_add(1) == Some(first.index),
+            None => first.index == 0,
+        };
+        let ends = match upper {
+            Some(upper) => last.index.checked_add(1) == Some(upper.index),
+            None => last.is_last(),
+        };
+        contiguous && starts && ends
+    }
+}
+
+/// 验证 Merkle Tree ADS 证明
+///
+/// 所有包含证明和边界都必须推导出证明中的根，且与 `expected_root`（Manager 记录的该 storager
+/// 的根）一致；`expected_root` 为空时拒绝
+pub fn verify_merkle_proof(proof: &[u8], expected_root: &[u8]) -> bool {
+    let Some(proof) = MerkleAdsProof::from_bytes(proof) else {
+        return false;
+    };
+    if expected_root != proof.root {
+        return false;
+    }
+    proof
+        .inclusions
+        .iter()
+        .chain(&proof.boundaries)
+        .all(|inclusion| inclusion.compute_root() == Some(proof.root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 从叶子层整体重算根，用于和增量更新的结果比对
+    fn naive_root(leaves: &[Hash]) -> Hash {
+        if leaves.is_empty() {
+            return EMPTY_HASH;
+        }
+        let mut level = leaves.to_vec();
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| node_hash(&pair[0], pair.get(1).unwrap_or(&EMPTY_HASH)))
+                .collect();
+        }
+        level[0]
+    }
+
+    #[test]
+    fn test_incremental_root_matches_naive() {
+        let mut tree = MerkleTree::new();
+        let mut leaves = Vec::new();
+        for i in 0..37 {
+            let leaf = leaf_hash("kw", &format!("f{}", i));
+            assert_eq!(tree.insert(leaf), i);
+            leaves.push(leaf);
+            assert_eq!(tree.root(), naive_root(&leaves));
+        }
+    }
+
+    #[test]
+    fn test_proofs_verify() {
+        let mut tree = MerkleTree::new();
+        let leaves: Vec<Hash> = (0..13).map(|i| leaf_hash("kw", &i.to_string())).collect();
+        for leaf in &leaves {
+            tree.insert(*leaf);
+        }
+
+        let root = tree.root();
+        for (i, leaf) in leaves.iter().enumerate() {
+            let proof = tree.prove(i).unwrap();
+            assert!(proof.verify(leaf, &root));
+            assert!(!proof.verify(&leaf_hash("kw", "other"), &root));
+            assert_eq!(MerkleProof::from_bytes(&proof.to_bytes()), Some(proof));
+        }
+        assert!(tree.prove(13).is_none());
+        assert!(MerkleProof::from_bytes(&[0u8; 7]).is_none());
+        assert!(MerkleProof::from_bytes(&[0u8; 9]).is_none());
+    }
+
+    #[test]
+    fn test_remove_and_reuse_slot() {
+        let mut tree = MerkleTree::new();
+        let a = tree.insert(leaf_hash("kw", "a"));
+        let b = tree.insert(leaf_hash("kw", "b"));
+        let root_ab = tree.root();
+
+        assert_eq!(tree.remove(a), Some(leaf_hash("kw", "a")));
+        assert_eq!(tree.remove(a), None);
+        assert_eq!(tree.len(), 1);
+        assert_ne!(tree.root(), root_ab);
+        assert!(tree
+            .prove(b)
+            .unwrap()
+            .verify(&leaf_hash("kw", "b"), &tree.root()));
+
+        // 被删除的位置被复用
+        assert_eq!(tree.insert(leaf_hash("kw", "a")), a);
+        assert_eq!(tree.root(), root_ab);
+
+        tree.remove(a);
+        tree.remove(b);
+        assert!(tree.is_empty());
+        assert_eq!(tree.root(), EMPTY_HASH);
+    }
+
+    #[test]
+    fn test_from_leaves_matches_incremental() {
+        let mut tree = MerkleTree::new();
+        for i in 0..11 {
+            tree.insert(leaf_hash("kw", &i.to_string()));
+        }
+        tree.remove(3);
+        tree.remove(10);
+
+        let mut rebuilt = MerkleTree::from_leaves(tree.leaves().to_vec());
+        assert_eq!(rebuilt.root(), tree.root());
+        assert_eq!(rebuilt.len(), tree.len());
+        assert_eq!(rebuilt.prove(4), tree.prove(4));
+
+        // 空叶子的位置被复用，之后的插入与原树保持一致
+        let leaf = leaf_hash("kw", "new");
+        assert!([3, 10].contains(&rebuilt.insert(leaf)));
+        assert!(MerkleTree::from_leaves(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_ordered_updates_match_naive() {
+        let mut tree = MerkleTree::new();
+        let mut leaves = Vec::new();
+        for i in 0..23usize {
+            let leaf = leaf_hash("kw", &i.to_string());
+            let index = (i * 7) % (leaves.len() + 1);
+            tree.insert_at(index, leaf);
+            leaves.insert(index, leaf);
+            assert_eq!(tree.root(), naive_root(&leaves));
+        }
+        for i in [0, 21, 5, 5, 13] {
+            assert_eq!(tree.remove_at(i), Some(leaves.remove(i)));
+            assert_eq!(tree.root(), naive_root(&leaves));
+            let rebuilt = MerkleTree::from_leaves(leaves.clone());
+            assert_eq!(
+                tree.prove(leaves.len() / 2),
+                rebuilt.prove(leaves.len() / 2)
+            );
+        }
+        assert_eq!(tree.remove_at(leaves.len()), None);
+        while !leaves.is_empty() {
+            tree.remove_at(0);
+            leaves.remove(0);
+            assert_eq!(tree.root(), naive_root(&leaves));
+        }
+        assert!(tree.is_empty());
+    }
+
+    /// 按 (keyword, fid) 排好序的叶子构成的树，以及位置 `index` 的包含证明
+    fn sorted_tree(entries: &[(&str, &str)]) -> (MerkleTree, impl Fn(usize) -> MerkleInclusion) {
+        let tree = MerkleTree::from_leaves(entries.iter().map(|(k, f)| leaf_hash(k, f)).collect());
+        let entries: Vec<(String, String)> = entries
+            .iter()
+            .map(|(k, f)| (k.to_string(), f.to_string()))
+            .collect();
+        let prover = tree.clone();
+        let inclusion = move |index: usize| {
+            let proof = prover.prove(index).unwrap();
+            MerkleInclusion {
+                keyword: entries[index].0.clone(),
+                fid: entries[index].1.clone(),
+                index: proof.index,
+                siblings: proof.siblings,
+            }
+        };
+        (tree, inclusion)
+    }
+
+    #[test]
+    fn test_covers_keyword() {
+        let (tree, inclusion) = sorted_tree(&[
+            ("db", "f1"),
+            ("go", "f1"),
+            ("go", "f2"),
+            ("rust", "f1"),
+            ("rust", "f3"),
+        ]);
+        let proof = |inclusions: &[usize], boundaries: &[usize]| MerkleAdsProof {
+            root: tree.root(),
+            inclusions: inclusions.iter().map(|i| inclusion(*i)).collect(),
+            boundaries: boundaries.iter().map(|i| inclusion(*i)).collect(),
+        };
+
+        assert!(proof(&[1, 2], &[0, 3]).covers_keyword("go"));
+        assert!(proof(&[0], &[1]).covers_keyword("db"));
+        assert!(proof(&[3, 4], &[2]).covers_keyword("rust"));
+        assert!(inclusion(4).is_last() && !inclusion(3).is_last());
+        // 漏掉一个叶子、缺少边界、边界不相邻或属于同一个 keyword 都不成立
+        assert!(!proof(&[1], &[0, 3]).covers_keyword("go"));
+        assert!(!proof(&[2], &[0, 3]).covers_keyword("go"));
+        assert!(!proof(&[1, 2], &[0]).covers_keyword("go"));
+        assert!(!proof(&[3], &[2]).covers_keyword("rust"));
+        assert!(!proof(&[4], &[2]).covers_keyword("rust"));
+        assert!(!proof(&[1, 2], &[3]).covers_keyword("rust"));
+
+        // keyword 不存在：相邻的两个边界，或者位于开头、结尾的一个边界
+        assert!(proof(&[], &[2, 3]).covers_keyword("java"));
+        assert!(proof(&[], &[0]).covers_keyword("c"));
+        assert!(proof(&[], &[4]).covers_keyword("zig"));
+        assert!(!proof(&[], &[0, 3]).covers_keyword("java"));
+        assert!(!proof(&[], &[3]).covers_keyword("zig"));
+        assert!(!proof(&[], &[1, 2]).covers_keyword("go"));
+        assert!(MerkleAdsProof::default().covers_keyword("go"));
+        assert!(!proof(&[], &[]).covers_keyword("go"));
+
+        let bytes = proof(&[1, 2], &[0, 3]).to_bytes();
+        assert_eq!(
+            MerkleAdsProof::from_bytes(&bytes),
+            Some(proof(&[1, 2], &[0, 3]))
+        );
+        assert!(verify_merkle_proof(&bytes, &tree.root()));
+    }
+
+    fn two_leaf_proof() -> MerkleAdsProof {
+        let a = leaf_hash("rust", "f1");
+        let b = leaf_hash("rust", "f2");
+        MerkleAdsProof {
+            root: node_hash(&a, &b),
+            inclusions: vec![
+                MerkleInclusion {
+                    keyword: "rust".to_string(),
+                    fid: "f1".to_string(),
+                    index: 0,
+                    siblings: vec![b],
+                },
+                MerkleInclusion {
+                    keyword: "rust".to_string(),
+                    fid: "f2".to_string(),
+                    index: 1,
+                    siblings: vec![a],
+                },
+            ],
+            boundaries: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_proof_roundtrip_and_verify() {
+        let proof = two_leaf_proof();
+        let bytes = proof.to_bytes();
+        assert_eq!(MerkleAdsProof::from_bytes(&bytes), Some(proof.clone()));
+        assert!(!verify_merkle_proof(&bytes, &[]));
+        assert!(verify_merkle_proof(&bytes, &proof.root));
+        assert!(!verify_merkle_proof(&bytes, &[0u8; 32]));
+        assert!(!verify_merkle_proof(&bytes[..bytes.len() - 1], &proof.root));
+    }
+
+    #[test]
+    fn test_tampered_fid_rejected() {
+        let mut proof = two_leaf_proof();
+        proof.inclusions[1].fid = "f3".to_string();
+        assert!(!verify_merkle_proof(&proof.to_bytes(), &proof.root));
+
+        // index 超出路径深度
+        let mut proof = two_leaf_proof();
+        proof.inclusions[0].index = 2;
+        assert!(!verify_merkle_proof(&proof.to_bytes(), &proof.root));
+    }
+}
@@ -0,0 +1,647 @@
+//! MPT 节点和证明的二进制编码
+//!
+//! 节点和证明原来用 serde_json 编码，32 字节的哈希被写成十进制数字列表，体积是二进制的数倍。
+//! 这里使用紧凑的二进制格式，每个编码以格式版本、哈希算法和类型开头：
+//!
+//! ```text
+//! 编码       = 版本 (1 字节，当前为 1) | 哈希算法 (1 字节) | 类型 (1 字节) | 内容
+//! 类型       = 0 叶子 | 1 扩展 | 2 分支 | 3 单键证明 | 4 范围证明
+//! 叶子       = 节点哈希 (32 字节) | 字节串(前缀) | 字节串(后缀) | 可选字节串(值)
+//! 扩展       = 节点哈希 (32 字节) | 字节串(前缀) | 字节串(后缀) | 下一个节点的哈希 (32 字节)
+//! 分支       = 节点哈希 (32 字节) | 子节点位图 (2 字节，大端) | 每个子节点的字节串(哈希)
+//!              | 可选字节串(值)
+//! 单键证明   = 字节串(值) | 是否存在 (1 字节) | 层数 (变长整数) | 元素数 (变长整数) | 证明元素…
+//! 证明元素   = 层级 (变长整数) | 类型 (1 字节) | 字节串(前缀) | 字节串(后缀) | 字节串(值)
+//!              | 字节串(下一个节点的哈希) | 子节点位图 (2 字节) | 每个子节点的字节串(哈希)
+//! 范围证明   = 范围节点
+//! 范围节点   = 0 空树 | 1 字节串(哈希) | 2 可选字节串(值) 子节点数 (变长整数) (下标 (1 字节) 范围节点)…
+//!              | 3 字节串(前缀) 字节串(后缀) 可选字节串(值) | 4 字节串(前缀) 字节串(后缀) 范围节点
+//! 字节串     = 长度 (变长整数) | 字节
+//! 可选字节串 = 0 | 1 字节串
+//! 变长整数   = LEB128，每字节低 7 位，最高位表示后面还有字节
+//! ```
+//!
+//! 节点哈希只由节点内容决定（见 [`HashAlgorithm`]），与编码无关：
+//! [`MPT::migrate_encoding`] 在原来的键下把 JSON 编码的节点改写为二进制编码，
+//! 根哈希和证明都不变。JSON 编码以 `{` 或 `"` 开头，与版本字节不冲突，解码时两种编码都能识别，
+//! 旧编码的节点和证明一律按 SHA-256 处理。
+
+use super::error::MPTError;
+use super::hasher::HashAlgorithm;
+use super::mpt::MPT;
+use super::node::{Database, FullNode, ShortNode, WriteBatch};
+use super::proof::{MPTProof, ProofElement, ValueProof};
+use super::range::{RangeNode, RangeProof};
+use std::collections::HashSet;
+
+/// 二进制编码的格式版本
+pub const FORMAT_VERSION: u8 = 1;
+
+const LEAF: u8 = 0;
+const EXTENSION: u8 = 1;
+const BRANCH: u8 = 2;
+const VALUE_PROOF: u8 = 3;
+const RANGE_PROOF: u8 = 4;
+
+/// 范围证明的最大嵌套深度：键路径的每个半字节最多对应一层分支和一层扩展
+const MAX_RANGE_DEPTH: usize = 4096;
+
+/// 是否为旧的 JSON 编码
+pub fn is_legacy_json(data: &[u8]) -> bool {
+    matches!(data.first(), Some(b'{' | b'"'))
+}
+
+fn invalid(message: impl Into<String>) -> MPTError {
+    MPTError::InvalidData(message.into())
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn new(hash: HashAlgorithm, kind: u8) -> Self {
+        Writer(vec![FORMAT_VERSION, hash.code(), kind])
+    }
+
+    fn byte(&mut self, byte: u8) {
+        self.0.push(byte);
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn raw(&mut self, data: &[u8]) {
+        self.0.extend_from_slice(data);
+    }
+
+    fn bytes(&mut self, data: &[u8]) {
+        self.varint(data.len() as u64);
+        self.raw(data);
+    }
+
+    fn optional(&mut self, data: Option<&[u8]>) {
+        match data {
+            Some(data) => {
+                self.byte(1);
+                self.bytes(data);
+            }
+            None => self.byte(0),
+        }
+    }
+
+    /// 子节点位图，随后依次写入存在的子节点哈希
+    fn children<'a>(&mut self, children: impl Iterator<Item = Option<&'a [u8]>> + Clone) {
+        let bitmap = children
+            .clone()
+            .enumerate()
+            .filter(|(_, child)| child.is_some())
+            .fold(0u16, |bitmap, (i, _)| bitmap | 1 << i);
+        self.raw(&bitmap.to_be_bytes());
+        for child in children.flatten() {
+            self.bytes(child);
+        }
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// 读取编码头，返回哈希算法；类型必须是 `kinds` 之一
+    fn new(data: &'a [u8], kinds: &[u8]) -> Result<(Self, HashAlgorithm, u8), MPTError> {
+        let mut reader = Reader { data, pos: 0 };
+        let version = reader.byte()?;
+        if version != FORMAT_VERSION {
+            return Err(invalid(format!(
+                "unsupported MPT encoding version {}",
+                version
+            )));
+        }
+        let code = reader.byte()?;
+        let hash = HashAlgorithm::from_code(code)
+            .ok_or_else(|| invalid(format!("unknown hash algorithm code {}", code)))?;
+        let kind = reader.byte()?;
+        if !kinds.contains(&kind) {
+            return Err(invalid(format!("unexpected MPT encoding type {}", kind)));
+        }
+        Ok((reader, hash, kind))
+    }
+
+    fn byte(&mut self) -> Result<u8, MPTError> {
+        Ok(self.raw(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, MPTError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("varint is too long"))
+    }
+
+    fn raw(&mut self, len: usize) -> Result<&'a [u8], MPTError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| invalid("truncated MPT encoding"))?;
+        let data = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(data)
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, MPTError> {
+        let len = usize::try_from(self.varint()?).map_err(|_| invalid("length overflow"))?;
+        Ok(self.raw(len)?.to_vec())
+    }
+
+    fn string(&mut self) -> Result<String, MPTError> {
+        String::from_utf8(self.bytes()?).map_err(|_| invalid("string is not valid UTF-8"))
+    }
+
+    fn optional(&mut self) -> Result<Option<Vec<u8>>, MPTError> {
+        match self.byte()? {
+            0 => Ok(None),
+            1 => Ok(Some(self.bytes()?)),
+            flag => Err(invalid(format!("invalid option flag {}", flag))),
+        }
+    }
+
+    fn hash(&mut self) -> Result<[u8; 32], MPTError> {
+        Ok(self.raw(32)?.try_into().unwrap())
+    }
+
+    fn children(&mut self) -> Result<[Option<Vec<u8>>; 16], MPTError> {
+        let bitmap = u16::from_be_bytes(self.raw(2)?.try_into().unwrap());
+        let mut children: [Option<Vec<u8>>; 16] = Default::default();
+        for (i, child) in children.iter_mut().enumerate() {
+            if bitmap & (1 << i) != 0 {
+                *child = Some(self.bytes()?);
+            }
+        }
+        Ok(children)
+    }
+
+    fn finish<T>(self, value: T) -> Result<T, MPTError> {
+        if self.pos != self.data.len() {
+            return Err(invalid("trailing bytes after MPT encoding"));
+        }
+        Ok(value)
+    }
+}
+
+/// 编码叶子或扩展节点
+pub fn encode_short_node(node: &ShortNode) -> Vec<u8> {
+    let kind = if node.is_leaf { LEAF } else { EXTENSION };
+    let mut writer = Writer::new(node.hash_algorithm, kind);
+    writer.raw(&node.node_hash);
+    writer.bytes(node.prefix.as_bytes());
+    writer.bytes(node.suffix.as_bytes());
+    if node.is_leaf {
+        writer.optional(node.value.as_deref());
+    } else {
+        writer.raw(&node.next_node_hash);
+    }
+    writer.0
+}
+
+pub fn decode_short_node(data: &[u8]) -> Result<ShortNode, MPTError> {
+    let (mut reader, hash_algorithm, kind) = Reader::new(data, &[LEAF, EXTENSION])?;
+    let mut node = ShortNode {
+        hash_algorithm,
+        node_hash: reader.hash()?,
+        prefix: reader.string()?,
+        suffix: reader.string()?,
+        is_leaf: kind == LEAF,
+        ..Default::default()
+    };
+    if node.is_leaf {
+        node.value = reader.optional()?;
+    } else {
+        node.next_node_hash = reader.hash()?;
+    }
+    reader.finish(node)
+}
+
+/// 编码分支节点
+pub fn encode_full_node(node: &FullNode) -> Vec<u8> {
+    let mut writer = Writer::new(node.hash_algorithm, BRANCH);
+    writer.raw(&node.node_hash);
+    writer.children(node.children_hash.iter().map(Option::as_deref));
+    writer.optional(node.value.as_deref());
+    writer.0
+}
+
+pub fn decode_full_node(data: &[u8]) -> Result<FullNode, MPTError> {
+    let (mut reader, hash_algorithm, _) = Reader::new(data, &[BRANCH])?;
+    let node = FullNode {
+        hash_algorithm,
+        node_hash: reader.hash()?,
+        children_hash: reader.children()?,
+        value: reader.optional()?,
+        ..Default::default()
+    };
+    reader.finish(node)
+}
+
+/// 编码附带值的单键证明
+pub fn encode_value_proof(proof: &ValueProof) -> Vec<u8> {
+    let mpt_proof = &proof.proof;
+    let mut writer = Writer::new(mpt_proof.hash, VALUE_PROOF);
+    writer.bytes(proof.value.as_bytes());
+    writer.byte(mpt_proof.is_exist as u8);
+    writer.varint(u64::from(mpt_proof.levels));
+    writer.varint(mpt_proof.proofs.len() as u64);
+    for element in &mpt_proof.proofs {
+        writer.varint(u64::from(element.level));
+        writer.byte(element.proof_type);
+        writer.bytes(element.prefix.as_bytes());
+        writer.bytes(element.suffix.as_bytes());
+        writer.bytes(&element.value);
+        writer.bytes(&element.next_node_hash);
+        writer.children(
+            element
+                .children_hashes
+                .iter()
+                .map(|hash| (!hash.is_empty()).then_some(hash.as_slice())),
+        );
+    }
+    writer.0
+}
+
+pub fn decode_value_proof(data: &[u8]) -> Result<ValueProof, MPTError> {
+    let (mut reader, hash, _) = Reader::new(data, &[VALUE_PROOF])?;
+    let value = reader.string()?;
+    let is_exist = reader.byte()? != 0;
+    let levels = u32::try_from(reader.varint()?).map_err(|_| invalid("too many levels"))?;
+    let count = reader.varint()?;
+    let mut proofs = Vec::new();
+    for _ in 0..count {
+        let level = u32::try_from(reader.varint()?).map_err(|_| invalid("level overflow"))?;
+        let proof_type = reader.byte()?;
+        let prefix = reader.string()?;
+        let suffix = reader.string()?;
+        let value = reader.bytes()?;
+        let next_node_hash = reader.bytes()?;
+        let children_hashes = reader.children()?.map(Option::unwrap_or_default);
+        proofs.push(ProofElement::new(
+            level,
+            proof_type,
+            prefix,
+            suffix,
+            value,
+            next_node_hash,
+            children_hashes,
+        ));
+    }
+    let mut proof = MPTProof::new(is_exist, levels, proofs);
+    proof.hash = hash;
+    reader.finish(ValueProof::new(value, proof))
+}
+
+/// 编码范围证明
+pub fn encode_range_proof(proof: &RangeProof) -> Vec<u8> {
+    let mut writer = Writer::new(proof.hash, RANGE_PROOF);
+    encode_range_node(&mut writer, &proof.root);
+    writer.0
+}
+
+fn encode_range_node(writer: &mut Writer, node: &RangeNode) {
+    match node {
+        RangeNode::Empty => writer.byte(0),
+        RangeNode::Pruned(hash) => {
+            writer.byte(1);
+            writer.bytes(hash);
+        }
+        RangeNode::Branch { value, children } => {
+            writer.byte(2);
+            writer.optional(value.as_deref());
+            writer.varint(children.len() as u64);
+            for (index, child) in children {
+                writer.byte(*index);
+                encode_range_node(writer, child);
+            }
+        }
+        RangeNode::Leaf {
+            prefix,
+            suffix,
+            value,
+        } => {
+            writer.byte(3);
+            writer.bytes(prefix.as_bytes());
+            writer.bytes(suffix.as_bytes());
+            writer.optional(value.as_deref());
+        }
+        RangeNode::Extension {
+            prefix,
+            suffix,
+            next,
+        } => {
+            writer.byte(4);
+            writer.bytes(prefix.as_bytes());
+            writer.bytes(suffix.as_bytes());
+            encode_range_node(writer, next);
+        }
+    }
+}
+
+pub fn decode_range_proof(data: &[u8]) -> Result<RangeProof, MPTError> {
+    let (mut reader, hash, _) = Reader::new(data, &[RANGE_PROOF])?;
+    let root = decode_range_node(&mut reader, 0)?;
+    reader.finish(RangeProof { hash, root })
+}
+
+fn decode_range_node(reader: &mut Reader, depth: usize) -> Result<RangeNode, MPTError> {
+    if depth > MAX_RANGE_DEPTH {
+        return Err(invalid("range proof is nested too deeply"));
+    }
+    Ok(match reader.byte()? {
+        0 => RangeNode::Empty,
+        1 => RangeNode::Pruned(reader.bytes()?),
+        2 => {
+            let value = reader.optional()?;
+            let count = reader.varint()?;
+            // 每个子节点至少占两个字节，避免按伪造的数量预留空间
+            let mut children = Vec::new();
+            for _ in 0..count {
+                let index = reader.byte()?;
+                children.push((index, decode_range_node(reader, depth + 1)?));
+            }
+            RangeNode::Branch { value, children }
+        }
+        3 => RangeNode::Leaf {
+            prefix: reader.string()?,
+            suffix: reader.string()?,
+            value: reader.optional()?,
+        },
+        4 => RangeNode::Extension {
+            prefix: reader.string()?,
+            suffix: reader.string()?,
+            next: Box::new(decode_range_node(reader, depth + 1)?),
+        },
+        tag => return Err(invalid(format!("unknown range proof node {}", tag))),
+    })
+}
+
+/// 迁移时待访问的节点
+enum Visit {
+    Full([u8; 32]),
+    Short([u8; 32]),
+}
+
+impl MPT {
+    /// 把数据库中 JSON 编码的节点改写为二进制编码，返回改写的节点数量
+    ///
+    /// 先执行 [`batch_fix`](Self::batch_fix)，再从当前根和历史根索引中记录的根出发遍历可达的节点，
+    /// 在一次批量写入中以原来的键（节点哈希）写入新的编码。节点哈希不变，根哈希和证明不受影响；
+    /// 已经是二进制编码的节点和不可达的旧节点保持不变。历史根在数据库中不完整（已被回收）时跳过
+    pub fn migrate_encoding(&mut self, db: &mut dyn Database) -> Result<usize, MPTError> {
+        self.batch_fix(db)?;
+
+        let mut roots = vec![self.root_hash];
+        // 数据库不支持遍历时没有历史根可查，只迁移当前根下的节点
+        if let Ok(history) = Self::root_history(db) {
+            roots.extend(history.into_iter().map(|(_, root)| root));
+        }
+
+        let mut visited = HashSet::new();
+        let mut batch = WriteBatch::new();
+        for root in roots {
+            if root == [0u8; 32] || db.get(&root)?.is_none() {
+                continue;
+            }
+            let mut stack = vec![Visit::Full(root)];
+            while let Some(visit) = stack.pop() {
+                let (Visit::Full(hash) | Visit::Short(hash)) = visit;
+                if !visited.insert(hash) {
+                    continue;
+                }
+                let Some(data) = db.get(&hash)? else {
+                    continue;
+                };
+                let legacy = is_legacy_json(&data);
+                match visit {
+                    Visit::Full(_) => {
+                        let node = FullNode::deserialize(&data)?;
+                        for child in node.children_hash.iter().flatten() {
+                            let child: [u8; 32] = child
+                                .as_slice()
+                                .try_into()
+                                .map_err(|_| invalid("invalid child hash length"))?;
+                            stack.push(Visit::Short(child));
+                        }
+                        if legacy {
+                            batch.put(&hash, &encode_full_node(&node));
+                        }
+                    }
+                    Visit::Short(_) => {
+                        let node = ShortNode::deserialize(&data)?;
+                        if !node.is_leaf && node.next_node_hash != [0u8; 32] {
+                            stack.push(Visit::Full(node.next_node_hash));
+                        }
+                        if legacy {
+                            batch.put(&hash, &encode_short_node(&node));
+                        }
+                    }
+                }
+            }
+        }
+
+        let migrated = batch.len();
+        if migrated > 0 {
+            db.write_batch(batch)?;
+        }
+        Ok(migrated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpt::db::MemoryDatabase;
+    use crate::mpt::node::{SerializableFullNode, SerializableShortNode};
+    use crate::mpt::proof::compute_mpt_root;
+    use crate::mpt::KVPair;
+
+    fn build(hash: HashAlgorithm, keys: &[&str]) -> (MPT, MemoryDatabase) {
+        let mut db = MemoryDatabase::new();
+        let mut mpt = MPT::new(None).with_hash_algorithm(hash);
+        for key in keys {
+            let kv = KVPair::new(key.to_string(), format!("v-{}", key));
+            mpt.insert(kv, &mut db, true, false).unwrap();
+        }
+        mpt.batch_fix(&mut db).unwrap();
+        (mpt, db)
+    }
+
+    /// 把数据库中所有节点改写为 JSON 编码，模拟旧版本写入的数据库
+    fn downgrade_to_json(db: &mut MemoryDatabase) -> usize {
+        let mut converted = 0;
+        for (key, data) in db.entries().unwrap() {
+            if key.len() != 32 || is_legacy_json(&data) {
+                continue;
+            }
+            let json = if let Ok(node) = decode_full_node(&data) {
+                serde_json::to_vec(&SerializableFullNode {
+                    node_hash: node.node_hash,
+                    children_hash: node.children_hash,
+                    value: node.value,
+                })
+            } else if let Ok(node) = decode_short_node(&data) {
+                serde_json::to_vec(&SerializableShortNode {
+                    node_hash: node.node_hash,
+                    prefix: node.prefix,
+                    is_leaf: node.is_leaf,
+                    suffix: node.suffix,
+                    next_node_hash: node.next_node_hash,
+                    value: node.value,
+                })
+            } else {
+                continue;
+            };
+            db.put(&key, &json.unwrap()).unwrap();
+            converted += 1;
+        }
+        converted
+    }
+
+    #[test]
+    fn test_node_roundtrip() {
+        let leaf = ShortNode {
+            prefix: "6".to_string(),
+            suffix: "1f".to_string(),
+            is_leaf: true,
+            value: Some(vec![]),
+            hash_algorithm: HashAlgorithm::Keccak256,
+            ..Default::default()
+        };
+        let decoded = decode_short_node(&encode_short_node(&leaf)).unwrap();
+        assert_eq!(decoded.hash_algorithm, HashAlgorithm::Keccak256);
+        assert!(decoded.is_leaf);
+        assert_eq!(
+            (decoded.prefix.as_str(), decoded.suffix.as_str()),
+            ("6", "1f")
+        );
+        // 空值（墓碑）与没有值区分开
+        assert_eq!(decoded.value, Some(vec![]));
+
+        let mut branch = FullNode {
+            value: Some(b"v".to_vec()),
+            ..Default::default()
+        };
+        branch.children_hash[3] = Some(vec![7u8; 32]);
+        branch.children_hash[15] = Some(vec![9u8; 32]);
+        branch.update_hash();
+        let encoded = encode_full_node(&branch);
+        let decoded = decode_full_node(&encoded).unwrap();
+        assert_eq!(decoded.node_hash, branch.node_hash);
+        assert_eq!(decoded.children_hash, branch.children_hash);
+        assert_eq!(decoded.value, branch.value);
+        assert!(encoded.len() < serde_json::to_vec(&branch.children_hash).unwrap().len());
+
+        // 截断、类型不符或附加多余字节时拒绝
+        assert!(decode_full_node(&encoded[..encoded.len() - 1]).is_err());
+        assert!(decode_short_node(&encoded).is_err());
+        let mut trailing = encoded.clone();
+        trailing.push(0);
+        assert!(decode_full_node(&trailing).is_err());
+    }
+
+    #[test]
+    fn test_proofs_roundtrip_and_shrink() {
+        for hash in [HashAlgorithm::Sha256, HashAlgorithm::Keccak256] {
+            let (mpt, mut db) = build(hash, &["rust", "rusty", "go", "golang", "python"]);
+
+            let (value, proof) = mpt.query_by_key("rusty", &mut db).unwrap();
+            let proof = ValueProof::new(value, proof);
+            let encoded = proof.to_bytes();
+            assert!(encoded.len() * 2 < serde_json::to_vec(&proof).unwrap().len());
+            let decoded = ValueProof::from_bytes(&encoded).unwrap();
+            assert_eq!(decoded.proof.hash, hash);
+            assert_eq!(decoded.compute_root(), mpt.root_hash);
+            assert!(decoded.binds_key("rusty"));
+
+            let (_, proof) = mpt.prefix_query("go", &mut db).unwrap();
+            let decoded = RangeProof::from_bytes(&proof.to_bytes()).unwrap();
+            assert_eq!(decoded, proof);
+            let (root, pairs) = decoded.compute_prefix_root("go").unwrap();
+            assert_eq!(root, mpt.root_hash);
+            assert_eq!(pairs.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_legacy_json_proofs_decode() {
+        let (mpt, mut db) = build(HashAlgorithm::Sha256, &["a", "b"]);
+        let (value, proof) = mpt.query_by_key("a", &mut db).unwrap();
+        let json = serde_json::to_vec(&ValueProof::new(value, proof)).unwrap();
+        let decoded = ValueProof::from_bytes(&json).unwrap();
+        assert_eq!(
+            compute_mpt_root(&decoded.value, &decoded.proof),
+            mpt.root_hash
+        );
+
+        let (_, proof) = mpt.range_query("", "", &mut db).unwrap();
+        let json = serde_json::to_vec(&proof.root).unwrap();
+        assert_eq!(RangeProof::from_bytes(&json).unwrap(), proof);
+    }
+
+    #[test]
+    fn test_keccak_trie_reloads_with_its_algorithm() {
+        let keys = ["kw1", "kw10", "kw2"];
+        let (mpt, mut db) = build(HashAlgorithm::Keccak256, &keys);
+        let (sha, _) = build(HashAlgorithm::Sha256, &keys);
+        assert_ne!(mpt.root_hash, sha.root_hash);
+
+        let (value, proof) = mpt.query_by_key("kw10", &mut db).unwrap();
+        assert_eq!(proof.hash, HashAlgorithm::Keccak256);
+        assert!(mpt.verify_query_result(&value, &proof));
+
+        // 节点编码中记录了算法，从数据库加载的树继续使用 Keccak-256
+        let mut loaded = MPT::load_from_db(&mpt.root_hash, &mut db, None).unwrap();
+        assert_eq!(loaded.hash_algorithm, HashAlgorithm::Keccak256);
+        let kv = KVPair::new("kw3".to_string(), "v-kw3".to_string());
+        loaded.insert(kv, &mut db, true, false).unwrap();
+        loaded.batch_fix(&mut db).unwrap();
+        let (expected, _) = build(HashAlgorithm::Keccak256, &["kw1", "kw10", "kw2", "kw3"]);
+        assert_eq!(loaded.root_hash, expected.root_hash);
+    }
+
+    #[test]
+    fn test_migrate_json_nodes() {
+        let keys = ["category", "cat", "catalog", "dog", "category:art"];
+        let (mut mpt, mut db) = build(HashAlgorithm::Sha256, &keys);
+        let root = mpt.root_hash;
+        let converted = downgrade_to_json(&mut db);
+        assert!(converted > 0);
+
+        // JSON 编码的数据库仍然可以加载和查询
+        let mut legacy = MPT::load_from_db(&root, &mut db, None).unwrap();
+        let (value, proof) = legacy.query_by_key("catalog", &mut db).unwrap();
+        assert_eq!(value, "v-catalog");
+        assert!(legacy.verify_query_result(&value, &proof));
+
+        // 只改写可达的节点，插入过程中写入的旧版本节点保持不变
+        let migrated = legacy.migrate_encoding(&mut db).unwrap();
+        assert!(migrated > 0 && migrated <= converted);
+        assert_eq!(legacy.migrate_encoding(&mut db).unwrap(), 0);
+
+        mpt = MPT::load_from_db(&root, &mut db, None).unwrap();
+        assert_eq!(mpt.root_hash, root);
+        for key in keys {
+            let (value, proof) = mpt.query_by_key(key, &mut db).unwrap();
+            assert_eq!(value, format!("v-{}", key));
+            assert!(mpt.verify_query_result(&value, &proof));
+        }
+    }
+}
@@ -1,74 +1,143 @@
 use super::error::MPTError;
 use super::node::Database;
-use rocksdb::{Options, DB};
+use rocksdb::{IteratorMode, Options, DB};
 use std::path::Path;
+use std::sync::Arc;
+use storage_backend::{BatchOp, ColumnDb, ColumnStore, DbIter, WriteBatch};
 
+pub use storage_backend::{Column, DbError, MemoryDatabase};
+
+fn db_error(e: rocksdb::Error) -> DbError {
+    DbError::Backend(e.to_string())
+}
+
+/// RocksDB 数据库
+///
+/// 打开时创建缺少的列族；[`Database`] 实现读写默认列族，
+/// 各列族通过 [`column`](Self::column) 访问。克隆共享同一个数据库实例
+#[derive(Clone)]
 pub struct RocksDbAdapter {
-    db: DB,
+    db: Arc<DB>,
 }
 
 impl RocksDbAdapter {
     pub fn open(path: &Path) -> Result<Self, MPTError> {
         let mut opts = Options::default();
         opts.create_if_missing(true);
-        let db = DB::open(&opts, path).map_err(|e| MPTError::DatabaseError(e.to_string()))?;
-        Ok(Self { db })
+        opts.create_missing_column_families(true);
+        let columns = Column::ALL.map(Column::name);
+        let db = DB::open_cf(&opts, path, columns)
+            .map_err(|e| MPTError::DatabaseError(e.to_string()))?;
+        Ok(Self { db: Arc::new(db) })
+    }
+
+    /// 访问一个列族
+    pub fn column(&self, column: Column) -> RocksColumn {
+        RocksColumn {
+            db: self.db.clone(),
+            column,
+        }
+    }
+}
+
+impl ColumnStore for RocksDbAdapter {
+    fn column(&self, column: Column) -> Result<ColumnDb, DbError> {
+        Ok(Box::new(RocksDbAdapter::column(self, column)))
     }
 }
 
 impl Database for RocksDbAdapter {
-    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, MPTError> {
-        match self.db.get(key) {
-            Ok(Some(v)) => Ok(Some(v)),
-            Ok(None) => Ok(None),
-            Err(e) => Err(MPTError::DatabaseError(e.to_string())),
-        }
+    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError> {
+        self.db.get(key).map_err(db_error)
     }
 
-    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), MPTError> {
-        self.db
-            .put(key, value)
-            .map_err(|e| MPTError::DatabaseError(e.to_string()))
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
+        self.db.put(key, value).map_err(db_error)
     }
 
-    fn delete(&mut self, key: &[u8]) -> Result<(), MPTError> {
-        self.db
-            .delete(key)
-            .map_err(|e| MPTError::DatabaseError(e.to_string()))
+    fn delete(&mut self, key: &[u8]) -> Result<(), DbError> {
+        self.db.delete(key).map_err(db_error)
     }
 }
 
-/// 内存数据库（用于测试）
-pub struct MemoryDatabase {
-    data: std::collections::HashMap<Vec<u8>, Vec<u8>>,
+/// RocksDB 中的一个列族
+#[derive(Clone)]
+pub struct RocksColumn {
+    db: Arc<DB>,
+    column: Column,
 }
 
-impl MemoryDatabase {
-    pub fn new() -> Self {
-        Self {
-            data: std::collections::HashMap::new(),
-        }
+impl RocksColumn {
+    fn handle(&self) -> Result<&rocksdb::ColumnFamily, DbError> {
+        self.db.cf_handle(self.column.name()).ok_or_else(|| {
+            DbError::Backend(format!("missing column family '{}'", self.column.name()))
+        })
     }
 }
 
-impl Default for MemoryDatabase {
-    fn default() -> Self {
-        Self::new()
+impl Database for RocksColumn {
+    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError> {
+        self.db.get_cf(&self.handle()?, key).map_err(db_error)
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
+        self.db
+            .put_cf(&self.handle()?, key, value)
+            .map_err(db_error)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<(), DbError> {
+        self.db.delete_cf(&self.handle()?, key).map_err(db_error)
     }
-}
 
-impl Database for MemoryDatabase {
-    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, MPTError> {
-        Ok(self.data.get(key).cloned())
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<(), DbError> {
+        let handle = self.handle()?;
+        let mut rocks_batch = rocksdb::WriteBatch::default();
+        for op in batch.into_ops() {
+            match op {
+                BatchOp::Put(key, value) => rocks_batch.put_cf(&handle, key, value),
+                BatchOp::Delete(key) => rocks_batch.delete_cf(&handle, key),
+            }
+        }
+        self.db.write(rocks_batch).map_err(db_error)
     }
 
-    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), MPTError> {
-        self.data.insert(key.to_vec(), value.to_vec());
-        Ok(())
+    fn iter(&mut self) -> Result<DbIter<'_>, DbError> {
+        let entries = self.db.iterator_cf(&self.handle()?, IteratorMode::Start);
+        Ok(Box::new(entries.map(|entry| {
+            entry
+                .map(|(key, value)| (key.to_vec(), value.to_vec()))
+                .map_err(db_error)
+        })))
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columns_are_separate_and_persistent() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let db = RocksDbAdapter::open(dir.path()).unwrap();
+            let mut nodes = db.column(Column::Nodes);
+            let mut wal = db.column(Column::Wal);
+            nodes.put(b"key", b"node").unwrap();
+            wal.put(&2u64.to_be_bytes(), b"second").unwrap();
+            wal.put(&1u64.to_be_bytes(), b"first").unwrap();
+            assert_eq!(db.column(Column::Metadata).get(b"key").unwrap(), None);
+        }
 
-    fn delete(&mut self, key: &[u8]) -> Result<(), MPTError> {
-        self.data.remove(key);
-        Ok(())
+        let db = RocksDbAdapter::open(dir.path()).unwrap();
+        assert_eq!(
+            db.column(Column::Nodes).get(b"key").unwrap(),
+            Some(b"node".to_vec())
+        );
+        let mut wal = db.column(Column::Wal);
+        let records: Vec<Vec<u8>> = wal.entries().unwrap().into_iter().map(|(_, v)| v).collect();
+        assert_eq!(records, vec![b"first".to_vec(), b"second".to_vec()]);
+        wal.clear().unwrap();
+        assert!(wal.entries().unwrap().is_empty());
     }
 }
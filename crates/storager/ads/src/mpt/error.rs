@@ -25,4 +25,10 @@ pub enum MPTError {
     
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
-}
\ No newline at end of file
+}
+
+impl From<storage_backend::DbError> for MPTError {
+    fn from(e: storage_backend::DbError) -> Self {
+        MPTError::DatabaseError(e.to_string())
+    }
+}
@@ -0,0 +1,145 @@
+//! MPT 节点哈希函数
+//!
+//! 默认使用 SHA-256；也可以选择以太坊 trie 使用的 Keccak-256。同一棵树的节点使用同一种哈希，
+//! 算法记录在每个节点的二进制编码（见 [`codec`](super::codec)）和证明中，
+//! 验证方按证明中的算法重算根哈希。两种算法得到的根哈希不同，已有的树不能原地切换算法，
+//! 需要把键值对重新插入一棵新树。
+//!
+//! 数据库中 MPT 索引的键（根哈希的哈希）与节点哈希无关，始终使用 SHA-256。
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+use std::fmt;
+use std::str::FromStr;
+
+/// 节点哈希算法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    Keccak256,
+}
+
+impl HashAlgorithm {
+    /// 创建该算法的增量哈希器
+    pub fn hasher(self) -> NodeHasher {
+        match self {
+            HashAlgorithm::Sha256 => NodeHasher::Sha256(Sha256::new()),
+            HashAlgorithm::Keccak256 => NodeHasher::Keccak256(Keccak256::new()),
+        }
+    }
+
+    /// 计算 `data` 的哈希
+    pub fn digest(self, data: &[u8]) -> [u8; 32] {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Keccak256 => "keccak256",
+        }
+    }
+
+    /// 二进制编码中表示算法的字节
+    pub(crate) fn code(self) -> u8 {
+        match self {
+            HashAlgorithm::Sha256 => 0,
+            HashAlgorithm::Keccak256 => 1,
+        }
+    }
+
+    pub(crate) fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(HashAlgorithm::Sha256),
+            1 => Some(HashAlgorithm::Keccak256),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sha256" | "sha-256" => Ok(HashAlgorithm::Sha256),
+            "keccak256" | "keccak-256" | "keccak" => Ok(HashAlgorithm::Keccak256),
+            _ => Err(format!(
+                "unknown hash algorithm '{}' (expected sha256 or keccak256)",
+                s
+            )),
+        }
+    }
+}
+
+/// [`HashAlgorithm::hasher`] 创建的增量哈希器
+///
+/// 只在计算一个哈希的过程中存在于栈上，不必为 Keccak 较大的状态单独分配
+#[allow(clippy::large_enum_variant)]
+pub enum NodeHasher {
+    Sha256(Sha256),
+    Keccak256(Keccak256),
+}
+
+impl NodeHasher {
+    pub fn update(&mut self, data: impl AsRef<[u8]>) {
+        match self {
+            NodeHasher::Sha256(hasher) => hasher.update(data),
+            NodeHasher::Keccak256(hasher) => hasher.update(data),
+        }
+    }
+
+    pub fn finalize(self) -> [u8; 32] {
+        match self {
+            NodeHasher::Sha256(hasher) => hasher.finalize().into(),
+            NodeHasher::Keccak256(hasher) => hasher.finalize().into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_digests() {
+        assert_eq!(
+            hex::encode(HashAlgorithm::Sha256.digest(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex::encode(HashAlgorithm::Keccak256.digest(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+
+        // 增量输入与一次输入结果相同
+        let mut hasher = HashAlgorithm::Keccak256.hasher();
+        hasher.update(b"hello ");
+        hasher.update(b"world");
+        assert_eq!(
+            hasher.finalize(),
+            HashAlgorithm::Keccak256.digest(b"hello world")
+        );
+    }
+
+    #[test]
+    fn test_parse_and_codes() {
+        for algorithm in [HashAlgorithm::Sha256, HashAlgorithm::Keccak256] {
+            assert_eq!(algorithm.name().parse::<HashAlgorithm>(), Ok(algorithm));
+            assert_eq!(HashAlgorithm::from_code(algorithm.code()), Some(algorithm));
+        }
+        assert_eq!("Keccak".parse(), Ok(HashAlgorithm::Keccak256));
+        assert!("md5".parse::<HashAlgorithm>().is_err());
+        assert_eq!(HashAlgorithm::from_code(7), None);
+    }
+}
@@ -0,0 +1,142 @@
+//! 历史版本查询
+//!
+//! 节点以哈希为键写入数据库后不会被修改，因此只要旧根下的节点没有被回收，
+//! 就可以从旧根重新加载整棵树并生成针对旧根的证明。
+//! 每次 batch_fix 完成时记录一条 (版本号, 根哈希)，客户端据此选择要审计的历史时刻。
+
+use super::error::MPTError;
+use super::mpt::MPT;
+use super::node::{Database, WriteBatch};
+use super::proof::MPTProof;
+use super::snapshot::MptSnapshot;
+
+/// 保存最新版本号的键
+pub(crate) const EPOCH_KEY: &[u8] = b"mpt:epoch";
+
+/// 历史根索引的键前缀，后接大端序的版本号，值为该版本的根哈希
+pub(crate) const HISTORY_PREFIX: &[u8] = b"mpt:history:";
+
+/// 版本号对应的历史根索引键
+pub(crate) fn history_key(epoch: u64) -> Vec<u8> {
+    [HISTORY_PREFIX, &epoch.to_be_bytes()].concat()
+}
+
+impl MPT {
+    /// 为本次修复分配新的版本号，并把当前根加入历史索引
+    pub(crate) fn record_root(&mut self, batch: &mut WriteBatch) {
+        self.epoch += 1;
+        batch.put(EPOCH_KEY, &self.epoch.to_be_bytes());
+        batch.put(&history_key(self.epoch), &self.root_hash);
+    }
+
+    /// 读取数据库中记录的最新版本号，没有记录时为 0
+    pub(crate) fn stored_epoch(db: &mut dyn Database) -> Result<u64, MPTError> {
+        match db.get(EPOCH_KEY)? {
+            Some(data) => {
+                let bytes: [u8; 8] = data.as_slice().try_into().map_err(|_| {
+                    MPTError::InvalidData(format!("Invalid epoch length: {}", data.len()))
+                })?;
+                Ok(u64::from_be_bytes(bytes))
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// 按版本号升序列出历史根 (版本号, 根哈希)
+    ///
+    /// 数据库必须支持遍历（[`Database::iter`]）
+    pub fn root_history(db: &mut dyn Database) -> Result<Vec<(u64, [u8; 32])>, MPTError> {
+        let mut history = Vec::new();
+        for (key, value) in db.entries()? {
+            let Some(epoch) = key.strip_prefix(HISTORY_PREFIX) else {
+                continue;
+            };
+            let epoch: [u8; 8] = epoch
+                .try_into()
+                .map_err(|_| MPTError::InvalidData("Invalid history key".to_string()))?;
+            let root: [u8; 32] = value
+                .as_slice()
+                .try_into()
+                .map_err(|_| MPTError::InvalidData("Invalid history root hash".to_string()))?;
+            history.push((u64::from_be_bytes(epoch), root));
+        }
+        Ok(history)
+    }
+
+    /// 针对指定根哈希查询键，返回值和相对该根的证明
+    ///
+    /// 当前根直接在内存中的树上查询（可能包含尚未修复的节点）；
+    /// 其他根从数据库重新加载，根节点不存在（从未落盘或已被回收）时返回 [`MPTError::NodeNotFound`]
+    ///
+    /// # Arguments
+    /// * `root_hash` - 要查询的版本的根哈希
+    /// * `key` - 查询的键
+    /// * `db` - 节点数据库
+    pub fn query_at_root(
+        &self,
+        root_hash: &[u8; 32],
+        key: &str,
+        db: &mut dyn Database,
+    ) -> Result<(String, MPTProof), MPTError> {
+        if root_hash == &self.root_hash {
+            return self.query_by_key(key, db);
+        }
+        MptSnapshot::at_root(*root_hash, db)?.query_by_key(key, db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpt::db::MemoryDatabase;
+    use crate::mpt::proof::compute_mpt_root;
+    use crate::mpt::KVPair;
+
+    fn insert(mpt: &mut MPT, db: &mut MemoryDatabase, key: &str, value: &str) {
+        let kv = KVPair::new(key.to_string(), value.to_string());
+        mpt.insert(kv, db, true, false).unwrap();
+        mpt.batch_fix(db).unwrap();
+    }
+
+    #[test]
+    fn test_query_at_past_roots() {
+        let mut db = MemoryDatabase::new();
+        let mut mpt = MPT::new(None);
+        insert(&mut mpt, &mut db, "rust", "f1");
+        insert(&mut mpt, &mut db, "go", "f2");
+        insert(&mut mpt, &mut db, "rust", "f1,f3");
+
+        let history = MPT::root_history(&mut db).unwrap();
+        assert_eq!(
+            history.iter().map(|(epoch, _)| *epoch).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(history[2].1, mpt.root_hash);
+
+        let expected = [("f1", ""), ("f1", "f2"), ("f1,f3", "f2")];
+        for ((_, root), (rust, go)) in history.iter().zip(expected) {
+            for (key, value) in [("rust", rust), ("go", go)] {
+                let (found, proof) = mpt.query_at_root(root, key, &mut db).unwrap();
+                assert_eq!(found, value, "key {}", key);
+                assert_eq!(compute_mpt_root(&found, &proof), *root);
+            }
+        }
+        assert!(matches!(
+            mpt.query_at_root(&[9u8; 32], "rust", &mut db),
+            Err(MPTError::NodeNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_epoch_survives_reload() {
+        let mut db = MemoryDatabase::new();
+        let mut mpt = MPT::new(None);
+        insert(&mut mpt, &mut db, "rust", "f1");
+        mpt.persist_to_db(&mut db).unwrap();
+
+        let mut restored = MPT::restore_from_db(&mut db, None).unwrap();
+        assert_eq!(restored.epoch, 1);
+        insert(&mut restored, &mut db, "go", "f2");
+        assert_eq!(MPT::root_history(&mut db).unwrap().len(), 2);
+    }
+}
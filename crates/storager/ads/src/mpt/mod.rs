@@ -1,15 +1,39 @@
+//! Merkle Patricia Trie
+//!
+//! ## 并发模型
+//!
+//! 节点以 `Arc<RwLock<..>>` 相互引用（带父指针），插入和删除原地修改路径上的节点并标记为脏，
+//! 之后由 [`MPT::batch_fix`] 或 [`SlicedFix`] 重新计算哈希。查询只需要 `&MPT`，
+//! 使用方（storager 的 `MptAds`）用外层读写锁让查询并发、写入串行；
+//! 长时间的只读访问使用 [`MptSnapshot`]，它从节点数据库加载固定根下的节点，不与活动树共享节点。
+//!
+//! 改为不可变节点加原子根切换（路径复制、读者无锁）的方案没有实现：范围证明、分片修复、
+//! 回收和根历史都建立在可变节点和父指针之上，需要连同它们一起重写，目前不在计划范围内。
+
+pub mod codec;
 pub mod db;
 pub mod error;
+pub mod hasher;
+pub mod history;
 pub mod mpt;
 pub mod node;
 pub mod proof;
+pub mod prune;
+pub mod range;
+pub mod sliced_fix;
+pub mod snapshot;
 pub mod utils;
 
-pub use db::RocksDbAdapter;
+pub use db::{Column, DbError, RocksColumn, RocksDbAdapter};
 pub use error::MPTError;
+pub use hasher::HashAlgorithm;
 pub use mpt::MPT;
 pub use node::{FullNode, ShortNode, NodeCache};
-pub use proof::{MPTProof, ProofElement};
+pub use proof::{MPTProof, ProofElement, ValueProof};
+pub use prune::PruneStats;
+pub use range::{RangeNode, RangeProof};
+pub use sliced_fix::{SliceMetrics, SlicedFix};
+pub use snapshot::MptSnapshot;
 pub use utils::KVPair;
 
 #[cfg(test)]
@@ -1,28 +1,50 @@
 use super::error::MPTError;
-use super::node::{Database, FullNode, NodeCache, ShortNode};
+use super::hasher::HashAlgorithm;
+use super::node::{Database, FullNode, NodeCache, ShortNode, WriteBatch};
 use super::proof::{MPTProof, ProofElement};
-use super::utils::{byte_to_hex_index, common_prefix_len, key_to_hex_path, KVPair};
+use super::utils::{
+    byte_to_hex_index, common_prefix_len, key_to_hex_path, nibbles_to_suffix, KVPair,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex, RwLock};
 
+/// 元数据中的节点编码版本：1 为 JSON 编码，2 为二进制编码（见 [`codec`](super::codec)）
+pub const NODE_FORMAT_VERSION: u32 = 2;
+
 /// MPT 元数据,用于持久化和恢复
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MPTMetadata {
     pub root_hash: [u8; 32],
+    /// 节点编码版本，低于 [`NODE_FORMAT_VERSION`] 时恢复会迁移节点编码
     pub version: u32,
+    /// 持久化时的墙上时间（UNIX 秒，仅供参考）
     pub timestamp: u64,
+    /// 持久化序号，每次持久化加一（旧格式没有该字段，按 0 处理）
+    #[serde(default)]
+    pub sequence: u64,
+    /// 节点哈希算法（旧格式没有该字段，按 SHA-256 处理）
+    #[serde(default)]
+    pub hash_algorithm: HashAlgorithm,
 }
 
 impl MPTMetadata {
     pub fn new(root_hash: [u8; 32]) -> Self {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        Self::at(root_hash, timestamp, 0)
+    }
+
+    /// 使用调用方时钟提供的时间戳和序号创建
+    pub fn at(root_hash: [u8; 32], timestamp: u64, sequence: u64) -> Self {
         Self {
             root_hash,
-            version: 1,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            version: NODE_FORMAT_VERSION,
+            timestamp,
+            sequence,
+            hash_algorithm: HashAlgorithm::default(),
         }
     }
 }
@@ -33,6 +55,8 @@ pub struct MPT {
     pub cache: Option<Mutex<NodeCache>>, // 与 Go 的 *[]interface{} 类似地封装两个 LRU
     pub latch: Arc<RwLock<()>>,          // 用于根节点重构的读写锁
     pub update_latch: Arc<Mutex<()>>,    // 用于更新操作的互斥锁
+    pub epoch: u64,                      // 已记录的历史根版本号，每次修复加一
+    pub hash_algorithm: HashAlgorithm,   // 新建节点使用的哈希算法
 }
 
 impl Default for MPT {
@@ -43,6 +67,8 @@ impl Default for MPT {
             cache: None,
             latch: Arc::new(RwLock::new(())),
             update_latch: Arc::new(Mutex::new(())),
+            epoch: 0,
+            hash_algorithm: HashAlgorithm::default(),
         }
     }
 }
@@ -55,9 +81,17 @@ impl MPT {
             cache: cache.map(Mutex::new),
             latch: Arc::new(RwLock::new(())),
             update_latch: Arc::new(Mutex::new(())),
+            epoch: 0,
+            hash_algorithm: HashAlgorithm::default(),
         }
     }
 
+    /// 设置节点哈希算法，只能用于空树：已有节点的哈希不会重新计算
+    pub fn with_hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = hash_algorithm;
+        self
+    }
+
     /// 获取根节点，如为空则尝试从 DB 以 root_hash 读取
     /// 使用 TryLock 确保只有一个线程进行重构
     pub fn get_root(
@@ -118,7 +152,13 @@ impl MPT {
             // 创建新的根节点
             let children: [Option<Arc<RwLock<ShortNode>>>; 16] = Default::default();
             let mut cache_opt = self.cache.as_ref().map(|m| m.lock().ok()).flatten();
-            let node = FullNode::new(children, None, db, cache_opt.as_deref_mut())?;
+            let node = FullNode::new(
+                children,
+                None,
+                self.hash_algorithm,
+                db,
+                cache_opt.as_deref_mut(),
+            )?;
             self.root = Some(node.clone());
             return Ok(node);
         } else {
@@ -145,9 +185,29 @@ impl MPT {
         db: &mut dyn Database,
         is_primary: bool,
         flag: bool,
+    ) -> Result<(String, bool), MPTError> {
+        self.insert_bytes(
+            kv.get_key().as_bytes(),
+            kv.get_value(),
+            db,
+            is_primary,
+            flag,
+        )
+    }
+
+    /// 插入任意字节键
+    ///
+    /// 键按半字节路径存储，不经过字符串转换，非 UTF-8 的键也能原样查询、删除和证明
+    pub fn insert_bytes(
+        &mut self,
+        key: &[u8],
+        value: &str,
+        db: &mut dyn Database,
+        is_primary: bool,
+        flag: bool,
     ) -> Result<(String, bool), MPTError> {
         // 将键转换为十六进制路径
-        let key_path = key_to_hex_path(kv.get_key());
+        let key_path = key_to_hex_path(key);
 
         // 确保根节点存在
         let root = self.ensure_root(db)?;
@@ -156,7 +216,7 @@ impl MPT {
         let (old_value, need_delete) = self.recursive_insert_full_node(
             &key_path,
             0, // 当前路径位置
-            kv.get_value().as_bytes().to_vec(),
+            value.as_bytes().to_vec(),
             root.clone(),
             db,
             is_primary,
@@ -170,7 +230,18 @@ impl MPT {
     }
 
     /// 删除键值对
+    ///
+    /// 删除后只剩一项的分支会与上层的 Extension 节点合并，树的形状与直接插入剩余键得到的相同
     pub fn delete(&mut self, key: &str, db: &mut dyn Database) -> Result<Option<String>, MPTError> {
+        self.delete_bytes(key.as_bytes(), db)
+    }
+
+    /// 删除任意字节键，见 [`MPT::insert_bytes`]
+    pub fn delete_bytes(
+        &mut self,
+        key: &[u8],
+        db: &mut dyn Database,
+    ) -> Result<Option<String>, MPTError> {
         // 如果MPT为空，直接返回None
         if self.root.is_none() {
             return Ok(None);
@@ -245,6 +316,16 @@ impl MPT {
             .write()
             .map_err(|_| MPTError::LockError("Failed to write FullNode".to_string()))?;
 
+        // 辅助索引的延迟删除记录在键路径上最深的已有节点，结构变化后可能留在路径上更高的节点，
+        // 因此插入时沿路径逐个节点检查
+        let key_str = String::from_utf8_lossy(key_path).to_string();
+        let value_str = String::from_utf8_lossy(&value).to_string();
+        if !is_primary && !flag && take_pending_delete(&mut guard.to_del_map, &key_str, &value_str)
+        {
+            // 插入和删除相互抵消
+            return Ok((String::new(), false));
+        }
+
         // 如果已经到达键的末尾，将值存储在当前 FullNode
         if pos >= key_path.len() {
             let old_value_str = if let Some(ref old_val) = guard.value {
@@ -258,50 +339,20 @@ impl MPT {
 
             if !is_primary {
                 // 辅助索引: 需要追加或删除值
-                let key_str = String::from_utf8_lossy(key_path).to_string();
-                let value_str = String::from_utf8_lossy(&value).to_string();
                 let mut kv = KVPair::new(key_str.clone(), old_value_str.clone());
 
                 if flag {
                     // 删除模式
                     is_change = kv.del_value(&value_str);
                 } else {
-                    // 插入模式: 先检查延迟删除
-                    let to_del_count = *guard
-                        .to_del_map
-                        .get(&key_str)
-                        .and_then(|m| m.get(&value_str))
-                        .unwrap_or(&0);
-
-                    if to_del_count > 0 {
-                        // 有延迟删除记录,抵消
-                        if let Some(inner_map) = guard.to_del_map.get_mut(&key_str) {
-                            if let Some(count) = inner_map.get_mut(&value_str) {
-                                *count -= 1;
-                                if *count == 0 {
-                                    inner_map.remove(&value_str);
-                                }
-                            }
-                            if inner_map.is_empty() {
-                                guard.to_del_map.remove(&key_str);
-                            }
-                        }
-                        return Ok((String::new(), false));
-                    }
-
+                    // 插入模式（延迟删除已在上面检查）
                     is_change = kv.add_value(&value_str);
                 }
 
                 if !is_change {
                     if flag {
                         // 删除操作但找不到值,记录延迟删除
-                        guard
-                            .to_del_map
-                            .entry(key_str.clone())
-                            .or_insert_with(HashMap::new)
-                            .entry(value_str.clone())
-                            .and_modify(|c| *c += 1)
-                            .or_insert(1);
+                        record_pending_delete(&mut guard.to_del_map, &key_str, &value_str);
                     }
                     return Ok((String::new(), false));
                 }
@@ -314,6 +365,8 @@ impl MPT {
 
             guard.value = Some(final_value.clone());
             guard.is_dirty = true;
+            // 值存放在 FullNode 自身，需要立即重算哈希，上层节点才能发现变化
+            guard.update_hash();
 
             // 主索引模式下返回旧值,辅助索引模式返回空字符串
             if is_primary {
@@ -329,64 +382,24 @@ impl MPT {
         // 获取当前路径索引
         let index = byte_to_hex_index(key_path[pos]);
 
+        // 从数据库加载的树中子节点只有哈希，先按需加载
+        guard.get_child(index, db, None)?;
+
         // 如果对应索引的子节点不存在
         if guard.children[index].is_none() {
             // 如果是辅助索引的删除操作,记录延迟删除而不是创建节点
             if !is_primary && flag {
-                let key_str = String::from_utf8_lossy(key_path).to_string();
-                let value_str = String::from_utf8_lossy(&value).to_string();
-
-                guard
-                    .to_del_map
-                    .entry(key_str.clone())
-                    .or_insert_with(HashMap::new)
-                    .entry(value_str.clone())
-                    .and_modify(|c| *c += 1)
-                    .or_insert(1);
-
+                record_pending_delete(&mut guard.to_del_map, &key_str, &value_str);
                 return Ok((String::new(), false));
             }
 
-            // 如果是辅助索引的插入操作,先检查是否有延迟删除需要抵消
-            if !is_primary {
-                let key_str = String::from_utf8_lossy(key_path).to_string();
-                let value_str = String::from_utf8_lossy(&value).to_string();
-
-                let to_del_count = guard
-                    .to_del_map
-                    .get(&key_str)
-                    .and_then(|m| m.get(&value_str))
-                    .copied()
-                    .unwrap_or(0);
-
-                if to_del_count > 0 {
-                    // 有延迟删除记录,抵消
-                    if let Some(inner_map) = guard.to_del_map.get_mut(&key_str) {
-                        if let Some(count) = inner_map.get_mut(&value_str) {
-                            *count -= 1;
-                            if *count == 0 {
-                                inner_map.remove(&value_str);
-                            }
-                        }
-                        if inner_map.is_empty() {
-                            guard.to_del_map.remove(&key_str);
-                        }
-                    }
-                    // 插入和删除相互抵消,不创建节点
-                    return Ok((String::new(), false));
-                }
-            }
-
             // 创建新的叶子节点
             let remaining_path = &key_path[pos + 1..];
             let suffix = if remaining_path.is_empty() {
                 String::new()
             } else {
                 // 将剩余路径转换为字符串表示（用于调试）
-                remaining_path
-                    .iter()
-                    .map(|&b| format!("{:x}", b))
-                    .collect::<String>()
+                nibbles_to_suffix(remaining_path)
             };
 
             let leaf_node = ShortNode::new(
@@ -395,6 +408,7 @@ impl MPT {
                 suffix.clone(),
                 None, // next_node
                 Some(value.clone()),
+                self.hash_algorithm,
                 db,
                 None, // cache暂不使用
             )?;
@@ -440,15 +454,17 @@ impl MPT {
             .write()
             .map_err(|_| MPTError::LockError("Failed to write ShortNode".to_string()))?;
 
+        if !is_primary
+            && !flag
+            && take_pending_delete(&mut child_guard.to_del_map, &key_str, &value_str)
+        {
+            return Ok((String::new(), false));
+        }
+
         if child_guard.is_leaf {
             // 如果是叶子节点，需要检查键是否匹配
             let current_key_suffix: Vec<u8> = key_path[pos + 1..].to_vec();
-            let stored_suffix: Vec<u8> = child_guard
-                .suffix
-                .chars()
-                .filter_map(|c| c.to_digit(16))
-                .map(|d| d as u8)
-                .collect();
+            let stored_suffix: Vec<u8> = child_guard.suffix_nibbles()?;
 
             if current_key_suffix == stored_suffix {
                 // 键完全匹配，根据 is_primary 决定是覆盖还是追加值
@@ -463,40 +479,13 @@ impl MPT {
 
                 if !is_primary {
                     // 辅助索引: 需要追加或删除值
-                    let key_str = String::from_utf8_lossy(key_path).to_string();
-                    let value_str = String::from_utf8_lossy(&value).to_string();
                     let mut kv = KVPair::new(key_str.clone(), old_value_str.clone());
 
                     if flag {
                         // 删除模式: 从值列表中移除
                         is_change = kv.del_value(&value_str);
                     } else {
-                        // 插入模式: 先检查延迟删除
-                        let to_del_count = child_guard
-                            .to_del_map
-                            .get(&key_str)
-                            .and_then(|m| m.get(&value_str))
-                            .copied()
-                            .unwrap_or(0);
-
-                        if to_del_count > 0 {
-                            // 有延迟删除记录,抵消
-                            if let Some(inner_map) = child_guard.to_del_map.get_mut(&key_str) {
-                                if let Some(count) = inner_map.get_mut(&value_str) {
-                                    *count -= 1;
-                                    if *count == 0 {
-                                        inner_map.remove(&value_str);
-                                    }
-                                }
-                                if inner_map.is_empty() {
-                                    child_guard.to_del_map.remove(&key_str);
-                                }
-                            }
-                            // 插入和删除相互抵消,不做任何改变
-                            return Ok((String::new(), false));
-                        }
-
-                        // 正常追加值
+                        // 插入模式（延迟删除已在上面检查）
                         is_change = kv.add_value(&value_str);
                     }
 
@@ -504,13 +493,11 @@ impl MPT {
                         // 值没有变化
                         if flag {
                             // 删除操作但找不到值,记录延迟删除
-                            child_guard
-                                .to_del_map
-                                .entry(key_str.clone())
-                                .or_insert_with(HashMap::new)
-                                .entry(value_str.clone())
-                                .and_modify(|c| *c += 1)
-                                .or_insert(1);
+                            record_pending_delete(
+                                &mut child_guard.to_del_map,
+                                &key_str,
+                                &value_str,
+                            );
                         }
                         return Ok((String::new(), false));
                     }
@@ -555,54 +542,37 @@ impl MPT {
                 // 键不匹配，需要进行节点分裂
                 // 如果是辅助索引的删除操作,记录延迟删除
                 if !is_primary && flag {
-                    let key_str = String::from_utf8_lossy(key_path).to_string();
-                    let value_str = String::from_utf8_lossy(&value).to_string();
-
-                    child_guard
-                        .to_del_map
-                        .entry(key_str.clone())
-                        .or_insert_with(HashMap::new)
-                        .entry(value_str.clone())
-                        .and_modify(|c| *c += 1)
-                        .or_insert(1);
-
+                    record_pending_delete(&mut child_guard.to_del_map, &key_str, &value_str);
                     return Ok((String::new(), false));
                 }
 
-                let old_value = if let Some(ref old_val) = child_guard.value {
-                    String::from_utf8_lossy(old_val).to_string()
-                } else {
-                    String::new()
-                };
-
-                let had_old_value = !old_value.is_empty();
-
                 // 计算公共前缀长度
                 let common_len = common_prefix_len(&current_key_suffix, &stored_suffix);
 
                 if common_len == 0 {
                     // 没有公共前缀，需要创建分支节点来分离两个不同的键
                     let old_data = child_guard.value.clone();
+                    // 原叶子上的延迟删除记录移到替换它的节点上，仍位于这些键的路径上
+                    let pending = std::mem::take(&mut child_guard.to_del_map);
                     drop(child_guard); // 释放原节点锁
 
                     // 创建新的分支节点
-                    let new_branch = FullNode::new(Default::default(), None, db, None)?;
+                    let new_branch =
+                        FullNode::new(Default::default(), None, self.hash_algorithm, db, None)?;
 
                     // 处理原有键
                     if !stored_suffix.is_empty() {
                         let old_index = byte_to_hex_index(stored_suffix[0]);
                         if stored_suffix.len() > 1 {
                             // 原有键还有剩余字符，创建叶子节点
-                            let old_suffix_str: String = stored_suffix[1..]
-                                .iter()
-                                .map(|&b| format!("{:x}", b))
-                                .collect();
+                            let old_suffix_str: String = nibbles_to_suffix(&stored_suffix[1..]);
                             let old_leaf = ShortNode::new(
-                                String::new(),
+                                format!("{}", old_index),
                                 true,
                                 old_suffix_str,
                                 None,
                                 old_data.clone(),
+                                self.hash_algorithm,
                                 db,
                                 None,
                             )?;
@@ -616,11 +586,12 @@ impl MPT {
                         } else {
                             // 原有键在此处结束，也创建一个叶子节点（空后缀）
                             let old_leaf = ShortNode::new(
-                                String::new(),
+                                format!("{}", old_index),
                                 true,
                                 String::new(), // 空后缀
                                 None,
                                 old_data.clone(),
+                                self.hash_algorithm,
                                 db,
                                 None,
                             )?;
@@ -640,19 +611,20 @@ impl MPT {
                     }
 
                     // 处理新键
-                    let new_index = byte_to_hex_index(current_key_suffix[0]);
-                    if current_key_suffix.len() > 1 {
+                    if current_key_suffix.is_empty() {
+                        // 新键在当前节点结束（原键更长），将值直接存在branch节点上
+                        new_branch.write().unwrap().value = Some(value);
+                    } else if current_key_suffix.len() > 1 {
                         // 新键还有剩余字符，创建叶子节点
-                        let new_suffix_str: String = current_key_suffix[1..]
-                            .iter()
-                            .map(|&b| format!("{:x}", b))
-                            .collect();
+                        let new_index = byte_to_hex_index(current_key_suffix[0]);
+                        let new_suffix_str: String = nibbles_to_suffix(&current_key_suffix[1..]);
                         let new_leaf = ShortNode::new(
-                            String::new(),
+                            format!("{}", new_index),
                             true,
                             new_suffix_str,
                             None,
                             Some(value),
+                            self.hash_algorithm,
                             db,
                             None,
                         )?;
@@ -665,12 +637,14 @@ impl MPT {
                         }
                     } else {
                         // 新键在此处结束，也创建一个叶子节点（空后缀）
+                        let new_index = byte_to_hex_index(current_key_suffix[0]);
                         let new_leaf = ShortNode::new(
-                            String::new(),
+                            format!("{}", new_index),
                             true,
                             String::new(), // 空后缀
                             None,
                             Some(value),
+                            self.hash_algorithm,
                             db,
                             None,
                         )?;
@@ -685,18 +659,23 @@ impl MPT {
 
                     // 更新分支节点哈希并直接替换原叶子节点
                     new_branch.write().unwrap().update_hash();
+                    // 子节点在分支节点写入数据库之后才挂上，标记为脏节点，修复时重新保存
+                    new_branch.write().unwrap().is_dirty = true;
 
                     // 直接将分支节点作为 extension node 替换原来的叶子节点
                     let extension_node = ShortNode::new(
-                        String::new(),    // prefix
-                        false,            // is_leaf: false 表示这是 extension node
-                        String::new(),    // suffix
-                        Some(new_branch), // next_node 指向分支节点
-                        None,             // value
+                        format!("{}", index), // prefix: 原叶子节点的槽位
+                        false,                // is_leaf: false 表示这是 extension node
+                        String::new(),        // suffix
+                        Some(new_branch),     // next_node 指向分支节点
+                        None,                 // value
+                        self.hash_algorithm,
                         db,
                         None, // cache
                     )?;
 
+                    extension_node.write().unwrap().is_dirty = true;
+                    extension_node.write().unwrap().to_del_map = pending;
                     // 获取 extension_node 的哈希
                     let ext_hash = extension_node.read().unwrap().node_hash.to_vec();
 
@@ -712,17 +691,21 @@ impl MPT {
                         parent_guard.update_hash();
                     }
 
-                    Ok((old_value, had_old_value))
+                    // 新键与叶子中的键不同，没有旧值
+                    Ok((String::new(), false))
                 } else {
                     // 有公共前缀的情况：需要更复杂的分裂逻辑
                     // 例如：original = [1,2,3,4], new = [1,2,5,6], common_len = 2
                     // 需要创建分支节点在位置 2 处分离两条路径
 
                     let old_data = child_guard.value.clone();
+                    // 原叶子上的延迟删除记录移到替换它的节点上，仍位于这些键的路径上
+                    let pending = std::mem::take(&mut child_guard.to_del_map);
                     drop(child_guard); // 释放原节点锁
 
                     // 创建分支节点来分离公共前缀之后的路径
-                    let new_branch = FullNode::new(Default::default(), None, db, None)?;
+                    let new_branch =
+                        FullNode::new(Default::default(), None, self.hash_algorithm, db, None)?;
 
                     // 处理原有键的剩余部分（从 common_len 开始）
                     let old_remaining = &stored_suffix[common_len..];
@@ -733,16 +716,14 @@ impl MPT {
                         // 原有键还有剩余，创建叶子节点
                         let old_index = byte_to_hex_index(old_remaining[0]);
                         if old_remaining.len() > 1 {
-                            let old_suffix_str: String = old_remaining[1..]
-                                .iter()
-                                .map(|&b| format!("{:x}", b))
-                                .collect();
+                            let old_suffix_str: String = nibbles_to_suffix(&old_remaining[1..]);
                             let old_leaf = ShortNode::new(
-                                String::new(),
+                                format!("{}", old_index),
                                 true,
                                 old_suffix_str,
                                 None,
                                 old_data,
+                                self.hash_algorithm,
                                 db,
                                 None,
                             )?;
@@ -754,11 +735,12 @@ impl MPT {
                         } else {
                             // 只剩一个字符，创建空后缀的叶子节点
                             let old_leaf = ShortNode::new(
-                                String::new(),
+                                format!("{}", old_index),
                                 true,
                                 String::new(),
                                 None,
                                 old_data,
+                                self.hash_algorithm,
                                 db,
                                 None,
                             )?;
@@ -779,16 +761,14 @@ impl MPT {
                         // 新键还有剩余，创建叶子节点
                         let new_index = byte_to_hex_index(new_remaining[0]);
                         if new_remaining.len() > 1 {
-                            let new_suffix_str: String = new_remaining[1..]
-                                .iter()
-                                .map(|&b| format!("{:x}", b))
-                                .collect();
+                            let new_suffix_str: String = nibbles_to_suffix(&new_remaining[1..]);
                             let new_leaf = ShortNode::new(
-                                String::new(),
+                                format!("{}", new_index),
                                 true,
                                 new_suffix_str,
                                 None,
                                 Some(value),
+                                self.hash_algorithm,
                                 db,
                                 None,
                             )?;
@@ -800,11 +780,12 @@ impl MPT {
                         } else {
                             // 只剩一个字符，创建空后缀的叶子节点
                             let new_leaf = ShortNode::new(
-                                String::new(),
+                                format!("{}", new_index),
                                 true,
                                 String::new(),
                                 None,
                                 Some(value),
+                                self.hash_algorithm,
                                 db,
                                 None,
                             )?;
@@ -818,27 +799,29 @@ impl MPT {
 
                     // 更新分支节点哈希
                     new_branch.write().unwrap().update_hash();
+                    new_branch.write().unwrap().is_dirty = true;
                     let branch_hash = new_branch.read().unwrap().node_hash;
 
                     // 如果有公共前缀，需要创建 Extension node 来保存公共前缀
                     if common_len > 0 {
                         // 公共前缀字符串
-                        let common_prefix_str: String = stored_suffix[..common_len]
-                            .iter()
-                            .map(|&b| format!("{:x}", b))
-                            .collect();
+                        let common_prefix_str: String =
+                            nibbles_to_suffix(&stored_suffix[..common_len]);
 
                         // 创建 Extension node 保存公共前缀，指向分支节点
                         let extension_node = ShortNode::new(
-                            String::new(),
+                            format!("{}", index),
                             false,
                             common_prefix_str,
                             Some(new_branch),
                             None,
+                            self.hash_algorithm,
                             db,
                             None,
                         )?;
 
+                        extension_node.write().unwrap().is_dirty = true;
+                        extension_node.write().unwrap().to_del_map = pending;
                         // 获取 extension_node 的哈希
                         let ext_hash = extension_node.read().unwrap().node_hash.to_vec();
 
@@ -860,19 +843,16 @@ impl MPT {
                         parent_guard.update_hash();
                     }
 
-                    Ok((old_value, had_old_value))
+                    // 新键与叶子中的键不同，没有旧值
+                    Ok((String::new(), false))
                 }
             }
         } else {
             // Extension node，需要检查并消费后缀
+            child_guard.get_next_node(db, None)?;
             if let Some(next_node) = &child_guard.next_node {
                 // 获取 Extension node 的后缀
-                let ext_suffix: Vec<u8> = child_guard
-                    .suffix
-                    .chars()
-                    .filter_map(|c| c.to_digit(16))
-                    .map(|d| d as u8)
-                    .collect();
+                let ext_suffix: Vec<u8> = child_guard.suffix_nibbles()?;
 
                 // 检查当前键路径是否与 Extension node 的后缀匹配
                 let current_remaining = &key_path[pos + 1..];
@@ -923,15 +903,16 @@ impl MPT {
                     }
 
                     Ok(result)
+                } else if !is_primary && flag {
+                    // 键在 Extension 的后缀中分叉，不存在，记录延迟删除
+                    record_pending_delete(&mut child_guard.to_del_map, &key_str, &value_str);
+                    Ok((String::new(), false))
                 } else if common_len == current_remaining.len() {
                     // 情况2: 新键被Extension的suffix完全包含
                     // 例如: Extension suffix="abc", 新键剩余="ab"
                     // 需要: 创建Branch节点,Extension节点移到Branch的某个分支,新键的value设为Branch的value
 
-                    let common_prefix_str: String = ext_suffix[..common_len]
-                        .iter()
-                        .map(|&b| format!("{:x}", b))
-                        .collect();
+                    let common_prefix_str: String = nibbles_to_suffix(&ext_suffix[..common_len]);
 
                     drop(child_guard);
 
@@ -940,11 +921,12 @@ impl MPT {
                     let split_index = ext_suffix[common_len]; // Extension在split点的字符
 
                     // Extension新的suffix是去掉公共前缀和split字符后的部分
-                    let new_ext_suffix_str: String = ext_suffix[common_len + 1..]
-                        .iter()
-                        .map(|&b| format!("{:x}", b))
-                        .collect();
+                    let new_ext_suffix_str: String =
+                        nibbles_to_suffix(&ext_suffix[common_len + 1..]);
 
+                    // 原Extension移到新Branch的split_index槽位下，延迟删除记录留给新的上层Extension
+                    let pending = std::mem::take(&mut ext_guard.to_del_map);
+                    ext_guard.prefix = format!("{}", split_index);
                     ext_guard.suffix = new_ext_suffix_str;
                     ext_guard.is_dirty = true;
                     ext_guard.update_hash();
@@ -952,44 +934,44 @@ impl MPT {
                     drop(ext_guard);
 
                     // 创建新的Branch节点,包含原Extension节点和新键的value
-                    let new_branch =
-                        FullNode::new(Default::default(), Some(value.clone()), db, None)?;
+                    let new_branch = FullNode::new(
+                        Default::default(),
+                        Some(value.clone()),
+                        self.hash_algorithm,
+                        db,
+                        None,
+                    )?;
                     {
                         let mut branch_guard = new_branch.write().unwrap();
                         branch_guard.children[byte_to_hex_index(split_index)] =
                             Some(child_node.clone());
                         branch_guard.children_hash[byte_to_hex_index(split_index)] = Some(ext_hash);
                         branch_guard.update_hash();
+                        branch_guard.is_dirty = true;
                     }
 
-                    // 如果有公共前缀,创建新Extension节点
-                    if common_len > 0 {
-                        let new_extension = ShortNode::new(
-                            String::new(),
-                            false,
-                            common_prefix_str,
-                            Some(new_branch),
-                            None,
-                            db,
-                            None,
-                        )?;
+                    // 创建新Extension节点保存公共前缀（没有公共前缀时后缀为空），指向Branch节点
+                    let new_extension = ShortNode::new(
+                        format!("{}", index),
+                        false,
+                        common_prefix_str,
+                        Some(new_branch),
+                        None,
+                        self.hash_algorithm,
+                        db,
+                        None,
+                    )?;
 
-                        let new_ext_hash = new_extension.read().unwrap().node_hash.to_vec();
+                    new_extension.write().unwrap().is_dirty = true;
+                    new_extension.write().unwrap().to_del_map = pending;
+                    let new_ext_hash = new_extension.read().unwrap().node_hash.to_vec();
 
-                        // 更新父节点
-                        let mut parent_guard = full_node.write().unwrap();
-                        parent_guard.children[index] = Some(new_extension);
-                        parent_guard.children_hash[index] = Some(new_ext_hash);
-                        parent_guard.is_dirty = true;
-                        parent_guard.update_hash();
-                    } else {
-                        // 没有公共前缀,直接用Branch节点替换Extension节点
-                        let branch_hash = new_branch.read().unwrap().node_hash.to_vec();
-                        let mut parent_guard = full_node.write().unwrap();
-                        parent_guard.children_hash[index] = Some(branch_hash);
-                        parent_guard.is_dirty = true;
-                        parent_guard.update_hash();
-                    }
+                    // 更新父节点
+                    let mut parent_guard = full_node.write().unwrap();
+                    parent_guard.children[index] = Some(new_extension);
+                    parent_guard.children_hash[index] = Some(new_ext_hash);
+                    parent_guard.is_dirty = true;
+                    parent_guard.update_hash();
 
                     Ok((String::new(), false))
                 } else {
@@ -997,10 +979,7 @@ impl MPT {
                     // 例如: Extension suffix="abc", 新键剩余="adc"
                     // 公共前缀="a", 然后Extension走'b', 新键走'd'
 
-                    let common_prefix_str: String = ext_suffix[..common_len]
-                        .iter()
-                        .map(|&b| format!("{:x}", b))
-                        .collect();
+                    let common_prefix_str: String = nibbles_to_suffix(&ext_suffix[..common_len]);
 
                     drop(child_guard);
 
@@ -1009,15 +988,17 @@ impl MPT {
 
                     // 如果有公共前缀,需要修改原Extension节点
                     // 如果common_len=0,也需要修改原Extension,去掉第一个字符(因为会被Branch消费)
+                    let mut pending = HashMap::new();
                     let ext_node_to_use = {
                         let mut ext_guard = child_node.write().unwrap();
+                        // 原Extension下移，延迟删除记录留给替换它的节点
+                        std::mem::swap(&mut pending, &mut ext_guard.to_del_map);
 
                         // 去掉公共前缀和split字符(被Branch消费的字符)
-                        let new_ext_suffix_str: String = ext_suffix[common_len + 1..]
-                            .iter()
-                            .map(|&b| format!("{:x}", b))
-                            .collect();
+                        let new_ext_suffix_str: String =
+                            nibbles_to_suffix(&ext_suffix[common_len + 1..]);
 
+                        ext_guard.prefix = format!("{}", ext_split_index);
                         ext_guard.suffix = new_ext_suffix_str;
                         ext_guard.is_dirty = true;
                         ext_guard.update_hash();
@@ -1031,24 +1012,23 @@ impl MPT {
                     // 创建新的叶子节点
                     let new_key_split_index = current_remaining[common_len];
                     let new_key_remaining = &current_remaining[common_len + 1..];
-                    let new_key_suffix_str: String = new_key_remaining
-                        .iter()
-                        .map(|&b| format!("{:x}", b))
-                        .collect();
+                    let new_key_suffix_str: String = nibbles_to_suffix(new_key_remaining);
 
                     let new_leaf = ShortNode::new(
-                        String::new(),
+                        format!("{}", new_key_split_index),
                         true,
                         new_key_suffix_str,
                         None,
                         Some(value.clone()),
+                        self.hash_algorithm,
                         db,
                         None,
                     )?;
                     let leaf_hash = new_leaf.read().unwrap().node_hash.to_vec();
 
                     // 创建新的Branch节点,包含原Extension节点和新叶子节点
-                    let new_branch = FullNode::new(Default::default(), None, db, None)?;
+                    let new_branch =
+                        FullNode::new(Default::default(), None, self.hash_algorithm, db, None)?;
                     {
                         let mut branch_guard = new_branch.write().unwrap();
                         branch_guard.children[byte_to_hex_index(ext_split_index)] =
@@ -1060,20 +1040,24 @@ impl MPT {
                         branch_guard.children_hash[byte_to_hex_index(new_key_split_index)] =
                             Some(leaf_hash);
                         branch_guard.update_hash();
+                        branch_guard.is_dirty = true;
                     }
 
                     // 如果有公共前缀,创建新Extension节点
                     if common_len > 0 {
                         let new_extension = ShortNode::new(
-                            String::new(),
+                            format!("{}", index),
                             false,
                             common_prefix_str,
                             Some(new_branch),
                             None,
+                            self.hash_algorithm,
                             db,
                             None,
                         )?;
 
+                        new_extension.write().unwrap().is_dirty = true;
+                        new_extension.write().unwrap().to_del_map = pending;
                         let new_ext_hash = new_extension.read().unwrap().node_hash.to_vec();
 
                         // 更新父节点
@@ -1086,15 +1070,18 @@ impl MPT {
                         // 没有公共前缀,直接用Branch节点替换Extension节点
                         // 需要创建一个Extension节点(suffix为空)指向Branch
                         let wrapper_extension = ShortNode::new(
-                            String::new(),
+                            format!("{}", index),
                             false,
                             String::new(), // 空suffix
                             Some(new_branch),
                             None,
+                            self.hash_algorithm,
                             db,
                             None,
                         )?;
 
+                        wrapper_extension.write().unwrap().is_dirty = true;
+                        wrapper_extension.write().unwrap().to_del_map = pending;
                         let wrapper_hash = wrapper_extension.read().unwrap().node_hash.to_vec();
                         let mut parent_guard = full_node.write().unwrap();
                         parent_guard.children[index] = Some(wrapper_extension);
@@ -1111,17 +1098,47 @@ impl MPT {
         }
     }
 
+    /// 只读地获取根节点：已加载时直接返回，否则从数据库读取但不保存到 `self.root`
+    ///
+    /// 子节点的按需加载通过节点自身的读写锁完成，因此查询只需要 `&self`，多个读者可以并发查询
+    pub fn read_root(
+        &self,
+        db: &mut dyn Database,
+    ) -> Result<Option<Arc<RwLock<FullNode>>>, MPTError> {
+        if let Some(root) = &self.root {
+            return Ok(Some(root.clone()));
+        }
+        if self.root_hash == [0u8; 32] {
+            return Ok(None);
+        }
+        match db.get(&self.root_hash)? {
+            Some(data) => Ok(Some(Arc::new(RwLock::new(FullNode::deserialize(&data)?)))),
+            None => Ok(None),
+        }
+    }
+
     /// 根据键查询值 - 真正的 Patricia Trie 查询
     pub fn query_by_key(
-        &mut self,
+        &self,
         key: &str,
         db: &mut dyn Database,
+    ) -> Result<(String, MPTProof), MPTError> {
+        self.query_by_bytes(key.as_bytes(), db)
+    }
+
+    /// 查询任意字节键，见 [`MPT::insert_bytes`]
+    pub fn query_by_bytes(
+        &self,
+        key: &[u8],
+        db: &mut dyn Database,
     ) -> Result<(String, MPTProof), MPTError> {
         // 将键转换为十六进制路径
         let key_path = key_to_hex_path(key);
 
-        if let Some(root) = self.get_root(db)? {
-            self.recursive_query_full_node(&key_path, 0, 0, root, db)
+        if let Some(root) = self.read_root(db)? {
+            let (value, mut proof) = self.recursive_query_full_node(&key_path, 0, 0, root, db)?;
+            proof.hash = self.hash_algorithm;
+            Ok((value, proof))
         } else {
             let empty_proof = ProofElement::new(
                 0,
@@ -1141,14 +1158,14 @@ impl MPT {
         let computed_root = super::proof::compute_mpt_root(value, mpt_proof);
 
         if computed_root != self.root_hash {
-            println!(
+            debug!(
                 "Root hash {:x?} verification failed, computed {:x?}",
                 self.root_hash, computed_root
             );
             return false;
         }
 
-        println!("Root hash {:x?} verified successfully", computed_root);
+        trace!("Root hash {:x?} verified successfully", computed_root);
         true
     }
 
@@ -1241,12 +1258,7 @@ impl MPT {
         if child_guard.is_leaf {
             // 叶子节点，检查后缀是否匹配
             let current_key_suffix: Vec<u8> = key_path[pos + 1..].to_vec();
-            let stored_suffix: Vec<u8> = child_guard
-                .suffix
-                .chars()
-                .filter_map(|c| c.to_digit(16))
-                .map(|d| d as u8)
-                .collect();
+            let stored_suffix: Vec<u8> = child_guard.suffix_nibbles()?;
 
             let leaf_proof = ProofElement::new(
                 level + 1,
@@ -1306,32 +1318,27 @@ impl MPT {
                     .map_err(|_| MPTError::LockError("Failed to read ShortNode".to_string()))?;
 
                 // 检查 Extension node 的后缀
-                let ext_suffix: Vec<u8> = child_guard
-                    .suffix
-                    .chars()
-                    .filter_map(|c| c.to_digit(16))
-                    .map(|d| d as u8)
-                    .collect();
+                let ext_suffix: Vec<u8> = child_guard.suffix_nibbles()?;
 
                 // 当前路径的剩余部分（从 pos+1 开始，因为已经通过了当前索引）
                 let current_remaining = &key_path[pos + 1..];
 
+                // 创建 Extension node 的 proof
+                let ext_proof = ProofElement::new(
+                    level + 1,
+                    1, // Extension node 类型
+                    child_guard.prefix.clone(),
+                    child_guard.suffix.clone(),
+                    vec![],
+                    child_guard.next_node_hash.to_vec(),
+                    Default::default(),
+                );
+
                 // 检查后缀是否匹配
                 if current_remaining.len() >= ext_suffix.len() {
                     let matches = current_remaining[..ext_suffix.len()] == ext_suffix[..];
 
                     if matches {
-                        // 创建 Extension node 的 proof
-                        let ext_proof = ProofElement::new(
-                            level + 1,
-                            1, // Extension node 类型
-                            child_guard.prefix.clone(),
-                            child_guard.suffix.clone(),
-                            vec![],
-                            child_guard.next_node_hash.to_vec(),
-                            Default::default(),
-                        );
-
                         // 后缀匹配，继续递归到分支节点
                         let next_node_clone = next_node.clone();
                         drop(child_guard);
@@ -1355,17 +1362,17 @@ impl MPT {
                             MPTProof::new(sub_proof.is_exist, sub_proof.levels, all_proofs),
                         ))
                     } else {
-                        // 后缀不匹配，键不存在
+                        // 后缀不匹配，键不存在；证明中带上 Extension node，验证方才能看到分叉
                         Ok((
                             String::new(),
-                            MPTProof::new(false, level, vec![proof_element]),
+                            MPTProof::new(false, level + 1, vec![ext_proof, proof_element]),
                         ))
                     }
                 } else {
                     // 剩余路径长度不足，键不存在
                     Ok((
                         String::new(),
-                        MPTProof::new(false, level, vec![proof_element]),
+                        MPTProof::new(false, level + 1, vec![ext_proof, proof_element]),
                     ))
                 }
             } else {
@@ -1394,7 +1401,8 @@ impl MPT {
 
     /// 序列化 MPT 元数据
     pub fn serialize_metadata(&self) -> Result<Vec<u8>, MPTError> {
-        let metadata = MPTMetadata::new(self.root_hash);
+        let mut metadata = MPTMetadata::new(self.root_hash);
+        metadata.hash_algorithm = self.hash_algorithm;
         serde_json::to_vec(&metadata).map_err(MPTError::SerializationError)
     }
 
@@ -1414,14 +1422,20 @@ impl MPT {
         // 创建新的 MPT 实例
         let mut mpt = MPT::new(cache);
 
-        // 设置根哈希
+        // 设置根哈希和历史根版本号
         mpt.root_hash = *root_hash;
+        mpt.epoch = Self::stored_epoch(db)?;
 
         // 如果根哈希不为零,从数据库加载根节点
         if root_hash != &[0u8; 32] {
             // 使用 get_root 方法从数据库加载根节点
-            // 这会自动处理节点的反序列化和缓存
-            let _root = mpt.get_root(db)?;
+            // 这会自动处理节点的反序列化和缓存；哈希算法记录在根节点的编码中
+            if let Some(root) = mpt.get_root(db)? {
+                mpt.hash_algorithm = root
+                    .read()
+                    .map_err(|_| MPTError::LockError("Failed to read root".to_string()))?
+                    .hash_algorithm;
+            }
         }
 
         Ok(mpt)
@@ -1431,26 +1445,74 @@ impl MPT {
     ///
     /// 保存 MPT 元数据和所有节点数据
     pub fn persist_to_db(&mut self, db: &mut dyn Database) -> Result<(), MPTError> {
+        let timestamp = MPTMetadata::new(self.root_hash).timestamp;
+        self.persist_to_db_at(db, timestamp)
+    }
+
+    /// 使用给定的墙上时间（UNIX 秒）持久化 MPT
+    ///
+    /// 元数据中的序号在数据库中已有元数据的基础上加一
+    pub fn persist_to_db_at(
+        &mut self,
+        db: &mut dyn Database,
+        timestamp: u64,
+    ) -> Result<(), MPTError> {
         // 首先执行 batch_fix 确保所有节点哈希是最新的
         self.batch_fix(db)?;
 
         // 保存元数据
-        let metadata = self.serialize_metadata()?;
         let metadata_key = b"mpt:metadata";
-        db.put(metadata_key, &metadata)?;
+        let sequence = match db.get(metadata_key)? {
+            Some(data) => Self::deserialize_metadata(&data)?.sequence + 1,
+            None => 1,
+        };
+        let mut metadata = MPTMetadata::at(self.root_hash, timestamp, sequence);
+        metadata.hash_algorithm = self.hash_algorithm;
+        let metadata = serde_json::to_vec(&metadata).map_err(MPTError::SerializationError)?;
+        let mut batch = WriteBatch::new();
+        batch.put(metadata_key, &metadata);
 
         // 保存根哈希索引,方便快速查找
         let root_hash_key = b"mpt:root_hash";
-        db.put(root_hash_key, &self.root_hash)?;
+        batch.put(root_hash_key, &self.root_hash);
 
+        db.write_batch(batch)?;
         Ok(())
     }
 
     /// 从数据库恢复最新的 MPT
+    ///
+    /// 元数据记录的节点编码版本低于 [`NODE_FORMAT_VERSION`] 时，
+    /// 把 JSON 编码的节点迁移为二进制编码（见 [`migrate_encoding`](Self::migrate_encoding)）并更新元数据
     pub fn restore_from_db(
         db: &mut dyn Database,
         cache: Option<NodeCache>,
     ) -> Result<Self, MPTError> {
+        let metadata_key = b"mpt:metadata";
+        let metadata = match db.get(metadata_key)? {
+            Some(data) => Some(Self::deserialize_metadata(&data)?),
+            None => None,
+        };
+
+        let mut mpt = Self::restore_root(db, cache)?;
+        if let Some(mut metadata) = metadata {
+            if mpt.root_hash == [0u8; 32] {
+                // 空树没有根节点，哈希算法以元数据为准
+                mpt.hash_algorithm = metadata.hash_algorithm;
+            }
+            if metadata.version < NODE_FORMAT_VERSION {
+                mpt.migrate_encoding(db)?;
+                metadata.version = NODE_FORMAT_VERSION;
+                metadata.hash_algorithm = mpt.hash_algorithm;
+                let data = serde_json::to_vec(&metadata).map_err(MPTError::SerializationError)?;
+                db.put(metadata_key, &data)?;
+            }
+        }
+        Ok(mpt)
+    }
+
+    /// 按 `mpt:root_hash` 记录的根哈希加载 MPT，没有记录时返回空树
+    fn restore_root(db: &mut dyn Database, cache: Option<NodeCache>) -> Result<Self, MPTError> {
         // 读取根哈希
         let root_hash_key = b"mpt:root_hash";
         let root_hash_data = db.get(root_hash_key)?;
@@ -1493,55 +1555,8 @@ impl MPT {
             return Ok(());
         }
 
-        // 处理所有脏的子节点（使用并发）
-        let mut handles = vec![];
-        let children_to_process: Vec<(usize, Arc<RwLock<ShortNode>>)> = {
-            let root_guard = root
-                .read()
-                .map_err(|_| MPTError::LockError("Failed to read root".to_string()))?;
-
-            root_guard
-                .children
-                .iter()
-                .enumerate()
-                .filter_map(|(i, child_opt)| {
-                    if let Some(child) = child_opt {
-                        // 检查是否是脏节点
-                        if let Ok(child_guard) = child.read() {
-                            if child_guard.is_dirty {
-                                return Some((i, child.clone()));
-                            }
-                        }
-                    }
-                    None
-                })
-                .collect()
-        };
-
-        // 并行处理每个脏的子节点
-        use std::thread;
-        for (idx, child) in children_to_process {
-            let child_clone = child.clone();
-            let handle = thread::spawn(move || {
-                // 在每个线程中递归修复
-                Self::short_node_batch_fix_no_db(child_clone.clone()).ok();
-                (idx, child_clone)
-            });
-            handles.push(handle);
-        }
-
-        // 等待所有线程完成并更新父节点的子节点哈希
-        for handle in handles {
-            if let Ok((idx, child)) = handle.join() {
-                let mut root_guard = root
-                    .write()
-                    .map_err(|_| MPTError::LockError("Failed to write root".to_string()))?;
-
-                if let Ok(child_guard) = child.read() {
-                    root_guard.children_hash[idx] = Some(child_guard.node_hash.to_vec());
-                }
-            }
-        }
+        // 使用 rayon 并行修复所有脏子树（工作窃取，深层子树会继续拆分）
+        Self::fix_dirty_children(&root)?;
 
         // 更新根节点的哈希
         {
@@ -1551,33 +1566,32 @@ impl MPT {
             root_guard.update_hash();
             root_guard.is_dirty = false;
 
-            // 保存到数据库或缓存
+            // 放入缓存；节点数据随下面的批量写入一起落盘
             let root_hash = root_guard.node_hash;
-            let serialized = root_guard.serialize()?;
-
             if let Some(cache_mutex) = &self.cache {
                 if let Ok(mut cache) = cache_mutex.lock() {
                     cache.insert_full_node(root_hash, root.clone(), db)?;
                 }
-            } else {
-                db.put(&root_hash, &serialized)?;
             }
 
             // 更新 MPT 的根哈希
             self.root_hash = root_hash;
         }
 
-        // 保存所有节点到数据库
-        Self::save_tree_to_db(root.clone(), db)?;
-
-        // 更新 MPT 到数据库
-        self.update_mpt_in_db(db)?;
+        // 所有节点和 MPT 索引在一次批量写入中保存，RocksDB 上是原子的
+        let mut batch = WriteBatch::new();
+        Self::save_tree_to_batch(root.clone(), &mut batch)?;
+        self.stage_mpt_update(&mut batch)?;
+        db.write_batch(batch)?;
 
         Ok(())
     }
 
-    /// 递归保存整个树到数据库
-    fn save_tree_to_db(node: Arc<RwLock<FullNode>>, db: &mut dyn Database) -> Result<(), MPTError> {
+    /// 递归收集整个树的节点到批量写入中
+    fn save_tree_to_batch(
+        node: Arc<RwLock<FullNode>>,
+        batch: &mut WriteBatch,
+    ) -> Result<(), MPTError> {
         // 保存当前FullNode
         let (node_hash, serialized, children_to_save) = {
             let guard = node
@@ -1592,20 +1606,20 @@ impl MPT {
             (node_hash, serialized, children)
         };
 
-        db.put(&node_hash, &serialized)?;
+        batch.put(&node_hash, &serialized);
 
         // 递归保存所有子节点
         for child in children_to_save {
-            Self::save_short_node_to_db(child, db)?;
+            Self::save_short_node_to_batch(child, batch)?;
         }
 
         Ok(())
     }
 
-    /// 递归保存ShortNode及其子树到数据库
-    fn save_short_node_to_db(
+    /// 递归收集ShortNode及其子树到批量写入中
+    fn save_short_node_to_batch(
         node: Arc<RwLock<ShortNode>>,
-        db: &mut dyn Database,
+        batch: &mut WriteBatch,
     ) -> Result<(), MPTError> {
         let (node_hash, serialized, next_node) = {
             let guard = node
@@ -1615,11 +1629,11 @@ impl MPT {
             (guard.node_hash, guard.serialize()?, guard.next_node.clone())
         };
 
-        db.put(&node_hash, &serialized)?;
+        batch.put(&node_hash, &serialized);
 
         // 如果有next_node (Extension节点),递归保存
         if let Some(next) = next_node {
-            Self::save_tree_to_db(next, db)?;
+            Self::save_tree_to_batch(next, batch)?;
         }
 
         Ok(())
@@ -1691,6 +1705,25 @@ impl MPT {
             return Ok(());
         }
 
+        // 并行修复所有脏的子节点
+        Self::fix_dirty_children(&node)?;
+
+        // 更新当前节点的哈希
+        let mut guard = node
+            .write()
+            .map_err(|_| MPTError::LockError("Failed to write FullNode".to_string()))?;
+        guard.update_hash();
+        guard.is_dirty = false;
+
+        Ok(())
+    }
+
+    /// 并行修复 FullNode 的所有脏子节点，并更新其 children_hash
+    ///
+    /// 子节点在 rayon 线程池中并行处理，递归调用中的嵌套并行由工作窃取调度
+    fn fix_dirty_children(node: &Arc<RwLock<FullNode>>) -> Result<(), MPTError> {
+        use rayon::prelude::*;
+
         // 收集所有脏的子节点
         let dirty_children: Vec<(usize, Arc<RwLock<ShortNode>>)> = {
             let guard = node
@@ -1702,38 +1735,32 @@ impl MPT {
                 .iter()
                 .enumerate()
                 .filter_map(|(i, child_opt)| {
-                    if let Some(child) = child_opt {
-                        if let Ok(child_guard) = child.read() {
-                            if child_guard.is_dirty {
-                                return Some((i, child.clone()));
-                            }
-                        }
-                    }
-                    None
+                    let child = child_opt.as_ref()?;
+                    let is_dirty = child.read().map(|g| g.is_dirty).unwrap_or(false);
+                    is_dirty.then(|| (i, child.clone()))
                 })
                 .collect()
         };
 
         // 递归修复所有脏的子节点
-        for (idx, child) in dirty_children {
-            Self::short_node_batch_fix_no_db(child.clone())?;
-
-            // 更新子节点哈希
-            let mut guard = node
-                .write()
-                .map_err(|_| MPTError::LockError("Failed to write FullNode".to_string()))?;
-            let child_guard = child
-                .read()
-                .map_err(|_| MPTError::LockError("Failed to read child".to_string()))?;
-            guard.children_hash[idx] = Some(child_guard.node_hash.to_vec());
-        }
+        let fixed: Vec<(usize, [u8; 32])> = dirty_children
+            .into_par_iter()
+            .map(|(idx, child)| {
+                Self::short_node_batch_fix_no_db(child.clone())?;
+                let child_guard = child
+                    .read()
+                    .map_err(|_| MPTError::LockError("Failed to read child".to_string()))?;
+                Ok((idx, child_guard.node_hash))
+            })
+            .collect::<Result<_, MPTError>>()?;
 
-        // 更新当前节点的哈希
+        // 更新子节点哈希
         let mut guard = node
             .write()
             .map_err(|_| MPTError::LockError("Failed to write FullNode".to_string()))?;
-        guard.update_hash();
-        guard.is_dirty = false;
+        for (idx, hash) in fixed {
+            guard.children_hash[idx] = Some(hash.to_vec());
+        }
 
         Ok(())
     }
@@ -1748,10 +1775,12 @@ impl MPT {
         Self::full_node_batch_fix_no_db(node)
     }
 
-    /// 更新 MPT 到数据库，使用互斥锁保证线程安全
-    fn update_mpt_in_db(&mut self, db: &mut dyn Database) -> Result<(), MPTError> {
+    /// 把 MPT 索引和历史根的更新加入批量写入，使用互斥锁保证线程安全
+    pub(crate) fn stage_mpt_update(&mut self, batch: &mut WriteBatch) -> Result<(), MPTError> {
         use sha2::{Digest, Sha256};
 
+        self.record_root(batch);
+
         // 获取更新锁，确保同一时间只有一个线程更新
         let _update_guard = self
             .update_latch
@@ -1762,7 +1791,7 @@ impl MPT {
         let mut hasher = Sha256::new();
         hasher.update(&self.root_hash);
         let old_mpt_hash: [u8; 32] = hasher.finalize().into();
-        db.delete(&old_mpt_hash)?;
+        batch.delete(&old_mpt_hash);
 
         // 计算新的 MPT 哈希
         let mut hasher = Sha256::new();
@@ -1773,7 +1802,7 @@ impl MPT {
         let mpt_data = serde_json::to_vec(&self.root_hash)?;
 
         // 写入新的 MPT
-        db.put(&new_mpt_hash, &mpt_data)?;
+        batch.put(&new_mpt_hash, &mpt_data);
 
         Ok(())
     }
@@ -1934,6 +1963,7 @@ impl MPT {
         // 如果已经消费完所有路径，删除当前节点的值
         if pos >= key_path.len() {
             if let Some(value) = guard.value.take() {
+                guard.is_dirty = true;
                 guard.update_hash();
                 return Ok(Some(String::from_utf8_lossy(&value).to_string()));
             } else {
@@ -1948,8 +1978,8 @@ impl MPT {
         }
 
         // 如果没有对应的子节点，返回None
-        let child_arc = match guard.children[index].as_ref() {
-            Some(child) => child.clone(),
+        let child_arc = match guard.get_child(index, db, None)? {
+            Some(child) => child,
             None => return Ok(None),
         };
 
@@ -1979,8 +2009,16 @@ impl MPT {
             if should_remove_child {
                 guard.children[index] = None;
                 guard.children_hash[index] = None;
-                guard.update_hash();
+            } else {
+                // 子节点仍然存在但哈希已经改变，同步到当前节点
+                let child_hash = child_arc
+                    .read()
+                    .map_err(|_| MPTError::LockError("Failed to read child".to_string()))?
+                    .node_hash;
+                guard.children_hash[index] = Some(child_hash.to_vec());
             }
+            guard.is_dirty = true;
+            guard.update_hash();
         }
 
         Ok(deleted_value)
@@ -2002,16 +2040,12 @@ impl MPT {
             // 叶子节点：检查剩余路径是否匹配suffix
             let remaining_path = &key_path[pos..];
             // 将stored suffix从十六进制字符串转换为u8数组（与查询逻辑一致）
-            let stored_suffix: Vec<u8> = guard
-                .suffix
-                .chars()
-                .filter_map(|c| c.to_digit(16))
-                .map(|d| d as u8)
-                .collect();
+            let stored_suffix: Vec<u8> = guard.suffix_nibbles()?;
 
             if remaining_path == stored_suffix.as_slice() {
                 // 路径匹配，删除值
                 if let Some(value) = guard.value.take() {
+                    guard.is_dirty = true;
                     guard.update_hash();
                     return Ok(Some(String::from_utf8_lossy(&value).to_string()));
                 }
@@ -2020,12 +2054,7 @@ impl MPT {
         } else {
             // Extension节点：检查suffix是否匹配
             // 将stored suffix从十六进制字符串转换为u8数组（与查询逻辑一致）
-            let stored_suffix: Vec<u8> = guard
-                .suffix
-                .chars()
-                .filter_map(|c| c.to_digit(16))
-                .map(|d| d as u8)
-                .collect();
+            let stored_suffix: Vec<u8> = guard.suffix_nibbles()?;
             let remaining_path = &key_path[pos..];
 
             if remaining_path.len() < stored_suffix.len() {
@@ -2036,8 +2065,7 @@ impl MPT {
                 // Suffix匹配，继续到下一个节点
                 let next_pos = pos + stored_suffix.len();
 
-                if let Some(next_node) = guard.next_node.as_ref() {
-                    let next_node_clone = next_node.clone();
+                if let Some(next_node_clone) = guard.get_next_node(db, None)? {
                     drop(guard); // 释放锁
 
                     let deleted_value =
@@ -2067,8 +2095,18 @@ impl MPT {
                         if should_remove_next {
                             guard.next_node = None;
                             guard.next_node_hash = [0u8; 32];
-                            guard.update_hash();
+                        } else if let Some(next_node) = guard.next_node.clone() {
+                            guard.next_node_hash = next_node
+                                .read()
+                                .map_err(|_| {
+                                    MPTError::LockError("Failed to read next node".to_string())
+                                })?
+                                .node_hash;
+                            // 分支只剩一项时与当前 Extension 节点合并
+                            Self::collapse_extension(&mut guard, &next_node, db)?;
                         }
+                        guard.is_dirty = true;
+                        guard.update_hash();
                     }
 
                     return Ok(deleted_value);
@@ -2078,6 +2116,51 @@ impl MPT {
         }
     }
 
+    /// 删除后 Extension 节点指向的分支只剩一项时，把它与 Extension 节点合并
+    ///
+    /// 只剩值时 Extension 节点变为叶子节点；只剩一个子节点时把子节点的槽位和后缀接到
+    /// Extension 节点的后缀之后，由 Extension 节点取代子节点（槽位即前缀保持不变）。
+    /// 合并后树的形状与直接插入剩余键得到的相同，根哈希只由键集合决定
+    fn collapse_extension(
+        ext: &mut ShortNode,
+        next_node: &Arc<RwLock<FullNode>>,
+        db: &mut dyn Database,
+    ) -> Result<(), MPTError> {
+        let mut next_guard = next_node
+            .write()
+            .map_err(|_| MPTError::LockError("Failed to write next node".to_string()))?;
+        let slots: Vec<usize> = (0..16)
+            .filter(|&i| next_guard.children_hash[i].is_some())
+            .collect();
+
+        match (next_guard.value.is_some(), slots.as_slice()) {
+            (true, []) => {
+                ext.is_leaf = true;
+                ext.value = next_guard.value.clone();
+                ext.next_node = None;
+                ext.next_node_hash = [0u8; 32];
+            }
+            (false, &[slot]) => {
+                let child = next_guard
+                    .get_child(slot, db, None)?
+                    .ok_or(MPTError::NodeNotFound)?;
+                let child_guard = child
+                    .read()
+                    .map_err(|_| MPTError::LockError("Failed to read child".to_string()))?;
+                ext.suffix = format!("{}{:x}{}", ext.suffix, slot, child_guard.suffix);
+                ext.is_leaf = child_guard.is_leaf;
+                ext.value = child_guard.value.clone();
+                ext.next_node = child_guard.next_node.clone();
+                ext.next_node_hash = child_guard.next_node_hash;
+                ext.to_del_map.extend(child_guard.to_del_map.clone());
+            }
+            _ => return Ok(()),
+        }
+        // 被合并节点上的延迟删除记录转移到合并后的节点
+        ext.to_del_map.extend(next_guard.to_del_map.clone());
+        Ok(())
+    }
+
     /// 清理根节点（如果需要）
     fn cleanup_root_if_needed(&mut self, _db: &mut dyn Database) -> Result<(), MPTError> {
         if let Some(root) = &self.root {
@@ -2099,3 +2182,33 @@ impl MPT {
         Ok(())
     }
 }
+
+/// 辅助索引的延迟删除记录：键路径 -> 值 -> 尚未抵消的删除次数
+type PendingDeletes = HashMap<String, HashMap<String, u32>>;
+
+/// 删除的值不存在时记录一次延迟删除，之后插入同一个值时抵消
+fn record_pending_delete(to_del_map: &mut PendingDeletes, key: &str, value: &str) {
+    *to_del_map
+        .entry(key.to_string())
+        .or_default()
+        .entry(value.to_string())
+        .or_insert(0) += 1;
+}
+
+/// 有对应的延迟删除记录时抵消一次并返回 true
+fn take_pending_delete(to_del_map: &mut PendingDeletes, key: &str, value: &str) -> bool {
+    let Some(inner_map) = to_del_map.get_mut(key) else {
+        return false;
+    };
+    let Some(count) = inner_map.get_mut(value) else {
+        return false;
+    };
+    *count -= 1;
+    if *count == 0 {
+        inner_map.remove(value);
+    }
+    if inner_map.is_empty() {
+        to_del_map.remove(key);
+    }
+    true
+}
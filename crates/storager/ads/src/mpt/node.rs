@@ -1,7 +1,9 @@
+use super::codec;
 use super::error::MPTError;
+use super::hasher::HashAlgorithm;
+use super::utils::suffix_to_nibbles;
 use lru::LruCache;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
 use std::sync::{Arc, RwLock};
@@ -17,6 +19,7 @@ pub struct FullNode {
     pub is_dirty: bool,
     pub to_del_map: HashMap<String, HashMap<String, u32>>,
     pub child_latches: [Arc<RwLock<()>>; 16],
+    pub hash_algorithm: HashAlgorithm,
 }
 
 /// ShortNode 表示 MPT 中的叶子节点或扩展节点
@@ -32,9 +35,10 @@ pub struct ShortNode {
     pub next_node_hash: [u8; 32],
     pub value: Option<Vec<u8>>,
     pub to_del_map: HashMap<String, HashMap<String, u32>>,
+    pub hash_algorithm: HashAlgorithm,
 }
 
-/// 用于序列化的 ShortNode
+/// 旧版本 JSON 编码的 ShortNode
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SerializableShortNode {
     pub node_hash: [u8; 32],
@@ -45,7 +49,7 @@ pub struct SerializableShortNode {
     pub value: Option<Vec<u8>>,
 }
 
-/// 用于序列化的 FullNode
+/// 旧版本 JSON 编码的 FullNode
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SerializableFullNode {
     pub node_hash: [u8; 32],
@@ -81,6 +85,7 @@ impl Default for FullNode {
                 Arc::new(RwLock::new(())),
                 Arc::new(RwLock::new(())),
             ],
+            hash_algorithm: HashAlgorithm::default(),
         }
     }
 }
@@ -89,12 +94,14 @@ impl FullNode {
     pub fn new(
         children_array: [Option<Arc<RwLock<ShortNode>>>; 16],
         value: Option<Vec<u8>>,
+        hash_algorithm: HashAlgorithm,
         db: &mut dyn Database,
         cache: Option<&mut NodeCache>,
     ) -> Result<Arc<RwLock<Self>>, MPTError> {
         let mut node = Self {
             children: children_array,
             value: value.clone(),
+            hash_algorithm,
             ..Default::default()
         };
 
@@ -194,7 +201,7 @@ impl FullNode {
     }
 
     pub fn update_hash(&mut self) {
-        let mut hasher = Sha256::new();
+        let mut hasher = self.hash_algorithm.hasher();
 
         // 添加所有子节点哈希
         for child_hash in &self.children_hash {
@@ -208,20 +215,19 @@ impl FullNode {
             hasher.update(value);
         }
 
-        self.node_hash = hasher.finalize().into();
+        self.node_hash = hasher.finalize();
     }
 
+    /// 编码为二进制格式（见 [`codec`]）
     pub fn serialize(&self) -> Result<Vec<u8>, MPTError> {
-        let serializable = SerializableFullNode {
-            node_hash: self.node_hash,
-            children_hash: self.children_hash.clone(),
-            value: self.value.clone(),
-        };
-
-        Ok(serde_json::to_vec(&serializable)?)
+        Ok(codec::encode_full_node(self))
     }
 
+    /// 解码二进制格式或旧版本的 JSON 编码，JSON 编码的节点使用 SHA-256
     pub fn deserialize(data: &[u8]) -> Result<Self, MPTError> {
+        if !codec::is_legacy_json(data) {
+            return codec::decode_full_node(data);
+        }
         let serializable: SerializableFullNode = serde_json::from_slice(data)?;
 
         Ok(Self {
@@ -246,17 +252,20 @@ impl Default for ShortNode {
             next_node_hash: [0u8; 32],
             value: None,
             to_del_map: HashMap::new(),
+            hash_algorithm: HashAlgorithm::default(),
         }
     }
 }
 
 impl ShortNode {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         prefix: String,
         is_leaf: bool,
         suffix: String,
         next_node: Option<Arc<RwLock<FullNode>>>,
         value: Option<Vec<u8>>,
+        hash_algorithm: HashAlgorithm,
         db: &mut dyn Database,
         cache: Option<&mut NodeCache>,
     ) -> Result<Arc<RwLock<Self>>, MPTError> {
@@ -266,6 +275,7 @@ impl ShortNode {
             suffix: suffix.clone(),
             next_node: next_node.clone(),
             value: value.clone(),
+            hash_algorithm,
             ..Default::default()
         };
 
@@ -342,8 +352,15 @@ impl ShortNode {
         Ok(self.next_node.clone())
     }
 
+    /// 以半字节数组返回 suffix
+    pub fn suffix_nibbles(&self) -> Result<Vec<u8>, MPTError> {
+        suffix_to_nibbles(&self.suffix).ok_or_else(|| {
+            MPTError::InvalidData(format!("invalid ShortNode suffix '{}'", self.suffix))
+        })
+    }
+
     pub fn update_hash(&mut self) {
-        let mut hasher = Sha256::new();
+        let mut hasher = self.hash_algorithm.hasher();
 
         // 添加前缀和后缀
         hasher.update(self.prefix.as_bytes());
@@ -359,23 +376,19 @@ impl ShortNode {
             hasher.update(&self.next_node_hash);
         }
 
-        self.node_hash = hasher.finalize().into();
+        self.node_hash = hasher.finalize();
     }
 
+    /// 编码为二进制格式（见 [`codec`]）
     pub fn serialize(&self) -> Result<Vec<u8>, MPTError> {
-        let serializable = SerializableShortNode {
-            node_hash: self.node_hash,
-            prefix: self.prefix.clone(),
-            is_leaf: self.is_leaf,
-            suffix: self.suffix.clone(),
-            next_node_hash: self.next_node_hash,
-            value: self.value.clone(),
-        };
-
-        Ok(serde_json::to_vec(&serializable)?)
+        Ok(codec::encode_short_node(self))
     }
 
+    /// 解码二进制格式或旧版本的 JSON 编码，JSON 编码的节点使用 SHA-256
     pub fn deserialize(data: &[u8]) -> Result<Self, MPTError> {
+        if !codec::is_legacy_json(data) {
+            return codec::decode_short_node(data);
+        }
         let serializable: SerializableShortNode = serde_json::from_slice(data)?;
 
         Ok(Self {
@@ -390,12 +403,8 @@ impl ShortNode {
     }
 }
 
-/// 数据库trait，抽象化数据库操作
-pub trait Database {
-    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, MPTError>;
-    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), MPTError>;
-    fn delete(&mut self, key: &[u8]) -> Result<(), MPTError>;
-}
+/// 数据库trait 和批量写入定义在 storage_backend 中，MPT、累加器和 storager 共用
+pub use storage_backend::{BatchOp, Database, WriteBatch};
 
 /// 节点缓存，带淘汰回调功能
 pub struct NodeCache {
@@ -431,7 +440,7 @@ impl NodeCache {
                     let serialized = guard.serialize()?;
                     db.put(&evicted_hash, &serialized)?;
                     drop(guard);
-                    println!("Evicted ShortNode {:x?} to database", &evicted_hash[..8]);
+                    trace!("Evicted ShortNode {:x?} to database", &evicted_hash[..8]);
                 }
             }
         }
@@ -460,7 +469,7 @@ impl NodeCache {
                     let serialized = guard.serialize()?;
                     db.put(&evicted_hash, &serialized)?;
                     drop(guard);
-                    println!("Evicted FullNode {:x?} to database", &evicted_hash[..8]);
+                    trace!("Evicted FullNode {:x?} to database", &evicted_hash[..8]);
                 }
             }
         }
@@ -469,24 +478,25 @@ impl NodeCache {
         Ok(())
     }
 
-    /// 清空缓存，所有节点写入数据库
+    /// 清空缓存，所有节点在一次批量写入中写入数据库
     pub fn purge(&mut self, db: &mut dyn Database) -> Result<(), MPTError> {
+        let mut batch = WriteBatch::new();
+
         // 将所有 ShortNode 写入数据库
         while let Some((hash, node)) = self.short_node_cache.pop_lru() {
             if let Ok(guard) = node.read() {
-                let serialized = guard.serialize()?;
-                db.put(&hash, &serialized)?;
+                batch.put(&hash, &guard.serialize()?);
             }
         }
 
         // 将所有 FullNode 写入数据库
         while let Some((hash, node)) = self.full_node_cache.pop_lru() {
             if let Ok(guard) = node.read() {
-                let serialized = guard.serialize()?;
-                db.put(&hash, &serialized)?;
+                batch.put(&hash, &guard.serialize()?);
             }
         }
 
+        db.write_batch(batch)?;
         self.short_node_cache.clear();
         self.full_node_cache.clear();
         Ok(())
@@ -497,6 +507,7 @@ impl NodeCache {
 mod tests {
     use super::*;
     use std::collections::HashMap;
+    use storage_backend::DbError;
 
     /// 测试用的内存数据库
     struct MemoryDatabase {
@@ -512,16 +523,16 @@ mod tests {
     }
 
     impl Database for MemoryDatabase {
-        fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, MPTError> {
+        fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError> {
             Ok(self.data.get(key).cloned())
         }
 
-        fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), MPTError> {
+        fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
             self.data.insert(key.to_vec(), value.to_vec());
             Ok(())
         }
 
-        fn delete(&mut self, key: &[u8]) -> Result<(), MPTError> {
+        fn delete(&mut self, key: &[u8]) -> Result<(), DbError> {
             self.data.remove(key);
             Ok(())
         }
@@ -538,6 +549,7 @@ mod tests {
             "suffix".to_string(),
             None,
             Some(b"value".to_vec()),
+            HashAlgorithm::Sha256,
             &mut db,
             Some(&mut cache),
         )
@@ -559,6 +571,7 @@ mod tests {
         let node = FullNode::new(
             children,
             Some(b"branch_value".to_vec()),
+            HashAlgorithm::Keccak256,
             &mut db,
             Some(&mut cache),
         )
@@ -566,6 +579,10 @@ mod tests {
 
         let node_guard = node.read().unwrap();
         assert_eq!(node_guard.value, Some(b"branch_value".to_vec()));
+        assert_eq!(
+            node_guard.node_hash,
+            HashAlgorithm::Keccak256.digest(b"branch_value")
+        );
     }
 
     #[test]
@@ -585,5 +602,20 @@ mod tests {
         assert_eq!(deserialized.suffix, "key");
         assert!(deserialized.is_leaf);
         assert_eq!(deserialized.value, Some(b"test_value".to_vec()));
+
+        // 旧版本 JSON 编码的节点仍然可以解码
+        let legacy = serde_json::to_vec(&SerializableShortNode {
+            node_hash: [1u8; 32],
+            prefix: "test".to_string(),
+            is_leaf: true,
+            suffix: "key".to_string(),
+            next_node_hash: [0u8; 32],
+            value: Some(b"test_value".to_vec()),
+        })
+        .unwrap();
+        let deserialized = ShortNode::deserialize(&legacy).unwrap();
+        assert_eq!(deserialized.node_hash, [1u8; 32]);
+        assert_eq!(deserialized.hash_algorithm, HashAlgorithm::Sha256);
+        assert_eq!(deserialized.value, Some(b"test_value".to_vec()));
     }
 }
@@ -1,5 +1,8 @@
+use super::codec;
+use super::error::MPTError;
+use super::hasher::HashAlgorithm;
+use super::utils::{key_to_hex_path, suffix_to_nibbles};
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProofElement {
@@ -83,6 +86,9 @@ pub struct MPTProof {
     pub is_exist: bool,
     pub levels: u32,
     pub proofs: Vec<ProofElement>,
+    /// 节点哈希算法（旧格式没有该字段，按 SHA-256 处理）
+    #[serde(default)]
+    pub hash: HashAlgorithm,
 }
 
 impl MPTProof {
@@ -91,6 +97,7 @@ impl MPTProof {
             is_exist,
             levels,
             proofs,
+            hash: HashAlgorithm::default(),
         }
     }
 
@@ -126,6 +133,138 @@ impl MPTProof {
     }
 }
 
+/// 附带被证明值的 MPT 证明
+///
+/// 验证方只有根哈希，需要连同值一起传输才能用 [`compute_mpt_root`] 重算根哈希
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValueProof {
+    pub value: String,
+    pub proof: MPTProof,
+}
+
+impl ValueProof {
+    pub fn new(value: String, proof: MPTProof) -> Self {
+        Self { value, proof }
+    }
+
+    /// 编码为二进制格式（见 [`codec`]）
+    pub fn to_bytes(&self) -> Vec<u8> {
+        codec::encode_value_proof(self)
+    }
+
+    /// 从 [`to_bytes`](Self::to_bytes) 的结果或旧版本的 JSON 编码解码
+    pub fn from_bytes(data: &[u8]) -> Result<Self, MPTError> {
+        if codec::is_legacy_json(data) {
+            return serde_json::from_slice(data).map_err(MPTError::SerializationError);
+        }
+        codec::decode_value_proof(data)
+    }
+
+    /// 由值和证明重算出的根哈希；证明路径不连贯时返回全零
+    pub fn compute_root(&self) -> [u8; 32] {
+        compute_mpt_root(&self.value, &self.proof)
+    }
+
+    /// 是否为空 MPT 上的不存在证明
+    ///
+    /// 空 MPT 的根哈希记为全零，查询它只会得到一个没有子节点的分支节点
+    pub fn proves_empty_trie(&self) -> bool {
+        !self.proof.is_exist
+            && self.proof.proofs.iter().all(|p| {
+                p.proof_type == 2
+                    && p.value.is_empty()
+                    && p.children_hashes.iter().all(Vec::is_empty)
+            })
+    }
+
+    /// 证明的路径是否沿 `key` 展开，并且终点处的值与 `value` 一致
+    ///
+    /// [`compute_root`](Self::compute_root) 只检查路径连贯，不检查路径属于哪个 key，
+    /// 不存在证明可以是任意 key 的。这里从根开始按 key 的半字节逐层下行，
+    /// 每个子节点的哈希必须位于 key 对应的槽位；路径在 key 处中断
+    /// （槽位为空、后缀分叉或节点没有值）时证明 key 不存在，此时 `value` 必须为空
+    pub fn binds_key(&self, key: &str) -> bool {
+        self.binds_bytes(key.as_bytes())
+    }
+
+    /// 任意字节键的 [`binds_key`](Self::binds_key)
+    pub fn binds_bytes(&self, key: &[u8]) -> bool {
+        let path = key_to_hex_path(key);
+        let mut pos = 0;
+        let mut elements = self.proof.proofs.iter().rev().peekable();
+        while let Some(element) = elements.next() {
+            let child = elements.peek();
+            let suffix = match element.proof_type {
+                0 | 1 => match suffix_to_nibbles(&element.suffix) {
+                    Some(suffix) => suffix,
+                    None => return false,
+                },
+                _ => Vec::new(),
+            };
+            let found: &[u8] = match element.proof_type {
+                2 if pos == path.len() => &element.value,
+                2 => {
+                    let slot = &element.children_hashes[path[pos] as usize];
+                    match child {
+                        Some(child) if *slot == element_hash(child, self.proof.hash) => {
+                            pos += 1;
+                            continue;
+                        }
+                        None if slot.is_empty() => &[],
+                        _ => return false,
+                    }
+                }
+                0 if suffix == path[pos..] => &element.value,
+                0 => &[],
+                1 => {
+                    if !path[pos..].starts_with(&suffix) {
+                        &[]
+                    } else {
+                        match child {
+                            Some(child)
+                                if child.proof_type == 2
+                                    && element.next_node_hash
+                                        == element_hash(child, self.proof.hash) =>
+                            {
+                                pos += suffix.len();
+                                continue;
+                            }
+                            _ => return false,
+                        }
+                    }
+                }
+                _ => return false,
+            };
+            // 路径在终点之后不能再有节点
+            return child.is_none() && found == self.value.as_bytes();
+        }
+        false
+    }
+}
+
+/// 按 [`compute_mpt_root`] 的规则计算单个证明元素的哈希
+fn element_hash(element: &ProofElement, hash: HashAlgorithm) -> Vec<u8> {
+    let mut hasher = hash.hasher();
+    match element.proof_type {
+        0 | 1 => {
+            hasher.update(element.prefix.as_bytes());
+            hasher.update(element.suffix.as_bytes());
+            hasher.update(if element.proof_type == 0 {
+                &element.value
+            } else {
+                &element.next_node_hash
+            });
+        }
+        _ => {
+            for child_hash in &element.children_hashes {
+                hasher.update(child_hash);
+            }
+            hasher.update(&element.value);
+        }
+    }
+    hasher.finalize().to_vec()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -167,6 +306,65 @@ mod tests {
         assert_eq!(mpt_proof.get_levels(), 1);
         assert_eq!(mpt_proof.get_proofs().len(), 1);
     }
+
+    #[test]
+    fn test_value_proof_round_trip() {
+        let proof_element = ProofElement::new(
+            0,
+            0,
+            "test".to_string(),
+            "key".to_string(),
+            vec![],
+            vec![],
+            Default::default(),
+        );
+        let proof = ValueProof::new(
+            "f1,f2".to_string(),
+            MPTProof::new(true, 0, vec![proof_element]),
+        );
+
+        let decoded = ValueProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded.value, "f1,f2");
+        assert_eq!(decoded.compute_root(), proof.compute_root());
+        assert!(ValueProof::from_bytes(b"not json").is_err());
+    }
+
+    #[test]
+    fn test_binds_key() {
+        use crate::mpt::db::MemoryDatabase;
+        use crate::mpt::{KVPair, MPT};
+
+        let mut db = MemoryDatabase::new();
+        let mut mpt = MPT::new(None);
+        for key in ["category", "categoryx", "cat", "dog"] {
+            let kv = KVPair::new(key.to_string(), format!("v-{}", key));
+            mpt.insert(kv, &mut db, true, false).unwrap();
+        }
+        let root = mpt.get_root_hash();
+        let mut prove = |key: &str| {
+            let (value, proof) = mpt.query_by_key(key, &mut db).unwrap();
+            ValueProof::new(value, proof)
+        };
+
+        let present = prove("category");
+        assert_eq!(present.value, "v-category");
+        assert!(present.binds_key("category"));
+        assert!(!present.binds_key("dog"));
+
+        // 空槽位、后缀分叉、key 在 Extension 后缀中途结束
+        for absent in ["zebra", "dot", "catego", "categoryz"] {
+            let proof = prove(absent);
+            assert!(proof.value.is_empty(), "{}", absent);
+            assert!(proof.binds_key(absent), "{}", absent);
+            assert_eq!(proof.compute_root(), root, "{}", absent);
+        }
+
+        // 不存在证明不能冒充另一个 key 的空结果，也不能去掉值冒充不存在
+        assert!(!prove("zebra").binds_key("dog"));
+        let mut stripped = prove("dog");
+        stripped.value.clear();
+        assert!(!stripped.binds_key("dog"));
+    }
 }
 
 /// Compute MPT root hash from value and proof
@@ -180,28 +378,35 @@ pub fn compute_mpt_root(value: &str, mpt_proof: &MPTProof) -> [u8; 32] {
         node_hash_0[..value_bytes.len()].copy_from_slice(value_bytes);
     } else {
         // If value is longer than 32 bytes, hash it first
-        let mut hasher = Sha256::new();
+        let mut hasher = mpt_proof.hash.hasher();
         hasher.update(value_bytes);
-        node_hash_0 = hasher.finalize().into();
+        node_hash_0 = hasher.finalize();
     }
 
-    println!("Starting verification with value: {}", value);
-    println!("Initial node_hash_0: {:x?}", node_hash_0);
+    trace!(
+        "Computing MPT root for value {:?}, initial hash {:x?}",
+        value,
+        node_hash_0
+    );
 
     // Process proofs from leaf to root (forward order)
     for (i, proof) in proofs.iter().enumerate() {
-        println!(
-            "\n--- Processing proof {} (level={}, type={}) ---",
-            i, proof.level, proof.proof_type
+        trace!(
+            "Processing proof {} (level={}, type={})",
+            i,
+            proof.level,
+            proof.proof_type
         );
         let mut node_data = Vec::new();
 
         match proof.proof_type {
             0 => {
                 // Leaf node: hash(prefix + suffix + value)
-                println!(
+                trace!(
                     "Leaf node: prefix='{}', suffix='{}', value={:x?}",
-                    proof.prefix, proof.suffix, proof.value
+                    proof.prefix,
+                    proof.suffix,
+                    proof.value
                 );
 
                 node_data.extend_from_slice(proof.prefix.as_bytes());
@@ -214,17 +419,19 @@ pub fn compute_mpt_root(value: &str, mpt_proof: &MPTProof) -> [u8; 32] {
                     node_data.extend_from_slice(&proof.value);
                 }
 
-                println!("Hashing data (len={}): {:x?}", node_data.len(), node_data);
-                let mut hasher = Sha256::new();
+                trace!("Hashing data (len={}): {:x?}", node_data.len(), node_data);
+                let mut hasher = mpt_proof.hash.hasher();
                 hasher.update(&node_data);
-                node_hash_0 = hasher.finalize().into();
-                println!("Computed hash: {:x?}", node_hash_0);
+                node_hash_0 = hasher.finalize();
+                trace!("Computed hash: {:x?}", node_hash_0);
             }
             1 => {
                 // Extension node: verify and hash(prefix + suffix + next_node_hash)
-                println!(
+                trace!(
                     "Extension node: prefix='{}', suffix='{}', next_node_hash={:x?}",
-                    proof.prefix, proof.suffix, proof.next_node_hash
+                    proof.prefix,
+                    proof.suffix,
+                    proof.next_node_hash
                 );
 
                 // If not the bottom level, verify next node hash matches computed hash
@@ -232,12 +439,13 @@ pub fn compute_mpt_root(value: &str, mpt_proof: &MPTProof) -> [u8; 32] {
                     if proof.next_node_hash.len() == 32 {
                         let mut expected = [0u8; 32];
                         expected.copy_from_slice(&proof.next_node_hash);
-                        println!(
+                        trace!(
                             "Verifying next_node_hash: expected={:x?}, got={:x?}",
-                            expected, node_hash_0
+                            expected,
+                            node_hash_0
                         );
                         if expected != node_hash_0 {
-                            println!(
+                            debug!(
                                 "Level {} nextNodeHash={:x?} verification failed",
                                 proof.level, node_hash_0
                             );
@@ -250,15 +458,15 @@ pub fn compute_mpt_root(value: &str, mpt_proof: &MPTProof) -> [u8; 32] {
                 node_data.extend_from_slice(proof.suffix.as_bytes());
                 node_data.extend_from_slice(&proof.next_node_hash);
 
-                println!("Hashing data (len={}): {:x?}", node_data.len(), node_data);
-                let mut hasher = Sha256::new();
+                trace!("Hashing data (len={}): {:x?}", node_data.len(), node_data);
+                let mut hasher = mpt_proof.hash.hasher();
                 hasher.update(&node_data);
-                node_hash_0 = hasher.finalize().into();
-                println!("Computed hash: {:x?}", node_hash_0);
+                node_hash_0 = hasher.finalize();
+                trace!("Computed hash: {:x?}", node_hash_0);
             }
             2 => {
                 // Branch node: verify and hash(all children hashes + value)
-                println!(
+                trace!(
                     "Branch node: value={:x?}, {} children",
                     proof.value,
                     proof
@@ -270,7 +478,7 @@ pub fn compute_mpt_root(value: &str, mpt_proof: &MPTProof) -> [u8; 32] {
 
                 // If not the bottom level, verify computed hash is in children
                 if proof.level != mpt_proof.levels {
-                    println!(
+                    trace!(
                         "Verifying computed hash {:x?} is in children...",
                         node_hash_0
                     );
@@ -280,14 +488,14 @@ pub fn compute_mpt_root(value: &str, mpt_proof: &MPTProof) -> [u8; 32] {
                             let mut hash_arr = [0u8; 32];
                             hash_arr.copy_from_slice(child_hash);
                             if hash_arr == node_hash_0 {
-                                println!("Found at index {}", idx);
+                                trace!("Found at index {}", idx);
                                 is_in = true;
                                 break;
                             }
                         }
                     }
                     if !is_in {
-                        println!(
+                        debug!(
                             "Level {} childrenHashes={:x?} verification failed",
                             proof.level, node_hash_0
                         );
@@ -301,19 +509,19 @@ pub fn compute_mpt_root(value: &str, mpt_proof: &MPTProof) -> [u8; 32] {
                 }
                 node_data.extend_from_slice(&proof.value);
 
-                println!("Hashing data (len={})", node_data.len());
-                let mut hasher = Sha256::new();
+                trace!("Hashing data (len={})", node_data.len());
+                let mut hasher = mpt_proof.hash.hasher();
                 hasher.update(&node_data);
-                node_hash_0 = hasher.finalize().into();
-                println!("Computed hash: {:x?}", node_hash_0);
+                node_hash_0 = hasher.finalize();
+                debug!("Computed hash: {:x?}", node_hash_0);
             }
             _ => {
-                println!("Unknown proof type: {}", proof.proof_type);
+                debug!("Unknown proof type: {}", proof.proof_type);
                 return [0u8; 32];
             }
         }
     }
 
-    println!("\n=== Final computed root hash: {:x?} ===", node_hash_0);
+    trace!("Computed MPT root {:x?}", node_hash_0);
     node_hash_0
 }
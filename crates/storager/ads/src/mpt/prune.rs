@@ -0,0 +1,186 @@
+//! 过期节点回收
+//!
+//! 每次修复都会以新的哈希写入路径上的节点，旧版本的节点从不删除，数据库会无限增长。
+//! [`MPT::prune`] 采用标记-清除：从需要保留的根出发遍历数据库中可达的节点，
+//! 再在一次批量写入中删除其余以节点哈希为键的条目。
+
+use super::error::MPTError;
+use super::history::HISTORY_PREFIX;
+use super::mpt::MPT;
+use super::node::{Database, FullNode, ShortNode, WriteBatch};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+/// 一次回收的统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneStats {
+    /// 从保留的根可达的节点数量
+    pub nodes_kept: u64,
+    /// 删除的条目数量
+    pub nodes_deleted: u64,
+}
+
+/// 标记阶段的待访问节点
+enum Visit {
+    Full([u8; 32]),
+    Short([u8; 32]),
+}
+
+impl MPT {
+    /// 删除从当前根和 `keep_roots` 都不可达的节点
+    ///
+    /// 先执行 [`batch_fix`](Self::batch_fix) 使当前树完整落盘。
+    /// 清除 32 字节的键（节点哈希和 MPT 索引）以及不再保留的历史根记录，元数据等其他键保持不变。
+    /// 任一保留的根在数据库中不完整时返回错误，且不删除任何数据。
+    /// 数据库必须支持遍历（[`Database::iter`]）。
+    ///
+    /// # Arguments
+    /// * `db` - 节点数据库
+    /// * `keep_roots` - 仍需查询或证明的历史根哈希
+    pub fn prune(
+        &mut self,
+        db: &mut dyn Database,
+        keep_roots: &[[u8; 32]],
+    ) -> Result<PruneStats, MPTError> {
+        self.batch_fix(db)?;
+
+        let mut roots: HashSet<[u8; 32]> = keep_roots.iter().copied().collect();
+        roots.insert(self.root_hash);
+        roots.remove(&[0u8; 32]);
+
+        let mut live = HashSet::new();
+        for root in &roots {
+            Self::mark_reachable(*root, db, &mut live)?;
+        }
+        let nodes_kept = live.len() as u64;
+        // MPT 索引以根哈希的哈希为键，保留的根对应的索引同样保留
+        live.extend(
+            roots
+                .iter()
+                .map(|root| -> [u8; 32] { Sha256::digest(root).into() }),
+        );
+
+        let mut batch = WriteBatch::new();
+        let mut stale_history = Vec::new();
+        for (key, value) in db.entries()? {
+            if key.len() == 32 && !live.contains(key.as_slice()) {
+                batch.delete(&key);
+            } else if key.starts_with(HISTORY_PREFIX) && !roots.contains(value.as_slice()) {
+                stale_history.push(key);
+            }
+        }
+        let nodes_deleted = batch.len() as u64;
+        // 历史根索引中不再保留的版本已无法查询，一并删除
+        for key in stale_history {
+            batch.delete(&key);
+        }
+        db.write_batch(batch)?;
+
+        Ok(PruneStats {
+            nodes_kept,
+            nodes_deleted,
+        })
+    }
+
+    /// 从 `root` 出发，把数据库中可达的节点哈希加入 `live`
+    fn mark_reachable(
+        root: [u8; 32],
+        db: &mut dyn Database,
+        live: &mut HashSet<[u8; 32]>,
+    ) -> Result<(), MPTError> {
+        let mut stack = vec![Visit::Full(root)];
+        while let Some(visit) = stack.pop() {
+            let (Visit::Full(hash) | Visit::Short(hash)) = visit;
+            if !live.insert(hash) {
+                continue;
+            }
+            let data = db.get(&hash)?.ok_or(MPTError::NodeNotFound)?;
+            match visit {
+                Visit::Full(_) => {
+                    let node = FullNode::deserialize(&data)?;
+                    for child in node.children_hash.iter().flatten() {
+                        let child: [u8; 32] = child.as_slice().try_into().map_err(|_| {
+                            MPTError::InvalidData("invalid child hash length".to_string())
+                        })?;
+                        stack.push(Visit::Short(child));
+                    }
+                }
+                Visit::Short(_) => {
+                    let node = ShortNode::deserialize(&data)?;
+                    if node.next_node_hash != [0u8; 32] {
+                        stack.push(Visit::Full(node.next_node_hash));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpt::db::MemoryDatabase;
+    use crate::mpt::KVPair;
+
+    fn insert(mpt: &mut MPT, db: &mut MemoryDatabase, key: &str, value: &str) {
+        let kv = KVPair::new(key.to_string(), value.to_string());
+        mpt.insert(kv, db, true, false).unwrap();
+        mpt.batch_fix(db).unwrap();
+    }
+
+    #[test]
+    fn test_prune_keeps_retained_versions() {
+        let mut db = MemoryDatabase::new();
+        let mut mpt = MPT::new(None);
+        for key in ["rust", "go", "python"] {
+            insert(&mut mpt, &mut db, key, "v1");
+        }
+        let old_root = mpt.root_hash;
+        db.put(b"mpt:root_hash", &old_root).unwrap();
+        for key in ["rust", "go", "java"] {
+            insert(&mut mpt, &mut db, key, "v2");
+        }
+
+        let before = db.entries().unwrap().len() as u64;
+        let stats = mpt.prune(&mut db, &[old_root]).unwrap();
+        assert!(stats.nodes_deleted > 0);
+        // 6 次修复中只有旧根和当前根的历史记录被保留
+        assert_eq!(
+            db.entries().unwrap().len() as u64,
+            before - stats.nodes_deleted - 4
+        );
+        assert!(db.get(b"mpt:root_hash").unwrap().is_some());
+        let history = MPT::root_history(&mut db).unwrap();
+        assert_eq!(
+            history.iter().map(|(_, root)| *root).collect::<Vec<_>>(),
+            vec![old_root, mpt.root_hash]
+        );
+
+        // 保留的旧版本仍可完整加载
+        let old = MPT::load_from_db(&old_root, &mut db, None).unwrap();
+        let (value, proof) = old.query_by_key("python", &mut db).unwrap();
+        assert_eq!(value, "v1");
+        assert!(old.verify_query_result(&value, &proof));
+
+        // 不再保留旧版本后，只剩当前版本的节点
+        let stats = mpt.prune(&mut db, &[]).unwrap();
+        assert!(stats.nodes_deleted > 0);
+        assert_eq!(db.get(&old_root).unwrap(), None);
+        let current = MPT::load_from_db(&mpt.root_hash, &mut db, None).unwrap();
+        let (value, _) = current.query_by_key("java", &mut db).unwrap();
+        assert_eq!(value, "v2");
+        assert_eq!(mpt.prune(&mut db, &[]).unwrap().nodes_deleted, 0);
+    }
+
+    #[test]
+    fn test_prune_rejects_missing_root() {
+        let mut db = MemoryDatabase::new();
+        let mut mpt = MPT::new(None);
+        insert(&mut mpt, &mut db, "rust", "v1");
+
+        let before = db.entries().unwrap();
+        assert!(mpt.prune(&mut db, &[[7u8; 32]]).is_err());
+        assert_eq!(db.entries().unwrap(), before);
+    }
+}
@@ -0,0 +1,602 @@
+//! MPT 范围查询
+//!
+//! MPT 的键按十六进制路径（每字节拆成两个半字节）排列，路径的字典序与键的字节序一致，
+//! 因此 `[start, end)` 内的键在树中是连续的一段。范围证明是从根出发的一棵剪枝子树：
+//! 与范围相交的子树展开，完全落在范围之外的子树只保留哈希。验证方由证明重算根哈希，
+//! 并检查每个被剪掉的子树确实在范围之外，从而确认返回的键值对没有遗漏。
+//!
+//! 前缀查询是范围查询的特例：以前缀为路径的节点之下的整棵子树展开，
+//! 根到该节点路径之外的兄弟子树都被剪掉，证明即为“从根到前缀节点的路径 + 前缀子树”。
+//!
+//! 注意：分支节点的哈希只按下标顺序串联存在的子节点哈希，并不包含下标本身，
+//! 证明对键位置的约束与现有哈希方案一致——子节点的相对顺序被绑定，具体下标则没有。
+
+use super::codec;
+use super::error::MPTError;
+use super::hasher::HashAlgorithm;
+use super::mpt::MPT;
+use super::node::{Database, FullNode, NodeCache, ShortNode};
+use super::utils::{hex_path_to_bytes, key_to_hex_path, suffix_to_nibbles, KVPair};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+/// 键范围 `[start, end)`，以十六进制路径表示；`end` 为 None 表示没有上界
+struct KeyRange {
+    start: Vec<u8>,
+    end: Option<Vec<u8>>,
+}
+
+impl KeyRange {
+    fn new(start_key: &[u8], end_key: &[u8]) -> Self {
+        KeyRange {
+            start: key_to_hex_path(start_key),
+            end: (!end_key.is_empty()).then(|| key_to_hex_path(end_key)),
+        }
+    }
+
+    /// 以 `prefix` 开头的所有键：上界为前缀路径最后一个小于 0xf 的半字节加一
+    fn prefix(prefix: &[u8]) -> Self {
+        let start = key_to_hex_path(prefix);
+        let mut end = start.clone();
+        while let Some(last) = end.pop() {
+            if last < 0xf {
+                end.push(last + 1);
+                return KeyRange {
+                    start,
+                    end: Some(end),
+                };
+            }
+        }
+        KeyRange { start, end: None }
+    }
+
+    fn contains(&self, path: &[u8]) -> bool {
+        path >= self.start.as_slice() && self.end.as_ref().is_none_or(|end| path < end)
+    }
+
+    /// 以 `prefix` 为路径前缀的所有键是否都在范围之外
+    fn excludes_subtree(&self, prefix: &[u8]) -> bool {
+        let below = prefix < self.start.as_slice() && !self.start.starts_with(prefix);
+        let above = self
+            .end
+            .as_ref()
+            .is_some_and(|end| prefix >= end.as_slice());
+        below || above
+    }
+}
+
+/// 范围证明：从根出发、剪掉范围外子树后的 MPT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeProof {
+    /// 节点哈希算法
+    pub hash: HashAlgorithm,
+    /// 剪枝后的根节点
+    pub root: RangeNode,
+}
+
+/// 范围证明中的节点
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RangeNode {
+    /// 空 MPT（根哈希为全零）
+    Empty,
+    /// 完全落在范围之外的子树，只保留其哈希
+    Pruned(Vec<u8>),
+    /// 分支节点，`children` 为 (下标, 子节点)，按下标递增
+    Branch {
+        value: Option<Vec<u8>>,
+        children: Vec<(u8, RangeNode)>,
+    },
+    /// 叶子节点
+    Leaf {
+        prefix: String,
+        suffix: String,
+        value: Option<Vec<u8>>,
+    },
+    /// 扩展节点，`next` 为它指向的分支节点
+    Extension {
+        prefix: String,
+        suffix: String,
+        next: Box<RangeNode>,
+    },
+}
+
+/// 按键排序的 (字节键, 值) 列表，字节键不经过字符串转换
+pub type BytePairs = Vec<(Vec<u8>, String)>;
+
+/// 把字节键的结果转换为字符串键值对，非 UTF-8 的键会被替换
+fn to_kv_pairs(pairs: BytePairs) -> Vec<KVPair> {
+    pairs
+        .into_iter()
+        .map(|(key, value)| KVPair::new(String::from_utf8_lossy(&key).into_owned(), value))
+        .collect()
+}
+
+impl MPT {
+    /// 查询 `[start_key, end_key)` 内的所有键值对，返回结果（按键排序）和范围证明
+    ///
+    /// # Arguments
+    /// * `start_key` - 范围下界（包含）
+    /// * `end_key` - 范围上界（不包含），为空表示没有上界
+    /// * `db` - 节点数据库
+    pub fn range_query(
+        &self,
+        start_key: &str,
+        end_key: &str,
+        db: &mut dyn Database,
+    ) -> Result<(Vec<KVPair>, RangeProof), MPTError> {
+        let (pairs, proof) =
+            self.range_query_bytes(start_key.as_bytes(), end_key.as_bytes(), db)?;
+        Ok((to_kv_pairs(pairs), proof))
+    }
+
+    /// 任意字节键的 [`range_query`](Self::range_query)，结果中的键原样返回
+    pub fn range_query_bytes(
+        &self,
+        start_key: &[u8],
+        end_key: &[u8],
+        db: &mut dyn Database,
+    ) -> Result<(BytePairs, RangeProof), MPTError> {
+        self.query_key_range(&KeyRange::new(start_key, end_key), db)
+    }
+
+    /// 查询以 `prefix` 开头的所有键值对，返回结果（按键排序）和前缀子树证明
+    ///
+    /// # Arguments
+    /// * `prefix` - 键前缀，为空时返回全部键
+    /// * `db` - 节点数据库
+    pub fn prefix_query(
+        &self,
+        prefix: &str,
+        db: &mut dyn Database,
+    ) -> Result<(Vec<KVPair>, RangeProof), MPTError> {
+        let (pairs, proof) = self.prefix_query_bytes(prefix.as_bytes(), db)?;
+        Ok((to_kv_pairs(pairs), proof))
+    }
+
+    /// 任意字节键的 [`prefix_query`](Self::prefix_query)，结果中的键原样返回
+    pub fn prefix_query_bytes(
+        &self,
+        prefix: &[u8],
+        db: &mut dyn Database,
+    ) -> Result<(BytePairs, RangeProof), MPTError> {
+        self.query_key_range(&KeyRange::prefix(prefix), db)
+    }
+
+    fn query_key_range(
+        &self,
+        range: &KeyRange,
+        db: &mut dyn Database,
+    ) -> Result<(BytePairs, RangeProof), MPTError> {
+        let mut results = Vec::new();
+        let root = match self.read_root(db)? {
+            Some(root) => {
+                let mut cache = self.cache.as_ref().and_then(|m| m.lock().ok());
+                prove_full_node(
+                    &root,
+                    &mut Vec::new(),
+                    range,
+                    db,
+                    cache.as_deref_mut(),
+                    &mut results,
+                )?
+            }
+            None => RangeNode::Empty,
+        };
+        let proof = RangeProof {
+            hash: self.hash_algorithm,
+            root,
+        };
+        Ok((results, proof))
+    }
+}
+
+/// 为分支节点生成证明，`path` 为到达该节点的路径
+fn prove_full_node(
+    node: &Arc<RwLock<FullNode>>,
+    path: &mut Vec<u8>,
+    range: &KeyRange,
+    db: &mut dyn Database,
+    mut cache: Option<&mut NodeCache>,
+    results: &mut BytePairs,
+) -> Result<RangeNode, MPTError> {
+    let mut guard = node
+        .write()
+        .map_err(|_| MPTError::LockError("Failed to lock FullNode".to_string()))?;
+
+    if let Some(value) = &guard.value {
+        if range.contains(path) {
+            results.push((
+                hex_path_to_bytes(path),
+                String::from_utf8_lossy(value).to_string(),
+            ));
+        }
+    }
+
+    let mut children = Vec::new();
+    for index in 0..16u8 {
+        let child_hash = match &guard.children_hash[index as usize] {
+            Some(hash) => hash.clone(),
+            None => continue,
+        };
+        path.push(index);
+        let child = if range.excludes_subtree(path) {
+            RangeNode::Pruned(child_hash)
+        } else {
+            let child = guard
+                .get_child(index as usize, db, cache.as_deref_mut())?
+                .ok_or(MPTError::NodeNotFound)?;
+            prove_short_node(&child, path, range, db, cache.as_deref_mut(), results)?
+        };
+        path.pop();
+        children.push((index, child));
+    }
+
+    Ok(RangeNode::Branch {
+        value: guard.value.clone(),
+        children,
+    })
+}
+
+/// 为叶子或扩展节点生成证明，`path` 包含父分支节点中的下标
+fn prove_short_node(
+    node: &Arc<RwLock<ShortNode>>,
+    path: &mut Vec<u8>,
+    range: &KeyRange,
+    db: &mut dyn Database,
+    mut cache: Option<&mut NodeCache>,
+    results: &mut BytePairs,
+) -> Result<RangeNode, MPTError> {
+    let mut guard = node
+        .write()
+        .map_err(|_| MPTError::LockError("Failed to lock ShortNode".to_string()))?;
+    let suffix = guard.suffix_nibbles()?;
+    let depth = path.len();
+    path.extend_from_slice(&suffix);
+
+    let proof = if guard.is_leaf {
+        if let Some(value) = &guard.value {
+            if range.contains(path) {
+                results.push((
+                    hex_path_to_bytes(path),
+                    String::from_utf8_lossy(value).to_string(),
+                ));
+            }
+        }
+        RangeNode::Leaf {
+            prefix: guard.prefix.clone(),
+            suffix: guard.suffix.clone(),
+            value: guard.value.clone(),
+        }
+    } else {
+        let next = if range.excludes_subtree(path) {
+            RangeNode::Pruned(guard.next_node_hash.to_vec())
+        } else {
+            let next = guard
+                .get_next_node(db, cache.as_deref_mut())?
+                .ok_or(MPTError::NodeNotFound)?;
+            prove_full_node(&next, path, range, db, cache, results)?
+        };
+        RangeNode::Extension {
+            prefix: guard.prefix.clone(),
+            suffix: guard.suffix.clone(),
+            next: Box::new(next),
+        }
+    };
+
+    path.truncate(depth);
+    Ok(proof)
+}
+
+impl RangeProof {
+    /// 编码为二进制格式（见 [`codec`]）
+    pub fn to_bytes(&self) -> Vec<u8> {
+        codec::encode_range_proof(self)
+    }
+
+    /// 从 [`to_bytes`](Self::to_bytes) 的结果或旧版本的 JSON 编码（只有节点，使用 SHA-256）解码
+    pub fn from_bytes(data: &[u8]) -> Result<Self, MPTError> {
+        if codec::is_legacy_json(data) {
+            return Ok(RangeProof {
+                hash: HashAlgorithm::Sha256,
+                root: serde_json::from_slice(data)?,
+            });
+        }
+        codec::decode_range_proof(data)
+    }
+
+    /// 由证明重算根哈希，并收集证明中 `[start_key, end_key)` 内的全部键值对
+    ///
+    /// 节点结构不合法，或者某个被剪掉的子树可能包含范围内的键时返回 None
+    pub fn compute_root(&self, start_key: &str, end_key: &str) -> Option<([u8; 32], Vec<KVPair>)> {
+        let (root, pairs) = self.compute_root_bytes(start_key.as_bytes(), end_key.as_bytes())?;
+        Some((root, to_kv_pairs(pairs)))
+    }
+
+    /// 任意字节键的 [`compute_root`](Self::compute_root)
+    pub fn compute_root_bytes(
+        &self,
+        start_key: &[u8],
+        end_key: &[u8],
+    ) -> Option<([u8; 32], BytePairs)> {
+        self.compute_root_in(&KeyRange::new(start_key, end_key))
+    }
+
+    /// 由前缀查询的证明重算根哈希，并收集以 `prefix` 开头的全部键值对
+    pub fn compute_prefix_root(&self, prefix: &str) -> Option<([u8; 32], Vec<KVPair>)> {
+        let (root, pairs) = self.compute_prefix_root_bytes(prefix.as_bytes())?;
+        Some((root, to_kv_pairs(pairs)))
+    }
+
+    /// 任意字节键的 [`compute_prefix_root`](Self::compute_prefix_root)
+    pub fn compute_prefix_root_bytes(&self, prefix: &[u8]) -> Option<([u8; 32], BytePairs)> {
+        self.compute_root_in(&KeyRange::prefix(prefix))
+    }
+
+    fn compute_root_in(&self, range: &KeyRange) -> Option<([u8; 32], BytePairs)> {
+        let mut results = Vec::new();
+        let root = match &self.root {
+            RangeNode::Empty => [0u8; 32],
+            root @ RangeNode::Branch { .. } => {
+                root.walk(self.hash, &mut Vec::new(), range, &mut results)?
+            }
+            _ => return None,
+        };
+        Some((root, results))
+    }
+
+    /// 对照根哈希验证证明，返回范围内的全部键值对
+    pub fn verify(&self, start_key: &str, end_key: &str, root_hash: &[u8]) -> Option<Vec<KVPair>> {
+        let (root, results) = self.compute_root(start_key, end_key)?;
+        (root.as_slice() == root_hash).then_some(results)
+    }
+
+    /// 任意字节键的 [`verify`](Self::verify)
+    pub fn verify_bytes(
+        &self,
+        start_key: &[u8],
+        end_key: &[u8],
+        root_hash: &[u8],
+    ) -> Option<BytePairs> {
+        let (root, results) = self.compute_root_bytes(start_key, end_key)?;
+        (root.as_slice() == root_hash).then_some(results)
+    }
+}
+
+impl RangeNode {
+    /// 重算节点哈希并收集范围内的键值对
+    fn walk(
+        &self,
+        hash: HashAlgorithm,
+        path: &mut Vec<u8>,
+        range: &KeyRange,
+        results: &mut BytePairs,
+    ) -> Option<[u8; 32]> {
+        let mut hasher = hash.hasher();
+        match self {
+            RangeNode::Empty => return None,
+            RangeNode::Pruned(pruned) => {
+                if !range.excludes_subtree(path) {
+                    return None;
+                }
+                return pruned.as_slice().try_into().ok();
+            }
+            RangeNode::Branch { value, children } => {
+                if let Some(value) = value {
+                    push_in_range(path, value, range, results)?;
+                }
+                let mut last = None;
+                for (index, child) in children {
+                    if *index >= 16 || last.is_some_and(|last| *index <= last) {
+                        return None;
+                    }
+                    last = Some(*index);
+                    if !matches!(
+                        child,
+                        RangeNode::Pruned(_) | RangeNode::Leaf { .. } | RangeNode::Extension { .. }
+                    ) {
+                        return None;
+                    }
+                    path.push(*index);
+                    let child_hash = child.walk(hash, path, range, results);
+                    path.pop();
+                    hasher.update(child_hash?);
+                }
+                if let Some(value) = value {
+                    hasher.update(value);
+                }
+            }
+            RangeNode::Leaf {
+                prefix,
+                suffix,
+                value,
+            } => {
+                let depth = path.len();
+                path.extend(suffix_to_nibbles(suffix)?);
+                if let Some(value) = value {
+                    push_in_range(path, value, range, results)?;
+                }
+                path.truncate(depth);
+                hasher.update(prefix.as_bytes());
+                hasher.update(suffix.as_bytes());
+                if let Some(value) = value {
+                    hasher.update(value);
+                }
+            }
+            RangeNode::Extension {
+                prefix,
+                suffix,
+                next,
+            } => {
+                if !matches!(**next, RangeNode::Pruned(_) | RangeNode::Branch { .. }) {
+                    return None;
+                }
+                let depth = path.len();
+                path.extend(suffix_to_nibbles(suffix)?);
+                let next_hash = next.walk(hash, path, range, results);
+                path.truncate(depth);
+                hasher.update(prefix.as_bytes());
+                hasher.update(suffix.as_bytes());
+                hasher.update(next_hash?);
+            }
+        }
+        Some(hasher.finalize())
+    }
+}
+
+/// 键在范围内时记入结果；范围内的键必须由完整的字节组成
+fn push_in_range(
+    path: &[u8],
+    value: &[u8],
+    range: &KeyRange,
+    results: &mut BytePairs,
+) -> Option<()> {
+    if range.contains(path) {
+        if !path.len().is_multiple_of(2) {
+            return None;
+        }
+        results.push((
+            hex_path_to_bytes(path),
+            String::from_utf8_lossy(value).to_string(),
+        ));
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpt::db::MemoryDatabase;
+
+    fn build(keys: &[&str]) -> (MPT, MemoryDatabase) {
+        let mut db = MemoryDatabase::new();
+        let mut mpt = MPT::new(None);
+        for key in keys {
+            let kv = KVPair::new(key.to_string(), format!("v-{}", key));
+            mpt.insert(kv, &mut db, true, false).unwrap();
+        }
+        (mpt, db)
+    }
+
+    fn keys(pairs: &[KVPair]) -> Vec<&str> {
+        pairs.iter().map(KVPair::get_key).collect()
+    }
+
+    fn entries(pairs: &[KVPair]) -> Vec<(&str, &str)> {
+        pairs
+            .iter()
+            .map(|kv| (kv.get_key(), kv.get_value()))
+            .collect()
+    }
+
+    #[test]
+    fn test_range_query_returns_sorted_keys_and_verifies() {
+        let all = [
+            "category:books",
+            "category:art",
+            "category",
+            "cat",
+            "catalog",
+            "dog",
+            "category:music",
+            "categoryx",
+        ];
+        let (mpt, mut db) = build(&all);
+
+        let (pairs, proof) = mpt.range_query("category:", "category;", &mut db).unwrap();
+        assert_eq!(
+            keys(&pairs),
+            vec!["category:art", "category:books", "category:music"]
+        );
+        assert_eq!(pairs[0].get_value(), "v-category:art");
+
+        let decoded = RangeProof::from_bytes(&proof.to_bytes()).unwrap();
+        let verified = decoded
+            .verify("category:", "category;", &mpt.root_hash)
+            .unwrap();
+        assert_eq!(entries(&verified), entries(&pairs));
+
+        // 没有上界时返回起点之后的全部键
+        let (pairs, proof) = mpt.range_query("category", "", &mut db).unwrap();
+        assert_eq!(
+            keys(&pairs),
+            vec![
+                "category",
+                "category:art",
+                "category:books",
+                "category:music",
+                "categoryx",
+                "dog"
+            ]
+        );
+        let verified = proof.verify("category", "", &mpt.root_hash).unwrap();
+        assert_eq!(entries(&verified), entries(&pairs));
+    }
+
+    #[test]
+    fn test_range_proof_rejects_tampering() {
+        let (mpt, mut db) = build(&["a1", "a2", "b1", "b2", "c1"]);
+        let (pairs, proof) = mpt.range_query("b", "c", &mut db).unwrap();
+        assert_eq!(keys(&pairs), vec!["b1", "b2"]);
+
+        // 用于其他范围或其他根哈希时验证失败
+        assert!(proof.verify("b", "c", &[1u8; 32]).is_none());
+        assert!(proof.verify("a", "c", &mpt.root_hash).is_none());
+
+        // 把范围内的子树替换为哈希，隐藏其中的键
+        fn hide_in_range(proof: &mut RangeNode) -> bool {
+            match proof {
+                RangeNode::Branch { children, .. } => {
+                    children.iter_mut().any(|(_, child)| hide_in_range(child))
+                }
+                RangeNode::Extension { next, .. } => hide_in_range(next),
+                RangeNode::Leaf {
+                    prefix,
+                    suffix,
+                    value,
+                } => {
+                    let mut node = ShortNode {
+                        prefix: prefix.clone(),
+                        suffix: suffix.clone(),
+                        is_leaf: true,
+                        value: value.clone(),
+                        ..Default::default()
+                    };
+                    node.update_hash();
+                    *proof = RangeNode::Pruned(node.node_hash.to_vec());
+                    true
+                }
+                _ => false,
+            }
+        }
+        let mut hidden = proof.clone();
+        assert!(hide_in_range(&mut hidden.root));
+        assert!(hidden.verify("b", "c", &mpt.root_hash).is_none());
+    }
+
+    #[test]
+    fn test_prefix_query() {
+        let (mpt, mut db) = build(&["car", "card", "care", "cat", "dog", "cab"]);
+
+        let (pairs, proof) = mpt.prefix_query("car", &mut db).unwrap();
+        assert_eq!(keys(&pairs), vec!["car", "card", "care"]);
+        let (root, verified) = proof.compute_prefix_root("car").unwrap();
+        assert_eq!(root, mpt.root_hash);
+        assert_eq!(entries(&verified), entries(&pairs));
+        // 同一个证明不能冒充更宽的前缀
+        assert!(proof.compute_prefix_root("ca").is_none());
+
+        let (pairs, _) = mpt.prefix_query("", &mut db).unwrap();
+        assert_eq!(pairs.len(), 6);
+        let (pairs, proof) = mpt.prefix_query("x", &mut db).unwrap();
+        assert!(pairs.is_empty());
+        assert_eq!(proof.compute_prefix_root("x").unwrap().0, mpt.root_hash);
+    }
+
+    #[test]
+    fn test_range_query_on_empty_trie() {
+        let (mpt, mut db) = build(&[]);
+        let (pairs, proof) = mpt.range_query("a", "z", &mut db).unwrap();
+        assert!(pairs.is_empty());
+        assert!(proof.verify("a", "z", &[0u8; 32]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,216 @@
+//! 分时片的 batch_fix
+//!
+//! 批量导入后一次性执行 `batch_fix` 可能让 storager 停顿数秒。
+//! 这里把脏子树的修复拆成可中断的深度优先遍历：每次调用 [`MPT::fix_slice`]
+//! 只在给定的时间预算内推进，调用方可以在两个时间片之间释放锁、继续服务请求。
+//! 发布根哈希之前调用 [`MPT::finish_sliced_fix`] 强制完成剩余工作。
+
+use super::error::MPTError;
+use super::mpt::MPT;
+use super::node::{Database, FullNode, ShortNode, WriteBatch};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// 遍历栈中的一项
+enum FixTask {
+    /// 首次访问 FullNode：压入子节点
+    EnterFull(Arc<RwLock<FullNode>>),
+    /// 子节点全部修复后：重算 FullNode 哈希并持久化
+    ExitFull(Arc<RwLock<FullNode>>),
+    /// 首次访问 ShortNode
+    EnterShort(Arc<RwLock<ShortNode>>),
+    /// next_node 修复后：重算 ShortNode 哈希并持久化
+    ExitShort(Arc<RwLock<ShortNode>>),
+}
+
+/// 时间片统计
+#[derive(Debug, Clone, Default)]
+pub struct SliceMetrics {
+    /// 已执行的时间片数量
+    pub slices: u64,
+    /// 已修复的节点数量
+    pub nodes_fixed: u64,
+    /// 所有时间片的总耗时
+    pub total: Duration,
+    /// 最长的单个时间片
+    pub max_slice: Duration,
+    /// 最近一个时间片的耗时
+    pub last_slice: Duration,
+}
+
+impl SliceMetrics {
+    /// 记录一个时间片的耗时
+    pub fn record(&mut self, elapsed: Duration) {
+        self.slices += 1;
+        self.total += elapsed;
+        self.last_slice = elapsed;
+        if elapsed > self.max_slice {
+            self.max_slice = elapsed;
+        }
+    }
+}
+
+/// 一次进行中的分片修复
+#[derive(Default)]
+pub struct SlicedFix {
+    stack: Vec<FixTask>,
+    metrics: SliceMetrics,
+}
+
+impl SlicedFix {
+    /// 当前遍历是否已经结束
+    pub fn is_idle(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// 时间片统计
+    pub fn metrics(&self) -> &SliceMetrics {
+        &self.metrics
+    }
+}
+
+fn lock_err(what: &str) -> MPTError {
+    MPTError::LockError(format!("Failed to lock {} during sliced fix", what))
+}
+
+impl MPT {
+    /// 树中是否存在尚未修复的脏节点
+    pub fn needs_fix(&self) -> bool {
+        self.root
+            .as_ref()
+            .and_then(|root| root.read().ok().map(|r| r.is_dirty))
+            .unwrap_or(false)
+    }
+
+    /// 开始一次分片修复（不做任何修复工作）
+    pub fn begin_sliced_fix(&self) -> SlicedFix {
+        let mut fix = SlicedFix::default();
+        if let Some(root) = &self.root {
+            fix.stack.push(FixTask::EnterFull(root.clone()));
+        }
+        fix
+    }
+
+    /// 在时间预算内推进修复
+    ///
+    /// 返回 `true` 表示脏子树已全部修复，根哈希已更新并持久化。
+    /// 两次调用之间插入的新数据会在后续时间片中被重新发现。
+    /// 一个时间片内修复的节点在时间片结束时一次性写入数据库。
+    pub fn fix_slice(
+        &mut self,
+        fix: &mut SlicedFix,
+        db: &mut dyn Database,
+        budget: Duration,
+    ) -> Result<bool, MPTError> {
+        let start = Instant::now();
+        let mut batch = WriteBatch::new();
+
+        loop {
+            if fix.stack.is_empty() && !self.restart_if_dirty(fix)? {
+                break;
+            }
+            // 每个时间片至少推进一步，避免预算过小时永远无法完成
+            if let Some(task) = fix.stack.pop() {
+                if Self::run_fix_task(task, &mut fix.stack, &mut batch)? {
+                    fix.metrics.nodes_fixed += 1;
+                }
+            }
+            if start.elapsed() >= budget {
+                break;
+            }
+        }
+
+        fix.metrics.record(start.elapsed());
+
+        let done = fix.stack.is_empty();
+        if done {
+            if let Some(root) = &self.root {
+                self.root_hash = root.read().map_err(|_| lock_err("root"))?.node_hash;
+            }
+            self.stage_mpt_update(&mut batch)?;
+        }
+        db.write_batch(batch)?;
+        Ok(done)
+    }
+
+    /// 强制完成剩余的修复工作（发布根哈希之前调用）
+    pub fn finish_sliced_fix(
+        &mut self,
+        mut fix: SlicedFix,
+        db: &mut dyn Database,
+    ) -> Result<SliceMetrics, MPTError> {
+        while !self.fix_slice(&mut fix, db, Duration::MAX)? {}
+        Ok(fix.metrics)
+    }
+
+    /// 栈已空但根节点又被写脏时，重新开始一轮遍历
+    fn restart_if_dirty(&self, fix: &mut SlicedFix) -> Result<bool, MPTError> {
+        match &self.root {
+            Some(root) if root.read().map_err(|_| lock_err("root"))?.is_dirty => {
+                fix.stack.push(FixTask::EnterFull(root.clone()));
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// 执行一个遍历步骤，返回是否修复了一个节点
+    fn run_fix_task(
+        task: FixTask,
+        stack: &mut Vec<FixTask>,
+        batch: &mut WriteBatch,
+    ) -> Result<bool, MPTError> {
+        match task {
+            FixTask::EnterFull(node) => {
+                let guard = node.read().map_err(|_| lock_err("FullNode"))?;
+                if !guard.is_dirty {
+                    return Ok(false);
+                }
+                let children: Vec<_> = guard.children.iter().flatten().cloned().collect();
+                drop(guard);
+
+                stack.push(FixTask::ExitFull(node));
+                stack.extend(children.into_iter().map(FixTask::EnterShort));
+                Ok(false)
+            }
+            FixTask::EnterShort(node) => {
+                let guard = node.read().map_err(|_| lock_err("ShortNode"))?;
+                if !guard.is_dirty {
+                    return Ok(false);
+                }
+                let next = guard.next_node.clone();
+                drop(guard);
+
+                stack.push(FixTask::ExitShort(node));
+                if let Some(next) = next {
+                    stack.push(FixTask::EnterFull(next));
+                }
+                Ok(false)
+            }
+            FixTask::ExitFull(node) => {
+                let mut guard = node.write().map_err(|_| lock_err("FullNode"))?;
+                for i in 0..16 {
+                    if let Some(child) = &guard.children[i] {
+                        let hash = child.read().map_err(|_| lock_err("child"))?.node_hash;
+                        guard.children_hash[i] = Some(hash.to_vec());
+                    }
+                }
+                guard.update_hash();
+                guard.is_dirty = false;
+                batch.put(&guard.node_hash, &guard.serialize()?);
+                Ok(true)
+            }
+            FixTask::ExitShort(node) => {
+                let mut guard = node.write().map_err(|_| lock_err("ShortNode"))?;
+                if let Some(next) = guard.next_node.clone() {
+                    guard.next_node_hash =
+                        next.read().map_err(|_| lock_err("next node"))?.node_hash;
+                }
+                guard.update_hash();
+                guard.is_dirty = false;
+                batch.put(&guard.node_hash, &guard.serialize()?);
+                Ok(true)
+            }
+        }
+    }
+}
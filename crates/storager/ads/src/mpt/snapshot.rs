@@ -0,0 +1,139 @@
+//! MPT 快照
+//!
+//! 插入会原地修改活动树中的节点，因此快照不能直接共享活动树的节点。
+//! 不过修复后的节点以哈希为键写入数据库后不再改变：[`MPT::snapshot`] 先完成修复，
+//! 再从数据库按需加载固定根下的节点。快照的所有克隆共享同一组已加载的节点并可以并发查询，
+//! 长时间的查询或导出在一致的版本上进行，活动树上的写入不受影响。
+//!
+//! 快照的节点仍在节点数据库中，回收（[`MPT::prune`]）时应把快照的根放入保留列表。
+
+use super::error::MPTError;
+use super::mpt::MPT;
+use super::node::Database;
+use super::proof::MPTProof;
+use super::range::RangeProof;
+use super::utils::KVPair;
+use std::sync::Arc;
+
+/// 固定在某个根哈希上的只读视图，克隆开销很小
+#[derive(Clone)]
+pub struct MptSnapshot {
+    root_hash: [u8; 32],
+    trie: Arc<MPT>,
+}
+
+impl MPT {
+    /// 创建固定在当前根上的快照
+    ///
+    /// 先执行 [`batch_fix`](Self::batch_fix)，保证当前版本的节点全部落盘
+    pub fn snapshot(&mut self, db: &mut dyn Database) -> Result<MptSnapshot, MPTError> {
+        self.batch_fix(db)?;
+        MptSnapshot::at_root(self.root_hash, db)
+    }
+}
+
+impl MptSnapshot {
+    /// 打开数据库中某个历史根的快照，根节点不存在时返回 [`MPTError::NodeNotFound`]
+    pub fn at_root(root_hash: [u8; 32], db: &mut dyn Database) -> Result<Self, MPTError> {
+        if root_hash != [0u8; 32] && db.get(&root_hash)?.is_none() {
+            return Err(MPTError::NodeNotFound);
+        }
+        Ok(Self {
+            root_hash,
+            trie: Arc::new(MPT::load_from_db(&root_hash, db, None)?),
+        })
+    }
+
+    /// 快照对应的根哈希
+    pub fn root_hash(&self) -> [u8; 32] {
+        self.root_hash
+    }
+
+    /// 查询键，证明对照快照的根哈希验证
+    pub fn query_by_key(
+        &self,
+        key: &str,
+        db: &mut dyn Database,
+    ) -> Result<(String, MPTProof), MPTError> {
+        self.trie.query_by_key(key, db)
+    }
+
+    /// 范围查询，见 [`MPT::range_query`]
+    pub fn range_query(
+        &self,
+        start_key: &str,
+        end_key: &str,
+        db: &mut dyn Database,
+    ) -> Result<(Vec<KVPair>, RangeProof), MPTError> {
+        self.trie.range_query(start_key, end_key, db)
+    }
+
+    /// 前缀查询，见 [`MPT::prefix_query`]
+    pub fn prefix_query(
+        &self,
+        prefix: &str,
+        db: &mut dyn Database,
+    ) -> Result<(Vec<KVPair>, RangeProof), MPTError> {
+        self.trie.prefix_query(prefix, db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpt::db::MemoryDatabase;
+    use crate::mpt::proof::compute_mpt_root;
+
+    fn insert(mpt: &mut MPT, db: &mut MemoryDatabase, key: &str, value: &str) {
+        let kv = KVPair::new(key.to_string(), value.to_string());
+        mpt.insert(kv, db, true, false).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_ignores_later_writes() {
+        let mut db = MemoryDatabase::new();
+        let mut mpt = MPT::new(None);
+        insert(&mut mpt, &mut db, "rust", "f1");
+        insert(&mut mpt, &mut db, "go", "f2");
+
+        let snapshot = mpt.snapshot(&mut db).unwrap();
+        assert_eq!(snapshot.root_hash(), mpt.root_hash);
+        let clone = snapshot.clone();
+
+        insert(&mut mpt, &mut db, "rust", "f1,f3");
+        insert(&mut mpt, &mut db, "java", "f4");
+        mpt.batch_fix(&mut db).unwrap();
+        assert_ne!(mpt.root_hash, snapshot.root_hash());
+
+        // 快照可以在另一个线程中用自己的数据库句柄查询
+        let mut reader_db = db.clone();
+        let (value, proof) = std::thread::spawn(move || clone.query_by_key("rust", &mut reader_db))
+            .join()
+            .unwrap()
+            .unwrap();
+        assert_eq!(value, "f1");
+        assert_eq!(compute_mpt_root(&value, &proof), snapshot.root_hash());
+
+        let (pairs, _) = snapshot.range_query("", "", &mut db).unwrap();
+        let keys: Vec<_> = pairs.iter().map(|kv| kv.get_key()).collect();
+        assert_eq!(keys, vec!["go", "rust"]);
+
+        // 活动树仍然看到最新的写入
+        let (value, _) = mpt.query_by_key("rust", &mut db).unwrap();
+        assert_eq!(value, "f1,f3");
+    }
+
+    #[test]
+    fn test_snapshot_of_empty_and_missing_roots() {
+        let mut db = MemoryDatabase::new();
+        let mut mpt = MPT::new(None);
+        let snapshot = mpt.snapshot(&mut db).unwrap();
+        let (pairs, _) = snapshot.range_query("", "", &mut db).unwrap();
+        assert!(pairs.is_empty());
+
+        assert!(matches!(
+            MptSnapshot::at_root([3u8; 32], &mut db),
+            Err(MPTError::NodeNotFound)
+        ));
+    }
+}
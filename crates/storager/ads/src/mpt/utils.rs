@@ -59,9 +59,12 @@ pub fn char_to_hex_index(byte: u8) -> Option<usize> {
 }
 
 /// 将键转换为适合 Trie 的路径（每个字节拆分为两个半字节）
-pub fn key_to_hex_path(key: &str) -> Vec<u8> {
-    let mut path = Vec::new();
-    for byte in key.as_bytes() {
+///
+/// 键可以是任意字节序列，字符串键按其 UTF-8 字节处理
+pub fn key_to_hex_path(key: impl AsRef<[u8]>) -> Vec<u8> {
+    let key = key.as_ref();
+    let mut path = Vec::with_capacity(key.len() * 2);
+    for byte in key {
         path.push((byte >> 4) & 0x0F); // 高4位
         path.push(byte & 0x0F); // 低4位
     }
@@ -82,11 +85,30 @@ pub fn hex_path_to_bytes(path: &[u8]) -> Vec<u8> {
 }
 
 /// 从十六进制路径重构原始字符串键
+///
+/// 非 UTF-8 的键会被替换为 U+FFFD，二进制键应使用 [`hex_path_to_bytes`]
 pub fn hex_path_to_key(path: &[u8]) -> String {
     let bytes = hex_path_to_bytes(path);
     String::from_utf8_lossy(&bytes).to_string()
 }
 
+/// 将半字节路径编码为节点 suffix（每个半字节一个十六进制字符）
+pub fn nibbles_to_suffix(nibbles: &[u8]) -> String {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    nibbles
+        .iter()
+        .map(|&n| HEX[byte_to_hex_index(n)] as char)
+        .collect()
+}
+
+/// 将节点 suffix 解码为半字节路径，遇到非十六进制字符返回 `None`
+pub fn suffix_to_nibbles(suffix: &str) -> Option<Vec<u8>> {
+    suffix
+        .bytes()
+        .map(|c| char_to_hex_index(c).map(|n| n as u8))
+        .collect()
+}
+
 /// 计算两个字符串的公共前缀
 pub fn common_prefix(str1: &str, str2: &str) -> String {
     let bytes1 = str1.as_bytes();
@@ -171,6 +193,23 @@ mod tests {
         assert_eq!(bytes, vec![0x61, 0x62]); // 'a', 'b'
     }
 
+    #[test]
+    fn test_binary_key_roundtrip() {
+        let key = [0x00u8, 0xff, 0x80, 0xc3, 0x28, 0x0a];
+        let path = key_to_hex_path(key);
+        assert_eq!(path, vec![0, 0, 15, 15, 8, 0, 12, 3, 2, 8, 0, 10]);
+        assert_eq!(hex_path_to_bytes(&path), key);
+
+        let suffix = nibbles_to_suffix(&path);
+        assert_eq!(suffix, "00ff80c3280a");
+        assert_eq!(suffix_to_nibbles(&suffix), Some(path));
+        assert_eq!(suffix_to_nibbles(""), Some(vec![]));
+        assert_eq!(suffix_to_nibbles("0g"), None);
+
+        // 非 UTF-8 键经过字符串转换会丢失信息
+        assert_ne!(hex_path_to_key(&key_to_hex_path(key)).as_bytes(), key);
+    }
+
     #[test]
     fn test_common_prefix() {
         assert_eq!(common_prefix("hello", "help"), "hel");
@@ -0,0 +1,492 @@
+//! Sparse Merkle Tree
+//!
+//! 256 层的稀疏 Merkle 树，每个键是一个 256 位的路径（通常为 `SHA256(keyword)`），
+//! 键的最高位决定从根向左（0）还是向右（1）走。任意键都有固定的位置，
+//! 因此不存在的键同样可以证明：它的位置上是空叶子。
+//!
+//! 绝大多数子树是空的，空子树的哈希只由高度决定，预先计算在 [`default_hash`] 中。
+//! 树只保存至少包含两个叶子的内部节点，只含一个叶子的子树按需由叶子向上计算，
+//! 内存占用与叶子数成正比。
+//!
+//! ```text
+//! leaf       = SHA256(0x00 || key || value)
+//! node       = SHA256(0x01 || left || right)
+//! default[0] = 全零，default[h + 1] = node(default[h], default[h])
+//! ```
+//!
+//! 证明只携带不是空子树的兄弟节点，另用 256 位的位图标记它们所在的高度，
+//! n 个叶子的树中证明约为 32 + 32 * log2(n) 字节。
+//!
+//! # 示例
+//!
+//! ```
+//! use esa_rust::smt::{key_hash, SparseMerkleTree};
+//!
+//! let mut tree = SparseMerkleTree::new();
+//! let key = key_hash("rust");
+//! tree.insert(key, [1u8; 32]);
+//!
+//! let proof = tree.prove(&key);
+//! assert!(proof.verify(&key, Some(&[1u8; 32]), &tree.root()));
+//!
+//! // 不存在的键：同样格式的证明，叶子为空
+//! let absent = key_hash("java");
+//! assert!(tree.prove(&absent).verify(&absent, None, &tree.root()));
+//! ```
+
+use crate::merkle_tree::node_hash;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+
+/// 哈希值，也用作键
+pub type Hash = [u8; 32];
+
+/// 树的高度（键的位数）
+pub const DEPTH: usize = 256;
+
+lazy_static! {
+    static ref DEFAULT_HASHES: Vec<Hash> = {
+        let mut hashes = vec![[0u8; 32]];
+        for height in 0..DEPTH {
+            hashes.push(node_hash(&hashes[height], &hashes[height]));
+        }
+        hashes
+    };
+}
+
+/// 高度为 `height` 的空子树的哈希（0 为叶子，[`DEPTH`] 为整棵树）
+pub fn default_hash(height: usize) -> Hash {
+    DEFAULT_HASHES[height]
+}
+
+/// 由字符串得到树中的键
+pub fn key_hash(key: &str) -> Hash {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+/// 计算叶子哈希，键参与哈希，不同位置的相同值不会得到相同的叶子
+pub fn leaf_hash(key: &Hash, value: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0u8]);
+    hasher.update(key);
+    hasher.update(value);
+    hasher.finalize().into()
+}
+
+/// 计算 fid 列表的值哈希: SHA256(count as u32 LE || (len as u32 LE || fid)...)
+pub fn fids_hash(fids: &[String]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update((fids.len() as u32).to_le_bytes());
+    for fid in fids {
+        hasher.update((fid.len() as u32).to_le_bytes());
+        hasher.update(fid.as_bytes());
+    }
+    hasher.finalize().into()
+}
+
+/// 键从最高位起的第 `index` 位
+fn bit(key: &Hash, index: usize) -> bool {
+    key[index / 8] >> (7 - index % 8) & 1 == 1
+}
+
+/// 键所在的高度为 `height` 的子树的前缀（低 `height` 位清零）
+fn prefix(key: &Hash, height: usize) -> Hash {
+    let mut prefix = *key;
+    for index in DEPTH - height..DEPTH {
+        prefix[index / 8] &= !(1 << (7 - index % 8));
+    }
+    prefix
+}
+
+/// 高度为 `height` 的子树覆盖的最后一个键（低 `height` 位置一）
+fn last_key(prefix: &Hash, height: usize) -> Hash {
+    let mut last = *prefix;
+    for index in DEPTH - height..DEPTH {
+        last[index / 8] |= 1 << (7 - index % 8);
+    }
+    last
+}
+
+/// 高度为 `height` 的兄弟子树的前缀
+fn sibling_prefix(key: &Hash, height: usize) -> Hash {
+    let mut sibling = prefix(key, height);
+    let index = DEPTH - 1 - height;
+    sibling[index / 8] ^= 1 << (7 - index % 8);
+    sibling
+}
+
+/// 由高度为 `height` 的子节点和兄弟节点计算父节点
+fn parent_hash(key: &Hash, height: usize, child: &Hash, sibling: &Hash) -> Hash {
+    if bit(key, DEPTH - 1 - height) {
+        node_hash(sibling, child)
+    } else {
+        node_hash(child, sibling)
+    }
+}
+
+/// 键的包含或非成员资格证明
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmtProof {
+    /// 第 h 位为 1 表示高度 h 的兄弟节点不是空子树（字节内从低位起）
+    pub bitmap: [u8; 32],
+    /// 不是空子树的兄弟节点哈希，自底向上
+    pub siblings: Vec<Hash>,
+}
+
+impl SmtProof {
+    /// 由键、叶子的值（`None` 表示空叶子）和路径计算根
+    ///
+    /// 位图与兄弟节点的数量不一致时返回 None
+    pub fn compute_root(&self, key: &Hash, value: Option<&Hash>) -> Option<Hash> {
+        let mut current = value.map_or(default_hash(0), |value| leaf_hash(key, value));
+        let mut siblings = self.siblings.iter();
+        for height in 0..DEPTH {
+            let sibling = if self.bitmap[height / 8] >> (height % 8) & 1 == 1 {
+                *siblings.next()?
+            } else {
+                default_hash(height)
+            };
+            current = parent_hash(key, height, &current, &sibling);
+        }
+        siblings.next().is_none().then_some(current)
+    }
+
+    /// 验证键在给定根下的值为 `value`；`None` 验证键不存在
+    pub fn verify(&self, key: &Hash, value: Option<&Hash>, root: &Hash) -> bool {
+        self.compute_root(key, value).as_ref() == Some(root)
+    }
+
+    /// 编码: bitmap(32) | siblings(32 * 位图中 1 的个数)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(32 * (1 + self.siblings.len()));
+        out.extend_from_slice(&self.bitmap);
+        for sibling in &self.siblings {
+            out.extend_from_slice(sibling);
+        }
+        out
+    }
+
+    /// 解码，格式错误时返回 None
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (bitmap, rest) = bytes.split_at_checked(32)?;
+        let count: u32 = bitmap.iter().map(|byte| byte.count_ones()).sum();
+        if rest.len() != 32 * count as usize {
+            return None;
+        }
+        Some(SmtProof {
+            bitmap: bitmap.try_into().ok()?,
+            siblings: rest
+                .chunks_exact(32)
+                .map(|chunk| chunk.try_into().unwrap())
+                .collect(),
+        })
+    }
+}
+
+/// keyword 的 fid 列表及其在树中的证明，fid 列表为空表示 keyword 不存在
+///
+/// 叶子的键为 [`key_hash`]`(keyword)`，值为 [`fids_hash`]`(fids)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordProof {
+    pub keyword: String,
+    pub fids: Vec<String>,
+    pub path: SmtProof,
+}
+
+impl KeywordProof {
+    /// 由 keyword、fid 列表和路径计算根
+    pub fn compute_root(&self) -> Option<Hash> {
+        let value = (!self.fids.is_empty()).then(|| fids_hash(&self.fids));
+        self.path
+            .compute_root(&key_hash(&self.keyword), value.as_ref())
+    }
+
+    /// 编码
+    ///
+    /// 格式: len(u32) | keyword | count(u32) | 每项 [len(u32) | fid] | path，整数均为小端序
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&(self.keyword.len() as u32).to_le_bytes());
+        out.extend_from_slice(self.keyword.as_bytes());
+        out.extend_from_slice(&(self.fids.len() as u32).to_le_bytes());
+        for fid in &self.fids {
+            out.extend_from_slice(&(fid.len() as u32).to_le_bytes());
+            out.extend_from_slice(fid.as_bytes());
+        }
+        out.extend_from_slice(&self.path.to_bytes());
+        out
+    }
+
+    /// 解码，格式错误时返回 None
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut rest = bytes;
+        let keyword = take_string(&mut rest)?;
+        let count = u32::from_le_bytes(take(&mut rest, 4)?.try_into().ok()?);
+        let mut fids = Vec::new();
+        for _ in 0..count {
+            fids.push(take_string(&mut rest)?);
+        }
+        Some(KeywordProof {
+            keyword,
+            fids,
+            path: SmtProof::from_bytes(rest)?,
+        })
+    }
+}
+
+fn take<'a>(rest: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    let (head, tail) = rest.split_at_checked(n)?;
+    *rest = tail;
+    Some(head)
+}
+
+fn take_string(rest: &mut &[u8]) -> Option<String> {
+    let len = u32::from_le_bytes(take(rest, 4)?.try_into().ok()?) as usize;
+    String::from_utf8(take(rest, len)?.to_vec()).ok()
+}
+
+/// 稀疏 Merkle 树
+#[derive(Debug, Clone)]
+pub struct SparseMerkleTree {
+    /// 键 -> 值，按键排序，子树覆盖的叶子是一段连续的键
+    leaves: BTreeMap<Hash, Hash>,
+    /// 至少包含两个叶子的内部节点: (高度, 前缀) -> 哈希
+    branches: HashMap<(u16, Hash), Hash>,
+    root: Hash,
+}
+
+impl Default for SparseMerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SparseMerkleTree {
+    /// 创建空树
+    pub fn new() -> Self {
+        SparseMerkleTree {
+            leaves: BTreeMap::new(),
+            branches: HashMap::new(),
+            root: default_hash(DEPTH),
+        }
+    }
+
+    /// 根哈希，空树为 `default_hash(DEPTH)`
+    pub fn root(&self) -> Hash {
+        self.root
+    }
+
+    /// 叶子数量
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// 树是否为空
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// 读取键的值
+    pub fn get(&self, key: &Hash) -> Option<&Hash> {
+        self.leaves.get(key)
+    }
+
+    /// 写入键的值，返回原来的值
+    pub fn insert(&mut self, key: Hash, value: Hash) -> Option<Hash> {
+        let old = self.leaves.insert(key, value);
+        if old != Some(value) {
+            self.update_path(&key, leaf_hash(&key, &value));
+        }
+        old
+    }
+
+    /// 删除键（置为空叶子），返回原来的值
+    pub fn remove(&mut self, key: &Hash) -> Option<Hash> {
+        let old = self.leaves.remove(key)?;
+        self.update_path(key, default_hash(0));
+        Some(old)
+    }
+
+    /// 生成键的证明：键存在时为包含证明，否则为非成员资格证明
+    pub fn prove(&self, key: &Hash) -> SmtProof {
+        let mut bitmap = [0u8; 32];
+        let mut siblings = Vec::new();
+        for height in 0..DEPTH {
+            let sibling = self.subtree_hash(height, &sibling_prefix(key, height));
+            if sibling != default_hash(height) {
+                bitmap[height / 8] |= 1 << (height % 8);
+                siblings.push(sibling);
+            }
+        }
+        SmtProof { bitmap, siblings }
+    }
+
+    /// 子树中的叶子
+    fn leaves_under(&self, height: usize, prefix: &Hash) -> impl Iterator<Item = (&Hash, &Hash)> {
+        self.leaves.range(*prefix..=last_key(prefix, height))
+    }
+
+    /// 高度为 `height`、前缀为 `prefix` 的子树的哈希
+    fn subtree_hash(&self, height: usize, prefix: &Hash) -> Hash {
+        if let Some(hash) = self.branches.get(&(height as u16, *prefix)) {
+            return *hash;
+        }
+        // 不在 branches 中的子树最多只有一个叶子
+        match self.leaves_under(height, prefix).next() {
+            None => default_hash(height),
+            Some((key, value)) => {
+                let mut current = leaf_hash(key, value);
+                for h in 0..height {
+                    current = parent_hash(key, h, &current, &default_hash(h));
+                }
+                current
+            }
+        }
+    }
+
+    /// 叶子 `key` 的哈希变为 `leaf` 后，重算到根的路径并维护 branches
+    fn update_path(&mut self, key: &Hash, leaf: Hash) {
+        let mut current = leaf;
+        for height in 0..DEPTH {
+            let sibling = self.subtree_hash(height, &sibling_prefix(key, height));
+            current = parent_hash(key, height, &current, &sibling);
+
+            let parent = prefix(key, height + 1);
+            let is_branch = self.leaves_under(height + 1, &parent).nth(1).is_some();
+            if is_branch {
+                self.branches.insert((height as u16 + 1, parent), current);
+            } else {
+                self.branches.remove(&(height as u16 + 1, parent));
+            }
+        }
+        self.root = current;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 由全部叶子递归计算子树哈希，用于和增量维护的结果比对
+    fn naive_hash(leaves: &[(Hash, Hash)], height: usize) -> Hash {
+        match leaves {
+            [] => default_hash(height),
+            [(key, value)] if height == 0 => leaf_hash(key, value),
+            _ => {
+                let index = DEPTH - height;
+                let split = leaves.partition_point(|(key, _)| !bit(key, index));
+                node_hash(
+                    &naive_hash(&leaves[..split], height - 1),
+                    &naive_hash(&leaves[split..], height - 1),
+                )
+            }
+        }
+    }
+
+    fn naive_root(tree: &SparseMerkleTree) -> Hash {
+        let leaves: Vec<(Hash, Hash)> = tree.leaves.iter().map(|(k, v)| (*k, *v)).collect();
+        naive_hash(&leaves, DEPTH)
+    }
+
+    #[test]
+    fn test_incremental_root_matches_naive() {
+        let mut tree = SparseMerkleTree::new();
+        assert_eq!(tree.root(), naive_root(&tree));
+        for i in 0..40 {
+            tree.insert(key_hash(&format!("kw{}", i)), [i as u8; 32]);
+            assert_eq!(tree.root(), naive_root(&tree));
+        }
+        // 相邻的键（只有最低位不同）
+        let mut near = key_hash("kw0");
+        near[31] ^= 1;
+        tree.insert(near, [7u8; 32]);
+        assert_eq!(tree.root(), naive_root(&tree));
+
+        for i in (0..40).step_by(3) {
+            tree.remove(&key_hash(&format!("kw{}", i)));
+            assert_eq!(tree.root(), naive_root(&tree));
+        }
+        assert!(tree.branches.len() < 2 * tree.len());
+    }
+
+    #[test]
+    fn test_membership_and_non_membership_proofs() {
+        let mut tree = SparseMerkleTree::new();
+        let keys: Vec<Hash> = (0..20).map(|i| key_hash(&i.to_string())).collect();
+        for key in &keys {
+            tree.insert(*key, *key);
+        }
+        let root = tree.root();
+
+        for key in &keys {
+            let proof = tree.prove(key);
+            assert!(proof.verify(key, Some(key), &root));
+            assert!(!proof.verify(key, Some(&[0u8; 32]), &root));
+            assert!(!proof.verify(key, None, &root));
+            assert_eq!(SmtProof::from_bytes(&proof.to_bytes()), Some(proof));
+        }
+
+        let absent = key_hash("absent");
+        let proof = tree.prove(&absent);
+        assert!(proof.verify(&absent, None, &root));
+        assert!(!proof.verify(&keys[0], None, &root));
+        // 证明只携带非空的兄弟节点
+        assert!(proof.siblings.len() < 16);
+
+        // 位图与兄弟节点数量不一致
+        let mut bytes = proof.to_bytes();
+        bytes.truncate(bytes.len() - 32);
+        assert!(SmtProof::from_bytes(&bytes).is_none());
+        let mut broken = proof.clone();
+        broken.siblings.pop();
+        assert!(broken.compute_root(&absent, None).is_none());
+    }
+
+    #[test]
+    fn test_remove_restores_root() {
+        let mut tree = SparseMerkleTree::new();
+        let empty = tree.root();
+        tree.insert(key_hash("a"), [1u8; 32]);
+        let root_a = tree.root();
+
+        assert_eq!(tree.insert(key_hash("b"), [2u8; 32]), None);
+        assert_eq!(tree.insert(key_hash("b"), [3u8; 32]), Some([2u8; 32]));
+        assert_eq!(tree.remove(&key_hash("b")), Some([3u8; 32]));
+        assert_eq!(tree.remove(&key_hash("b")), None);
+        assert_eq!(tree.root(), root_a);
+
+        tree.remove(&key_hash("a"));
+        assert!(tree.is_empty() && tree.branches.is_empty());
+        assert_eq!(tree.root(), empty);
+    }
+
+    #[test]
+    fn test_keyword_proof_roundtrip() {
+        let mut tree = SparseMerkleTree::new();
+        let fids = vec!["f1".to_string(), "f2".to_string()];
+        tree.insert(key_hash("rust"), fids_hash(&fids));
+        tree.insert(key_hash("go"), fids_hash(&["f3".to_string()]));
+
+        let proof = KeywordProof {
+            keyword: "rust".to_string(),
+            fids: fids.clone(),
+            path: tree.prove(&key_hash("rust")),
+        };
+        assert_eq!(proof.compute_root(), Some(tree.root()));
+        let decoded = KeywordProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded, proof);
+
+        // 少报一个 fid 得不到同一个根
+        let mut dropped = proof.clone();
+        dropped.fids.pop();
+        assert_ne!(dropped.compute_root(), Some(tree.root()));
+
+        let absent = KeywordProof {
+            keyword: "java".to_string(),
+            fids: vec![],
+            path: tree.prove(&key_hash("java")),
+        };
+        assert_eq!(absent.compute_root(), Some(tree.root()));
+        assert!(KeywordProof::from_bytes(&[1, 2]).is_none());
+    }
+}
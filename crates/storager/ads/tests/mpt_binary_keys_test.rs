@@ -0,0 +1,132 @@
+//! MPT 任意字节键测试
+//!
+//! 用随机字节键（包括非 UTF-8 序列）检查插入、查询、删除和证明都不经过有损的字符串转换
+use esa_rust::mpt::db::MemoryDatabase;
+use esa_rust::mpt::utils::{
+    hex_path_to_bytes, key_to_hex_path, nibbles_to_suffix, suffix_to_nibbles,
+};
+use esa_rust::mpt::{ValueProof, MPT};
+use proptest::collection::{btree_map, vec};
+use proptest::prelude::*;
+use std::collections::BTreeMap;
+
+fn build(entries: &BTreeMap<Vec<u8>, String>) -> (MPT, MemoryDatabase) {
+    let mut db = MemoryDatabase::new();
+    let mut mpt = MPT::new(None);
+    for (key, value) in entries {
+        mpt.insert_bytes(key, value, &mut db, true, false).unwrap();
+    }
+    (mpt, db)
+}
+
+fn entries_strategy() -> impl Strategy<Value = BTreeMap<Vec<u8>, String>> {
+    btree_map(vec(any::<u8>(), 1..6), "[a-z0-9]{1,8}", 1..24)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn nibble_path_roundtrip(key in vec(any::<u8>(), 0..32)) {
+        let path = key_to_hex_path(&key);
+        prop_assert_eq!(hex_path_to_bytes(&path), key);
+        prop_assert_eq!(suffix_to_nibbles(&nibbles_to_suffix(&path)), Some(path));
+    }
+
+    #[test]
+    fn query_and_proofs_use_exact_keys(
+        entries in entries_strategy(),
+        absent in vec(any::<u8>(), 1..6),
+    ) {
+        let (mpt, mut db) = build(&entries);
+        let root = mpt.get_root_hash();
+
+        for (key, value) in &entries {
+            let (found, proof) = mpt.query_by_bytes(key, &mut db).unwrap();
+            prop_assert_eq!(&found, value);
+            let proof = ValueProof::new(found, proof);
+            prop_assert_eq!(proof.compute_root(), root);
+            prop_assert!(proof.binds_bytes(key));
+        }
+
+        if !entries.contains_key(&absent) {
+            let (found, proof) = mpt.query_by_bytes(&absent, &mut db).unwrap();
+            prop_assert_eq!(found.as_str(), "");
+            let proof = ValueProof::new(found, proof);
+            prop_assert_eq!(proof.compute_root(), root);
+            prop_assert!(proof.binds_bytes(&absent));
+        }
+    }
+
+    #[test]
+    fn delete_matches_fresh_build(
+        entries in entries_strategy(),
+        picks in vec(any::<prop::sample::Index>(), 0..8),
+    ) {
+        let (mut mpt, mut db) = build(&entries);
+        let keys: Vec<Vec<u8>> = entries.keys().cloned().collect();
+        let mut remaining = entries.clone();
+        for pick in picks {
+            let key = pick.get(&keys);
+            let expected = remaining.remove(key);
+            prop_assert_eq!(mpt.delete_bytes(key, &mut db).unwrap(), expected);
+        }
+
+        let (fresh, _) = build(&remaining);
+        prop_assert_eq!(mpt.get_root_hash(), fresh.get_root_hash());
+        for key in &keys {
+            let (found, _) = mpt.query_by_bytes(key, &mut db).unwrap();
+            prop_assert_eq!(found, remaining.get(key).cloned().unwrap_or_default());
+        }
+    }
+
+    #[test]
+    fn range_returns_keys_unchanged(
+        entries in entries_strategy(),
+        start in vec(any::<u8>(), 0..3),
+        end in vec(any::<u8>(), 0..3),
+    ) {
+        let (mpt, mut db) = build(&entries);
+        let expected: Vec<(Vec<u8>, String)> = entries
+            .iter()
+            .filter(|(key, _)| **key >= start && (end.is_empty() || **key < end))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        let (pairs, proof) = mpt.range_query_bytes(&start, &end, &mut db).unwrap();
+        prop_assert_eq!(&pairs, &expected);
+        prop_assert_eq!(
+            proof.verify_bytes(&start, &end, &mpt.get_root_hash()),
+            Some(expected)
+        );
+    }
+}
+
+#[test]
+fn test_non_utf8_keys_stay_distinct() {
+    // 这两个键经过 from_utf8_lossy 后都变成 "\u{FFFD}"
+    let entries = BTreeMap::from([
+        (vec![0xff], "first".to_string()),
+        (vec![0xfe], "second".to_string()),
+    ]);
+    let (mut mpt, mut db) = build(&entries);
+
+    let (pairs, proof) = mpt.prefix_query_bytes(&[], &mut db).unwrap();
+    assert_eq!(
+        pairs,
+        vec![
+            (vec![0xfe], "second".to_string()),
+            (vec![0xff], "first".to_string()),
+        ]
+    );
+    let (root, proved) = proof.compute_prefix_root_bytes(&[]).unwrap();
+    assert_eq!(root, mpt.get_root_hash());
+    assert_eq!(proved, pairs);
+
+    assert_eq!(
+        mpt.delete_bytes(&[0xff], &mut db).unwrap().as_deref(),
+        Some("first")
+    );
+    let (found, _) = mpt.query_by_bytes(&[0xfe], &mut db).unwrap();
+    assert_eq!(found, "second");
+}
@@ -1,8 +1,8 @@
-use esa_rust::mpt::node::Database;
+use esa_rust::mpt::node::{BatchOp, Database, WriteBatch};
 /// MPT ADS 集成测试
 ///
 /// 测试 MPT 作为 ADS（Authenticated Data Structure）的完整功能
-use esa_rust::mpt::{MPTError, MPT};
+use esa_rust::mpt::{DbError, MPT};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
@@ -21,11 +21,11 @@ impl MemoryDB {
 }
 
 impl Database for MemoryDB {
-    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, MPTError> {
+    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError> {
         Ok(self.data.lock().unwrap().get(key).cloned())
     }
 
-    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), MPTError> {
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
         self.data
             .lock()
             .unwrap()
@@ -33,7 +33,7 @@ impl Database for MemoryDB {
         Ok(())
     }
 
-    fn delete(&mut self, key: &[u8]) -> Result<(), MPTError> {
+    fn delete(&mut self, key: &[u8]) -> Result<(), DbError> {
         self.data.lock().unwrap().remove(key);
         Ok(())
     }
@@ -171,7 +171,7 @@ fn test_mpt_persist_and_restore() {
     println!("✓ 持久化 MPT");
 
     // 从数据库恢复
-    let mut mpt2 = MPT::restore_from_db(&mut db, None).unwrap();
+    let mpt2 = MPT::restore_from_db(&mut db, None).unwrap();
     println!("✓ 从数据库恢复 MPT");
 
     let restored_root_hash = mpt2.get_root_hash();
@@ -191,6 +191,32 @@ fn test_mpt_persist_and_restore() {
     println!("✓ 恢复的数据正确: test2={}", value2);
 }
 
+#[test]
+fn test_mpt_metadata_sequence() {
+    let mut db = MemoryDB::new();
+    let mut mpt = MPT::new(None);
+    let kv = esa_rust::mpt::KVPair::new("test1".to_string(), "data1".to_string());
+    mpt.insert(kv, &mut db, true, false).unwrap();
+
+    let metadata = |db: &mut MemoryDB| {
+        let data = db.get(b"mpt:metadata").unwrap().unwrap();
+        MPT::deserialize_metadata(&data).unwrap()
+    };
+
+    mpt.persist_to_db_at(&mut db, 2_000).unwrap();
+    let first = metadata(&mut db);
+    assert_eq!((first.sequence, first.timestamp), (1, 2_000));
+
+    // 墙上时间回拨时序号仍然递增
+    mpt.persist_to_db_at(&mut db, 1_000).unwrap();
+    let second = metadata(&mut db);
+    assert_eq!((second.sequence, second.timestamp), (2, 1_000));
+
+    // 旧格式的元数据没有序号
+    let legacy = br#"{"root_hash":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"version":1,"timestamp":5}"#;
+    assert_eq!(MPT::deserialize_metadata(legacy).unwrap().sequence, 0);
+}
+
 #[test]
 fn test_mpt_proof_verification() {
     println!("\n=== 测试证明验证 ===");
@@ -295,3 +321,216 @@ fn test_mpt_secondary_index() {
     assert!(!value.contains("apple"));
     println!("✓ 辅助索引删除成功");
 }
+
+#[test]
+fn test_mpt_sliced_fix_matches_batch_fix() {
+    let mut db1 = MemoryDB::new();
+    let mut db2 = MemoryDB::new();
+    let mut mpt1 = MPT::new(None);
+    let mut mpt2 = MPT::new(None);
+
+    for i in 0..200 {
+        let key = format!("key{}", i);
+        let value = format!("value{}", i);
+        mpt1.insert(
+            esa_rust::mpt::KVPair::new(key.clone(), value.clone()),
+            &mut db1,
+            true,
+            false,
+        )
+        .unwrap();
+        mpt2.insert(esa_rust::mpt::KVPair::new(key, value), &mut db2, true, false)
+            .unwrap();
+    }
+
+    mpt1.batch_fix(&mut db1).unwrap();
+
+    // 零预算：每个时间片只推进一步
+    let mut fix = mpt2.begin_sliced_fix();
+    let mut done = false;
+    for _ in 0..3 {
+        done = mpt2
+            .fix_slice(&mut fix, &mut db2, std::time::Duration::ZERO)
+            .unwrap();
+    }
+    assert!(!done);
+    assert!(!fix.is_idle());
+
+    let metrics = mpt2.finish_sliced_fix(fix, &mut db2).unwrap();
+    assert!(metrics.slices > 3);
+    assert!(metrics.nodes_fixed > 0);
+    assert!(metrics.max_slice <= metrics.total);
+    assert_eq!(mpt1.get_root_hash(), mpt2.get_root_hash());
+
+    // 修复完成后的树可以从数据库恢复
+    let restored = MPT::load_from_db(&mpt2.get_root_hash(), &mut db2, None).unwrap();
+    assert_eq!(restored.get_root_hash(), mpt2.get_root_hash());
+}
+
+#[test]
+fn test_mpt_proofs_track_updates_and_deletes() {
+    let mut db = MemoryDB::new();
+    let mut mpt = MPT::new(None);
+
+    // "kw1" 是 "kw10" 的前缀，它的值存放在分支节点上
+    for key in ["kw10", "kw1", "kw2", "kw3"] {
+        let kv = esa_rust::mpt::KVPair::new(key.to_string(), "a".to_string());
+        mpt.insert(kv, &mut db, true, false).unwrap();
+    }
+    let kv = esa_rust::mpt::KVPair::new("kw1".to_string(), "a,b".to_string());
+    mpt.insert(kv, &mut db, true, false).unwrap();
+    mpt.delete("kw2", &mut db).unwrap();
+
+    // 不做修复，每个键的证明都应对应最新的根哈希
+    for key in ["kw10", "kw1", "kw2", "kw3"] {
+        let (value, proof) = mpt.query_by_key(key, &mut db).unwrap();
+        assert!(mpt.verify_query_result(&value, &proof), "key {}", key);
+    }
+}
+
+#[test]
+fn test_mpt_insert_prefix_of_existing_key() {
+    // 较短的键在较长的键之后插入，新键在已有叶子节点处结束
+    let orders = [
+        ["rusty", "rust", "ru", "r"],
+        ["ab", "a", "abc", "b"],
+        ["kw10", "kw2", "kw1", "k"],
+    ];
+    for keys in orders {
+        let mut db = MemoryDB::new();
+        let mut mpt = MPT::new(None);
+        for key in keys {
+            let kv = esa_rust::mpt::KVPair::new(key.to_string(), key.to_uppercase());
+            mpt.insert(kv, &mut db, true, false).unwrap();
+        }
+
+        for key in keys {
+            let (value, proof) = mpt.query_by_key(key, &mut db).unwrap();
+            assert_eq!(value, key.to_uppercase(), "keys {:?}", keys);
+            assert!(mpt.verify_query_result(&value, &proof), "key {}", key);
+        }
+    }
+}
+
+fn build_mpt<'a>(keys: impl IntoIterator<Item = &'a str>, db: &mut MemoryDB) -> MPT {
+    let mut mpt = MPT::new(None);
+    for key in keys {
+        let kv = esa_rust::mpt::KVPair::new(key.to_string(), key.to_uppercase());
+        mpt.insert(kv, db, true, false).unwrap();
+    }
+    mpt
+}
+
+#[test]
+fn test_mpt_delete_collapses_to_fresh_trie() {
+    // 共享前缀的键、作为其他键前缀的键（值在分支节点上），删除后分支只剩一项
+    let keys = [
+        "rust", "rusty", "ru", "go", "golang", "gopher", "kw1", "kw10", "kw2", "a", "b",
+    ];
+    for deleted in keys {
+        let mut db = MemoryDB::new();
+        let mut mpt = build_mpt(keys, &mut db);
+        assert_eq!(
+            mpt.delete(deleted, &mut db).unwrap(),
+            Some(deleted.to_uppercase())
+        );
+
+        // 与按相反顺序直接插入其余键得到的树相同
+        let remaining = keys.iter().rev().copied().filter(|key| *key != deleted);
+        let fresh = build_mpt(remaining, &mut MemoryDB::new());
+        assert_eq!(
+            mpt.get_root_hash(),
+            fresh.get_root_hash(),
+            "deleted {}",
+            deleted
+        );
+
+        for key in keys {
+            let (value, proof) = mpt.query_by_key(key, &mut db).unwrap();
+            assert!(mpt.verify_query_result(&value, &proof), "key {}", key);
+        }
+    }
+}
+
+#[test]
+fn test_mpt_sequential_deletes_match_fresh_build() {
+    let keys = ["kw1", "kw10", "kw100", "kw2", "kw20", "k", "x", "xy", "xz"];
+    let mut db = MemoryDB::new();
+    let mut mpt = build_mpt(keys, &mut db);
+
+    // 每次删除后都与直接插入剩余键得到的树一致，全部删除后根哈希归零
+    let order = ["kw10", "xy", "k", "kw1", "kw2", "x", "kw100", "xz", "kw20"];
+    for (i, key) in order.iter().enumerate() {
+        assert!(mpt.delete(key, &mut db).unwrap().is_some());
+        let fresh = build_mpt(order[i + 1..].iter().copied(), &mut MemoryDB::new());
+        assert_eq!(
+            mpt.get_root_hash(),
+            fresh.get_root_hash(),
+            "deleted {}",
+            key
+        );
+    }
+    assert_eq!(mpt.get_root_hash(), [0u8; 32]);
+}
+
+/// 记录单条写入和批量写入次数的数据库
+struct CountingDB {
+    inner: MemoryDB,
+    puts: usize,
+    batches: usize,
+}
+
+impl Database for CountingDB {
+    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError> {
+        self.inner.get(key)
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
+        self.puts += 1;
+        self.inner.put(key, value)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<(), DbError> {
+        self.inner.delete(key)
+    }
+
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<(), DbError> {
+        self.batches += 1;
+        for op in batch.into_ops() {
+            match op {
+                BatchOp::Put(key, value) => self.inner.put(&key, &value)?,
+                BatchOp::Delete(key) => self.inner.delete(&key)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_mpt_batch_fix_writes_one_batch() {
+    let mut db = CountingDB {
+        inner: MemoryDB::new(),
+        puts: 0,
+        batches: 0,
+    };
+    let mut mpt = MPT::new(None);
+    for i in 0..50 {
+        let kv = esa_rust::mpt::KVPair::new(format!("kw{}", i), format!("v{}", i));
+        mpt.insert(kv, &mut db, true, false).unwrap();
+    }
+
+    let puts = db.puts;
+    mpt.batch_fix(&mut db).unwrap();
+    assert_eq!(db.puts, puts);
+    assert_eq!(db.batches, 1);
+
+    mpt.persist_to_db(&mut db).unwrap();
+    let restored = MPT::restore_from_db(&mut db.inner, None).unwrap();
+    assert_eq!(restored.get_root_hash(), mpt.get_root_hash());
+    for i in 0..50 {
+        let (value, _) = restored
+            .query_by_key(&format!("kw{}", i), &mut db.inner)
+            .unwrap();
+        assert_eq!(value, format!("v{}", i));
+    }
+}
@@ -0,0 +1,174 @@
+//! MPT 模型测试
+//!
+//! 随机生成插入、删除、查询序列，同时作用于 MPT 和 HashMap 参考模型，检查：
+//! - 每次查询的值与模型一致，证明能重算出当前根哈希并绑定到查询的键
+//! - 插入返回的旧值、删除返回的值与模型一致
+//! - 根哈希只取决于最终内容，与操作顺序无关
+//!
+//! 键取自很小的字节集合，使得键之间频繁共享前缀，覆盖叶子拆分、扩展节点拆分和删除后合并的各种情形。
+//! 失败时 proptest 会把操作序列收缩到最短的反例。
+use esa_rust::mpt::db::MemoryDatabase;
+use esa_rust::mpt::{ValueProof, MPT};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use std::collections::HashMap;
+
+/// 主索引操作
+#[derive(Debug, Clone)]
+enum Op {
+    Insert(Vec<u8>, String),
+    Delete(Vec<u8>),
+    Query(Vec<u8>),
+}
+
+/// 辅助索引操作：同一个键下维护逗号分隔的多个值
+#[derive(Debug, Clone)]
+enum SecondaryOp {
+    Add(Vec<u8>, String),
+    Remove(Vec<u8>, String),
+    Query(Vec<u8>),
+}
+
+/// 由 0x00、0x01、0x10、0x11 组成的短键，半字节只有 0 和 1 两种
+fn key() -> impl Strategy<Value = Vec<u8>> {
+    vec(prop::sample::select(vec![0x00u8, 0x01, 0x10, 0x11]), 0..4)
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        3 => (key(), "[a-z]{1,3}").prop_map(|(k, v)| Op::Insert(k, v)),
+        1 => key().prop_map(Op::Delete),
+        1 => key().prop_map(Op::Query),
+    ]
+}
+
+fn secondary_op() -> impl Strategy<Value = SecondaryOp> {
+    // 值互不为子串，避免 KVPair 按子串判断包含关系带来的歧义
+    let value = prop::sample::select(vec!["f1", "f2", "f3", "f4"]).prop_map(String::from);
+    prop_oneof![
+        3 => (key(), value.clone()).prop_map(|(k, v)| SecondaryOp::Add(k, v)),
+        2 => (key(), value).prop_map(|(k, v)| SecondaryOp::Remove(k, v)),
+        1 => key().prop_map(SecondaryOp::Query),
+    ]
+}
+
+/// 查询 `key` 并检查值和证明
+fn check_query(
+    mpt: &MPT,
+    db: &mut MemoryDatabase,
+    key: &[u8],
+    expected: &str,
+) -> Result<(), TestCaseError> {
+    let (value, proof) = mpt.query_by_bytes(key, db).unwrap();
+    prop_assert_eq!(value.as_str(), expected, "key {:02x?}", key);
+    let proof = ValueProof::new(value, proof);
+    if mpt.get_root_hash() == [0u8; 32] {
+        prop_assert!(proof.proves_empty_trie(), "key {:02x?}", key);
+        return Ok(());
+    }
+    prop_assert_eq!(
+        proof.compute_root(),
+        mpt.get_root_hash(),
+        "key {:02x?}",
+        key
+    );
+    prop_assert!(proof.binds_bytes(key), "key {:02x?}", key);
+    Ok(())
+}
+
+/// 按给定顺序插入全部键值对
+fn build<'a>(entries: impl IntoIterator<Item = (&'a Vec<u8>, &'a String)>) -> MPT {
+    let mut db = MemoryDatabase::new();
+    let mut mpt = MPT::new(None);
+    for (key, value) in entries {
+        mpt.insert_bytes(key, value, &mut db, true, false).unwrap();
+    }
+    mpt
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn primary_index_matches_model(ops in vec(op(), 1..40)) {
+        let mut db = MemoryDatabase::new();
+        let mut mpt = MPT::new(None);
+        let mut model: HashMap<Vec<u8>, String> = HashMap::new();
+
+        for op in &ops {
+            match op {
+                Op::Insert(key, value) => {
+                    let (old, _) = mpt.insert_bytes(key, value, &mut db, true, false).unwrap();
+                    let expected = model.insert(key.clone(), value.clone()).unwrap_or_default();
+                    prop_assert_eq!(old, expected, "insert {:02x?}", key);
+                }
+                Op::Delete(key) => {
+                    let deleted = mpt.delete_bytes(key, &mut db).unwrap();
+                    prop_assert_eq!(deleted, model.remove(key), "delete {:02x?}", key);
+                }
+                Op::Query(key) => {
+                    let expected = model.get(key).cloned().unwrap_or_default();
+                    check_query(&mpt, &mut db, key, &expected)?;
+                }
+            }
+        }
+
+        for key in ops.iter().map(|op| match op {
+            Op::Insert(key, _) | Op::Delete(key) | Op::Query(key) => key,
+        }) {
+            let expected = model.get(key).cloned().unwrap_or_default();
+            check_query(&mpt, &mut db, key, &expected)?;
+        }
+
+        // 根哈希与插入顺序无关
+        let mut entries: Vec<_> = model.iter().collect();
+        entries.sort();
+        prop_assert_eq!(build(entries.iter().copied()).get_root_hash(), mpt.get_root_hash());
+        prop_assert_eq!(build(entries.into_iter().rev()).get_root_hash(), mpt.get_root_hash());
+    }
+
+    #[test]
+    fn secondary_index_matches_model(ops in vec(secondary_op(), 1..40)) {
+        let mut db = MemoryDatabase::new();
+        let mut mpt = MPT::new(None);
+        // 每个键当前的值（按加入顺序），以及尚未抵消的延迟删除次数
+        let mut model: HashMap<Vec<u8>, Vec<String>> = HashMap::new();
+        let mut pending: HashMap<(Vec<u8>, String), usize> = HashMap::new();
+
+        for op in &ops {
+            match op {
+                SecondaryOp::Add(key, value) => {
+                    mpt.insert_bytes(key, value, &mut db, false, false).unwrap();
+                    let slot = (key.clone(), value.clone());
+                    match pending.get_mut(&slot) {
+                        Some(count) if *count > 0 => *count -= 1,
+                        _ => {
+                            let values = model.entry(key.clone()).or_default();
+                            if !values.contains(value) {
+                                values.push(value.clone());
+                            }
+                        }
+                    }
+                }
+                SecondaryOp::Remove(key, value) => {
+                    mpt.insert_bytes(key, value, &mut db, false, true).unwrap();
+                    let values = model.entry(key.clone()).or_default();
+                    match values.iter().position(|v| v == value) {
+                        Some(i) => {
+                            values.remove(i);
+                        }
+                        None => *pending.entry((key.clone(), value.clone())).or_default() += 1,
+                    }
+                }
+                SecondaryOp::Query(key) => {
+                    let expected = model.get(key).map(|v| v.join(",")).unwrap_or_default();
+                    check_query(&mpt, &mut db, key, &expected)?;
+                }
+            }
+        }
+
+        for (key, values) in &model {
+            check_query(&mpt, &mut db, key, &values.join(","))?;
+        }
+    }
+}
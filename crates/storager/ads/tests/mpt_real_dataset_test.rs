@@ -2,7 +2,7 @@ use esa_rust::mpt::node::Database;
 /// MPT ADS 真实数据集测试
 ///
 /// 使用 data/testdata 中的真实数据测试 MPT 的性能和正确性
-use esa_rust::mpt::{KVPair, MPTError, MPT};
+use esa_rust::mpt::{DbError, KVPair, MPT};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -28,11 +28,11 @@ impl MemoryDB {
 }
 
 impl Database for MemoryDB {
-    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, MPTError> {
+    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError> {
         Ok(self.data.lock().unwrap().get(key).cloned())
     }
 
-    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), MPTError> {
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
         self.data
             .lock()
             .unwrap()
@@ -40,7 +40,7 @@ impl Database for MemoryDB {
         Ok(())
     }
 
-    fn delete(&mut self, key: &[u8]) -> Result<(), MPTError> {
+    fn delete(&mut self, key: &[u8]) -> Result<(), DbError> {
         self.data.lock().unwrap().remove(key);
         Ok(())
     }
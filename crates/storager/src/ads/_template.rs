@@ -1,3 +1,4 @@
+use crate::ads::AdsResult;
 /// 新 ADS 实现模板
 ///
 /// 复制此文件并重命名，实现 AdsOperations trait
@@ -10,7 +11,7 @@
 /// 5. 在 mod.rs 中注册此模块
 /// 6. 在 storager.rs 中添加构造函数
 use crate::ads_trait::AdsOperations;
-use common::RootHash;
+use common::Proof;
 
 /// 新 ADS 实现
 ///
@@ -33,11 +34,11 @@ impl AdsOperations for NewAds {
     /// 添加 (keyword, fid) 对到 ADS
     ///
     /// 返回: (proof, root_hash)
-    fn add(&mut self, keyword: &str, fid: &str) -> (Vec<u8>, RootHash) {
+    fn add(&mut self, keyword: &str, fid: &str) -> AdsResult {
         // TODO: 实现添加逻辑
         // 1. 更新内部数据结构
         // 2. 生成证明
-        // 3. 返回 (证明, 新的根哈希)
+        // 3. 返回 Ok((证明, 新的根哈希))，无法完成时返回 AdsError
 
         unimplemented!("Add operation not implemented")
     }
@@ -45,7 +46,7 @@ impl AdsOperations for NewAds {
     /// 查询 keyword 对应的所有 fid
     ///
     /// 返回: (fids, proof)
-    fn query(&self, keyword: &str) -> (Vec<String>, Vec<u8>) {
+    fn query(&self, keyword: &str) -> (Vec<String>, Proof) {
         // TODO: 实现查询逻辑
         // 1. 查找 keyword 对应的所有 fid
         // 2. 生成成员资格证明
@@ -57,11 +58,11 @@ impl AdsOperations for NewAds {
     /// 从 ADS 中删除 (keyword, fid) 对
     ///
     /// 返回: (proof, root_hash)
-    fn delete(&mut self, keyword: &str, fid: &str) -> (Vec<u8>, RootHash) {
+    fn delete(&mut self, keyword: &str, fid: &str) -> AdsResult {
         // TODO: 实现删除逻辑
         // 1. 从数据结构中移除元素
         // 2. 生成删除证明
-        // 3. 返回 (证明, 新的根哈希)
+        // 3. 返回 Ok((证明, 新的根哈希))，无法完成时返回 AdsError
 
         unimplemented!("Delete operation not implemented")
     }
@@ -3,77 +3,189 @@
 //! 基于 BLS12-381 椭圆曲线的密码学累加器
 //! 支持恒定大小的成员资格证明
 
-use super::AdsOperations;
+use super::state::{put_bytes, put_u32, StateReader};
+use super::{AdsOperations, AdsResult, Mutation, ProofJob};
+use ark_bls12_381::Fr;
 use ark_serialize::CanonicalSerialize;
-use common::RootHash;
-use esa_rust::crypto_accumulator::acc::dynamic_accumulator::{DynamicAccumulator, QueryResult};
-use std::collections::HashMap;
+use common::rpc::{boolean_proof::Node, BooleanProof, BooleanProofOperation};
+use common::{AdsError, BooleanExpr, Proof, RootHash};
+use esa_rust::crypto_accumulator::acc::dynamic_accumulator::{
+    element_to_field, BatchMembershipProof, DynamicAccumulator,
+};
+use esa_rust::mpt::node::Database;
+use std::collections::{HashMap, HashSet};
+use tracing::{error, warn};
+
+/// 数据库中保存 keyword 列表的键
+const KEYWORDS_KEY: &[u8] = b"acc/keywords";
+
+/// 编码字符串列表: [count | string...]
+fn encode_strings<'a>(strings: impl ExactSizeIterator<Item = &'a String>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    put_u32(&mut buf, strings.len() as u32);
+    for string in strings {
+        put_bytes(&mut buf, string.as_bytes());
+    }
+    buf
+}
+
+fn read_strings(reader: &mut StateReader) -> Result<Vec<String>, String> {
+    (0..reader.u32()?).map(|_| reader.string()).collect()
+}
+
+/// 一个 keyword 的累加器摘要，见 [`CryptoAccumulatorAds::keyword_accumulator`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordAccumulator {
+    /// 序列化的累加器值，即该 keyword 写入后返回的根哈希
+    pub value: RootHash,
+    /// 累加器中的元素（fid）数量
+    pub len: usize,
+}
 
 /// 密码学累加器 ADS 实现
 pub struct CryptoAccumulatorAds {
     /// 存储每个 keyword 对应的累加器和文件列表
     /// HashMap<keyword, (accumulator, fid_list)>
     accumulators: HashMap<String, (DynamicAccumulator, Vec<String>)>,
+    /// 所有有 fid 的 keyword 构成的集合，用于证明被查询的 keyword 不存在
+    keyword_set: DynamicAccumulator,
 }
 
 impl CryptoAccumulatorAds {
     pub fn new() -> Self {
         CryptoAccumulatorAds {
             accumulators: HashMap::new(),
+            keyword_set: DynamicAccumulator::new(),
         }
     }
 
-    /// 将 keyword+fid 组合转换为累加器元素
-    ///
-    /// 使用 keyword:fid 格式确保同一个 fid 在不同 keyword 下是不同的元素
-    fn fid_to_element(keyword: &str, fid: &str) -> i64 {
-        let combined = format!("{}:{}", keyword, fid);
-        combined
-            .bytes()
-            .fold(0i64, |acc, b| acc.wrapping_mul(31).wrapping_add(b as i64))
-    }
-
     /// 序列化添加/删除证明
     ///
-    /// 格式: [old_acc(96) | new_acc(96) | element(8) | valid(1)]
-    /// 总计: 201 字节
+    /// 格式: [old_acc(96) | new_acc(96) | element(32) | valid(1)]
+    /// 总计: 225 字节
     fn serialize_update_proof(
         old_acc: &ark_bls12_381::G1Affine,
         new_acc: &ark_bls12_381::G1Affine,
-        element: i64,
+        element: &Fr,
         is_valid: bool,
     ) -> Vec<u8> {
         let mut proof = Vec::new();
         old_acc.serialize(&mut proof).unwrap();
         new_acc.serialize(&mut proof).unwrap();
-        proof.extend_from_slice(&element.to_le_bytes());
+        element.serialize(&mut proof).unwrap();
         proof.push(if is_valid { 1 } else { 0 });
         proof
     }
 
-    /// 序列化成员资格证明
+    /// 序列化批量成员资格证明，一个 witness 覆盖所有元素
     ///
-    /// 格式: [witness(96) | element(8) | acc_value(96) | valid(1)]
-    /// 总计: 201 字节
+    /// 格式: [witness(96) | count(4) | element(32) * count | acc_value(96) | valid(1)]
+    /// 总计: 197 + 32 * count 字节
     fn serialize_membership_proof(
         witness: &ark_bls12_381::G1Affine,
-        element: i64,
+        elements: &[Fr],
         acc_value: &ark_bls12_381::G1Affine,
         is_valid: bool,
     ) -> Vec<u8> {
         let mut proof = Vec::new();
         witness.serialize(&mut proof).unwrap();
-        proof.extend_from_slice(&element.to_le_bytes());
+        proof.extend_from_slice(&(elements.len() as u32).to_le_bytes());
+        for element in elements {
+            element.serialize(&mut proof).unwrap();
+        }
         acc_value.serialize(&mut proof).unwrap();
         proof.push(if is_valid { 1 } else { 0 });
         proof
     }
-}
 
-impl AdsOperations for CryptoAccumulatorAds {
-    fn add(&mut self, keyword: &str, fid: &str) -> (Vec<u8>, RootHash) {
-        let element = Self::fid_to_element(keyword, fid);
+    /// 为 `fids` 生成一个批量成员资格证明
+    fn membership_proof(acc: &DynamicAccumulator, fids: &[String]) -> Proof {
+        if fids.is_empty() {
+            return Proof::AccumulatorMembership(vec![1]); // 空结果有效
+        }
+        let elements: Vec<Fr> = fids
+            .iter()
+            .map(|fid| element_to_field(fid.as_str()))
+            .collect();
+
+        // 单个元素的批量 witness 就是它的成员资格 witness，可以直接使用缓存
+        let batch_proof = match fids {
+            [fid] => acc
+                .prove_membership(fid.as_str())
+                .map(|proof| BatchMembershipProof {
+                    witness: proof.witness,
+                    elements: vec![proof.element],
+                }),
+            _ => acc.prove_membership_batch(fids),
+        };
+        let proof = match batch_proof {
+            Ok(batch_proof) => {
+                let is_valid = acc.verify_membership_batch(&batch_proof);
+
+                Self::serialize_membership_proof(
+                    &batch_proof.witness,
+                    &elements,
+                    &acc.acc_value,
+                    is_valid,
+                )
+            }
+            Err(_) => vec![0],
+        };
+        Proof::AccumulatorMembership(proof)
+    }
 
+    /// 缓存 fid 在 keyword 累加器中的 witness，之后的写入增量更新它，
+    /// 证明该 fid 属于 keyword 时不再重新计算
+    ///
+    /// 适合被频繁单独查询的热点 (keyword, fid)；每个缓存的 witness 让该 keyword 的每次写入
+    /// 多一次标量乘法
+    pub fn cache_witness(&mut self, keyword: &str, fid: &str) -> Result<(), String> {
+        let (acc, _) = self
+            .accumulators
+            .get_mut(keyword)
+            .ok_or_else(|| format!("keyword '{}' has no fids", keyword))?;
+        acc.cache_witness(fid)
+            .map_err(|e| format!("fid '{}' is not under keyword '{}': {}", fid, keyword, e))
+    }
+
+    /// 证明单个 fid 属于 keyword，格式与查询的成员资格证明相同
+    pub fn prove_fid(&self, keyword: &str, fid: &str) -> Proof {
+        match self.accumulators.get(keyword) {
+            Some((acc, _)) => Self::membership_proof(acc, &[fid.to_string()]),
+            None => Proof::AccumulatorMembership(vec![0]),
+        }
+    }
+
+    /// 证明 `keyword` 不在 keyword 集合累加器中，即它没有任何 fid
+    ///
+    /// 格式: [keyword_set_acc | element(32) | g1_a | witness | valid(1)]
+    fn non_membership_proof(&self, keyword: &str) -> Proof {
+        let Ok(proof) = self.keyword_set.prove_non_membership(keyword) else {
+            return Proof::AccumulatorNonMembership(vec![0]);
+        };
+        let is_valid = self.keyword_set.verify_non_membership(&proof);
+
+        let mut data = Vec::new();
+        self.keyword_set.acc_value.serialize(&mut data).unwrap();
+        proof.element.serialize(&mut data).unwrap();
+        proof.g1_a.serialize(&mut data).unwrap();
+        proof.witness.serialize(&mut data).unwrap();
+        data.push(if is_valid { 1 } else { 0 });
+        Proof::AccumulatorNonMembership(data)
+    }
+
+    /// 把 keyword 加入 keyword 集合；元素冲突或超出公开参数的次数时只记录错误，
+    /// 这样的 keyword 不存在时无法给出证明
+    fn insert_keyword(&mut self, keyword: &str) {
+        if let Err(e) = self.keyword_set.add(keyword) {
+            error!("Failed to add keyword '{}' to the keyword set: {}", keyword, e);
+        }
+    }
+
+    /// 把 fid 加入 keyword 的累加器，返回生成证明的任务和新的累加器值
+    ///
+    /// 证明中的配对检查留给任务执行；累加器拒绝添加时返回错误，状态不变
+    fn add_deferred(&mut self, keyword: &str, fid: &str) -> Result<(ProofJob, RootHash), AdsError> {
         let entry = self
             .accumulators
             .entry(keyword.to_string())
@@ -83,101 +195,823 @@ impl AdsOperations for CryptoAccumulatorAds {
 
         // Check if this fid is already in the list (防御性检查)
         if entry.1.contains(&fid.to_string()) {
-            println!("Warning: fid '{}' already exists for keyword '{}', skipping add", fid, keyword);
-            // Return current state without adding again
-            let proof = Self::serialize_update_proof(&old_acc_value, &entry.0.acc_value, element, true);
+            warn!(
+                "Fid '{}' already exists for keyword '{}', skipping add",
+                fid, keyword
+            );
+            // Return current state without adding again; the record shows the fid is already there
+            let proof = Self::existing_member_proof(&entry.0, fid);
             let mut root_hash = Vec::new();
             entry.0.acc_value.serialize(&mut root_hash).unwrap();
-            return (proof, root_hash);
+            return Ok((Box::new(move || proof), root_hash));
         }
 
-        // 添加到累加器并验证
-        let add_result = entry.0.add(&element);
-        
-        let is_valid = match add_result {
-            Ok(proof) => proof.verify(),
+        // 添加到累加器，证明在任务中验证
+        let add_proof = match entry.0.add(fid) {
+            Ok(proof) => proof,
             Err(e) => {
-                eprintln!("Error adding element to accumulator for keyword='{}', fid='{}': {:?}", keyword, fid, e);
-                // Return empty proof on error
-                let proof = Self::serialize_update_proof(&old_acc_value, &old_acc_value, element, false);
-                let mut root_hash = Vec::new();
-                old_acc_value.serialize(&mut root_hash).unwrap();
-                return (proof, root_hash);
+                let error = if entry.0.contains(fid) {
+                    AdsError::DuplicateElement {
+                        keyword: keyword.to_string(),
+                        fid: fid.to_string(),
+                    }
+                } else {
+                    AdsError::Backend(format!(
+                        "accumulator of '{}' rejected fid '{}': {}",
+                        keyword, fid, e
+                    ))
+                };
+                // 不留下为这次添加创建的空累加器
+                if entry.1.is_empty() {
+                    self.accumulators.remove(keyword);
+                }
+                return Err(error);
             }
         };
 
-        // 记录 fid
+        // 记录 fid；keyword 的第一个 fid 同时把 keyword 加入 keyword 集合
         entry.1.push(fid.to_string());
-
-        // 序列化证明
-        let proof =
-            Self::serialize_update_proof(&old_acc_value, &entry.0.acc_value, element, is_valid);
+        let is_new_keyword = entry.1.len() == 1;
+        let new_acc_value = entry.0.acc_value;
 
         // 序列化 root hash
         let mut root_hash = Vec::new();
-        entry.0.acc_value.serialize(&mut root_hash).unwrap();
+        new_acc_value.serialize(&mut root_hash).unwrap();
 
-        (proof, root_hash)
+        if is_new_keyword {
+            self.insert_keyword(keyword);
+        }
+        let job: ProofJob = Box::new(move || {
+            Proof::AccumulatorAdd(Self::serialize_update_proof(
+                &old_acc_value,
+                &new_acc_value,
+                &add_proof.element,
+                add_proof.verify(),
+            ))
+        });
+        Ok((job, root_hash))
     }
 
-    fn query(&self, keyword: &str) -> (Vec<String>, Vec<u8>) {
-        if let Some((acc, fids)) = self.accumulators.get(keyword) {
-            let proof = if !fids.is_empty() {
-                let element = Self::fid_to_element(keyword, &fids[0]);
+    /// 添加已经存在的 fid 时的证明，格式与添加证明相同: [witness | acc | element | valid(1)]
+    ///
+    /// witness 加入 fid 得到当前累加器，与添加证明的验证等式相同；新值等于当前累加器，
+    /// Manager 跟踪的累加器值不变
+    fn existing_member_proof(acc: &DynamicAccumulator, fid: &str) -> Proof {
+        let proof = match acc.prove_membership(fid) {
+            Ok(proof) => Self::serialize_update_proof(
+                &proof.witness,
+                &acc.acc_value,
+                &proof.element,
+                acc.verify_membership(&proof),
+            ),
+            Err(_) => vec![0],
+        };
+        Proof::AccumulatorAdd(proof)
+    }
 
-                match acc.query(&element) {
-                    QueryResult::Membership(membership_proof) => {
-                        let is_valid = membership_proof.verify(acc.acc_value);
+    /// 把每个 keyword 的记录依次拼接成一个证明，遇到无效的记录时停止
+    ///
+    /// Manager 要求每个 keyword 都有一条有效的记录，截断的证明会被拒绝
+    fn concat_records(proofs: impl IntoIterator<Item = Proof>) -> Proof {
+        let mut records = Vec::new();
+        let mut result = None;
+        for proof in proofs {
+            let is_valid = proof.data().last() == Some(&1);
+            records.extend_from_slice(proof.data());
+            result = Some(proof);
+            if !is_valid {
+                break;
+            }
+        }
+        match result.expect("batch has at least one keyword") {
+            Proof::AccumulatorDelete(_) => Proof::AccumulatorDelete(records),
+            _ => Proof::AccumulatorAdd(records),
+        }
+    }
 
-                        Self::serialize_membership_proof(
-                            &membership_proof.witness,
-                            element,
-                            &acc.acc_value,
-                            is_valid,
-                        )
-                    }
-                    _ => vec![0],
-                }
-            } else {
-                vec![1] // 空结果有效
-            };
+    /// 与 [`add_batch`](AdsOperations::add_batch) 相同，但证明在返回的任务中生成
+    ///
+    /// 累加器拒绝某个 keyword 时返回错误，之前的 keyword 已经写入，之后的 keyword 不再写入
+    fn add_batch_deferred(
+        &mut self,
+        keywords: &[String],
+        fid: &str,
+    ) -> Result<(ProofJob, RootHash), AdsError> {
+        if keywords.is_empty() {
+            return Err(AdsError::EmptyBatch);
+        }
+        let mut jobs = Vec::new();
+        let mut root_hash = Vec::new();
+        for keyword in keywords {
+            let (job, root) = self.add_deferred(keyword, fid)?;
+            jobs.push(job);
+            root_hash = root;
+        }
+        let job: ProofJob =
+            Box::new(move || Self::concat_records(jobs.into_iter().map(|job| job())));
+        Ok((job, root_hash))
+    }
+
+    /// 从 keyword 的累加器中删除 fid，删除证明在返回的任务中验证
+    ///
+    /// 累加器无法证明 fid 不存在，不存在的 (keyword, fid) 返回错误
+    fn delete_deferred(
+        &mut self,
+        keyword: &str,
+        fid: &str,
+    ) -> Result<(ProofJob, RootHash), AdsError> {
+        let Some((acc, fids)) = self.accumulators.get_mut(keyword) else {
+            return Err(AdsError::MissingKeyword(keyword.to_string()));
+        };
+        if !fids.iter().any(|f| f == fid) {
+            return Err(AdsError::MissingFid {
+                keyword: keyword.to_string(),
+                fid: fid.to_string(),
+            });
+        }
+        let old_acc_value = acc.acc_value;
+
+        // 从累加器删除，证明在任务中验证
+        let delete_proof = acc.delete(fid).map_err(|e| {
+            AdsError::Backend(format!(
+                "accumulator of '{}' failed to delete fid '{}': {}",
+                keyword, fid, e
+            ))
+        })?;
+        let new_acc_value = acc.acc_value;
 
-            (fids.clone(), proof)
+        fids.retain(|f| f != fid);
+
+        let root_hash = if fids.is_empty() {
+            self.remove_keyword(keyword);
+            vec![]
         } else {
-            (vec![], vec![1])
+            let mut rh = Vec::new();
+            new_acc_value.serialize(&mut rh).unwrap();
+            rh
+        };
+
+        let job: ProofJob = Box::new(move || {
+            Proof::AccumulatorDelete(Self::serialize_update_proof(
+                &old_acc_value,
+                &new_acc_value,
+                &delete_proof.element,
+                delete_proof.verify(),
+            ))
+        });
+        Ok((job, root_hash))
+    }
+
+    /// 移除已经没有 fid 的 keyword，并把它从 keyword 集合中删除
+    fn remove_keyword(&mut self, keyword: &str) {
+        self.accumulators.remove(keyword);
+        if let Err(e) = self.keyword_set.delete(keyword) {
+            error!(
+                "Failed to remove keyword '{}' from the keyword set: {}",
+                keyword, e
+            );
         }
     }
 
-    fn delete(&mut self, keyword: &str, fid: &str) -> (Vec<u8>, RootHash) {
-        let element = Self::fid_to_element(keyword, fid);
+    fn accumulator_key(keyword: &str) -> Vec<u8> {
+        format!("acc/value/{}", keyword).into_bytes()
+    }
+
+    fn fids_key(keyword: &str) -> Vec<u8> {
+        format!("acc/fids/{}", keyword).into_bytes()
+    }
 
-        if let Some((acc, fids)) = self.accumulators.get_mut(keyword) {
-            let old_acc_value = acc.acc_value;
+    /// 由序列化的累加器和 fid 列表恢复一个 keyword，检查两者的元素数量一致
+    ///
+    /// 旧版本的元素是 fid（更早是 `keyword:fid`）的 64 位哈希，这样的累加器会按 fid 列表重新构建
+    fn restore_entry(
+        &mut self,
+        keyword: String,
+        accumulator: &[u8],
+        fids: Vec<String>,
+    ) -> Result<(), String> {
+        let acc = DynamicAccumulator::from_bytes(accumulator)
+            .map_err(|e| format!("failed to restore accumulator of '{}': {}", keyword, e))?;
+        if acc.len() != fids.len() {
+            return Err(format!(
+                "accumulator of '{}' holds {} element(s) but {} fid(s) are listed",
+                keyword,
+                acc.len(),
+                fids.len()
+            ));
+        }
+        let acc = if fids.iter().all(|fid| acc.contains(fid)) {
+            acc
+        } else {
+            let mut rebuilt = DynamicAccumulator::new();
+            rebuilt
+                .add_batch(&fids)
+                .map_err(|e| format!("failed to rebuild accumulator of '{}': {}", keyword, e))?;
+            rebuilt
+        };
+        if !fids.is_empty() && !self.accumulators.contains_key(&keyword) {
+            self.insert_keyword(&keyword);
+        }
+        self.accumulators.insert(keyword, (acc, fids));
+        Ok(())
+    }
 
-            // 从累加器删除并验证
-            let delete_proof = acc
-                .delete(&element)
-                .expect("Failed to delete from accumulator");
-            let is_valid = delete_proof.verify();
+    /// 按字典序列出所有有 fid 的 keyword
+    pub fn list_keywords(&self) -> Vec<String> {
+        let mut keywords: Vec<String> = self.accumulators.keys().cloned().collect();
+        keywords.sort();
+        keywords
+    }
 
-            fids.retain(|f| f != fid);
+    /// keyword 当前的累加器值和元素数量，keyword 没有 fid 时返回 None
+    pub fn keyword_accumulator(&self, keyword: &str) -> Option<KeywordAccumulator> {
+        self.accumulators
+            .get(keyword)
+            .map(|(acc, fids)| KeywordAccumulator {
+                value: Self::encode_accumulator(acc),
+                len: fids.len(),
+            })
+    }
 
-            // 序列化证明
-            let proof =
-                Self::serialize_update_proof(&old_acc_value, &acc.acc_value, element, is_valid);
+    /// 导出单个 keyword 的序列化累加器和 fid 列表，用于迁移和修复副本
+    ///
+    /// 格式: [accumulator | fid_count | fid...]，与 [`export_state`](AdsOperations::export_state)
+    /// 中每个 keyword 的条目相同
+    pub fn export_keyword(&self, keyword: &str) -> Option<Vec<u8>> {
+        let (acc, fids) = self.accumulators.get(keyword)?;
+        Self::encode_entry(acc, fids)
+    }
 
-            let root_hash = if fids.is_empty() {
-                self.accumulators.remove(keyword);
-                vec![]
-            } else {
-                let mut rh = Vec::new();
-                acc.acc_value.serialize(&mut rh).unwrap();
-                rh
-            };
+    /// 用 [`export_keyword`](Self::export_keyword) 导出的状态替换 keyword 的累加器和 fid 列表
+    ///
+    /// 其他 keyword 不受影响；状态中没有 fid 时 keyword 被移除。状态无法解码或累加器与 fid
+    /// 列表不一致时返回错误，原有状态保持不变
+    pub fn import_keyword(&mut self, keyword: &str, state: &[u8]) -> Result<(), String> {
+        let mut reader = StateReader::new(state);
+        let accumulator = reader.bytes()?;
+        let fids = read_strings(&mut reader)?;
+        reader.finish()?;
 
-            (proof, root_hash)
-        } else {
-            (vec![0], vec![])
+        if fids.is_empty() {
+            DynamicAccumulator::from_bytes(accumulator)
+                .ok()
+                .filter(|acc| acc.is_empty())
+                .ok_or_else(|| format!("state of '{}' lists no fids but is not empty", keyword))?;
+            if self.accumulators.remove(keyword).is_some() {
+                if let Err(e) = self.keyword_set.delete(keyword) {
+                    error!(
+                        "Failed to remove keyword '{}' from the keyword set: {}",
+                        keyword, e
+                    );
+                }
+            }
+            return Ok(());
+        }
+        self.restore_entry(keyword.to_string(), accumulator, fids)
+    }
+
+    /// 编码一个 keyword 的条目: [accumulator | fid_count | fid...]
+    fn encode_entry(acc: &DynamicAccumulator, fids: &[String]) -> Option<Vec<u8>> {
+        let mut entry = Vec::new();
+        put_bytes(&mut entry, &acc.to_bytes().ok()?);
+        entry.extend_from_slice(&encode_strings(fids.iter()));
+        Some(entry)
+    }
+
+    /// 把所有 keyword 的累加器和 fid 列表写入数据库，并删除已不存在的 keyword
+    pub fn save_to_db(&self, db: &mut dyn Database) -> Result<(), String> {
+        for keyword in Self::stored_keywords(db)? {
+            if !self.accumulators.contains_key(&keyword) {
+                db.delete(&Self::accumulator_key(&keyword))
+                    .and_then(|_| db.delete(&Self::fids_key(&keyword)))
+                    .map_err(|e| e.to_string())?;
+            }
         }
+
+        for (keyword, (acc, fids)) in &self.accumulators {
+            acc.save_to_db(db, &Self::accumulator_key(keyword))
+                .map_err(|e| e.to_string())?;
+            db.put(&Self::fids_key(keyword), &encode_strings(fids.iter()))
+                .map_err(|e| e.to_string())?;
+        }
+
+        db.put(KEYWORDS_KEY, &encode_strings(self.accumulators.keys()))
+            .map_err(|e| e.to_string())
+    }
+
+    /// 从 [`save_to_db`](Self::save_to_db) 写入的数据恢复（数据库为空时返回空的 ADS）
+    pub fn load_from_db(db: &mut dyn Database) -> Result<Self, String> {
+        let mut ads = Self::new();
+        for keyword in Self::stored_keywords(db)? {
+            let accumulator = db
+                .get(&Self::accumulator_key(&keyword))
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("missing accumulator of '{}'", keyword))?;
+            let encoded = db
+                .get(&Self::fids_key(&keyword))
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("missing fids of '{}'", keyword))?;
+            let mut reader = StateReader::new(&encoded);
+            let fids = read_strings(&mut reader)?;
+            reader.finish()?;
+            ads.restore_entry(keyword, &accumulator, fids)?;
+        }
+        Ok(ads)
+    }
+
+    /// 递归求值布尔表达式，返回结果集合的累加器、fid 列表和证明节点
+    ///
+    /// AND / OR / AND NOT 分别使用交集、并集、差集证明把节点的累加器绑定到两个子节点
+    fn prove_expr(
+        &self,
+        expr: &BooleanExpr,
+    ) -> Result<(DynamicAccumulator, Vec<String>, BooleanProof), String> {
+        let (left, right) = match expr {
+            BooleanExpr::Keyword(keyword) => {
+                let (acc, fids) = self.accumulators.get(keyword).cloned().unwrap_or_default();
+                let proof = BooleanProof {
+                    accumulator: Self::encode_accumulator(&acc),
+                    node: Some(Node::Keyword(keyword.clone())),
+                };
+                return Ok((acc, fids, proof));
+            }
+            BooleanExpr::Not(_) => {
+                return Err("NOT is only supported as an operand of AND".to_string());
+            }
+            BooleanExpr::And(left, right) | BooleanExpr::Or(left, right) => (&**left, &**right),
+        };
+        let difference = match (expr, left, right) {
+            (BooleanExpr::And(..), included, BooleanExpr::Not(excluded))
+            | (BooleanExpr::And(..), BooleanExpr::Not(excluded), included) => {
+                Some((included, &**excluded))
+            }
+            _ => None,
+        };
+        let (left, right) = difference.unwrap_or((left, right));
+
+        let (left_acc, left_fids, left_proof) = self.prove_expr(left)?;
+        let (right_acc, right_fids, right_proof) = self.prove_expr(right)?;
+        let right_set: HashSet<&String> = right_fids.iter().collect();
+
+        let (acc, fids, proof, make_node): (_, Vec<String>, _, fn(_) -> Node) = match expr {
+            _ if difference.is_some() => {
+                let (acc, proof) = left_acc
+                    .prove_difference(&right_acc)
+                    .map_err(|e| e.to_string())?;
+                let fids = left_fids.iter().filter(|f| !right_set.contains(f));
+                (acc, fids.cloned().collect(), proof.to_bytes(), Node::AndNot)
+            }
+            BooleanExpr::And(..) => {
+                let (acc, proof) = left_acc
+                    .prove_intersection(&right_acc)
+                    .map_err(|e| e.to_string())?;
+                let fids = left_fids.iter().filter(|f| right_set.contains(f));
+                (acc, fids.cloned().collect(), proof.to_bytes(), Node::And)
+            }
+            _ => {
+                let (acc, proof) = left_acc
+                    .prove_union(&right_acc)
+                    .map_err(|e| e.to_string())?;
+                let left_set: HashSet<&String> = left_fids.iter().collect();
+                let mut fids = left_fids.clone();
+                fids.extend(right_fids.iter().filter(|f| !left_set.contains(f)).cloned());
+                (acc, fids, proof.to_bytes(), Node::Or)
+            }
+        };
+
+        let node = make_node(Box::new(BooleanProofOperation {
+            left: Some(Box::new(left_proof)),
+            right: Some(Box::new(right_proof)),
+            proof: proof.map_err(|e| e.to_string())?,
+        }));
+        let proof = BooleanProof {
+            accumulator: Self::encode_accumulator(&acc),
+            node: Some(node),
+        };
+        Ok((acc, fids, proof))
+    }
+
+    fn encode_accumulator(acc: &DynamicAccumulator) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        acc.acc_value.serialize(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn stored_keywords(db: &mut dyn Database) -> Result<Vec<String>, String> {
+        let Some(encoded) = db.get(KEYWORDS_KEY).map_err(|e| e.to_string())? else {
+            return Ok(Vec::new());
+        };
+        let mut reader = StateReader::new(&encoded);
+        let keywords = read_strings(&mut reader)?;
+        reader.finish()?;
+        Ok(keywords)
+    }
+}
+
+impl AdsOperations for CryptoAccumulatorAds {
+    fn add(&mut self, keyword: &str, fid: &str) -> AdsResult {
+        let (job, root_hash) = self.add_deferred(keyword, fid)?;
+        Ok((job(), root_hash))
+    }
+
+    /// 证明依次包含每个 keyword 的添加记录，Manager 据此跟踪每个 keyword 的累加器值；
+    /// 某个 keyword 的记录无效时截断，Manager 会拒绝整个批次
+    fn add_batch(&mut self, keywords: &[String], fid: &str) -> AdsResult {
+        let (job, root_hash) = self.add_batch_deferred(keywords, fid)?;
+        Ok((job(), root_hash))
+    }
+
+    fn query(&self, keyword: &str) -> (Vec<String>, Proof) {
+        match self.accumulators.get(keyword) {
+            Some((acc, fids)) if !fids.is_empty() => {
+                (fids.clone(), Self::membership_proof(acc, fids))
+            }
+            _ => (vec![], self.non_membership_proof(keyword)),
+        }
+    }
+
+    /// 证明格式: [acc(96) | excluded_acc(96) | difference_proof | valid(1)]
+    ///
+    /// `excluded_acc` 由 `excluded` 构建，验证方需要确认它等于被排除 keyword 的累加器
+    fn prove_difference(
+        &self,
+        keyword: &str,
+        excluded: &[String],
+    ) -> Option<(Vec<String>, Vec<u8>)> {
+        let empty = (DynamicAccumulator::new(), Vec::new());
+        let (acc, fids) = self.accumulators.get(keyword).unwrap_or(&empty);
+
+        let excluded: HashSet<&String> = excluded.iter().collect();
+        let excluded_fids: Vec<&String> = excluded.iter().copied().collect();
+        let mut excluded_acc = DynamicAccumulator::new();
+        if excluded_acc.add_batch(&excluded_fids).is_err() {
+            return Some((vec![], vec![0]));
+        }
+
+        let difference: Vec<String> = fids
+            .iter()
+            .filter(|fid| !excluded.contains(fid))
+            .cloned()
+            .collect();
+
+        let Ok((_, difference_proof)) = acc.prove_difference(&excluded_acc) else {
+            return Some((vec![], vec![0]));
+        };
+        let is_valid = DynamicAccumulator::verify_difference_with_values(
+            acc.acc_value,
+            excluded_acc.acc_value,
+            &difference,
+            &difference_proof,
+        );
+
+        let mut proof = Vec::new();
+        acc.acc_value.serialize(&mut proof).unwrap();
+        excluded_acc.acc_value.serialize(&mut proof).unwrap();
+        proof.extend_from_slice(&difference_proof.to_bytes().ok()?);
+        proof.push(if is_valid { 1 } else { 0 });
+        Some((difference, proof))
+    }
+
+    fn query_boolean(&self, expr: &BooleanExpr) -> Result<(Vec<String>, BooleanProof), String> {
+        let (_, fids, proof) = self.prove_expr(expr)?;
+        Ok((fids, proof))
+    }
+
+    fn delete(&mut self, keyword: &str, fid: &str) -> AdsResult {
+        let (job, root_hash) = self.delete_deferred(keyword, fid)?;
+        Ok((job(), root_hash))
+    }
+
+    /// 证明依次包含每个 keyword 的删除记录，与 [`add_batch`](AdsOperations::add_batch) 一致
+    fn delete_batch(&mut self, keywords: &[String], fid: &str) -> AdsResult {
+        if keywords.is_empty() {
+            return Err(AdsError::EmptyBatch);
+        }
+        let mut proofs = Vec::new();
+        let mut root_hash = Vec::new();
+        for keyword in keywords {
+            let (proof, root) = self.delete(keyword, fid)?;
+            let is_valid = proof.data().last() == Some(&1);
+            proofs.push(proof);
+            root_hash = root;
+            if !is_valid {
+                break;
+            }
+        }
+        Ok((Self::concat_records(proofs), root_hash))
+    }
+
+    /// 在同一个累加器上完成删除和添加，keyword 不会因为暂时没有 fid 而离开 keyword 集合
+    ///
+    /// 返回添加 `new_fid` 的证明，其旧值是删除 `old_fid` 之后的累加器；
+    /// `old_fid` 不存在、`new_fid` 已经存在或两者相同时按默认实现先删除再添加
+    fn update(&mut self, keyword: &str, old_fid: &str, new_fid: &str) -> AdsResult {
+        let replaceable = old_fid != new_fid
+            && self.accumulators.get(keyword).is_some_and(|(_, fids)| {
+                fids.iter().any(|f| f == old_fid) && !fids.iter().any(|f| f == new_fid)
+            });
+        if !replaceable {
+            self.delete(keyword, old_fid)?;
+            return self.add(keyword, new_fid);
+        }
+
+        let (acc, fids) = self.accumulators.get_mut(keyword).unwrap();
+        let add_proof = match acc.update(old_fid, new_fid) {
+            Ok((_, add_proof)) => add_proof,
+            Err(e) => {
+                // 删除已经生效，只是新的 fid 没能加入
+                fids.retain(|f| f != old_fid);
+                if fids.is_empty() {
+                    self.remove_keyword(keyword);
+                }
+                return Err(AdsError::Backend(format!(
+                    "accumulator of '{}' replaced fid '{}' but rejected '{}': {}",
+                    keyword, old_fid, new_fid, e
+                )));
+            }
+        };
+        fids.retain(|f| f != old_fid);
+        fids.push(new_fid.to_string());
+
+        let mut root_hash = Vec::new();
+        add_proof.new_acc_value.serialize(&mut root_hash).unwrap();
+        let proof = Self::serialize_update_proof(
+            &add_proof.old_acc_value,
+            &add_proof.new_acc_value,
+            &add_proof.element,
+            add_proof.verify(),
+        );
+        Ok((Proof::AccumulatorAdd(proof), root_hash))
+    }
+
+    /// keyword 集合累加器的值
+    ///
+    /// 写操作返回的是单个 keyword 的累加器；keyword 集合只承诺哪些 keyword 有 fid，不覆盖 fid 本身
+    fn root_hash(&self) -> Option<RootHash> {
+        let mut root_hash = Vec::new();
+        self.keyword_set
+            .acc_value
+            .serialize(&mut root_hash)
+            .unwrap();
+        Some(root_hash)
+    }
+
+    /// 只在这里更新累加器，配对检查（添加和删除证明的主要开销）留给返回的任务
+    fn apply_deferred(&mut self, mutation: Mutation) -> Result<(ProofJob, RootHash), AdsError> {
+        match mutation {
+            Mutation::Add { keyword, fid } => self.add_deferred(keyword, fid),
+            Mutation::AddBatch { keywords, fid } => self.add_batch_deferred(keywords, fid),
+            Mutation::Delete { keyword, fid } => self.delete_deferred(keyword, fid),
+        }
+    }
+
+    /// 导出每个 keyword 的序列化累加器和 fid 列表，恢复时不需要重放添加
+    ///
+    /// 格式: [count | (keyword | accumulator | fid_count | fid...)...]，keyword 按字典序
+    fn export_state(&self) -> Option<Vec<u8>> {
+        let mut keywords: Vec<_> = self.accumulators.iter().collect();
+        keywords.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut state = Vec::new();
+        put_u32(&mut state, keywords.len() as u32);
+        for (keyword, (acc, fids)) in keywords {
+            put_bytes(&mut state, keyword.as_bytes());
+            state.extend_from_slice(&Self::encode_entry(acc, fids)?);
+        }
+        Some(state)
+    }
+
+    fn import_state(&mut self, state: &[u8]) -> Result<(), String> {
+        let mut reader = StateReader::new(state);
+        for _ in 0..reader.u32()? {
+            let keyword = reader.string()?;
+            let accumulator = reader.bytes()?;
+            let fids = read_strings(&mut reader)?;
+            self.restore_entry(keyword, accumulator, fids)?;
+        }
+        reader.finish()
+    }
+
+    fn keywords(&self) -> Option<Vec<String>> {
+        Some(self.accumulators.keys().cloned().collect())
+    }
+
+    /// 每个 keyword 的累加器和 fid 列表分别保存，见 [`save_to_db`](Self::save_to_db)
+    fn save_state(&mut self, db: &mut dyn Database) -> Result<(), String> {
+        self.save_to_db(db)
+    }
+
+    fn load_state(&mut self, db: &mut dyn Database) -> Result<(), String> {
+        *self = Self::load_from_db(db)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use esa_rust::mpt::db::MemoryDatabase;
+
+    fn sample() -> CryptoAccumulatorAds {
+        let mut ads = CryptoAccumulatorAds::new();
+        ads.add("rust", "f1").unwrap();
+        ads.add("go", "f2").unwrap();
+        ads.add("rust", "f3").unwrap();
+        ads
+    }
+
+    #[test]
+    fn test_state_roundtrip_keeps_root() {
+        let ads = sample();
+        let (_, proof_before) = ads.query("rust");
+
+        let mut restored = CryptoAccumulatorAds::new();
+        restored.import_state(&ads.export_state().unwrap()).unwrap();
+        let (fids, proof) = restored.query("rust");
+        assert_eq!(fids, vec!["f1", "f3"]);
+        assert_eq!(proof, proof_before);
+        assert_eq!(restored.export_state(), ads.export_state());
+        assert!(CryptoAccumulatorAds::new().import_state(&[1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load_from_db() {
+        let mut db = MemoryDatabase::new();
+        assert!(CryptoAccumulatorAds::load_from_db(&mut db)
+            .unwrap()
+            .accumulators
+            .is_empty());
+
+        let mut ads = sample();
+        ads.save_to_db(&mut db).unwrap();
+        ads.delete("go", "f2").unwrap();
+        ads.save_to_db(&mut db).unwrap();
+
+        let mut loaded = CryptoAccumulatorAds::load_from_db(&mut db).unwrap();
+        assert_eq!(loaded.export_state(), ads.export_state());
+        assert_eq!(db.get(b"acc/fids/go").unwrap(), None);
+
+        // 恢复后继续写入，证明仍然有效
+        let (proof, _) = loaded.add("rust", "f4").unwrap();
+        assert_eq!(proof.data().last(), Some(&1));
+    }
+
+    #[test]
+    fn test_keyword_registry() {
+        let mut ads = sample();
+        let (_, root_hash) = ads.add("rust", "f4").unwrap();
+        assert_eq!(ads.list_keywords(), vec!["go", "rust"]);
+        let rust = ads.keyword_accumulator("rust").unwrap();
+        assert_eq!((rust.len, &rust.value), (3, &root_hash));
+        assert!(ads.keyword_accumulator("java").is_none());
+        assert!(ads.export_keyword("java").is_none());
+
+        // 修复落后的副本：只替换一个 keyword 的状态
+        let mut replica = CryptoAccumulatorAds::new();
+        replica.add("rust", "f1").unwrap();
+        replica.add("python", "f9").unwrap();
+        replica
+            .import_keyword("rust", &ads.export_keyword("rust").unwrap())
+            .unwrap();
+        assert_eq!(replica.keyword_accumulator("rust"), Some(rust));
+        assert_eq!(replica.query("rust").1, ads.query("rust").1);
+        assert_eq!(replica.list_keywords(), vec!["python", "rust"]);
+
+        // 没有 fid 的状态移除 keyword，keyword 集合随之更新
+        let empty = CryptoAccumulatorAds::encode_entry(&DynamicAccumulator::new(), &[]).unwrap();
+        replica.import_keyword("python", &empty).unwrap();
+        assert_eq!(replica.list_keywords(), vec!["rust"]);
+        assert!(matches!(
+            replica.query("python").1,
+            Proof::AccumulatorNonMembership(_)
+        ));
+
+        // 累加器与 fid 列表不一致时拒绝，原有状态不变
+        let mismatched = CryptoAccumulatorAds::encode_entry(
+            &replica.accumulators["rust"].0,
+            &["f1".to_string()],
+        )
+        .unwrap();
+        assert!(replica.import_keyword("rust", &mismatched).is_err());
+        assert!(replica.import_keyword("rust", &[1, 2]).is_err());
+        assert_eq!(replica.keyword_accumulator("rust").unwrap().len, 3);
+    }
+
+    #[test]
+    fn test_update_keeps_keyword_in_set() {
+        let mut ads = sample();
+        let keyword_set = ads.root_hash();
+        let (proof, root_hash) = ads.update("go", "f2", "f4").unwrap();
+        assert!(matches!(proof, Proof::AccumulatorAdd(_)));
+        assert_eq!(proof.data().last(), Some(&1));
+        assert_eq!(ads.root_hash(), keyword_set);
+
+        // 与先删除再添加的结果相同
+        let mut expected = sample();
+        expected.delete("go", "f2").unwrap();
+        assert_eq!(expected.add("go", "f4").unwrap().1, root_hash);
+        assert_eq!(ads.export_state(), expected.export_state());
+
+        // 替换不成立时退化为删除加添加，删除不存在的 fid 失败
+        let missing = AdsError::MissingFid {
+            keyword: "go".to_string(),
+            fid: "f9".to_string(),
+        };
+        assert_eq!(ads.update("go", "f9", "f4"), Err(missing));
+        let (proof, _) = ads.update("go", "f4", "f4").unwrap();
+        assert_eq!(proof.data().last(), Some(&1));
+        assert_eq!(ads.query("go").0, vec!["f4"]);
+
+        ads.add("rust", "f4").unwrap();
+        let keywords = ["rust".to_string(), "go".to_string()];
+        let (proof, root_hash) = ads.delete_batch(&keywords, "f4").unwrap();
+        assert_eq!(proof.data().last(), Some(&1));
+        assert!(root_hash.is_empty());
+        assert_eq!(ads.list_keywords(), vec!["rust"]);
+        // 某个 keyword 没有 fid 时返回错误，之前的 keyword 已经删除
+        assert_eq!(
+            ads.delete_batch(&keywords, "f1"),
+            Err(AdsError::MissingKeyword("go".to_string()))
+        );
+        assert_eq!(ads.query("rust").0, vec!["f3"]);
+        assert_eq!(ads.delete_batch(&[], "f3"), Err(AdsError::EmptyBatch));
+    }
+
+    #[test]
+    fn test_absent_keyword_proves_non_membership() {
+        let mut ads = sample();
+        let (fids, proof) = ads.query("java");
+        assert!(fids.is_empty());
+        assert!(matches!(proof, Proof::AccumulatorNonMembership(_)));
+        assert_eq!(proof.data().last(), Some(&1));
+
+        // 最后一个 fid 被删除后 keyword 离开 keyword 集合
+        ads.delete("go", "f2").unwrap();
+        let (_, proof) = ads.query("go");
+        assert!(matches!(proof, Proof::AccumulatorNonMembership(_)));
+        assert_eq!(proof.data().last(), Some(&1));
+
+        // 导入状态时重建 keyword 集合
+        let mut restored = CryptoAccumulatorAds::new();
+        restored.import_state(&ads.export_state().unwrap()).unwrap();
+        assert_eq!(restored.keyword_set, ads.keyword_set);
+        assert!(restored.keyword_set.contains("rust"));
+    }
+
+    #[test]
+    fn test_prove_difference() {
+        let mut ads = sample();
+        ads.add("go", "f3").unwrap();
+        let excluded = ads.query("go").0;
+
+        let (fids, proof) = ads.prove_difference("rust", &excluded).unwrap();
+        assert_eq!(fids, vec!["f1"]);
+        assert_eq!(proof.last(), Some(&1));
+
+        // 被排除的 keyword 不存在时差集就是全部 fid
+        let (fids, proof) = ads.prove_difference("rust", &[]).unwrap();
+        assert_eq!(fids, vec!["f1", "f3"]);
+        assert_eq!(proof.last(), Some(&1));
+    }
+
+    #[test]
+    fn test_cached_witness_proves_fid() {
+        let mut ads = sample();
+        let uncached = ads.prove_fid("rust", "f1");
+        ads.cache_witness("rust", "f1").unwrap();
+        assert!(ads.cache_witness("rust", "f2").is_err());
+        assert!(ads.cache_witness("java", "f1").is_err());
+        assert_eq!(ads.prove_fid("rust", "f1"), uncached);
+
+        // 缓存的 witness 随写入更新
+        ads.add("rust", "f4").unwrap();
+        ads.delete("rust", "f3").unwrap();
+        let mut fresh = CryptoAccumulatorAds::new();
+        fresh.add("rust", "f1").unwrap();
+        fresh.add("rust", "f4").unwrap();
+        let proof = ads.prove_fid("rust", "f1");
+        assert_eq!(proof, fresh.prove_fid("rust", "f1"));
+        assert_eq!(proof.data().last(), Some(&1));
+        assert_eq!(ads.prove_fid("java", "f1").data(), &[0]);
+    }
+
+    #[test]
+    fn test_restore_rebuilds_legacy_elements() {
+        // 旧版本按 keyword:fid 的 64 位哈希计算元素
+        let legacy_element = "rust:f1"
+            .bytes()
+            .fold(0i64, |acc, b| acc.wrapping_mul(31).wrapping_add(b as i64));
+        let mut legacy = DynamicAccumulator::new();
+        legacy.add(&legacy_element).unwrap();
+
+        let mut ads = CryptoAccumulatorAds::new();
+        ads.restore_entry(
+            "rust".to_string(),
+            &legacy.to_bytes().unwrap(),
+            vec!["f1".to_string()],
+        )
+        .unwrap();
+        assert!(ads.accumulators["rust"].0.contains("f1"));
+        assert_eq!(ads.query("rust").1.data().last(), Some(&1));
     }
 }
@@ -0,0 +1,317 @@
+//! Merkle Tree ADS Implementation
+//!
+//! 整个 storager 只维护一棵二叉 Merkle 树，每个 (keyword, fid) 对是一个叶子，
+//! 树根即 storager 的根哈希。叶子按 (keyword, fid) 排序，查询证明带上 keyword 那一段叶子
+//! 两侧的相邻叶子，证明结果完整或 keyword 不存在；代价是插入和删除要移动其后的叶子，
+//! 为 O(n)。证明格式见 [`esa_rust::merkle_tree::MerkleAdsProof`]。
+
+use super::state::{put_bytes, put_u32, StateReader};
+use super::{AdsOperations, AdsResult, QueryBatch};
+use common::{AdsError, Proof, RootHash};
+use esa_rust::merkle_tree::{leaf_hash, MerkleAdsProof, MerkleInclusion, MerkleTree};
+use std::ops::Range;
+
+/// Merkle Tree ADS 实现
+pub struct MerkleTreeAds {
+    tree: MerkleTree,
+    /// 与树的叶子层一一对应的 (keyword, fid)，按 (keyword, fid) 排序
+    entries: Vec<(String, String)>,
+}
+
+impl MerkleTreeAds {
+    pub fn new() -> Self {
+        MerkleTreeAds {
+            tree: MerkleTree::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    fn inclusion(&self, index: usize) -> MerkleInclusion {
+        let proof = self
+            .tree
+            .prove(index)
+            .expect("leaf index tracked by MerkleTreeAds must exist");
+        let (keyword, fid) = &self.entries[index];
+        MerkleInclusion {
+            keyword: keyword.clone(),
+            fid: fid.clone(),
+            index: proof.index,
+            siblings: proof.siblings,
+        }
+    }
+
+    /// (keyword, fid) 叶子的位置；不存在时为它应当插入的位置
+    fn position(&self, keyword: &str, fid: &str) -> Result<usize, usize> {
+        self.entries
+            .binary_search_by(|(k, f)| (k.as_str(), f.as_str()).cmp(&(keyword, fid)))
+    }
+
+    /// keyword 的叶子所在的一段位置
+    fn range(&self, keyword: &str) -> Range<usize> {
+        let start = self.entries.partition_point(|(k, _)| k.as_str() < keyword);
+        let end = self.entries.partition_point(|(k, _)| k.as_str() <= keyword);
+        start..end
+    }
+
+    /// 插入 (keyword, fid) 叶子，重复添加时树不变
+    fn insert_leaf(&mut self, keyword: &str, fid: &str) {
+        if let Err(index) = self.position(keyword, fid) {
+            self.entries
+                .insert(index, (keyword.to_string(), fid.to_string()));
+            self.tree.insert_at(index, leaf_hash(keyword, fid));
+        }
+    }
+
+    /// keyword 的叶子中 `batch` 这一段的证明
+    ///
+    /// 这一段从 keyword 的第一个叶子开始时带上之前的相邻叶子，到最后一个叶子结束时带上之后的
+    fn range_proof(&self, keyword: &str, batch: Range<usize>) -> Proof {
+        let range = self.range(keyword);
+        let mut boundaries = Vec::new();
+        if batch.start == range.start && range.start > 0 {
+            boundaries.push(self.inclusion(range.start - 1));
+        }
+        if batch.end == range.end && range.end < self.entries.len() {
+            boundaries.push(self.inclusion(range.end));
+        }
+        let inclusions = batch.map(|index| self.inclusion(index)).collect();
+        self.proof(inclusions, boundaries).0
+    }
+
+    /// 生成证明并返回当前根
+    fn proof(
+        &self,
+        inclusions: Vec<MerkleInclusion>,
+        boundaries: Vec<MerkleInclusion>,
+    ) -> (Proof, RootHash) {
+        let root = self.tree.root();
+        let proof = MerkleAdsProof {
+            root,
+            inclusions,
+            boundaries,
+        };
+        (Proof::Merkle(proof.to_bytes()), root.to_vec())
+    }
+}
+
+impl Default for MerkleTreeAds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdsOperations for MerkleTreeAds {
+    fn add(&mut self, keyword: &str, fid: &str) -> AdsResult {
+        self.add_batch(&[keyword.to_string()], fid)
+    }
+
+    fn add_batch(&mut self, keywords: &[String], fid: &str) -> AdsResult {
+        if keywords.is_empty() {
+            return Err(AdsError::EmptyBatch);
+        }
+        for keyword in keywords {
+            self.insert_leaf(keyword, fid);
+        }
+
+        // 所有叶子写入后再定位并生成证明，使每个包含证明都对应最终的根
+        let inclusions = keywords
+            .iter()
+            .filter_map(|keyword| self.position(keyword, fid).ok())
+            .map(|index| self.inclusion(index))
+            .collect();
+        Ok(self.proof(inclusions, Vec::new()))
+    }
+
+    fn query(&self, keyword: &str) -> (Vec<String>, Proof) {
+        let range = self.range(keyword);
+        let fids = self.entries[range.clone()]
+            .iter()
+            .map(|(_, fid)| fid.clone())
+            .collect();
+        (fids, self.range_proof(keyword, range))
+    }
+
+    /// 每批的证明只包含这批 fid 的包含证明，第一批和最后一批分别带上两侧的边界；
+    /// keyword 没有 fid 时是它不存在的证明
+    fn query_batch(&self, keyword: &str, offset: usize, limit: usize) -> Option<QueryBatch> {
+        let range = self.range(keyword);
+        let start = (range.start + offset).min(range.end);
+        let batch = start..(start + limit).min(range.end);
+
+        let fids = self.entries[batch.clone()]
+            .iter()
+            .map(|(_, fid)| fid.clone())
+            .collect();
+        Some((fids, self.range_proof(keyword, batch), range.len()))
+    }
+
+    fn delete(&mut self, keyword: &str, fid: &str) -> AdsResult {
+        if let Ok(index) = self.position(keyword, fid) {
+            self.entries.remove(index);
+            self.tree.remove_at(index);
+        }
+
+        // 删除后的 keyword 由查询证明其不存在，这里只返回删除后的根
+        Ok(self.proof(Vec::new(), Vec::new()))
+    }
+
+    fn root_hash(&self) -> Option<RootHash> {
+        Some(self.tree.root().to_vec())
+    }
+
+    /// 格式：按 keyword 排序的 (keyword, [fid])，恢复时按同样的顺序重建树，根与导出时相同
+    fn export_state(&self) -> Option<Vec<u8>> {
+        let mut keywords: Vec<(&str, Vec<&str>)> = Vec::new();
+        for (keyword, fid) in &self.entries {
+            match keywords.last_mut() {
+                Some((last, fids)) if last == keyword => fids.push(fid),
+                _ => keywords.push((keyword, vec![fid])),
+            }
+        }
+
+        let mut buf = Vec::new();
+        put_u32(&mut buf, keywords.len() as u32);
+        for (keyword, fids) in keywords {
+            put_bytes(&mut buf, keyword.as_bytes());
+            put_u32(&mut buf, fids.len() as u32);
+            for fid in fids {
+                put_bytes(&mut buf, fid.as_bytes());
+            }
+        }
+        Some(buf)
+    }
+
+    fn import_state(&mut self, state: &[u8]) -> Result<(), String> {
+        let mut reader = StateReader::new(state);
+        let mut entries = Vec::new();
+        for _ in 0..reader.u32()? {
+            let keyword = reader.string()?;
+            for _ in 0..reader.u32()? {
+                entries.push((keyword.clone(), reader.string()?));
+            }
+        }
+        reader.finish()?;
+        if !entries.windows(2).all(|pair| pair[0] < pair[1]) {
+            return Err("state entries are not sorted and unique".to_string());
+        }
+
+        self.tree = MerkleTree::from_leaves(entries.iter().map(|(k, f)| leaf_hash(k, f)).collect());
+        self.entries = entries;
+        Ok(())
+    }
+
+    fn keywords(&self) -> Option<Vec<String>> {
+        let mut keywords: Vec<String> = self.entries.iter().map(|(k, _)| k.clone()).collect();
+        keywords.dedup();
+        Some(keywords)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use esa_rust::merkle_tree::verify_merkle_proof;
+
+    #[test]
+    fn test_proofs_verify_with_manager_verifier() {
+        let mut ads = MerkleTreeAds::new();
+        ads.add("rust", "f1").unwrap();
+        ads.add("go", "f2").unwrap();
+        let (proof, root) = ads.add("rust", "f3").unwrap();
+        assert!(verify_merkle_proof(proof.data(), &root));
+
+        let (fids, proof) = ads.query("rust");
+        assert_eq!(fids, vec!["f1", "f3"]);
+        assert!(verify_merkle_proof(proof.data(), &root));
+        let decoded = MerkleAdsProof::from_bytes(proof.data()).unwrap();
+        assert_eq!(decoded.fids(), vec!["f1", "f3"]);
+        assert!(decoded.covers_keyword("rust"));
+        assert!(!decoded.covers_keyword("go"));
+
+        // 不存在的 keyword 返回空列表和相邻叶子组成的不存在证明
+        let (fids, proof) = ads.query("java");
+        assert!(fids.is_empty());
+        assert!(verify_merkle_proof(proof.data(), &root));
+        let decoded = MerkleAdsProof::from_bytes(proof.data()).unwrap();
+        assert!(decoded.inclusions.is_empty() && decoded.covers_keyword("java"));
+    }
+
+    #[test]
+    fn test_batches_cover_keyword_together() {
+        let mut ads = MerkleTreeAds::new();
+        for i in 0..7 {
+            ads.add("rust", &format!("f{}", i)).unwrap();
+        }
+        ads.add("go", "f0").unwrap();
+        let (_, root) = ads.add("zig", "f0").unwrap();
+
+        let mut merged = MerkleAdsProof::default();
+        for offset in [0, 3, 6] {
+            let (fids, proof, total) = ads.query_batch("rust", offset, 3).unwrap();
+            assert_eq!((fids.len(), total), (if offset == 6 { 1 } else { 3 }, 7));
+            assert!(verify_merkle_proof(proof.data(), &root));
+            let batch = MerkleAdsProof::from_bytes(proof.data()).unwrap();
+            // 每批最多带一侧的边界，单独不能证明完整
+            assert!(!batch.covers_keyword("rust"));
+            merged.root = batch.root;
+            merged.inclusions.extend(batch.inclusions);
+            merged.boundaries.extend(batch.boundaries);
+        }
+        assert!(merged.covers_keyword("rust"));
+        assert_eq!(merged.fids(), ads.query("rust").0);
+    }
+
+    #[test]
+    fn test_add_batch_single_proof() {
+        let mut ads = MerkleTreeAds::new();
+        ads.add("go", "f0").unwrap();
+        let keywords = vec!["rust".to_string(), "db".to_string(), "rust".to_string()];
+        let (proof, root) = ads.add_batch(&keywords, "f1").unwrap();
+        assert!(verify_merkle_proof(proof.data(), &root));
+
+        let decoded = MerkleAdsProof::from_bytes(proof.data()).unwrap();
+        assert_eq!(decoded.inclusions.len(), 3);
+        assert_eq!(ads.tree.len(), 3);
+        assert_eq!(ads.query("rust").0, vec!["f1"]);
+        assert_eq!(ads.query("db").0, vec!["f1"]);
+    }
+
+    #[test]
+    fn test_delete_updates_root() {
+        let mut ads = MerkleTreeAds::new();
+        let (_, root_one) = ads.add("rust", "f1").unwrap();
+        let (_, root_two) = ads.add("rust", "f2").unwrap();
+        assert_ne!(root_one, root_two);
+
+        let (proof, root) = ads.delete("rust", "f2").unwrap();
+        assert!(verify_merkle_proof(proof.data(), &root));
+        assert_ne!(root, root_two);
+        assert_eq!(ads.query("rust").0, vec!["f1"]);
+
+        // 旧根下的证明不再被接受
+        let (_, query_proof) = ads.query("rust");
+        assert!(!verify_merkle_proof(query_proof.data(), &root_two));
+
+        let (_, root) = ads.delete("rust", "f1").unwrap();
+        assert_eq!(root, vec![0u8; 32]);
+        assert!(ads.query("rust").0.is_empty());
+    }
+
+    #[test]
+    fn test_state_roundtrip_keeps_root() {
+        let mut ads = MerkleTreeAds::new();
+        ads.add("rust", "f1").unwrap();
+        ads.add("go", "f2").unwrap();
+        ads.add("rust", "f3").unwrap();
+        let (_, root) = ads.delete("go", "f2").unwrap();
+
+        let mut restored = MerkleTreeAds::new();
+        restored.import_state(&ads.export_state().unwrap()).unwrap();
+        assert_eq!(restored.tree.root().to_vec(), root);
+
+        let (fids, proof) = restored.query("rust");
+        assert_eq!(fids, vec!["f1", "f3"]);
+        assert!(verify_merkle_proof(proof.data(), &root));
+        assert!(MerkleTreeAds::new().import_state(&[1, 2]).is_err());
+    }
+}
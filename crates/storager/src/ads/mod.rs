@@ -6,30 +6,312 @@
 //! ## 可用的 ADS 实现
 //! - **CryptoAccumulatorAds**: 基于 BLS12-381 的密码学累加器
 //! - **MptAds**: Merkle Patricia Trie (以太坊风格)
+//! - **MerkleTreeAds**: 二叉 Merkle 树（包含证明）
+//! - **SmtAds**: 稀疏 Merkle 树（包含证明和紧凑的非成员资格证明）
+//!
+//! 第三方 ADS 可以通过 [`registry::register_ads_backend`] 在启动时注册；
+//! 任意 ADS 都可以用 [`PersistentAds`] 包装，将状态持久化到 RocksDB
+
+use common::rpc::BooleanProof;
+use common::{AdsError, BooleanExpr, Proof, RootHash};
+use esa_rust::mpt::node::Database;
+use std::time::Duration;
+
+pub use esa_rust::mpt::PruneStats;
+
+/// [`AdsOperations::save_state`] 默认实现保存导出状态的键
+const EXPORTED_STATE_KEY: &[u8] = b"ads:state";
+
+/// 范围查询的结果：按 keyword 排序的 (keyword, fids)
+pub type RangeEntries = Vec<(String, Vec<String>)>;
+
+/// 前缀查询的结果：按 keyword 排序的 (keyword, fid 数量)
+pub type PrefixEntries = Vec<(String, u64)>;
+
+/// 流式查询的一批结果: (fids, 只覆盖这批 fid 的 proof, 列表总长度)
+pub type QueryBatch = (Vec<String>, Proof, usize);
+
+/// 推迟生成的写入证明，在 ADS 线程池中执行
+pub type ProofJob = Box<dyn FnOnce() -> Proof + Send>;
+
+/// 可以推迟证明生成的写操作，见 [`AdsOperations::apply_deferred`]
+#[derive(Debug, Clone, Copy)]
+pub enum Mutation<'a> {
+    Add { keyword: &'a str, fid: &'a str },
+    AddBatch { keywords: &'a [String], fid: &'a str },
+    Delete { keyword: &'a str, fid: &'a str },
+}
 
-use common::RootHash;
+/// 写操作的结果: (proof, root_hash)
+pub type AdsResult = Result<(Proof, RootHash), AdsError>;
+
+/// 墓碑删除的统计，见 [`AdsOperations::tombstone_stats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TombstoneStats {
+    /// 当前的墓碑数量
+    pub tombstones: u64,
+    /// 有 fid 的 keyword 数量
+    pub live: u64,
+    /// 已执行的压缩次数
+    pub compactions: u64,
+    /// 压缩物理删除的墓碑总数
+    pub compacted: u64,
+}
+
+impl TombstoneStats {
+    /// 墓碑占 ADS 中 keyword（含墓碑）的比例
+    pub fn ratio(&self) -> f64 {
+        match self.tombstones + self.live {
+            0 => 0.0,
+            total => self.tombstones as f64 / total as f64,
+        }
+    }
+}
 
 /// ADS 操作的通用 trait
 ///
-/// 所有认证数据结构都需要实现这个 trait
+/// 所有认证数据结构都需要实现这个 trait。写操作失败时返回 [`AdsError`]，
+/// 重复添加和删除不存在的 fid 仍然是成功的写操作，除非实现无法为它们给出证明
 pub trait AdsOperations: Send + Sync {
     /// 添加 (keyword, fid) 对到 ADS
     /// 返回: (proof, root_hash)
-    fn add(&mut self, keyword: &str, fid: &str) -> (Vec<u8>, RootHash);
+    fn add(&mut self, keyword: &str, fid: &str) -> AdsResult;
+
+    /// 将同一个 fid 添加到多个 keyword 下
+    /// 返回: (proof, root_hash)，证明覆盖本次添加的所有 keyword；`keywords` 为空时返回
+    /// [`AdsError::EmptyBatch`]
+    ///
+    /// 默认实现逐个调用 [`add`](Self::add) 并返回最后一次的结果，遇到错误时停止；
+    /// 证明可以合并的实现应覆盖此方法
+    fn add_batch(&mut self, keywords: &[String], fid: &str) -> AdsResult {
+        let mut result = Err(AdsError::EmptyBatch);
+        for keyword in keywords {
+            result = Ok(self.add(keyword, fid)?);
+        }
+        result
+    }
 
     /// 查询 keyword 对应的所有 fid
     /// 返回: (fids, proof)
-    fn query(&self, keyword: &str) -> (Vec<String>, Vec<u8>);
+    fn query(&self, keyword: &str) -> (Vec<String>, Proof);
+
+    /// 查询 keyword 倒排列表中 `[offset, offset + limit)` 的一批 fid（流式查询）
+    ///
+    /// 每批证明单独对照根哈希验证，接收方不需要完整的证明，收齐各批后再确认列表完整。
+    /// 证明绑定整个列表的 ADS（MPT、稀疏 Merkle 树、累加器）返回 `None`，
+    /// 流式查询改为在最后传输完整的证明
+    fn query_batch(
+        &self,
+        _keyword: &str,
+        _offset: usize,
+        _limit: usize,
+    ) -> Option<QueryBatch> {
+        None
+    }
+
+    /// 证明 keyword 的 fid 集合去掉 `excluded` 之后的差集（`A AND NOT B` 查询）
+    /// 返回: (差集 fids, proof)
+    ///
+    /// `excluded` 是被排除 keyword 的完整 fid 集合；返回 `None` 表示不支持差集证明
+    fn prove_difference(
+        &self,
+        _keyword: &str,
+        _excluded: &[String],
+    ) -> Option<(Vec<String>, Vec<u8>)> {
+        None
+    }
+
+    /// 在本地 keyword 上求值布尔表达式，并为每个子表达式生成证明节点
+    /// 返回: (fids, proof)
+    ///
+    /// 默认不支持；表达式无法证明（例如单独的 NOT）时同样返回错误
+    fn query_boolean(&self, _expr: &BooleanExpr) -> Result<(Vec<String>, BooleanProof), String> {
+        Err("boolean query proofs are not supported".to_string())
+    }
+
+    /// 列出 `[start, end)` 内的 keyword 及其 fid（按 keyword 排序），附带范围证明
+    /// 返回: (entries, proof)
+    ///
+    /// `end` 为空表示没有上界；返回 `None` 表示不支持范围查询
+    fn range_query(
+        &self,
+        _start: &str,
+        _end: &str,
+    ) -> Option<(RangeEntries, Vec<u8>)> {
+        None
+    }
+
+    /// 列出以 `prefix` 开头的 keyword 及其 fid 数量（按 keyword 排序），附带前缀子树证明
+    /// 返回: (keywords, proof)
+    ///
+    /// 返回 `None` 表示不支持前缀查询
+    fn prefix_query(&self, _prefix: &str) -> Option<(PrefixEntries, Vec<u8>)> {
+        None
+    }
+
+    /// 针对历史根哈希查询 keyword，证明对照该根验证
+    /// 返回: (fids, proof)
+    ///
+    /// 默认不支持；根哈希未知或其数据已被回收时同样返回错误
+    fn query_at_root(
+        &self,
+        _keyword: &str,
+        _root_hash: &[u8],
+    ) -> Result<(Vec<String>, Proof), String> {
+        Err("historical queries are not supported".to_string())
+    }
+
+    /// 列出可以用 [`query_at_root`](Self::query_at_root) 查询的历史根 (版本号, 根哈希)，按版本号升序
+    ///
+    /// 返回 `None` 表示不保存历史版本
+    fn root_history(&self) -> Option<Vec<(u64, RootHash)>> {
+        None
+    }
 
     /// 从 ADS 中删除 (keyword, fid) 对
     /// 返回: (proof, root_hash)
-    fn delete(&mut self, keyword: &str, fid: &str) -> (Vec<u8>, RootHash);
+    ///
+    /// 无法证明 fid 不存在的实现对不存在的 (keyword, fid) 返回
+    /// [`AdsError::MissingKeyword`] 或 [`AdsError::MissingFid`]
+    fn delete(&mut self, keyword: &str, fid: &str) -> AdsResult;
+
+    /// 从多个 keyword 下删除同一个 fid
+    /// 返回: (proof, root_hash)；`keywords` 为空时返回 [`AdsError::EmptyBatch`]
+    ///
+    /// 默认实现逐个调用 [`delete`](Self::delete) 并返回最后一次的结果，遇到错误时停止
+    fn delete_batch(&mut self, keywords: &[String], fid: &str) -> AdsResult {
+        let mut result = Err(AdsError::EmptyBatch);
+        for keyword in keywords {
+            result = Ok(self.delete(keyword, fid)?);
+        }
+        result
+    }
+
+    /// 把 keyword 下的 `old_fid` 替换为 `new_fid`
+    /// 返回: (proof, root_hash)
+    ///
+    /// 结果必须与先删除 `old_fid` 再添加 `new_fid` 相同（WAL 重放时就是这样执行的）。
+    /// 默认实现依次调用 [`delete`](Self::delete) 和 [`add`](Self::add)，返回添加的结果；
+    /// 能一次完成替换的实现应覆盖此方法
+    fn update(&mut self, keyword: &str, old_fid: &str, new_fid: &str) -> AdsResult {
+        self.delete(keyword, old_fid)?;
+        self.add(keyword, new_fid)
+    }
+
+    /// 当前的全局根哈希，不需要执行写操作
+    ///
+    /// 返回 `None` 表示该 ADS 没有覆盖所有 keyword 的根哈希
+    fn root_hash(&self) -> Option<RootHash> {
+        None
+    }
+
+    /// 执行写操作，把证明的生成留给返回的任务
+    /// 返回: (proof_job, root_hash)
+    ///
+    /// 任务不再访问 ADS，之后的写入不影响它生成的证明。
+    /// 默认实现立即生成证明；证明开销大的实现应覆盖此方法，只在这里完成状态更新
+    fn apply_deferred(&mut self, mutation: Mutation) -> Result<(ProofJob, RootHash), AdsError> {
+        let (proof, root_hash) = match mutation {
+            Mutation::Add { keyword, fid } => self.add(keyword, fid)?,
+            Mutation::AddBatch { keywords, fid } => self.add_batch(keywords, fid)?,
+            Mutation::Delete { keyword, fid } => self.delete(keyword, fid)?,
+        };
+        Ok((Box::new(move || proof), root_hash))
+    }
+
+    /// 是否有待执行的后台维护工作（如 MPT 的脏节点修复）
+    fn needs_maintenance(&self) -> bool {
+        false
+    }
+
+    /// 在时间预算内推进一次后台维护
+    /// 返回: 是否仍有剩余工作
+    fn maintenance_slice(&mut self, _budget: Duration) -> bool {
+        false
+    }
+
+    /// 强制完成所有待执行的维护工作（发布根哈希之前调用）
+    fn finish_maintenance(&mut self) {}
+
+    /// 导出全部状态，用于进程交接（见 [`state`]）
+    ///
+    /// 返回 `None` 表示不支持导出，此时 storager 会拒绝交接
+    fn export_state(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// 从 [`export_state`](Self::export_state) 的输出恢复状态
+    ///
+    /// 只在空的 ADS 上调用；恢复后的根哈希和查询结果必须与导出时一致
+    fn import_state(&mut self, _state: &[u8]) -> Result<(), String> {
+        Err("state import is not supported".to_string())
+    }
+
+    /// 列出 ADS 中的所有 keyword（关键词迁移时使用）
+    ///
+    /// 返回 `None` 表示不支持枚举，此时该 storager 不能作为迁移的源节点
+    fn keywords(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    /// 墓碑删除的统计
+    ///
+    /// 返回 `None` 表示删除立即从结构中移除，没有墓碑
+    fn tombstone_stats(&self) -> Option<TombstoneStats> {
+        None
+    }
+
+    /// 删除从当前根和 `keep_roots` 都不可达的历史数据
+    ///
+    /// 默认实现不做任何事：只在内存中保存当前版本的 ADS 没有可回收的数据
+    fn prune(&mut self, _keep_roots: &[RootHash]) -> Result<PruneStats, String> {
+        Ok(PruneStats::default())
+    }
+
+    /// 把尚未落盘的写入持久化（管理接口的 `Flush` 调用）
+    ///
+    /// 默认实现不做任何事：只在内存中保存的 ADS 没有需要落盘的数据
+    fn flush(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// 把当前状态写入检查点数据库（见 [`persistent`]），覆盖之前的检查点
+    ///
+    /// 默认实现把 [`export_state`](Self::export_state) 的输出保存在一个键下；
+    /// 状态可以增量保存的实现应覆盖此方法
+    fn save_state(&mut self, db: &mut dyn Database) -> Result<(), String> {
+        let state = self
+            .export_state()
+            .ok_or("the configured ADS does not support persistence")?;
+        db.put(EXPORTED_STATE_KEY, &state)
+            .map_err(|e| e.to_string())
+    }
+
+    /// 从 [`save_state`](Self::save_state) 写入的检查点恢复状态，没有检查点时保持为空
+    ///
+    /// 只在空的 ADS 上调用
+    fn load_state(&mut self, db: &mut dyn Database) -> Result<(), String> {
+        match db.get(EXPORTED_STATE_KEY).map_err(|e| e.to_string())? {
+            Some(state) => self.import_state(&state),
+            None => Ok(()),
+        }
+    }
 }
 
 // ADS 实现模块
 pub mod crypto_accumulator;
+pub mod merkle_tree;
 pub mod mpt;
+pub mod persistent;
+pub mod pool;
+pub mod registry;
+pub mod smt;
+pub mod state;
 
 // 导出 ADS 实现
 pub use crypto_accumulator::CryptoAccumulatorAds;
-pub use mpt::MptAds;
+pub use merkle_tree::MerkleTreeAds;
+pub use mpt::{CompactionPolicy, MptAds};
+pub use persistent::PersistentAds;
+pub use pool::AdsPool;
+pub use smt::SmtAds;
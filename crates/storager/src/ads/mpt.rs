@@ -2,67 +2,222 @@
 //!
 //! 使用以太坊风格的 Merkle Patricia Trie 作为认证数据结构
 //! 支持高效的键值存储和成员资格证明
+//!
+//! ## 墓碑删除
+//!
+//! keyword 的最后一个 fid 被删除时不立即从 MPT 中删除该 keyword（删除需要合并节点、
+//! 重建路径），而是把它的值改写为空（墓碑）：只更新叶子的值，树的结构不变。
+//! 空值的查询证明与不存在证明一样表明 keyword 没有 fid，范围和前缀查询跳过墓碑。
+//!
+//! 墓碑占 MPT 中 keyword 的比例超过 [`CompactionPolicy`] 的阈值时，
+//! 触发阈值的删除在返回之前执行一次压缩，从 MPT 中物理删除所有墓碑。
+//! 压缩只在写操作中进行，它改变的根哈希随这次写操作的证明一起返回给 Manager
+//!
+//! ## 节点编码
+//!
+//! 节点以二进制格式保存（见 [`esa_rust::mpt::codec`]），哈希算法默认为 SHA-256，
+//! 可以用 [`MptAds::with_hash_algorithm`] 选择 Keccak-256。检查点记录节点编码版本，
+//! 加载旧版本（JSON 编码）的检查点时把可达的节点迁移为二进制编码，根哈希不变
 
-use super::AdsOperations;
-use common::RootHash;
-use esa_rust::mpt::{node::Database, KVPair, MPTError, MPT};
-use std::collections::HashMap;
+use super::state::{decode_postings, encode_postings};
+use super::{AdsOperations, AdsResult, PrefixEntries, PruneStats, RangeEntries, TombstoneStats};
+use common::{AdsError, Proof, RootHash};
+use esa_rust::mpt::db::MemoryDatabase;
+use esa_rust::mpt::mpt::NODE_FORMAT_VERSION;
+use esa_rust::mpt::{node::Database, HashAlgorithm, KVPair, SlicedFix, ValueProof, MPT};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::RwLock;
+use std::time::Duration;
+use storage_backend::SharedDatabase;
+use tracing::{debug, error, info, warn};
 
-/// 简单的内存数据库实现
-struct MemoryDb {
-    data: HashMap<Vec<u8>, Vec<u8>>,
-}
+/// 检查点中保存根哈希的键
+const ROOT_HASH_KEY: &[u8] = b"mpt:root_hash";
 
-impl MemoryDb {
-    fn new() -> Self {
-        Self {
-            data: HashMap::new(),
-        }
-    }
-}
-
-impl Database for MemoryDb {
-    fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, MPTError> {
-        Ok(self.data.get(key).cloned())
-    }
+/// 检查点中保存节点编码版本的键（大端 u32），没有该键的检查点为 JSON 编码
+const FORMAT_KEY: &[u8] = b"mpt:format";
 
-    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<(), MPTError> {
-        self.data.insert(key.to_vec(), value.to_vec());
-        Ok(())
-    }
+/// 墓碑压缩的触发条件
+///
+/// 墓碑数量不少于 `min_tombstones`，并且占 MPT 中 keyword（含墓碑）的比例超过
+/// `max_ratio` 时压缩
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompactionPolicy {
+    pub min_tombstones: usize,
+    pub max_ratio: f64,
+}
 
-    fn delete(&mut self, key: &[u8]) -> Result<(), MPTError> {
-        self.data.remove(key);
-        Ok(())
+impl Default for CompactionPolicy {
+    fn default() -> Self {
+        CompactionPolicy {
+            min_tombstones: 64,
+            max_ratio: 0.25,
+        }
     }
 }
 
 /// MPT ADS 实现
+///
+/// 所有 keyword 共用一棵 MPT（key 为 keyword，value 为编码后的 fid 列表），
+/// 因此 storager 只有一个根哈希，每个 keyword 的证明都能对照它验证
 pub struct MptAds {
-    /// 存储每个 keyword 对应的 MPT 实例、数据库和文件列表
-    /// HashMap<keyword, (mpt, db, fid_list)>
-    tries: HashMap<String, (MPT, MemoryDb, Vec<String>)>,
+    /// 查询只需要 MPT 的共享引用，并发的查询各自持有一个节点数据库句柄
+    trie: RwLock<MPT>,
+    /// MPT 节点数据库
+    db: SharedDatabase,
+    /// 每个 keyword 对应的 fid 列表
+    postings: HashMap<String, Vec<String>>,
+    /// 值为空、尚未从 MPT 中删除的 keyword
+    tombstones: BTreeSet<String>,
+    /// 墓碑压缩的触发条件
+    compaction: CompactionPolicy,
+    /// 已执行的压缩次数和物理删除的墓碑总数
+    compactions: u64,
+    compacted: u64,
+    /// 正在进行中的分片修复
+    pending_fix: Option<SlicedFix>,
 }
 
 impl MptAds {
     pub fn new() -> Self {
+        Self::with_db(Box::new(MemoryDatabase::new()))
+    }
+
+    /// 把 MPT 节点保存在指定的数据库中（如 RocksDB 的节点列族）
+    ///
+    /// 节点在写入和修复时直接落盘，检查点只需记录根哈希，
+    /// 见 [`save_state`](AdsOperations::save_state)
+    pub fn with_db(db: Box<dyn Database + Send>) -> Self {
         MptAds {
-            tries: HashMap::new(),
+            trie: RwLock::new(MPT::new(None)),
+            db: SharedDatabase::new(db),
+            postings: HashMap::new(),
+            tombstones: BTreeSet::new(),
+            compaction: CompactionPolicy::default(),
+            compactions: 0,
+            compacted: 0,
+            pending_fix: None,
         }
     }
 
+    /// 设置新建节点的哈希算法
+    ///
+    /// 只影响空树；从检查点加载的树沿用节点中记录的算法
+    pub fn with_hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        self.trie.get_mut().unwrap().hash_algorithm = hash_algorithm;
+        self
+    }
+
+    /// 设置墓碑压缩的触发条件
+    pub fn with_compaction(mut self, policy: CompactionPolicy) -> Self {
+        self.compaction = policy;
+        self
+    }
+
     /// 将 fid 列表编码为字符串
     fn encode_fids(fids: &[String]) -> String {
         fids.join(",")
     }
 
-    /// 从字符串解码 fid 列表
-    fn decode_fids(data: &str) -> Vec<String> {
-        if data.is_empty() {
-            Vec::new()
-        } else {
-            data.split(',').map(|s| s.to_string()).collect()
+    /// [`encode_fids`](Self::encode_fids) 的逆操作
+    fn decode_fids(value: &str) -> Vec<String> {
+        if value.is_empty() {
+            return Vec::new();
         }
+        value.split(',').map(str::to_string).collect()
+    }
+
+    /// 将 keyword 当前的 fid 列表写入 MPT，列表为空时写入墓碑（空值）
+    fn write_keyword(&mut self, keyword: &str) -> Result<(), AdsError> {
+        let value = match self.postings.get(keyword) {
+            Some(fids) => {
+                self.tombstones.remove(keyword);
+                Self::encode_fids(fids)
+            }
+            None => {
+                self.tombstones.insert(keyword.to_string());
+                String::new()
+            }
+        };
+        let trie = self.trie.get_mut().map_err(|_| AdsError::LockPoisoned)?;
+        trie.insert(
+            KVPair::new(keyword.to_string(), value),
+            &mut self.db,
+            true,
+            false,
+        )
+        .map(|_| ())
+        .map_err(|e| AdsError::Backend(format!("MPT write of '{}' failed: {}", keyword, e)))
+    }
+
+    /// 从 keyword 下删除 fid，返回 keyword 是否因此变为墓碑
+    fn remove_fid(&mut self, keyword: &str, fid: &str) -> Result<bool, AdsError> {
+        let Some(fids) = self.postings.get_mut(keyword) else {
+            return Ok(false);
+        };
+        fids.retain(|f| f != fid);
+        let emptied = fids.is_empty();
+        if emptied {
+            self.postings.remove(keyword);
+        }
+        self.write_keyword(keyword)?;
+        Ok(emptied)
+    }
+
+    /// 墓碑是否超过了压缩阈值
+    fn should_compact(&self) -> bool {
+        let tombstones = self.tombstones.len();
+        let total = tombstones + self.postings.len();
+        tombstones >= self.compaction.min_tombstones.max(1)
+            && tombstones as f64 > self.compaction.max_ratio * total as f64
+    }
+
+    /// 从 MPT 中物理删除所有墓碑，返回删除的数量
+    ///
+    /// 压缩改变根哈希，只能在写操作中（生成证明之前）调用
+    pub fn compact(&mut self) -> Result<usize, AdsError> {
+        if self.tombstones.is_empty() {
+            return Ok(0);
+        }
+        let trie = self.trie.get_mut().map_err(|_| AdsError::LockPoisoned)?;
+        for keyword in &self.tombstones {
+            trie.delete(keyword, &mut self.db).map_err(|e| {
+                AdsError::Backend(format!("MPT compaction of '{}' failed: {}", keyword, e))
+            })?;
+        }
+        let count = self.tombstones.len();
+        self.tombstones.clear();
+        self.compactions += 1;
+        self.compacted += count as u64;
+        debug!("Compacted {} MPT tombstone(s)", count);
+        Ok(count)
+    }
+
+    /// 墓碑超过阈值时压缩
+    fn compact_if_needed(&mut self) -> Result<(), AdsError> {
+        if self.should_compact() {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// 生成 keyword 的证明（不存在时为不存在证明）和当前根哈希
+    fn prove(&self, keyword: &str) -> AdsResult {
+        let trie = self.trie.read().map_err(|_| AdsError::LockPoisoned)?;
+        let (value, proof) = trie
+            .query_by_key(keyword, &mut self.db.clone())
+            .map_err(|e| AdsError::Backend(format!("MPT query of '{}' failed: {}", keyword, e)))?;
+        let proof = Proof::Mpt(ValueProof::new(value, proof).to_bytes());
+        Ok((proof, trie.root_hash.to_vec()))
+    }
+
+    /// 取出进行中的修复，没有时检查是否需要开始新的修复
+    fn next_fix(&mut self) -> Option<SlicedFix> {
+        if let Some(fix) = self.pending_fix.take() {
+            return Some(fix);
+        }
+        let trie = self.trie.get_mut().unwrap();
+        trie.needs_fix().then(|| trie.begin_sliced_fix())
     }
 }
 
@@ -73,77 +228,426 @@ impl Default for MptAds {
 }
 
 impl AdsOperations for MptAds {
-    fn add(&mut self, keyword: &str, fid: &str) -> (Vec<u8>, RootHash) {
-        let entry = self
-            .tries
-            .entry(keyword.to_string())
-            .or_insert_with(|| (MPT::new(None), MemoryDb::new(), Vec::new()));
+    fn add(&mut self, keyword: &str, fid: &str) -> AdsResult {
+        let fids = self.postings.entry(keyword.to_string()).or_default();
+        if !fids.contains(&fid.to_string()) {
+            fids.push(fid.to_string());
+        }
+        self.write_keyword(keyword)?;
+        self.prove(keyword)
+    }
+
+    fn query(&self, keyword: &str) -> (Vec<String>, Proof) {
+        let fids = self.postings.get(keyword).cloned().unwrap_or_default();
+        let proof = match self.prove(keyword) {
+            Ok((proof, _)) => proof,
+            Err(e) => {
+                error!("{}", e);
+                Proof::Mpt(vec![])
+            }
+        };
+        (fids, proof)
+    }
 
-        // 添加 fid 到列表
-        if !entry.2.contains(&fid.to_string()) {
-            entry.2.push(fid.to_string());
+    /// 所有 keyword 在同一棵 MPT 中按键排序，范围证明覆盖整段连续的键
+    fn range_query(&self, start: &str, end: &str) -> Option<(RangeEntries, Vec<u8>)> {
+        let trie = self.trie.read().unwrap();
+        let (pairs, proof) = match trie.range_query(start, end, &mut self.db.clone()) {
+            Ok(result) => result,
+            Err(e) => {
+                error!("MPT range query failed for [{}, {}): {}", start, end, e);
+                return None;
+            }
+        };
+        let entries = pairs
+            .iter()
+            .filter(|kv| !kv.get_value().is_empty())
+            .map(|kv| (kv.get_key().to_string(), Self::decode_fids(kv.get_value())))
+            .collect();
+        Some((entries, proof.to_bytes()))
+    }
+
+    /// 证明即从根到前缀节点的路径加上前缀下的整棵子树，子树中的值就是完整的 fid 列表
+    fn prefix_query(&self, prefix: &str) -> Option<(PrefixEntries, Vec<u8>)> {
+        let trie = self.trie.read().unwrap();
+        let (pairs, proof) = match trie.prefix_query(prefix, &mut self.db.clone()) {
+            Ok(result) => result,
+            Err(e) => {
+                error!("MPT prefix query failed for '{}': {}", prefix, e);
+                return None;
+            }
+        };
+        let keywords = pairs
+            .iter()
+            .filter(|kv| !kv.get_value().is_empty())
+            .map(|kv| {
+                let count = Self::decode_fids(kv.get_value()).len() as u64;
+                (kv.get_key().to_string(), count)
+            })
+            .collect();
+        Some((keywords, proof.to_bytes()))
+    }
+
+    /// 旧根下的节点仍在节点数据库中（未被回收）时，可以重新加载该版本并生成证明
+    fn query_at_root(
+        &self,
+        keyword: &str,
+        root_hash: &[u8],
+    ) -> Result<(Vec<String>, Proof), String> {
+        let root_hash: [u8; 32] = root_hash
+            .try_into()
+            .map_err(|_| format!("invalid MPT root hash length: {}", root_hash.len()))?;
+        let trie = self.trie.read().unwrap();
+        let (value, proof) = trie
+            .query_at_root(&root_hash, keyword, &mut self.db.clone())
+            .map_err(|e| format!("cannot query root {:x?}: {}", &root_hash[..8], e))?;
+        let proof = Proof::Mpt(ValueProof::new(value.clone(), proof).to_bytes());
+        Ok((Self::decode_fids(&value), proof))
+    }
+
+    /// 每次修复完成时记录的根；修复之间发布的中间根不在其中
+    fn root_history(&self) -> Option<Vec<(u64, RootHash)>> {
+        match MPT::root_history(&mut self.db.clone()) {
+            Ok(history) => Some(
+                history
+                    .into_iter()
+                    .map(|(epoch, root)| (epoch, root.to_vec()))
+                    .collect(),
+            ),
+            Err(e) => {
+                error!("Failed to read MPT root history: {}", e);
+                None
+            }
         }
+    }
 
-        // 更新 MPT
-        let value = Self::encode_fids(&entry.2);
-        let kv = KVPair::new(keyword.to_string(), value);
+    /// 不存在的 (keyword, fid) 不改变 MPT，返回的证明表明 fid 不在 keyword 下
+    ///
+    /// 删除最后一个 fid 时写入墓碑，墓碑超过阈值时在生成证明之前压缩
+    fn delete(&mut self, keyword: &str, fid: &str) -> AdsResult {
+        if self.remove_fid(keyword, fid)? {
+            self.compact_if_needed()?;
+        }
+        self.prove(keyword)
+    }
 
-        let _ = entry.0.insert(kv, &mut entry.1, true, false);
+    /// 只为最后一个 keyword 生成证明，它对照所有删除完成后的根验证
+    fn delete_batch(&mut self, keywords: &[String], fid: &str) -> AdsResult {
+        let last = keywords.last().ok_or(AdsError::EmptyBatch)?;
+        for keyword in keywords {
+            if self.remove_fid(keyword, fid)? {
+                self.compact_if_needed()?;
+            }
+        }
+        self.prove(last)
+    }
 
-        // 获取根哈希
-        let root_hash = entry.0.root_hash.to_vec();
+    /// 只写入一次 MPT，keyword 不会在替换过程中被删除再重新插入
+    ///
+    /// 先删除再添加时，删除 keyword 唯一的 fid 会产生一个墓碑并可能触发压缩；
+    /// 这里按同样的条件压缩，两种执行方式得到相同的状态
+    fn update(&mut self, keyword: &str, old_fid: &str, new_fid: &str) -> AdsResult {
+        let fids = self.postings.entry(keyword.to_string()).or_default();
+        let emptied = fids.len() == 1 && fids[0] == old_fid;
+        fids.retain(|f| f != old_fid);
+        if !fids.iter().any(|f| f == new_fid) {
+            fids.push(new_fid.to_string());
+        }
+        if emptied {
+            self.tombstones.insert(keyword.to_string());
+            if self.should_compact() {
+                self.compact()?;
+            }
+        }
+        self.write_keyword(keyword)?;
+        self.prove(keyword)
+    }
 
-        // 生成简单的证明（包含根哈希）
-        let proof = root_hash.clone();
+    fn root_hash(&self) -> Option<RootHash> {
+        Some(self.trie.read().unwrap().root_hash.to_vec())
+    }
 
-        (proof, root_hash)
+    fn needs_maintenance(&self) -> bool {
+        self.pending_fix.is_some() || self.trie.read().unwrap().needs_fix()
     }
 
-    fn query(&self, keyword: &str) -> (Vec<String>, Vec<u8>) {
-        if let Some((trie, _db, fids)) = self.tries.get(keyword) {
-            // 生成成员资格证明（使用根哈希作为简化的证明）
-            let proof = trie.root_hash.to_vec();
+    fn maintenance_slice(&mut self, budget: Duration) -> bool {
+        let Some(mut fix) = self.next_fix() else {
+            return false;
+        };
+        let trie = self.trie.get_mut().unwrap();
+        match trie.fix_slice(&mut fix, &mut self.db, budget) {
+            Ok(true) => self.needs_maintenance(),
+            Ok(false) => {
+                self.pending_fix = Some(fix);
+                true
+            }
+            Err(e) => {
+                error!("Sliced fix failed: {}", e);
+                false
+            }
+        }
+    }
 
-            (fids.clone(), proof)
-        } else {
-            // 关键字不存在，返回空列表
-            (vec![], vec![])
+    fn finish_maintenance(&mut self) {
+        if let Some(fix) = self.next_fix() {
+            let trie = self.trie.get_mut().unwrap();
+            if let Err(e) = trie.finish_sliced_fix(fix, &mut self.db) {
+                error!("Sliced fix failed: {}", e);
+            }
         }
     }
 
-    fn delete(&mut self, keyword: &str, fid: &str) -> (Vec<u8>, RootHash) {
-        if let Some((trie, db, fids)) = self.tries.get_mut(keyword) {
-            // 从列表中移除 fid
-            fids.retain(|f| f != fid);
+    /// MPT 只保存每个 keyword 完整的 fid 列表，导出列表即可重建；墓碑导出为空列表
+    fn export_state(&self) -> Option<Vec<u8>> {
+        let empty = Vec::new();
+        let tombstones = self.tombstones.iter().map(|keyword| (keyword, &empty));
+        Some(encode_postings(self.postings.iter().chain(tombstones)))
+    }
 
+    fn import_state(&mut self, state: &[u8]) -> Result<(), String> {
+        for (keyword, fids) in decode_postings(state)? {
+            let trie = self.trie.get_mut().unwrap();
+            let kv = KVPair::new(keyword.clone(), Self::encode_fids(&fids));
+            trie.insert(kv, &mut self.db, true, false)
+                .map_err(|e| format!("failed to restore '{}': {}", keyword, e))?;
             if fids.is_empty() {
-                // 如果列表为空，从 MPT 中删除整个键
-                let _ = trie.delete(keyword, db);
+                self.tombstones.insert(keyword);
+            } else {
+                self.postings.insert(keyword, fids);
+            }
+        }
+        self.finish_maintenance();
+        Ok(())
+    }
 
-                let root_hash = trie.root_hash.to_vec();
+    fn keywords(&self) -> Option<Vec<String>> {
+        Some(self.postings.keys().cloned().collect())
+    }
 
-                // 如果 trie 为空，移除整个条目
-                if trie.root_hash == [0; 32] {
-                    self.tries.remove(keyword);
-                    return (vec![], vec![]);
-                }
+    fn tombstone_stats(&self) -> Option<TombstoneStats> {
+        Some(TombstoneStats {
+            tombstones: self.tombstones.len() as u64,
+            live: self.postings.len() as u64,
+            compactions: self.compactions,
+            compacted: self.compacted,
+        })
+    }
 
-                (vec![], root_hash)
-            } else {
-                // 更新 MPT
-                let value = Self::encode_fids(fids);
-                let kv = KVPair::new(keyword.to_string(), value);
+    /// 标记-清除节点数据库中不再可达的旧版本节点
+    fn prune(&mut self, keep_roots: &[RootHash]) -> Result<PruneStats, String> {
+        let keep_roots = keep_roots
+            .iter()
+            .map(|root| {
+                root.as_slice()
+                    .try_into()
+                    .map_err(|_| format!("invalid MPT root hash length: {}", root.len()))
+            })
+            .collect::<Result<Vec<[u8; 32]>, _>>()?;
+        self.finish_maintenance();
+        let trie = self.trie.get_mut().unwrap();
+        trie.prune(&mut self.db, &keep_roots)
+            .map_err(|e| e.to_string())
+    }
 
-                let _ = trie.insert(kv, db, true, false);
+    /// 节点已经在节点数据库中，检查点只记录修复后的根哈希
+    fn save_state(&mut self, db: &mut dyn Database) -> Result<(), String> {
+        self.finish_maintenance();
+        let trie = self.trie.get_mut().unwrap();
+        trie.batch_fix(&mut self.db).map_err(|e| e.to_string())?;
+        db.put(FORMAT_KEY, &NODE_FORMAT_VERSION.to_be_bytes())
+            .map_err(|e| e.to_string())?;
+        db.put(ROOT_HASH_KEY, &trie.root_hash)
+            .map_err(|e| e.to_string())
+    }
 
-                let root_hash = trie.root_hash.to_vec();
-                let proof = root_hash.clone();
+    /// 从节点数据库加载检查点记录的根，fid 列表由一次全范围遍历重建
+    ///
+    /// 检查点的节点编码版本低于当前版本时先迁移节点编码
+    fn load_state(&mut self, db: &mut dyn Database) -> Result<(), String> {
+        let Some(root_hash) = db.get(ROOT_HASH_KEY).map_err(|e| e.to_string())? else {
+            return Ok(());
+        };
+        let root_hash: [u8; 32] = root_hash
+            .try_into()
+            .map_err(|_| "invalid MPT root hash in checkpoint".to_string())?;
+        let format = match db.get(FORMAT_KEY).map_err(|e| e.to_string())? {
+            Some(data) => u32::from_be_bytes(
+                data.try_into()
+                    .map_err(|_| "invalid MPT format version in checkpoint".to_string())?,
+            ),
+            None => 1,
+        };
 
-                (proof, root_hash)
-            }
-        } else {
-            // 关键字不存在
-            (vec![], vec![])
+        let trie = self.trie.get_mut().unwrap();
+        let configured = trie.hash_algorithm;
+        *trie = MPT::load_from_db(&root_hash, &mut self.db, None).map_err(|e| e.to_string())?;
+        if root_hash == [0u8; 32] {
+            trie.hash_algorithm = configured;
+        } else if trie.hash_algorithm != configured {
+            warn!(
+                "MPT checkpoint uses {} node hashes, ignoring configured {}",
+                trie.hash_algorithm, configured
+            );
+        }
+        if format < NODE_FORMAT_VERSION {
+            let migrated = trie
+                .migrate_encoding(&mut self.db)
+                .map_err(|e| format!("failed to migrate MPT node encoding: {}", e))?;
+            db.put(FORMAT_KEY, &NODE_FORMAT_VERSION.to_be_bytes())
+                .map_err(|e| e.to_string())?;
+            info!(
+                "Migrated {} MPT nodes from format {} to {}",
+                migrated, format, NODE_FORMAT_VERSION
+            );
+        }
+        let (pairs, _) = trie
+            .range_query("", "", &mut self.db)
+            .map_err(|e| format!("failed to read MPT checkpoint: {}", e))?;
+        let (tombstones, pairs): (Vec<_>, Vec<_>) =
+            pairs.iter().partition(|kv| kv.get_value().is_empty());
+        self.tombstones = tombstones
+            .iter()
+            .map(|kv| kv.get_key().to_string())
+            .collect();
+        self.postings = pairs
+            .iter()
+            .map(|kv| (kv.get_key().to_string(), Self::decode_fids(kv.get_value())))
+            .collect();
+        self.pending_fix = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(min_tombstones: usize, max_ratio: f64) -> CompactionPolicy {
+        CompactionPolicy {
+            min_tombstones,
+            max_ratio,
+        }
+    }
+
+    #[test]
+    fn test_delete_leaves_tombstone() {
+        let mut ads = MptAds::new();
+        ads.add("rust", "f1").unwrap();
+        ads.add("go", "f2").unwrap();
+        ads.delete("rust", "f1").unwrap();
+
+        // 墓碑的查询证明是空值，与不存在证明一样绑定到 keyword
+        let (fids, proof) = ads.query("rust");
+        assert!(fids.is_empty());
+        let Proof::Mpt(data) = proof else {
+            panic!("expected an MPT proof");
+        };
+        let proof = ValueProof::from_bytes(&data).unwrap();
+        assert!(proof.value.is_empty());
+        assert!(proof.binds_key("rust"));
+
+        let (entries, _) = ads.range_query("", "").unwrap();
+        assert_eq!(entries, vec![("go".to_string(), vec!["f2".to_string()])]);
+        assert_eq!(ads.keywords().unwrap(), vec!["go"]);
+        let stats = ads.tombstone_stats().unwrap();
+        assert_eq!((stats.tombstones, stats.live), (1, 1));
+        assert_eq!(stats.ratio(), 0.5);
+
+        // 重新添加覆盖墓碑
+        ads.add("rust", "f3").unwrap();
+        assert_eq!(ads.tombstone_stats().unwrap().tombstones, 0);
+        assert_eq!(ads.query("rust").0, vec!["f3"]);
+    }
+
+    #[test]
+    fn test_compaction_threshold() {
+        let mut ads = MptAds::new().with_compaction(policy(2, 0.5));
+        for keyword in ["a", "b", "c"] {
+            ads.add(keyword, "f1").unwrap();
         }
+        // 一个墓碑少于 min_tombstones
+        ads.delete("a", "f1").unwrap();
+        assert_eq!(ads.tombstone_stats().unwrap().compactions, 0);
+
+        // 2/3 超过 max_ratio，删除返回之前压缩，证明对照压缩后的根
+        let (_, root_hash) = ads.delete("b", "f1").unwrap();
+        let stats = ads.tombstone_stats().unwrap();
+        assert_eq!(stats.tombstones, 0);
+        assert_eq!((stats.compactions, stats.compacted), (1, 2));
+        assert_eq!(ads.root_hash(), Some(root_hash));
+        // 墓碑已经从 MPT 中删除
+        let (pairs, _) = ads
+            .trie
+            .read()
+            .unwrap()
+            .range_query("", "", &mut ads.db.clone())
+            .unwrap();
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(ads.query("c").0, vec!["f1"]);
+    }
+
+    #[test]
+    fn test_update_matches_delete_then_add() {
+        let build = || {
+            let mut ads = MptAds::new().with_compaction(policy(2, 0.0));
+            ads.add("a", "f1").unwrap();
+            ads.add("b", "f1").unwrap();
+            ads.add("c", "f1").unwrap();
+            ads.delete("b", "f1").unwrap();
+            ads
+        };
+        let mut updated = build();
+        updated.update("a", "f1", "f2").unwrap();
+        let mut replayed = build();
+        replayed.delete("a", "f1").unwrap();
+        replayed.add("a", "f2").unwrap();
+
+        assert_eq!(updated.root_hash(), replayed.root_hash());
+        assert_eq!(
+            updated.tombstone_stats().unwrap(),
+            replayed.tombstone_stats().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_tombstones_survive_export() {
+        let mut ads = MptAds::new();
+        ads.add("rust", "f1").unwrap();
+        ads.add("go", "f2").unwrap();
+        ads.delete("rust", "f1").unwrap();
+
+        let mut restored = MptAds::new();
+        restored.import_state(&ads.export_state().unwrap()).unwrap();
+        assert_eq!(restored.root_hash(), ads.root_hash());
+        assert_eq!(restored.tombstone_stats().unwrap().tombstones, 1);
+    }
+
+    #[test]
+    fn test_checkpoint_keeps_hash_algorithm() {
+        let nodes = SharedDatabase::new(Box::new(MemoryDatabase::new()));
+        let mut ads =
+            MptAds::with_db(Box::new(nodes.clone())).with_hash_algorithm(HashAlgorithm::Keccak256);
+        ads.add("rust", "f1").unwrap();
+        ads.add("go", "f2").unwrap();
+        let mut checkpoint = MemoryDatabase::new();
+        ads.save_state(&mut checkpoint).unwrap();
+
+        // 加载的树沿用节点中记录的算法，与恢复方的配置无关
+        let mut restored = MptAds::with_db(Box::new(nodes));
+        restored.load_state(&mut checkpoint).unwrap();
+        assert_eq!(restored.root_hash(), ads.root_hash());
+        ads.add("java", "f3").unwrap();
+        restored.add("java", "f3").unwrap();
+        assert_eq!(restored.root_hash(), ads.root_hash());
+
+        let (fids, proof) = restored.query("rust");
+        assert_eq!(fids, vec!["f1"]);
+        let Proof::Mpt(data) = proof else {
+            panic!("expected an MPT proof");
+        };
+        let proof = ValueProof::from_bytes(&data).unwrap();
+        assert_eq!(proof.proof.hash, HashAlgorithm::Keccak256);
+        assert_eq!(Some(proof.compute_root().to_vec()), restored.root_hash());
     }
 }
@@ -0,0 +1,495 @@
+//! 持久化的 ADS
+//!
+//! [`PersistentAds`] 包装任意 ADS，状态保存在 [`ColumnStore`]（RocksDB 或 sled）的三个列族中：
+//! - **nodes**: MPT 节点，写入和修复时直接落盘
+//! - **metadata**: 最近一次检查点（[`AdsOperations::save_state`]）和 ADS 模式
+//! - **wal**: 检查点之后的写操作，键为大端序号，按写入顺序排列
+//!
+//! 每个写操作先追加到 WAL 再执行，每 [`DEFAULT_CHECKPOINT_INTERVAL`] 次写入保存一次检查点并清空 WAL。
+//! 重新打开时先加载检查点，再按顺序重放 WAL。添加和删除都是幂等的集合操作，
+//! 因此保存检查点后、清空 WAL 前崩溃时重放已包含在检查点中的记录也不会改变状态。
+
+use super::registry::create_ads;
+use super::state::{put_bytes, put_u32, StateReader};
+use super::{
+    AdsOperations, AdsResult, MptAds, Mutation, PrefixEntries, ProofJob, PruneStats, RangeEntries,
+    TombstoneStats,
+};
+use common::rpc::BooleanProof;
+use common::{AdsError, AdsMode, BooleanExpr, Proof, RootHash};
+use esa_rust::mpt::node::Database;
+use std::time::Duration;
+use storage_backend::{Column, ColumnDb, ColumnStore};
+use tracing::{error, warn};
+
+/// 默认的检查点间隔（写操作数）
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 1024;
+
+/// 元数据列族中记录 ADS 模式的键
+const MODE_KEY: &[u8] = b"storager:ads_mode";
+
+/// WAL 记录的操作类型
+const OP_ADD: u32 = 0;
+const OP_DELETE: u32 = 1;
+
+/// 一条 WAL 记录：fid 被添加到 keywords 下，或从 keywords 下删除
+#[derive(Debug, PartialEq)]
+struct WalRecord {
+    op: u32,
+    keywords: Vec<String>,
+    fid: String,
+}
+
+impl WalRecord {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        put_u32(&mut buf, self.op);
+        put_bytes(&mut buf, self.fid.as_bytes());
+        put_u32(&mut buf, self.keywords.len() as u32);
+        for keyword in &self.keywords {
+            put_bytes(&mut buf, keyword.as_bytes());
+        }
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> Result<Self, String> {
+        let mut reader = StateReader::new(buf);
+        let op = reader.u32()?;
+        let fid = reader.string()?;
+        let keywords: Vec<String> = (0..reader.u32()?)
+            .map(|_| reader.string())
+            .collect::<Result<_, _>>()?;
+        reader.finish()?;
+        let record = WalRecord { op, keywords, fid };
+        if !matches!(op, OP_ADD | OP_DELETE) || record.keywords.is_empty() {
+            return Err(format!("invalid WAL record {:?}", record));
+        }
+        Ok(record)
+    }
+
+    /// 在 ADS 上执行这条记录
+    fn apply(&self, ads: &mut dyn AdsOperations) -> AdsResult {
+        match (self.op, self.keywords.as_slice()) {
+            (OP_ADD, [keyword]) => ads.add(keyword, &self.fid),
+            (OP_ADD, keywords) => ads.add_batch(keywords, &self.fid),
+            (_, [keyword]) => ads.delete(keyword, &self.fid),
+            (_, keywords) => ads.delete_batch(keywords, &self.fid),
+        }
+    }
+}
+
+impl From<Mutation<'_>> for WalRecord {
+    fn from(mutation: Mutation) -> Self {
+        let (op, keywords, fid) = match mutation {
+            Mutation::Add { keyword, fid } => (OP_ADD, vec![keyword.to_string()], fid),
+            Mutation::AddBatch { keywords, fid } => (OP_ADD, keywords.to_vec(), fid),
+            Mutation::Delete { keyword, fid } => (OP_DELETE, vec![keyword.to_string()], fid),
+        };
+        WalRecord {
+            op,
+            keywords,
+            fid: fid.to_string(),
+        }
+    }
+}
+
+fn column(store: &dyn ColumnStore, column: Column) -> Result<ColumnDb, String> {
+    store
+        .column(column)
+        .map_err(|e| format!("failed to open column '{}': {}", column.name(), e))
+}
+
+/// 状态持久化到键值存储的 ADS，见[模块文档](self)
+pub struct PersistentAds {
+    inner: Box<dyn AdsOperations>,
+    metadata: ColumnDb,
+    wal: ColumnDb,
+    /// 下一条 WAL 记录的序号
+    next_seq: u64,
+    /// 当前 WAL 中的记录数
+    wal_len: u64,
+    checkpoint_interval: u64,
+}
+
+impl PersistentAds {
+    /// 从已打开的存储中恢复 `mode` 对应的 ADS
+    ///
+    /// 存储由其他模式的 ADS 创建时返回错误
+    ///
+    /// # Arguments
+    /// * `mode` - ADS 模式，MPT 的节点保存在 nodes 列族中，其他模式只使用检查点
+    /// * `store` - 持久化存储
+    pub fn open(mode: AdsMode, store: &dyn ColumnStore) -> Result<Self, String> {
+        let inner: Box<dyn AdsOperations> = match mode {
+            AdsMode::Mpt => Box::new(MptAds::with_db(column(store, Column::Nodes)?)),
+            mode => {
+                create_ads(mode).ok_or_else(|| format!("unknown ADS mode '{}'", mode.name()))?
+            }
+        };
+        Self::with_inner(inner, mode, store)
+    }
+
+    /// 用已创建的（空的）ADS 打开数据库：加载检查点并重放 WAL
+    pub fn with_inner(
+        mut inner: Box<dyn AdsOperations>,
+        mode: AdsMode,
+        store: &dyn ColumnStore,
+    ) -> Result<Self, String> {
+        let mut metadata = column(store, Column::Metadata)?;
+        let name = mode.name();
+        match metadata.get(MODE_KEY).map_err(|e| e.to_string())? {
+            Some(stored) if stored != name.as_bytes() => {
+                return Err(format!(
+                    "database was created by ADS mode '{}', not '{}'",
+                    String::from_utf8_lossy(&stored),
+                    name
+                ));
+            }
+            Some(_) => {}
+            None => metadata
+                .put(MODE_KEY, name.as_bytes())
+                .map_err(|e| e.to_string())?,
+        }
+        inner.load_state(metadata.as_mut())?;
+
+        let mut wal = column(store, Column::Wal)?;
+        let records = wal.entries().map_err(|e| e.to_string())?;
+        let mut next_seq = 0;
+        for (key, value) in &records {
+            let seq: [u8; 8] = key
+                .as_slice()
+                .try_into()
+                .map_err(|_| "invalid WAL key".to_string())?;
+            // 失败的写操作同样记录在 WAL 中，重放时以同样的方式失败
+            if let Err(e) = WalRecord::decode(value)?.apply(inner.as_mut()) {
+                error!(
+                    "WAL record {} failed again on replay: {}",
+                    u64::from_be_bytes(seq),
+                    e
+                );
+            }
+            next_seq = u64::from_be_bytes(seq) + 1;
+        }
+
+        let mut ads = PersistentAds {
+            inner,
+            metadata,
+            wal,
+            next_seq,
+            wal_len: records.len() as u64,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+        };
+        if ads.wal_len > 0 {
+            ads.checkpoint()?;
+        }
+        Ok(ads)
+    }
+
+    /// 每 `interval` 次写操作保存一次检查点（至少为 1）
+    pub fn with_checkpoint_interval(mut self, interval: u64) -> Self {
+        self.checkpoint_interval = interval.max(1);
+        self
+    }
+
+    /// 当前 WAL 中尚未写入检查点的记录数
+    pub fn wal_len(&self) -> u64 {
+        self.wal_len
+    }
+
+    /// 保存检查点并清空 WAL
+    pub fn checkpoint(&mut self) -> Result<(), String> {
+        self.inner.save_state(self.metadata.as_mut())?;
+        self.wal.clear().map_err(|e| e.to_string())?;
+        self.wal_len = 0;
+        Ok(())
+    }
+
+    /// 追加 WAL 记录后执行，达到检查点间隔时保存检查点
+    fn write(&mut self, record: WalRecord) -> AdsResult {
+        self.logged(std::slice::from_ref(&record), |inner| record.apply(inner))
+    }
+
+    /// 追加 WAL 记录后在内部 ADS 上执行 `apply`，`apply` 必须执行与这些记录依次重放相同的写操作
+    fn logged<R>(
+        &mut self,
+        records: &[WalRecord],
+        apply: impl FnOnce(&mut dyn AdsOperations) -> R,
+    ) -> R {
+        for record in records {
+            if let Err(e) = self.wal.put(&self.next_seq.to_be_bytes(), &record.encode()) {
+                error!("Failed to append WAL record {}: {}", self.next_seq, e);
+            }
+            self.next_seq += 1;
+            self.wal_len += 1;
+        }
+
+        let result = apply(self.inner.as_mut());
+        if self.wal_len >= self.checkpoint_interval {
+            if let Err(e) = self.checkpoint() {
+                warn!("Checkpoint failed, keeping the WAL: {}", e);
+            }
+        }
+        result
+    }
+}
+
+impl AdsOperations for PersistentAds {
+    fn add(&mut self, keyword: &str, fid: &str) -> AdsResult {
+        self.write(WalRecord {
+            op: OP_ADD,
+            keywords: vec![keyword.to_string()],
+            fid: fid.to_string(),
+        })
+    }
+
+    fn add_batch(&mut self, keywords: &[String], fid: &str) -> AdsResult {
+        if keywords.is_empty() {
+            return Err(AdsError::EmptyBatch);
+        }
+        self.write(WalRecord {
+            op: OP_ADD,
+            keywords: keywords.to_vec(),
+            fid: fid.to_string(),
+        })
+    }
+
+    fn query(&self, keyword: &str) -> (Vec<String>, Proof) {
+        self.inner.query(keyword)
+    }
+
+    fn prove_difference(
+        &self,
+        keyword: &str,
+        excluded: &[String],
+    ) -> Option<(Vec<String>, Vec<u8>)> {
+        self.inner.prove_difference(keyword, excluded)
+    }
+
+    fn query_boolean(&self, expr: &BooleanExpr) -> Result<(Vec<String>, BooleanProof), String> {
+        self.inner.query_boolean(expr)
+    }
+
+    fn range_query(&self, start: &str, end: &str) -> Option<(RangeEntries, Vec<u8>)> {
+        self.inner.range_query(start, end)
+    }
+
+    fn prefix_query(&self, prefix: &str) -> Option<(PrefixEntries, Vec<u8>)> {
+        self.inner.prefix_query(prefix)
+    }
+
+    fn delete(&mut self, keyword: &str, fid: &str) -> AdsResult {
+        self.write(WalRecord {
+            op: OP_DELETE,
+            keywords: vec![keyword.to_string()],
+            fid: fid.to_string(),
+        })
+    }
+
+    fn delete_batch(&mut self, keywords: &[String], fid: &str) -> AdsResult {
+        if keywords.is_empty() {
+            return Err(AdsError::EmptyBatch);
+        }
+        self.write(WalRecord {
+            op: OP_DELETE,
+            keywords: keywords.to_vec(),
+            fid: fid.to_string(),
+        })
+    }
+
+    /// 记录为一次删除和一次添加，重放时得到相同的状态；执行时仍使用内部 ADS 的替换
+    fn update(&mut self, keyword: &str, old_fid: &str, new_fid: &str) -> AdsResult {
+        let records = [
+            WalRecord {
+                op: OP_DELETE,
+                keywords: vec![keyword.to_string()],
+                fid: old_fid.to_string(),
+            },
+            WalRecord {
+                op: OP_ADD,
+                keywords: vec![keyword.to_string()],
+                fid: new_fid.to_string(),
+            },
+        ];
+        self.logged(&records, |inner| inner.update(keyword, old_fid, new_fid))
+    }
+
+    fn root_hash(&self) -> Option<RootHash> {
+        self.inner.root_hash()
+    }
+
+    fn apply_deferred(&mut self, mutation: Mutation) -> Result<(ProofJob, RootHash), AdsError> {
+        self.logged(&[mutation.into()], |inner| inner.apply_deferred(mutation))
+    }
+
+    fn needs_maintenance(&self) -> bool {
+        self.inner.needs_maintenance()
+    }
+
+    fn maintenance_slice(&mut self, budget: Duration) -> bool {
+        self.inner.maintenance_slice(budget)
+    }
+
+    fn query_at_root(
+        &self,
+        keyword: &str,
+        root_hash: &[u8],
+    ) -> Result<(Vec<String>, Proof), String> {
+        self.inner.query_at_root(keyword, root_hash)
+    }
+
+    fn root_history(&self) -> Option<Vec<(u64, RootHash)>> {
+        self.inner.root_history()
+    }
+
+    fn finish_maintenance(&mut self) {
+        self.inner.finish_maintenance()
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        if self.wal_len > 0 {
+            self.checkpoint()?;
+        }
+        Ok(())
+    }
+
+    fn export_state(&self) -> Option<Vec<u8>> {
+        self.inner.export_state()
+    }
+
+    /// 导入的状态不经过 WAL，导入后立即保存检查点
+    fn import_state(&mut self, state: &[u8]) -> Result<(), String> {
+        self.inner.import_state(state)?;
+        self.checkpoint()
+    }
+
+    fn keywords(&self) -> Option<Vec<String>> {
+        self.inner.keywords()
+    }
+
+    fn tombstone_stats(&self) -> Option<TombstoneStats> {
+        self.inner.tombstone_stats()
+    }
+
+    /// 先保存检查点：重启时 WAL 从检查点的根开始重放，该根的节点必须在回收后仍然存在
+    fn prune(&mut self, keep_roots: &[RootHash]) -> Result<PruneStats, String> {
+        self.checkpoint()?;
+        self.inner.prune(keep_roots)
+    }
+
+    fn save_state(&mut self, db: &mut dyn Database) -> Result<(), String> {
+        self.inner.save_state(db)
+    }
+
+    fn load_state(&mut self, db: &mut dyn Database) -> Result<(), String> {
+        self.inner.load_state(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use esa_rust::mpt::RocksDbAdapter;
+    use std::path::Path;
+
+    fn open(mode: AdsMode, path: &Path) -> PersistentAds {
+        let store = RocksDbAdapter::open(path).unwrap();
+        PersistentAds::open(mode, &store).unwrap()
+    }
+
+    #[test]
+    fn test_wal_record_roundtrip() {
+        let record = WalRecord {
+            op: OP_ADD,
+            keywords: vec!["rust".to_string(), "go".to_string()],
+            fid: "f1".to_string(),
+        };
+        let encoded = record.encode();
+        assert_eq!(WalRecord::decode(&encoded).unwrap(), record);
+        assert!(WalRecord::decode(&encoded[..encoded.len() - 1]).is_err());
+    }
+
+    /// 写入的操作序列，同时在内存中的 MPT 上执行作为对照
+    fn write(ads: &mut dyn AdsOperations) -> RootHash {
+        ads.add("rust", "f1").unwrap();
+        ads.add_batch(&["rust".to_string(), "go".to_string()], "f2")
+            .unwrap();
+        ads.add("go", "f3").unwrap();
+        ads.delete("rust", "f1").unwrap();
+        ads.add("python", "f4").unwrap().1
+    }
+
+    #[test]
+    fn test_replays_wal_after_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut expected = MptAds::new();
+        let root_hash = write(&mut expected);
+        {
+            let mut ads = open(AdsMode::Mpt, dir.path()).with_checkpoint_interval(3);
+            assert_eq!(write(&mut ads), root_hash);
+            // 前三次写入已经保存为检查点，后两次只在 WAL 中
+            assert_eq!(ads.wal_len(), 2);
+        }
+
+        let mut ads = open(AdsMode::Mpt, dir.path());
+        assert_eq!(ads.wal_len(), 0);
+        assert_eq!(ads.query("rust").0, vec!["f2"]);
+        assert_eq!(ads.query("python").0, vec!["f4"]);
+        let (entries, _) = ads.range_query("", "").unwrap();
+        let keywords: Vec<&str> = entries.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keywords, vec!["go", "python", "rust"]);
+
+        // 重新打开后的写入继续在同一棵树上进行
+        assert_eq!(
+            ads.add("java", "f5").unwrap().1,
+            expected.add("java", "f5").unwrap().1
+        );
+    }
+
+    #[test]
+    fn test_replays_update_and_delete_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut expected = MptAds::new();
+        write(&mut expected);
+        let keywords = ["rust".to_string(), "go".to_string()];
+        expected.update("go", "f3", "f5").unwrap();
+        let (_, root_hash) = expected.delete_batch(&keywords, "f2").unwrap();
+        {
+            let mut ads = open(AdsMode::Mpt, dir.path());
+            write(&mut ads);
+            ads.update("go", "f3", "f5").unwrap();
+            assert_eq!(ads.delete_batch(&keywords, "f2").unwrap().1, root_hash);
+            // 替换记录为删除和添加两条记录
+            assert_eq!(ads.wal_len(), 8);
+        }
+
+        let ads = open(AdsMode::Mpt, dir.path());
+        assert_eq!(ads.root_hash(), Some(root_hash));
+        assert_eq!(ads.query("go").0, vec!["f5"]);
+        assert!(ads.query("rust").0.is_empty());
+    }
+
+    #[test]
+    fn test_flush_checkpoints_wal() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ads = open(AdsMode::Mpt, dir.path());
+        let root_hash = write(&mut ads);
+        assert_eq!(ads.wal_len(), 5);
+        ads.flush().unwrap();
+        assert_eq!(ads.wal_len(), 0);
+        drop(ads);
+
+        let ads = open(AdsMode::Mpt, dir.path());
+        assert_eq!(ads.root_hash(), Some(root_hash));
+    }
+
+    #[test]
+    fn test_rejects_other_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        drop(open(AdsMode::Mpt, dir.path()));
+        let store = RocksDbAdapter::open(dir.path()).unwrap();
+        let error = PersistentAds::open(AdsMode::MerkleTree, &store)
+            .err()
+            .unwrap();
+        assert!(error.contains("mpt"), "{}", error);
+    }
+}
@@ -0,0 +1,138 @@
+//! ADS 计算线程池
+//!
+//! 累加器的配对和多项式运算、MPT 的修复和大范围证明都是同步的 CPU 密集操作，
+//! 直接在 RPC 处理函数中执行会占住 Tokio 工作线程，同一线程上的其他请求（包括健康检查）
+//! 都要等它完成。[`AdsPool::run`] 把这些操作交给 rayon 线程池执行，处理函数只等待结果。
+//!
+//! ADS 内部的并行计算（`par_iter` 等）在哪个池中被调用就使用哪个池，
+//! 因此专用线程池同时限制了 ADS 占用的 CPU 核数。
+//!
+//! 确定性模拟使用 [`AdsPool::inline`]：操作直接在调用方线程上执行，
+//! 单线程运行时不会因为等待其他线程而被视为空闲。
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+use tokio::sync::oneshot;
+
+/// 执行 ADS 操作的线程池，克隆共享同一个池
+#[derive(Clone, Default)]
+pub struct AdsPool {
+    workers: Workers,
+}
+
+#[derive(Clone, Default)]
+enum Workers {
+    /// rayon 的全局线程池
+    #[default]
+    Global,
+    /// 专用线程池
+    Dedicated(Arc<ThreadPool>),
+    /// 在调用方线程上执行
+    Inline,
+}
+
+impl AdsPool {
+    /// 使用 rayon 的全局线程池（线程数默认等于 CPU 核数）
+    pub fn global() -> Self {
+        Self::default()
+    }
+
+    /// 不使用线程池，在调用方线程上直接执行（会阻塞调用方所在的 Tokio 工作线程）
+    pub fn inline() -> Self {
+        AdsPool {
+            workers: Workers::Inline,
+        }
+    }
+
+    /// 创建有 `threads` 个线程的专用线程池
+    pub fn with_threads(threads: usize) -> Result<Self, String> {
+        if threads == 0 {
+            return Err("the ADS worker pool needs at least one thread".to_string());
+        }
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("ads-worker-{}", i))
+            .build()
+            .map_err(|e| format!("failed to start the ADS worker pool: {}", e))?;
+        Ok(Self {
+            workers: Workers::Dedicated(Arc::new(pool)),
+        })
+    }
+
+    /// 线程池中的线程数
+    pub fn threads(&self) -> usize {
+        match &self.workers {
+            Workers::Global => rayon::current_num_threads(),
+            Workers::Dedicated(pool) => pool.current_num_threads(),
+            Workers::Inline => 1,
+        }
+    }
+
+    /// 在线程池中执行 `f` 并等待结果
+    ///
+    /// 调用方的 future 被取消时 `f` 仍会执行完毕，已经开始的写入不会只做一半。
+    /// `f` 中的 panic 在调用方重新抛出，与直接调用时一致
+    pub async fn run<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let job = move || {
+            let _ = tx.send(catch_unwind(AssertUnwindSafe(f)));
+        };
+        match &self.workers {
+            Workers::Global => rayon::spawn(job),
+            Workers::Dedicated(pool) => pool.spawn(job),
+            Workers::Inline => job(),
+        }
+        match rx.await.expect("ADS worker exited without a result") {
+            Ok(result) => result,
+            Err(panic) => resume_unwind(panic),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_runtime_stays_responsive_during_heavy_work() {
+        let pool = AdsPool::with_threads(1).unwrap();
+        assert_eq!(pool.threads(), 1);
+
+        // 单线程运行时上，如果重活在运行时线程上执行，计时任务只能等它结束
+        let heavy = pool.run(|| {
+            std::thread::sleep(Duration::from_millis(300));
+            std::thread::current().name().map(str::to_string)
+        });
+        let ticker = async {
+            let start = std::time::Instant::now();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            start.elapsed()
+        };
+        let (worker, waited) = tokio::join!(heavy, ticker);
+        assert_eq!(worker.as_deref(), Some("ads-worker-0"));
+        assert!(waited < Duration::from_millis(250), "{:?}", waited);
+    }
+
+    #[tokio::test]
+    async fn test_panics_reach_the_caller() {
+        let pool = AdsPool::with_threads(1).unwrap();
+        let result =
+            tokio::spawn(async move { pool.run(|| -> u32 { panic!("bad proof") }).await }).await;
+        assert!(result.unwrap_err().is_panic());
+        assert!(AdsPool::with_threads(0).is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_inline_runs_on_the_caller_thread() {
+        let caller = std::thread::current().id();
+        let pool = AdsPool::inline();
+        assert_eq!(pool.threads(), 1);
+        assert_eq!(pool.run(move || std::thread::current().id()).await, caller);
+    }
+}
@@ -0,0 +1,52 @@
+//! ADS 实现注册表
+//!
+//! 将 [`AdsMode`] 映射到创建 ADS 实例的工厂函数。
+//! 内置的累加器和 MPT 始终可用；第三方 ADS crate 在启动时调用
+//! [`register_ads_backend`] 注册模式描述和工厂，之后即可通过
+//! `Storager::from_config("<name>")` 使用。
+
+use super::{AdsOperations, CryptoAccumulatorAds, MerkleTreeAds, MptAds, SmtAds};
+use common::registry::{register_ads_mode, AdsDescriptor};
+use common::AdsMode;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+/// 创建 ADS 实例的工厂函数
+pub type AdsFactory = fn() -> Box<dyn AdsOperations>;
+
+fn factories() -> &'static RwLock<HashMap<&'static str, AdsFactory>> {
+    static FACTORIES: OnceLock<RwLock<HashMap<&'static str, AdsFactory>>> = OnceLock::new();
+    FACTORIES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// 注册第三方 ADS 实现
+///
+/// 同时在 common 的模式注册表中登记描述信息，返回分配到的 [`AdsMode`]
+pub fn register_ads_backend(
+    descriptor: AdsDescriptor,
+    factory: AdsFactory,
+) -> Result<AdsMode, String> {
+    let mode = register_ads_mode(descriptor)?;
+    factories()
+        .write()
+        .unwrap()
+        .insert(descriptor.name, factory);
+    Ok(mode)
+}
+
+/// 根据模式创建 ADS 实例
+///
+/// 第三方模式未注册工厂时返回 None
+pub fn create_ads(mode: AdsMode) -> Option<Box<dyn AdsOperations>> {
+    match mode {
+        AdsMode::CryptoAccumulator => Some(Box::new(CryptoAccumulatorAds::new())),
+        AdsMode::Mpt => Some(Box::new(MptAds::new())),
+        AdsMode::MerkleTree => Some(Box::new(MerkleTreeAds::new())),
+        AdsMode::SparseMerkleTree => Some(Box::new(SmtAds::new())),
+        AdsMode::Custom(name) => factories()
+            .read()
+            .unwrap()
+            .get(name)
+            .map(|factory| factory()),
+    }
+}
@@ -0,0 +1,213 @@
+//! Sparse Merkle Tree ADS Implementation
+//!
+//! 整个 storager 维护一棵 256 层的稀疏 Merkle 树，每个 keyword 是一个叶子，
+//! 位置为 `SHA256(keyword)`，值为其 fid 列表的哈希，树根即 storager 的根哈希。
+//!
+//! 与 MPT 相比，写入只重算一条固定长度的路径，证明只携带非空的兄弟节点；
+//! 与累加器相比，不存在的 keyword 的证明与查询证明格式相同，生成和验证都只需要哈希。
+//! 适合 keyword 频繁出现和消失的场景。证明格式见 [`esa_rust::smt::KeywordProof`]。
+
+use super::state::{put_bytes, put_u32, StateReader};
+use super::{AdsOperations, AdsResult};
+use common::{Proof, RootHash};
+use esa_rust::smt::{fids_hash, key_hash, KeywordProof, SparseMerkleTree};
+use std::collections::HashMap;
+
+/// Sparse Merkle Tree ADS 实现
+#[derive(Default)]
+pub struct SmtAds {
+    tree: SparseMerkleTree,
+    /// keyword 对应的 fid（按插入顺序），不保存没有 fid 的 keyword
+    fids: HashMap<String, Vec<String>>,
+}
+
+impl SmtAds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 把 keyword 的 fid 列表写入树，列表为空时删除叶子
+    fn sync_leaf(&mut self, keyword: &str) {
+        let key = key_hash(keyword);
+        match self.fids.get(keyword) {
+            Some(fids) => {
+                self.tree.insert(key, fids_hash(fids));
+            }
+            None => {
+                self.tree.remove(&key);
+            }
+        }
+    }
+
+    /// keyword 当前的 fid 列表和证明
+    fn keyword_proof(&self, keyword: &str) -> (Vec<String>, Proof) {
+        let fids = self.fids.get(keyword).cloned().unwrap_or_default();
+        let proof = KeywordProof {
+            keyword: keyword.to_string(),
+            fids: fids.clone(),
+            path: self.tree.prove(&key_hash(keyword)),
+        };
+        (fids, Proof::Smt(proof.to_bytes()))
+    }
+
+    /// keyword 写入后的证明和当前根
+    fn mutation_result(&self, keyword: &str) -> AdsResult {
+        let (_, proof) = self.keyword_proof(keyword);
+        Ok((proof, self.tree.root().to_vec()))
+    }
+}
+
+impl AdsOperations for SmtAds {
+    fn add(&mut self, keyword: &str, fid: &str) -> AdsResult {
+        let fids = self.fids.entry(keyword.to_string()).or_default();
+        // 重复添加时树不变，证明显示 fid 已经存在
+        if !fids.iter().any(|f| f == fid) {
+            fids.push(fid.to_string());
+            self.sync_leaf(keyword);
+        }
+        self.mutation_result(keyword)
+    }
+
+    fn query(&self, keyword: &str) -> (Vec<String>, Proof) {
+        self.keyword_proof(keyword)
+    }
+
+    /// 证明删除后 keyword 的 fid 列表；删除最后一个 fid 后即为 keyword 不存在的证明
+    fn delete(&mut self, keyword: &str, fid: &str) -> AdsResult {
+        if let Some(fids) = self.fids.get_mut(keyword) {
+            let before = fids.len();
+            fids.retain(|f| f != fid);
+            let changed = fids.len() != before;
+            if fids.is_empty() {
+                self.fids.remove(keyword);
+            }
+            if changed {
+                self.sync_leaf(keyword);
+            }
+        }
+        self.mutation_result(keyword)
+    }
+
+    fn root_hash(&self) -> Option<RootHash> {
+        Some(self.tree.root().to_vec())
+    }
+
+    /// 格式: [count | (keyword | fid_count | fid...)...]，keyword 按字典序
+    ///
+    /// 树由 fid 列表重新计算，恢复后的根与导出时相同
+    fn export_state(&self) -> Option<Vec<u8>> {
+        let mut keywords: Vec<_> = self.fids.iter().collect();
+        keywords.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut buf = Vec::new();
+        put_u32(&mut buf, keywords.len() as u32);
+        for (keyword, fids) in keywords {
+            put_bytes(&mut buf, keyword.as_bytes());
+            put_u32(&mut buf, fids.len() as u32);
+            for fid in fids {
+                put_bytes(&mut buf, fid.as_bytes());
+            }
+        }
+        Some(buf)
+    }
+
+    fn import_state(&mut self, state: &[u8]) -> Result<(), String> {
+        let mut reader = StateReader::new(state);
+        let mut ads = SmtAds::new();
+        for _ in 0..reader.u32()? {
+            let keyword = reader.string()?;
+            let fids = (0..reader.u32()?)
+                .map(|_| reader.string())
+                .collect::<Result<Vec<_>, _>>()?;
+            if fids.is_empty() {
+                return Err(format!("keyword '{}' has no fids in state", keyword));
+            }
+            ads.fids.insert(keyword.clone(), fids);
+            ads.sync_leaf(&keyword);
+        }
+        reader.finish()?;
+
+        *self = ads;
+        Ok(())
+    }
+
+    fn keywords(&self) -> Option<Vec<String>> {
+        Some(self.fids.keys().cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(proof: &Proof) -> KeywordProof {
+        let Proof::Smt(data) = proof else {
+            panic!("not an SMT proof: {:?}", proof);
+        };
+        KeywordProof::from_bytes(data).unwrap()
+    }
+
+    #[test]
+    fn test_proofs_bind_fids_to_root() {
+        let mut ads = SmtAds::new();
+        ads.add("rust", "f1").unwrap();
+        ads.add("go", "f2").unwrap();
+        let (proof, root) = ads.add("rust", "f3").unwrap();
+        let proof = decode(&proof);
+        assert_eq!(proof.fids, vec!["f1", "f3"]);
+        assert_eq!(proof.compute_root().unwrap().to_vec(), root);
+
+        // 重复添加不改变根
+        let (_, same_root) = ads.add("rust", "f1").unwrap();
+        assert_eq!(same_root, root);
+
+        let (fids, proof) = ads.query("rust");
+        assert_eq!(fids, vec!["f1", "f3"]);
+        assert_eq!(decode(&proof).compute_root().unwrap().to_vec(), root);
+
+        // 不存在的 keyword 得到同样格式的非成员资格证明
+        let (fids, proof) = ads.query("java");
+        assert!(fids.is_empty());
+        let proof = decode(&proof);
+        assert_eq!(proof.keyword, "java");
+        assert_eq!(proof.compute_root().unwrap().to_vec(), root);
+    }
+
+    #[test]
+    fn test_delete_last_fid_proves_absence() {
+        let mut ads = SmtAds::new();
+        let empty_root = ads.tree.root().to_vec();
+        let (_, root_one) = ads.add("rust", "f1").unwrap();
+        ads.add("rust", "f2").unwrap();
+
+        let (proof, root) = ads.delete("rust", "f2").unwrap();
+        assert_eq!(root, root_one);
+        assert_eq!(decode(&proof).fids, vec!["f1"]);
+
+        let (proof, root) = ads.delete("rust", "f1").unwrap();
+        assert_eq!(root, empty_root);
+        let proof = decode(&proof);
+        assert!(proof.fids.is_empty());
+        assert_eq!(proof.compute_root().unwrap().to_vec(), root);
+        assert!(ads.keywords().unwrap().is_empty());
+
+        // 删除不存在的 fid 不改变根
+        assert_eq!(ads.delete("rust", "f1").unwrap().1, empty_root);
+    }
+
+    #[test]
+    fn test_state_roundtrip_keeps_root() {
+        let mut ads = SmtAds::new();
+        ads.add("rust", "f1").unwrap();
+        ads.add("go", "f2").unwrap();
+        ads.add("rust", "f3").unwrap();
+        let (_, root) = ads.delete("go", "f2").unwrap();
+
+        let mut restored = SmtAds::new();
+        restored.import_state(&ads.export_state().unwrap()).unwrap();
+        assert_eq!(restored.tree.root().to_vec(), root);
+        assert_eq!(restored.query("rust"), ads.query("rust"));
+        assert_eq!(restored.export_state(), ads.export_state());
+        assert!(SmtAds::new().import_state(&[1, 2]).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+//! ADS 全量状态的编码
+//!
+//! [`AdsOperations::export_state`](super::AdsOperations::export_state) 导出的状态
+//! 在进程间交接时使用（见 [`crate::handover`]）。整数均为小端 u32 或 u64，
+//! 字节串和字符串带 u32 长度前缀。
+
+/// 写入 u32
+pub fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+/// 写入 u64
+pub fn put_u64(buf: &mut Vec<u8>, value: u64) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+/// 写入带长度前缀的字节串
+pub fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    put_u32(buf, bytes.len() as u32);
+    buf.extend_from_slice(bytes);
+}
+
+/// 按 [`put_u32`] / [`put_u64`] / [`put_bytes`] 的格式读取
+pub struct StateReader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        StateReader { buf }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.buf.len() < len {
+            return Err("truncated state".to_string());
+        }
+        let (head, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(head)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn bytes(&mut self) -> Result<&'a [u8], String> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    pub fn string(&mut self) -> Result<String, String> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|e| e.to_string())
+    }
+
+    /// 是否已经读完
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// 确认所有数据都已读取
+    pub fn finish(self) -> Result<(), String> {
+        if self.buf.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("{} trailing bytes in state", self.buf.len()))
+        }
+    }
+}
+
+/// 编码 keyword -> fid 列表（fid 保持写入顺序，keyword 按字典序输出）
+pub fn encode_postings<'a, I>(postings: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = (&'a String, &'a Vec<String>)>,
+{
+    let mut postings: Vec<_> = postings.into_iter().collect();
+    postings.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut buf = Vec::new();
+    put_u32(&mut buf, postings.len() as u32);
+    for (keyword, fids) in postings {
+        put_bytes(&mut buf, keyword.as_bytes());
+        put_u32(&mut buf, fids.len() as u32);
+        for fid in fids {
+            put_bytes(&mut buf, fid.as_bytes());
+        }
+    }
+    buf
+}
+
+/// 解码 [`encode_postings`] 的输出
+pub fn decode_postings(state: &[u8]) -> Result<Vec<(String, Vec<String>)>, String> {
+    let mut reader = StateReader::new(state);
+    let count = reader.u32()?;
+    let mut postings = Vec::new();
+    for _ in 0..count {
+        let keyword = reader.string()?;
+        let fids = (0..reader.u32()?)
+            .map(|_| reader.string())
+            .collect::<Result<_, _>>()?;
+        postings.push((keyword, fids));
+    }
+    reader.finish()?;
+    Ok(postings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_postings_roundtrip() {
+        let mut postings = HashMap::new();
+        postings.insert("rust".to_string(), vec!["f2".to_string(), "f1".to_string()]);
+        postings.insert("go".to_string(), vec![]);
+
+        let encoded = encode_postings(&postings);
+        assert_eq!(
+            decode_postings(&encoded).unwrap(),
+            vec![
+                ("go".to_string(), vec![]),
+                ("rust".to_string(), vec!["f2".to_string(), "f1".to_string()]),
+            ]
+        );
+        assert!(decode_postings(&encoded[..encoded.len() - 1]).is_err());
+    }
+}
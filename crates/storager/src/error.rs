@@ -0,0 +1,98 @@
+//! storager 的错误类型
+//!
+//! RPC 处理函数内部用 [`StoragerError`] 表示失败，返回时转换为带有
+//! [`ErrorKind`] 的 [`Status`]，Manager 和客户端据此区分请求错误与存储失败。
+
+use crate::proof_queue::ProofError;
+use common::{AdsError, ErrorKind};
+use thiserror::Error;
+use tonic::{Code, Status};
+
+/// storager 处理请求失败的原因
+#[derive(Debug, Error)]
+pub enum StoragerError {
+    /// 密码学参数没有加载成功，所有 ADS 请求都被拒绝
+    #[error("crypto subsystem unavailable: {0}")]
+    CryptoUnavailable(String),
+
+    /// 正在把状态交接给新进程，写请求被拒绝
+    #[error("storager is handing over to a new process, retry shortly")]
+    HandingOver,
+
+    #[error(transparent)]
+    Ads(#[from] AdsError),
+
+    #[error(transparent)]
+    Proof(#[from] ProofError),
+
+    /// 配置的 ADS 没有实现该操作
+    #[error("ADS does not support {0}")]
+    Unsupported(&'static str),
+
+    /// 开启 fid 驻留时无法给出 Manager 能验证的证明
+    #[error("{0} are not supported with fid interning")]
+    Interned(&'static str),
+
+    /// 当前状态下无法完成（如表达式无法证明、回收失败）
+    #[error("{0}")]
+    Precondition(String),
+
+    /// 请求参数无效
+    #[error("{0}")]
+    InvalidRequest(String),
+
+    /// 历史根哈希未知或已被回收
+    #[error("{0}")]
+    UnknownRoot(String),
+
+    /// 连接不上迁移的目标 storager
+    #[error("Failed to connect to target storager: {0}")]
+    TargetUnavailable(String),
+
+    /// 迁移的目标 storager 拒绝了写入
+    #[error("Target MigrateIn failed: {0}")]
+    TargetFailed(String),
+}
+
+impl From<StoragerError> for Status {
+    fn from(error: StoragerError) -> Status {
+        let (kind, code) = match error {
+            StoragerError::Ads(ads) => return ads.into(),
+            StoragerError::CryptoUnavailable(_) | StoragerError::HandingOver => {
+                (ErrorKind::Storage, Code::Unavailable)
+            }
+            StoragerError::Proof(ProofError::UnknownHandle(_)) => {
+                (ErrorKind::InvalidRequest, Code::NotFound)
+            }
+            StoragerError::Proof(ProofError::Failed(_)) => (ErrorKind::Storage, Code::Internal),
+            StoragerError::Unsupported(_) => (ErrorKind::Storage, Code::Unimplemented),
+            StoragerError::Interned(_) | StoragerError::Precondition(_) => {
+                (ErrorKind::Storage, Code::FailedPrecondition)
+            }
+            StoragerError::InvalidRequest(_) => (ErrorKind::InvalidRequest, Code::InvalidArgument),
+            StoragerError::UnknownRoot(_) => (ErrorKind::InvalidRequest, Code::NotFound),
+            StoragerError::TargetUnavailable(_) => (ErrorKind::Routing, Code::Unavailable),
+            StoragerError::TargetFailed(_) => (ErrorKind::Storage, Code::Internal),
+        };
+        kind.status(code, error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statuses_carry_kind() {
+        let status = Status::from(StoragerError::HandingOver);
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(ErrorKind::from_status(&status), Some(ErrorKind::Storage));
+
+        let status = Status::from(StoragerError::from(AdsError::EmptyBatch));
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(status.message(), AdsError::EmptyBatch.to_string());
+
+        let status = Status::from(StoragerError::TargetFailed("disk full".to_string()));
+        assert_eq!(status.message(), "Target MigrateIn failed: disk full");
+    }
+}
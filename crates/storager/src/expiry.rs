@@ -0,0 +1,316 @@
+//! 带过期时间的 (keyword, fid)
+//!
+//! Add / BatchAdd 请求可以携带过期时间（Unix 毫秒，由 Manager 根据 TTL 计算，各副本相同）。
+//! storager 记录每个 (keyword, fid) 的过期时间，清理任务
+//! （[`Storager::spawn_expiry_sweeper`]）定期通过与 Delete 相同的 ADS 删除路径删除到期的条目，
+//! 生成删除证明并推进版本号。每次删除按顺序记入清理日志，Manager 通过 `ListExpired`
+//! 拉取、验证并发布新的根哈希。
+//!
+//! 不带过期时间再次加入同一 (keyword, fid) 或删除它会取消过期。过期时间随进程交接导出，
+//! 但不写入持久化后端，重启后不会恢复；迁移到其他 storager 的 keyword 也不再过期。
+
+use crate::error::StoragerError;
+use crate::storager::Storager;
+use common::{Proof, RootHash};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// 清理日志保留的删除数
+pub const DEFAULT_SWEEP_LOG_CAPACITY: usize = 4096;
+
+/// `ListExpired` 一次最多返回的删除数
+pub const MAX_EXPIRED_ENTRIES: usize = 1024;
+
+/// 每个 (keyword, fid) 的过期时间
+#[derive(Default)]
+pub struct ExpiryIndex {
+    inner: Mutex<Schedule>,
+}
+
+#[derive(Default)]
+struct Schedule {
+    /// (keyword, fid) -> 过期时间
+    by_entry: HashMap<(String, String), u64>,
+    /// 按过期时间排序的 (过期时间, keyword, fid)
+    by_time: BTreeSet<(u64, String, String)>,
+}
+
+impl ExpiryIndex {
+    /// 设置 (keyword, fid) 的过期时间（Unix 毫秒），0 取消过期
+    pub fn schedule(&self, keyword: &str, fid: &str, expires_at_ms: u64) {
+        let mut schedule = self.inner.lock().unwrap();
+        let entry = (keyword.to_string(), fid.to_string());
+        if let Some(old) = schedule.by_entry.remove(&entry) {
+            schedule
+                .by_time
+                .remove(&(old, entry.0.clone(), entry.1.clone()));
+        }
+        if expires_at_ms > 0 {
+            schedule
+                .by_time
+                .insert((expires_at_ms, entry.0.clone(), entry.1.clone()));
+            schedule.by_entry.insert(entry, expires_at_ms);
+        }
+    }
+
+    /// 取消 (keyword, fid) 的过期
+    pub fn cancel(&self, keyword: &str, fid: &str) {
+        self.schedule(keyword, fid, 0);
+    }
+
+    /// 取出 `now_ms` 时已经到期的 (keyword, fid)，按过期时间排序
+    pub fn take_due(&self, now_ms: u64) -> Vec<(String, String)> {
+        let mut schedule = self.inner.lock().unwrap();
+        let mut due = Vec::new();
+        while let Some((expires_at, _, _)) = schedule.by_time.first() {
+            if *expires_at > now_ms {
+                break;
+            }
+            let (_, keyword, fid) = schedule.by_time.pop_first().unwrap();
+            schedule.by_entry.remove(&(keyword.clone(), fid.clone()));
+            due.push((keyword, fid));
+        }
+        due
+    }
+
+    /// 等待过期的 (keyword, fid, 过期时间)，按过期时间排序
+    pub fn entries(&self) -> Vec<(String, String, u64)> {
+        let schedule = self.inner.lock().unwrap();
+        schedule
+            .by_time
+            .iter()
+            .map(|(expires_at, keyword, fid)| (keyword.clone(), fid.clone(), *expires_at))
+            .collect()
+    }
+
+    /// 等待过期的 (keyword, fid) 数量
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().by_entry.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 清理任务删除的一个 (keyword, fid)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SweptEntry {
+    pub sequence: u64,
+    pub keyword: String,
+    pub fid: String,
+    /// 删除证明，以及删除后的根哈希和版本号
+    pub proof: Proof,
+    pub root_hash: RootHash,
+    pub epoch: u64,
+}
+
+/// 最近的清理删除，序号从 1 开始递增
+pub struct SweepLog {
+    inner: Mutex<(u64, VecDeque<SweptEntry>)>,
+    capacity: usize,
+}
+
+impl Default for SweepLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_SWEEP_LOG_CAPACITY)
+    }
+}
+
+impl SweepLog {
+    pub fn new(capacity: usize) -> Self {
+        SweepLog {
+            inner: Mutex::new((0, VecDeque::new())),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// 记录一次删除，返回它的序号；超出容量时淘汰最早的记录
+    pub fn push(
+        &self,
+        keyword: String,
+        fid: String,
+        proof: Proof,
+        root_hash: RootHash,
+        epoch: u64,
+    ) -> u64 {
+        let mut inner = self.inner.lock().unwrap();
+        inner.0 += 1;
+        let sequence = inner.0;
+        inner.1.push_back(SweptEntry {
+            sequence,
+            keyword,
+            fid,
+            proof,
+            root_hash,
+            epoch,
+        });
+        if inner.1.len() > self.capacity {
+            inner.1.pop_front();
+        }
+        sequence
+    }
+
+    /// 序号大于 `after` 的删除（最多 `limit` 条）、最新的序号，以及其中是否有已被淘汰的记录
+    pub fn since(&self, after: u64, limit: usize) -> (Vec<SweptEntry>, u64, bool) {
+        let inner = self.inner.lock().unwrap();
+        let entries: Vec<SweptEntry> = inner
+            .1
+            .iter()
+            .filter(|entry| entry.sequence > after)
+            .take(limit)
+            .cloned()
+            .collect();
+        let oldest = inner.1.front().map_or(inner.0 + 1, |entry| entry.sequence);
+        let truncated = after < inner.0 && oldest > after + 1;
+        (entries, inner.0, truncated)
+    }
+}
+
+impl Storager {
+    /// 删除当前已经到期的 (keyword, fid)，返回删除的数量
+    ///
+    /// 与 Delete 请求一样持有 ADS 写锁执行，每次删除推进版本号并记入清理日志。
+    /// 交接冻结期间不删除，到期的条目留到之后的清理
+    pub fn sweep_expired(&self) -> Result<usize, StoragerError> {
+        if self.expiries.is_empty() {
+            return Ok(0);
+        }
+        self.ensure_crypto_ready()?;
+        let mut ads = self.write_ads()?;
+        self.ensure_writable()?;
+
+        let now = self.clock.unix_millis();
+        let mut swept = 0;
+        for (keyword, fid) in self.expiries.take_due(now) {
+            let stored = self.lookup_fid(&fid);
+            let (proof, root_hash) = match ads.delete(&keyword, &stored) {
+                Ok(result) => result,
+                Err(e) => {
+                    warn!("Failed to delete expired ({}, {}): {}", keyword, fid, e);
+                    continue;
+                }
+            };
+            let epoch = self.advance_epoch();
+            self.keyword_stats.record_delete(&keyword);
+            debug!("Expired ({}, {}) at epoch {}", keyword, fid, epoch);
+            self.swept.push(keyword, fid, proof, root_hash, epoch);
+            swept += 1;
+        }
+        Ok(swept)
+    }
+
+    /// 启动过期清理任务，每隔 `interval` 清理默认命名空间和已打开的命名空间
+    pub fn spawn_expiry_sweeper(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let storager = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+
+                let namespaces = storager.opened_namespaces().into_iter().map(|(_, s)| s);
+                for instance in std::iter::once(storager.clone()).chain(namespaces) {
+                    match instance.run_ads(|s| s.sweep_expired()).await {
+                        Ok(0) => {}
+                        Ok(swept) => info!("Deleted {} expired entry(ies)", swept),
+                        Err(e) => debug!("Expiry sweep skipped: {}", e),
+                    }
+                }
+            }
+        })
+    }
+
+    /// 等待过期的 (keyword, fid) 及其过期时间
+    pub fn expiries(&self) -> &ExpiryIndex {
+        &self.expiries
+    }
+
+    /// 清理任务删除的记录
+    pub fn sweep_log(&self) -> &SweepLog {
+        &self.swept
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::clock::MockClock;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_schedule_and_cancel() {
+        let index = ExpiryIndex::default();
+        index.schedule("rust", "f1", 200);
+        index.schedule("rust", "f2", 100);
+        index.schedule("go", "f1", 300);
+        // 重新设置覆盖之前的过期时间，0 取消
+        index.schedule("go", "f1", 150);
+        index.schedule("rust", "f2", 0);
+        assert_eq!(index.len(), 2);
+
+        assert!(index.take_due(99).is_empty());
+        assert_eq!(
+            index.take_due(200),
+            vec![
+                ("go".to_string(), "f1".to_string()),
+                ("rust".to_string(), "f1".to_string())
+            ]
+        );
+        assert!(index.is_empty());
+    }
+
+    #[test]
+    fn test_sweep_log_reports_truncation() {
+        let log = SweepLog::new(2);
+        for i in 0..3 {
+            log.push(
+                format!("k{}", i),
+                "f".to_string(),
+                Proof::Mpt(vec![]),
+                vec![],
+                i,
+            );
+        }
+        let (entries, last, truncated) = log.since(0, 10);
+        assert_eq!(last, 3);
+        assert!(truncated);
+        assert_eq!(
+            entries.iter().map(|e| e.sequence).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        let (entries, _, truncated) = log.since(1, 1);
+        assert!(!truncated);
+        assert_eq!(entries[0].sequence, 2);
+        assert_eq!(log.since(3, 10), (vec![], 3, false));
+    }
+
+    #[test]
+    fn test_sweep_deletes_due_entries() {
+        let clock = MockClock::new(Duration::from_secs(100));
+        let storager = Storager::with_mpt().with_clock(Arc::new(clock.clone()));
+        {
+            let mut ads = storager.write_ads().unwrap();
+            ads.add("rust", "f1").unwrap();
+            ads.add("rust", "f2").unwrap();
+        }
+        storager.expiries().schedule("rust", "f1", 100_500);
+        storager.expiries().schedule("rust", "f2", 200_000);
+
+        assert_eq!(storager.sweep_expired().unwrap(), 0);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(storager.sweep_expired().unwrap(), 1);
+        assert_eq!(storager.ads.read().unwrap().query("rust").0, vec!["f2"]);
+
+        let (entries, last, _) = storager.sweep_log().since(0, 10);
+        assert_eq!(last, 1);
+        assert_eq!(entries[0].fid, "f1");
+        assert_eq!(entries[0].epoch, storager.epoch());
+        assert_eq!(
+            Some(entries[0].root_hash.clone()),
+            storager.ads.read().unwrap().root_hash()
+        );
+    }
+}
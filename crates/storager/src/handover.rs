@@ -0,0 +1,308 @@
+//! 零停机升级：监听 socket 交接
+//!
+//! 旧进程以 `--handover=<控制路径>` 启动，新版本的二进制以
+//! `--takeover=<控制路径>` 启动后接管旧进程的监听 socket 和全部状态：
+//!
+//! 1. 旧进程在控制路径上监听 Unix domain socket（[`serve_handover`]）
+//! 2. 新进程连接控制 socket 并发送 `TAKEOVER\n`（[`take_over`]）
+//! 3. 旧进程冻结写请求（Add / BatchAdd / Delete 返回 `UNAVAILABLE`，查询照常），
+//!    完成待修复的工作后导出全部状态（见 [`Storager::export_state`]）
+//! 4. 旧进程发送 16 字节的头部 `(tcp 数量: u32, unix 数量: u32, 状态长度: u64)`（小端），
+//!    所有监听 fd 通过 `SCM_RIGHTS` 附带在头部上，随后发送状态
+//! 5. 新进程导入状态，在继承的 fd 上开始服务并回复 `READY\n`（[`Takeover::ready`]）
+//! 6. 旧进程停止 accept，已建立的 HTTP/2 连接收到 GOAWAY，处理完在途请求后关闭
+//!
+//! 监听 socket 本身在两个进程之间共享，内核中的 accept 队列不会丢失，
+//! 交接期间到达的连接由新进程接受。已建立的连接无法迁移到另一个进程：
+//! Manager 的 channel 在收到 GOAWAY 后自动重连到同一地址，由新进程接受，
+//! 调用方感知到的只是冻结窗口内的写请求被拒绝（可以重试）。
+//!
+//! 新进程在发送 `READY` 之前失败（控制连接断开或返回其他内容）时，
+//! 旧进程解冻写请求并继续服务，等待下一次交接。
+
+use crate::storager::Storager;
+use common::net::Listeners;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+const TAKEOVER: &str = "TAKEOVER";
+const READY: &str = "READY";
+const HEADER_LEN: usize = 16;
+/// 单次交接最多传递的监听 fd 数量
+const MAX_FDS: usize = 64;
+/// 旧进程等待新进程就绪的最长时间
+const READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 新进程从旧进程接管到的内容
+pub struct Takeover {
+    /// 旧进程的监听 socket
+    pub listeners: Listeners,
+    /// 旧进程导出的状态，传给 [`Storager::import_state`]
+    pub state: Vec<u8>,
+    control: UnixStream,
+}
+
+impl Takeover {
+    /// 通知旧进程新进程已经开始服务，旧进程随后退出
+    pub fn ready(mut self) -> io::Result<()> {
+        writeln!(self.control, "{}", READY)?;
+        self.control.flush()
+    }
+}
+
+/// 连接旧进程的控制 socket，接管监听 socket 和状态（阻塞）
+pub fn take_over(control: &Path) -> io::Result<Takeover> {
+    let mut stream = UnixStream::connect(control)?;
+    writeln!(stream, "{}", TAKEOVER)?;
+
+    let mut header = [0u8; HEADER_LEN];
+    let (received, fds) = recv_with_fds(&stream, &mut header)?;
+    stream.read_exact(&mut header[received..])?;
+
+    let tcp_count = u32::from_le_bytes(header[0..4].try_into().unwrap()) as usize;
+    let unix_count = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+    let state_len = u64::from_le_bytes(header[8..16].try_into().unwrap()) as usize;
+    if fds.len() != tcp_count + unix_count {
+        return Err(invalid_data(format!(
+            "expected {} listening sockets, received {}",
+            tcp_count + unix_count,
+            fds.len()
+        )));
+    }
+
+    let mut state = vec![0u8; state_len];
+    stream.read_exact(&mut state)?;
+
+    let mut fds = fds.into_iter();
+    let listeners = Listeners {
+        tcp: fds.by_ref().take(tcp_count).map(Into::into).collect(),
+        unix: fds.map(Into::into).collect(),
+    };
+
+    Ok(Takeover {
+        listeners,
+        state,
+        control: stream,
+    })
+}
+
+/// 在控制路径上等待新进程接管
+///
+/// 交接成功后返回，可以作为 [`common::net::serve_listeners`] 的关闭信号；
+/// 交接失败时解冻写请求并继续等待
+///
+/// # 参数
+///
+/// * `control` - 控制 socket 路径
+/// * `storager` - 正在服务的实例
+/// * `listeners` - 正在服务的监听 socket（[`Listeners::try_clone`] 得到的副本）
+pub async fn serve_handover(
+    control: &Path,
+    storager: Arc<Storager>,
+    listeners: Listeners,
+) -> io::Result<()> {
+    let listener = common::net::bind_unix(control)?;
+    listener.set_nonblocking(true)?;
+    let listener = tokio::net::UnixListener::from_std(listener)?;
+    let listeners = Arc::new(listeners);
+
+    loop {
+        let stream = listener.accept().await?.0.into_std()?;
+        stream.set_nonblocking(false)?;
+
+        let (storager, listeners) = (storager.clone(), listeners.clone());
+        let result = tokio::task::spawn_blocking(move || hand_over(stream, &storager, &listeners))
+            .await
+            .map_err(io::Error::other)?;
+
+        match result {
+            Ok(()) => return Ok(()),
+            Err(e) => warn!("Handover aborted, continuing to serve: {}", e),
+        }
+    }
+}
+
+/// 处理一次交接请求，失败时解冻写请求
+fn hand_over(stream: UnixStream, storager: &Storager, listeners: &Listeners) -> io::Result<()> {
+    stream.set_read_timeout(Some(READY_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    if read_line(&mut reader)? != TAKEOVER {
+        return Err(invalid_data("unexpected handover request".to_string()));
+    }
+
+    storager.freeze();
+    let result = send_state(&stream, &mut reader, storager, listeners);
+    if result.is_err() {
+        storager.unfreeze();
+    }
+    result
+}
+
+fn send_state(
+    mut stream: &UnixStream,
+    reader: &mut impl BufRead,
+    storager: &Storager,
+    listeners: &Listeners,
+) -> io::Result<()> {
+    let state = storager.export_state().map_err(invalid_data)?;
+
+    let mut fds: Vec<RawFd> = listeners.tcp.iter().map(|l| l.as_raw_fd()).collect();
+    fds.extend(listeners.unix.iter().map(|l| l.as_raw_fd()));
+    if fds.len() > MAX_FDS {
+        return Err(invalid_data(format!(
+            "cannot hand over more than {} listening sockets",
+            MAX_FDS
+        )));
+    }
+
+    let mut header = [0u8; HEADER_LEN];
+    header[0..4].copy_from_slice(&(listeners.tcp.len() as u32).to_le_bytes());
+    header[4..8].copy_from_slice(&(listeners.unix.len() as u32).to_le_bytes());
+    header[8..16].copy_from_slice(&(state.len() as u64).to_le_bytes());
+    let sent = send_with_fds(stream, &header, &fds)?;
+    stream.write_all(&header[sent..])?;
+    stream.write_all(&state)?;
+    stream.flush()?;
+
+    match read_line(reader)?.as_str() {
+        READY => Ok(()),
+        other => Err(invalid_data(format!(
+            "new process did not become ready: {:?}",
+            other
+        ))),
+    }
+}
+
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(line.trim_end().to_string())
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// 发送数据并通过 `SCM_RIGHTS` 附带 fd，返回已发送的字节数
+fn send_with_fds(stream: &UnixStream, data: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+    let fds_len = mem::size_of_val(fds);
+    let mut control = vec![0u64; cmsg_space(fds_len).div_ceil(8)];
+    let mut iov = libc::iovec {
+        iov_base: data.as_ptr() as *mut libc::c_void,
+        iov_len: data.len(),
+    };
+
+    // SAFETY: msghdr 指向的缓冲区在 sendmsg 返回前一直有效，控制缓冲区按 u64 对齐，
+    // 大小由 CMSG_SPACE 计算
+    let sent = unsafe {
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        if !fds.is_empty() {
+            msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+            msg.msg_controllen = cmsg_space(fds_len) as _;
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(fds_len as u32) as _;
+            std::ptr::copy_nonoverlapping(
+                fds.as_ptr() as *const u8,
+                libc::CMSG_DATA(cmsg),
+                fds_len,
+            );
+        }
+        libc::sendmsg(stream.as_raw_fd(), &msg, 0)
+    };
+    if sent < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(sent as usize)
+}
+
+/// 接收数据和 `SCM_RIGHTS` 附带的 fd，返回 (接收的字节数, fd)
+fn recv_with_fds(stream: &UnixStream, buf: &mut [u8]) -> io::Result<(usize, Vec<OwnedFd>)> {
+    let fds_len = MAX_FDS * mem::size_of::<RawFd>();
+    let mut control = vec![0u64; cmsg_space(fds_len).div_ceil(8)];
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+
+    // SAFETY: 同 send_with_fds；内核写入的控制消息只在 msg_controllen 范围内遍历，
+    // 每个收到的 fd 只被 OwnedFd 接管一次
+    unsafe {
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = cmsg_space(fds_len) as _;
+
+        let received = libc::recvmsg(stream.as_raw_fd(), &mut msg, 0);
+        if received < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut fds = Vec::new();
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(cmsg);
+                let count = ((*cmsg).cmsg_len as usize - (data as usize - cmsg as usize))
+                    / mem::size_of::<RawFd>();
+                for i in 0..count {
+                    let fd = std::ptr::read_unaligned((data as *const RawFd).add(i));
+                    fds.push(OwnedFd::from_raw_fd(fd));
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+        if msg.msg_flags & libc::MSG_CTRUNC != 0 {
+            return Err(invalid_data("listening sockets were truncated".to_string()));
+        }
+        if received == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok((received as usize, fds))
+    }
+}
+
+fn cmsg_space(len: usize) -> usize {
+    // SAFETY: CMSG_SPACE 只做算术运算
+    unsafe { libc::CMSG_SPACE(len as u32) as usize }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fds_survive_transfer() {
+        let (left, right) = UnixStream::pair().unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        assert_eq!(
+            send_with_fds(&left, b"hello", &[listener.as_raw_fd()]).unwrap(),
+            5
+        );
+        let mut buf = [0u8; 5];
+        let (received, mut fds) = recv_with_fds(&right, &mut buf).unwrap();
+        assert_eq!((received, &buf), (5, b"hello"));
+        assert_eq!(fds.len(), 1);
+
+        // 关闭原 fd 后，接收到的副本仍然监听同一地址
+        drop(listener);
+        let inherited: std::net::TcpListener = fds.remove(0).into();
+        assert_eq!(inherited.local_addr().unwrap(), addr);
+        let _client = std::net::TcpStream::connect(addr).unwrap();
+        assert!(inherited.accept().is_ok());
+    }
+}
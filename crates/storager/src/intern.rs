@@ -0,0 +1,266 @@
+//! Fid 驻留 (interning) 模块
+//!
+//! 同一个 fid 往往出现在很多 keyword 下，在每个 posting 中都保存完整字符串
+//! 会浪费内存和证明字节。启用驻留后，Storager 在 RPC 边界把比紧凑 id 长的 fid
+//! 翻译成按驻留顺序编号的紧凑 id（见 [`common::fid_intern::compact_id`]）再写入 ADS，
+//! 查询时再翻译回真实 fid。
+//!
+//! 映射表按序号保存 fid，每个 fid 字符串只保存一份。映射表的 Merkle 根（摘要）写入 ADS 的
+//! 保留 keyword，查询证明附带结果中紧凑 id 的映射表条目（见 [`FidInterner::entries_for`]）。
+//! 持久化后端把映射表写入单独的列族，重启时截断到与 ADS 中摘要一致的长度。
+
+use common::fid_intern::{
+    compact_id, parse_compact_id, should_intern, TableEntry, FID_TABLE_KEYWORD,
+};
+use esa_rust::merkle_tree::{leaf_hash, Hash, MerkleTree};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use storage_backend::ColumnDb;
+
+/// Fid 驻留表：序号 <-> fid
+#[derive(Default)]
+pub struct FidInterner {
+    /// 按序号排列的已驻留 fid
+    fids: Vec<Arc<str>>,
+    ids: HashMap<Arc<str>, u64>,
+    /// 第 i 个叶子为 `leaf_hash(FID_TABLE_KEYWORD, fids[i])`
+    tree: MerkleTree,
+    /// 持久化映射表的列族（内存后端为 None）
+    db: Option<ColumnDb>,
+}
+
+impl FidInterner {
+    /// 创建空的驻留表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从列族载入映射表，之后新驻留的 fid 也写入该列族
+    pub fn open(mut db: ColumnDb) -> Result<Self, String> {
+        let mut interner = FidInterner::new();
+        for (key, value) in db.entries().map_err(|e| e.to_string())? {
+            let index = key
+                .try_into()
+                .map(u64::from_be_bytes)
+                .map_err(|_| "invalid fid table key".to_string())?;
+            if index != interner.fids.len() as u64 {
+                return Err(format!("fid table has a gap before entry {}", index));
+            }
+            let fid = String::from_utf8(value).map_err(|e| e.to_string())?;
+            interner.push(&fid);
+        }
+        interner.db = Some(db);
+        Ok(interner)
+    }
+
+    fn push(&mut self, fid: &str) -> u64 {
+        let index = self.fids.len() as u64;
+        let fid: Arc<str> = Arc::from(fid);
+        self.tree.insert(leaf_hash(FID_TABLE_KEYWORD, &fid));
+        self.ids.insert(fid.clone(), index);
+        self.fids.push(fid);
+        index
+    }
+
+    /// 驻留一个 fid，返回 (ADS 中保存的形式, 映射表是否新增了条目)
+    ///
+    /// 不比紧凑 id 长的 fid 原样保存，不进入映射表
+    pub fn intern(&mut self, fid: &str) -> Result<(String, bool), String> {
+        if let Some(&index) = self.ids.get(fid) {
+            return Ok((compact_id(index), false));
+        }
+        let index = self.fids.len() as u64;
+        let id = compact_id(index);
+        if !should_intern(fid, &id) {
+            return Ok((fid.to_string(), false));
+        }
+        if let Some(db) = &mut self.db {
+            db.put(&index.to_be_bytes(), fid.as_bytes())
+                .map_err(|e| e.to_string())?;
+        }
+        self.push(fid);
+        Ok((id, true))
+    }
+
+    /// 只保留前 `len` 个条目
+    pub fn truncate(&mut self, len: usize) -> Result<(), String> {
+        if len >= self.fids.len() {
+            return Ok(());
+        }
+        if let Some(db) = &mut self.db {
+            for index in len..self.fids.len() {
+                db.delete(&(index as u64).to_be_bytes())
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        for fid in self.fids.drain(len..) {
+            self.ids.remove(&fid);
+        }
+        self.tree = MerkleTree::from_leaves(self.tree.leaves()[..len].to_vec());
+        Ok(())
+    }
+
+    /// 截断到摘要等于 `committed` 的最长前缀（`committed` 为 None 时清空）
+    ///
+    /// 映射表先于 ADS 中的摘要写入，进程在两者之间退出时映射表会多出几个条目。
+    /// 没有前缀与摘要一致时不修改并返回 false
+    pub fn restore(&mut self, committed: Option<Hash>) -> Result<bool, String> {
+        let Some(committed) = committed else {
+            self.truncate(0)?;
+            return Ok(true);
+        };
+        let mut prefix = MerkleTree::new();
+        let mut matched = None;
+        for (len, leaf) in self.tree.leaves().iter().enumerate() {
+            prefix.insert(*leaf);
+            if prefix.root() == committed {
+                matched = Some(len + 1);
+            }
+        }
+        match matched {
+            Some(len) => self.truncate(len).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    /// 映射表摘要，映射表为空时返回 None
+    pub fn digest(&self) -> Option<Hash> {
+        (!self.fids.is_empty()).then(|| self.tree.root())
+    }
+
+    /// 所有已驻留 fid，按序号排列
+    pub fn entries(&self) -> Vec<String> {
+        self.fids.iter().map(|fid| fid.to_string()).collect()
+    }
+
+    /// ADS 中保存的 fid 列表里每个紧凑 id 的映射表条目（同一个 id 只取一次）
+    pub fn entries_for(&self, stored: &[String]) -> Vec<TableEntry> {
+        let mut seen = HashSet::new();
+        stored
+            .iter()
+            .filter_map(|stored| parse_compact_id(stored))
+            .filter(|index| seen.insert(*index))
+            .filter_map(|index| {
+                let fid = self.fids.get(index as usize)?;
+                let proof = self.tree.prove(index as usize)?;
+                Some(TableEntry {
+                    index,
+                    fid: fid.to_string(),
+                    siblings: proof.siblings,
+                })
+            })
+            .collect()
+    }
+
+    /// fid 在 ADS 中保存的形式：已驻留时为紧凑 id，否则为 fid 本身
+    pub fn lookup(&self, fid: &str) -> String {
+        match self.ids.get(fid) {
+            Some(&index) => compact_id(index),
+            None => fid.to_string(),
+        }
+    }
+
+    /// 根据紧凑 id 还原 fid
+    pub fn resolve(&self, id: &str) -> Option<&str> {
+        let index = parse_compact_id(id)?;
+        self.fids
+            .get(usize::try_from(index).ok()?)
+            .map(|fid| &**fid)
+    }
+
+    /// 将 ADS 返回的列表翻译回真实 fid
+    ///
+    /// 无法识别的条目原样返回（未驻留的短 fid，以及启用驻留前写入的数据）
+    pub fn translate_back(&self, encoded: Vec<String>) -> Vec<String> {
+        encoded
+            .into_iter()
+            .map(|e| match self.resolve(&e) {
+                Some(fid) => fid.to_string(),
+                None => e,
+            })
+            .collect()
+    }
+
+    /// 已驻留的 fid 数量
+    pub fn len(&self) -> usize {
+        self.fids.len()
+    }
+
+    /// 驻留表是否为空
+    pub fn is_empty(&self) -> bool {
+        self.fids.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use esa_rust::merkle_tree::MerkleProof;
+    use storage_backend::MemoryDatabase;
+
+    #[test]
+    fn test_intern_is_stable() {
+        let mut interner = FidInterner::new();
+        let (a, new_a) = interner.intern("file1").unwrap();
+        let (b, new_b) = interner.intern("file2").unwrap();
+        let (a2, new_a2) = interner.intern("file1").unwrap();
+
+        assert!(new_a && new_b && !new_a2);
+        assert_eq!(a, a2);
+        assert_eq!((a.as_str(), b.as_str()), ("~0", "~1"));
+        assert_eq!(interner.resolve(&b), Some("file2"));
+        assert_eq!(interner.lookup("file2"), b);
+        assert_eq!(interner.lookup("file3"), "file3");
+        assert_eq!(interner.len(), 2);
+
+        // 不比紧凑 id 长的 fid 原样保存
+        assert_eq!(interner.intern("f3").unwrap(), ("f3".to_string(), false));
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn test_translate_back() {
+        let mut interner = FidInterner::new();
+        let (id, _) = interner.intern("file1").unwrap();
+
+        assert_eq!(
+            interner.translate_back(vec![id.clone(), "raw".to_string(), "~9".to_string()]),
+            vec!["file1".to_string(), "raw".to_string(), "~9".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_entries_prove_against_digest() {
+        let mut interner = FidInterner::new();
+        for fid in ["file-a", "file-b", "file-c"] {
+            interner.intern(fid).unwrap();
+        }
+        let digest = interner.digest().unwrap();
+        let stored = ["~2", "raw", "~0", "~2"].map(String::from);
+
+        let entries = interner.entries_for(&stored);
+        assert_eq!(entries.len(), 2);
+        for entry in &entries {
+            let proof = MerkleProof {
+                index: entry.index,
+                siblings: entry.siblings.clone(),
+            };
+            assert!(proof.verify(&leaf_hash(FID_TABLE_KEYWORD, &entry.fid), &digest));
+        }
+    }
+
+    #[test]
+    fn test_restore_truncates_to_committed_digest() {
+        let mut interner = FidInterner::open(Box::new(MemoryDatabase::new())).unwrap();
+        interner.intern("file-a").unwrap();
+        let committed = interner.digest();
+        interner.intern("file-b").unwrap();
+
+        assert!(interner.restore(committed).unwrap());
+        assert_eq!(interner.entries(), vec!["file-a"]);
+        assert_eq!(interner.digest(), committed);
+        assert!(!interner.restore(Some([1; 32])).unwrap());
+        assert!(interner.restore(None).unwrap());
+        assert!(interner.is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+//! keyword 基数和查询频率统计
+//!
+//! 每个 storager（每个命名空间各一份）记录每个 keyword 下的 fid 数量和被查询的次数，
+//! 通过 `Stats` RPC 报告查询最多和 fid 最多的 keyword。Manager 汇总所有 storager 的报告，
+//! 找出查询量集中的热点 keyword 并建议增加副本（见 Manager 的 `AdminService.HotKeywords`）。
+//!
+//! fid 数量随成功的写入增减，每次查询时用 ADS 返回的实际结果校正，
+//! 因此从导出状态恢复后，在 keyword 第一次被查询之前可能偏小。统计只保存在内存中。
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// `Stats` 请求没有指定数量时每个排行列出的 keyword 数
+pub const DEFAULT_TOP_KEYWORDS: usize = 10;
+
+/// 单个 keyword 的计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeywordCounters {
+    /// keyword 下的 fid 数量
+    pub fids: u64,
+    /// 启动以来的查询次数
+    pub queries: u64,
+}
+
+/// 所有 keyword 的计数
+#[derive(Default)]
+pub struct KeywordStats {
+    counters: RwLock<HashMap<String, KeywordCounters>>,
+}
+
+impl KeywordStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录写入了一个 fid
+    pub fn record_add(&self, keyword: &str) {
+        let mut counters = self.counters.write().unwrap();
+        counters.entry(keyword.to_string()).or_default().fids += 1;
+    }
+
+    /// 记录删除了一个 fid
+    pub fn record_delete(&self, keyword: &str) {
+        let mut counters = self.counters.write().unwrap();
+        if let Some(entry) = counters.get_mut(keyword) {
+            entry.fids = entry.fids.saturating_sub(1);
+        }
+    }
+
+    /// 记录一次查询；`fids` 是查询得到的实际 fid 数量，未知时（布尔查询）为 None
+    pub fn record_query(&self, keyword: &str, fids: Option<usize>) {
+        let mut counters = self.counters.write().unwrap();
+        let entry = counters.entry(keyword.to_string()).or_default();
+        entry.queries += 1;
+        if let Some(fids) = fids {
+            entry.fids = fids as u64;
+        }
+    }
+
+    /// 直接设置 fid 数量（关键词迁移替换了整个 fid 列表）
+    pub fn set_fids(&self, keyword: &str, fids: usize) {
+        let mut counters = self.counters.write().unwrap();
+        counters.entry(keyword.to_string()).or_default().fids = fids as u64;
+    }
+
+    /// 单个 keyword 的计数
+    pub fn get(&self, keyword: &str) -> KeywordCounters {
+        let counters = self.counters.read().unwrap();
+        counters.get(keyword).copied().unwrap_or_default()
+    }
+
+    /// 汇总：(keyword 数, fid 总数, 查询总数)，不计 fid 数量为 0 且从未被查询的 keyword
+    pub fn totals(&self) -> (u64, u64, u64) {
+        let counters = self.counters.read().unwrap();
+        counters
+            .values()
+            .filter(|c| c.fids > 0 || c.queries > 0)
+            .fold((0, 0, 0), |(keywords, fids, queries), c| {
+                (keywords + 1, fids + c.fids, queries + c.queries)
+            })
+    }
+
+    /// 查询次数最多的 `n` 个 keyword（次数相同时按 keyword 排序），不含从未被查询的 keyword
+    pub fn hottest(&self, n: usize) -> Vec<(String, KeywordCounters)> {
+        self.top(n, |c| c.queries)
+    }
+
+    /// fid 最多的 `n` 个 keyword，不含空 keyword
+    pub fn largest(&self, n: usize) -> Vec<(String, KeywordCounters)> {
+        self.top(n, |c| c.fids)
+    }
+
+    fn top(
+        &self,
+        n: usize,
+        key: impl Fn(&KeywordCounters) -> u64,
+    ) -> Vec<(String, KeywordCounters)> {
+        let counters = self.counters.read().unwrap();
+        let mut entries: Vec<(String, KeywordCounters)> = counters
+            .iter()
+            .filter(|(_, c)| key(c) > 0)
+            .map(|(keyword, c)| (keyword.clone(), *c))
+            .collect();
+        entries.sort_by(|(ka, a), (kb, b)| key(b).cmp(&key(a)).then_with(|| ka.cmp(kb)));
+        entries.truncate(n);
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_follow_mutations_and_queries() {
+        let stats = KeywordStats::new();
+        stats.record_add("rust");
+        stats.record_add("rust");
+        stats.record_add("go");
+        stats.record_delete("go");
+        stats.record_delete("java");
+        assert_eq!(stats.get("rust").fids, 2);
+        assert_eq!(stats.get("go").fids, 0);
+        assert_eq!(stats.get("java"), KeywordCounters::default());
+
+        // 查询结果校正 fid 数量；布尔查询只计次数
+        stats.record_query("rust", Some(5));
+        stats.record_query("rust", None);
+        stats.record_query("go", Some(0));
+        assert_eq!(
+            stats.get("rust"),
+            KeywordCounters {
+                fids: 5,
+                queries: 2
+            }
+        );
+        assert_eq!(stats.totals(), (2, 5, 3));
+    }
+
+    #[test]
+    fn test_rankings() {
+        let stats = KeywordStats::new();
+        for (keyword, fids, queries) in [("a", 1, 3), ("b", 4, 3), ("c", 2, 9), ("d", 0, 0)] {
+            stats.set_fids(keyword, fids);
+            for _ in 0..queries {
+                stats.record_query(keyword, None);
+            }
+        }
+        let names = |entries: Vec<(String, KeywordCounters)>| {
+            entries.into_iter().map(|(k, _)| k).collect::<Vec<_>>()
+        };
+        assert_eq!(names(stats.hottest(2)), ["c", "a"]);
+        assert_eq!(names(stats.hottest(10)), ["c", "a", "b"]);
+        assert_eq!(names(stats.largest(10)), ["b", "c", "a"]);
+    }
+}
@@ -1,6 +1,19 @@
 pub mod ads;
+pub mod error;
+pub mod expiry;
+#[cfg(unix)]
+pub mod handover;
+pub mod intern;
+pub mod keyword_stats;
+pub mod namespace;
+pub mod proof_queue;
+pub mod request_log;
 pub mod service;
 pub mod storager;
 
 pub use ads::AdsOperations;
-pub use storager::Storager;
+pub use error::StoragerError;
+pub use intern::FidInterner;
+pub use keyword_stats::KeywordStats;
+pub use namespace::NamespaceAds;
+pub use storager::{CryptoHealth, DbBackend, Storager};
@@ -16,45 +16,412 @@
 //! # 指定 ADS 类型和端口
 //! cargo run --bin storager -- 50053 mpt
 //! cargo run --bin storager -- 50053 accumulator
+//! cargo run --bin storager -- 50053 --ads-mode=merkle
+//! cargo run --bin storager -- 50053 --ads-mode=smt
+//!
+//! # 从 SystemConfig JSON 读取 ADS 模式和 storager_addrs[1] 的监听/通告地址（命令行参数优先）
+//! cargo run --bin storager -- --config config.json --node 1
+//!
+//! # 每个参数都可以用环境变量设置（名称见 --help），命令行优先于环境变量
+//! DSS_PORT=50053 DSS_ADS_MODE=mpt DSS_DB_BACKEND=rocksdb DSS_DB_PATH=/var/lib/dss/storager-0 cargo run --bin storager
+//!
+//! # 把 ADS 状态持久化到 RocksDB（节点、检查点和 WAL 分别保存在各自的列族中），重启后恢复
+//! cargo run --bin storager -- 50053 mpt --db-backend=rocksdb --db-path=/var/lib/dss/storager-0
+//!
+//! # 没有 C++ 工具链时使用纯 Rust 的 sled 后端
+//! cargo run --bin storager --features sled -- 50053 mpt --db-backend=sled --db-path=/var/lib/dss/storager-0
+//!
+//! # 启用 fid 驻留
+//! cargo run --bin storager -- 50053 mpt --intern-fids
+//!
+//! # 启用后台分片修复（MPT 脏节点在后台按时间片修复）
+//! cargo run --bin storager -- 50053 mpt --background-fix
+//!
+//! # 每 5 秒删除到期（Add 请求的 ttl_seconds）的 keyword-fid 对，默认每秒一次，0 关闭
+//! cargo run --bin storager -- 50053 mpt --expiry-sweep-interval=5
+//!
+//! # 在 4 个线程的专用线程池中执行 ADS 运算（默认使用与 CPU 核数相同的共享线程池）
+//! cargo run --bin storager -- 50053 accumulator --ads-threads=4
+//!
+//! # 同时监听 IPv4 和 IPv6，并通告容器外部可达的地址
+//! cargo run --bin storager -- 50053 mpt --listen=0.0.0.0,:: --advertise=http://10.0.0.5:50053
+//!
+//! # 与 Manager 部署在同一主机时监听 Unix domain socket
+//! cargo run --bin storager -- 50053 mpt --listen=unix:/run/dss/storager-0.sock
+//!
+//! # 使用自定义累加器参数文件（默认使用内置参数）
+//! cargo run --bin storager -- 50053 accumulator --crypto-params=/etc/dss/acc.params
+//!
+//! # 使用可信设置生成的公开参数（默认使用由内置陷门生成的开发用参数）
+//! cargo run -p esa_rust --bin acc-setup -- generate --max-degree 4096 --output /etc/dss/acc.pp
+//! cargo run --bin storager -- 50053 accumulator --public-params=/etc/dss/acc.pp
+//!
+//! # 零停机升级：旧进程在控制 socket 上等待交接，新版本接管监听 socket 和状态
+//! cargo run --bin storager -- 50053 mpt --handover=/run/dss/storager-0.ctl
+//! ./storager-new 50053 mpt --takeover=/run/dss/storager-0.ctl --handover=/run/dss/storager-0.ctl
+//!
+//! # 放宽消息大小上限（MiB，默认 64）、压缩响应、开启 keepalive（秒）
+//! cargo run --bin storager -- 50053 accumulator --max-message-mib=256 --compression=zstd --keepalive=30
+//!
+//! # 双向 TLS：只接受出示由 CA 签发证书的客户端（Manager 和迁移时的其他 storager）
+//! cargo run --bin storager -- 50053 mpt --tls-cert=/etc/dss/storager-0.pem --tls-key=/etc/dss/storager-0.key \
+//!     --tls-ca=/etc/dss/ca.pem --tls-client-auth --advertise=https://storager-0:50053
+//!
+//! # 调整日志级别（默认读取 RUST_LOG，否则为 info），输出 JSON 格式的日志
+//! cargo run --bin storager -- 50053 mpt --log-level=debug --log-json
+//!
+//! # 追踪：证明生成的 span 挂在 Manager 传来的 trace 下，以 --features otlp 构建时导出
+//! OTEL_EXPORTER_OTLP_ENDPOINT=http://localhost:4317 cargo run --features otlp --bin storager -- 50053 mpt
+//!
+//! # 收到 SIGINT / SIGTERM 后最多等待 60 秒让进行中的请求完成（默认 30 秒）
+//! cargo run --bin storager -- 50053 mpt --db-backend=rocksdb --db-path=/var/lib/dss/storager-0 --drain-timeout=60
 //! ```
+//!
+//! 收到 SIGINT 或 SIGTERM 时停止接受新请求，等待进行中的请求（最多 `--drain-timeout` 秒）
+//! 和推迟生成的证明完成后，完成 MPT 待修复的工作并把所有命名空间落盘，然后退出。
+//! 落盘期间再次收到信号时不再等待，直接退出。
+//!
+//! 累加器参数初始化失败时进程不会退出：Storager 继续运行但拒绝 ADS 请求，
+//! 并通过 Health RPC 报告原因，Manager 据此停止向该节点路由。
+//!
+//! 交接协议见 [`storager::handover`]。接管方的 ADS 类型和 `--intern-fids`
+//! 必须与旧进程一致，`--listen` 被忽略（使用继承的监听 socket）。
+//!
+//! 持久化后端把 fid 驻留表保存在数据目录的 `fid_table` 子目录中，重启后与 ADS 一起恢复。
+//! 密码学累加器的证明中是驻留编号的元素，Manager 无法用请求中的 fid 核对，
+//! 因此 `--intern-fids` 也不能与 crypto_accumulator 模式同时使用。
+
+use clap::Parser;
+use common::cli::{parse_ads_mode, LogArgs, TransportArgs};
+use common::net::{
+    serve_with_drain, shutdown_signal, validate_address, ListenConfig, Listeners,
+    DEFAULT_DRAIN_SECS,
+};
+use common::telemetry::{init_tracing, Traced};
+use common::{AdsMode, SystemConfig};
+use esa_rust::crypto_accumulator::{init_params, init_public_params};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use storager::ads::AdsPool;
+use storager::handover::{serve_handover, take_over};
+use storager::{CryptoHealth, DbBackend, Storager};
+use tracing::{error, info, warn};
+
+/// 未指定端口和配置文件时的监听端口
+const DEFAULT_PORT: u16 = 50052;
+
+fn parse_advertise(addr: &str) -> Result<String, String> {
+    validate_address(addr)?;
+    Ok(addr.to_string())
+}
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "storager",
+    about = "Storager Server - Distributed Storage System",
+    after_help = "Every option can also be set through the environment variable shown next to it."
+)]
+struct Cli {
+    /// Server port [default: 50052]
+    #[arg(env = "DSS_PORT")]
+    port: Option<u16>,
+
+    /// ADS mode: accumulator|mpt|merkle|smt, same as --ads-mode [default: accumulator]
+    #[arg(value_name = "ADS_MODE", value_parser = parse_ads_mode)]
+    mode: Option<AdsMode>,
+
+    /// ADS mode, takes precedence over the positional ADS_MODE
+    #[arg(long, env = "DSS_ADS_MODE", value_name = "MODE", value_parser = parse_ads_mode)]
+    ads_mode: Option<AdsMode>,
+
+    /// SystemConfig JSON providing the ADS mode and this node's listen/advertise addresses
+    #[arg(long, env = "DSS_CONFIG", value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Index of this storager in the storager_addrs of --config
+    #[arg(
+        long,
+        env = "DSS_NODE",
+        value_name = "INDEX",
+        default_value_t = 0,
+        requires = "config"
+    )]
+    node: usize,
+
+    /// Comma-separated listen addresses, ip:port or unix:/path [default: [::1]:PORT]
+    #[arg(long, env = "DSS_LISTEN", value_name = "ADDRS")]
+    listen: Option<String>,
+
+    /// Address advertised to the manager and other storagers
+    #[arg(long, env = "DSS_ADVERTISE", value_name = "URL", value_parser = parse_advertise)]
+    advertise: Option<String>,
 
-use common::rpc::storager_service_server::StoragerServiceServer;
-use storager::Storager;
-use tonic::transport::Server;
+    /// Database backend: memory|rocksdb|sled
+    #[arg(
+        long,
+        env = "DSS_DB_BACKEND",
+        value_name = "NAME",
+        default_value = "memory"
+    )]
+    db_backend: String,
+
+    /// Data directory of a persistent --db-backend
+    #[arg(long, env = "DSS_DB_PATH", value_name = "DIR")]
+    db_path: Option<String>,
+
+    /// Store fids longer than their compact id in the ADS as sequence-numbered compact ids
+    #[arg(long, env = "DSS_INTERN_FIDS")]
+    intern_fids: bool,
+
+    /// Repair dirty MPT nodes in background time slices
+    #[arg(long, env = "DSS_BACKGROUND_FIX")]
+    background_fix: bool,
+
+    /// Seconds between deletions of expired keyword-fid pairs, 0 disables
+    #[arg(
+        long,
+        env = "DSS_EXPIRY_SWEEP_INTERVAL",
+        value_name = "SECS",
+        default_value_t = 1
+    )]
+    expiry_sweep_interval: u64,
+
+    /// Threads of a dedicated ADS pool (default: the shared pool, one thread per core)
+    #[arg(long, env = "DSS_ADS_THREADS", value_name = "N")]
+    ads_threads: Option<usize>,
+
+    /// Accumulator parameter file (default: built-in parameters)
+    #[arg(long, env = "DSS_CRYPTO_PARAMS", value_name = "PATH")]
+    crypto_params: Option<PathBuf>,
+
+    /// Accumulator public parameters from a trusted setup (default: development parameters)
+    #[arg(long, env = "DSS_PUBLIC_PARAMS", value_name = "PATH")]
+    public_params: Option<PathBuf>,
+
+    /// Take over the listening sockets and state of the process serving this control socket
+    #[arg(long, value_name = "PATH")]
+    takeover: Option<PathBuf>,
+
+    /// Wait on this control socket for a new process to take over, then exit
+    #[arg(long, env = "DSS_HANDOVER", value_name = "PATH")]
+    handover: Option<PathBuf>,
+
+    /// Seconds to wait for in-flight requests after SIGINT/SIGTERM before closing connections
+    #[arg(long, env = "DSS_DRAIN_TIMEOUT", value_name = "SECS", default_value_t = DEFAULT_DRAIN_SECS)]
+    drain_timeout: u64,
+
+    #[command(flatten)]
+    transport: TransportArgs,
+
+    #[command(flatten)]
+    log: LogArgs,
+}
+
+impl Cli {
+    /// 命令行和环境变量覆盖配置文件，两者都没有时使用默认值
+    fn resolve(&self) -> Result<(AdsMode, ListenConfig), String> {
+        let config = self.config.as_ref().map(SystemConfig::load).transpose()?;
+
+        let ads_mode = self
+            .ads_mode
+            .or(self.mode)
+            .or(config.as_ref().map(|c| c.ads_mode))
+            .unwrap_or(AdsMode::CryptoAccumulator);
+        let port = self.port.unwrap_or(DEFAULT_PORT);
+        let mut listen = match (&self.listen, &config) {
+            (Some(spec), _) => ListenConfig::parse(spec, port)?,
+            (None, Some(config)) if self.port.is_none() => config.storager_listen(self.node)?,
+            (None, _) => ListenConfig::localhost(port),
+        };
+        if let Some(advertise) = &self.advertise {
+            listen = listen.with_advertise(advertise.clone());
+        }
+        Ok((ads_mode, listen))
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // 解析命令行参数
-    let args: Vec<String> = std::env::args().collect();
-
-    // 第一个参数：端口号（默认 50052）
-    let port = if args.len() > 1 {
-        args[1].parse::<u16>().unwrap_or(50052)
-    } else {
-        50052
-    };
+    let cli = Cli::parse();
+    init_tracing("storager", &cli.log.config())?;
 
-    // 第二个参数：ADS 类型（默认 accumulator）
-    let ads_type = if args.len() > 2 {
-        args[2].as_str()
-    } else {
-        "accumulator"
-    };
+    let (ads_mode, listen) = cli.resolve()?;
+    let ads_type = ads_mode.name();
+    let intern_fids = cli.intern_fids;
+
+    let backend = DbBackend::parse(&cli.db_backend, cli.db_path.as_deref())?;
+    if intern_fids && ads_mode == AdsMode::CryptoAccumulator {
+        return Err("--intern-fids is not supported with the crypto_accumulator ADS mode".into());
+    }
 
-    let addr = format!("[::1]:{}", port).parse()?;
+    let crypto_health = (ads_mode == AdsMode::CryptoAccumulator).then(|| {
+        let initialized = init_public_params(cli.public_params.as_deref())
+            .and_then(|_| init_params(cli.crypto_params.as_deref()));
+        match initialized {
+            Ok(_) => CryptoHealth::Ready,
+            Err(e) => {
+                error!(
+                    "Crypto accumulator initialization failed: {}. \
+                     ADS requests will be rejected until the storager is restarted \
+                     with valid parameters.",
+                    e
+                );
+                CryptoHealth::Failed(e.to_string())
+            }
+        }
+    });
 
     // 根据配置创建 Storager 实例
-    let storager = Storager::from_config(ads_type);
+    // 恢复累加器状态需要已初始化的参数，参数不可用时不打开数据库（ADS 请求反正会被拒绝）
+    let mut storager = match &crypto_health {
+        Some(CryptoHealth::Failed(_)) => Storager::from_config(ads_type),
+        _ => Storager::open(ads_type, &backend)?,
+    };
+    if intern_fids {
+        storager = storager.with_fid_interning()?;
+    }
+    if let Some(health) = crypto_health {
+        storager = storager.with_crypto_health(health);
+    }
+    if let Some(threads) = cli.ads_threads {
+        storager = storager.with_ads_pool(AdsPool::with_threads(threads)?);
+    }
+    let transport = cli.transport.config()?;
+    let tls_client_auth = cli.transport.tls_client_auth;
+    let server = transport.server()?;
+    storager = storager.with_transport(transport.clone());
+    if cli.background_fix {
+        storager.spawn_background_fix(Duration::from_millis(50), Duration::from_millis(5));
+    }
+    if cli.expiry_sweep_interval > 0 {
+        storager.spawn_expiry_sweeper(Duration::from_secs(cli.expiry_sweep_interval));
+    }
+
+    // 从旧进程接管监听 socket 和状态
+    let (listeners, takeover) = match &cli.takeover {
+        Some(control) => {
+            let mut takeover = take_over(control)?;
+            storager.import_state(&takeover.state)?;
+            info!(
+                "Took over {} listening socket(s) and {} bytes of state from {}",
+                takeover.listeners.len(),
+                takeover.state.len(),
+                control.display()
+            );
+            (std::mem::take(&mut takeover.listeners), Some(takeover))
+        }
+        None => (Listeners::bind(&listen)?, None),
+    };
 
-    println!(
-        "🚀 Storager server listening on {} (ADS: {})",
-        addr, ads_type
+    info!(
+        "Storager server listening on {:?} {:?}, advertised as {} (ADS: {}, fid interning: {}, db: {:?})",
+        listen.bind_addrs,
+        listen.unix_paths,
+        listen.advertise_url(),
+        ads_type,
+        intern_fids,
+        backend
     );
+    if transport.tls.is_some() {
+        info!(
+            "TLS: on (client certificates required: {})",
+            tls_client_auth
+        );
+    }
+
+    // 在控制 socket 上等待新进程接管，交接完成后退出
+    let storager = Arc::new(storager);
+    let handover = match cli.handover {
+        Some(control) => Some((control, listeners.try_clone()?)),
+        None => None,
+    };
+    let handed_over = {
+        let storager = storager.clone();
+        async move {
+            let Some((control, listeners)) = handover else {
+                return std::future::pending().await;
+            };
+            match serve_handover(&control, storager, listeners).await {
+                Ok(()) => info!("Handed over to the new process, draining connections"),
+                Err(e) => {
+                    error!("Handover control socket failed: {}", e);
+                    std::future::pending().await
+                }
+            }
+        }
+    };
+    // 交接后状态归新进程所有，只有收到信号退出时才由本进程落盘
+    let (stopped_tx, stopped_rx) = tokio::sync::oneshot::channel();
+    let shutdown = async move {
+        tokio::select! {
+            _ = handed_over => {}
+            signal = shutdown_signal() => {
+                info!("Received {}, draining in-flight requests", signal);
+                let _ = stopped_tx.send(());
+            }
+        }
+    };
+
+    let service = Traced::new(transport.storager_server(storager.clone()));
+    if let Some(takeover) = takeover {
+        takeover.ready()?;
+    }
+    let drain = Duration::from_secs(cli.drain_timeout);
+    serve_with_drain(
+        listeners,
+        || server.clone().add_service(service.clone()),
+        shutdown,
+        drain,
+    )
+    .await?;
 
-    Server::builder()
-        .add_service(StoragerServiceServer::new(storager))
-        .serve(addr)
-        .await?;
+    if stopped_rx.await.is_ok() {
+        tokio::select! {
+            flushed = storager.shutdown() => match flushed {
+                Ok(namespaces) => info!("Flushed {} namespace(s), exiting", namespaces),
+                Err(e) => {
+                    error!("Failed to flush state on shutdown: {}", e);
+                    return Err(e.into());
+                }
+            },
+            signal = shutdown_signal() => {
+                warn!("Received {} again, exiting without flushing", signal);
+            }
+        }
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_positional_and_flag_forms() {
+        let cli = Cli::try_parse_from(["storager", "50053", "mpt", "--background-fix"]).unwrap();
+        let (mode, listen) = cli.resolve().unwrap();
+        assert_eq!(mode, AdsMode::Mpt);
+        assert_eq!(listen, ListenConfig::localhost(50053));
+        assert!(cli.background_fix);
+
+        // --ads-mode 优先于位置参数
+        let cli = Cli::try_parse_from(["storager", "50053", "mpt", "--ads-mode=smt"]).unwrap();
+        assert_eq!(cli.resolve().unwrap().0, AdsMode::SparseMerkleTree);
+
+        for args in [
+            &["storager", "port"][..],
+            &["storager", "50053", "btree"],
+            &["storager", "--node", "1"],
+        ] {
+            assert!(Cli::try_parse_from(args).is_err(), "{:?}", args);
+        }
+    }
+}
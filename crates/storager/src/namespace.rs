@@ -0,0 +1,146 @@
+//! storager 上的命名空间
+//!
+//! 每个命名空间是一个独立的 [`Storager`]：自己的 ADS、fid 驻留表、草图、版本号和延迟证明队列，
+//! 与默认命名空间共享线程池、时间源、冻结状态和密码学健康状态。
+//! 默认命名空间（空名称）就是 Storager 本身；其他命名空间在第一次被请求时创建，
+//! 持久化后端把它们保存在数据目录的 `namespaces/<名称>` 子目录中，重启后按需重新打开。
+
+use crate::ads::AdsOperations;
+use crate::error::StoragerError;
+use crate::storager::Storager;
+use common::validate_namespace;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, RwLock};
+use tracing::info;
+
+/// 为命名空间创建 ADS 实例，参数是命名空间名称
+pub type NamespaceAds = Arc<dyn Fn(&str) -> Result<Box<dyn AdsOperations>, String> + Send + Sync>;
+
+/// 已创建的命名空间
+#[derive(Default)]
+pub(crate) struct Namespaces {
+    /// 为新命名空间创建 ADS；None 时只有默认命名空间
+    create: Option<NamespaceAds>,
+    /// 数据目录中已有、尚未打开的命名空间
+    on_disk: BTreeSet<String>,
+    opened: RwLock<BTreeMap<String, Storager>>,
+}
+
+impl Namespaces {
+    pub(crate) fn new(create: NamespaceAds, on_disk: BTreeSet<String>) -> Self {
+        Namespaces {
+            create: Some(create),
+            on_disk,
+            opened: RwLock::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl Storager {
+    /// 命名空间对应的 Storager，不存在时创建；空名称返回默认命名空间（自身的克隆）
+    pub fn namespace(&self, name: &str) -> Result<Storager, StoragerError> {
+        if name.is_empty() {
+            return Ok(self.clone());
+        }
+        if let Some(storager) = self.namespaces.opened.read().unwrap().get(name) {
+            return Ok(storager.clone());
+        }
+        validate_namespace(name).map_err(StoragerError::InvalidRequest)?;
+        let create = self
+            .namespaces
+            .create
+            .as_ref()
+            .ok_or(StoragerError::Unsupported("namespaces"))?;
+
+        let mut opened = self.namespaces.opened.write().unwrap();
+        if let Some(storager) = opened.get(name) {
+            return Ok(storager.clone());
+        }
+        let ads = create(name).map_err(StoragerError::Precondition)?;
+        let storager = self
+            .namespace_instance(name, ads)
+            .map_err(StoragerError::Precondition)?;
+        opened.insert(name.to_string(), storager.clone());
+        info!("Opened namespace '{}'", name);
+        Ok(storager)
+    }
+
+    /// 除默认命名空间外的所有命名空间（包括尚未打开的），按字典序排列
+    pub fn namespaces(&self) -> Vec<String> {
+        let opened = self.namespaces.opened.read().unwrap();
+        let names: BTreeSet<&String> = opened.keys().chain(&self.namespaces.on_disk).collect();
+        names.into_iter().cloned().collect()
+    }
+
+    /// 已打开的命名空间
+    pub(crate) fn opened_namespaces(&self) -> Vec<(String, Storager)> {
+        let opened = self.namespaces.opened.read().unwrap();
+        opened
+            .iter()
+            .map(|(name, storager)| (name.clone(), storager.clone()))
+            .collect()
+    }
+
+    /// 请求指定了其他命名空间时返回该命名空间的 Storager，并清空请求中的名称，
+    /// 这样处理函数可以把请求原样转交给它
+    pub(crate) fn route_namespace(
+        &self,
+        namespace: &mut String,
+    ) -> Result<Option<Storager>, StoragerError> {
+        if namespace.is_empty() {
+            return Ok(None);
+        }
+        let storager = self.namespace(namespace)?;
+        namespace.clear();
+        Ok(Some(storager))
+    }
+
+    /// 新命名空间的实例，沿用默认命名空间的运行配置
+    fn namespace_instance(
+        &self,
+        name: &str,
+        ads: Box<dyn AdsOperations>,
+    ) -> Result<Storager, String> {
+        let mut storager = Storager::with_ads(ads);
+        storager.backend = self.backend.for_namespace(name);
+        if self.interner.is_some() {
+            storager.interner = Some(Arc::new(RwLock::new(storager.load_interner()?)));
+        }
+        storager.fix_metrics = self.fix_metrics.clone();
+        storager.clock = self.clock.clone();
+        storager.crypto_health = self.crypto_health.clone();
+        storager.frozen = self.frozen.clone();
+        storager.pool = self.pool.clone();
+        storager.transport = self.transport.clone();
+        Ok(storager)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespaces_are_isolated() {
+        let storager = Storager::with_mpt();
+        let tenant = storager.namespace("tenant-a").unwrap();
+        tenant.write_ads().unwrap().add("rust", "f1").unwrap();
+
+        assert!(storager.ads.read().unwrap().query("rust").0.is_empty());
+        assert_eq!(tenant.ads.read().unwrap().query("rust").0, vec!["f1"]);
+        assert_ne!(
+            storager.ads.read().unwrap().root_hash(),
+            tenant.ads.read().unwrap().root_hash()
+        );
+
+        // 再次获取得到同一个实例
+        let again = storager.namespace("tenant-a").unwrap();
+        assert_eq!(again.ads.read().unwrap().query("rust").0, vec!["f1"]);
+        assert_eq!(storager.namespaces(), vec!["tenant-a"]);
+
+        assert!(storager.namespace("../escape").is_err());
+        assert!(Storager::with_ads(Box::new(crate::ads::MptAds::new()))
+            .namespace("tenant-a")
+            .is_err());
+    }
+}
@@ -0,0 +1,276 @@
+//! 后台证明生成队列
+//!
+//! 写请求设置 `defer_proof` 时，Storager 更新 ADS 后立即返回新的根哈希和一个证明句柄，
+//! 证明（累加器的配对检查等）在 ADS 线程池中生成。客户端用 GetProof RPC 按句柄取回证明，
+//! 证明尚未生成时等待它完成。
+//!
+//! 尚未取回的句柄最多保留 [`DEFAULT_MAX_UNFETCHED`] 个，超出时淘汰最早分配的，
+//! 从不取回证明的写请求不会让内存无限增长；Manager 在异步确认的结算中立即取回证明，
+//! 取回时句柄已被淘汰则把审计记录标记为 Rejected。
+//! 句柄只保存在内存中，storager 重启之前没有取回的证明会丢失。
+//! 取回后句柄仍然保留，客户端可以重试；已取回的句柄超过 [`DEFAULT_CAPACITY`] 个时
+//! 淘汰最早的。淘汰后的句柄无法再取回，GetProof 返回 [`ProofError::UnknownHandle`]。
+
+use crate::ads::{AdsPool, ProofJob};
+use common::Proof;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::sync::watch;
+use tracing::warn;
+
+/// 默认保留的句柄数
+pub const DEFAULT_CAPACITY: usize = 4096;
+
+/// 默认最多保留的未取回句柄数
+pub const DEFAULT_MAX_UNFETCHED: usize = 65536;
+
+/// 写操作的证明：已经生成，或等待提交给队列
+pub enum PendingProof {
+    Ready(Proof),
+    Deferred(ProofJob),
+}
+
+/// 按句柄取回证明失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProofError {
+    /// 句柄从未分配过，或已被淘汰
+    UnknownHandle(u64),
+    /// 生成证明的任务没有完成（panic）
+    Failed(u64),
+}
+
+impl std::fmt::Display for ProofError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProofError::UnknownHandle(handle) => write!(f, "unknown proof handle {}", handle),
+            ProofError::Failed(handle) => write!(f, "proof {} could not be generated", handle),
+        }
+    }
+}
+
+impl std::error::Error for ProofError {}
+
+/// 等待生成的证明，见[模块文档](self)
+pub struct ProofQueue {
+    /// 最近分配的句柄，0 表示没有句柄
+    last_handle: AtomicU64,
+    proofs: Mutex<Handles>,
+    capacity: usize,
+    max_unfetched: usize,
+}
+
+/// 仍可取回的句柄
+#[derive(Default)]
+struct Handles {
+    /// 句柄 -> 生成完成后为 `Some` 的证明；句柄递增，最小的即最早的
+    proofs: BTreeMap<u64, watch::Receiver<Option<Proof>>>,
+    /// 已经取回过的句柄
+    fetched: BTreeSet<u64>,
+    /// 还没有取回过的句柄
+    unfetched: BTreeSet<u64>,
+}
+
+impl Default for ProofQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl ProofQueue {
+    /// 句柄总数超过 `capacity`（至少为 1）时淘汰已取回的句柄，
+    /// 未取回的句柄最多保留 [`DEFAULT_MAX_UNFETCHED`] 个
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            last_handle: AtomicU64::new(0),
+            proofs: Mutex::new(Handles::default()),
+            capacity: capacity.max(1),
+            max_unfetched: DEFAULT_MAX_UNFETCHED,
+        }
+    }
+
+    /// 设置最多保留的未取回句柄数（至少为 1）
+    pub fn with_max_unfetched(mut self, max_unfetched: usize) -> Self {
+        self.max_unfetched = max_unfetched.max(1);
+        self
+    }
+
+    /// 在 `pool` 中执行 `job`，返回取回证明的句柄
+    ///
+    /// 必须在 Tokio 运行时中调用
+    pub fn submit(&self, pool: &AdsPool, job: ProofJob) -> u64 {
+        let handle = self.last_handle.fetch_add(1, Ordering::SeqCst) + 1;
+        let (tx, rx) = watch::channel(None);
+        {
+            let mut handles = self.proofs.lock().unwrap();
+            handles.proofs.insert(handle, rx);
+            handles.unfetched.insert(handle);
+            while handles.unfetched.len() > self.max_unfetched {
+                let oldest = handles.unfetched.pop_first().expect("over the limit");
+                handles.proofs.remove(&oldest);
+                warn!("Evicted proof handle {} before it was fetched", oldest);
+            }
+            while handles.proofs.len() > self.capacity {
+                let Some(oldest) = handles.fetched.pop_first() else {
+                    break;
+                };
+                handles.proofs.remove(&oldest);
+            }
+        }
+
+        let pool = pool.clone();
+        tokio::spawn(async move {
+            let proof = pool.run(job).await;
+            // 句柄在生成期间被淘汰时没有接收方，丢弃即可
+            let _ = tx.send(Some(proof));
+        });
+        handle
+    }
+
+    /// 已生成的证明原样返回（句柄为 0），推迟的证明提交到队列后返回句柄
+    pub fn resolve(&self, pool: &AdsPool, pending: PendingProof) -> (Option<Proof>, u64) {
+        match pending {
+            PendingProof::Ready(proof) => (Some(proof), 0),
+            PendingProof::Deferred(job) => (None, self.submit(pool, job)),
+        }
+    }
+
+    /// 等待句柄对应的证明生成完成
+    pub async fn get(&self, handle: u64) -> Result<Proof, ProofError> {
+        let mut rx = {
+            let mut handles = self.proofs.lock().unwrap();
+            let rx = handles
+                .proofs
+                .get(&handle)
+                .cloned()
+                .ok_or(ProofError::UnknownHandle(handle))?;
+            if handles.unfetched.remove(&handle) {
+                handles.fetched.insert(handle);
+            }
+            rx
+        };
+        let proof = rx
+            .wait_for(Option::is_some)
+            .await
+            .map_err(|_| ProofError::Failed(handle))?;
+        Ok(proof.clone().expect("waited for Some"))
+    }
+
+    /// 等待所有仍可取回的证明生成完成（关闭之前调用）
+    ///
+    /// 已被淘汰的句柄无法再取回，不等待它们
+    pub async fn drain(&self) {
+        let pending: Vec<_> = self
+            .proofs
+            .lock()
+            .unwrap()
+            .proofs
+            .values()
+            .cloned()
+            .collect();
+        for mut rx in pending {
+            let _ = rx.wait_for(Option::is_some).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn job(data: u8, delay: Duration) -> ProofJob {
+        Box::new(move || {
+            std::thread::sleep(delay);
+            Proof::Merkle(vec![data])
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_waits_for_the_proof() {
+        let queue = ProofQueue::new(2);
+        let pool = AdsPool::with_threads(1).unwrap();
+
+        let slow = queue.submit(&pool, job(1, Duration::from_millis(100)));
+        let fast = queue.submit(&pool, job(2, Duration::ZERO));
+        assert_eq!((slow, fast), (1, 2));
+        assert_eq!(queue.get(fast).await.unwrap().data(), &[2]);
+        assert_eq!(queue.get(slow).await.unwrap().data(), &[1]);
+        // 取回后仍可重试
+        assert_eq!(queue.get(slow).await.unwrap().data(), &[1]);
+
+        // 超出容量时最早取回的句柄被淘汰
+        let newest = queue.submit(&pool, job(3, Duration::ZERO));
+        assert_eq!(queue.get(slow).await, Err(ProofError::UnknownHandle(slow)));
+        assert_eq!(queue.get(newest).await.unwrap().data(), &[3]);
+        assert_eq!(queue.get(0).await, Err(ProofError::UnknownHandle(0)));
+    }
+
+    #[tokio::test]
+    async fn test_unfetched_handles_are_kept() {
+        let queue = ProofQueue::new(2);
+        let pool = AdsPool::with_threads(1).unwrap();
+        let handles: Vec<u64> = (0..5)
+            .map(|i| queue.submit(&pool, job(i, Duration::ZERO)))
+            .collect();
+        // 超出容量时没有取回过的句柄可淘汰，全部保留
+        for (i, &handle) in handles.iter().enumerate() {
+            assert_eq!(queue.get(handle).await.unwrap().data(), &[i as u8]);
+        }
+        // 全部取回后，新句柄把已取回的句柄淘汰到容量以内
+        let newest = queue.submit(&pool, job(5, Duration::ZERO));
+        assert_eq!(queue.proofs.lock().unwrap().proofs.len(), 2);
+        assert_eq!(
+            queue.get(handles[3]).await,
+            Err(ProofError::UnknownHandle(handles[3]))
+        );
+        assert_eq!(queue.get(handles[4]).await.unwrap().data(), &[4]);
+        assert_eq!(queue.get(newest).await.unwrap().data(), &[5]);
+    }
+
+    #[tokio::test]
+    async fn test_unfetched_handles_are_capped() {
+        let queue = ProofQueue::new(8).with_max_unfetched(2);
+        let pool = AdsPool::with_threads(1).unwrap();
+        let first = queue.submit(&pool, job(0, Duration::ZERO));
+        assert_eq!(queue.get(first).await.unwrap().data(), &[0]);
+        let handles: Vec<u64> = (1..4)
+            .map(|i| queue.submit(&pool, job(i, Duration::ZERO)))
+            .collect();
+
+        // 超出上限时淘汰最早的未取回句柄，已取回的句柄不受影响
+        assert_eq!(
+            queue.get(handles[0]).await,
+            Err(ProofError::UnknownHandle(handles[0]))
+        );
+        assert_eq!(queue.get(first).await.unwrap().data(), &[0]);
+        assert_eq!(queue.get(handles[1]).await.unwrap().data(), &[2]);
+        assert_eq!(queue.get(handles[2]).await.unwrap().data(), &[3]);
+        assert_eq!(queue.proofs.lock().unwrap().unfetched.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_panicking_job_fails_the_handle() {
+        let queue = ProofQueue::default();
+        let pool = AdsPool::with_threads(1).unwrap();
+        let handle = queue.submit(&pool, Box::new(|| panic!("bad proof")));
+        assert_eq!(queue.get(handle).await, Err(ProofError::Failed(handle)));
+        // 失败的任务不会让关闭一直等待
+        queue.drain().await;
+    }
+
+    #[tokio::test]
+    async fn test_drain_waits_for_pending_jobs() {
+        let queue = ProofQueue::default();
+        let pool = AdsPool::with_threads(2).unwrap();
+        let handles: Vec<u64> = (0..3)
+            .map(|i| queue.submit(&pool, job(i, Duration::from_millis(50))))
+            .collect();
+
+        queue.drain().await;
+        for handle in handles {
+            let rx = queue.proofs.lock().unwrap().proofs[&handle].clone();
+            assert!(rx.borrow().is_some());
+        }
+    }
+}
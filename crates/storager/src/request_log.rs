@@ -0,0 +1,184 @@
+//! 写请求去重
+//!
+//! Manager 重试写请求时沿用同一个 `request_id`。第一次执行已经应用到 ADS、但响应在路上丢失
+//! （超时、连接断开）时，重试拿到第一次执行的结果而不会再次应用变更；第一次执行尚未结束时，
+//! 重试等待它完成。执行失败的请求不留下记录，重试会重新执行。
+//!
+//! 只保留最近 [`DEFAULT_CAPACITY`] 个请求 id，更早的请求再次到达时会被当作新请求执行。
+
+use common::rpc::Proof;
+use common::RootHash;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tokio::sync::watch;
+
+/// 默认保留的请求 id 数
+pub const DEFAULT_CAPACITY: usize = 4096;
+
+/// 一次写请求的响应内容（Add、BatchAdd、Delete 的响应字段相同）
+#[derive(Debug, Clone, PartialEq)]
+pub struct MutationOutcome {
+    pub proof: Option<Proof>,
+    pub root_hash: RootHash,
+    pub epoch: u64,
+    pub proof_handle: u64,
+    /// 添加后每个 keyword 草图的叶子哈希，删除时为空
+    pub sketch_digests: Vec<RootHash>,
+}
+
+type OutcomeReceiver = watch::Receiver<Option<MutationOutcome>>;
+
+/// 请求 id 第一次出现，还是重复出现
+pub enum Claim<'a> {
+    /// 由调用方执行，完成后调用 [`Slot::complete`]
+    First(Slot<'a>),
+    /// 已有相同 id 的执行，用 [`RequestLog::wait`] 等待它的结果
+    Duplicate(OutcomeReceiver),
+}
+
+/// 最近写请求的结果，见[模块文档](self)
+pub struct RequestLog {
+    inner: Mutex<Entries>,
+    capacity: usize,
+}
+
+#[derive(Default)]
+struct Entries {
+    outcomes: HashMap<String, OutcomeReceiver>,
+    /// 请求 id 按到达顺序排列，用于淘汰最早的记录
+    order: VecDeque<String>,
+}
+
+impl Default for RequestLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl RequestLog {
+    pub fn new(capacity: usize) -> Self {
+        RequestLog {
+            inner: Mutex::new(Entries::default()),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// 登记请求 id；第一次出现时由调用方执行
+    pub fn claim(&self, request_id: &str) -> Claim<'_> {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(rx) = inner.outcomes.get(request_id) {
+            return Claim::Duplicate(rx.clone());
+        }
+
+        let (tx, rx) = watch::channel(None);
+        inner.outcomes.insert(request_id.to_string(), rx);
+        inner.order.push_back(request_id.to_string());
+        while inner.order.len() > self.capacity {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.outcomes.remove(&oldest);
+            }
+        }
+        Claim::First(Slot {
+            log: self,
+            request_id: request_id.to_string(),
+            tx: Some(tx),
+        })
+    }
+
+    /// 等待相同 id 的第一次执行结束；它失败时返回 None，调用方应重新 [`claim`](Self::claim)
+    pub async fn wait(mut rx: OutcomeReceiver) -> Option<MutationOutcome> {
+        let outcome = rx.wait_for(Option::is_some).await.ok()?;
+        outcome.clone()
+    }
+
+    fn forget(&self, request_id: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.outcomes.remove(request_id);
+        inner.order.retain(|id| id != request_id);
+    }
+}
+
+/// 第一次执行的请求；没有 [`complete`](Self::complete) 就被丢弃时删除记录
+pub struct Slot<'a> {
+    log: &'a RequestLog,
+    request_id: String,
+    tx: Option<watch::Sender<Option<MutationOutcome>>>,
+}
+
+impl Slot<'_> {
+    /// 记录执行结果，唤醒等待的重试
+    pub fn complete(mut self, outcome: MutationOutcome) {
+        if let Some(tx) = self.tx.take() {
+            tx.send_replace(Some(outcome));
+        }
+    }
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        if self.tx.is_some() {
+            self.log.forget(&self.request_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(epoch: u64) -> MutationOutcome {
+        MutationOutcome {
+            proof: None,
+            root_hash: vec![epoch as u8],
+            epoch,
+            proof_handle: 0,
+            sketch_digests: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_waits_for_first_outcome() {
+        let log = RequestLog::default();
+        let Claim::First(slot) = log.claim("r1") else {
+            panic!("first claim must execute");
+        };
+        let Claim::Duplicate(rx) = log.claim("r1") else {
+            panic!("second claim must wait");
+        };
+        let waiter = tokio::spawn(RequestLog::wait(rx));
+        slot.complete(outcome(3));
+        assert_eq!(waiter.await.unwrap(), Some(outcome(3)));
+
+        let Claim::Duplicate(rx) = log.claim("r1") else {
+            panic!("completed request must be remembered");
+        };
+        assert_eq!(RequestLog::wait(rx).await, Some(outcome(3)));
+    }
+
+    #[tokio::test]
+    async fn test_failed_attempt_is_forgotten() {
+        let log = RequestLog::default();
+        let Claim::First(slot) = log.claim("r1") else {
+            panic!("first claim must execute");
+        };
+        let Claim::Duplicate(rx) = log.claim("r1") else {
+            panic!("second claim must wait");
+        };
+        drop(slot);
+        assert_eq!(RequestLog::wait(rx).await, None);
+        assert!(matches!(log.claim("r1"), Claim::First(_)));
+    }
+
+    #[test]
+    fn test_capacity_evicts_oldest() {
+        let log = RequestLog::new(2);
+        for id in ["r1", "r2", "r3"] {
+            let Claim::First(slot) = log.claim(id) else {
+                panic!("{} is new", id);
+            };
+            slot.complete(outcome(1));
+        }
+        assert!(matches!(log.claim("r1"), Claim::First(_)));
+        assert!(matches!(log.claim("r3"), Claim::Duplicate(_)));
+    }
+}
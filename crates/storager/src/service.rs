@@ -1,54 +1,1249 @@
-use crate::storager::Storager;
+use crate::ads::Mutation;
+use crate::error::StoragerError;
+use crate::expiry::MAX_EXPIRED_ENTRIES;
+use crate::keyword_stats::{KeywordCounters, DEFAULT_TOP_KEYWORDS};
+use crate::request_log::MutationOutcome;
+use crate::storager::{CryptoHealth, Storager};
+use common::query_stream::{
+    batch_chunk, header_chunk, split_query, trailer_chunk, DEFAULT_FIDS_PER_CHUNK,
+    DEFAULT_PROOF_PART_SIZE,
+};
 use common::rpc::{
-    storager_service_server::StoragerService, StoragerAddRequest, StoragerAddResponse,
-    StoragerDeleteRequest, StoragerDeleteResponse, StoragerQueryRequest, StoragerQueryResponse,
+    storager_service_server::StoragerService, BulkAddRecord, BulkAddStep, ExpiredEntry,
+    FlushRequest, FlushResponse, GetProofRequest, GetProofResponse, KeywordActivity, KeywordCount,
+    KeywordFilterRequest, KeywordFilterResponse, KeywordPostings, ListExpiredRequest,
+    ListExpiredResponse, ListKeywordsRequest, ListKeywordsResponse, ListNamespacesRequest,
+    ListNamespacesResponse, ListRootHistoryRequest, ListRootHistoryResponse, MigrateInResponse,
+    MigrateOutRequest, MigrateOutResponse, MigrationEntry, PrefixQueryRequest,
+    ProveDifferenceRequest, ProveDifferenceResponse, PruneRequest, PruneResponse,
+    QueryAtRootRequest, QueryAtRootResponse, RangeQueryRequest, RootVersion, StoragerAddRequest,
+    StoragerAddResponse, StoragerApproxCountRequest, StoragerApproxCountResponse,
+    StoragerBatchAddRequest, StoragerBatchAddResponse, StoragerBooleanQueryRequest,
+    StoragerBooleanQueryResponse, StoragerBulkAddResponse, StoragerDeleteRequest,
+    StoragerDeleteResponse, StoragerHealthRequest, StoragerHealthResponse,
+    StoragerPrefixQueryResponse, StoragerQueryChunk, StoragerQueryRequest, StoragerQueryResponse,
+    StoragerRangeQueryResponse, StoragerStatsRequest, StoragerStatsResponse,
 };
-use tonic::{Request, Response, Status};
+use common::{paginate, parse_boolean_expr};
+use std::pin::Pin;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{debug, info};
 
+// 处理函数交给 ADS 线程池的闭包与处理函数本身一样返回 tonic::Status
+#[allow(clippy::result_large_err)]
 #[tonic::async_trait]
 impl StoragerService for Storager {
     async fn add(
         &self,
-        request: Request<StoragerAddRequest>,
+        mut request: Request<StoragerAddRequest>,
     ) -> Result<Response<StoragerAddResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.add(request).await;
+        }
         let req = request.into_inner();
-        println!(
+        debug!(
             "Storager received Add request: keyword={}, fid={}",
             req.keyword, req.fid
         );
 
-        let mut ads = self.ads.write().unwrap();
-        let (proof, root_hash) = ads.add(&req.keyword, &req.fid);
+        Self::check_keywords(&[&req.keyword])?;
+        self.ensure_crypto_ready()?;
+
+        let request_id = req.request_id.clone();
+        let outcome = self
+            .deduplicate(&request_id, async {
+                let (pending, root_hash, epoch, sketch_digest) = self
+                    .run_ads(move |storager| {
+                        // 持有写锁后再检查，保证交接导出的状态包含所有已确认的写入
+                        let mut ads = storager.write_ads()?;
+                        storager.ensure_writable()?;
+                        let fid = storager.intern_fid(ads.as_mut(), &req.fid)?;
+                        let mutation = Mutation::Add {
+                            keyword: &req.keyword,
+                            fid: &fid,
+                        };
+                        let (pending, root_hash) =
+                            storager.apply_mutation(ads.as_mut(), mutation, req.defer_proof)?;
+                        let epoch = storager.advance_epoch();
+                        let sketch_digest = storager.record_added(&req.keyword, &req.fid);
+                        storager
+                            .expiries
+                            .schedule(&req.keyword, &req.fid, req.expires_at_ms);
+                        Ok::<_, Status>((pending, root_hash, epoch, sketch_digest))
+                    })
+                    .await?;
+                let (proof, proof_handle) = self.proofs.resolve(&self.pool, pending);
+                Ok::<_, Status>(MutationOutcome {
+                    proof: proof.map(Into::into),
+                    root_hash,
+                    epoch,
+                    proof_handle,
+                    sketch_digests: vec![sketch_digest],
+                })
+            })
+            .await?;
 
-        Ok(Response::new(StoragerAddResponse { proof, root_hash }))
+        Ok(Response::new(StoragerAddResponse {
+            proof: outcome.proof,
+            root_hash: outcome.root_hash,
+            epoch: outcome.epoch,
+            proof_handle: outcome.proof_handle,
+            sketch_digest: outcome.sketch_digests.concat(),
+        }))
+    }
+
+    async fn batch_add(
+        &self,
+        mut request: Request<StoragerBatchAddRequest>,
+    ) -> Result<Response<StoragerBatchAddResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.batch_add(request).await;
+        }
+        let req = request.into_inner();
+        debug!(
+            "Storager received BatchAdd request: {} keyword(s), fid={}",
+            req.keywords.len(),
+            req.fid
+        );
+
+        if req.keywords.is_empty() {
+            return Err(StoragerError::InvalidRequest("No keywords provided".to_string()).into());
+        }
+        Self::check_keywords(&req.keywords)?;
+        self.ensure_crypto_ready()?;
+
+        let request_id = req.request_id.clone();
+        let outcome = self
+            .deduplicate(&request_id, async {
+                let (pending, root_hash, epoch, sketch_digests) = self
+                    .run_ads(move |storager| {
+                        let mut ads = storager.write_ads()?;
+                        storager.ensure_writable()?;
+                        let fid = storager.intern_fid(ads.as_mut(), &req.fid)?;
+                        let mutation = Mutation::AddBatch {
+                            keywords: &req.keywords,
+                            fid: &fid,
+                        };
+                        let (pending, root_hash) =
+                            storager.apply_mutation(ads.as_mut(), mutation, req.defer_proof)?;
+                        let epoch = storager.advance_epoch();
+                        let mut sketch_digests = Vec::with_capacity(req.keywords.len());
+                        for keyword in &req.keywords {
+                            sketch_digests.push(storager.record_added(keyword, &req.fid));
+                            storager
+                                .expiries
+                                .schedule(keyword, &req.fid, req.expires_at_ms);
+                        }
+                        Ok::<_, Status>((pending, root_hash, epoch, sketch_digests))
+                    })
+                    .await?;
+                let (proof, proof_handle) = self.proofs.resolve(&self.pool, pending);
+                Ok::<_, Status>(MutationOutcome {
+                    proof: proof.map(Into::into),
+                    root_hash,
+                    epoch,
+                    proof_handle,
+                    sketch_digests,
+                })
+            })
+            .await?;
+
+        Ok(Response::new(StoragerBatchAddResponse {
+            proof: outcome.proof,
+            root_hash: outcome.root_hash,
+            epoch: outcome.epoch,
+            proof_handle: outcome.proof_handle,
+            sketch_digests: outcome.sketch_digests,
+        }))
     }
 
     async fn query(
         &self,
-        request: Request<StoragerQueryRequest>,
+        mut request: Request<StoragerQueryRequest>,
     ) -> Result<Response<StoragerQueryResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.query(request).await;
+        }
+        let req = request.into_inner();
+        debug!("Storager received Query request: keyword={}", req.keyword);
+
+        self.ensure_crypto_ready()?;
+
+        self.run_ads(move |storager| {
+            let ads = storager.ads.read().unwrap();
+            let result = tracing::info_span!("prove_query").in_scope(|| ads.query(&req.keyword));
+            let (fids, proof) = storager.resolve_query(&**ads, result);
+            // 分页查询只在第一页计一次
+            if req.page_token.is_empty() {
+                storager
+                    .keyword_stats
+                    .record_query(&req.keyword, Some(fids.len()));
+            }
+            let page = paginate(fids, req.page_size, &req.page_token)?;
+
+            Ok(Response::new(StoragerQueryResponse {
+                proof: page.is_last().then(|| proof.into()),
+                fids: page.fids,
+                epoch: storager.epoch(),
+                total_count: page.total_count,
+                next_page_token: page.next_page_token,
+            }))
+        })
+        .await
+    }
+
+    async fn prove_difference(
+        &self,
+        mut request: Request<ProveDifferenceRequest>,
+    ) -> Result<Response<ProveDifferenceResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.prove_difference(request).await;
+        }
+        let req = request.into_inner();
+        debug!(
+            "Storager received ProveDifference request: keyword={}, {} excluded fid(s)",
+            req.keyword,
+            req.excluded_fids.len()
+        );
+
+        self.ensure_crypto_ready()?;
+        // 证明中的元素由 ADS 中的紧凑 id 计算，Manager 无法用真实 fid 验证
+        if self.fid_interning_enabled() {
+            return Err(StoragerError::Interned("Difference proofs").into());
+        }
+
+        self.run_ads(move |storager| {
+            let ads = storager.ads.read().unwrap();
+            let (fids, proof) = ads
+                .prove_difference(&req.keyword, &req.excluded_fids)
+                .ok_or(StoragerError::Unsupported("difference proofs"))?;
+
+            Ok(Response::new(ProveDifferenceResponse { fids, proof }))
+        })
+        .await
+    }
+
+    async fn boolean_query(
+        &self,
+        mut request: Request<StoragerBooleanQueryRequest>,
+    ) -> Result<Response<StoragerBooleanQueryResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.boolean_query(request).await;
+        }
         let req = request.into_inner();
-        println!("Storager received Query request: keyword={}", req.keyword);
+        debug!("Storager received BooleanQuery request: {}", req.expression);
 
-        let ads = self.ads.read().unwrap();
-        let (fids, proof) = ads.query(&req.keyword);
+        self.ensure_crypto_ready()?;
+        // 与差集证明相同，证明树中的元素无法由真实 fid 验证
+        if self.fid_interning_enabled() {
+            return Err(StoragerError::Interned("Boolean query proofs").into());
+        }
+        let expr = parse_boolean_expr(&req.expression).map_err(StoragerError::InvalidRequest)?;
 
-        Ok(Response::new(StoragerQueryResponse { fids, proof }))
+        self.run_ads(move |storager| {
+            let ads = storager.ads.read().unwrap();
+            let (fids, proof) = ads
+                .query_boolean(&expr)
+                .map_err(StoragerError::Precondition)?;
+            for keyword in expr.get_keywords() {
+                storager.keyword_stats.record_query(&keyword, None);
+            }
+
+            Ok(Response::new(StoragerBooleanQueryResponse {
+                fids,
+                proof: Some(proof),
+            }))
+        })
+        .await
     }
 
     async fn delete(
         &self,
-        request: Request<StoragerDeleteRequest>,
+        mut request: Request<StoragerDeleteRequest>,
     ) -> Result<Response<StoragerDeleteResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.delete(request).await;
+        }
         let req = request.into_inner();
-        println!(
+        debug!(
             "Storager received Delete request: keyword={}, fid={}",
             req.keyword, req.fid
         );
 
-        let mut ads = self.ads.write().unwrap();
-        let (proof, root_hash) = ads.delete(&req.keyword, &req.fid);
+        Self::check_keywords(&[&req.keyword])?;
+        self.ensure_crypto_ready()?;
+
+        let request_id = req.request_id.clone();
+        let outcome = self
+            .deduplicate(&request_id, async {
+                let (pending, root_hash, epoch) = self
+                    .run_ads(move |storager| {
+                        let mut ads = storager.write_ads()?;
+                        storager.ensure_writable()?;
+                        let fid = storager.lookup_fid(&req.fid);
+                        let mutation = Mutation::Delete {
+                            keyword: &req.keyword,
+                            fid: &fid,
+                        };
+                        let (pending, root_hash) =
+                            storager.apply_mutation(ads.as_mut(), mutation, req.defer_proof)?;
+                        let epoch = storager.advance_epoch();
+                        storager.keyword_stats.record_delete(&req.keyword);
+                        storager.expiries.cancel(&req.keyword, &req.fid);
+                        Ok::<_, Status>((pending, root_hash, epoch))
+                    })
+                    .await?;
+                let (proof, proof_handle) = self.proofs.resolve(&self.pool, pending);
+                Ok::<_, Status>(MutationOutcome {
+                    proof: proof.map(Into::into),
+                    root_hash,
+                    epoch,
+                    proof_handle,
+                    sketch_digests: vec![],
+                })
+            })
+            .await?;
+
+        Ok(Response::new(StoragerDeleteResponse {
+            proof: outcome.proof,
+            root_hash: outcome.root_hash,
+            epoch: outcome.epoch,
+            proof_handle: outcome.proof_handle,
+        }))
+    }
+
+    async fn approx_count(
+        &self,
+        mut request: Request<StoragerApproxCountRequest>,
+    ) -> Result<Response<StoragerApproxCountResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.approx_count(request).await;
+        }
+        let req = request.into_inner();
+        debug!(
+            "Storager received ApproxCount request: keyword={}",
+            req.keyword
+        );
+
+        let response = match self.sketch(&req.keyword) {
+            Some(sketch) => StoragerApproxCountResponse {
+                found: true,
+                sketch,
+            },
+            None => StoragerApproxCountResponse {
+                found: false,
+                sketch: vec![],
+            },
+        };
+
+        Ok(Response::new(response))
+    }
+
+    async fn health(
+        &self,
+        _request: Request<StoragerHealthRequest>,
+    ) -> Result<Response<StoragerHealthResponse>, Status> {
+        let response = match self.crypto_health() {
+            CryptoHealth::Failed(message) => StoragerHealthResponse {
+                crypto_ready: false,
+                message,
+            },
+            _ => StoragerHealthResponse {
+                crypto_ready: true,
+                message: String::new(),
+            },
+        };
+
+        Ok(Response::new(response))
+    }
+
+    async fn list_keywords(
+        &self,
+        mut request: Request<ListKeywordsRequest>,
+    ) -> Result<Response<ListKeywordsResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.list_keywords(request).await;
+        }
+        debug!("Storager received ListKeywords request");
+
+        let keywords = self.keywords()?;
+        Ok(Response::new(ListKeywordsResponse { keywords }))
+    }
+
+    async fn list_namespaces(
+        &self,
+        _request: Request<ListNamespacesRequest>,
+    ) -> Result<Response<ListNamespacesResponse>, Status> {
+        Ok(Response::new(ListNamespacesResponse {
+            namespaces: self.namespaces(),
+        }))
+    }
+
+    async fn migrate_out(
+        &self,
+        request: Request<MigrateOutRequest>,
+    ) -> Result<Response<MigrateOutResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "Storager received MigrateOut request: {} keyword(s) to {}",
+            req.keywords.len(),
+            req.target
+        );
+
+        self.ensure_crypto_ready()?;
+
+        // 目标 storager 把数据写入同名的命名空间
+        let namespace = req.namespace.clone();
+        let entries: Vec<MigrationEntry> = self
+            .namespace(&req.namespace)?
+            .run_ads(move |storager| {
+                let ads = storager.ads.read().unwrap();
+                req.keywords
+                    .into_iter()
+                    .map(|keyword| {
+                        let result =
+                            tracing::info_span!("prove_query").in_scope(|| ads.query(&keyword));
+                        let (fids, proof) = storager.resolve_query(&**ads, result);
+                        MigrationEntry {
+                            keyword,
+                            fids,
+                            proof: Some(proof.into()),
+                            namespace: namespace.clone(),
+                        }
+                    })
+                    .collect()
+            })
+            .await;
+
+        let channel = common::net::connect_with(&req.target, &self.transport)
+            .await
+            .map_err(|e| StoragerError::TargetUnavailable(e.to_string()))?;
+        let response = self
+            .transport
+            .storager_client(channel)
+            .migrate_in(tokio_stream::iter(entries.clone()))
+            .await
+            .map_err(|e| StoragerError::TargetFailed(e.message().to_string()))?
+            .into_inner();
+
+        Ok(Response::new(MigrateOutResponse {
+            entries,
+            target_root_hash: response.root_hash,
+            target_epoch: response.epoch,
+        }))
+    }
+
+    async fn migrate_in(
+        &self,
+        request: Request<Streaming<MigrationEntry>>,
+    ) -> Result<Response<MigrateInResponse>, Status> {
+        let mut stream = request.into_inner();
+        let mut keywords = 0;
+        let mut root_hash = Vec::new();
+        let mut epoch = 0;
+
+        while let Some(entry) = stream.message().await? {
+            let keyword = entry.keyword;
+            let replaced = self
+                .namespace(&entry.namespace)?
+                .run_ads(move |storager| storager.replace_postings(&keyword, &entry.fids))
+                .await;
+            if let Some((root, written_at)) = replaced? {
+                root_hash = root;
+                epoch = written_at;
+            }
+            keywords += 1;
+        }
+        debug!(
+            "Storager received MigrateIn: {} keyword(s) replaced",
+            keywords
+        );
+
+        Ok(Response::new(MigrateInResponse {
+            keywords,
+            root_hash,
+            epoch,
+        }))
+    }
+
+    async fn bulk_add(
+        &self,
+        request: Request<Streaming<BulkAddRecord>>,
+    ) -> Result<Response<StoragerBulkAddResponse>, Status> {
+        self.ensure_crypto_ready()?;
+
+        let mut stream = request.into_inner();
+        let mut steps = Vec::new();
+
+        while let Some(record) = stream.message().await? {
+            if record.keywords.is_empty() {
+                return Err(StoragerError::InvalidRequest(format!(
+                    "No keywords provided for fid {}",
+                    record.fid
+                ))
+                .into());
+            }
+            Self::check_keywords(&record.keywords)?;
+
+            // 每条记录单独持有写锁，导入期间查询仍然可以穿插进来
+            let (fid, keywords) = (record.fid.clone(), record.keywords.clone());
+            let (proof, root_hash, epoch, sketch_digests) = self
+                .namespace(&record.namespace)?
+                .run_ads(move |storager| {
+                    let mut ads = storager.write_ads()?;
+                    storager.ensure_writable()?;
+                    let stored = storager.intern_fid(ads.as_mut(), &fid)?;
+                    let (proof, root_hash) = ads.add_batch(&keywords, &stored)?;
+                    let epoch = storager.advance_epoch();
+                    let sketch_digests = keywords
+                        .iter()
+                        .map(|keyword| storager.record_added(keyword, &fid))
+                        .collect::<Vec<_>>();
+                    Ok::<_, Status>((proof, root_hash, epoch, sketch_digests))
+                })
+                .await?;
+
+            steps.push(BulkAddStep {
+                fid: record.fid,
+                keywords: record.keywords,
+                proof: Some(proof.into()),
+                root_hash,
+                epoch,
+                sketch_digests,
+            });
+        }
+        debug!(
+            "Storager received BulkAdd: {} record(s) applied",
+            steps.len()
+        );
+
+        Ok(Response::new(StoragerBulkAddResponse { steps }))
+    }
+
+    async fn range_query(
+        &self,
+        mut request: Request<RangeQueryRequest>,
+    ) -> Result<Response<StoragerRangeQueryResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.range_query(request).await;
+        }
+        let req = request.into_inner();
+        debug!(
+            "Storager received RangeQuery request: [{}, {})",
+            req.start_key, req.end_key
+        );
+
+        self.ensure_crypto_ready()?;
+        // 证明中的值是紧凑 id 列表，Manager 无法把它们与真实 fid 对应起来
+        if self.fid_interning_enabled() {
+            return Err(StoragerError::Interned("Range queries").into());
+        }
+
+        self.run_ads(move |storager| {
+            let ads = storager.ads.read().unwrap();
+            let (entries, range_proof) = ads
+                .range_query(&req.start_key, &req.end_key)
+                .ok_or(StoragerError::Unsupported("range queries"))?;
+
+            Ok(Response::new(StoragerRangeQueryResponse {
+                entries: entries
+                    .into_iter()
+                    .map(|(keyword, fids)| KeywordPostings { keyword, fids })
+                    .collect(),
+                range_proof,
+                epoch: storager.epoch(),
+            }))
+        })
+        .await
+    }
+
+    async fn query_by_prefix(
+        &self,
+        mut request: Request<PrefixQueryRequest>,
+    ) -> Result<Response<StoragerPrefixQueryResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.query_by_prefix(request).await;
+        }
+        let req = request.into_inner();
+        debug!("Storager received QueryByPrefix request: '{}'", req.prefix);
+
+        self.ensure_crypto_ready()?;
+        // 与范围查询相同，fid 数量虽然不受驻留影响，但证明中的值仍是紧凑 id
+        if self.fid_interning_enabled() {
+            return Err(StoragerError::Interned("Prefix queries").into());
+        }
+
+        self.run_ads(move |storager| {
+            let ads = storager.ads.read().unwrap();
+            let (keywords, subtree_proof) = ads
+                .prefix_query(&req.prefix)
+                .ok_or(StoragerError::Unsupported("prefix queries"))?;
+
+            Ok(Response::new(StoragerPrefixQueryResponse {
+                keywords: keywords
+                    .into_iter()
+                    .map(|(keyword, fid_count)| KeywordCount { keyword, fid_count })
+                    .collect(),
+                subtree_proof,
+                epoch: storager.epoch(),
+            }))
+        })
+        .await
+    }
+
+    async fn prune(
+        &self,
+        mut request: Request<PruneRequest>,
+    ) -> Result<Response<PruneResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.prune(request).await;
+        }
+        let req = request.into_inner();
+        debug!(
+            "Storager received Prune request: keeping {} historical root(s)",
+            req.keep_roots.len()
+        );
+
+        self.run_ads(move |storager| {
+            // 回收不改变根哈希，但与写入一样需要独占 ADS
+            let mut ads = storager.ads.write().unwrap();
+            let stats = ads
+                .prune(&req.keep_roots)
+                .map_err(StoragerError::Precondition)?;
+            info!(
+                "Pruned {} node(s), {} reachable",
+                stats.nodes_deleted, stats.nodes_kept
+            );
+
+            Ok(Response::new(PruneResponse {
+                nodes_kept: stats.nodes_kept,
+                nodes_deleted: stats.nodes_deleted,
+            }))
+        })
+        .await
+    }
+
+    async fn flush(
+        &self,
+        _request: Request<FlushRequest>,
+    ) -> Result<Response<FlushResponse>, Status> {
+        debug!("Storager received Flush request");
+
+        self.run_ads(|storager| {
+            let namespaces = storager.flush_namespaces()?;
+            info!("Flushed {} namespace(s)", namespaces);
+            Ok(Response::new(FlushResponse { namespaces }))
+        })
+        .await
+    }
+
+    async fn query_at_root(
+        &self,
+        mut request: Request<QueryAtRootRequest>,
+    ) -> Result<Response<QueryAtRootResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.query_at_root(request).await;
+        }
+        let req = request.into_inner();
+        debug!(
+            "Storager received QueryAtRoot request: keyword={}, root={:x?}",
+            req.keyword,
+            &req.root_hash[..req.root_hash.len().min(8)]
+        );
+
+        self.ensure_crypto_ready()?;
+        // 驻留表只反映当前的 fid，无法保证历史证明中的紧凑 id 仍能对应回来
+        if self.fid_interning_enabled() {
+            return Err(StoragerError::Interned("Historical queries").into());
+        }
+
+        self.run_ads(move |storager| {
+            let ads = storager.ads.read().unwrap();
+            let (fids, proof) = ads
+                .query_at_root(&req.keyword, &req.root_hash)
+                .map_err(StoragerError::UnknownRoot)?;
+
+            Ok(Response::new(QueryAtRootResponse {
+                fids,
+                proof: Some(proof.into()),
+            }))
+        })
+        .await
+    }
+
+    async fn list_root_history(
+        &self,
+        mut request: Request<ListRootHistoryRequest>,
+    ) -> Result<Response<ListRootHistoryResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.list_root_history(request).await;
+        }
+        self.run_ads(move |storager| {
+            let ads = storager.ads.read().unwrap();
+            let history = ads
+                .root_history()
+                .ok_or(StoragerError::Unsupported("root history"))?;
+
+            Ok(Response::new(ListRootHistoryResponse {
+                versions: history
+                    .into_iter()
+                    .map(|(version, root_hash)| RootVersion { version, root_hash })
+                    .collect(),
+            }))
+        })
+        .await
+    }
+
+    async fn get_proof(
+        &self,
+        mut request: Request<GetProofRequest>,
+    ) -> Result<Response<GetProofResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.get_proof(request).await;
+        }
+        let req = request.into_inner();
+        let proof = self
+            .proofs
+            .get(req.proof_handle)
+            .await
+            .map_err(StoragerError::from)?;
+
+        Ok(Response::new(GetProofResponse {
+            proof: Some(proof.into()),
+        }))
+    }
+
+    async fn stats(
+        &self,
+        mut request: Request<StoragerStatsRequest>,
+    ) -> Result<Response<StoragerStatsResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.stats(request).await;
+        }
+        let req = request.into_inner();
+        debug!("Storager received Stats request: top={}", req.top);
+
+        let top = match req.top {
+            0 => DEFAULT_TOP_KEYWORDS,
+            top => top as usize,
+        };
+        let activity = |entries: Vec<(String, KeywordCounters)>| -> Vec<KeywordActivity> {
+            entries
+                .into_iter()
+                .map(|(keyword, counters)| KeywordActivity {
+                    keyword,
+                    fids: counters.fids,
+                    queries: counters.queries,
+                })
+                .collect()
+        };
+        let stats = self.keyword_stats();
+        let (keywords, total_fids, total_queries) = stats.totals();
+        let tombstones = self
+            .ads
+            .read()
+            .unwrap()
+            .tombstone_stats()
+            .unwrap_or_default();
+        Ok(Response::new(StoragerStatsResponse {
+            keywords,
+            total_fids,
+            total_queries,
+            hottest: activity(stats.hottest(top)),
+            largest: activity(stats.largest(top)),
+            tombstones: tombstones.tombstones,
+            tombstone_ratio: tombstones.ratio(),
+            compactions: tombstones.compactions,
+        }))
+    }
+
+    async fn keyword_filter(
+        &self,
+        mut request: Request<KeywordFilterRequest>,
+    ) -> Result<Response<KeywordFilterResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return StoragerService::keyword_filter(&storager, request).await;
+        }
+        debug!("Storager received KeywordFilter request");
+
+        let snapshot = self.run_ads(|storager| storager.keyword_filter()).await?;
+        Ok(Response::new(KeywordFilterResponse {
+            bits: snapshot.filter.bits().to_vec(),
+            hashes: snapshot.filter.hashes(),
+            root_hash: snapshot.root_hash.clone(),
+            epoch: snapshot.epoch,
+            keywords: snapshot.keywords as u64,
+        }))
+    }
+
+    async fn list_expired(
+        &self,
+        mut request: Request<ListExpiredRequest>,
+    ) -> Result<Response<ListExpiredResponse>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.list_expired(request).await;
+        }
+        let req = request.into_inner();
+        debug!(
+            "Storager received ListExpired request: after_sequence={}",
+            req.after_sequence
+        );
+
+        let (entries, last_sequence, truncated) = self
+            .sweep_log()
+            .since(req.after_sequence, MAX_EXPIRED_ENTRIES);
+        Ok(Response::new(ListExpiredResponse {
+            entries: entries
+                .into_iter()
+                .map(|entry| ExpiredEntry {
+                    sequence: entry.sequence,
+                    keyword: entry.keyword,
+                    fid: entry.fid,
+                    proof: Some(entry.proof.into()),
+                    root_hash: entry.root_hash,
+                    epoch: entry.epoch,
+                })
+                .collect(),
+            last_sequence,
+            truncated,
+        }))
+    }
+
+    type QueryStreamStream = Pin<Box<dyn Stream<Item = Result<StoragerQueryChunk, Status>> + Send>>;
+
+    async fn query_stream(
+        &self,
+        mut request: Request<StoragerQueryRequest>,
+    ) -> Result<Response<Self::QueryStreamStream>, Status> {
+        if let Some(storager) = self.route_namespace(&mut request.get_mut().namespace)? {
+            return storager.query_stream(request).await;
+        }
+        let req = request.into_inner();
+        debug!(
+            "Storager received QueryStream request: keyword={}",
+            req.keyword
+        );
+
+        self.ensure_crypto_ready()?;
+
+        let keyword = req.keyword.clone();
+        let first = self
+            .run_ads(move |storager| storager.query_batch(&keyword, 0, None))
+            .await?;
+        let Some(((fids, proof, total), epoch)) = first else {
+            // 证明绑定整个 fid 列表，在 fid 之后分段传输完整的证明
+            let response = self
+                .run_ads(move |storager| {
+                    let ads = storager.ads.read().unwrap();
+                    let result =
+                        tracing::info_span!("prove_query").in_scope(|| ads.query(&req.keyword));
+                    let (fids, proof) = storager.resolve_query(&**ads, result);
+                    storager
+                        .keyword_stats
+                        .record_query(&req.keyword, Some(fids.len()));
+                    StoragerQueryResponse {
+                        total_count: fids.len() as u64,
+                        fids,
+                        proof: Some(proof.into()),
+                        epoch: storager.epoch(),
+                        next_page_token: String::new(),
+                    }
+                })
+                .await;
+            let chunks = split_query(response, DEFAULT_FIDS_PER_CHUNK, DEFAULT_PROOF_PART_SIZE);
+            return Ok(Response::new(Box::pin(tokio_stream::iter(
+                chunks.into_iter().map(Ok),
+            ))));
+        };
+        self.keyword_stats.record_query(&req.keyword, Some(total));
+
+        // 之后的批次在接收方取走前一批后才生成，每批单独持有读锁
+        let (tx, rx) = mpsc::channel(2);
+        let storager = self.clone();
+        tokio::spawn(async move {
+            let mut offset = fids.len();
+            let first = [
+                header_chunk(total as u64, 0, epoch),
+                batch_chunk(fids, Some(proof.into())),
+            ];
+            for chunk in first {
+                if tx.send(Ok(chunk)).await.is_err() {
+                    return;
+                }
+            }
+            while offset < total {
+                let keyword = req.keyword.clone();
+                let batch = storager
+                    .run_ads(move |storager| storager.query_batch(&keyword, offset, Some(epoch)))
+                    .await;
+                let chunk = match batch {
+                    Ok(Some(((fids, proof, _), _))) if !fids.is_empty() => {
+                        offset += fids.len();
+                        batch_chunk(fids, Some(proof.into()))
+                    }
+                    Ok(_) => {
+                        let error = StoragerError::Precondition(format!(
+                            "query stream for '{}' lost its batches",
+                            req.keyword
+                        ));
+                        let _ = tx.send(Err(error.into())).await;
+                        return;
+                    }
+                    Err(error) => {
+                        let _ = tx.send(Err(error.into())).await;
+                        return;
+                    }
+                };
+                if tx.send(Ok(chunk)).await.is_err() {
+                    return;
+                }
+            }
+            let _ = tx.send(Ok(trailer_chunk())).await;
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::query_stream::QueryAssembler;
+    use tokio_stream::StreamExt;
+
+    #[tokio::test]
+    async fn test_rejects_ads_requests_when_crypto_failed() {
+        let storager =
+            Storager::with_mpt().with_crypto_health(CryptoHealth::Failed("bad params".to_string()));
+
+        let status = storager
+            .query(Request::new(StoragerQueryRequest {
+                keyword: "rust".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+
+        let health = storager
+            .health(Request::new(StoragerHealthRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!health.crypto_ready);
+        assert_eq!(health.message, "bad params");
+
+        storager.set_crypto_health(CryptoHealth::Ready);
+        assert!(storager
+            .query(Request::new(StoragerQueryRequest {
+                keyword: "rust".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_frozen_storager_rejects_writes() {
+        let storager = Storager::with_merkle_tree();
+        let add = || {
+            Request::new(StoragerAddRequest {
+                keyword: "rust".to_string(),
+                fid: "f1".to_string(),
+                ..Default::default()
+            })
+        };
+
+        storager.freeze();
+        let status = storager.add(add()).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert!(storager
+            .query(Request::new(StoragerQueryRequest {
+                keyword: "rust".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .is_ok());
+
+        storager.unfreeze();
+        assert!(storager.add(add()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_ads_errors_become_statuses() {
+        let storager = Storager::new();
+        let delete = |fid: &str| {
+            Request::new(StoragerDeleteRequest {
+                keyword: "rust".to_string(),
+                fid: fid.to_string(),
+                ..Default::default()
+            })
+        };
+        let status = storager.delete(delete("f1")).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        assert!(status.message().contains("rust"), "{}", status.message());
+
+        storager
+            .add(Request::new(StoragerAddRequest {
+                keyword: "rust".to_string(),
+                fid: "f1".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        let status = storager.delete(delete("f2")).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        // 失败的写操作不推进版本号
+        assert_eq!(storager.epoch(), 1);
+        assert!(storager.delete(delete("f1")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_retried_delete_is_applied_once() {
+        let storager = Storager::new();
+        storager
+            .add(Request::new(StoragerAddRequest {
+                keyword: "rust".to_string(),
+                fid: "f1".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+
+        let delete = || {
+            Request::new(StoragerDeleteRequest {
+                keyword: "rust".to_string(),
+                fid: "f1".to_string(),
+                request_id: "manager-7".to_string(),
+                ..Default::default()
+            })
+        };
+        let first = storager.delete(delete()).await.unwrap().into_inner();
+        // 重试返回第一次的结果，而不是 fid 不存在的错误
+        let retried = storager.delete(delete()).await.unwrap().into_inner();
+        assert_eq!(retried, first);
+        assert_eq!(storager.epoch(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_replace_postings_is_idempotent() {
+        let storager = Storager::with_merkle_tree().with_fid_interning().unwrap();
+        let fids = |list: &[&str]| list.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+
+        assert!(storager
+            .replace_postings("rust", &fids(&["file-1", "file-2"]))
+            .unwrap()
+            .is_some());
+        assert!(storager
+            .replace_postings("rust", &fids(&["file-2", "file-1"]))
+            .unwrap()
+            .is_none());
+        storager
+            .replace_postings("rust", &fids(&["file-2", "file-3"]))
+            .unwrap();
+
+        let response = storager
+            .query(Request::new(StoragerQueryRequest {
+                keyword: "rust".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        // Merkle 树按 ADS 中保存的形式（紧凑 id）排序，顺序与 fid 无关
+        let mut fids = response.fids;
+        fids.sort();
+        assert_eq!(fids, vec!["file-2", "file-3"]);
+        // 映射表摘要的保留 keyword 不会被迁移
+        assert_eq!(storager.keywords().unwrap(), vec!["rust"]);
+    }
+
+    #[tokio::test]
+    async fn test_deferred_proof_matches_eager_proof() {
+        let eager = Storager::with_merkle_tree();
+        let deferred = Storager::with_merkle_tree();
+        let add = |defer_proof| {
+            Request::new(StoragerAddRequest {
+                keyword: "rust".to_string(),
+                fid: "f1".to_string(),
+                defer_proof,
+                ..Default::default()
+            })
+        };
+
+        let expected = eager.add(add(false)).await.unwrap().into_inner();
+        assert_eq!(expected.proof_handle, 0);
+        let response = deferred.add(add(true)).await.unwrap().into_inner();
+        assert!(response.proof.is_none());
+        assert_ne!(response.proof_handle, 0);
+        assert_eq!(response.root_hash, expected.root_hash);
+
+        let fetched = deferred
+            .get_proof(Request::new(GetProofRequest {
+                proof_handle: response.proof_handle,
+                namespace: String::new(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(fetched.proof, expected.proof);
+
+        let status = deferred
+            .get_proof(Request::new(GetProofRequest {
+                proof_handle: response.proof_handle + 1,
+                namespace: String::new(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_query_at_past_root() {
+        let storager = Storager::with_mpt();
+        for fid in ["f1", "f2"] {
+            storager
+                .add(Request::new(StoragerAddRequest {
+                    keyword: "rust".to_string(),
+                    fid: fid.to_string(),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap();
+            storager.ads.write().unwrap().finish_maintenance();
+        }
+
+        let versions = storager
+            .list_root_history(Request::new(ListRootHistoryRequest {
+                namespace: String::new(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .versions;
+        assert_eq!(versions.len(), 2);
+
+        let verifier = manager::core::ProofVerifier::new(common::AdsMode::Mpt);
+        for (version, expected) in versions.iter().zip([vec!["f1"], vec!["f1", "f2"]]) {
+            let response = storager
+                .query_at_root(Request::new(QueryAtRootRequest {
+                    keyword: "rust".to_string(),
+                    root_hash: version.root_hash.clone(),
+                    namespace: String::new(),
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(response.fids, expected);
+            let proof = response.proof.unwrap().try_into().unwrap();
+            assert!(verifier.verify(&proof, &version.root_hash));
+        }
+
+        let status = storager
+            .query_at_root(Request::new(QueryAtRootRequest {
+                keyword: "rust".to_string(),
+                root_hash: vec![7u8; 32],
+                namespace: String::new(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_query_stream_matches_query() {
+        let request = || {
+            Request::new(StoragerQueryRequest {
+                keyword: "rust".to_string(),
+                ..Default::default()
+            })
+        };
+        let add = |storager: Storager, count: usize| async move {
+            for i in 0..count {
+                storager
+                    .add(Request::new(StoragerAddRequest {
+                        keyword: "rust".to_string(),
+                        fid: format!("f{}", i),
+                        ..Default::default()
+                    }))
+                    .await
+                    .unwrap();
+            }
+        };
+
+        // MPT 的证明绑定整个列表：fid 之后分段传输完整的证明
+        let storager = Storager::with_mpt();
+        add(storager.clone(), DEFAULT_FIDS_PER_CHUNK + 5).await;
+        let expected = storager.query(request()).await.unwrap().into_inner();
+        let mut stream = storager.query_stream(request()).await.unwrap().into_inner();
+        let mut assembler = QueryAssembler::new();
+        let mut chunks = 0;
+        while let Some(chunk) = stream.next().await {
+            assert_eq!(assembler.push(chunk.unwrap()).unwrap(), None);
+            chunks += 1;
+        }
+        // 头部、两批 fid、至少一段证明、尾部
+        assert!(chunks >= 5, "{} chunk(s)", chunks);
+        assert_eq!(assembler.finish().unwrap(), expected);
+
+        // Merkle 树的每批 fid 附带只覆盖这批的证明，单独对照根验证，合并后证明列表完整
+        let storager = Storager::with_merkle_tree();
+        add(storager.clone(), DEFAULT_FIDS_PER_CHUNK + 5).await;
+        let expected = storager.query(request()).await.unwrap().into_inner();
+        let root = storager.ads.read().unwrap().root_hash().unwrap();
+        let verifier = manager::core::ProofVerifier::new(common::AdsMode::MerkleTree);
+        let mut stream = storager.query_stream(request()).await.unwrap().into_inner();
+        let mut assembler = QueryAssembler::new();
+        let mut batches = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            if let Some(proof) = assembler.push(chunk.unwrap()).unwrap() {
+                let proof = common::Proof::try_from(proof).unwrap();
+                assert!(verifier.verify(&proof, &root));
+                assert!(verifier.verify_batch(&proof, "rust", assembler.last_batch()));
+                assert!(!verifier.verify_completeness(&proof, "rust", assembler.last_batch()));
+                batches.push(proof);
+            }
+        }
+        assert_eq!(batches.len(), 2);
+        let fids = assembler.finish().unwrap().fids;
+        assert_eq!(fids, expected.fids);
+        let merged = verifier.merge_batch_proofs(batches).unwrap();
+        assert!(verifier.verify(&merged, &root));
+        assert!(verifier.verify_completeness(&merged, "rust", &fids));
+    }
+
+    #[tokio::test]
+    async fn test_query_stream_stops_after_a_write() {
+        let storager = Storager::with_merkle_tree();
+        let add = |fid: String| {
+            storager.add(Request::new(StoragerAddRequest {
+                keyword: "rust".to_string(),
+                fid,
+                ..Default::default()
+            }))
+        };
+        for i in 0..(DEFAULT_FIDS_PER_CHUNK * 2 + 5) {
+            add(format!("f{}", i)).await.unwrap();
+        }
+        let mut stream = storager
+            .query_stream(Request::new(StoragerQueryRequest {
+                keyword: "rust".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
 
-        Ok(Response::new(StoragerDeleteResponse { proof, root_hash }))
+        // 最后一批在取走前面的消息之后才生成，此时的根已经不是前面各批证明的根
+        add("late".to_string()).await.unwrap();
+        let mut results = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            results.push(chunk);
+        }
+        let status = results.pop().unwrap().unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        assert!(results.into_iter().all(|chunk| chunk.is_ok()));
     }
 }
@@ -1,11 +1,185 @@
-use crate::ads::{AdsOperations, CryptoAccumulatorAds, MptAds};
-use std::sync::{Arc, RwLock};
+use crate::ads::registry::create_ads;
+use crate::ads::state::{put_bytes, put_u32, put_u64, StateReader};
+use crate::ads::{
+    AdsOperations, AdsPool, CryptoAccumulatorAds, MerkleTreeAds, MptAds, Mutation, PersistentAds,
+    QueryBatch, SmtAds,
+};
+use crate::error::StoragerError;
+use crate::expiry::{ExpiryIndex, SweepLog};
+use crate::intern::FidInterner;
+use crate::keyword_stats::KeywordStats;
+use crate::namespace::{NamespaceAds, Namespaces};
+use crate::proof_queue::{PendingProof, ProofQueue};
+use crate::request_log::{Claim, MutationOutcome, RequestLog};
+use common::bloom::{KeywordFilterCache, KeywordFilterSnapshot};
+use common::clock::{system_clock, SharedClock};
+use common::fid_intern::{
+    check_keyword, digest_entry, is_reserved_keyword, parse_digest_entry, InternedProof,
+    FID_TABLE_KEYWORD,
+};
+use common::query_stream::DEFAULT_FIDS_PER_CHUNK;
+use common::sketch::HyperLogLog;
+use common::transport::TransportConfig;
+use common::{AdsError, AdsMode, Proof, RootHash};
+use esa_rust::merkle_tree::leaf_hash_bytes;
+use esa_rust::mpt::{RocksDbAdapter, SliceMetrics};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock, RwLockWriteGuard};
+use std::time::Duration;
+#[cfg(feature = "sled")]
+use storage_backend::sled::SledStore;
+use storage_backend::{Column, ColumnStore};
+use tracing::{debug, warn};
+
+/// 密码学子系统的健康状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CryptoHealth {
+    /// 参数已初始化并通过自检
+    Ready,
+    /// 当前 ADS 不依赖密码学参数
+    NotRequired,
+    /// 初始化失败，ADS 请求会被拒绝
+    Failed(String),
+}
+
+impl CryptoHealth {
+    /// 是否可以处理 ADS 请求
+    pub fn is_ready(&self) -> bool {
+        !matches!(self, CryptoHealth::Failed(_))
+    }
+}
+
+/// ADS 状态的存储后端
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DbBackend {
+    /// 只保存在内存中，进程退出后丢失
+    #[default]
+    Memory,
+    /// 保存在指定目录的 RocksDB 中，重启后恢复（见 [`PersistentAds`]）
+    RocksDb(PathBuf),
+    /// 保存在指定目录的 sled 数据库中，不需要 C++ 工具链（`sled` feature）
+    #[cfg(feature = "sled")]
+    Sled(PathBuf),
+}
+
+impl DbBackend {
+    /// 解析命令行中的后端名称（"memory"、"rocksdb" 或 "sled"），持久化后端需要数据目录
+    pub fn parse(name: &str, path: Option<&str>) -> Result<Self, String> {
+        match (name, path) {
+            ("memory", _) => Ok(DbBackend::Memory),
+            ("rocksdb", Some(path)) => Ok(DbBackend::RocksDb(PathBuf::from(path))),
+            #[cfg(feature = "sled")]
+            ("sled", Some(path)) => Ok(DbBackend::Sled(PathBuf::from(path))),
+            ("rocksdb" | "sled", None) => Err(format!("the {} backend requires --db-path", name)),
+            #[cfg(not(feature = "sled"))]
+            ("sled", Some(_)) => Err("storager was built without the sled feature".to_string()),
+            (other, _) => Err(format!("unknown database backend '{}'", other)),
+        }
+    }
+
+    /// 命名空间使用的后端：持久化后端位于数据目录的 `namespaces/<名称>` 子目录
+    pub fn for_namespace(&self, name: &str) -> DbBackend {
+        match self {
+            DbBackend::Memory => DbBackend::Memory,
+            DbBackend::RocksDb(path) => DbBackend::RocksDb(Self::namespace_dir(path).join(name)),
+            #[cfg(feature = "sled")]
+            DbBackend::Sled(path) => DbBackend::Sled(Self::namespace_dir(path).join(name)),
+        }
+    }
+
+    /// fid 驻留表使用的后端：持久化后端位于数据目录的 `fid_table` 子目录
+    fn for_fid_table(&self) -> DbBackend {
+        match self {
+            DbBackend::Memory => DbBackend::Memory,
+            DbBackend::RocksDb(path) => DbBackend::RocksDb(path.join("fid_table")),
+            #[cfg(feature = "sled")]
+            DbBackend::Sled(path) => DbBackend::Sled(path.join("fid_table")),
+        }
+    }
+
+    /// 打开后端的列族存储，内存后端返回 None
+    fn open_store(&self) -> Result<Option<Box<dyn ColumnStore>>, String> {
+        Ok(Some(match self {
+            DbBackend::Memory => return Ok(None),
+            DbBackend::RocksDb(path) => {
+                Box::new(RocksDbAdapter::open(path).map_err(|e| e.to_string())?)
+            }
+            #[cfg(feature = "sled")]
+            DbBackend::Sled(path) => Box::new(SledStore::open(path).map_err(|e| e.to_string())?),
+        }))
+    }
+
+    /// 数据目录中已有的命名空间
+    fn existing_namespaces(&self) -> Result<BTreeSet<String>, String> {
+        let path = match self {
+            DbBackend::Memory => return Ok(BTreeSet::new()),
+            DbBackend::RocksDb(path) => path,
+            #[cfg(feature = "sled")]
+            DbBackend::Sled(path) => path,
+        };
+        let dir = Self::namespace_dir(path);
+        if !dir.exists() {
+            return Ok(BTreeSet::new());
+        }
+        let entries = std::fs::read_dir(&dir).map_err(|e| e.to_string())?;
+        let mut names = BTreeSet::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| e.to_string())?;
+            if entry.path().is_dir() {
+                names.extend(entry.file_name().into_string().ok());
+            }
+        }
+        Ok(names)
+    }
+
+    fn namespace_dir(path: &Path) -> PathBuf {
+        path.join("namespaces")
+    }
+}
 
 /// Storager 结构
 ///
-/// 负责管理单个存储节点的 ADS 实例
+/// 负责管理单个存储节点的 ADS 实例，克隆共享同一份状态
+#[derive(Clone)]
 pub struct Storager {
     pub(crate) ads: Arc<RwLock<Box<dyn AdsOperations>>>,
+    /// 可选的 fid 驻留表（启用后 ADS 中保存长 fid 的紧凑 id）
+    pub(crate) interner: Option<Arc<RwLock<FidInterner>>>,
+    /// ADS 状态的存储后端，持久化后端的 fid 驻留表也保存在其数据目录中
+    pub(crate) backend: DbBackend,
+    /// 每个 keyword 的 HyperLogLog 草图
+    pub(crate) sketches: Arc<RwLock<BTreeMap<String, HyperLogLog>>>,
+    /// 每个 keyword 的 fid 数量和查询次数（见 [`crate::keyword_stats`]）
+    pub(crate) keyword_stats: Arc<KeywordStats>,
+    /// 最近一次发给 Manager 的 keyword 过滤器（见 [`common::bloom`]）
+    pub(crate) keyword_filter: Arc<KeywordFilterCache>,
+    /// 后台分片修复的时间片统计
+    pub(crate) fix_metrics: Arc<RwLock<SliceMetrics>>,
+    /// 时间源
+    pub(crate) clock: SharedClock,
+    /// 密码学子系统的健康状态
+    pub(crate) crypto_health: Arc<RwLock<CryptoHealth>>,
+    /// 是否拒绝写请求（进程交接期间）
+    pub(crate) frozen: Arc<AtomicBool>,
+    /// ADS 的版本号：每次写入后加一，只在持有 ADS 写锁时修改
+    pub(crate) epoch: Arc<AtomicU64>,
+    /// 执行 ADS 操作的线程池
+    pub(crate) pool: AdsPool,
+    /// 延迟生成的写证明（`defer_proof` 请求）
+    pub(crate) proofs: Arc<ProofQueue>,
+    /// 最近写请求的结果，Manager 重试时不会重复应用（见 [`crate::request_log`]）
+    pub(crate) requests: Arc<RequestLog>,
+    /// 连接其他 storager（迁移）时使用的传输参数
+    pub(crate) transport: TransportConfig,
+    /// 默认命名空间之外的命名空间（见 [`crate::namespace`]）
+    pub(crate) namespaces: Arc<Namespaces>,
+    /// 带过期时间的 (keyword, fid)（见 [`crate::expiry`]）
+    pub(crate) expiries: Arc<ExpiryIndex>,
+    /// 过期清理删除的记录，Manager 通过 `ListExpired` 拉取
+    pub(crate) swept: Arc<SweepLog>,
 }
 
 impl Storager {
@@ -16,35 +190,81 @@ impl Storager {
 
     /// 使用密码学累加器创建实例
     pub fn with_crypto_accumulator() -> Self {
-        let ads: Box<dyn AdsOperations> = Box::new(CryptoAccumulatorAds::new());
-        Storager {
-            ads: Arc::new(RwLock::new(ads)),
-        }
+        Self::with_ads(Box::new(CryptoAccumulatorAds::new()))
+            .with_namespace_ads(|_| Ok(Box::new(CryptoAccumulatorAds::new())))
     }
 
     /// 使用 Merkle Patricia Trie 创建实例
     pub fn with_mpt() -> Self {
-        let ads: Box<dyn AdsOperations> = Box::new(MptAds::new());
+        Self::with_ads(Box::new(MptAds::new())).with_namespace_ads(|_| Ok(Box::new(MptAds::new())))
+    }
+
+    /// 使用二叉 Merkle 树创建实例
+    pub fn with_merkle_tree() -> Self {
+        Self::with_ads(Box::new(MerkleTreeAds::new()))
+            .with_namespace_ads(|_| Ok(Box::new(MerkleTreeAds::new())))
+    }
+
+    /// 使用稀疏 Merkle 树创建实例
+    pub fn with_sparse_merkle_tree() -> Self {
+        Self::with_ads(Box::new(SmtAds::new())).with_namespace_ads(|_| Ok(Box::new(SmtAds::new())))
+    }
+
+    /// 使用任意 ADS 实例创建 Storager
+    ///
+    /// 这样创建的实例只有默认命名空间，需要其他命名空间时用
+    /// [`with_namespace_ads`](Self::with_namespace_ads) 提供创建方式
+    pub fn with_ads(ads: Box<dyn AdsOperations>) -> Self {
         Storager {
             ads: Arc::new(RwLock::new(ads)),
+            interner: None,
+            backend: DbBackend::Memory,
+            sketches: Arc::new(RwLock::new(BTreeMap::new())),
+            keyword_stats: Arc::new(KeywordStats::new()),
+            keyword_filter: Arc::new(KeywordFilterCache::default()),
+            fix_metrics: Arc::new(RwLock::new(SliceMetrics::default())),
+            clock: system_clock(),
+            crypto_health: Arc::new(RwLock::new(CryptoHealth::NotRequired)),
+            frozen: Arc::new(AtomicBool::new(false)),
+            epoch: Arc::new(AtomicU64::new(0)),
+            pool: AdsPool::global(),
+            proofs: Arc::new(ProofQueue::default()),
+            requests: Arc::new(RequestLog::default()),
+            transport: TransportConfig::default(),
+            namespaces: Arc::new(Namespaces::default()),
+            expiries: Arc::new(ExpiryIndex::default()),
+            swept: Arc::new(SweepLog::default()),
         }
     }
 
+    /// 设置为新命名空间创建 ADS 的方式（必须在第一个命名空间创建之前调用）
+    pub fn with_namespace_ads<F>(mut self, create: F) -> Self
+    where
+        F: Fn(&str) -> Result<Box<dyn AdsOperations>, String> + Send + Sync + 'static,
+    {
+        self.namespaces = Arc::new(Namespaces::new(Arc::new(create), BTreeSet::new()));
+        self
+    }
+
     /// 根据配置字符串创建实例
     ///
     /// # Arguments
-    /// * `ads_type` - ADS 类型: "accumulator" 或 "mpt"
+    /// * `ads_type` - ADS 类型: "accumulator"、"mpt"、"merkle"、"smt" 或已注册的第三方 ADS 名称
     ///
     /// # Examples
     /// ```
+    /// use storager::Storager;
+    ///
     /// let storager = Storager::from_config("mpt");
     /// ```
     pub fn from_config(ads_type: &str) -> Self {
-        match ads_type.to_lowercase().as_str() {
-            "mpt" => Self::with_mpt(),
-            "accumulator" | "crypto" => Self::with_crypto_accumulator(),
-            _ => {
-                eprintln!(
+        match AdsMode::from_name(ads_type).and_then(|mode| Some((mode, create_ads(mode)?))) {
+            Some((mode, ads)) => Self::with_ads(ads).with_namespace_ads(move |_| {
+                create_ads(mode)
+                    .ok_or_else(|| format!("ADS '{}' is not registered", String::from(mode)))
+            }),
+            None => {
+                warn!(
                     "Unknown ADS type '{}', using default (crypto accumulator)",
                     ads_type
                 );
@@ -52,6 +272,664 @@ impl Storager {
             }
         }
     }
+
+    /// 根据配置字符串和存储后端创建实例
+    ///
+    /// 内存后端与 [`from_config`](Self::from_config) 相同；持久化后端恢复目录中已有的状态，
+    /// 此时 ADS 类型必须是已知的，且与创建数据库时一致。
+    /// 目录中已有的命名空间在第一次被请求时打开
+    ///
+    /// # Arguments
+    /// * `ads_type` - ADS 类型，同 [`from_config`](Self::from_config)
+    /// * `backend` - 存储后端
+    pub fn open(ads_type: &str, backend: &DbBackend) -> Result<Self, String> {
+        if *backend == DbBackend::Memory {
+            return Ok(Self::from_config(ads_type));
+        }
+        let mode = AdsMode::from_name(ads_type)
+            .ok_or_else(|| format!("unknown ADS type '{}'", ads_type))?;
+        let ads = Self::open_persistent(mode, backend)?;
+
+        let root = backend.clone();
+        let create: NamespaceAds = Arc::new(move |name| {
+            let backend = root.for_namespace(name);
+            // RocksDB 只创建最后一级目录
+            if let DbBackend::RocksDb(path) = &backend {
+                std::fs::create_dir_all(path).map_err(|e| e.to_string())?;
+            }
+            Self::open_persistent(mode, &backend)
+        });
+        let mut storager = Self::with_ads(ads);
+        storager.backend = backend.clone();
+        storager.namespaces = Arc::new(Namespaces::new(create, backend.existing_namespaces()?));
+        Ok(storager)
+    }
+
+    fn open_persistent(
+        mode: AdsMode,
+        backend: &DbBackend,
+    ) -> Result<Box<dyn AdsOperations>, String> {
+        match backend.open_store()? {
+            Some(store) => Ok(Box::new(PersistentAds::open(mode, store.as_ref())?)),
+            None => create_ads(mode).ok_or("unknown ADS type".to_string()),
+        }
+    }
+
+    /// 启用 fid 驻留
+    ///
+    /// 长 fid 在 RPC 边界被翻译为紧凑 id 后再写入 ADS，映射表的摘要记录在 ADS 的保留 keyword 下
+    /// （见 [`crate::intern`]）；持久化后端重启时载入数据目录中的映射表
+    pub fn with_fid_interning(mut self) -> Result<Self, String> {
+        self.interner = Some(Arc::new(RwLock::new(self.load_interner()?)));
+        Ok(self)
+    }
+
+    /// 载入后端中的驻留表，并截断到与 ADS 中的摘要一致
+    pub(crate) fn load_interner(&self) -> Result<FidInterner, String> {
+        let mut interner = match self.backend.for_fid_table().open_store()? {
+            Some(store) => {
+                FidInterner::open(store.column(Column::FidTable).map_err(|e| e.to_string())?)?
+            }
+            None => FidInterner::new(),
+        };
+        let committed = self.ads.read().unwrap().query(FID_TABLE_KEYWORD).0;
+        let committed = match committed.as_slice() {
+            [] => None,
+            [entry] => Some(
+                parse_digest_entry(entry)
+                    .ok_or_else(|| format!("invalid fid table digest '{}'", entry))?,
+            ),
+            _ => return Err("the ADS holds more than one fid table digest".to_string()),
+        };
+        if !interner.restore(committed)? {
+            return Err("the fid table does not match the digest committed in the ADS".to_string());
+        }
+        Ok(interner)
+    }
+
+    /// 在指定的线程池中执行 ADS 操作（默认使用 rayon 的全局线程池）
+    pub fn with_ads_pool(mut self, pool: AdsPool) -> Self {
+        self.pool = pool;
+        self
+    }
+
+    /// 使用指定的证明队列（设置保留的句柄数）
+    pub fn with_proof_queue(mut self, queue: ProofQueue) -> Self {
+        self.proofs = Arc::new(queue);
+        self
+    }
+
+    /// 设置迁移时发往其他 storager 的消息大小上限、压缩算法和 keepalive
+    pub fn with_transport(mut self, transport: TransportConfig) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// 使用指定的时间源（测试和确定性模拟使用）
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 设置密码学子系统的健康状态（启动时初始化参数后调用）
+    pub fn with_crypto_health(self, health: CryptoHealth) -> Self {
+        self.set_crypto_health(health);
+        self
+    }
+
+    /// 更新密码学子系统的健康状态
+    pub fn set_crypto_health(&self, health: CryptoHealth) {
+        *self.crypto_health.write().unwrap() = health;
+    }
+
+    /// 当前的密码学子系统健康状态
+    pub fn crypto_health(&self) -> CryptoHealth {
+        self.crypto_health.read().unwrap().clone()
+    }
+
+    /// 在 ADS 线程池中执行 `f`，RPC 处理函数只等待结果，不占用 Tokio 工作线程
+    pub(crate) async fn run_ads<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&Storager) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let storager = self.clone();
+        // 线程池中的 span（证明生成等）挂在当前 RPC 的 span 下
+        let span = tracing::Span::current();
+        self.pool.run(move || span.in_scope(|| f(&storager))).await
+    }
+
+    /// 执行写请求；相同 `request_id` 的重试不再执行 `apply`，而是返回第一次执行的结果
+    ///
+    /// `request_id` 为空时不去重。第一次执行失败时不留下记录，等待它的重试会自己执行
+    pub(crate) async fn deduplicate<F, E>(
+        &self,
+        request_id: &str,
+        apply: F,
+    ) -> Result<MutationOutcome, E>
+    where
+        F: Future<Output = Result<MutationOutcome, E>>,
+    {
+        if request_id.is_empty() {
+            return apply.await;
+        }
+        loop {
+            match self.requests.claim(request_id) {
+                Claim::First(slot) => {
+                    let outcome = apply.await?;
+                    slot.complete(outcome.clone());
+                    return Ok(outcome);
+                }
+                Claim::Duplicate(rx) => {
+                    if let Some(outcome) = RequestLog::wait(rx).await {
+                        debug!(
+                            "Request {} already applied, returning its result",
+                            request_id
+                        );
+                        return Ok(outcome);
+                    }
+                }
+            }
+        }
+    }
+
+    /// 取得 ADS 写锁；持有写锁的线程 panic 过时返回错误，而不是让这次请求也 panic
+    pub(crate) fn write_ads(
+        &self,
+    ) -> Result<RwLockWriteGuard<'_, Box<dyn AdsOperations>>, AdsError> {
+        self.ads.write().map_err(|_| AdsError::LockPoisoned)
+    }
+
+    /// 执行写操作；`defer` 为真时只更新 ADS，证明留给后台队列生成
+    pub(crate) fn apply_mutation(
+        &self,
+        ads: &mut dyn AdsOperations,
+        mutation: Mutation,
+        defer: bool,
+    ) -> Result<(PendingProof, RootHash), AdsError> {
+        if defer {
+            let (job, root_hash) = ads.apply_deferred(mutation)?;
+            return Ok((PendingProof::Deferred(job), root_hash));
+        }
+        let _span = tracing::info_span!("prove_mutation").entered();
+        let (proof, root_hash) = match mutation {
+            Mutation::Add { keyword, fid } => ads.add(keyword, fid)?,
+            Mutation::AddBatch { keywords, fid } => ads.add_batch(keywords, fid)?,
+            Mutation::Delete { keyword, fid } => ads.delete(keyword, fid)?,
+        };
+        Ok((PendingProof::Ready(proof), root_hash))
+    }
+
+    /// 密码学子系统不可用时拒绝 ADS 请求，避免在未初始化的参数上 panic
+    pub(crate) fn ensure_crypto_ready(&self) -> Result<(), StoragerError> {
+        match &*self.crypto_health.read().unwrap() {
+            CryptoHealth::Failed(reason) => Err(StoragerError::CryptoUnavailable(reason.clone())),
+            _ => Ok(()),
+        }
+    }
+
+    /// 拒绝之后的写请求（查询不受影响）
+    pub fn freeze(&self) {
+        self.frozen.store(true, Ordering::SeqCst);
+    }
+
+    /// 恢复写请求
+    pub fn unfreeze(&self) {
+        self.frozen.store(false, Ordering::SeqCst);
+    }
+
+    /// 是否正在拒绝写请求
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.load(Ordering::SeqCst)
+    }
+
+    /// 拒绝写入 fid 驻留使用的保留 keyword
+    pub(crate) fn check_keywords<S: AsRef<str>>(keywords: &[S]) -> Result<(), StoragerError> {
+        keywords
+            .iter()
+            .try_for_each(|keyword| check_keyword(keyword.as_ref()))
+            .map_err(StoragerError::InvalidRequest)
+    }
+
+    /// 冻结期间拒绝写请求
+    pub(crate) fn ensure_writable(&self) -> Result<(), StoragerError> {
+        if self.is_frozen() {
+            Err(StoragerError::HandingOver)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 当前 ADS 的版本号（从未写入时为 0）
+    ///
+    /// 响应中的根哈希和证明都对应这个版本，Manager 据此选择验证用的历史根哈希
+    pub fn epoch(&self) -> u64 {
+        self.epoch.load(Ordering::SeqCst)
+    }
+
+    /// 记录一次写入，返回写入后的版本号（调用方必须持有 ADS 写锁）
+    pub(crate) fn advance_epoch(&self) -> u64 {
+        self.epoch.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// 是否启用了 fid 驻留
+    pub fn fid_interning_enabled(&self) -> bool {
+        self.interner.is_some()
+    }
+
+    /// 将请求中的 fid 翻译为写入 ADS 的形式
+    ///
+    /// 新驻留的 fid 追加到映射表，并把 ADS 中的映射表摘要换成新的摘要
+    pub(crate) fn intern_fid(
+        &self,
+        ads: &mut dyn AdsOperations,
+        fid: &str,
+    ) -> Result<String, AdsError> {
+        let interner = match &self.interner {
+            Some(interner) => interner,
+            None => return Ok(fid.to_string()),
+        };
+
+        let mut interner = interner.write().unwrap();
+        let old = interner.digest();
+        let (stored, is_new) = interner.intern(fid).map_err(AdsError::Backend)?;
+        if is_new {
+            let new = digest_entry(&interner.digest().expect("fid table is not empty"));
+            let committed = match old {
+                Some(old) => ads.update(FID_TABLE_KEYWORD, &digest_entry(&old), &new),
+                None => ads.add(FID_TABLE_KEYWORD, &new),
+            };
+            if let Err(e) = committed {
+                // 摘要没有更新，撤销映射表中的新条目
+                let len = interner.len() - 1;
+                interner.truncate(len).map_err(AdsError::Backend)?;
+                return Err(e);
+            }
+        }
+        Ok(stored)
+    }
+
+    /// 查找 fid 在 ADS 中保存的形式（不会创建新条目）
+    pub(crate) fn lookup_fid(&self, fid: &str) -> String {
+        match &self.interner {
+            Some(interner) => interner.read().unwrap().lookup(fid),
+            None => fid.to_string(),
+        }
+    }
+
+    /// 将 ADS 返回的 fid 列表翻译回真实 fid
+    pub(crate) fn resolve_fids(&self, fids: Vec<String>) -> Vec<String> {
+        match &self.interner {
+            Some(interner) => interner.read().unwrap().translate_back(fids),
+            None => fids,
+        }
+    }
+
+    /// 将 ADS 的查询结果翻译回真实 fid
+    ///
+    /// 结果中有紧凑 id 时，证明换成 [`Proof::Interned`]：附带映射表摘要的证明和各紧凑 id 的
+    /// 映射表条目，Manager 据此把证明中的 id 还原为 fid
+    pub(crate) fn resolve_query(
+        &self,
+        ads: &dyn AdsOperations,
+        (stored, proof): (Vec<String>, Proof),
+    ) -> (Vec<String>, Proof) {
+        let Some(interner) = &self.interner else {
+            return (stored, proof);
+        };
+        let interner = interner.read().unwrap();
+        let entries = interner.entries_for(&stored);
+        if entries.is_empty() {
+            return (stored, proof);
+        }
+        let proof = InternedProof {
+            proof,
+            table: ads.query(FID_TABLE_KEYWORD).1,
+            entries,
+        };
+        (
+            interner.translate_back(stored),
+            Proof::Interned(proof.to_bytes()),
+        )
+    }
+
+    /// 流式查询中从 `offset` 开始的一批 fid 及只覆盖它们的证明
+    /// 返回: ((fids, proof, 列表总长度), epoch)
+    ///
+    /// ADS 不能逐批证明时返回 None；启用 fid 驻留时也返回 None，证明要附带映射表条目，
+    /// 整体传输（见 [`resolve_query`](Self::resolve_query)）。`epoch` 是流开始时的版本号，之后有写入时拒绝继续，
+    /// 各批证明才对应同一个根
+    pub(crate) fn query_batch(
+        &self,
+        keyword: &str,
+        offset: usize,
+        epoch: Option<u64>,
+    ) -> Result<Option<(QueryBatch, u64)>, StoragerError> {
+        let ads = self.ads.read().unwrap();
+        let current = self.epoch();
+        if epoch.is_some_and(|epoch| epoch != current) {
+            return Err(StoragerError::Precondition(format!(
+                "'{}' changed while streaming its query, retry",
+                keyword
+            )));
+        }
+        if self.interner.is_some() {
+            return Ok(None);
+        }
+        let batch = tracing::info_span!("prove_query")
+            .in_scope(|| ads.query_batch(keyword, offset, DEFAULT_FIDS_PER_CHUNK));
+        Ok(batch.map(|batch| (batch, current)))
+    }
+
+    /// 记录写入 keyword 的 fid：加入 keyword 的草图，fid 计数加一
+    ///
+    /// 返回加入后草图的叶子哈希，随写入响应交给 Manager 跟踪
+    pub(crate) fn record_added(&self, keyword: &str, fid: &str) -> RootHash {
+        let mut sketches = self.sketches.write().unwrap();
+        let sketch = sketches.entry(keyword.to_string()).or_default();
+        sketch.insert(fid);
+        self.keyword_stats.record_add(keyword);
+        leaf_hash_bytes(keyword, &sketch.to_bytes()).to_vec()
+    }
+
+    /// 每个 keyword 的 fid 数量和查询次数
+    pub fn keyword_stats(&self) -> &KeywordStats {
+        &self.keyword_stats
+    }
+
+    /// 获取 keyword 的草图编码，keyword 没有草图时返回 None
+    pub fn sketch(&self, keyword: &str) -> Option<Vec<u8>> {
+        self.sketches
+            .read()
+            .unwrap()
+            .get(keyword)
+            .map(HyperLogLog::to_bytes)
+    }
+
+    /// 启动后台分片修复任务
+    ///
+    /// 每隔 `interval` 获取一次 ADS 写锁，最多执行 `budget` 时长的修复工作后释放，
+    /// 两个时间片之间查询照常进行（读取的是已修复的稳定快照）。
+    /// 已打开的命名空间各自获得一个时间片
+    ///
+    /// # 参数
+    ///
+    /// * `interval` - 时间片之间的间隔
+    /// * `budget` - 单个时间片的时间预算
+    pub fn spawn_background_fix(
+        &self,
+        interval: Duration,
+        budget: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        let storager = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+
+                let namespaces = storager.opened_namespaces().into_iter().map(|(_, s)| s);
+                for instance in std::iter::once(storager.clone()).chain(namespaces) {
+                    if !instance.ads.read().unwrap().needs_maintenance() {
+                        continue;
+                    }
+
+                    let start = instance.clock.monotonic();
+                    instance.ads.write().unwrap().maintenance_slice(budget);
+                    instance
+                        .fix_metrics
+                        .write()
+                        .unwrap()
+                        .record(instance.clock.monotonic().saturating_sub(start));
+                }
+            }
+        })
+    }
+
+    /// 强制完成所有待修复的工作（发布根哈希之前调用）
+    pub fn finish_background_fix(&self) {
+        let start = self.clock.monotonic();
+        let mut ads = self.ads.write().unwrap();
+        if ads.needs_maintenance() {
+            ads.finish_maintenance();
+            let elapsed = self.clock.monotonic().saturating_sub(start);
+            self.fix_metrics.write().unwrap().record(elapsed);
+        }
+    }
+
+    /// 完成待修复的工作并把 ADS 落盘，对已打开的命名空间同样处理
+    ///
+    /// 返回处理的命名空间数量（包括默认命名空间）
+    pub fn flush_namespaces(&self) -> Result<u32, StoragerError> {
+        let namespaces = self.opened_namespaces();
+        for storager in std::iter::once(self).chain(namespaces.iter().map(|(_, s)| s)) {
+            storager.finish_background_fix();
+            storager
+                .ads
+                .write()
+                .unwrap()
+                .flush()
+                .map_err(StoragerError::Precondition)?;
+        }
+        Ok(namespaces.len() as u32 + 1)
+    }
+
+    /// 进程退出之前调用：等待推迟生成的证明完成，再把所有命名空间落盘
+    ///
+    /// 调用时服务应已停止接受请求（见 [`common::net::serve_with_drain`]），
+    /// 返回落盘的命名空间数量
+    pub async fn shutdown(&self) -> Result<u32, StoragerError> {
+        let namespaces = self.opened_namespaces();
+        for storager in std::iter::once(self).chain(namespaces.iter().map(|(_, s)| s)) {
+            storager.proofs.drain().await;
+        }
+        self.run_ads(|storager| storager.flush_namespaces()).await
+    }
+
+    /// 导出全部状态（ADS、fid 驻留表、草图、所有命名空间和过期时间），用于进程交接
+    ///
+    /// 导出前先完成待修复的工作；调用方应先 [`freeze`](Self::freeze)，
+    /// 否则导出之后的写入会丢失
+    pub fn export_state(&self) -> Result<Vec<u8>, String> {
+        self.finish_background_fix();
+
+        let mut buf = Vec::new();
+        let ads_state = self
+            .ads
+            .read()
+            .unwrap()
+            .export_state()
+            .ok_or("the configured ADS does not support state export")?;
+        put_bytes(&mut buf, &ads_state);
+
+        let fids = match &self.interner {
+            Some(interner) => interner.read().unwrap().entries(),
+            None => Vec::new(),
+        };
+        put_u32(&mut buf, fids.len() as u32);
+        for fid in &fids {
+            put_bytes(&mut buf, fid.as_bytes());
+        }
+
+        let sketches = self.sketches.read().unwrap();
+        put_u32(&mut buf, sketches.len() as u32);
+        for (keyword, sketch) in sketches.iter() {
+            put_bytes(&mut buf, keyword.as_bytes());
+            put_bytes(&mut buf, &sketch.to_bytes());
+        }
+        put_u64(&mut buf, self.epoch());
+
+        let names = self.namespaces();
+        put_u32(&mut buf, names.len() as u32);
+        for name in &names {
+            let state = self
+                .namespace(name)
+                .map_err(|e| e.to_string())?
+                .export_state()?;
+            put_bytes(&mut buf, name.as_bytes());
+            put_bytes(&mut buf, &state);
+        }
+
+        let expiries = self.expiries.entries();
+        put_u32(&mut buf, expiries.len() as u32);
+        for (keyword, fid, expires_at_ms) in &expiries {
+            put_bytes(&mut buf, keyword.as_bytes());
+            put_bytes(&mut buf, fid.as_bytes());
+            put_u64(&mut buf, *expires_at_ms);
+        }
+        Ok(buf)
+    }
+
+    /// 从 [`export_state`](Self::export_state) 的输出恢复状态
+    ///
+    /// 只能在新创建的实例上调用，ADS 类型和 fid 驻留配置必须与导出方一致
+    pub fn import_state(&self, state: &[u8]) -> Result<(), String> {
+        let mut reader = StateReader::new(state);
+        self.ads.write().unwrap().import_state(reader.bytes()?)?;
+
+        let count = reader.u32()?;
+        if count > 0 {
+            let interner = self
+                .interner
+                .as_ref()
+                .ok_or("state contains interned fids but fid interning is disabled")?;
+            let mut interner = interner.write().unwrap();
+            for _ in 0..count {
+                interner.intern(&reader.string()?)?;
+            }
+            let committed = self.ads.read().unwrap().query(FID_TABLE_KEYWORD).0;
+            if committed
+                != interner
+                    .digest()
+                    .map(|d| digest_entry(&d))
+                    .into_iter()
+                    .collect::<Vec<_>>()
+            {
+                return Err(
+                    "interned fids do not match the fid table digest in the state".to_string(),
+                );
+            }
+        }
+
+        let mut sketches = self.sketches.write().unwrap();
+        for _ in 0..reader.u32()? {
+            let keyword = reader.string()?;
+            let sketch = HyperLogLog::from_bytes(reader.bytes()?)
+                .ok_or_else(|| format!("invalid sketch for keyword '{}'", keyword))?;
+            sketches.insert(keyword, sketch);
+        }
+        // 新进程从旧进程的版本号继续，Manager 记录的历史根哈希仍然有效
+        self.epoch.store(reader.u64()?, Ordering::SeqCst);
+
+        // 支持命名空间之前的版本导出的状态到此结束
+        if !reader.is_empty() {
+            for _ in 0..reader.u32()? {
+                let name = reader.string()?;
+                self.namespace(&name)
+                    .map_err(|e| e.to_string())?
+                    .import_state(reader.bytes()?)?;
+            }
+        }
+        // 支持过期时间之前的版本导出的状态到此结束
+        if !reader.is_empty() {
+            for _ in 0..reader.u32()? {
+                let keyword = reader.string()?;
+                let fid = reader.string()?;
+                self.expiries.schedule(&keyword, &fid, reader.u64()?);
+            }
+        }
+        reader.finish()
+    }
+
+    /// 列出存储的 keyword（不含 fid 驻留表的保留 keyword），按字典序排列
+    pub fn keywords(&self) -> Result<Vec<String>, StoragerError> {
+        let mut keywords = self
+            .ads
+            .read()
+            .unwrap()
+            .keywords()
+            .ok_or(StoragerError::Unsupported("keyword enumeration"))?;
+        keywords.retain(|keyword| !is_reserved_keyword(keyword));
+        keywords.sort();
+        Ok(keywords)
+    }
+
+    /// 当前根哈希下所有 keyword 的 Bloom filter（写入之后第一次调用时重新构建）
+    pub fn keyword_filter(&self) -> Result<Arc<KeywordFilterSnapshot>, StoragerError> {
+        // 写入持有 ADS 写锁并在其中推进版本号，读锁下的根哈希、版本号和 keyword 列表一致
+        let ads = self.ads.read().unwrap();
+        let root_hash = ads
+            .root_hash()
+            .ok_or(StoragerError::Unsupported("keyword filters"))?;
+        let epoch = self.epoch();
+        self.keyword_filter.get_or_build(epoch, &root_hash, || {
+            let mut keywords = ads
+                .keywords()
+                .ok_or(StoragerError::Unsupported("keyword filters"))?;
+            keywords.retain(|keyword| !is_reserved_keyword(keyword));
+            Ok(KeywordFilterSnapshot::build(
+                &keywords,
+                root_hash.clone(),
+                epoch,
+            ))
+        })
+    }
+
+    /// 用迁移来的 fid 列表替换 keyword 的内容（关键词迁移的目标端）
+    ///
+    /// 删除不在列表中的 fid 并添加缺少的 fid，因此重复迁移同一个 keyword 是幂等的。
+    /// keyword 的草图按列表重建，Manager 可以从验证过的 fid 列表算出同样的草图。
+    /// 返回最后一次写入后的 (根哈希, 版本号)，内容没有变化时返回 None
+    pub fn replace_postings(
+        &self,
+        keyword: &str,
+        fids: &[String],
+    ) -> Result<Option<(RootHash, u64)>, StoragerError> {
+        Self::check_keywords(&[keyword])?;
+        self.ensure_crypto_ready()?;
+        let mut ads = self.write_ads()?;
+        self.ensure_writable()?;
+
+        let current = self.resolve_fids(ads.query(keyword).0);
+        let current: HashSet<String> = current.into_iter().collect();
+        let wanted: HashSet<&String> = fids.iter().collect();
+
+        let mut removed = current.iter().filter(|fid| !wanted.contains(fid));
+        let mut added = fids.iter().filter(|fid| !current.contains(*fid));
+
+        // 删除和添加的 fid 成对替换，剩下的单独删除或添加
+        let mut root_hash = None;
+        loop {
+            let result = match (removed.next(), added.next()) {
+                (None, None) => break,
+                (Some(old), None) => ads.delete(keyword, &self.lookup_fid(old))?,
+                (old, Some(new)) => {
+                    let stored = self.intern_fid(ads.as_mut(), new)?;
+                    self.record_added(keyword, new);
+                    match old {
+                        Some(old) => ads.update(keyword, &self.lookup_fid(old), &stored)?,
+                        None => ads.add(keyword, &stored)?,
+                    }
+                }
+            };
+            root_hash = Some(result.1);
+        }
+        self.keyword_stats.set_fids(keyword, fids.len());
+        let mut sketches = self.sketches.write().unwrap();
+        if fids.is_empty() {
+            sketches.remove(keyword);
+        } else {
+            let sketch = HyperLogLog::from_items(fids.iter().map(String::as_str));
+            sketches.insert(keyword.to_string(), sketch);
+        }
+        drop(sketches);
+        Ok(root_hash.map(|root_hash| (root_hash, self.advance_epoch())))
+    }
+
+    /// 后台分片修复的时间片统计
+    pub fn fix_metrics(&self) -> SliceMetrics {
+        self.fix_metrics.read().unwrap().clone()
+    }
 }
 
 impl Default for Storager {
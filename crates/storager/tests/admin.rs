@@ -0,0 +1,351 @@
+//! 管理接口测试
+//!
+//! Manager 在同一个端口上提供 `ManagerService` 和 `AdminService`：写入数据后检查集群状态、
+//! keyword 的基数和归属、热点 keyword、查询结果缓存和不存在 keyword 的缓存，再平衡后所有 keyword 仍能查到并通过验证，以及 FlushAll 的结果。
+
+mod support;
+
+use common::auth::attach_token;
+use common::rpc::admin_service_client::AdminServiceClient;
+use common::rpc::admin_service_server::AdminServiceServer;
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::rpc::{
+    query_request::QueryType, AckMode, AddRequest, ClusterStatusRequest, FlushAllRequest,
+    HotKeywordsRequest, KeywordStatsRequest, QueryRequest, RebalanceNowRequest,
+};
+use common::{AdsMode, ErrorKind};
+use manager::core::{Access, AccessControl, Principal};
+use manager::Manager;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use storager::Storager;
+use support::{add, query_keyword, serve};
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::{Channel, Server};
+use tonic::{Code, Request};
+
+/// 启动 `count` 个 storager 和同时提供两个服务的 Manager
+async fn start(
+    count: usize,
+    configure: impl FnOnce(Manager) -> Manager,
+) -> (ManagerServiceClient<Channel>, AdminServiceClient<Channel>) {
+    let mut storager_addrs = Vec::new();
+    for _ in 0..count {
+        let storager = StoragerServiceServer::new(Storager::with_mpt());
+        storager_addrs.push(serve(move || {
+            Server::builder().add_service(storager.clone())
+        }));
+    }
+
+    let manager = Arc::new(configure(Manager::new(storager_addrs, AdsMode::Mpt)));
+    let interceptor = manager.auth_interceptor();
+    let service = InterceptedService::new(
+        ManagerServiceServer::from_arc(manager.clone()),
+        interceptor.clone(),
+    );
+    let admin = InterceptedService::new(AdminServiceServer::from_arc(manager), interceptor);
+    let manager_addr = serve(move || {
+        Server::builder()
+            .add_service(service.clone())
+            .add_service(admin.clone())
+    });
+    let channel = Channel::from_shared(manager_addr)
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    (
+        ManagerServiceClient::new(channel.clone()),
+        AdminServiceClient::new(channel),
+    )
+}
+
+/// 写入一组 fid，返回每个 keyword 应有的 fid
+async fn populate(
+    client: &mut ManagerServiceClient<Channel>,
+) -> BTreeMap<String, BTreeSet<String>> {
+    let mut expected: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for i in 0..24 {
+        let fid = format!("f{}", i);
+        let keywords: Vec<String> = (0..3).map(|j| format!("kw{}", (i + j) % 16)).collect();
+        add(client, &fid, &keywords).await;
+        for keyword in keywords {
+            expected.entry(keyword).or_default().insert(fid.clone());
+        }
+    }
+    expected
+}
+
+/// 单关键词查询，返回验证通过的 fid
+async fn query_fids(client: &mut ManagerServiceClient<Channel>, keyword: &str) -> BTreeSet<String> {
+    let response = query_keyword(client, keyword).await;
+    assert!(response.verified);
+    response.fids.into_iter().collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cluster_status_and_keyword_stats() {
+    let (mut client, mut admin) = start(3, |manager| manager).await;
+    let expected = populate(&mut client).await;
+
+    let status = admin
+        .cluster_status(ClusterStatusRequest {})
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(status.ads_mode, "Mpt");
+    assert_eq!(status.replication_factor, 1);
+    let names: Vec<&str> = status.storagers.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["storager-0", "storager-1", "storager-2"]);
+    for storager in &status.storagers {
+        assert!(storager.reachable && storager.crypto_ready && storager.routed);
+        assert!(storager.virtual_nodes > 0);
+    }
+    let total: f64 = status.storagers.iter().map(|s| s.hash_space_fraction).sum();
+    assert!((total - 1.0).abs() < 1e-9);
+    // 每个收到过写入的 storager 都发布了根哈希
+    assert!(!status.roots.is_empty());
+    assert!(status.roots.iter().all(|root| !root.root_hash.is_empty()));
+
+    for (keyword, fids) in &expected {
+        let stats = admin
+            .keyword_stats(KeywordStatsRequest {
+                keyword: keyword.clone(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(stats.cardinality, fids.len() as u64, "keyword {}", keyword);
+        assert_eq!(stats.replicas, vec![stats.owner.clone()]);
+        let owner = status
+            .storagers
+            .iter()
+            .find(|s| s.name == stats.owner)
+            .unwrap();
+        assert_eq!(stats.owner_address, owner.address);
+    }
+
+    let status = admin
+        .keyword_stats(KeywordStatsRequest::default())
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_hot_keywords() {
+    let (mut client, mut admin) = start(3, |manager| manager).await;
+    let expected = populate(&mut client).await;
+
+    for keyword in std::iter::repeat_n("kw0", 10).chain(["kw1"]) {
+        client
+            .query(QueryRequest {
+                query_type: Some(QueryType::Keyword(keyword.to_string())),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+    }
+
+    let hot = admin
+        .hot_keywords(HotKeywordsRequest::default())
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(hot.unreachable.is_empty());
+    assert!(hot.total_queries >= 11);
+    let kw0 = &hot.keywords[0];
+    assert_eq!(kw0.keyword, "kw0");
+    assert_eq!(kw0.queries, 10);
+    assert_eq!(kw0.fids, expected["kw0"].len() as u64);
+    assert_eq!(kw0.replicas.len(), 1);
+    // 占大部分查询的 keyword 需要不止一个副本
+    assert!(kw0.suggested_replicas >= 2);
+
+    let status = admin
+        .hot_keywords(HotKeywordsRequest {
+            min_share: 2.0,
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_query_cache() {
+    let (mut client, mut admin) = start(2, |manager| manager.with_query_cache(16)).await;
+    let mut expected = populate(&mut client).await;
+
+    for _ in 0..3 {
+        assert_eq!(query_fids(&mut client, "kw0").await, expected["kw0"]);
+    }
+    let status = admin
+        .cluster_status(ClusterStatusRequest {})
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!((status.query_cache_hits, status.query_cache_misses), (2, 1));
+    assert_eq!(status.query_cache_entries, 1);
+    // 命中的查询不经过 storager
+    let hot = admin
+        .hot_keywords(HotKeywordsRequest::default())
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(hot.keywords[0].queries, 1);
+
+    // 写入发布新的根哈希后缓存的结果失效
+    let response = client
+        .add(AddRequest {
+            fid: "new".to_string(),
+            keywords: vec!["kw0".to_string()],
+            ack_mode: AckMode::Sync as i32,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.success, "{}", response.message);
+    expected.get_mut("kw0").unwrap().insert("new".to_string());
+    assert_eq!(query_fids(&mut client, "kw0").await, expected["kw0"]);
+    let status = admin
+        .cluster_status(ClusterStatusRequest {})
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!((status.query_cache_hits, status.query_cache_misses), (2, 2));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_negative_cache() {
+    let (mut client, mut admin) = start(2, |manager| manager.with_negative_cache(8)).await;
+    let expected = populate(&mut client).await;
+
+    for _ in 0..3 {
+        let response = client
+            .query(QueryRequest {
+                query_type: Some(QueryType::BooleanFunction("kw0 OR missing".to_string())),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.verified);
+        let fids: BTreeSet<String> = response.fids.into_iter().collect();
+        assert_eq!(fids, expected["kw0"]);
+    }
+    let status = admin
+        .cluster_status(ClusterStatusRequest {})
+        .await
+        .unwrap()
+        .into_inner();
+    // 只缓存不存在的 keyword
+    assert_eq!(status.negative_cache_hits, 2);
+    assert_eq!(status.negative_cache_entries, 1);
+    assert_eq!(
+        (status.query_cache_hits, status.query_cache_entries),
+        (0, 0)
+    );
+
+    // keyword 被写入后不再返回缓存的不存在结果
+    let response = client
+        .add(AddRequest {
+            fid: "new".to_string(),
+            keywords: vec!["missing".to_string()],
+            ack_mode: AckMode::Sync as i32,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.success, "{}", response.message);
+    assert_eq!(
+        query_fids(&mut client, "missing").await,
+        BTreeSet::from(["new".to_string()])
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rebalance_keeps_every_keyword() {
+    let (mut client, mut admin) = start(3, |manager| manager).await;
+    let expected = populate(&mut client).await;
+
+    let preview = admin
+        .rebalance_now(RebalanceNowRequest { dry_run: true })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(preview.imbalance_after < preview.imbalance_before);
+    assert!(!preview.ranges.is_empty());
+    assert_eq!(preview.migrated_keywords, 0);
+
+    let rebalance = admin
+        .rebalance_now(RebalanceNowRequest { dry_run: false })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(rebalance.ranges, preview.ranges);
+    assert_eq!(rebalance.imbalance_after, preview.imbalance_after);
+
+    // 再次平衡时已经没有更均衡的布局
+    let again = admin
+        .rebalance_now(RebalanceNowRequest { dry_run: true })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(again.imbalance_before, rebalance.imbalance_after);
+
+    for (keyword, fids) in &expected {
+        let response = client
+            .query(QueryRequest {
+                query_type: Some(QueryType::Keyword(keyword.clone())),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let found: BTreeSet<String> = response.fids.into_iter().collect();
+        assert_eq!(&found, fids, "keyword {}", keyword);
+        assert!(response.verified, "keyword {}", keyword);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_flush_all_requires_admin() {
+    let access = AccessControl::new()
+        .with_token("reader-token", Principal::new("reader", Access::Read))
+        .with_token("ops-token", Principal::new("ops", Access::Admin));
+    let (_, mut admin) = start(2, |manager| manager.with_access_control(access)).await;
+
+    let mut request = Request::new(FlushAllRequest {});
+    attach_token(&mut request, "reader-token").unwrap();
+    let status = admin.flush_all(request).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+    assert_eq!(ErrorKind::from_status(&status), Some(ErrorKind::Auth));
+
+    // 读权限可以查看集群状态
+    let mut request = Request::new(ClusterStatusRequest {});
+    attach_token(&mut request, "reader-token").unwrap();
+    assert_eq!(
+        admin
+            .cluster_status(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .storagers
+            .len(),
+        2
+    );
+
+    let mut request = Request::new(FlushAllRequest {});
+    attach_token(&mut request, "ops-token").unwrap();
+    let flushed = admin.flush_all(request).await.unwrap().into_inner();
+    assert_eq!(flushed.storagers.len(), 2);
+    for result in flushed.storagers {
+        assert!(result.success, "{}: {}", result.name, result.message);
+        assert_eq!(result.namespaces, 1);
+    }
+}
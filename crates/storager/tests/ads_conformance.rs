@@ -0,0 +1,475 @@
+//! ADS 一致性测试
+//!
+//! 对每个已注册且有工厂的 ADS 实现运行同一组场景（增删改查、批量添加、不存在的 key、
+//! 重复添加、布尔组合），要求所有实现给出相同的查询结果，
+//! 并且证明都能通过 Manager 的验证器。新增的 ADS 后端注册后会自动加入测试。
+//! 除固定场景外，还用随机生成的操作序列做差分测试，每个后端的观察结果都要与参考模型一致。
+//!
+//! 各实现在"证据能力"上允许存在差异（例如对空结果是否有可验证的证明），
+//! 这些差异在 [`expectations`] 中逐个声明，未声明的后端按最严格的要求检查。
+
+use common::registry::registered_ads_modes;
+use common::{parse_boolean_expr, AdsMode, Proof, RootHash};
+use manager::core::ProofVerifier;
+use proptest::collection::vec;
+use proptest::prelude::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use storager::ads::registry::create_ads;
+use storager::AdsOperations;
+
+/// 单个 ADS 在证据能力上的差异
+struct Expectations {
+    /// 不存在的 keyword 的查询证明能否通过验证，并表明该 keyword 确实不存在
+    absent_query_verifies: bool,
+    /// 删除不存在的 (keyword, fid) 时能否返回通过验证的证明；不能时删除返回错误
+    absent_delete_verifies: bool,
+    /// `root_hash()` 是否就是写操作返回的根
+    global_root: bool,
+}
+
+fn expectations(mode: AdsMode) -> Expectations {
+    match mode {
+        // 累加器无法证明被删除的 fid 不存在，这样的删除返回错误；
+        // 写操作返回的是单个 keyword 的累加器
+        AdsMode::CryptoAccumulator => Expectations {
+            absent_query_verifies: true,
+            absent_delete_verifies: false,
+            global_root: false,
+        },
+        _ => Expectations {
+            absent_query_verifies: true,
+            absent_delete_verifies: true,
+            global_root: true,
+        },
+    }
+}
+
+/// 所有可以实例化的 ADS 后端
+fn backends() -> Vec<AdsMode> {
+    let modes: Vec<AdsMode> = registered_ads_modes()
+        .iter()
+        .map(|d| d.mode())
+        .filter(|mode| create_ads(*mode).is_some())
+        .collect();
+    assert!(modes.len() >= 3, "built-in backends must be available");
+    modes
+}
+
+/// 模拟 Manager 驱动一个 ADS：验证每个证明，并像 Manager 一样只在验证通过后更新根
+struct Harness {
+    mode: AdsMode,
+    ads: Box<dyn AdsOperations>,
+    verifier: ProofVerifier,
+    root: RootHash,
+}
+
+impl Harness {
+    /// 与从空 storager 开始跟踪的 Manager 一样，以空 ADS 的根作为初始根
+    fn new(mode: AdsMode) -> Self {
+        let ads = create_ads(mode).unwrap();
+        let root = ads.root_hash().unwrap_or_default();
+        Harness {
+            mode,
+            ads,
+            verifier: ProofVerifier::new(mode),
+            root,
+        }
+    }
+
+    fn settle(&mut self, op: &str, proof: Proof, root: RootHash, must_verify: bool) {
+        let verified = self.verifier.verify(&proof, &root);
+        if must_verify {
+            assert!(verified, "[{}] {} proof rejected", self.mode.name(), op);
+        }
+        if verified {
+            if expectations(self.mode).global_root {
+                assert_eq!(
+                    self.ads.root_hash().as_ref(),
+                    Some(&root),
+                    "[{}] root_hash() differs from the {} root",
+                    self.mode.name(),
+                    op
+                );
+            }
+            self.root = root;
+        }
+    }
+
+    fn add(&mut self, keyword: &str, fid: &str) {
+        let (proof, root) = self.ads.add(keyword, fid).unwrap();
+        self.settle(&format!("add({}, {})", keyword, fid), proof, root, true);
+    }
+
+    fn add_batch(&mut self, keywords: &[&str], fid: &str) {
+        let keywords: Vec<String> = keywords.iter().map(|k| k.to_string()).collect();
+        let (proof, root) = self.ads.add_batch(&keywords, fid).unwrap();
+        self.settle(
+            &format!("add_batch({:?}, {})", keywords, fid),
+            proof,
+            root,
+            true,
+        );
+    }
+
+    fn delete(&mut self, keyword: &str, fid: &str) {
+        let present = self.query(keyword).contains(fid);
+        let must_verify = present || expectations(self.mode).absent_delete_verifies;
+
+        let op = format!("delete({}, {})", keyword, fid);
+        match self.ads.delete(keyword, fid) {
+            Ok((proof, root)) => self.settle(&op, proof, root, must_verify),
+            Err(e) => assert!(!must_verify, "[{}] {} failed: {}", self.mode.name(), op, e),
+        }
+    }
+
+    fn delete_batch(&mut self, keywords: &[&str], fid: &str) {
+        let keywords: Vec<String> = keywords.iter().map(|k| k.to_string()).collect();
+        let (proof, root) = self.ads.delete_batch(&keywords, fid).unwrap();
+        self.settle(
+            &format!("delete_batch({:?}, {})", keywords, fid),
+            proof,
+            root,
+            true,
+        );
+    }
+
+    /// 把 keyword 下的 old_fid 替换为 new_fid
+    fn replace_fid(&mut self, keyword: &str, old_fid: &str, new_fid: &str) {
+        let (proof, root) = self.ads.update(keyword, old_fid, new_fid).unwrap();
+        self.settle(
+            &format!("update({}, {}, {})", keyword, old_fid, new_fid),
+            proof,
+            root,
+            true,
+        );
+    }
+
+    /// 与 Manager 的 Update 一致：先删除旧关键词，再添加新关键词
+    fn update(&mut self, fid: &str, old_keywords: &[&str], new_keywords: &[&str]) {
+        for keyword in old_keywords {
+            self.delete(keyword, fid);
+        }
+        for keyword in new_keywords {
+            self.add(keyword, fid);
+        }
+    }
+
+    fn query(&self, keyword: &str) -> BTreeSet<String> {
+        let (fids, proof) = self.ads.query(keyword);
+        let must_verify = !fids.is_empty() || expectations(self.mode).absent_query_verifies;
+        if must_verify {
+            assert!(
+                self.verifier.verify(&proof, &self.root),
+                "[{}] query({}) proof rejected",
+                self.mode.name(),
+                keyword
+            );
+            assert!(
+                !fids.is_empty() || self.verifier.verify_absence(&proof, keyword),
+                "[{}] query({}) returned no fids without a non-existence proof",
+                self.mode.name(),
+                keyword
+            );
+            assert!(
+                fids.is_empty() || self.verifier.verify_completeness(&proof, keyword, &fids),
+                "[{}] query({}) result is incomplete",
+                self.mode.name(),
+                keyword
+            );
+        }
+        fids.into_iter().collect()
+    }
+
+    fn boolean(&self, expr: &str) -> BTreeSet<String> {
+        let expr = parse_boolean_expr(expr).unwrap();
+        let results: HashMap<String, HashSet<String>> = expr
+            .get_keywords()
+            .into_iter()
+            .map(|k| {
+                let fids = self.query(&k).into_iter().collect();
+                (k, fids)
+            })
+            .collect();
+        expr.evaluate(&results).into_iter().collect()
+    }
+}
+
+fn set(fids: &[&str]) -> BTreeSet<String> {
+    fids.iter().map(|s| s.to_string()).collect()
+}
+
+/// 在所有后端上运行同一场景，并要求观察结果完全一致
+fn run_scenario<F>(name: &str, scenario: F)
+where
+    F: Fn(&mut Harness) -> Vec<BTreeSet<String>>,
+{
+    let mut reference: Option<(AdsMode, Vec<BTreeSet<String>>)> = None;
+    for mode in backends() {
+        let mut harness = Harness::new(mode);
+        let observed = scenario(&mut harness);
+        match &reference {
+            None => reference = Some((mode, observed)),
+            Some((ref_mode, expected)) => assert_eq!(
+                &observed,
+                expected,
+                "scenario '{}': {} diverges from {}",
+                name,
+                mode.name(),
+                ref_mode.name()
+            ),
+        }
+    }
+}
+
+/// 注册表中声明的能力必须与后端的实际行为一致
+#[test]
+fn test_capabilities_match_backends() {
+    for descriptor in registered_ads_modes() {
+        let Some(mut ads) = create_ads(descriptor.mode()) else {
+            continue;
+        };
+        ads.add("go", "f1").unwrap();
+        ads.add("rust", "f2").unwrap();
+        assert_eq!(
+            descriptor.capabilities.supports_range,
+            ads.range_query("", "").is_some(),
+            "{}: supports_range does not match range_query",
+            descriptor.name
+        );
+    }
+}
+
+#[test]
+fn test_add_and_query() {
+    run_scenario("add/query", |h| {
+        h.add("rust", "f1");
+        h.add("rust", "f2");
+        h.add("go", "f1");
+        let observed = vec![h.query("rust"), h.query("go")];
+        assert_eq!(observed, vec![set(&["f1", "f2"]), set(&["f1"])]);
+        observed
+    });
+}
+
+#[test]
+fn test_batch_add() {
+    run_scenario("batch add", |h| {
+        h.add("rust", "f0");
+        h.add_batch(&["rust", "go", "db"], "f1");
+        h.add_batch(&["rust"], "f1");
+        let observed = vec![h.query("rust"), h.query("go"), h.query("db")];
+        assert_eq!(
+            observed,
+            vec![set(&["f0", "f1"]), set(&["f1"]), set(&["f1"])]
+        );
+        observed
+    });
+}
+
+#[test]
+fn test_duplicate_add() {
+    run_scenario("duplicates", |h| {
+        h.add("rust", "f1");
+        h.add("rust", "f1");
+        let after_add = h.query("rust");
+        h.delete("rust", "f1");
+        let after_delete = h.query("rust");
+        assert_eq!(after_add, set(&["f1"]));
+        assert!(after_delete.is_empty());
+        vec![after_add, after_delete]
+    });
+}
+
+#[test]
+fn test_absent_keys() {
+    run_scenario("absent keys", |h| {
+        h.add("rust", "f1");
+        let absent = h.query("java");
+        h.delete("java", "f1");
+        h.delete("rust", "f9");
+        let observed = vec![absent, h.query("rust"), h.query("java")];
+        assert_eq!(observed, vec![set(&[]), set(&["f1"]), set(&[])]);
+        observed
+    });
+}
+
+#[test]
+fn test_delete_last_and_readd() {
+    run_scenario("delete last", |h| {
+        h.add("rust", "f1");
+        h.add("rust", "f2");
+        h.delete("rust", "f1");
+        let one_left = h.query("rust");
+        h.delete("rust", "f2");
+        let emptied = h.query("rust");
+        h.add("rust", "f3");
+        let readded = h.query("rust");
+        assert_eq!(readded, set(&["f3"]));
+        vec![one_left, emptied, readded]
+    });
+}
+
+#[test]
+fn test_update() {
+    run_scenario("update", |h| {
+        h.add("rust", "f1");
+        h.add("go", "f1");
+        h.add("go", "f2");
+        h.update("f1", &["rust", "go"], &["go", "java"]);
+        let observed = vec![h.query("rust"), h.query("go"), h.query("java")];
+        assert_eq!(observed, vec![set(&[]), set(&["f1", "f2"]), set(&["f1"])]);
+        observed
+    });
+}
+
+#[test]
+fn test_replace_fid_and_delete_batch() {
+    run_scenario("replace/delete batch", |h| {
+        h.add("rust", "f1");
+        h.add("rust", "f2");
+        h.add("go", "f3");
+        h.replace_fid("rust", "f1", "f4");
+        // 唯一的 fid 被替换时 keyword 仍然存在
+        h.replace_fid("go", "f3", "f5");
+        let replaced = vec![h.query("rust"), h.query("go")];
+        assert_eq!(replaced, vec![set(&["f2", "f4"]), set(&["f5"])]);
+
+        h.add_batch(&["rust", "go", "db"], "f6");
+        h.delete_batch(&["rust", "go", "db"], "f6");
+        let mut observed = vec![h.query("rust"), h.query("go"), h.query("db")];
+        assert_eq!(observed, vec![set(&["f2", "f4"]), set(&["f5"]), set(&[])]);
+        observed.extend(replaced);
+        observed
+    });
+}
+
+#[test]
+fn test_boolean_combinations() {
+    run_scenario("boolean", |h| {
+        for (keyword, fid) in [
+            ("rust", "f1"),
+            ("rust", "f2"),
+            ("go", "f2"),
+            ("go", "f3"),
+            ("java", "f3"),
+        ] {
+            h.add(keyword, fid);
+        }
+        let observed = vec![
+            h.boolean("rust AND go"),
+            h.boolean("rust OR java"),
+            h.boolean("(rust OR go) AND java"),
+            h.boolean("rust AND python"),
+        ];
+        assert_eq!(
+            observed,
+            vec![
+                set(&["f2"]),
+                set(&["f1", "f2", "f3"]),
+                set(&["f3"]),
+                set(&[]),
+            ]
+        );
+        observed
+    });
+}
+
+/// 随机操作序列中的一步
+#[derive(Debug, Clone)]
+enum Op {
+    Add(&'static str, &'static str),
+    AddBatch(Vec<&'static str>, &'static str),
+    Delete(&'static str, &'static str),
+    Query(&'static str),
+    Boolean(&'static str),
+}
+
+const KEYWORDS: [&str; 4] = ["rust", "go", "db", "java"];
+const FIDS: [&str; 3] = ["f1", "f2", "f3"];
+
+fn op() -> impl Strategy<Value = Op> {
+    let keyword = || prop::sample::select(KEYWORDS.to_vec());
+    let fid = || prop::sample::select(FIDS.to_vec());
+    prop_oneof![
+        3 => (keyword(), fid()).prop_map(|(k, f)| Op::Add(k, f)),
+        1 => (prop::sample::subsequence(KEYWORDS.to_vec(), 1..=3), fid())
+            .prop_map(|(ks, f)| Op::AddBatch(ks, f)),
+        2 => (keyword(), fid()).prop_map(|(k, f)| Op::Delete(k, f)),
+        2 => keyword().prop_map(Op::Query),
+        1 => prop::sample::select(vec!["rust AND go", "rust OR db", "(go OR java) AND db"])
+            .prop_map(Op::Boolean),
+    ]
+}
+
+impl Op {
+    /// 在 ADS 上执行，查询类操作返回观察到的 fid 集合
+    fn apply(&self, h: &mut Harness) -> Option<BTreeSet<String>> {
+        match self {
+            Op::Add(keyword, fid) => h.add(keyword, fid),
+            Op::AddBatch(keywords, fid) => h.add_batch(keywords, fid),
+            Op::Delete(keyword, fid) => h.delete(keyword, fid),
+            Op::Query(keyword) => return Some(h.query(keyword)),
+            Op::Boolean(expr) => return Some(h.boolean(expr)),
+        }
+        None
+    }
+
+    /// 在参考模型（keyword -> fid 集合）上执行
+    fn apply_model(
+        &self,
+        model: &mut HashMap<String, HashSet<String>>,
+    ) -> Option<BTreeSet<String>> {
+        match self {
+            Op::Add(keyword, fid) => {
+                model
+                    .entry(keyword.to_string())
+                    .or_default()
+                    .insert(fid.to_string());
+            }
+            Op::AddBatch(keywords, fid) => {
+                for keyword in keywords {
+                    model
+                        .entry(keyword.to_string())
+                        .or_default()
+                        .insert(fid.to_string());
+                }
+            }
+            Op::Delete(keyword, fid) => {
+                if let Some(fids) = model.get_mut(*keyword) {
+                    fids.remove(*fid);
+                }
+            }
+            Op::Query(keyword) => {
+                return Some(model.get(*keyword).into_iter().flatten().cloned().collect())
+            }
+            Op::Boolean(expr) => {
+                let expr = parse_boolean_expr(expr).unwrap();
+                return Some(expr.evaluate(model).into_iter().collect());
+            }
+        }
+        None
+    }
+}
+
+proptest! {
+    // 累加器的证明生成较慢，用例数和序列长度都保持较小
+    #![proptest_config(ProptestConfig::with_cases(12))]
+
+    #[test]
+    fn test_random_sequences_agree(ops in vec(op(), 1..16)) {
+        let mut model = HashMap::new();
+        let expected: Vec<BTreeSet<String>> =
+            ops.iter().filter_map(|op| op.apply_model(&mut model)).collect();
+        run_scenario("random", |h| {
+            let observed: Vec<BTreeSet<String>> = ops.iter().filter_map(|op| op.apply(h)).collect();
+            assert_eq!(
+                observed,
+                expected,
+                "[{}] diverges from the model for {:?}",
+                h.mode.name(),
+                ops
+            );
+            observed
+        });
+    }
+}
@@ -0,0 +1,66 @@
+//! 近似计数测试
+//!
+//! ApproxCount 返回的草图不在 ADS 根之下，Manager 只相信验证通过的添加报告的草图摘要：
+//! storager 绕过 Manager 改变的草图、Manager 从未记录过的草图都报告为未验证。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::storager_service_server::StoragerService;
+use common::rpc::{ApproxCountRequest, ApproxCountResponse, StoragerAddRequest};
+use common::AdsMode;
+use manager::Manager;
+use std::sync::Arc;
+use storager::Storager;
+use support::{add, connect_manager, serve_storager};
+use tonic::transport::Channel;
+
+async fn approx_count(
+    client: &mut ManagerServiceClient<Channel>,
+    keyword: &str,
+) -> ApproxCountResponse {
+    client
+        .approx_count(ApproxCountRequest {
+            keyword: keyword.to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sketch_must_match_verified_adds() {
+    let storager = Arc::new(Storager::with_mpt());
+    let storager_addr = serve_storager(storager.clone());
+    let manager = Manager::new(vec![storager_addr], AdsMode::Mpt);
+    let mut client = connect_manager(Arc::new(manager)).await;
+
+    // Manager 还没有跟踪这个节点时，不能确认 keyword 没有草图
+    assert!(!approx_count(&mut client, "java").await.verified);
+
+    add(&mut client, "f1", &["rust", "go"]).await;
+    add(&mut client, "f2", &["rust"]).await;
+    let result = approx_count(&mut client, "rust").await;
+    assert!(result.verified);
+    assert_eq!(result.estimate, 2);
+    assert!(approx_count(&mut client, "go").await.verified);
+    let result = approx_count(&mut client, "java").await;
+    assert_eq!(result.estimate, 0);
+    assert!(result.verified);
+
+    // storager 绕过 Manager 写入，草图与记录的摘要不再一致
+    for keyword in ["rust", "java"] {
+        let request = StoragerAddRequest {
+            keyword: keyword.to_string(),
+            fid: "f3".to_string(),
+            ..Default::default()
+        };
+        storager.add(tonic::Request::new(request)).await.unwrap();
+    }
+    let result = approx_count(&mut client, "rust").await;
+    assert_eq!(result.estimate, 3);
+    assert!(!result.verified);
+    assert!(!approx_count(&mut client, "java").await.verified);
+    assert!(approx_count(&mut client, "go").await.verified);
+}
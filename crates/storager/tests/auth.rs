@@ -0,0 +1,135 @@
+//! Manager 访问控制测试
+//!
+//! 没有 token 或 token 未知的请求被拦截器拒绝；只读客户端不能写入，
+//! 有 keyword 前缀限制的客户端只能读写自己的前缀，只有 admin 可以变更集群成员。
+
+mod support;
+
+use common::auth::attach_token;
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::rpc::*;
+use common::{AdsMode, ErrorKind};
+use manager::core::{Access, AccessControl, Principal};
+use manager::Manager;
+use storager::Storager;
+use support::serve;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::{Channel, Server};
+use tonic::{Code, Request, Status};
+
+async fn start() -> ManagerServiceClient<Channel> {
+    let storager = StoragerServiceServer::new(Storager::with_mpt());
+    let storager_addr = serve(move || Server::builder().add_service(storager.clone()));
+
+    let access = AccessControl::new()
+        .with_token("reader-token", Principal::new("reader", Access::Read))
+        .with_token(
+            "logs-token",
+            Principal::new("logs", Access::Write).with_keyword_prefixes(vec!["logs-".into()]),
+        )
+        .with_token("ops-token", Principal::new("ops", Access::Admin));
+    let manager = Manager::new(vec![storager_addr], AdsMode::Mpt).with_access_control(access);
+    let interceptor = manager.auth_interceptor();
+    let service = InterceptedService::new(ManagerServiceServer::new(manager), interceptor);
+    let manager_addr = serve(move || Server::builder().add_service(service.clone()));
+    ManagerServiceClient::connect(manager_addr).await.unwrap()
+}
+
+fn with_token<T>(message: T, token: Option<&str>) -> Request<T> {
+    let mut request = Request::new(message);
+    if let Some(token) = token {
+        attach_token(&mut request, token).unwrap();
+    }
+    request
+}
+
+async fn add(
+    client: &mut ManagerServiceClient<Channel>,
+    token: Option<&str>,
+    keyword: &str,
+) -> Result<AddResponse, Status> {
+    let request = AddRequest {
+        fid: "f1".to_string(),
+        keywords: vec![keyword.to_string()],
+        ack_mode: AckMode::Sync as i32,
+        ..Default::default()
+    };
+    client
+        .add(with_token(request, token))
+        .await
+        .map(|response| response.into_inner())
+}
+
+fn assert_denied(result: Result<impl std::fmt::Debug, Status>, code: Code) {
+    let status = result.unwrap_err();
+    assert_eq!(status.code(), code, "{}", status.message());
+    assert_eq!(ErrorKind::from_status(&status), Some(ErrorKind::Auth));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_tokens_and_permissions() {
+    let mut client = start().await;
+
+    assert_denied(
+        add(&mut client, None, "logs-a").await,
+        Code::Unauthenticated,
+    );
+    assert_denied(
+        add(&mut client, Some("stolen"), "logs-a").await,
+        Code::Unauthenticated,
+    );
+    assert_denied(
+        add(&mut client, Some("reader-token"), "logs-a").await,
+        Code::PermissionDenied,
+    );
+    assert_denied(
+        add(&mut client, Some("logs-token"), "metrics-cpu").await,
+        Code::PermissionDenied,
+    );
+    assert!(
+        add(&mut client, Some("logs-token"), "logs-a")
+            .await
+            .unwrap()
+            .success
+    );
+
+    let query = |keyword: &str| QueryRequest {
+        query_type: Some(query_request::QueryType::Keyword(keyword.to_string())),
+        ..Default::default()
+    };
+    let response = client
+        .query(with_token(query("logs-a"), Some("reader-token")))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.fids, vec!["f1"]);
+    // 布尔查询中的每个 keyword 都要在允许的前缀内
+    let boolean = QueryRequest {
+        query_type: Some(query_request::QueryType::BooleanFunction(
+            "logs-a OR secrets-b".to_string(),
+        )),
+        ..Default::default()
+    };
+    assert_denied(
+        client.query(with_token(boolean, Some("logs-token"))).await,
+        Code::PermissionDenied,
+    );
+
+    let deregister = || DeregisterStoragerRequest {
+        name: "missing".to_string(),
+    };
+    assert_denied(
+        client
+            .deregister_storager(with_token(deregister(), Some("logs-token")))
+            .await,
+        Code::PermissionDenied,
+    );
+    // admin 通过授权，请求本身因节点不存在而失败
+    let status = client
+        .deregister_storager(with_token(deregister(), Some("ops-token")))
+        .await
+        .unwrap_err();
+    assert_eq!(ErrorKind::from_status(&status), Some(ErrorKind::Membership));
+}
@@ -0,0 +1,128 @@
+//! 批量写入测试
+//!
+//! 一次 `Batch` 请求中的操作按顺序执行，每个操作单独返回结果，失败的操作不影响其他操作。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::rpc::*;
+use common::AdsMode;
+use manager::{Manager, MAX_BATCH_OPERATIONS};
+use storager::Storager;
+use support::{query_keyword, serve};
+use tonic::transport::{Channel, Server};
+use tonic::Code;
+
+async fn start_cluster() -> ManagerServiceClient<Channel> {
+    let service = StoragerServiceServer::new(Storager::with_mpt());
+    let storager_addr = serve(move || Server::builder().add_service(service.clone()));
+    let service = ManagerServiceServer::new(Manager::new(vec![storager_addr], AdsMode::Mpt));
+    let addr = serve(move || Server::builder().add_service(service.clone()));
+    ManagerServiceClient::connect(addr).await.unwrap()
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+fn add(fid: &str, keywords: &[&str]) -> BatchOperation {
+    BatchOperation {
+        operation: Some(batch_operation::Operation::Add(AddRequest {
+            fid: fid.to_string(),
+            keywords: strings(keywords),
+            ack_mode: AckMode::Sync as i32,
+            ..Default::default()
+        })),
+    }
+}
+
+async fn query(client: &mut ManagerServiceClient<Channel>, keyword: &str) -> Vec<String> {
+    let response = query_keyword(client, keyword).await;
+    assert!(response.verified, "keyword {}", keyword);
+    let mut fids = response.fids;
+    fids.sort();
+    fids
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_batch_reports_each_operation() {
+    let mut client = start_cluster().await;
+
+    let operations = vec![
+        add("f1", &["rust", "go"]),
+        add("f2", &["rust"]),
+        // 没有 keyword：Manager 返回 success = false
+        add("f3", &[]),
+        // 无效的命名空间：操作被拒绝，其余操作照常执行
+        BatchOperation {
+            operation: Some(batch_operation::Operation::Delete(DeleteRequest {
+                fid: "f2".to_string(),
+                namespace: "not a namespace".to_string(),
+                ..Default::default()
+            })),
+        },
+        // 同一个 fid 上的操作按顺序生效
+        BatchOperation {
+            operation: Some(batch_operation::Operation::Update(UpdateRequest {
+                fid: "f1".to_string(),
+                old_keywords: strings(&["go"]),
+                new_keywords: strings(&["java"]),
+                ack_mode: AckMode::Sync as i32,
+                ..Default::default()
+            })),
+        },
+        BatchOperation {
+            operation: Some(batch_operation::Operation::Delete(DeleteRequest {
+                fid: "f2".to_string(),
+                keywords: strings(&["rust"]),
+                ack_mode: AckMode::Sync as i32,
+                ..Default::default()
+            })),
+        },
+        BatchOperation { operation: None },
+    ];
+    let response = client
+        .batch(BatchRequest { operations })
+        .await
+        .unwrap()
+        .into_inner();
+
+    let outcomes: Vec<(bool, Code)> = response
+        .results
+        .iter()
+        .map(|result| (result.success, Code::from(result.code)))
+        .collect();
+    assert_eq!(
+        outcomes,
+        vec![
+            (true, Code::Ok),
+            (true, Code::Ok),
+            (false, Code::Ok),
+            (false, Code::InvalidArgument),
+            (true, Code::Ok),
+            (true, Code::Ok),
+            (false, Code::InvalidArgument),
+        ]
+    );
+    assert_eq!(response.succeeded, 4);
+    assert_eq!(response.results[2].message, "No keywords provided");
+
+    assert_eq!(query(&mut client, "rust").await, vec!["f1"]);
+    assert!(query(&mut client, "go").await.is_empty());
+    assert_eq!(query(&mut client, "java").await, vec!["f1"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_batch_rejects_oversized_requests() {
+    let mut client = start_cluster().await;
+
+    let operations = (0..=MAX_BATCH_OPERATIONS)
+        .map(|i| add(&format!("f{}", i), &["rust"]))
+        .collect();
+    let status = client.batch(BatchRequest { operations }).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    // 整个请求被拒绝，没有执行任何操作（也就没有可以用来验证的根哈希）
+    assert!(query_keyword(&mut client, "rust").await.fids.is_empty());
+}
@@ -0,0 +1,191 @@
+//! 布尔查询证明测试
+//!
+//! 密码学累加器模式下所有 keyword 都在同一个 storager 时，由它证明整个表达式，
+//! Manager 逐层验证证明树后才把结果标记为已验证。拉取 storager 的 keyword 过滤器后，
+//! 布尔查询跳过肯定不存在的 keyword，并在响应中列出它们；请求附带 keyword 明细时，
+//! 响应按 keyword 列出各自的结果、证明和所在 storager。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_client::StoragerServiceClient;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::rpc::{
+    query_request::QueryType, QueryRequest, StoragerBooleanQueryRequest, StoragerQueryRequest,
+};
+use common::{AdsMode, Proof};
+use manager::core::ProofVerifier;
+use manager::Manager;
+use std::collections::HashMap;
+use std::sync::Arc;
+use storager::Storager;
+use support::{add, query_boolean, serve};
+use tonic::transport::Server;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_boolean_query_is_proven_by_storager() {
+    let service = StoragerServiceServer::new(Storager::with_crypto_accumulator());
+    let storager_addr = serve(move || Server::builder().add_service(service.clone()));
+    let manager = Manager::new(vec![storager_addr.clone()], AdsMode::CryptoAccumulator);
+    let manager_service = ManagerServiceServer::new(manager);
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    add(&mut client, "f1", &["rust", "storage"]).await;
+    add(&mut client, "f2", &["rust", "python"]).await;
+    add(&mut client, "f3", &["go", "storage"]).await;
+    add(&mut client, "f4", &["python"]).await;
+
+    let response = query_boolean(&mut client, "rust AND storage").await;
+    assert_eq!(response.fids, vec!["f1"]);
+    assert!(response.verified && response.boolean_proof.is_some());
+
+    let response = query_boolean(&mut client, "(rust OR go) AND NOT python").await;
+    assert_eq!(response.fids, vec!["f1", "f3"]);
+    assert!(response.verified && response.boolean_proof.is_some());
+
+    let response = query_boolean(&mut client, "python OR go").await;
+    assert_eq!(response.fids, vec!["f2", "f3", "f4"]);
+    assert!(response.verified && response.boolean_proof.is_some());
+
+    // 篡改结果后证明树无法通过验证
+    let mut storager = StoragerServiceClient::connect(storager_addr).await.unwrap();
+    let mut keyword_proofs = HashMap::new();
+    for keyword in ["rust", "storage"] {
+        let read = storager
+            .query(StoragerQueryRequest {
+                keyword: keyword.to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let proof = Proof::try_from(read.proof).unwrap();
+        keyword_proofs.insert(keyword.to_string(), proof);
+    }
+    let resp = storager
+        .boolean_query(StoragerBooleanQueryRequest {
+            expression: "rust AND storage".to_string(),
+            namespace: String::new(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let proof = resp.proof.unwrap();
+    let verifier = ProofVerifier::new(AdsMode::CryptoAccumulator);
+    assert!(verifier.verify_boolean_proof(&proof, &keyword_proofs, &resp.fids));
+    let tampered = vec!["f1".to_string(), "f2".to_string()];
+    assert!(!verifier.verify_boolean_proof(&proof, &keyword_proofs, &tampered));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_keyword_filter_skips_absent_keywords() {
+    let service = StoragerServiceServer::new(Storager::with_mpt());
+    let storager_addr = serve(move || Server::builder().add_service(service.clone()));
+    let manager = Arc::new(Manager::new(vec![storager_addr], AdsMode::Mpt));
+    let manager_service = ManagerServiceServer::from_arc(manager.clone());
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    add(&mut client, "f1", &["rust", "storage"]).await;
+    add(&mut client, "f2", &["go"]).await;
+
+    // 还没有拉取过滤器时逐个查询
+    let response = query_boolean(&mut client, "rust OR missing").await;
+    assert_eq!(response.fids, vec!["f1"]);
+    assert!(response.filtered_keywords.is_empty());
+
+    assert_eq!(manager.refresh_keyword_filters().await, 1);
+    let response = query_boolean(&mut client, "(rust OR missing) AND NOT go").await;
+    assert!(response.verified);
+    assert_eq!(response.fids, vec!["f1"]);
+    assert_eq!(response.filtered_keywords, vec!["missing"]);
+    let response = query_boolean(&mut client, "rust AND missing").await;
+    assert!(response.fids.is_empty());
+    assert_eq!(response.filtered_keywords, vec!["missing"]);
+
+    // 写入发布新的根哈希后过滤器过时，不再跳过刚写入的 keyword
+    add(&mut client, "f3", &["missing"]).await;
+    let response = query_boolean(&mut client, "rust OR missing").await;
+    assert_eq!(response.fids, vec!["f1", "f3"]);
+    assert!(response.filtered_keywords.is_empty());
+
+    // 重新拉取的过滤器包含新的 keyword
+    manager.refresh_keyword_filters().await;
+    let response = query_boolean(&mut client, "missing OR absent").await;
+    assert_eq!(response.fids, vec!["f3"]);
+    assert_eq!(response.filtered_keywords, vec!["absent"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_keyword_breakdown() {
+    let service = StoragerServiceServer::new(Storager::with_mpt());
+    let storager_addr = serve(move || Server::builder().add_service(service.clone()));
+    let manager = Arc::new(Manager::new(vec![storager_addr], AdsMode::Mpt));
+    let manager_service = ManagerServiceServer::from_arc(manager.clone());
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    add(&mut client, "f1", &["rust", "storage"]).await;
+    add(&mut client, "f2", &["go", "rust"]).await;
+
+    // 默认不附带
+    let response = query_boolean(&mut client, "rust AND NOT go").await;
+    assert!(response.keyword_breakdown.is_empty());
+
+    let breakdown_query = |page_size, page_token: String| QueryRequest {
+        query_type: Some(QueryType::BooleanFunction(
+            "(rust AND NOT go) OR missing".to_string(),
+        )),
+        page_size,
+        page_token,
+        keyword_breakdown: true,
+        ..Default::default()
+    };
+    manager.refresh_keyword_filters().await;
+    let response = client
+        .query(breakdown_query(0, String::new()))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.fids, vec!["f1"]);
+    let keywords: Vec<(&str, usize, bool)> = response
+        .keyword_breakdown
+        .iter()
+        .map(|entry| (entry.keyword.as_str(), entry.fids.len(), entry.filtered))
+        .collect();
+    assert_eq!(
+        keywords,
+        vec![("go", 1, false), ("missing", 0, true), ("rust", 2, false)]
+    );
+    for entry in &response.keyword_breakdown {
+        // 被过滤的 keyword 没有证明
+        assert_eq!(entry.proof.is_some(), !entry.filtered);
+        assert!(!entry.storager.is_empty());
+        assert_eq!(entry.root_hash.is_empty(), entry.filtered);
+    }
+
+    // 分页时只随最后一页返回
+    let first = client
+        .query(breakdown_query(1, String::new()))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(first.next_page_token.is_empty());
+    assert_eq!(first.keyword_breakdown.len(), 3);
+    add(&mut client, "f3", &["rust"]).await;
+    let first = client
+        .query(breakdown_query(1, String::new()))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(first.keyword_breakdown.is_empty());
+    let last = client
+        .query(breakdown_query(1, first.next_page_token))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(last.next_page_token.is_empty());
+    assert_eq!(last.keyword_breakdown.len(), 3);
+}
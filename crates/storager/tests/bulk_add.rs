@@ -0,0 +1,111 @@
+//! 批量导入测试
+//!
+//! 客户端通过 `BulkAdd` 流式发送记录，Manager 分批写入 storager 并逐条验证证明，
+//! 导入结束时每个 storager 只发布一次根哈希。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::rpc::{query_request, BulkAddRecord, QueryRequest, SubscribeRootHashesRequest};
+use common::AdsMode;
+use manager::Manager;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use storager::Storager;
+use support::serve;
+use tonic::transport::Server;
+use tonic::Code;
+
+fn record(fid: &str, keywords: &[&str]) -> BulkAddRecord {
+    BulkAddRecord {
+        fid: fid.to_string(),
+        keywords: keywords.iter().map(|k| k.to_string()).collect(),
+        namespace: String::new(),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_bulk_add_publishes_one_transition_per_storager() {
+    let storagers: Vec<Arc<Storager>> = (0..2).map(|_| Arc::new(Storager::with_mpt())).collect();
+    let storager_addrs = storagers
+        .iter()
+        .map(|storager| {
+            let service = StoragerServiceServer::from_arc(storager.clone());
+            serve(move || Server::builder().add_service(service.clone()))
+        })
+        .collect();
+    let manager = Manager::new(storager_addrs, AdsMode::Mpt).with_bulk_batch(3);
+    let manager_service = ManagerServiceServer::new(manager);
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    let keywords = ["red", "green", "blue", "animal", "flower", "food"];
+    let records: Vec<BulkAddRecord> = (0..10)
+        .map(|i| {
+            // 重复的 keyword 只写入一次
+            record(
+                &format!("f{}", i),
+                &[keywords[i % 6], keywords[(i + 2) % 6], keywords[i % 6]],
+            )
+        })
+        .collect();
+    let mut expected: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+    for (i, r) in records.iter().enumerate() {
+        for keyword in [keywords[i % 6], keywords[(i + 2) % 6]] {
+            expected.entry(keyword).or_default().insert(r.fid.clone());
+        }
+    }
+
+    let response = client
+        .bulk_add(tokio_stream::iter(records))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.success, "{}", response.message);
+    assert_eq!(response.records, 10);
+
+    // 每个 storager 一次根哈希变化，epoch 与 storager 的写入次数一致
+    let written: u64 = storagers.iter().map(|s| s.epoch()).sum();
+    assert_eq!(
+        response.transitions.iter().map(|t| t.epoch).sum::<u64>(),
+        written
+    );
+    let mut updates = client
+        .subscribe_root_hashes(SubscribeRootHashesRequest {})
+        .await
+        .unwrap()
+        .into_inner();
+    for transition in &response.transitions {
+        assert!(transition.old_root_hash.is_empty());
+        let current = updates.message().await.unwrap().unwrap();
+        let published = response
+            .transitions
+            .iter()
+            .find(|t| t.storager == current.storager)
+            .unwrap();
+        assert_eq!(current.root_hash, published.new_root_hash);
+    }
+
+    for (keyword, fids) in expected {
+        let result = client
+            .query(QueryRequest {
+                query_type: Some(query_request::QueryType::Keyword(keyword.to_string())),
+                allow_background: false,
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(result.verified, "{}", keyword);
+        assert_eq!(result.fids.into_iter().collect::<BTreeSet<_>>(), fids);
+    }
+
+    // 没有 keyword 的记录使整个请求失败
+    let error = client
+        .bulk_add(tokio_stream::iter(vec![record("f10", &[])]))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::InvalidArgument);
+}
@@ -0,0 +1,75 @@
+//! 并发查询测试
+//!
+//! MPT 查询只需要共享引用，多个 Query RPC 在 storager 的读锁下并发执行，
+//! 同时还有写入在进行。每个响应中的 fid 列表都必须与其证明中的值完全一致。
+
+use common::rpc::storager_service_server::StoragerService;
+use common::rpc::{StoragerAddRequest, StoragerQueryRequest};
+use common::AdsMode;
+use manager::core::ProofVerifier;
+use std::sync::Arc;
+use storager::Storager;
+use tonic::Request;
+
+const KEYWORDS: [&str; 4] = ["rust", "go", "python", "java"];
+
+async fn add(storager: &Storager, keyword: &str, fid: String) {
+    storager
+        .add(Request::new(StoragerAddRequest {
+            keyword: keyword.to_string(),
+            fid,
+            ..Default::default()
+        }))
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_queries_run_alongside_writes() {
+    let storager = Arc::new(Storager::with_mpt());
+    for keyword in KEYWORDS {
+        add(&storager, keyword, format!("{}-0", keyword)).await;
+    }
+
+    let writer = {
+        let storager = storager.clone();
+        tokio::spawn(async move {
+            for i in 1..=25 {
+                for keyword in KEYWORDS {
+                    add(&storager, keyword, format!("{}-{}", keyword, i)).await;
+                }
+            }
+        })
+    };
+
+    let readers = (0..8).map(|reader| {
+        let storager = storager.clone();
+        tokio::spawn(async move {
+            let verifier = ProofVerifier::new(AdsMode::Mpt);
+            for i in 0..50 {
+                let keyword = KEYWORDS[(reader + i) % KEYWORDS.len()];
+                let response = storager
+                    .query(Request::new(StoragerQueryRequest {
+                        keyword: keyword.to_string(),
+                        ..Default::default()
+                    }))
+                    .await
+                    .unwrap()
+                    .into_inner();
+                assert!(!response.fids.is_empty());
+                let proof = response.proof.unwrap().try_into().unwrap();
+                assert!(
+                    verifier.verify_completeness(&proof, keyword, &response.fids),
+                    "{} {:?}",
+                    keyword,
+                    response.fids
+                );
+            }
+        })
+    });
+
+    for reader in readers.collect::<Vec<_>>() {
+        reader.await.unwrap();
+    }
+    writer.await.unwrap();
+}
@@ -0,0 +1,123 @@
+//! 推迟生成证明的异步确认测试
+//!
+//! 异步确认的写请求让 storager 推迟生成证明，Manager 在后台用 GetProof 取回并验证，
+//! 验证通过后审计记录变为 Confirmed，记录中保存取回的证明。storager 只保留有限个未取回的句柄。
+
+mod support;
+
+use common::rpc::storager_service_server::StoragerService;
+use common::rpc::{AckMode, AddRequest, DeleteRequest, GetProofRequest, StoragerAddRequest};
+use common::AdsMode;
+use manager::core::{AuditEntry, AuditStatus, ProofVerifier};
+use manager::Manager;
+use std::sync::Arc;
+use std::time::Duration;
+use storager::proof_queue::ProofQueue;
+use storager::Storager;
+use support::{connect_manager, query_keyword, serve_storager};
+use tonic::{Code, Request};
+
+/// 等待审计记录离开 Pending 状态
+async fn settled(manager: &Manager, id: u64) -> AuditEntry {
+    for _ in 0..200 {
+        let entry = manager.audit_log().get(id).unwrap();
+        if entry.status != AuditStatus::Pending {
+            return entry;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("audit entry {} is still pending", id);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_async_writes_settle_with_deferred_proofs() {
+    for mode in [
+        AdsMode::Mpt,
+        AdsMode::MerkleTree,
+        AdsMode::CryptoAccumulator,
+    ] {
+        let storager = Arc::new(Storager::from_config(mode.name()));
+        let manager = Arc::new(Manager::new(vec![serve_storager(storager)], mode));
+        let mut client = connect_manager(manager.clone()).await;
+        let verifier = ProofVerifier::new(mode);
+
+        // 两个 keyword 走 BatchAdd，删除走单个 Delete
+        let response = client
+            .add(AddRequest {
+                fid: "f1".to_string(),
+                keywords: vec!["rust".to_string(), "go".to_string()],
+                ack_mode: AckMode::Async as i32,
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.success, "{}", response.message);
+        let mut ids = response.pending_ops;
+        let response = client
+            .delete(DeleteRequest {
+                fid: "f1".to_string(),
+                keywords: vec!["go".to_string()],
+                ack_mode: AckMode::Async as i32,
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.success, "{}", response.message);
+        ids.extend(response.pending_ops);
+        assert_eq!(ids.len(), 3, "{:?}", mode);
+
+        for id in ids {
+            let entry = settled(&manager, id).await;
+            assert_eq!(entry.status, AuditStatus::Confirmed, "{:?}", mode);
+            assert!(
+                verifier.verify(&entry.proof, &entry.root_hash),
+                "{:?}",
+                mode
+            );
+        }
+        let result = query_keyword(&mut client, "rust").await;
+        assert!(result.verified, "{:?}", mode);
+        assert_eq!(result.fids, vec!["f1"]);
+        assert!(query_keyword(&mut client, "go").await.fids.is_empty());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unfetched_proofs_are_evicted() {
+    let storager =
+        Storager::with_mpt().with_proof_queue(ProofQueue::default().with_max_unfetched(2));
+    let mut handles = Vec::new();
+    for fid in ["f1", "f2", "f3"] {
+        let response = storager
+            .add(Request::new(StoragerAddRequest {
+                keyword: "rust".to_string(),
+                fid: fid.to_string(),
+                defer_proof: true,
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        handles.push(response.proof_handle);
+    }
+
+    let get_proof = |handle| {
+        storager.get_proof(Request::new(GetProofRequest {
+            proof_handle: handle,
+            ..Default::default()
+        }))
+    };
+    // 从不取回证明的写请求不会让句柄无限累积，最早的句柄被淘汰
+    let status = get_proof(handles[0]).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+    for &handle in &handles[1..] {
+        assert!(get_proof(handle)
+            .await
+            .unwrap()
+            .into_inner()
+            .proof
+            .is_some());
+    }
+}
@@ -0,0 +1,53 @@
+//! 差集查询测试
+//!
+//! 密码学累加器模式下通过 Manager 执行 `A AND NOT B` 查询，
+//! 检查结果来自 storager 的差集证明并通过验证。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::AdsMode;
+use manager::Manager;
+use storager::Storager;
+use support::{add, query_boolean, serve};
+use tonic::transport::{Channel, Server};
+
+async fn query(client: &mut ManagerServiceClient<Channel>, func: &str) -> (Vec<String>, bool) {
+    let response = query_boolean(client, func).await;
+    (response.fids, response.verified)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_and_not_query_is_proven_by_storager() {
+    let addrs = (0..2)
+        .map(|_| {
+            let service = StoragerServiceServer::new(Storager::with_crypto_accumulator());
+            serve(move || Server::builder().add_service(service.clone()))
+        })
+        .collect();
+    let manager = Manager::new(addrs, AdsMode::CryptoAccumulator);
+    let manager_service = ManagerServiceServer::new(manager);
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    add(&mut client, "f1", &["rust"]).await;
+    add(&mut client, "f2", &["rust", "python"]).await;
+    add(&mut client, "f3", &["rust", "storage"]).await;
+    add(&mut client, "f4", &["python"]).await;
+
+    assert_eq!(
+        query(&mut client, "rust AND NOT python").await,
+        (vec!["f1".to_string(), "f3".to_string()], true)
+    );
+    assert_eq!(
+        query(&mut client, "NOT rust AND python").await,
+        (vec!["f4".to_string()], true)
+    );
+    // 被排除的 keyword 包含 A 的全部 fid 时差集为空
+    assert_eq!(
+        query(&mut client, "storage AND NOT rust").await,
+        (vec![], true)
+    );
+}
@@ -0,0 +1,97 @@
+//! 带 TTL 的写入测试
+//!
+//! Add 请求的 TTL 到期后 storager 的清理删除对应的 (keyword, fid)，Manager 拉取清理日志、
+//! 验证删除证明并发布新的根哈希，之后的查询结果不再包含该 fid 且验证通过；
+//! 没有 TTL 的写入不受影响。
+
+mod support;
+
+use common::clock::MockClock;
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::rpc::{AckMode, AddRequest};
+use common::AdsMode;
+use manager::Manager;
+use std::sync::Arc;
+use std::time::Duration;
+use storager::Storager;
+use support::{query_keyword, serve};
+use tonic::transport::{Channel, Server};
+
+async fn add(client: &mut ManagerServiceClient<Channel>, fid: &str, keyword: &str, ttl: u64) {
+    let response = client
+        .add(AddRequest {
+            fid: fid.to_string(),
+            keywords: vec![keyword.to_string()],
+            ack_mode: AckMode::Sync as i32,
+            tenant: String::new(),
+            namespace: String::new(),
+            ttl_seconds: ttl,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.success, "{}", response.message);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_expired_entries_are_deleted_and_verified() {
+    let clock = MockClock::new(Duration::from_secs(1_000));
+    let storager = Arc::new(Storager::with_mpt().with_clock(Arc::new(clock.clone())));
+    let service = StoragerServiceServer::from_arc(storager.clone());
+    let storager_addr = serve(move || Server::builder().add_service(service.clone()));
+    let manager = Arc::new(
+        Manager::new(vec![storager_addr], AdsMode::Mpt).with_clock(Arc::new(clock.clone())),
+    );
+    let manager_service = ManagerServiceServer::from_arc(manager.clone());
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    add(&mut client, "f1", "rust", 60).await;
+    add(&mut client, "f2", "rust", 0).await;
+    add(&mut client, "f3", "go", 120).await;
+
+    // 还没有到期
+    clock.advance(Duration::from_secs(59));
+    assert_eq!(storager.sweep_expired().unwrap(), 0);
+    assert_eq!(manager.collect_expired().await, 0);
+
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(storager.sweep_expired().unwrap(), 1);
+    assert_eq!(manager.collect_expired().await, 1);
+    let response = query_keyword(&mut client, "rust").await;
+    assert_eq!(response.fids, vec!["f2"]);
+    assert!(response.verified);
+
+    // 已经拉取过的删除不会重复应用
+    assert_eq!(manager.collect_expired().await, 0);
+
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(storager.sweep_expired().unwrap(), 1);
+    assert_eq!(manager.collect_expired().await, 1);
+    let response = query_keyword(&mut client, "go").await;
+    assert!(response.fids.is_empty());
+    assert!(response.verified);
+    assert_eq!(query_keyword(&mut client, "rust").await.fids, vec!["f2"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_readding_without_ttl_cancels_expiry() {
+    let clock = MockClock::new(Duration::from_secs(1_000));
+    let storager = Arc::new(Storager::with_mpt().with_clock(Arc::new(clock.clone())));
+    let service = StoragerServiceServer::from_arc(storager.clone());
+    let storager_addr = serve(move || Server::builder().add_service(service.clone()));
+    let manager =
+        Manager::new(vec![storager_addr], AdsMode::Mpt).with_clock(Arc::new(clock.clone()));
+    let manager_service = ManagerServiceServer::new(manager);
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    add(&mut client, "f1", "rust", 60).await;
+    add(&mut client, "f1", "rust", 0).await;
+
+    clock.advance(Duration::from_secs(3_600));
+    assert_eq!(storager.sweep_expired().unwrap(), 0);
+    assert_eq!(query_keyword(&mut client, "rust").await.fids, vec!["f1"]);
+}
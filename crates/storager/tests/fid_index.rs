@@ -0,0 +1,75 @@
+//! fid 反向索引测试
+//!
+//! Delete 只给出 fid 时，Manager 从反向索引中找到 fid 所在的 keyword 并删除；
+//! 索引写入文件，重启后的 Manager 仍然能按 fid 删除。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::rpc::*;
+use common::AdsMode;
+use manager::Manager;
+use std::path::Path;
+use std::sync::Arc;
+use storager::Storager;
+use support::{add, connect_manager, query_keyword, serve};
+use tonic::transport::{Channel, Server};
+
+async fn start_manager(storager_addr: &str, index: &Path) -> ManagerServiceClient<Channel> {
+    let manager = Manager::new(vec![storager_addr.to_string()], AdsMode::Mpt)
+        .with_fid_index(index)
+        .unwrap();
+    connect_manager(Arc::new(manager)).await
+}
+
+async fn delete(
+    client: &mut ManagerServiceClient<Channel>,
+    fid: &str,
+    strict: bool,
+) -> DeleteResponse {
+    client
+        .delete(DeleteRequest {
+            fid: fid.to_string(),
+            ack_mode: AckMode::Sync as i32,
+            strict,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+async fn query(client: &mut ManagerServiceClient<Channel>, keyword: &str) -> Vec<String> {
+    let response = query_keyword(client, keyword).await;
+    assert!(response.verified, "keyword {}", keyword);
+    response.fids
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_delete_by_fid_after_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let index = dir.path().join("fids.json");
+    let service = StoragerServiceServer::new(Storager::with_mpt());
+    let storager_addr = serve(move || Server::builder().add_service(service.clone()));
+
+    let mut client = start_manager(&storager_addr, &index).await;
+    add(&mut client, "f1", &["rust", "go"]).await;
+    add(&mut client, "f2", &["rust"]).await;
+
+    // strict 模式不使用索引
+    let response = delete(&mut client, "f1", true).await;
+    assert!(!response.success);
+    assert_eq!(query(&mut client, "go").await, vec!["f1"]);
+
+    // 重启后的 Manager 从索引文件中恢复 f1 的 keyword
+    let mut client = start_manager(&storager_addr, &index).await;
+    let response = delete(&mut client, "f1", false).await;
+    assert!(response.success, "{}", response.message);
+    assert_eq!(query(&mut client, "rust").await, vec!["f2"]);
+    assert!(query(&mut client, "go").await.is_empty());
+
+    // 已经删除的 fid 不再有记录
+    let response = delete(&mut client, "f1", false).await;
+    assert!(!response.success);
+}
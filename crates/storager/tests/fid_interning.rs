@@ -0,0 +1,147 @@
+//! Fid 驻留测试
+//!
+//! 启用驻留的 storager 在 ADS 中保存长 fid 的紧凑 id，查询证明附带映射表条目，Manager
+//! 按 ADS 中的映射表摘要检查条目后还原 fid：storager 把 id 翻译成别的 fid 时结果不能通过验证。
+//! 映射表随持久化后端一起恢复；客户端不能写入保留 keyword。
+
+mod support;
+
+use common::fid_intern::{InternedProof, FID_TABLE_KEYWORD};
+use common::rpc::storager_service_server::StoragerService;
+use common::rpc::{AddRequest, StoragerAddRequest, StoragerQueryRequest, StoragerQueryResponse};
+use common::{AdsMode, Proof};
+use manager::core::ProofVerifier;
+use manager::Manager;
+use std::sync::Arc;
+use storager::{DbBackend, Storager};
+use support::{add, connect_manager, query_keyword, serve_storager};
+use tonic::Request;
+
+async fn query(storager: &Storager, keyword: &str) -> StoragerQueryResponse {
+    storager
+        .query(Request::new(StoragerQueryRequest {
+            keyword: keyword.to_string(),
+            ..Default::default()
+        }))
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_proofs_bind_interned_fids() {
+    let fids = |list: &[&str]| list.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+    for mode in [AdsMode::Mpt, AdsMode::MerkleTree, AdsMode::SparseMerkleTree] {
+        let storager = Arc::new(
+            Storager::from_config(mode.name())
+                .with_fid_interning()
+                .unwrap(),
+        );
+        let manager = Manager::new(vec![serve_storager(storager.clone())], mode);
+        let mut client = connect_manager(Arc::new(manager)).await;
+        add(&mut client, "file-1", &["rust"]).await;
+        add(&mut client, "file-2", &["rust", "go"]).await;
+        // 不比紧凑 id 长的 fid 原样保存
+        add(&mut client, "f3", &["rust"]).await;
+
+        let result = query_keyword(&mut client, "rust").await;
+        assert!(result.verified, "{:?}", mode);
+        let mut returned = result.fids.clone();
+        returned.sort();
+        assert_eq!(returned, fids(&["f3", "file-1", "file-2"]));
+
+        // 证明中保存的是紧凑 id，换成其他 fid、漏掉 fid 或拿去冒充别的 keyword 都不成立
+        let response = query(&storager, "rust").await;
+        let proof: Proof = response.proof.unwrap().try_into().unwrap();
+        let verifier = ProofVerifier::new(mode);
+        assert!(verifier.verify_completeness(&proof, "rust", &response.fids));
+        let mut swapped = response.fids.clone();
+        swapped[0] = "file-9".to_string();
+        assert!(!verifier.verify_completeness(&proof, "rust", &swapped));
+        assert!(!verifier.verify_completeness(&proof, "rust", &response.fids[1..]));
+        assert!(!verifier.verify_completeness(&proof, "go", &response.fids));
+
+        // 映射表条目只列出两个紧凑 id，改动条目中的 fid 后不再与摘要一致
+        let Proof::Interned(data) = &proof else {
+            panic!("{:?}: query proof does not carry the fid table", mode);
+        };
+        let mut interned = InternedProof::from_bytes(data).unwrap();
+        assert_eq!(interned.entries.len(), 2);
+        let original = std::mem::replace(&mut interned.entries[0].fid, "file-9".to_string());
+        let forged = Proof::Interned(interned.to_bytes());
+        let forged_fids: Vec<String> = response
+            .fids
+            .iter()
+            .map(|fid| {
+                if *fid == original {
+                    "file-9".to_string()
+                } else {
+                    fid.clone()
+                }
+            })
+            .collect();
+        assert!(!verifier.verify_completeness(&forged, "rust", &forged_fids));
+
+        // 映射表摘要所在的保留 keyword 不出现在 keyword 列表中
+        assert_eq!(storager.keywords().unwrap(), vec!["go", "rust"]);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reserved_keywords_are_rejected() {
+    let storager = Arc::new(Storager::with_mpt().with_fid_interning().unwrap());
+    let reserved = FID_TABLE_KEYWORD.to_string();
+    let status = storager
+        .add(Request::new(StoragerAddRequest {
+            keyword: reserved.clone(),
+            fid: "f9".to_string(),
+            ..Default::default()
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    let manager = Manager::new(vec![serve_storager(storager.clone())], AdsMode::Mpt);
+    let mut client = connect_manager(Arc::new(manager)).await;
+    let status = client
+        .add(AddRequest {
+            fid: "f9".to_string(),
+            keywords: vec![reserved],
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_mapping_survives_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let backend = DbBackend::RocksDb(dir.path().to_path_buf());
+    {
+        let storager = Storager::open(AdsMode::Mpt.name(), &backend)
+            .unwrap()
+            .with_fid_interning()
+            .unwrap();
+        for (keyword, fid) in [("rust", "file-1"), ("rust", "file-2"), ("go", "file-1")] {
+            let request = StoragerAddRequest {
+                keyword: keyword.to_string(),
+                fid: fid.to_string(),
+                ..Default::default()
+            };
+            storager.add(Request::new(request)).await.unwrap();
+        }
+        storager.flush_namespaces().unwrap();
+    }
+
+    let storager = Storager::open(AdsMode::Mpt.name(), &backend)
+        .unwrap()
+        .with_fid_interning()
+        .unwrap();
+    let response = query(&storager, "rust").await;
+    assert_eq!(response.fids, vec!["file-1", "file-2"]);
+    let proof = response.proof.unwrap().try_into().unwrap();
+    let verifier = ProofVerifier::new(AdsMode::Mpt);
+    assert!(verifier.verify_completeness(&proof, "rust", &response.fids));
+    assert_eq!(query(&storager, "go").await.fids, vec!["file-1"]);
+}
@@ -0,0 +1,227 @@
+//! 进程交接测试
+//!
+//! 在同一个进程内模拟旧、新两个 storager：旧实例在 TCP 监听 socket 上服务，
+//! 新实例通过控制 socket 接管监听 socket 和状态，客户端始终连接同一个地址。
+
+use common::net::{bind_tcp, serve_listeners, Listeners};
+use common::rpc::storager_service_client::StoragerServiceClient;
+use common::rpc::storager_service_server::{StoragerService, StoragerServiceServer};
+use common::rpc::{
+    StoragerAddRequest, StoragerDeleteRequest, StoragerQueryRequest, StoragerQueryResponse,
+};
+use common::{AdsMode, Proof};
+use manager::core::ProofVerifier;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use storager::ads::registry::create_ads;
+use storager::handover::{serve_handover, take_over, Takeover};
+use storager::Storager;
+use tokio::task::JoinHandle;
+use tonic::transport::{Channel, Server};
+use tonic::Request;
+
+fn control_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("storager-{}-{}.ctl", name, std::process::id()))
+}
+
+/// 启动一个可以被接管的 storager，返回 (监听地址, 服务任务)
+fn serve_old(storager: Arc<Storager>, control: &Path) -> (SocketAddr, JoinHandle<()>) {
+    let listeners = Listeners {
+        tcp: vec![bind_tcp("127.0.0.1:0".parse().unwrap()).unwrap()],
+        ..Default::default()
+    };
+    let addr = listeners.tcp[0].local_addr().unwrap();
+    let handover_listeners = listeners.try_clone().unwrap();
+    let handover_storager = storager.clone();
+    let control = control.to_path_buf();
+
+    let service = StoragerServiceServer::from_arc(storager);
+    let task = tokio::spawn(async move {
+        let shutdown = async {
+            serve_handover(&control, handover_storager, handover_listeners)
+                .await
+                .unwrap()
+        };
+        serve_listeners(
+            listeners,
+            || Server::builder().add_service(service.clone()),
+            shutdown,
+        )
+        .await
+        .unwrap();
+        let _ = std::fs::remove_file(&control);
+    });
+    (addr, task)
+}
+
+/// 连接控制 socket 发起接管（等待旧进程开始监听）
+async fn connect_takeover(control: &Path) -> Takeover {
+    let control = control.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        for _ in 0..100 {
+            match take_over(&control) {
+                Ok(takeover) => return takeover,
+                Err(_) => std::thread::sleep(Duration::from_millis(20)),
+            }
+        }
+        panic!("old storager never accepted the takeover");
+    })
+    .await
+    .unwrap()
+}
+
+async fn add(client: &mut StoragerServiceClient<Channel>, keyword: &str, fid: &str) -> Vec<u8> {
+    client
+        .add(StoragerAddRequest {
+            keyword: keyword.to_string(),
+            fid: fid.to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .root_hash
+}
+
+/// Merkle 树按 ADS 中保存的紧凑 id 排序，比较前按 fid 排序
+fn sorted(mut fids: Vec<String>) -> Vec<String> {
+    fids.sort();
+    fids
+}
+
+async fn query(storager: &Storager, keyword: &str) -> StoragerQueryResponse {
+    storager
+        .query(Request::new(StoragerQueryRequest {
+            keyword: keyword.to_string(),
+            ..Default::default()
+        }))
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_handover_keeps_serving_same_address() {
+    let control = control_path("handover");
+    let old = Arc::new(Storager::with_merkle_tree().with_fid_interning().unwrap());
+    let (addr, old_task) = serve_old(old.clone(), &control);
+
+    let mut client = StoragerServiceClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+    add(&mut client, "rust", "file-1").await;
+    add(&mut client, "go", "file-2").await;
+    let root = add(&mut client, "rust", "file-3").await;
+
+    // 新进程接管：导入状态，在继承的监听 socket 上服务后通知旧进程
+    let mut takeover = connect_takeover(&control).await;
+    assert!(old.is_frozen());
+    let new = Arc::new(Storager::with_merkle_tree().with_fid_interning().unwrap());
+    new.import_state(&takeover.state).unwrap();
+    let listeners = std::mem::take(&mut takeover.listeners);
+    assert_eq!(listeners.tcp[0].local_addr().unwrap(), addr);
+
+    let service = StoragerServiceServer::from_arc(new.clone());
+    tokio::spawn(async move {
+        serve_listeners(
+            listeners,
+            || Server::builder().add_service(service.clone()),
+            std::future::pending(),
+        )
+        .await
+        .unwrap()
+    });
+    takeover.ready().unwrap();
+    tokio::time::timeout(Duration::from_secs(10), old_task)
+        .await
+        .expect("old storager should drain and exit")
+        .unwrap();
+
+    // 同一个客户端继续工作，查询结果和证明与交接前的根一致
+    let response = client
+        .query(StoragerQueryRequest {
+            keyword: "rust".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(sorted(response.fids), vec!["file-1", "file-3"]);
+    let proof = Proof::try_from(response.proof).unwrap();
+    assert!(ProofVerifier::new(AdsMode::MerkleTree).verify(&proof, &root));
+
+    let new_root = add(&mut client, "rust", "file-4").await;
+    assert_ne!(new_root, root);
+    assert_eq!(
+        sorted(query(&new, "rust").await.fids),
+        vec!["file-1", "file-3", "file-4"]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_aborted_handover_resumes_writes() {
+    let control = control_path("aborted");
+    let old = Arc::new(Storager::with_merkle_tree());
+    let (addr, old_task) = serve_old(old.clone(), &control);
+
+    // 新进程在就绪前退出
+    let takeover = connect_takeover(&control).await;
+    assert!(old.is_frozen());
+    drop(takeover);
+
+    for _ in 0..100 {
+        if !old.is_frozen() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(!old.is_frozen());
+    assert!(!old_task.is_finished());
+
+    let mut client = StoragerServiceClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+    add(&mut client, "rust", "f1").await;
+    old_task.abort();
+    let _ = std::fs::remove_file(control);
+}
+
+#[tokio::test]
+async fn test_state_roundtrip_for_builtin_backends() {
+    for mode in [
+        AdsMode::CryptoAccumulator,
+        AdsMode::Mpt,
+        AdsMode::MerkleTree,
+    ] {
+        let old = Storager::with_ads(create_ads(mode).unwrap());
+        for (keyword, fid) in [("rust", "f1"), ("go", "f2"), ("rust", "f3"), ("go", "f4")] {
+            old.add(Request::new(StoragerAddRequest {
+                keyword: keyword.to_string(),
+                fid: fid.to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        }
+        old.delete(Request::new(StoragerDeleteRequest {
+            keyword: "go".to_string(),
+            fid: "f2".to_string(),
+            ..Default::default()
+        }))
+        .await
+        .unwrap();
+
+        let new = Storager::with_ads(create_ads(mode).unwrap());
+        new.import_state(&old.export_state().unwrap()).unwrap();
+        assert_eq!(new.epoch(), old.epoch(), "{:?}", mode);
+
+        for keyword in ["rust", "go", "java"] {
+            let before = query(&old, keyword).await;
+            let after = query(&new, keyword).await;
+            assert_eq!(before.fids, after.fids, "{:?} {}", mode, keyword);
+            assert_eq!(before.proof, after.proof, "{:?} {}", mode, keyword);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! 关键词迁移测试
+//!
+//! 在同一个进程内启动 Manager 和 storager，通过 Manager 的 gRPC 接口写入数据后
+//! 加入、移除节点，检查迁移后每个 keyword 的查询结果完整且证明验证通过。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::{DeregisterStoragerRequest, RegisterStoragerRequest};
+use common::AdsMode;
+use manager::Manager;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use storager::ads::registry::create_ads;
+use storager::Storager;
+use support::{add, query_keyword, serve, serve_storager};
+use tonic::transport::{Channel, Server};
+
+/// 通过 Manager 查询所有 keyword，检查结果与写入的数据一致且验证通过
+async fn assert_complete(
+    client: &mut ManagerServiceClient<Channel>,
+    expected: &BTreeMap<String, BTreeSet<String>>,
+) {
+    for (keyword, fids) in expected {
+        let response = query_keyword(client, keyword).await;
+        let found: BTreeSet<String> = response.fids.into_iter().collect();
+        assert_eq!(&found, fids, "keyword {}", keyword);
+        assert!(response.verified, "keyword {}", keyword);
+    }
+}
+
+async fn run_membership_changes(mode: AdsMode, files: usize) {
+    let first = Arc::new(Storager::with_ads(create_ads(mode).unwrap()));
+    let manager = Manager::new(vec![serve_storager(first.clone())], mode);
+    let manager_service = ManagerServiceServer::new(manager);
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    let mut expected: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for i in 0..files {
+        let keywords = vec![format!("kw{}", i % 11), format!("kw{}", (i * 7 + 3) % 11)];
+        let fid = format!("file-{}", i);
+        add(&mut client, &fid, &keywords).await;
+        for keyword in keywords {
+            expected.entry(keyword).or_default().insert(fid.clone());
+        }
+    }
+
+    // 加入节点：换主的 keyword 在切换路由之前复制到新节点
+    let second = Arc::new(Storager::with_ads(create_ads(mode).unwrap()));
+    let response = client
+        .register_storager(RegisterStoragerRequest {
+            name: "storager-1".to_string(),
+            address: serve_storager(second.clone()),
+            virtual_nodes: 0,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.migrated_keywords > 0, "{:?}", mode);
+    assert_eq!(
+        second.keywords().unwrap().len() as u64,
+        response.migrated_keywords
+    );
+    assert_complete(&mut client, &expected).await;
+
+    // 迁移之后的写入和查询照常进行
+    let extra = vec!["kw0".to_string(), "kw5".to_string()];
+    add(&mut client, "file-extra", &extra).await;
+    for keyword in extra {
+        expected
+            .entry(keyword)
+            .or_default()
+            .insert("file-extra".to_string());
+    }
+
+    // 移除原来的节点：剩余的 keyword 全部迁移到新节点
+    let response = client
+        .deregister_storager(DeregisterStoragerRequest {
+            name: "storager-0".to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.migrated_keywords > 0, "{:?}", mode);
+    assert_eq!(second.keywords().unwrap(), first.keywords().unwrap());
+    assert_complete(&mut client, &expected).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_membership_changes_migrate_keywords() {
+    run_membership_changes(AdsMode::MerkleTree, 30).await;
+    run_membership_changes(AdsMode::Mpt, 30).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_membership_changes_migrate_accumulator_keywords() {
+    run_membership_changes(AdsMode::CryptoAccumulator, 6).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unreachable_target_keeps_routing() {
+    let storager = Arc::new(Storager::with_merkle_tree());
+    let manager = Manager::new(vec![serve_storager(storager)], AdsMode::MerkleTree);
+    let manager_service = ManagerServiceServer::new(manager);
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    let keywords: Vec<String> = (0..20).map(|i| format!("kw{}", i)).collect();
+    add(&mut client, "f1", &keywords).await;
+
+    let status = client
+        .register_storager(RegisterStoragerRequest {
+            name: "storager-1".to_string(),
+            address: "http://127.0.0.1:1".to_string(),
+            virtual_nodes: 0,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+    let expected = keywords
+        .into_iter()
+        .map(|keyword| (keyword, BTreeSet::from(["f1".to_string()])))
+        .collect();
+    assert_complete(&mut client, &expected).await;
+}
@@ -0,0 +1,146 @@
+//! 命名空间隔离测试
+//!
+//! 同一个 keyword 在两个命名空间中保存各自的 fid，查询互不可见；
+//! 每个命名空间有独立的根哈希，非法的命名空间名称被拒绝。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_client::StoragerServiceClient;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::rpc::*;
+use common::{AdsMode, ErrorKind};
+use manager::core::AckPolicy;
+use manager::Manager;
+use std::collections::HashMap;
+use std::time::Duration;
+use storager::Storager;
+use support::serve;
+use tonic::transport::{Channel, Server};
+use tonic::Code;
+
+async fn add(
+    client: &mut ManagerServiceClient<Channel>,
+    namespace: &str,
+    fid: &str,
+    keyword: &str,
+) -> AddResponse {
+    client
+        .add(AddRequest {
+            fid: fid.to_string(),
+            keywords: vec![keyword.to_string()],
+            ack_mode: AckMode::Sync as i32,
+            namespace: namespace.to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+fn query(namespace: &str, keyword: &str) -> QueryRequest {
+    QueryRequest {
+        query_type: Some(query_request::QueryType::Keyword(keyword.to_string())),
+        namespace: namespace.to_string(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_namespaces_are_isolated() {
+    let storager = StoragerServiceServer::new(Storager::with_mpt());
+    let storager_addr = serve(move || Server::builder().add_service(storager.clone()));
+    let manager =
+        ManagerServiceServer::new(Manager::new(vec![storager_addr.clone()], AdsMode::Mpt));
+    let manager_addr = serve(move || Server::builder().add_service(manager.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    assert!(add(&mut client, "", "f1", "rust").await.success);
+    assert!(add(&mut client, "tenant-a", "f2", "rust").await.success);
+
+    for (namespace, expected) in [("", "f1"), ("tenant-a", "f2")] {
+        let response = client
+            .query(query(namespace, "rust"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.verified);
+        assert_eq!(response.fids, vec![expected]);
+    }
+    let response = client
+        .query(query("tenant-b", "rust"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.fids.is_empty());
+    // 没有写入过的命名空间没有跟踪的根哈希，结果无从验证
+    assert!(!response.verified);
+
+    // 订阅开始时先收到每个 (storager, 命名空间) 的当前根
+    let mut updates = client
+        .subscribe_root_hashes(SubscribeRootHashesRequest {})
+        .await
+        .unwrap()
+        .into_inner();
+    let mut roots = HashMap::new();
+    while roots.len() < 2 {
+        let update = tokio::time::timeout(Duration::from_secs(5), updates.message())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        roots.insert(update.namespace, update.root_hash);
+    }
+    assert_ne!(roots[""], roots["tenant-a"]);
+
+    let mut storager = StoragerServiceClient::connect(storager_addr).await.unwrap();
+    let namespaces = storager
+        .list_namespaces(ListNamespacesRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .namespaces;
+    // 查询不存在的命名空间也会创建它（空的 ADS 才能给出不存在证明）
+    assert_eq!(namespaces, vec!["tenant-a", "tenant-b"]);
+
+    let status = client.query(query("bad/name", "rust")).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(
+        ErrorKind::from_status(&status),
+        Some(ErrorKind::InvalidRequest)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sync_ack_follows_namespace() {
+    let storager = StoragerServiceServer::new(Storager::with_mpt());
+    let storager_addr = serve(move || Server::builder().add_service(storager.clone()));
+    let manager = Manager::new(vec![storager_addr], AdsMode::Mpt)
+        .with_ack_policy(AckPolicy::default().require_sync_for("bank"));
+    let manager = ManagerServiceServer::new(manager);
+    let manager_addr = serve(move || Server::builder().add_service(manager.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    let add_async = |namespace: &str, tenant: &str| AddRequest {
+        fid: "f1".to_string(),
+        keywords: vec!["rust".to_string()],
+        ack_mode: AckMode::Async as i32,
+        tenant: tenant.to_string(),
+        namespace: namespace.to_string(),
+        ..Default::default()
+    };
+    // 关键租户的命名空间总是同步确认，请求自报的 tenant 不起作用
+    let response = client
+        .add(add_async("bank", "blog"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.success && response.pending_ops.is_empty());
+    let response = client
+        .add(add_async("blog", "bank"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.success && !response.pending_ops.is_empty());
+}
@@ -0,0 +1,108 @@
+//! 不存在证明测试
+//!
+//! keyword 没有 fid 时 storager 返回空列表和不存在证明：MPT 给出沿 keyword 路径的证明，
+//! 累加器给出 keyword 集合上的非成员资格证明。Manager 只有在证明确实针对被查询的 keyword 时
+//! 才把空结果标记为已验证。
+//!
+//! 累加器的证明只说明结果构成证明中的累加器；Manager 跟踪每个 keyword 的累加器值，
+//! storager 绕过 Manager 丢掉 fid 后，剩下的结果和不存在证明都不能通过验证。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::storager_service_server::StoragerService;
+use common::rpc::{BulkAddRecord, DeleteRequest, StoragerDeleteRequest};
+use common::AdsMode;
+use manager::Manager;
+use std::sync::Arc;
+use storager::Storager;
+use support::{connect_manager, query_keyword, serve_storager};
+use tonic::transport::Channel;
+
+async fn start(storager: Arc<Storager>, mode: AdsMode) -> ManagerServiceClient<Channel> {
+    let storager_addr = serve_storager(storager);
+    let mut client = connect_manager(Arc::new(Manager::new(vec![storager_addr], mode))).await;
+
+    let records = [("f1", "rust"), ("f2", "rust"), ("f3", "go")]
+        .into_iter()
+        .map(|(fid, keyword)| BulkAddRecord {
+            fid: fid.to_string(),
+            keywords: vec![keyword.to_string()],
+            namespace: String::new(),
+        })
+        .collect::<Vec<_>>();
+    let response = client
+        .bulk_add(tokio_stream::iter(records))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.success, "{}", response.message);
+    client
+}
+
+async fn check_absent_keywords(storager: Storager, mode: AdsMode) {
+    let mut client = start(Arc::new(storager), mode).await;
+
+    let result = query_keyword(&mut client, "rust").await;
+    assert!(result.verified, "{}", mode.name());
+    assert_eq!(result.fids.len(), 2);
+
+    // 从未写入的 keyword，以及与已有 keyword 共享前缀的 keyword
+    for keyword in ["java", "rus", "rusty"] {
+        let result = query_keyword(&mut client, keyword).await;
+        assert!(result.fids.is_empty(), "{}", keyword);
+        assert!(result.verified, "{}: {}", mode.name(), keyword);
+    }
+
+    // 最后一个 fid 被删除后同样可以证明 keyword 不存在
+    let response = client
+        .delete(DeleteRequest {
+            fid: "f3".to_string(),
+            keywords: vec!["go".to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.success, "{}", response.message);
+    let result = query_keyword(&mut client, "go").await;
+    assert!(result.fids.is_empty());
+    assert!(result.verified, "{}", mode.name());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_mpt_proves_absent_keywords() {
+    check_absent_keywords(Storager::with_mpt(), AdsMode::Mpt).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_accumulator_proves_absent_keywords() {
+    check_absent_keywords(
+        Storager::with_crypto_accumulator(),
+        AdsMode::CryptoAccumulator,
+    )
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_accumulator_rejects_dropped_fids() {
+    let storager = Arc::new(Storager::with_crypto_accumulator());
+    let mut client = start(storager.clone(), AdsMode::CryptoAccumulator).await;
+
+    // storager 绕过 Manager 删除 fid，剩下的 fid 构成一个自洽的累加器
+    for (keyword, fid) in [("rust", "f2"), ("go", "f3")] {
+        let request = StoragerDeleteRequest {
+            keyword: keyword.to_string(),
+            fid: fid.to_string(),
+            ..Default::default()
+        };
+        storager.delete(tonic::Request::new(request)).await.unwrap();
+    }
+
+    let result = query_keyword(&mut client, "rust").await;
+    assert_eq!(result.fids, ["f1"]);
+    assert!(!result.verified);
+    let result = query_keyword(&mut client, "go").await;
+    assert!(result.fids.is_empty());
+    assert!(!result.verified);
+}
@@ -0,0 +1,153 @@
+//! 查询分页测试
+//!
+//! 单关键词查询由 storager 按游标分页，证明只随最后一页返回，Manager 在游标中折叠已交付的 fid，
+//! 最后一页连同之前各页一起对照证明；
+//! 翻页期间 keyword 被写入时旧游标失效。布尔查询在 Manager 上计算完整结果后分页。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::query_request::QueryType;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::rpc::{BulkAddRecord, QueryRequest, QueryResponse};
+use common::AdsMode;
+use manager::Manager;
+use std::collections::BTreeSet;
+use storager::Storager;
+use support::serve;
+use tonic::transport::{Channel, Server};
+use tonic::{Code, Status};
+
+async fn start() -> ManagerServiceClient<Channel> {
+    let service = StoragerServiceServer::new(Storager::with_mpt());
+    let storager_addr = serve(move || Server::builder().add_service(service.clone()));
+    let manager_service =
+        ManagerServiceServer::new(Manager::new(vec![storager_addr], AdsMode::Mpt));
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    ManagerServiceClient::connect(manager_addr).await.unwrap()
+}
+
+async fn add(client: &mut ManagerServiceClient<Channel>, fids: &[&str], keywords: &[&str]) {
+    let records: Vec<BulkAddRecord> = fids
+        .iter()
+        .map(|fid| BulkAddRecord {
+            fid: fid.to_string(),
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            namespace: String::new(),
+        })
+        .collect();
+    let response = client
+        .bulk_add(tokio_stream::iter(records))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.success, "{}", response.message);
+}
+
+async fn page(
+    client: &mut ManagerServiceClient<Channel>,
+    query_type: QueryType,
+    page_token: &str,
+) -> Result<QueryResponse, Status> {
+    client
+        .query(QueryRequest {
+            query_type: Some(query_type),
+            page_size: 3,
+            page_token: page_token.to_string(),
+            ..Default::default()
+        })
+        .await
+        .map(|response| response.into_inner())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_keyword_pages_carry_proof_on_last_page() {
+    let mut client = start().await;
+    let fids = ["f0", "f1", "f2", "f3", "f4", "f5", "f6"];
+    add(&mut client, &fids, &["rust"]).await;
+
+    let keyword = || QueryType::Keyword("rust".to_string());
+    let mut collected = Vec::new();
+    let mut token = String::new();
+    let mut pages = 0;
+    let last = loop {
+        let response = page(&mut client, keyword(), &token).await.unwrap();
+        pages += 1;
+        assert_eq!(response.total_count, 7);
+        collected.extend(response.fids.clone());
+        if response.next_page_token.is_empty() {
+            break response;
+        }
+        // 中间页不带证明
+        assert!(response.proof.is_none());
+        assert!(!response.verified);
+        token = response.next_page_token;
+    };
+    assert_eq!(pages, 3);
+    assert!(last.proof.is_some());
+    assert!(last.verified);
+    assert_eq!(
+        collected
+            .iter()
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>(),
+        fids.into_iter().collect()
+    );
+
+    // 翻页期间 keyword 被写入，旧游标失效
+    let first = page(&mut client, keyword(), "").await.unwrap();
+    add(&mut client, &["f7"], &["rust"]).await;
+    let error = page(&mut client, keyword(), &first.next_page_token)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::Aborted);
+
+    let error = page(&mut client, keyword(), "garbage").await.unwrap_err();
+    assert_eq!(error.code(), Code::InvalidArgument);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_last_page_checks_earlier_pages() {
+    let mut client = start().await;
+    add(&mut client, &["f0", "f1", "f2", "f3", "f4"], &["rust"]).await;
+
+    let keyword = || QueryType::Keyword("rust".to_string());
+    let first = page(&mut client, keyword(), "").await.unwrap();
+    let (storager_token, _) = first.next_page_token.rsplit_once('|').unwrap();
+
+    // 游标中已交付 fid 的摘要与第一页不符（相当于第一页被替换），完整证明也不能让结果通过验证
+    let forged = format!("{}|{}", storager_token, "00".repeat(32));
+    let last = page(&mut client, keyword(), &forged).await.unwrap();
+    assert!(last.next_page_token.is_empty());
+    assert!(last.proof.is_some());
+    assert!(!last.verified);
+
+    let last = page(&mut client, keyword(), &first.next_page_token)
+        .await
+        .unwrap();
+    assert!(last.verified);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_boolean_query_pages() {
+    let mut client = start().await;
+    add(&mut client, &["f0", "f1", "f2"], &["rust"]).await;
+    add(&mut client, &["f3", "f4"], &["go"]).await;
+
+    let query = || QueryType::BooleanFunction("rust OR go".to_string());
+    let first = page(&mut client, query(), "").await.unwrap();
+    assert_eq!(first.fids.len(), 3);
+    assert_eq!(first.total_count, 5);
+    assert!(first.proof.is_none());
+
+    let second = page(&mut client, query(), &first.next_page_token)
+        .await
+        .unwrap();
+    assert_eq!(second.fids.len(), 2);
+    assert!(second.next_page_token.is_empty());
+    assert!(second.verified);
+
+    let all: BTreeSet<String> = first.fids.into_iter().chain(second.fids).collect();
+    assert_eq!(all.len(), 5);
+}
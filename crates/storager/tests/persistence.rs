@@ -0,0 +1,165 @@
+//! 持久化测试
+//!
+//! storager 使用 RocksDB（或 sled）后端写入数据后关闭，在同一目录上重新打开：
+//! 检查点之后只存在于 WAL 中的写操作被重放，查询结果和证明对照关闭前发布的根哈希仍然通过验证。
+
+use common::rpc::storager_service_server::StoragerService;
+use common::rpc::{
+    PruneRequest, StoragerAddRequest, StoragerBatchAddRequest, StoragerDeleteRequest,
+    StoragerQueryRequest,
+};
+use common::AdsMode;
+use manager::core::ProofVerifier;
+use std::collections::HashMap;
+use storager::{DbBackend, Storager};
+use tonic::Request;
+
+/// 写入数据，返回每个 keyword 最后一次写入后的根哈希
+async fn write(storager: &Storager) -> HashMap<&'static str, Vec<u8>> {
+    let mut roots = HashMap::new();
+    for (keyword, fid) in [("rust", "f1"), ("go", "f2"), ("rust", "f3")] {
+        let response = storager
+            .add(Request::new(StoragerAddRequest {
+                keyword: keyword.to_string(),
+                fid: fid.to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        roots.insert(keyword, response.root_hash);
+    }
+    let response = storager
+        .batch_add(Request::new(StoragerBatchAddRequest {
+            fid: "f4".to_string(),
+            keywords: vec!["go".to_string(), "python".to_string()],
+            ..Default::default()
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    roots.insert("go", response.root_hash.clone());
+    roots.insert("python", response.root_hash);
+    let response = storager
+        .delete(Request::new(StoragerDeleteRequest {
+            keyword: "rust".to_string(),
+            fid: "f1".to_string(),
+            ..Default::default()
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    roots.insert("rust", response.root_hash);
+    roots
+}
+
+async fn check_restart(mode: AdsMode, backend: DbBackend) {
+    let roots = {
+        let storager = Storager::open(mode.name(), &backend).unwrap();
+        write(&storager).await
+    };
+    // MPT 所有 keyword 共用一个根，以最后一次写入为准
+    let latest = roots["rust"].clone();
+
+    let storager = Storager::open(mode.name(), &backend).unwrap();
+    let verifier = ProofVerifier::new(mode);
+    for (keyword, expected) in [
+        ("rust", vec!["f3"]),
+        ("go", vec!["f2", "f4"]),
+        ("python", vec!["f4"]),
+    ] {
+        let response = storager
+            .query(Request::new(StoragerQueryRequest {
+                keyword: keyword.to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let mut fids = response.fids.clone();
+        fids.sort();
+        assert_eq!(fids, expected, "{:?} {}", mode, keyword);
+
+        let proof = response.proof.unwrap().try_into().unwrap();
+        let root_hash = match mode {
+            AdsMode::Mpt => &latest,
+            _ => &roots[keyword],
+        };
+        assert!(verifier.verify(&proof, root_hash), "{:?} {}", mode, keyword);
+        assert!(verifier.verify_completeness(&proof, keyword, &response.fids));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_mpt_state_survives_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    check_restart(AdsMode::Mpt, DbBackend::RocksDb(dir.path().to_path_buf())).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_accumulator_state_survives_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let backend = DbBackend::RocksDb(dir.path().to_path_buf());
+    check_restart(AdsMode::CryptoAccumulator, backend).await;
+}
+
+#[cfg(feature = "sled")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sled_state_survives_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    check_restart(AdsMode::Mpt, DbBackend::Sled(dir.path().join("mpt"))).await;
+    let backend = DbBackend::Sled(dir.path().join("accumulator"));
+    check_restart(AdsMode::CryptoAccumulator, backend).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_pruned_mpt_survives_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let backend = DbBackend::RocksDb(dir.path().to_path_buf());
+    let root = {
+        let storager = Storager::open(AdsMode::Mpt.name(), &backend).unwrap();
+        let roots = write(&storager).await;
+        let response = storager
+            .prune(Request::new(PruneRequest {
+                keep_roots: vec![],
+                namespace: String::new(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.nodes_deleted > 0);
+        assert!(response.nodes_kept > 0);
+        roots["rust"].clone()
+    };
+
+    let storager = Storager::open(AdsMode::Mpt.name(), &backend).unwrap();
+    let response = storager
+        .query(Request::new(StoragerQueryRequest {
+            keyword: "go".to_string(),
+            ..Default::default()
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.fids, vec!["f2", "f4"]);
+    let proof = response.proof.unwrap().try_into().unwrap();
+    assert!(ProofVerifier::new(AdsMode::Mpt).verify(&proof, &root));
+
+    let status = storager
+        .prune(Request::new(PruneRequest {
+            keep_roots: vec![vec![0u8; 5]],
+            namespace: String::new(),
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rocksdb_requires_known_mode() {
+    let dir = tempfile::tempdir().unwrap();
+    let backend = DbBackend::RocksDb(dir.path().to_path_buf());
+    assert!(Storager::open("no-such-ads", &backend).is_err());
+    assert!(DbBackend::parse("rocksdb", None).is_err());
+    assert_eq!(DbBackend::parse("memory", None), Ok(DbBackend::Memory));
+}
@@ -0,0 +1,152 @@
+//! keyword 范围查询测试
+//!
+//! keyword 分散在多个 storager 上，Manager 合并每个 storager 的范围证明结果，
+//! 前缀搜索 `category:*` 只返回该前缀下的 keyword；前缀查询（自动补全）只返回 fid 数量。
+
+mod support;
+
+use common::prefix_range_end;
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::rpc::{BulkAddRecord, PrefixQueryRequest, RangeQueryRequest, RangeQueryResponse};
+use common::AdsMode;
+use manager::Manager;
+use storager::Storager;
+use support::serve;
+use tonic::transport::{Channel, Server};
+
+async fn range(
+    client: &mut ManagerServiceClient<Channel>,
+    start_key: &str,
+    end_key: &str,
+) -> RangeQueryResponse {
+    client
+        .range_query(RangeQueryRequest {
+            start_key: start_key.to_string(),
+            end_key: end_key.to_string(),
+            namespace: String::new(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+fn keywords(response: &RangeQueryResponse) -> Vec<&str> {
+    response
+        .entries
+        .iter()
+        .map(|entry| entry.keyword.as_str())
+        .collect()
+}
+
+/// 启动三个 MPT storager 和 Manager，写入测试数据
+async fn start() -> ManagerServiceClient<Channel> {
+    let storager_addrs = (0..3)
+        .map(|_| {
+            let service = StoragerServiceServer::new(Storager::with_mpt());
+            serve(move || Server::builder().add_service(service.clone()))
+        })
+        .collect();
+    let manager_service = ManagerServiceServer::new(Manager::new(storager_addrs, AdsMode::Mpt));
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    let records = [
+        ("f1", vec!["category:books", "lang:en"]),
+        ("f2", vec!["category:art", "category:books"]),
+        ("f3", vec!["category", "categoryx", "lang:fr"]),
+        ("f4", vec!["category:music", "dog"]),
+        // 落在所有查询范围之外，保证每个 storager 都有数据、Manager 跟踪了它的根哈希
+        ("f5", vec!["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"]),
+    ]
+    .into_iter()
+    .map(|(fid, keywords)| BulkAddRecord {
+        fid: fid.to_string(),
+        keywords: keywords.into_iter().map(str::to_string).collect(),
+        namespace: String::new(),
+    })
+    .collect::<Vec<_>>();
+    let response = client
+        .bulk_add(tokio_stream::iter(records))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.success, "{}", response.message);
+    client
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_prefix_search_across_storagers() {
+    let mut client = start().await;
+
+    let end = prefix_range_end("category:");
+    assert_eq!(end, "category;");
+    let result = range(&mut client, "category:", &end).await;
+    assert!(result.verified);
+    assert_eq!(
+        keywords(&result),
+        vec!["category:art", "category:books", "category:music"]
+    );
+    let books = &result.entries[1];
+    let mut fids = books.fids.clone();
+    fids.sort();
+    assert_eq!(fids, vec!["f1", "f2"]);
+
+    // 没有上界时返回起点之后的全部 keyword
+    let result = range(&mut client, "d", "").await;
+    assert!(result.verified);
+    assert_eq!(keywords(&result), vec!["dog", "lang:en", "lang:fr"]);
+
+    // 空范围同样带有可验证的证明
+    let result = range(&mut client, "x", "y").await;
+    assert!(result.verified);
+    assert!(result.entries.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_prefix_completion_counts() {
+    let mut client = start().await;
+
+    let complete = |prefix: &str| PrefixQueryRequest {
+        prefix: prefix.to_string(),
+        namespace: String::new(),
+    };
+    let result = client
+        .query_by_prefix(complete("cat"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(result.verified);
+    let counts: Vec<(&str, u64)> = result
+        .keywords
+        .iter()
+        .map(|k| (k.keyword.as_str(), k.fid_count))
+        .collect();
+    assert_eq!(
+        counts,
+        vec![
+            ("category", 1),
+            ("category:art", 1),
+            ("category:books", 2),
+            ("category:music", 1),
+            ("categoryx", 1),
+        ]
+    );
+
+    let result = client
+        .query_by_prefix(complete("lang:"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(result.verified);
+    assert_eq!(result.keywords.len(), 2);
+
+    let result = client
+        .query_by_prefix(complete("zebra"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(result.verified);
+    assert!(result.keywords.is_empty());
+}
@@ -0,0 +1,152 @@
+//! 读修复测试
+//!
+//! 复制因子为 3 时冻结一个副本使它错过写入，解冻后通过 Manager 查询，
+//! 检查查询返回多数副本的结果，并且落后的副本被补齐；复制因子增大时已有的 keyword
+//! 先复制到新增的副本。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::{StoragerService, StoragerServiceServer};
+use common::rpc::{
+    query_request::QueryType, AckMode, AddRequest, QueryRequest, StoragerQueryRequest,
+};
+use common::AdsMode;
+use manager::{Manager, ReloadConfig};
+use std::sync::Arc;
+use storager::Storager;
+use support::serve;
+use tonic::transport::{Channel, Server};
+
+async fn add(client: &mut ManagerServiceClient<Channel>, fid: &str, keyword: &str) -> bool {
+    client
+        .add(AddRequest {
+            fid: fid.to_string(),
+            keywords: vec![keyword.to_string()],
+            ack_mode: AckMode::Sync as i32,
+            tenant: String::new(),
+            namespace: String::new(),
+            ttl_seconds: 0,
+        })
+        .await
+        .is_ok_and(|response| response.into_inner().success)
+}
+
+async fn local_fids(storager: &Storager, keyword: &str) -> Vec<String> {
+    let mut fids = StoragerService::query(
+        storager,
+        tonic::Request::new(StoragerQueryRequest {
+            keyword: keyword.to_string(),
+            ..Default::default()
+        }),
+    )
+    .await
+    .unwrap()
+    .into_inner()
+    .fids;
+    fids.sort();
+    fids
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_query_repairs_lagging_replica() {
+    let storagers: Vec<Arc<Storager>> = (0..3)
+        .map(|_| Arc::new(Storager::with_merkle_tree()))
+        .collect();
+    let addrs = storagers
+        .iter()
+        .map(|storager| {
+            let service = StoragerServiceServer::from_arc(storager.clone());
+            serve(move || Server::builder().add_service(service.clone()))
+        })
+        .collect();
+    let manager = Manager::new(addrs, AdsMode::MerkleTree).with_replication_factor(3);
+    let manager_service = ManagerServiceServer::new(manager);
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    let keywords: Vec<String> = (0..8).map(|i| format!("kw{}", i)).collect();
+    for keyword in &keywords {
+        assert!(add(&mut client, "f0", keyword).await);
+    }
+
+    // 冻结的副本拒绝写入；它是主副本的 keyword 写入失败，其余 keyword 只缺这个副本
+    let lagging = &storagers[2];
+    lagging.freeze();
+    let mut missed = Vec::new();
+    for keyword in &keywords {
+        if add(&mut client, "f1", keyword).await {
+            missed.push(keyword.clone());
+        }
+    }
+    lagging.unfreeze();
+    assert!(!missed.is_empty());
+
+    for keyword in &missed {
+        assert_eq!(local_fids(lagging, keyword).await, vec!["f0"]);
+        let response = client
+            .query(QueryRequest {
+                query_type: Some(QueryType::Keyword(keyword.clone())),
+                allow_background: false,
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut fids = response.fids;
+        fids.sort();
+        assert_eq!(fids, vec!["f0", "f1"], "keyword {}", keyword);
+        assert!(response.verified, "keyword {}", keyword);
+        assert_eq!(local_fids(lagging, keyword).await, vec!["f0", "f1"]);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_raising_replication_factor_backfills_replicas() {
+    let storagers: Vec<Arc<Storager>> = (0..3)
+        .map(|_| Arc::new(Storager::with_merkle_tree()))
+        .collect();
+    let addrs = storagers
+        .iter()
+        .map(|storager| {
+            let service = StoragerServiceServer::from_arc(storager.clone());
+            serve(move || Server::builder().add_service(service.clone()))
+        })
+        .collect();
+    let manager = Arc::new(Manager::new(addrs, AdsMode::MerkleTree));
+    let manager_service = ManagerServiceServer::from_arc(manager.clone());
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    let keywords: Vec<String> = (0..8).map(|i| format!("kw{}", i)).collect();
+    for keyword in &keywords {
+        assert!(add(&mut client, "f0", keyword).await);
+    }
+
+    // 切换复制因子之前每个 keyword 已经复制到另外两个节点
+    let report = manager
+        .reload(ReloadConfig {
+            replication_factor: Some(3),
+            ..ReloadConfig::default()
+        })
+        .await;
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!(report.backfilled.keywords, 2 * keywords.len());
+    assert_eq!(manager.replication_factor(), 3);
+    for keyword in &keywords {
+        for storager in &storagers {
+            assert_eq!(local_fids(storager, keyword).await, vec!["f0"]);
+        }
+        let response = client
+            .query(QueryRequest {
+                query_type: Some(QueryType::Keyword(keyword.clone())),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.fids, vec!["f0"], "keyword {}", keyword);
+        assert!(response.verified, "keyword {}", keyword);
+    }
+}
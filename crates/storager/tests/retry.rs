@@ -0,0 +1,121 @@
+//! Manager → Storager 调用的重试测试
+//!
+//! 连接失败按退避重试，storager 启动后请求成功；fid 不存在之类的错误不重试，
+//! 状态码和错误类别原样返回给客户端。
+
+use common::net::{bind_tcp, serve_listeners, Listeners};
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::rpc::{AddRequest, DeleteRequest};
+use common::{AdsMode, ErrorKind};
+use manager::core::RetryPolicy;
+use manager::Manager;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use storager::Storager;
+use tonic::transport::server::Router;
+use tonic::transport::{Channel, Server};
+use tonic::Code;
+
+/// 在指定地址上启动服务
+fn serve_at<F>(addr: SocketAddr, make_router: F)
+where
+    F: FnMut() -> Router + Send + 'static,
+{
+    let listeners = Listeners {
+        tcp: vec![bind_tcp(addr).unwrap()],
+        ..Default::default()
+    };
+    tokio::spawn(async move {
+        serve_listeners(listeners, make_router, std::future::pending())
+            .await
+            .unwrap()
+    });
+}
+
+/// 取得一个当前空闲的本地端口
+fn free_addr() -> SocketAddr {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap()
+}
+
+fn policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 8,
+        initial_backoff: Duration::from_millis(50),
+        max_backoff: Duration::from_millis(200),
+        timeout: Some(Duration::from_secs(10)),
+    }
+}
+
+async fn start_manager(
+    storager_addr: SocketAddr,
+    ads_mode: AdsMode,
+) -> ManagerServiceClient<Channel> {
+    let manager = Manager::new(vec![format!("http://{}", storager_addr)], ads_mode)
+        .with_retry_policy(policy());
+    let service = ManagerServiceServer::new(manager);
+    let manager_addr = free_addr();
+    serve_at(manager_addr, move || {
+        Server::builder().add_service(service.clone())
+    });
+    let endpoint = format!("http://{}", manager_addr);
+    for _ in 0..50 {
+        if let Ok(client) = ManagerServiceClient::connect(endpoint.clone()).await {
+            return client;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("manager did not start");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_add_retries_until_storager_starts() {
+    let storager_addr = free_addr();
+    let mut client = start_manager(storager_addr, AdsMode::Mpt).await;
+
+    // storager 晚于第一次尝试启动
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let service = StoragerServiceServer::new(Storager::with_mpt());
+        serve_at(storager_addr, move || {
+            Server::builder().add_service(service.clone())
+        });
+    });
+
+    let response = client
+        .add(AddRequest {
+            fid: "f1".to_string(),
+            keywords: vec!["rust".to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.success, "{}", response.message);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_permanent_errors_are_not_retried() {
+    let storager_addr = free_addr();
+    let service = StoragerServiceServer::new(Storager::new());
+    serve_at(storager_addr, move || {
+        Server::builder().add_service(service.clone())
+    });
+    let mut client = start_manager(storager_addr, AdsMode::CryptoAccumulator).await;
+
+    let started = Instant::now();
+    let status = client
+        .delete(DeleteRequest {
+            fid: "f1".to_string(),
+            keywords: vec!["rust".to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+    assert_eq!(ErrorKind::from_status(&status), Some(ErrorKind::Storage));
+    // 8 次尝试的退避时间合计超过 1 秒
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
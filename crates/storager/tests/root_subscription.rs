@@ -0,0 +1,65 @@
+//! 根哈希订阅测试
+//!
+//! 客户端订阅 Manager 的根哈希后，先收到当前的根哈希，之后每次写入验证通过都收到新的根；
+//! 用收到的根哈希可以独立验证 storager 直接返回的查询证明。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::{StoragerService, StoragerServiceServer};
+use common::rpc::{StoragerQueryRequest, SubscribeRootHashesRequest};
+use common::{AdsMode, Proof};
+use manager::core::ProofVerifier;
+use manager::Manager;
+use std::sync::Arc;
+use storager::Storager;
+use support::{add, serve};
+use tonic::transport::Server;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_subscribers_receive_verified_roots() {
+    let storager = Arc::new(Storager::with_mpt());
+    let service = StoragerServiceServer::from_arc(storager.clone());
+    let storager_addr = serve(move || Server::builder().add_service(service.clone()));
+    let manager = Manager::new(vec![storager_addr], AdsMode::Mpt);
+    let manager_service = ManagerServiceServer::new(manager);
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    add(&mut client, "f0", &["rust"]).await;
+
+    let mut updates = client
+        .subscribe_root_hashes(SubscribeRootHashesRequest {})
+        .await
+        .unwrap()
+        .into_inner();
+
+    // 订阅时先收到当前的根哈希
+    let current = updates.message().await.unwrap().unwrap();
+    assert!(current.version > 0);
+    assert!(!current.root_hash.is_empty());
+
+    add(&mut client, "f1", &["go"]).await;
+    let update = updates.message().await.unwrap().unwrap();
+    assert_eq!(update.storager, current.storager);
+    assert!(update.version > current.version);
+    assert_ne!(update.root_hash, current.root_hash);
+
+    // 客户端用订阅到的根哈希独立验证 storager 的证明
+    let response = StoragerService::query(
+        storager.as_ref(),
+        tonic::Request::new(StoragerQueryRequest {
+            keyword: "rust".to_string(),
+            ..Default::default()
+        }),
+    )
+    .await
+    .unwrap()
+    .into_inner();
+    assert_eq!(response.fids, vec!["f0"]);
+    let proof = Proof::try_from(response.proof).unwrap();
+    let verifier = ProofVerifier::new(AdsMode::Mpt);
+    assert!(verifier.verify(&proof, &update.root_hash));
+    assert!(!verifier.verify(&proof, &current.root_hash));
+}
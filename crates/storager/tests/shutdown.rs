@@ -0,0 +1,152 @@
+//! 优雅关闭测试
+//!
+//! 关闭信号到达后服务停止接受新连接；长期打开的订阅流在排空时限到达后被关闭，
+//! 服务随之返回。之后 storager 等待推迟生成的证明完成并落盘，Manager 保存路由表。
+
+use common::net::{bind_tcp, serve_with_drain, Listeners};
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::{StoragerService, StoragerServiceServer};
+use common::rpc::{
+    AckMode, AddRequest, GetProofRequest, StoragerAddRequest, SubscribeRootHashesRequest,
+};
+use common::AdsMode;
+use manager::Manager;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use storager::Storager;
+use tokio::sync::oneshot;
+use tonic::transport::server::Router;
+use tonic::transport::Server;
+use tonic::Request;
+
+/// 在随机端口上启动服务，`stop` 发送后开始排空；返回通告地址和服务任务
+fn serve<F>(
+    make_router: F,
+    drain: Duration,
+) -> (String, oneshot::Sender<()>, tokio::task::JoinHandle<()>)
+where
+    F: FnMut() -> Router + Send + 'static,
+{
+    let listeners = Listeners {
+        tcp: vec![bind_tcp("127.0.0.1:0".parse().unwrap()).unwrap()],
+        ..Default::default()
+    };
+    let addr = format!("http://{}", listeners.tcp[0].local_addr().unwrap());
+    let (stop, stopped) = oneshot::channel();
+    let task = tokio::spawn(async move {
+        let shutdown = async {
+            let _ = stopped.await;
+        };
+        serve_with_drain(listeners, make_router, shutdown, drain)
+            .await
+            .unwrap()
+    });
+    (addr, stop, task)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_drain_closes_open_streams_and_saves_state() {
+    let ring_state =
+        std::env::temp_dir().join(format!("shutdown-ring-{}.json", std::process::id()));
+    let storager = Arc::new(Storager::with_mpt());
+    let service = StoragerServiceServer::from_arc(storager.clone());
+    let (storager_addr, stop_storager, storager_task) = serve(
+        move || Server::builder().add_service(service.clone()),
+        Duration::from_secs(5),
+    );
+    let manager = Arc::new(
+        Manager::new(vec![format!("s1={}", storager_addr)], AdsMode::Mpt)
+            .with_ring_state(&ring_state)
+            .unwrap(),
+    );
+    let manager_service = ManagerServiceServer::from_arc(manager.clone());
+    let (manager_addr, stop_manager, manager_task) = serve(
+        move || Server::builder().add_service(manager_service.clone()),
+        Duration::from_millis(300),
+    );
+
+    let mut client = ManagerServiceClient::connect(manager_addr.clone())
+        .await
+        .unwrap();
+    let mut roots = client
+        .subscribe_root_hashes(SubscribeRootHashesRequest {})
+        .await
+        .unwrap()
+        .into_inner();
+    let response = client
+        .add(AddRequest {
+            fid: "f1".to_string(),
+            keywords: vec!["rust".to_string()],
+            ack_mode: AckMode::Sync as i32,
+            tenant: String::new(),
+            namespace: String::new(),
+            ttl_seconds: 0,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.success, "{}", response.message);
+    assert!(roots.message().await.unwrap().is_some());
+
+    // 订阅流不会自己结束，排空时限到达后连接被关闭
+    let started = Instant::now();
+    stop_manager.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), manager_task)
+        .await
+        .expect("manager did not stop after the drain timeout")
+        .unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    assert!(ManagerServiceClient::connect(manager_addr).await.is_err());
+
+    let _ = std::fs::remove_file(&ring_state);
+    manager.shutdown().await.unwrap();
+    assert!(ring_state.exists());
+    std::fs::remove_file(&ring_state).unwrap();
+
+    // 没有进行中的请求时 storager 立即停止
+    stop_storager.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(1), storager_task)
+        .await
+        .expect("idle storager did not stop promptly")
+        .unwrap();
+    assert_eq!(storager.shutdown().await.unwrap(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_storager_shutdown_waits_for_deferred_proofs() {
+    let storager = Storager::from_config("accumulator");
+    let mut handles = Vec::new();
+    for (namespace, fid) in [("", "f1"), ("tenant", "f2")] {
+        let response = storager
+            .add(Request::new(StoragerAddRequest {
+                keyword: "rust".to_string(),
+                fid: fid.to_string(),
+                defer_proof: true,
+                request_id: String::new(),
+                namespace: namespace.to_string(),
+                expires_at_ms: 0,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.proof.is_none());
+        handles.push((namespace, response.proof_handle));
+    }
+
+    // 默认命名空间和已打开的命名空间都被落盘
+    assert_eq!(storager.shutdown().await.unwrap(), 2);
+    for (namespace, proof_handle) in handles {
+        let proof = tokio::time::timeout(
+            Duration::from_millis(50),
+            storager.get_proof(Request::new(GetProofRequest {
+                proof_handle,
+                namespace: namespace.to_string(),
+            })),
+        )
+        .await
+        .expect("proof was not ready after shutdown")
+        .unwrap();
+        assert!(proof.into_inner().proof.is_some());
+    }
+}
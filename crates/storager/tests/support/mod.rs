@@ -0,0 +1,95 @@
+//! 集成测试共用的辅助函数
+//!
+//! 每个测试文件是一个独立的 crate，只用到其中一部分函数
+#![allow(dead_code)]
+
+use common::net::{bind_tcp, serve_listeners, Listeners};
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::rpc::{query_request::QueryType, AckMode, AddRequest, QueryRequest, QueryResponse};
+use manager::Manager;
+use std::sync::Arc;
+use storager::Storager;
+use tonic::transport::server::Router;
+use tonic::transport::{Channel, Server};
+
+/// 在随机端口上启动服务，返回通告地址
+pub fn serve<F>(make_router: F) -> String
+where
+    F: FnMut() -> Router + Send + 'static,
+{
+    let listeners = Listeners {
+        tcp: vec![bind_tcp("127.0.0.1:0".parse().unwrap()).unwrap()],
+        ..Default::default()
+    };
+    let addr = format!("http://{}", listeners.tcp[0].local_addr().unwrap());
+    tokio::spawn(async move {
+        serve_listeners(listeners, make_router, std::future::pending())
+            .await
+            .unwrap()
+    });
+    addr
+}
+
+/// 在随机端口上启动 storager，返回通告地址
+pub fn serve_storager(storager: Arc<Storager>) -> String {
+    let service = StoragerServiceServer::from_arc(storager);
+    serve(move || Server::builder().add_service(service.clone()))
+}
+
+/// 在随机端口上启动 Manager 并连接它
+pub async fn connect_manager(manager: Arc<Manager>) -> ManagerServiceClient<Channel> {
+    let service = ManagerServiceServer::from_arc(manager);
+    let addr = serve(move || Server::builder().add_service(service.clone()));
+    ManagerServiceClient::connect(addr).await.unwrap()
+}
+
+/// 同步写入一个文件，要求写入成功
+pub async fn add<S: AsRef<str>>(
+    client: &mut ManagerServiceClient<Channel>,
+    fid: &str,
+    keywords: &[S],
+) {
+    let response = client
+        .add(AddRequest {
+            fid: fid.to_string(),
+            keywords: keywords.iter().map(|k| k.as_ref().to_string()).collect(),
+            ack_mode: AckMode::Sync as i32,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.success, "{}", response.message);
+}
+
+/// 单关键词查询
+pub async fn query_keyword(
+    client: &mut ManagerServiceClient<Channel>,
+    keyword: &str,
+) -> QueryResponse {
+    query(client, QueryType::Keyword(keyword.to_string())).await
+}
+
+/// 布尔查询，结果按 fid 排序
+pub async fn query_boolean(
+    client: &mut ManagerServiceClient<Channel>,
+    func: &str,
+) -> QueryResponse {
+    let mut response = query(client, QueryType::BooleanFunction(func.to_string())).await;
+    response.fids.sort();
+    response
+}
+
+async fn query(client: &mut ManagerServiceClient<Channel>, query_type: QueryType) -> QueryResponse {
+    client
+        .query(QueryRequest {
+            query_type: Some(query_type),
+            allow_background: false,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+}
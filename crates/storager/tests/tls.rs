@@ -0,0 +1,158 @@
+//! TLS 测试
+//!
+//! storager 要求客户端证书：持有集群证书的 Manager 可以写入，
+//! 只信任 CA 而没有证书的客户端和明文客户端都被拒绝。Manager 对客户端只提供单向 TLS。
+
+mod support;
+
+use common::net::connect_with;
+use common::rpc::*;
+use common::tls::TlsConfig;
+use common::transport::TransportConfig;
+use common::AdsMode;
+use manager::Manager;
+use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa};
+use std::sync::Arc;
+use storager::Storager;
+use support::serve;
+
+/// 测试用 CA 及其签发的证书
+struct Pki {
+    ca: Certificate,
+}
+
+impl Pki {
+    fn new() -> Self {
+        let mut params = CertificateParams::new(Vec::new());
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        Pki {
+            ca: Certificate::from_params(params).unwrap(),
+        }
+    }
+
+    fn ca_pem(&self) -> String {
+        self.ca.serialize_pem().unwrap()
+    }
+
+    /// 签发 `localhost` 的证书，返回 (证书, 私钥)
+    fn issue(&self) -> (String, String) {
+        let leaf = Certificate::from_params(CertificateParams::new(vec!["localhost".to_string()]))
+            .unwrap();
+        (
+            leaf.serialize_pem_with_signer(&self.ca).unwrap(),
+            leaf.serialize_private_key_pem(),
+        )
+    }
+
+    /// 持有本 CA 签发证书的集群成员
+    fn member(&self) -> TlsConfig {
+        let (cert, key) = self.issue();
+        TlsConfig::new()
+            .with_identity(cert, key)
+            .with_ca(self.ca_pem())
+            .with_domain("localhost")
+    }
+
+    /// 只信任本 CA、没有证书的客户端
+    fn anonymous(&self) -> TlsConfig {
+        TlsConfig::new()
+            .with_ca(self.ca_pem())
+            .with_domain("localhost")
+    }
+}
+
+fn add_request(fid: &str, keyword: &str) -> StoragerAddRequest {
+    StoragerAddRequest {
+        fid: fid.to_string(),
+        keyword: keyword.to_string(),
+        ..Default::default()
+    }
+}
+
+/// 把 [`serve`] 返回的通告地址改为 https
+fn https(addr: String) -> String {
+    addr.replacen("http://", "https://", 1)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_mutual_tls_between_manager_and_storager() {
+    let pki = Pki::new();
+
+    let storager_transport =
+        TransportConfig::default().with_tls(Some(pki.member().require_client_cert(true)));
+    let service = storager_transport.storager_server(Arc::new(Storager::with_mpt()));
+    let server = storager_transport.server().unwrap();
+    let storager_addr = https(serve(move || server.clone().add_service(service.clone())));
+
+    let manager_transport = TransportConfig::default().with_tls(Some(pki.member()));
+    let manager = Manager::new(vec![storager_addr.clone()], AdsMode::Mpt)
+        .with_transport(manager_transport.clone());
+    let service = manager_transport.manager_server(Arc::new(manager));
+    let server = manager_transport.server().unwrap();
+    let manager_addr = https(serve(move || server.clone().add_service(service.clone())));
+
+    // 客户端只需要信任 CA
+    let client_transport = TransportConfig::default().with_tls(Some(pki.anonymous()));
+    let channel = connect_with(&manager_addr, &client_transport)
+        .await
+        .unwrap();
+    let mut client = client_transport.manager_client(channel);
+    client
+        .add(AddRequest {
+            fid: "f1".to_string(),
+            keywords: vec!["rust".to_string()],
+            ack_mode: AckMode::Sync as i32,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let response = client
+        .query(QueryRequest {
+            query_type: Some(query_request::QueryType::Keyword("rust".to_string())),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.verified);
+    assert_eq!(response.fids, vec!["f1"]);
+
+    // 没有客户端证书时 storager 拒绝握手，连接或第一个请求失败
+    let anonymous = async {
+        let channel = connect_with(&storager_addr, &client_transport).await?;
+        client_transport
+            .storager_client(channel)
+            .add(add_request("f2", "rust"))
+            .await?;
+        Ok::<_, Box<dyn std::error::Error>>(())
+    };
+    assert!(anonymous.await.is_err());
+
+    // 明文客户端同样无法调用
+    let plaintext = TransportConfig::default();
+    let plain_addr = storager_addr.replace("https://", "http://");
+    let plain = async {
+        let channel = connect_with(&plain_addr, &plaintext).await?;
+        plaintext
+            .storager_client(channel)
+            .add(add_request("f2", "rust"))
+            .await?;
+        Ok::<_, Box<dyn std::error::Error>>(())
+    };
+    assert!(plain.await.is_err());
+
+    // 集群成员可以直接调用
+    let member = TransportConfig::default().with_tls(Some(pki.member()));
+    let channel = connect_with(&storager_addr, &member).await.unwrap();
+    let fids = member
+        .storager_client(channel)
+        .query(StoragerQueryRequest {
+            keyword: "rust".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .fids;
+    assert_eq!(fids, vec!["f1"]);
+}
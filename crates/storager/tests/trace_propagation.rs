@@ -0,0 +1,119 @@
+//! 追踪上下文传递测试
+//!
+//! 客户端带着 `traceparent` 发起 Add，Manager 的 RPC span 沿用客户端的 trace id，
+//! 扇出到 storager 的请求携带 Manager 的 span 作为父 span。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::rpc::*;
+use common::telemetry::{TraceContext, Traced, TRACEPARENT_METADATA_KEY};
+use common::AdsMode;
+use manager::Manager;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use storager::Storager;
+use support::serve;
+use tonic::codegen::http;
+use tonic::server::NamedService;
+use tonic::transport::Server;
+use tower::Service;
+
+/// 记录收到的 `traceparent` 的服务包装
+#[derive(Clone)]
+struct Recording<S> {
+    inner: S,
+    seen: Arc<Mutex<Vec<Option<TraceContext>>>>,
+}
+
+impl<S: NamedService> NamedService for Recording<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S, B> Service<http::Request<B>> for Recording<S>
+where
+    S: Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let context = request
+            .headers()
+            .get(TRACEPARENT_METADATA_KEY)
+            .and_then(|value| value.to_str().ok())
+            .and_then(TraceContext::parse);
+        self.seen.lock().unwrap().push(context);
+        self.inner.call(request)
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_add_carries_trace_to_storagers() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut storager_addrs = Vec::new();
+    for _ in 0..2 {
+        let storager = Recording {
+            inner: StoragerServiceServer::new(Storager::with_mpt()),
+            seen: seen.clone(),
+        };
+        storager_addrs.push(serve(move || {
+            Server::builder().add_service(storager.clone())
+        }));
+    }
+    let manager = Traced::new(ManagerServiceServer::new(Manager::new(
+        storager_addrs,
+        AdsMode::Mpt,
+    )));
+    let manager_addr = serve(move || Server::builder().add_service(manager.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    let origin = TraceContext::new_root();
+    let mut request = tonic::Request::new(AddRequest {
+        fid: "f1".to_string(),
+        keywords: (0..8).map(|i| format!("kw{}", i)).collect(),
+        ack_mode: AckMode::Sync as i32,
+        ..Default::default()
+    });
+    origin.inject(request.metadata_mut());
+    let response = client.add(request).await.unwrap();
+    let rpc = TraceContext::from_metadata(response.metadata()).unwrap();
+    assert!(response.into_inner().success);
+    assert_eq!(rpc.trace_id, origin.trace_id);
+    assert_ne!(rpc.span_id, origin.span_id);
+
+    let seen = seen.lock().unwrap();
+    assert!(!seen.is_empty());
+    for context in seen.iter() {
+        assert_eq!(*context, Some(rpc));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_request_without_trace_starts_new_trace() {
+    let storager = StoragerServiceServer::new(Storager::with_mpt());
+    let storager_addr = serve(move || Server::builder().add_service(storager.clone()));
+    let manager = Traced::new(ManagerServiceServer::new(Manager::new(
+        vec![storager_addr],
+        AdsMode::Mpt,
+    )));
+    let manager_addr = serve(move || Server::builder().add_service(manager.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    let query = || QueryRequest {
+        query_type: Some(query_request::QueryType::Keyword("rust".to_string())),
+        ..Default::default()
+    };
+    let first = client.query(query()).await.unwrap();
+    let second = client.query(query()).await.unwrap();
+    let first = TraceContext::from_metadata(first.metadata()).unwrap();
+    let second = TraceContext::from_metadata(second.metadata()).unwrap();
+    assert_ne!(first.trace_id, second.trace_id);
+}
@@ -0,0 +1,322 @@
+//! Update 协调测试
+//!
+//! Update 先加入新 keyword 再删除旧 keyword；加入失败或加入的证明未通过验证时
+//! 撤销已加入的 keyword，fid 仍然只在旧 keyword 下。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::{StoragerService, StoragerServiceServer};
+use common::rpc::*;
+use common::{AdsMode, ErrorKind};
+use manager::Manager;
+use std::sync::{Arc, Mutex};
+use storager::Storager;
+use support::{query_keyword, serve};
+use tonic::transport::{Channel, Server};
+use tonic::{Code, Request, Response, Status, Streaming};
+
+/// 记录写入顺序、拒绝写入某个 keyword 并对另一个 keyword 返回伪造根哈希的 storager
+struct FlakyStorager {
+    inner: Arc<Storager>,
+    broken_keyword: String,
+    forged_keyword: String,
+    mutations: Mutex<Vec<String>>,
+}
+
+impl FlakyStorager {
+    fn log(&self, mutation: String) {
+        self.mutations.lock().unwrap().push(mutation);
+    }
+
+    fn take_mutations(&self) -> Vec<String> {
+        std::mem::take(&mut self.mutations.lock().unwrap())
+    }
+}
+
+#[tonic::async_trait]
+impl StoragerService for FlakyStorager {
+    async fn add(
+        &self,
+        request: Request<StoragerAddRequest>,
+    ) -> Result<Response<StoragerAddResponse>, Status> {
+        let keyword = request.get_ref().keyword.clone();
+        if keyword == self.broken_keyword {
+            return Err(Status::internal("disk full"));
+        }
+        let mut response = self.inner.add(request).await;
+        self.log(format!("add {}", keyword));
+        if keyword == self.forged_keyword {
+            if let Ok(response) = &mut response {
+                response.get_mut().root_hash = vec![7; 32];
+            }
+        }
+        response
+    }
+
+    async fn batch_add(
+        &self,
+        request: Request<StoragerBatchAddRequest>,
+    ) -> Result<Response<StoragerBatchAddResponse>, Status> {
+        self.inner.batch_add(request).await
+    }
+
+    async fn query(
+        &self,
+        request: Request<StoragerQueryRequest>,
+    ) -> Result<Response<StoragerQueryResponse>, Status> {
+        self.inner.query(request).await
+    }
+
+    async fn prove_difference(
+        &self,
+        request: Request<ProveDifferenceRequest>,
+    ) -> Result<Response<ProveDifferenceResponse>, Status> {
+        self.inner.prove_difference(request).await
+    }
+
+    async fn boolean_query(
+        &self,
+        request: Request<StoragerBooleanQueryRequest>,
+    ) -> Result<Response<StoragerBooleanQueryResponse>, Status> {
+        self.inner.boolean_query(request).await
+    }
+
+    async fn delete(
+        &self,
+        request: Request<StoragerDeleteRequest>,
+    ) -> Result<Response<StoragerDeleteResponse>, Status> {
+        let keyword = request.get_ref().keyword.clone();
+        let response = self.inner.delete(request).await;
+        self.log(format!("delete {}", keyword));
+        response
+    }
+
+    async fn approx_count(
+        &self,
+        request: Request<StoragerApproxCountRequest>,
+    ) -> Result<Response<StoragerApproxCountResponse>, Status> {
+        self.inner.approx_count(request).await
+    }
+
+    async fn health(
+        &self,
+        request: Request<StoragerHealthRequest>,
+    ) -> Result<Response<StoragerHealthResponse>, Status> {
+        self.inner.health(request).await
+    }
+
+    async fn flush(
+        &self,
+        request: Request<FlushRequest>,
+    ) -> Result<Response<FlushResponse>, Status> {
+        self.inner.flush(request).await
+    }
+
+    async fn stats(
+        &self,
+        request: Request<StoragerStatsRequest>,
+    ) -> Result<Response<StoragerStatsResponse>, Status> {
+        self.inner.stats(request).await
+    }
+
+    async fn keyword_filter(
+        &self,
+        request: Request<KeywordFilterRequest>,
+    ) -> Result<Response<KeywordFilterResponse>, Status> {
+        StoragerService::keyword_filter(&*self.inner, request).await
+    }
+
+    async fn list_expired(
+        &self,
+        request: Request<ListExpiredRequest>,
+    ) -> Result<Response<ListExpiredResponse>, Status> {
+        self.inner.list_expired(request).await
+    }
+
+    async fn list_keywords(
+        &self,
+        request: Request<ListKeywordsRequest>,
+    ) -> Result<Response<ListKeywordsResponse>, Status> {
+        self.inner.list_keywords(request).await
+    }
+
+    async fn list_namespaces(
+        &self,
+        request: Request<ListNamespacesRequest>,
+    ) -> Result<Response<ListNamespacesResponse>, Status> {
+        self.inner.list_namespaces(request).await
+    }
+
+    async fn migrate_out(
+        &self,
+        request: Request<MigrateOutRequest>,
+    ) -> Result<Response<MigrateOutResponse>, Status> {
+        self.inner.migrate_out(request).await
+    }
+
+    async fn migrate_in(
+        &self,
+        request: Request<Streaming<MigrationEntry>>,
+    ) -> Result<Response<MigrateInResponse>, Status> {
+        self.inner.migrate_in(request).await
+    }
+
+    async fn bulk_add(
+        &self,
+        request: Request<Streaming<BulkAddRecord>>,
+    ) -> Result<Response<StoragerBulkAddResponse>, Status> {
+        self.inner.bulk_add(request).await
+    }
+
+    async fn range_query(
+        &self,
+        request: Request<RangeQueryRequest>,
+    ) -> Result<Response<StoragerRangeQueryResponse>, Status> {
+        self.inner.range_query(request).await
+    }
+
+    async fn query_by_prefix(
+        &self,
+        request: Request<PrefixQueryRequest>,
+    ) -> Result<Response<StoragerPrefixQueryResponse>, Status> {
+        self.inner.query_by_prefix(request).await
+    }
+
+    async fn prune(
+        &self,
+        request: Request<PruneRequest>,
+    ) -> Result<Response<PruneResponse>, Status> {
+        self.inner.prune(request).await
+    }
+
+    async fn query_at_root(
+        &self,
+        request: Request<QueryAtRootRequest>,
+    ) -> Result<Response<QueryAtRootResponse>, Status> {
+        self.inner.query_at_root(request).await
+    }
+
+    async fn list_root_history(
+        &self,
+        request: Request<ListRootHistoryRequest>,
+    ) -> Result<Response<ListRootHistoryResponse>, Status> {
+        self.inner.list_root_history(request).await
+    }
+
+    async fn get_proof(
+        &self,
+        request: Request<GetProofRequest>,
+    ) -> Result<Response<GetProofResponse>, Status> {
+        self.inner.get_proof(request).await
+    }
+
+    type QueryStreamStream = <Storager as StoragerService>::QueryStreamStream;
+
+    async fn query_stream(
+        &self,
+        request: Request<StoragerQueryRequest>,
+    ) -> Result<Response<Self::QueryStreamStream>, Status> {
+        self.inner.query_stream(request).await
+    }
+}
+
+async fn start() -> (Arc<FlakyStorager>, ManagerServiceClient<Channel>) {
+    let storager = Arc::new(FlakyStorager {
+        inner: Arc::new(Storager::with_mpt()),
+        broken_keyword: "broken".to_string(),
+        forged_keyword: "forged".to_string(),
+        mutations: Mutex::new(Vec::new()),
+    });
+    let service = StoragerServiceServer::from_arc(storager.clone());
+    let storager_addr = serve(move || Server::builder().add_service(service.clone()));
+    let manager_service =
+        ManagerServiceServer::new(Manager::new(vec![storager_addr], AdsMode::Mpt));
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+    (storager, client)
+}
+
+async fn update(
+    client: &mut ManagerServiceClient<Channel>,
+    fid: &str,
+    old_keywords: &[&str],
+    new_keywords: &[&str],
+) -> Result<UpdateResponse, Status> {
+    client
+        .update(UpdateRequest {
+            fid: fid.to_string(),
+            old_keywords: old_keywords.iter().map(|k| k.to_string()).collect(),
+            new_keywords: new_keywords.iter().map(|k| k.to_string()).collect(),
+            ack_mode: AckMode::Sync as i32,
+            ..Default::default()
+        })
+        .await
+        .map(Response::into_inner)
+}
+
+async fn query(client: &mut ManagerServiceClient<Channel>, keyword: &str) -> Vec<String> {
+    let response = query_keyword(client, keyword).await;
+    assert!(response.verified, "keyword {}", keyword);
+    response.fids
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_adds_before_deleting() {
+    let (storager, mut client) = start().await;
+    update(&mut client, "f1", &[], &["a", "b"]).await.unwrap();
+    storager.take_mutations();
+
+    let response = update(&mut client, "f1", &["a", "b"], &["b", "c"])
+        .await
+        .unwrap();
+    assert!(response.success, "{}", response.message);
+    // 加入阶段并发执行；b 同时在两个集合中，重复加入而不删除
+    let mut mutations = storager.take_mutations();
+    assert_eq!(mutations.pop().as_deref(), Some("delete a"));
+    mutations.sort();
+    assert_eq!(mutations, vec!["add b", "add c"]);
+
+    assert!(query(&mut client, "a").await.is_empty());
+    assert_eq!(query(&mut client, "b").await, vec!["f1"]);
+    assert_eq!(query(&mut client, "c").await, vec!["f1"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_failed_add_rolls_back() {
+    let (storager, mut client) = start().await;
+    update(&mut client, "f1", &[], &["a"]).await.unwrap();
+    storager.take_mutations();
+
+    let status = update(&mut client, "f1", &["a"], &["b", "broken"])
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Internal);
+    assert_eq!(ErrorKind::from_status(&status), Some(ErrorKind::Storage));
+    // 旧 keyword 没有被删除，已加入的新 keyword 被撤销
+    assert_eq!(storager.take_mutations(), vec!["add b", "delete b"]);
+
+    assert_eq!(query(&mut client, "a").await, vec!["f1"]);
+    assert!(query(&mut client, "b").await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unverified_add_rolls_back() {
+    let (storager, mut client) = start().await;
+    update(&mut client, "f1", &[], &["a"]).await.unwrap();
+    storager.take_mutations();
+
+    let response = update(&mut client, "f1", &["a"], &["forged"])
+        .await
+        .unwrap();
+    assert!(!response.success);
+    // 加入的证明未通过验证时不删除旧 keyword
+    assert_eq!(
+        storager.take_mutations(),
+        vec!["add forged", "delete forged"]
+    );
+
+    assert_eq!(query(&mut client, "a").await, vec!["f1"]);
+    assert!(query(&mut client, "forged").await.is_empty());
+}
@@ -0,0 +1,243 @@
+//! 带版本的根哈希测试
+//!
+//! 查询在 storager 上完成计算后、Manager 验证前，同一 storager 又完成了一次写入并发布了新根。
+//! 查询响应标注了计算时的 epoch，Manager 用该 epoch 对应的历史根哈希验证，结果仍然通过。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::{StoragerService, StoragerServiceServer};
+use common::rpc::*;
+use common::AdsMode;
+use manager::Manager;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use storager::Storager;
+use support::{add, query_keyword, serve};
+use tokio::sync::Notify;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status, Streaming};
+
+/// 可以把查询响应扣住的 storager：响应已经计算好，但在放行前不返回给 Manager
+struct SlowStorager {
+    inner: Arc<Storager>,
+    hold_query: AtomicBool,
+    computed: Notify,
+    release: Notify,
+}
+
+#[tonic::async_trait]
+impl StoragerService for SlowStorager {
+    async fn add(
+        &self,
+        request: Request<StoragerAddRequest>,
+    ) -> Result<Response<StoragerAddResponse>, Status> {
+        self.inner.add(request).await
+    }
+
+    async fn batch_add(
+        &self,
+        request: Request<StoragerBatchAddRequest>,
+    ) -> Result<Response<StoragerBatchAddResponse>, Status> {
+        self.inner.batch_add(request).await
+    }
+
+    async fn query(
+        &self,
+        request: Request<StoragerQueryRequest>,
+    ) -> Result<Response<StoragerQueryResponse>, Status> {
+        self.inner.query(request).await
+    }
+
+    async fn prove_difference(
+        &self,
+        request: Request<ProveDifferenceRequest>,
+    ) -> Result<Response<ProveDifferenceResponse>, Status> {
+        self.inner.prove_difference(request).await
+    }
+
+    async fn boolean_query(
+        &self,
+        request: Request<StoragerBooleanQueryRequest>,
+    ) -> Result<Response<StoragerBooleanQueryResponse>, Status> {
+        self.inner.boolean_query(request).await
+    }
+
+    async fn delete(
+        &self,
+        request: Request<StoragerDeleteRequest>,
+    ) -> Result<Response<StoragerDeleteResponse>, Status> {
+        self.inner.delete(request).await
+    }
+
+    async fn approx_count(
+        &self,
+        request: Request<StoragerApproxCountRequest>,
+    ) -> Result<Response<StoragerApproxCountResponse>, Status> {
+        self.inner.approx_count(request).await
+    }
+
+    async fn health(
+        &self,
+        request: Request<StoragerHealthRequest>,
+    ) -> Result<Response<StoragerHealthResponse>, Status> {
+        self.inner.health(request).await
+    }
+
+    async fn flush(
+        &self,
+        request: Request<FlushRequest>,
+    ) -> Result<Response<FlushResponse>, Status> {
+        self.inner.flush(request).await
+    }
+
+    async fn stats(
+        &self,
+        request: Request<StoragerStatsRequest>,
+    ) -> Result<Response<StoragerStatsResponse>, Status> {
+        self.inner.stats(request).await
+    }
+
+    async fn keyword_filter(
+        &self,
+        request: Request<KeywordFilterRequest>,
+    ) -> Result<Response<KeywordFilterResponse>, Status> {
+        StoragerService::keyword_filter(&*self.inner, request).await
+    }
+
+    async fn list_expired(
+        &self,
+        request: Request<ListExpiredRequest>,
+    ) -> Result<Response<ListExpiredResponse>, Status> {
+        self.inner.list_expired(request).await
+    }
+
+    async fn list_keywords(
+        &self,
+        request: Request<ListKeywordsRequest>,
+    ) -> Result<Response<ListKeywordsResponse>, Status> {
+        self.inner.list_keywords(request).await
+    }
+
+    async fn list_namespaces(
+        &self,
+        request: Request<ListNamespacesRequest>,
+    ) -> Result<Response<ListNamespacesResponse>, Status> {
+        self.inner.list_namespaces(request).await
+    }
+
+    async fn migrate_out(
+        &self,
+        request: Request<MigrateOutRequest>,
+    ) -> Result<Response<MigrateOutResponse>, Status> {
+        self.inner.migrate_out(request).await
+    }
+
+    async fn migrate_in(
+        &self,
+        request: Request<Streaming<MigrationEntry>>,
+    ) -> Result<Response<MigrateInResponse>, Status> {
+        self.inner.migrate_in(request).await
+    }
+
+    async fn bulk_add(
+        &self,
+        request: Request<Streaming<BulkAddRecord>>,
+    ) -> Result<Response<StoragerBulkAddResponse>, Status> {
+        self.inner.bulk_add(request).await
+    }
+
+    async fn range_query(
+        &self,
+        request: Request<RangeQueryRequest>,
+    ) -> Result<Response<StoragerRangeQueryResponse>, Status> {
+        self.inner.range_query(request).await
+    }
+
+    async fn query_by_prefix(
+        &self,
+        request: Request<PrefixQueryRequest>,
+    ) -> Result<Response<StoragerPrefixQueryResponse>, Status> {
+        self.inner.query_by_prefix(request).await
+    }
+
+    async fn prune(
+        &self,
+        request: Request<PruneRequest>,
+    ) -> Result<Response<PruneResponse>, Status> {
+        self.inner.prune(request).await
+    }
+
+    async fn query_at_root(
+        &self,
+        request: Request<QueryAtRootRequest>,
+    ) -> Result<Response<QueryAtRootResponse>, Status> {
+        self.inner.query_at_root(request).await
+    }
+
+    async fn list_root_history(
+        &self,
+        request: Request<ListRootHistoryRequest>,
+    ) -> Result<Response<ListRootHistoryResponse>, Status> {
+        self.inner.list_root_history(request).await
+    }
+    async fn get_proof(
+        &self,
+        request: Request<GetProofRequest>,
+    ) -> Result<Response<GetProofResponse>, Status> {
+        self.inner.get_proof(request).await
+    }
+
+    type QueryStreamStream = <Storager as StoragerService>::QueryStreamStream;
+
+    async fn query_stream(
+        &self,
+        request: Request<StoragerQueryRequest>,
+    ) -> Result<Response<Self::QueryStreamStream>, Status> {
+        let response = self.inner.query_stream(request).await;
+        if self.hold_query.swap(false, Ordering::SeqCst) {
+            self.computed.notify_one();
+            self.release.notified().await;
+        }
+        response
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_slow_query_verifies_against_its_epoch() {
+    let inner = Arc::new(Storager::with_mpt());
+    let slow = Arc::new(SlowStorager {
+        inner: inner.clone(),
+        hold_query: AtomicBool::new(false),
+        computed: Notify::new(),
+        release: Notify::new(),
+    });
+    let service = StoragerServiceServer::from_arc(slow.clone());
+    let storager_addr = serve(move || Server::builder().add_service(service.clone()));
+    let manager_service =
+        ManagerServiceServer::new(Manager::new(vec![storager_addr], AdsMode::Mpt));
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    add(&mut client, "f0", &["rust"]).await;
+    assert_eq!(inner.epoch(), 1);
+
+    slow.hold_query.store(true, Ordering::SeqCst);
+    let mut query_client = client.clone();
+    let slow_query = tokio::spawn(async move { query_keyword(&mut query_client, "rust").await });
+    slow.computed.notified().await;
+
+    // 查询在 epoch 1 计算完成后，Manager 验证并发布了 epoch 2 的根
+    add(&mut client, "f1", &["go"]).await;
+    assert_eq!(inner.epoch(), 2);
+    slow.release.notify_one();
+
+    let stale = slow_query.await.unwrap();
+    assert_eq!(stale.fids, vec!["f0"]);
+    assert!(stale.verified);
+
+    let fresh = query_keyword(&mut client, "rust").await;
+    assert!(fresh.verified);
+    assert_ne!(fresh.root_hash, stale.root_hash);
+}
@@ -0,0 +1,132 @@
+//! keyword 变更订阅测试
+//!
+//! 订阅布尔表达式后先收到当前的结果，之后只有结果发生变化的写请求才产生通知，
+//! 通知中给出新增和移除的 fid；无效的表达式和命名空间在建立订阅时被拒绝。
+
+mod support;
+
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::manager_service_server::ManagerServiceServer;
+use common::rpc::storager_service_server::StoragerServiceServer;
+use common::rpc::{AckMode, DeleteRequest, UpdateRequest, WatchKeywordEvent, WatchKeywordRequest};
+use common::AdsMode;
+use manager::Manager;
+use storager::Storager;
+use support::{add, serve};
+use tonic::transport::{Channel, Server};
+use tonic::{Code, Streaming};
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+async fn watch(
+    client: &mut ManagerServiceClient<Channel>,
+    expression: &str,
+) -> Streaming<WatchKeywordEvent> {
+    client
+        .watch_keyword(WatchKeywordRequest {
+            expression: expression.to_string(),
+            namespace: String::new(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+async fn next(events: &mut Streaming<WatchKeywordEvent>) -> WatchKeywordEvent {
+    tokio::time::timeout(std::time::Duration::from_secs(10), events.message())
+        .await
+        .expect("no watch event")
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_watch_reports_result_changes() {
+    let service = StoragerServiceServer::new(Storager::with_mpt());
+    let storager_addr = serve(move || Server::builder().add_service(service.clone()));
+    let manager = Manager::new(vec![storager_addr], AdsMode::Mpt);
+    let manager_service = ManagerServiceServer::new(manager);
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    add(&mut client, "f0", &["rust"]).await;
+    let mut events = watch(&mut client, "rust AND NOT go").await;
+    let mut keyword_events = watch(&mut client, "go").await;
+
+    // 第一条通知是当前结果
+    let initial = next(&mut events).await;
+    assert_eq!(initial.sequence, 0);
+    assert_eq!(initial.added, vec!["f0"]);
+    assert!(initial.removed.is_empty());
+    assert!(!initial.root_hash.is_empty());
+    assert!(next(&mut keyword_events).await.added.is_empty());
+
+    add(&mut client, "f1", &["rust"]).await;
+    let event = next(&mut events).await;
+    assert_eq!(event.sequence, 1);
+    assert_eq!(event.added, vec!["f1"]);
+    assert!(event.removed.is_empty());
+
+    // 涉及 keyword 但结果不变的写入没有通知，下一条通知来自之后的删除
+    add(&mut client, "f2", &["rust", "go"]).await;
+    assert_eq!(next(&mut keyword_events).await.added, vec!["f2"]);
+    client
+        .update(UpdateRequest {
+            fid: "f1".to_string(),
+            old_keywords: strings(&["rust"]),
+            new_keywords: strings(&["rust", "go"]),
+            ack_mode: AckMode::Sync as i32,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let event = next(&mut events).await;
+    assert_eq!(event.sequence, 2);
+    assert!(event.added.is_empty());
+    assert_eq!(event.removed, vec!["f1"]);
+    assert_eq!(next(&mut keyword_events).await.added, vec!["f1"]);
+
+    client
+        .delete(DeleteRequest {
+            fid: "f2".to_string(),
+            keywords: strings(&["go"]),
+            ack_mode: AckMode::Sync as i32,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let event = next(&mut events).await;
+    assert_eq!(event.sequence, 3);
+    assert_eq!(event.added, vec!["f2"]);
+    assert_eq!(next(&mut keyword_events).await.removed, vec!["f2"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_watch_rejects_invalid_requests() {
+    let service = StoragerServiceServer::new(Storager::with_mpt());
+    let storager_addr = serve(move || Server::builder().add_service(service.clone()));
+    let manager = Manager::new(vec![storager_addr], AdsMode::Mpt);
+    let manager_service = ManagerServiceServer::new(manager);
+    let manager_addr = serve(move || Server::builder().add_service(manager_service.clone()));
+    let mut client = ManagerServiceClient::connect(manager_addr).await.unwrap();
+
+    let status = client
+        .watch_keyword(WatchKeywordRequest {
+            expression: "rust AND (".to_string(),
+            namespace: String::new(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    let status = client
+        .watch_keyword(WatchKeywordRequest {
+            expression: "rust".to_string(),
+            namespace: "not a namespace".to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
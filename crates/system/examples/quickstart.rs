@@ -0,0 +1,35 @@
+//! 不需要手动启动任何节点的完整示例
+//!
+//! 在进程内启动一个 Manager 和三个 storager，写入几个文件后执行已验证的查询。
+//!
+//! ```bash
+//! cargo run -p system --example quickstart
+//! ```
+
+use common::AdsMode;
+use system::testkit::TestCluster;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cluster = TestCluster::start(AdsMode::Mpt, 3).await;
+    println!("Manager listening on {}", cluster.manager_addr());
+
+    cluster
+        .add_files(&[
+            ("file1", &["rust", "distributed", "storage"]),
+            ("file2", &["python", "ai"]),
+            ("file3", &["rust", "blockchain"]),
+        ])
+        .await;
+
+    for expression in ["rust", "rust AND blockchain", "python OR storage"] {
+        let response = cluster.client().query(expression).await?;
+        println!(
+            "{:<20} verified={} fids={:?}",
+            expression, response.verified, response.fids
+        );
+    }
+
+    cluster.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,359 @@
+//! ADS 模式对比基准的报告格式
+//!
+//! `bench-compare` 对每种 ADS 模式运行相同的工作负载，把结果汇总为 [`BenchReport`]
+//! 并以 JSON 输出，便于脚本比较或绘图。延迟单位为微秒，大小单位为字节。
+//!
+//! [`Driver`] 为一种 ADS 模式启动本地集群并通过 gRPC 发出写入、删除和查询，把每次操作记入
+//! [`Samples`]；`bench-compare` 和 `experiments`（见 [`crate::experiment`]）都用它运行工作负载。
+//!
+//! # 示例
+//!
+//! ```
+//! use std::time::Duration;
+//! use system::bench::{LatencySummary, SizeSummary};
+//!
+//! let samples: Vec<Duration> = (1..=100).map(Duration::from_micros).collect();
+//! let latency = LatencySummary::from_samples(&samples);
+//! assert_eq!(latency.p50_us, 50.0);
+//! assert_eq!(latency.p99_us, 99.0);
+//!
+//! let sizes = SizeSummary::from_samples(&[96, 128]).unwrap();
+//! assert_eq!(sizes.mean, 112.0);
+//! ```
+
+use crate::runner::{RunningSystem, SystemRunner};
+use common::rpc::manager_service_client::ManagerServiceClient;
+use common::rpc::{query_request::QueryType, AckMode, AddRequest, DeleteRequest, QueryRequest};
+use common::{AdsMode, Proof};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::time::{Duration, Instant};
+use tonic::transport::Channel;
+
+/// 完整的对比报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub config: BenchConfig,
+    pub modes: Vec<ModeReport>,
+}
+
+/// 所有模式共用的工作负载参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchConfig {
+    /// 写入的文件数量
+    pub files: usize,
+    /// 每个文件的关键词数量
+    pub keywords_per_file: usize,
+    /// 关键词表大小
+    pub vocabulary: usize,
+    /// 单关键词查询和布尔查询各自的次数
+    pub queries: usize,
+    /// storager 数量
+    pub storagers: usize,
+    /// 工作负载生成器的随机种子
+    pub seed: u64,
+}
+
+/// 单个 ADS 模式的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModeReport {
+    pub ads_mode: AdsMode,
+    pub workloads: Vec<WorkloadReport>,
+    pub storage: StorageFootprint,
+}
+
+/// 单个工作负载的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkloadReport {
+    /// ingest / point_query / boolean_query / delete
+    pub name: String,
+    pub operations: usize,
+    /// RPC 失败或 Manager 报告失败的操作数
+    pub failures: usize,
+    /// 证明验证通过的操作数（查询工作负载）
+    pub verified: usize,
+    pub latency: LatencySummary,
+    /// 返回给客户端的证明大小（只有查询工作负载有证明）
+    pub proof_bytes: Option<SizeSummary>,
+}
+
+/// 延迟分布
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub mean_us: f64,
+    pub p50_us: f64,
+    pub p90_us: f64,
+    pub p99_us: f64,
+    pub max_us: f64,
+}
+
+impl LatencySummary {
+    /// 由样本计算分布（最近秩法取分位数，没有样本时全部为 0）
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut micros: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1e6).collect();
+        micros.sort_by(f64::total_cmp);
+
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * micros.len() as f64).ceil() as usize;
+            micros[rank.clamp(1, micros.len()) - 1]
+        };
+        LatencySummary {
+            mean_us: micros.iter().sum::<f64>() / micros.len() as f64,
+            p50_us: percentile(50.0),
+            p90_us: percentile(90.0),
+            p99_us: percentile(99.0),
+            max_us: micros[micros.len() - 1],
+        }
+    }
+}
+
+/// 大小分布
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SizeSummary {
+    pub min: u64,
+    pub mean: f64,
+    pub max: u64,
+    pub total: u64,
+}
+
+impl SizeSummary {
+    /// 由样本计算分布（没有样本时返回 None）
+    pub fn from_samples(samples: &[u64]) -> Option<Self> {
+        let total: u64 = samples.iter().sum();
+        Some(SizeSummary {
+            min: *samples.iter().min()?,
+            mean: total as f64 / samples.len() as f64,
+            max: *samples.iter().max()?,
+            total,
+        })
+    }
+}
+
+/// 存储占用（storager 导出的全部状态的大小）
+///
+/// 后端不支持导出状态时对应的值为 None
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageFootprint {
+    /// 写入完成后所有 storager 的状态大小之和
+    pub after_ingest_bytes: Option<usize>,
+    /// 删除完成后所有 storager 的状态大小之和
+    pub after_delete_bytes: Option<usize>,
+    /// 写入完成后每个 storager 的状态大小
+    pub per_storager_after_ingest: BTreeMap<String, Option<usize>>,
+}
+
+/// 一类操作的样本
+#[derive(Debug, Clone, Default)]
+pub struct Samples {
+    pub latencies: Vec<Duration>,
+    /// RPC 失败或 Manager 报告失败的操作数
+    pub failures: usize,
+    /// 证明验证通过的查询数
+    pub verified: usize,
+    /// 每个带证明的查询的证明字节数
+    pub proof_bytes: Vec<u64>,
+    pub pairing_ops: u64,
+    pub hash_ops: u64,
+}
+
+impl Samples {
+    /// 合并另一组样本
+    pub fn extend(&mut self, other: &Samples) {
+        self.latencies.extend_from_slice(&other.latencies);
+        self.failures += other.failures;
+        self.verified += other.verified;
+        self.proof_bytes.extend_from_slice(&other.proof_bytes);
+        self.pairing_ops += other.pairing_ops;
+        self.hash_ops += other.hash_ops;
+    }
+
+    /// 汇总为名为 `name` 的工作负载结果
+    pub fn report(&self, name: &str) -> WorkloadReport {
+        WorkloadReport {
+            name: name.to_string(),
+            operations: self.latencies.len(),
+            failures: self.failures,
+            verified: self.verified,
+            latency: LatencySummary::from_samples(&self.latencies),
+            proof_bytes: SizeSummary::from_samples(&self.proof_bytes),
+        }
+    }
+}
+
+/// 一种 ADS 模式的进程内集群和连到 Manager 的客户端
+pub struct Driver {
+    system: RunningSystem,
+    client: ManagerServiceClient<Channel>,
+}
+
+impl Driver {
+    /// 启动 `storagers` 个 storager 的集群并连接 Manager
+    pub async fn start(mode: AdsMode, storagers: usize) -> Result<Self, Box<dyn Error>> {
+        let system = SystemRunner::new(mode)
+            .with_storagers(storagers)
+            .with_drain_timeout(Duration::from_secs(1))
+            .start()
+            .await?;
+        let client = ManagerServiceClient::connect(system.manager_addr().to_string()).await?;
+        Ok(Driver { system, client })
+    }
+
+    /// 同步写入一个文件
+    pub async fn add(&mut self, fid: &str, keywords: &[String], samples: &mut Samples) {
+        let request = AddRequest {
+            fid: fid.to_string(),
+            keywords: keywords.to_vec(),
+            ack_mode: AckMode::Sync as i32,
+            tenant: String::new(),
+            namespace: String::new(),
+            ttl_seconds: 0,
+        };
+        let start = Instant::now();
+        let result = self.client.add(request).await;
+        samples.latencies.push(start.elapsed());
+        if !result.is_ok_and(|r| r.into_inner().success) {
+            samples.failures += 1;
+        }
+    }
+
+    /// 同步删除一个文件的全部 keyword
+    pub async fn delete(&mut self, fid: &str, keywords: &[String], samples: &mut Samples) {
+        let request = DeleteRequest {
+            fid: fid.to_string(),
+            keywords: keywords.to_vec(),
+            ack_mode: AckMode::Sync as i32,
+            tenant: String::new(),
+            strict: true,
+            namespace: String::new(),
+        };
+        let start = Instant::now();
+        let result = self.client.delete(request).await;
+        samples.latencies.push(start.elapsed());
+        if !result.is_ok_and(|r| r.into_inner().success) {
+            samples.failures += 1;
+        }
+    }
+
+    /// 查询并记录证明大小和验证代价
+    pub async fn query(&mut self, query_type: QueryType, samples: &mut Samples) {
+        let request = QueryRequest {
+            query_type: Some(query_type),
+            allow_background: false,
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let result = self.client.query(request).await;
+        samples.latencies.push(start.elapsed());
+        match result {
+            Ok(response) => {
+                let response = response.into_inner();
+                if response.verified {
+                    samples.verified += 1;
+                }
+                // Manager 报告的验证代价覆盖布尔查询的全部证明，旧版本 Manager 只能统计合并后的证明
+                match response.metrics {
+                    Some(metrics) => {
+                        samples.proof_bytes.push(metrics.proof_bytes);
+                        samples.pairing_ops += metrics.pairing_ops;
+                        samples.hash_ops += metrics.hash_ops;
+                    }
+                    None => {
+                        let proof = Proof::try_from(response.proof).map_or(0, |p| p.data().len());
+                        samples.proof_bytes.push(proof as u64);
+                    }
+                }
+            }
+            Err(_) => samples.failures += 1,
+        }
+    }
+
+    /// 所有 storager 导出状态的大小之和（任一后端不支持导出时为 None）和每个 storager 的大小
+    pub fn storage_footprint(&self) -> (Option<usize>, BTreeMap<String, Option<usize>>) {
+        let per_storager: BTreeMap<String, Option<usize>> = self
+            .system
+            .storagers()
+            .into_iter()
+            .enumerate()
+            .map(|(idx, storager)| {
+                (
+                    format!("storager-{}", idx),
+                    storager.export_state().ok().map(|s| s.len()),
+                )
+            })
+            .collect();
+        (per_storager.values().copied().sum(), per_storager)
+    }
+
+    /// 关闭集群
+    pub async fn shutdown(self) -> Result<(), Box<dyn Error>> {
+        drop(self.client);
+        self.system.shutdown().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_percentiles() {
+        let samples: Vec<Duration> = [5, 1, 4, 2, 3]
+            .iter()
+            .map(|&ms| Duration::from_millis(ms))
+            .collect();
+        let latency = LatencySummary::from_samples(&samples);
+        assert_eq!(latency.mean_us, 3000.0);
+        assert_eq!(latency.p50_us, 3000.0);
+        assert_eq!(latency.p90_us, 5000.0);
+        assert_eq!(latency.max_us, 5000.0);
+        assert_eq!(LatencySummary::from_samples(&[]), LatencySummary::default());
+        assert!(SizeSummary::from_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn test_samples_report() {
+        let mut samples = Samples {
+            latencies: vec![Duration::from_millis(1)],
+            proof_bytes: vec![100],
+            verified: 1,
+            ..Default::default()
+        };
+        samples.extend(&Samples {
+            latencies: vec![Duration::from_millis(3)],
+            failures: 1,
+            ..Default::default()
+        });
+        let report = samples.report("point_query");
+        assert_eq!(report.operations, 2);
+        assert_eq!(report.failures, 1);
+        assert_eq!(report.verified, 1);
+        assert_eq!(report.latency.mean_us, 2000.0);
+        assert_eq!(report.proof_bytes.unwrap().total, 100);
+    }
+
+    #[test]
+    fn test_report_is_json() {
+        let report = BenchReport {
+            config: BenchConfig {
+                files: 1,
+                keywords_per_file: 1,
+                vocabulary: 1,
+                queries: 1,
+                storagers: 1,
+                seed: 7,
+            },
+            modes: vec![ModeReport {
+                ads_mode: AdsMode::Mpt,
+                workloads: vec![],
+                storage: StorageFootprint::default(),
+            }],
+        };
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["modes"][0]["ads_mode"], "Mpt");
+        assert!(json["modes"][0]["storage"]["after_ingest_bytes"].is_null());
+    }
+}
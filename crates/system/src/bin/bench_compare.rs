@@ -0,0 +1,237 @@
+//! ADS 模式对比基准
+//!
+//! 在进程内依次为每种 ADS 模式启动一个本地集群（Manager + 若干 storager，
+//! 均监听 `127.0.0.1` 的随机端口），通过 gRPC 运行完全相同的工作负载：
+//! - `ingest`：写入文件，每个文件带若干关键词
+//! - `point_query`：单关键词查询
+//! - `boolean_query`：两个关键词的 AND / OR 查询（交替）
+//! - `delete`：删除所有文件
+//!
+//! 报告以 JSON 写入 `--output` 指定的文件（格式见 [`system::bench`]），包含延迟分位数、
+//! 证明大小（Manager 报告的全部证明字节数，见 [`system::bench::Driver`]），
+//! 以及 storager 导出状态的大小（存储占用）。
+//! 各节点的运行日志仍打印到标准输出，因此报告不写到标准输出。
+//!
+//! # 使用方法
+//! ```bash
+//! cargo run --release --bin bench-compare
+//!
+//! # 指定模式和规模
+//! cargo run --release --bin bench-compare -- --modes accumulator,mpt,merkle \
+//!     --files 500 --keywords-per-file 4 --queries 300 --output report.json
+//! ```
+
+use common::rpc::query_request::QueryType;
+use common::AdsMode;
+use std::error::Error;
+use system::bench::{BenchConfig, BenchReport, Driver, ModeReport, Samples, StorageFootprint};
+
+struct Options {
+    modes: Vec<AdsMode>,
+    config: BenchConfig,
+    output: String,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let args: Vec<String> = std::env::args().collect();
+    let mut modes = vec![AdsMode::CryptoAccumulator, AdsMode::Mpt];
+    let mut config = BenchConfig {
+        files: 200,
+        keywords_per_file: 3,
+        vocabulary: 50,
+        queries: 200,
+        storagers: 2,
+        seed: 42,
+    };
+    let mut output = "bench-compare.json".to_string();
+
+    let mut i = 1;
+    while i < args.len() {
+        let flag = args[i].as_str();
+        if flag == "--help" || flag == "-h" {
+            println!("USAGE:");
+            println!("    bench-compare [--modes <LIST>] [--files <N>] [--keywords-per-file <N>]");
+            println!("                  [--vocabulary <N>] [--queries <N>] [--storagers <N>]");
+            println!("                  [--seed <N>] [--output <PATH>]");
+            std::process::exit(0);
+        }
+        let value = args
+            .get(i + 1)
+            .ok_or_else(|| format!("{} requires a value", flag))?;
+        let number = || {
+            value
+                .parse::<usize>()
+                .map_err(|_| format!("{} expects a number, got '{}'", flag, value))
+        };
+        match flag {
+            "--modes" => {
+                modes = value
+                    .split(',')
+                    .map(|name| {
+                        AdsMode::from_name(name.trim())
+                            .ok_or_else(|| format!("unknown ADS mode '{}'", name))
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            "--files" => config.files = number()?,
+            "--keywords-per-file" => config.keywords_per_file = number()?,
+            "--vocabulary" => config.vocabulary = number()?,
+            "--queries" => config.queries = number()?,
+            "--storagers" => config.storagers = number()?,
+            "--seed" => config.seed = number()? as u64,
+            "--output" => output = value.clone(),
+            other => return Err(format!("unknown argument '{}' (see --help)", other)),
+        }
+        i += 2;
+    }
+
+    if config.vocabulary < config.keywords_per_file.max(2) || config.storagers == 0 {
+        return Err(
+            "vocabulary must cover keywords-per-file (and at least 2), storagers must be positive"
+                .to_string(),
+        );
+    }
+    Ok(Options {
+        modes,
+        config,
+        output,
+    })
+}
+
+/// 确定性的工作负载生成器（SplitMix64）
+struct Workload {
+    state: u64,
+}
+
+impl Workload {
+    fn new(seed: u64) -> Self {
+        Workload { state: seed }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn keyword(&mut self, vocabulary: usize) -> String {
+        format!("kw{}", self.next() % vocabulary as u64)
+    }
+
+    /// 不重复的 `count` 个关键词
+    fn keywords(&mut self, vocabulary: usize, count: usize) -> Vec<String> {
+        let mut keywords = Vec::with_capacity(count);
+        while keywords.len() < count {
+            let keyword = self.keyword(vocabulary);
+            if !keywords.contains(&keyword) {
+                keywords.push(keyword);
+            }
+        }
+        keywords
+    }
+}
+
+async fn run_mode(mode: AdsMode, config: &BenchConfig) -> Result<ModeReport, Box<dyn Error>> {
+    let mut driver = Driver::start(mode, config.storagers).await?;
+    let mut workload = Workload::new(config.seed);
+
+    // 每种模式使用相同的种子，生成完全相同的文件和查询序列
+    let files: Vec<(String, Vec<String>)> = (0..config.files)
+        .map(|i| {
+            let keywords = workload.keywords(config.vocabulary, config.keywords_per_file);
+            (format!("file-{}", i), keywords)
+        })
+        .collect();
+
+    let mut ingest = Samples::default();
+    for (fid, keywords) in &files {
+        driver.add(fid, keywords, &mut ingest).await;
+    }
+    let (after_ingest_bytes, per_storager_after_ingest) = driver.storage_footprint();
+
+    let mut point_query = Samples::default();
+    for _ in 0..config.queries {
+        let keyword = workload.keyword(config.vocabulary);
+        driver
+            .query(QueryType::Keyword(keyword), &mut point_query)
+            .await;
+    }
+
+    let mut boolean_query = Samples::default();
+    for i in 0..config.queries {
+        let pair = workload.keywords(config.vocabulary, 2);
+        let op = if i % 2 == 0 { "AND" } else { "OR" };
+        let expr = format!("{} {} {}", pair[0], op, pair[1]);
+        driver
+            .query(QueryType::BooleanFunction(expr), &mut boolean_query)
+            .await;
+    }
+
+    let mut delete = Samples::default();
+    for (fid, keywords) in &files {
+        driver.delete(fid, keywords, &mut delete).await;
+    }
+    let (after_delete_bytes, _) = driver.storage_footprint();
+    driver.shutdown().await?;
+
+    Ok(ModeReport {
+        ads_mode: mode,
+        workloads: vec![
+            ingest.report("ingest"),
+            point_query.report("point_query"),
+            boolean_query.report("boolean_query"),
+            delete.report("delete"),
+        ],
+        storage: StorageFootprint {
+            after_ingest_bytes,
+            after_delete_bytes,
+            per_storager_after_ingest,
+        },
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let options = parse_args()?;
+
+    let mut modes = Vec::new();
+    for mode in &options.modes {
+        println!("=== bench-compare: running {} ===", mode.name());
+        modes.push(run_mode(*mode, &options.config).await?);
+    }
+
+    let report = BenchReport {
+        config: options.config,
+        modes,
+    };
+    std::fs::write(&options.output, serde_json::to_vec_pretty(&report)?)?;
+
+    println!("\n=== bench-compare summary (p50 / p99 latency, mean proof size) ===");
+    for mode in &report.modes {
+        println!("{}:", mode.ads_mode.name());
+        for workload in &mode.workloads {
+            println!(
+                "  {:<14} {:>10.0}us {:>10.0}us {:>10} failures: {}",
+                workload.name,
+                workload.latency.p50_us,
+                workload.latency.p99_us,
+                workload
+                    .proof_bytes
+                    .as_ref()
+                    .map_or("-".to_string(), |p| format!("{:.0}B", p.mean)),
+                workload.failures
+            );
+        }
+        println!(
+            "  storage after ingest: {}",
+            mode.storage
+                .after_ingest_bytes
+                .map_or("n/a".to_string(), |b| format!("{} bytes", b))
+        );
+    }
+    println!("Report written to {}", options.output);
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+//! 一条命令拉起本地集群
+//!
+//! 启动一个 Manager 和若干 storager（均监听 `127.0.0.1` 的随机端口），等待它们就绪后
+//! 打印各节点地址，直到收到 Ctrl-C / SIGTERM 再依次关闭。默认所有节点在当前进程中运行；
+//! `--spawn` 时以子进程运行与本程序位于同一目录（或 `--bin-dir`）的 `manager` 和 `storager`。
+//!
+//! # 使用方法
+//! ```bash
+//! cargo run --bin dss-cluster -- --ads-mode mpt --storagers 3 --config-out cluster.json
+//!
+//! # 客户端按写出的配置连接 Manager
+//! cargo run --bin client -- --config cluster.json add f1 --keywords rust,grpc
+//!
+//! # 以子进程运行（先构建 manager 和 storager 二进制）
+//! cargo build --bin manager --bin storager
+//! cargo run --bin dss-cluster -- --spawn
+//! ```
+
+use clap::Parser;
+use common::cli::{parse_ads_mode, LogArgs};
+use common::net::{shutdown_signal, DEFAULT_DRAIN_SECS};
+use common::telemetry::init_tracing;
+use common::AdsMode;
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+use system::runner::SystemRunner;
+use system::save_config;
+use tracing::info;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "dss-cluster",
+    about = "Start a local manager and storagers and wire them together"
+)]
+struct Cli {
+    /// ADS mode: accumulator|mpt|merkle|smt
+    #[arg(long, value_name = "MODE", default_value = "accumulator", value_parser = parse_ads_mode)]
+    ads_mode: AdsMode,
+
+    /// Number of storagers
+    #[arg(long, value_name = "N", default_value_t = 2)]
+    storagers: usize,
+
+    /// Run the nodes as manager/storager child processes instead of in this process
+    #[arg(long)]
+    spawn: bool,
+
+    /// Directory of the manager and storager binaries [default: this program's directory]
+    #[arg(long, value_name = "DIR", requires = "spawn")]
+    bin_dir: Option<PathBuf>,
+
+    /// Write the SystemConfig with every node's address to this file
+    #[arg(long, value_name = "PATH")]
+    config_out: Option<PathBuf>,
+
+    /// Seconds each node waits for in-flight requests when shutting down
+    #[arg(long, value_name = "SECS", default_value_t = DEFAULT_DRAIN_SECS)]
+    drain_timeout: u64,
+
+    #[command(flatten)]
+    log: LogArgs,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    init_tracing("dss-cluster", &cli.log.config())?;
+
+    let mut runner = SystemRunner::new(cli.ads_mode)
+        .with_storagers(cli.storagers)
+        .with_drain_timeout(Duration::from_secs(cli.drain_timeout));
+    if cli.spawn {
+        let bin_dir = match cli.bin_dir {
+            Some(dir) => dir,
+            None => std::env::current_exe()?
+                .parent()
+                .ok_or("cannot locate the binary directory")?
+                .to_path_buf(),
+        };
+        runner = runner.spawn(bin_dir);
+    }
+
+    let system = runner.start().await?;
+    info!("Manager: {}", system.manager_addr());
+    for entry in system.storager_addrs() {
+        info!("Storager: {}", entry);
+    }
+    if let Some(path) = &cli.config_out {
+        save_config(system.config(), &path.to_string_lossy())?;
+        info!("Cluster config written to {}", path.display());
+    }
+
+    let signal = shutdown_signal().await;
+    info!("Received {}, shutting down the cluster", signal);
+    system.shutdown().await
+}
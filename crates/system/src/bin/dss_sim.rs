@@ -0,0 +1,157 @@
+//! 按种子运行确定性集群模拟
+//!
+//! 依次运行 `--seed` 开始的 `--runs` 个种子，打印每个种子的统计；发现不一致时打印
+//! 不一致之处和最后的事件历史，并以非零状态退出。用同一个种子重新运行即可复现。
+//!
+//! # 使用方法
+//! ```bash
+//! # 搜索 100 个种子
+//! cargo run --release --bin dss-sim -- --runs 100 --replication-factor 2
+//!
+//! # 重放失败的种子并打印完整历史
+//! cargo run --release --bin dss-sim -- --seed 101 --replication-factor 2 --history
+//! ```
+
+use clap::Parser;
+use common::cli::parse_ads_mode;
+use common::AdsMode;
+use std::process::ExitCode;
+use std::time::Duration;
+use system::sim::{FaultConfig, Simulation};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "dss-sim",
+    about = "Run seeded, deterministic cluster simulations with fault injection"
+)]
+struct Cli {
+    /// First seed to run
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Number of consecutive seeds to run
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    runs: u64,
+
+    /// Steps per simulation
+    #[arg(long, value_name = "N", default_value_t = 200)]
+    steps: usize,
+
+    /// ADS mode: accumulator|mpt|merkle|smt
+    #[arg(long, value_name = "MODE", default_value = "merkle", value_parser = parse_ads_mode)]
+    ads_mode: AdsMode,
+
+    /// Initial number of storagers
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    storagers: usize,
+
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    replication_factor: usize,
+
+    /// Maximum concurrent operations per step
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    concurrency: usize,
+
+    /// Do not add, remove or rebalance storagers
+    #[arg(long)]
+    no_membership: bool,
+
+    /// Probability that a storager RPC request is dropped
+    #[arg(long, value_name = "P")]
+    drop_request: Option<f64>,
+
+    /// Probability that a storager RPC response is dropped after it was applied
+    #[arg(long, value_name = "P")]
+    drop_response: Option<f64>,
+
+    /// Probability that a storager RPC response is delayed
+    #[arg(long, value_name = "P")]
+    delay: Option<f64>,
+
+    /// Upper bound of injected delays in milliseconds
+    #[arg(long, value_name = "MS")]
+    max_delay_ms: Option<u64>,
+
+    /// Probability per step that a storager crashes
+    #[arg(long, value_name = "P")]
+    crash: Option<f64>,
+
+    /// Print the full event history of every run
+    #[arg(long)]
+    history: bool,
+}
+
+impl Cli {
+    fn faults(&self) -> FaultConfig {
+        let defaults = FaultConfig::default();
+        FaultConfig {
+            drop_request: self.drop_request.unwrap_or(defaults.drop_request),
+            drop_response: self.drop_response.unwrap_or(defaults.drop_response),
+            delay: self.delay.unwrap_or(defaults.delay),
+            max_delay: self
+                .max_delay_ms
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.max_delay),
+            crash: self.crash.unwrap_or(defaults.crash),
+            ..defaults
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let faults = cli.faults();
+    for p in [
+        faults.drop_request,
+        faults.drop_response,
+        faults.delay,
+        faults.crash,
+    ] {
+        if !(0.0..=1.0).contains(&p) {
+            eprintln!("Error: probabilities must be between 0 and 1, got {}", p);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let mut failed = Vec::new();
+    for seed in cli.seed..cli.seed.saturating_add(cli.runs) {
+        let report = Simulation::new(seed)
+            .with_ads_mode(cli.ads_mode)
+            .with_storagers(cli.storagers)
+            .with_steps(cli.steps)
+            .with_concurrency(cli.concurrency)
+            .with_replication_factor(cli.replication_factor)
+            .with_membership_changes(!cli.no_membership)
+            .with_faults(faults.clone())
+            .run();
+        let stats = &report.stats;
+        println!(
+            "seed {}: {} op(s), {} failed, {} fault(s), {} crash(es), {} membership change(s), {} violation(s)",
+            seed,
+            stats.operations,
+            stats.failed_operations,
+            stats.injected_faults,
+            stats.crashes,
+            stats.membership_changes,
+            report.violations.len()
+        );
+        if cli.history {
+            for event in &report.history {
+                println!("  {}", event);
+            }
+        }
+        if !report.is_consistent() {
+            for violation in &report.violations {
+                println!("  ! {}", violation);
+            }
+            failed.push(seed);
+        }
+    }
+
+    if failed.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        println!("Inconsistent seeds: {:?}", failed);
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,191 @@
+//! ADS 模式对比实验
+//!
+//! 从数据集加载记录（格式见 `client::dataset`），为每种 ADS 模式在进程内启动一个本地集群，
+//! 预载一部分记录后运行相同的混合工作负载（写入 / 单关键词查询 / 布尔查询 / 删除，
+//! 比例由 `--mix` 指定，见 [`system::experiment`]），把每种模式每类操作的延迟、吞吐量、
+//! 证明大小、验证代价和存储占用写成 CSV。
+//!
+//! 各节点的运行日志打印到标准输出，因此 CSV 写到 `--output` 指定的文件。
+//!
+//! # 使用方法
+//! ```bash
+//! cargo run --release --bin experiments -- --dataset data/testdata
+//!
+//! # 指定模式、负载比例和规模
+//! cargo run --release --bin experiments -- --dataset records.jsonl --format jsonl \
+//!     --modes accumulator,mpt --mix add=20,query=60,boolean=15,delete=5 \
+//!     --preload 500 --operations 2000 --output experiments.csv
+//! ```
+
+use clap::Parser;
+use common::cli::parse_ads_mode;
+use common::rpc::query_request::QueryType;
+use common::AdsMode;
+use std::error::Error;
+use std::time::Instant;
+use system::bench::{Driver, Samples};
+use system::experiment::{plan, write_csv, ExperimentRow, OpKind, Operation, OperationMix, Plan};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "experiments",
+    about = "Run the same dataset-driven workload against several ADS modes and write a CSV"
+)]
+struct Cli {
+    /// Dataset file with (fid, keywords) records
+    #[arg(long, value_name = "PATH")]
+    dataset: String,
+
+    /// Dataset format: csv|jsonl (defaults to the file extension, csv otherwise)
+    #[arg(long, value_name = "FORMAT")]
+    format: Option<client::DatasetFormat>,
+
+    /// Comma-separated ADS modes to compare
+    #[arg(long, value_name = "LIST", value_delimiter = ',', value_parser = parse_ads_mode,
+          default_value = "accumulator,mpt")]
+    modes: Vec<AdsMode>,
+
+    /// Relative weights of the operations in the mixed workload
+    #[arg(
+        long,
+        value_name = "MIX",
+        default_value = "add=30,query=50,boolean=15,delete=5"
+    )]
+    mix: OperationMix,
+
+    /// Records written before the measured workload (defaults to half the dataset)
+    #[arg(long, value_name = "N")]
+    preload: Option<usize>,
+
+    /// Operations in the measured workload
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    operations: usize,
+
+    #[arg(long, value_name = "N", default_value_t = 2)]
+    storagers: usize,
+
+    /// Seed of the workload generator; identical for every mode
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+
+    /// CSV output path
+    #[arg(long, value_name = "PATH", default_value = "experiments.csv")]
+    output: String,
+}
+
+async fn run_mode(
+    mode: AdsMode,
+    plan: &Plan,
+    storagers: usize,
+) -> Result<Vec<ExperimentRow>, Box<dyn Error>> {
+    let mut driver = Driver::start(mode, storagers).await?;
+
+    let mut preload = Samples::default();
+    for (fid, keywords) in &plan.preload {
+        driver.add(fid, keywords, &mut preload).await;
+    }
+    if preload.failures > 0 {
+        eprintln!(
+            "warning: {} of {} preload writes failed",
+            preload.failures,
+            plan.preload.len()
+        );
+    }
+
+    let mut samples: Vec<Samples> = vec![Samples::default(); OpKind::ALL.len()];
+    let start = Instant::now();
+    for operation in &plan.operations {
+        let recorder = &mut samples[operation.kind() as usize];
+        match operation {
+            Operation::Add((fid, keywords)) => driver.add(fid, keywords, recorder).await,
+            Operation::Delete((fid, keywords)) => driver.delete(fid, keywords, recorder).await,
+            Operation::Query(keyword) => {
+                driver
+                    .query(QueryType::Keyword(keyword.clone()), recorder)
+                    .await
+            }
+            Operation::Boolean(expr) => {
+                driver
+                    .query(QueryType::BooleanFunction(expr.clone()), recorder)
+                    .await
+            }
+        }
+    }
+    let elapsed = start.elapsed();
+    let (storage_bytes, _) = driver.storage_footprint();
+    driver.shutdown().await?;
+
+    let mut all = Samples::default();
+    let mut rows = Vec::new();
+    for (kind, samples) in OpKind::ALL.into_iter().zip(&samples) {
+        all.extend(samples);
+        rows.push(ExperimentRow::from_samples(
+            mode,
+            kind.name(),
+            samples,
+            None,
+            storage_bytes,
+        ));
+    }
+    rows.push(ExperimentRow::from_samples(
+        mode,
+        "all",
+        &all,
+        Some(elapsed),
+        storage_bytes,
+    ));
+    Ok(rows)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    if cli.storagers == 0 {
+        return Err("--storagers must be positive".into());
+    }
+
+    let format = cli.format.unwrap_or(if cli.dataset.ends_with(".jsonl") {
+        client::DatasetFormat::Jsonl
+    } else {
+        client::DatasetFormat::Csv
+    });
+    let input = std::fs::read_to_string(&cli.dataset)?;
+    let records = client::dataset::parse_records(&input, format)?;
+    if records.is_empty() {
+        return Err(format!("dataset {} has no records", cli.dataset).into());
+    }
+    let preload = cli.preload.unwrap_or(records.len() / 2);
+    let plan = plan(&records, preload, cli.operations, &cli.mix, cli.seed);
+    println!(
+        "=== experiments: {} records, {} preloaded, {} operations ({}) ===",
+        records.len(),
+        plan.preload.len(),
+        plan.operations.len(),
+        cli.mix
+    );
+
+    let mut rows = Vec::new();
+    for mode in &cli.modes {
+        println!("=== experiments: running {} ===", mode.name());
+        rows.extend(run_mode(*mode, &plan, cli.storagers).await?);
+    }
+
+    let mut csv = Vec::new();
+    write_csv(&rows, &mut csv)?;
+    std::fs::write(&cli.output, csv)?;
+
+    println!("\n=== experiments summary (throughput, p50 latency, mean proof size) ===");
+    for row in rows.iter().filter(|row| row.operation == "all") {
+        println!(
+            "{:<12} {:>10.1} ops/s {:>10.0}us {:>10.0}B failures: {}",
+            row.ads_mode.name(),
+            row.throughput,
+            row.latency.p50_us,
+            row.proof_bytes_mean,
+            row.failures
+        );
+    }
+    println!("CSV written to {}", cli.output);
+
+    Ok(())
+}
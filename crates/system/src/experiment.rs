@@ -0,0 +1,378 @@
+//! ADS 模式对比实验
+//!
+//! `experiments` 二进制从数据集文件加载记录，先预载一部分，再按 [`OperationMix`]
+//! 给出的比例生成写入、单关键词查询、布尔查询和删除的混合操作序列（见 [`plan`]）。
+//! 序列只由数据集和种子决定，每种 ADS 模式运行完全相同的操作；各模式的集群使用
+//! 相同数量的 storager 和相同的哈希环，keyword 的路由也完全相同。
+//!
+//! 操作由 [`crate::bench::Driver`] 发出，样本格式与 `bench-compare` 相同（[`Samples`]）。
+//! 每种模式每类操作一行 [`ExperimentRow`]，另有一行 `all` 汇总整个混合负载，
+//! 以 CSV 输出（见 [`write_csv`]），便于直接导入表格或绘图工具。
+//!
+//! # 示例
+//!
+//! ```
+//! use system::experiment::{OpKind, OperationMix};
+//!
+//! let mix: OperationMix = "add=1,query=3".parse().unwrap();
+//! assert_eq!(mix.pick(0), OpKind::Add);
+//! assert_eq!(mix.pick(3), OpKind::Query);
+//! ```
+
+use crate::bench::{LatencySummary, Samples};
+use client::dataset::Record;
+use common::AdsMode;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// 操作类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OpKind {
+    Add,
+    Query,
+    Boolean,
+    Delete,
+}
+
+impl OpKind {
+    pub const ALL: [OpKind; 4] = [OpKind::Add, OpKind::Query, OpKind::Boolean, OpKind::Delete];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OpKind::Add => "add",
+            OpKind::Query => "query",
+            OpKind::Boolean => "boolean",
+            OpKind::Delete => "delete",
+        }
+    }
+}
+
+/// 各类操作的相对权重，格式为 `add=30,query=50,boolean=15,delete=5`，未列出的类别权重为 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperationMix {
+    weights: [u32; 4],
+}
+
+impl Default for OperationMix {
+    fn default() -> Self {
+        OperationMix {
+            weights: [30, 50, 15, 5],
+        }
+    }
+}
+
+impl OperationMix {
+    /// 权重之和
+    pub fn total(&self) -> u32 {
+        self.weights.iter().sum()
+    }
+
+    /// 把 `0..total()` 中的一个数映射到操作类别
+    pub fn pick(&self, mut roll: u32) -> OpKind {
+        for (kind, weight) in OpKind::ALL.into_iter().zip(self.weights) {
+            if roll < weight {
+                return kind;
+            }
+            roll -= weight;
+        }
+        OpKind::Query
+    }
+}
+
+impl FromStr for OperationMix {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut weights = [0u32; 4];
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, weight) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("mix entry '{}' must be <operation>=<weight>", entry))?;
+            let index = OpKind::ALL
+                .iter()
+                .position(|kind| kind.name() == name.trim())
+                .ok_or_else(|| {
+                    format!(
+                        "unknown operation '{}' (expected add, query, boolean or delete)",
+                        name
+                    )
+                })?;
+            weights[index] = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("weight of '{}' must be a non-negative integer", name))?;
+        }
+        let mix = OperationMix { weights };
+        if mix.total() == 0 {
+            return Err("operation mix needs at least one positive weight".to_string());
+        }
+        Ok(mix)
+    }
+}
+
+impl fmt::Display for OperationMix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<String> = OpKind::ALL
+            .iter()
+            .zip(self.weights)
+            .map(|(kind, weight)| format!("{}={}", kind.name(), weight))
+            .collect();
+        f.write_str(&entries.join(","))
+    }
+}
+
+/// 工作负载中的一个操作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Add(Record),
+    Query(String),
+    Boolean(String),
+    Delete(Record),
+}
+
+impl Operation {
+    pub fn kind(&self) -> OpKind {
+        match self {
+            Operation::Add(_) => OpKind::Add,
+            Operation::Query(_) => OpKind::Query,
+            Operation::Boolean(_) => OpKind::Boolean,
+            Operation::Delete(_) => OpKind::Delete,
+        }
+    }
+}
+
+/// 由数据集生成的实验计划
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    /// 混合负载开始前写入的记录，不计入结果
+    pub preload: Vec<Record>,
+    pub operations: Vec<Operation>,
+}
+
+/// 生成实验计划
+///
+/// 前 `preload` 条记录预先写入，写入操作按数据集顺序取剩下的记录；删除随机选一条
+/// 已写入的记录并删除它的全部 keyword；查询从数据集的 keyword 表中随机选取，
+/// 因此也会查询已删除或尚未写入的 keyword。没有可写入或可删除的记录时改为单关键词查询
+pub fn plan(
+    records: &[Record],
+    preload: usize,
+    operations: usize,
+    mix: &OperationMix,
+    seed: u64,
+) -> Plan {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let preload = preload.min(records.len());
+    let vocabulary: Vec<String> = records
+        .iter()
+        .flat_map(|(_, keywords)| keywords.iter().cloned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let mut live: Vec<Record> = records[..preload].to_vec();
+    let mut pending: VecDeque<Record> = records[preload..].iter().cloned().collect();
+
+    let mut planned = Vec::with_capacity(operations);
+    if vocabulary.is_empty() {
+        return Plan {
+            preload: live,
+            operations: planned,
+        };
+    }
+    for _ in 0..operations {
+        let operation = match mix.pick(rng.gen_range(0..mix.total())) {
+            OpKind::Add if !pending.is_empty() => {
+                let record = pending.pop_front().expect("checked non-empty");
+                live.push(record.clone());
+                Operation::Add(record)
+            }
+            OpKind::Delete if !live.is_empty() => {
+                let record = live.swap_remove(rng.gen_range(0..live.len()));
+                Operation::Delete(record)
+            }
+            OpKind::Boolean if vocabulary.len() >= 2 => {
+                let pair: Vec<&String> = vocabulary.choose_multiple(&mut rng, 2).collect();
+                let op = if rng.gen_bool(0.5) { "AND" } else { "OR" };
+                Operation::Boolean(format!("{} {} {}", pair[0], op, pair[1]))
+            }
+            _ => Operation::Query(vocabulary.choose(&mut rng).expect("non-empty").clone()),
+        };
+        planned.push(operation);
+    }
+    Plan {
+        preload: records[..preload].to_vec(),
+        operations: planned,
+    }
+}
+
+/// CSV 中的一行：一种 ADS 模式下一类操作的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExperimentRow {
+    pub ads_mode: AdsMode,
+    /// add / query / boolean / delete，或整个混合负载 all
+    pub operation: String,
+    pub operations: usize,
+    pub failures: usize,
+    pub verified: usize,
+    /// 每秒操作数；各类操作按各自的耗时之和计算，`all` 按混合负载的总耗时计算
+    pub throughput: f64,
+    pub latency: LatencySummary,
+    pub proof_bytes_mean: f64,
+    pub proof_bytes_max: u64,
+    /// 每个带证明的查询平均的配对和哈希次数（见 `ProofMetrics`）
+    pub pairing_ops_mean: f64,
+    pub hash_ops_mean: f64,
+    /// 混合负载结束后所有 storager 导出状态的大小之和，后端不支持导出时为空
+    pub storage_bytes: Option<usize>,
+}
+
+impl ExperimentRow {
+    /// 由样本计算一行；`elapsed` 为 None 时按样本延迟之和计算吞吐量
+    pub fn from_samples(
+        ads_mode: AdsMode,
+        operation: &str,
+        samples: &Samples,
+        elapsed: Option<Duration>,
+        storage_bytes: Option<usize>,
+    ) -> Self {
+        let elapsed = elapsed.unwrap_or_else(|| samples.latencies.iter().sum());
+        let operations = samples.latencies.len();
+        let throughput = if elapsed.is_zero() {
+            0.0
+        } else {
+            operations as f64 / elapsed.as_secs_f64()
+        };
+        let proofs = samples.proof_bytes.len().max(1) as f64;
+        ExperimentRow {
+            ads_mode,
+            operation: operation.to_string(),
+            operations,
+            failures: samples.failures,
+            verified: samples.verified,
+            throughput,
+            latency: LatencySummary::from_samples(&samples.latencies),
+            proof_bytes_mean: samples.proof_bytes.iter().sum::<u64>() as f64 / proofs,
+            proof_bytes_max: samples.proof_bytes.iter().copied().max().unwrap_or(0),
+            pairing_ops_mean: samples.pairing_ops as f64 / proofs,
+            hash_ops_mean: samples.hash_ops as f64 / proofs,
+            storage_bytes,
+        }
+    }
+}
+
+/// CSV 表头，列顺序与 [`write_csv`] 写出的行一致
+pub const CSV_HEADER: &str = "ads_mode,operation,operations,failures,verified,throughput_ops,\
+latency_mean_us,latency_p50_us,latency_p90_us,latency_p99_us,latency_max_us,\
+proof_bytes_mean,proof_bytes_max,pairing_ops_mean,hash_ops_mean,storage_bytes";
+
+/// 写出表头和所有行
+pub fn write_csv<W: Write>(rows: &[ExperimentRow], mut writer: W) -> io::Result<()> {
+    writeln!(writer, "{}", CSV_HEADER)?;
+    for row in rows {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{:.2},{:.1},{:.1},{:.1},{:.1},{:.1},{:.1},{},{:.2},{:.2},{}",
+            row.ads_mode.name(),
+            row.operation,
+            row.operations,
+            row.failures,
+            row.verified,
+            row.throughput,
+            row.latency.mean_us,
+            row.latency.p50_us,
+            row.latency.p90_us,
+            row.latency.p99_us,
+            row.latency.max_us,
+            row.proof_bytes_mean,
+            row.proof_bytes_max,
+            row.pairing_ops_mean,
+            row.hash_ops_mean,
+            row.storage_bytes.map_or(String::new(), |b| b.to_string()),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<Record> {
+        (0..10)
+            .map(|i| {
+                let keywords = vec![format!("kw{}", i % 3), format!("kw{}", 3 + i % 2)];
+                (format!("file-{}", i), keywords)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_mix() {
+        let mix: OperationMix = "query=3, delete=1".parse().unwrap();
+        assert_eq!(mix.total(), 4);
+        assert_eq!(mix.pick(0), OpKind::Query);
+        assert_eq!(mix.pick(3), OpKind::Delete);
+        assert_eq!(mix.to_string(), "add=0,query=3,boolean=0,delete=1");
+        assert!("add=0".parse::<OperationMix>().is_err());
+        assert!("scan=1".parse::<OperationMix>().is_err());
+        assert!("add".parse::<OperationMix>().is_err());
+    }
+
+    #[test]
+    fn test_plan_is_deterministic_and_consistent() {
+        let records = records();
+        let mix = OperationMix::default();
+        let first = plan(&records, 4, 50, &mix, 7);
+        assert_eq!(first, plan(&records, 4, 50, &mix, 7));
+        assert_eq!(first.preload, records[..4]);
+        assert_eq!(first.operations.len(), 50);
+
+        // 写入按数据集顺序，删除只针对已写入且尚未删除的记录
+        let mut live: BTreeSet<String> = first.preload.iter().map(|(f, _)| f.clone()).collect();
+        let mut next = 4;
+        for operation in &first.operations {
+            match operation {
+                Operation::Add((fid, _)) => {
+                    assert_eq!(fid, &records[next].0);
+                    next += 1;
+                    live.insert(fid.clone());
+                }
+                Operation::Delete((fid, _)) => assert!(live.remove(fid)),
+                Operation::Query(_) | Operation::Boolean(_) => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_csv_row() {
+        let samples = Samples {
+            latencies: vec![Duration::from_millis(10), Duration::from_millis(30)],
+            verified: 2,
+            proof_bytes: vec![100, 300],
+            pairing_ops: 4,
+            ..Default::default()
+        };
+        let row = ExperimentRow::from_samples(AdsMode::Mpt, "query", &samples, None, None);
+        assert_eq!(row.throughput, 50.0);
+        assert_eq!(row.proof_bytes_mean, 200.0);
+        assert_eq!(row.pairing_ops_mean, 2.0);
+
+        let mut csv = Vec::new();
+        write_csv(&[row], &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].starts_with("mpt,query,2,0,2,50.00,"));
+        assert_eq!(
+            lines[0].split(',').count(),
+            lines[1].split(',').count(),
+            "header and row have the same columns"
+        );
+    }
+}
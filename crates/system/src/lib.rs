@@ -3,14 +3,27 @@
 //! 提供整个分布式存储系统的初始化与配置读写工具：
 //! - `initialize` 用于根据参数构造 `SystemConfig`
 //! - `load_config` / `save_config` 用于从文件加载和保存配置
+//! - [`runner`] 在进程内或以子进程一次拉起 Manager 和所有 storager（`dss-cluster` 二进制）
+//! - [`sim`] 在单线程运行时中确定性地模拟集群，注入 RPC 故障和节点宕机（`dss-sim` 二进制）
+//! - [`testkit`] 为端到端测试启动进程内集群并提供断言辅助函数
+//! - [`bench`] 是 `bench-compare` 二进制输出的 ADS 模式对比报告格式
+//! - [`experiment`] 由数据集生成混合工作负载，`experiments` 二进制把各 ADS 模式的结果写成 CSV
 
+pub mod bench;
+pub mod experiment;
+pub mod runner;
+pub mod sim;
+pub mod testkit;
+
+use common::cli::parse_storager_addr;
+use common::net::validate_address;
 use common::{AdsMode, SystemConfig};
 use std::error::Error;
 
 /// Initialize the distributed storage system
 ///
-/// 当前实现仅构造并返回 `SystemConfig`，不负责真正拉起各个进程。
-/// 可以在测试或实验脚本中复用。
+/// 只校验参数并构造 `SystemConfig`，不启动任何节点；
+/// 需要真正拉起集群时使用 [`runner::SystemRunner`]。
 pub async fn initialize(
     num_clients: usize,
     num_storagers: usize,
@@ -34,6 +47,12 @@ pub async fn initialize(
         return Err("Number of client addresses must match num_clients or be empty".into());
     }
 
+    // 地址可以是 http(s):// URL 或 unix:/path；storager 地址允许 name= 前缀
+    validate_address(&manager_addr)?;
+    for entry in &storager_addrs {
+        parse_storager_addr(entry)?;
+    }
+
     let config = SystemConfig {
         num_clients,
         num_storagers,
@@ -41,6 +60,14 @@ pub async fn initialize(
         manager_addr,
         storager_addrs,
         client_addrs,
+        manager_bind_addrs: Vec::new(),
+        storager_bind_addrs: Vec::new(),
+        replication_factor: None,
+        fanout_limit: None,
+        root_history: None,
+        query_cache: None,
+        negative_cache: None,
+        log_level: None,
     };
 
     println!("System initialized successfully!");
@@ -50,9 +77,7 @@ pub async fn initialize(
 
 /// Load system configuration from a file
 pub fn load_config(path: &str) -> Result<SystemConfig, Box<dyn Error>> {
-    let content = std::fs::read_to_string(path)?;
-    let config: SystemConfig = serde_json::from_str(&content)?;
-    Ok(config)
+    Ok(SystemConfig::load(path)?)
 }
 
 /// Save system configuration to a file
@@ -87,4 +112,51 @@ mod tests {
         assert_eq!(config.num_clients, 2);
         assert_eq!(config.num_storagers, 3);
     }
+
+    #[tokio::test]
+    async fn test_initialize_validates_addresses() {
+        let config = initialize(
+            1,
+            2,
+            AdsMode::Mpt,
+            "unix:/run/dss/manager.sock".to_string(),
+            vec![
+                "s1=unix:/run/dss/storager-0.sock".to_string(),
+                "http://[::1]:50053".to_string(),
+            ],
+            vec![],
+        )
+        .await;
+        assert!(config.is_ok());
+
+        let config = initialize(
+            1,
+            1,
+            AdsMode::Mpt,
+            "http://[::1]:50051".to_string(),
+            vec!["unix:relative.sock".to_string()],
+            vec![],
+        )
+        .await;
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn test_load_config_without_bind_addrs() {
+        let json = r#"{
+            "num_clients": 1,
+            "num_storagers": 1,
+            "ads_mode": "Mpt",
+            "manager_addr": "http://[::1]:50051",
+            "storager_addrs": ["s1=http://10.0.0.5:50052"],
+            "client_addrs": []
+        }"#;
+        let path = std::env::temp_dir().join("system_config_bind_addrs_test.json");
+        std::fs::write(&path, json).unwrap();
+
+        let config = load_config(path.to_str().unwrap()).unwrap();
+        assert!(config.manager_bind_addrs.is_empty());
+        assert!(config.storager_bind_addrs.is_empty());
+        std::fs::remove_file(path).ok();
+    }
 }
@@ -0,0 +1,504 @@
+//! 一次调用拉起完整的本地集群
+//!
+//! [`SystemRunner`] 启动若干 storager 和一个 Manager，自动分配 `127.0.0.1` 上的端口并把
+//! storager 地址（`storager-{idx}=url`）交给 Manager，等待所有节点可以接受请求后返回
+//! [`RunningSystem`]。节点有两种运行方式：
+//!
+//! - 进程内（默认）：各节点作为当前 Tokio 运行时中的任务运行，可以直接访问
+//!   [`Manager`] 和 [`Storager`] 实例，适合测试
+//! - 子进程（[`SystemRunner::spawn`]）：运行编译好的 `manager` / `storager` 二进制，
+//!   与真实部署相同，适合演示
+//!
+//! [`RunningSystem::shutdown`] 先关闭 Manager 再关闭 storager，与单独部署时收到
+//! SIGTERM 的处理相同：排空进行中的请求后保存状态。
+//!
+//! # 示例
+//!
+//! ```no_run
+//! use common::AdsMode;
+//! use system::runner::SystemRunner;
+//!
+//! # async fn demo() -> Result<(), Box<dyn std::error::Error>> {
+//! let system = SystemRunner::new(AdsMode::Mpt).with_storagers(3).start().await?;
+//! println!("manager at {}", system.manager_addr());
+//! system.shutdown().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use common::net::{bind_tcp, connect, serve_with_drain, Listeners, DEFAULT_DRAIN_SECS};
+use common::rpc::{ClusterStatusRequest, StoragerHealthRequest};
+use common::transport::TransportConfig;
+use common::{AdsMode, SystemConfig};
+use manager::Manager;
+use std::error::Error;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use storager::Storager;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tonic::transport::server::Router;
+use tonic::transport::Server;
+
+/// 等待节点就绪的默认时长
+pub const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 就绪检查的重试间隔
+const READY_POLL: Duration = Duration::from_millis(50);
+
+/// 集群的启动参数
+#[derive(Debug, Clone)]
+pub struct SystemRunner {
+    ads_mode: AdsMode,
+    storagers: usize,
+    /// 子进程模式下二进制所在的目录，None 表示进程内运行
+    bin_dir: Option<PathBuf>,
+    ready_timeout: Duration,
+    drain_timeout: Duration,
+}
+
+impl SystemRunner {
+    /// 两个 storager、进程内运行
+    pub fn new(ads_mode: AdsMode) -> Self {
+        SystemRunner {
+            ads_mode,
+            storagers: 2,
+            bin_dir: None,
+            ready_timeout: DEFAULT_READY_TIMEOUT,
+            drain_timeout: Duration::from_secs(DEFAULT_DRAIN_SECS),
+        }
+    }
+
+    /// 设置 storager 数量（至少为 1）
+    pub fn with_storagers(mut self, count: usize) -> Self {
+        self.storagers = count.max(1);
+        self
+    }
+
+    /// 以子进程运行 `bin_dir` 中的 `manager` 和 `storager` 二进制
+    ///
+    /// 子进程继承当前进程的标准输出、标准错误和环境变量（例如 `RUST_LOG`）
+    pub fn spawn(mut self, bin_dir: impl Into<PathBuf>) -> Self {
+        self.bin_dir = Some(bin_dir.into());
+        self
+    }
+
+    /// 设置等待每个节点就绪的最长时间
+    pub fn with_ready_timeout(mut self, timeout: Duration) -> Self {
+        self.ready_timeout = timeout;
+        self
+    }
+
+    /// 设置关闭时等待进行中请求的最长时间
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// 启动所有节点并等待它们就绪；任一节点启动失败时关闭已启动的节点
+    pub async fn start(self) -> Result<RunningSystem, Box<dyn Error>> {
+        let mut system = RunningSystem {
+            config: SystemConfig {
+                num_clients: 0,
+                num_storagers: self.storagers,
+                ads_mode: self.ads_mode,
+                manager_addr: String::new(),
+                storager_addrs: Vec::new(),
+                client_addrs: Vec::new(),
+                manager_bind_addrs: Vec::new(),
+                storager_bind_addrs: Vec::new(),
+                replication_factor: None,
+                fanout_limit: None,
+                root_history: None,
+                query_cache: None,
+                negative_cache: None,
+                log_level: None,
+            },
+            manager: None,
+            storagers: Vec::new(),
+            drain_timeout: self.drain_timeout,
+        };
+        match self.start_nodes(&mut system).await {
+            Ok(()) => Ok(system),
+            Err(e) => {
+                let _ = system.shutdown().await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn start_nodes(&self, system: &mut RunningSystem) -> Result<(), Box<dyn Error>> {
+        for idx in 0..self.storagers {
+            let (addr, node) = match &self.bin_dir {
+                Some(bin_dir) => {
+                    let port = free_port()?;
+                    let child = spawn_child(
+                        &bin_dir.join("storager"),
+                        &[
+                            port.to_string(),
+                            format!("--ads-mode={}", self.ads_mode.name()),
+                            format!("--listen=127.0.0.1:{}", port),
+                            format!("--drain-timeout={}", self.drain_timeout.as_secs()),
+                        ],
+                    )?;
+                    (format!("http://127.0.0.1:{}", port), Node::Process(child))
+                }
+                None => {
+                    let storager = Arc::new(Storager::from_config(self.ads_mode.name()));
+                    let service = TransportConfig::default().storager_server(storager.clone());
+                    let router = move || Server::builder().add_service(service.clone());
+                    let (addr, server) = LocalServer::start(router, self.drain_timeout)?;
+                    (addr, Node::Storager(server, storager))
+                }
+            };
+            system
+                .config
+                .storager_addrs
+                .push(format!("storager-{}={}", idx, addr));
+            system.storagers.push(node);
+            self.wait_ready(
+                &addr,
+                system.storagers.last_mut().unwrap(),
+                |addr| async move {
+                    let channel = connect(&addr).await.map_err(|e| e.to_string())?;
+                    let health = TransportConfig::default()
+                        .storager_client(channel)
+                        .health(StoragerHealthRequest {})
+                        .await
+                        .map_err(|e| e.message().to_string())?
+                        .into_inner();
+                    match health.crypto_ready {
+                        true => Ok(()),
+                        false => Err(health.message),
+                    }
+                },
+            )
+            .await?;
+        }
+
+        let (addr, node) = match &self.bin_dir {
+            Some(bin_dir) => {
+                let port = free_port()?;
+                let child = spawn_child(
+                    &bin_dir.join("manager"),
+                    &[
+                        format!("--port={}", port),
+                        format!("--listen=127.0.0.1:{}", port),
+                        format!("--ads-mode={}", self.ads_mode.name()),
+                        format!("--storagers={}", system.config.storager_addrs.join(",")),
+                        format!("--drain-timeout={}", self.drain_timeout.as_secs()),
+                    ],
+                )?;
+                (format!("http://127.0.0.1:{}", port), Node::Process(child))
+            }
+            None => {
+                let manager = Arc::new(Manager::new(
+                    system.config.storager_addrs.clone(),
+                    self.ads_mode,
+                ));
+                let transport = TransportConfig::default();
+                let service = transport.manager_server(manager.clone());
+                let admin = transport.admin_server(manager.clone());
+                let router = move || {
+                    Server::builder()
+                        .add_service(service.clone())
+                        .add_service(admin.clone())
+                };
+                let (addr, server) = LocalServer::start(router, self.drain_timeout)?;
+                (addr, Node::Manager(server, manager))
+            }
+        };
+        system.config.manager_addr = addr.clone();
+        let manager = system.manager.insert(node);
+        self.wait_ready(&addr, manager, |addr| async move {
+            let channel = connect(&addr).await.map_err(|e| e.to_string())?;
+            TransportConfig::default()
+                .admin_client(channel)
+                .cluster_status(ClusterStatusRequest {})
+                .await
+                .map(|_| ())
+                .map_err(|e| e.message().to_string())
+        })
+        .await
+    }
+
+    /// 反复执行 `probe` 直到成功；子进程提前退出或超时时返回最后一次的错误
+    async fn wait_ready<F, Fut>(
+        &self,
+        addr: &str,
+        node: &mut Node,
+        probe: F,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<(), String>>,
+    {
+        let deadline = tokio::time::Instant::now() + self.ready_timeout;
+        loop {
+            let error = match probe(addr.to_string()).await {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            if let Some(status) = node.exited()? {
+                return Err(format!("node at {} exited during startup ({})", addr, status).into());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(format!(
+                    "node at {} not ready after {:?}: {}",
+                    addr, self.ready_timeout, error
+                )
+                .into());
+            }
+            tokio::time::sleep(READY_POLL).await;
+        }
+    }
+}
+
+/// 运行中的集群
+pub struct RunningSystem {
+    config: SystemConfig,
+    manager: Option<Node>,
+    storagers: Vec<Node>,
+    drain_timeout: Duration,
+}
+
+impl RunningSystem {
+    /// 各节点的通告地址，可以用 [`crate::save_config`] 保存后交给客户端的 `--config`
+    pub fn config(&self) -> &SystemConfig {
+        &self.config
+    }
+
+    /// Manager 的通告地址
+    pub fn manager_addr(&self) -> &str {
+        &self.config.manager_addr
+    }
+
+    /// storager 配置项（`storager-{idx}=url`）
+    pub fn storager_addrs(&self) -> &[String] {
+        &self.config.storager_addrs
+    }
+
+    /// 进程内运行时的 Manager 实例
+    pub fn manager(&self) -> Option<&Arc<Manager>> {
+        match &self.manager {
+            Some(Node::Manager(_, manager)) => Some(manager),
+            _ => None,
+        }
+    }
+
+    /// 进程内运行时的 storager 实例，顺序与 [`storager_addrs`](Self::storager_addrs) 一致
+    pub fn storagers(&self) -> Vec<&Arc<Storager>> {
+        self.storagers
+            .iter()
+            .filter_map(|node| match node {
+                Node::Storager(_, storager) => Some(storager),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 先关闭 Manager 再关闭 storager，每个节点排空进行中的请求后保存状态
+    ///
+    /// 所有节点都会被关闭，返回遇到的第一个错误
+    pub async fn shutdown(mut self) -> Result<(), Box<dyn Error>> {
+        let mut result = Ok(());
+        let nodes = self
+            .manager
+            .take()
+            .into_iter()
+            .chain(self.storagers.drain(..));
+        for node in nodes {
+            if let Err(e) = node.stop(self.drain_timeout).await {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// 进程内运行的 gRPC 服务
+struct LocalServer {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<Result<(), String>>,
+}
+
+impl LocalServer {
+    /// 在 127.0.0.1 的随机端口上启动服务，返回 (通告地址, 服务)
+    fn start<F>(make_router: F, drain: Duration) -> Result<(String, Self), Box<dyn Error>>
+    where
+        F: FnMut() -> Router + Send + 'static,
+    {
+        let listener = bind_tcp("127.0.0.1:0".parse().unwrap())?;
+        let addr = format!("http://{}", listener.local_addr()?);
+        let listeners = Listeners {
+            tcp: vec![listener],
+            ..Default::default()
+        };
+        let (stop, stopped) = oneshot::channel::<()>();
+        let task = tokio::spawn(async move {
+            let shutdown = async {
+                let _ = stopped.await;
+            };
+            serve_with_drain(listeners, make_router, shutdown, drain)
+                .await
+                .map_err(|e| e.to_string())
+        });
+        Ok((addr, LocalServer { stop, task }))
+    }
+
+    async fn stop(self) -> Result<(), Box<dyn Error>> {
+        let _ = self.stop.send(());
+        Ok(self.task.await??)
+    }
+}
+
+/// 集群中的一个节点
+enum Node {
+    Manager(LocalServer, Arc<Manager>),
+    Storager(LocalServer, Arc<Storager>),
+    Process(tokio::process::Child),
+}
+
+impl Node {
+    /// 子进程已经退出时返回退出状态
+    fn exited(&mut self) -> std::io::Result<Option<std::process::ExitStatus>> {
+        match self {
+            Node::Process(child) => child.try_wait(),
+            _ => Ok(None),
+        }
+    }
+
+    async fn stop(self, drain: Duration) -> Result<(), Box<dyn Error>> {
+        match self {
+            Node::Manager(server, manager) => {
+                server.stop().await?;
+                Ok(manager.shutdown().await?)
+            }
+            Node::Storager(server, storager) => {
+                server.stop().await?;
+                storager.shutdown().await?;
+                Ok(())
+            }
+            Node::Process(mut child) => {
+                if child.try_wait()?.is_some() {
+                    return Ok(());
+                }
+                terminate(&child);
+                // 进程自己排空并保存状态；超出排空时限太久时强制结束
+                let status =
+                    match tokio::time::timeout(drain + Duration::from_secs(10), child.wait()).await
+                    {
+                        Ok(status) => status?,
+                        Err(_) => {
+                            child.kill().await?;
+                            return Err(format!(
+                                "process {:?} did not exit after SIGTERM and was killed",
+                                child.id()
+                            )
+                            .into());
+                        }
+                    };
+                match status.success() {
+                    true => Ok(()),
+                    false => Err(format!("process exited with {}", status).into()),
+                }
+            }
+        }
+    }
+}
+
+/// 请求子进程退出（触发与 Ctrl-C 相同的排空和落盘）
+fn terminate(child: &tokio::process::Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: 只向自己启动且尚未回收的子进程发送信号
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGTERM);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = child;
+}
+
+/// 启动子进程，当前进程退出时一并结束
+fn spawn_child(program: &Path, args: &[String]) -> Result<tokio::process::Child, Box<dyn Error>> {
+    tokio::process::Command::new(program)
+        .args(args)
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", program.display(), e).into())
+}
+
+/// 由系统分配一个当前空闲的端口（子进程随后绑定它）
+fn free_port() -> std::io::Result<u16> {
+    Ok(std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::rpc::manager_service_client::ManagerServiceClient;
+    use common::rpc::{query_request::QueryType, AckMode, AddRequest, QueryRequest};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_in_process_cluster() {
+        let system = SystemRunner::new(AdsMode::Mpt)
+            .with_storagers(3)
+            .with_drain_timeout(Duration::from_secs(1))
+            .start()
+            .await
+            .unwrap();
+        assert_eq!(system.storager_addrs().len(), 3);
+        assert!(system.storager_addrs()[0].starts_with("storager-0=http://127.0.0.1:"));
+        assert_eq!(system.storagers().len(), 3);
+        assert_eq!(system.manager().unwrap().get_storagers().len(), 3);
+
+        let mut client = ManagerServiceClient::connect(system.manager_addr().to_string())
+            .await
+            .unwrap();
+        let added = client
+            .add(AddRequest {
+                fid: "f1".to_string(),
+                keywords: vec!["rust".to_string()],
+                ack_mode: AckMode::Sync as i32,
+                tenant: String::new(),
+                namespace: String::new(),
+                ttl_seconds: 0,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(added.success, "{}", added.message);
+        let queried = client
+            .query(QueryRequest {
+                query_type: Some(QueryType::Keyword("rust".to_string())),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(queried.fids, vec!["f1"]);
+
+        let addr = system.manager_addr().to_string();
+        system.shutdown().await.unwrap();
+        assert!(connect(&addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_missing_binaries_fail_to_start() {
+        let dir = std::env::temp_dir().join("system-runner-no-binaries");
+        let err = SystemRunner::new(AdsMode::Mpt)
+            .spawn(&dir)
+            .start()
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("storager"), "{}", err);
+    }
+}
@@ -0,0 +1,973 @@
+//! 确定性集群模拟
+//!
+//! [`Simulation`] 在一个单线程、时间暂停的 Tokio 运行时中运行一个 Manager 和若干 storager，
+//! 节点之间通过进程内连接（`memory:` 地址）通信，ADS 操作在调用方线程上执行。
+//! 所有 I/O 和计时器都由同一个运行时调度，时间只在运行时空闲时跳到下一个计时器，
+//! 因此超时、重试退避和注入的延迟都不消耗真实时间。
+//!
+//! 每一步先按种子决定是否让某个 storager 宕机或恢复，再并发执行一批客户端操作
+//! （添加、删除、查询）和拓扑变更（加入、移除节点、再平衡）。storager 收到的每个 RPC
+//! 可能被注入故障：
+//!
+//! - 丢弃请求：storager 不处理，Manager 收到 `UNAVAILABLE`
+//! - 丢弃响应：storager 已经应用，Manager 仍收到 `UNAVAILABLE`（重试必须幂等）
+//! - 延迟响应：可能超过 Manager 的单次超时，触发重试
+//!
+//! 宕机的 storager 拒绝所有请求，处理中的请求也不再返回响应；恢复后保留宕机前的状态
+//! （相当于使用持久化后端重启）。
+//!
+//! 模拟同时维护一份参考模型：确认成功的写入确定了 (keyword, fid) 是否存在，失败的写入
+//! 结果未知。每个通过验证的查询结果都与模型比对，最后撤销所有故障并逐个检查 keyword。
+//! 同一个种子总是生成相同的操作和故障序列，失败的种子可以用 `dss-sim --seed` 重放。
+//!
+//! # 示例
+//!
+//! ```no_run
+//! use system::sim::Simulation;
+//!
+//! let report = Simulation::new(42).with_steps(100).with_replication_factor(2).run();
+//! report.assert_consistent();
+//! ```
+
+use common::clock::{SharedClock, SimulatedClock};
+use common::net::memory_incoming;
+use common::rpc::manager_service_server::ManagerService;
+use common::rpc::{query_request::QueryType, AckMode, AddRequest, DeleteRequest, QueryRequest};
+use common::transport::TransportConfig;
+use common::AdsMode;
+use futures::future::join_all;
+use manager::core::RetryPolicy;
+use manager::{Manager, DEFAULT_VIRTUAL_NODES};
+use rand::rngs::StdRng;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use storager::ads::AdsPool;
+use storager::Storager;
+use tokio::sync::oneshot;
+use tonic::body::BoxBody;
+use tonic::transport::Server;
+use tonic::{Request, Status};
+use tower::{Layer, Service};
+
+/// 模拟中 Manager 对 storager 的单次超时
+const SIM_RPC_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 报告中保留的最后几条历史（用于失败信息）
+const HISTORY_TAIL: usize = 40;
+
+/// 区分同一进程中并行运行的模拟（进程内地址全局唯一）
+static RUNS: AtomicU64 = AtomicU64::new(0);
+
+/// storager RPC 的故障注入概率
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultConfig {
+    /// 请求被丢弃（未被处理）的概率
+    pub drop_request: f64,
+    /// 请求被处理但响应被丢弃的概率
+    pub drop_response: f64,
+    /// 响应被延迟的概率
+    pub delay: f64,
+    /// 延迟的上限（超过 Manager 单次超时的延迟会触发重试）
+    pub max_delay: Duration,
+    /// 每一步让一个 storager 宕机的概率
+    pub crash: f64,
+    /// 每一步让一个宕机的 storager 恢复的概率
+    pub restart: f64,
+    /// 同时宕机的 storager 数量上限
+    pub max_down: usize,
+}
+
+impl FaultConfig {
+    /// 不注入任何故障
+    pub fn none() -> Self {
+        FaultConfig {
+            drop_request: 0.0,
+            drop_response: 0.0,
+            delay: 0.0,
+            max_delay: Duration::ZERO,
+            crash: 0.0,
+            restart: 0.0,
+            max_down: 0,
+        }
+    }
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        FaultConfig {
+            drop_request: 0.02,
+            drop_response: 0.02,
+            delay: 0.05,
+            max_delay: SIM_RPC_TIMEOUT * 2,
+            crash: 0.03,
+            restart: 0.3,
+            max_down: 1,
+        }
+    }
+}
+
+/// 一次模拟的参数
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    seed: u64,
+    ads_mode: AdsMode,
+    storagers: usize,
+    steps: usize,
+    concurrency: usize,
+    replication_factor: usize,
+    vocabulary: usize,
+    files: usize,
+    membership_changes: bool,
+    faults: FaultConfig,
+}
+
+impl Simulation {
+    /// 默认：Merkle 树、3 个 storager、200 步、每步最多 4 个并发操作、默认故障概率
+    pub fn new(seed: u64) -> Self {
+        Simulation {
+            seed,
+            ads_mode: AdsMode::MerkleTree,
+            storagers: 3,
+            steps: 200,
+            concurrency: 4,
+            replication_factor: 1,
+            vocabulary: 12,
+            files: 40,
+            membership_changes: true,
+            faults: FaultConfig::default(),
+        }
+    }
+
+    pub fn with_ads_mode(mut self, ads_mode: AdsMode) -> Self {
+        self.ads_mode = ads_mode;
+        self
+    }
+
+    /// 初始 storager 数量（至少为 1）
+    pub fn with_storagers(mut self, count: usize) -> Self {
+        self.storagers = count.max(1);
+        self
+    }
+
+    pub fn with_steps(mut self, steps: usize) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    /// 每一步并发执行的最大操作数（至少为 1）
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn with_replication_factor(mut self, factor: usize) -> Self {
+        self.replication_factor = factor.max(1);
+        self
+    }
+
+    /// keyword 和 fid 的取值数量，越小冲突越多
+    pub fn with_keyspace(mut self, vocabulary: usize, files: usize) -> Self {
+        self.vocabulary = vocabulary.max(1);
+        self.files = files.max(1);
+        self
+    }
+
+    /// 是否在运行中加入、移除节点和再平衡（默认开启）
+    pub fn with_membership_changes(mut self, enabled: bool) -> Self {
+        self.membership_changes = enabled;
+        self
+    }
+
+    pub fn with_faults(mut self, faults: FaultConfig) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    /// 运行模拟
+    ///
+    /// 使用自己的单线程运行时，不能在 Tokio 运行时中调用
+    pub fn run(&self) -> SimulationReport {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .start_paused(true)
+            .build()
+            .expect("failed to build the simulation runtime");
+        runtime.block_on(self.simulate())
+    }
+
+    async fn simulate(&self) -> SimulationReport {
+        let clock: SharedClock = Arc::new(SimulatedClock::new(
+            Duration::from_secs(1_700_000_000),
+            Duration::from_millis(1),
+        ));
+        let mut cluster = SimCluster {
+            run: RUNS.fetch_add(1, Ordering::Relaxed),
+            seed: self.seed,
+            ads_mode: self.ads_mode,
+            faults: self.faults.clone(),
+            clock: clock.clone(),
+            history: History::default(),
+            nodes: Vec::new(),
+        };
+        for _ in 0..self.storagers {
+            cluster.start_node();
+        }
+        let entries = cluster
+            .nodes
+            .iter()
+            .map(|node| format!("{}={}", node.name, node.addr))
+            .collect();
+        let manager = Manager::new(entries, self.ads_mode)
+            .with_replication_factor(self.replication_factor)
+            .with_retry_policy(RetryPolicy {
+                timeout: Some(SIM_RPC_TIMEOUT),
+                ..RetryPolicy::default()
+            })
+            .with_clock(clock);
+        for node in &mut cluster.nodes {
+            node.registered = true;
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut model = Model::default();
+        let mut stats = SimulationStats::default();
+        let mut violations = Vec::new();
+
+        for step in 0..self.steps {
+            cluster.inject_crashes(&mut rng, step, &mut stats);
+
+            let ops = self.next_batch(&mut rng, &model, &mut cluster);
+            let outcomes = join_all(ops.iter().map(|op| execute(&manager, op))).await;
+            let written: HashSet<(String, String)> = ops.iter().flat_map(|op| op.pairs()).collect();
+
+            for (op, outcome) in ops.iter().zip(&outcomes) {
+                stats.operations += 1;
+                if outcome.is_err() {
+                    stats.failed_operations += 1;
+                }
+                cluster
+                    .history
+                    .record(format!("#{} {} -> {}", step, op, describe(outcome)));
+                match (op, outcome) {
+                    (Op::Query { keyword }, Ok(Outcome::Query { fids, verified })) => {
+                        if *verified {
+                            for problem in model.check(keyword, fids, &written) {
+                                violations.push(format!("step {}: {}", step, problem));
+                            }
+                        } else {
+                            stats.unverified_queries += 1;
+                        }
+                    }
+                    (Op::Join { name, .. }, result) => {
+                        let joined = result.is_ok();
+                        cluster.set_registered(name, joined);
+                        stats.membership_changes += joined as usize;
+                    }
+                    (Op::Leave { name }, Ok(_)) => {
+                        cluster.set_registered(name, false);
+                        stats.membership_changes += 1;
+                    }
+                    _ => {}
+                }
+                model.apply(op, outcome);
+            }
+        }
+
+        // 撤销所有故障后，每个 keyword 的结果都必须通过验证并与模型一致
+        cluster.heal();
+        cluster
+            .history
+            .record("heal: all storagers up, faults off".to_string());
+        for idx in 0..self.vocabulary {
+            let keyword = keyword_name(idx);
+            let op = Op::Query {
+                keyword: keyword.clone(),
+            };
+            let outcome = execute(&manager, &op).await;
+            cluster
+                .history
+                .record(format!("final {} -> {}", op, describe(&outcome)));
+            match outcome {
+                Ok(Outcome::Query {
+                    fids,
+                    verified: true,
+                }) => {
+                    for problem in model.check(&keyword, &fids, &HashSet::new()) {
+                        violations.push(format!("final: {}", problem));
+                    }
+                }
+                Ok(_) => violations.push(format!("final: query '{}' was not verified", keyword)),
+                Err(e) => violations.push(format!("final: query '{}' failed: {}", keyword, e)),
+            }
+        }
+
+        stats.injected_faults = cluster.injected_faults();
+        stats.unknown_pairs = model.unknown();
+        let history = cluster.history.take();
+        cluster.stop();
+        SimulationReport {
+            seed: self.seed,
+            history,
+            violations,
+            stats,
+        }
+    }
+
+    /// 生成一批并发操作；同一批中的写入使用不同的 fid
+    fn next_batch(&self, rng: &mut StdRng, model: &Model, cluster: &mut SimCluster) -> Vec<Op> {
+        let count = rng.gen_range(1..=self.concurrency);
+        let mut fids = HashSet::new();
+        let mut membership = false;
+        let mut ops = Vec::with_capacity(count);
+        for _ in 0..count {
+            let roll = rng.gen_range(0..100);
+            let op = match roll {
+                0..=39 => self.next_add(rng, &mut fids),
+                40..=59 => match model.deletable(rng, &fids) {
+                    Some((fid, keywords)) => {
+                        fids.insert(fid.clone());
+                        Some(Op::Delete { fid, keywords })
+                    }
+                    None => self.next_add(rng, &mut fids),
+                },
+                60..=91 => Some(Op::Query {
+                    keyword: keyword_name(rng.gen_range(0..self.vocabulary)),
+                }),
+                // 拓扑变更在 Manager 中串行执行，一批中最多一个
+                _ if !self.membership_changes || membership => None,
+                92..=94 if cluster.registered().len() < self.storagers * 2 => {
+                    membership = true;
+                    let node = cluster.start_node();
+                    Some(Op::Join {
+                        name: node.name.clone(),
+                        addr: node.addr.clone(),
+                    })
+                }
+                95..=97 if cluster.registered().len() > self.replication_factor => {
+                    membership = true;
+                    cluster
+                        .registered()
+                        .choose(rng)
+                        .map(|name| Op::Leave { name: name.clone() })
+                }
+                _ => {
+                    membership = true;
+                    Some(Op::Rebalance)
+                }
+            };
+            ops.extend(op);
+        }
+        ops
+    }
+
+    fn next_add(&self, rng: &mut StdRng, used: &mut HashSet<String>) -> Option<Op> {
+        let fid = (0..self.files)
+            .map(|idx| format!("f{}", idx))
+            .filter(|fid| !used.contains(fid))
+            .choose(rng)?;
+        used.insert(fid.clone());
+        let count = rng.gen_range(1..=3.min(self.vocabulary));
+        let keywords = (0..self.vocabulary)
+            .choose_multiple(rng, count)
+            .into_iter()
+            .map(keyword_name)
+            .collect();
+        Some(Op::Add { fid, keywords })
+    }
+}
+
+/// 一次模拟的结果
+#[derive(Debug, Clone)]
+pub struct SimulationReport {
+    pub seed: u64,
+    /// 按发生顺序记录的操作、结果和注入的故障
+    pub history: Vec<String>,
+    /// 与参考模型不一致的结果
+    pub violations: Vec<String>,
+    pub stats: SimulationStats,
+}
+
+impl SimulationReport {
+    pub fn is_consistent(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// 有不一致时 panic，信息中包含种子、所有不一致和最后几条历史
+    pub fn assert_consistent(&self) {
+        if self.is_consistent() {
+            return;
+        }
+        let tail = &self.history[self.history.len().saturating_sub(HISTORY_TAIL)..];
+        panic!(
+            "simulation with seed {} found {} violation(s):\n  {}\nlast {} event(s):\n  {}",
+            self.seed,
+            self.violations.len(),
+            self.violations.join("\n  "),
+            tail.len(),
+            tail.join("\n  ")
+        );
+    }
+}
+
+/// 模拟过程的统计
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimulationStats {
+    pub operations: usize,
+    pub failed_operations: usize,
+    /// 成功返回但未通过验证的查询
+    pub unverified_queries: usize,
+    pub injected_faults: usize,
+    pub crashes: usize,
+    pub membership_changes: usize,
+    /// 结束时结果未知的 (keyword, fid)（对应的写入失败过）
+    pub unknown_pairs: usize,
+}
+
+/// 模拟中的一个操作
+#[derive(Debug, Clone)]
+enum Op {
+    Add { fid: String, keywords: Vec<String> },
+    Delete { fid: String, keywords: Vec<String> },
+    Query { keyword: String },
+    Join { name: String, addr: String },
+    Leave { name: String },
+    Rebalance,
+}
+
+impl Op {
+    /// 写入涉及的 (keyword, fid)
+    fn pairs(&self) -> Vec<(String, String)> {
+        match self {
+            Op::Add { fid, keywords } | Op::Delete { fid, keywords } => keywords
+                .iter()
+                .map(|keyword| (keyword.clone(), fid.clone()))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for Op {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Op::Add { fid, keywords } => write!(f, "add {} {:?}", fid, keywords),
+            Op::Delete { fid, keywords } => write!(f, "delete {} {:?}", fid, keywords),
+            Op::Query { keyword } => write!(f, "query {}", keyword),
+            Op::Join { name, .. } => write!(f, "join {}", name),
+            Op::Leave { name } => write!(f, "leave {}", name),
+            Op::Rebalance => write!(f, "rebalance"),
+        }
+    }
+}
+
+/// 操作的结果
+#[derive(Debug, Clone)]
+enum Outcome {
+    /// 写入得到确认（false 表示 Manager 返回失败）
+    Write(bool),
+    Query {
+        fids: Vec<String>,
+        verified: bool,
+    },
+    Membership(usize),
+}
+
+async fn execute(manager: &Manager, op: &Op) -> Result<Outcome, String> {
+    match op {
+        Op::Add { fid, keywords } => manager
+            .add(Request::new(AddRequest {
+                fid: fid.clone(),
+                keywords: keywords.clone(),
+                ack_mode: AckMode::Sync as i32,
+                ..Default::default()
+            }))
+            .await
+            .map(|response| Outcome::Write(response.into_inner().success))
+            .map_err(|status| status.message().to_string()),
+        Op::Delete { fid, keywords } => manager
+            .delete(Request::new(DeleteRequest {
+                fid: fid.clone(),
+                keywords: keywords.clone(),
+                ack_mode: AckMode::Sync as i32,
+                strict: true,
+                ..Default::default()
+            }))
+            .await
+            .map(|response| Outcome::Write(response.into_inner().success))
+            .map_err(|status| status.message().to_string()),
+        Op::Query { keyword } => manager
+            .query(Request::new(QueryRequest {
+                query_type: Some(QueryType::Keyword(keyword.clone())),
+                ..Default::default()
+            }))
+            .await
+            .map(|response| {
+                let response = response.into_inner();
+                Outcome::Query {
+                    fids: response.fids,
+                    verified: response.verified,
+                }
+            })
+            .map_err(|status| status.message().to_string()),
+        Op::Join { name, addr } => manager
+            .register_storager(Some(name), addr, DEFAULT_VIRTUAL_NODES)
+            .await
+            .map(|change| Outcome::Membership(change.migrated.keywords)),
+        Op::Leave { name } => manager
+            .deregister_storager(name)
+            .await
+            .map(|change| Outcome::Membership(change.migrated.keywords)),
+        Op::Rebalance => manager
+            .rebalance_now(false)
+            .await
+            .map(|rebalance| Outcome::Membership(rebalance.migrated.keywords)),
+    }
+}
+
+fn describe(outcome: &Result<Outcome, String>) -> String {
+    match outcome {
+        Ok(Outcome::Write(true)) => "ok".to_string(),
+        Ok(Outcome::Write(false)) => "rejected".to_string(),
+        Ok(Outcome::Query { fids, verified }) => {
+            let mut fids = fids.clone();
+            fids.sort();
+            format!("{:?}{}", fids, if *verified { "" } else { " (unverified)" })
+        }
+        Ok(Outcome::Membership(keywords)) => format!("ok, {} keyword(s) migrated", keywords),
+        Err(e) => format!("error: {}", e),
+    }
+}
+
+fn keyword_name(idx: usize) -> String {
+    format!("k{}", idx)
+}
+
+/// (keyword, fid) 在参考模型中的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PairState {
+    Present,
+    Absent,
+    /// 写入失败，可能已经部分应用
+    Unknown,
+}
+
+/// 参考模型：确认成功的写入决定 (keyword, fid) 是否存在
+#[derive(Debug, Default)]
+struct Model {
+    pairs: BTreeMap<(String, String), PairState>,
+}
+
+impl Model {
+    fn apply(&mut self, op: &Op, outcome: &Result<Outcome, String>) {
+        let state = match (op, outcome) {
+            (Op::Add { .. }, Ok(Outcome::Write(true))) => PairState::Present,
+            (Op::Delete { .. }, Ok(Outcome::Write(true))) => PairState::Absent,
+            (Op::Add { .. } | Op::Delete { .. }, _) => PairState::Unknown,
+            _ => return,
+        };
+        for pair in op.pairs() {
+            self.pairs.insert(pair, state);
+        }
+    }
+
+    fn state(&self, keyword: &str, fid: &str) -> PairState {
+        self.pairs
+            .get(&(keyword.to_string(), fid.to_string()))
+            .copied()
+            .unwrap_or(PairState::Absent)
+    }
+
+    /// 通过验证的查询结果与模型不一致之处；`skip` 中的 (keyword, fid) 正在被并发写入
+    fn check(
+        &self,
+        keyword: &str,
+        fids: &[String],
+        skip: &HashSet<(String, String)>,
+    ) -> Vec<String> {
+        let skipped = |fid: &str| skip.contains(&(keyword.to_string(), fid.to_string()));
+        let returned: BTreeSet<&str> = fids.iter().map(String::as_str).collect();
+        let mut problems = Vec::new();
+        for fid in &returned {
+            if self.state(keyword, fid) == PairState::Absent && !skipped(fid) {
+                problems.push(format!(
+                    "query '{}' returned {} which was never added or was deleted",
+                    keyword, fid
+                ));
+            }
+        }
+        for ((k, fid), state) in &self.pairs {
+            if k == keyword
+                && *state == PairState::Present
+                && !returned.contains(fid.as_str())
+                && !skipped(fid)
+            {
+                problems.push(format!(
+                    "query '{}' is missing acknowledged fid {}",
+                    keyword, fid
+                ));
+            }
+        }
+        problems
+    }
+
+    /// 随机选择一个可能存在的 fid 及其部分 keyword（跳过 `used` 中的 fid）
+    fn deletable(&self, rng: &mut StdRng, used: &HashSet<String>) -> Option<(String, Vec<String>)> {
+        let mut by_fid: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for ((keyword, fid), state) in &self.pairs {
+            if *state != PairState::Absent && !used.contains(fid) {
+                by_fid.entry(fid).or_default().push(keyword.clone());
+            }
+        }
+        let (fid, keywords) = by_fid.into_iter().choose(rng)?;
+        let count = rng.gen_range(1..=keywords.len());
+        let keywords = keywords.choose_multiple(rng, count).cloned().collect();
+        Some((fid.to_string(), keywords))
+    }
+
+    fn unknown(&self) -> usize {
+        self.pairs
+            .values()
+            .filter(|state| **state == PairState::Unknown)
+            .count()
+    }
+}
+
+/// 按发生顺序记录的事件，各节点共享
+#[derive(Debug, Clone, Default)]
+struct History(Arc<Mutex<Vec<String>>>);
+
+impl History {
+    fn record(&self, event: String) {
+        self.0.lock().unwrap().push(event);
+    }
+
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+/// 模拟中的 storager 和它们的故障状态
+struct SimCluster {
+    run: u64,
+    seed: u64,
+    ads_mode: AdsMode,
+    faults: FaultConfig,
+    clock: SharedClock,
+    history: History,
+    nodes: Vec<SimNode>,
+}
+
+struct SimNode {
+    name: String,
+    addr: String,
+    /// 是否在 Manager 的哈希环上
+    registered: bool,
+    faults: Arc<NodeFaults>,
+    stop: oneshot::Sender<()>,
+}
+
+impl SimCluster {
+    /// 启动一个新的 storager（尚未加入 Manager）
+    fn start_node(&mut self) -> &SimNode {
+        let idx = self.nodes.len();
+        let name = format!("storager-{}", idx);
+        let incoming = memory_incoming(&format!("sim{}-{}", self.run, name))
+            .expect("simulation addresses are unique");
+        let addr = incoming.url();
+        let faults = Arc::new(NodeFaults {
+            name: name.clone(),
+            config: self.faults.clone(),
+            rng: Mutex::new(StdRng::seed_from_u64(
+                self.seed ^ (idx as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15),
+            )),
+            down: AtomicBool::new(false),
+            enabled: AtomicBool::new(true),
+            injected: AtomicU64::new(0),
+            history: self.history.clone(),
+        });
+
+        let storager = Arc::new(
+            Storager::from_config(self.ads_mode.name())
+                .with_ads_pool(AdsPool::inline())
+                .with_clock(self.clock.clone()),
+        );
+        let service = TransportConfig::default().storager_server(storager);
+        let layer = FaultLayer(faults.clone());
+        let (stop, stopped) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let shutdown = async {
+                let _ = stopped.await;
+            };
+            let _ = Server::builder()
+                .layer(layer)
+                .add_service(service)
+                .serve_with_incoming_shutdown(incoming, shutdown)
+                .await;
+        });
+
+        self.nodes.push(SimNode {
+            name,
+            addr,
+            registered: false,
+            faults,
+            stop,
+        });
+        self.nodes.last().unwrap()
+    }
+
+    /// Manager 哈希环上的节点名称
+    fn registered(&self) -> Vec<String> {
+        self.nodes
+            .iter()
+            .filter(|node| node.registered)
+            .map(|node| node.name.clone())
+            .collect()
+    }
+
+    fn set_registered(&mut self, name: &str, registered: bool) {
+        if let Some(node) = self.nodes.iter_mut().find(|node| node.name == name) {
+            node.registered = registered;
+        }
+    }
+
+    /// 按概率让一个运行中的 storager 宕机，或让一个宕机的 storager 恢复
+    fn inject_crashes(&self, rng: &mut StdRng, step: usize, stats: &mut SimulationStats) {
+        let (down, up): (Vec<&SimNode>, Vec<&SimNode>) = self
+            .nodes
+            .iter()
+            .filter(|node| node.registered)
+            .partition(|node| node.faults.is_down());
+        if down.len() < self.faults.max_down && rng.gen_bool(self.faults.crash) {
+            if let Some(node) = up.choose(rng) {
+                node.faults.down.store(true, Ordering::SeqCst);
+                stats.crashes += 1;
+                self.history
+                    .record(format!("#{} crash {}", step, node.name));
+            }
+        }
+        if !down.is_empty() && rng.gen_bool(self.faults.restart) {
+            if let Some(node) = down.choose(rng) {
+                node.faults.down.store(false, Ordering::SeqCst);
+                self.history
+                    .record(format!("#{} restart {}", step, node.name));
+            }
+        }
+    }
+
+    /// 恢复所有节点并停止注入故障
+    fn heal(&self) {
+        for node in &self.nodes {
+            node.faults.down.store(false, Ordering::SeqCst);
+            node.faults.enabled.store(false, Ordering::SeqCst);
+        }
+    }
+
+    fn injected_faults(&self) -> usize {
+        self.nodes
+            .iter()
+            .map(|node| node.faults.injected.load(Ordering::SeqCst) as usize)
+            .sum()
+    }
+
+    fn stop(self) {
+        for node in self.nodes {
+            let _ = node.stop.send(());
+        }
+    }
+}
+
+/// 一次 RPC 被注入的故障
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Fault {
+    DropRequest,
+    DropResponse,
+    Delay(Duration),
+}
+
+/// 一个 storager 的故障状态
+struct NodeFaults {
+    name: String,
+    config: FaultConfig,
+    /// 按请求到达的顺序抽取
+    rng: Mutex<StdRng>,
+    down: AtomicBool,
+    /// 为 false 时不再注入 RPC 故障
+    enabled: AtomicBool,
+    injected: AtomicU64,
+    history: History,
+}
+
+impl NodeFaults {
+    fn is_down(&self) -> bool {
+        self.down.load(Ordering::SeqCst)
+    }
+
+    fn decide(&self, rpc: &str) -> Option<Fault> {
+        if !self.enabled.load(Ordering::SeqCst) {
+            return None;
+        }
+        let config = &self.config;
+        let mut rng = self.rng.lock().unwrap();
+        let roll: f64 = rng.gen();
+        let fault = if roll < config.drop_request {
+            Fault::DropRequest
+        } else if roll < config.drop_request + config.drop_response {
+            Fault::DropResponse
+        } else if roll < config.drop_request + config.drop_response + config.delay {
+            Fault::Delay(config.max_delay.mul_f64(rng.gen()))
+        } else {
+            return None;
+        };
+        self.injected.fetch_add(1, Ordering::SeqCst);
+        self.history
+            .record(format!("fault {} {} {:?}", self.name, rpc, fault));
+        Some(fault)
+    }
+
+    fn unavailable(&self) -> http::Response<BoxBody> {
+        Status::unavailable(format!("{} is unreachable (simulated)", self.name)).to_http()
+    }
+}
+
+/// 在 storager 服务前注入故障的 tower 层
+#[derive(Clone)]
+struct FaultLayer(Arc<NodeFaults>);
+
+impl<S> Layer<S> for FaultLayer {
+    type Service = FaultService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        FaultService {
+            inner,
+            faults: self.0.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct FaultService<S> {
+    inner: S,
+    faults: Arc<NodeFaults>,
+}
+
+impl<S, B> Service<http::Request<B>> for FaultService<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        // 使用已经就绪的服务，留下一个克隆等待下一次 poll_ready
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let faults = self.faults.clone();
+        Box::pin(async move {
+            if faults.is_down() {
+                return Ok(faults.unavailable());
+            }
+            let rpc = request.uri().path().rsplit('/').next().unwrap_or_default();
+            let fault = faults.decide(rpc);
+            if fault == Some(Fault::DropRequest) {
+                return Ok(faults.unavailable());
+            }
+            let response = inner.call(request).await?;
+            if let Some(Fault::Delay(delay)) = fault {
+                tokio::time::sleep(delay).await;
+            }
+            // 处理期间宕机的节点不再返回响应
+            if fault == Some(Fault::DropResponse) || faults.is_down() {
+                return Ok(faults.unavailable());
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(keyword: &str, fid: &str) -> (String, String) {
+        (keyword.to_string(), fid.to_string())
+    }
+
+    #[test]
+    fn test_model_check() {
+        let mut model = Model::default();
+        let add = |fid: &str| Op::Add {
+            fid: fid.to_string(),
+            keywords: vec!["k0".to_string()],
+        };
+        model.apply(&add("f1"), &Ok(Outcome::Write(true)));
+        model.apply(&add("f2"), &Err("timeout".to_string()));
+        model.apply(
+            &Op::Delete {
+                fid: "f3".to_string(),
+                keywords: vec!["k0".to_string()],
+            },
+            &Ok(Outcome::Write(true)),
+        );
+        assert_eq!(model.unknown(), 1);
+
+        // 结果未知的 f2 可有可无
+        let fids = |fids: &[&str]| fids.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        assert!(model
+            .check("k0", &fids(&["f1"]), &HashSet::new())
+            .is_empty());
+        assert!(model
+            .check("k0", &fids(&["f1", "f2"]), &HashSet::new())
+            .is_empty());
+
+        let problems = model.check("k0", &fids(&["f3"]), &HashSet::new());
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(problems[0].contains("f3"));
+        assert!(problems[1].contains("missing acknowledged fid f1"));
+
+        // 正在并发写入的 (keyword, fid) 不参与比对
+        let skip = HashSet::from([pair("k0", "f1"), pair("k0", "f3")]);
+        assert!(model.check("k0", &fids(&["f3"]), &skip).is_empty());
+    }
+
+    #[test]
+    fn test_fault_decisions_follow_the_seed() {
+        let decide = |seed| {
+            let faults = NodeFaults {
+                name: "storager-0".to_string(),
+                config: FaultConfig {
+                    drop_request: 0.2,
+                    drop_response: 0.2,
+                    delay: 0.2,
+                    max_delay: Duration::from_secs(1),
+                    ..FaultConfig::none()
+                },
+                rng: Mutex::new(StdRng::seed_from_u64(seed)),
+                down: AtomicBool::new(false),
+                enabled: AtomicBool::new(true),
+                injected: AtomicU64::new(0),
+                history: History::default(),
+            };
+            let decisions: Vec<_> = (0..50).map(|_| faults.decide("Add")).collect();
+            (decisions, faults.history.take())
+        };
+        let (decisions, history) = decide(7);
+        assert_eq!(decide(7), (decisions.clone(), history.clone()));
+        assert_eq!(history.len(), decisions.iter().flatten().count());
+        assert!(decisions.contains(&Some(Fault::DropRequest)));
+        assert!(decisions.contains(&None));
+    }
+}
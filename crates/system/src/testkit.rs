@@ -0,0 +1,159 @@
+//! 端到端测试工具
+//!
+//! [`TestCluster`] 在当前 Tokio 运行时中启动一个 Manager 和 N 个使用内存存储的
+//! storager（均监听 `127.0.0.1` 的随机端口），并返回已经指向 Manager 的 [`Client`]。
+//! 集成测试和示例不需要再在多个终端里手动启动各节点，也不会与固定端口冲突。
+//!
+//! 断言辅助函数在失败时直接 panic 并给出完整的上下文，适合在 `#[tokio::test]` 中使用。
+//!
+//! # 示例
+//!
+//! ```no_run
+//! use common::AdsMode;
+//! use system::testkit::{assert_query_verified, TestCluster};
+//!
+//! # async fn demo() {
+//! let cluster = TestCluster::start(AdsMode::Mpt, 2).await;
+//! cluster.add_file("f1", &["rust", "grpc"]).await;
+//! assert_query_verified(cluster.client(), "rust AND grpc", &["f1"]).await;
+//! cluster.shutdown().await;
+//! # }
+//! ```
+
+use crate::runner::{RunningSystem, SystemRunner};
+use client::{Client, ClientBuilder};
+use common::AdsMode;
+use manager::Manager;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+use storager::Storager;
+
+/// 测试集群关闭时等待进行中请求的时长
+const TEST_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 进程内运行的测试集群
+pub struct TestCluster {
+    system: RunningSystem,
+    client: Client,
+}
+
+impl TestCluster {
+    /// 启动 `storagers` 个 storager 和一个 Manager，节点无法启动时 panic
+    pub async fn start(ads_mode: AdsMode, storagers: usize) -> Self {
+        let system = SystemRunner::new(ads_mode)
+            .with_storagers(storagers)
+            .with_drain_timeout(TEST_DRAIN_TIMEOUT)
+            .start()
+            .await
+            .unwrap_or_else(|e| panic!("failed to start {:?} test cluster: {}", ads_mode, e));
+        let client = Client::new(system.manager_addr().to_string())
+            .unwrap_or_else(|e| panic!("failed to create client: {}", e));
+        TestCluster { system, client }
+    }
+
+    /// 使用默认配置的 Client
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// 指向 Manager 的 ClientBuilder，用于设置命名空间、租户或确认模式
+    pub fn client_builder(&self) -> ClientBuilder {
+        Client::builder(self.manager_addr().to_string())
+    }
+
+    /// Manager 的通告地址
+    pub fn manager_addr(&self) -> &str {
+        self.system.manager_addr()
+    }
+
+    /// Manager 实例
+    pub fn manager(&self) -> &Arc<Manager> {
+        self.system
+            .manager()
+            .expect("test clusters always run the manager in-process")
+    }
+
+    /// storager 实例，顺序与 [`RunningSystem::storager_addrs`] 一致
+    pub fn storagers(&self) -> Vec<&Arc<Storager>> {
+        self.system.storagers()
+    }
+
+    /// 底层的集群
+    pub fn system(&self) -> &RunningSystem {
+        &self.system
+    }
+
+    /// 添加一个文件，Manager 未确认时 panic
+    pub async fn add_file(&self, fid: &str, keywords: &[&str]) {
+        let response = self
+            .client
+            .add(
+                fid.to_string(),
+                keywords.iter().map(|k| k.to_string()).collect(),
+            )
+            .await
+            .unwrap_or_else(|e| panic!("add {} failed: {}", fid, e));
+        assert!(response.success, "add {} failed: {}", fid, response.message);
+    }
+
+    /// 依次添加多个文件
+    pub async fn add_files(&self, files: &[(&str, &[&str])]) {
+        for (fid, keywords) in files {
+            self.add_file(fid, keywords).await;
+        }
+    }
+
+    /// 关闭所有节点，失败时 panic
+    pub async fn shutdown(self) {
+        self.system
+            .shutdown()
+            .await
+            .unwrap_or_else(|e| panic!("failed to shut down test cluster: {}", e));
+    }
+}
+
+/// 查询 keyword 或布尔表达式，断言结果已通过验证且 fid 集合与 `expected` 相同（不计顺序）
+pub async fn assert_query_verified(client: &Client, expression: &str, expected: &[&str]) {
+    let response = client
+        .query(expression)
+        .await
+        .unwrap_or_else(|e| panic!("query '{}' failed: {}", expression, e));
+    assert!(
+        response.verified,
+        "query '{}' was not verified (fids: {:?})",
+        expression, response.fids
+    );
+    let actual: BTreeSet<&str> = response.fids.iter().map(String::as_str).collect();
+    let expected: BTreeSet<&str> = expected.iter().copied().collect();
+    assert_eq!(
+        actual, expected,
+        "unexpected fids for query '{}'",
+        expression
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cluster_add_and_query() {
+        let cluster = TestCluster::start(AdsMode::MerkleTree, 2).await;
+        assert_eq!(cluster.storagers().len(), 2);
+        cluster
+            .add_files(&[("f1", &["rust", "grpc"]), ("f2", &["rust"])])
+            .await;
+        assert_query_verified(cluster.client(), "rust", &["f2", "f1"]).await;
+        assert_query_verified(cluster.client(), "missing", &[]).await;
+        cluster.shutdown().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[should_panic(expected = "unexpected fids for query 'rust'")]
+    async fn test_assert_query_verified_reports_mismatch() {
+        let cluster = TestCluster::start(AdsMode::MerkleTree, 1).await;
+        cluster.add_file("f1", &["rust"]).await;
+        assert_query_verified(cluster.client(), "rust", &["f2"]).await;
+    }
+}
@@ -0,0 +1,67 @@
+//! 端到端测试：通过 Client 对完整的进程内集群读写
+//!
+//! 每种内置 ADS 模式各启动一个集群，写入、布尔查询、删除和命名空间隔离的结果都必须通过验证。
+
+use common::AdsMode;
+use system::testkit::{assert_query_verified, TestCluster};
+
+async fn round_trip(mode: AdsMode) {
+    let cluster = TestCluster::start(mode, 3).await;
+    cluster
+        .add_files(&[
+            ("file1", &["rust", "distributed", "storage"]),
+            ("file2", &["python", "ai"]),
+            ("file3", &["rust", "blockchain"]),
+        ])
+        .await;
+
+    let client = cluster.client();
+    assert_query_verified(client, "rust", &["file1", "file3"]).await;
+    assert_query_verified(client, "rust AND blockchain", &["file3"]).await;
+    assert_query_verified(client, "python OR storage", &["file1", "file2"]).await;
+
+    let response = client
+        .delete("file3".to_string(), vec!["rust".to_string()])
+        .await
+        .unwrap();
+    assert!(response.success, "{}", response.message);
+    assert_query_verified(client, "rust", &["file1"]).await;
+
+    // 同一 keyword 在其他命名空间中互不可见
+    let tenant = cluster
+        .client_builder()
+        .with_namespace("tenant-a")
+        .build()
+        .unwrap();
+    assert!(
+        tenant
+            .add("file9".to_string(), vec!["rust".to_string()])
+            .await
+            .unwrap()
+            .success
+    );
+    assert_query_verified(&tenant, "rust", &["file9"]).await;
+    assert_query_verified(client, "rust", &["file1"]).await;
+
+    cluster.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_end_to_end_crypto_accumulator() {
+    round_trip(AdsMode::CryptoAccumulator).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_end_to_end_mpt() {
+    round_trip(AdsMode::Mpt).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_end_to_end_merkle_tree() {
+    round_trip(AdsMode::MerkleTree).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_end_to_end_sparse_merkle_tree() {
+    round_trip(AdsMode::SparseMerkleTree).await;
+}
@@ -0,0 +1,72 @@
+//! 确定性模拟：在注入故障、节点宕机和拓扑变更的情况下，已确认的写入不丢失，
+//! 通过验证的查询结果与参考模型一致
+
+use common::AdsMode;
+use system::sim::{FaultConfig, Simulation};
+
+#[test]
+fn test_same_seed_replays_the_same_history() {
+    let simulation = Simulation::new(11).with_steps(60);
+    let first = simulation.run();
+    let second = simulation.run();
+    assert_eq!(first.history, second.history);
+    assert_eq!(first.stats, second.stats);
+    assert!(first.stats.injected_faults > 0, "{:?}", first.stats);
+
+    let other = Simulation::new(12).with_steps(60).run();
+    assert_ne!(first.history, other.history);
+}
+
+#[test]
+fn test_no_faults_leaves_nothing_unknown() {
+    let report = Simulation::new(3)
+        .with_steps(80)
+        .with_faults(FaultConfig::none())
+        .run();
+    report.assert_consistent();
+    assert_eq!(report.stats.failed_operations, 0, "{:?}", report.history);
+    assert_eq!(report.stats.unknown_pairs, 0);
+    assert!(report.stats.membership_changes > 0, "{:?}", report.stats);
+}
+
+#[test]
+fn test_faults_with_rebalancing() {
+    for seed in 0..4 {
+        Simulation::new(seed)
+            .with_steps(120)
+            .run()
+            .assert_consistent();
+    }
+}
+
+#[test]
+fn test_faults_with_replication() {
+    // 主副本不宕机时，读请求总能读到主副本或法定结果
+    let faults = FaultConfig {
+        crash: 0.0,
+        ..FaultConfig::default()
+    };
+    for seed in 100..103 {
+        Simulation::new(seed)
+            .with_ads_mode(AdsMode::Mpt)
+            .with_replication_factor(2)
+            .with_faults(faults.clone())
+            .with_steps(120)
+            .run()
+            .assert_consistent();
+    }
+}
+
+/// 副本写入失败时只跳过该副本，主副本随后宕机时，读请求退回到错过了删除的副本，
+/// 返回已确认删除的 fid（其证明对该副本自己的根哈希是有效的）
+#[test]
+#[ignore = "known gap: reads fall back to a stale replica while the primary is down"]
+fn test_crashed_primary_exposes_stale_replica() {
+    Simulation::new(101)
+        .with_ads_mode(AdsMode::Mpt)
+        .with_replication_factor(2)
+        .with_membership_changes(false)
+        .with_steps(150)
+        .run()
+        .assert_consistent();
+}